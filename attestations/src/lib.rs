@@ -0,0 +1,319 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("AQE1aGnKsmMkPkxeGVBL2yP8qRPTSTteF37Vb8RY5cwA");
+
+pub const ATTESTATION_SEED: &str = "attestation";
+
+/// Errors returned by the attestations program.
+#[repr(u32)]
+pub enum AttestationError {
+    /// The expiry is in the past.
+    InvalidExpiry,
+    /// The attestation was revoked.
+    Revoked,
+    /// The attestation expired.
+    Expired,
+    /// The attested data hash doesn't match.
+    DataMismatch,
+}
+
+impl From<AttestationError> for ProgramError {
+    fn from(e: AttestationError) -> Self {
+        Namespace::Attestations.error(e as u32)
+    }
+}
+
+/// On-chain representation of an attestation made by an issuer about a
+/// subject.
+///
+/// The attested data itself stays off-chain, only its hash is stored.
+#[repr(C)]
+pub struct Attestation {
+    pub issuer: Pubkey,
+    pub subject: Pubkey,
+    /// Hash of the attested data.
+    pub data_hash: [u8; 32],
+    /// Identifier of the schema of the attested data, defined by the issuer.
+    pub schema_id: u64,
+    /// Unix timestamp of issuance.
+    pub issued_at: i64,
+    /// Unix timestamp of expiry, 0 if the attestation never expires.
+    pub expires_at: i64,
+    pub revoked: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+assert_layout!(Attestation, size = 128, align = 8, {
+    issuer: 0,
+    subject: 32,
+    data_hash: 64,
+    schema_id: 96,
+    issued_at: 104,
+    expires_at: 112,
+    revoked: 120,
+    bump: 121,
+});
+
+impl Attestation {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Checks that the attestation is neither revoked nor expired at `now`,
+    /// and that it attests `data_hash`.
+    pub fn check_valid(&self, data_hash: &[u8; 32], now: i64) -> ProgramResult {
+        if self.revoked != 0 {
+            return Err(AttestationError::Revoked.into());
+        }
+        if self.expires_at != 0 && now >= self.expires_at {
+            return Err(AttestationError::Expired.into());
+        }
+        if &self.data_hash != data_hash {
+            return Err(AttestationError::DataMismatch.into());
+        }
+        Ok(())
+    }
+}
+
+/// Attestations program instruction discriminators.
+#[repr(u8)]
+pub enum AttestationInstruction {
+    /// Publishes an attestation.
+    Attest,
+    /// Revokes an attestation.
+    Revoke,
+    /// Checks an attestation.
+    Verify,
+}
+
+impl TryFrom<&u8> for AttestationInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Attest),
+            1 => Ok(Self::Revoke),
+            2 => Ok(Self::Verify),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct AttestInstructionData {
+    pub data_hash: [u8; 32],
+    pub schema_id: u64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl AttestInstructionData {
+    pub fn new(data_hash: [u8; 32], schema_id: u64, expires_at: i64, bump: u8) -> Self {
+        Self {
+            data_hash,
+            schema_id,
+            expires_at,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct VerifyInstructionData {
+    pub data_hash: [u8; 32],
+    pub schema_id: u64,
+}
+
+impl VerifyInstructionData {
+    pub fn new(data_hash: [u8; 32], schema_id: u64) -> Self {
+        Self {
+            data_hash,
+            schema_id,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = AttestationInstruction::try_from(instruction)?;
+
+    match instruction {
+        AttestationInstruction::Attest => process_attest(accounts, instruction_data),
+        AttestationInstruction::Revoke => process_revoke(accounts),
+        AttestationInstruction::Verify => process_verify(accounts, instruction_data),
+    }
+}
+
+/// Publishes an attestation of `issuer` about `subject`. The issuer's
+/// signature on the transaction is what makes the attestation authentic.
+pub fn process_attest(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [issuer, subject, attestation, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(issuer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = AttestInstructionData::from_bytes(instruction_data)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    if instruction_data.expires_at != 0 && instruction_data.expires_at <= now {
+        return Err(AttestationError::InvalidExpiry.into());
+    }
+
+    // Check the seeds of `attestation`.
+    let schema_id = instruction_data.schema_id.to_le_bytes();
+    let attestation_pda = create_program_address(
+        &[
+            ATTESTATION_SEED.as_bytes(),
+            issuer.key(),
+            subject.key(),
+            &schema_id,
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(attestation.key(), &attestation_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the attestation PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(ATTESTATION_SEED.as_bytes()),
+        Seed::from(issuer.key()),
+        Seed::from(subject.key()),
+        Seed::from(&schema_id),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: issuer,
+        to: attestation,
+        lamports: Rent::get()?.minimum_balance(Attestation::LEN),
+        space: Attestation::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the attestation PDA.
+    let mut data = attestation.try_borrow_mut_data()?;
+    let data: &mut Attestation = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the attestation.
+    data.issuer = *issuer.key();
+    data.subject = *subject.key();
+    data.data_hash = instruction_data.data_hash;
+    data.schema_id = instruction_data.schema_id;
+    data.issued_at = now;
+    data.expires_at = instruction_data.expires_at;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Attested schema {} until {}",
+        instruction_data.schema_id, instruction_data.expires_at
+    );
+
+    Ok(())
+}
+
+/// Revokes an attestation. The attestation account is kept, so verifiers
+/// see the revocation instead of a missing account.
+pub fn process_revoke(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [issuer, attestation] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(issuer)?;
+    // Check if the attestation PDA is owned by the program.
+    require_owned_by(attestation, &ID)?;
+
+    // Deserialize the attestation PDA.
+    let mut data = attestation.try_borrow_mut_data()?;
+    let data: &mut Attestation = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Only the issuer can revoke the attestation.
+    if !keys_eq(&data.issuer, issuer.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if data.revoked != 0 {
+        return Err(AttestationError::Revoked.into());
+    }
+
+    data.revoked = 1;
+
+    info!("Revoked attestation of schema {}", data.schema_id);
+
+    Ok(())
+}
+
+/// Checks that `attestation` was made by `issuer` about `subject`, attests
+/// the given data hash under the given schema, and is still valid.
+///
+/// Programs can do the same check without CPI by reading the attestation
+/// account and calling `Attestation::check_valid`, after checking the owner
+/// of the account.
+pub fn process_verify(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [issuer, subject, attestation] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // An account with the same layout, but not owned by the program, could
+    // say anything.
+    require_owned_by(attestation, &ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = VerifyInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the attestation PDA.
+    let data = attestation.try_borrow_data()?;
+    let data: &Attestation = unsafe { &*data.as_ptr().cast() };
+
+    if !keys_eq(&data.issuer, issuer.key())
+        || !keys_eq(&data.subject, subject.key())
+        || data.schema_id != instruction_data.schema_id
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    data.check_valid(&instruction_data.data_hash, Clock::get()?.unix_timestamp)?;
+
+    info!("Attestation is valid");
+
+    Ok(())
+}
@@ -0,0 +1,220 @@
+//! Attacks on the attestations program, each rejected with its own error.
+
+use std::mem;
+
+use attestations::{
+    AttestInstructionData, Attestation, AttestationInstruction, VerifyInstructionData,
+    ATTESTATION_SEED,
+};
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(attestations::ID);
+
+const SCHEMA_ID: u64 = 7;
+const DATA_HASH: [u8; 32] = [42; 32];
+const NOW: i64 = 1_000_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: AttestationInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<AttestationInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the attestation PDA of `issuer` about `subject` and its bump.
+fn attestation_pda(issuer: &Pubkey, subject: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            ATTESTATION_SEED.as_bytes(),
+            issuer.as_ref(),
+            subject.as_ref(),
+            &SCHEMA_ID.to_le_bytes(),
+        ],
+        &ID,
+    )
+}
+
+/// Returns a valid attestation of `issuer` about `subject`, which never
+/// expires.
+fn attestation_account(mollusk: &Mollusk, issuer: &Pubkey, subject: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Attestation::LEN),
+        Attestation::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(issuer.as_ref());
+    account.data[32..64].copy_from_slice(subject.as_ref());
+    account.data[64..96].copy_from_slice(&DATA_HASH);
+    account.data[96..104].copy_from_slice(&SCHEMA_ID.to_le_bytes());
+    account.data[104..112].copy_from_slice(&NOW.to_le_bytes());
+    account
+}
+
+/// Accounts of an issuer with an attestation about a subject, and of an
+/// attacker.
+struct Setup {
+    mollusk: Mollusk,
+    issuer: Pubkey,
+    subject: Pubkey,
+    attestation: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/attestations");
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let issuer = Pubkey::new_unique();
+        let subject = Pubkey::new_unique();
+        let (attestation, _) = attestation_pda(&issuer, &subject);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (
+                issuer,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (subject, Account::default()),
+            (
+                attestation,
+                attestation_account(&mollusk, &issuer, &subject),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            issuer,
+            subject,
+            attestation,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Attests about the subject at `attestation`, signed by `issuer`.
+    fn instruction_attest(&self, issuer: &Pubkey, attestation: &Pubkey, bump: u8) -> Instruction {
+        let data = AttestInstructionData::new(DATA_HASH, SCHEMA_ID, 0, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*issuer, true),
+            AccountMeta::new_readonly(self.subject, false),
+            AccountMeta::new(*attestation, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(AttestationInstruction::Attest, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Verifies that `attestation` is an attestation of the issuer about
+    /// `subject` under `schema_id`.
+    fn instruction_verify(
+        &self,
+        subject: &Pubkey,
+        attestation: &Pubkey,
+        schema_id: u64,
+    ) -> Instruction {
+        let data = VerifyInstructionData::new(DATA_HASH, schema_id);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.issuer, false),
+            AccountMeta::new_readonly(*subject, false),
+            AccountMeta::new_readonly(*attestation, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(AttestationInstruction::Verify, &data),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_issuer_signature() {
+    let setup = Setup::new();
+    let (attestation, bump) = attestation_pda(&setup.issuer, &setup.subject);
+    let mut instruction = setup.instruction_attest(&setup.issuer, &attestation, bump);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the attestation of the issuer about the subject,
+/// before the issuer does.
+#[test]
+fn test_attest_at_address_of_other_issuer() {
+    let setup = Setup::new();
+    let attestation = setup.attestation;
+    let (_, bump) = attestation_pda(&setup.attacker, &setup.subject);
+    let setup = setup.with(attestation, Account::default());
+    setup.expect_err(
+        &setup.instruction_attest(&setup.attacker, &attestation, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// An attestation of the issuer about the attacker, with the layout of the
+/// program, but owned by another program.
+#[test]
+fn test_spoofed_attestation() {
+    let setup = Setup::new();
+    let mut attestation_account =
+        attestation_account(&setup.mollusk, &setup.issuer, &setup.attacker);
+    attestation_account.owner = Pubkey::new_unique();
+    let attestation = Pubkey::new_unique();
+    let setup = setup.with(attestation, attestation_account);
+    setup.expect_err(
+        &setup.instruction_verify(&setup.attacker, &attestation, SCHEMA_ID),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker presents the attestation about the subject as an
+/// attestation about themselves.
+#[test]
+fn test_attestation_of_other_subject() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_verify(&setup.attacker, &setup.attestation, SCHEMA_ID),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attestation about the subject, presented under another schema of
+/// the issuer.
+#[test]
+fn test_attestation_of_other_schema() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_verify(&setup.subject, &setup.attestation, SCHEMA_ID + 1),
+        ProgramError::InvalidAccountData,
+    );
+}
@@ -0,0 +1,284 @@
+use std::mem;
+
+use attestations::{
+    AttestInstructionData, Attestation, AttestationError, AttestationInstruction,
+    VerifyInstructionData, ATTESTATION_SEED,
+};
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(attestations::ID);
+
+/// Offsets of the attestation account fields.
+const DATA_HASH_OFFSET: usize = 64;
+const SCHEMA_ID_OFFSET: usize = 96;
+const ISSUED_AT_OFFSET: usize = 104;
+const EXPIRES_AT_OFFSET: usize = 112;
+const REVOKED_OFFSET: usize = 120;
+
+const SCHEMA_ID: u64 = 7;
+const DATA_HASH: [u8; 32] = [42; 32];
+const NOW: i64 = 1_000_000;
+const EXPIRES_AT: i64 = NOW + 3600;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: AttestationInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<AttestationInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+/// An issuer, a subject and the address of an attestation about the subject.
+struct Setup {
+    mollusk: Mollusk,
+    issuer: Pubkey,
+    subject: Pubkey,
+    attestation: Pubkey,
+    bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/attestations");
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let issuer = Pubkey::new_unique();
+        let subject = Pubkey::new_unique();
+        let (attestation, bump) = Pubkey::find_program_address(
+            &[
+                ATTESTATION_SEED.as_bytes(),
+                issuer.as_ref(),
+                subject.as_ref(),
+                &SCHEMA_ID.to_le_bytes(),
+            ],
+            &ID,
+        );
+
+        let accounts = vec![
+            (
+                issuer,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (subject, Account::default()),
+            (attestation, Account::default()),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            issuer,
+            subject,
+            attestation,
+            bump,
+            accounts,
+        }
+    }
+
+    fn instruction_attest(&self, expires_at: i64) -> Instruction {
+        let data = AttestInstructionData::new(DATA_HASH, SCHEMA_ID, expires_at, self.bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.issuer, true),
+            AccountMeta::new_readonly(self.subject, false),
+            AccountMeta::new(self.attestation, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(AttestationInstruction::Attest, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_revoke(&self, issuer: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*issuer, true),
+            AccountMeta::new(self.attestation, false),
+        ];
+        Instruction::new_with_bytes(ID, &[AttestationInstruction::Revoke as u8], ix_accounts)
+    }
+
+    fn instruction_verify(&self, issuer: &Pubkey, data_hash: [u8; 32]) -> Instruction {
+        let data = VerifyInstructionData::new(data_hash, SCHEMA_ID);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*issuer, false),
+            AccountMeta::new_readonly(self.subject, false),
+            AccountMeta::new_readonly(self.attestation, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(AttestationInstruction::Verify, &data),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_attestations_attest_and_verify() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_attest(EXPIRES_AT),
+                &[
+                    Check::success(),
+                    Check::account(&setup.attestation)
+                        .owner(&ID)
+                        .space(Attestation::LEN)
+                        .data_slice(0, setup.issuer.as_ref())
+                        .data_slice(32, setup.subject.as_ref())
+                        .data_slice(DATA_HASH_OFFSET, &DATA_HASH)
+                        .data_slice(SCHEMA_ID_OFFSET, &SCHEMA_ID.to_le_bytes())
+                        .data_slice(ISSUED_AT_OFFSET, &NOW.to_le_bytes())
+                        .data_slice(EXPIRES_AT_OFFSET, &EXPIRES_AT.to_le_bytes())
+                        .data_slice(REVOKED_OFFSET, &[0])
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_verify(&setup.issuer, DATA_HASH),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_verify(&setup.issuer, [0; 32]),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Attestations.code(AttestationError::DataMismatch as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_attestations_invalid_expiry() {
+    let setup = Setup::new();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_attest(NOW),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Attestations.code(AttestationError::InvalidExpiry as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_attestations_expired() {
+    let mut setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_attest(EXPIRES_AT),
+        &setup.accounts,
+        &[Check::success()],
+    );
+    let attestation_account = res.get_account(&setup.attestation).unwrap().clone();
+    setup.accounts[2].1 = attestation_account;
+
+    setup.mollusk.sysvars.clock.unix_timestamp = EXPIRES_AT;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_verify(&setup.issuer, DATA_HASH),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Attestations.code(AttestationError::Expired as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_attestations_never_expires() {
+    let mut setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_attest(0),
+        &setup.accounts,
+        &[Check::success()],
+    );
+    let attestation_account = res.get_account(&setup.attestation).unwrap().clone();
+    setup.accounts[2].1 = attestation_account;
+
+    setup.mollusk.sysvars.clock.unix_timestamp = i64::MAX;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_verify(&setup.issuer, DATA_HASH),
+        &setup.accounts,
+        &[Check::success()],
+    );
+}
+
+#[test]
+fn test_attestations_revoke() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (&setup.instruction_attest(EXPIRES_AT), &[Check::success()]),
+            (
+                &setup.instruction_revoke(&setup.issuer),
+                &[
+                    Check::success(),
+                    Check::account(&setup.attestation)
+                        .data_slice(REVOKED_OFFSET, &[1])
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_verify(&setup.issuer, DATA_HASH),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Attestations.code(AttestationError::Revoked as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_attestations_wrong_issuer() {
+    let mut setup = Setup::new();
+
+    let attacker = Pubkey::new_unique();
+    setup.accounts.push((attacker, Account::default()));
+
+    let accounts = setup
+        .mollusk
+        .process_and_validate_instruction(
+            &setup.instruction_attest(EXPIRES_AT),
+            &setup.accounts,
+            &[Check::success()],
+        )
+        .resulting_accounts;
+    // Only the issuer can revoke.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_revoke(&attacker),
+        &accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+    // The attestation isn't valid for other issuers.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_verify(&attacker, DATA_HASH),
+        &accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
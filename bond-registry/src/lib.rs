@@ -0,0 +1,664 @@
+//! Registry of bonds posted by operators and slashed for misbehavior.
+//!
+//! Operators of off-chain infrastructure, e.g. oracles or relayers, post a
+//! bond into a PDA of the registry with [`BondRegistryInstruction::Bond`].
+//! The authority of the registry slashes a bond, partially or fully, to the
+//! beneficiary of the registry with [`BondRegistryInstruction::Slash`].
+//! Anyone can slash a whole bond with
+//! [`BondRegistryInstruction::SlashWithProof`] by revealing the preimage of
+//! its fault hash, a secret the operator committed to when bonding which
+//! only leaks through misbehavior, e.g. its share of a threshold key.
+//!
+//! Operators leave with [`BondRegistryInstruction::RequestUnbond`] and
+//! withdraw what's left of their bond with
+//! [`BondRegistryInstruction::Withdraw`] after the unbonding period of the
+//! registry. Bonds stay slashable during the period, so misbehavior right
+//! before leaving is still punished.
+//!
+//! A registry holds either lamports, in the bonds themselves on top of
+//! their rent, or tokens of its mint, in vaults owned by the bonds. The
+//! instructions moving tokens take the token accounts and the token program
+//! after the accounts they take for lamports.
+
+#![no_std]
+
+use common::{
+    assert_layout, check_pda,
+    checks::{require_program, require_signer},
+    close::close_account,
+    create::create_account,
+    info, keys_eq, AccountData,
+};
+use errors::{Namespace, SharedError};
+use macros::{InstructionData, InstructionDiscriminator};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::Transfer as SystemTransfer;
+use pinocchio_token::{
+    instructions::{CloseAccount, Transfer},
+    state::{Mint, TokenAccount},
+};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("4dWQ4CMSABmwBz7iAZrSgv8abrCQy8jD8o558HiDtk4d");
+
+pub const REGISTRY_SEED: &str = "registry";
+pub const BOND_SEED: &str = "bond";
+
+/// Errors returned by the bond registry program.
+#[derive(Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum BondRegistryError {
+    /// The unbonding period is negative.
+    InvalidUnbondingPeriod,
+    /// The beneficiary doesn't match the registry.
+    BeneficiaryMismatch,
+    /// The slashed amount exceeds the bond.
+    SlashExceedsBond,
+    /// The revealed secret doesn't hash to the fault hash of the bond.
+    InvalidProof,
+    /// The operator already requested to unbond.
+    AlreadyUnbonding,
+    /// The operator didn't request to unbond or the unbonding period didn't
+    /// elapse yet.
+    StillBonded,
+}
+
+impl From<BondRegistryError> for ProgramError {
+    fn from(e: BondRegistryError) -> Self {
+        Namespace::BondRegistry.error(e as u32)
+    }
+}
+
+/// On-chain representation of a registry.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Registry {
+    /// Slashes bonds.
+    pub authority: Pubkey,
+    /// Receives the slashed funds, a system account for lamports and a token
+    /// account of [`Registry::mint`] for tokens.
+    pub beneficiary: Pubkey,
+    /// Mint of the bonded tokens, zeroed for lamports.
+    pub mint: Pubkey,
+    /// Seconds between the unbonding request and the withdrawal of a bond.
+    pub unbonding_period: i64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+unsafe impl AccountData for Registry {
+    const OWNER: Pubkey = ID;
+}
+
+assert_layout!(Registry, size = 112, align = 8, {
+    authority: 0,
+    beneficiary: 32,
+    mint: 64,
+    unbonding_period: 96,
+    bump: 104,
+});
+
+impl Registry {
+    /// Returns whether the bonds are tokens of [`Registry::mint`] rather
+    /// than lamports.
+    pub fn holds_tokens(&self) -> bool {
+        self.mint != [0; 32]
+    }
+}
+
+/// On-chain representation of a bond.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Bond {
+    pub registry: Pubkey,
+    pub operator: Pubkey,
+    /// SHA-256 hash of the secret whose revelation slashes the whole bond.
+    pub fault_hash: [u8; 32],
+    /// Bonded amount left after slashing.
+    pub amount: u64,
+    /// Unix timestamp from which the bond can be withdrawn, zero until the
+    /// operator requests to unbond.
+    pub withdrawable_at: i64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+unsafe impl AccountData for Bond {
+    const OWNER: Pubkey = ID;
+}
+
+assert_layout!(Bond, size = 120, align = 8, {
+    registry: 0,
+    operator: 32,
+    fault_hash: 64,
+    amount: 96,
+    withdrawable_at: 104,
+    bump: 112,
+});
+
+/// Bond registry program instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum BondRegistryInstruction {
+    /// Creates a registry.
+    InitializeRegistry,
+    /// Posts the bond of an operator.
+    Bond,
+    /// Slashes part of a bond, signed by the authority.
+    Slash,
+    /// Slashes a whole bond, proven by the secret of its fault hash.
+    SlashWithProof,
+    /// Starts the unbonding period of a bond.
+    RequestUnbond,
+    /// Withdraws a bond after its unbonding period.
+    Withdraw,
+}
+
+/// Instruction data of [`BondRegistryInstruction::InitializeRegistry`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct InitializeRegistryInstructionData {
+    pub unbonding_period: i64,
+    pub bump: u8,
+}
+
+/// Instruction data of [`BondRegistryInstruction::Bond`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct BondInstructionData {
+    pub amount: u64,
+    pub fault_hash: [u8; 32],
+    pub bump: u8,
+}
+
+/// Instruction data of [`BondRegistryInstruction::Slash`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct SlashInstructionData {
+    pub amount: u64,
+}
+
+/// Instruction data of [`BondRegistryInstruction::SlashWithProof`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct SlashWithProofInstructionData {
+    /// Preimage of the fault hash of the bond.
+    pub secret: [u8; 32],
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = BondRegistryInstruction::split(instruction_data)?;
+
+    match instruction {
+        BondRegistryInstruction::InitializeRegistry => {
+            process_initialize_registry(accounts, instruction_data)
+        }
+        BondRegistryInstruction::Bond => process_bond(accounts, instruction_data),
+        BondRegistryInstruction::Slash => process_slash(accounts, instruction_data),
+        BondRegistryInstruction::SlashWithProof => {
+            process_slash_with_proof(accounts, instruction_data)
+        }
+        BondRegistryInstruction::RequestUnbond => process_request_unbond(accounts),
+        BondRegistryInstruction::Withdraw => process_withdraw(accounts),
+    }
+}
+
+/// Creates a registry of bonds in lamports or, if a mint is passed, in
+/// tokens of the mint.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Authority and payer
+///   1. `[WRITE]` Registry PDA
+///   2. `[]` Beneficiary, a token account of the mint for tokens
+///   3. `[]` System program
+///   4. `[]` (Optional) Mint
+pub fn process_initialize_registry(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let (authority, registry, beneficiary, system_program, mint) = match accounts {
+        [authority, registry, beneficiary, system_program] => {
+            (authority, registry, beneficiary, system_program, None)
+        }
+        [authority, registry, beneficiary, system_program, mint] => {
+            (authority, registry, beneficiary, system_program, Some(mint))
+        }
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+
+    require_signer(authority)?;
+    require_program(system_program, &pinocchio_system::ID)?;
+
+    // Slashed tokens are transferred to the beneficiary, which has to hold
+    // them.
+    let mint = match mint {
+        Some(mint) => {
+            Mint::from_account_info(mint)?;
+            if !keys_eq(
+                TokenAccount::from_account_info(beneficiary)?.mint(),
+                mint.key(),
+            ) {
+                return Err(BondRegistryError::BeneficiaryMismatch.into());
+            }
+            *mint.key()
+        }
+        None => [0; 32],
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeRegistryInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.unbonding_period < 0 {
+        return Err(BondRegistryError::InvalidUnbondingPeriod.into());
+    }
+
+    check_pda(
+        &[REGISTRY_SEED.as_bytes(), authority.key()],
+        instruction_data.bump,
+        registry.key(),
+        &ID,
+    )?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(REGISTRY_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    create_account(
+        authority,
+        registry,
+        Registry::LEN,
+        &ID,
+        &[Signer::from(&seeds)],
+    )?;
+
+    let mut data = Registry::init(registry)?;
+    data.authority = *authority.key();
+    data.beneficiary = *beneficiary.key();
+    data.mint = mint;
+    data.unbonding_period = instruction_data.unbonding_period;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Initialized registry with {} s unbonding period",
+        instruction_data.unbonding_period
+    );
+
+    Ok(())
+}
+
+/// Posts a bond of `amount` for the operator, committed to `fault_hash`.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Operator and payer
+///   1. `[]` Registry
+///   2. `[WRITE]` Bond PDA
+///   3. `[]` System program
+///
+/// For tokens, followed by:
+///   4. `[WRITE]` Token account of the operator
+///   5. `[WRITE]` Vault, a token account of the mint owned by the bond
+///   6. `[]` Token program
+pub fn process_bond(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [operator, registry, bond, system_program, token_accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(operator)?;
+    require_program(system_program, &pinocchio_system::ID)?;
+
+    let registry_data = *Registry::load(registry)?;
+
+    // Deserialize instruction data.
+    let instruction_data = BondInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.amount == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+
+    check_pda(
+        &[BOND_SEED.as_bytes(), registry.key(), operator.key()],
+        instruction_data.bump,
+        bond.key(),
+        &ID,
+    )?;
+
+    let token_accounts = match token_accounts {
+        [] if !registry_data.holds_tokens() => None,
+        [operator_token_account, vault, token_program] if registry_data.holds_tokens() => {
+            require_program(token_program, &pinocchio_token::ID)?;
+            require_vault(&registry_data, bond, vault)?;
+            Some((operator_token_account, vault))
+        }
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+
+    // Create the bond PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(BOND_SEED.as_bytes()),
+        Seed::from(registry.key()),
+        Seed::from(operator.key()),
+        Seed::from(&bump),
+    ];
+    create_account(operator, bond, Bond::LEN, &ID, &[Signer::from(&seeds)])?;
+
+    {
+        let mut data = Bond::init(bond)?;
+        data.registry = *registry.key();
+        data.operator = *operator.key();
+        data.fault_hash = instruction_data.fault_hash;
+        data.amount = instruction_data.amount;
+        data.withdrawable_at = 0;
+        data.bump = instruction_data.bump;
+    }
+
+    // Deposit the bond.
+    match token_accounts {
+        Some((operator_token_account, vault)) => Transfer {
+            from: operator_token_account,
+            to: vault,
+            authority: operator,
+            amount: instruction_data.amount,
+        }
+        .invoke()?,
+        None => SystemTransfer {
+            from: operator,
+            to: bond,
+            lamports: instruction_data.amount,
+        }
+        .invoke()?,
+    }
+
+    info!("Bonded {}", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Slashes `amount` of a bond to the beneficiary of the registry.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Authority of the registry
+///   1. `[]` Registry
+///   2. `[WRITE]` Bond
+///   3. `[WRITE]` Beneficiary
+///
+/// For tokens, followed by:
+///   4. `[WRITE]` Vault of the bond
+///   5. `[]` Token program
+pub fn process_slash(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, registry, bond, beneficiary, token_accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    let registry_data = *Registry::load(registry)?;
+    if !keys_eq(&registry_data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = SlashInstructionData::from_bytes(instruction_data)?;
+
+    slash(
+        registry,
+        &registry_data,
+        bond,
+        beneficiary,
+        token_accounts,
+        |_| Ok(instruction_data.amount),
+    )
+}
+
+/// Slashes a whole bond to the beneficiary of the registry, proven by the
+/// preimage of its fault hash. Anyone who knows the secret can submit it.
+///
+/// ### Accounts:
+///   0. `[]` Registry
+///   1. `[WRITE]` Bond
+///   2. `[WRITE]` Beneficiary
+///
+/// For tokens, followed by:
+///   3. `[WRITE]` Vault of the bond
+///   4. `[]` Token program
+pub fn process_slash_with_proof(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [registry, bond, beneficiary, token_accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let registry_data = *Registry::load(registry)?;
+
+    // Deserialize instruction data.
+    let instruction_data = SlashWithProofInstructionData::from_bytes(instruction_data)?;
+
+    slash(
+        registry,
+        &registry_data,
+        bond,
+        beneficiary,
+        token_accounts,
+        |bond| {
+            if merkle::hashv(&[&instruction_data.secret]) != bond.fault_hash {
+                return Err(BondRegistryError::InvalidProof.into());
+            }
+            Ok(bond.amount)
+        },
+    )
+}
+
+/// Starts the unbonding period of the bond of the operator. The bond can
+/// still be slashed until it's withdrawn.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Operator
+///   1. `[]` Registry
+///   2. `[WRITE]` Bond
+pub fn process_request_unbond(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [operator, registry, bond] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(operator)?;
+
+    let unbonding_period = Registry::load(registry)?.unbonding_period;
+
+    let mut data = Bond::load_mut(bond)?;
+    if !keys_eq(&data.registry, registry.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !keys_eq(&data.operator, operator.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if data.withdrawable_at != 0 {
+        return Err(BondRegistryError::AlreadyUnbonding.into());
+    }
+
+    data.withdrawable_at = Clock::get()?
+        .unix_timestamp
+        .checked_add(unbonding_period)
+        .ok_or(SharedError::Overflow)?;
+
+    info!("Unbonding until {}", data.withdrawable_at);
+
+    Ok(())
+}
+
+/// Withdraws what's left of the bond of the operator after its unbonding
+/// period and closes it, returning the rent to the operator.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Operator
+///   1. `[]` Registry
+///   2. `[WRITE]` Bond
+///
+/// For tokens, followed by:
+///   3. `[WRITE]` Token account of the operator
+///   4. `[WRITE]` Vault of the bond
+///   5. `[]` Token program
+pub fn process_withdraw(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [operator, registry, bond, token_accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(operator)?;
+
+    let registry_data = *Registry::load(registry)?;
+
+    let data = *Bond::load(bond)?;
+    if !keys_eq(&data.registry, registry.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !keys_eq(&data.operator, operator.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if data.withdrawable_at == 0 || Clock::get()?.unix_timestamp < data.withdrawable_at {
+        return Err(BondRegistryError::StillBonded.into());
+    }
+
+    match token_accounts {
+        // The bond holds the lamports on top of its rent, closing it
+        // withdraws both.
+        [] if !registry_data.holds_tokens() => {}
+        [operator_token_account, vault, token_program] if registry_data.holds_tokens() => {
+            require_program(token_program, &pinocchio_token::ID)?;
+            require_vault(&registry_data, bond, vault)?;
+
+            let bump = [data.bump];
+            let seeds = bond_seeds(&data, &bump);
+            if data.amount > 0 {
+                Transfer {
+                    from: vault,
+                    to: operator_token_account,
+                    authority: bond,
+                    amount: data.amount,
+                }
+                .invoke_signed(&[Signer::from(&seeds)])?;
+            }
+            CloseAccount {
+                account: vault,
+                destination: operator,
+                authority: bond,
+            }
+            .invoke_signed(&[Signer::from(&seeds)])?;
+        }
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    }
+
+    close_account(bond, operator)?;
+
+    info!("Withdrew {}", data.amount);
+
+    Ok(())
+}
+
+/// Slashes the amount returned by `amount`, given the bond, from `bond` to
+/// `beneficiary`, moving lamports or, with `token_accounts`, tokens.
+fn slash(
+    registry: &AccountInfo,
+    registry_data: &Registry,
+    bond: &AccountInfo,
+    beneficiary: &AccountInfo,
+    token_accounts: &[AccountInfo],
+    amount: impl FnOnce(&Bond) -> Result<u64, ProgramError>,
+) -> ProgramResult {
+    if !keys_eq(&registry_data.beneficiary, beneficiary.key()) {
+        return Err(BondRegistryError::BeneficiaryMismatch.into());
+    }
+
+    let data = *Bond::load(bond)?;
+    if !keys_eq(&data.registry, registry.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let amount = amount(&data)?;
+    if amount == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+    let remaining = data
+        .amount
+        .checked_sub(amount)
+        .ok_or(BondRegistryError::SlashExceedsBond)?;
+
+    match token_accounts {
+        // The program owns the bond, so it moves the lamports without a CPI.
+        [] if !registry_data.holds_tokens() => {
+            let mut bond_lamports = bond.try_borrow_mut_lamports()?;
+            *bond_lamports = bond_lamports
+                .checked_sub(amount)
+                .ok_or(ProgramError::InsufficientFunds)?;
+            let mut beneficiary_lamports = beneficiary.try_borrow_mut_lamports()?;
+            *beneficiary_lamports = beneficiary_lamports
+                .checked_add(amount)
+                .ok_or(SharedError::Overflow)?;
+        }
+        [vault, token_program] if registry_data.holds_tokens() => {
+            require_program(token_program, &pinocchio_token::ID)?;
+            require_vault(registry_data, bond, vault)?;
+
+            let bump = [data.bump];
+            let seeds = bond_seeds(&data, &bump);
+            Transfer {
+                from: vault,
+                to: beneficiary,
+                authority: bond,
+                amount,
+            }
+            .invoke_signed(&[Signer::from(&seeds)])?;
+        }
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    }
+
+    Bond::load_mut(bond)?.amount = remaining;
+
+    info!("Slashed {}, {} left", amount, remaining);
+
+    Ok(())
+}
+
+/// Checks that `vault` is a token account of the mint of the registry owned
+/// by `bond`.
+fn require_vault(registry: &Registry, bond: &AccountInfo, vault: &AccountInfo) -> ProgramResult {
+    let vault = TokenAccount::from_account_info(vault)?;
+    if !keys_eq(vault.owner(), bond.key()) || !keys_eq(vault.mint(), &registry.mint) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Returns the seeds of the PDA of `bond`, signing for its vault.
+fn bond_seeds<'a>(bond: &'a Bond, bump: &'a [u8; 1]) -> [Seed<'a>; 4] {
+    [
+        Seed::from(BOND_SEED.as_bytes()),
+        Seed::from(&bond.registry),
+        Seed::from(&bond.operator),
+        Seed::from(bump),
+    ]
+}
@@ -0,0 +1,444 @@
+//! Attacks on the bond registry program, each rejected with its own error.
+
+use bond_registry::{
+    BondInstructionData, BondRegistryInstruction, InitializeRegistryInstructionData,
+    SlashInstructionData, SlashWithProofInstructionData, BOND_SEED, REGISTRY_SEED,
+};
+use mollusk_svm::{
+    program::{keyed_account_for_system_program, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::{funded_account, keyed_token_program, mint_account, programs, token_account};
+
+const ID: Pubkey = Pubkey::new_from_array(bond_registry::ID);
+
+const AMOUNT: u64 = 1_000_000;
+const NOW: i64 = 1_000_000;
+const UNBONDING_PERIOD: i64 = 86_400;
+const SECRET: [u8; 32] = [7; 32];
+
+fn instruction(
+    instruction: BondRegistryInstruction,
+    data: &[u8],
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let mut instruction_data = instruction.discriminator().to_vec();
+    instruction_data.extend_from_slice(data);
+    Instruction::new_with_bytes(ID, &instruction_data, accounts)
+}
+
+fn registry_address(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REGISTRY_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+fn bond_address(registry: &Pubkey, operator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[BOND_SEED.as_bytes(), registry.as_ref(), operator.as_ref()],
+        &ID,
+    )
+}
+
+/// Returns an instruction creating a lamport registry of `authority`, whose
+/// slashed lamports go to `beneficiary`.
+fn initialize_registry(
+    authority: &Pubkey,
+    beneficiary: &Pubkey,
+    unbonding_period: i64,
+) -> Instruction {
+    let (registry, bump) = registry_address(authority);
+    instruction(
+        BondRegistryInstruction::InitializeRegistry,
+        &InitializeRegistryInstructionData {
+            unbonding_period,
+            bump,
+        }
+        .to_bytes(),
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*beneficiary, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ],
+    )
+}
+
+/// A lamport registry in which the operator posted a bond of [`AMOUNT`],
+/// and an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    operator: Pubkey,
+    beneficiary: Pubkey,
+    registry: Pubkey,
+    bond: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    /// Creates the registry and, if `bonded`, posts the bond of the operator.
+    fn new(bonded: bool) -> Self {
+        let mut mollusk = programs::mollusk();
+        mollusk.add_program(&ID, "target/deploy/bond_registry", &LOADER_V3);
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let authority = Pubkey::new_unique();
+        let operator = Pubkey::new_unique();
+        let beneficiary = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let (registry, _) = registry_address(&authority);
+        let (bond, _) = bond_address(&registry, &operator);
+
+        let mut setup = Self {
+            mollusk,
+            authority,
+            operator,
+            beneficiary,
+            registry,
+            bond,
+            attacker,
+            accounts: vec![
+                (authority, funded_account(LAMPORTS_PER_SOL)),
+                (operator, funded_account(LAMPORTS_PER_SOL)),
+                (beneficiary, funded_account(LAMPORTS_PER_SOL)),
+                (attacker, funded_account(LAMPORTS_PER_SOL)),
+                (registry, Account::default()),
+                (bond, Account::default()),
+                keyed_account_for_system_program(),
+                keyed_token_program(),
+            ],
+        };
+        setup.process(&initialize_registry(
+            &authority,
+            &beneficiary,
+            UNBONDING_PERIOD,
+        ));
+        if bonded {
+            setup.process(&setup.bond(&operator, &bond));
+        }
+        setup
+    }
+
+    /// Processes `instruction`, which has to succeed, and keeps the
+    /// resulting accounts.
+    fn process(&mut self, instruction: &Instruction) {
+        let res = self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::success()],
+        );
+        self.accounts = res.resulting_accounts;
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    /// Creates a lamport registry of `authority`, who is also its
+    /// beneficiary, without an unbonding period, and returns its address.
+    fn registry_of(&mut self, authority: &Pubkey) -> Pubkey {
+        let (registry, _) = registry_address(authority);
+        self.accounts.push((registry, Account::default()));
+        self.process(&initialize_registry(authority, authority, 0));
+        registry
+    }
+
+    /// Returns an instruction posting a bond of `operator` at `bond`, with
+    /// the bump of the PDA of `operator`.
+    fn bond(&self, operator: &Pubkey, bond: &Pubkey) -> Instruction {
+        let (_, bump) = bond_address(&self.registry, operator);
+        instruction(
+            BondRegistryInstruction::Bond,
+            &BondInstructionData {
+                amount: AMOUNT,
+                fault_hash: merkle::hashv(&[&SECRET]),
+                bump,
+            }
+            .to_bytes(),
+            vec![
+                AccountMeta::new(*operator, true),
+                AccountMeta::new_readonly(self.registry, false),
+                AccountMeta::new(*bond, false),
+                AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            ],
+        )
+    }
+
+    fn slash(&self, authority: &Pubkey, registry: &Pubkey, beneficiary: &Pubkey) -> Instruction {
+        instruction(
+            BondRegistryInstruction::Slash,
+            &SlashInstructionData { amount: AMOUNT }.to_bytes(),
+            vec![
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new_readonly(*registry, false),
+                AccountMeta::new(self.bond, false),
+                AccountMeta::new(*beneficiary, false),
+            ],
+        )
+    }
+
+    fn slash_with_proof(&self, bond: &Pubkey) -> Instruction {
+        instruction(
+            BondRegistryInstruction::SlashWithProof,
+            &SlashWithProofInstructionData { secret: SECRET }.to_bytes(),
+            vec![
+                AccountMeta::new_readonly(self.registry, false),
+                AccountMeta::new(*bond, false),
+                AccountMeta::new(self.beneficiary, false),
+            ],
+        )
+    }
+
+    fn request_unbond(&self, operator: &Pubkey, registry: &Pubkey) -> Instruction {
+        instruction(
+            BondRegistryInstruction::RequestUnbond,
+            &[],
+            vec![
+                AccountMeta::new_readonly(*operator, true),
+                AccountMeta::new_readonly(*registry, false),
+                AccountMeta::new(self.bond, false),
+            ],
+        )
+    }
+
+    fn withdraw(&self, operator: &Pubkey) -> Instruction {
+        instruction(
+            BondRegistryInstruction::Withdraw,
+            &[],
+            vec![
+                AccountMeta::new(*operator, true),
+                AccountMeta::new_readonly(self.registry, false),
+                AccountMeta::new(self.bond, false),
+            ],
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new(true);
+    let mut instruction = setup.slash(&setup.authority, &setup.registry, &setup.beneficiary);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+#[test]
+fn test_missing_operator_signature() {
+    let mut setup = Setup::new(true);
+    setup.process(&setup.request_unbond(&setup.operator, &setup.registry));
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + UNBONDING_PERIOD;
+    let mut instruction = setup.withdraw(&setup.operator);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker slashes the bond to themselves through a registry of their
+/// own.
+#[test]
+fn test_slash_through_other_registry() {
+    let mut setup = Setup::new(true);
+    let attacker = setup.attacker;
+    let registry = setup.registry_of(&attacker);
+    setup.expect_err(
+        &setup.slash(&setup.attacker, &registry, &setup.attacker),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The operator skips the unbonding period through a registry of their own
+/// without one.
+#[test]
+fn test_unbond_through_other_registry() {
+    let mut setup = Setup::new(true);
+    let operator = setup.operator;
+    let registry = setup.registry_of(&operator);
+    setup.expect_err(
+        &setup.request_unbond(&setup.operator, &registry),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker starts the unbonding period of the bond, e.g. to have it
+/// withdrawn before the operator could react to a slashing.
+#[test]
+fn test_unbond_by_attacker() {
+    let setup = Setup::new(true);
+    setup.expect_err(
+        &setup.request_unbond(&setup.attacker, &setup.registry),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker withdraws the bond after its unbonding period.
+#[test]
+fn test_withdraw_by_attacker() {
+    let mut setup = Setup::new(true);
+    setup.process(&setup.request_unbond(&setup.operator, &setup.registry));
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + UNBONDING_PERIOD;
+    setup.expect_err(&setup.withdraw(&setup.attacker), ProgramError::IllegalOwner);
+}
+
+/// A registry with the layout of the program, but owned by another one,
+/// e.g. with the attacker as the authority.
+#[test]
+fn test_spoofed_registry() {
+    let setup = Setup::new(true);
+    let registry = setup.registry;
+    let mut registry_account = setup.account(&registry);
+    registry_account.owner = Pubkey::new_unique();
+    let setup = setup.with(registry, registry_account);
+    setup.expect_err(
+        &setup.slash(&setup.authority, &setup.registry, &setup.beneficiary),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The bond passed as the registry, which would be read as a registry of
+/// another authority.
+#[test]
+fn test_bond_as_registry() {
+    let setup = Setup::new(true);
+    setup.expect_err(
+        &setup.slash(&setup.authority, &setup.bond, &setup.beneficiary),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The registry passed as the bond, whose lamports would be slashed.
+#[test]
+fn test_registry_as_bond() {
+    let setup = Setup::new(true);
+    setup.expect_err(
+        &setup.slash_with_proof(&setup.registry),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker posts a bond at the PDA of the operator, e.g. committed to
+/// a fault hash of their choice.
+#[test]
+fn test_wrong_bond_seeds() {
+    let setup = Setup::new(false);
+    let instruction = setup.bond(&setup.attacker, &setup.bond);
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The authority of a token registry slashes the bond from a vault of
+/// another bond.
+#[test]
+fn test_slash_vault_of_other_bond() {
+    let mollusk = {
+        let mut mollusk = programs::mollusk();
+        mollusk.add_program(&ID, "target/deploy/bond_registry", &LOADER_V3);
+        mollusk
+    };
+    let authority = Pubkey::new_unique();
+    let (registry, registry_bump) = registry_address(&authority);
+    let mint = Pubkey::new_unique();
+    let beneficiary_ata = Pubkey::new_unique();
+    let operator = Pubkey::new_unique();
+    let (bond, bond_bump) = bond_address(&registry, &operator);
+    let vault = Pubkey::new_unique();
+    let (other_bond, _) = bond_address(&registry, &Pubkey::new_unique());
+    let other_vault = Pubkey::new_unique();
+    let operator_ata = Pubkey::new_unique();
+
+    let token_program = keyed_token_program().0;
+    let initialize_registry = instruction(
+        BondRegistryInstruction::InitializeRegistry,
+        &InitializeRegistryInstructionData {
+            unbonding_period: UNBONDING_PERIOD,
+            bump: registry_bump,
+        }
+        .to_bytes(),
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(beneficiary_ata, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(mint, false),
+        ],
+    );
+    let bond_instruction = instruction(
+        BondRegistryInstruction::Bond,
+        &BondInstructionData {
+            amount: AMOUNT,
+            fault_hash: merkle::hashv(&[&SECRET]),
+            bump: bond_bump,
+        }
+        .to_bytes(),
+        vec![
+            AccountMeta::new(operator, true),
+            AccountMeta::new_readonly(registry, false),
+            AccountMeta::new(bond, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new(operator_ata, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+    );
+    let slash = instruction(
+        BondRegistryInstruction::Slash,
+        &SlashInstructionData { amount: AMOUNT }.to_bytes(),
+        vec![
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new_readonly(registry, false),
+            AccountMeta::new(bond, false),
+            AccountMeta::new(beneficiary_ata, false),
+            AccountMeta::new(other_vault, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+    );
+
+    let accounts = vec![
+        (authority, funded_account(LAMPORTS_PER_SOL)),
+        (operator, funded_account(LAMPORTS_PER_SOL)),
+        (registry, Account::default()),
+        (bond, Account::default()),
+        (mint, mint_account(6, 2 * AMOUNT)),
+        (beneficiary_ata, token_account(&mint, &authority, 0)),
+        (operator_ata, token_account(&mint, &operator, AMOUNT)),
+        (vault, token_account(&mint, &bond, 0)),
+        (other_vault, token_account(&mint, &other_bond, AMOUNT)),
+        keyed_account_for_system_program(),
+        keyed_token_program(),
+    ];
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&initialize_registry, &[Check::success()]),
+            (&bond_instruction, &[Check::success()]),
+        ],
+        &accounts,
+    );
+    mollusk.process_and_validate_instruction(
+        &slash,
+        &res.resulting_accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
@@ -0,0 +1,474 @@
+use bond_registry::{
+    Bond, BondInstructionData, BondRegistryError, BondRegistryInstruction,
+    InitializeRegistryInstructionData, Registry, SlashInstructionData,
+    SlashWithProofInstructionData, BOND_SEED, REGISTRY_SEED,
+};
+use common::AccountData;
+use errors::{Namespace, SharedError};
+use mollusk_svm::{
+    program::{keyed_account_for_system_program, loader_keys::LOADER_V3},
+    result::{Check, InstructionResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::{
+    funded_account, keyed_token_program, mint_account, programs, token_account, token_amount,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(bond_registry::ID);
+
+const AMOUNT: u64 = 1_000_000;
+const NOW: i64 = 1_000_000;
+const UNBONDING_PERIOD: i64 = 86_400;
+const SECRET: [u8; 32] = [7; 32];
+
+fn error(e: BondRegistryError) -> ProgramError {
+    ProgramError::Custom(Namespace::BondRegistry.code(e as u32))
+}
+
+fn instruction(
+    instruction: BondRegistryInstruction,
+    data: &[u8],
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let mut instruction_data = instruction.discriminator().to_vec();
+    instruction_data.extend_from_slice(data);
+    Instruction::new_with_bytes(ID, &instruction_data, accounts)
+}
+
+/// A registry of lamports or tokens, with one operator, whose accounts are
+/// threaded through the instructions.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    operator: Pubkey,
+    /// Receives the slashed lamports, or tokens in `beneficiary_ata`.
+    beneficiary: Pubkey,
+    registry: Pubkey,
+    registry_bump: u8,
+    bond: Pubkey,
+    bond_bump: u8,
+    /// Mint, token accounts of the operator, the beneficiary and the vault
+    /// of the bond, for tokens.
+    tokens: Option<(Pubkey, Pubkey, Pubkey, Pubkey)>,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new(tokens: bool) -> Self {
+        let mut mollusk = programs::mollusk();
+        mollusk.add_program(&ID, "target/deploy/bond_registry", &LOADER_V3);
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let authority = Pubkey::new_unique();
+        let operator = Pubkey::new_unique();
+        let beneficiary = Pubkey::new_unique();
+        let (registry, registry_bump) =
+            Pubkey::find_program_address(&[REGISTRY_SEED.as_bytes(), authority.as_ref()], &ID);
+        let (bond, bond_bump) = Pubkey::find_program_address(
+            &[BOND_SEED.as_bytes(), registry.as_ref(), operator.as_ref()],
+            &ID,
+        );
+
+        let mut accounts = vec![
+            (authority, funded_account(LAMPORTS_PER_SOL)),
+            (operator, funded_account(LAMPORTS_PER_SOL)),
+            (beneficiary, funded_account(LAMPORTS_PER_SOL)),
+            (registry, Account::default()),
+            (bond, Account::default()),
+            keyed_account_for_system_program(),
+            keyed_token_program(),
+        ];
+        let tokens = tokens.then(|| {
+            let mint = Pubkey::new_unique();
+            let operator_ata = Pubkey::new_unique();
+            let beneficiary_ata = Pubkey::new_unique();
+            let vault = Pubkey::new_unique();
+            accounts.extend([
+                (mint, mint_account(6, AMOUNT)),
+                (operator_ata, token_account(&mint, &operator, AMOUNT)),
+                (beneficiary_ata, token_account(&mint, &beneficiary, 0)),
+                (vault, token_account(&mint, &bond, 0)),
+            ]);
+            (mint, operator_ata, beneficiary_ata, vault)
+        });
+
+        Self {
+            mollusk,
+            authority,
+            operator,
+            beneficiary,
+            registry,
+            registry_bump,
+            bond,
+            bond_bump,
+            tokens,
+            accounts,
+        }
+    }
+
+    /// Processes `instruction`, keeping the resulting accounts if it
+    /// succeeded.
+    fn process(&mut self, instruction: &Instruction, checks: &[Check]) -> InstructionResult {
+        let res =
+            self.mollusk
+                .process_and_validate_instruction(instruction, &self.accounts, checks);
+        if res.program_result.is_ok() {
+            self.accounts = res.resulting_accounts.clone();
+        }
+        res
+    }
+
+    fn account(&self, address: &Pubkey) -> &Account {
+        &self
+            .accounts
+            .iter()
+            .find(|(key, _)| key == address)
+            .unwrap()
+            .1
+    }
+
+    fn bond_state(&self) -> Bond {
+        Bond::read(&self.account(&self.bond).data).unwrap()
+    }
+
+    /// Returns the lamports of the beneficiary or the tokens of its token
+    /// account.
+    fn beneficiary_balance(&self) -> u64 {
+        match self.tokens {
+            Some((_, _, beneficiary_ata, _)) => token_amount(self.account(&beneficiary_ata)),
+            None => self.account(&self.beneficiary).lamports,
+        }
+    }
+
+    fn initialize_registry(&self, unbonding_period: i64) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new(self.registry, false),
+            AccountMeta::new_readonly(self.beneficiary_key(), false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        if let Some((mint, ..)) = self.tokens {
+            accounts.push(AccountMeta::new_readonly(mint, false));
+        }
+        instruction(
+            BondRegistryInstruction::InitializeRegistry,
+            &InitializeRegistryInstructionData {
+                unbonding_period,
+                bump: self.registry_bump,
+            }
+            .to_bytes(),
+            accounts,
+        )
+    }
+
+    fn bond(&self, amount: u64) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(self.operator, true),
+            AccountMeta::new_readonly(self.registry, false),
+            AccountMeta::new(self.bond, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        if let Some((_, operator_ata, _, vault)) = self.tokens {
+            accounts.extend([
+                AccountMeta::new(operator_ata, false),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(keyed_token_program().0, false),
+            ]);
+        }
+        instruction(
+            BondRegistryInstruction::Bond,
+            &BondInstructionData {
+                amount,
+                fault_hash: merkle::hashv(&[&SECRET]),
+                bump: self.bond_bump,
+            }
+            .to_bytes(),
+            accounts,
+        )
+    }
+
+    fn slash(&self, authority: &Pubkey, amount: u64) -> Instruction {
+        let mut accounts = vec![AccountMeta::new_readonly(*authority, true)];
+        accounts.extend(self.slash_accounts());
+        instruction(
+            BondRegistryInstruction::Slash,
+            &SlashInstructionData { amount }.to_bytes(),
+            accounts,
+        )
+    }
+
+    fn slash_with_proof(&self, secret: [u8; 32]) -> Instruction {
+        instruction(
+            BondRegistryInstruction::SlashWithProof,
+            &SlashWithProofInstructionData { secret }.to_bytes(),
+            self.slash_accounts(),
+        )
+    }
+
+    fn request_unbond(&self) -> Instruction {
+        instruction(
+            BondRegistryInstruction::RequestUnbond,
+            &[],
+            vec![
+                AccountMeta::new_readonly(self.operator, true),
+                AccountMeta::new_readonly(self.registry, false),
+                AccountMeta::new(self.bond, false),
+            ],
+        )
+    }
+
+    fn withdraw(&self) -> Instruction {
+        let mut accounts = vec![
+            AccountMeta::new(self.operator, true),
+            AccountMeta::new_readonly(self.registry, false),
+            AccountMeta::new(self.bond, false),
+        ];
+        if let Some((_, operator_ata, _, vault)) = self.tokens {
+            accounts.extend([
+                AccountMeta::new(operator_ata, false),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(keyed_token_program().0, false),
+            ]);
+        }
+        instruction(BondRegistryInstruction::Withdraw, &[], accounts)
+    }
+
+    /// The beneficiary of the registry, its token account for tokens.
+    fn beneficiary_key(&self) -> Pubkey {
+        match self.tokens {
+            Some((_, _, beneficiary_ata, _)) => beneficiary_ata,
+            None => self.beneficiary,
+        }
+    }
+
+    fn slash_accounts(&self) -> Vec<AccountMeta> {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.registry, false),
+            AccountMeta::new(self.bond, false),
+            AccountMeta::new(self.beneficiary_key(), false),
+        ];
+        if let Some((_, _, _, vault)) = self.tokens {
+            accounts.extend([
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(keyed_token_program().0, false),
+            ]);
+        }
+        accounts
+    }
+
+    /// Creates the registry and posts the bond of the operator.
+    fn bonded(tokens: bool) -> Self {
+        let mut setup = Self::new(tokens);
+        setup.process(
+            &setup.initialize_registry(UNBONDING_PERIOD),
+            &[Check::success()],
+        );
+        setup.process(&setup.bond(AMOUNT), &[Check::success()]);
+        setup
+    }
+}
+
+#[test]
+fn test_bond() {
+    for tokens in [false, true] {
+        let setup = Setup::bonded(tokens);
+
+        let registry = Registry::read(&setup.account(&setup.registry).data).unwrap();
+        assert_eq!(registry.authority, setup.authority.to_bytes());
+        assert_eq!(registry.beneficiary, setup.beneficiary_key().to_bytes());
+        assert_eq!(registry.holds_tokens(), tokens);
+        assert_eq!(registry.unbonding_period, UNBONDING_PERIOD);
+
+        let bond = setup.bond_state();
+        assert_eq!(bond.operator, setup.operator.to_bytes());
+        assert_eq!(bond.amount, AMOUNT);
+        assert_eq!(bond.withdrawable_at, 0);
+
+        // The bond holds lamports on top of its rent, the vault holds
+        // tokens.
+        let rent = setup.mollusk.sysvars.rent.minimum_balance(Bond::LEN);
+        match setup.tokens {
+            Some((_, operator_ata, _, vault)) => {
+                assert_eq!(setup.account(&setup.bond).lamports, rent);
+                assert_eq!(token_amount(setup.account(&vault)), AMOUNT);
+                assert_eq!(token_amount(setup.account(&operator_ata)), 0);
+            }
+            None => assert_eq!(setup.account(&setup.bond).lamports, rent + AMOUNT),
+        }
+    }
+}
+
+#[test]
+fn test_slash() {
+    for tokens in [false, true] {
+        let mut setup = Setup::bonded(tokens);
+        let before = setup.beneficiary_balance();
+
+        setup.process(
+            &setup.slash(&setup.authority, AMOUNT / 4),
+            &[Check::success()],
+        );
+        assert_eq!(setup.bond_state().amount, AMOUNT - AMOUNT / 4);
+        assert_eq!(setup.beneficiary_balance(), before + AMOUNT / 4);
+
+        setup.process(
+            &setup.slash(&setup.authority, AMOUNT),
+            &[Check::err(error(BondRegistryError::SlashExceedsBond))],
+        );
+        setup.process(
+            &setup.slash(&setup.authority, 0),
+            &[Check::err(ProgramError::Custom(
+                Namespace::Shared.code(SharedError::InvalidAmount as u32),
+            ))],
+        );
+
+        // Only the authority slashes without a proof.
+        let other = Pubkey::new_unique();
+        setup
+            .accounts
+            .push((other, funded_account(LAMPORTS_PER_SOL)));
+        setup.process(
+            &setup.slash(&other, 1),
+            &[Check::err(ProgramError::IllegalOwner)],
+        );
+
+        // The slashed funds only go to the beneficiary of the registry.
+        let mut slash = setup.slash(&setup.authority, 1);
+        slash.accounts[3].pubkey = other;
+        setup.process(
+            &slash,
+            &[Check::err(error(BondRegistryError::BeneficiaryMismatch))],
+        );
+
+        // The rest of the bond.
+        setup.process(
+            &setup.slash(&setup.authority, AMOUNT - AMOUNT / 4),
+            &[Check::success()],
+        );
+        assert_eq!(setup.bond_state().amount, 0);
+        assert_eq!(setup.beneficiary_balance(), before + AMOUNT);
+    }
+}
+
+#[test]
+fn test_slash_with_proof() {
+    for tokens in [false, true] {
+        let mut setup = Setup::bonded(tokens);
+        let before = setup.beneficiary_balance();
+
+        setup.process(
+            &setup.slash_with_proof([8; 32]),
+            &[Check::err(error(BondRegistryError::InvalidProof))],
+        );
+
+        // Anyone revealing the secret slashes the whole bond, without the
+        // authority.
+        setup.process(&setup.slash_with_proof(SECRET), &[Check::success()]);
+        assert_eq!(setup.bond_state().amount, 0);
+        assert_eq!(setup.beneficiary_balance(), before + AMOUNT);
+
+        // Nothing is left to slash.
+        setup.process(
+            &setup.slash_with_proof(SECRET),
+            &[Check::err(ProgramError::Custom(
+                Namespace::Shared.code(SharedError::InvalidAmount as u32),
+            ))],
+        );
+    }
+}
+
+#[test]
+fn test_unbond_and_withdraw() {
+    for tokens in [false, true] {
+        let mut setup = Setup::bonded(tokens);
+
+        // The operator has to request to unbond first.
+        setup.process(
+            &setup.withdraw(),
+            &[Check::err(error(BondRegistryError::StillBonded))],
+        );
+
+        setup.process(&setup.request_unbond(), &[Check::success()]);
+        assert_eq!(setup.bond_state().withdrawable_at, NOW + UNBONDING_PERIOD);
+        setup.process(
+            &setup.request_unbond(),
+            &[Check::err(error(BondRegistryError::AlreadyUnbonding))],
+        );
+
+        // The bond can't be withdrawn during the unbonding period, but it
+        // can still be slashed.
+        setup.mollusk.sysvars.clock.unix_timestamp = NOW + UNBONDING_PERIOD - 1;
+        setup.process(
+            &setup.withdraw(),
+            &[Check::err(error(BondRegistryError::StillBonded))],
+        );
+        setup.process(
+            &setup.slash(&setup.authority, AMOUNT / 2),
+            &[Check::success()],
+        );
+
+        // The operator withdraws the rest and the rents.
+        setup.mollusk.sysvars.clock.unix_timestamp = NOW + UNBONDING_PERIOD;
+        let operator_lamports = setup.account(&setup.operator).lamports;
+        let rents = setup.account(&setup.bond).lamports
+            + setup
+                .tokens
+                .map_or(0, |(.., vault)| setup.account(&vault).lamports);
+        setup.process(&setup.withdraw(), &[Check::success()]);
+
+        assert_eq!(setup.account(&setup.bond).lamports, 0);
+        match setup.tokens {
+            Some((_, operator_ata, _, vault)) => {
+                assert_eq!(token_amount(setup.account(&operator_ata)), AMOUNT / 2);
+                assert_eq!(setup.account(&vault).lamports, 0);
+                assert_eq!(
+                    setup.account(&setup.operator).lamports,
+                    operator_lamports + rents
+                );
+            }
+            // The lamports of the bond include the rest of the bond.
+            None => assert_eq!(
+                setup.account(&setup.operator).lamports,
+                operator_lamports + rents
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_initialize_registry_errors() {
+    let mut setup = Setup::new(false);
+    setup.process(
+        &setup.initialize_registry(-1),
+        &[Check::err(error(BondRegistryError::InvalidUnbondingPeriod))],
+    );
+
+    // Slashed tokens can't go to a token account of another mint.
+    let mut setup = Setup::new(true);
+    let (_, _, beneficiary_ata, _) = setup.tokens.unwrap();
+    let other_mint = Pubkey::new_unique();
+    setup.accounts.retain(|(key, _)| *key != beneficiary_ata);
+    setup.accounts.push((
+        beneficiary_ata,
+        token_account(&other_mint, &setup.beneficiary, 0),
+    ));
+    setup.process(
+        &setup.initialize_registry(UNBONDING_PERIOD),
+        &[Check::err(error(BondRegistryError::BeneficiaryMismatch))],
+    );
+
+    // Bonds of a token registry need the token accounts.
+    let mut setup = Setup::new(true);
+    setup.process(
+        &setup.initialize_registry(UNBONDING_PERIOD),
+        &[Check::success()],
+    );
+    let mut bond = setup.bond(AMOUNT);
+    bond.accounts.truncate(4);
+    setup.process(&bond, &[Check::err(ProgramError::NotEnoughAccountKeys)]);
+}
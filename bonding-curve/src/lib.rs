@@ -0,0 +1,472 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::{Namespace, SharedError};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::{CreateAccount, Transfer as SystemTransfer};
+use pinocchio_token::{
+    instructions::{Burn, InitializeMint2, MintTo},
+    state::Mint,
+};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("HMmYoB8B5ZZzeTvjMxwtMtBo1f2MJnHweFSrpth6FcJc");
+
+pub const CURVE_SEED: &str = "curve";
+pub const MINT_SEED: &str = "mint";
+
+/// Errors returned by the bonding curve program.
+#[repr(u32)]
+pub enum BondingCurveError {
+    /// The price is worse than the limit set by the user.
+    SlippageExceeded,
+}
+
+impl From<BondingCurveError> for ProgramError {
+    fn from(e: BondingCurveError) -> Self {
+        Namespace::BondingCurve.error(e as u32)
+    }
+}
+
+/// Shape of a bonding curve. The price of the token number `s` (counting
+/// from 0) is:
+///
+/// * `base_price + slope * s` for [`CurveKind::Linear`],
+/// * `base_price + slope * s^2` for [`CurveKind::Quadratic`].
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum CurveKind {
+    Linear,
+    Quadratic,
+}
+
+impl TryFrom<&u8> for CurveKind {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Linear),
+            1 => Ok(Self::Quadratic),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Returns `0 + 1 + ... + (n - 1)`.
+fn sum(n: u128) -> Option<u128> {
+    if n == 0 {
+        return Some(0);
+    }
+    n.checked_mul(n - 1).map(|x| x / 2)
+}
+
+/// Returns `0^2 + 1^2 + ... + (n - 1)^2`.
+fn sum_of_squares(n: u128) -> Option<u128> {
+    if n == 0 {
+        return Some(0);
+    }
+    (n - 1)
+        .checked_mul(n)?
+        .checked_mul(2 * n - 1)
+        .map(|x| x / 6)
+}
+
+/// On-chain representation of a bonding curve.
+///
+/// The curve PDA is the mint authority of its mint and holds the lamports
+/// paid for the tokens sold. Since every sale is priced by the same curve
+/// as the buy-back, the reserve always covers selling all the tokens back.
+#[repr(C)]
+pub struct Curve {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// Price of the first token, in lamports.
+    pub base_price: u64,
+    pub slope: u64,
+    /// Number of tokens in circulation.
+    pub supply: u64,
+    /// `CurveKind` of the curve.
+    pub kind: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+assert_layout!(Curve, size = 96, align = 8, {
+    authority: 0,
+    mint: 32,
+    base_price: 64,
+    slope: 72,
+    supply: 80,
+    kind: 88,
+    bump: 89,
+});
+
+impl Curve {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Returns the price in lamports of `amount` tokens, following the
+    /// first `supply` tokens, or `None` if it overflows.
+    pub fn price(&self, supply: u64, amount: u64) -> Option<u64> {
+        let kind = CurveKind::try_from(&self.kind).ok()?;
+        let (start, end) = (supply as u128, supply as u128 + amount as u128);
+
+        let sum = match kind {
+            CurveKind::Linear => sum(end)? - sum(start)?,
+            CurveKind::Quadratic => sum_of_squares(end)? - sum_of_squares(start)?,
+        };
+        let price = (self.base_price as u128)
+            .checked_mul(amount as u128)?
+            .checked_add((self.slope as u128).checked_mul(sum)?)?;
+
+        u64::try_from(price).ok()
+    }
+
+    /// Returns the cost in lamports of buying `amount` tokens.
+    pub fn buy_cost(&self, amount: u64) -> Option<u64> {
+        self.price(self.supply, amount)
+    }
+
+    /// Returns the refund in lamports for selling `amount` tokens back.
+    pub fn sell_refund(&self, amount: u64) -> Option<u64> {
+        self.price(self.supply.checked_sub(amount)?, amount)
+    }
+}
+
+/// Bonding curve program instruction discriminators.
+#[repr(u8)]
+pub enum BondingCurveInstruction {
+    /// Creates a curve and its mint.
+    Initialize,
+    /// Buys tokens from the curve.
+    Buy,
+    /// Sells tokens back to the curve.
+    Sell,
+}
+
+impl TryFrom<&u8> for BondingCurveInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Buy),
+            2 => Ok(Self::Sell),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct InitializeInstructionData {
+    pub base_price: u64,
+    pub slope: u64,
+    /// `CurveKind` of the curve.
+    pub kind: u8,
+    pub curve_bump: u8,
+    pub mint_bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(
+        kind: CurveKind,
+        base_price: u64,
+        slope: u64,
+        curve_bump: u8,
+        mint_bump: u8,
+    ) -> Self {
+        Self {
+            base_price,
+            slope,
+            kind: kind as u8,
+            curve_bump,
+            mint_bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct TradeInstructionData {
+    pub amount: u64,
+    /// Maximum cost of a purchase, or minimum refund of a sale.
+    pub limit: u64,
+}
+
+impl TradeInstructionData {
+    pub fn new(amount: u64, limit: u64) -> Self {
+        Self { amount, limit }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = BondingCurveInstruction::try_from(instruction)?;
+
+    match instruction {
+        BondingCurveInstruction::Initialize => process_initialize(accounts, instruction_data),
+        BondingCurveInstruction::Buy => process_buy(accounts, instruction_data),
+        BondingCurveInstruction::Sell => process_sell(accounts, instruction_data),
+    }
+}
+
+/// Deserializes trade instruction data.
+fn trade_instruction_data(instruction_data: &[u8]) -> Result<TradeInstructionData, ProgramError> {
+    let instruction_data = TradeInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.amount == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+    Ok(instruction_data)
+}
+
+/// Checks that `curve` is owned by the program and that `mint` is its mint.
+fn check_curve(curve: &AccountInfo, mint: &AccountInfo) -> ProgramResult {
+    require_owned_by(curve, &ID)?;
+
+    let data = curve.try_borrow_data()?;
+    let data: &Curve = unsafe { &*data.as_ptr().cast() };
+
+    if !keys_eq(&data.mint, mint.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Creates a curve of `authority` and a mint with no decimals, controlled by
+/// the curve PDA.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, curve, mint, _system_program, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    CurveKind::try_from(&instruction_data.kind)?;
+
+    // Check the seeds of `curve` and `mint`.
+    let curve_pda = create_program_address(
+        &[
+            CURVE_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.curve_bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(curve.key(), &curve_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let mint_pda = create_program_address(
+        &[
+            MINT_SEED.as_bytes(),
+            curve.key(),
+            &[instruction_data.mint_bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(mint.key(), &mint_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the curve PDA.
+    let bump = [instruction_data.curve_bump];
+    let seeds = [
+        Seed::from(CURVE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: curve,
+        lamports: Rent::get()?.minimum_balance(Curve::LEN),
+        space: Curve::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Create the mint PDA, owned by the token program.
+    let bump = [instruction_data.mint_bump];
+    let seeds = [
+        Seed::from(MINT_SEED.as_bytes()),
+        Seed::from(curve.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: mint,
+        lamports: Rent::get()?.minimum_balance(Mint::LEN),
+        space: Mint::LEN as u64,
+        owner: &pinocchio_token::ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+    InitializeMint2 {
+        mint,
+        decimals: 0,
+        mint_authority: curve.key(),
+        freeze_authority: None,
+    }
+    .invoke()?;
+
+    // Deserialize the curve PDA.
+    let mut data = curve.try_borrow_mut_data()?;
+    let data: &mut Curve = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the curve.
+    data.authority = *authority.key();
+    data.mint = *mint.key();
+    data.base_price = instruction_data.base_price;
+    data.slope = instruction_data.slope;
+    data.kind = instruction_data.kind;
+    data.bump = instruction_data.curve_bump;
+
+    info!(
+        "Initialized curve, base price {}, slope {}",
+        instruction_data.base_price, instruction_data.slope
+    );
+
+    Ok(())
+}
+
+/// Buys `amount` tokens for at most `limit` lamports. The lamports go to the
+/// reserve held by the curve PDA and the tokens are minted to `buyer_ata`.
+pub fn process_buy(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [buyer, buyer_ata, curve, mint, _system_program, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(buyer)?;
+
+    check_curve(curve, mint)?;
+
+    let instruction_data = trade_instruction_data(instruction_data)?;
+
+    // Price the purchase.
+    let (cost, authority, bump) = {
+        let data = curve.try_borrow_data()?;
+        let data: &Curve = unsafe { &*data.as_ptr().cast() };
+
+        let cost = data
+            .buy_cost(instruction_data.amount)
+            .ok_or(SharedError::Overflow)?;
+        (cost, data.authority, data.bump)
+    };
+    if cost > instruction_data.limit {
+        return Err(BondingCurveError::SlippageExceeded.into());
+    }
+
+    // Pay the reserve and mint the tokens.
+    SystemTransfer {
+        from: buyer,
+        to: curve,
+        lamports: cost,
+    }
+    .invoke()?;
+
+    let bump = [bump];
+    let seeds = [
+        Seed::from(CURVE_SEED.as_bytes()),
+        Seed::from(&authority),
+        Seed::from(&bump),
+    ];
+    MintTo {
+        mint,
+        account: buyer_ata,
+        mint_authority: curve,
+        amount: instruction_data.amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the curve PDA.
+    let mut data = curve.try_borrow_mut_data()?;
+    let data: &mut Curve = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    data.supply += instruction_data.amount;
+
+    info!(
+        "Bought {} tokens for {} lamports",
+        instruction_data.amount, cost
+    );
+
+    Ok(())
+}
+
+/// Sells `amount` tokens from `seller_ata` back to the curve for at least
+/// `limit` lamports. The tokens are burned and the refund is paid from the
+/// reserve.
+pub fn process_sell(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [seller, seller_ata, curve, mint, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(seller)?;
+
+    check_curve(curve, mint)?;
+
+    let instruction_data = trade_instruction_data(instruction_data)?;
+
+    // The token program checks that the seller owns the tokens.
+    Burn {
+        account: seller_ata,
+        mint,
+        authority: seller,
+        amount: instruction_data.amount,
+    }
+    .invoke()?;
+
+    // Deserialize the curve PDA.
+    let mut data = curve.try_borrow_mut_data()?;
+    let data: &mut Curve = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    let refund = data
+        .sell_refund(instruction_data.amount)
+        .ok_or(SharedError::Overflow)?;
+    if refund < instruction_data.limit {
+        return Err(BondingCurveError::SlippageExceeded.into());
+    }
+
+    data.supply -= instruction_data.amount;
+
+    // The curve PDA is owned by the program, so the refund can be paid
+    // without a CPI.
+    *curve.try_borrow_mut_lamports()? -= refund;
+    *seller.try_borrow_mut_lamports()? += refund;
+
+    info!(
+        "Sold {} tokens for {} lamports",
+        instruction_data.amount, refund
+    );
+
+    Ok(())
+}
@@ -0,0 +1,347 @@
+//! Attacks on the bonding curve program, each rejected with its own error.
+
+use std::mem;
+
+use bonding_curve::{
+    BondingCurveInstruction, Curve, CurveKind, InitializeInstructionData, TradeInstructionData,
+    CURVE_SEED, MINT_SEED,
+};
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    error::TokenError,
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(bonding_curve::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+const BASE_PRICE: u64 = 1_000;
+const SLOPE: u64 = 100;
+const SUPPLY: u64 = 10;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: BondingCurveInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<BondingCurveInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn mint_account(mollusk: &Mollusk, authority: &Pubkey, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::Some(*authority),
+            supply,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns the curve PDA of `authority`, its mint PDA and the curve account,
+/// with `supply` tokens sold at `base_price`.
+fn curve_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    base_price: u64,
+    supply: u64,
+) -> (Pubkey, Pubkey, Account) {
+    let (curve, bump) =
+        Pubkey::find_program_address(&[CURVE_SEED.as_bytes(), authority.as_ref()], &ID);
+    let (mint, _) = Pubkey::find_program_address(&[MINT_SEED.as_bytes(), curve.as_ref()], &ID);
+
+    let mut data = vec![0; Curve::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64..72].copy_from_slice(&base_price.to_le_bytes());
+    data[72..80].copy_from_slice(&SLOPE.to_le_bytes());
+    data[80..88].copy_from_slice(&supply.to_le_bytes());
+    data[88] = CurveKind::Linear as u8;
+    data[89] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Curve::LEN),
+        Curve::LEN,
+        &ID,
+    );
+    account.data = data;
+    (curve, mint, account)
+}
+
+/// Accounts of a linear curve of which a holder bought [`SUPPLY`] tokens, and
+/// of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    curve: Pubkey,
+    mint: Pubkey,
+    holder_ata: Pubkey,
+    attacker: Pubkey,
+    attacker_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/bonding_curve");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+
+        let authority = Pubkey::new_unique();
+        let (curve, mint, mut curve_account) =
+            curve_account(&mollusk, &authority, BASE_PRICE, SUPPLY);
+        // Price of the tokens sold, which covers selling them back.
+        curve_account.lamports += SUPPLY * BASE_PRICE + SLOPE * SUPPLY * (SUPPLY - 1) / 2;
+        let holder = Pubkey::new_unique();
+        let holder_ata = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let attacker_ata = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (curve, curve_account),
+            (mint, mint_account(&mollusk, &curve, SUPPLY)),
+            (holder, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (holder_ata, token_account(&mollusk, &mint, &holder, SUPPLY)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (attacker_ata, token_account(&mollusk, &mint, &attacker, 0)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            curve,
+            mint,
+            holder_ata,
+            attacker,
+            attacker_ata,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    /// Returns an instruction initializing the curve PDA of `authority` for
+    /// the attacker.
+    fn instruction_initialize(&self, authority: &Pubkey, mint: &Pubkey) -> Instruction {
+        let (curve, curve_bump) =
+            Pubkey::find_program_address(&[CURVE_SEED.as_bytes(), authority.as_ref()], &ID);
+        let (_, mint_bump) =
+            Pubkey::find_program_address(&[MINT_SEED.as_bytes(), curve.as_ref()], &ID);
+        let data = InitializeInstructionData::new(CurveKind::Linear, 0, 0, curve_bump, mint_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new(curve, false),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(BondingCurveInstruction::Initialize, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Returns an instruction buying one token for the attacker.
+    fn instruction_buy(&self, curve: &Pubkey) -> Instruction {
+        let data = TradeInstructionData::new(1, LAMPORTS_PER_SOL);
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new(self.attacker_ata, false),
+            AccountMeta::new(*curve, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(BondingCurveInstruction::Buy, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Returns an instruction selling all the tokens of `seller_ata` for the
+    /// attacker.
+    fn instruction_sell(&self, seller_ata: &Pubkey, curve: &Pubkey) -> Instruction {
+        let data = TradeInstructionData::new(SUPPLY, 0);
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new(*seller_ata, false),
+            AccountMeta::new(*curve, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(BondingCurveInstruction::Sell, &data),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_buyer_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_buy(&setup.curve);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker sells the tokens of the holder to the reserve.
+#[test]
+fn test_sell_tokens_of_other_holder() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_sell(&setup.holder_ata, &setup.curve),
+        ProgramError::Custom(TokenError::OwnerMismatch as u32),
+    );
+}
+
+/// A curve with the layout of the program, but owned by another one, e.g.
+/// with a price of zero.
+#[test]
+fn test_spoofed_curve() {
+    let setup = Setup::new();
+    let curve = setup.curve;
+    let mut curve_account = setup.account(&curve);
+    curve_account.owner = Pubkey::new_unique();
+    let setup = setup.with(curve, curve_account);
+    setup.expect_err(
+        &setup.instruction_buy(&setup.curve),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The tokens of the curve are bought through a curve of the attacker with
+/// a price of zero.
+#[test]
+fn test_buy_through_other_curve() {
+    let setup = Setup::new();
+    let (other_curve, _, other_curve_account) =
+        curve_account(&setup.mollusk, &setup.attacker, 0, 0);
+    let setup = setup.with(other_curve, other_curve_account);
+    setup.expect_err(
+        &setup.instruction_buy(&other_curve),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The tokens of the curve are sold to a curve of the attacker with a higher
+/// price, draining its reserve.
+#[test]
+fn test_sell_through_other_curve() {
+    let setup = Setup::new();
+    let (other_curve, _, mut other_curve_account) =
+        curve_account(&setup.mollusk, &setup.attacker, 10 * BASE_PRICE, SUPPLY);
+    other_curve_account.lamports += LAMPORTS_PER_SOL;
+    let attacker_ata = setup.attacker_ata;
+    let attacker_ata_account = token_account(&setup.mollusk, &setup.mint, &setup.attacker, SUPPLY);
+    let setup = setup
+        .with(other_curve, other_curve_account)
+        .with(attacker_ata, attacker_ata_account);
+    setup.expect_err(
+        &setup.instruction_sell(&attacker_ata, &other_curve),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker creates a curve at the PDA of another authority.
+#[test]
+fn test_wrong_curve_seeds() {
+    let setup = Setup::new();
+    let authority = Pubkey::new_unique();
+    let (curve, _) =
+        Pubkey::find_program_address(&[CURVE_SEED.as_bytes(), authority.as_ref()], &ID);
+    let (mint, _) = Pubkey::find_program_address(&[MINT_SEED.as_bytes(), curve.as_ref()], &ID);
+    let instruction = setup.instruction_initialize(&authority, &mint);
+    let setup = setup
+        .with(curve, Account::default())
+        .with(mint, Account::default());
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker creates a curve with a mint other than its PDA, e.g. one
+/// with a supply minted by the attacker before.
+#[test]
+fn test_wrong_mint_seeds() {
+    let setup = Setup::new();
+    let (curve, _) =
+        Pubkey::find_program_address(&[CURVE_SEED.as_bytes(), setup.attacker.as_ref()], &ID);
+    let mint = Pubkey::new_unique();
+    let instruction = setup.instruction_initialize(&setup.attacker, &mint);
+    let setup = setup
+        .with(curve, Account::default())
+        .with(mint, Account::default());
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
@@ -0,0 +1,383 @@
+use std::mem;
+
+use bonding_curve::{
+    BondingCurveError, BondingCurveInstruction, Curve, CurveKind, InitializeInstructionData,
+    TradeInstructionData, CURVE_SEED, MINT_SEED,
+};
+use common::encoding::Encoding;
+use errors::{Namespace, SharedError};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(bonding_curve::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offset of the supply in a curve account.
+const SUPPLY_OFFSET: usize = 80;
+
+const BASE_PRICE: u64 = 1_000;
+const SLOPE: u64 = 100;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: BondingCurveInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<BondingCurveInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/bonding_curve");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk
+}
+
+fn curve(kind: CurveKind, supply: u64) -> Curve {
+    Curve {
+        authority: [0; 32],
+        mint: [0; 32],
+        base_price: BASE_PRICE,
+        slope: SLOPE,
+        supply,
+        kind: kind as u8,
+        bump: 0,
+        _padding: [0; 6],
+    }
+}
+
+fn mint_account(mollusk: &Mollusk, authority: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::Some(*authority),
+            supply: 0,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount: 0,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn curve_account(mollusk: &Mollusk, authority: &Pubkey, mint: &Pubkey, bump: u8) -> Account {
+    let mut data = vec![0; Curve::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64..72].copy_from_slice(&BASE_PRICE.to_le_bytes());
+    data[72..80].copy_from_slice(&SLOPE.to_le_bytes());
+    data[88] = CurveKind::Linear as u8;
+    data[89] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Curve::LEN),
+        Curve::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// A linear curve with nothing sold yet and a buyer.
+struct Setup {
+    mollusk: Mollusk,
+    curve: Pubkey,
+    mint: Pubkey,
+    buyer: Pubkey,
+    buyer_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let authority = Pubkey::new_unique();
+        let (curve, curve_bump) =
+            Pubkey::find_program_address(&[CURVE_SEED.as_bytes(), authority.as_ref()], &ID);
+        let (mint, _) = Pubkey::find_program_address(&[MINT_SEED.as_bytes(), curve.as_ref()], &ID);
+        let buyer = Pubkey::new_unique();
+        let buyer_ata = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                curve,
+                curve_account(&mollusk, &authority, &mint, curve_bump),
+            ),
+            (mint, mint_account(&mollusk, &curve)),
+            (buyer, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (buyer_ata, token_account(&mollusk, &mint, &buyer)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            curve,
+            mint,
+            buyer,
+            buyer_ata,
+            accounts,
+        }
+    }
+
+    fn instruction_buy(&self, amount: u64, max_cost: u64) -> Instruction {
+        let data = TradeInstructionData::new(amount, max_cost);
+        let ix_accounts = vec![
+            AccountMeta::new(self.buyer, true),
+            AccountMeta::new(self.buyer_ata, false),
+            AccountMeta::new(self.curve, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(BondingCurveInstruction::Buy, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_sell(&self, amount: u64, min_refund: u64) -> Instruction {
+        let data = TradeInstructionData::new(amount, min_refund);
+        let ix_accounts = vec![
+            AccountMeta::new(self.buyer, true),
+            AccountMeta::new(self.buyer_ata, false),
+            AccountMeta::new(self.curve, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(BondingCurveInstruction::Sell, &data),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_bonding_curve_price() {
+    // 1000 + 1100 + 1200
+    assert_eq!(curve(CurveKind::Linear, 0).buy_cost(3), Some(3_300));
+    // 1000 + 100 * 2^2 + 1000 + 100 * 3^2
+    assert_eq!(curve(CurveKind::Quadratic, 2).buy_cost(2), Some(3_300));
+
+    // Buying in one go costs the same as buying one by one.
+    for kind in [CurveKind::Linear, CurveKind::Quadratic] {
+        let total = curve(kind, 5).buy_cost(10).unwrap();
+        let one_by_one = (5..15)
+            .map(|supply| curve(kind, supply).buy_cost(1).unwrap())
+            .sum::<u64>();
+        assert_eq!(total, one_by_one);
+
+        // Selling back refunds what was paid.
+        assert_eq!(curve(kind, 15).sell_refund(10), Some(total));
+    }
+
+    assert_eq!(curve(CurveKind::Linear, 3).sell_refund(4), None);
+    assert_eq!(curve(CurveKind::Linear, 0).buy_cost(u64::MAX), None);
+    assert_eq!(curve(CurveKind::Quadratic, u64::MAX / 2).buy_cost(1), None);
+}
+
+#[test]
+fn test_bonding_curve_initialize() {
+    let mollusk = mollusk();
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let authority = Pubkey::new_unique();
+    let (curve, curve_bump) =
+        Pubkey::find_program_address(&[CURVE_SEED.as_bytes(), authority.as_ref()], &ID);
+    let (mint, mint_bump) =
+        Pubkey::find_program_address(&[MINT_SEED.as_bytes(), curve.as_ref()], &ID);
+
+    let data =
+        InitializeInstructionData::new(CurveKind::Linear, BASE_PRICE, SLOPE, curve_bump, mint_bump);
+    let ix_accounts = vec![
+        AccountMeta::new(authority, true),
+        AccountMeta::new(curve, false),
+        AccountMeta::new(mint, false),
+        AccountMeta::new_readonly(system_program, false),
+        AccountMeta::new_readonly(TOKEN_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(BondingCurveInstruction::Initialize, &data),
+        ix_accounts,
+    );
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program),
+            ),
+            (curve, Account::default()),
+            (mint, Account::default()),
+            (system_program, system_account),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ],
+        &[
+            Check::success(),
+            Check::account(&curve)
+                .owner(&ID)
+                .data(&curve_account(&mollusk, &authority, &mint, curve_bump).data)
+                .build(),
+            Check::account(&mint)
+                .owner(&TOKEN_ID)
+                .data(&mint_account(&mollusk, &curve).data)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_bonding_curve_buy_and_sell() {
+    let setup = Setup::new();
+    let curve_rent = setup.mollusk.sysvars.rent.minimum_balance(Curve::LEN);
+
+    // 10 * 1000 + 100 * (0 + 1 + ... + 9)
+    let cost = 14_500;
+    // 4 * 1000 + 100 * (6 + 7 + 8 + 9)
+    let refund = 7_000;
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_buy(10, cost),
+                &[
+                    Check::success(),
+                    Check::account(&setup.buyer)
+                        .lamports(LAMPORTS_PER_SOL - cost)
+                        .build(),
+                    Check::account(&setup.buyer_ata)
+                        .data_slice(AMOUNT_OFFSET, &10u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.curve)
+                        .lamports(curve_rent + cost)
+                        .data_slice(SUPPLY_OFFSET, &10u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_sell(4, refund),
+                &[
+                    Check::success(),
+                    Check::account(&setup.buyer)
+                        .lamports(LAMPORTS_PER_SOL - cost + refund)
+                        .build(),
+                    Check::account(&setup.buyer_ata)
+                        .data_slice(AMOUNT_OFFSET, &6u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.curve)
+                        .lamports(curve_rent + cost - refund)
+                        .data_slice(SUPPLY_OFFSET, &6u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            // Selling everything back empties the reserve.
+            (
+                &setup.instruction_sell(6, cost - refund),
+                &[
+                    Check::success(),
+                    Check::account(&setup.buyer)
+                        .lamports(LAMPORTS_PER_SOL)
+                        .build(),
+                    Check::account(&setup.curve).lamports(curve_rent).build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_bonding_curve_slippage() {
+    let setup = Setup::new();
+
+    let slippage_exceeded = || {
+        [Check::err(ProgramError::Custom(
+            Namespace::BondingCurve.code(BondingCurveError::SlippageExceeded as u32),
+        ))]
+    };
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_buy(10, 14_499),
+        &setup.accounts,
+        &slippage_exceeded(),
+    );
+    let accounts = setup
+        .mollusk
+        .process_and_validate_instruction(
+            &setup.instruction_buy(10, 14_500),
+            &setup.accounts,
+            &[Check::success()],
+        )
+        .resulting_accounts;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_sell(4, 7_001),
+        &accounts,
+        &slippage_exceeded(),
+    );
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_sell(0, 0),
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Shared.code(SharedError::InvalidAmount as u32),
+        ))],
+    );
+}
@@ -0,0 +1,259 @@
+//! Checkers board and move validation, following the rules of English
+//! draughts.
+//!
+//! Only the 32 dark squares are playable, so the board stores one byte per
+//! dark square. Squares are numbered row by row from the black side:
+//!
+//! ```text
+//!     0   1   2   3     row 0 (black)
+//!   4   5   6   7       row 1
+//!     8   9  10  11     row 2
+//!  12  13  14  15       row 3
+//!    16  17  18  19     row 4
+//!  20  21  22  23       row 5
+//!    24  25  26  27     row 6
+//!  28  29  30  31       row 7 (white)
+//! ```
+
+use crate::CheckersError;
+use common::assert_layout;
+
+/// Number of playable squares.
+pub const SQUARES: usize = 32;
+
+pub const EMPTY: u8 = 0;
+pub const BLACK_MAN: u8 = 1;
+pub const WHITE_MAN: u8 = 2;
+/// Flag set on crowned pieces.
+pub const KING: u8 = 4;
+pub const BLACK_KING: u8 = BLACK_MAN | KING;
+pub const WHITE_KING: u8 = WHITE_MAN | KING;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// Starts on rows 0-2, moves first and towards row 7.
+    Black,
+    /// Starts on rows 5-7 and moves towards row 0.
+    White,
+}
+
+impl Color {
+    pub fn opponent(self) -> Self {
+        match self {
+            Self::Black => Self::White,
+            Self::White => Self::Black,
+        }
+    }
+
+    /// Row on which the men of this color are crowned.
+    fn crowning_row(self) -> i8 {
+        match self {
+            Self::Black => 7,
+            Self::White => 0,
+        }
+    }
+}
+
+/// Returns the color of `piece`, `None` for an empty square.
+pub fn color_of(piece: u8) -> Option<Color> {
+    match piece & !KING {
+        BLACK_MAN => Some(Color::Black),
+        WHITE_MAN => Some(Color::White),
+        _ => None,
+    }
+}
+
+/// Returns the row and column of a playable square.
+fn coords(square: u8) -> (i8, i8) {
+    let row = (square / 4) as i8;
+    let col = 2 * (square % 4) as i8 + if row % 2 == 0 { 1 } else { 0 };
+    (row, col)
+}
+
+/// Returns the playable square at the given row and column, `None` if it's
+/// outside of the board or not a dark square.
+fn square_at(row: i8, col: i8) -> Option<u8> {
+    if !(0..8).contains(&row) || !(0..8).contains(&col) || (row + col) % 2 == 0 {
+        return None;
+    }
+    Some((row * 4 + col / 2) as u8)
+}
+
+/// Returns the row directions in which `piece` can move.
+fn directions(piece: u8, color: Color) -> &'static [i8] {
+    if piece & KING != 0 {
+        &[1, -1]
+    } else if color == Color::Black {
+        &[1]
+    } else {
+        &[-1]
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Board {
+    pub squares: [u8; SQUARES],
+}
+
+assert_layout!(Board, size = 32, align = 1, { squares: 0 });
+
+impl Board {
+    pub const fn new(squares: [u8; SQUARES]) -> Self {
+        Self { squares }
+    }
+
+    /// Returns the board at the start of a game, with 12 men per side.
+    pub const fn initial() -> Self {
+        let mut squares = [EMPTY; SQUARES];
+        let mut i = 0;
+        while i < 12 {
+            squares[i] = BLACK_MAN;
+            squares[SQUARES - 1 - i] = WHITE_MAN;
+            i += 1;
+        }
+        Self { squares }
+    }
+
+    /// Returns the square jumped over and the landing square of a jump of
+    /// the piece on `square` in the given direction, if the jump is legal.
+    fn jump(&self, square: u8, piece: u8, dr: i8, dc: i8) -> Option<(u8, u8)> {
+        let color = color_of(piece)?;
+        let (row, col) = coords(square);
+        let over = square_at(row + dr, col + dc)?;
+        let to = square_at(row + 2 * dr, col + 2 * dc)?;
+        if color_of(self.squares[over as usize]) == Some(color.opponent())
+            && self.squares[to as usize] == EMPTY
+        {
+            Some((over, to))
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether `piece`, standing on `square`, can jump.
+    fn can_jump_from(&self, square: u8, piece: u8) -> bool {
+        let Some(color) = color_of(piece) else {
+            return false;
+        };
+        directions(piece, color).iter().any(|&dr| {
+            [1, -1]
+                .iter()
+                .any(|&dc| self.jump(square, piece, dr, dc).is_some())
+        })
+    }
+
+    /// Returns whether `piece`, standing on `square`, can make a simple move.
+    fn can_step_from(&self, square: u8, piece: u8) -> bool {
+        let Some(color) = color_of(piece) else {
+            return false;
+        };
+        let (row, col) = coords(square);
+        directions(piece, color).iter().any(|&dr| {
+            [1, -1].iter().any(|&dc| {
+                square_at(row + dr, col + dc).is_some_and(|to| self.squares[to as usize] == EMPTY)
+            })
+        })
+    }
+
+    /// Returns whether any piece of `color` can jump. Captures are mandatory.
+    pub fn can_capture(&self, color: Color) -> bool {
+        (0..SQUARES as u8).any(|square| {
+            let piece = self.squares[square as usize];
+            color_of(piece) == Some(color) && self.can_jump_from(square, piece)
+        })
+    }
+
+    /// Returns whether `color` has any legal move. A player without legal
+    /// moves loses.
+    pub fn has_legal_move(&self, color: Color) -> bool {
+        (0..SQUARES as u8).any(|square| {
+            let piece = self.squares[square as usize];
+            color_of(piece) == Some(color)
+                && (self.can_step_from(square, piece) || self.can_jump_from(square, piece))
+        })
+    }
+
+    /// Validates and plays a move of `color`, given as the path of squares
+    /// visited by the moving piece.
+    ///
+    /// A path of two adjacent squares is a simple move. Otherwise, every step
+    /// has to be a jump over an opponent's piece, which is removed right
+    /// away. When a capture is available, the move has to capture and has
+    /// to continue jumping as long as possible, unless the piece gets crowned,
+    /// which ends the move.
+    ///
+    /// On error the board is left partially updated, which is fine as the
+    /// transaction is aborted.
+    pub fn apply_move(&mut self, color: Color, path: &[u8]) -> Result<(), CheckersError> {
+        let [from, rest @ ..] = path else {
+            return Err(CheckersError::InvalidMove);
+        };
+        if rest.is_empty() || path.iter().any(|&square| square as usize >= SQUARES) {
+            return Err(CheckersError::InvalidMove);
+        }
+
+        let mut piece = self.squares[*from as usize];
+        if color_of(piece) != Some(color) {
+            return Err(CheckersError::InvalidMove);
+        }
+
+        let (from_row, from_col) = coords(*from);
+        let (to_row, to_col) = coords(rest[0]);
+        let (dr, dc) = (to_row - from_row, to_col - from_col);
+
+        // Simple move.
+        if dr.abs() == 1 {
+            if rest.len() != 1 || dc.abs() != 1 || !directions(piece, color).contains(&dr) {
+                return Err(CheckersError::InvalidMove);
+            }
+            if self.squares[rest[0] as usize] != EMPTY {
+                return Err(CheckersError::InvalidMove);
+            }
+            if self.can_capture(color) {
+                return Err(CheckersError::MustCapture);
+            }
+            if to_row == color.crowning_row() {
+                piece |= KING;
+            }
+            self.squares[*from as usize] = EMPTY;
+            self.squares[rest[0] as usize] = piece;
+            return Ok(());
+        }
+
+        // Sequence of jumps. The piece is lifted from its square, so a king
+        // can jump through the square it started from.
+        self.squares[*from as usize] = EMPTY;
+        let mut square = *from;
+        for (i, &next) in rest.iter().enumerate() {
+            let (row, col) = coords(square);
+            let (next_row, next_col) = coords(next);
+            let (dr, dc) = (next_row - row, next_col - col);
+            if dr.abs() != 2 || dc.abs() != 2 || !directions(piece, color).contains(&(dr / 2)) {
+                return Err(CheckersError::InvalidMove);
+            }
+            let Some((over, to)) = self.jump(square, piece, dr / 2, dc / 2) else {
+                return Err(CheckersError::InvalidMove);
+            };
+            self.squares[over as usize] = EMPTY;
+            square = to;
+
+            if piece & KING == 0 && next_row == color.crowning_row() {
+                // Crowning ends the move.
+                if i != rest.len() - 1 {
+                    return Err(CheckersError::InvalidMove);
+                }
+                self.squares[square as usize] = piece | KING;
+                return Ok(());
+            }
+        }
+        self.squares[square as usize] = piece;
+
+        if self.can_jump_from(square, piece) {
+            return Err(CheckersError::IncompleteMove);
+        }
+
+        Ok(())
+    }
+}
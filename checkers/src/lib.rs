@@ -0,0 +1,492 @@
+//! Two-player checkers with chess clocks.
+//!
+//! The whole game, including the board, lives in a single zero-copy account.
+//! Every move is validated on-chain, see [`board::Board::apply_move`].
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::{AccountInfo, RefMut},
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+use board::{Board, Color};
+
+pub mod board;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("HSbkZrSMpmDiSEW1pCAaUnjttjNYoymd3HJtCTZR7WHf");
+
+pub const GAME_SEED: &str = "game";
+
+/// Maximum number of squares in the path of a move, i.e. up to 9 jumps.
+pub const MAX_PATH_LEN: usize = 10;
+
+/// Errors returned by the checkers program.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckersError {
+    /// The time control is not positive.
+    InvalidTimeControl,
+    /// The game is not in progress.
+    GameNotActive,
+    /// It's the other player's turn.
+    NotYourTurn,
+    /// The move doesn't follow the rules.
+    InvalidMove,
+    /// A capture is available, so the move has to be a jump.
+    MustCapture,
+    /// The moved piece can keep jumping, so the move has to continue.
+    IncompleteMove,
+    /// The clock of the player on turn has run out.
+    TimeExpired,
+    /// The clock of the player on turn has not run out yet.
+    TimeNotExpired,
+    /// The opponent hasn't offered a draw.
+    NoDrawOffer,
+}
+
+impl From<CheckersError> for ProgramError {
+    fn from(e: CheckersError) -> Self {
+        Namespace::Checkers.error(e as u32)
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    /// Waiting for white to join.
+    Pending,
+    Active,
+    BlackWon,
+    WhiteWon,
+    Draw,
+}
+
+impl GameStatus {
+    fn won_by(color: Color) -> Self {
+        match color {
+            Color::Black => Self::BlackWon,
+            Color::White => Self::WhiteWon,
+        }
+    }
+}
+
+/// No draw offer is pending.
+pub const NO_DRAW_OFFER: u8 = u8::MAX;
+
+/// On-chain representation of a game.
+#[repr(C)]
+pub struct Game {
+    pub black: Pubkey,
+    pub white: Pubkey,
+    pub id: u64,
+    /// Remaining time of black, in seconds, as of the start of the turn.
+    pub black_time: i64,
+    /// Remaining time of white, in seconds, as of the start of the turn.
+    pub white_time: i64,
+    /// Unix timestamp at which the current turn started.
+    pub turn_started_at: i64,
+    pub board: Board,
+    /// [`Color`] of the player on turn.
+    pub turn: u8,
+    /// [`GameStatus`] of the game.
+    pub status: u8,
+    /// [`Color`] of the player who offered a draw, or [`NO_DRAW_OFFER`].
+    pub draw_offer: u8,
+    pub bump: u8,
+    pub _padding: [u8; 4],
+}
+
+assert_layout!(Game, size = 136, align = 8, {
+    black: 0,
+    white: 32,
+    id: 64,
+    black_time: 72,
+    white_time: 80,
+    turn_started_at: 88,
+    board: 96,
+    turn: 128,
+    status: 129,
+    draw_offer: 130,
+    bump: 131,
+});
+
+impl Game {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Loads a game owned by the program.
+    fn load_mut(account: &AccountInfo) -> Result<RefMut<'_, Self>, ProgramError> {
+        require_owned_by(account, &ID)?;
+        if account.data_len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(RefMut::map(account.try_borrow_mut_data()?, |data| unsafe {
+            &mut *data.as_mut_ptr().cast()
+        }))
+    }
+
+    /// Returns the color played by `player`.
+    fn color_of(&self, player: &Pubkey) -> Result<Color, ProgramError> {
+        if player == &self.black {
+            Ok(Color::Black)
+        } else if player == &self.white {
+            Ok(Color::White)
+        } else {
+            Err(ProgramError::IllegalOwner)
+        }
+    }
+
+    fn turn(&self) -> Color {
+        if self.turn == Color::Black as u8 {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    fn time_mut(&mut self, color: Color) -> &mut i64 {
+        match color {
+            Color::Black => &mut self.black_time,
+            Color::White => &mut self.white_time,
+        }
+    }
+
+    fn check_active(&self) -> ProgramResult {
+        if self.status != GameStatus::Active as u8 {
+            return Err(CheckersError::GameNotActive.into());
+        }
+        Ok(())
+    }
+
+    /// Returns the time left to the player on turn at `now`.
+    fn time_left(&self, now: i64) -> i64 {
+        let time = match self.turn() {
+            Color::Black => self.black_time,
+            Color::White => self.white_time,
+        };
+        time.saturating_sub(now.saturating_sub(self.turn_started_at))
+    }
+}
+
+/// Checkers program instruction discriminators.
+#[repr(u8)]
+pub enum CheckersInstruction {
+    /// Creates a game, played by the signer as black.
+    Create,
+    /// Joins a game as white and starts the clocks.
+    Join,
+    /// Moves a piece.
+    Move,
+    /// Resigns the game.
+    Resign,
+    /// Offers a draw to the opponent.
+    OfferDraw,
+    /// Accepts the draw offered by the opponent.
+    AcceptDraw,
+    /// Wins the game when the clock of the opponent ran out.
+    ClaimTimeout,
+}
+
+impl TryFrom<&u8> for CheckersInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Create),
+            1 => Ok(Self::Join),
+            2 => Ok(Self::Move),
+            3 => Ok(Self::Resign),
+            4 => Ok(Self::OfferDraw),
+            5 => Ok(Self::AcceptDraw),
+            6 => Ok(Self::ClaimTimeout),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct CreateInstructionData {
+    pub white: Pubkey,
+    pub id: u64,
+    /// Time of each player for the whole game, in seconds.
+    pub time_control: i64,
+    pub bump: u8,
+}
+
+impl CreateInstructionData {
+    pub fn new(white: Pubkey, id: u64, time_control: i64, bump: u8) -> Self {
+        Self {
+            white,
+            id,
+            time_control,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct MoveInstructionData {
+    /// Number of squares in `path`.
+    pub len: u8,
+    /// Squares visited by the moving piece, starting with its square.
+    pub path: [u8; MAX_PATH_LEN],
+}
+
+impl MoveInstructionData {
+    pub fn new(path: &[u8]) -> Self {
+        let mut data = Self {
+            len: path.len() as u8,
+            path: [0; MAX_PATH_LEN],
+        };
+        data.path[..path.len()].copy_from_slice(path);
+        data
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = CheckersInstruction::try_from(instruction)?;
+
+    match instruction {
+        CheckersInstruction::Create => process_create(accounts, instruction_data),
+        CheckersInstruction::Join => process_join(accounts),
+        CheckersInstruction::Move => process_move(accounts, instruction_data),
+        CheckersInstruction::Resign => process_resign(accounts),
+        CheckersInstruction::OfferDraw => process_offer_draw(accounts),
+        CheckersInstruction::AcceptDraw => process_accept_draw(accounts),
+        CheckersInstruction::ClaimTimeout => process_claim_timeout(accounts),
+    }
+}
+
+/// Returns the signing player and the game.
+fn player_and_game(accounts: &[AccountInfo]) -> Result<(&AccountInfo, &AccountInfo), ProgramError> {
+    let [player, game] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(player)?;
+
+    Ok((player, game))
+}
+
+/// Creates a game between the signer, playing black, and `white`.
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [black, game, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(black)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CreateInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.time_control <= 0 {
+        return Err(CheckersError::InvalidTimeControl.into());
+    }
+
+    // Check the seeds of `game`.
+    let id = instruction_data.id.to_le_bytes();
+    let game_pda = create_program_address(
+        &[
+            GAME_SEED.as_bytes(),
+            black.key(),
+            &id,
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(game.key(), &game_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the game PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(GAME_SEED.as_bytes()),
+        Seed::from(black.key()),
+        Seed::from(&id),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: black,
+        to: game,
+        lamports: Rent::get()?.minimum_balance(Game::LEN),
+        space: Game::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Initialize the game.
+    let mut data = Game::load_mut(game)?;
+    data.black = *black.key();
+    data.white = instruction_data.white;
+    data.id = instruction_data.id;
+    data.black_time = instruction_data.time_control;
+    data.white_time = instruction_data.time_control;
+    data.board = Board::initial();
+    data.turn = Color::Black as u8;
+    data.status = GameStatus::Pending as u8;
+    data.draw_offer = NO_DRAW_OFFER;
+    data.bump = instruction_data.bump;
+
+    info!("Created game {}", instruction_data.id);
+
+    Ok(())
+}
+
+/// Joins the game as white. Black moves first and its clock starts now.
+pub fn process_join(accounts: &[AccountInfo]) -> ProgramResult {
+    let (white, game) = player_and_game(accounts)?;
+    let mut game = Game::load_mut(game)?;
+
+    if !keys_eq(&game.white, white.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if game.status != GameStatus::Pending as u8 {
+        return Err(CheckersError::GameNotActive.into());
+    }
+
+    game.status = GameStatus::Active as u8;
+    game.turn_started_at = Clock::get()?.unix_timestamp;
+
+    info!("Game {} started", game.id);
+
+    Ok(())
+}
+
+/// Plays a move of the player on turn, given as the path of the moving
+/// piece. The time spent is taken from the player's clock. The player wins
+/// when the opponent is left without a legal move.
+pub fn process_move(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let (player, game) = player_and_game(accounts)?;
+    let mut game = Game::load_mut(game)?;
+
+    game.check_active()?;
+    let color = game.color_of(player.key())?;
+    if color != game.turn() {
+        return Err(CheckersError::NotYourTurn.into());
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = MoveInstructionData::from_bytes(instruction_data)?;
+    let path = instruction_data
+        .path
+        .get(..instruction_data.len as usize)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    // Stop the clock of the player.
+    let now = Clock::get()?.unix_timestamp;
+    let time_left = game.time_left(now);
+    if time_left <= 0 {
+        return Err(CheckersError::TimeExpired.into());
+    }
+    *game.time_mut(color) = time_left;
+
+    game.board.apply_move(color, path)?;
+
+    // Pass the turn, any pending draw offer is declined by moving.
+    let opponent = color.opponent();
+    game.turn = opponent as u8;
+    game.turn_started_at = now;
+    game.draw_offer = NO_DRAW_OFFER;
+
+    if !game.board.has_legal_move(opponent) {
+        game.status = GameStatus::won_by(color) as u8;
+        info!("Game {} won by {}", game.id, color as u8);
+    }
+
+    Ok(())
+}
+
+/// Resigns the game, the opponent wins.
+pub fn process_resign(accounts: &[AccountInfo]) -> ProgramResult {
+    let (player, game) = player_and_game(accounts)?;
+    let mut game = Game::load_mut(game)?;
+
+    game.check_active()?;
+    let color = game.color_of(player.key())?;
+    game.status = GameStatus::won_by(color.opponent()) as u8;
+
+    info!("Game {} resigned by {}", game.id, color as u8);
+
+    Ok(())
+}
+
+/// Offers a draw. The offer stands until the opponent accepts it or any
+/// player moves.
+pub fn process_offer_draw(accounts: &[AccountInfo]) -> ProgramResult {
+    let (player, game) = player_and_game(accounts)?;
+    let mut game = Game::load_mut(game)?;
+
+    game.check_active()?;
+    let color = game.color_of(player.key())?;
+    game.draw_offer = color as u8;
+
+    Ok(())
+}
+
+/// Accepts the draw offered by the opponent.
+pub fn process_accept_draw(accounts: &[AccountInfo]) -> ProgramResult {
+    let (player, game) = player_and_game(accounts)?;
+    let mut game = Game::load_mut(game)?;
+
+    game.check_active()?;
+    let color = game.color_of(player.key())?;
+    if game.draw_offer != color.opponent() as u8 {
+        return Err(CheckersError::NoDrawOffer.into());
+    }
+    game.status = GameStatus::Draw as u8;
+
+    info!("Game {} drawn", game.id);
+
+    Ok(())
+}
+
+/// Wins the game for the signer, when the clock of the opponent, who is on
+/// turn, ran out.
+pub fn process_claim_timeout(accounts: &[AccountInfo]) -> ProgramResult {
+    let (player, game) = player_and_game(accounts)?;
+    let mut game = Game::load_mut(game)?;
+
+    game.check_active()?;
+    // Only the clock of the player on turn is running.
+    let color = game.color_of(player.key())?;
+    if color == game.turn() || game.time_left(Clock::get()?.unix_timestamp) > 0 {
+        return Err(CheckersError::TimeNotExpired.into());
+    }
+    game.status = GameStatus::won_by(color) as u8;
+
+    info!("Game {} won by {} on time", game.id, color as u8);
+
+    Ok(())
+}
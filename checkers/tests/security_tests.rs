@@ -0,0 +1,215 @@
+//! Attacks on the checkers program, each rejected with its own error.
+
+use std::mem;
+
+use checkers::{
+    board::{Board, Color, SQUARES},
+    CheckersInstruction, CreateInstructionData, Game, GameStatus, GAME_SEED,
+};
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(checkers::ID);
+
+const GAME_ID: u64 = 7;
+const TIME_CONTROL: i64 = 600;
+const NOW: i64 = 1_000_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: CheckersInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<CheckersInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the game PDA of `black` and its bump.
+fn game_pda(black: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[GAME_SEED.as_bytes(), black.as_ref(), &GAME_ID.to_le_bytes()],
+        &ID,
+    )
+}
+
+/// Returns an active game at the initial position, in which black offered
+/// a draw and let their clock run out before [`NOW`].
+fn game_account(mollusk: &Mollusk, black: &Pubkey, white: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Game::LEN),
+        Game::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(black.as_ref());
+    account.data[32..64].copy_from_slice(white.as_ref());
+    account.data[64..72].copy_from_slice(&GAME_ID.to_le_bytes());
+    account.data[72..80].copy_from_slice(&TIME_CONTROL.to_le_bytes());
+    account.data[80..88].copy_from_slice(&TIME_CONTROL.to_le_bytes());
+    account.data[88..96].copy_from_slice(&(NOW - TIME_CONTROL - 1).to_le_bytes());
+    account.data[96..96 + SQUARES].copy_from_slice(&Board::initial().squares);
+    account.data[128] = Color::Black as u8;
+    account.data[129] = GameStatus::Active as u8;
+    account.data[130] = Color::Black as u8;
+    account.data[131] = game_pda(black).1;
+    account
+}
+
+/// Accounts of an active game between two players, and of an attacker who
+/// plays in neither.
+struct Setup {
+    mollusk: Mollusk,
+    black: Pubkey,
+    white: Pubkey,
+    game: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/checkers");
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let black = Pubkey::new_unique();
+        let white = Pubkey::new_unique();
+        let (game, _) = game_pda(&black);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (black, Account::default()),
+            (white, Account::default()),
+            (game, game_account(&mollusk, &black, &white)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            black,
+            white,
+            game,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Builds an instruction without data on `game`, signed by `player`.
+    fn instruction(
+        &self,
+        discriminator: CheckersInstruction,
+        player: &Pubkey,
+        game: &Pubkey,
+    ) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*player, true),
+            AccountMeta::new(*game, false),
+        ];
+        Instruction::new_with_bytes(ID, &[discriminator as u8], ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_player_signature() {
+    let setup = Setup::new();
+    let mut instruction =
+        setup.instruction(CheckersInstruction::ClaimTimeout, &setup.white, &setup.game);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker claims the win of white, whose opponent ran out of time.
+#[test]
+fn test_claim_timeout_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction(
+            CheckersInstruction::ClaimTimeout,
+            &setup.attacker,
+            &setup.game,
+        ),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker accepts the draw offered by black in place of white.
+#[test]
+fn test_accept_draw_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction(
+            CheckersInstruction::AcceptDraw,
+            &setup.attacker,
+            &setup.game,
+        ),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker resigns the game in place of one of the players.
+#[test]
+fn test_resign_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction(CheckersInstruction::Resign, &setup.attacker, &setup.game),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A game of the attacker against black, with the layout of the program,
+/// but owned by another program.
+#[test]
+fn test_spoofed_game() {
+    let setup = Setup::new();
+    let mut game_account = game_account(&setup.mollusk, &setup.black, &setup.attacker);
+    game_account.owner = Pubkey::new_unique();
+    let game = Pubkey::new_unique();
+    let setup = setup.with(game, game_account);
+    setup.expect_err(
+        &setup.instruction(CheckersInstruction::ClaimTimeout, &setup.attacker, &game),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker creates a game against themselves at the address of the
+/// game of black, before black does.
+#[test]
+fn test_create_game_of_other_player() {
+    let setup = Setup::new();
+    let game = setup.game;
+    let (_, bump) = game_pda(&setup.attacker);
+    let setup = setup.with(game, Account::default());
+    let data = CreateInstructionData::new(setup.attacker.to_bytes(), GAME_ID, TIME_CONTROL, bump);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(game, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(CheckersInstruction::Create, &data),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
@@ -0,0 +1,601 @@
+use std::mem;
+
+use checkers::{
+    board::{Board, Color, BLACK_KING, BLACK_MAN, EMPTY, SQUARES, WHITE_KING, WHITE_MAN},
+    CheckersError, CheckersInstruction, CreateInstructionData, Game, GameStatus,
+    MoveInstructionData, GAME_SEED, NO_DRAW_OFFER,
+};
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(checkers::ID);
+
+/// Offsets of the game fields.
+const BLACK_TIME_OFFSET: usize = 72;
+const WHITE_TIME_OFFSET: usize = 80;
+const TURN_STARTED_AT_OFFSET: usize = 88;
+const BOARD_OFFSET: usize = 96;
+const TURN_OFFSET: usize = 128;
+const STATUS_OFFSET: usize = 129;
+const DRAW_OFFER_OFFSET: usize = 130;
+
+const GAME_ID: u64 = 7;
+const TIME_CONTROL: i64 = 600;
+const NOW: i64 = 1_000_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: CheckersInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<CheckersInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+/// Returns a board with the given pieces.
+fn board(pieces: &[(u8, u8)]) -> Board {
+    let mut squares = [EMPTY; SQUARES];
+    for (square, piece) in pieces {
+        squares[*square as usize] = *piece;
+    }
+    Board::new(squares)
+}
+
+#[test]
+fn test_checkers_board_simple_moves() {
+    let initial = Board::initial();
+
+    // Black opens from row 2 to row 3.
+    for path in [[8, 12], [8, 13], [9, 13], [9, 14], [11, 15]] {
+        let mut board = initial;
+        board.apply_move(Color::Black, &path).unwrap();
+        assert_eq!(board.squares[path[0] as usize], EMPTY);
+        assert_eq!(board.squares[path[1] as usize], BLACK_MAN);
+    }
+
+    let mut board = initial;
+    board.apply_move(Color::White, &[20, 16]).unwrap();
+    assert_eq!(board.squares[16], WHITE_MAN);
+
+    for (color, path) in [
+        // Occupied destination.
+        (Color::Black, &[4, 8][..]),
+        // No piece.
+        (Color::Black, &[12, 16]),
+        // Piece of the opponent.
+        (Color::Black, &[20, 16]),
+        // Not adjacent.
+        (Color::Black, &[8, 16]),
+        (Color::Black, &[8, 9]),
+        // Missing destination and out of the board.
+        (Color::Black, &[8]),
+        (Color::Black, &[8, 32]),
+        // Jumping over nothing.
+        (Color::Black, &[9, 16]),
+    ] {
+        assert_eq!(
+            initial.clone().apply_move(color, path),
+            Err(CheckersError::InvalidMove),
+            "{path:?}"
+        );
+    }
+}
+
+#[test]
+fn test_checkers_board_men_move_forward() {
+    // Men can't move backwards, kings can.
+    assert_eq!(
+        board(&[(13, BLACK_MAN)]).apply_move(Color::Black, &[13, 9]),
+        Err(CheckersError::InvalidMove)
+    );
+    assert_eq!(
+        board(&[(17, WHITE_MAN)]).apply_move(Color::White, &[17, 21]),
+        Err(CheckersError::InvalidMove)
+    );
+    board(&[(13, BLACK_KING)])
+        .apply_move(Color::Black, &[13, 9])
+        .unwrap();
+    board(&[(17, WHITE_KING)])
+        .apply_move(Color::White, &[17, 21])
+        .unwrap();
+
+    // Same for jumps.
+    assert_eq!(
+        board(&[(22, BLACK_MAN), (17, WHITE_MAN)]).apply_move(Color::Black, &[22, 13]),
+        Err(CheckersError::InvalidMove)
+    );
+    let mut king = board(&[(22, BLACK_KING), (17, WHITE_MAN)]);
+    king.apply_move(Color::Black, &[22, 13]).unwrap();
+    assert_eq!(king, board(&[(13, BLACK_KING)]));
+}
+
+#[test]
+fn test_checkers_board_crowning() {
+    let mut board_ = board(&[(24, BLACK_MAN)]);
+    board_.apply_move(Color::Black, &[24, 28]).unwrap();
+    assert_eq!(board_, board(&[(28, BLACK_KING)]));
+
+    let mut board_ = board(&[(5, WHITE_MAN)]);
+    board_.apply_move(Color::White, &[5, 0]).unwrap();
+    assert_eq!(board_, board(&[(0, WHITE_KING)]));
+
+    // Crowning ends the move, even if the new king could keep jumping.
+    let pieces = [(21, BLACK_MAN), (25, WHITE_MAN), (26, WHITE_MAN)];
+    let mut board_ = board(&pieces);
+    board_.apply_move(Color::Black, &[21, 30]).unwrap();
+    assert_eq!(board_, board(&[(30, BLACK_KING), (26, WHITE_MAN)]));
+    assert_eq!(
+        board(&pieces).apply_move(Color::Black, &[21, 30, 23]),
+        Err(CheckersError::InvalidMove)
+    );
+}
+
+#[test]
+fn test_checkers_board_captures() {
+    // Captures are mandatory, for any piece.
+    let pieces = [(0, BLACK_MAN), (13, BLACK_MAN), (17, WHITE_MAN)];
+    for path in [[13, 16], [0, 5]] {
+        assert_eq!(
+            board(&pieces).apply_move(Color::Black, &path),
+            Err(CheckersError::MustCapture)
+        );
+    }
+    let mut board_ = board(&pieces);
+    board_.apply_move(Color::Black, &[13, 22]).unwrap();
+    assert_eq!(board_, board(&[(0, BLACK_MAN), (22, BLACK_MAN)]));
+
+    // Multiple jumps have to be completed.
+    let pieces = [(4, BLACK_MAN), (8, WHITE_MAN), (17, WHITE_MAN)];
+    assert_eq!(
+        board(&pieces).apply_move(Color::Black, &[4, 13]),
+        Err(CheckersError::IncompleteMove)
+    );
+    let mut board_ = board(&pieces);
+    board_.apply_move(Color::Black, &[4, 13, 22]).unwrap();
+    assert_eq!(board_, board(&[(22, BLACK_MAN)]));
+
+    // Own pieces can't be jumped.
+    assert_eq!(
+        board(&[(4, BLACK_MAN), (8, BLACK_MAN)]).apply_move(Color::Black, &[4, 13]),
+        Err(CheckersError::InvalidMove)
+    );
+}
+
+#[test]
+fn test_checkers_board_legal_moves() {
+    let initial = Board::initial();
+    assert!(initial.has_legal_move(Color::Black));
+    assert!(initial.has_legal_move(Color::White));
+    assert!(!initial.can_capture(Color::Black));
+
+    // A blocked man has no legal move.
+    let blocked = board(&[(28, WHITE_MAN), (24, BLACK_MAN), (21, BLACK_MAN)]);
+    assert!(!blocked.has_legal_move(Color::White));
+    assert!(blocked.has_legal_move(Color::Black));
+
+    // Neither has a player without pieces.
+    assert!(!board(&[(0, BLACK_MAN)]).has_legal_move(Color::White));
+}
+
+fn game_address(black: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[GAME_SEED.as_bytes(), black.as_ref(), &GAME_ID.to_le_bytes()],
+        &ID,
+    )
+}
+
+fn instruction_create(black: &Pubkey, white: &Pubkey, time_control: i64) -> Instruction {
+    let (game, bump) = game_address(black);
+    let data = CreateInstructionData::new(white.to_bytes(), GAME_ID, time_control, bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*black, true),
+        AccountMeta::new(game, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(CheckersInstruction::Create, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_move(player: &Pubkey, game: &Pubkey, path: &[u8]) -> Instruction {
+    let data = MoveInstructionData::new(path);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*player, true),
+        AccountMeta::new(*game, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(CheckersInstruction::Move, &data),
+        ix_accounts,
+    )
+}
+
+/// Builds an instruction without data, signed by `player`.
+fn instruction(discriminator: CheckersInstruction, player: &Pubkey, game: &Pubkey) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*player, true),
+        AccountMeta::new(*game, false),
+    ];
+    Instruction::new_with_bytes(ID, &[discriminator as u8], ix_accounts)
+}
+
+/// Creates an active game account with black on turn.
+fn game_account(mollusk: &Mollusk, black: &Pubkey, white: &Pubkey, board: &Board) -> Account {
+    let mut data = vec![0; Game::LEN];
+    data[0..32].copy_from_slice(black.as_ref());
+    data[32..64].copy_from_slice(white.as_ref());
+    data[64..72].copy_from_slice(&GAME_ID.to_le_bytes());
+    data[BLACK_TIME_OFFSET..BLACK_TIME_OFFSET + 8].copy_from_slice(&TIME_CONTROL.to_le_bytes());
+    data[WHITE_TIME_OFFSET..WHITE_TIME_OFFSET + 8].copy_from_slice(&TIME_CONTROL.to_le_bytes());
+    data[TURN_STARTED_AT_OFFSET..TURN_STARTED_AT_OFFSET + 8].copy_from_slice(&NOW.to_le_bytes());
+    data[BOARD_OFFSET..BOARD_OFFSET + SQUARES].copy_from_slice(&board.squares);
+    data[TURN_OFFSET] = Color::Black as u8;
+    data[STATUS_OFFSET] = GameStatus::Active as u8;
+    data[DRAW_OFFER_OFFSET] = NO_DRAW_OFFER;
+    data[131] = game_address(black).1;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Game::LEN),
+        Game::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Two players and an active game between them.
+struct Setup {
+    mollusk: Mollusk,
+    black: Pubkey,
+    white: Pubkey,
+    game: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new(board: &Board) -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/checkers");
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let black = Pubkey::new_unique();
+        let white = Pubkey::new_unique();
+        let (game, _) = game_address(&black);
+        let accounts = vec![
+            (black, Account::default()),
+            (white, Account::default()),
+            (game, game_account(&mollusk, &black, &white, board)),
+        ];
+
+        Self {
+            mollusk,
+            black,
+            white,
+            game,
+            accounts,
+        }
+    }
+
+    /// Runs the instruction, checks the result and returns the resulting
+    /// accounts.
+    fn process(&self, instruction: &Instruction, checks: &[Check]) -> Vec<(Pubkey, Account)> {
+        self.mollusk
+            .process_and_validate_instruction(instruction, &self.accounts, checks)
+            .resulting_accounts
+    }
+}
+
+#[test]
+fn test_checkers_create_and_join() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/checkers");
+    mollusk.sysvars.clock.unix_timestamp = NOW;
+
+    let black = Pubkey::new_unique();
+    let white = Pubkey::new_unique();
+    let (game, _) = game_address(&black);
+
+    let mut expected = game_account(&mollusk, &black, &white, &Board::initial()).data;
+    expected[TURN_STARTED_AT_OFFSET..TURN_STARTED_AT_OFFSET + 8].fill(0);
+    expected[STATUS_OFFSET] = GameStatus::Pending as u8;
+
+    let accounts = [
+        (black, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+        (white, Account::default()),
+        (game, Account::default()),
+        keyed_account_for_system_program(),
+    ];
+    mollusk.process_and_validate_instruction(
+        &instruction_create(&black, &white, 0),
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::InvalidTimeControl as u32),
+        ))],
+    );
+    let accounts = mollusk
+        .process_and_validate_instruction(
+            &instruction_create(&black, &white, TIME_CONTROL),
+            &accounts,
+            &[
+                Check::success(),
+                Check::account(&game).owner(&ID).data(&expected).build(),
+            ],
+        )
+        .resulting_accounts;
+
+    // The game can't start before white joins.
+    mollusk.process_and_validate_instruction(
+        &instruction_move(&black, &game, &[8, 12]),
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::GameNotActive as u32),
+        ))],
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction(CheckersInstruction::Join, &black, &game),
+        &accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+    let accounts = mollusk
+        .process_and_validate_instruction(
+            &instruction(CheckersInstruction::Join, &white, &game),
+            &accounts,
+            &[
+                Check::success(),
+                Check::account(&game)
+                    .data_slice(STATUS_OFFSET, &[GameStatus::Active as u8])
+                    .data_slice(TURN_STARTED_AT_OFFSET, &NOW.to_le_bytes())
+                    .build(),
+            ],
+        )
+        .resulting_accounts;
+    mollusk.process_and_validate_instruction(
+        &instruction(CheckersInstruction::Join, &white, &game),
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::GameNotActive as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_checkers_turns() {
+    let mut setup = Setup::new(&Board::initial());
+    let attacker = Pubkey::new_unique();
+    setup.accounts.push((attacker, Account::default()));
+
+    let mut after_black = Board::initial();
+    after_black.apply_move(Color::Black, &[9, 13]).unwrap();
+    let mut after_white = after_black;
+    after_white.apply_move(Color::White, &[22, 17]).unwrap();
+
+    setup.process(
+        &instruction_move(&setup.white, &setup.game, &[20, 16]),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::NotYourTurn as u32),
+        ))],
+    );
+    setup.process(
+        &instruction_move(&attacker, &setup.game, &[9, 13]),
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+    setup.process(
+        &instruction_move(&setup.black, &setup.game, &[9, 17]),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::InvalidMove as u32),
+        ))],
+    );
+    setup.accounts = setup.process(
+        &instruction_move(&setup.black, &setup.game, &[9, 13]),
+        &[
+            Check::success(),
+            Check::account(&setup.game)
+                .data_slice(BOARD_OFFSET, &after_black.squares)
+                .data_slice(TURN_OFFSET, &[Color::White as u8])
+                .build(),
+        ],
+    );
+    setup.process(
+        &instruction_move(&setup.black, &setup.game, &[10, 14]),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::NotYourTurn as u32),
+        ))],
+    );
+    setup.accounts = setup.process(
+        &instruction_move(&setup.white, &setup.game, &[22, 17]),
+        &[
+            Check::success(),
+            Check::account(&setup.game)
+                .data_slice(BOARD_OFFSET, &after_white.squares)
+                .data_slice(TURN_OFFSET, &[Color::Black as u8])
+                .build(),
+        ],
+    );
+
+    // Black has to take the man on 17.
+    setup.process(
+        &instruction_move(&setup.black, &setup.game, &[10, 14]),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::MustCapture as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_checkers_win_without_pieces() {
+    let setup = Setup::new(&board(&[(13, BLACK_MAN), (17, WHITE_MAN)]));
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_move(&setup.black, &setup.game, &[13, 22]),
+                &[
+                    Check::success(),
+                    Check::account(&setup.game)
+                        .data_slice(BOARD_OFFSET, &board(&[(22, BLACK_MAN)]).squares)
+                        .data_slice(STATUS_OFFSET, &[GameStatus::BlackWon as u8])
+                        .build(),
+                ],
+            ),
+            (
+                &instruction(CheckersInstruction::Resign, &setup.black, &setup.game),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Checkers.code(CheckersError::GameNotActive as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_checkers_resign() {
+    let setup = Setup::new(&Board::initial());
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction(CheckersInstruction::Resign, &setup.white, &setup.game),
+                &[
+                    Check::success(),
+                    Check::account(&setup.game)
+                        .data_slice(STATUS_OFFSET, &[GameStatus::BlackWon as u8])
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_move(&setup.black, &setup.game, &[9, 13]),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Checkers.code(CheckersError::GameNotActive as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_checkers_draw() {
+    let mut setup = Setup::new(&Board::initial());
+    let no_draw_offer = || {
+        [Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::NoDrawOffer as u32),
+        ))]
+    };
+
+    setup.process(
+        &instruction(CheckersInstruction::AcceptDraw, &setup.white, &setup.game),
+        &no_draw_offer(),
+    );
+    setup.accounts = setup.process(
+        &instruction(CheckersInstruction::OfferDraw, &setup.black, &setup.game),
+        &[
+            Check::success(),
+            Check::account(&setup.game)
+                .data_slice(DRAW_OFFER_OFFSET, &[Color::Black as u8])
+                .build(),
+        ],
+    );
+    // A player can't accept their own offer.
+    setup.process(
+        &instruction(CheckersInstruction::AcceptDraw, &setup.black, &setup.game),
+        &no_draw_offer(),
+    );
+
+    // Moving declines the offer.
+    setup.accounts = setup.process(
+        &instruction_move(&setup.black, &setup.game, &[9, 13]),
+        &[
+            Check::success(),
+            Check::account(&setup.game)
+                .data_slice(DRAW_OFFER_OFFSET, &[NO_DRAW_OFFER])
+                .build(),
+        ],
+    );
+    setup.process(
+        &instruction(CheckersInstruction::AcceptDraw, &setup.white, &setup.game),
+        &no_draw_offer(),
+    );
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction(CheckersInstruction::OfferDraw, &setup.white, &setup.game),
+                &[Check::success()],
+            ),
+            (
+                &instruction(CheckersInstruction::AcceptDraw, &setup.black, &setup.game),
+                &[
+                    Check::success(),
+                    Check::account(&setup.game)
+                        .data_slice(STATUS_OFFSET, &[GameStatus::Draw as u8])
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_checkers_clocks() {
+    let mut setup = Setup::new(&Board::initial());
+
+    // Black thinks for 10 seconds.
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + 10;
+    setup.accounts = setup.process(
+        &instruction_move(&setup.black, &setup.game, &[9, 13]),
+        &[
+            Check::success(),
+            Check::account(&setup.game)
+                .data_slice(BLACK_TIME_OFFSET, &(TIME_CONTROL - 10).to_le_bytes())
+                .data_slice(WHITE_TIME_OFFSET, &TIME_CONTROL.to_le_bytes())
+                .data_slice(TURN_STARTED_AT_OFFSET, &(NOW + 10).to_le_bytes())
+                .build(),
+        ],
+    );
+
+    // Nobody can win on time while white still has time.
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + 10 + TIME_CONTROL - 1;
+    for player in [setup.black, setup.white] {
+        setup.mollusk.process_and_validate_instruction(
+            &instruction(CheckersInstruction::ClaimTimeout, &player, &setup.game),
+            &setup.accounts,
+            &[Check::err(ProgramError::Custom(
+                Namespace::Checkers.code(CheckersError::TimeNotExpired as u32),
+            ))],
+        );
+    }
+
+    // Once the clock of white ran out, white can't move anymore and black
+    // wins.
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + 10 + TIME_CONTROL;
+    setup.process(
+        &instruction_move(&setup.white, &setup.game, &[22, 17]),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Checkers.code(CheckersError::TimeExpired as u32),
+        ))],
+    );
+    setup.process(
+        &instruction(CheckersInstruction::ClaimTimeout, &setup.black, &setup.game),
+        &[
+            Check::success(),
+            Check::account(&setup.game)
+                .data_slice(STATUS_OFFSET, &[GameStatus::BlackWon as u8])
+                .build(),
+        ],
+    );
+}
@@ -0,0 +1,145 @@
+//! Transactions of the instructions built by the client crates.
+//!
+//! [`TransactionBuilder`] wraps instructions into a v0 transaction, the
+//! format wallets and mainnet apps send, optionally with address lookup
+//! tables and compute budget instructions. On a congested cluster, a
+//! priority fee gets the transaction scheduled sooner, and a compute unit
+//! limit close to the consumed units keeps that fee low.
+//!
+//! [`ui_amount`] converts amounts of tokens from and to the ones shown to
+//! users, with the decimals of their mint.
+
+use std::fmt;
+
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_hash::Hash;
+use solana_instruction::Instruction;
+use solana_message::{v0::Message, AddressLookupTableAccount, CompileError, VersionedMessage};
+use solana_pubkey::Pubkey;
+use solana_signer::{signers::Signers, SignerError};
+use solana_transaction::versioned::VersionedTransaction;
+
+pub mod ui_amount;
+
+/// Error building a transaction.
+#[derive(Debug)]
+pub enum Error {
+    /// The message doesn't fit the v0 format, e.g. with too many accounts.
+    Compile(CompileError),
+    /// The signers don't match the ones required by the message.
+    Sign(SignerError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compile(e) => write!(f, "failed to compile the message: {e}"),
+            Self::Sign(e) => write!(f, "failed to sign the transaction: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<CompileError> for Error {
+    fn from(e: CompileError) -> Self {
+        Self::Compile(e)
+    }
+}
+
+impl From<SignerError> for Error {
+    fn from(e: SignerError) -> Self {
+        Self::Sign(e)
+    }
+}
+
+/// Builds a v0 transaction paid by a payer.
+#[derive(Clone, Debug)]
+pub struct TransactionBuilder {
+    payer: Pubkey,
+    instructions: Vec<Instruction>,
+    lookup_tables: Vec<AddressLookupTableAccount>,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<u64>,
+}
+
+impl TransactionBuilder {
+    /// Creates a builder of a transaction paid by `payer`.
+    pub fn new(payer: &Pubkey) -> Self {
+        Self {
+            payer: *payer,
+            instructions: Vec::new(),
+            lookup_tables: Vec::new(),
+            compute_unit_limit: None,
+            compute_unit_price: None,
+        }
+    }
+
+    /// Appends `instruction`.
+    pub fn instruction(mut self, instruction: Instruction) -> Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// Appends `instructions`.
+    pub fn instructions(mut self, instructions: impl IntoIterator<Item = Instruction>) -> Self {
+        self.instructions.extend(instructions);
+        self
+    }
+
+    /// Adds a lookup table, whose addresses are referenced by index instead
+    /// of being listed in the message.
+    pub fn lookup_table(mut self, lookup_table: AddressLookupTableAccount) -> Self {
+        self.lookup_tables.push(lookup_table);
+        self
+    }
+
+    /// Sets the maximum of compute units consumed by the transaction.
+    pub fn compute_unit_limit(mut self, units: u32) -> Self {
+        self.compute_unit_limit = Some(units);
+        self
+    }
+
+    /// Sets the priority fee, in micro-lamports per compute unit.
+    pub fn priority_fee(mut self, micro_lamports: u64) -> Self {
+        self.compute_unit_price = Some(micro_lamports);
+        self
+    }
+
+    /// Returns the instructions of the transaction, with the compute budget
+    /// instructions first.
+    pub fn build_instructions(&self) -> Vec<Instruction> {
+        let compute_budget = [
+            self.compute_unit_limit
+                .map(ComputeBudgetInstruction::set_compute_unit_limit),
+            self.compute_unit_price
+                .map(ComputeBudgetInstruction::set_compute_unit_price),
+        ];
+        compute_budget
+            .into_iter()
+            .flatten()
+            .chain(self.instructions.iter().cloned())
+            .collect()
+    }
+
+    /// Compiles the v0 message of the transaction.
+    pub fn message(&self, recent_blockhash: Hash) -> Result<VersionedMessage, Error> {
+        let message = Message::try_compile(
+            &self.payer,
+            &self.build_instructions(),
+            &self.lookup_tables,
+            recent_blockhash,
+        )?;
+        Ok(VersionedMessage::V0(message))
+    }
+
+    /// Compiles and signs the transaction.
+    pub fn transaction<T: Signers + ?Sized>(
+        &self,
+        recent_blockhash: Hash,
+        signers: &T,
+    ) -> Result<VersionedTransaction, Error> {
+        let message = self.message(recent_blockhash)?;
+        Ok(VersionedTransaction::try_new(message, signers)?)
+    }
+}
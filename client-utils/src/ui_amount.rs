@@ -0,0 +1,79 @@
+//! Amounts of tokens as shown to users.
+//!
+//! The token programs count tokens in the smallest unit of their mint, whose
+//! decimals say where the decimal point of the amount shown to users goes:
+//! 1.5 tokens of a mint with 6 decimals are 1,500,000 units. Converting with
+//! the decimals of the mint, read from its account, avoids amounts off by a
+//! power of ten.
+
+use std::{fmt, iter};
+
+/// Error converting an amount typed by a user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiAmountError {
+    /// The amount isn't a non-negative decimal number, e.g. `1,5` or `-1`.
+    Invalid,
+    /// The amount has more fractional digits than the mint has decimals.
+    TooPrecise,
+    /// The amount doesn't fit into a `u64` in the smallest unit.
+    Overflow,
+}
+
+impl fmt::Display for UiAmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid => write!(f, "the amount isn't a decimal number"),
+            Self::TooPrecise => write!(f, "the amount has more decimals than the mint"),
+            Self::Overflow => write!(f, "the amount is too large"),
+        }
+    }
+}
+
+impl std::error::Error for UiAmountError {}
+
+/// Converts `ui_amount`, e.g. `1.5`, into the smallest unit of a mint with
+/// `decimals`.
+///
+/// Trailing zeros of the fraction are ignored, so `1.50` is valid for a mint
+/// with 1 decimal.
+pub fn ui_amount_to_amount(ui_amount: &str, decimals: u8) -> Result<u64, UiAmountError> {
+    let (integer, fraction) = ui_amount.split_once('.').unwrap_or((ui_amount, ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(UiAmountError::Invalid);
+    }
+    if !integer
+        .bytes()
+        .chain(fraction.bytes())
+        .all(|byte| byte.is_ascii_digit())
+    {
+        return Err(UiAmountError::Invalid);
+    }
+
+    let fraction = fraction.trim_end_matches('0');
+    let padding = (decimals as usize)
+        .checked_sub(fraction.len())
+        .ok_or(UiAmountError::TooPrecise)?;
+
+    integer
+        .bytes()
+        .chain(fraction.bytes())
+        .chain(iter::repeat(b'0').take(padding))
+        .try_fold(0u64, |amount, digit| {
+            amount.checked_mul(10)?.checked_add(u64::from(digit - b'0'))
+        })
+        .ok_or(UiAmountError::Overflow)
+}
+
+/// Formats `amount`, in the smallest unit of a mint with `decimals`, for
+/// users, without trailing zeros, e.g. `1.5`.
+pub fn amount_to_ui_amount(amount: u64, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    let digits = format!("{amount:0>width$}", width = decimals + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_owned()
+    } else {
+        format!("{integer}.{fraction}")
+    }
+}
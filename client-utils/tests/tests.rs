@@ -0,0 +1,139 @@
+use client_utils::{
+    ui_amount::{amount_to_ui_amount, ui_amount_to_amount, UiAmountError},
+    TransactionBuilder,
+};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_keypair::Keypair;
+use solana_message::{AddressLookupTableAccount, VersionedMessage};
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+
+fn instruction(accounts: &[Pubkey]) -> Instruction {
+    Instruction::new_with_bytes(
+        Pubkey::new_unique(),
+        &[1, 2, 3],
+        accounts
+            .iter()
+            .map(|account| AccountMeta::new(*account, false))
+            .collect(),
+    )
+}
+
+/// The compute budget instructions come before the instructions of the
+/// transaction.
+#[test]
+fn test_compute_budget_instructions() {
+    let payer = Pubkey::new_unique();
+    let transfer = instruction(&[Pubkey::new_unique()]);
+
+    let instructions = TransactionBuilder::new(&payer)
+        .instruction(transfer.clone())
+        .compute_unit_limit(10_000)
+        .priority_fee(1_000)
+        .build_instructions();
+    assert_eq!(
+        instructions,
+        [
+            ComputeBudgetInstruction::set_compute_unit_limit(10_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000),
+            transfer.clone(),
+        ]
+    );
+
+    let instructions = TransactionBuilder::new(&payer)
+        .instruction(transfer.clone())
+        .build_instructions();
+    assert_eq!(instructions, [transfer]);
+}
+
+/// Accounts in a lookup table are loaded from it instead of being listed in
+/// the message.
+#[test]
+fn test_lookup_tables() {
+    let payer = Pubkey::new_unique();
+    let accounts: Vec<_> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let lookup_table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: accounts.clone(),
+    };
+
+    let message = TransactionBuilder::new(&payer)
+        .instruction(instruction(&accounts))
+        .lookup_table(lookup_table.clone())
+        .message(Hash::default())
+        .unwrap();
+    let VersionedMessage::V0(message) = message else {
+        panic!("the message should be v0");
+    };
+    assert!(accounts
+        .iter()
+        .all(|account| !message.account_keys.contains(account)));
+    assert_eq!(message.address_table_lookups.len(), 1);
+    assert_eq!(
+        message.address_table_lookups[0].account_key,
+        lookup_table.key
+    );
+    assert_eq!(
+        message.address_table_lookups[0].writable_indexes,
+        [0, 1, 2, 3]
+    );
+}
+
+/// The transaction is signed by the payer.
+#[test]
+fn test_transaction() {
+    let payer = Keypair::new();
+    let transaction = TransactionBuilder::new(&payer.pubkey())
+        .instruction(instruction(&[Pubkey::new_unique()]))
+        .priority_fee(1)
+        .transaction(Hash::new_unique(), &[&payer])
+        .unwrap();
+    assert_eq!(transaction.message.static_account_keys()[0], payer.pubkey());
+    assert_eq!(
+        transaction.signatures,
+        [payer.sign_message(&transaction.message.serialize())]
+    );
+
+    let other = Keypair::new();
+    assert!(TransactionBuilder::new(&payer.pubkey())
+        .transaction(Hash::new_unique(), &[&other])
+        .is_err());
+}
+
+/// Amounts typed by users are converted with the decimals of the mint, and
+/// formatted back without trailing zeros.
+#[test]
+fn test_ui_amounts() {
+    assert_eq!(ui_amount_to_amount("1.5", 6), Ok(1_500_000));
+    assert_eq!(ui_amount_to_amount("1.50", 1), Ok(15));
+    assert_eq!(ui_amount_to_amount(".5", 1), Ok(5));
+    assert_eq!(ui_amount_to_amount("2.", 0), Ok(2));
+    assert_eq!(ui_amount_to_amount("0", 255), Ok(0));
+    assert_eq!(ui_amount_to_amount("18446744073709551615", 0), Ok(u64::MAX));
+
+    for ui_amount in ["", ".", "-1", "1,5", "1.5.0", " 1"] {
+        assert_eq!(
+            ui_amount_to_amount(ui_amount, 6),
+            Err(UiAmountError::Invalid)
+        );
+    }
+    assert_eq!(
+        ui_amount_to_amount("1.05", 1),
+        Err(UiAmountError::TooPrecise)
+    );
+    assert_eq!(
+        ui_amount_to_amount("18446744073709.551616", 6),
+        Err(UiAmountError::Overflow)
+    );
+
+    assert_eq!(amount_to_ui_amount(1_500_000, 6), "1.5");
+    assert_eq!(amount_to_ui_amount(5, 3), "0.005");
+    assert_eq!(amount_to_ui_amount(100, 0), "100");
+    assert_eq!(amount_to_ui_amount(0, 2), "0");
+    assert_eq!(
+        ui_amount_to_amount(&amount_to_ui_amount(u64::MAX, 9), 9),
+        Ok(u64::MAX)
+    );
+}
@@ -0,0 +1,100 @@
+// Renders the Rust clients of the example programs from their Codama nodes.
+//
+// The nodes are built from the shank IDLs of the programs, emitted by their
+// `test_idl` tests into `<program>/idl`, and completed with what the
+// annotations don't carry: the instruction arguments and the seeds of the
+// PDAs. They're written to `nodes/<program>.json`, then rendered into
+// `<program>-client/src/generated`.
+//
+// The generated modules aren't compiled into the clients yet, but diffing
+// them against the handwritten builders shows when the latter fall behind
+// the programs. Run with `cargo xtask codama` from the root of the
+// repository, which emits the IDLs first.
+
+import { mkdirSync, readFileSync, writeFileSync } from 'node:fs';
+import { dirname, join } from 'node:path';
+import { fileURLToPath } from 'node:url';
+
+import {
+  addPdasVisitor,
+  arrayTypeNode,
+  constantPdaSeedNodeFromString,
+  createFromRoot,
+  fixedCountNode,
+  numberTypeNode,
+  publicKeyTypeNode,
+  updateInstructionsVisitor,
+  variablePdaSeedNode,
+} from 'codama';
+import { rootNodeFromAnchor } from '@codama/nodes-from-anchor';
+import { renderVisitor } from '@codama/renderers-rust';
+
+const root = join(dirname(fileURLToPath(import.meta.url)), '..');
+
+/** Returns the PDA seeded with `prefix`, followed by the `keys`. */
+function pda(name, prefix, keys) {
+  return {
+    name,
+    seeds: [
+      constantPdaSeedNodeFromString('utf8', prefix),
+      ...keys.map((key) => variablePdaSeedNode(key, publicKeyTypeNode())),
+    ],
+  };
+}
+
+/** Returns the update adding `arguments` to an instruction. */
+function withArguments(args) {
+  return {
+    arguments: Object.fromEntries(
+      Object.entries(args).map(([name, type]) => [name, { type }]),
+    ),
+  };
+}
+
+const bump = { bump: numberTypeNode('u8') };
+
+const programs = {
+  counter: [
+    addPdasVisitor({ counter: [pda('counter', 'counter', ['owner'])] }),
+    updateInstructionsVisitor({
+      create: withArguments(bump),
+      increment: withArguments(bump),
+      decrement: withArguments(bump),
+      delete: withArguments(bump),
+    }),
+  ],
+  escrow: [
+    addPdasVisitor({ escrow: [pda('escrow', 'escrow', ['sender', 'receiver'])] }),
+    updateInstructionsVisitor({
+      initialize: withArguments({
+        amount: numberTypeNode('u64'),
+        ...bump,
+        padding: arrayTypeNode(numberTypeNode('u8'), fixedCountNode(7)),
+      }),
+      exchange: withArguments(bump),
+      cancel: withArguments(bump),
+    }),
+  ],
+};
+
+for (const [program, visitors] of Object.entries(programs)) {
+  const idlPath = join(root, program, 'idl', `${program}.json`);
+  const idl = JSON.parse(readFileSync(idlPath, 'utf8'));
+
+  const codama = createFromRoot(rootNodeFromAnchor(idl));
+  for (const visitor of visitors) {
+    codama.update(visitor);
+  }
+
+  mkdirSync(join(root, 'codama', 'nodes'), { recursive: true });
+  writeFileSync(join(root, 'codama', 'nodes', `${program}.json`), codama.getJson());
+
+  const client = join(root, `${program}-client`);
+  codama.accept(
+    renderVisitor(join(client, 'src', 'generated'), {
+      crateFolder: client,
+      deleteFolderBeforeRendering: true,
+      formatCode: true,
+    }),
+  );
+}
@@ -0,0 +1,70 @@
+//! Collects the build metadata embedded by `build_metadata!`: the git
+//! revision of the sources, the version of the compiler and the versions of
+//! the pinocchio crates the program is built with.
+
+use std::{env, fs, path::Path, process::Command};
+
+/// Returns the trimmed output of `command`, if it succeeded.
+fn output(command: &mut Command) -> Option<String> {
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_owned())
+}
+
+/// Returns the versions of the pinocchio crates in the lock file of the
+/// program being built, e.g. `pinocchio 0.8.4, pinocchio-log 0.4.0`.
+///
+/// Every program is its own workspace, so its lock file is the first one
+/// above the output directory, which is in the `target` of the program.
+fn dependencies() -> Option<String> {
+    let out_dir = env::var("OUT_DIR").ok()?;
+    let lock_path = Path::new(&out_dir)
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.exists())?;
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+
+    let lock = fs::read_to_string(lock_path).ok()?;
+    let mut dependencies = Vec::new();
+    let mut name = None;
+    for line in lock.lines() {
+        if let Some(value) = line.strip_prefix("name = ") {
+            name = Some(value.trim_matches('"'));
+        } else if let (Some(value), Some(name)) = (line.strip_prefix("version = "), name) {
+            if name == "pinocchio" || name.starts_with("pinocchio-") {
+                dependencies.push(format!("{name} {}", value.trim_matches('"')));
+            }
+        }
+    }
+    Some(dependencies.join(", "))
+}
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let git = |args: &[&str]| output(Command::new("git").args(args).current_dir(&manifest_dir));
+
+    // Rerun after commits and checkouts, which move `HEAD` or the branch it
+    // points to.
+    for path in ["HEAD", "refs/heads", "packed-refs"] {
+        if let Some(path) = git(&["rev-parse", "--git-path", path]) {
+            println!(
+                "cargo:rerun-if-changed={}",
+                Path::new(&manifest_dir).join(path).display()
+            );
+        }
+    }
+    let revision = git(&["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_owned());
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let rustc =
+        output(Command::new(rustc).arg("--version")).unwrap_or_else(|| "unknown".to_owned());
+
+    println!("cargo:rustc-env=BUILD_SOURCE_REVISION={revision}");
+    println!("cargo:rustc-env=BUILD_RUSTC={rustc}");
+    println!(
+        "cargo:rustc-env=BUILD_DEPENDENCIES={}",
+        dependencies().unwrap_or_default()
+    );
+}
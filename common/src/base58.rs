@@ -0,0 +1,45 @@
+//! Serde representation of addresses as base58 strings.
+//!
+//! Addresses are plain byte arrays in the state and would be serialized as
+//! arrays of numbers otherwise. Use it on `Pubkey` fields with
+//! `#[serde(with = "common::base58")]`.
+
+use core::fmt;
+
+use pinocchio::pubkey::Pubkey;
+use serde::{
+    de::{self, Visitor},
+    Deserializer, Serializer,
+};
+
+/// Length of the longest base58 encoding of an address.
+const MAX_LEN: usize = 44;
+
+pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut encoded = [0; MAX_LEN];
+    let len = bs58::encode(pubkey).onto(&mut encoded[..]).unwrap();
+    // Base58 is ASCII.
+    serializer.serialize_str(core::str::from_utf8(&encoded[..len]).unwrap())
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    deserializer.deserialize_str(PubkeyVisitor)
+}
+
+struct PubkeyVisitor;
+
+impl Visitor<'_> for PubkeyVisitor {
+    type Value = Pubkey;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a base58-encoded address")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Pubkey, E> {
+        let mut pubkey = Pubkey::default();
+        match bs58::decode(value).onto(&mut pubkey[..]) {
+            Ok(len) if len == pubkey.len() => Ok(pubkey),
+            _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Account constraints.
+//!
+//! Every check fails with its own error, so tests can tell which constraint
+//! an account didn't meet.
+
+use errors::SharedError;
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, sysvars::rent::Rent,
+    ProgramResult,
+};
+
+use crate::keys_eq;
+
+/// Checks that `account` signed the transaction.
+///
+/// Fails with [`ProgramError::MissingRequiredSignature`].
+#[inline(always)]
+pub fn require_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+/// Checks that `account` isn't marked as a signer, e.g. a PDA which the
+/// program signs for with its seeds. Only a runtime which doesn't verify
+/// the signatures lets an instruction through with such a flag.
+///
+/// Fails with [`SharedError::UnexpectedSigner`].
+#[inline(always)]
+pub fn require_not_signer(account: &AccountInfo) -> ProgramResult {
+    if account.is_signer() {
+        return Err(SharedError::UnexpectedSigner.into());
+    }
+    Ok(())
+}
+
+/// Checks that `account` is writable.
+///
+/// Fails with [`ProgramError::Immutable`].
+#[inline(always)]
+pub fn require_writable(account: &AccountInfo) -> ProgramResult {
+    if !account.is_writable() {
+        return Err(ProgramError::Immutable);
+    }
+    Ok(())
+}
+
+/// Checks that `account` is owned by `owner`.
+///
+/// Fails with [`ProgramError::IllegalOwner`].
+#[inline(always)]
+pub fn require_owned_by(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if !account.is_owned_by(owner) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Checks that `account` is a system account without data, e.g. before
+/// creating a PDA at its address. It may hold lamports, which anyone can
+/// send to an address before it's created.
+///
+/// Fails with [`ProgramError::AccountAlreadyInitialized`].
+#[inline(always)]
+pub fn require_uninitialized(account: &AccountInfo) -> ProgramResult {
+    if !account.is_owned_by(&pinocchio_system::ID) || !account.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    Ok(())
+}
+
+/// Checks that `account` holds enough lamports to be exempt from rent.
+///
+/// Fails with [`ProgramError::AccountNotRentExempt`].
+#[inline(always)]
+pub fn require_rent_exempt(account: &AccountInfo, rent: &Rent) -> ProgramResult {
+    if !rent.is_exempt(account.lamports(), account.data_len()) {
+        return Err(ProgramError::AccountNotRentExempt);
+    }
+    Ok(())
+}
+
+/// Checks that the key of `account` is `key`, e.g. for sysvars and other
+/// accounts with a well-known address.
+///
+/// Fails with [`ProgramError::InvalidArgument`].
+#[inline(always)]
+pub fn require_key_eq(account: &AccountInfo, key: &Pubkey) -> ProgramResult {
+    if !keys_eq(account.key(), key) {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+/// Checks that no account is passed in more than one role, e.g. the same
+/// token account as both the source and the destination of a transfer,
+/// which would otherwise pass the checks of each role on its own.
+///
+/// Fails with [`SharedError::DuplicateAccount`].
+#[inline(always)]
+pub fn require_distinct(accounts: &[&AccountInfo]) -> ProgramResult {
+    for (i, account) in accounts.iter().enumerate() {
+        if accounts[i + 1..]
+            .iter()
+            .any(|other| keys_eq(other.key(), account.key()))
+        {
+            return Err(SharedError::DuplicateAccount.into());
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the program runs under its declared address `id`, so a copy
+/// deployed at another address fails instead of deriving PDAs which never
+/// match the ones its clients derive.
+///
+/// Fails with [`ProgramError::IncorrectProgramId`].
+#[inline(always)]
+pub fn require_program_id(program_id: &Pubkey, id: &Pubkey) -> ProgramResult {
+    if !keys_eq(program_id, id) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Checks that `account` is the program `program_id`, e.g. before invoking
+/// it, so a substituted program is rejected instead of failing in the CPI.
+///
+/// Fails with [`ProgramError::IncorrectProgramId`].
+#[inline(always)]
+pub fn require_program(account: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
+    if !keys_eq(account.key(), program_id) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Closing of program accounts.
+//!
+//! [`close_account`] moves the lamports of an account with checked math and
+//! zeroes its data before closing it, so the account can't be revived with
+//! its old state later in the same transaction.
+
+use errors::SharedError;
+use pinocchio::{account_info::AccountInfo, ProgramResult};
+
+use crate::keys_eq;
+
+/// Closes `account`, moving all its lamports to `destination`.
+///
+/// Fails with [`SharedError::DuplicateAccount`] if `destination` is
+/// `account`, and with [`SharedError::Overflow`] if the lamports of
+/// `destination` overflow. The data of `account` mustn't be borrowed.
+pub fn close_account(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    if keys_eq(account.key(), destination.key()) {
+        return Err(SharedError::DuplicateAccount.into());
+    }
+
+    account.try_borrow_mut_data()?.fill(0);
+
+    {
+        let mut destination_lamports = destination.try_borrow_mut_lamports()?;
+        *destination_lamports = destination_lamports
+            .checked_add(account.lamports())
+            .ok_or(SharedError::Overflow)?;
+    }
+
+    account.close()
+}
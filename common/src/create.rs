@@ -0,0 +1,78 @@
+//! Creation of program accounts.
+//!
+//! `CreateAccount` fails for addresses which already hold lamports, which
+//! anyone can cause by sending lamports to a PDA before it's created, so
+//! [`create_account`] tops such addresses up, allocates and assigns them
+//! instead.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::Signer,
+    pubkey::Pubkey,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_system::instructions::{Allocate, Assign, CreateAccount, Transfer};
+
+use crate::checks::{require_rent_exempt, require_uninitialized};
+
+/// Creates `account` with `space` bytes of data, owned by `owner` and made
+/// rent-exempt by `payer`. `signers` sign for `account`, e.g. with the
+/// seeds of a PDA.
+///
+/// Fails with [`pinocchio::program_error::ProgramError::AccountAlreadyInitialized`]
+/// if `account` already has data or another owner than the system program.
+pub fn create_account(
+    payer: &AccountInfo,
+    account: &AccountInfo,
+    space: usize,
+    owner: &Pubkey,
+    signers: &[Signer],
+) -> ProgramResult {
+    create_account_with_rent(payer, account, space, owner, signers, &Rent::get()?)
+}
+
+/// Creates `account` like [`create_account`], with the `rent` already
+/// read by the instruction, e.g. from [`crate::sysvars::Sysvars`].
+pub fn create_account_with_rent(
+    payer: &AccountInfo,
+    account: &AccountInfo,
+    space: usize,
+    owner: &Pubkey,
+    signers: &[Signer],
+    rent: &Rent,
+) -> ProgramResult {
+    require_uninitialized(account)?;
+
+    let lamports = rent.minimum_balance(space);
+    if account.lamports() == 0 {
+        CreateAccount {
+            from: payer,
+            to: account,
+            lamports,
+            space: space as u64,
+            owner,
+        }
+        .invoke_signed(signers)?;
+    } else {
+        // Only pay what's missing.
+        let missing = lamports.saturating_sub(account.lamports());
+        if missing > 0 {
+            Transfer {
+                from: payer,
+                to: account,
+                lamports: missing,
+            }
+            .invoke()?;
+        }
+        Allocate {
+            account,
+            space: space as u64,
+        }
+        .invoke_signed(signers)?;
+        Assign { account, owner }.invoke_signed(signers)?;
+    }
+
+    // Check that the new account is rent-exempt before trusting it.
+    require_rent_exempt(account, rent)
+}
@@ -0,0 +1,59 @@
+//! Fixed-size little-endian encoding of instruction data.
+//!
+//! Instruction data comes as an unaligned byte slice, so it can't be cast to
+//! a struct like account data. Types implementing [`Encoding`] are decoded
+//! field by field instead, usually through the `from_bytes` and `to_bytes`
+//! methods generated by `#[derive(InstructionData)]`.
+
+/// Types with a fixed-size little-endian encoding.
+pub trait Encoding: Sized {
+    /// Length of the encoding.
+    const LEN: usize;
+
+    /// Decodes a value from `data`, which is exactly [`Self::LEN`] bytes long.
+    fn decode(data: &[u8]) -> Self;
+
+    /// Encodes the value into `data`, which is exactly [`Self::LEN`] bytes
+    /// long.
+    fn encode(&self, data: &mut [u8]);
+}
+
+macro_rules! impl_encoding_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl Encoding for $ty {
+                const LEN: usize = core::mem::size_of::<$ty>();
+
+                #[inline(always)]
+                fn decode(data: &[u8]) -> Self {
+                    let mut bytes = [0; Self::LEN];
+                    bytes.copy_from_slice(data);
+                    <$ty>::from_le_bytes(bytes)
+                }
+
+                #[inline(always)]
+                fn encode(&self, data: &mut [u8]) {
+                    data.copy_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_encoding_for_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl<T: Encoding, const N: usize> Encoding for [T; N] {
+    const LEN: usize = T::LEN * N;
+
+    #[inline(always)]
+    fn decode(data: &[u8]) -> Self {
+        core::array::from_fn(|i| T::decode(&data[i * T::LEN..(i + 1) * T::LEN]))
+    }
+
+    #[inline(always)]
+    fn encode(&self, data: &mut [u8]) {
+        for (value, data) in self.iter().zip(data.chunks_exact_mut(T::LEN)) {
+            value.encode(data);
+        }
+    }
+}
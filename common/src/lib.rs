@@ -0,0 +1,282 @@
+//! Zero-copy account state shared by the example programs.
+//!
+//! Programs store their state as `#[repr(C)]` structs and read it by
+//! casting the account data to them. The cast is only sound when the
+//! account really holds such a struct: it has to be owned by the program,
+//! have the size of the struct and be aligned for it. [`AccountData`] checks
+//! all of that once, so programs don't need their own `unsafe` casts, and
+//! [`assert_layout`] pins the layout of the structs at compile time.
+//!
+//! [`check_pda`] and [`find_and_check_pda`] check the addresses of PDAs
+//! passed to programs, and [`checks`] the other constraints on accounts.
+//! Keys are compared with [`keys_eq`].
+//! [`create::create_account`] creates PDAs, also at addresses which already
+//! hold lamports, and [`close::close_account`] closes accounts.
+//! [`sysvars::Sysvars`] reads the sysvars an instruction needs once.
+//!
+//! Instruction data is decoded with [`encoding`] instead, and the versions
+//! of its layout are told apart with [`version`].
+//!
+//! Programs log with [`info!`], [`debug!`] and [`trace!`], which only log
+//! with the `verbose-logs` feature, so production builds don't spend
+//! compute units on logs.
+//!
+//! [`build_metadata!`] embeds the version, sources and toolchain of a
+//! program into its binary, read back with [`metadata::fields`].
+//!
+//! With the `bytemuck` feature, state implementing `bytemuck::Pod` can also
+//! be converted from and to bytes without `unsafe`. With the `serde`
+//! feature, [`base58`] serializes the addresses in state for off-chain use.
+
+#![no_std]
+
+use core::mem;
+
+use pinocchio::{
+    account_info::{AccountInfo, Ref, RefMut},
+    program_error::ProgramError,
+    pubkey::{create_program_address, try_find_program_address, Pubkey, MAX_SEEDS},
+    ProgramResult,
+};
+
+#[cfg(feature = "serde")]
+pub mod base58;
+pub mod checks;
+pub mod close;
+pub mod create;
+pub mod encoding;
+mod log;
+pub mod metadata;
+pub mod sysvars;
+pub mod version;
+
+/// State stored in the data of accounts owned by [`AccountData::OWNER`].
+///
+/// # Safety
+///
+/// Implementors have to be `#[repr(C)]` structs without implicit padding,
+/// made of fields for which any bit pattern is valid (integers, byte arrays
+/// and structs of them). `bool` and enums are not allowed, as the account
+/// data could hold any value.
+pub unsafe trait AccountData: Sized {
+    /// Program owning the accounts.
+    const OWNER: Pubkey;
+    /// Bytes preceding the state in the data, e.g. Anchor's 8-byte account
+    /// discriminator. Its length has to keep the state aligned.
+    const PREFIX: &'static [u8] = &[];
+    /// Expected first byte of the state, if it starts with a discriminator
+    /// identifying it among other kinds of accounts of the same program.
+    const DISCRIMINATOR: Option<u8> = None;
+    /// Size of the data.
+    const LEN: usize = Self::PREFIX.len() + mem::size_of::<Self>();
+
+    /// Checks that `data` can be cast to `Self`.
+    fn validate(data: &[u8]) -> ProgramResult {
+        Self::check_data(data)?;
+        if data[Self::PREFIX.len()..].as_ptr() as usize & (mem::align_of::<Self>() - 1) != 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+
+    /// Checks the length, the prefix and the discriminator of `data`.
+    fn check_data(data: &[u8]) -> ProgramResult {
+        if data.len() != Self::LEN || !data.starts_with(Self::PREFIX) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if let Some(discriminator) = Self::DISCRIMINATOR {
+            if data[Self::PREFIX.len()] != discriminator {
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the state out of `data`, which doesn't have to be aligned.
+    ///
+    /// Meant for off-chain clients decoding fetched account data. Programs
+    /// should use [`AccountData::load`] instead.
+    fn read(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::check_data(data)?;
+        Ok(unsafe {
+            data[Self::PREFIX.len()..]
+                .as_ptr()
+                .cast::<Self>()
+                .read_unaligned()
+        })
+    }
+
+    /// Casts `data`, which has to be aligned, to the state.
+    ///
+    /// Unlike [`AccountData::load`], the cast goes through bytemuck, which
+    /// checks the alignment and the size itself.
+    #[cfg(feature = "bytemuck")]
+    fn from_bytes(data: &[u8]) -> Result<&Self, ProgramError>
+    where
+        Self: bytemuck::Pod,
+    {
+        Self::check_data(data)?;
+        bytemuck::try_from_bytes(&data[Self::PREFIX.len()..])
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Returns the bytes of the state, without the prefix, e.g. to write it
+    /// into account data in tests.
+    #[cfg(feature = "bytemuck")]
+    fn as_bytes(&self) -> &[u8]
+    where
+        Self: bytemuck::Pod,
+    {
+        bytemuck::bytes_of(self)
+    }
+
+    /// Borrows the state from `account`.
+    fn load(account: &AccountInfo) -> Result<Ref<'_, Self>, ProgramError> {
+        if !account.is_owned_by(&Self::OWNER) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let data = account.try_borrow_data()?;
+        Self::validate(&data)?;
+        Ok(Ref::map(data, |data| unsafe {
+            &*data[Self::PREFIX.len()..].as_ptr().cast()
+        }))
+    }
+
+    /// Borrows the state from `account` without tracking the borrow, which
+    /// saves the compute units of [`AccountData::load`] checking and
+    /// releasing it.
+    ///
+    /// # Safety
+    ///
+    /// The data of `account` must not be borrowed mutably while the
+    /// returned reference is live, neither by the program nor by a program
+    /// it invokes.
+    unsafe fn load_unchecked(account: &AccountInfo) -> Result<&Self, ProgramError> {
+        if !account.is_owned_by(&Self::OWNER) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let data = account.borrow_data_unchecked();
+        Self::validate(data)?;
+        Ok(&*data[Self::PREFIX.len()..].as_ptr().cast())
+    }
+
+    /// Borrows the state mutably from `account`.
+    fn load_mut(account: &AccountInfo) -> Result<RefMut<'_, Self>, ProgramError> {
+        if !account.is_owned_by(&Self::OWNER) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        let data = account.try_borrow_mut_data()?;
+        Self::validate(&data)?;
+        Ok(RefMut::map(data, |data| unsafe {
+            &mut *data[Self::PREFIX.len()..].as_mut_ptr().cast()
+        }))
+    }
+
+    /// Borrows the state of a newly created `account` mutably, after
+    /// writing the prefix and the discriminator into its data.
+    fn init(account: &AccountInfo) -> Result<RefMut<'_, Self>, ProgramError> {
+        if !account.is_owned_by(&Self::OWNER) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        {
+            let mut data = account.try_borrow_mut_data()?;
+            if data.len() != Self::LEN {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            data[..Self::PREFIX.len()].copy_from_slice(Self::PREFIX);
+            if let Some(discriminator) = Self::DISCRIMINATOR {
+                data[Self::PREFIX.len()] = discriminator;
+            }
+        }
+        Self::load_mut(account)
+    }
+}
+
+/// Asserts the layout of a `#[repr(C)]` state struct at compile time.
+///
+/// The size, the alignment and the offset of every field are part of the
+/// on-chain format, so a change of the struct that moves any of them (e.g.
+/// by introducing padding) has to fail the build instead of silently
+/// corrupting existing accounts.
+///
+/// ```ignore
+/// assert_layout!(Counter, size = 40, align = 8, { owner: 0, count: 32 });
+/// ```
+#[macro_export]
+macro_rules! assert_layout {
+    ($ty:ty, size = $size:expr, align = $align:expr, { $($field:ident: $offset:expr),* $(,)? }) => {
+        const _: () = {
+            assert!(::core::mem::size_of::<$ty>() == $size);
+            assert!(::core::mem::align_of::<$ty>() == $align);
+            $(assert!(::core::mem::offset_of!($ty, $field) == $offset);)*
+        };
+    };
+}
+
+/// Returns whether the keys `a` and `b` are equal.
+///
+/// `==` on the arrays compiles to a call of the `sol_memcmp_` syscall,
+/// costing its 10 compute units plus the instructions passing the
+/// arguments and checking the result. This compares the keys as four `u64`
+/// words instead, which is a handful of instructions without any call. All
+/// words are compared, so the cost doesn't depend on where the keys differ.
+///
+/// The `key-comparison` program compares keys both ways, and its tests
+/// check that this one costs fewer compute units. The saving adds up in
+/// loops over many accounts, e.g. [`checks::require_distinct`], more than
+/// in a single check of a PDA.
+#[inline(always)]
+pub fn keys_eq(a: &Pubkey, b: &Pubkey) -> bool {
+    let a = a.as_ptr().cast::<u64>();
+    let b = b.as_ptr().cast::<u64>();
+    // Keys are byte arrays, which don't have to be aligned for `u64`.
+    unsafe {
+        (a.read_unaligned() ^ b.read_unaligned())
+            | (a.add(1).read_unaligned() ^ b.add(1).read_unaligned())
+            | (a.add(2).read_unaligned() ^ b.add(2).read_unaligned())
+            | (a.add(3).read_unaligned() ^ b.add(3).read_unaligned())
+            == 0
+    }
+}
+
+/// Checks that `expected` is the PDA of `program_id` derived from `seeds`
+/// and `bump`.
+pub fn check_pda(
+    seeds: &[&[u8]],
+    bump: u8,
+    expected: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if seeds.len() >= MAX_SEEDS {
+        return Err(ProgramError::MaxSeedLengthExceeded);
+    }
+    let bump = [bump];
+    let mut seeds_with_bump: [&[u8]; MAX_SEEDS] = [&[]; MAX_SEEDS];
+    seeds_with_bump[..seeds.len()].copy_from_slice(seeds);
+    seeds_with_bump[seeds.len()] = &bump;
+
+    let pda = create_program_address(&seeds_with_bump[..seeds.len() + 1], program_id)?;
+    if !keys_eq(&pda, expected) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Checks that `expected` is the PDA of `program_id` derived from `seeds`
+/// and the canonical bump, which is returned.
+///
+/// Searching for the bump costs much more compute units than
+/// [`check_pda`], so programs should prefer storing the bump or taking it
+/// from the instruction data.
+pub fn find_and_check_pda(
+    seeds: &[&[u8]],
+    expected: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<u8, ProgramError> {
+    let (pda, bump) =
+        try_find_program_address(seeds, program_id).ok_or(ProgramError::InvalidSeeds)?;
+    if !keys_eq(&pda, expected) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(bump)
+}
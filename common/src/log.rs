@@ -0,0 +1,60 @@
+//! Log levels compiled in only with the `verbose-logs` feature.
+//!
+//! Every log costs compute units, mostly to format its arguments, so the
+//! programs only log with [`info!`], [`debug!`] and [`trace!`], which
+//! forward to `pinocchio_log::log!` with the feature and do nothing without
+//! it. The arguments stay type-checked, but the dead branch is optimized
+//! out of the binary.
+//!
+//! - [`info!`] logs the outcome of an instruction, e.g. a state change.
+//! - [`debug!`] logs values the instruction computed, e.g. its return data.
+//! - [`trace!`] logs the steps of an instruction, e.g. every iteration of a
+//!   loop.
+//!
+//! The macros expand to `pinocchio_log::log!`, so the programs using them
+//! depend on `pinocchio-log`.
+
+#[cfg(feature = "verbose-logs")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($($arg:tt)*) => {
+        ::pinocchio_log::log!($($arg)*)
+    };
+}
+
+#[cfg(not(feature = "verbose-logs"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($($arg:tt)*) => {
+        if false {
+            ::pinocchio_log::log!($($arg)*)
+        }
+    };
+}
+
+/// Logs the outcome of an instruction with the `verbose-logs` feature.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::__log!($($arg)*)
+    };
+}
+
+/// Logs a value computed by an instruction with the `verbose-logs`
+/// feature.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::__log!($($arg)*)
+    };
+}
+
+/// Logs a step of an instruction with the `verbose-logs` feature.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::__log!($($arg)*)
+    };
+}
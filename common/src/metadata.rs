@@ -0,0 +1,128 @@
+//! Build metadata embedded into the binaries of the programs.
+//!
+//! [`build_metadata!`](crate::build_metadata) puts the name and version of
+//! the program, the repository and git revision of its sources, the
+//! version of the compiler and the versions of the pinocchio crates into a
+//! `.build_metadata` section of its binary. Like the `security.txt` of
+//! `solana-security-txt`, the section is a list of null-terminated keys and
+//! values between null-terminated markers, so it can be found in a binary
+//! fetched from a cluster with [`fields`] and checked against the sources,
+//! before comparing the hashes of verifiable builds.
+//!
+//! The fields are:
+//!
+//! - `name` and `version` of the crate of the program,
+//! - `source_code`, the URL of the repository,
+//! - `source_revision`, the git commit the program was built from,
+//! - `rustc`, the output of `rustc --version` of the compiler,
+//! - `dependencies`, e.g. `pinocchio 0.8.4, pinocchio-log 0.4.0`.
+//!
+//! The revision, compiler and dependencies are collected by the build
+//! script of this crate and are `unknown` or empty when it can't find them,
+//! e.g. when building outside of a git checkout.
+
+use core::str;
+
+/// Marker preceding the fields.
+pub const BEGIN: &str = "=======BEGIN BUILD METADATA V1=======\0";
+/// Marker following the fields.
+pub const END: &str = "=======END BUILD METADATA V1=======\0";
+
+/// URL of the repository of the programs.
+pub const SOURCE_CODE: &str = "https://github.com/vadorovsky/pinocchio-examples";
+/// Git commit the programs are built from.
+pub const SOURCE_REVISION: &str = env!("BUILD_SOURCE_REVISION");
+/// Version of the compiler building the programs.
+pub const RUSTC: &str = env!("BUILD_RUSTC");
+/// Versions of the pinocchio crates the programs are built with.
+pub const DEPENDENCIES: &str = env!("BUILD_DEPENDENCIES");
+
+/// Returns the total length of `parts`.
+pub const fn len(parts: &[&str]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        len += parts[i].len();
+        i += 1;
+    }
+    len
+}
+
+/// Concatenates `parts` into an array, whose length `N` has to be their
+/// [`len`].
+pub const fn concat<const N: usize>(parts: &[&str]) -> [u8; N] {
+    let mut bytes = [0; N];
+    let mut offset = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let part = parts[i].as_bytes();
+        let mut j = 0;
+        while j < part.len() {
+            bytes[offset] = part[j];
+            offset += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(offset == N);
+    bytes
+}
+
+/// Returns the position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Returns the keys and values of the build metadata embedded in `binary`,
+/// in their order, or `None` if it has none.
+pub fn fields(binary: &[u8]) -> Option<impl Iterator<Item = (&str, &str)>> {
+    let start = find(binary, BEGIN.as_bytes())? + BEGIN.len();
+    let len = find(&binary[start..], END.as_bytes())?;
+    let mut parts = binary[start..start + len].split(|&byte| byte == 0);
+    // Every key and value is terminated, so the last part is empty.
+    Some(core::iter::from_fn(move || {
+        let key = parts.next().filter(|key| !key.is_empty())?;
+        let value = parts.next()?;
+        Some((str::from_utf8(key).ok()?, str::from_utf8(value).ok()?))
+    }))
+}
+
+/// Embeds the build metadata of the program into its binary.
+///
+/// Use it with the same `cfg` as the entrypoint, so it isn't embedded again
+/// in programs depending on the crate of the program:
+///
+/// ```ignore
+/// #[cfg(target_os = "solana")]
+/// common::build_metadata!();
+/// ```
+#[macro_export]
+macro_rules! build_metadata {
+    () => {
+        const _: () = {
+            const PARTS: &[&str] = &[
+                $crate::metadata::BEGIN,
+                concat!("name\0", env!("CARGO_PKG_NAME"), "\0"),
+                concat!("version\0", env!("CARGO_PKG_VERSION"), "\0"),
+                "source_code\0",
+                $crate::metadata::SOURCE_CODE,
+                "\0source_revision\0",
+                $crate::metadata::SOURCE_REVISION,
+                "\0rustc\0",
+                $crate::metadata::RUSTC,
+                "\0dependencies\0",
+                $crate::metadata::DEPENDENCIES,
+                "\0",
+                $crate::metadata::END,
+            ];
+
+            #[no_mangle]
+            #[used]
+            #[link_section = ".build_metadata"]
+            static BUILD_METADATA: [u8; $crate::metadata::len(PARTS)] =
+                $crate::metadata::concat(PARTS);
+        };
+    };
+}
@@ -0,0 +1,85 @@
+//! Sysvars read once per instruction.
+//!
+//! `Sysvar::get` copies a sysvar out of the runtime with a syscall, which
+//! costs 100 compute units plus the size of the sysvar, e.g. 124 for `Rent`
+//! and 140 for `Clock`, on every call. As instructions grow, the same
+//! sysvar ends up fetched by several functions, e.g. to fund a PDA and a
+//! mint created by the same instruction. [`Sysvars`] fetches each sysvar on
+//! its first use only and is passed by reference to the functions needing
+//! them:
+//!
+//! ```ignore
+//! let sysvars = Sysvars::new();
+//! create_account_with_rent(payer, config, Config::LEN, &ID, signers, sysvars.rent()?)?;
+//! let lamports = sysvars.rent()?.minimum_balance(MINT_LEN);
+//! ```
+//!
+//! Instructions which take the sysvar account anyway, e.g. to pass it on
+//! to a program still requiring it, can read the sysvar from it with
+//! [`Sysvars::with_rent_account`] and [`Sysvars::with_clock_account`]
+//! instead, which costs no syscall at all. Adding the account only for that
+//! isn't worth it, as every account makes the transaction 32 bytes larger
+//! and the runtime serializes it into the input of the program.
+
+use core::{cell::OnceCell, ops::Deref};
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+};
+
+/// Sysvars of an instruction, fetched on their first use.
+#[derive(Default)]
+pub struct Sysvars {
+    rent: OnceCell<Rent>,
+    clock: OnceCell<Clock>,
+}
+
+impl Sysvars {
+    /// Creates the sysvars of the instruction, without fetching any yet.
+    pub const fn new() -> Self {
+        Self {
+            rent: OnceCell::new(),
+            clock: OnceCell::new(),
+        }
+    }
+
+    /// Reads the rent from `account`, the rent sysvar account.
+    ///
+    /// Fails with [`ProgramError::InvalidArgument`] if `account` isn't the
+    /// rent sysvar account.
+    pub fn with_rent_account(self, account: &AccountInfo) -> Result<Self, ProgramError> {
+        let rent = Rent::from_account_info(account)?.deref().clone();
+        let _ = self.rent.set(rent);
+        Ok(self)
+    }
+
+    /// Reads the clock from `account`, the clock sysvar account.
+    ///
+    /// Fails with [`ProgramError::InvalidArgument`] if `account` isn't the
+    /// clock sysvar account.
+    pub fn with_clock_account(self, account: &AccountInfo) -> Result<Self, ProgramError> {
+        let clock = Clock::from_account_info(account)?.deref().clone();
+        let _ = self.clock.set(clock);
+        Ok(self)
+    }
+
+    /// Returns the rent, fetching it on the first call.
+    pub fn rent(&self) -> Result<&Rent, ProgramError> {
+        if let Some(rent) = self.rent.get() {
+            return Ok(rent);
+        }
+        let rent = Rent::get()?;
+        Ok(self.rent.get_or_init(|| rent))
+    }
+
+    /// Returns the clock, fetching it on the first call.
+    pub fn clock(&self) -> Result<&Clock, ProgramError> {
+        if let Some(clock) = self.clock.get() {
+            return Ok(clock);
+        }
+        let clock = Clock::get()?;
+        Ok(self.clock.get_or_init(|| clock))
+    }
+}
@@ -0,0 +1,43 @@
+//! Versions of instruction data.
+//!
+//! Instructions derived with `#[derive(InstructionDiscriminator)]` and
+//! `#[versioned]` start their data with their family, which says what they
+//! do, followed by a [`Version`] byte, which says how the rest of the data
+//! is laid out. Without the `anchor` feature, the family is one byte, so the
+//! discriminator is two bytes long.
+//!
+//! A program changes the data of an instruction by adding a version of it,
+//! and keeps processing the previous versions, so clients built for them
+//! don't break.
+
+use pinocchio::program_error::ProgramError;
+
+/// Version of the layout of the data of an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Version {
+    /// The first layout of every instruction.
+    V1 = 1,
+    /// The second layout, of the instructions which have one.
+    V2 = 2,
+    /// The third layout, of the instructions which have one.
+    V3 = 3,
+    /// The fourth layout, of the instructions which have one.
+    V4 = 4,
+}
+
+impl Version {
+    /// Splits the version off the start of `data`.
+    ///
+    /// Fails with [`ProgramError::InvalidInstructionData`] if `data` is empty
+    /// or starts with an unknown version.
+    pub fn split(data: &[u8]) -> Result<(Self, &[u8]), ProgramError> {
+        match data.split_first() {
+            Some((1, data)) => Ok((Self::V1, data)),
+            Some((2, data)) => Ok((Self::V2, data)),
+            Some((3, data)) => Ok((Self::V3, data)),
+            Some((4, data)) => Ok((Self::V4, data)),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
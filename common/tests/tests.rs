@@ -0,0 +1,180 @@
+use common::{assert_layout, check_pda, keys_eq, metadata, version::Version, AccountData};
+use pinocchio::{program_error::ProgramError, pubkey::MAX_SEEDS};
+
+#[repr(C)]
+struct State {
+    owner: [u8; 32],
+    value: u64,
+}
+
+unsafe impl AccountData for State {
+    const OWNER: [u8; 32] = [1; 32];
+}
+
+#[repr(C)]
+struct TaggedState {
+    discriminator: u8,
+    _padding: [u8; 7],
+    value: u64,
+}
+
+unsafe impl AccountData for TaggedState {
+    const OWNER: [u8; 32] = [1; 32];
+    const DISCRIMINATOR: Option<u8> = Some(2);
+}
+
+#[repr(C)]
+struct PrefixedState {
+    value: u64,
+}
+
+unsafe impl AccountData for PrefixedState {
+    const OWNER: [u8; 32] = [1; 32];
+    const PREFIX: &'static [u8] = &[7; 8];
+}
+
+assert_layout!(TaggedState, size = 16, align = 8, {
+    discriminator: 0,
+    _padding: 1,
+    value: 8,
+});
+
+/// Account data aligned like in the runtime.
+#[repr(C, align(8))]
+struct Data([u8; 64]);
+
+#[test]
+fn test_len() {
+    assert_eq!(State::LEN, 40);
+    assert_eq!(TaggedState::LEN, 16);
+    assert_eq!(PrefixedState::LEN, 16);
+}
+
+#[test]
+fn test_validate() {
+    let data = Data([0; 64]);
+
+    assert_eq!(State::validate(&data.0[..40]), Ok(()));
+    for invalid in [&data.0[..39], &data.0[..41], &data.0[8..47], &data.0[1..41]] {
+        assert_eq!(
+            State::validate(invalid),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
+
+#[test]
+fn test_validate_discriminator() {
+    let mut data = Data([0; 64]);
+
+    assert_eq!(
+        TaggedState::validate(&data.0[..16]),
+        Err(ProgramError::InvalidAccountData)
+    );
+    data.0[0] = 2;
+    assert_eq!(TaggedState::validate(&data.0[..16]), Ok(()));
+}
+
+#[test]
+fn test_validate_prefix() {
+    let mut data = Data([0; 64]);
+    data.0[8..16].copy_from_slice(&42u64.to_le_bytes());
+
+    assert_eq!(
+        PrefixedState::validate(&data.0[..16]),
+        Err(ProgramError::InvalidAccountData)
+    );
+    data.0[..8].fill(7);
+    assert_eq!(PrefixedState::validate(&data.0[..16]), Ok(()));
+    assert_eq!(PrefixedState::read(&data.0[..16]).unwrap().value, 42);
+}
+
+#[test]
+fn test_read() {
+    let mut data = Data([0; 64]);
+    data.0[1..33].fill(3);
+    data.0[33..41].copy_from_slice(&42u64.to_le_bytes());
+
+    // Unaligned data is fine to read.
+    let state = State::read(&data.0[1..41]).unwrap();
+    assert_eq!(state.owner, [3; 32]);
+    assert_eq!(state.value, 42);
+
+    assert!(matches!(
+        State::read(&data.0[1..40]),
+        Err(ProgramError::InvalidAccountData)
+    ));
+    assert!(matches!(
+        TaggedState::read(&data.0[..16]),
+        Err(ProgramError::InvalidAccountData)
+    ));
+}
+
+#[test]
+fn test_check_pda_max_seeds() {
+    // The bump is one more seed.
+    let seeds = [b"seed".as_slice(); MAX_SEEDS];
+    assert_eq!(
+        check_pda(&seeds, 255, &[0; 32], &[1; 32]),
+        Err(ProgramError::MaxSeedLengthExceeded)
+    );
+}
+
+#[test]
+fn test_keys_eq() {
+    let key = core::array::from_fn(|i| i as u8);
+    assert!(keys_eq(&key, &key));
+    // Every byte counts, whichever word it's in.
+    for i in 0..32 {
+        let mut other = key;
+        other[i] ^= 0x80;
+        assert!(!keys_eq(&key, &other), "byte {i}");
+    }
+}
+
+#[test]
+fn test_version_split() {
+    assert_eq!(Version::split(&[1, 7]), Ok((Version::V1, [7].as_slice())));
+    assert_eq!(Version::split(&[2]), Ok((Version::V2, [].as_slice())));
+    assert_eq!(Version::split(&[3, 7]), Ok((Version::V3, [7].as_slice())));
+    assert_eq!(Version::split(&[4, 7]), Ok((Version::V4, [7].as_slice())));
+    for data in [&[][..], &[0], &[5, 7]] {
+        assert_eq!(
+            Version::split(data),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
+
+#[test]
+fn test_metadata_fields() {
+    let section = [
+        metadata::BEGIN,
+        "name\0counter\0",
+        "version\0\0",
+        "rustc\0rustc 1.84.1\0",
+        metadata::END,
+    ]
+    .concat();
+    let mut binary = b"\x7fELF\0\0".to_vec();
+    binary.extend_from_slice(section.as_bytes());
+    binary.extend_from_slice(&[0; 8]);
+
+    assert_eq!(
+        metadata::fields(&binary).unwrap().collect::<Vec<_>>(),
+        [
+            ("name", "counter"),
+            ("version", ""),
+            ("rustc", "rustc 1.84.1")
+        ]
+    );
+    assert!(metadata::fields(&binary[..binary.len() - 20]).is_none());
+    assert!(metadata::fields(b"\x7fELF").is_none());
+}
+
+#[test]
+fn test_metadata_concat() {
+    const PARTS: &[&str] = &["ab", "", "cde"];
+    const BYTES: [u8; metadata::len(PARTS)] = metadata::concat(PARTS);
+    assert_eq!(&BYTES, b"abcde");
+}
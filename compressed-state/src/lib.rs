@@ -0,0 +1,354 @@
+//! Compressed state: only a hash of the state is stored on-chain.
+//!
+//! Every owner has a state PDA holding the SHA-256 hash of their [`State`],
+//! and nothing else, so the account costs the same rent no matter how large
+//! the state is. The state itself lives off-chain. Instructions take the
+//! full prior state, which proves itself valid by hashing to the stored
+//! hash, apply a transition to it and store the hash of the new state.
+//!
+//! The new state is emitted with `sol_log_data`, so indexers can follow the
+//! state of every account from the transaction logs and serve it to clients
+//! for their next instruction.
+//!
+//! With a single state per account, the preimage of the hash is the whole
+//! validity proof. Storing many states under one hash is the next step,
+//! which is what the `compressed-tree` example does with a Merkle tree.
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::{mem, slice};
+
+use common::checks::{require_owned_by, require_signer};
+use common::close::close_account;
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    log::sol_log_data,
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("FAxetUzSvqjvdh3qF9wof6iPYCzVZgTmvpvZ87r2v8gQ");
+
+pub const STATE_SEED: &str = "state";
+
+/// Length of the state hash.
+pub const HASH_LEN: usize = 32;
+
+/// Errors returned by the compressed state program.
+#[repr(u32)]
+pub enum CompressedStateError {
+    /// The provided state doesn't hash to the stored hash.
+    StateMismatch,
+}
+
+impl From<CompressedStateError> for ProgramError {
+    fn from(e: CompressedStateError) -> Self {
+        Namespace::CompressedState.error(e as u32)
+    }
+}
+
+/// Off-chain state of an account.
+#[repr(C)]
+#[derive(Clone, Copy, InstructionData)]
+pub struct State {
+    pub owner: Pubkey,
+    /// Number of transitions applied to the state. Makes every state hash
+    /// unique, even if the value goes back to a previous one.
+    pub sequence: u64,
+    pub value: u64,
+}
+
+assert_layout!(State, size = 48, align = 8, { owner: 0, sequence: 32, value: 40 });
+
+impl State {
+    /// Returns the state of a newly initialized account.
+    pub fn new(owner: Pubkey) -> Self {
+        Self {
+            owner,
+            sequence: 0,
+            value: 0,
+        }
+    }
+
+    /// Returns the serialized state, which is hashed and emitted.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self as *const Self as *const u8, Self::LEN) }
+    }
+
+    /// Returns the hash of the state.
+    pub fn hash(&self) -> [u8; HASH_LEN] {
+        sha256v(&[self.as_bytes()])
+    }
+}
+
+/// On-chain representation of a compressed state account.
+#[repr(C)]
+pub struct CompressedState {
+    /// Hash of the current [`State`].
+    pub hash: [u8; HASH_LEN],
+}
+
+assert_layout!(CompressedState, size = 32, align = 1, { hash: 0 });
+
+impl CompressedState {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Compressed state program instruction discriminators.
+#[repr(u8)]
+pub enum CompressedStateInstruction {
+    /// Creates the state PDA of the owner with an empty state.
+    Initialize,
+    /// Adds to the value of the state.
+    Update,
+    /// Closes the state PDA.
+    Close,
+}
+
+impl TryFrom<&u8> for CompressedStateInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Update),
+            2 => Ok(Self::Close),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct UpdateInstructionData {
+    /// Current state, as emitted by the previous instruction.
+    pub state: State,
+    pub amount: u64,
+}
+
+impl UpdateInstructionData {
+    pub fn new(state: State, amount: u64) -> Self {
+        Self { state, amount }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct CloseInstructionData {
+    /// Current state, as emitted by the previous instruction.
+    pub state: State,
+}
+
+impl CloseInstructionData {
+    pub fn new(state: State) -> Self {
+        Self { state }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = CompressedStateInstruction::try_from(instruction)?;
+
+    match instruction {
+        CompressedStateInstruction::Initialize => process_initialize(accounts, instruction_data),
+        CompressedStateInstruction::Update => process_update(accounts, instruction_data),
+        CompressedStateInstruction::Close => process_close(accounts, instruction_data),
+    }
+}
+
+/// Creates the state PDA of `owner` and stores the hash of an empty state.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner
+///   1. `[WRITE]` State PDA of the owner
+///   2. `[]` System program
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, state_account, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    let state_pda = create_program_address(
+        &[STATE_SEED.as_bytes(), owner.key(), &[instruction_data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(state_account.key(), &state_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(STATE_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: owner,
+        to: state_account,
+        lamports: Rent::get()?.minimum_balance(CompressedState::LEN),
+        space: CompressedState::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    store(state_account, &State::new(*owner.key()))?;
+
+    info!("Initialized the state");
+
+    Ok(())
+}
+
+/// Adds `amount` to the value of the state of `owner`.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner
+///   1. `[WRITE]` State PDA of the owner
+pub fn process_update(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, state_account] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = UpdateInstructionData::from_bytes(instruction_data)?;
+
+    let state = &instruction_data.state;
+    validate(owner, state_account, state)?;
+
+    let value = state
+        .value
+        .checked_add(instruction_data.amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let new_state = State {
+        owner: state.owner,
+        sequence: state.sequence + 1,
+        value,
+    };
+    store(state_account, &new_state)?;
+
+    info!("Updated the state to {}", value);
+
+    Ok(())
+}
+
+/// Closes the state PDA of `owner`, returning the rent to them.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner
+///   1. `[WRITE]` State PDA of the owner
+pub fn process_close(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, state_account] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CloseInstructionData::from_bytes(instruction_data)?;
+
+    validate(owner, state_account, &instruction_data.state)?;
+
+    close_account(state_account, owner)?;
+
+    info!("Closed the state");
+
+    Ok(())
+}
+
+/// Checks that `state` is the current state of `state_account` and belongs
+/// to `owner`.
+fn validate(owner: &AccountInfo, state_account: &AccountInfo, state: &State) -> ProgramResult {
+    require_owned_by(state_account, &ID)?;
+
+    let data = state_account.try_borrow_data()?;
+    if data.len() != CompressedState::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let compressed_state: &CompressedState = unsafe { &*data.as_ptr().cast() };
+    if compressed_state.hash != state.hash() {
+        return Err(CompressedStateError::StateMismatch.into());
+    }
+
+    // The state PDA is derived from the owner, so the owner stored in its
+    // state is enough to tell whom it belongs to.
+    if !keys_eq(&state.owner, owner.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    Ok(())
+}
+
+/// Stores the hash of `state` in `state_account` and emits the state.
+fn store(state_account: &AccountInfo, state: &State) -> ProgramResult {
+    let mut data = state_account.try_borrow_mut_data()?;
+    let compressed_state: &mut CompressedState = unsafe { &mut *data.as_mut_ptr().cast() };
+    compressed_state.hash = state.hash();
+
+    sol_log_data(&[state_account.key(), state.as_bytes()]);
+
+    Ok(())
+}
+
+/// Computes the SHA-256 hash of the concatenation of `vals` using the
+/// `sol_sha256` syscall.
+pub fn sha256v(vals: &[&[u8]]) -> [u8; HASH_LEN] {
+    let mut hash = [0; HASH_LEN];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        );
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((vals, &mut hash));
+
+    hash
+}
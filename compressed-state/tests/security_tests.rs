@@ -0,0 +1,189 @@
+//! Attacks on the compressed state program, each rejected with its own
+//! error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use compressed_state::{
+    CloseInstructionData, CompressedState, CompressedStateInstruction, State,
+    UpdateInstructionData, STATE_SEED,
+};
+use errors::Namespace;
+use mollusk_svm::{result::Check, Mollusk};
+use sha2::{Digest, Sha256};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(compressed_state::ID);
+
+/// `CompressedStateError::StateMismatch`.
+const STATE_MISMATCH: u32 = Namespace::CompressedState.code(0);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: CompressedStateInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator =
+        vec![0; mem::size_of::<CompressedStateInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns an account of `owner` storing the hash of `state`.
+fn compressed_state_account(mollusk: &Mollusk, state: &State, owner: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(CompressedState::LEN),
+        CompressedState::LEN,
+        owner,
+    );
+    account.data = Sha256::digest(state.as_bytes()).to_vec();
+    account
+}
+
+/// Accounts of an owner with a state PDA after one update, and of an
+/// attacker who knows the state, as it's emitted.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    state: State,
+    state_account: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/compressed_state");
+
+        let owner = Pubkey::new_unique();
+        let state = State {
+            owner,
+            sequence: 1,
+            value: 5,
+        };
+        let (state_account, _) =
+            Pubkey::find_program_address(&[STATE_SEED.as_bytes(), owner.as_ref()], &ID);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (
+                state_account,
+                compressed_state_account(&mollusk, &state, &ID),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            state,
+            state_account,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Adds to the value of `state` in `state_account`, signed by `owner`.
+    fn instruction_update(
+        &self,
+        owner: &Pubkey,
+        state_account: &Pubkey,
+        state: State,
+    ) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*state_account, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                CompressedStateInstruction::Update,
+                &UpdateInstructionData::new(state, 1_000),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_update(&setup.owner, &setup.state_account, setup.state);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker updates the state PDA of the owner with a made up state
+/// that they own.
+#[test]
+fn test_made_up_state_of_attacker() {
+    let setup = Setup::new();
+    let state = State {
+        owner: setup.attacker,
+        ..setup.state
+    };
+    setup.expect_err(
+        &setup.instruction_update(&setup.attacker, &setup.state_account, state),
+        ProgramError::Custom(STATE_MISMATCH),
+    );
+}
+
+/// An account storing the hash of a made up state of the attacker, owned
+/// by another program.
+#[test]
+fn test_spoofed_state_account() {
+    let setup = Setup::new();
+    let state = State {
+        owner: setup.attacker,
+        sequence: 1,
+        value: u64::MAX - 1_000,
+    };
+    let state_account = compressed_state_account(&setup.mollusk, &state, &Pubkey::new_unique());
+    let address = Pubkey::new_unique();
+    let setup = setup.with(address, state_account);
+    setup.expect_err(
+        &setup.instruction_update(&setup.attacker, &address, state),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker closes the state PDA of the owner, whose state they know,
+/// to collect its rent.
+#[test]
+fn test_close_by_attacker() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(setup.state_account, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            CompressedStateInstruction::Close,
+            &CloseInstructionData::new(setup.state),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
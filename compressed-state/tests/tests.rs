@@ -0,0 +1,271 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use compressed_state::{
+    CloseInstructionData, CompressedState, CompressedStateInstruction, InitializeInstructionData,
+    State, UpdateInstructionData, STATE_SEED,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use sha2::{Digest, Sha256};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(compressed_state::ID);
+
+/// `CompressedStateError::StateMismatch`.
+const STATE_MISMATCH: u32 = Namespace::CompressedState.code(0);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: CompressedStateInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<CompressedStateInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+/// Computes the hash of `state` the same way as the program, with the
+/// host-side SHA-256.
+fn hash(state: &State) -> [u8; 32] {
+    Sha256::digest(state.as_bytes()).into()
+}
+
+struct Setup {
+    owner: Pubkey,
+    state: Pubkey,
+    bump: u8,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let owner = Pubkey::new_unique();
+        let (state, bump) =
+            Pubkey::find_program_address(&[STATE_SEED.as_bytes(), owner.as_ref()], &ID);
+        Self { owner, state, bump }
+    }
+
+    fn accounts(&self) -> Vec<(Pubkey, Account)> {
+        vec![
+            (
+                self.owner,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (self.state, Account::default()),
+            keyed_account_for_system_program(),
+        ]
+    }
+
+    fn instruction_initialize(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.state, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                CompressedStateInstruction::Initialize,
+                &InitializeInstructionData::new(self.bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_update(&self, state: State, amount: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.state, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                CompressedStateInstruction::Update,
+                &UpdateInstructionData::new(state, amount),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_close(&self, state: State) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.state, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                CompressedStateInstruction::Close,
+                &CloseInstructionData::new(state),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Returns the state after updates by `amounts`.
+    fn state(&self, amounts: &[u64]) -> State {
+        State {
+            owner: self.owner.to_bytes(),
+            sequence: amounts.len() as u64,
+            value: amounts.iter().sum(),
+        }
+    }
+}
+
+#[test]
+fn test_state_layout() {
+    // The layout is what indexers decode the emitted state with.
+    let state = State {
+        owner: [7; 32],
+        sequence: 1,
+        value: 42,
+    };
+    assert_eq!(State::LEN, 48);
+    assert_eq!(
+        state.as_bytes(),
+        [
+            [7; 32].as_slice(),
+            &1u64.to_le_bytes(),
+            &42u64.to_le_bytes()
+        ]
+        .concat()
+    );
+    assert_eq!(CompressedState::LEN, 32);
+}
+
+#[test]
+fn test_compressed_state() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/compressed_state");
+    let setup = Setup::new();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_initialize(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.state)
+                        .owner(&ID)
+                        .space(CompressedState::LEN)
+                        .data(&hash(&setup.state(&[])))
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_update(setup.state(&[]), 5),
+                &[
+                    Check::success(),
+                    Check::account(&setup.state)
+                        .data(&hash(&setup.state(&[5])))
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_update(setup.state(&[5]), 7),
+                &[
+                    Check::success(),
+                    Check::account(&setup.state)
+                        .data(&hash(&setup.state(&[5, 7])))
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_close(setup.state(&[5, 7])),
+                &[
+                    Check::success(),
+                    Check::account(&setup.state).closed().build(),
+                    Check::account(&setup.owner)
+                        .lamports(LAMPORTS_PER_SOL)
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts(),
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_compressed_state_invalid() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/compressed_state");
+    let setup = Setup::new();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&setup.instruction_initialize(), &[Check::success()]),
+            (
+                &setup.instruction_update(setup.state(&[]), 5),
+                &[Check::success()],
+            ),
+        ],
+        &setup.accounts(),
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+    for (ix, err) in [
+        // A stale state doesn't hash to the stored hash anymore.
+        (
+            setup.instruction_update(setup.state(&[]), 5),
+            ProgramError::Custom(STATE_MISMATCH),
+        ),
+        // Neither does a made up one.
+        (
+            setup.instruction_update(setup.state(&[500]), 5),
+            ProgramError::Custom(STATE_MISMATCH),
+        ),
+        (
+            setup.instruction_close(setup.state(&[5, 5])),
+            ProgramError::Custom(STATE_MISMATCH),
+        ),
+        (
+            setup.instruction_update(setup.state(&[5]), u64::MAX),
+            ProgramError::ArithmeticOverflow,
+        ),
+    ] {
+        mollusk.process_and_validate_instruction(&ix, &res.resulting_accounts, &[Check::err(err)]);
+    }
+
+    // The state is bound to the owner, so nobody else can update it, even
+    // when they know the state.
+    let attacker = Pubkey::new_unique();
+    let mut ix = setup.instruction_update(setup.state(&[]), 5);
+    ix.accounts[0].pubkey = attacker;
+    let mut accounts = setup.accounts();
+    accounts[0].0 = attacker;
+    accounts[1].1 = Account {
+        lamports: LAMPORTS_PER_SOL,
+        data: hash(&setup.state(&[])).to_vec(),
+        owner: ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    mollusk.process_and_validate_instruction(
+        &ix,
+        &accounts[..2],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+
+    // The state PDA has to be derived from the owner.
+    let mut ix = setup.instruction_initialize();
+    ix.accounts[1].pubkey = Pubkey::new_unique();
+    let mut accounts = setup.accounts();
+    accounts[1].0 = ix.accounts[1].pubkey;
+    mollusk.process_and_validate_instruction(
+        &ix,
+        &accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
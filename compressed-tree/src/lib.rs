@@ -0,0 +1,379 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo, cpi::invoke, instruction::Instruction, program_error::ProgramError,
+    pubkey::Pubkey, ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("AkAArNRSpLhEX1w1KZhdmqH8NPSHY9BC2S3gxJuuJY8Q");
+
+/// ID of the program from the `noop` example, used as a log sink for
+/// indexers.
+pub const NOOP_PROGRAM_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("4o3T9ZpyejfhzDBNFBLD4TrqH97u2mod5rdi7p8yavRr");
+
+/// Depth of the tree. The tree can hold `2^MAX_DEPTH` leaves.
+pub const MAX_DEPTH: usize = 20;
+/// Number of recent roots kept in the tree account. Proofs computed against
+/// any of them are still accepted, which lets multiple clients use the tree
+/// concurrently without their proofs getting invalidated by each other's
+/// appends.
+pub const ROOT_HISTORY_SIZE: usize = 64;
+
+/// Length of the event emitted through the noop program on every append.
+pub const APPEND_EVENT_LEN: usize = 32 + 8 + 4 + 32;
+
+/// Errors returned by the compressed tree program.
+#[repr(u32)]
+pub enum CompressedTreeError {
+    /// The tree account is already initialized.
+    AlreadyInitialized,
+    /// The tree has no space left for new leaves.
+    TreeFull,
+    /// The proof doesn't lead to any of the recent roots.
+    InvalidProof,
+    /// The leaf index is past the last appended leaf.
+    InvalidIndex,
+}
+
+impl From<CompressedTreeError> for ProgramError {
+    fn from(e: CompressedTreeError) -> Self {
+        Namespace::CompressedTree.error(e as u32)
+    }
+}
+
+/// On-chain representation of an append-only Merkle tree.
+///
+/// Only the data needed to append new leaves is stored: the hashes of empty
+/// subtrees, the left-hand nodes of the rightmost path and a ring buffer of
+/// recent roots. The leaves themselves live off-chain and are reconstructed
+/// by indexers from the noop program logs.
+#[repr(C)]
+pub struct MerkleTree {
+    pub authority: Pubkey,
+    pub is_initialized: u8,
+    pub _padding: [u8; 3],
+    /// Index of the next leaf to append.
+    pub next_index: u32,
+    /// Number of appends since initialization.
+    pub sequence: u64,
+    /// Position of the current root in `roots`.
+    pub root_index: u64,
+    /// `zeros[i]` is the root of an empty subtree of height `i`.
+    pub zeros: [[u8; 32]; MAX_DEPTH],
+    /// `filled_subtrees[i]` is the last left-hand node at height `i`.
+    pub filled_subtrees: [[u8; 32]; MAX_DEPTH],
+    /// Ring buffer of recent roots.
+    pub roots: [[u8; 32]; ROOT_HISTORY_SIZE],
+}
+
+assert_layout!(MerkleTree, size = 3384, align = 8, {
+    authority: 0,
+    is_initialized: 32,
+    _padding: 33,
+    next_index: 36,
+    sequence: 40,
+    root_index: 48,
+    zeros: 56,
+    filled_subtrees: 696,
+    roots: 1336,
+});
+
+impl MerkleTree {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Returns the current root.
+    pub fn root(&self) -> &[u8; 32] {
+        &self.roots[self.root_index as usize]
+    }
+
+    /// Checks whether `root` is one of the recent roots.
+    pub fn is_recent_root(&self, root: &[u8; 32]) -> bool {
+        let recent = (self.sequence as usize + 1).min(ROOT_HISTORY_SIZE);
+        (0..recent).any(|i| {
+            let index = (self.root_index as usize + ROOT_HISTORY_SIZE - i) % ROOT_HISTORY_SIZE;
+            &self.roots[index] == root
+        })
+    }
+}
+
+/// Compressed tree program instruction discriminators.
+#[repr(u8)]
+pub enum CompressedTreeInstruction {
+    /// Initializes a pre-allocated tree account.
+    Initialize,
+    /// Appends a leaf to the tree.
+    Append,
+    /// Verifies that a leaf is included in one of the recent roots.
+    VerifyLeaf,
+}
+
+impl TryFrom<&u8> for CompressedTreeInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Append),
+            2 => Ok(Self::VerifyLeaf),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AppendInstructionData {
+    pub leaf: [u8; 32],
+}
+
+impl AppendInstructionData {
+    pub fn new(leaf: [u8; 32]) -> Self {
+        Self { leaf }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct VerifyLeafInstructionData {
+    pub index: u32,
+    pub leaf: [u8; 32],
+    pub proof: [[u8; 32]; MAX_DEPTH],
+}
+
+impl VerifyLeafInstructionData {
+    pub fn new(index: u32, leaf: [u8; 32], proof: [[u8; 32]; MAX_DEPTH]) -> Self {
+        Self { index, leaf, proof }
+    }
+}
+
+/// Computes the SHA-256 hash of the concatenation of `vals` using the
+/// `sol_sha256` syscall.
+pub fn hashv(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash = [0; 32];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        );
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((vals, &mut hash));
+
+    hash
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = CompressedTreeInstruction::try_from(instruction)?;
+
+    match instruction {
+        CompressedTreeInstruction::Initialize => process_initialize(accounts),
+        CompressedTreeInstruction::Append => process_append(accounts, instruction_data),
+        CompressedTreeInstruction::VerifyLeaf => process_verify_leaf(accounts, instruction_data),
+    }
+}
+
+/// Initializes a tree account.
+///
+/// The account has to be created beforehand with [`MerkleTree::LEN`] bytes of
+/// space and this program as the owner. Accounts bigger than 10 KiB can't be
+/// created through CPI, so allocating them is the client's job, like in
+/// spl-account-compression.
+pub fn process_initialize(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, tree] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+    // Check if the tree account is owned by the program.
+    require_owned_by(tree, &ID)?;
+
+    // Deserialize the tree account.
+    let mut data = tree.try_borrow_mut_data()?;
+    if data.len() != MerkleTree::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data: &mut MerkleTree = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    if data.is_initialized != 0 {
+        return Err(CompressedTreeError::AlreadyInitialized.into());
+    }
+
+    // Initialize the tree.
+    data.authority = *authority.key();
+    data.is_initialized = 1;
+    data.next_index = 0;
+    data.sequence = 0;
+    data.root_index = 0;
+
+    // Precompute the hashes of empty subtrees. The root of an empty tree is
+    // the hash of an empty subtree of `MAX_DEPTH` height.
+    let mut zero = [0; 32];
+    for i in 0..MAX_DEPTH {
+        data.zeros[i] = zero;
+        data.filled_subtrees[i] = zero;
+        zero = hashv(&[&zero, &zero]);
+    }
+    data.roots[0] = zero;
+
+    info!("Initialized tree with depth {}", MAX_DEPTH);
+
+    Ok(())
+}
+
+/// Appends a leaf to the tree and emits it through the noop program.
+pub fn process_append(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, tree, noop_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+    // Check if the tree account is owned by the program.
+    require_owned_by(tree, &ID)?;
+    if !keys_eq(noop_program.key(), &NOOP_PROGRAM_ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = AppendInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the tree account.
+    let mut data = tree.try_borrow_mut_data()?;
+    if data.len() != MerkleTree::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data: &mut MerkleTree = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Check if the tree was created by the `authority`.
+    if data.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if !keys_eq(&data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let index = data.next_index;
+    if index as u64 >= 1 << MAX_DEPTH {
+        return Err(CompressedTreeError::TreeFull.into());
+    }
+
+    // Walk up the rightmost path. When the current node is a left child, its
+    // sibling is an empty subtree and the node becomes the new filled
+    // subtree. Otherwise its sibling is the last filled subtree.
+    let mut node = instruction_data.leaf;
+    for level in 0..MAX_DEPTH {
+        if (index >> level) & 1 == 0 {
+            data.filled_subtrees[level] = node;
+            node = hashv(&[&node, &data.zeros[level]]);
+        } else {
+            node = hashv(&[&data.filled_subtrees[level], &node]);
+        }
+    }
+
+    // Store the new root.
+    data.root_index = (data.root_index + 1) % ROOT_HISTORY_SIZE as u64;
+    data.roots[data.root_index as usize] = node;
+    data.next_index += 1;
+    data.sequence += 1;
+
+    // Emit the change, so indexers can reconstruct the leaves. Logging
+    // through a CPI instead of `sol_log_data` keeps the event in the
+    // transaction's inner instructions, which are not truncated like logs.
+    let mut event = [0; APPEND_EVENT_LEN];
+    event[0..32].copy_from_slice(tree.key());
+    event[32..40].copy_from_slice(&data.sequence.to_le_bytes());
+    event[40..44].copy_from_slice(&index.to_le_bytes());
+    event[44..76].copy_from_slice(&instruction_data.leaf);
+    invoke::<0>(
+        &Instruction {
+            program_id: noop_program.key(),
+            accounts: &[],
+            data: &event,
+        },
+        &[],
+    )?;
+
+    info!("Appended leaf {}", index);
+
+    Ok(())
+}
+
+/// Verifies that a leaf is included in one of the recent roots.
+pub fn process_verify_leaf(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [tree] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Check if the tree account is owned by the program.
+    require_owned_by(tree, &ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = VerifyLeafInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the tree account.
+    let data = tree.try_borrow_data()?;
+    if data.len() != MerkleTree::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data: &MerkleTree = unsafe { &*data.as_ptr().cast() };
+
+    if data.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // Only appended leaves can be verified. Unused indices hold the empty
+    // leaf, which has a valid proof too, and the proof only covers the low
+    // `MAX_DEPTH` bits of the index.
+    if instruction_data.index >= data.next_index {
+        return Err(CompressedTreeError::InvalidIndex.into());
+    }
+
+    // Compute the root from the leaf and its proof.
+    let mut node = instruction_data.leaf;
+    for (level, sibling) in instruction_data.proof.iter().enumerate() {
+        node = if (instruction_data.index >> level) & 1 == 0 {
+            hashv(&[&node, sibling])
+        } else {
+            hashv(&[sibling, &node])
+        };
+    }
+
+    if !data.is_recent_root(&node) {
+        return Err(CompressedTreeError::InvalidProof.into());
+    }
+
+    info!("Verified leaf {}", instruction_data.index);
+
+    Ok(())
+}
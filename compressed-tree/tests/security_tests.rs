@@ -0,0 +1,204 @@
+//! Attacks on the compressed tree program, each rejected with its own error.
+
+use std::mem;
+
+use compressed_tree::{
+    AppendInstructionData, CompressedTreeInstruction, MerkleTree, VerifyLeafInstructionData,
+    MAX_DEPTH,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use sha2::{Digest, Sha256};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(compressed_tree::ID);
+const NOOP_ID: Pubkey = Pubkey::new_from_array(compressed_tree::NOOP_PROGRAM_ID);
+
+/// `CompressedTreeError::AlreadyInitialized`.
+const ALREADY_INITIALIZED: u32 = Namespace::CompressedTree.code(0);
+
+/// Offset of the `roots` ring buffer in the tree account.
+const ROOTS_OFFSET: usize = 56 + 2 * MAX_DEPTH * 32;
+
+/// Returns the proof of the first leaf of a tree without other leaves,
+/// which consists of the hashes of empty subtrees.
+fn first_leaf_proof() -> [[u8; 32]; MAX_DEPTH] {
+    let mut zero = [0; 32];
+    let mut proof = [[0; 32]; MAX_DEPTH];
+    for sibling in proof.iter_mut() {
+        *sibling = zero;
+        zero = Sha256::new()
+            .chain_update(zero)
+            .chain_update(zero)
+            .finalize()
+            .into();
+    }
+    proof
+}
+
+/// Returns the root of a tree with `leaf` as its only leaf.
+fn first_leaf_root(leaf: [u8; 32]) -> [u8; 32] {
+    first_leaf_proof().iter().fold(leaf, |node, sibling| {
+        Sha256::new()
+            .chain_update(node)
+            .chain_update(sibling)
+            .finalize()
+            .into()
+    })
+}
+
+/// Returns an initialized tree of `authority` with `next_index` leaves and
+/// `root` as its current root.
+fn tree_account(mollusk: &Mollusk, authority: &Pubkey, next_index: u32, root: [u8; 32]) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(MerkleTree::LEN),
+        MerkleTree::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(authority.as_ref());
+    account.data[32] = 1;
+    account.data[36..40].copy_from_slice(&next_index.to_le_bytes());
+    account.data[ROOTS_OFFSET..ROOTS_OFFSET + 32].copy_from_slice(&root);
+    account
+}
+
+/// Accounts of an empty tree of an authority, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    tree: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/compressed_tree");
+        // Built with `cargo build-sbf` in the `noop` directory.
+        mollusk.add_program(&NOOP_ID, "../noop/target/deploy/noop", &LOADER_V3);
+
+        let authority = Pubkey::new_unique();
+        let tree = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (authority, Account::default()),
+            (
+                tree,
+                tree_account(&mollusk, &authority, 0, first_leaf_root([0; 32])),
+            ),
+            (attacker, Account::default()),
+            (NOOP_ID, create_program_account_loader_v3(&NOOP_ID)),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            tree,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Appends a leaf to the tree through `noop_program`, signed by the
+    /// authority.
+    fn instruction_append(&self, noop_program: &Pubkey) -> Instruction {
+        let mut data = Vec::with_capacity(
+            mem::size_of::<CompressedTreeInstruction>() + AppendInstructionData::LEN,
+        );
+        data.push(CompressedTreeInstruction::Append as u8);
+        data.extend_from_slice(&AppendInstructionData::new([1; 32]).to_bytes());
+
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.authority, true),
+            AccountMeta::new(self.tree, false),
+            AccountMeta::new_readonly(*noop_program, false),
+        ];
+        Instruction::new_with_bytes(ID, &data, ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_append(&NOOP_ID);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker initializes the tree of the authority again, to become its
+/// authority and reset its leaves.
+#[test]
+fn test_reinitialize_by_attacker() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.attacker, true),
+        AccountMeta::new(setup.tree, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &[CompressedTreeInstruction::Initialize as u8],
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::Custom(ALREADY_INITIALIZED));
+}
+
+/// The leaf is emitted through a program posing as the noop program, which
+/// drops it, so indexers can't reconstruct the tree.
+#[test]
+fn test_fake_noop_program() {
+    let setup = Setup::new();
+    let noop_program = Pubkey::new_unique();
+    let setup = setup.with(noop_program, Account::default());
+    setup.expect_err(
+        &setup.instruction_append(&noop_program),
+        ProgramError::IncorrectProgramId,
+    );
+}
+
+/// A tree of the authority with a made up leaf of the attacker, owned by
+/// another program.
+#[test]
+fn test_spoofed_tree() {
+    let setup = Setup::new();
+    let leaf = [42; 32];
+    let mut tree_account = tree_account(&setup.mollusk, &setup.authority, 1, first_leaf_root(leaf));
+    tree_account.owner = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+    let setup = setup.with(tree, tree_account);
+
+    let data = VerifyLeafInstructionData::new(0, leaf, first_leaf_proof());
+    let mut data_with_discriminator = Vec::with_capacity(
+        mem::size_of::<CompressedTreeInstruction>() + VerifyLeafInstructionData::LEN,
+    );
+    data_with_discriminator.push(CompressedTreeInstruction::VerifyLeaf as u8);
+    data_with_discriminator.extend_from_slice(&data.to_bytes());
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &data_with_discriminator,
+        vec![AccountMeta::new_readonly(tree, false)],
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
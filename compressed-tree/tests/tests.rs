@@ -0,0 +1,366 @@
+use std::mem;
+
+use compressed_tree::{
+    AppendInstructionData, CompressedTreeError, CompressedTreeInstruction, MerkleTree,
+    VerifyLeafInstructionData, MAX_DEPTH,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use sha2::{Digest, Sha256};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(compressed_tree::ID);
+const NOOP_ID: Pubkey = Pubkey::new_from_array(compressed_tree::NOOP_PROGRAM_ID);
+
+/// Offset of the `roots` ring buffer in the tree account.
+const ROOTS_OFFSET: usize = 56 + 2 * MAX_DEPTH * 32;
+
+fn hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Host-side reference implementation of the tree, keeping all leaves.
+struct ReferenceTree {
+    leaves: Vec<[u8; 32]>,
+}
+
+impl ReferenceTree {
+    fn new() -> Self {
+        Self { leaves: Vec::new() }
+    }
+
+    /// Returns all levels of the tree, from the leaves up to the root, with
+    /// missing nodes filled with empty subtree hashes.
+    fn levels(&self) -> Vec<Vec<[u8; 32]>> {
+        let mut zero = [0; 32];
+        let mut levels = vec![self.leaves.clone()];
+        for _ in 0..MAX_DEPTH {
+            let level = levels.last().unwrap();
+            let next = level
+                .chunks(2)
+                .map(|pair| hash(&pair[0], pair.get(1).unwrap_or(&zero)))
+                .collect();
+            levels.push(next);
+            zero = hash(&zero, &zero);
+        }
+        levels
+    }
+
+    fn root(&self) -> [u8; 32] {
+        let mut zero = [0; 32];
+        for _ in 0..MAX_DEPTH {
+            zero = hash(&zero, &zero);
+        }
+        self.levels()[MAX_DEPTH].first().copied().unwrap_or(zero)
+    }
+
+    fn proof(&self, index: usize) -> [[u8; 32]; MAX_DEPTH] {
+        let levels = self.levels();
+        let mut zero = [0; 32];
+        let mut proof = [[0; 32]; MAX_DEPTH];
+        for (level, sibling) in proof.iter_mut().enumerate() {
+            let sibling_index = (index >> level) ^ 1;
+            *sibling = levels[level].get(sibling_index).copied().unwrap_or(zero);
+            zero = hash(&zero, &zero);
+        }
+        proof
+    }
+}
+
+fn instruction_initialize(authority: &Pubkey, tree: &Pubkey) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*tree, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &[CompressedTreeInstruction::Initialize as u8],
+        ix_accounts,
+    )
+}
+
+fn instruction_append(leaf: [u8; 32], authority: &Pubkey, tree: &Pubkey) -> Instruction {
+    // Create instruction data.
+    let data = AppendInstructionData::new(leaf);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
+        mem::size_of::<CompressedTreeInstruction>() + AppendInstructionData::LEN,
+    );
+    data_with_discriminator.push(CompressedTreeInstruction::Append as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*tree, false),
+        AccountMeta::new_readonly(NOOP_ID, false),
+    ];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+fn instruction_verify_leaf(
+    index: u32,
+    leaf: [u8; 32],
+    proof: [[u8; 32]; MAX_DEPTH],
+    tree: &Pubkey,
+) -> Instruction {
+    // Create instruction data.
+    let data = VerifyLeafInstructionData::new(index, leaf, proof);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
+        mem::size_of::<CompressedTreeInstruction>() + VerifyLeafInstructionData::LEN,
+    );
+    data_with_discriminator.push(CompressedTreeInstruction::VerifyLeaf as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![AccountMeta::new_readonly(*tree, false)];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/compressed_tree");
+    // Built with `cargo build-sbf` in the `noop` directory.
+    mollusk.add_program(&NOOP_ID, "../noop/target/deploy/noop", &LOADER_V3);
+    mollusk
+}
+
+/// Creates an empty tree account, like a client would with a top-level
+/// `CreateAccount` instruction.
+fn tree_account(mollusk: &Mollusk) -> Account {
+    Account::new(
+        mollusk.sysvars.rent.minimum_balance(MerkleTree::LEN),
+        MerkleTree::LEN,
+        &ID,
+    )
+}
+
+fn leaf(i: u8) -> [u8; 32] {
+    Sha256::digest([i]).into()
+}
+
+#[test]
+fn test_compressed_tree_initialize() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+
+    let empty_root = ReferenceTree::new().root();
+    let res = mollusk.process_and_validate_instruction(
+        &instruction_initialize(&authority, &tree),
+        &[
+            (authority, Account::default()),
+            (tree, tree_account(&mollusk)),
+        ],
+        &[
+            Check::success(),
+            Check::account(&tree)
+                .data_slice(0, authority.as_ref())
+                .data_slice(ROOTS_OFFSET, &empty_root)
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_compressed_tree_append_success() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+
+    let mut reference = ReferenceTree::new();
+    let mut instructions = vec![instruction_initialize(&authority, &tree)];
+    let mut roots = vec![reference.root()];
+    for i in 0..5 {
+        instructions.push(instruction_append(leaf(i), &authority, &tree));
+        reference.leaves.push(leaf(i));
+        roots.push(reference.root());
+    }
+
+    let checks = roots
+        .iter()
+        .enumerate()
+        .map(|(i, root)| {
+            vec![
+                Check::success(),
+                Check::account(&tree)
+                    .data_slice(ROOTS_OFFSET + i * 32, root)
+                    .build(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let chain = instructions
+        .iter()
+        .zip(checks.iter())
+        .map(|(ix, checks)| (ix, checks.as_slice()))
+        .collect::<Vec<_>>();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &chain,
+        &[
+            (authority, Account::default()),
+            (tree, tree_account(&mollusk)),
+            (NOOP_ID, create_program_account_loader_v3(&NOOP_ID)),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_compressed_tree_append_wrong_authority() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+
+    mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize(&authority, &tree),
+                &[Check::success()],
+            ),
+            (
+                &instruction_append(leaf(0), &attacker, &tree),
+                &[Check::err(ProgramError::IllegalOwner)],
+            ),
+        ],
+        &[
+            (authority, Account::default()),
+            (attacker, Account::default()),
+            (tree, tree_account(&mollusk)),
+            (NOOP_ID, create_program_account_loader_v3(&NOOP_ID)),
+        ],
+    );
+}
+
+#[test]
+fn test_compressed_tree_verify_stale_proof() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+
+    // The proof for leaf 1 is computed when the tree has 2 leaves...
+    let mut reference = ReferenceTree::new();
+    reference.leaves.extend([leaf(0), leaf(1)]);
+    let proof = reference.proof(1);
+
+    // ...and is still accepted after more leaves get appended, since the
+    // root it leads to is still in the root history.
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize(&authority, &tree),
+                &[Check::success()],
+            ),
+            (
+                &instruction_append(leaf(0), &authority, &tree),
+                &[Check::success()],
+            ),
+            (
+                &instruction_append(leaf(1), &authority, &tree),
+                &[Check::success()],
+            ),
+            (
+                &instruction_append(leaf(2), &authority, &tree),
+                &[Check::success()],
+            ),
+            (
+                &instruction_verify_leaf(1, leaf(1), proof, &tree),
+                &[Check::success()],
+            ),
+            (
+                &instruction_verify_leaf(1, leaf(42), proof, &tree),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::CompressedTree.code(CompressedTreeError::InvalidProof as u32),
+                ))],
+            ),
+        ],
+        &[
+            (authority, Account::default()),
+            (tree, tree_account(&mollusk)),
+            (NOOP_ID, create_program_account_loader_v3(&NOOP_ID)),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_compressed_tree_verify_unused_index() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+
+    let mut reference = ReferenceTree::new();
+    reference.leaves.extend([leaf(0), leaf(1)]);
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize(&authority, &tree),
+                &[Check::success()],
+            ),
+            (
+                &instruction_append(leaf(0), &authority, &tree),
+                &[Check::success()],
+            ),
+            (
+                &instruction_append(leaf(1), &authority, &tree),
+                &[Check::success()],
+            ),
+        ],
+        &[
+            (authority, Account::default()),
+            (tree, tree_account(&mollusk)),
+            (NOOP_ID, create_program_account_loader_v3(&NOOP_ID)),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+
+    let invalid_index = [Check::err(ProgramError::Custom(
+        Namespace::CompressedTree.code(CompressedTreeError::InvalidIndex as u32),
+    ))];
+    // The empty leaf at the next index leads to the current root, but
+    // wasn't appended.
+    mollusk.process_and_validate_instruction(
+        &instruction_verify_leaf(2, [0; 32], reference.proof(2), &tree),
+        &res.resulting_accounts,
+        &invalid_index,
+    );
+    // Only the low `MAX_DEPTH` bits of the index select the path, so
+    // `2^MAX_DEPTH` would pass for leaf 0.
+    mollusk.process_and_validate_instruction(
+        &instruction_verify_leaf(1 << MAX_DEPTH, leaf(0), reference.proof(0), &tree),
+        &res.resulting_accounts,
+        &invalid_index,
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction_verify_leaf(0, leaf(0), reference.proof(0), &tree),
+        &res.resulting_accounts,
+        &[Check::success()],
+    );
+}
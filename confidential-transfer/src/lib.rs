@@ -0,0 +1,430 @@
+//! Confidential transfers with the token-2022 program.
+//!
+//! The program creates a mint with the confidential transfer extension and
+//! wraps the flow which moves tokens into the confidential balance of an
+//! account: configuring the account, depositing from its public balance and
+//! applying the pending balance.
+//!
+//! Everything involving the secret keys of the account owner has to happen
+//! client-side, the program only forwards the results:
+//!
+//! * The ElGamal keypair and the AES key are derived from signatures of the
+//!   owner and never leave the client.
+//! * `ConfigureAccount` needs a `VerifyPubkeyValidity` proof of the ElGamal
+//!   public key, generated by the client and verified by the ZK ElGamal
+//!   proof program. The proof instruction has to directly follow the
+//!   `ConfigureAccount` instruction of this program in the transaction.
+//! * The decryptable balances are encrypted with the AES key. To apply the
+//!   pending balance, the client decrypts it with the ElGamal secret key and
+//!   encrypts the new available balance.
+//! * Transfers and withdrawals need range and equality proofs, which are
+//!   out of scope of this example.
+
+#![no_std]
+
+use common::{checks::require_signer, info, keys_eq};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::token_2022::{
+    ApplyPendingBalance, ConfigureAccount, Deposit, InitializeConfidentialTransferMint,
+    InitializeMint2, MintTo, Reallocate, AE_CIPHERTEXT_LEN, CONFIDENTIAL_TRANSFER_ACCOUNT,
+    MINT_WITH_CONFIDENTIAL_TRANSFER_LEN,
+};
+
+pub mod token_2022;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("GDpcWUGgFpdcfDDodPZ65sn5j5LTB1HjRSAmqXB8eDiG");
+
+pub const MINT_AUTHORITY_SEED: &str = "mint_authority";
+
+/// Position of the `VerifyPubkeyValidity` proof instruction relative to the
+/// `ConfigureAccount` instruction.
+pub const PROOF_INSTRUCTION_OFFSET: i8 = 1;
+
+/// Confidential transfer program instruction discriminators.
+#[repr(u8)]
+pub enum ConfidentialTransferInstruction {
+    /// Creates a mint with the confidential transfer extension.
+    InitializeMint,
+    /// Mints tokens to the public balance of an account.
+    MintTo,
+    /// Configures an account for confidential transfers.
+    ConfigureAccount,
+    /// Deposits tokens from the public balance of an account to its pending
+    /// confidential balance.
+    Deposit,
+    /// Applies the pending confidential balance of an account.
+    ApplyPendingBalance,
+}
+
+impl TryFrom<&u8> for ConfidentialTransferInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeMint),
+            1 => Ok(Self::MintTo),
+            2 => Ok(Self::ConfigureAccount),
+            3 => Ok(Self::Deposit),
+            4 => Ok(Self::ApplyPendingBalance),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeMintInstructionData {
+    pub decimals: u8,
+    pub bump: u8,
+}
+
+impl InitializeMintInstructionData {
+    pub fn new(decimals: u8, bump: u8) -> Self {
+        Self { decimals, bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct MintToInstructionData {
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl MintToInstructionData {
+    pub fn new(amount: u64, bump: u8) -> Self {
+        Self { amount, bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct ConfigureAccountInstructionData {
+    pub maximum_pending_balance_credit_counter: u64,
+    /// Encryption of zero under the AES key of the owner.
+    pub decryptable_zero_balance: [u8; AE_CIPHERTEXT_LEN],
+}
+
+impl ConfigureAccountInstructionData {
+    pub fn new(
+        maximum_pending_balance_credit_counter: u64,
+        decryptable_zero_balance: [u8; AE_CIPHERTEXT_LEN],
+    ) -> Self {
+        Self {
+            maximum_pending_balance_credit_counter,
+            decryptable_zero_balance,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct DepositInstructionData {
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+impl DepositInstructionData {
+    pub fn new(amount: u64, decimals: u8) -> Self {
+        Self { amount, decimals }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct ApplyPendingBalanceInstructionData {
+    pub expected_pending_balance_credit_counter: u64,
+    /// New available balance, encrypted under the AES key of the owner.
+    pub new_decryptable_available_balance: [u8; AE_CIPHERTEXT_LEN],
+}
+
+impl ApplyPendingBalanceInstructionData {
+    pub fn new(
+        expected_pending_balance_credit_counter: u64,
+        new_decryptable_available_balance: [u8; AE_CIPHERTEXT_LEN],
+    ) -> Self {
+        Self {
+            expected_pending_balance_credit_counter,
+            new_decryptable_available_balance,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = ConfidentialTransferInstruction::try_from(instruction)?;
+
+    match instruction {
+        ConfidentialTransferInstruction::InitializeMint => {
+            process_initialize_mint(accounts, instruction_data)
+        }
+        ConfidentialTransferInstruction::MintTo => process_mint_to(accounts, instruction_data),
+        ConfidentialTransferInstruction::ConfigureAccount => {
+            process_configure_account(accounts, instruction_data)
+        }
+        ConfidentialTransferInstruction::Deposit => process_deposit(accounts, instruction_data),
+        ConfidentialTransferInstruction::ApplyPendingBalance => {
+            process_apply_pending_balance(accounts, instruction_data)
+        }
+    }
+}
+
+/// Creates a mint with the confidential transfer extension. The mint
+/// authority PDA of `admin` is both the mint authority and the confidential
+/// transfer authority. New accounts are approved automatically and there is
+/// no auditor.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[WRITE, SIGNER]` Mint
+///   2. `[]` Mint authority PDA
+///   3. `[]` System program
+///   4. `[]` Token-2022 program
+pub fn process_initialize_mint(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, mint, mint_authority, _system_program, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeMintInstructionData::from_bytes(instruction_data)?;
+
+    validate_mint_authority(admin, mint, mint_authority, instruction_data.bump)?;
+
+    CreateAccount {
+        from: admin,
+        to: mint,
+        lamports: Rent::get()?.minimum_balance(MINT_WITH_CONFIDENTIAL_TRANSFER_LEN),
+        space: MINT_WITH_CONFIDENTIAL_TRANSFER_LEN as u64,
+        owner: &token_2022::ID,
+    }
+    .invoke()?;
+
+    // Extensions have to be initialized before the mint itself.
+    InitializeConfidentialTransferMint {
+        mint,
+        authority: Some(mint_authority.key()),
+        auto_approve_new_accounts: true,
+        auditor_elgamal_pubkey: None,
+    }
+    .invoke()?;
+
+    InitializeMint2 {
+        mint,
+        decimals: instruction_data.decimals,
+        mint_authority: mint_authority.key(),
+        freeze_authority: None,
+    }
+    .invoke()?;
+
+    info!("Initialized a confidential mint");
+
+    Ok(())
+}
+
+/// Mints `amount` tokens to the public balance of `token_account`.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Admin
+///   1. `[WRITE]` Mint
+///   2. `[WRITE]` Token account
+///   3. `[]` Mint authority PDA
+///   4. `[]` Token-2022 program
+pub fn process_mint_to(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, mint, token_account, mint_authority, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = MintToInstructionData::from_bytes(instruction_data)?;
+
+    validate_mint_authority(admin, mint, mint_authority, instruction_data.bump)?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(MINT_AUTHORITY_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(admin.key()),
+        Seed::from(&bump),
+    ];
+    MintTo {
+        mint,
+        account: token_account,
+        mint_authority,
+        amount: instruction_data.amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Minted {} tokens", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Makes space for the confidential transfer extension in `token_account`
+/// and configures it. The `VerifyPubkeyValidity` proof instruction has to
+/// directly follow this instruction.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner of the token account
+///   1. `[WRITE]` Token account
+///   2. `[]` Mint
+///   3. `[]` Instructions sysvar
+///   4. `[]` System program
+///   5. `[]` Token-2022 program
+pub fn process_configure_account(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, token_account, mint, instructions_sysvar, system_program, _token_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = ConfigureAccountInstructionData::from_bytes(instruction_data)?;
+
+    // The owner pays for the extra space.
+    Reallocate {
+        account: token_account,
+        payer: owner,
+        system_program,
+        owner,
+        extension_type: CONFIDENTIAL_TRANSFER_ACCOUNT,
+    }
+    .invoke()?;
+
+    // The token program reads the proof from the instructions sysvar, so
+    // the offset is relative to the instruction of this program.
+    ConfigureAccount {
+        account: token_account,
+        mint,
+        instructions_sysvar,
+        owner,
+        decryptable_zero_balance: &instruction_data.decryptable_zero_balance,
+        maximum_pending_balance_credit_counter: instruction_data
+            .maximum_pending_balance_credit_counter,
+        proof_instruction_offset: PROOF_INSTRUCTION_OFFSET,
+    }
+    .invoke()?;
+
+    info!("Configured the account for confidential transfers");
+
+    Ok(())
+}
+
+/// Deposits `amount` tokens from the public balance of `token_account` to
+/// its pending confidential balance.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner of the token account
+///   1. `[WRITE]` Token account
+///   2. `[]` Mint
+///   3. `[]` Token-2022 program
+pub fn process_deposit(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, token_account, mint, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = DepositInstructionData::from_bytes(instruction_data)?;
+
+    Deposit {
+        account: token_account,
+        mint,
+        owner,
+        amount: instruction_data.amount,
+        decimals: instruction_data.decimals,
+    }
+    .invoke()?;
+
+    info!("Deposited {} tokens", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Applies the pending confidential balance of `token_account` to its
+/// available confidential balance.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner of the token account
+///   1. `[WRITE]` Token account
+///   2. `[]` Token-2022 program
+pub fn process_apply_pending_balance(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, token_account, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = ApplyPendingBalanceInstructionData::from_bytes(instruction_data)?;
+
+    ApplyPendingBalance {
+        account: token_account,
+        owner,
+        expected_pending_balance_credit_counter: instruction_data
+            .expected_pending_balance_credit_counter,
+        new_decryptable_available_balance: &instruction_data.new_decryptable_available_balance,
+    }
+    .invoke()?;
+
+    info!("Applied the pending balance");
+
+    Ok(())
+}
+
+/// Checks that `mint_authority` is the mint authority PDA of `admin` for
+/// `mint`.
+fn validate_mint_authority(
+    admin: &AccountInfo,
+    mint: &AccountInfo,
+    mint_authority: &AccountInfo,
+    bump: u8,
+) -> ProgramResult {
+    let mint_authority_pda = create_program_address(
+        &[
+            MINT_AUTHORITY_SEED.as_bytes(),
+            mint.key(),
+            admin.key(),
+            &[bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(mint_authority.key(), &mint_authority_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
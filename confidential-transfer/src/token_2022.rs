@@ -0,0 +1,476 @@
+//! Instructions and account layout of the token-2022 program.
+//!
+//! `pinocchio-token` only supports the original token program, so the CPIs
+//! are built by hand, the same way `pinocchio-token` does it.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+/// Token-2022 program ID.
+pub const ID: Pubkey = pinocchio_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Size of a token account without extensions. Mints with extensions are
+/// padded to the same size, so the account type byte and the extensions
+/// start at the same offset in both.
+pub const ACCOUNT_LEN: usize = 165;
+
+/// Offset of the account type byte, followed by the extensions. Every
+/// extension is encoded as its type (`u16`), length (`u16`) and value.
+pub const ACCOUNT_TYPE_OFFSET: usize = ACCOUNT_LEN;
+pub const EXTENSIONS_OFFSET: usize = ACCOUNT_TYPE_OFFSET + 1;
+pub const EXTENSION_HEADER_LEN: usize = 4;
+
+/// `ExtensionType::ConfidentialTransferMint`.
+pub const CONFIDENTIAL_TRANSFER_MINT: u16 = 4;
+/// `ExtensionType::ConfidentialTransferAccount`.
+pub const CONFIDENTIAL_TRANSFER_ACCOUNT: u16 = 5;
+
+/// Size of the `ConfidentialTransferMint` extension.
+pub const CONFIDENTIAL_TRANSFER_MINT_LEN: usize = 65;
+/// Size of the `ConfidentialTransferAccount` extension.
+pub const CONFIDENTIAL_TRANSFER_ACCOUNT_LEN: usize = 295;
+
+/// Size of a mint with the `ConfidentialTransferMint` extension.
+pub const MINT_WITH_CONFIDENTIAL_TRANSFER_LEN: usize =
+    EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN + CONFIDENTIAL_TRANSFER_MINT_LEN;
+
+/// Size of an ElGamal public key.
+pub const ELGAMAL_PUBKEY_LEN: usize = 32;
+/// Size of an authenticated encryption ciphertext, used for the balance
+/// which the owner decrypts with their AES key.
+pub const AE_CIPHERTEXT_LEN: usize = 36;
+
+/// `TokenInstruction::ConfidentialTransferExtension`.
+const CONFIDENTIAL_TRANSFER_EXTENSION: u8 = 27;
+
+/// Initialize the `ConfidentialTransferMint` extension of a mint. Has to
+/// precede `InitializeMint2`.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializeConfidentialTransferMint<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Authority allowed to approve accounts and change the configuration.
+    pub authority: Option<&'a Pubkey>,
+
+    /// Whether new accounts can be used without being approved by the
+    /// authority.
+    pub auto_approve_new_accounts: bool,
+
+    /// ElGamal public key of an auditor able to decrypt all transfers.
+    pub auditor_elgamal_pubkey: Option<&'a [u8; ELGAMAL_PUBKEY_LEN]>,
+}
+
+impl InitializeConfidentialTransferMint<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0   ]: extension discriminator
+        // -  [1   ]: instruction discriminator
+        // -  [2..34]: authority, zeros if none
+        // -  [34  ]: auto approve new accounts
+        // -  [35..67]: auditor ElGamal public key, zeros if none
+        let mut instruction_data = [0; 67];
+        instruction_data[0] = CONFIDENTIAL_TRANSFER_EXTENSION;
+        if let Some(authority) = self.authority {
+            instruction_data[2..34].copy_from_slice(authority);
+        }
+        instruction_data[34] = self.auto_approve_new_accounts as u8;
+        if let Some(auditor_elgamal_pubkey) = self.auditor_elgamal_pubkey {
+            instruction_data[35..67].copy_from_slice(auditor_elgamal_pubkey);
+        }
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Initialize a mint, without requiring the rent sysvar.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializeMint2<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Decimals.
+    pub decimals: u8,
+
+    /// Mint authority.
+    pub mint_authority: &'a Pubkey,
+
+    /// Freeze authority.
+    pub freeze_authority: Option<&'a Pubkey>,
+}
+
+impl InitializeMint2<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1   ]: decimals
+        // -  [2..34]: mint authority
+        // -  [34  ]: freeze authority presence flag
+        // -  [35..67]: freeze authority
+        let mut instruction_data = [0; 67];
+        instruction_data[0] = 20;
+        instruction_data[1] = self.decimals;
+        instruction_data[2..34].copy_from_slice(self.mint_authority);
+        if let Some(freeze_authority) = self.freeze_authority {
+            instruction_data[34] = 1;
+            instruction_data[35..67].copy_from_slice(freeze_authority);
+        }
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Mint new tokens to an account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Mint
+///   1. `[WRITE]` Token account to mint to
+///   2. `[SIGNER]` Mint authority
+pub struct MintTo<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Mint authority.
+    pub mint_authority: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+}
+
+impl MintTo<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.mint.key()),
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly_signer(self.mint_authority.key()),
+        ];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1..9]: amount
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 7;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.mint, self.account, self.mint_authority],
+            signers,
+        )
+    }
+}
+
+/// Grow a token account to fit the given extension, paying for the rent.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Token account
+///   1. `[WRITE, SIGNER]` Payer
+///   2. `[]` System program
+///   3. `[SIGNER]` Owner of the token account
+pub struct Reallocate<'a> {
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Payer of the rent.
+    pub payer: &'a AccountInfo,
+
+    /// System program.
+    pub system_program: &'a AccountInfo,
+
+    /// Owner of the token account.
+    pub owner: &'a AccountInfo,
+
+    /// Type of the extension to make space for.
+    pub extension_type: u16,
+}
+
+impl Reallocate<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 4] = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::writable_signer(self.payer.key()),
+            AccountMeta::readonly(self.system_program.key()),
+            AccountMeta::readonly_signer(self.owner.key()),
+        ];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1..3]: extension type
+        let mut instruction_data = [0; 3];
+        instruction_data[0] = 29;
+        instruction_data[1..3].copy_from_slice(&self.extension_type.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.account, self.payer, self.system_program, self.owner],
+            signers,
+        )
+    }
+}
+
+/// Configure a token account for confidential transfers.
+///
+/// The token program checks a `VerifyPubkeyValidity` proof of the ElGamal
+/// public key of the account, which has to be an instruction of the same
+/// transaction, `proof_instruction_offset` instructions away from the
+/// current one.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Token account
+///   1. `[]` Mint
+///   2. `[]` Instructions sysvar
+///   3. `[SIGNER]` Owner of the token account
+pub struct ConfigureAccount<'a> {
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Instructions sysvar.
+    pub instructions_sysvar: &'a AccountInfo,
+
+    /// Owner of the token account.
+    pub owner: &'a AccountInfo,
+
+    /// Encryption of zero under the AES key of the owner.
+    pub decryptable_zero_balance: &'a [u8; AE_CIPHERTEXT_LEN],
+
+    /// Number of deposits and incoming transfers after which the owner has
+    /// to apply the pending balance before receiving more.
+    pub maximum_pending_balance_credit_counter: u64,
+
+    /// Position of the proof instruction relative to the current one.
+    pub proof_instruction_offset: i8,
+}
+
+impl ConfigureAccount<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 4] = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::readonly(self.instructions_sysvar.key()),
+            AccountMeta::readonly_signer(self.owner.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: extension discriminator
+        // -  [1    ]: instruction discriminator
+        // -  [2..38 ]: decryptable zero balance
+        // -  [38..46]: maximum pending balance credit counter
+        // -  [46   ]: proof instruction offset
+        let mut instruction_data = [0; 47];
+        instruction_data[0] = CONFIDENTIAL_TRANSFER_EXTENSION;
+        instruction_data[1] = 2;
+        instruction_data[2..38].copy_from_slice(self.decryptable_zero_balance);
+        instruction_data[38..46]
+            .copy_from_slice(&self.maximum_pending_balance_credit_counter.to_le_bytes());
+        instruction_data[46] = self.proof_instruction_offset as u8;
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.account,
+                self.mint,
+                self.instructions_sysvar,
+                self.owner,
+            ],
+            signers,
+        )
+    }
+}
+
+/// Move tokens from the public balance of a token account to its pending
+/// confidential balance.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Token account
+///   1. `[]` Mint
+///   2. `[SIGNER]` Owner of the token account
+pub struct Deposit<'a> {
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Owner of the token account.
+    pub owner: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+
+    /// Decimals of the mint.
+    pub decimals: u8,
+}
+
+impl Deposit<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::readonly_signer(self.owner.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: extension discriminator
+        // -  [1    ]: instruction discriminator
+        // -  [2..10]: amount
+        // -  [10   ]: decimals
+        let mut instruction_data = [0; 11];
+        instruction_data[0] = CONFIDENTIAL_TRANSFER_EXTENSION;
+        instruction_data[1] = 5;
+        instruction_data[2..10].copy_from_slice(&self.amount.to_le_bytes());
+        instruction_data[10] = self.decimals;
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.account, self.mint, self.owner],
+            signers,
+        )
+    }
+}
+
+/// Add the pending confidential balance of a token account to its
+/// available confidential balance.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Token account
+///   1. `[SIGNER]` Owner of the token account
+pub struct ApplyPendingBalance<'a> {
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Owner of the token account.
+    pub owner: &'a AccountInfo,
+
+    /// Pending balance credit counter the new decryptable balance was
+    /// computed at.
+    pub expected_pending_balance_credit_counter: u64,
+
+    /// New available balance, encrypted under the AES key of the owner.
+    pub new_decryptable_available_balance: &'a [u8; AE_CIPHERTEXT_LEN],
+}
+
+impl ApplyPendingBalance<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 2] = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly_signer(self.owner.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: extension discriminator
+        // -  [1    ]: instruction discriminator
+        // -  [2..10]: expected pending balance credit counter
+        // -  [10..46]: new decryptable available balance
+        let mut instruction_data = [0; 46];
+        instruction_data[0] = CONFIDENTIAL_TRANSFER_EXTENSION;
+        instruction_data[1] = 8;
+        instruction_data[2..10]
+            .copy_from_slice(&self.expected_pending_balance_credit_counter.to_le_bytes());
+        instruction_data[10..46].copy_from_slice(self.new_decryptable_available_balance);
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.account, self.owner], signers)
+    }
+}
@@ -0,0 +1,264 @@
+//! Attacks on the confidential transfer program, each rejected with its own
+//! error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use confidential_transfer::{
+    token_2022::{
+        ACCOUNT_TYPE_OFFSET, AE_CIPHERTEXT_LEN, CONFIDENTIAL_TRANSFER_ACCOUNT,
+        CONFIDENTIAL_TRANSFER_ACCOUNT_LEN, CONFIDENTIAL_TRANSFER_MINT,
+        CONFIDENTIAL_TRANSFER_MINT_LEN, EXTENSIONS_OFFSET, EXTENSION_HEADER_LEN,
+        MINT_WITH_CONFIDENTIAL_TRANSFER_LEN,
+    },
+    ApplyPendingBalanceInstructionData, ConfidentialTransferInstruction, MintToInstructionData,
+    MINT_AUTHORITY_SEED,
+};
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(confidential_transfer::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(confidential_transfer::token_2022::ID);
+
+/// `TokenError::OwnerMismatch`.
+const OWNER_MISMATCH: u32 = 4;
+
+const DECIMALS: u8 = 6;
+
+/// Offset of the value of the first extension.
+const EXTENSION_OFFSET: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN;
+/// Offsets of the `ConfidentialTransferAccount` fields.
+const ALLOW_CONFIDENTIAL_CREDITS_OFFSET: usize = EXTENSION_OFFSET + 261;
+const MAXIMUM_PENDING_BALANCE_CREDIT_COUNTER_OFFSET: usize = EXTENSION_OFFSET + 271;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(
+    discriminator: ConfidentialTransferInstruction,
+    data: &T,
+) -> Vec<u8> {
+    let mut data_with_discriminator =
+        vec![0; mem::size_of::<ConfidentialTransferInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the mint authority PDA of `admin` for `mint` and its bump.
+fn mint_authority_pda(mint: &Pubkey, admin: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            MINT_AUTHORITY_SEED.as_bytes(),
+            mint.as_ref(),
+            admin.as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// Returns a mint with the `ConfidentialTransferMint` extension, as created
+/// by `InitializeMint`.
+fn mint_account(mollusk: &Mollusk, mint_authority: &Pubkey) -> Account {
+    let mut data = vec![0; MINT_WITH_CONFIDENTIAL_TRANSFER_LEN];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(mint_authority.as_ref());
+    data[44] = DECIMALS;
+    data[45] = 1;
+    data[ACCOUNT_TYPE_OFFSET] = 1;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&CONFIDENTIAL_TRANSFER_MINT.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..EXTENSION_OFFSET]
+        .copy_from_slice(&(CONFIDENTIAL_TRANSFER_MINT_LEN as u16).to_le_bytes());
+    data[EXTENSION_OFFSET..EXTENSION_OFFSET + 32].copy_from_slice(mint_authority.as_ref());
+    data[EXTENSION_OFFSET + 32] = 1;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns an empty token account configured for confidential transfers.
+fn confidential_token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let len = EXTENSION_OFFSET + CONFIDENTIAL_TRANSFER_ACCOUNT_LEN;
+    let mut data = vec![0; len];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    // Initialized.
+    data[108] = 1;
+    data[ACCOUNT_TYPE_OFFSET] = 2;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&CONFIDENTIAL_TRANSFER_ACCOUNT.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..EXTENSION_OFFSET]
+        .copy_from_slice(&(CONFIDENTIAL_TRANSFER_ACCOUNT_LEN as u16).to_le_bytes());
+    // Approved.
+    data[EXTENSION_OFFSET] = 1;
+    // Confidential and non-confidential credits allowed.
+    data[ALLOW_CONFIDENTIAL_CREDITS_OFFSET] = 1;
+    data[ALLOW_CONFIDENTIAL_CREDITS_OFFSET + 1] = 1;
+    data[MAXIMUM_PENDING_BALANCE_CREDIT_COUNTER_OFFSET
+        ..MAXIMUM_PENDING_BALANCE_CREDIT_COUNTER_OFFSET + 8]
+        .copy_from_slice(&65_536u64.to_le_bytes());
+
+    let mut account = Account::new(mollusk.sysvars.rent.minimum_balance(len), len, &TOKEN_ID);
+    account.data = data;
+    account
+}
+
+/// Accounts of a confidential mint of an admin, with a configured token
+/// account of an owner, and of an attacker with a configured token account
+/// of the same mint.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    mint: Pubkey,
+    mint_authority: Pubkey,
+    mint_authority_bump: u8,
+    token_account: Pubkey,
+    attacker: Pubkey,
+    attacker_token_account: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/confidential_transfer");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+
+        let admin = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (mint_authority, mint_authority_bump) = mint_authority_pda(&mint, &admin);
+        let owner = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let attacker_token_account = Pubkey::new_unique();
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &mint_authority)),
+            (mint_authority, Account::default()),
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (
+                token_account,
+                confidential_token_account(&mollusk, &mint, &owner),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_token_account,
+                confidential_token_account(&mollusk, &mint, &attacker),
+            ),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            mint,
+            mint_authority,
+            mint_authority_bump,
+            token_account,
+            attacker,
+            attacker_token_account,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Mints tokens to the attacker through the mint authority PDA of
+    /// `admin`, signed by `admin`.
+    fn instruction_mint_to(
+        &self,
+        admin: &Pubkey,
+        mint_authority: &Pubkey,
+        bump: u8,
+    ) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.attacker_token_account, false),
+            AccountMeta::new_readonly(*mint_authority, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                ConfidentialTransferInstruction::MintTo,
+                &MintToInstructionData::new(1_000, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_admin_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_mint_to(
+        &setup.admin,
+        &setup.mint_authority,
+        setup.mint_authority_bump,
+    );
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker mints with their own mint authority PDA for the mint, which
+/// the program signs for, but which is not the authority of the mint.
+#[test]
+fn test_mint_authority_of_attacker() {
+    let setup = Setup::new();
+    let (mint_authority, bump) = mint_authority_pda(&setup.mint, &setup.attacker);
+    let setup = setup.with(mint_authority, Account::default());
+    setup.expect_err(
+        &setup.instruction_mint_to(&setup.attacker, &mint_authority, bump),
+        ProgramError::Custom(OWNER_MISMATCH),
+    );
+}
+
+/// The attacker applies the pending balance of the owner with a made up
+/// decryptable balance, which the owner couldn't decrypt anymore.
+#[test]
+fn test_apply_pending_balance_by_attacker() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.attacker, true),
+        AccountMeta::new(setup.token_account, false),
+        AccountMeta::new_readonly(TOKEN_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            ConfidentialTransferInstruction::ApplyPendingBalance,
+            &ApplyPendingBalanceInstructionData::new(0, [7; AE_CIPHERTEXT_LEN]),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::Custom(OWNER_MISMATCH));
+}
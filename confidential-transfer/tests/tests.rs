@@ -0,0 +1,388 @@
+//! The token-2022 program is loaded from `third-party/spl_token_2022.so`,
+//! which can be dumped from mainnet with:
+//!
+//! ```sh
+//! solana program dump -um TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb \
+//!     third-party/spl_token_2022.so
+//! ```
+//!
+//! `ConfigureAccount` needs a `VerifyPubkeyValidity` proof verified by the
+//! ZK ElGamal proof program, which Mollusk doesn't provide, so the tests
+//! start with an already configured token account instead.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use confidential_transfer::{
+    token_2022::{
+        ACCOUNT_LEN, ACCOUNT_TYPE_OFFSET, AE_CIPHERTEXT_LEN, CONFIDENTIAL_TRANSFER_ACCOUNT,
+        CONFIDENTIAL_TRANSFER_ACCOUNT_LEN, CONFIDENTIAL_TRANSFER_MINT,
+        CONFIDENTIAL_TRANSFER_MINT_LEN, EXTENSIONS_OFFSET, EXTENSION_HEADER_LEN,
+        MINT_WITH_CONFIDENTIAL_TRANSFER_LEN,
+    },
+    ApplyPendingBalanceInstructionData, ConfidentialTransferInstruction,
+    ConfigureAccountInstructionData, DepositInstructionData, InitializeMintInstructionData,
+    MintToInstructionData, MINT_AUTHORITY_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(confidential_transfer::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(confidential_transfer::token_2022::ID);
+
+/// `TokenError::InsufficientFunds`.
+const INSUFFICIENT_FUNDS: u32 = 1;
+/// `TokenError::OwnerMismatch`.
+const OWNER_MISMATCH: u32 = 4;
+
+const DECIMALS: u8 = 6;
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offset of the value of the first extension.
+const EXTENSION_OFFSET: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN;
+/// Offsets of the `ConfidentialTransferAccount` fields.
+const DECRYPTABLE_AVAILABLE_BALANCE_OFFSET: usize = EXTENSION_OFFSET + 225;
+const ALLOW_CONFIDENTIAL_CREDITS_OFFSET: usize = EXTENSION_OFFSET + 261;
+const PENDING_BALANCE_CREDIT_COUNTER_OFFSET: usize = EXTENSION_OFFSET + 263;
+const MAXIMUM_PENDING_BALANCE_CREDIT_COUNTER_OFFSET: usize = EXTENSION_OFFSET + 271;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(
+    discriminator: ConfidentialTransferInstruction,
+    data: &T,
+) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<ConfidentialTransferInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/confidential_transfer");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+    mollusk
+}
+
+/// Returns a mint with the `ConfidentialTransferMint` extension, as created
+/// by `InitializeMint`.
+fn mint_account(mollusk: &Mollusk, mint_authority: &Pubkey, supply: u64) -> Account {
+    let mut data = vec![0; MINT_WITH_CONFIDENTIAL_TRANSFER_LEN];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(mint_authority.as_ref());
+    data[36..44].copy_from_slice(&supply.to_le_bytes());
+    data[44] = DECIMALS;
+    data[45] = 1;
+    data[ACCOUNT_TYPE_OFFSET] = 1;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&CONFIDENTIAL_TRANSFER_MINT.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..EXTENSION_OFFSET]
+        .copy_from_slice(&(CONFIDENTIAL_TRANSFER_MINT_LEN as u16).to_le_bytes());
+    data[EXTENSION_OFFSET..EXTENSION_OFFSET + 32].copy_from_slice(mint_authority.as_ref());
+    data[EXTENSION_OFFSET + 32] = 1;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns a token account configured for confidential transfers, with
+/// all of its confidential balances zero.
+fn confidential_token_account(
+    mollusk: &Mollusk,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Account {
+    let len = EXTENSION_OFFSET + CONFIDENTIAL_TRANSFER_ACCOUNT_LEN;
+    let mut data = vec![0; len];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[AMOUNT_OFFSET..AMOUNT_OFFSET + 8].copy_from_slice(&amount.to_le_bytes());
+    // Initialized.
+    data[108] = 1;
+    data[ACCOUNT_TYPE_OFFSET] = 2;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&CONFIDENTIAL_TRANSFER_ACCOUNT.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..EXTENSION_OFFSET]
+        .copy_from_slice(&(CONFIDENTIAL_TRANSFER_ACCOUNT_LEN as u16).to_le_bytes());
+    // Approved.
+    data[EXTENSION_OFFSET] = 1;
+    // Confidential and non-confidential credits allowed.
+    data[ALLOW_CONFIDENTIAL_CREDITS_OFFSET] = 1;
+    data[ALLOW_CONFIDENTIAL_CREDITS_OFFSET + 1] = 1;
+    data[MAXIMUM_PENDING_BALANCE_CREDIT_COUNTER_OFFSET
+        ..MAXIMUM_PENDING_BALANCE_CREDIT_COUNTER_OFFSET + 8]
+        .copy_from_slice(&65_536u64.to_le_bytes());
+
+    let mut account = Account::new(mollusk.sysvars.rent.minimum_balance(len), len, &TOKEN_ID);
+    account.data = data;
+    account
+}
+
+/// An admin with a confidential mint and an owner with a configured token
+/// account.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    mint: Pubkey,
+    mint_authority: Pubkey,
+    mint_authority_bump: u8,
+    owner: Pubkey,
+    token_account: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let admin = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (mint_authority, mint_authority_bump) = Pubkey::find_program_address(
+            &[
+                MINT_AUTHORITY_SEED.as_bytes(),
+                mint.as_ref(),
+                admin.as_ref(),
+            ],
+            &ID,
+        );
+        let owner = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &mint_authority, 0)),
+            (mint_authority, Account::default()),
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (
+                token_account,
+                confidential_token_account(&mollusk, &mint, &owner, 0),
+            ),
+            keyed_account_for_system_program(),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            mint,
+            mint_authority,
+            mint_authority_bump,
+            owner,
+            token_account,
+            accounts,
+        }
+    }
+
+    fn instruction_initialize_mint(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.mint, true),
+            AccountMeta::new_readonly(self.mint_authority, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                ConfidentialTransferInstruction::InitializeMint,
+                &InitializeMintInstructionData::new(DECIMALS, self.mint_authority_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_mint_to(&self, amount: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.token_account, false),
+            AccountMeta::new_readonly(self.mint_authority, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                ConfidentialTransferInstruction::MintTo,
+                &MintToInstructionData::new(amount, self.mint_authority_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_deposit(&self, amount: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.token_account, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                ConfidentialTransferInstruction::Deposit,
+                &DepositInstructionData::new(amount, DECIMALS),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_apply_pending_balance(
+        &self,
+        expected_pending_balance_credit_counter: u64,
+        new_decryptable_available_balance: [u8; AE_CIPHERTEXT_LEN],
+    ) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.token_account, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                ConfidentialTransferInstruction::ApplyPendingBalance,
+                &ApplyPendingBalanceInstructionData::new(
+                    expected_pending_balance_credit_counter,
+                    new_decryptable_available_balance,
+                ),
+            ),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(MINT_WITH_CONFIDENTIAL_TRANSFER_LEN, 235);
+    assert_eq!(
+        EXTENSION_OFFSET + CONFIDENTIAL_TRANSFER_ACCOUNT_LEN,
+        ACCOUNT_LEN + 300
+    );
+    assert_eq!(ConfigureAccountInstructionData::LEN, 48);
+    assert_eq!(ApplyPendingBalanceInstructionData::LEN, 48);
+}
+
+#[test]
+fn test_initialize_mint() {
+    let mut setup = Setup::new();
+    // The mint is created by the program.
+    setup.accounts[1].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_mint(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.mint)
+                .owner(&TOKEN_ID)
+                .space(MINT_WITH_CONFIDENTIAL_TRANSFER_LEN)
+                .data_slice(0, &1u32.to_le_bytes())
+                .data_slice(4, setup.mint_authority.as_ref())
+                .data_slice(44, &[DECIMALS, 1])
+                .data_slice(ACCOUNT_TYPE_OFFSET, &[1])
+                .data_slice(EXTENSIONS_OFFSET, &CONFIDENTIAL_TRANSFER_MINT.to_le_bytes())
+                .data_slice(EXTENSION_OFFSET, setup.mint_authority.as_ref())
+                // Auto approve new accounts.
+                .data_slice(EXTENSION_OFFSET + 32, &[1])
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_deposit_and_apply() {
+    let setup = Setup::new();
+    let new_decryptable_available_balance = [7; AE_CIPHERTEXT_LEN];
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_mint_to(1_000),
+                &[
+                    Check::success(),
+                    Check::account(&setup.token_account)
+                        .data_slice(AMOUNT_OFFSET, &1_000u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_deposit(400),
+                &[
+                    Check::success(),
+                    Check::account(&setup.token_account)
+                        .data_slice(AMOUNT_OFFSET, &600u64.to_le_bytes())
+                        .data_slice(PENDING_BALANCE_CREDIT_COUNTER_OFFSET, &1u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_apply_pending_balance(1, new_decryptable_available_balance),
+                &[
+                    Check::success(),
+                    Check::account(&setup.token_account)
+                        .data_slice(
+                            DECRYPTABLE_AVAILABLE_BALANCE_OFFSET,
+                            &new_decryptable_available_balance,
+                        )
+                        .data_slice(PENDING_BALANCE_CREDIT_COUNTER_OFFSET, &0u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_invalid() {
+    let setup = Setup::new();
+
+    // Only the admin can mint through their mint authority PDA.
+    let mut ix = setup.instruction_mint_to(1_000);
+    ix.accounts[0].pubkey = setup.owner;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+
+    // Deposits can't exceed the public balance.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_deposit(1),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(INSUFFICIENT_FUNDS))],
+    );
+
+    // Only the owner can deposit.
+    let mut ix = setup.instruction_deposit(0);
+    ix.accounts[0].pubkey = setup.admin;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(OWNER_MISMATCH))],
+    );
+}
@@ -0,0 +1,49 @@
+//! Accounts of the counter instructions.
+//!
+//! All instructions take the same accounts, the owner and the counter PDA
+//! derived from it, converted into the metas of the instruction in the
+//! order the program expects them. Only the compact instructions leave out
+//! the system program.
+
+use solana_instruction::AccountMeta;
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::system_program;
+
+use crate::find_counter_address;
+
+/// Accounts of every [`crate::CounterInstruction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CounterAccounts {
+    /// Owner of the counter, paying for it and receiving its rent back.
+    pub owner: Pubkey,
+    /// Counter PDA.
+    pub counter: Pubkey,
+}
+
+impl CounterAccounts {
+    /// Returns the accounts of the counter of `owner`.
+    pub fn new(owner: &Pubkey) -> Self {
+        Self {
+            owner: *owner,
+            counter: find_counter_address(owner).0,
+        }
+    }
+
+    /// Returns the metas of the accounts, followed by the system program.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.counter, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+    }
+
+    /// Returns the metas of the accounts of the compact instructions,
+    /// without the system program.
+    pub fn to_compact_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.counter, false),
+        ]
+    }
+}
@@ -0,0 +1,318 @@
+//! Client of the counter program.
+//!
+//! Builds its instructions, which all take the same [`accounts`], and decodes
+//! its accounts off-chain. [`TransactionBuilder`] puts them into v0
+//! transactions, with a priority fee if needed. The events logged by the
+//! program are re-exported to decode them from transaction logs. With the
+//! `rpc` feature, [`rpc`] lists its accounts with `getProgramAccounts`. With
+//! the `wasm` feature, [`wasm`] exports it to JavaScript. The client builds
+//! for `wasm32-unknown-unknown` without the `rpc` feature.
+
+use common::AccountData;
+use counter::{CounterInstructionData, CounterInstructionDataV2, CreateInstructionDataV2};
+use pinocchio::program_error::ProgramError;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+pub mod accounts;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
+
+pub use accounts::CounterAccounts;
+pub use client_utils::TransactionBuilder;
+pub use common::version::Version;
+pub use counter::{
+    Counter, CounterCreated, CounterDecremented, CounterDeleted, CounterIncremented,
+    CounterInstruction, CounterMode, EpochCounter, COUNTER_SEED,
+};
+
+/// Address of the counter program.
+pub const ID: Pubkey = Pubkey::new_from_array(counter::ID);
+
+/// Returns the counter PDA of `owner` and its bump.
+pub fn find_counter_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COUNTER_SEED.as_bytes(), owner.as_ref()], &ID)
+}
+
+/// Arguments of a counter instruction, which [`InstructionArgs::encode`]
+/// encodes into its instruction data and [`InstructionArgs::decode`]
+/// decodes from it.
+///
+/// The data is the discriminator of the instruction and its [`Version`],
+/// followed by the bump of the counter PDA and, in version 2, the amount or,
+/// for the creation, the [`CounterMode`]. The compact instructions are only
+/// the discriminator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstructionArgs {
+    Create {
+        bump: u8,
+    },
+    Increment {
+        bump: u8,
+    },
+    Decrement {
+        bump: u8,
+    },
+    Delete {
+        bump: u8,
+    },
+    CompactIncrement,
+    CompactDecrement,
+    /// Version 2 of [`CounterInstruction::Increment`].
+    IncrementV2 {
+        bump: u8,
+        amount: u64,
+    },
+    /// Version 2 of [`CounterInstruction::Decrement`].
+    DecrementV2 {
+        bump: u8,
+        amount: u64,
+    },
+    /// Version 2 of [`CounterInstruction::Create`].
+    CreateV2 {
+        bump: u8,
+        mode: CounterMode,
+    },
+}
+
+impl InstructionArgs {
+    /// Encodes the instruction data.
+    pub fn encode(&self) -> Vec<u8> {
+        let (instruction, version, fields) = match *self {
+            Self::Create { bump } => (CounterInstruction::Create, Version::V1, v1(bump)),
+            Self::Increment { bump } => (CounterInstruction::Increment, Version::V1, v1(bump)),
+            Self::Decrement { bump } => (CounterInstruction::Decrement, Version::V1, v1(bump)),
+            Self::Delete { bump } => (CounterInstruction::Delete, Version::V1, v1(bump)),
+            Self::CompactIncrement => (
+                CounterInstruction::CompactIncrement,
+                Version::V1,
+                Vec::new(),
+            ),
+            Self::CompactDecrement => (
+                CounterInstruction::CompactDecrement,
+                Version::V1,
+                Vec::new(),
+            ),
+            Self::IncrementV2 { bump, amount } => {
+                (CounterInstruction::Increment, Version::V2, v2(bump, amount))
+            }
+            Self::DecrementV2 { bump, amount } => {
+                (CounterInstruction::Decrement, Version::V2, v2(bump, amount))
+            }
+            Self::CreateV2 { bump, mode } => (
+                CounterInstruction::Create,
+                Version::V2,
+                create_v2(bump, mode),
+            ),
+        };
+        [instruction.discriminator(version).as_slice(), &fields].concat()
+    }
+
+    /// Decodes instruction data.
+    ///
+    /// Fails with [`ProgramError::InvalidInstructionData`] for an unknown
+    /// discriminator or version, or data of the wrong length, like the
+    /// program.
+    pub fn decode(data: &[u8]) -> Result<Self, ProgramError> {
+        let (instruction, version, data) = CounterInstruction::split(data)?;
+        let bump = || CounterInstructionData::from_bytes(data).map(|data| data.bump);
+        Ok(match (instruction, version) {
+            (CounterInstruction::Create, Version::V1) => Self::Create { bump: bump()? },
+            (CounterInstruction::Increment, Version::V1) => Self::Increment { bump: bump()? },
+            (CounterInstruction::Decrement, Version::V1) => Self::Decrement { bump: bump()? },
+            (CounterInstruction::Delete, Version::V1) => Self::Delete { bump: bump()? },
+            (CounterInstruction::CompactIncrement, Version::V1) if data.is_empty() => {
+                Self::CompactIncrement
+            }
+            (CounterInstruction::CompactDecrement, Version::V1) if data.is_empty() => {
+                Self::CompactDecrement
+            }
+            (CounterInstruction::Increment, Version::V2) => {
+                let data = CounterInstructionDataV2::from_bytes(data)?;
+                Self::IncrementV2 {
+                    bump: data.bump,
+                    amount: data.amount,
+                }
+            }
+            (CounterInstruction::Decrement, Version::V2) => {
+                let data = CounterInstructionDataV2::from_bytes(data)?;
+                Self::DecrementV2 {
+                    bump: data.bump,
+                    amount: data.amount,
+                }
+            }
+            (CounterInstruction::Create, Version::V2) => {
+                let data = CreateInstructionDataV2::from_bytes(data)?;
+                Self::CreateV2 {
+                    bump: data.bump,
+                    mode: CounterMode::try_from(data.mode)?,
+                }
+            }
+            _ => return Err(ProgramError::InvalidInstructionData),
+        })
+    }
+}
+
+/// Encodes the instruction data of version 1 of the instructions taking a
+/// bump.
+fn v1(bump: u8) -> Vec<u8> {
+    CounterInstructionData { bump }.to_bytes().to_vec()
+}
+
+/// Encodes the instruction data of version 2 of
+/// [`CounterInstruction::Increment`] and [`CounterInstruction::Decrement`].
+fn v2(bump: u8, amount: u64) -> Vec<u8> {
+    CounterInstructionDataV2 { bump, amount }
+        .to_bytes()
+        .to_vec()
+}
+
+/// Encodes the instruction data of version 2 of
+/// [`CounterInstruction::Create`].
+fn create_v2(bump: u8, mode: CounterMode) -> Vec<u8> {
+    CreateInstructionDataV2 {
+        bump,
+        mode: mode as u8,
+    }
+    .to_bytes()
+    .to_vec()
+}
+
+/// Builds version 1 of `counter_instruction` for the counter of `accounts`.
+pub fn instruction(
+    counter_instruction: CounterInstruction,
+    accounts: &CounterAccounts,
+) -> Instruction {
+    let (_, bump) = find_counter_address(&accounts.owner);
+
+    let mut data =
+        Vec::with_capacity(CounterInstruction::DISCRIMINATOR_LEN + CounterInstructionData::LEN);
+    data.extend_from_slice(&counter_instruction.discriminator(Version::V1));
+    data.extend_from_slice(&CounterInstructionData { bump }.to_bytes());
+
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds version 2 of `counter_instruction`, which is
+/// [`CounterInstruction::Increment`] or [`CounterInstruction::Decrement`],
+/// changing the counter of `accounts` by `amount`.
+pub fn instruction_v2(
+    counter_instruction: CounterInstruction,
+    accounts: &CounterAccounts,
+    amount: u64,
+) -> Instruction {
+    let (_, bump) = find_counter_address(&accounts.owner);
+
+    let mut data =
+        Vec::with_capacity(CounterInstruction::DISCRIMINATOR_LEN + CounterInstructionDataV2::LEN);
+    data.extend_from_slice(&counter_instruction.discriminator(Version::V2));
+    data.extend_from_slice(&CounterInstructionDataV2 { bump, amount }.to_bytes());
+
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds `counter_instruction`, one of the compact instructions, for the
+/// counter of `accounts`. It's only the discriminator, without the bump.
+pub fn compact_instruction(
+    counter_instruction: CounterInstruction,
+    accounts: &CounterAccounts,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &counter_instruction.discriminator(Version::V1),
+        accounts.to_compact_account_metas(),
+    )
+}
+
+/// Builds an instruction creating the counter of `owner`.
+pub fn create(owner: &Pubkey) -> Instruction {
+    instruction(CounterInstruction::Create, &CounterAccounts::new(owner))
+}
+
+/// Builds an instruction creating the counter of `owner` in `mode`, e.g.
+/// an [`EpochCounter`] going back to zero in every epoch.
+pub fn create_with_mode(owner: &Pubkey, mode: CounterMode) -> Instruction {
+    let accounts = CounterAccounts::new(owner);
+    let (_, bump) = find_counter_address(owner);
+    Instruction::new_with_bytes(
+        ID,
+        &InstructionArgs::CreateV2 { bump, mode }.encode(),
+        accounts.to_account_metas(),
+    )
+}
+
+/// Builds an instruction incrementing the counter of `owner`.
+pub fn increment(owner: &Pubkey) -> Instruction {
+    instruction(CounterInstruction::Increment, &CounterAccounts::new(owner))
+}
+
+/// Builds an instruction decrementing the counter of `owner`.
+pub fn decrement(owner: &Pubkey) -> Instruction {
+    instruction(CounterInstruction::Decrement, &CounterAccounts::new(owner))
+}
+
+/// Builds an instruction incrementing the counter of `owner` by `amount`.
+pub fn increment_by(owner: &Pubkey, amount: u64) -> Instruction {
+    instruction_v2(
+        CounterInstruction::Increment,
+        &CounterAccounts::new(owner),
+        amount,
+    )
+}
+
+/// Builds an instruction decrementing the counter of `owner` by `amount`.
+pub fn decrement_by(owner: &Pubkey, amount: u64) -> Instruction {
+    instruction_v2(
+        CounterInstruction::Decrement,
+        &CounterAccounts::new(owner),
+        amount,
+    )
+}
+
+/// Builds a compact instruction incrementing the counter of `owner`, which
+/// is smaller than [`increment`] and cheaper to process.
+pub fn compact_increment(owner: &Pubkey) -> Instruction {
+    compact_instruction(
+        CounterInstruction::CompactIncrement,
+        &CounterAccounts::new(owner),
+    )
+}
+
+/// Builds a compact instruction decrementing the counter of `owner`, which
+/// is smaller than [`decrement`] and cheaper to process.
+pub fn compact_decrement(owner: &Pubkey) -> Instruction {
+    compact_instruction(
+        CounterInstruction::CompactDecrement,
+        &CounterAccounts::new(owner),
+    )
+}
+
+/// Builds an instruction deleting the counter of `owner`.
+pub fn delete(owner: &Pubkey) -> Instruction {
+    instruction(CounterInstruction::Delete, &CounterAccounts::new(owner))
+}
+
+/// Builds an instruction deleting the counter of `owner` and sending its
+/// rent to `destination`, which has to sign as well unless it's the owner.
+pub fn delete_to(owner: &Pubkey, destination: &Pubkey) -> Instruction {
+    let mut instruction = delete(owner);
+    instruction
+        .accounts
+        .push(AccountMeta::new(*destination, destination != owner));
+    instruction
+}
+
+/// Decodes a counter from the data of its account.
+pub fn try_from_account_data(data: &[u8]) -> Result<Counter, ProgramError> {
+    Counter::read(data)
+}
+
+/// Decodes an epoch counter from the data of its account.
+///
+/// Its count is the one of its last update, which the next update resets if
+/// the epoch advanced since [`EpochCounter::epoch`].
+pub fn try_from_epoch_account_data(data: &[u8]) -> Result<EpochCounter, ProgramError> {
+    EpochCounter::read(data)
+}
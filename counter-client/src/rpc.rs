@@ -0,0 +1,43 @@
+//! Listing of counters with `getProgramAccounts`.
+
+use core::mem::offset_of;
+
+use common::AccountData;
+use solana_account::Account;
+use solana_pubkey::Pubkey;
+use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+
+use crate::{try_from_account_data, Counter};
+
+/// Offset of [`Counter::owner`] in the data of counters.
+pub const OWNER_OFFSET: usize = Counter::PREFIX.len() + offset_of!(Counter, owner);
+
+/// Returns the filters matching the counters of `owner`.
+pub fn counters_by_owner(owner: &Pubkey) -> Vec<RpcFilterType> {
+    let mut filters = vec![RpcFilterType::DataSize(Counter::LEN as u64)];
+    if !Counter::PREFIX.is_empty() {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            0,
+            Counter::PREFIX,
+        )));
+    }
+    filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+        OWNER_OFFSET,
+        owner.as_ref(),
+    )));
+    filters
+}
+
+/// Decodes the accounts returned by `getProgramAccounts`, skipping the ones
+/// which aren't counters.
+pub fn decode_counters(
+    accounts: impl IntoIterator<Item = (Pubkey, Account)>,
+) -> Vec<(Pubkey, Counter)> {
+    accounts
+        .into_iter()
+        .filter_map(|(address, account)| {
+            let counter = try_from_account_data(&account.data).ok()?;
+            Some((address, counter))
+        })
+        .collect()
+}
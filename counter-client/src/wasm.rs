@@ -0,0 +1,82 @@
+//! Bindings for browser dApps.
+//!
+//! Build them with `wasm-pack build --target web -- --features wasm`. On
+//! `wasm32`, [`Pubkey`] and [`Instruction`] are exported to JavaScript by
+//! their own crates, so the instructions can be added to transactions built
+//! with them.
+
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+use wasm_bindgen::prelude::*;
+
+use crate::{find_counter_address, try_from_account_data};
+
+/// Returns the counter PDA of `owner`.
+#[wasm_bindgen(js_name = counterAddress)]
+pub fn counter_address(owner: &Pubkey) -> Pubkey {
+    find_counter_address(owner).0
+}
+
+/// Builds an instruction creating the counter of `owner`.
+#[wasm_bindgen]
+pub fn create(owner: &Pubkey) -> Instruction {
+    crate::create(owner)
+}
+
+/// Builds an instruction incrementing the counter of `owner`.
+#[wasm_bindgen]
+pub fn increment(owner: &Pubkey) -> Instruction {
+    crate::increment(owner)
+}
+
+/// Builds an instruction decrementing the counter of `owner`.
+#[wasm_bindgen]
+pub fn decrement(owner: &Pubkey) -> Instruction {
+    crate::decrement(owner)
+}
+
+/// Builds an instruction incrementing the counter of `owner` by `amount`.
+#[wasm_bindgen(js_name = incrementBy)]
+pub fn increment_by(owner: &Pubkey, amount: u64) -> Instruction {
+    crate::increment_by(owner, amount)
+}
+
+/// Builds an instruction decrementing the counter of `owner` by `amount`.
+#[wasm_bindgen(js_name = decrementBy)]
+pub fn decrement_by(owner: &Pubkey, amount: u64) -> Instruction {
+    crate::decrement_by(owner, amount)
+}
+
+/// Builds a compact instruction incrementing the counter of `owner`.
+#[wasm_bindgen(js_name = compactIncrement)]
+pub fn compact_increment(owner: &Pubkey) -> Instruction {
+    crate::compact_increment(owner)
+}
+
+/// Builds a compact instruction decrementing the counter of `owner`.
+#[wasm_bindgen(js_name = compactDecrement)]
+pub fn compact_decrement(owner: &Pubkey) -> Instruction {
+    crate::compact_decrement(owner)
+}
+
+/// Builds an instruction deleting the counter of `owner`.
+#[wasm_bindgen]
+pub fn delete(owner: &Pubkey) -> Instruction {
+    crate::delete(owner)
+}
+
+/// Builds an instruction deleting the counter of `owner` and sending its
+/// rent to `destination`, which has to sign as well unless it's the owner.
+#[wasm_bindgen(js_name = deleteTo)]
+pub fn delete_to(owner: &Pubkey, destination: &Pubkey) -> Instruction {
+    crate::delete_to(owner, destination)
+}
+
+/// Decodes a counter from the data of its account into an object with the
+/// fields of [`crate::Counter`], the owner being base58-encoded.
+#[wasm_bindgen(js_name = decodeCounter)]
+pub fn decode_counter(data: &[u8]) -> Result<JsValue, JsError> {
+    let counter = try_from_account_data(data)
+        .map_err(|e| JsError::new(&format!("invalid counter: {e:?}")))?;
+    Ok(serde_wasm_bindgen::to_value(&counter)?)
+}
@@ -0,0 +1,40 @@
+//! Events of the counters, decoded from transaction logs.
+
+use counter_client::{CounterCreated, CounterDecremented, CounterDeleted, CounterIncremented, ID};
+use events::decode::{decode_event, program_data};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+/// Change of a counter, with its count after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CounterEvent {
+    Created,
+    Incremented { count: u64 },
+    Decremented { count: u64 },
+    Deleted,
+}
+
+/// Returns the events logged by the counter program in `logs`, the log
+/// messages of a transaction, with the owner of their counter, in the order
+/// they were logged.
+pub fn decode_counter_events(logs: &[String]) -> Vec<(Pubkey, CounterEvent)> {
+    program_data(&ID.to_bytes(), logs)
+        .into_iter()
+        .filter_map(|data| {
+            if let Some(event) = decode_event::<CounterCreated>(data) {
+                Some((event.owner, CounterEvent::Created))
+            } else if let Some(event) = decode_event::<CounterIncremented>(data) {
+                let count = event.count;
+                Some((event.owner, CounterEvent::Incremented { count }))
+            } else if let Some(event) = decode_event::<CounterDecremented>(data) {
+                let count = event.count;
+                Some((event.owner, CounterEvent::Decremented { count }))
+            } else {
+                decode_event::<CounterDeleted>(data)
+                    .map(|event| (event.owner, CounterEvent::Deleted))
+            }
+        })
+        .map(|(owner, event)| (Pubkey::new_from_array(owner), event))
+        .collect()
+}
@@ -0,0 +1,127 @@
+//! Indexes the history of the counters of the counter program deployed on
+//! a cluster.
+//!
+//! `index` subscribes over WebSocket to the logs of the transactions of the
+//! program, decodes the events it logs and appends them to the history of
+//! the owner of their counter in a JSON file. Transactions confirmed while
+//! the indexer isn't running are not indexed. `history` prints the history
+//! of an owner from the file.
+
+mod event;
+mod store;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use counter_client::ID;
+use solana_client::{
+    pubsub_client::PubsubClient,
+    rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+use crate::{
+    event::decode_counter_events,
+    store::{Record, Store},
+};
+
+#[derive(Parser)]
+#[command(about = "Indexes the history of the counters of the counter program")]
+struct Cli {
+    /// JSON file storing the history of the counters.
+    #[arg(short, long, global = true, default_value = "counter-history.json")]
+    store: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Indexes the events of the counters until interrupted.
+    Index {
+        /// WebSocket URL of the cluster, or one of the `localnet` and
+        /// `devnet` monikers.
+        #[arg(short, long, default_value = "localnet")]
+        url: String,
+    },
+    /// Prints the history of the counter of an owner.
+    History {
+        /// Owner of the counter.
+        owner: Pubkey,
+        /// Prints the records as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Resolves cluster monikers to their WebSocket URLs.
+fn cluster_ws_url(url: &str) -> &str {
+    match url {
+        "localnet" => "ws://127.0.0.1:8900",
+        "devnet" => "wss://api.devnet.solana.com",
+        url => url,
+    }
+}
+
+fn index(mut store: Store, url: &str) -> Result<()> {
+    let (_subscription, receiver) = PubsubClient::logs_subscribe(
+        url,
+        RpcTransactionLogsFilter::Mentions(vec![ID.to_string()]),
+        RpcTransactionLogsConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+        },
+    )?;
+    eprintln!("Indexing the counters of {ID} from {url}");
+
+    for response in receiver {
+        let slot = response.context.slot;
+        let logs = response.value;
+        // Failed transactions log events before failing, but don't change
+        // the counters.
+        if logs.err.is_some() {
+            continue;
+        }
+
+        let events = decode_counter_events(&logs.logs);
+        if events.is_empty() {
+            continue;
+        }
+        for (owner, event) in events {
+            println!("{}: {owner} {event:?}", logs.signature);
+            let record = Record {
+                slot,
+                signature: logs.signature.clone(),
+                event,
+            };
+            store.push(&owner, record);
+        }
+        store.save()?;
+    }
+    Err(anyhow!("the subscription was closed"))
+}
+
+fn history(store: &Store, owner: &Pubkey, json: bool) -> Result<()> {
+    let records = store.history(owner);
+    if json {
+        println!("{}", serde_json::to_string_pretty(records)?);
+        return Ok(());
+    }
+    for record in records {
+        println!(
+            "slot {} {:?} in {}",
+            record.slot, record.event, record.signature
+        );
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let store = Store::open(cli.store)?;
+
+    match cli.command {
+        Command::Index { url } => index(store, cluster_ws_url(&url)),
+        Command::History { owner, json } => history(&store, &owner, json),
+    }
+}
@@ -0,0 +1,76 @@
+//! History of the counters of each owner, stored in a JSON file.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::event::CounterEvent;
+
+/// Event of a counter with the transaction which emitted it.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Record {
+    pub slot: u64,
+    pub signature: String,
+    #[serde(flatten)]
+    pub event: CounterEvent,
+}
+
+/// Records of the counters, by base58 address of their owner, oldest
+/// first.
+pub struct Store {
+    path: PathBuf,
+    histories: BTreeMap<String, Vec<Record>>,
+}
+
+impl Store {
+    /// Opens the store at `path`, which is empty if the file doesn't exist
+    /// yet.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let histories = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("invalid store {}", path.display()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).context(format!("failed to read {}", path.display())),
+        };
+        Ok(Self { path, histories })
+    }
+
+    /// Returns the records of the counter of `owner`.
+    pub fn history(&self, owner: &Pubkey) -> &[Record] {
+        self.histories
+            .get(&owner.to_string())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Appends `record` to the history of `owner`, without saving it.
+    pub fn push(&mut self, owner: &Pubkey, record: Record) {
+        self.histories
+            .entry(owner.to_string())
+            .or_default()
+            .push(record);
+    }
+
+    /// Writes the store to its file. A temporary file is renamed over it,
+    /// so an interrupted write doesn't lose the previous records.
+    pub fn save(&self) -> Result<()> {
+        let tmp = tmp_path(&self.path);
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.histories)?)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed to replace {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    PathBuf::from(tmp)
+}
@@ -0,0 +1,233 @@
+//! Reading accounts of another program.
+//!
+//! Awards badges to the counters of the `counter` example, with a level
+//! going up every [`COUNTS_PER_LEVEL`] counts. The program never calls the
+//! counter program, it only reads counter accounts passed to it.
+//!
+//! Anyone can create an account with the same layout as a counter, so the
+//! owner of the account has to be checked before trusting its data. Only
+//! the counter program can create accounts owned by it and modify their
+//! data. The layout comes from the `counter` crate, built with the
+//! `no-entrypoint` feature, so both programs always agree on it, and
+//! [`AccountData::load`] checks the owner before casting the data.
+
+#![no_std]
+
+use core::mem;
+
+use common::{
+    assert_layout,
+    checks::{require_owned_by, require_signer},
+    info, keys_eq, AccountData,
+};
+use counter::Counter;
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("Fo4my19JYV7kYgnHKjFeNntDcMnHaW6yEejKikWAs3GG");
+
+pub const BADGE_SEED: &str = "badge";
+
+/// Number of counts needed for every level.
+pub const COUNTS_PER_LEVEL: u64 = 10;
+
+/// Errors returned by the counter reader program.
+#[repr(u32)]
+pub enum CounterReaderError {
+    /// The counter didn't reach the next level.
+    NoProgress,
+}
+
+impl From<CounterReaderError> for ProgramError {
+    fn from(e: CounterReaderError) -> Self {
+        Namespace::CounterReader.error(e as u32)
+    }
+}
+
+/// On-chain badge of a counter.
+#[repr(C)]
+pub struct Badge {
+    pub counter: Pubkey,
+    pub level: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Badge, size = 48, align = 8, { counter: 0, level: 32, bump: 40 });
+
+impl Badge {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Counter reader program instruction discriminators.
+#[repr(u8)]
+pub enum CounterReaderInstruction {
+    /// Creates a badge for a counter.
+    Create,
+    /// Raises the level of a badge to the count of its counter.
+    LevelUp,
+}
+
+impl TryFrom<&u8> for CounterReaderInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Create),
+            1 => Ok(Self::LevelUp),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct CreateInstructionData {
+    pub bump: u8,
+}
+
+impl CreateInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = CounterReaderInstruction::try_from(instruction)?;
+
+    match instruction {
+        CounterReaderInstruction::Create => process_create(accounts, instruction_data),
+        CounterReaderInstruction::LevelUp => process_level_up(accounts),
+    }
+}
+
+/// Creates the badge of `counter`, at the level of its current count.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner of the counter
+///   1. `[]` Counter
+///   2. `[WRITE]` Badge PDA
+///   3. `[]` System program
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, counter, badge, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+    let level = counter_level(owner, counter)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CreateInstructionData::from_bytes(instruction_data)?;
+
+    let badge_pda = create_program_address(
+        &[
+            BADGE_SEED.as_bytes(),
+            counter.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(badge.key(), &badge_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(BADGE_SEED.as_bytes()),
+        Seed::from(counter.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: owner,
+        to: badge,
+        lamports: Rent::get()?.minimum_balance(Badge::LEN),
+        space: Badge::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut data = badge.try_borrow_mut_data()?;
+    let badge: &mut Badge = unsafe { &mut *data.as_mut_ptr().cast() };
+    badge.counter = *counter.key();
+    badge.level = level;
+    badge.bump = instruction_data.bump;
+
+    info!("Created a badge of level {}", level);
+
+    Ok(())
+}
+
+/// Raises the level of `badge` to the current count of its counter.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner of the counter
+///   1. `[]` Counter
+///   2. `[WRITE]` Badge PDA
+pub fn process_level_up(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, counter, badge] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+    let level = counter_level(owner, counter)?;
+
+    require_owned_by(badge, &ID)?;
+    let mut data = badge.try_borrow_mut_data()?;
+    if data.len() != Badge::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let badge: &mut Badge = unsafe { &mut *data.as_mut_ptr().cast() };
+    if !keys_eq(&badge.counter, counter.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if level <= badge.level {
+        return Err(CounterReaderError::NoProgress.into());
+    }
+    badge.level = level;
+
+    info!("Raised the badge to level {}", level);
+
+    Ok(())
+}
+
+/// Returns the level reached by `counter`, after checking that it belongs
+/// to `owner`.
+fn counter_level(owner: &AccountInfo, counter: &AccountInfo) -> Result<u64, ProgramError> {
+    // Fails for accounts not owned by the counter program, which could have
+    // any data.
+    let counter = Counter::load(counter)?;
+    if !keys_eq(&counter.owner, owner.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(counter.count / COUNTS_PER_LEVEL)
+}
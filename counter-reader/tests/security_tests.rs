@@ -0,0 +1,192 @@
+//! Attacks on the counter reader program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use common::AccountData;
+use counter::{Counter, COUNTER_SEED};
+use counter_reader::{
+    Badge, CounterReaderInstruction, CreateInstructionData, BADGE_SEED, COUNTS_PER_LEVEL,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(counter_reader::ID);
+const COUNTER_ID: Pubkey = Pubkey::new_from_array(counter::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: CounterReaderInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<CounterReaderInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the counter PDA of `owner` and its account, with `count`.
+fn counter_account(mollusk: &Mollusk, owner: &Pubkey, count: u64) -> (Pubkey, Account) {
+    let (counter, _) =
+        Pubkey::find_program_address(&[COUNTER_SEED.as_bytes(), owner.as_ref()], &COUNTER_ID);
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Counter::LEN),
+        Counter::LEN,
+        &COUNTER_ID,
+    );
+    account.data[0..32].copy_from_slice(owner.as_ref());
+    account.data[32..40].copy_from_slice(&count.to_le_bytes());
+    (counter, account)
+}
+
+/// Returns the badge PDA of `counter` and its bump.
+fn badge_pda(counter: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[BADGE_SEED.as_bytes(), counter.as_ref()], &ID)
+}
+
+/// Returns a badge of `counter` at the first level.
+fn badge_account(mollusk: &Mollusk, counter: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Badge::LEN),
+        Badge::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(counter.as_ref());
+    account.data[40] = badge_pda(counter).1;
+    account
+}
+
+/// Accounts of an owner with a counter at the first level and its badge,
+/// and of an attacker with a counter far ahead, without a badge.
+struct Setup {
+    mollusk: Mollusk,
+    counter: Pubkey,
+    badge: Pubkey,
+    attacker: Pubkey,
+    attacker_counter: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/counter_reader");
+
+        let attacker = Pubkey::new_unique();
+        let (attacker_counter, attacker_counter_account) =
+            counter_account(&mollusk, &attacker, 100 * COUNTS_PER_LEVEL);
+        let owner = Pubkey::new_unique();
+        let (counter, counter_account) = counter_account(&mollusk, &owner, COUNTS_PER_LEVEL);
+        let (badge, _) = badge_pda(&counter);
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (counter, counter_account),
+            (badge, badge_account(&mollusk, &counter)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (attacker_counter, attacker_counter_account),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            counter,
+            badge,
+            attacker,
+            attacker_counter,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Creates `badge` for `counter`, signed by the attacker.
+    fn attacker_create(&self, counter: &Pubkey, badge: &Pubkey, bump: u8) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new_readonly(*counter, false),
+            AccountMeta::new(*badge, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                CounterReaderInstruction::Create,
+                &CreateInstructionData::new(bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Raises `badge` to the level of `counter`, signed by the attacker.
+    fn attacker_level_up(&self, counter: &Pubkey, badge: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.attacker, true),
+            AccountMeta::new_readonly(*counter, false),
+            AccountMeta::new(*badge, false),
+        ];
+        Instruction::new_with_bytes(ID, &[CounterReaderInstruction::LevelUp as u8], ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.attacker_level_up(&setup.attacker_counter, &setup.badge);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the badge of the counter of the owner, before the
+/// owner does.
+#[test]
+fn test_create_badge_of_other_counter() {
+    let setup = Setup::new();
+    let (badge, bump) = badge_pda(&setup.counter);
+    let setup = setup.with(badge, Account::default());
+    setup.expect_err(
+        &setup.attacker_create(&setup.counter, &badge, bump),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker creates a badge for their counter at the address of the
+/// badge of the owner, which then can't be created.
+#[test]
+fn test_create_badge_at_address_of_other_counter() {
+    let setup = Setup::new();
+    let badge = setup.badge;
+    let (_, bump) = badge_pda(&setup.attacker_counter);
+    let setup = setup.with(badge, Account::default());
+    setup.expect_err(
+        &setup.attacker_create(&setup.attacker_counter, &badge, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker raises the badge of the owner to the level of their own
+/// counter.
+#[test]
+fn test_level_up_badge_of_other_counter() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_level_up(&setup.attacker_counter, &setup.badge),
+        ProgramError::InvalidAccountData,
+    );
+}
@@ -0,0 +1,246 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use common::AccountData;
+use counter::{Counter, COUNTER_SEED};
+use counter_reader::{
+    Badge, CounterReaderInstruction, CreateInstructionData, BADGE_SEED, COUNTS_PER_LEVEL,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(counter_reader::ID);
+const COUNTER_ID: Pubkey = Pubkey::new_from_array(counter::ID);
+
+/// `CounterReaderError::NoProgress`.
+const NO_PROGRESS: u32 = Namespace::CounterReader.code(0);
+
+/// Offset of the level of a badge.
+const LEVEL_OFFSET: usize = 32;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: CounterReaderInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<CounterReaderInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+/// Creates an account with the layout of a counter, owned by `program_id`.
+fn counter_account(mollusk: &Mollusk, program_id: &Pubkey, owner: &Pubkey, count: u64) -> Account {
+    let mut data = vec![0; Counter::LEN];
+    data[0..32].copy_from_slice(owner.as_ref());
+    data[32..40].copy_from_slice(&count.to_le_bytes());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Counter::LEN),
+        Counter::LEN,
+        program_id,
+    );
+    account.data = data;
+    account
+}
+
+fn badge_account(mollusk: &Mollusk, counter: &Pubkey, level: u64, bump: u8) -> Account {
+    let mut data = vec![0; Badge::LEN];
+    data[0..32].copy_from_slice(counter.as_ref());
+    data[LEVEL_OFFSET..LEVEL_OFFSET + 8].copy_from_slice(&level.to_le_bytes());
+    data[40] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Badge::LEN),
+        Badge::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// A counter of the counter program and its badge.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    counter: Pubkey,
+    badge: Pubkey,
+    badge_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new(count: u64, level: u64) -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/counter_reader");
+
+        let owner = Pubkey::new_unique();
+        let (counter, _) =
+            Pubkey::find_program_address(&[COUNTER_SEED.as_bytes(), owner.as_ref()], &COUNTER_ID);
+        let (badge, badge_bump) =
+            Pubkey::find_program_address(&[BADGE_SEED.as_bytes(), counter.as_ref()], &ID);
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (
+                counter,
+                counter_account(&mollusk, &COUNTER_ID, &owner, count),
+            ),
+            (badge, badge_account(&mollusk, &counter, level, badge_bump)),
+            system_program,
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            counter,
+            badge,
+            badge_bump,
+            accounts,
+        }
+    }
+
+    fn instruction_create(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new_readonly(self.counter, false),
+            AccountMeta::new(self.badge, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                CounterReaderInstruction::Create,
+                &CreateInstructionData::new(self.badge_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_level_up(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new_readonly(self.counter, false),
+            AccountMeta::new(self.badge, false),
+        ];
+        Instruction::new_with_bytes(ID, &[CounterReaderInstruction::LevelUp as u8], ix_accounts)
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(Counter::LEN, 40);
+    assert_eq!(Badge::LEN, 48);
+}
+
+#[test]
+fn test_create() {
+    let mut setup = Setup::new(2 * COUNTS_PER_LEVEL + 3, 0);
+    // The badge is created by the program.
+    setup.accounts[2].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_create(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.badge)
+                .owner(&ID)
+                .data(&badge_account(&setup.mollusk, &setup.counter, 2, setup.badge_bump).data)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_level_up() {
+    let mut setup = Setup::new(COUNTS_PER_LEVEL - 1, 0);
+
+    // The counter didn't reach the first level yet.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_level_up(),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(NO_PROGRESS))],
+    );
+
+    setup.accounts[1].1 = counter_account(
+        &setup.mollusk,
+        &COUNTER_ID,
+        &setup.owner,
+        3 * COUNTS_PER_LEVEL,
+    );
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_level_up(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.badge)
+                        .data_slice(LEVEL_OFFSET, &3u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_level_up(),
+                &[Check::err(ProgramError::Custom(NO_PROGRESS))],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_level_up_invalid() {
+    let mut setup = Setup::new(3 * COUNTS_PER_LEVEL, 0);
+
+    // An account with the layout of a counter, but owned by another program.
+    let fake_counter = counter_account(
+        &setup.mollusk,
+        &Pubkey::new_unique(),
+        &setup.owner,
+        3 * COUNTS_PER_LEVEL,
+    );
+    // A counter of someone else.
+    let other_counter = counter_account(
+        &setup.mollusk,
+        &COUNTER_ID,
+        &Pubkey::new_unique(),
+        3 * COUNTS_PER_LEVEL,
+    );
+    // An account of the counter program, but not a counter.
+    let mut short_counter = counter_account(
+        &setup.mollusk,
+        &COUNTER_ID,
+        &setup.owner,
+        3 * COUNTS_PER_LEVEL,
+    );
+    short_counter.data.truncate(32);
+
+    for (counter, err) in [
+        (fake_counter, ProgramError::IllegalOwner),
+        (other_counter, ProgramError::IllegalOwner),
+        (short_counter, ProgramError::InvalidAccountData),
+    ] {
+        setup.accounts[1].1 = counter;
+        setup.mollusk.process_and_validate_instruction(
+            &setup.instruction_level_up(),
+            &setup.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
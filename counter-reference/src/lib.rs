@@ -0,0 +1,419 @@
+//! The counter program written with `solana-program` and borsh, as a
+//! baseline for the pinocchio `counter`.
+//!
+//! It has the same instructions, accounts and instruction data, so the same
+//! scenarios run through both programs and their compute units and binary
+//! sizes can be compared.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    declare_id,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+declare_id!("6PBMfdh7d8QHxgxHVabPDAUoDMY3znAkFED1q8uFxa3E");
+
+pub const COUNTER_SEED: &str = "counter";
+
+/// On-chain representation of a counter.
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct Counter {
+    pub owner: Pubkey,
+    pub count: u64,
+}
+
+impl Counter {
+    /// Size of the serialized counter.
+    pub const LEN: usize = 32 + 8;
+}
+
+/// On-chain representation of a counter going back to zero in every epoch.
+#[derive(BorshDeserialize, BorshSerialize, Debug)]
+pub struct EpochCounter {
+    pub owner: Pubkey,
+    pub count: u64,
+    /// Epoch of the creation or of the last update.
+    pub epoch: u64,
+}
+
+impl EpochCounter {
+    /// Size of the serialized counter.
+    pub const LEN: usize = 32 + 8 + 8;
+}
+
+/// Counter program instructions, carrying the bump of the counter PDA,
+/// except for the compact ones.
+///
+/// Like the instructions of the pinocchio `counter`, they're encoded as the
+/// family of the instruction and the version of its data, followed by the
+/// borsh-encoded arguments of that version.
+#[derive(Clone, Copy, Debug)]
+pub enum CounterInstruction {
+    /// Creates/initializes a counter account for the given user.
+    Create { bump: u8 },
+    /// Increments a counter.
+    Increment { bump: u8 },
+    /// Decrements a counter.
+    Decrement { bump: u8 },
+    /// Deletes/closes a counter account.
+    Delete { bump: u8 },
+    /// Increments a counter, without the system program.
+    CompactIncrement,
+    /// Decrements a counter, without the system program.
+    CompactDecrement,
+    /// Increments a counter by `amount`, version 2 of `Increment`.
+    IncrementV2 { bump: u8, amount: u64 },
+    /// Decrements a counter by `amount`, version 2 of `Decrement`.
+    DecrementV2 { bump: u8, amount: u64 },
+    /// Creates an [`EpochCounter`] if `epoch` is set, or a [`Counter`],
+    /// version 2 of `Create`.
+    CreateV2 { bump: u8, epoch: bool },
+}
+
+impl CounterInstruction {
+    /// Encodes the instruction.
+    pub fn pack(&self) -> Vec<u8> {
+        let (family, version, args) = match *self {
+            Self::Create { bump } => (0, 1, borsh::to_vec(&bump)),
+            Self::Increment { bump } => (1, 1, borsh::to_vec(&bump)),
+            Self::Decrement { bump } => (2, 1, borsh::to_vec(&bump)),
+            Self::Delete { bump } => (3, 1, borsh::to_vec(&bump)),
+            Self::CompactIncrement => (4, 1, Ok(Vec::new())),
+            Self::CompactDecrement => (5, 1, Ok(Vec::new())),
+            Self::IncrementV2 { bump, amount } => (1, 2, borsh::to_vec(&(bump, amount))),
+            Self::DecrementV2 { bump, amount } => (2, 2, borsh::to_vec(&(bump, amount))),
+            Self::CreateV2 { bump, epoch } => (0, 2, borsh::to_vec(&(bump, epoch))),
+        };
+        [vec![family, version], args.unwrap()].concat()
+    }
+
+    /// Decodes an instruction, failing for unknown families and versions and
+    /// for arguments of the wrong length.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let [family, version, args @ ..] = data else {
+            return Err(ProgramError::InvalidInstructionData);
+        };
+        let instruction = match (family, version) {
+            (0, 1) => Self::Create {
+                bump: unpack(args)?,
+            },
+            (1, 1) => Self::Increment {
+                bump: unpack(args)?,
+            },
+            (2, 1) => Self::Decrement {
+                bump: unpack(args)?,
+            },
+            (3, 1) => Self::Delete {
+                bump: unpack(args)?,
+            },
+            (4, 1) if args.is_empty() => Self::CompactIncrement,
+            (5, 1) if args.is_empty() => Self::CompactDecrement,
+            (1, 2) => {
+                let (bump, amount) = unpack(args)?;
+                Self::IncrementV2 { bump, amount }
+            }
+            (2, 2) => {
+                let (bump, amount) = unpack(args)?;
+                Self::DecrementV2 { bump, amount }
+            }
+            // The mode is encoded as a bool, which borsh only decodes from
+            // 0 and 1, like the modes of the pinocchio `counter`.
+            (0, 2) => {
+                let (bump, epoch) = unpack(args)?;
+                Self::CreateV2 { bump, epoch }
+            }
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        Ok(instruction)
+    }
+}
+
+/// Deserializes the borsh-encoded arguments of an instruction.
+fn unpack<T: BorshDeserialize>(args: &[u8]) -> Result<T, ProgramError> {
+    T::try_from_slice(args).map_err(|_| ProgramError::InvalidInstructionData)
+}
+
+/// Returns the counter PDA of `owner` and its bump.
+pub fn find_counter_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COUNTER_SEED.as_bytes(), owner.as_ref()], &ID)
+}
+
+/// Builds the instruction returned by `build` for the bump of the counter
+/// PDA of `owner`.
+pub fn instruction(owner: &Pubkey, build: fn(u8) -> CounterInstruction) -> Instruction {
+    let (counter, bump) = find_counter_address(owner);
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(counter, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+    Instruction::new_with_bytes(ID, &build(bump).pack(), accounts)
+}
+
+/// Builds `instruction`, one of the compact instructions, for the counter
+/// PDA of `owner`.
+pub fn compact_instruction(owner: &Pubkey, instruction: CounterInstruction) -> Instruction {
+    let (counter, _) = find_counter_address(owner);
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(counter, false),
+    ];
+    Instruction::new_with_bytes(ID, &instruction.pack(), accounts)
+}
+
+/// Entrypoint of the program.
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction = CounterInstruction::unpack(instruction_data)?;
+
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let counter = next_account_info(accounts_iter)?;
+
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // The compact instructions carry no bump, so the seeds of their counter
+    // aren't checked, only its owner.
+    let bump = match instruction {
+        CounterInstruction::Create { bump }
+        | CounterInstruction::Increment { bump }
+        | CounterInstruction::Decrement { bump }
+        | CounterInstruction::Delete { bump }
+        | CounterInstruction::IncrementV2 { bump, .. }
+        | CounterInstruction::DecrementV2 { bump, .. }
+        | CounterInstruction::CreateV2 { bump, .. } => bump,
+        CounterInstruction::CompactIncrement => {
+            return process_update(program_id, owner, counter, 1)
+        }
+        CounterInstruction::CompactDecrement => {
+            return process_update(program_id, owner, counter, -1)
+        }
+    };
+
+    let system_program = next_account_info(accounts_iter)?;
+    // An optional fourth account receives the rent of a deleted counter
+    // instead of the owner.
+    let destination = next_account_info(accounts_iter).unwrap_or(owner);
+
+    let seeds: &[&[u8]] = &[COUNTER_SEED.as_bytes(), owner.key.as_ref(), &[bump]];
+    if Pubkey::create_program_address(seeds, program_id)? != *counter.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    match instruction {
+        CounterInstruction::Create { .. } => {
+            process_create(program_id, owner, counter, system_program, seeds, false)
+        }
+        CounterInstruction::CreateV2 { epoch, .. } => {
+            process_create(program_id, owner, counter, system_program, seeds, epoch)
+        }
+        CounterInstruction::Increment { .. } => process_update(program_id, owner, counter, 1),
+        CounterInstruction::Decrement { .. } => process_update(program_id, owner, counter, -1),
+        CounterInstruction::IncrementV2 { amount, .. } => {
+            process_update(program_id, owner, counter, i128::from(amount))
+        }
+        CounterInstruction::DecrementV2 { amount, .. } => {
+            process_update(program_id, owner, counter, -i128::from(amount))
+        }
+        CounterInstruction::Delete { .. } => {
+            process_delete(program_id, owner, counter, destination)
+        }
+        CounterInstruction::CompactIncrement | CounterInstruction::CompactDecrement => {
+            unreachable!("handled above")
+        }
+    }
+}
+
+/// Creates/initializes a counter account for the given user, an
+/// [`EpochCounter`] if `epoch` is set.
+fn process_create(
+    program_id: &Pubkey,
+    owner: &AccountInfo,
+    counter: &AccountInfo,
+    system_program: &AccountInfo,
+    seeds: &[&[u8]],
+    epoch: bool,
+) -> ProgramResult {
+    if counter.owner != &system_program::ID || !counter.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let space = if epoch {
+        EpochCounter::LEN
+    } else {
+        Counter::LEN
+    };
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(space);
+    if counter.lamports() == 0 {
+        invoke_signed(
+            &system_instruction::create_account(
+                owner.key,
+                counter.key,
+                lamports,
+                space as u64,
+                program_id,
+            ),
+            &[owner.clone(), counter.clone(), system_program.clone()],
+            &[seeds],
+        )?;
+    } else {
+        // The address already holds lamports, so `create_account` would fail.
+        let missing = lamports.saturating_sub(counter.lamports());
+        if missing > 0 {
+            invoke(
+                &system_instruction::transfer(owner.key, counter.key, missing),
+                &[owner.clone(), counter.clone(), system_program.clone()],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(counter.key, space as u64),
+            &[counter.clone(), system_program.clone()],
+            &[seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(counter.key, program_id),
+            &[counter.clone(), system_program.clone()],
+            &[seeds],
+        )?;
+    }
+    if !rent.is_exempt(counter.lamports(), counter.data_len()) {
+        return Err(ProgramError::AccountNotRentExempt);
+    }
+
+    if epoch {
+        let data = EpochCounter {
+            owner: *owner.key,
+            count: 0,
+            epoch: Clock::get()?.epoch,
+        };
+        store(&data, counter)?;
+    } else {
+        let data = Counter {
+            owner: *owner.key,
+            count: 0,
+        };
+        store(&data, counter)?;
+    }
+
+    msg!("Created the counter account");
+
+    Ok(())
+}
+
+/// Increments or decrements a counter by `delta`.
+fn process_update(
+    program_id: &Pubkey,
+    owner: &AccountInfo,
+    counter: &AccountInfo,
+    delta: i128,
+) -> ProgramResult {
+    // Saturate like the pinocchio `counter`, also for amounts beyond `i64`.
+    let update = |count: u64| (i128::from(count) + delta).clamp(0, i128::from(u64::MAX)) as u64;
+
+    // Epoch counters are told apart by their length, like in the pinocchio
+    // `counter`, and start over in every epoch.
+    let count = if counter.data_len() == EpochCounter::LEN {
+        let mut data: EpochCounter = load(program_id, counter)?;
+        check_owner(&data.owner, owner)?;
+        let epoch = Clock::get()?.epoch;
+        if epoch > data.epoch {
+            data.count = 0;
+            data.epoch = epoch;
+        }
+        data.count = update(data.count);
+        store(&data, counter)?;
+        data.count
+    } else {
+        let mut data: Counter = load(program_id, counter)?;
+        check_owner(&data.owner, owner)?;
+        data.count = update(data.count);
+        store(&data, counter)?;
+        data.count
+    };
+
+    if delta > 0 {
+        msg!("Incremented the counter to {}", count);
+    } else {
+        msg!("Decremented the counter to {}", count);
+    }
+
+    Ok(())
+}
+
+/// Deletes/closes a counter account, sending its rent to `destination`,
+/// which has to sign unless it's the owner.
+fn process_delete(
+    program_id: &Pubkey,
+    owner: &AccountInfo,
+    counter: &AccountInfo,
+    destination: &AccountInfo,
+) -> ProgramResult {
+    let counter_owner = if counter.data_len() == EpochCounter::LEN {
+        load::<EpochCounter>(program_id, counter)?.owner
+    } else {
+        load::<Counter>(program_id, counter)?.owner
+    };
+    check_owner(&counter_owner, owner)?;
+
+    if destination.key != owner.key && !destination.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Close the counter account by moving its lamports to the destination,
+    // zeroing its data and giving it back to the system program, like
+    // `close` of pinocchio.
+    let lamports = destination
+        .lamports()
+        .checked_add(counter.lamports())
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **destination.try_borrow_mut_lamports()? = lamports;
+    **counter.try_borrow_mut_lamports()? = 0;
+    counter.try_borrow_mut_data()?.fill(0);
+    counter.assign(&system_program::ID);
+
+    Ok(())
+}
+
+/// Deserializes the counter, checking that it's owned by the program.
+fn load<T: BorshDeserialize>(
+    program_id: &Pubkey,
+    counter: &AccountInfo,
+) -> Result<T, ProgramError> {
+    if counter.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    T::try_from_slice(&counter.data.borrow()).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Checks that the counter owned by `counter_owner` was created by `owner`.
+fn check_owner(counter_owner: &Pubkey, owner: &AccountInfo) -> ProgramResult {
+    if counter_owner != owner.key {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Serializes `data` into the counter.
+fn store<T: BorshSerialize>(data: &T, counter: &AccountInfo) -> ProgramResult {
+    data.serialize(&mut &mut counter.data.borrow_mut()[..])
+        .map_err(|_| ProgramError::AccountDataTooSmall)
+}
@@ -0,0 +1,147 @@
+//! Attacks on the reference counter program, each rejected with the same
+//! error as in the pinocchio counter.
+
+use counter_reference::{
+    compact_instruction, find_counter_address, instruction, Counter, CounterInstruction, ID,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program::program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::funded_account;
+
+/// Returns a counter of `owner` with `count`.
+fn counter_account(mollusk: &Mollusk, owner: &Pubkey, count: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Counter::LEN),
+        Counter::LEN,
+        &ID,
+    );
+    account.data = borsh::to_vec(&Counter {
+        owner: *owner,
+        count,
+    })
+    .unwrap();
+    account
+}
+
+/// Accounts of an existing counter, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    counter: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/counter_reference");
+
+        let owner = Pubkey::new_unique();
+        let (counter, _) = find_counter_address(&owner);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (owner, funded_account(LAMPORTS_PER_SOL)),
+            (counter, counter_account(&mollusk, &owner, 1)),
+            (attacker, funded_account(LAMPORTS_PER_SOL)),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            counter,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let mut instruction = instruction(&setup.owner, |bump| CounterInstruction::Increment { bump });
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker increments the counter of the owner, with the bump of their
+/// own counter.
+#[test]
+fn test_counter_of_other_owner() {
+    let setup = Setup::new();
+    let mut instruction = instruction(&setup.attacker, |bump| CounterInstruction::Increment {
+        bump,
+    });
+    instruction.accounts[1].pubkey = setup.counter;
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The compact instructions don't check the seeds of the counter, so the
+/// attacker decrements the counter of the owner with them.
+#[test]
+fn test_compact_counter_of_other_owner() {
+    let setup = Setup::new();
+    let mut instruction =
+        compact_instruction(&setup.attacker, CounterInstruction::CompactDecrement);
+    instruction.accounts[1].pubkey = setup.counter;
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// A counter of the attacker at the address of their counter PDA, owned by
+/// another program.
+#[test]
+fn test_spoofed_counter() {
+    let setup = Setup::new();
+    let (counter, _) = find_counter_address(&setup.attacker);
+    let mut counter_account = counter_account(&setup.mollusk, &setup.attacker, u64::MAX);
+    counter_account.owner = Pubkey::new_unique();
+    let setup = setup.with(counter, counter_account);
+    setup.expect_err(
+        &instruction(&setup.attacker, |bump| CounterInstruction::Increment {
+            bump,
+        }),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The rent of the counter is swept to a third party who didn't sign the
+/// deletion.
+#[test]
+fn test_unauthorized_destination() {
+    let setup = Setup::new();
+    let mut instruction = instruction(&setup.owner, |bump| CounterInstruction::Delete { bump });
+    instruction
+        .accounts
+        .push(AccountMeta::new(setup.attacker, false));
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The owner creates their counter again, which would reset its count.
+#[test]
+fn test_reinitialization() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &instruction(&setup.owner, |bump| CounterInstruction::Create { bump }),
+        ProgramError::AccountAlreadyInitialized,
+    );
+}
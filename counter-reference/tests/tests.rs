@@ -0,0 +1,229 @@
+use std::{fmt::Write, fs};
+
+use borsh::BorshDeserialize;
+use counter_client::{CounterMode, InstructionArgs};
+use counter_reference::{
+    compact_instruction, find_counter_address, instruction, Counter, CounterInstruction, ID,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::Instruction;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_pubkey::Pubkey;
+use test_utils::{assertions::assert_closed, funded_account};
+
+#[test]
+fn test_counter_success() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/counter_reference");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_unique();
+    let (counter, _) = find_counter_address(&owner);
+
+    let mut accounts = vec![
+        (owner, funded_account(42 * LAMPORTS_PER_SOL)),
+        (counter, Account::new(0, 0, &system_program)),
+        (system_program, system_account),
+    ];
+    let steps: [(fn(u8) -> CounterInstruction, u64); 6] = [
+        (|bump| CounterInstruction::Create { bump }, 0),
+        (|bump| CounterInstruction::Increment { bump }, 1),
+        (|bump| CounterInstruction::Increment { bump }, 2),
+        (|bump| CounterInstruction::Decrement { bump }, 1),
+        (
+            |bump| CounterInstruction::IncrementV2 { bump, amount: 5 },
+            6,
+        ),
+        (
+            |bump| CounterInstruction::DecrementV2 { bump, amount: 4 },
+            2,
+        ),
+    ];
+    for (build, count) in steps {
+        let res = mollusk.process_and_validate_instruction(
+            &instruction(&owner, build),
+            &accounts,
+            &[Check::success()],
+        );
+        let state = Counter::try_from_slice(&res.get_account(&counter).unwrap().data).unwrap();
+        assert_eq!(state.owner, owner);
+        assert_eq!(state.count, count);
+        accounts = res.resulting_accounts;
+    }
+
+    let res = mollusk.process_and_validate_instruction(
+        &instruction(&owner, |bump| CounterInstruction::Delete { bump }),
+        &accounts,
+        &[Check::success()],
+    );
+    assert_closed(&res, &counter);
+}
+
+/// Both programs take the same instruction data.
+#[test]
+fn test_same_instruction_data() {
+    for (reference, pinocchio) in [
+        (
+            CounterInstruction::Create { bump: 255 },
+            InstructionArgs::Create { bump: 255 },
+        ),
+        (
+            CounterInstruction::Increment { bump: 254 },
+            InstructionArgs::Increment { bump: 254 },
+        ),
+        (
+            CounterInstruction::Decrement { bump: 253 },
+            InstructionArgs::Decrement { bump: 253 },
+        ),
+        (
+            CounterInstruction::Delete { bump: 252 },
+            InstructionArgs::Delete { bump: 252 },
+        ),
+        (
+            CounterInstruction::CompactIncrement,
+            InstructionArgs::CompactIncrement,
+        ),
+        (
+            CounterInstruction::CompactDecrement,
+            InstructionArgs::CompactDecrement,
+        ),
+        (
+            CounterInstruction::IncrementV2 {
+                bump: 251,
+                amount: u64::MAX,
+            },
+            InstructionArgs::IncrementV2 {
+                bump: 251,
+                amount: u64::MAX,
+            },
+        ),
+        (
+            CounterInstruction::DecrementV2 {
+                bump: 250,
+                amount: 7,
+            },
+            InstructionArgs::DecrementV2 {
+                bump: 250,
+                amount: 7,
+            },
+        ),
+        (
+            CounterInstruction::CreateV2 {
+                bump: 249,
+                epoch: true,
+            },
+            InstructionArgs::CreateV2 {
+                bump: 249,
+                mode: CounterMode::Epoch,
+            },
+        ),
+    ] {
+        let data = reference.pack();
+        assert_eq!(data, pinocchio.encode());
+        assert_eq!(InstructionArgs::decode(&data), Ok(pinocchio));
+        assert!(CounterInstruction::unpack(&data).is_ok());
+    }
+}
+
+/// Runs `instructions` one after another on a new counter of `owner` and
+/// returns the compute units consumed by each.
+fn compute_units(
+    mollusk: &Mollusk,
+    owner: Pubkey,
+    counter: Pubkey,
+    instructions: &[Instruction],
+) -> Vec<u64> {
+    let (system_program, system_account) = keyed_account_for_system_program();
+    let mut accounts = vec![
+        (owner, funded_account(42 * LAMPORTS_PER_SOL)),
+        (counter, Account::new(0, 0, &system_program)),
+        (system_program, system_account),
+    ];
+    instructions
+        .iter()
+        .map(|instruction| {
+            let res = mollusk.process_and_validate_instruction(
+                instruction,
+                &accounts,
+                &[Check::success()],
+            );
+            accounts = res.resulting_accounts;
+            res.compute_units_consumed
+        })
+        .collect()
+}
+
+/// Compares the compute units and the binary size of the pinocchio counter
+/// with this one and writes them into `benches/comparison.md`.
+#[test]
+fn test_compare_with_pinocchio() {
+    let owner = Pubkey::new_unique();
+
+    let mollusk = Mollusk::new(&ID, "target/deploy/counter_reference");
+    let (counter, _) = find_counter_address(&owner);
+    let reference = compute_units(
+        &mollusk,
+        owner,
+        counter,
+        &[
+            instruction(&owner, |bump| CounterInstruction::Create { bump }),
+            instruction(&owner, |bump| CounterInstruction::Increment { bump }),
+            instruction(&owner, |bump| CounterInstruction::Decrement { bump }),
+            compact_instruction(&owner, CounterInstruction::CompactIncrement),
+            compact_instruction(&owner, CounterInstruction::CompactDecrement),
+            instruction(&owner, |bump| CounterInstruction::Delete { bump }),
+        ],
+    );
+
+    let mollusk = Mollusk::new(&counter_client::ID, "../counter/target/deploy/counter");
+    let (counter, _) = counter_client::find_counter_address(&owner);
+    let pinocchio = compute_units(
+        &mollusk,
+        owner,
+        counter,
+        &[
+            counter_client::create(&owner),
+            counter_client::increment(&owner),
+            counter_client::decrement(&owner),
+            counter_client::compact_increment(&owner),
+            counter_client::compact_decrement(&owner),
+            counter_client::delete(&owner),
+        ],
+    );
+
+    let mut markdown =
+        String::from("| Instruction | pinocchio | solana-program |\n|---|---|---|\n");
+    for (name, (pinocchio, reference)) in [
+        "create",
+        "increment",
+        "decrement",
+        "compact_increment",
+        "compact_decrement",
+        "delete",
+    ]
+    .iter()
+    .zip(pinocchio.iter().zip(&reference))
+    {
+        writeln!(markdown, "| {name} | {pinocchio} CU | {reference} CU |").unwrap();
+        assert!(
+            pinocchio < reference,
+            "{name}: pinocchio consumed {pinocchio} compute units, solana-program {reference}"
+        );
+    }
+
+    let pinocchio_size = fs::metadata("../counter/target/deploy/counter.so")
+        .unwrap()
+        .len();
+    let reference_size = fs::metadata("target/deploy/counter_reference.so")
+        .unwrap()
+        .len();
+    writeln!(
+        markdown,
+        "| binary size | {pinocchio_size} B | {reference_size} B |"
+    )
+    .unwrap();
+    assert!(pinocchio_size < reference_size);
+
+    fs::create_dir_all("benches").unwrap();
+    fs::write("benches/comparison.md", markdown).unwrap();
+}
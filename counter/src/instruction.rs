@@ -0,0 +1,86 @@
+//! Instructions of the counter program.
+
+use macros::{InstructionData, InstructionDiscriminator};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use shank::{ShankInstruction, ShankType};
+
+/// Counter program instruction discriminators, the family of each
+/// instruction followed by the version of its data.
+#[derive(InstructionDiscriminator, ShankInstruction)]
+#[repr(u8)]
+#[versioned]
+pub enum CounterInstruction {
+    /// Creates/initializes a counter account for the given user. Instruction
+    /// data: [`CounterInstructionData`] in version 1, which creates a
+    /// [`Counter`](crate::Counter), and [`CreateInstructionDataV2`] in
+    /// version 2.
+    #[account(0, writable, signer, name = "owner", desc = "Owner and payer")]
+    #[account(1, writable, name = "counter", desc = "Counter PDA")]
+    #[account(2, name = "system_program", desc = "System program")]
+    Create,
+    /// Increments a counter. Instruction data: [`CounterInstructionData`] in
+    /// version 1, which increments it by one, and [`CounterInstructionDataV2`]
+    /// in version 2.
+    #[account(0, signer, name = "owner", desc = "Owner of the counter")]
+    #[account(1, writable, name = "counter", desc = "Counter PDA")]
+    #[account(2, name = "system_program", desc = "System program")]
+    Increment,
+    /// Decrements a counter. Instruction data: [`CounterInstructionData`] in
+    /// version 1, which decrements it by one, and [`CounterInstructionDataV2`]
+    /// in version 2.
+    #[account(0, signer, name = "owner", desc = "Owner of the counter")]
+    #[account(1, writable, name = "counter", desc = "Counter PDA")]
+    #[account(2, name = "system_program", desc = "System program")]
+    Decrement,
+    /// Deletes/closes a counter account.
+    #[account(0, writable, signer, name = "owner", desc = "Owner, receives the rent")]
+    #[account(1, writable, name = "counter", desc = "Counter PDA")]
+    #[account(2, name = "system_program", desc = "System program")]
+    #[account(
+        3,
+        optional,
+        writable,
+        signer,
+        name = "destination",
+        desc = "Receives the rent instead of the owner"
+    )]
+    Delete,
+    /// Increments a counter, without instruction data and without the
+    /// system program.
+    #[account(0, signer, name = "owner", desc = "Owner of the counter")]
+    #[account(1, writable, name = "counter", desc = "Counter PDA")]
+    CompactIncrement,
+    /// Decrements a counter, without instruction data and without the
+    /// system program.
+    #[account(0, signer, name = "owner", desc = "Owner of the counter")]
+    #[account(1, writable, name = "counter", desc = "Counter PDA")]
+    CompactDecrement,
+}
+
+/// Counter program instruction data.
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CounterInstructionData {
+    pub bump: u8,
+}
+
+/// Counter program instruction data of version 2 of
+/// [`CounterInstruction::Increment`] and [`CounterInstruction::Decrement`].
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CounterInstructionDataV2 {
+    pub bump: u8,
+    /// Amount the counter is incremented or decremented by.
+    pub amount: u64,
+}
+
+/// Counter program instruction data of version 2 of
+/// [`CounterInstruction::Create`].
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CreateInstructionDataV2 {
+    pub bump: u8,
+    /// [`CounterMode`](crate::CounterMode) of the counter.
+    pub mode: u8,
+}
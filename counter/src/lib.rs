@@ -1,69 +1,72 @@
 #![no_std]
 
-use core::mem;
-
+use common::{
+    check_pda,
+    checks::{require_program, require_program_id, require_signer},
+    close::close_account,
+    create::create_account,
+    info, keys_eq,
+    version::Version,
+    AccountData,
+};
+use errors::SharedError;
+use events::emit;
+use macros::Event;
 use pinocchio::{
     account_info::AccountInfo,
     entrypoint::{InstructionContext, MaybeAccount},
-    lazy_program_entrypoint, no_allocator, nostd_panic_handler,
+    instruction::{Seed, Signer},
     program_error::ProgramError,
-    pubkey::{create_program_address, Pubkey},
-    sysvars::{rent::Rent, Sysvar},
+    pubkey::Pubkey,
+    sysvars::{clock::Clock, Sysvar},
     ProgramResult,
 };
-use pinocchio_log::log;
-use pinocchio_system::instructions::CreateAccount;
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+use pinocchio::{lazy_program_entrypoint, no_allocator, nostd_panic_handler};
+
+pub mod instruction;
+pub mod state;
+
+pub use instruction::{
+    CounterInstruction, CounterInstructionData, CounterInstructionDataV2, CreateInstructionDataV2,
+};
+pub use state::{Counter, CounterMode, EpochCounter, COUNTER_SEED};
 
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
 lazy_program_entrypoint!(process_instruction);
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
 no_allocator!();
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
 nostd_panic_handler!();
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+common::build_metadata!();
 
 pinocchio_pubkey::declare_id!("9YxC88EDFbs4a2ypUmKy8HPUFdg1FTnwnZm7358J3w9u");
 
-pub const COUNTER_SEED: &'static str = "counter";
-
-/// On-chain representation of a counter.
-#[repr(C)]
-pub struct Counter {
+/// Emitted when a counter is created.
+#[derive(Clone, Copy, Event)]
+pub struct CounterCreated {
     pub owner: Pubkey,
-    pub count: u64,
-}
-
-impl Counter {
-    pub const LEN: usize = mem::size_of::<Self>();
 }
 
-/// Counter program instruction discriminators.
-#[repr(u8)]
-pub enum CounterInstruction {
-    /// Creates/initializes a counter account for the given user.
-    Create,
-    /// Increments a counter.
-    Increment,
-    /// Decrements a counter.
-    Decrement,
-    /// Deletes/closes a counter account.
-    Delete,
+/// Emitted when a counter is incremented.
+#[derive(Clone, Copy, Event)]
+pub struct CounterIncremented {
+    pub owner: Pubkey,
+    pub count: u64,
 }
 
-impl TryFrom<&u8> for CounterInstruction {
-    type Error = ProgramError;
-
-    fn try_from(value: &u8) -> Result<Self, Self::Error> {
-        match *value {
-            0 => Ok(Self::Create),
-            1 => Ok(Self::Increment),
-            2 => Ok(Self::Decrement),
-            3 => Ok(Self::Delete),
-            _ => Err(ProgramError::InvalidInstructionData),
-        }
-    }
+/// Emitted when a counter is decremented.
+#[derive(Clone, Copy, Event)]
+pub struct CounterDecremented {
+    pub owner: Pubkey,
+    pub count: u64,
 }
 
-/// Counter program instruction data.
-#[repr(C)]
-pub struct CounterInstructionData {
-    pub bump: u8,
+/// Emitted when a counter is deleted.
+#[derive(Clone, Copy, Event)]
+pub struct CounterDeleted {
+    pub owner: Pubkey,
 }
 
 /// Entrypoint of the program.
@@ -76,142 +79,272 @@ pub fn process_instruction(mut context: InstructionContext) -> ProgramResult {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
     // Check if the owner signed the transaction.
-    if !owner.is_signer() {
-        return Err(ProgramError::MissingRequiredSignature);
-    }
+    require_signer(owner)?;
 
-    // The second account is the counter PDA.
+    // The second account is the counter PDA, which can't be the owner.
     let MaybeAccount::Account(mut counter) = context.next_account()? else {
-        return Err(ProgramError::NotEnoughAccountKeys);
+        return Err(ProgramError::InvalidArgument);
     };
 
-    // The third (and last) account is the system program.
-    context.next_account()?;
-
-    // Deserialize instruction and instruction data.
-    let (instruction, instruction_data) = context
-        .instruction_data()?
-        .split_first()
-        .ok_or(ProgramError::InvalidInstructionData)?;
-    let instruction = CounterInstruction::try_from(instruction)?;
-    let instruction_data: &CounterInstructionData = unsafe { &*instruction_data.as_ptr().cast() };
-
-    let counter_pda = create_program_address(
-        &[
-            COUNTER_SEED.as_bytes(),
-            owner.key(),
-            &[instruction_data.bump],
-        ],
-        &ID,
-    )?;
-    if counter.key() != &counter_pda {
-        return Err(ProgramError::InvalidSeeds);
-    }
+    // The third account is the system program, which only the compact
+    // instructions don't take.
+    let system_program = if context.remaining() > 0 {
+        let MaybeAccount::Account(system_program) = context.next_account()? else {
+            return Err(ProgramError::IncorrectProgramId);
+        };
+        require_program(&system_program, &pinocchio_system::ID)?;
+        Some(system_program)
+    } else {
+        None
+    };
+
+    // An optional fourth account receives the rent of a deleted counter
+    // instead of the owner, who may also be passed again.
+    let destination = if context.remaining() > 0 {
+        match context.next_account()? {
+            MaybeAccount::Account(destination) => Some(destination),
+            MaybeAccount::Duplicated(0) => None,
+            MaybeAccount::Duplicated(_) => return Err(SharedError::DuplicateAccount.into()),
+        }
+    } else {
+        None
+    };
+
+    // Check that the program runs under its declared address, which the
+    // counter PDAs are derived from.
+    require_program_id(context.program_id()?, &ID)?;
+
+    // Deserialize instruction, its version and instruction data.
+    let (instruction, version, instruction_data) =
+        CounterInstruction::split(context.instruction_data()?)?;
+
+    // The compact instructions carry no bump, so the seeds of their counter
+    // aren't checked. The program only owns counters created at the PDA of
+    // their owner, which is checked against the stored one instead, and
+    // none of them needs the bump.
+    let (bump, amount, mode) = if matches!(
+        instruction,
+        CounterInstruction::CompactIncrement | CounterInstruction::CompactDecrement
+    ) {
+        if version != Version::V1 || !instruction_data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        (0, 1, CounterMode::Plain)
+    } else {
+        if system_program.is_none() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        // Version 1 increments and decrements by one, version 2 by the
+        // amount in the instruction data. Version 2 of the creation picks
+        // the mode of the counter. Only they have a version 2.
+        let (bump, amount, mode) = match (&instruction, version) {
+            (_, Version::V1) => (
+                CounterInstructionData::from_bytes(instruction_data)?.bump,
+                1,
+                CounterMode::Plain,
+            ),
+            (CounterInstruction::Increment | CounterInstruction::Decrement, Version::V2) => {
+                let instruction_data = CounterInstructionDataV2::from_bytes(instruction_data)?;
+                (
+                    instruction_data.bump,
+                    instruction_data.amount,
+                    CounterMode::Plain,
+                )
+            }
+            (CounterInstruction::Create, Version::V2) => {
+                let instruction_data = CreateInstructionDataV2::from_bytes(instruction_data)?;
+                (
+                    instruction_data.bump,
+                    1,
+                    CounterMode::try_from(instruction_data.mode)?,
+                )
+            }
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        check_pda(
+            &[COUNTER_SEED.as_bytes(), owner.key()],
+            bump,
+            counter.key(),
+            &ID,
+        )?;
+        (bump, amount, mode)
+    };
 
     match instruction {
-        CounterInstruction::Create => process_create(&owner, &mut counter)?,
-        CounterInstruction::Increment => process_increment(&owner, &mut counter)?,
-        CounterInstruction::Decrement => process_decrement(&owner, &mut counter)?,
-        CounterInstruction::Delete => process_delete(&mut owner, &mut counter)?,
+        CounterInstruction::Create => process_create(&owner, &mut counter, bump, mode)?,
+        CounterInstruction::Increment | CounterInstruction::CompactIncrement => {
+            process_increment(&owner, &mut counter, amount)?
+        }
+        CounterInstruction::Decrement | CounterInstruction::CompactDecrement => {
+            process_decrement(&owner, &mut counter, amount)?
+        }
+        CounterInstruction::Delete => {
+            process_delete(&mut owner, &mut counter, destination.as_ref())?
+        }
     }
 
     Ok(())
 }
 
-/// Creates/initializes a counter account for the given user.
-pub fn process_create(owner: &AccountInfo, counter: &mut AccountInfo) -> ProgramResult {
-    // Create the PDA.
-    CreateAccount {
-        from: owner,
-        to: &counter,
-        lamports: Rent::get()?.minimum_balance(Counter::LEN),
-        space: Counter::LEN as u64,
-        owner: &ID,
+/// Creates/initializes a counter account of `mode` for the given user.
+pub fn process_create(
+    owner: &AccountInfo,
+    counter: &mut AccountInfo,
+    bump: u8,
+    mode: CounterMode,
+) -> ProgramResult {
+    // Create the PDA, signing for it with its seeds, unless the address is
+    // already in use.
+    let bump = [bump];
+    let counter_seeds = [
+        Seed::from(COUNTER_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    let space = match mode {
+        CounterMode::Plain => Counter::LEN,
+        CounterMode::Epoch => EpochCounter::LEN,
+    };
+    create_account(owner, counter, space, &ID, &[Signer::from(&counter_seeds)])?;
+
+    // Deserialize the new counter PDA, writing its prefix, and initialize
+    // the counter.
+    match mode {
+        CounterMode::Plain => {
+            let mut data = Counter::init(counter)?;
+            data.owner = *owner.key();
+            data.count = 0;
+        }
+        CounterMode::Epoch => {
+            let mut data = EpochCounter::init(counter)?;
+            data.owner = *owner.key();
+            data.count = 0;
+            data.epoch = Clock::get()?.epoch;
+        }
     }
-    .invoke()?;
-
-    // Deserialize the counter PDA.
-    let mut data = counter.try_borrow_mut_data()?;
-    let data: &mut Counter = unsafe { &mut *data.as_mut_ptr().cast() };
-
-    // Initialize the counter.
-    data.owner = *owner.key();
-    data.count = 0;
 
-    log!("Created the counter account");
+    info!("Created the counter account");
+    emit!(CounterCreated {
+        owner: *owner.key()
+    });
 
     Ok(())
 }
 
-/// Increments a counter.
-pub fn process_increment(owner: &AccountInfo, counter: &mut AccountInfo) -> ProgramResult {
-    // Check if the counter PDA is owned by the program.
-    if !counter.is_owned_by(&ID) {
-        return Err(ProgramError::IllegalOwner);
-    }
+/// Increments a counter by `amount`.
+pub fn process_increment(
+    owner: &AccountInfo,
+    counter: &mut AccountInfo,
+    amount: u64,
+) -> ProgramResult {
+    // Increment the counter.
+    let count = update_count(owner, counter, |count| count.saturating_add(amount))?;
 
-    // Deserialize the counter PDA.
-    let mut data = counter.try_borrow_mut_data()?;
-    let data: &mut Counter = unsafe { &mut *data.as_mut_ptr().cast() };
+    info!("Incremented the counter to {}", count);
+    emit!(CounterIncremented {
+        owner: *owner.key(),
+        count,
+    });
 
-    // Check if the counter was created by the `owner`.
-    if &data.owner != owner.key() {
-        return Err(ProgramError::IllegalOwner);
-    }
+    Ok(())
+}
 
-    // Increment the counter.
-    data.count = data.count.saturating_add(1);
+/// Decrements a counter by `amount`.
+pub fn process_decrement(
+    owner: &AccountInfo,
+    counter: &mut AccountInfo,
+    amount: u64,
+) -> ProgramResult {
+    // Decrement the counter.
+    let count = update_count(owner, counter, |count| count.saturating_sub(amount))?;
 
-    log!("Incremented the counter to {}", data.count);
+    info!("Decremented the counter to {}", count);
+    emit!(CounterDecremented {
+        owner: *owner.key(),
+        count,
+    });
 
     Ok(())
 }
 
-/// Deletes/closes a counter account.
-pub fn process_decrement(owner: &AccountInfo, counter: &mut AccountInfo) -> ProgramResult {
-    // Check if the counter PDA is owned by the program.
-    if !counter.is_owned_by(&ID) {
-        return Err(ProgramError::IllegalOwner);
-    }
-
-    // Deserialize the counter PDA.
-    let mut data = counter.try_borrow_mut_data()?;
-    let data: &mut Counter = unsafe { &mut *data.as_mut_ptr().cast() };
+/// Deletes/closes a counter account, sending its rent to `destination`, or
+/// to the owner if there's none. A `destination` other than the owner has to
+/// sign, so the rent can't be swept to a third party.
+pub fn process_delete(
+    owner: &mut AccountInfo,
+    counter: &mut AccountInfo,
+    destination: Option<&AccountInfo>,
+) -> ProgramResult {
+    // Deserialize the counter PDA, checking that it's owned by the program.
+    let counter_owner = if counter.data_len() == EpochCounter::LEN {
+        EpochCounter::load(counter)?.owner
+    } else {
+        Counter::load(counter)?.owner
+    };
 
     // Check if the counter has correct ownership.
-    if &data.owner != owner.key() {
+    if !keys_eq(&counter_owner, owner.key()) {
         return Err(ProgramError::IllegalOwner);
     }
 
-    // Decrement the counter.
-    data.count = data.count.saturating_sub(1);
+    let destination = match destination {
+        Some(destination) => {
+            require_signer(destination)?;
+            destination
+        }
+        None => owner,
+    };
 
-    log!("Decremented the counter to {}", data.count);
+    // Close the counter account by moving its lamports to the destination.
+    close_account(counter, destination)?;
+
+    emit!(CounterDeleted {
+        owner: *owner.key()
+    });
 
     Ok(())
 }
 
-/// Decrements a counter.
-pub fn process_delete(owner: &mut AccountInfo, counter: &mut AccountInfo) -> ProgramResult {
-    // Check if the counter PDA is owned by the program.
-    if !counter.is_owned_by(&ID) {
-        return Err(ProgramError::IllegalOwner);
-    }
+/// Sets the count of `counter`, a [`Counter`] or an [`EpochCounter`], to
+/// `update` of its current count and returns the new count.
+///
+/// The count of an epoch counter last updated in an earlier epoch is reset
+/// to zero before the update.
+fn update_count(
+    owner: &AccountInfo,
+    counter: &AccountInfo,
+    update: impl FnOnce(u64) -> u64,
+) -> Result<u64, ProgramError> {
+    // Epoch counters are told apart by their length. Both states start with
+    // the owner and the count.
+    if counter.data_len() == EpochCounter::LEN {
+        // Deserialize the counter PDA, checking that it's owned by the
+        // program.
+        let mut data = EpochCounter::load_mut(counter)?;
+
+        // Check if the counter was created by the `owner`.
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
 
-    // Deserialize the counter PDA.
-    let mut data = counter.try_borrow_mut_data()?;
-    let data: &mut Counter = unsafe { &mut *data.as_mut_ptr().cast() };
+        let epoch = Clock::get()?.epoch;
+        if epoch > data.epoch {
+            data.count = 0;
+            data.epoch = epoch;
+        }
+        data.count = update(data.count);
+        Ok(data.count)
+    } else {
+        // Deserialize the counter PDA, checking that it's owned by the
+        // program.
+        let mut data = Counter::load_mut(counter)?;
+
+        // Check if the counter was created by the `owner`.
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
 
-    // Check if the counter has correct ownership.
-    if &data.owner != owner.key() {
-        return Err(ProgramError::IllegalOwner);
+        data.count = update(data.count);
+        Ok(data.count)
     }
-
-    // Close the counter account by moving its lamports to the owner.
-    let mut owner_lamports = owner.try_borrow_mut_lamports()?;
-    let mut counter_lamports = counter.try_borrow_mut_lamports()?;
-    *owner_lamports = owner_lamports.saturating_add(*counter_lamports);
-    *counter_lamports = 0;
-
-    Ok(())
 }
@@ -0,0 +1,83 @@
+//! State of the counter accounts.
+
+#[cfg(feature = "bytemuck")]
+use bytemuck::{Pod, Zeroable};
+use common::{assert_layout, AccountData};
+#[cfg(feature = "anchor")]
+use macros::anchor_discriminator;
+use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use shank::ShankAccount;
+
+use crate::ID;
+
+pub const COUNTER_SEED: &'static str = "counter";
+
+/// On-chain representation of a counter.
+#[derive(Clone, Copy, ShankAccount)]
+#[cfg_attr(feature = "bytemuck", derive(Pod, Zeroable))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[repr(C)]
+pub struct Counter {
+    #[cfg_attr(feature = "serde", serde(with = "common::base58"))]
+    pub owner: Pubkey,
+    pub count: u64,
+}
+
+unsafe impl AccountData for Counter {
+    const OWNER: Pubkey = ID;
+    #[cfg(feature = "anchor")]
+    const PREFIX: &'static [u8] = &anchor_discriminator!("account:Counter");
+}
+
+assert_layout!(Counter, size = 40, align = 8, { owner: 0, count: 32 });
+
+/// On-chain representation of a counter which goes back to zero in every
+/// epoch, e.g. to count a per-epoch quota. The first update in a later epoch
+/// than [`EpochCounter::epoch`] resets the count before applying the update.
+///
+/// The owner and the count are at the same offsets as in [`Counter`], it's
+/// told apart by its length.
+#[derive(Clone, Copy, ShankAccount)]
+#[cfg_attr(feature = "bytemuck", derive(Pod, Zeroable))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[repr(C)]
+pub struct EpochCounter {
+    #[cfg_attr(feature = "serde", serde(with = "common::base58"))]
+    pub owner: Pubkey,
+    pub count: u64,
+    /// Epoch of the creation or of the last update.
+    pub epoch: u64,
+}
+
+unsafe impl AccountData for EpochCounter {
+    const OWNER: Pubkey = ID;
+    #[cfg(feature = "anchor")]
+    const PREFIX: &'static [u8] = &anchor_discriminator!("account:EpochCounter");
+}
+
+assert_layout!(EpochCounter, size = 48, align = 8, { owner: 0, count: 32, epoch: 40 });
+
+/// Kinds of counters, chosen by version 2 of
+/// [`CounterInstruction::Create`](crate::CounterInstruction::Create).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CounterMode {
+    /// A [`Counter`], created by version 1 as well.
+    Plain = 0,
+    /// An [`EpochCounter`].
+    Epoch = 1,
+}
+
+impl TryFrom<u8> for CounterMode {
+    type Error = ProgramError;
+
+    fn try_from(mode: u8) -> Result<Self, Self::Error> {
+        match mode {
+            0 => Ok(Self::Plain),
+            1 => Ok(Self::Epoch),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
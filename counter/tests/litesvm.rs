@@ -0,0 +1,88 @@
+//! The scenarios of `tests.rs` run through LiteSVM, cross-validating the
+//! program against a second SVM test runtime.
+
+use counter::{CounterCreated, CounterDecremented, CounterDeleted, CounterIncremented};
+use counter_client::{find_counter_address, try_from_account_data, ID};
+use events::decode::decode_events;
+use litesvm::LiteSVM;
+use solana_instruction::Instruction;
+use solana_keypair::Keypair;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+/// Sends `instruction` in a transaction paid and signed by `payer`, returning
+/// its logs.
+fn send(svm: &mut LiteSVM, payer: &Keypair, instruction: Instruction) -> Vec<String> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer],
+        svm.latest_blockhash(),
+    );
+    let logs = svm.send_transaction(transaction).unwrap().logs;
+    // Lets the same instruction be sent again.
+    svm.expire_blockhash();
+    logs
+}
+
+#[test]
+fn test_counter_success() {
+    let mut svm = LiteSVM::new();
+    svm.add_program_from_file(ID, "target/deploy/counter.so")
+        .unwrap();
+
+    let owner = Keypair::new();
+    svm.airdrop(&owner.pubkey(), 42 * LAMPORTS_PER_SOL).unwrap();
+    let (counter, _) = find_counter_address(&owner.pubkey());
+
+    send(&mut svm, &owner, counter_client::create(&owner.pubkey()));
+    send(&mut svm, &owner, counter_client::increment(&owner.pubkey()));
+    send(&mut svm, &owner, counter_client::increment(&owner.pubkey()));
+    send(&mut svm, &owner, counter_client::decrement(&owner.pubkey()));
+
+    let counter_account = svm.get_account(&counter).unwrap();
+    let state = try_from_account_data(&counter_account.data).unwrap();
+    assert_eq!(state.owner, owner.pubkey().to_bytes());
+    assert_eq!(state.count, 1);
+
+    send(&mut svm, &owner, counter_client::delete(&owner.pubkey()));
+    assert!(svm
+        .get_account(&counter)
+        .is_none_or(|account| account.lamports == 0));
+}
+
+#[test]
+fn test_counter_events() {
+    let mut svm = LiteSVM::new();
+    svm.add_program_from_file(ID, "target/deploy/counter.so")
+        .unwrap();
+
+    let owner = Keypair::new();
+    svm.airdrop(&owner.pubkey(), 42 * LAMPORTS_PER_SOL).unwrap();
+    let owner_bytes = owner.pubkey().to_bytes();
+
+    let logs = send(&mut svm, &owner, counter_client::create(&owner.pubkey()));
+    let events = decode_events::<CounterCreated>(&counter::ID, &logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].owner, owner_bytes);
+
+    send(&mut svm, &owner, counter_client::increment(&owner.pubkey()));
+    let logs = send(&mut svm, &owner, counter_client::increment(&owner.pubkey()));
+    let events = decode_events::<CounterIncremented>(&counter::ID, &logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].owner, owner_bytes);
+    assert_eq!(events[0].count, 2);
+    // Each instruction emits only its own event.
+    assert!(decode_events::<CounterDecremented>(&counter::ID, &logs).is_empty());
+
+    let logs = send(&mut svm, &owner, counter_client::decrement(&owner.pubkey()));
+    let events = decode_events::<CounterDecremented>(&counter::ID, &logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].count, 1);
+
+    let logs = send(&mut svm, &owner, counter_client::delete(&owner.pubkey()));
+    let events = decode_events::<CounterDeleted>(&counter::ID, &logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].owner, owner_bytes);
+}
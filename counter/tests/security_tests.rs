@@ -0,0 +1,301 @@
+//! Attacks on the counter program, each rejected with its own error.
+
+use common::AccountData;
+use counter_client::{find_counter_address, Counter, CounterInstruction, Version};
+use errors::{Namespace, SharedError};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::{funded_account, programs::mollusk, state_account};
+
+/// The custom error of the system program for payers without enough
+/// lamports.
+const RESULT_WITH_NEGATIVE_LAMPORTS: u32 = 1;
+
+/// Accounts of an existing counter.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    counter: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+        let (system_program, system_account) = keyed_account_for_system_program();
+
+        let owner = Pubkey::new_unique();
+        let (counter, _) = find_counter_address(&owner);
+        let counter_account = state_account(&Counter {
+            owner: owner.to_bytes(),
+            count: 1,
+        });
+
+        Self {
+            mollusk,
+            owner,
+            counter,
+            accounts: vec![
+                (owner, funded_account(LAMPORTS_PER_SOL)),
+                (counter, counter_account),
+                (system_program, system_account),
+            ],
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_signer() {
+    let setup = Setup::new();
+    let mut instruction = counter_client::increment(&setup.owner);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// An attacker passes the counter of another owner.
+#[test]
+fn test_wrong_pda() {
+    let setup = Setup::new();
+    let attacker = Pubkey::new_unique();
+    let mut instruction = counter_client::increment(&attacker);
+    instruction.accounts[1].pubkey = setup.counter;
+    let setup = setup.with(attacker, funded_account(LAMPORTS_PER_SOL));
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The counter PDA holds a counter, but owned by another program.
+#[test]
+fn test_spoofed_counter() {
+    let setup = Setup::new();
+    let mut counter_account = setup.accounts[1].1.clone();
+    counter_account.owner = Pubkey::new_unique();
+    let setup = setup.with(setup.counter, counter_account);
+    setup.expect_err(
+        &counter_client::increment(&setup.owner),
+        ProgramError::IllegalOwner,
+    );
+}
+
+#[test]
+fn test_substituted_system_program() {
+    let setup = Setup::new();
+    let fake_program = Pubkey::new_unique();
+    let mut instruction = counter_client::increment(&setup.owner);
+    instruction.accounts[2].pubkey = fake_program;
+    let setup = setup.with(fake_program, Account::default());
+    setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+}
+
+/// A deployed program instead of the system program, which `create` would
+/// invoke with the signatures of the owner and the counter.
+#[test]
+fn test_substituted_system_program_on_create() {
+    let setup = Setup::new();
+    let program = counter_client::ID;
+    let mut instruction = counter_client::create(&setup.owner);
+    instruction.accounts[2].pubkey = program;
+    let counter = setup.counter;
+    let setup = setup
+        .with(counter, Account::default())
+        .with(program, create_program_account_loader_v3(&program));
+    setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+}
+
+/// A copy of the program deployed at another address, whose counters would
+/// never match the PDAs derived by the clients.
+#[test]
+fn test_redeployed_program() {
+    let mut setup = Setup::new();
+    let redeployed = Pubkey::new_unique();
+    setup
+        .mollusk
+        .add_program(&redeployed, "target/deploy/counter", &LOADER_V3);
+    let mut instruction = counter_client::increment(&setup.owner);
+    instruction.program_id = redeployed;
+    setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+}
+
+/// An owner who can't pay for the rent of the counter, which is never
+/// created underfunded.
+#[test]
+fn test_underfunded_owner() {
+    let setup = Setup::new();
+    let (owner, counter) = (setup.owner, setup.counter);
+    let setup = setup
+        .with(owner, funded_account(1))
+        .with(counter, Account::default());
+    setup.expect_err(
+        &counter_client::create(&owner),
+        ProgramError::Custom(RESULT_WITH_NEGATIVE_LAMPORTS),
+    );
+}
+
+/// An attacker sends lamports to the counter PDA before it's created, which
+/// would make `CreateAccount` fail. The counter is created anyway, with the
+/// owner only paying the missing rent.
+#[test]
+fn test_prefunded_counter() {
+    let setup = Setup::new();
+    let (owner, counter) = (setup.owner, setup.counter);
+    let setup = setup.with(counter, funded_account(1_000));
+    let rent = setup.mollusk.sysvars.rent.minimum_balance(Counter::LEN);
+    setup.mollusk.process_and_validate_instruction(
+        &counter_client::create(&owner),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&owner)
+                .lamports(LAMPORTS_PER_SOL - (rent - 1_000))
+                .build(),
+            Check::account(&counter)
+                .owner(&counter_client::ID)
+                .lamports(rent)
+                .space(Counter::LEN)
+                .build(),
+        ],
+    );
+}
+
+/// An account of another program at the address of the counter, which is
+/// rejected before invoking the system program.
+#[test]
+fn test_precreated_counter() {
+    let setup = Setup::new();
+    let (owner, counter) = (setup.owner, setup.counter);
+    let setup = setup.with(counter, Account::new(1_000, 0, &Pubkey::new_unique()));
+    setup.expect_err(
+        &counter_client::create(&owner),
+        ProgramError::AccountAlreadyInitialized,
+    );
+}
+
+/// The rent of the counter is swept to a third party who didn't sign the
+/// deletion, or to the counter itself.
+#[test]
+fn test_unauthorized_destination() {
+    let setup = Setup::new();
+    let third_party = Pubkey::new_unique();
+    let setup = setup.with(third_party, funded_account(LAMPORTS_PER_SOL));
+
+    let mut instruction = counter_client::delete_to(&setup.owner, &third_party);
+    instruction.accounts[3].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+
+    let mut instruction = counter_client::delete(&setup.owner);
+    instruction
+        .accounts
+        .push(AccountMeta::new(setup.counter, false));
+    setup.expect_err(
+        &instruction,
+        ProgramError::Custom(Namespace::Shared.code(SharedError::DuplicateAccount as u32)),
+    );
+}
+
+/// The owner is passed as its own counter.
+#[test]
+fn test_duplicated_accounts() {
+    let setup = Setup::new();
+    let mut instruction = counter_client::increment(&setup.owner);
+    instruction.accounts[1].pubkey = setup.owner;
+    setup.expect_err(&instruction, ProgramError::InvalidArgument);
+}
+
+#[test]
+fn test_truncated_instruction_data() {
+    let setup = Setup::new();
+    let mut instruction = counter_client::increment(&setup.owner);
+    for len in [
+        0,
+        CounterInstruction::FAMILY_LEN,
+        CounterInstruction::DISCRIMINATOR_LEN,
+    ] {
+        instruction.data.truncate(len);
+        setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+    }
+}
+
+/// Versions an instruction doesn't have, and the data of one version sent
+/// as another, are rejected.
+#[test]
+fn test_unknown_versions() {
+    let setup = Setup::new();
+    // The version follows the family of the instruction.
+    let version = CounterInstruction::FAMILY_LEN;
+
+    let mut instruction = counter_client::increment(&setup.owner);
+    instruction.data[version] = 3;
+    setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+
+    // Only incrementing and decrementing have a version 2.
+    for mut instruction in [
+        counter_client::create(&setup.owner),
+        counter_client::delete(&setup.owner),
+        counter_client::compact_increment(&setup.owner),
+    ] {
+        instruction.data[version] = Version::V2 as u8;
+        setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+    }
+
+    // Version 2 with the data of version 1, and the other way around.
+    let mut instruction = counter_client::increment(&setup.owner);
+    instruction.data[version] = Version::V2 as u8;
+    setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+    let mut instruction = counter_client::decrement_by(&setup.owner, 1);
+    instruction.data[version] = Version::V1 as u8;
+    setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+}
+
+/// The compact instructions don't check the seeds of the counter, so an
+/// attacker passes the counter of another owner, or a bump anyway.
+#[test]
+fn test_compact_instructions() {
+    let setup = Setup::new();
+    let attacker = Pubkey::new_unique();
+    let mut instruction = counter_client::compact_increment(&attacker);
+    instruction.accounts[1].pubkey = setup.counter;
+    let setup = setup.with(attacker, funded_account(LAMPORTS_PER_SOL));
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+
+    let mut instruction = counter_client::compact_decrement(&setup.owner);
+    instruction.data.push(0);
+    setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+
+    // The other instructions still need the system program.
+    let mut instruction = counter_client::increment(&setup.owner);
+    instruction.accounts.truncate(2);
+    setup.expect_err(&instruction, ProgramError::NotEnoughAccountKeys);
+}
+
+#[test]
+fn test_reinitialization() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &counter_client::create(&setup.owner),
+        ProgramError::AccountAlreadyInitialized,
+    );
+}
@@ -0,0 +1,73 @@
+//! Long chains of instructions, checking that the state of the counter
+//! doesn't drift from a model of it and that the compute units stay within
+//! the budgets. Run with `cargo test --features stress --test stress`.
+
+use counter_client::{find_counter_address, Counter};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check};
+use solana_account::Account;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_signer::Signer;
+use test_utils::{
+    assertions::account_state, budgets, fixtures::alice, funded_account, programs::mollusk,
+};
+
+/// Number of chains of instructions run on the counter.
+const CHAINS: u64 = 10;
+/// Number of instructions in a chain.
+const CHAIN_LEN: u64 = 500;
+
+#[test]
+fn test_counter_stress() {
+    let mollusk = mollusk();
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = alice().pubkey();
+    let (counter, _) = find_counter_address(&owner);
+    let accounts = [
+        (owner, funded_account(42 * LAMPORTS_PER_SOL)),
+        (counter, Account::new(0, 0, &system_program)),
+        (system_program, system_account),
+    ];
+    let res = mollusk.process_and_validate_instruction(
+        &counter_client::create(&owner),
+        &accounts,
+        &[Check::success()],
+    );
+    let mut accounts = res.resulting_accounts;
+
+    let increment = counter_client::increment(&owner);
+    let decrement = counter_client::decrement(&owner);
+    let checks = [Check::success()];
+    // Every third instruction decrements the counter, the others increment
+    // it.
+    let chain = (0..CHAIN_LEN)
+        .map(|i| {
+            let instruction = if i % 3 == 2 { &decrement } else { &increment };
+            (instruction, checks.as_slice())
+        })
+        .collect::<Vec<_>>();
+    let decrements = CHAIN_LEN / 3;
+    let increments = CHAIN_LEN - decrements;
+
+    let mut count = 0;
+    let mut compute_units = 0;
+    for _ in 0..CHAINS {
+        let res = mollusk.process_and_validate_instruction_chain(&chain, &accounts);
+        // The state is checked after every chain to locate a drift.
+        count += increments - decrements;
+        assert_eq!(account_state::<Counter>(&res, &counter).count, count);
+        compute_units += res.compute_units_consumed;
+        accounts = res.resulting_accounts;
+    }
+
+    // The budgets of increments and decrements are the same, so their
+    // average has to stay within it.
+    let instructions = CHAINS * CHAIN_LEN;
+    let average = compute_units / instructions;
+    assert!(
+        average <= budgets::counter::INCREMENT,
+        "consumed {average} compute units on average, over the budget of {}",
+        budgets::counter::INCREMENT
+    );
+    println!("{instructions} instructions consumed {compute_units} compute units");
+}
@@ -1,62 +1,45 @@
-use std::mem;
+use std::{collections::BTreeMap, fs, path::Path};
 
+use common::{metadata, AccountData};
 use mollusk_svm::{
     program::keyed_account_for_system_program,
     result::{Check, ProgramResult},
-    Mollusk,
 };
+use proptest::prelude::*;
+use shank_idl::{extract_idl, ParseIdlOpts};
 use solana_account::Account;
-use solana_instruction::{AccountMeta, Instruction};
 use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
 use solana_pubkey::Pubkey;
+use solana_rpc_client_api::filter::RpcFilterType;
+use solana_signer::Signer;
+use test_utils::{
+    assertions::{account_state, assert_closed},
+    bench::Bencher,
+    budgets::{self, assert_within_budget},
+    fixtures::{alice, bob, replay_fixtures},
+    logs::{assert_logs_snapshot, LogRecorder},
+    programs::mollusk,
+    state_account,
+};
 
-use counter::{CounterInstruction, CounterInstructionData, COUNTER_SEED};
-
-const ID: Pubkey = Pubkey::new_from_array(counter::ID);
-
-/// Creates a full instruction.
-fn instruction(
-    counter_instruction: CounterInstruction,
-    owner: &Pubkey,
-    counter: &Pubkey,
-    bump: u8,
-    system_program: &Pubkey,
-) -> Instruction {
-    // Create instruction data.
-    let data = CounterInstructionData { bump };
-    // Serialize instruction data to bytes.
-    let data = unsafe {
-        &*(&data as *const CounterInstructionData
-            as *const [u8; size_of::<CounterInstructionData>()])
-    };
-
-    // Construct the full instruction data, consisting of:
-    // * discriminator
-    // * serialized data
-    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
-        mem::size_of::<CounterInstruction>() + mem::size_of::<CounterInstructionData>(),
-    );
-    data_with_discriminator.push(counter_instruction as u8);
-    data_with_discriminator.extend_from_slice(data);
-
-    let ix_accounts = vec![
-        AccountMeta::new(*owner, true),
-        AccountMeta::new(*counter, true),
-        AccountMeta::new_readonly(*system_program, false),
-    ];
-    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
-}
+use counter_client::{
+    find_counter_address,
+    rpc::{counters_by_owner, decode_counters},
+    try_from_account_data, Counter, CounterInstruction, CounterMode, EpochCounter, InstructionArgs,
+    Version, ID,
+};
 
 #[test]
 fn test_counter_success() {
-    let mollusk = Mollusk::new(&ID, "target/deploy/counter");
+    let mut mollusk = mollusk();
+    let logs = LogRecorder::new(&mut mollusk);
     let (system_program, system_account) = keyed_account_for_system_program();
 
-    let owner = Pubkey::new_unique();
+    let owner = alice().pubkey();
     let owner_account = Account::new(42 * LAMPORTS_PER_SOL, 0, &system_program);
 
-    let (counter, bump) =
-        Pubkey::find_program_address(&[COUNTER_SEED.as_bytes(), owner.as_array()], &ID);
+    let (counter, _) = find_counter_address(&owner);
     // We don't specify the space for the counter PDA yet - we are letting the
     // `create` instruction do that.
     let counter_account = Account::new(0, 0, &system_program);
@@ -66,52 +49,616 @@ fn test_counter_success() {
         (counter, counter_account.clone()),
         (system_program, system_account.clone()),
     ];
+    let mut accounts = tx_accounts.to_vec();
+    for (name, instruction, budget, count) in [
+        // Create/initialize the counter.
+        (
+            "create",
+            counter_client::create(&owner),
+            budgets::counter::CREATE,
+            Some(0),
+        ),
+        (
+            "increment",
+            counter_client::increment(&owner),
+            budgets::counter::INCREMENT,
+            Some(1),
+        ),
+        (
+            "decrement",
+            counter_client::decrement(&owner),
+            budgets::counter::DECREMENT,
+            Some(0),
+        ),
+        // Delete/close the counter.
+        (
+            "delete",
+            counter_client::delete(&owner),
+            budgets::counter::DELETE,
+            None,
+        ),
+    ] {
+        let res =
+            mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+        assert!(matches!(res.program_result, ProgramResult::Success));
+        assert_within_budget(&res, budget);
+        assert_logs_snapshot("snapshots", name, &logs.take(), &[]);
+        match count {
+            Some(count) => assert_eq!(account_state::<Counter>(&res, &counter).count, count),
+            None => assert_closed(&res, &counter),
+        }
+        accounts = res.resulting_accounts;
+    }
+}
+
+/// Increments and decrements the counter with the compact instructions,
+/// which take neither the bump nor the system program.
+#[test]
+fn test_counter_compact() {
+    let mollusk = mollusk();
+    let (system_program, _) = keyed_account_for_system_program();
+
+    let owner = alice().pubkey();
+    let (counter, _) = find_counter_address(&owner);
+    let counter_account = state_account(&Counter {
+        owner: owner.to_bytes(),
+        count: 7,
+    });
+
+    let res = mollusk.process_and_validate_instruction(
+        &counter_client::compact_increment(&owner),
+        &[
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+            (counter, counter_account),
+        ],
+        &[Check::success()],
+    );
+    assert_eq!(account_state::<Counter>(&res, &counter).count, 8);
+    assert_within_budget(&res, budgets::counter::INCREMENT);
+
+    let res = mollusk.process_and_validate_instruction(
+        &counter_client::compact_decrement(&owner),
+        &res.resulting_accounts,
+        &[Check::success()],
+    );
+    assert_eq!(account_state::<Counter>(&res, &counter).count, 7);
+}
+
+/// An epoch counter keeps its count within an epoch and starts over from
+/// zero at the first update of a later epoch.
+#[test]
+fn test_epoch_counter() {
+    let mut mollusk = mollusk();
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = alice().pubkey();
+    let (counter, _) = find_counter_address(&owner);
+
+    mollusk.sysvars.clock.epoch = 5;
     let res = mollusk.process_and_validate_instruction_chain(
         &[
-            // Create/initialize the counter.
             (
-                &instruction(
-                    CounterInstruction::Create,
-                    &owner,
-                    &counter,
-                    bump,
-                    &system_program,
-                ),
+                &counter_client::create_with_mode(&owner, CounterMode::Epoch),
                 &[Check::success()],
             ),
             (
-                &instruction(
-                    CounterInstruction::Increment,
-                    &owner,
-                    &counter,
-                    bump,
-                    &system_program,
-                ),
+                &counter_client::increment_by(&owner, 3),
                 &[Check::success()],
             ),
             (
-                &instruction(
-                    CounterInstruction::Decrement,
-                    &owner,
-                    &counter,
-                    bump,
-                    &system_program,
-                ),
+                &counter_client::compact_increment(&owner),
                 &[Check::success()],
             ),
-            // Delete/close the counter.
+        ],
+        &[
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+            (counter, Account::new(0, 0, &system_program)),
+            (system_program, system_account),
+        ],
+    );
+    let state: EpochCounter = account_state(&res, &counter);
+    assert_eq!(state.owner, owner.to_bytes());
+    assert_eq!((state.count, state.epoch), (4, 5));
+
+    // The first update of the next epochs resets the count, the following
+    // ones add up again.
+    mollusk.sysvars.clock.epoch = 7;
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&counter_client::increment(&owner), &[Check::success()]),
             (
-                &instruction(
-                    CounterInstruction::Delete,
-                    &owner,
-                    &counter,
-                    bump,
-                    &system_program,
-                ),
+                &counter_client::increment_by(&owner, 2),
                 &[Check::success()],
             ),
         ],
+        &res.resulting_accounts,
+    );
+    let state: EpochCounter = account_state(&res, &counter);
+    assert_eq!((state.count, state.epoch), (3, 7));
+
+    // Decrementing resets it as well, saturating at zero.
+    mollusk.sysvars.clock.epoch = 8;
+    let res = mollusk.process_and_validate_instruction(
+        &counter_client::decrement(&owner),
+        &res.resulting_accounts,
+        &[Check::success()],
+    );
+    let state: EpochCounter = account_state(&res, &counter);
+    assert_eq!((state.count, state.epoch), (0, 8));
+
+    let res = mollusk.process_and_validate_instruction(
+        &counter_client::delete(&owner),
+        &res.resulting_accounts,
+        &[Check::success()],
+    );
+    assert_closed(&res, &counter);
+}
+
+/// Only the modes of [`CounterMode`] are accepted, and a plain counter
+/// created by version 2 doesn't reset.
+#[test]
+fn test_counter_modes() {
+    let mut mollusk = mollusk();
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = alice().pubkey();
+    let (counter, bump) = find_counter_address(&owner);
+    let accounts = [
+        (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+        (counter, Account::new(0, 0, &system_program)),
+        (system_program, system_account),
+    ];
+
+    // Version 2 of the creation with an unknown mode.
+    let mut create = counter_client::create(&owner);
+    create.data = [
+        CounterInstruction::Create
+            .discriminator(Version::V2)
+            .as_slice(),
+        &[bump, 2],
+    ]
+    .concat();
+    mollusk.process_and_validate_instruction(
+        &create,
+        &accounts,
+        &[Check::err(ProgramError::InvalidInstructionData)],
+    );
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &counter_client::create_with_mode(&owner, CounterMode::Plain),
+                &[Check::success()],
+            ),
+            (&counter_client::increment(&owner), &[Check::success()]),
+        ],
+        &accounts,
+    );
+    mollusk.sysvars.clock.epoch += 1;
+    let res = mollusk.process_and_validate_instruction(
+        &counter_client::increment(&owner),
+        &res.resulting_accounts,
+        &[Check::success()],
+    );
+    assert_eq!(account_state::<Counter>(&res, &counter).count, 2);
+}
+
+/// Deletes the counter with its rent sent to the owner passed explicitly and
+/// to a treasury signing the deletion.
+#[test]
+fn test_counter_delete_to() {
+    let mollusk = mollusk();
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = alice().pubkey();
+    let treasury = bob().pubkey();
+    let (counter, _) = find_counter_address(&owner);
+    let counter_account = state_account(&Counter {
+        owner: owner.to_bytes(),
+        count: 7,
+    });
+    let rent = counter_account.lamports;
+
+    for (destination, owner_lamports, treasury_lamports) in [
+        (owner, LAMPORTS_PER_SOL + rent, LAMPORTS_PER_SOL),
+        (treasury, LAMPORTS_PER_SOL, LAMPORTS_PER_SOL + rent),
+    ] {
+        let res = mollusk.process_and_validate_instruction(
+            &counter_client::delete_to(&owner, &destination),
+            &[
+                (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+                (counter, counter_account.clone()),
+                (system_program, system_account.clone()),
+                (treasury, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+            ],
+            &[
+                Check::success(),
+                Check::account(&owner).lamports(owner_lamports).build(),
+                Check::account(&treasury)
+                    .lamports(treasury_lamports)
+                    .build(),
+            ],
+        );
+        assert_closed(&res, &counter);
+    }
+}
+
+#[test]
+fn test_counter_state() {
+    let mollusk = mollusk();
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = alice().pubkey();
+    let owner_account = Account::new(42 * LAMPORTS_PER_SOL, 0, &system_program);
+
+    let (counter, _) = find_counter_address(&owner);
+    let counter_account = Account::new(0, 0, &system_program);
+
+    let tx_accounts = &[
+        (owner, owner_account),
+        (counter, counter_account),
+        (system_program, system_account),
+    ];
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&counter_client::create(&owner), &[Check::success()]),
+            (&counter_client::increment(&owner), &[Check::success()]),
+            (&counter_client::increment(&owner), &[Check::success()]),
+        ],
         tx_accounts,
     );
     assert!(matches!(res.program_result, ProgramResult::Success));
+
+    let state: Counter = account_state(&res, &counter);
+    assert_eq!(state.owner, owner.to_bytes());
+    assert_eq!(state.count, 2);
+}
+
+/// The state is stored as its fields in order, little-endian and without
+/// padding.
+#[test]
+fn test_counter_layout() {
+    let counter = Counter {
+        owner: [1; 32],
+        count: 0x0102030405060708,
+    };
+    let data = counter.as_bytes();
+
+    let mut expected = Vec::new();
+    expected.extend_from_slice(&counter.owner);
+    expected.extend_from_slice(&counter.count.to_le_bytes());
+    assert_eq!(data, expected);
+
+    let state = try_from_account_data(&state_account(&counter).data).unwrap();
+    assert_eq!(state.owner, counter.owner);
+    assert_eq!(state.count, counter.count);
+}
+
+/// Instruction data is the discriminator and the version followed by the
+/// bump and, in version 2, the amount, or only the discriminator for the
+/// compact instructions, and decodes back to the same arguments.
+#[test]
+fn test_instruction_data_round_trip() {
+    for args in [
+        InstructionArgs::Create { bump: 255 },
+        InstructionArgs::Increment { bump: 254 },
+        InstructionArgs::Decrement { bump: 253 },
+        InstructionArgs::Delete { bump: 252 },
+        InstructionArgs::CompactIncrement,
+        InstructionArgs::CompactDecrement,
+        InstructionArgs::IncrementV2 {
+            bump: 251,
+            amount: u64::MAX,
+        },
+        InstructionArgs::DecrementV2 {
+            bump: 250,
+            amount: 7,
+        },
+        InstructionArgs::CreateV2 {
+            bump: 249,
+            mode: CounterMode::Epoch,
+        },
+    ] {
+        assert_eq!(InstructionArgs::decode(&args.encode()), Ok(args));
+    }
+    assert_eq!(
+        InstructionArgs::Increment { bump: 254 }.encode(),
+        [
+            CounterInstruction::Increment
+                .discriminator(Version::V1)
+                .as_slice(),
+            &[254]
+        ]
+        .concat()
+    );
+    assert_eq!(
+        InstructionArgs::IncrementV2 {
+            bump: 254,
+            amount: 0x0102030405060708
+        }
+        .encode(),
+        [
+            CounterInstruction::Increment
+                .discriminator(Version::V2)
+                .as_slice(),
+            &[254, 8, 7, 6, 5, 4, 3, 2, 1]
+        ]
+        .concat()
+    );
+    assert_eq!(
+        InstructionArgs::CompactIncrement.encode(),
+        CounterInstruction::CompactIncrement.discriminator(Version::V1)
+    );
+
+    // The builders encode the same data.
+    let owner = Pubkey::new_unique();
+    let (_, bump) = find_counter_address(&owner);
+    assert_eq!(
+        InstructionArgs::decode(&counter_client::decrement(&owner).data),
+        Ok(InstructionArgs::Decrement { bump })
+    );
+    assert_eq!(
+        InstructionArgs::decode(&counter_client::increment_by(&owner, 3).data),
+        Ok(InstructionArgs::IncrementV2 { bump, amount: 3 })
+    );
+    assert_eq!(
+        InstructionArgs::decode(&counter_client::compact_increment(&owner).data),
+        Ok(InstructionArgs::CompactIncrement)
+    );
+
+    // Trailing or missing bytes, and unknown instructions and versions, are
+    // rejected, like by the program.
+    assert!(InstructionArgs::decode(&[1]).is_err());
+    assert!(InstructionArgs::decode(&[1, 1]).is_err());
+    assert!(InstructionArgs::decode(&[1, 1, bump, 0]).is_err());
+    assert!(InstructionArgs::decode(&[1, 2, bump]).is_err());
+    assert!(InstructionArgs::decode(&[4, 1, bump]).is_err());
+    assert!(InstructionArgs::decode(&[0, 2, bump]).is_err());
+    assert!(InstructionArgs::decode(&[0, 2, bump, 2]).is_err());
+    assert!(InstructionArgs::decode(&[1, 3, bump]).is_err());
+    assert!(InstructionArgs::decode(&[6, 1]).is_err());
+}
+
+/// A transaction mixing versions of the instructions, e.g. sent by a client
+/// which only moved some of them to version 2, processes all of them.
+#[test]
+fn test_mixed_versions() {
+    let mollusk = mollusk();
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = alice().pubkey();
+    let (counter, _) = find_counter_address(&owner);
+
+    let increment = counter_client::increment(&owner);
+    let increment_by = counter_client::increment_by(&owner, 10);
+    let compact_decrement = counter_client::compact_decrement(&owner);
+    let decrement_by = counter_client::decrement_by(&owner, 4);
+    let decrement = counter_client::decrement(&owner);
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&increment, &[Check::success()]),
+            (&increment_by, &[Check::success()]),
+            (&compact_decrement, &[Check::success()]),
+            (&decrement_by, &[Check::success()]),
+            (&decrement, &[Check::success()]),
+        ],
+        &[
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+            (
+                counter,
+                state_account(&Counter {
+                    owner: owner.to_bytes(),
+                    count: 7,
+                }),
+            ),
+            (system_program, system_account),
+        ],
+    );
+    // 7 + 1 + 10 - 1 - 4 - 1
+    assert_eq!(account_state::<Counter>(&res, &counter).count, 12);
+}
+
+/// Addresses are serialized as base58 strings.
+#[test]
+fn test_counter_json() {
+    let counter = Counter {
+        owner: counter::ID,
+        count: 42,
+    };
+    let json = serde_json::to_string(&counter).unwrap();
+    assert_eq!(
+        json,
+        r#"{"owner":"9YxC88EDFbs4a2ypUmKy8HPUFdg1FTnwnZm7358J3w9u","count":42}"#
+    );
+
+    let state: Counter = serde_json::from_str(&json).unwrap();
+    assert_eq!(state.owner, counter.owner);
+    assert_eq!(state.count, counter.count);
+    assert!(serde_json::from_str::<Counter>(r#"{"owner":"invalid","count":42}"#).is_err());
+}
+
+#[test]
+fn test_counter_rpc_filters() {
+    let owner = alice().pubkey();
+    let matches = |account: &Account| {
+        counters_by_owner(&owner).iter().all(|filter| match filter {
+            RpcFilterType::DataSize(size) => account.data.len() as u64 == *size,
+            RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(&account.data),
+            _ => unreachable!(),
+        })
+    };
+
+    let counter = state_account(&Counter {
+        owner: owner.to_bytes(),
+        count: 42,
+    });
+    assert!(matches(&counter));
+    let other = state_account(&Counter {
+        owner: bob().pubkey().to_bytes(),
+        count: 42,
+    });
+    assert!(!matches(&other));
+
+    let (address, _) = find_counter_address(&owner);
+    let decoded = decode_counters([
+        (address, counter),
+        (Pubkey::new_unique(), Account::default()),
+    ]);
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].0, address);
+    assert_eq!(decoded[0].1.count, 42);
+}
+
+/// Counter operations applied after creating the counter.
+#[derive(Clone, Copy, Debug)]
+enum CounterOp {
+    Increment,
+    Decrement,
+}
+
+fn counter_op() -> impl Strategy<Value = CounterOp> {
+    prop_oneof![Just(CounterOp::Increment), Just(CounterOp::Decrement)]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    /// The count follows the operations and never underflows.
+    #[test]
+    fn test_counter_ops(ops in prop::collection::vec(counter_op(), 0..32)) {
+        let mollusk = mollusk();
+        let (system_program, system_account) = keyed_account_for_system_program();
+
+        let owner = Pubkey::new_unique();
+        let owner_account = Account::new(42 * LAMPORTS_PER_SOL, 0, &system_program);
+
+        let (counter, _) = find_counter_address(&owner);
+        let counter_account = Account::new(0, 0, &system_program);
+
+        let tx_accounts = vec![
+            (owner, owner_account),
+            (counter, counter_account),
+            (system_program, system_account),
+        ];
+        let mut res = mollusk.process_and_validate_instruction(
+            &counter_client::create(&owner),
+            &tx_accounts,
+            &[Check::success()],
+        );
+
+        let mut expected: u64 = 0;
+        for op in ops {
+            let instruction = match op {
+                CounterOp::Increment => {
+                    expected += 1;
+                    counter_client::increment(&owner)
+                }
+                CounterOp::Decrement => {
+                    expected = expected.saturating_sub(1);
+                    counter_client::decrement(&owner)
+                }
+            };
+            res = mollusk.process_and_validate_instruction(
+                &instruction,
+                &res.resulting_accounts,
+                &[Check::success()],
+            );
+
+            let state: Counter = account_state(&res, &counter);
+            prop_assert_eq!(state.count, expected);
+        }
+    }
+}
+
+/// Replays the fixtures dumped into `fixtures/` by the other tests.
+#[test]
+fn test_replay_fixtures() {
+    let mollusk = mollusk();
+    assert!(replay_fixtures(&mollusk, "fixtures") > 0);
+}
+
+/// Measures the compute units of every instruction into `benches/`.
+#[test]
+fn test_compute_units() {
+    let mollusk = mollusk();
+    let mut bencher = Bencher::new(&mollusk, "benches");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = alice().pubkey();
+    let owner_account = Account::new(42 * LAMPORTS_PER_SOL, 0, &system_program);
+
+    let (counter, _) = find_counter_address(&owner);
+    let counter_account = Account::new(0, 0, &system_program);
+
+    let tx_accounts = [
+        (owner, owner_account),
+        (counter, counter_account),
+        (system_program, system_account),
+    ];
+    let res = bencher.bench("create", &counter_client::create(&owner), &tx_accounts);
+    let res = bencher.bench(
+        "increment",
+        &counter_client::increment(&owner),
+        &res.resulting_accounts,
+    );
+    let res = bencher.bench(
+        "decrement",
+        &counter_client::decrement(&owner),
+        &res.resulting_accounts,
+    );
+    // The compact variants next to the ones carrying the bump.
+    let res = bencher.bench(
+        "compact_increment",
+        &counter_client::compact_increment(&owner),
+        &res.resulting_accounts,
+    );
+    let res = bencher.bench(
+        "compact_decrement",
+        &counter_client::compact_decrement(&owner),
+        &res.resulting_accounts,
+    );
+    bencher.bench(
+        "delete",
+        &counter_client::delete(&owner),
+        &res.resulting_accounts,
+    );
+    bencher.execute();
+}
+
+/// Checks the build metadata embedded in the binary loaded by the tests.
+#[test]
+fn test_build_metadata() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let binary = fs::read(manifest_dir.join("target/deploy/counter.so")).unwrap();
+    let fields: BTreeMap<_, _> = metadata::fields(&binary)
+        .expect("the binary should embed build metadata")
+        .collect();
+
+    assert_eq!(fields["name"], "counter");
+    assert_eq!(fields["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(fields["source_code"], metadata::SOURCE_CODE);
+    // Built from the same checkout as the tests.
+    assert_eq!(fields["source_revision"], metadata::SOURCE_REVISION);
+    assert!(fields["rustc"].starts_with("rustc "));
+    assert!(fields["dependencies"].contains("pinocchio 0.8."));
+}
+
+/// Extracts the IDL of the program and emits it into `idl/counter.json`.
+#[test]
+fn test_idl() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let idl = extract_idl(
+        manifest_dir.join("src/lib.rs").to_str().unwrap(),
+        ParseIdlOpts {
+            program_address_override: Some(ID.to_string()),
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .expect("the program should have an IDL");
+    assert_eq!(idl.instructions.len(), 6);
+
+    let idl_dir = manifest_dir.join("idl");
+    fs::create_dir_all(&idl_dir).unwrap();
+    fs::write(idl_dir.join("counter.json"), idl.try_into_json().unwrap()).unwrap();
 }
@@ -0,0 +1,72 @@
+//! Program forwarding its instruction to another program through a CPI.
+//!
+//! The tests of `cpi-depth-guard` invoke its instructions through this
+//! program to check which ones refuse CPIs. Passing this program as the
+//! invoked program nests the CPIs deeper.
+
+#![no_std]
+
+use core::array;
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::slice_invoke,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("28qDf77uRZpFi6kjyQvC3A2vfB8wviSh8UMUDxq6M3ku");
+
+/// Maximum number of accounts passed on to the invoked program.
+pub const MAX_ACCOUNTS: usize = 8;
+
+/// Invokes the program of the first account with the instruction data and
+/// the other accounts, keeping their signer and writable flags. The return
+/// data of the invoked program stays the return data of the transaction.
+///
+/// ### Accounts:
+///   0. `[]` Invoked program
+///   1. ..`1+N` Accounts of the invoked instruction, up to [`MAX_ACCOUNTS`]
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let [program, accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if accounts.len() > MAX_ACCOUNTS {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // The arrays are filled up to `MAX_ACCOUNTS` with the program, only the
+    // passed accounts are forwarded.
+    let account_metas: [AccountMeta; MAX_ACCOUNTS] = array::from_fn(|i| {
+        let account = accounts.get(i).unwrap_or(program);
+        AccountMeta::new(account.key(), account.is_writable(), account.is_signer())
+    });
+    let account_infos: [&AccountInfo; MAX_ACCOUNTS] =
+        array::from_fn(|i| accounts.get(i).unwrap_or(program));
+
+    slice_invoke(
+        &Instruction {
+            program_id: program.key(),
+            accounts: &account_metas[..accounts.len()],
+            data: instruction_data,
+        },
+        &account_infos[..accounts.len()],
+    )
+}
@@ -0,0 +1,247 @@
+//! Instructions refusing to be invoked through CPIs.
+//!
+//! The runtime counts the programs on the invocation stack: top-level
+//! instructions of the transaction run at a stack height of
+//! [`TRANSACTION_LEVEL_STACK_HEIGHT`] and every CPI adds one, up to
+//! [`MAX_STACK_HEIGHT`]. The `sol_get_stack_height` syscall returns the
+//! height of the running instruction, so a program can tell whether a
+//! signer called it directly or another program invoked it.
+//!
+//! A program invoking an instruction runs code before and after it within
+//! its own instruction. It can borrow with a flash loan, move the state
+//! with the borrowed funds and repay, or inspect the outcome and fail the
+//! whole transaction if it doesn't like it. Instructions changing state
+//! that such a caller could exploit only run at the top level, where every
+//! step is a separate instruction of the signed transaction.
+//!
+//! Every instruction declares the stack height it runs at with
+//! [`CpiDepthGuardInstruction::max_stack_height`], checked before
+//! processing it. [`CpiDepthGuardInstruction::Update`] is top-level only,
+//! [`CpiDepthGuardInstruction::Initialize`] and
+//! [`CpiDepthGuardInstruction::Read`] explicitly allow CPIs, so other
+//! programs can create configs and read their values.
+
+#![no_std]
+
+use common::{
+    assert_layout, check_pda,
+    checks::{require_program, require_signer},
+    create::create_account,
+    info, keys_eq, AccountData,
+};
+use errors::Namespace;
+use macros::{InstructionData, InstructionDiscriminator};
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("BrWFzU8KREaYzWwCaJNNxDLjryKQtQ5tp1ArUyzQMjdT");
+
+pub const CONFIG_SEED: &str = "config";
+
+/// Stack height of the top-level instructions of a transaction.
+pub const TRANSACTION_LEVEL_STACK_HEIGHT: u64 = 1;
+
+/// Maximum stack height, of instructions invoked by four nested CPIs.
+pub const MAX_STACK_HEIGHT: u64 = 5;
+
+/// Errors returned by the CPI depth guard program.
+#[derive(Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CpiDepthGuardError {
+    /// The instruction was invoked through a CPI deeper than it allows.
+    CpiNotAllowed,
+}
+
+impl From<CpiDepthGuardError> for ProgramError {
+    fn from(e: CpiDepthGuardError) -> Self {
+        Namespace::CpiDepthGuard.error(e as u32)
+    }
+}
+
+/// On-chain representation of a config.
+#[repr(C)]
+pub struct Config {
+    pub authority: Pubkey,
+    pub value: u64,
+}
+
+unsafe impl AccountData for Config {
+    const OWNER: Pubkey = ID;
+}
+
+assert_layout!(Config, size = 40, align = 8, { authority: 0, value: 32 });
+
+/// CPI depth guard program instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum CpiDepthGuardInstruction {
+    /// Creates the config of the authority. Allowed in CPIs.
+    Initialize,
+    /// Sets the value of a config. Top-level only.
+    Update,
+    /// Sets the value of a config as return data. Allowed in CPIs.
+    Read,
+}
+
+impl CpiDepthGuardInstruction {
+    /// Returns the maximum stack height the instruction runs at,
+    /// [`TRANSACTION_LEVEL_STACK_HEIGHT`] for top-level only instructions.
+    pub const fn max_stack_height(&self) -> u64 {
+        match self {
+            Self::Update => TRANSACTION_LEVEL_STACK_HEIGHT,
+            Self::Initialize | Self::Read => MAX_STACK_HEIGHT,
+        }
+    }
+}
+
+/// Instruction data of [`CpiDepthGuardInstruction::Initialize`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+/// Instruction data of [`CpiDepthGuardInstruction::Update`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct UpdateInstructionData {
+    pub value: u64,
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = CpiDepthGuardInstruction::split(instruction_data)?;
+
+    // Check the stack height before anything else, so no instruction runs
+    // any of its code deeper than it allows.
+    require_stack_height(instruction.max_stack_height())?;
+
+    match instruction {
+        CpiDepthGuardInstruction::Initialize => process_initialize(accounts, instruction_data),
+        CpiDepthGuardInstruction::Update => process_update(accounts, instruction_data),
+        CpiDepthGuardInstruction::Read => process_read(accounts),
+    }
+}
+
+/// Creates the config of the authority, with a value of zero.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Authority and payer
+///   1. `[WRITE]` Config PDA
+///   2. `[]` System program
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, config, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+    require_program(system_program, &pinocchio_system::ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    check_pda(
+        &[CONFIG_SEED.as_bytes(), authority.key()],
+        instruction_data.bump,
+        config.key(),
+        &ID,
+    )?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(CONFIG_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    create_account(authority, config, Config::LEN, &ID, &[Signer::from(&seeds)])?;
+
+    let mut data = Config::init(config)?;
+    data.authority = *authority.key();
+    data.value = 0;
+
+    info!("Created the config");
+
+    Ok(())
+}
+
+/// Sets the value of the config of the authority.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Authority
+///   1. `[WRITE]` Config
+pub fn process_update(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = UpdateInstructionData::from_bytes(instruction_data)?;
+
+    let mut data = Config::load_mut(config)?;
+    if !keys_eq(&data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    data.value = instruction_data.value;
+
+    info!("Updated the value to {}", instruction_data.value);
+
+    Ok(())
+}
+
+/// Sets the value of a config as return data, as little-endian bytes.
+///
+/// ### Accounts:
+///   0. `[]` Config
+pub fn process_read(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    set_return_data(&Config::load(config)?.value.to_le_bytes());
+
+    Ok(())
+}
+
+/// Returns the height of the invocation stack, 1 for top-level instructions.
+pub fn stack_height() -> u64 {
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_get_stack_height()
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    TRANSACTION_LEVEL_STACK_HEIGHT
+}
+
+/// Checks that the instruction runs at most at `max_stack_height`.
+///
+/// Fails with [`CpiDepthGuardError::CpiNotAllowed`].
+pub fn require_stack_height(max_stack_height: u64) -> ProgramResult {
+    if stack_height() > max_stack_height {
+        return Err(CpiDepthGuardError::CpiNotAllowed.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,182 @@
+//! Attacks on the CPI depth guard program, each rejected with its own
+//! error.
+
+use common::AccountData;
+use cpi_depth_guard::{
+    Config, CpiDepthGuardInstruction, InitializeInstructionData, UpdateInstructionData, CONFIG_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(cpi_depth_guard::ID);
+
+/// Returns the config PDA of `authority` and its bump.
+fn config_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+fn config_account(mollusk: &Mollusk, authority: &Pubkey, value: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(authority.as_ref());
+    account.data[32..40].copy_from_slice(&value.to_le_bytes());
+    account
+}
+
+/// Accounts of an authority with a config, and of an attacker without one.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    config: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/cpi_depth_guard");
+
+        let authority = Pubkey::new_unique();
+        let (config, _) = config_pda(&authority);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (config, config_account(&mollusk, &authority, 42)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            config,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Creates `config`, signed by the attacker.
+    fn attacker_initialize(&self, config: &Pubkey, system_program: &Pubkey) -> Instruction {
+        let (_, bump) = config_pda(&self.attacker);
+        let mut data = CpiDepthGuardInstruction::Initialize
+            .discriminator()
+            .to_vec();
+        data.extend_from_slice(&InitializeInstructionData { bump }.to_bytes());
+        Instruction::new_with_bytes(
+            ID,
+            &data,
+            vec![
+                AccountMeta::new(self.attacker, true),
+                AccountMeta::new(*config, false),
+                AccountMeta::new_readonly(*system_program, false),
+            ],
+        )
+    }
+
+    /// Sets the value of `config`, signed by `authority`.
+    fn instruction_update(&self, authority: &Pubkey, config: &Pubkey) -> Instruction {
+        let mut data = CpiDepthGuardInstruction::Update.discriminator().to_vec();
+        data.extend_from_slice(&UpdateInstructionData { value: 7 }.to_bytes());
+        Instruction::new_with_bytes(
+            ID,
+            &data,
+            vec![
+                AccountMeta::new_readonly(*authority, true),
+                AccountMeta::new(*config, false),
+            ],
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_update(&setup.authority, &setup.config);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker sets the value of the config of the authority.
+#[test]
+fn test_update_config_of_other_authority() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_update(&setup.attacker, &setup.config),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker creates a config at the address of the config of the
+/// authority, before the authority does.
+#[test]
+fn test_initialize_config_of_other_authority() {
+    let setup = Setup::new();
+    let config = setup.config;
+    let setup = setup.with(config, Account::default());
+    setup.expect_err(
+        &setup.attacker_initialize(&config, &keyed_account_for_system_program().0),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// A program posing as the system program, which would be invoked with the
+/// signature of the config PDA.
+#[test]
+fn test_fake_system_program() {
+    let setup = Setup::new();
+    let (config, _) = config_pda(&setup.attacker);
+    let system_program = Pubkey::new_unique();
+    let setup = setup
+        .with(config, Account::default())
+        .with(system_program, Account::default());
+    setup.expect_err(
+        &setup.attacker_initialize(&config, &system_program),
+        ProgramError::IncorrectProgramId,
+    );
+}
+
+/// A config of the authority with a made up value, owned by another
+/// program, returned to programs reading it.
+#[test]
+fn test_read_spoofed_config() {
+    let setup = Setup::new();
+    let mut config_account = config_account(&setup.mollusk, &setup.authority, u64::MAX);
+    config_account.owner = Pubkey::new_unique();
+    let config = Pubkey::new_unique();
+    let setup = setup.with(config, config_account);
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        CpiDepthGuardInstruction::Read.discriminator(),
+        vec![AccountMeta::new_readonly(config, false)],
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
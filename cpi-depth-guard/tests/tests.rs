@@ -0,0 +1,194 @@
+use cpi_depth_guard::{
+    Config, CpiDepthGuardError, CpiDepthGuardInstruction, InitializeInstructionData,
+    UpdateInstructionData, CONFIG_SEED, MAX_STACK_HEIGHT, TRANSACTION_LEVEL_STACK_HEIGHT,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, InstructionResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(cpi_depth_guard::ID);
+const CALLER_ID: Pubkey = Pubkey::new_from_array(cpi_depth_guard_caller::ID);
+
+/// `CpiDepthGuardError::CpiNotAllowed`.
+const CPI_NOT_ALLOWED: u32 =
+    Namespace::CpiDepthGuard.code(CpiDepthGuardError::CpiNotAllowed as u32);
+
+/// The program, the caller and a config, whose accounts are threaded
+/// through the instructions.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    config: Pubkey,
+    config_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/cpi_depth_guard");
+        // Built with `cargo build-sbf` in the `cpi-depth-guard-caller`
+        // directory.
+        mollusk.add_program(
+            &CALLER_ID,
+            "../cpi-depth-guard-caller/target/deploy/cpi_depth_guard_caller",
+            &LOADER_V3,
+        );
+
+        let authority = Pubkey::new_unique();
+        let (config, config_bump) =
+            Pubkey::find_program_address(&[CONFIG_SEED.as_bytes(), authority.as_ref()], &ID);
+
+        let (system_program, system_account) = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program),
+            ),
+            (config, Account::default()),
+            (system_program, system_account),
+            (ID, create_program_account_loader_v3(&ID)),
+            (CALLER_ID, create_program_account_loader_v3(&CALLER_ID)),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            config,
+            config_bump,
+            accounts,
+        }
+    }
+
+    /// Processes `instruction`, keeping the resulting accounts if it
+    /// succeeded.
+    fn process(&mut self, instruction: &Instruction, checks: &[Check]) -> InstructionResult {
+        let res =
+            self.mollusk
+                .process_and_validate_instruction(instruction, &self.accounts, checks);
+        if res.program_result.is_ok() {
+            self.accounts = res.resulting_accounts.clone();
+        }
+        res
+    }
+
+    fn initialize(&self) -> Instruction {
+        let mut data = CpiDepthGuardInstruction::Initialize
+            .discriminator()
+            .to_vec();
+        data.extend_from_slice(
+            &InitializeInstructionData {
+                bump: self.config_bump,
+            }
+            .to_bytes(),
+        );
+        Instruction::new_with_bytes(
+            ID,
+            &data,
+            vec![
+                AccountMeta::new(self.authority, true),
+                AccountMeta::new(self.config, false),
+                AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            ],
+        )
+    }
+
+    fn update(&self, value: u64) -> Instruction {
+        let mut data = CpiDepthGuardInstruction::Update.discriminator().to_vec();
+        data.extend_from_slice(&UpdateInstructionData { value }.to_bytes());
+        Instruction::new_with_bytes(
+            ID,
+            &data,
+            vec![
+                AccountMeta::new_readonly(self.authority, true),
+                AccountMeta::new(self.config, false),
+            ],
+        )
+    }
+
+    fn read(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            ID,
+            CpiDepthGuardInstruction::Read.discriminator(),
+            vec![AccountMeta::new_readonly(self.config, false)],
+        )
+    }
+}
+
+/// Wraps `instruction` into an instruction of the caller program, which
+/// invokes it. Every wrap nests the CPI one level deeper.
+fn through_caller(instruction: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(instruction.program_id, false)];
+    accounts.extend(instruction.accounts);
+    Instruction::new_with_bytes(CALLER_ID, &instruction.data, accounts)
+}
+
+#[test]
+fn test_max_stack_height() {
+    assert_eq!(
+        CpiDepthGuardInstruction::Update.max_stack_height(),
+        TRANSACTION_LEVEL_STACK_HEIGHT
+    );
+    assert_eq!(
+        CpiDepthGuardInstruction::Read.max_stack_height(),
+        MAX_STACK_HEIGHT
+    );
+}
+
+#[test]
+fn test_top_level() {
+    let mut setup = Setup::new();
+
+    setup.process(&setup.initialize(), &[Check::success()]);
+    setup.process(&setup.update(42), &[Check::success()]);
+    setup.process(
+        &setup.read(),
+        &[Check::success(), Check::return_data(&42u64.to_le_bytes())],
+    );
+
+    let config = setup.accounts.iter().find(|(key, _)| *key == setup.config);
+    let config = Config::read(&config.unwrap().1.data).unwrap();
+    assert_eq!(config.authority, setup.authority.to_bytes());
+    assert_eq!(config.value, 42);
+}
+
+#[test]
+fn test_cpi() {
+    let mut setup = Setup::new();
+
+    // Creating a config is allowed through a CPI.
+    setup.process(&through_caller(setup.initialize()), &[Check::success()]);
+
+    // Updating it isn't, even with the signature of the authority, which the
+    // caller passes on.
+    setup.process(
+        &through_caller(setup.update(42)),
+        &[Check::err(ProgramError::Custom(CPI_NOT_ALLOWED))],
+    );
+    setup.process(&setup.update(42), &[Check::success()]);
+
+    // Reading it is allowed at any depth.
+    setup.process(
+        &through_caller(setup.read()),
+        &[Check::success(), Check::return_data(&42u64.to_le_bytes())],
+    );
+    setup.process(
+        &through_caller(through_caller(setup.read())),
+        &[Check::success(), Check::return_data(&42u64.to_le_bytes())],
+    );
+
+    // Nesting the CPI deeper doesn't get around the check.
+    setup.process(
+        &through_caller(through_caller(setup.update(43))),
+        &[Check::err(ProgramError::Custom(CPI_NOT_ALLOWED))],
+    );
+}
@@ -0,0 +1,220 @@
+//! Transfers from token-2022 accounts with the CPI guard extension.
+//!
+//! The CPI guard protects an account from programs the owner signs
+//! transactions for. While it's enabled, the token program refuses to
+//! transfer tokens in a CPI with the signature of the owner, so a program
+//! can't drain the account behind the back of the owner. Only a delegate
+//! approved by the owner outside of a CPI can transfer in a CPI.
+//!
+//! The program transfers tokens on behalf of the owner. For unguarded
+//! accounts, it forwards the signature of the owner. For guarded accounts,
+//! it transfers as the transfer delegate PDA of the owner, which the owner
+//! has to approve for the amount with a top-level `Approve` instruction,
+//! e.g. in the same transaction. Without the approval, the program fails
+//! with [`CpiGuardError::CpiGuardEnabled`] instead of the generic error of
+//! the token program, so clients know what to do.
+
+#![no_std]
+
+use common::checks::{require_owned_by, require_signer};
+use common::{info, keys_eq};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+use crate::token_2022::{
+    get_extension, TransferChecked, ACCOUNT_DELEGATED_AMOUNT_OFFSET, ACCOUNT_DELEGATE_OFFSET,
+    ACCOUNT_LEN, ACCOUNT_MINT_OFFSET, ACCOUNT_OWNER_OFFSET, CPI_GUARD, MINT_DECIMALS_OFFSET,
+};
+
+pub mod token_2022;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("DU6prxkjx864EEC6p95w8BUYYpZoJh51NQDwitF1hhpr");
+
+pub const TRANSFER_DELEGATE_SEED: &str = "transfer_delegate";
+
+/// Errors returned by the CPI guard program.
+#[repr(u32)]
+pub enum CpiGuardError {
+    /// The source account has the CPI guard enabled and the transfer
+    /// delegate PDA of the owner is not approved for the amount. Approve it
+    /// with a top-level instruction, or disable the CPI guard.
+    CpiGuardEnabled,
+}
+
+impl From<CpiGuardError> for ProgramError {
+    fn from(e: CpiGuardError) -> Self {
+        Namespace::CpiGuard.error(e as u32)
+    }
+}
+
+/// CPI guard program instruction discriminators.
+#[repr(u8)]
+pub enum CpiGuardInstruction {
+    /// Transfers tokens of the owner.
+    Transfer,
+}
+
+impl TryFrom<&u8> for CpiGuardInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Transfer),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct TransferInstructionData {
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl TransferInstructionData {
+    pub fn new(amount: u64, bump: u8) -> Self {
+        Self { amount, bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = CpiGuardInstruction::try_from(instruction)?;
+
+    match instruction {
+        CpiGuardInstruction::Transfer => process_transfer(accounts, instruction_data),
+    }
+}
+
+/// Transfers `amount` tokens from `source` of `owner` to `destination`.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner
+///   1. `[WRITE]` Source token account
+///   2. `[]` Mint
+///   3. `[WRITE]` Destination token account
+///   4. `[]` Transfer delegate PDA of the owner
+///   5. `[]` Token-2022 program
+pub fn process_transfer(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, source, mint, destination, transfer_delegate, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = TransferInstructionData::from_bytes(instruction_data)?;
+
+    let transfer_delegate_pda = create_program_address(
+        &[
+            TRANSFER_DELEGATE_SEED.as_bytes(),
+            owner.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(transfer_delegate.key(), &transfer_delegate_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    require_owned_by(source, &token_2022::ID)?;
+    require_owned_by(mint, &token_2022::ID)?;
+    let (guarded, approved) = {
+        let data = source.try_borrow_data()?;
+        if data.len() < ACCOUNT_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if &data[ACCOUNT_MINT_OFFSET..ACCOUNT_MINT_OFFSET + 32] != mint.key() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if &data[ACCOUNT_OWNER_OFFSET..ACCOUNT_OWNER_OFFSET + 32] != owner.key() {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        let guarded = get_extension(&data, CPI_GUARD).is_some_and(|lock_cpi| lock_cpi[0] != 0);
+
+        // The delegate is encoded as an option with a 4 byte tag.
+        let delegate = &data[ACCOUNT_DELEGATE_OFFSET..ACCOUNT_DELEGATE_OFFSET + 36];
+        let delegated_amount = u64::from_le_bytes(
+            data[ACCOUNT_DELEGATED_AMOUNT_OFFSET..ACCOUNT_DELEGATED_AMOUNT_OFFSET + 8]
+                .try_into()
+                .unwrap(),
+        );
+        let approved = delegate[0] == 1
+            && keys_eq(&delegate[4..], transfer_delegate.key())
+            && delegated_amount >= instruction_data.amount;
+
+        (guarded, approved)
+    };
+    let decimals = *mint
+        .try_borrow_data()?
+        .get(MINT_DECIMALS_OFFSET)
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    if !guarded {
+        TransferChecked {
+            from: source,
+            mint,
+            to: destination,
+            authority: owner,
+            amount: instruction_data.amount,
+            decimals,
+        }
+        .invoke()?;
+
+        info!(
+            "Transferred {} tokens as the owner",
+            instruction_data.amount
+        );
+    } else if approved {
+        let bump = [instruction_data.bump];
+        let seeds = [
+            Seed::from(TRANSFER_DELEGATE_SEED.as_bytes()),
+            Seed::from(owner.key()),
+            Seed::from(&bump),
+        ];
+        TransferChecked {
+            from: source,
+            mint,
+            to: destination,
+            authority: transfer_delegate,
+            amount: instruction_data.amount,
+            decimals,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        info!(
+            "Transferred {} tokens as the transfer delegate",
+            instruction_data.amount
+        );
+    } else {
+        return Err(CpiGuardError::CpiGuardEnabled.into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! Instructions and account layout of the token-2022 program.
+//!
+//! `pinocchio-token` only supports the original token program, so the CPIs
+//! are built by hand, the same way `pinocchio-token` does it.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+/// Token-2022 program ID.
+pub const ID: Pubkey = pinocchio_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Size of a token account without extensions.
+pub const ACCOUNT_LEN: usize = 165;
+
+/// Offset of the decimals in a mint.
+pub const MINT_DECIMALS_OFFSET: usize = 44;
+/// Offsets of the fields of a token account.
+pub const ACCOUNT_MINT_OFFSET: usize = 0;
+pub const ACCOUNT_OWNER_OFFSET: usize = 32;
+pub const ACCOUNT_DELEGATE_OFFSET: usize = 72;
+pub const ACCOUNT_DELEGATED_AMOUNT_OFFSET: usize = 121;
+
+/// Offset of the account type byte, followed by the extensions. Every
+/// extension is encoded as its type (`u16`), length (`u16`) and value.
+pub const ACCOUNT_TYPE_OFFSET: usize = ACCOUNT_LEN;
+pub const EXTENSIONS_OFFSET: usize = ACCOUNT_TYPE_OFFSET + 1;
+pub const EXTENSION_HEADER_LEN: usize = 4;
+
+/// `AccountType::Account`.
+pub const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+/// `ExtensionType::CpiGuard`.
+pub const CPI_GUARD: u16 = 11;
+
+/// Size of the `CpiGuard` extension.
+pub const CPI_GUARD_LEN: usize = 1;
+
+/// Returns the value of the extension of `extension_type` in the data of a
+/// token account, if it has one.
+pub fn get_extension(data: &[u8], extension_type: u16) -> Option<&[u8]> {
+    if data.len() <= ACCOUNT_TYPE_OFFSET || data[ACCOUNT_TYPE_OFFSET] != ACCOUNT_TYPE_ACCOUNT {
+        return None;
+    }
+
+    let mut offset = EXTENSIONS_OFFSET;
+    while offset + EXTENSION_HEADER_LEN <= data.len() {
+        let ty = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let len = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let value = data.get(offset + EXTENSION_HEADER_LEN..offset + EXTENSION_HEADER_LEN + len)?;
+        if ty == extension_type {
+            return Some(value);
+        }
+        offset += EXTENSION_HEADER_LEN + len;
+    }
+    None
+}
+
+/// Transfer tokens, checking the mint and its decimals.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Source token account
+///   1. `[]` Mint
+///   2. `[WRITE]` Destination token account
+///   3. `[SIGNER]` Owner or delegate of the source token account
+pub struct TransferChecked<'a> {
+    /// Source token account.
+    pub from: &'a AccountInfo,
+
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Destination token account.
+    pub to: &'a AccountInfo,
+
+    /// Owner or delegate of the source token account.
+    pub authority: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+
+    /// Decimals of the mint.
+    pub decimals: u8,
+}
+
+impl TransferChecked<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 4] = [
+            AccountMeta::writable(self.from.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::writable(self.to.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: instruction discriminator
+        // -  [1..9 ]: amount
+        // -  [9    ]: decimals
+        let mut instruction_data = [0; 10];
+        instruction_data[0] = 12;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+        instruction_data[9] = self.decimals;
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.from, self.mint, self.to, self.authority],
+            signers,
+        )
+    }
+}
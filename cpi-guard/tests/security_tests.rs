@@ -0,0 +1,248 @@
+//! Attacks on the CPI guard program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use cpi_guard::{
+    token_2022::{
+        ACCOUNT_DELEGATED_AMOUNT_OFFSET, ACCOUNT_DELEGATE_OFFSET, ACCOUNT_LEN,
+        ACCOUNT_TYPE_ACCOUNT, CPI_GUARD, CPI_GUARD_LEN,
+    },
+    CpiGuardInstruction, TransferInstructionData, TRANSFER_DELEGATE_SEED,
+};
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(cpi_guard::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(cpi_guard::token_2022::ID);
+
+const DECIMALS: u8 = 6;
+
+/// Size of a mint without extensions.
+const MINT_LEN: usize = 82;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: CpiGuardInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<CpiGuardInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the transfer delegate PDA of `owner` and its bump.
+fn transfer_delegate_pda(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TRANSFER_DELEGATE_SEED.as_bytes(), owner.as_ref()], &ID)
+}
+
+fn mint_account(mollusk: &Mollusk, decimals: u8) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(MINT_LEN),
+        MINT_LEN,
+        &TOKEN_ID,
+    );
+    account.data[36..44].copy_from_slice(&1_000u64.to_le_bytes());
+    account.data[44] = decimals;
+    account.data[45] = 1;
+    account
+}
+
+/// Returns a token account of `owner` with 1 000 tokens and the CPI guard
+/// enabled, with the transfer delegate PDA of the owner approved for all
+/// of them.
+fn guarded_token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let mut data = vec![0; ACCOUNT_LEN];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&1_000u64.to_le_bytes());
+    data[ACCOUNT_DELEGATE_OFFSET..ACCOUNT_DELEGATE_OFFSET + 4].copy_from_slice(&1u32.to_le_bytes());
+    data[ACCOUNT_DELEGATE_OFFSET + 4..ACCOUNT_DELEGATE_OFFSET + 36]
+        .copy_from_slice(transfer_delegate_pda(owner).0.as_ref());
+    // Initialized.
+    data[108] = 1;
+    data[ACCOUNT_DELEGATED_AMOUNT_OFFSET..ACCOUNT_DELEGATED_AMOUNT_OFFSET + 8]
+        .copy_from_slice(&1_000u64.to_le_bytes());
+    data.push(ACCOUNT_TYPE_ACCOUNT);
+    data.extend_from_slice(&CPI_GUARD.to_le_bytes());
+    data.extend_from_slice(&(CPI_GUARD_LEN as u16).to_le_bytes());
+    data.push(1);
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Accounts of an owner with a guarded token account, which approved their
+/// transfer delegate PDA, and of an attacker with a guarded token account of
+/// the same mint.
+struct Setup {
+    mollusk: Mollusk,
+    source: Pubkey,
+    mint: Pubkey,
+    transfer_delegate: Pubkey,
+    attacker: Pubkey,
+    attacker_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/cpi_guard");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+
+        let owner = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (transfer_delegate, _) = transfer_delegate_pda(&owner);
+        let attacker = Pubkey::new_unique();
+        let attacker_ata = Pubkey::new_unique();
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (source, guarded_token_account(&mollusk, &mint, &owner)),
+            (mint, mint_account(&mollusk, DECIMALS)),
+            (transfer_delegate, Account::default()),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_ata,
+                guarded_token_account(&mollusk, &mint, &attacker),
+            ),
+            (transfer_delegate_pda(&attacker).0, Account::default()),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            source,
+            mint,
+            transfer_delegate,
+            attacker,
+            attacker_ata,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Transfers tokens from `source` of `mint` to the token account of the
+    /// attacker, signed by the attacker.
+    fn attacker_transfer(
+        &self,
+        source: &Pubkey,
+        mint: &Pubkey,
+        transfer_delegate: &Pubkey,
+    ) -> Instruction {
+        let (_, bump) = transfer_delegate_pda(&self.attacker);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.attacker, true),
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(self.attacker_ata, false),
+            AccountMeta::new_readonly(*transfer_delegate, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                CpiGuardInstruction::Transfer,
+                &TransferInstructionData::new(1_000, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let (transfer_delegate, _) = transfer_delegate_pda(&setup.attacker);
+    let mut instruction =
+        setup.attacker_transfer(&setup.attacker_ata, &setup.mint, &transfer_delegate);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker transfers the tokens of the owner as the transfer delegate
+/// PDA the owner approved.
+#[test]
+fn test_transfer_delegate_of_other_owner() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_transfer(&setup.source, &setup.mint, &setup.transfer_delegate),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// A token account of the attacker with the layout of token-2022, but
+/// owned by another program.
+#[test]
+fn test_spoofed_source() {
+    let setup = Setup::new();
+    let mut source_account = guarded_token_account(&setup.mollusk, &setup.mint, &setup.attacker);
+    source_account.owner = Pubkey::new_unique();
+    let source = Pubkey::new_unique();
+    let setup = setup.with(source, source_account);
+    let (transfer_delegate, _) = transfer_delegate_pda(&setup.attacker);
+    setup.expect_err(
+        &setup.attacker_transfer(&source, &setup.mint, &transfer_delegate),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A mint with other decimals than the mint of the source account, owned by
+/// another program.
+#[test]
+fn test_spoofed_mint() {
+    let setup = Setup::new();
+    let mut mint_account = mint_account(&setup.mollusk, 0);
+    mint_account.owner = Pubkey::new_unique();
+    let mint = setup.mint;
+    let setup = setup.with(mint, mint_account);
+    let (transfer_delegate, _) = transfer_delegate_pda(&setup.attacker);
+    setup.expect_err(
+        &setup.attacker_transfer(&setup.attacker_ata, &mint, &transfer_delegate),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The token account of the attacker, passed with a mint of other decimals
+/// than its own.
+#[test]
+fn test_source_of_other_mint() {
+    let setup = Setup::new();
+    let mint = Pubkey::new_unique();
+    let mint_account = mint_account(&setup.mollusk, 0);
+    let setup = setup.with(mint, mint_account);
+    let (transfer_delegate, _) = transfer_delegate_pda(&setup.attacker);
+    setup.expect_err(
+        &setup.attacker_transfer(&setup.attacker_ata, &mint, &transfer_delegate),
+        ProgramError::InvalidAccountData,
+    );
+}
@@ -0,0 +1,320 @@
+//! The token-2022 program is loaded from `third-party/spl_token_2022.so`,
+//! which can be dumped from mainnet with:
+//!
+//! ```sh
+//! solana program dump -um TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb \
+//!     third-party/spl_token_2022.so
+//! ```
+
+use std::mem;
+
+use common::encoding::Encoding;
+use cpi_guard::{
+    token_2022::{
+        get_extension, ACCOUNT_DELEGATED_AMOUNT_OFFSET, ACCOUNT_DELEGATE_OFFSET, ACCOUNT_LEN,
+        ACCOUNT_TYPE_ACCOUNT, CPI_GUARD, CPI_GUARD_LEN, EXTENSIONS_OFFSET, EXTENSION_HEADER_LEN,
+    },
+    CpiGuardInstruction, TransferInstructionData, TRANSFER_DELEGATE_SEED,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(cpi_guard::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(cpi_guard::token_2022::ID);
+
+/// `CpiGuardError::CpiGuardEnabled`.
+const CPI_GUARD_ENABLED: u32 = Namespace::CpiGuard.code(0);
+
+const DECIMALS: u8 = 6;
+
+/// Size of a mint without extensions.
+const MINT_LEN: usize = 82;
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Size of a token account with the `CpiGuard` extension.
+const GUARDED_ACCOUNT_LEN: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN + CPI_GUARD_LEN;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: CpiGuardInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<CpiGuardInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/cpi_guard");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, supply: u64) -> Account {
+    let mut data = vec![0; MINT_LEN];
+    data[36..44].copy_from_slice(&supply.to_le_bytes());
+    data[44] = DECIMALS;
+    data[45] = 1;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(MINT_LEN),
+        MINT_LEN,
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns the data of a token account, with the `CpiGuard` extension if
+/// `lock_cpi` is set.
+fn token_account_data(
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+    lock_cpi: Option<bool>,
+) -> Vec<u8> {
+    let mut data = vec![0; ACCOUNT_LEN];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[AMOUNT_OFFSET..AMOUNT_OFFSET + 8].copy_from_slice(&amount.to_le_bytes());
+    // Initialized.
+    data[108] = 1;
+
+    if let Some(lock_cpi) = lock_cpi {
+        data.push(ACCOUNT_TYPE_ACCOUNT);
+        data.extend_from_slice(&CPI_GUARD.to_le_bytes());
+        data.extend_from_slice(&(CPI_GUARD_LEN as u16).to_le_bytes());
+        data.push(lock_cpi as u8);
+    }
+    data
+}
+
+fn token_account(
+    mollusk: &Mollusk,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+    lock_cpi: Option<bool>,
+) -> Account {
+    let data = token_account_data(mint, owner, amount, lock_cpi);
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// An owner with tokens in an account with the CPI guard enabled and a
+/// recipient.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    source: Pubkey,
+    mint: Pubkey,
+    destination: Pubkey,
+    transfer_delegate: Pubkey,
+    transfer_delegate_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let owner = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let (transfer_delegate, transfer_delegate_bump) =
+            Pubkey::find_program_address(&[TRANSFER_DELEGATE_SEED.as_bytes(), owner.as_ref()], &ID);
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (
+                source,
+                token_account(&mollusk, &mint, &owner, 1_000, Some(true)),
+            ),
+            (mint, mint_account(&mollusk, 1_000)),
+            (
+                destination,
+                token_account(&mollusk, &mint, &Pubkey::new_unique(), 0, None),
+            ),
+            (transfer_delegate, Account::default()),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            source,
+            mint,
+            destination,
+            transfer_delegate,
+            transfer_delegate_bump,
+            accounts,
+        }
+    }
+
+    /// Sets the CPI guard of the source account, or removes the extension.
+    fn lock_cpi(&mut self, lock_cpi: Option<bool>) {
+        self.accounts[1].1 = token_account(&self.mollusk, &self.mint, &self.owner, 1_000, lock_cpi);
+    }
+
+    /// Approves the transfer delegate PDA for `amount`, like a top-level
+    /// `Approve` instruction does.
+    fn approve(&mut self, amount: u64) {
+        let data = &mut self.accounts[1].1.data;
+        data[ACCOUNT_DELEGATE_OFFSET..ACCOUNT_DELEGATE_OFFSET + 4]
+            .copy_from_slice(&1u32.to_le_bytes());
+        data[ACCOUNT_DELEGATE_OFFSET + 4..ACCOUNT_DELEGATE_OFFSET + 36]
+            .copy_from_slice(self.transfer_delegate.as_ref());
+        data[ACCOUNT_DELEGATED_AMOUNT_OFFSET..ACCOUNT_DELEGATED_AMOUNT_OFFSET + 8]
+            .copy_from_slice(&amount.to_le_bytes());
+    }
+
+    fn instruction_transfer(&self, amount: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.source, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new(self.destination, false),
+            AccountMeta::new_readonly(self.transfer_delegate, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                CpiGuardInstruction::Transfer,
+                &TransferInstructionData::new(amount, self.transfer_delegate_bump),
+            ),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(TransferInstructionData::LEN, 16);
+    assert_eq!(GUARDED_ACCOUNT_LEN, 171);
+}
+
+#[test]
+fn test_get_extension() {
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+
+    let data = token_account_data(&mint, &owner, 0, Some(true));
+    assert_eq!(get_extension(&data, CPI_GUARD), Some(&[1][..]));
+    let data = token_account_data(&mint, &owner, 0, Some(false));
+    assert_eq!(get_extension(&data, CPI_GUARD), Some(&[0][..]));
+    let data = token_account_data(&mint, &owner, 0, None);
+    assert_eq!(get_extension(&data, CPI_GUARD), None);
+
+    // Truncated extensions are ignored.
+    let data = token_account_data(&mint, &owner, 0, Some(true));
+    assert_eq!(get_extension(&data[..data.len() - 1], CPI_GUARD), None);
+}
+
+#[test]
+fn test_transfer_unguarded() {
+    let mut setup = Setup::new();
+
+    for lock_cpi in [None, Some(false)] {
+        setup.lock_cpi(lock_cpi);
+
+        setup.mollusk.process_and_validate_instruction(
+            &setup.instruction_transfer(400),
+            &setup.accounts,
+            &[
+                Check::success(),
+                Check::account(&setup.source)
+                    .data_slice(AMOUNT_OFFSET, &600u64.to_le_bytes())
+                    .build(),
+                Check::account(&setup.destination)
+                    .data_slice(AMOUNT_OFFSET, &400u64.to_le_bytes())
+                    .build(),
+            ],
+        );
+    }
+}
+
+#[test]
+fn test_transfer_guarded() {
+    let mut setup = Setup::new();
+
+    // The owner has to approve the transfer delegate PDA first.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_transfer(400),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(CPI_GUARD_ENABLED))],
+    );
+
+    // The approval has to cover the amount.
+    setup.approve(300);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_transfer(400),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(CPI_GUARD_ENABLED))],
+    );
+
+    setup.approve(400);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_transfer(400),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.source)
+                .data_slice(AMOUNT_OFFSET, &600u64.to_le_bytes())
+                .data_slice(ACCOUNT_DELEGATED_AMOUNT_OFFSET, &0u64.to_le_bytes())
+                .build(),
+            Check::account(&setup.destination)
+                .data_slice(AMOUNT_OFFSET, &400u64.to_le_bytes())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_transfer_invalid() {
+    let setup = Setup::new();
+
+    let mut ix = setup.instruction_transfer(400);
+    ix.accounts[4].pubkey = Pubkey::new_unique();
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+
+    // Only the owner of the source account can transfer.
+    let mut accounts = setup.accounts.clone();
+    accounts[1].1 = token_account(
+        &setup.mollusk,
+        &setup.mint,
+        &Pubkey::new_unique(),
+        1_000,
+        None,
+    );
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_transfer(400),
+        &accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
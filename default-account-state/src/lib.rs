@@ -0,0 +1,549 @@
+//! KYC-gated tokens with the default account state and mint close
+//! authority extensions of token-2022.
+//!
+//! Every new token account of the mint starts frozen, so nobody can
+//! receive or move tokens before they pass KYC. The config PDA of the
+//! program is the freeze authority, so accounts can only be thawed through
+//! the program:
+//!
+//! * The admin approves a holder after checking them off-chain, which
+//!   creates their approval PDA.
+//! * Anyone can then thaw the token accounts of an approved holder, e.g.
+//!   the holder right after creating their account.
+//!
+//! The config PDA is also the close authority of the mint, which lets the
+//! admin close the mint and reclaim its rent once all tokens are burned.
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use common::close::close_account;
+use common::sysvars::Sysvars;
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::token_2022::{
+    CloseAccount, InitializeDefaultAccountState, InitializeMint2, InitializeMintCloseAuthority,
+    MintTo, ThawAccount, ACCOUNT_LEN, ACCOUNT_MINT_OFFSET, ACCOUNT_OWNER_OFFSET,
+    ACCOUNT_STATE_FROZEN, MINT_SUPPLY_OFFSET, MINT_WITH_EXTENSIONS_LEN,
+};
+
+pub mod token_2022;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("G1yCavVuCLXqmACZHkprnLnCBWdDm4QDkQQAfZM5hSiw");
+
+pub const CONFIG_SEED: &str = "config";
+pub const APPROVAL_SEED: &str = "approval";
+
+/// Errors returned by the default account state program.
+#[repr(u32)]
+pub enum DefaultAccountStateError {
+    /// The owner of the token account is not approved.
+    NotApproved,
+    /// The mint still has tokens in circulation.
+    SupplyNotZero,
+}
+
+impl From<DefaultAccountStateError> for ProgramError {
+    fn from(e: DefaultAccountStateError) -> Self {
+        Namespace::DefaultAccountState.error(e as u32)
+    }
+}
+
+/// On-chain configuration of a mint. The config PDA is the mint, freeze
+/// and close authority of the mint.
+#[repr(C)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+assert_layout!(Config, size = 65, align = 1, { admin: 0, mint: 32, bump: 64 });
+
+impl Config {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain record of an approved holder.
+#[repr(C)]
+pub struct Approval {
+    pub mint: Pubkey,
+    pub holder: Pubkey,
+}
+
+assert_layout!(Approval, size = 64, align = 1, { mint: 0, holder: 32 });
+
+impl Approval {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Default account state program instruction discriminators.
+#[repr(u8)]
+pub enum DefaultAccountStateInstruction {
+    /// Creates a mint whose token accounts start frozen.
+    InitializeMint,
+    /// Approves a holder.
+    Approve,
+    /// Thaws a token account of an approved holder.
+    Thaw,
+    /// Mints tokens to a token account.
+    MintTo,
+    /// Closes a mint without supply.
+    CloseMint,
+}
+
+impl TryFrom<&u8> for DefaultAccountStateInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeMint),
+            1 => Ok(Self::Approve),
+            2 => Ok(Self::Thaw),
+            3 => Ok(Self::MintTo),
+            4 => Ok(Self::CloseMint),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeMintInstructionData {
+    pub decimals: u8,
+    pub bump: u8,
+}
+
+impl InitializeMintInstructionData {
+    pub fn new(decimals: u8, bump: u8) -> Self {
+        Self { decimals, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ApproveInstructionData {
+    pub bump: u8,
+}
+
+impl ApproveInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct MintToInstructionData {
+    pub amount: u64,
+}
+
+impl MintToInstructionData {
+    pub fn new(amount: u64) -> Self {
+        Self { amount }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = DefaultAccountStateInstruction::try_from(instruction)?;
+
+    match instruction {
+        DefaultAccountStateInstruction::InitializeMint => {
+            process_initialize_mint(accounts, instruction_data)
+        }
+        DefaultAccountStateInstruction::Approve => process_approve(accounts, instruction_data),
+        DefaultAccountStateInstruction::Thaw => process_thaw(accounts),
+        DefaultAccountStateInstruction::MintTo => process_mint_to(accounts, instruction_data),
+        DefaultAccountStateInstruction::CloseMint => process_close_mint(accounts),
+    }
+}
+
+/// Creates a mint whose token accounts start frozen, and its config PDA,
+/// which is the mint, freeze and close authority.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[WRITE, SIGNER]` Mint
+///   2. `[WRITE]` Config PDA of the mint
+///   3. `[]` System program
+///   4. `[]` Token-2022 program
+///   5. `[]` Rent sysvar, optional, read instead of fetching the rent
+pub fn process_initialize_mint(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let (admin, mint, config, rent_sysvar) = match accounts {
+        [admin, mint, config, _system_program, _token_program] => (admin, mint, config, None),
+        [admin, mint, config, _system_program, _token_program, rent_sysvar] => {
+            (admin, mint, config, Some(rent_sysvar))
+        }
+        _ => return Err(ProgramError::NotEnoughAccountKeys),
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeMintInstructionData::from_bytes(instruction_data)?;
+
+    let config_pda = create_program_address(
+        &[CONFIG_SEED.as_bytes(), mint.key(), &[instruction_data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(config.key(), &config_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // The config and the mint are both funded with the rent, which is only
+    // read once.
+    let sysvars = match rent_sysvar {
+        Some(rent_sysvar) => Sysvars::new().with_rent_account(rent_sysvar)?,
+        None => Sysvars::new(),
+    };
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(CONFIG_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: admin,
+        to: config,
+        lamports: sysvars.rent()?.minimum_balance(Config::LEN),
+        space: Config::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    {
+        let mut data = config.try_borrow_mut_data()?;
+        let config: &mut Config = unsafe { &mut *data.as_mut_ptr().cast() };
+        config.admin = *admin.key();
+        config.mint = *mint.key();
+        config.bump = instruction_data.bump;
+    }
+
+    CreateAccount {
+        from: admin,
+        to: mint,
+        lamports: sysvars.rent()?.minimum_balance(MINT_WITH_EXTENSIONS_LEN),
+        space: MINT_WITH_EXTENSIONS_LEN as u64,
+        owner: &token_2022::ID,
+    }
+    .invoke()?;
+
+    // Extensions have to be initialized before the mint itself.
+    InitializeMintCloseAuthority {
+        mint,
+        close_authority: Some(config.key()),
+    }
+    .invoke()?;
+
+    InitializeDefaultAccountState {
+        mint,
+        state: ACCOUNT_STATE_FROZEN,
+    }
+    .invoke()?;
+
+    InitializeMint2 {
+        mint,
+        decimals: instruction_data.decimals,
+        mint_authority: config.key(),
+        freeze_authority: Some(config.key()),
+    }
+    .invoke()?;
+
+    info!("Initialized a mint with frozen accounts by default");
+
+    Ok(())
+}
+
+/// Approves `holder` by creating their approval PDA.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[]` Config PDA
+///   2. `[]` Holder
+///   3. `[WRITE]` Approval PDA of the holder
+///   4. `[]` System program
+pub fn process_approve(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, config, holder, approval, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = ApproveInstructionData::from_bytes(instruction_data)?;
+
+    let data = config.try_borrow_data()?;
+    let config = validate_config(config, &data)?;
+    validate_admin(admin, config)?;
+
+    let approval_pda = create_program_address(
+        &[
+            APPROVAL_SEED.as_bytes(),
+            &config.mint,
+            holder.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(approval.key(), &approval_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(APPROVAL_SEED.as_bytes()),
+        Seed::from(&config.mint),
+        Seed::from(holder.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: admin,
+        to: approval,
+        lamports: Rent::get()?.minimum_balance(Approval::LEN),
+        space: Approval::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut data = approval.try_borrow_mut_data()?;
+    let approval: &mut Approval = unsafe { &mut *data.as_mut_ptr().cast() };
+    approval.mint = config.mint;
+    approval.holder = *holder.key();
+
+    info!("Approved the holder");
+
+    Ok(())
+}
+
+/// Thaws `token_account`, whose owner has to be approved. Anyone can thaw
+/// the accounts of approved holders.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Token account
+///   1. `[]` Mint
+///   2. `[]` Config PDA of the mint
+///   3. `[]` Approval PDA of the owner of the token account
+///   4. `[]` Token-2022 program
+pub fn process_thaw(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [token_account, mint, config, approval, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // The config is the signer of the thaw, so it can't stay borrowed.
+    let config_bump = {
+        let data = config.try_borrow_data()?;
+        let config = validate_config(config, &data)?;
+        if !keys_eq(&config.mint, mint.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        config.bump
+    };
+
+    let holder = token_account_owner(token_account, mint)?;
+
+    if !approval.is_owned_by(&ID) {
+        return Err(DefaultAccountStateError::NotApproved.into());
+    }
+    {
+        let data = approval.try_borrow_data()?;
+        if data.len() != Approval::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let approval: &Approval = unsafe { &*data.as_ptr().cast() };
+        if !keys_eq(&approval.mint, mint.key()) || approval.holder != holder {
+            return Err(DefaultAccountStateError::NotApproved.into());
+        }
+    }
+
+    let bump = [config_bump];
+    let seeds = [
+        Seed::from(CONFIG_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
+    ];
+    ThawAccount {
+        account: token_account,
+        mint,
+        freeze_authority: config,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Thawed the token account");
+
+    Ok(())
+}
+
+/// Mints `amount` tokens to `token_account`, which has to be thawed first.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Admin
+///   1. `[]` Config PDA of the mint
+///   2. `[WRITE]` Mint
+///   3. `[WRITE]` Token account
+///   4. `[]` Token-2022 program
+pub fn process_mint_to(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, config, mint, token_account, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = MintToInstructionData::from_bytes(instruction_data)?;
+
+    // The config is the signer of the mint, so it can't stay borrowed.
+    let config_bump = {
+        let data = config.try_borrow_data()?;
+        let config = validate_config(config, &data)?;
+        validate_admin(admin, config)?;
+        if !keys_eq(&config.mint, mint.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        config.bump
+    };
+
+    let bump = [config_bump];
+    let seeds = [
+        Seed::from(CONFIG_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
+    ];
+    MintTo {
+        mint,
+        account: token_account,
+        mint_authority: config,
+        amount: instruction_data.amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Minted {} tokens", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Closes `mint`, whose supply has to be zero, and its config PDA. The
+/// rent of both goes to the admin.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[WRITE]` Config PDA of the mint
+///   2. `[WRITE]` Mint
+///   3. `[]` Token-2022 program
+pub fn process_close_mint(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, config, mint, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // The config is the signer of the close, so it can't stay borrowed.
+    let config_bump = {
+        let data = config.try_borrow_data()?;
+        let config = validate_config(config, &data)?;
+        validate_admin(admin, config)?;
+        if !keys_eq(&config.mint, mint.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        config.bump
+    };
+
+    // The token program refuses to close the mint as well, but with an
+    // error that doesn't say why.
+    require_owned_by(mint, &token_2022::ID)?;
+    {
+        let data = mint.try_borrow_data()?;
+        let supply = data
+            .get(MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        if supply != [0; 8] {
+            return Err(DefaultAccountStateError::SupplyNotZero.into());
+        }
+    }
+
+    let bump = [config_bump];
+    let seeds = [
+        Seed::from(CONFIG_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
+    ];
+    CloseAccount {
+        account: mint,
+        destination: admin,
+        authority: config,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    close_account(config, admin)?;
+
+    info!("Closed the mint");
+
+    Ok(())
+}
+
+/// Checks that `config` is a config PDA. Returns the deserialized config.
+fn validate_config<'a>(config: &AccountInfo, data: &'a [u8]) -> Result<&'a Config, ProgramError> {
+    require_owned_by(config, &ID)?;
+    if data.len() != Config::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(unsafe { &*data.as_ptr().cast() })
+}
+
+/// Checks that `admin` is the admin of `config` and signed.
+fn validate_admin(admin: &AccountInfo, config: &Config) -> ProgramResult {
+    require_signer(admin)?;
+    if !keys_eq(&config.admin, admin.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// Returns the owner of `token_account`, which has to be a token-2022
+/// account of `mint`.
+fn token_account_owner(
+    token_account: &AccountInfo,
+    mint: &AccountInfo,
+) -> Result<Pubkey, ProgramError> {
+    require_owned_by(token_account, &token_2022::ID)?;
+    let data = token_account.try_borrow_data()?;
+    if data.len() < ACCOUNT_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if &data[ACCOUNT_MINT_OFFSET..ACCOUNT_MINT_OFFSET + 32] != mint.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut owner = [0; 32];
+    owner.copy_from_slice(&data[ACCOUNT_OWNER_OFFSET..ACCOUNT_OWNER_OFFSET + 32]);
+    Ok(owner)
+}
@@ -0,0 +1,346 @@
+//! Instructions and account layout of the token-2022 program.
+//!
+//! `pinocchio-token` only supports the original token program, so the CPIs
+//! are built by hand, the same way `pinocchio-token` does it.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+/// Token-2022 program ID.
+pub const ID: Pubkey = pinocchio_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Size of a token account without extensions. Mints with extensions are
+/// padded to the same size, so the account type byte and the extensions
+/// start at the same offset in both.
+pub const ACCOUNT_LEN: usize = 165;
+
+/// Offset of the supply in a mint.
+pub const MINT_SUPPLY_OFFSET: usize = 36;
+/// Offsets of the fields of a token account.
+pub const ACCOUNT_MINT_OFFSET: usize = 0;
+pub const ACCOUNT_OWNER_OFFSET: usize = 32;
+pub const ACCOUNT_STATE_OFFSET: usize = 108;
+
+/// `AccountState::Initialized`.
+pub const ACCOUNT_STATE_INITIALIZED: u8 = 1;
+/// `AccountState::Frozen`.
+pub const ACCOUNT_STATE_FROZEN: u8 = 2;
+
+/// Offset of the account type byte, followed by the extensions. Every
+/// extension is encoded as its type (`u16`), length (`u16`) and value.
+pub const ACCOUNT_TYPE_OFFSET: usize = ACCOUNT_LEN;
+pub const EXTENSIONS_OFFSET: usize = ACCOUNT_TYPE_OFFSET + 1;
+pub const EXTENSION_HEADER_LEN: usize = 4;
+
+/// `ExtensionType::MintCloseAuthority`.
+pub const MINT_CLOSE_AUTHORITY: u16 = 3;
+/// `ExtensionType::DefaultAccountState`.
+pub const DEFAULT_ACCOUNT_STATE: u16 = 6;
+
+/// Size of the `MintCloseAuthority` extension.
+pub const MINT_CLOSE_AUTHORITY_LEN: usize = 32;
+/// Size of the `DefaultAccountState` extension.
+pub const DEFAULT_ACCOUNT_STATE_LEN: usize = 1;
+
+/// Size of a mint with the `MintCloseAuthority` and `DefaultAccountState`
+/// extensions.
+pub const MINT_WITH_EXTENSIONS_LEN: usize = EXTENSIONS_OFFSET
+    + EXTENSION_HEADER_LEN
+    + MINT_CLOSE_AUTHORITY_LEN
+    + EXTENSION_HEADER_LEN
+    + DEFAULT_ACCOUNT_STATE_LEN;
+
+/// Initialize the `MintCloseAuthority` extension of a mint. Has to precede
+/// `InitializeMint2`.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializeMintCloseAuthority<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Authority allowed to close the mint once its supply is zero.
+    pub close_authority: Option<&'a Pubkey>,
+}
+
+impl InitializeMintCloseAuthority<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0    ]: instruction discriminator
+        // -  [1    ]: close authority presence flag
+        // -  [2..34]: close authority
+        let mut instruction_data = [0; 34];
+        instruction_data[0] = 25;
+        if let Some(close_authority) = self.close_authority {
+            instruction_data[1] = 1;
+            instruction_data[2..34].copy_from_slice(close_authority);
+        }
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Initialize the `DefaultAccountState` extension of a mint. Has to precede
+/// `InitializeMint2`.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializeDefaultAccountState<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// State of new token accounts of the mint.
+    pub state: u8,
+}
+
+impl InitializeDefaultAccountState<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0]: instruction discriminator
+        // -  [1]: extension instruction discriminator (`Initialize`)
+        // -  [2]: state
+        let instruction_data = [28, 0, self.state];
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Initialize a mint, without requiring the rent sysvar.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializeMint2<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Decimals.
+    pub decimals: u8,
+
+    /// Mint authority.
+    pub mint_authority: &'a Pubkey,
+
+    /// Freeze authority.
+    pub freeze_authority: Option<&'a Pubkey>,
+}
+
+impl InitializeMint2<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1   ]: decimals
+        // -  [2..34]: mint authority
+        // -  [34  ]: freeze authority presence flag
+        // -  [35..67]: freeze authority
+        let mut instruction_data = [0; 67];
+        instruction_data[0] = 20;
+        instruction_data[1] = self.decimals;
+        instruction_data[2..34].copy_from_slice(self.mint_authority);
+        if let Some(freeze_authority) = self.freeze_authority {
+            instruction_data[34] = 1;
+            instruction_data[35..67].copy_from_slice(freeze_authority);
+        }
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Mint new tokens to an account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Mint
+///   1. `[WRITE]` Token account to mint to
+///   2. `[SIGNER]` Mint authority
+pub struct MintTo<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Mint authority.
+    pub mint_authority: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+}
+
+impl MintTo<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.mint.key()),
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly_signer(self.mint_authority.key()),
+        ];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1..9]: amount
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 7;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.mint, self.account, self.mint_authority],
+            signers,
+        )
+    }
+}
+
+/// Thaw a frozen token account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Token account to thaw
+///   1. `[]` Mint
+///   2. `[SIGNER]` Freeze authority
+pub struct ThawAccount<'a> {
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Freeze authority.
+    pub freeze_authority: &'a AccountInfo,
+}
+
+impl ThawAccount<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::readonly_signer(self.freeze_authority.key()),
+        ];
+
+        // instruction data
+        // -  [0]: instruction discriminator
+        let instruction_data = [11];
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.account, self.mint, self.freeze_authority],
+            signers,
+        )
+    }
+}
+
+/// Close an account and transfer its lamports to the destination. Mints
+/// can be closed by their close authority once their supply is zero.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Account to close
+///   1. `[WRITE]` Destination account
+///   2. `[SIGNER]` Owner or close authority of the account
+pub struct CloseAccount<'a> {
+    /// Account to close.
+    pub account: &'a AccountInfo,
+
+    /// Destination account.
+    pub destination: &'a AccountInfo,
+
+    /// Owner or close authority.
+    pub authority: &'a AccountInfo,
+}
+
+impl CloseAccount<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::writable(self.destination.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        // instruction data
+        // -  [0]: instruction discriminator
+        let instruction_data = [9];
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.account, self.destination, self.authority],
+            signers,
+        )
+    }
+}
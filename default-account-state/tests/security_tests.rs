@@ -0,0 +1,330 @@
+//! Attacks on the default account state program, each rejected with its own
+//! error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use default_account_state::{
+    token_2022::{
+        ACCOUNT_LEN, ACCOUNT_STATE_FROZEN, ACCOUNT_STATE_OFFSET, ACCOUNT_TYPE_OFFSET,
+        DEFAULT_ACCOUNT_STATE, DEFAULT_ACCOUNT_STATE_LEN, EXTENSIONS_OFFSET, EXTENSION_HEADER_LEN,
+        MINT_CLOSE_AUTHORITY, MINT_CLOSE_AUTHORITY_LEN, MINT_WITH_EXTENSIONS_LEN,
+    },
+    Approval, Config, DefaultAccountStateInstruction, MintToInstructionData, APPROVAL_SEED,
+    CONFIG_SEED,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(default_account_state::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(default_account_state::token_2022::ID);
+
+/// `DefaultAccountStateError::NotApproved`.
+const NOT_APPROVED: u32 = Namespace::DefaultAccountState.code(0);
+
+const DECIMALS: u8 = 6;
+
+/// Offset of the value of the `MintCloseAuthority` extension.
+const MINT_CLOSE_AUTHORITY_OFFSET: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN;
+/// Offset of the value of the `DefaultAccountState` extension.
+const DEFAULT_ACCOUNT_STATE_OFFSET: usize =
+    MINT_CLOSE_AUTHORITY_OFFSET + MINT_CLOSE_AUTHORITY_LEN + EXTENSION_HEADER_LEN;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(
+    discriminator: DefaultAccountStateInstruction,
+    data: &T,
+) -> Vec<u8> {
+    let mut data_with_discriminator =
+        vec![0; mem::size_of::<DefaultAccountStateInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns an empty mint administered by `config`.
+fn mint_account(mollusk: &Mollusk, config: &Pubkey) -> Account {
+    let mut data = vec![0; MINT_WITH_EXTENSIONS_LEN];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(config.as_ref());
+    data[44] = DECIMALS;
+    data[45] = 1;
+    data[46..50].copy_from_slice(&1u32.to_le_bytes());
+    data[50..82].copy_from_slice(config.as_ref());
+    data[ACCOUNT_TYPE_OFFSET] = 1;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&MINT_CLOSE_AUTHORITY.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..MINT_CLOSE_AUTHORITY_OFFSET]
+        .copy_from_slice(&(MINT_CLOSE_AUTHORITY_LEN as u16).to_le_bytes());
+    data[MINT_CLOSE_AUTHORITY_OFFSET..MINT_CLOSE_AUTHORITY_OFFSET + 32]
+        .copy_from_slice(config.as_ref());
+    let header = DEFAULT_ACCOUNT_STATE_OFFSET - EXTENSION_HEADER_LEN;
+    data[header..header + 2].copy_from_slice(&DEFAULT_ACCOUNT_STATE.to_le_bytes());
+    data[header + 2..DEFAULT_ACCOUNT_STATE_OFFSET]
+        .copy_from_slice(&(DEFAULT_ACCOUNT_STATE_LEN as u16).to_le_bytes());
+    data[DEFAULT_ACCOUNT_STATE_OFFSET] = ACCOUNT_STATE_FROZEN;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns a frozen token account without extensions.
+fn frozen_token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(ACCOUNT_LEN),
+        ACCOUNT_LEN,
+        &TOKEN_ID,
+    );
+    account.data[0..32].copy_from_slice(mint.as_ref());
+    account.data[32..64].copy_from_slice(owner.as_ref());
+    account.data[ACCOUNT_STATE_OFFSET] = ACCOUNT_STATE_FROZEN;
+    account
+}
+
+/// Returns the config PDA of `mint` and its account, administered by
+/// `admin`.
+fn config_account(mollusk: &Mollusk, admin: &Pubkey, mint: &Pubkey) -> (Pubkey, Account) {
+    let (config, bump) =
+        Pubkey::find_program_address(&[CONFIG_SEED.as_bytes(), mint.as_ref()], &ID);
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(admin.as_ref());
+    account.data[32..64].copy_from_slice(mint.as_ref());
+    account.data[64] = bump;
+    (config, account)
+}
+
+/// Returns the approval PDA of `holder` in `mint` and its account.
+fn approval_account(mollusk: &Mollusk, mint: &Pubkey, holder: &Pubkey) -> (Pubkey, Account) {
+    let (approval, _) = Pubkey::find_program_address(
+        &[APPROVAL_SEED.as_bytes(), mint.as_ref(), holder.as_ref()],
+        &ID,
+    );
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Approval::LEN),
+        Approval::LEN,
+        &ID,
+    );
+    account.data = [mint.as_ref(), holder.as_ref()].concat();
+    (approval, account)
+}
+
+/// Accounts of a mint of an admin, and of an attacker with a frozen token
+/// account of it, who administers a mint of their own, in which they are
+/// approved.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    mint: Pubkey,
+    config: Pubkey,
+    attacker: Pubkey,
+    attacker_account: Pubkey,
+    attacker_config: Pubkey,
+    attacker_approval: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/default_account_state");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+
+        let admin = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (config, config_account) = config_account(&mollusk, &admin, &mint);
+
+        let attacker = Pubkey::new_unique();
+        let attacker_account = Pubkey::new_unique();
+        let attacker_mint = Pubkey::new_unique();
+        let (attacker_config, attacker_config_account) =
+            config_account(&mollusk, &attacker, &attacker_mint);
+        let (attacker_approval, attacker_approval_account) =
+            approval_account(&mollusk, &attacker_mint, &attacker);
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &config)),
+            (config, config_account),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_account,
+                frozen_token_account(&mollusk, &mint, &attacker),
+            ),
+            (attacker_mint, mint_account(&mollusk, &attacker_config)),
+            (attacker_config, attacker_config_account),
+            (attacker_approval, attacker_approval_account),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            mint,
+            config,
+            attacker,
+            attacker_account,
+            attacker_config,
+            attacker_approval,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Thaws the token account of the attacker with `config` and
+    /// `approval`.
+    fn instruction_thaw(&self, config: &Pubkey, approval: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker_account, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new_readonly(*approval, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &[DefaultAccountStateInstruction::Thaw as u8],
+            ix_accounts,
+        )
+    }
+
+    /// Mints tokens to the attacker with `config`, signed by `admin`.
+    fn instruction_mint_to(&self, admin: &Pubkey, config: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new_readonly(*config, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.attacker_account, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DefaultAccountStateInstruction::MintTo,
+                &MintToInstructionData::new(1_000),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_admin_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_mint_to(&setup.admin, &setup.config);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker mints to themselves with the config of their own mint,
+/// which they administer.
+#[test]
+fn test_mint_to_with_config_of_other_mint() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_mint_to(&setup.attacker, &setup.attacker_config),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A config with the layout of the program, administered by the attacker,
+/// but owned by another program.
+#[test]
+fn test_spoofed_config() {
+    let setup = Setup::new();
+    let (_, mut config_account) = config_account(&setup.mollusk, &setup.attacker, &setup.mint);
+    config_account.owner = Pubkey::new_unique();
+    let config = Pubkey::new_unique();
+    let setup = setup.with(config, config_account);
+    setup.expect_err(
+        &setup.instruction_mint_to(&setup.attacker, &config),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker thaws their account with their approval in their own mint.
+#[test]
+fn test_approval_of_other_mint() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_thaw(&setup.config, &setup.attacker_approval),
+        ProgramError::Custom(NOT_APPROVED),
+    );
+}
+
+/// An approval of the attacker in the mint, but owned by another program.
+#[test]
+fn test_spoofed_approval() {
+    let setup = Setup::new();
+    let (approval, mut approval_account) =
+        approval_account(&setup.mollusk, &setup.mint, &setup.attacker);
+    approval_account.owner = Pubkey::new_unique();
+    let setup = setup.with(approval, approval_account);
+    setup.expect_err(
+        &setup.instruction_thaw(&setup.config, &approval),
+        ProgramError::Custom(NOT_APPROVED),
+    );
+}
+
+/// The attacker thaws their account with the config of their own mint, the
+/// freeze authority of which the program signs for.
+#[test]
+fn test_thaw_with_config_of_other_mint() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_thaw(&setup.attacker_config, &setup.attacker_approval),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker closes the empty mint to collect its rent and the rent of
+/// its config.
+#[test]
+fn test_close_mint_by_attacker() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(setup.config, false),
+        AccountMeta::new(setup.mint, false),
+        AccountMeta::new_readonly(TOKEN_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &[DefaultAccountStateInstruction::CloseMint as u8],
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
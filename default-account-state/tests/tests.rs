@@ -0,0 +1,530 @@
+//! The token-2022 program is loaded from `third-party/spl_token_2022.so`,
+//! which can be dumped from mainnet with:
+//!
+//! ```sh
+//! solana program dump -um TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb \
+//!     third-party/spl_token_2022.so
+//! ```
+
+use std::mem;
+
+use common::encoding::Encoding;
+use default_account_state::{
+    token_2022::{
+        ACCOUNT_LEN, ACCOUNT_STATE_FROZEN, ACCOUNT_STATE_INITIALIZED, ACCOUNT_STATE_OFFSET,
+        ACCOUNT_TYPE_OFFSET, DEFAULT_ACCOUNT_STATE, DEFAULT_ACCOUNT_STATE_LEN, EXTENSIONS_OFFSET,
+        EXTENSION_HEADER_LEN, MINT_CLOSE_AUTHORITY, MINT_CLOSE_AUTHORITY_LEN,
+        MINT_WITH_EXTENSIONS_LEN,
+    },
+    Approval, ApproveInstructionData, Config, DefaultAccountStateInstruction,
+    InitializeMintInstructionData, MintToInstructionData, APPROVAL_SEED, CONFIG_SEED,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::bench::Bencher;
+
+const ID: Pubkey = Pubkey::new_from_array(default_account_state::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(default_account_state::token_2022::ID);
+
+/// `DefaultAccountStateError::NotApproved`.
+const NOT_APPROVED: u32 = Namespace::DefaultAccountState.code(0);
+/// `DefaultAccountStateError::SupplyNotZero`.
+const SUPPLY_NOT_ZERO: u32 = Namespace::DefaultAccountState.code(1);
+/// `TokenError::AccountFrozen`.
+const ACCOUNT_FROZEN: u32 = 17;
+
+const DECIMALS: u8 = 6;
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offset of the value of the `MintCloseAuthority` extension.
+const MINT_CLOSE_AUTHORITY_OFFSET: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN;
+/// Offset of the value of the `DefaultAccountState` extension.
+const DEFAULT_ACCOUNT_STATE_OFFSET: usize =
+    MINT_CLOSE_AUTHORITY_OFFSET + MINT_CLOSE_AUTHORITY_LEN + EXTENSION_HEADER_LEN;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(
+    discriminator: DefaultAccountStateInstruction,
+    data: &T,
+) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<DefaultAccountStateInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/default_account_state");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+    mollusk
+}
+
+/// Returns a mint administered by `config`, as created by `InitializeMint`.
+fn mint_account(mollusk: &Mollusk, config: &Pubkey, supply: u64) -> Account {
+    let mut data = vec![0; MINT_WITH_EXTENSIONS_LEN];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(config.as_ref());
+    data[36..44].copy_from_slice(&supply.to_le_bytes());
+    data[44] = DECIMALS;
+    data[45] = 1;
+    data[46..50].copy_from_slice(&1u32.to_le_bytes());
+    data[50..82].copy_from_slice(config.as_ref());
+    data[ACCOUNT_TYPE_OFFSET] = 1;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&MINT_CLOSE_AUTHORITY.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..MINT_CLOSE_AUTHORITY_OFFSET]
+        .copy_from_slice(&(MINT_CLOSE_AUTHORITY_LEN as u16).to_le_bytes());
+    data[MINT_CLOSE_AUTHORITY_OFFSET..MINT_CLOSE_AUTHORITY_OFFSET + 32]
+        .copy_from_slice(config.as_ref());
+    let header = DEFAULT_ACCOUNT_STATE_OFFSET - EXTENSION_HEADER_LEN;
+    data[header..header + 2].copy_from_slice(&DEFAULT_ACCOUNT_STATE.to_le_bytes());
+    data[header + 2..DEFAULT_ACCOUNT_STATE_OFFSET]
+        .copy_from_slice(&(DEFAULT_ACCOUNT_STATE_LEN as u16).to_le_bytes());
+    data[DEFAULT_ACCOUNT_STATE_OFFSET] = ACCOUNT_STATE_FROZEN;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns a token account without extensions in `state`.
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, state: u8) -> Account {
+    let mut data = vec![0; ACCOUNT_LEN];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[ACCOUNT_STATE_OFFSET] = state;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(ACCOUNT_LEN),
+        ACCOUNT_LEN,
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+fn config_account(mollusk: &Mollusk, admin: &Pubkey, mint: &Pubkey, bump: u8) -> Account {
+    let mut data = vec![0; Config::LEN];
+    data[0..32].copy_from_slice(admin.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+fn approval_account(mollusk: &Mollusk, mint: &Pubkey, holder: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Approval::LEN),
+        Approval::LEN,
+        &ID,
+    );
+    account.data = [mint.as_ref(), holder.as_ref()].concat();
+    account
+}
+
+/// A mint administered by the program and a frozen token account of a
+/// holder.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    mint: Pubkey,
+    config: Pubkey,
+    config_bump: u8,
+    holder: Pubkey,
+    holder_account: Pubkey,
+    approval: Pubkey,
+    approval_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let admin = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (config, config_bump) =
+            Pubkey::find_program_address(&[CONFIG_SEED.as_bytes(), mint.as_ref()], &ID);
+        let holder = Pubkey::new_unique();
+        let holder_account = Pubkey::new_unique();
+        let (approval, approval_bump) = Pubkey::find_program_address(
+            &[APPROVAL_SEED.as_bytes(), mint.as_ref(), holder.as_ref()],
+            &ID,
+        );
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &config, 0)),
+            (config, config_account(&mollusk, &admin, &mint, config_bump)),
+            (holder, Account::default()),
+            (
+                holder_account,
+                token_account(&mollusk, &mint, &holder, ACCOUNT_STATE_FROZEN),
+            ),
+            (approval, Account::default()),
+            keyed_account_for_system_program(),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            mint,
+            config,
+            config_bump,
+            holder,
+            holder_account,
+            approval,
+            approval_bump,
+            accounts,
+        }
+    }
+
+    fn instruction_initialize_mint(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.mint, true),
+            AccountMeta::new(self.config, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DefaultAccountStateInstruction::InitializeMint,
+                &InitializeMintInstructionData::new(DECIMALS, self.config_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Returns the initialization reading the rent from `rent_sysvar`.
+    fn instruction_initialize_mint_with_rent_sysvar(&self, rent_sysvar: &Pubkey) -> Instruction {
+        let mut ix = self.instruction_initialize_mint();
+        ix.accounts
+            .push(AccountMeta::new_readonly(*rent_sysvar, false));
+        ix
+    }
+
+    fn instruction_approve(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new_readonly(self.holder, false),
+            AccountMeta::new(self.approval, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DefaultAccountStateInstruction::Approve,
+                &ApproveInstructionData::new(self.approval_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_thaw(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.holder_account, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new_readonly(self.approval, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &[DefaultAccountStateInstruction::Thaw as u8],
+            ix_accounts,
+        )
+    }
+
+    fn instruction_mint_to(&self, amount: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.holder_account, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DefaultAccountStateInstruction::MintTo,
+                &MintToInstructionData::new(amount),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_close_mint(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.config, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &[DefaultAccountStateInstruction::CloseMint as u8],
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(MINT_WITH_EXTENSIONS_LEN, 207);
+    assert_eq!(Config::LEN, 65);
+    assert_eq!(Approval::LEN, 64);
+}
+
+#[test]
+fn test_initialize_mint() {
+    let mut setup = Setup::new();
+    // The mint and the config are created by the program.
+    setup.accounts[1].1 = Account::default();
+    setup.accounts[2].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_mint(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.mint)
+                .owner(&TOKEN_ID)
+                .space(MINT_WITH_EXTENSIONS_LEN)
+                .data_slice(4, setup.config.as_ref())
+                .data_slice(44, &[DECIMALS, 1])
+                .data_slice(50, setup.config.as_ref())
+                .data_slice(ACCOUNT_TYPE_OFFSET, &[1])
+                .data_slice(EXTENSIONS_OFFSET, &MINT_CLOSE_AUTHORITY.to_le_bytes())
+                .data_slice(MINT_CLOSE_AUTHORITY_OFFSET, setup.config.as_ref())
+                .data_slice(DEFAULT_ACCOUNT_STATE_OFFSET, &[ACCOUNT_STATE_FROZEN])
+                .build(),
+            Check::account(&setup.config)
+                .owner(&ID)
+                .data(
+                    &[
+                        setup.admin.as_ref(),
+                        setup.mint.as_ref(),
+                        &[setup.config_bump],
+                    ]
+                    .concat(),
+                )
+                .build(),
+        ],
+    );
+}
+
+/// The rent is read from the rent sysvar account if it's passed, which has
+/// to be the real one.
+#[test]
+fn test_initialize_mint_with_rent_sysvar() {
+    let mut setup = Setup::new();
+    setup.accounts[1].1 = Account::default();
+    setup.accounts[2].1 = Account::default();
+    let (rent_sysvar, rent_account) = setup.mollusk.sysvars.keyed_account_for_rent_sysvar();
+    setup.accounts.push((rent_sysvar, rent_account));
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_mint_with_rent_sysvar(&rent_sysvar),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.mint)
+                .lamports(
+                    setup
+                        .mollusk
+                        .sysvars
+                        .rent
+                        .minimum_balance(MINT_WITH_EXTENSIONS_LEN),
+                )
+                .build(),
+            Check::account(&setup.config)
+                .lamports(setup.mollusk.sysvars.rent.minimum_balance(Config::LEN))
+                .build(),
+        ],
+    );
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_mint_with_rent_sysvar(&setup.holder),
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+/// Measures the compute units of the initialization into `benches/`, with
+/// the rent fetched by a syscall and read from the rent sysvar account.
+#[test]
+fn test_compute_units() {
+    let mut setup = Setup::new();
+    setup.accounts[1].1 = Account::default();
+    setup.accounts[2].1 = Account::default();
+    let (rent_sysvar, rent_account) = setup.mollusk.sysvars.keyed_account_for_rent_sysvar();
+    setup.accounts.push((rent_sysvar, rent_account));
+
+    let mut bencher = Bencher::new(&setup.mollusk, "benches");
+    let syscall = bencher.bench(
+        "initialize_mint",
+        &setup.instruction_initialize_mint(),
+        &setup.accounts,
+    );
+    let account = bencher.bench(
+        "initialize_mint_rent_sysvar",
+        &setup.instruction_initialize_mint_with_rent_sysvar(&rent_sysvar),
+        &setup.accounts,
+    );
+    // Reading the passed account is cheaper than the syscall, which the
+    // instruction only makes once for both accounts it funds.
+    assert!(account.compute_units_consumed < syscall.compute_units_consumed);
+    bencher.execute();
+}
+
+#[test]
+fn test_approve_and_thaw() {
+    let setup = Setup::new();
+
+    // Frozen accounts can't receive tokens.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_mint_to(1_000),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(ACCOUNT_FROZEN))],
+    );
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_thaw(),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(NOT_APPROVED))],
+    );
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_approve(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.approval)
+                        .owner(&ID)
+                        .data(&[setup.mint.as_ref(), setup.holder.as_ref()].concat())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_thaw(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.holder_account)
+                        .data_slice(ACCOUNT_STATE_OFFSET, &[ACCOUNT_STATE_INITIALIZED])
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_mint_to(1_000),
+                &[
+                    Check::success(),
+                    Check::account(&setup.holder_account)
+                        .data_slice(AMOUNT_OFFSET, &1_000u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_thaw_invalid() {
+    let mut setup = Setup::new();
+    setup.accounts[5].1 = approval_account(&setup.mollusk, &setup.mint, &setup.holder);
+
+    // The approval of one holder doesn't thaw the accounts of another.
+    let mut accounts = setup.accounts.clone();
+    accounts[4].1 = token_account(
+        &setup.mollusk,
+        &setup.mint,
+        &Pubkey::new_unique(),
+        ACCOUNT_STATE_FROZEN,
+    );
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_thaw(),
+        &accounts,
+        &[Check::err(ProgramError::Custom(NOT_APPROVED))],
+    );
+
+    // The config is not an approval, even though it's owned by the program
+    // and long enough to be read as one.
+    let mut ix = setup.instruction_thaw();
+    ix.accounts[3].pubkey = setup.config;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+
+    // Only the admin can approve.
+    let mut ix = setup.instruction_approve();
+    ix.accounts[0].pubkey = setup.holder;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+#[test]
+fn test_close_mint() {
+    let mut setup = Setup::new();
+
+    setup.accounts[1].1 = mint_account(&setup.mollusk, &setup.config, 1_000);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_close_mint(),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(SUPPLY_NOT_ZERO))],
+    );
+
+    setup.accounts[1].1 = mint_account(&setup.mollusk, &setup.config, 0);
+    let rent = setup.accounts[1].1.lamports + setup.accounts[2].1.lamports;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_close_mint(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.mint).closed().build(),
+            Check::account(&setup.config).closed().build(),
+            Check::account(&setup.admin)
+                .lamports(LAMPORTS_PER_SOL + rent)
+                .build(),
+        ],
+    );
+}
@@ -0,0 +1,348 @@
+//! Deploys the example programs to a cluster and verifies the deployments.
+//!
+//! `deploy` deploys the binaries built by `cargo xtask build` with the
+//! Solana CLI, at the addresses the programs declare with `declare_id!`,
+//! so the keypair of each program has to be the one of its address. It's
+//! read from `<dir>/<program>-keypair.json`, where `<dir>` is `--keypairs`
+//! or the `target/deploy` of the program. The address and the SHA-256 hash
+//! of the binary of every deployed program are recorded in
+//! `deployments.json`, by cluster.
+//!
+//! `verify` fetches the binaries deployed on a cluster and checks that they
+//! match the manifest and the local builds.
+//!
+//! Run with `cargo deploy <command>` from the root of the repository.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Signer},
+};
+
+/// Manifest of the deployments, at the root of the repository.
+const MANIFEST: &str = "deployments.json";
+
+#[derive(Parser)]
+struct Cli {
+    /// URL of the cluster, or one of the `localnet` and `devnet` monikers.
+    #[arg(short, long, global = true, default_value = "localnet")]
+    url: String,
+    #[command(subcommand)]
+    command: Cmd,
+}
+
+#[derive(Subcommand)]
+enum Cmd {
+    /// Deploys the built programs and records them in the manifest.
+    Deploy {
+        /// Keypair paying for the deployments. Defaults to the keypair of
+        /// the Solana CLI.
+        #[arg(short, long)]
+        keypair: Option<PathBuf>,
+        /// Directory of the keypairs of the programs. Defaults to the
+        /// `target/deploy` of each program.
+        #[arg(long)]
+        keypairs: Option<PathBuf>,
+        /// Programs to deploy. Defaults to all programs.
+        crates: Vec<String>,
+    },
+    /// Checks that the deployed programs match the manifest and the local
+    /// builds.
+    Verify {
+        /// Programs to verify. Defaults to all programs deployed on the
+        /// cluster according to the manifest.
+        crates: Vec<String>,
+    },
+}
+
+/// Program deployed on a cluster.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+struct Deployment {
+    program_id: String,
+    /// SHA-256 hash of the binary, in hex.
+    sha256: String,
+    /// Size of the binary, in bytes.
+    size: u64,
+}
+
+/// Deployments by cluster and by program.
+type Manifest = BTreeMap<String, BTreeMap<String, Deployment>>;
+
+fn main() -> Result<()> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let cli = Cli::parse();
+
+    match cli.command {
+        Cmd::Deploy {
+            keypair,
+            keypairs,
+            crates,
+        } => deploy(
+            root,
+            &cli.url,
+            keypair.as_deref(),
+            keypairs.as_deref(),
+            &select(root, crates)?,
+        ),
+        Cmd::Verify { crates } => verify(root, &cli.url, crates),
+    }
+}
+
+/// Returns the programs named in `names`, or all programs.
+fn select(root: &Path, names: Vec<String>) -> Result<Vec<PathBuf>> {
+    if !names.is_empty() {
+        return names
+            .into_iter()
+            .map(|name| {
+                let path = root.join(&name);
+                if !is_program(&path) {
+                    bail!("unknown program {name}");
+                }
+                Ok(path)
+            })
+            .collect();
+    }
+
+    let mut programs = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        // The template isn't a program until it's generated.
+        if path.ends_with("program-template") {
+            continue;
+        }
+        if is_program(&path) {
+            programs.push(path);
+        }
+    }
+    programs.sort();
+    Ok(programs)
+}
+
+/// Whether the crate is a program, built into a shared object. Clients are
+/// shared objects too, but built into WebAssembly.
+fn is_program(path: &Path) -> bool {
+    fs::read_to_string(path.join("Cargo.toml"))
+        .is_ok_and(|manifest| manifest.contains("cdylib") && !manifest.contains("wasm-bindgen"))
+}
+
+/// Returns the name of the binary of the program in `path`.
+fn program_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap()
+        .to_string_lossy()
+        .replace('-', "_")
+}
+
+/// Returns the address declared by the program in `path`.
+fn declared_id(path: &Path) -> Result<Pubkey> {
+    let lib = fs::read_to_string(path.join("src/lib.rs"))?;
+    let (_, rest) = lib
+        .split_once("declare_id!(\"")
+        .ok_or_else(|| anyhow!("{} declares no address", path.display()))?;
+    let (address, _) = rest.split_once('"').unwrap_or_default();
+    address
+        .parse()
+        .with_context(|| format!("invalid address {address} in {}", path.display()))
+}
+
+/// Returns the hex-encoded SHA-256 hash of `data`.
+fn sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Resolves cluster monikers to their RPC URLs.
+fn cluster_url(url: &str) -> &str {
+    match url {
+        "localnet" => "http://127.0.0.1:8899",
+        "devnet" => "https://api.devnet.solana.com",
+        url => url,
+    }
+}
+
+fn read_manifest(root: &Path) -> Result<Manifest> {
+    match fs::read_to_string(root.join(MANIFEST)) {
+        Ok(json) => serde_json::from_str(&json).with_context(|| format!("invalid {MANIFEST}")),
+        Err(_) => Ok(Manifest::new()),
+    }
+}
+
+/// Deploys `programs` to the cluster at `url`, paid by `keypair`, and
+/// records them in the manifest after each deployment.
+fn deploy(
+    root: &Path,
+    url: &str,
+    keypair: Option<&Path>,
+    keypairs: Option<&Path>,
+    programs: &[PathBuf],
+) -> Result<()> {
+    let mut manifest = read_manifest(root)?;
+
+    for program in programs {
+        let name = program_name(program);
+        let deploy_dir = program.join("target/deploy");
+        let binary_path = deploy_dir.join(format!("{name}.so"));
+        let binary = fs::read(&binary_path).with_context(|| {
+            format!(
+                "{} is not built, run `cargo xtask build` first",
+                binary_path.display()
+            )
+        })?;
+
+        // The programs derive their PDAs from their declared address, so
+        // they only work when deployed with the keypair of that address.
+        let program_id = declared_id(program)?;
+        let program_keypair_path = keypairs
+            .unwrap_or(&deploy_dir)
+            .join(format!("{name}-keypair.json"));
+        let program_keypair = read_keypair_file(&program_keypair_path)
+            .map_err(|e| anyhow!("failed to read {}: {e}", program_keypair_path.display()))?;
+        if program_keypair.pubkey() != program_id {
+            bail!(
+                "{} is the keypair of {}, but {name} declares {program_id}",
+                program_keypair_path.display(),
+                program_keypair.pubkey()
+            );
+        }
+
+        let mut command = Command::new("solana");
+        command.args(["program", "deploy", "--url", cluster_url(url)]);
+        if let Some(keypair) = keypair {
+            command.arg("--keypair").arg(keypair);
+        }
+        command
+            .arg("--program-id")
+            .arg(&program_keypair_path)
+            .arg(&binary_path);
+        run(&mut command)?;
+
+        manifest.entry(url.to_owned()).or_default().insert(
+            name,
+            Deployment {
+                program_id: program_id.to_string(),
+                sha256: sha256(&binary),
+                size: binary.len() as u64,
+            },
+        );
+        fs::write(
+            root.join(MANIFEST),
+            serde_json::to_string_pretty(&manifest)? + "\n",
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the binary deployed at `program_id`, without the zeros padding
+/// its program data account past `size`.
+///
+/// Fails if it isn't a program of the upgradeable loader or if its binary
+/// is longer than `size`.
+fn deployed_binary(client: &RpcClient, program_id: &Pubkey, size: usize) -> Result<Vec<u8>> {
+    let program = client.get_account(program_id)?;
+    if program.owner != bpf_loader_upgradeable::id() {
+        bail!("{program_id} is not owned by the upgradeable loader");
+    }
+    let UpgradeableLoaderState::Program {
+        programdata_address,
+    } = bincode::deserialize(&program.data)?
+    else {
+        bail!("{program_id} is not a program account");
+    };
+
+    let program_data = client.get_account(&programdata_address)?;
+    let binary = program_data
+        .data
+        .get(UpgradeableLoaderState::size_of_programdata_metadata()..)
+        .ok_or_else(|| anyhow!("{programdata_address} is not a program data account"))?;
+    let (binary, padding) = binary.split_at(size.min(binary.len()));
+    if padding.iter().any(|&byte| byte != 0) {
+        bail!("the binary deployed at {program_id} is longer than {size} bytes");
+    }
+    Ok(binary.to_vec())
+}
+
+/// Checks that the binaries of `names`, or of all programs deployed on the
+/// cluster at `url`, match the manifest and the local builds, printing them
+/// as a Markdown table.
+fn verify(root: &Path, url: &str, names: Vec<String>) -> Result<()> {
+    let manifest = read_manifest(root)?;
+    let deployments = manifest
+        .get(url)
+        .ok_or_else(|| anyhow!("no programs deployed on {url} in {MANIFEST}"))?;
+    let names: Vec<String> = if names.is_empty() {
+        deployments.keys().cloned().collect()
+    } else {
+        names.iter().map(|name| name.replace('-', "_")).collect()
+    };
+    let client =
+        RpcClient::new_with_commitment(cluster_url(url).to_owned(), CommitmentConfig::confirmed());
+
+    let mut table = String::from("| Program | Address | Manifest | On-chain | Local |\n");
+    table.push_str("|---|---|---|---|---|\n");
+    let mut mismatches = Vec::new();
+    for name in names {
+        let deployment = deployments
+            .get(&name)
+            .ok_or_else(|| anyhow!("{name} is not deployed on {url} in {MANIFEST}"))?;
+        let program_id: Pubkey = deployment.program_id.parse()?;
+
+        let local_path = root
+            .join(name.replace('_', "-"))
+            .join("target/deploy")
+            .join(format!("{name}.so"));
+        let local = fs::read(&local_path).ok().map(|binary| sha256(&binary));
+        let onchain = deployed_binary(&client, &program_id, deployment.size as usize)
+            .map(|binary| sha256(&binary));
+
+        let short = |hash: &str| hash[..8].to_owned();
+        writeln!(
+            table,
+            "| {name} | {program_id} | {} | {} | {} |",
+            short(&deployment.sha256),
+            onchain.as_deref().map_or("-".to_owned(), short),
+            local.as_deref().map_or("-".to_owned(), short),
+        )?;
+        match onchain {
+            Ok(hash) if hash == deployment.sha256 => {}
+            Ok(_) => mismatches.push(format!("{name}: the deployed binary differs")),
+            Err(e) => mismatches.push(format!("{name}: {e}")),
+        }
+        match local {
+            Some(hash) if hash == deployment.sha256 => {}
+            Some(_) => mismatches.push(format!("{name}: the local build differs")),
+            None => mismatches.push(format!("{name}: {} is not built", local_path.display())),
+        }
+    }
+    print!("{table}");
+
+    if !mismatches.is_empty() {
+        bail!(
+            "deployments don't match on {url}:\n{}",
+            mismatches.join("\n")
+        );
+    }
+    Ok(())
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("failed to run {command:?}"))?;
+    if !status.success() {
+        bail!("{command:?} failed with {status}");
+    }
+    Ok(())
+}
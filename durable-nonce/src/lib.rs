@@ -0,0 +1,349 @@
+//! Manager of durable nonce accounts.
+//!
+//! A durable nonce replaces the recent blockhash of a transaction, which lets
+//! it be signed offline and submitted much later. The first instruction of
+//! such a transaction has to advance the nonce, so it can't be replayed.
+//!
+//! The nonce accounts created by the program are controlled by a PDA of their
+//! owner, so only the program can advance them (invalidating every
+//! transaction signed with the current nonce) or withdraw from them. The
+//! runtime only accepts a top-level `AdvanceNonceAccount` signed by the nonce
+//! authority, which a PDA can't do, so before a nonce is used to sign a
+//! transaction, `Authorize` hands it over to a regular signer.
+
+#![no_std]
+
+use core::mem;
+
+use common::{assert_layout, checks::require_signer, info, keys_eq};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::{
+    AdvanceNonceAccount, AuthorizeNonceAccount, CreateAccount, InitializeNonceAccount,
+    WithdrawNonceAccount,
+};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("6RSS19xEULFCnwijAtkSCbbF5ckNpBygdCLJ6as96Nhj");
+
+pub const NONCE_AUTHORITY_SEED: &str = "nonce_authority";
+
+/// Layout of an initialized nonce account, owned by the system program.
+#[repr(C)]
+pub struct NonceState {
+    /// `1` for the current version.
+    pub version: u32,
+    /// `1` for an initialized nonce.
+    pub state: u32,
+    pub authority: Pubkey,
+    /// Value to use as the recent blockhash of a transaction.
+    pub durable_nonce: [u8; 32],
+    pub lamports_per_signature: u64,
+}
+
+assert_layout!(NonceState, size = 80, align = 8, {
+    version: 0,
+    state: 4,
+    authority: 8,
+    durable_nonce: 40,
+    lamports_per_signature: 72,
+});
+
+impl NonceState {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Durable nonce program instruction discriminators.
+#[repr(u8)]
+pub enum DurableNonceInstruction {
+    /// Creates a nonce account with the PDA of the owner as its authority.
+    Initialize,
+    /// Advances the nonce.
+    Advance,
+    /// Withdraws lamports from the nonce account.
+    Withdraw,
+    /// Hands the nonce over to a new authority.
+    Authorize,
+}
+
+impl TryFrom<&u8> for DurableNonceInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Advance),
+            2 => Ok(Self::Withdraw),
+            3 => Ok(Self::Authorize),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Instruction data of `Initialize` and `Advance`.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct NonceInstructionData {
+    /// Bump of the authority PDA.
+    pub bump: u8,
+}
+
+impl NonceInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct WithdrawInstructionData {
+    pub lamports: u64,
+    /// Bump of the authority PDA.
+    pub bump: u8,
+}
+
+impl WithdrawInstructionData {
+    pub fn new(lamports: u64, bump: u8) -> Self {
+        Self { lamports, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AuthorizeInstructionData {
+    pub new_authority: Pubkey,
+    /// Bump of the authority PDA.
+    pub bump: u8,
+}
+
+impl AuthorizeInstructionData {
+    pub fn new(new_authority: Pubkey, bump: u8) -> Self {
+        Self {
+            new_authority,
+            bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = DurableNonceInstruction::try_from(instruction)?;
+
+    match instruction {
+        DurableNonceInstruction::Initialize => process_initialize(accounts, instruction_data),
+        DurableNonceInstruction::Advance => process_advance(accounts, instruction_data),
+        DurableNonceInstruction::Withdraw => process_withdraw(accounts, instruction_data),
+        DurableNonceInstruction::Authorize => process_authorize(accounts, instruction_data),
+    }
+}
+
+/// Checks that `authority` is the nonce authority PDA of `owner`.
+fn check_authority(authority: &AccountInfo, owner: &AccountInfo, bump: u8) -> ProgramResult {
+    let expected = create_program_address(
+        &[NONCE_AUTHORITY_SEED.as_bytes(), owner.key(), &[bump]],
+        &ID,
+    )?;
+    if !keys_eq(authority.key(), &expected) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Deserializes the data of the instructions which only carry the bump.
+fn nonce_instruction_data(instruction_data: &[u8]) -> Result<NonceInstructionData, ProgramError> {
+    NonceInstructionData::from_bytes(instruction_data)
+}
+
+/// Creates a nonce account controlled by the authority PDA of `owner`.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner
+///   1. `[WRITE, SIGNER]` New nonce account
+///   2. `[]` Authority PDA of the owner
+///   3. `[]` RecentBlockhashes sysvar
+///   4. `[]` Rent sysvar
+///   5. `[]` System program
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, nonce, authority, recent_blockhashes, rent_sysvar, _system_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = nonce_instruction_data(instruction_data)?;
+
+    check_authority(authority, owner, instruction_data.bump)?;
+
+    CreateAccount {
+        from: owner,
+        to: nonce,
+        lamports: Rent::get()?.minimum_balance(NonceState::LEN),
+        space: NonceState::LEN as u64,
+        owner: &pinocchio_system::ID,
+    }
+    .invoke()?;
+
+    InitializeNonceAccount {
+        account: nonce,
+        recent_blockhashes_sysvar: recent_blockhashes,
+        rent_sysvar,
+        authority: authority.key(),
+    }
+    .invoke()?;
+
+    info!("Initialized a nonce account");
+
+    Ok(())
+}
+
+/// Advances the nonce, invalidating all transactions signed with the current
+/// one.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner
+///   1. `[WRITE]` Nonce account
+///   2. `[]` Authority PDA of the owner
+///   3. `[]` RecentBlockhashes sysvar
+///   4. `[]` System program
+pub fn process_advance(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, nonce, authority, recent_blockhashes, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = nonce_instruction_data(instruction_data)?;
+
+    check_authority(authority, owner, instruction_data.bump)?;
+
+    // The system program checks that the PDA is the nonce authority.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(NONCE_AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    AdvanceNonceAccount {
+        account: nonce,
+        recent_blockhashes_sysvar: recent_blockhashes,
+        authority,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Advanced the nonce");
+
+    Ok(())
+}
+
+/// Withdraws lamports from the nonce account. Withdrawing everything closes
+/// it.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner
+///   1. `[WRITE]` Nonce account
+///   2. `[WRITE]` Recipient
+///   3. `[]` Authority PDA of the owner
+///   4. `[]` RecentBlockhashes sysvar
+///   5. `[]` Rent sysvar
+///   6. `[]` System program
+pub fn process_withdraw(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, nonce, recipient, authority, recent_blockhashes, rent_sysvar, _system_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = WithdrawInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(authority, owner, instruction_data.bump)?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(NONCE_AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    WithdrawNonceAccount {
+        account: nonce,
+        recipient,
+        recent_blockhashes_sysvar: recent_blockhashes,
+        rent_sysvar,
+        authority,
+        lamports: instruction_data.lamports,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Withdrew {} lamports", instruction_data.lamports);
+
+    Ok(())
+}
+
+/// Hands the nonce over to `new_authority`, which can then use it in
+/// transactions signed offline.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner
+///   1. `[WRITE]` Nonce account
+///   2. `[]` Authority PDA of the owner
+///   3. `[]` System program
+pub fn process_authorize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, nonce, authority, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = AuthorizeInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(authority, owner, instruction_data.bump)?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(NONCE_AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    AuthorizeNonceAccount {
+        account: nonce,
+        authority,
+        new_authority: &instruction_data.new_authority,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Handed the nonce over to a new authority");
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+//! Attacks on the durable nonce program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use durable_nonce::{
+    AuthorizeInstructionData, DurableNonceInstruction, NonceInstructionData, NonceState,
+    WithdrawInstructionData, NONCE_AUTHORITY_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_nonce::{
+    state::{Data, DurableNonce, State},
+    versions::Versions,
+};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(durable_nonce::ID);
+
+const RECENT_BLOCKHASHES_ID: Pubkey = solana_sdk_ids::sysvar::recent_blockhashes::ID;
+
+const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: DurableNonceInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<DurableNonceInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the nonce authority PDA of `owner` and its bump.
+fn authority_pda(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[NONCE_AUTHORITY_SEED.as_bytes(), owner.as_ref()], &ID)
+}
+
+/// Returns a nonce account of `authority`, initialized by the system
+/// program, holding a SOL above the rent-exempt minimum.
+fn nonce_account(mollusk: &Mollusk, authority: &Pubkey) -> Account {
+    let state = State::Initialized(Data::new(
+        *authority,
+        DurableNonce::from_blockhash(&Hash::new_from_array([1; 32])),
+        LAMPORTS_PER_SIGNATURE,
+    ));
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(NonceState::LEN) + LAMPORTS_PER_SOL,
+        NonceState::LEN,
+        &keyed_account_for_system_program().0,
+    );
+    account.data = bincode::serialize(&Versions::new(state)).unwrap();
+    account
+}
+
+/// Returns a RecentBlockhashes sysvar account with a single entry.
+fn recent_blockhashes_account() -> (Pubkey, Account) {
+    let mut data = Vec::new();
+    data.extend_from_slice(&1u64.to_le_bytes());
+    data.extend_from_slice(&[2; 32]);
+    data.extend_from_slice(&LAMPORTS_PER_SIGNATURE.to_le_bytes());
+    (
+        RECENT_BLOCKHASHES_ID,
+        Account {
+            lamports: 1,
+            data,
+            owner: solana_sdk_ids::sysvar::ID,
+            ..Default::default()
+        },
+    )
+}
+
+/// Accounts of an owner with a nonce account of their authority PDA, and of
+/// an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    nonce: Pubkey,
+    authority: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/durable_nonce");
+
+        let owner = Pubkey::new_unique();
+        let nonce = Pubkey::new_unique();
+        let (authority, _) = authority_pda(&owner);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (nonce, nonce_account(&mollusk, &authority)),
+            (authority, Account::default()),
+            (attacker, Account::default()),
+            (authority_pda(&attacker).0, Account::default()),
+            recent_blockhashes_account(),
+            mollusk.sysvars.keyed_account_for_rent_sysvar(),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            nonce,
+            authority,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Advances the nonce with `authority`, signed by `owner`.
+    fn instruction_advance(&self, owner: &Pubkey, authority: &Pubkey, bump: u8) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(self.nonce, false),
+            AccountMeta::new_readonly(*authority, false),
+            AccountMeta::new_readonly(RECENT_BLOCKHASHES_ID, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DurableNonceInstruction::Advance,
+                &NonceInstructionData::new(bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Withdraws the lamports above the rent-exempt minimum of the nonce to
+    /// the attacker with `authority`, signed by the attacker.
+    fn attacker_withdraw(&self, authority: &Pubkey, bump: u8) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.attacker, true),
+            AccountMeta::new(self.nonce, false),
+            AccountMeta::new(self.attacker, false),
+            AccountMeta::new_readonly(*authority, false),
+            AccountMeta::new_readonly(RECENT_BLOCKHASHES_ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::rent::ID, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DurableNonceInstruction::Withdraw,
+                &WithdrawInstructionData::new(LAMPORTS_PER_SOL, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let (_, bump) = authority_pda(&setup.owner);
+    let mut instruction = setup.instruction_advance(&setup.owner, &setup.authority, bump);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker advances the nonce of the owner, which invalidates the
+/// transactions the owner signed offline with it.
+#[test]
+fn test_advance_with_authority_of_other_owner() {
+    let setup = Setup::new();
+    let (_, bump) = authority_pda(&setup.attacker);
+    setup.expect_err(
+        &setup.instruction_advance(&setup.attacker, &setup.authority, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker hands the nonce of the owner over to themselves.
+#[test]
+fn test_authorize_with_authority_of_other_owner() {
+    let setup = Setup::new();
+    let (_, bump) = authority_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.attacker, true),
+        AccountMeta::new(setup.nonce, false),
+        AccountMeta::new_readonly(setup.authority, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            DurableNonceInstruction::Authorize,
+            &AuthorizeInstructionData::new(setup.attacker.to_bytes(), bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker withdraws from the nonce of the owner, signed by their own
+/// authority PDA, which the system program doesn't accept.
+#[test]
+fn test_withdraw_with_own_authority() {
+    let setup = Setup::new();
+    let (authority, bump) = authority_pda(&setup.attacker);
+    setup.expect_err(
+        &setup.attacker_withdraw(&authority, bump),
+        ProgramError::MissingRequiredSignature,
+    );
+}
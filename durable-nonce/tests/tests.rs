@@ -0,0 +1,377 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use durable_nonce::{
+    AuthorizeInstructionData, DurableNonceInstruction, NonceInstructionData, NonceState,
+    WithdrawInstructionData, NONCE_AUTHORITY_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_keypair::Keypair;
+use solana_message::Message;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_nonce::{
+    state::{Data, DurableNonce, State},
+    versions::Versions,
+};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+
+const ID: Pubkey = Pubkey::new_from_array(durable_nonce::ID);
+
+const RECENT_BLOCKHASHES_ID: Pubkey = solana_sdk_ids::sysvar::recent_blockhashes::ID;
+
+const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: DurableNonceInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<DurableNonceInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+/// Reinterprets the data of a nonce account.
+fn nonce_state(data: &[u8]) -> &NonceState {
+    assert_eq!(data.len(), NonceState::LEN);
+    unsafe { &*data.as_ptr().cast() }
+}
+
+/// Returns the data of an initialized nonce account, serialized the same way
+/// as by the system program.
+fn nonce_account_data(authority: &Pubkey, blockhash: &Hash) -> Vec<u8> {
+    let state = State::Initialized(Data::new(
+        *authority,
+        DurableNonce::from_blockhash(blockhash),
+        LAMPORTS_PER_SIGNATURE,
+    ));
+    bincode::serialize(&Versions::new(state)).unwrap()
+}
+
+/// Returns a RecentBlockhashes sysvar account with a single entry. The
+/// sysvar is deprecated, but the nonce instructions still require it to be
+/// non-empty.
+fn recent_blockhashes_account(blockhash: &Hash) -> (Pubkey, Account) {
+    // `Vec<Entry>`, where an entry is a blockhash followed by the lamports
+    // per signature.
+    let mut data = Vec::new();
+    data.extend_from_slice(&1u64.to_le_bytes());
+    data.extend_from_slice(blockhash.as_ref());
+    data.extend_from_slice(&LAMPORTS_PER_SIGNATURE.to_le_bytes());
+    (
+        RECENT_BLOCKHASHES_ID,
+        Account {
+            lamports: 1,
+            data,
+            owner: solana_sdk_ids::sysvar::ID,
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn test_nonce_state_layout() {
+    let authority = Pubkey::new_unique();
+    let blockhash = Hash::new_from_array([7; 32]);
+    let data = nonce_account_data(&authority, &blockhash);
+
+    let state = nonce_state(&data);
+    assert_eq!(state.version, 1);
+    assert_eq!(state.state, 1);
+    assert_eq!(state.authority, authority.to_bytes());
+    assert_eq!(
+        &state.durable_nonce,
+        DurableNonce::from_blockhash(&blockhash).as_hash().as_ref()
+    );
+    assert_eq!(state.lamports_per_signature, LAMPORTS_PER_SIGNATURE);
+}
+
+#[test]
+fn test_offline_signed_transaction() {
+    // The owner handed the nonce over to their offline signer, which reads
+    // the durable nonce from the account fetched by an online machine.
+    let signer = Keypair::new();
+    let nonce = Pubkey::new_unique();
+    let data = nonce_account_data(&signer.pubkey(), &Hash::new_from_array([3; 32]));
+    let state = nonce_state(&data);
+    assert_eq!(state.authority, signer.pubkey().to_bytes());
+    let durable_nonce = Hash::new_from_array(state.durable_nonce);
+
+    // The transaction is signed with the nonce instead of a recent
+    // blockhash, so it stays valid until the nonce is advanced.
+    let recipient = Pubkey::new_unique();
+    let transfer = solana_system_interface::instruction::transfer(&signer.pubkey(), &recipient, 42);
+    let mut message = Message::new_with_nonce(
+        vec![transfer.clone()],
+        Some(&signer.pubkey()),
+        &nonce,
+        &signer.pubkey(),
+    );
+    message.recent_blockhash = durable_nonce;
+    let signature = signer.sign_message(&message.serialize());
+
+    // Whoever submits the transaction later only needs the message and the
+    // signature.
+    assert!(signature.verify(signer.pubkey().as_ref(), &message.serialize()));
+
+    // The runtime expects the nonce to be advanced by the first instruction,
+    // signed by the nonce authority.
+    let advance = &message.instructions[0];
+    assert_eq!(
+        message.account_keys[advance.program_id_index as usize],
+        keyed_account_for_system_program().0
+    );
+    let advance_accounts: Vec<Pubkey> = advance
+        .accounts
+        .iter()
+        .map(|index| message.account_keys[*index as usize])
+        .collect();
+    assert_eq!(
+        advance_accounts,
+        [nonce, RECENT_BLOCKHASHES_ID, signer.pubkey()]
+    );
+    assert!(message.is_signer(advance.accounts[2] as usize));
+    assert_eq!(message.instructions[1].data, transfer.data);
+
+    // Advancing the nonce invalidates the signature.
+    let mut advanced = message.clone();
+    advanced.recent_blockhash =
+        *DurableNonce::from_blockhash(&Hash::new_from_array([4; 32])).as_hash();
+    assert!(!signature.verify(signer.pubkey().as_ref(), &advanced.serialize()));
+}
+
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    nonce: Pubkey,
+    authority: Pubkey,
+    bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/durable_nonce");
+
+        let owner = Pubkey::new_unique();
+        let nonce = Pubkey::new_unique();
+        let (authority, bump) =
+            Pubkey::find_program_address(&[NONCE_AUTHORITY_SEED.as_bytes(), owner.as_ref()], &ID);
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (nonce, Account::default()),
+            (authority, Account::default()),
+            recent_blockhashes_account(&Hash::new_from_array([1; 32])),
+            mollusk.sysvars.keyed_account_for_rent_sysvar(),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            nonce,
+            authority,
+            bump,
+            accounts,
+        }
+    }
+
+    fn rent(&self) -> u64 {
+        self.mollusk.sysvars.rent.minimum_balance(NonceState::LEN)
+    }
+
+    fn instruction_initialize(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.nonce, true),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(RECENT_BLOCKHASHES_ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::rent::ID, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DurableNonceInstruction::Initialize,
+                &NonceInstructionData::new(self.bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_advance(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.nonce, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(RECENT_BLOCKHASHES_ID, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DurableNonceInstruction::Advance,
+                &NonceInstructionData::new(self.bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_withdraw(&self, recipient: &Pubkey, lamports: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.nonce, false),
+            AccountMeta::new(*recipient, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(RECENT_BLOCKHASHES_ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::rent::ID, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DurableNonceInstruction::Withdraw,
+                &WithdrawInstructionData::new(lamports, self.bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_authorize(&self, new_authority: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.nonce, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                DurableNonceInstruction::Authorize,
+                &AuthorizeInstructionData::new(new_authority.to_bytes(), self.bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn process(&self, instruction: &Instruction, checks: &[Check]) -> Vec<(Pubkey, Account)> {
+        self.mollusk
+            .process_and_validate_instruction(instruction, &self.accounts, checks)
+            .resulting_accounts
+    }
+
+    fn nonce_account(&self) -> &Account {
+        &self
+            .accounts
+            .iter()
+            .find(|(key, _)| key == &self.nonce)
+            .unwrap()
+            .1
+    }
+}
+
+#[test]
+fn test_durable_nonce_lifecycle() {
+    let mut setup = Setup::new();
+    let rent = setup.rent();
+
+    setup.accounts = setup.process(
+        &setup.instruction_initialize(),
+        &[
+            Check::success(),
+            Check::account(&setup.nonce)
+                .lamports(rent)
+                .owner(&keyed_account_for_system_program().0)
+                .space(NonceState::LEN)
+                .build(),
+        ],
+    );
+    let state = nonce_state(&setup.nonce_account().data);
+    assert_eq!(state.authority, setup.authority.to_bytes());
+    // Mollusk executes every instruction with the default blockhash.
+    let durable_nonce = DurableNonce::from_blockhash(&Hash::default());
+    assert_eq!(&state.durable_nonce, durable_nonce.as_hash().as_ref());
+
+    // The nonce can only be advanced once the blockhash changes,
+    // `SystemError::NonceBlockhashNotExpired`.
+    setup.process(
+        &setup.instruction_advance(),
+        &[Check::err(ProgramError::Custom(7))],
+    );
+
+    // Withdraw what's above the rent-exempt minimum.
+    let recipient = Pubkey::new_unique();
+    setup.accounts.push((recipient, Account::default()));
+    let extra = LAMPORTS_PER_SOL / 10;
+    let nonce = setup
+        .accounts
+        .iter_mut()
+        .find(|(key, _)| key == &setup.nonce)
+        .unwrap();
+    nonce.1.lamports += extra;
+    setup.accounts = setup.process(
+        &setup.instruction_withdraw(&recipient, extra),
+        &[
+            Check::success(),
+            Check::account(&setup.nonce).lamports(rent).build(),
+            Check::account(&recipient).lamports(extra).build(),
+        ],
+    );
+
+    // Hand the nonce over to an offline signer. The program can't use it
+    // anymore.
+    let signer = Pubkey::new_unique();
+    setup.accounts = setup.process(&setup.instruction_authorize(&signer), &[Check::success()]);
+    let state = nonce_state(&setup.nonce_account().data);
+    assert_eq!(state.authority, signer.to_bytes());
+    setup.process(
+        &setup.instruction_withdraw(&recipient, rent),
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+#[test]
+fn test_durable_nonce_invalid() {
+    let mut setup = Setup::new();
+
+    // Only the authority PDA of the owner is accepted.
+    let (other_authority, _) = Pubkey::find_program_address(
+        &[
+            NONCE_AUTHORITY_SEED.as_bytes(),
+            Pubkey::new_unique().as_ref(),
+        ],
+        &ID,
+    );
+    setup.accounts.push((other_authority, Account::default()));
+    let mut initialize = setup.instruction_initialize();
+    initialize.accounts[2] = AccountMeta::new_readonly(other_authority, false);
+    setup.process(&initialize, &[Check::err(ProgramError::InvalidSeeds)]);
+
+    setup.accounts = setup.process(&setup.instruction_initialize(), &[Check::success()]);
+
+    // Nobody else can withdraw using the PDA of the owner.
+    let attacker = Pubkey::new_unique();
+    setup.accounts.push((attacker, Account::default()));
+    let mut withdraw = setup.instruction_withdraw(&attacker, setup.rent());
+    withdraw.accounts[0] = AccountMeta::new_readonly(attacker, true);
+    setup.process(&withdraw, &[Check::err(ProgramError::InvalidSeeds)]);
+
+    // The rent-exempt minimum can only be withdrawn all at once, closing the
+    // account, `InstructionError::InsufficientFunds`.
+    setup.process(
+        &setup.instruction_withdraw(&attacker, 1),
+        &[Check::err(ProgramError::InsufficientFunds)],
+    );
+}
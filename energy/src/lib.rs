@@ -0,0 +1,454 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("6m9nzc87vz1UMWrxY7ZKa3Hqj7YQqKy49Wef9ZAZ571X");
+
+pub const GAME_SEED: &str = "game";
+pub const PLAYER_SEED: &str = "player";
+
+/// Errors returned by the energy program.
+#[repr(u32)]
+pub enum EnergyError {
+    /// The cap, the regeneration amount or the regeneration interval is zero.
+    InvalidConfig,
+    /// The player doesn't have enough energy for the action.
+    NotEnoughEnergy,
+}
+
+impl From<EnergyError> for ProgramError {
+    fn from(e: EnergyError) -> Self {
+        Namespace::Energy.error(e as u32)
+    }
+}
+
+/// On-chain representation of a game.
+///
+/// Players regenerate `regen_amount` energy every `regen_interval` seconds,
+/// up to `max_energy`.
+#[repr(C)]
+pub struct Game {
+    pub admin: Pubkey,
+    pub max_energy: u64,
+    pub regen_amount: u64,
+    /// Regeneration interval in seconds.
+    pub regen_interval: i64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Game, size = 64, align = 8, {
+    admin: 0,
+    max_energy: 32,
+    regen_amount: 40,
+    regen_interval: 48,
+    bump: 56,
+});
+
+impl Game {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain representation of a player of a game.
+///
+/// The energy isn't updated in the background. Instead, every action first
+/// applies the regeneration accumulated since `last_update`.
+#[repr(C)]
+pub struct Player {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub energy: u64,
+    /// Unix timestamp up to which the regeneration was applied.
+    pub last_update: i64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Player, size = 88, align = 8, {
+    game: 0,
+    player: 32,
+    energy: 64,
+    last_update: 72,
+    bump: 80,
+});
+
+impl Player {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Applies the regeneration accumulated until `now`.
+    ///
+    /// Only whole intervals are regenerated. The remainder of an unfinished
+    /// interval is kept by advancing `last_update` by the regenerated
+    /// intervals only, so frequent actions don't slow down the regeneration.
+    /// Time spent at the cap doesn't count towards the next interval.
+    pub fn regenerate(&mut self, game: &Game, now: i64) {
+        // The clock can't go backwards within a cluster, but don't underflow
+        // if it ever does.
+        let elapsed = now.saturating_sub(self.last_update).max(0);
+        let intervals = elapsed / game.regen_interval;
+
+        let energy = (intervals as u128 * game.regen_amount as u128 + self.energy as u128)
+            .min(game.max_energy as u128) as u64;
+        if energy >= game.max_energy {
+            self.last_update = now;
+        } else {
+            self.last_update += intervals * game.regen_interval;
+        }
+        self.energy = energy;
+    }
+}
+
+/// Energy program instruction discriminators.
+#[repr(u8)]
+pub enum EnergyInstruction {
+    /// Creates a game.
+    InitializeGame,
+    /// Changes the energy config of a game.
+    SetConfig,
+    /// Creates a player account with full energy.
+    Register,
+    /// Spends energy on an action.
+    Act,
+}
+
+impl TryFrom<&u8> for EnergyInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeGame),
+            1 => Ok(Self::SetConfig),
+            2 => Ok(Self::Register),
+            3 => Ok(Self::Act),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Energy config of a game.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ConfigInstructionData {
+    pub max_energy: u64,
+    pub regen_amount: u64,
+    pub regen_interval: i64,
+}
+
+impl ConfigInstructionData {
+    pub fn new(max_energy: u64, regen_amount: u64, regen_interval: i64) -> Self {
+        Self {
+            max_energy,
+            regen_amount,
+            regen_interval,
+        }
+    }
+
+    fn validate(&self) -> ProgramResult {
+        if self.max_energy == 0 || self.regen_amount == 0 || self.regen_interval <= 0 {
+            return Err(EnergyError::InvalidConfig.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(InstructionData)]
+pub struct InitializeGameInstructionData {
+    pub config: ConfigInstructionData,
+    pub bump: u8,
+}
+
+impl InitializeGameInstructionData {
+    pub fn new(config: ConfigInstructionData, bump: u8) -> Self {
+        Self { config, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct RegisterInstructionData {
+    pub bump: u8,
+}
+
+impl RegisterInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ActInstructionData {
+    /// Energy cost of the action.
+    pub cost: u64,
+}
+
+impl ActInstructionData {
+    pub fn new(cost: u64) -> Self {
+        Self { cost }
+    }
+}
+
+/// Checks that `game` is a game of the program. The program owns player
+/// accounts too, which are told apart by their length.
+fn check_game(game: &AccountInfo) -> ProgramResult {
+    require_owned_by(game, &ID)?;
+    if game.data_len() != Game::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = EnergyInstruction::try_from(instruction)?;
+
+    match instruction {
+        EnergyInstruction::InitializeGame => process_initialize_game(accounts, instruction_data),
+        EnergyInstruction::SetConfig => process_set_config(accounts, instruction_data),
+        EnergyInstruction::Register => process_register(accounts, instruction_data),
+        EnergyInstruction::Act => process_act(accounts, instruction_data),
+    }
+}
+
+/// Creates a game of `admin`.
+pub fn process_initialize_game(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, game, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeGameInstructionData::from_bytes(instruction_data)?;
+
+    instruction_data.config.validate()?;
+
+    // Check the seeds of `game`.
+    let game_pda = create_program_address(
+        &[GAME_SEED.as_bytes(), admin.key(), &[instruction_data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(game.key(), &game_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the game PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(GAME_SEED.as_bytes()),
+        Seed::from(admin.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: admin,
+        to: game,
+        lamports: Rent::get()?.minimum_balance(Game::LEN),
+        space: Game::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the game PDA.
+    let mut data = game.try_borrow_mut_data()?;
+    let data: &mut Game = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the game.
+    data.admin = *admin.key();
+    data.max_energy = instruction_data.config.max_energy;
+    data.regen_amount = instruction_data.config.regen_amount;
+    data.regen_interval = instruction_data.config.regen_interval;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Initialized game with max energy {}",
+        instruction_data.config.max_energy
+    );
+
+    Ok(())
+}
+
+/// Changes the energy config of a game.
+///
+/// Players pick the new config up lazily on their next action, so the time
+/// since their last action regenerates at the new rate. A lowered cap clamps
+/// the energy of players above it.
+pub fn process_set_config(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, game] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+    // Check that `game` is a game PDA of the program.
+    check_game(game)?;
+
+    // Deserialize instruction data.
+    let instruction_data = ConfigInstructionData::from_bytes(instruction_data)?;
+
+    instruction_data.validate()?;
+
+    // Deserialize the game PDA.
+    let mut data = game.try_borrow_mut_data()?;
+    let data: &mut Game = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    if !keys_eq(&data.admin, admin.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    data.max_energy = instruction_data.max_energy;
+    data.regen_amount = instruction_data.regen_amount;
+    data.regen_interval = instruction_data.regen_interval;
+
+    info!("Set max energy to {}", instruction_data.max_energy);
+
+    Ok(())
+}
+
+/// Creates a player account of `player` in `game`, starting with full
+/// energy.
+pub fn process_register(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [player, game, player_account, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(player)?;
+    // Check that `game` is a game PDA of the program.
+    check_game(game)?;
+
+    // Deserialize instruction data.
+    let instruction_data = RegisterInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `player_account`.
+    let player_pda = create_program_address(
+        &[
+            PLAYER_SEED.as_bytes(),
+            game.key(),
+            player.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(player_account.key(), &player_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the player PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(PLAYER_SEED.as_bytes()),
+        Seed::from(game.key()),
+        Seed::from(player.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: player,
+        to: player_account,
+        lamports: Rent::get()?.minimum_balance(Player::LEN),
+        space: Player::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the game PDA.
+    let game_data = game.try_borrow_data()?;
+    let game_data: &Game = unsafe { &*game_data.as_ptr().cast() };
+
+    // Deserialize the player PDA.
+    let mut data = player_account.try_borrow_mut_data()?;
+    let data: &mut Player = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the player.
+    data.game = *game.key();
+    data.player = *player.key();
+    data.energy = game_data.max_energy;
+    data.last_update = Clock::get()?.unix_timestamp;
+    data.bump = instruction_data.bump;
+
+    info!("Registered player with {} energy", data.energy);
+
+    Ok(())
+}
+
+/// Regenerates the energy of the player and spends `cost` of it.
+///
+/// Sets the remaining energy as return data.
+pub fn process_act(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [player, game, player_account] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(player)?;
+    // Check that the PDAs are a game and a player account of the program.
+    check_game(game)?;
+    require_owned_by(player_account, &ID)?;
+    if player_account.data_len() != Player::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = ActInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the game PDA.
+    let game_data = game.try_borrow_data()?;
+    let game_data: &Game = unsafe { &*game_data.as_ptr().cast() };
+
+    // Deserialize the player PDA.
+    let mut data = player_account.try_borrow_mut_data()?;
+    let data: &mut Player = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Check that the player account belongs to `player` and `game`.
+    if !keys_eq(&data.player, player.key()) || !keys_eq(&data.game, game.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    data.regenerate(game_data, Clock::get()?.unix_timestamp);
+
+    data.energy = data
+        .energy
+        .checked_sub(instruction_data.cost)
+        .ok_or(EnergyError::NotEnoughEnergy)?;
+
+    set_return_data(&data.energy.to_le_bytes());
+
+    info!(
+        "Spent {} energy, {} left",
+        instruction_data.cost, data.energy
+    );
+
+    Ok(())
+}
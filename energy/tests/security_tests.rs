@@ -0,0 +1,236 @@
+//! Attacks on the energy program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use energy::{
+    ActInstructionData, EnergyInstruction, Game, Player, RegisterInstructionData, PLAYER_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(energy::ID);
+
+const MAX_ENERGY: u64 = 100;
+const REGEN_AMOUNT: u64 = 10;
+const REGEN_INTERVAL: i64 = 60;
+const NOW: i64 = 1_000_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: EnergyInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<EnergyInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the player PDA of `player` in `game` and its bump.
+fn player_pda(game: &Pubkey, player: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[PLAYER_SEED.as_bytes(), game.as_ref(), player.as_ref()],
+        &ID,
+    )
+}
+
+fn game_account(mollusk: &Mollusk, admin: &Pubkey, max_energy: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Game::LEN),
+        Game::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(admin.as_ref());
+    account.data[32..40].copy_from_slice(&max_energy.to_le_bytes());
+    account.data[40..48].copy_from_slice(&REGEN_AMOUNT.to_le_bytes());
+    account.data[48..56].copy_from_slice(&REGEN_INTERVAL.to_le_bytes());
+    account
+}
+
+/// Returns a player account with `energy`, last updated at [`NOW`].
+fn player_account(mollusk: &Mollusk, game: &Pubkey, player: &Pubkey, energy: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Player::LEN),
+        Player::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(game.as_ref());
+    account.data[32..64].copy_from_slice(player.as_ref());
+    account.data[64..72].copy_from_slice(&energy.to_le_bytes());
+    account.data[72..80].copy_from_slice(&NOW.to_le_bytes());
+    account
+}
+
+/// Accounts of a game with a player who isn't registered yet, and of an
+/// attacker out of energy in the game, who administers a game of their own
+/// without an energy cap, in which they are registered.
+struct Setup {
+    mollusk: Mollusk,
+    game: Pubkey,
+    player: Pubkey,
+    attacker: Pubkey,
+    attacker_account: Pubkey,
+    attacker_game: Pubkey,
+    attacker_game_account: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/energy");
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let admin = Pubkey::new_unique();
+        let game = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let (attacker_account, _) = player_pda(&game, &attacker);
+        let attacker_game = Pubkey::new_unique();
+        let (attacker_game_account, _) = player_pda(&attacker_game, &attacker);
+
+        let accounts = vec![
+            (game, game_account(&mollusk, &admin, MAX_ENERGY)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_account,
+                player_account(&mollusk, &game, &attacker, 0),
+            ),
+            (attacker_game, game_account(&mollusk, &attacker, u64::MAX)),
+            (
+                attacker_game_account,
+                player_account(&mollusk, &attacker_game, &attacker, u64::MAX),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            game,
+            player,
+            attacker,
+            attacker_account,
+            attacker_game,
+            attacker_game_account,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Spends energy of `player_account` in `game`, signed by the attacker.
+    fn attacker_act(&self, game: &Pubkey, player_account: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.attacker, true),
+            AccountMeta::new_readonly(*game, false),
+            AccountMeta::new(*player_account, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(EnergyInstruction::Act, &ActInstructionData::new(10)),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_player_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.attacker_act(&setup.game, &setup.attacker_account);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the player account of the player, before the
+/// player registers.
+#[test]
+fn test_register_player_account_of_other_player() {
+    let setup = Setup::new();
+    let (player_account, _) = player_pda(&setup.game, &setup.player);
+    let (_, bump) = player_pda(&setup.game, &setup.attacker);
+    let setup = setup.with(player_account, Account::default());
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new_readonly(setup.game, false),
+        AccountMeta::new(player_account, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            EnergyInstruction::Register,
+            &RegisterInstructionData::new(bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker spends energy in the game with their player account of
+/// their own game, which never runs out.
+#[test]
+fn test_player_account_of_other_game() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_act(&setup.game, &setup.attacker_game_account),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A player account of the attacker in the game with the layout of the
+/// program and full energy, but owned by another program.
+#[test]
+fn test_spoofed_player_account() {
+    let setup = Setup::new();
+    let mut player_account = player_account(&setup.mollusk, &setup.game, &setup.attacker, u64::MAX);
+    player_account.owner = Pubkey::new_unique();
+    let address = Pubkey::new_unique();
+    let setup = setup.with(address, player_account);
+    setup.expect_err(
+        &setup.attacker_act(&setup.game, &address),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A game without an energy cap, owned by another program, in which the
+/// attacker's energy would regenerate.
+#[test]
+fn test_spoofed_game() {
+    let setup = Setup::new();
+    let mut game_account = game_account(&setup.mollusk, &setup.attacker, u64::MAX);
+    game_account.owner = Pubkey::new_unique();
+    let game = setup.game;
+    let setup = setup.with(game, game_account);
+    setup.expect_err(
+        &setup.attacker_act(&game, &setup.attacker_account),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker passes the game they administer as their player account,
+/// with the energy read past its end.
+#[test]
+fn test_game_as_player_account() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_act(&setup.attacker_game, &setup.attacker_game),
+        ProgramError::InvalidAccountData,
+    );
+}
@@ -0,0 +1,476 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use energy::{
+    ActInstructionData, ConfigInstructionData, EnergyError, EnergyInstruction, Game,
+    InitializeGameInstructionData, Player, RegisterInstructionData, GAME_SEED, PLAYER_SEED,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(energy::ID);
+
+/// Offsets of the player account fields.
+const ENERGY_OFFSET: usize = 64;
+const LAST_UPDATE_OFFSET: usize = 72;
+
+const MAX_ENERGY: u64 = 100;
+const REGEN_AMOUNT: u64 = 10;
+const REGEN_INTERVAL: i64 = 60;
+/// Unix timestamp at which the player accounts in tests were last updated.
+const START: i64 = 1_000_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: EnergyInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<EnergyInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn config() -> ConfigInstructionData {
+    ConfigInstructionData::new(MAX_ENERGY, REGEN_AMOUNT, REGEN_INTERVAL)
+}
+
+fn instruction_initialize_game(
+    admin: &Pubkey,
+    game: &Pubkey,
+    config: ConfigInstructionData,
+    bump: u8,
+) -> Instruction {
+    let data = InitializeGameInstructionData::new(config, bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new(*game, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(EnergyInstruction::InitializeGame, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_set_config(
+    admin: &Pubkey,
+    game: &Pubkey,
+    config: ConfigInstructionData,
+) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*game, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(EnergyInstruction::SetConfig, &config),
+        ix_accounts,
+    )
+}
+
+fn instruction_register(
+    player: &Pubkey,
+    game: &Pubkey,
+    player_account: &Pubkey,
+    bump: u8,
+) -> Instruction {
+    let data = RegisterInstructionData::new(bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*player, true),
+        AccountMeta::new_readonly(*game, false),
+        AccountMeta::new(*player_account, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(EnergyInstruction::Register, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_act(
+    cost: u64,
+    player: &Pubkey,
+    game: &Pubkey,
+    player_account: &Pubkey,
+) -> Instruction {
+    let data = ActInstructionData::new(cost);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*player, true),
+        AccountMeta::new_readonly(*game, false),
+        AccountMeta::new(*player_account, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(EnergyInstruction::Act, &data),
+        ix_accounts,
+    )
+}
+
+/// Creates an initialized game account with the default config.
+fn game_account(mollusk: &Mollusk, admin: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Game::LEN),
+        Game::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(admin.as_ref());
+    account.data[32..40].copy_from_slice(&MAX_ENERGY.to_le_bytes());
+    account.data[40..48].copy_from_slice(&REGEN_AMOUNT.to_le_bytes());
+    account.data[48..56].copy_from_slice(&REGEN_INTERVAL.to_le_bytes());
+    account
+}
+
+/// Creates an initialized player account with the given energy, last updated
+/// at `START`.
+fn player_account_data(mollusk: &Mollusk, game: &Pubkey, player: &Pubkey, energy: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Player::LEN),
+        Player::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(game.as_ref());
+    account.data[32..64].copy_from_slice(player.as_ref());
+    account.data[ENERGY_OFFSET..LAST_UPDATE_OFFSET].copy_from_slice(&energy.to_le_bytes());
+    account.data[LAST_UPDATE_OFFSET..LAST_UPDATE_OFFSET + 8].copy_from_slice(&START.to_le_bytes());
+    account
+}
+
+#[test]
+fn test_energy_initialize_and_register() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/energy");
+    mollusk.sysvars.clock.unix_timestamp = START;
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let admin = Pubkey::new_unique();
+    let (game, game_bump) =
+        Pubkey::find_program_address(&[GAME_SEED.as_bytes(), admin.as_ref()], &ID);
+    let player = Pubkey::new_unique();
+    let (player_account, player_bump) = Pubkey::find_program_address(
+        &[PLAYER_SEED.as_bytes(), game.as_ref(), player.as_ref()],
+        &ID,
+    );
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize_game(&admin, &game, config(), game_bump),
+                &[
+                    Check::success(),
+                    Check::account(&game)
+                        .owner(&ID)
+                        .space(Game::LEN)
+                        .data_slice(0, admin.as_ref())
+                        .data_slice(32, &MAX_ENERGY.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_register(&player, &game, &player_account, player_bump),
+                &[
+                    Check::success(),
+                    Check::account(&player_account)
+                        .owner(&ID)
+                        .space(Player::LEN)
+                        .data_slice(0, game.as_ref())
+                        .data_slice(32, player.as_ref())
+                        .data_slice(ENERGY_OFFSET, &MAX_ENERGY.to_le_bytes())
+                        .data_slice(LAST_UPDATE_OFFSET, &START.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &[
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (game, Account::default()),
+            (
+                player,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (player_account, Account::default()),
+            (system_program, system_account),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_energy_invalid_config() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/energy");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let admin = Pubkey::new_unique();
+    let (game, game_bump) =
+        Pubkey::find_program_address(&[GAME_SEED.as_bytes(), admin.as_ref()], &ID);
+
+    mollusk.process_and_validate_instruction(
+        &instruction_initialize_game(
+            &admin,
+            &game,
+            ConfigInstructionData::new(MAX_ENERGY, REGEN_AMOUNT, 0),
+            game_bump,
+        ),
+        &[
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (game, Account::default()),
+            (system_program, system_account),
+        ],
+        &[Check::err(ProgramError::Custom(
+            Namespace::Energy.code(EnergyError::InvalidConfig as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_energy_act() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/energy");
+    mollusk.sysvars.clock.unix_timestamp = START;
+
+    let admin = Pubkey::new_unique();
+    let game = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_act(30, &player, &game, &player_account),
+                &[
+                    Check::success(),
+                    Check::return_data(&70u64.to_le_bytes()),
+                    // Spending from the cap starts the regeneration.
+                    Check::account(&player_account)
+                        .data_slice(ENERGY_OFFSET, &70u64.to_le_bytes())
+                        .data_slice(LAST_UPDATE_OFFSET, &START.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_act(70, &player, &game, &player_account),
+                &[Check::success(), Check::return_data(&0u64.to_le_bytes())],
+            ),
+            (
+                &instruction_act(1, &player, &game, &player_account),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Energy.code(EnergyError::NotEnoughEnergy as u32),
+                ))],
+            ),
+        ],
+        &[
+            (player, Account::default()),
+            (game, game_account(&mollusk, &admin)),
+            (
+                player_account,
+                player_account_data(&mollusk, &game, &player, MAX_ENERGY),
+            ),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_energy_regenerate() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/energy");
+    // Two and a half intervals later.
+    mollusk.sysvars.clock.unix_timestamp = START + 2 * REGEN_INTERVAL + REGEN_INTERVAL / 2;
+
+    let admin = Pubkey::new_unique();
+    let game = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+
+    // Only whole intervals regenerate, the unfinished one carries over.
+    mollusk.process_and_validate_instruction(
+        &instruction_act(0, &player, &game, &player_account),
+        &[
+            (player, Account::default()),
+            (game, game_account(&mollusk, &admin)),
+            (
+                player_account,
+                player_account_data(&mollusk, &game, &player, 0),
+            ),
+        ],
+        &[
+            Check::success(),
+            Check::return_data(&(2 * REGEN_AMOUNT).to_le_bytes()),
+            Check::account(&player_account)
+                .data_slice(
+                    LAST_UPDATE_OFFSET,
+                    &(START + 2 * REGEN_INTERVAL).to_le_bytes(),
+                )
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_energy_regenerate_up_to_cap() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/energy");
+    let now = START + 1000 * REGEN_INTERVAL + 1;
+    mollusk.sysvars.clock.unix_timestamp = now;
+
+    let admin = Pubkey::new_unique();
+    let game = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+
+    // Time spent at the cap is dropped.
+    mollusk.process_and_validate_instruction(
+        &instruction_act(10, &player, &game, &player_account),
+        &[
+            (player, Account::default()),
+            (game, game_account(&mollusk, &admin)),
+            (
+                player_account,
+                player_account_data(&mollusk, &game, &player, 5),
+            ),
+        ],
+        &[
+            Check::success(),
+            Check::return_data(&(MAX_ENERGY - 10).to_le_bytes()),
+            Check::account(&player_account)
+                .data_slice(LAST_UPDATE_OFFSET, &now.to_le_bytes())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_energy_wrong_player() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/energy");
+    mollusk.sysvars.clock.unix_timestamp = START;
+
+    let admin = Pubkey::new_unique();
+    let game = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+
+    let accounts = [
+        (attacker, Account::default()),
+        (game, game_account(&mollusk, &admin)),
+        (
+            player_account,
+            player_account_data(&mollusk, &game, &player, MAX_ENERGY),
+        ),
+    ];
+    for instruction in [
+        // Only the player can spend their energy.
+        instruction_act(10, &attacker, &game, &player_account),
+        // Only the admin can change the config.
+        instruction_set_config(&attacker, &game, config()),
+    ] {
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[Check::err(ProgramError::IllegalOwner)],
+        );
+    }
+}
+
+/// Player accounts can't pose as games, whose config would be read from the
+/// player key.
+#[test]
+fn test_energy_player_as_game() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/energy");
+    mollusk.sysvars.clock.unix_timestamp = START;
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let game = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let (player_account, _) = Pubkey::find_program_address(
+        &[PLAYER_SEED.as_bytes(), game.as_ref(), player.as_ref()],
+        &ID,
+    );
+    let (other_player_account, bump) = Pubkey::find_program_address(
+        &[
+            PLAYER_SEED.as_bytes(),
+            player_account.as_ref(),
+            player.as_ref(),
+        ],
+        &ID,
+    );
+
+    let accounts = [
+        (
+            player,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        ),
+        (
+            player_account,
+            player_account_data(&mollusk, &game, &player, MAX_ENERGY),
+        ),
+        (other_player_account, Account::default()),
+        (system_program, system_account),
+    ];
+    for instruction in [
+        instruction_register(&player, &player_account, &other_player_account, bump),
+        instruction_set_config(&player, &player_account, config()),
+    ] {
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[Check::err(ProgramError::InvalidAccountData)],
+        );
+    }
+}
+
+#[test]
+fn test_energy_set_config() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/energy");
+    mollusk.sysvars.clock.unix_timestamp = START + REGEN_INTERVAL;
+
+    let admin = Pubkey::new_unique();
+    let game = Pubkey::new_unique();
+    let player = Pubkey::new_unique();
+    let player_account = Pubkey::new_unique();
+
+    // The new rate applies to the time since the last action.
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_set_config(
+                    &admin,
+                    &game,
+                    ConfigInstructionData::new(MAX_ENERGY, 50, REGEN_INTERVAL),
+                ),
+                &[
+                    Check::success(),
+                    Check::account(&game)
+                        .data_slice(40, &50u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_act(0, &player, &game, &player_account),
+                &[Check::success(), Check::return_data(&50u64.to_le_bytes())],
+            ),
+        ],
+        &[
+            (admin, Account::default()),
+            (player, Account::default()),
+            (game, game_account(&mollusk, &admin)),
+            (
+                player_account,
+                player_account_data(&mollusk, &game, &player, 0),
+            ),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
@@ -0,0 +1,453 @@
+//! Rewards emission halving every few epochs.
+//!
+//! A pool mints rewards to registered users, who can claim once per epoch.
+//! Every claim emits `initial_emission` tokens at first, then half as many
+//! every `halving_interval` epochs, until nothing is left to emit.
+//!
+//! Users keep a checkpoint of the next epoch in which they can claim, so
+//! claiming in every epoch and claiming once after a long break are both
+//! limited to a single emission per epoch.
+//!
+//! During the warmup of a cluster, epochs start short and double in length
+//! until the first normal epoch. The schedule never starts before that
+//! epoch, read from the `EpochSchedule` sysvar, so the halvings are evenly
+//! spaced in time. `pinocchio` doesn't provide that sysvar, so it's defined
+//! here and loaded with the `sol_get_epoch_schedule_sysvar` syscall.
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+use pinocchio_token::{instructions::MintTo, state::Mint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("3u7oN7DpbL87puYNgLwjQp1zBck1sk5kLp1ySrwtJPSh");
+
+pub const POOL_SEED: &str = "pool";
+pub const CLAIMER_SEED: &str = "claimer";
+
+/// Epoch schedule of the cluster.
+#[repr(C)]
+#[derive(Default)]
+pub struct EpochSchedule {
+    /// Maximum number of slots in an epoch.
+    pub slots_per_epoch: u64,
+    /// Number of slots before an epoch in which its leader schedule is
+    /// calculated.
+    pub leader_schedule_slot_offset: u64,
+    /// Whether epochs start short and grow.
+    pub warmup: bool,
+    /// First epoch after the warmup.
+    pub first_normal_epoch: u64,
+    /// First slot after the warmup.
+    pub first_normal_slot: u64,
+}
+
+assert_layout!(EpochSchedule, size = 40, align = 8, {
+    slots_per_epoch: 0,
+    leader_schedule_slot_offset: 8,
+    warmup: 16,
+    first_normal_epoch: 24,
+    first_normal_slot: 32,
+});
+
+impl Sysvar for EpochSchedule {
+    pinocchio::impl_sysvar_get!(sol_get_epoch_schedule_sysvar);
+}
+
+/// Errors returned by the epoch emission program.
+#[repr(u32)]
+pub enum EpochEmissionError {
+    /// The schedule starts in a later epoch.
+    NotStarted,
+    /// The user already claimed in the current epoch.
+    AlreadyClaimed,
+    /// The emission halved down to zero.
+    EmissionEnded,
+}
+
+impl From<EpochEmissionError> for ProgramError {
+    fn from(e: EpochEmissionError) -> Self {
+        Namespace::EpochEmission.error(e as u32)
+    }
+}
+
+/// On-chain rewards pool, the mint authority of its mint.
+#[repr(C)]
+pub struct Pool {
+    pub mint: Pubkey,
+    /// Tokens emitted per claim before the first halving.
+    pub initial_emission: u64,
+    /// Number of epochs between halvings.
+    pub halving_interval: u64,
+    /// First epoch of the schedule.
+    pub start_epoch: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Pool, size = 64, align = 8, {
+    mint: 0,
+    initial_emission: 32,
+    halving_interval: 40,
+    start_epoch: 48,
+    bump: 56,
+});
+
+impl Pool {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Returns the number of tokens emitted per claim in `epoch`, which
+    /// can't be before `start_epoch`.
+    pub fn emission(&self, epoch: u64) -> u64 {
+        let halvings = (epoch - self.start_epoch) / self.halving_interval;
+        self.initial_emission
+            .checked_shr(halvings.try_into().unwrap_or(u32::MAX))
+            .unwrap_or(0)
+    }
+}
+
+/// Claim checkpoint of a user.
+#[repr(C)]
+pub struct Claimer {
+    pub user: Pubkey,
+    /// First epoch in which the user can claim.
+    pub next_epoch: u64,
+    /// Total claimed tokens.
+    pub claimed: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Claimer, size = 56, align = 8, { user: 0, next_epoch: 32, claimed: 40, bump: 48 });
+
+impl Claimer {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Epoch emission program instruction discriminators.
+#[repr(u8)]
+pub enum EpochEmissionInstruction {
+    /// Creates a pool for a mint.
+    InitializePool,
+    /// Creates a claim checkpoint of a user.
+    Register,
+    /// Mints the emission of the current epoch to a user.
+    Claim,
+}
+
+impl TryFrom<&u8> for EpochEmissionInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializePool),
+            1 => Ok(Self::Register),
+            2 => Ok(Self::Claim),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct InitializePoolInstructionData {
+    pub initial_emission: u64,
+    pub halving_interval: u64,
+    pub bump: u8,
+}
+
+impl InitializePoolInstructionData {
+    pub fn new(initial_emission: u64, halving_interval: u64, bump: u8) -> Self {
+        Self {
+            initial_emission,
+            halving_interval,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct RegisterInstructionData {
+    pub bump: u8,
+}
+
+impl RegisterInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = EpochEmissionInstruction::try_from(instruction)?;
+
+    match instruction {
+        EpochEmissionInstruction::InitializePool => {
+            process_initialize_pool(accounts, instruction_data)
+        }
+        EpochEmissionInstruction::Register => process_register(accounts, instruction_data),
+        EpochEmissionInstruction::Claim => process_claim(accounts),
+    }
+}
+
+/// Creates the pool of `mint`. The mint authority of `mint` has to be the
+/// pool PDA already. The schedule starts in the current epoch, or in the
+/// first normal epoch during the warmup.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Payer
+///   1. `[]` Mint
+///   2. `[WRITE]` Pool PDA
+///   3. `[]` System program
+pub fn process_initialize_pool(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [payer, mint, pool, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(payer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializePoolInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.halving_interval == 0 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let pool_pda = create_program_address(
+        &[POOL_SEED.as_bytes(), mint.key(), &[instruction_data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(pool.key(), &pool_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if Mint::from_account_info(mint)?.mint_authority() != Some(&pool_pda) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let start_epoch = Clock::get()?
+        .epoch
+        .max(EpochSchedule::get()?.first_normal_epoch);
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(POOL_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: payer,
+        to: pool,
+        lamports: Rent::get()?.minimum_balance(Pool::LEN),
+        space: Pool::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut data = pool.try_borrow_mut_data()?;
+    let pool: &mut Pool = unsafe { &mut *data.as_mut_ptr().cast() };
+    pool.mint = *mint.key();
+    pool.initial_emission = instruction_data.initial_emission;
+    pool.halving_interval = instruction_data.halving_interval;
+    pool.start_epoch = start_epoch;
+    pool.bump = instruction_data.bump;
+
+    info!("Created the pool, starting in epoch {}", start_epoch);
+
+    Ok(())
+}
+
+/// Creates the claim checkpoint of `user` in `pool`. The user can claim
+/// from the current epoch.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` User
+///   1. `[]` Pool PDA
+///   2. `[WRITE]` Claimer PDA
+///   3. `[]` System program
+pub fn process_register(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, pool, claimer, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(user)?;
+    require_owned_by(pool, &ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = RegisterInstructionData::from_bytes(instruction_data)?;
+
+    let claimer_pda = create_program_address(
+        &[
+            CLAIMER_SEED.as_bytes(),
+            pool.key(),
+            user.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(claimer.key(), &claimer_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(CLAIMER_SEED.as_bytes()),
+        Seed::from(pool.key()),
+        Seed::from(user.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: user,
+        to: claimer,
+        lamports: Rent::get()?.minimum_balance(Claimer::LEN),
+        space: Claimer::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut data = claimer.try_borrow_mut_data()?;
+    let claimer: &mut Claimer = unsafe { &mut *data.as_mut_ptr().cast() };
+    claimer.user = *user.key();
+    claimer.next_epoch = Clock::get()?.epoch;
+    claimer.bump = instruction_data.bump;
+
+    info!("Registered the user");
+
+    Ok(())
+}
+
+/// Mints the emission of the current epoch to `token_account` and moves
+/// the checkpoint of `user` to the next epoch.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` User
+///   1. `[]` Pool PDA
+///   2. `[WRITE]` Claimer PDA
+///   3. `[WRITE]` Mint
+///   4. `[WRITE]` Token account
+///   5. `[]` Token program
+pub fn process_claim(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, pool, claimer, mint, token_account, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(user)?;
+    require_owned_by(pool, &ID)?;
+    check_claimer(claimer, pool, user)?;
+
+    // The pool is the signer of the mint, so it can't stay borrowed.
+    let (amount, bump) = {
+        let data = pool.try_borrow_data()?;
+        if data.len() != Pool::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let pool: &Pool = unsafe { &*data.as_ptr().cast() };
+        if !keys_eq(&pool.mint, mint.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut data = claimer.try_borrow_mut_data()?;
+        let claimer: &mut Claimer = unsafe { &mut *data.as_mut_ptr().cast() };
+        if !keys_eq(&claimer.user, user.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        let epoch = Clock::get()?.epoch;
+        if epoch < pool.start_epoch {
+            return Err(EpochEmissionError::NotStarted.into());
+        }
+        if epoch < claimer.next_epoch {
+            return Err(EpochEmissionError::AlreadyClaimed.into());
+        }
+        let amount = pool.emission(epoch);
+        if amount == 0 {
+            return Err(EpochEmissionError::EmissionEnded.into());
+        }
+
+        claimer.next_epoch = epoch + 1;
+        claimer.claimed = claimer
+            .claimed
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        (amount, pool.bump)
+    };
+
+    let bump = [bump];
+    let seeds = [
+        Seed::from(POOL_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
+    ];
+    MintTo {
+        mint,
+        account: token_account,
+        mint_authority: pool,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Claimed {} tokens", amount);
+
+    Ok(())
+}
+
+/// Checks that `claimer` is the claimer PDA of `user` in `pool`. Claimers
+/// don't store their pool, so the ownership alone would let a user
+/// registered in two pools claim twice per epoch from one of them.
+fn check_claimer(claimer: &AccountInfo, pool: &AccountInfo, user: &AccountInfo) -> ProgramResult {
+    require_owned_by(claimer, &ID)?;
+    if claimer.data_len() != Claimer::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Check the seeds of `claimer`.
+    let data = claimer.try_borrow_data()?;
+    let data: &Claimer = unsafe { &*data.as_ptr().cast() };
+    let claimer_pda = create_program_address(
+        &[
+            CLAIMER_SEED.as_bytes(),
+            pool.key(),
+            user.key(),
+            &[data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(claimer.key(), &claimer_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+//! Attacks on the epoch emission program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use epoch_emission::{
+    Claimer, EpochEmissionInstruction, Pool, RegisterInstructionData, CLAIMER_SEED, POOL_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(epoch_emission::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+const INITIAL_EMISSION: u64 = 1_000;
+const HALVING_INTERVAL: u64 = 4;
+const EPOCH: u64 = 700;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: EpochEmissionInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<EpochEmissionInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn mint_account(mollusk: &Mollusk, mint_authority: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::Some(*mint_authority),
+            supply: 0,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount: 0,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns the pool PDA of `mint` and its account, started in [`EPOCH`].
+fn pool_account(mollusk: &Mollusk, mint: &Pubkey) -> (Pubkey, Account) {
+    let (pool, bump) = Pubkey::find_program_address(&[POOL_SEED.as_bytes(), mint.as_ref()], &ID);
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Pool::LEN),
+        Pool::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(mint.as_ref());
+    account.data[32..40].copy_from_slice(&INITIAL_EMISSION.to_le_bytes());
+    account.data[40..48].copy_from_slice(&HALVING_INTERVAL.to_le_bytes());
+    account.data[48..56].copy_from_slice(&EPOCH.to_le_bytes());
+    account.data[56] = bump;
+    (pool, account)
+}
+
+/// Returns the claimer PDA of `user` in `pool` and its account, which can
+/// claim in [`EPOCH`].
+fn claimer_account(mollusk: &Mollusk, pool: &Pubkey, user: &Pubkey) -> (Pubkey, Account) {
+    let (claimer, bump) = Pubkey::find_program_address(
+        &[CLAIMER_SEED.as_bytes(), pool.as_ref(), user.as_ref()],
+        &ID,
+    );
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Claimer::LEN),
+        Claimer::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(user.as_ref());
+    account.data[32..40].copy_from_slice(&EPOCH.to_le_bytes());
+    account.data[48] = bump;
+    (claimer, account)
+}
+
+/// Accounts of a started pool, of a user who isn't registered yet, and of
+/// a registered attacker with a token account of the mint.
+struct Setup {
+    mollusk: Mollusk,
+    mint: Pubkey,
+    pool: Pubkey,
+    user: Pubkey,
+    attacker: Pubkey,
+    attacker_claimer: Pubkey,
+    attacker_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/epoch_emission");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+        mollusk.sysvars.clock.epoch = EPOCH;
+
+        let mint = Pubkey::new_unique();
+        let (pool, pool_account) = pool_account(&mollusk, &mint);
+        let user = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let (attacker_claimer, attacker_claimer_account) =
+            claimer_account(&mollusk, &pool, &attacker);
+        let attacker_ata = Pubkey::new_unique();
+
+        let accounts = vec![
+            (mint, mint_account(&mollusk, &pool)),
+            (pool, pool_account),
+            (user, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (attacker_claimer, attacker_claimer_account),
+            (attacker_ata, token_account(&mollusk, &mint, &attacker)),
+            keyed_account_for_system_program(),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            mint,
+            pool,
+            user,
+            attacker,
+            attacker_claimer,
+            attacker_ata,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Registers the attacker in `pool` with `claimer`.
+    fn attacker_register(&self, pool: &Pubkey, claimer: &Pubkey, bump: u8) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new(*claimer, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                EpochEmissionInstruction::Register,
+                &RegisterInstructionData::new(bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Claims the emission of the attacker from `pool` with `claimer`.
+    fn attacker_claim(&self, pool: &Pubkey, claimer: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.attacker, true),
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new(*claimer, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.attacker_ata, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[EpochEmissionInstruction::Claim as u8], ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_user_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.attacker_claim(&setup.pool, &setup.attacker_claimer);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the claimer PDA of the user, before the user
+/// registers.
+#[test]
+fn test_register_claimer_of_other_user() {
+    let setup = Setup::new();
+    let (claimer, _) = Pubkey::find_program_address(
+        &[
+            CLAIMER_SEED.as_bytes(),
+            setup.pool.as_ref(),
+            setup.user.as_ref(),
+        ],
+        &ID,
+    );
+    let (_, bump) = Pubkey::find_program_address(
+        &[
+            CLAIMER_SEED.as_bytes(),
+            setup.pool.as_ref(),
+            setup.attacker.as_ref(),
+        ],
+        &ID,
+    );
+    let setup = setup.with(claimer, Account::default());
+    setup.expect_err(
+        &setup.attacker_register(&setup.pool, &claimer, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker registers in a pool with the layout of the program, but
+/// owned by another program.
+#[test]
+fn test_register_in_spoofed_pool() {
+    let setup = Setup::new();
+    let (pool, mut pool_account) = pool_account(&setup.mollusk, &Pubkey::new_unique());
+    pool_account.owner = Pubkey::new_unique();
+    let (claimer, bump) = Pubkey::find_program_address(
+        &[
+            CLAIMER_SEED.as_bytes(),
+            pool.as_ref(),
+            setup.attacker.as_ref(),
+        ],
+        &ID,
+    );
+    let setup = setup
+        .with(pool, pool_account)
+        .with(claimer, Account::default());
+    setup.expect_err(
+        &setup.attacker_register(&pool, &claimer, bump),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A pool of the mint with a made up initial emission, owned by another
+/// program.
+#[test]
+fn test_claim_from_spoofed_pool() {
+    let setup = Setup::new();
+    let (_, mut pool_account) = pool_account(&setup.mollusk, &setup.mint);
+    pool_account.owner = Pubkey::new_unique();
+    pool_account.data[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+    let pool = Pubkey::new_unique();
+    let setup = setup.with(pool, pool_account);
+    setup.expect_err(
+        &setup.attacker_claim(&pool, &setup.attacker_claimer),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker registers with their claimer as the pool, then claims from
+/// it, with the attacker read as the mint of the pool.
+#[test]
+fn test_claimer_as_pool() {
+    let setup = Setup::new();
+    let (claimer, claimer_account) =
+        claimer_account(&setup.mollusk, &setup.attacker_claimer, &setup.attacker);
+    let setup = setup.with(claimer, claimer_account);
+    setup.expect_err(
+        &setup.attacker_claim(&setup.attacker_claimer, &claimer),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker registers in the pool of another mint, with a made up
+/// initial emission, then claims the mint of the pool from it.
+#[test]
+fn test_claim_from_pool_of_other_mint() {
+    let setup = Setup::new();
+    let mint = Pubkey::new_unique();
+    let (pool, mut pool_account) = pool_account(&setup.mollusk, &mint);
+    pool_account.data[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+    let (claimer, claimer_account) = claimer_account(&setup.mollusk, &pool, &setup.attacker);
+    let mint_account = mint_account(&setup.mollusk, &pool);
+    let setup = setup
+        .with(mint, mint_account)
+        .with(pool, pool_account)
+        .with(claimer, claimer_account);
+    setup.expect_err(
+        &setup.attacker_claim(&pool, &claimer),
+        ProgramError::InvalidAccountData,
+    );
+}
@@ -0,0 +1,498 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use epoch_emission::{
+    Claimer, EpochEmissionInstruction, EpochSchedule, InitializePoolInstructionData, Pool,
+    RegisterInstructionData, CLAIMER_SEED, POOL_SEED,
+};
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(epoch_emission::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// `EpochEmissionError::NotStarted`.
+const NOT_STARTED: u32 = Namespace::EpochEmission.code(0);
+/// `EpochEmissionError::AlreadyClaimed`.
+const ALREADY_CLAIMED: u32 = Namespace::EpochEmission.code(1);
+/// `EpochEmissionError::EmissionEnded`.
+const EMISSION_ENDED: u32 = Namespace::EpochEmission.code(2);
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offsets of the fields of a claimer.
+const NEXT_EPOCH_OFFSET: usize = 32;
+const CLAIMED_OFFSET: usize = 40;
+
+const INITIAL_EMISSION: u64 = 1_000;
+const HALVING_INTERVAL: u64 = 4;
+const EPOCH: u64 = 700;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: EpochEmissionInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<EpochEmissionInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/epoch_emission");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk.sysvars.clock.epoch = EPOCH;
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, mint_authority: &Pubkey, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::Some(*mint_authority),
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn pool_account(mollusk: &Mollusk, mint: &Pubkey, start_epoch: u64, bump: u8) -> Account {
+    let mut data = vec![0; Pool::LEN];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..40].copy_from_slice(&INITIAL_EMISSION.to_le_bytes());
+    data[40..48].copy_from_slice(&HALVING_INTERVAL.to_le_bytes());
+    data[48..56].copy_from_slice(&start_epoch.to_le_bytes());
+    data[56] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Pool::LEN),
+        Pool::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+fn claimer_account(
+    mollusk: &Mollusk,
+    user: &Pubkey,
+    next_epoch: u64,
+    claimed: u64,
+    bump: u8,
+) -> Account {
+    let mut data = vec![0; Claimer::LEN];
+    data[0..32].copy_from_slice(user.as_ref());
+    data[NEXT_EPOCH_OFFSET..CLAIMED_OFFSET].copy_from_slice(&next_epoch.to_le_bytes());
+    data[CLAIMED_OFFSET..CLAIMED_OFFSET + 8].copy_from_slice(&claimed.to_le_bytes());
+    data[48] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Claimer::LEN),
+        Claimer::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// A pool started in [`EPOCH`] and a registered user.
+struct Setup {
+    mollusk: Mollusk,
+    mint: Pubkey,
+    pool: Pubkey,
+    pool_bump: u8,
+    user: Pubkey,
+    claimer: Pubkey,
+    claimer_bump: u8,
+    user_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let mint = Pubkey::new_unique();
+        let (pool, pool_bump) =
+            Pubkey::find_program_address(&[POOL_SEED.as_bytes(), mint.as_ref()], &ID);
+        let user = Pubkey::new_unique();
+        let (claimer, claimer_bump) = Pubkey::find_program_address(
+            &[CLAIMER_SEED.as_bytes(), pool.as_ref(), user.as_ref()],
+            &ID,
+        );
+        let user_ata = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (mint, mint_account(&mollusk, &pool, 0)),
+            (pool, pool_account(&mollusk, &mint, EPOCH, pool_bump)),
+            (user, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (
+                claimer,
+                claimer_account(&mollusk, &user, EPOCH, 0, claimer_bump),
+            ),
+            (user_ata, token_account(&mollusk, &mint, &user, 0)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            mint,
+            pool,
+            pool_bump,
+            user,
+            claimer,
+            claimer_bump,
+            user_ata,
+            accounts,
+        }
+    }
+
+    fn instruction_initialize_pool(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.user, true),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                EpochEmissionInstruction::InitializePool,
+                &InitializePoolInstructionData::new(
+                    INITIAL_EMISSION,
+                    HALVING_INTERVAL,
+                    self.pool_bump,
+                ),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_register(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.user, true),
+            AccountMeta::new_readonly(self.pool, false),
+            AccountMeta::new(self.claimer, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                EpochEmissionInstruction::Register,
+                &RegisterInstructionData::new(self.claimer_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_claim(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.user, true),
+            AccountMeta::new_readonly(self.pool, false),
+            AccountMeta::new(self.claimer, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.user_ata, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[EpochEmissionInstruction::Claim as u8], ix_accounts)
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(Pool::LEN, 64);
+    assert_eq!(Claimer::LEN, 56);
+
+    // The syscall copies the `EpochSchedule` of the runtime as is.
+    assert_eq!(
+        mem::size_of::<EpochSchedule>(),
+        mem::size_of::<solana_epoch_schedule::EpochSchedule>()
+    );
+    let expected = solana_epoch_schedule::EpochSchedule::new(8_192);
+    let epoch_schedule: EpochSchedule = unsafe { mem::transmute_copy(&expected) };
+    assert_eq!(epoch_schedule.slots_per_epoch, expected.slots_per_epoch);
+    assert_eq!(
+        epoch_schedule.leader_schedule_slot_offset,
+        expected.leader_schedule_slot_offset
+    );
+    assert!(epoch_schedule.warmup);
+    assert_eq!(
+        epoch_schedule.first_normal_epoch,
+        expected.first_normal_epoch
+    );
+    assert_eq!(epoch_schedule.first_normal_slot, expected.first_normal_slot);
+}
+
+#[test]
+fn test_emission() {
+    let pool = Pool {
+        mint: [0; 32],
+        initial_emission: INITIAL_EMISSION,
+        halving_interval: HALVING_INTERVAL,
+        start_epoch: EPOCH,
+        bump: 0,
+        _padding: [0; 7],
+    };
+
+    assert_eq!(pool.emission(EPOCH), INITIAL_EMISSION);
+    assert_eq!(
+        pool.emission(EPOCH + HALVING_INTERVAL - 1),
+        INITIAL_EMISSION
+    );
+    assert_eq!(
+        pool.emission(EPOCH + HALVING_INTERVAL),
+        INITIAL_EMISSION / 2
+    );
+    assert_eq!(
+        pool.emission(EPOCH + 3 * HALVING_INTERVAL),
+        INITIAL_EMISSION / 8
+    );
+    // 1000 halves down to zero after 10 halvings.
+    assert_eq!(pool.emission(EPOCH + 9 * HALVING_INTERVAL), 1);
+    assert_eq!(pool.emission(EPOCH + 10 * HALVING_INTERVAL), 0);
+    // Shifting by more than the width of `u64` doesn't overflow.
+    assert_eq!(pool.emission(EPOCH + 64 * HALVING_INTERVAL), 0);
+    assert_eq!(pool.emission(u64::MAX), 0);
+}
+
+#[test]
+fn test_initialize_pool() {
+    let mut setup = Setup::new();
+    // The pool is created by the program.
+    setup.accounts[1].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_pool(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.pool)
+                .owner(&ID)
+                .data(&pool_account(&setup.mollusk, &setup.mint, EPOCH, setup.pool_bump).data)
+                .build(),
+        ],
+    );
+
+    // During the warmup, the schedule starts in the first normal epoch.
+    let epoch_schedule = solana_epoch_schedule::EpochSchedule::new(8_192);
+    assert_eq!(epoch_schedule.first_normal_epoch, 8);
+    setup.mollusk.sysvars.epoch_schedule = epoch_schedule;
+    setup.mollusk.sysvars.clock.epoch = 3;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_pool(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.pool)
+                .data(&pool_account(&setup.mollusk, &setup.mint, 8, setup.pool_bump).data)
+                .build(),
+        ],
+    );
+
+    // The pool has to be the mint authority.
+    setup.accounts[0].1 = mint_account(&setup.mollusk, &setup.user, 0);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_pool(),
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+#[test]
+fn test_register() {
+    let mut setup = Setup::new();
+    // The claimer is created by the program.
+    setup.accounts[3].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_register(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.claimer)
+                .owner(&ID)
+                .data(
+                    &claimer_account(&setup.mollusk, &setup.user, EPOCH, 0, setup.claimer_bump)
+                        .data,
+                )
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_claim() {
+    let mut setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_claim(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.user_ata)
+                        .data_slice(AMOUNT_OFFSET, &INITIAL_EMISSION.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.claimer)
+                        .data_slice(NEXT_EPOCH_OFFSET, &(EPOCH + 1).to_le_bytes())
+                        .data_slice(CLAIMED_OFFSET, &INITIAL_EMISSION.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            // One claim per epoch.
+            (
+                &setup.instruction_claim(),
+                &[Check::err(ProgramError::Custom(ALREADY_CLAIMED))],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+
+    // Skipping epochs doesn't accumulate claims, the checkpoint moves to the
+    // epoch after the claim.
+    setup.accounts[4].1 = token_account(&setup.mollusk, &setup.mint, &setup.user, INITIAL_EMISSION);
+    setup.accounts[3].1 = claimer_account(
+        &setup.mollusk,
+        &setup.user,
+        EPOCH + 1,
+        INITIAL_EMISSION,
+        setup.claimer_bump,
+    );
+    let epoch = EPOCH + 2 * HALVING_INTERVAL + 1;
+    setup.mollusk.sysvars.clock.epoch = epoch;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_claim(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.user_ata)
+                .data_slice(
+                    AMOUNT_OFFSET,
+                    &(INITIAL_EMISSION + INITIAL_EMISSION / 4).to_le_bytes(),
+                )
+                .build(),
+            Check::account(&setup.claimer)
+                .data_slice(NEXT_EPOCH_OFFSET, &(epoch + 1).to_le_bytes())
+                .data_slice(
+                    CLAIMED_OFFSET,
+                    &(INITIAL_EMISSION + INITIAL_EMISSION / 4).to_le_bytes(),
+                )
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_claim_invalid() {
+    let mut setup = Setup::new();
+
+    // The schedule hasn't started yet.
+    setup.accounts[1].1 = pool_account(&setup.mollusk, &setup.mint, EPOCH + 1, setup.pool_bump);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_claim(),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(NOT_STARTED))],
+    );
+
+    // Everything was emitted.
+    setup.accounts[1].1 = pool_account(&setup.mollusk, &setup.mint, EPOCH, setup.pool_bump);
+    setup.mollusk.sysvars.clock.epoch = EPOCH + 10 * HALVING_INTERVAL;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_claim(),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(EMISSION_ENDED))],
+    );
+
+    // Users can only claim with their own checkpoint.
+    setup.mollusk.sysvars.clock.epoch = EPOCH;
+    setup.accounts[3].1 = claimer_account(
+        &setup.mollusk,
+        &Pubkey::new_unique(),
+        EPOCH,
+        0,
+        setup.claimer_bump,
+    );
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_claim(),
+        &setup.accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+
+    // Nor with their checkpoint in another pool.
+    let (claimer, claimer_bump) = Pubkey::find_program_address(
+        &[
+            CLAIMER_SEED.as_bytes(),
+            Pubkey::new_unique().as_ref(),
+            setup.user.as_ref(),
+        ],
+        &ID,
+    );
+    setup.accounts.push((
+        claimer,
+        claimer_account(&setup.mollusk, &setup.user, EPOCH, 0, claimer_bump),
+    ));
+    let mut instruction = setup.instruction_claim();
+    instruction.accounts[2].pubkey = claimer;
+    setup.mollusk.process_and_validate_instruction(
+        &instruction,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
@@ -0,0 +1,276 @@
+//! Emission schedule following the staking rewards of the cluster.
+//!
+//! At the start of every epoch, the runtime calculates the staking rewards
+//! and distributes them over the first blocks of the epoch. The
+//! `EpochRewards` sysvar describes the distribution of the current epoch.
+//!
+//! The program stores an emission schedule in a PDA and, once per epoch,
+//! sets its rate proportionally to the total rewards of the epoch: epochs
+//! paying `reference_rewards` lamports emit `base_rate` tokens, epochs paying
+//! twice as much emit twice as many.
+//!
+//! `pinocchio` doesn't provide the `EpochRewards` sysvar, so it's defined
+//! here and loaded with the `sol_get_epoch_rewards_sysvar` syscall, the same
+//! way `pinocchio` loads `Clock`.
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("3MAPXW4Htuy9v2q7qREL3VK15UV2FDEAcPzfc1UvdkEo");
+
+pub const SCHEDULE_SEED: &str = "schedule";
+
+/// Staking rewards distribution of the current epoch.
+#[repr(C, align(16))]
+#[derive(Default)]
+pub struct EpochRewards {
+    /// Block height at which the distribution started.
+    pub distribution_starting_block_height: u64,
+    /// Number of partitions of the distribution.
+    pub num_partitions: u64,
+    /// Blockhash of the parent of the first block of the epoch.
+    pub parent_blockhash: [u8; 32],
+    /// Total points, the sum of the delegated stake times the credits
+    /// observed of all delegations.
+    pub total_points: u128,
+    /// Total rewards of the epoch, in lamports.
+    pub total_rewards: u64,
+    /// Rewards distributed so far, in lamports.
+    pub distributed_rewards: u64,
+    /// Whether the distribution is in progress.
+    pub active: bool,
+}
+
+impl Sysvar for EpochRewards {
+    pinocchio::impl_sysvar_get!(sol_get_epoch_rewards_sysvar);
+}
+
+/// Errors returned by the epoch rewards program.
+#[repr(u32)]
+pub enum EpochRewardsError {
+    /// The rate was already updated in the current epoch.
+    AlreadyUpdated,
+    /// The current epoch doesn't pay any rewards.
+    NoRewards,
+}
+
+impl From<EpochRewardsError> for ProgramError {
+    fn from(e: EpochRewardsError) -> Self {
+        Namespace::EpochRewards.error(e as u32)
+    }
+}
+
+/// On-chain emission schedule.
+#[repr(C)]
+pub struct Schedule {
+    pub authority: Pubkey,
+    /// Tokens emitted per epoch when the epoch pays `reference_rewards`.
+    pub base_rate: u64,
+    /// Epoch rewards at which `base_rate` tokens are emitted, in lamports.
+    pub reference_rewards: u64,
+    /// Tokens emitted per epoch.
+    pub rate: u64,
+    /// First epoch in which `rate` can be updated.
+    pub next_epoch: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Schedule, size = 72, align = 8, {
+    authority: 0,
+    base_rate: 32,
+    reference_rewards: 40,
+    rate: 48,
+    next_epoch: 56,
+    bump: 64,
+});
+
+impl Schedule {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Epoch rewards program instruction discriminators.
+#[repr(u8)]
+pub enum EpochRewardsInstruction {
+    /// Creates an emission schedule.
+    Initialize,
+    /// Updates the rate of the emission schedule to the rewards of the
+    /// current epoch.
+    Update,
+}
+
+impl TryFrom<&u8> for EpochRewardsInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Update),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct InitializeInstructionData {
+    pub base_rate: u64,
+    pub reference_rewards: u64,
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(base_rate: u64, reference_rewards: u64, bump: u8) -> Self {
+        Self {
+            base_rate,
+            reference_rewards,
+            bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = EpochRewardsInstruction::try_from(instruction)?;
+
+    match instruction {
+        EpochRewardsInstruction::Initialize => process_initialize(accounts, instruction_data),
+        EpochRewardsInstruction::Update => process_update(accounts),
+    }
+}
+
+/// Creates the emission schedule of `authority`. The rate stays zero until
+/// the first update.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Authority
+///   1. `[WRITE]` Schedule PDA
+///   2. `[]` System program
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, schedule, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.reference_rewards == 0 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let schedule_pda = create_program_address(
+        &[
+            SCHEDULE_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(schedule.key(), &schedule_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(SCHEDULE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: schedule,
+        lamports: Rent::get()?.minimum_balance(Schedule::LEN),
+        space: Schedule::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut data = schedule.try_borrow_mut_data()?;
+    let schedule: &mut Schedule = unsafe { &mut *data.as_mut_ptr().cast() };
+    schedule.authority = *authority.key();
+    schedule.base_rate = instruction_data.base_rate;
+    schedule.reference_rewards = instruction_data.reference_rewards;
+    schedule.next_epoch = Clock::get()?.epoch;
+    schedule.bump = instruction_data.bump;
+
+    info!("Created the emission schedule");
+
+    Ok(())
+}
+
+/// Sets the rate of `schedule` proportionally to the total rewards of the
+/// current epoch. Anyone can update the rate, once per epoch.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Schedule PDA
+pub fn process_update(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [schedule] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_owned_by(schedule, &ID)?;
+    let mut data = schedule.try_borrow_mut_data()?;
+    if data.len() != Schedule::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let schedule: &mut Schedule = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    let epoch = Clock::get()?.epoch;
+    if epoch < schedule.next_epoch {
+        return Err(EpochRewardsError::AlreadyUpdated.into());
+    }
+
+    let epoch_rewards = EpochRewards::get()?;
+    if epoch_rewards.total_rewards == 0 {
+        return Err(EpochRewardsError::NoRewards.into());
+    }
+
+    let rate = schedule.base_rate as u128 * epoch_rewards.total_rewards as u128
+        / schedule.reference_rewards as u128;
+    schedule.rate = rate
+        .try_into()
+        .map_err(|_| ProgramError::ArithmeticOverflow)?;
+    schedule.next_epoch = epoch + 1;
+
+    info!(
+        "Epoch {} pays {} lamports, emitting {} tokens",
+        epoch, epoch_rewards.total_rewards, schedule.rate
+    );
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Attacks on the epoch rewards program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use epoch_rewards::{EpochRewardsInstruction, InitializeInstructionData, Schedule, SCHEDULE_SEED};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(epoch_rewards::ID);
+
+const BASE_RATE: u64 = 1_000_000;
+const REFERENCE_REWARDS: u64 = 100_000 * LAMPORTS_PER_SOL;
+const EPOCH: u64 = 700;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: EpochRewardsInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<EpochRewardsInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the schedule PDA of `authority` and its bump.
+fn schedule_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SCHEDULE_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Accounts of an authority without a schedule yet, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    schedule: Pubkey,
+    schedule_bump: u8,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/epoch_rewards");
+        mollusk.sysvars.clock.epoch = EPOCH;
+        mollusk.sysvars.epoch_rewards = solana_epoch_rewards::EpochRewards {
+            total_rewards: REFERENCE_REWARDS,
+            active: true,
+            ..Default::default()
+        };
+
+        let authority = Pubkey::new_unique();
+        let (schedule, schedule_bump) = schedule_pda(&authority);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (schedule, Account::default()),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            schedule,
+            schedule_bump,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Creates the schedule of the authority, signed by `authority`.
+    fn instruction_initialize(&self, authority: &Pubkey, bump: u8) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(self.schedule, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                EpochRewardsInstruction::Initialize,
+                &InitializeInstructionData::new(BASE_RATE, REFERENCE_REWARDS, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_initialize(&setup.authority, setup.schedule_bump);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the schedule of the authority with their own rates,
+/// before the authority does.
+#[test]
+fn test_initialize_schedule_of_other_authority() {
+    let setup = Setup::new();
+    let (_, bump) = schedule_pda(&setup.attacker);
+    setup.expect_err(
+        &setup.instruction_initialize(&setup.attacker, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// A schedule with the layout of the program and a made up base rate, but
+/// owned by another program, whose rate the program would update.
+#[test]
+fn test_spoofed_schedule() {
+    let setup = Setup::new();
+    let mut schedule_account = Account::new(LAMPORTS_PER_SOL, Schedule::LEN, &Pubkey::new_unique());
+    schedule_account.data[0..32].copy_from_slice(setup.attacker.as_ref());
+    schedule_account.data[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+    schedule_account.data[40..48].copy_from_slice(&1u64.to_le_bytes());
+    let schedule = Pubkey::new_unique();
+    let setup = setup.with(schedule, schedule_account);
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &[EpochRewardsInstruction::Update as u8],
+        vec![AccountMeta::new(schedule, false)],
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
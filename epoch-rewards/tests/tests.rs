@@ -0,0 +1,223 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use epoch_rewards::{
+    EpochRewards, EpochRewardsInstruction, InitializeInstructionData, Schedule, SCHEDULE_SEED,
+};
+use errors::Namespace;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(epoch_rewards::ID);
+
+/// `EpochRewardsError::AlreadyUpdated`.
+const ALREADY_UPDATED: u32 = Namespace::EpochRewards.code(0);
+/// `EpochRewardsError::NoRewards`.
+const NO_REWARDS: u32 = Namespace::EpochRewards.code(1);
+
+const BASE_RATE: u64 = 1_000_000;
+const REFERENCE_REWARDS: u64 = 100_000 * LAMPORTS_PER_SOL;
+const EPOCH: u64 = 700;
+
+/// Offsets of the fields of a schedule.
+const RATE_OFFSET: usize = 48;
+const NEXT_EPOCH_OFFSET: usize = 56;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: EpochRewardsInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<EpochRewardsInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/epoch_rewards");
+    mollusk.sysvars.clock.epoch = EPOCH;
+    mollusk
+}
+
+/// Overrides the `EpochRewards` sysvar with a distribution of
+/// `total_rewards` in progress.
+fn set_epoch_rewards(mollusk: &mut Mollusk, total_rewards: u64) {
+    mollusk.sysvars.epoch_rewards = solana_epoch_rewards::EpochRewards {
+        distribution_starting_block_height: 1_000,
+        num_partitions: 4,
+        total_points: 1 << 80,
+        total_rewards,
+        distributed_rewards: total_rewards / 4,
+        active: true,
+        ..Default::default()
+    };
+}
+
+fn schedule_account(mollusk: &Mollusk, authority: &Pubkey, next_epoch: u64, bump: u8) -> Account {
+    let mut data = vec![0; Schedule::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..40].copy_from_slice(&BASE_RATE.to_le_bytes());
+    data[40..48].copy_from_slice(&REFERENCE_REWARDS.to_le_bytes());
+    data[NEXT_EPOCH_OFFSET..NEXT_EPOCH_OFFSET + 8].copy_from_slice(&next_epoch.to_le_bytes());
+    data[64] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Schedule::LEN),
+        Schedule::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+fn instruction_initialize(authority: &Pubkey, schedule: &Pubkey, bump: u8) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*schedule, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            EpochRewardsInstruction::Initialize,
+            &InitializeInstructionData::new(BASE_RATE, REFERENCE_REWARDS, bump),
+        ),
+        ix_accounts,
+    )
+}
+
+fn instruction_update(schedule: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        ID,
+        &[EpochRewardsInstruction::Update as u8],
+        vec![AccountMeta::new(*schedule, false)],
+    )
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(Schedule::LEN, 72);
+
+    // The syscall copies the `EpochRewards` of the runtime as is.
+    assert_eq!(
+        mem::size_of::<EpochRewards>(),
+        mem::size_of::<solana_epoch_rewards::EpochRewards>()
+    );
+    assert_eq!(
+        mem::align_of::<EpochRewards>(),
+        mem::align_of::<solana_epoch_rewards::EpochRewards>()
+    );
+    let expected = solana_epoch_rewards::EpochRewards {
+        distribution_starting_block_height: 1,
+        num_partitions: 2,
+        total_points: 3,
+        total_rewards: 4,
+        distributed_rewards: 5,
+        active: true,
+        ..Default::default()
+    };
+    let epoch_rewards: EpochRewards = unsafe { mem::transmute_copy(&expected) };
+    assert_eq!(epoch_rewards.distribution_starting_block_height, 1);
+    assert_eq!(epoch_rewards.num_partitions, 2);
+    assert_eq!(epoch_rewards.total_points, 3);
+    assert_eq!(epoch_rewards.total_rewards, 4);
+    assert_eq!(epoch_rewards.distributed_rewards, 5);
+    assert!(epoch_rewards.active);
+}
+
+#[test]
+fn test_initialize() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let (schedule, bump) =
+        Pubkey::find_program_address(&[SCHEDULE_SEED.as_bytes(), authority.as_ref()], &ID);
+
+    mollusk.process_and_validate_instruction(
+        &instruction_initialize(&authority, &schedule, bump),
+        &[
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (schedule, Account::default()),
+            keyed_account_for_system_program(),
+        ],
+        &[
+            Check::success(),
+            Check::account(&schedule)
+                .owner(&ID)
+                .data(&schedule_account(&mollusk, &authority, EPOCH, bump).data)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_update() {
+    let mut mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let (schedule, bump) =
+        Pubkey::find_program_address(&[SCHEDULE_SEED.as_bytes(), authority.as_ref()], &ID);
+    let accounts = [(
+        schedule,
+        schedule_account(&mollusk, &authority, EPOCH, bump),
+    )];
+
+    // Half of the reference rewards, half of the base rate.
+    set_epoch_rewards(&mut mollusk, REFERENCE_REWARDS / 2);
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_update(&schedule),
+                &[
+                    Check::success(),
+                    Check::account(&schedule)
+                        .data_slice(RATE_OFFSET, &(BASE_RATE / 2).to_le_bytes())
+                        .data_slice(NEXT_EPOCH_OFFSET, &(EPOCH + 1).to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_update(&schedule),
+                &[Check::err(ProgramError::Custom(ALREADY_UPDATED))],
+            ),
+        ],
+        &accounts,
+    );
+    let accounts = [(schedule, res.get_account(&schedule).unwrap().clone())];
+
+    // Three times the reference rewards in the next epoch.
+    mollusk.sysvars.clock.epoch = EPOCH + 1;
+    set_epoch_rewards(&mut mollusk, REFERENCE_REWARDS * 3);
+    mollusk.process_and_validate_instruction(
+        &instruction_update(&schedule),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&schedule)
+                .data_slice(RATE_OFFSET, &(BASE_RATE * 3).to_le_bytes())
+                .data_slice(NEXT_EPOCH_OFFSET, &(EPOCH + 2).to_le_bytes())
+                .build(),
+        ],
+    );
+
+    // Epochs without rewards keep the previous rate.
+    set_epoch_rewards(&mut mollusk, 0);
+    mollusk.process_and_validate_instruction(
+        &instruction_update(&schedule),
+        &accounts,
+        &[Check::err(ProgramError::Custom(NO_REWARDS))],
+    );
+}
@@ -0,0 +1,242 @@
+//! Error codes of the example programs.
+//!
+//! Every program gets its own [`Namespace`] of [`NAMESPACE_LEN`] custom
+//! error codes, so a code alone tells which program returned it and clients
+//! can map it to a message with [`Namespace::decode`]. Namespaces are never
+//! renumbered, new programs get the next free one.
+//!
+//! Errors that aren't specific to a single program are in [`SharedError`].
+
+#![no_std]
+
+use pinocchio::program_error::ProgramError;
+
+/// Number of error codes in a namespace.
+pub const NAMESPACE_LEN: u32 = 0x100;
+
+/// Range of custom error codes owned by a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Namespace {
+    /// Codes of SPL Token, which `mini-token` keeps so that clients of SPL
+    /// Token decode its errors.
+    Token = 0,
+    /// [`SharedError`].
+    Shared = 1,
+    /// `attestations`.
+    Attestations = 2,
+    /// `bonding-curve`.
+    BondingCurve = 3,
+    /// `checkers`.
+    Checkers = 4,
+    /// `compressed-state`.
+    CompressedState = 5,
+    /// `compressed-tree`.
+    CompressedTree = 6,
+    /// `confidential-transfer`.
+    ConfidentialTransfer = 7,
+    /// `counter`.
+    Counter = 8,
+    /// `counter-reader`.
+    CounterReader = 9,
+    /// `cpi-guard`.
+    CpiGuard = 10,
+    /// `default-account-state`.
+    DefaultAccountState = 11,
+    /// `durable-nonce`.
+    DurableNonce = 12,
+    /// `energy`.
+    Energy = 13,
+    /// `epoch-emission`.
+    EpochEmission = 14,
+    /// `epoch-rewards`.
+    EpochRewards = 15,
+    /// `escrow`.
+    Escrow = 16,
+    /// `event-queue`.
+    EventQueue = 17,
+    /// `fixed-point-demo`.
+    FixedPointDemo = 18,
+    /// `governance-cpi`.
+    GovernanceCpi = 19,
+    /// `groth16`.
+    Groth16 = 20,
+    /// `hashing`.
+    Hashing = 21,
+    /// `hello-world`.
+    HelloWorld = 22,
+    /// `interest-bearing`.
+    InterestBearing = 23,
+    /// `kv-store`.
+    KvStore = 24,
+    /// `launchpad`.
+    Launchpad = 25,
+    /// `lockup`.
+    Lockup = 26,
+    /// `lookup-tables`.
+    LookupTables = 27,
+    /// `marketplace`.
+    Marketplace = 28,
+    /// `memo`.
+    Memo = 29,
+    /// `merkle-proof`.
+    MerkleProof = 30,
+    /// `nested-pda`.
+    NestedPda = 31,
+    /// `noop`.
+    Noop = 32,
+    /// `noop-caller`.
+    NoopCaller = 33,
+    /// `passkey-vault`.
+    PasskeyVault = 34,
+    /// `permanent-delegate`.
+    PermanentDelegate = 35,
+    /// `poseidon`.
+    Poseidon = 36,
+    /// `program-config`.
+    ProgramConfig = 37,
+    /// `proof-of-recency`.
+    ProofOfRecency = 38,
+    /// `quadratic-voting`.
+    QuadraticVoting = 39,
+    /// `randomness`.
+    Randomness = 40,
+    /// `rbac`.
+    Rbac = 41,
+    /// `referral`.
+    Referral = 42,
+    /// `royalties`.
+    Royalties = 43,
+    /// `soulbound`.
+    Soulbound = 44,
+    /// `stake-manager`.
+    StakeManager = 45,
+    /// `swap-desk`.
+    SwapDesk = 46,
+    /// `system-tour`.
+    SystemTour = 47,
+    /// `token-multisig`.
+    TokenMultisig = 48,
+    /// `twap`.
+    Twap = 49,
+    /// `upgrade-manager`.
+    UpgradeManager = 50,
+    /// `wide-math`.
+    WideMath = 51,
+    /// `leaderboard`.
+    Leaderboard = 52,
+    /// `bond-registry`.
+    BondRegistry = 53,
+    /// `cpi-depth-guard`.
+    CpiDepthGuard = 54,
+}
+
+impl Namespace {
+    /// All namespaces, in order.
+    pub const ALL: [Self; 55] = [
+        Self::Token,
+        Self::Shared,
+        Self::Attestations,
+        Self::BondingCurve,
+        Self::Checkers,
+        Self::CompressedState,
+        Self::CompressedTree,
+        Self::ConfidentialTransfer,
+        Self::Counter,
+        Self::CounterReader,
+        Self::CpiGuard,
+        Self::DefaultAccountState,
+        Self::DurableNonce,
+        Self::Energy,
+        Self::EpochEmission,
+        Self::EpochRewards,
+        Self::Escrow,
+        Self::EventQueue,
+        Self::FixedPointDemo,
+        Self::GovernanceCpi,
+        Self::Groth16,
+        Self::Hashing,
+        Self::HelloWorld,
+        Self::InterestBearing,
+        Self::KvStore,
+        Self::Launchpad,
+        Self::Lockup,
+        Self::LookupTables,
+        Self::Marketplace,
+        Self::Memo,
+        Self::MerkleProof,
+        Self::NestedPda,
+        Self::Noop,
+        Self::NoopCaller,
+        Self::PasskeyVault,
+        Self::PermanentDelegate,
+        Self::Poseidon,
+        Self::ProgramConfig,
+        Self::ProofOfRecency,
+        Self::QuadraticVoting,
+        Self::Randomness,
+        Self::Rbac,
+        Self::Referral,
+        Self::Royalties,
+        Self::Soulbound,
+        Self::StakeManager,
+        Self::SwapDesk,
+        Self::SystemTour,
+        Self::TokenMultisig,
+        Self::Twap,
+        Self::UpgradeManager,
+        Self::WideMath,
+        Self::Leaderboard,
+        Self::BondRegistry,
+        Self::CpiDepthGuard,
+    ];
+
+    /// Returns the first error code of the namespace.
+    pub const fn base(self) -> u32 {
+        self as u32 * NAMESPACE_LEN
+    }
+
+    /// Returns the custom error code of the `error`-th error of the
+    /// namespace.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `error` doesn't fit in the namespace.
+    pub const fn code(self, error: u32) -> u32 {
+        assert!(error < NAMESPACE_LEN, "error code out of namespace");
+        self.base() + error
+    }
+
+    /// Returns the `error`-th error of the namespace as a [`ProgramError`].
+    pub const fn error(self, error: u32) -> ProgramError {
+        ProgramError::Custom(self.code(error))
+    }
+
+    /// Splits a custom error code into its namespace and the error within
+    /// it. Returns `None` for codes outside of all namespaces.
+    pub fn decode(code: u32) -> Option<(Self, u32)> {
+        let namespace = *Self::ALL.get((code / NAMESPACE_LEN) as usize)?;
+        Some((namespace, code % NAMESPACE_LEN))
+    }
+}
+
+/// Errors returned by more than one program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SharedError {
+    /// The amount is zero or otherwise out of range.
+    InvalidAmount,
+    /// An arithmetic operation overflowed.
+    Overflow,
+    /// The same account is passed in more than one role.
+    DuplicateAccount,
+    /// An account which only a program can sign for, e.g. a PDA, is marked
+    /// as a signer of the instruction.
+    UnexpectedSigner,
+}
+
+impl From<SharedError> for ProgramError {
+    fn from(e: SharedError) -> Self {
+        Namespace::Shared.error(e as u32)
+    }
+}
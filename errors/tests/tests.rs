@@ -0,0 +1,46 @@
+use errors::{Namespace, SharedError, NAMESPACE_LEN};
+use pinocchio::program_error::ProgramError;
+
+#[test]
+fn test_namespaces_never_collide() {
+    // Namespaces are numbered consecutively, so their ranges of codes are
+    // disjoint and every code decodes to exactly one of them.
+    for (i, namespace) in Namespace::ALL.into_iter().enumerate() {
+        assert_eq!(namespace as usize, i);
+        assert_eq!(namespace.base(), i as u32 * NAMESPACE_LEN);
+    }
+}
+
+#[test]
+fn test_decode() {
+    for namespace in Namespace::ALL {
+        for error in [0, 1, NAMESPACE_LEN - 1] {
+            assert_eq!(
+                Namespace::decode(namespace.code(error)),
+                Some((namespace, error))
+            );
+        }
+    }
+    let last = Namespace::ALL[Namespace::ALL.len() - 1];
+    assert_eq!(Namespace::decode(last.base() + NAMESPACE_LEN), None);
+    assert_eq!(Namespace::decode(u32::MAX), None);
+}
+
+#[test]
+#[should_panic(expected = "error code out of namespace")]
+fn test_code_out_of_namespace() {
+    Namespace::Counter.code(NAMESPACE_LEN);
+}
+
+#[test]
+fn test_shared_error() {
+    assert_eq!(Namespace::Token.error(1), ProgramError::Custom(1));
+    assert_eq!(
+        ProgramError::from(SharedError::Overflow),
+        ProgramError::Custom(NAMESPACE_LEN + 1)
+    );
+    assert_eq!(
+        ProgramError::from(SharedError::DuplicateAccount),
+        ProgramError::Custom(NAMESPACE_LEN + 2)
+    );
+}
@@ -0,0 +1,561 @@
+//! Accounts of the escrow instructions.
+//!
+//! Every instruction takes its accounts as a struct with a field per
+//! account, converted into the metas of the instruction in the order the
+//! program expects them. [`AccountsBuilder`] derives the escrow PDA and the
+//! associated token accounts of a mint, so most clients never write an
+//! address besides the sender, the receiver and the mint. The escrowed
+//! tokens are SPL Token ones, unless the builder is set to Token-2022.
+
+use solana_instruction::AccountMeta;
+use solana_pubkey::{pubkey, Pubkey};
+use solana_sdk_ids::system_program;
+
+use crate::{find_escrow_address, TOKEN_PROGRAM_ID};
+
+/// Address of the associated token account program.
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWWjeFNtiiZdpPbVm7kJL");
+
+/// Returns the associated token account of `owner` for `mint`, a mint of
+/// the SPL Token program.
+pub fn find_associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    find_associated_token_address_with_program(owner, mint, &TOKEN_PROGRAM_ID)
+}
+
+/// Returns the associated token account of `owner` for `mint`, a mint of
+/// `token_program`.
+pub fn find_associated_token_address_with_program(
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Accounts of [`crate::EscrowInstruction::Initialize`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InitializeAccounts {
+    /// Sender and payer.
+    pub sender: Pubkey,
+    /// Token account of the sender, holding the deposited tokens.
+    pub sender_ata: Pubkey,
+    /// Receiver of the escrowed tokens.
+    pub receiver: Pubkey,
+    /// Escrow PDA, created by the instruction.
+    pub escrow: Pubkey,
+    /// Token account of the escrow, receiving the deposited tokens.
+    pub escrow_ata: Pubkey,
+    /// Token program of the mint.
+    pub token_program: Pubkey,
+}
+
+impl InitializeAccounts {
+    /// Returns the metas of the accounts, followed by the system and token
+    /// programs.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.sender, true),
+            AccountMeta::new(self.sender_ata, false),
+            AccountMeta::new(self.receiver, false),
+            AccountMeta::new(self.escrow, false),
+            AccountMeta::new(self.escrow_ata, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(self.token_program, false),
+        ]
+    }
+}
+
+/// Accounts of [`crate::EscrowInstruction::InitializeChecked`] and
+/// [`crate::EscrowInstruction::InitializeNft`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InitializeCheckedAccounts {
+    /// Sender and payer.
+    pub sender: Pubkey,
+    /// Token account of the sender, holding the deposited tokens.
+    pub sender_ata: Pubkey,
+    /// Receiver of the escrowed tokens.
+    pub receiver: Pubkey,
+    /// Escrow PDA, created by the instruction.
+    pub escrow: Pubkey,
+    /// Token account of the escrow, receiving the deposited tokens.
+    pub escrow_ata: Pubkey,
+    /// Mint of the escrowed tokens.
+    pub mint: Pubkey,
+    /// Token program of the mint.
+    pub token_program: Pubkey,
+}
+
+impl InitializeCheckedAccounts {
+    /// Returns the metas of the accounts, followed by the system and token
+    /// programs.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.sender, true),
+            AccountMeta::new(self.sender_ata, false),
+            AccountMeta::new(self.receiver, false),
+            AccountMeta::new(self.escrow, false),
+            AccountMeta::new(self.escrow_ata, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(self.token_program, false),
+        ]
+    }
+}
+
+/// Accounts of [`crate::EscrowInstruction::Exchange`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExchangeAccounts {
+    /// Sender of the escrow.
+    pub sender: Pubkey,
+    /// Receiver of the escrowed tokens.
+    pub receiver: Pubkey,
+    /// Token account of the receiver, receiving the escrowed tokens.
+    pub receiver_ata: Pubkey,
+    /// Escrow PDA.
+    pub escrow: Pubkey,
+    /// Token account of the escrow, holding the escrowed tokens.
+    pub escrow_ata: Pubkey,
+    /// Token program of the mint.
+    pub token_program: Pubkey,
+}
+
+impl ExchangeAccounts {
+    /// Returns the metas of the accounts, followed by the token program.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.sender, false),
+            AccountMeta::new(self.receiver, true),
+            AccountMeta::new(self.receiver_ata, false),
+            AccountMeta::new(self.escrow, false),
+            AccountMeta::new(self.escrow_ata, false),
+            AccountMeta::new_readonly(self.token_program, false),
+        ]
+    }
+}
+
+/// Accounts of [`crate::EscrowInstruction::Cancel`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CancelAccounts {
+    /// Sender of the escrow.
+    pub sender: Pubkey,
+    /// Token account of the sender, receiving the refunded tokens.
+    pub sender_ata: Pubkey,
+    /// Receiver of the escrow.
+    pub receiver: Pubkey,
+    /// Escrow PDA.
+    pub escrow: Pubkey,
+    /// Token account of the escrow, holding the escrowed tokens.
+    pub escrow_ata: Pubkey,
+    /// Token program of the mint.
+    pub token_program: Pubkey,
+    /// Token account of the receiver, paid the penalty if the receiver
+    /// accepted the escrow.
+    pub receiver_ata: Pubkey,
+}
+
+impl CancelAccounts {
+    /// Returns the metas of the accounts, with the token program followed
+    /// by the token account of the receiver.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.sender, true),
+            AccountMeta::new(self.sender_ata, false),
+            AccountMeta::new(self.receiver, false),
+            AccountMeta::new(self.escrow, false),
+            AccountMeta::new(self.escrow_ata, false),
+            AccountMeta::new_readonly(self.token_program, false),
+            AccountMeta::new(self.receiver_ata, false),
+        ]
+    }
+}
+
+/// Accounts of [`crate::EscrowInstruction::Accept`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcceptAccounts {
+    /// Sender of the escrow.
+    pub sender: Pubkey,
+    /// Receiver of the escrowed tokens, accepting the escrow.
+    pub receiver: Pubkey,
+    /// Escrow PDA.
+    pub escrow: Pubkey,
+}
+
+impl AcceptAccounts {
+    /// Returns the metas of the accounts.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(self.sender, false),
+            AccountMeta::new_readonly(self.receiver, true),
+            AccountMeta::new(self.escrow, false),
+        ]
+    }
+}
+
+/// Accounts of [`crate::EscrowInstruction::InitializeDelegated`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InitializeDelegatedAccounts {
+    /// Sender and payer.
+    pub sender: Pubkey,
+    /// Token account of the sender, delegating the escrowed tokens.
+    pub sender_ata: Pubkey,
+    /// Receiver of the escrowed tokens.
+    pub receiver: Pubkey,
+    /// Escrow PDA, created by the instruction.
+    pub escrow: Pubkey,
+    /// Token program of the mint.
+    pub token_program: Pubkey,
+}
+
+impl InitializeDelegatedAccounts {
+    /// Returns the metas of the accounts, followed by the system and token
+    /// programs.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.sender, true),
+            AccountMeta::new(self.sender_ata, false),
+            AccountMeta::new_readonly(self.receiver, false),
+            AccountMeta::new(self.escrow, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(self.token_program, false),
+        ]
+    }
+}
+
+/// Accounts of [`crate::EscrowInstruction::CancelDelegated`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CancelDelegatedAccounts {
+    /// Sender of the escrow.
+    pub sender: Pubkey,
+    /// Token account of the sender, delegating the escrowed tokens.
+    pub sender_ata: Pubkey,
+    /// Receiver of the escrow.
+    pub receiver: Pubkey,
+    /// Escrow PDA.
+    pub escrow: Pubkey,
+    /// Token program of the mint.
+    pub token_program: Pubkey,
+    /// Token account of the receiver, paid the penalty if the receiver
+    /// accepted the escrow.
+    pub receiver_ata: Pubkey,
+}
+
+impl CancelDelegatedAccounts {
+    /// Returns the metas of the accounts, with the token program followed
+    /// by the token account of the receiver.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.sender, true),
+            AccountMeta::new(self.sender_ata, false),
+            AccountMeta::new_readonly(self.receiver, false),
+            AccountMeta::new_readonly(self.escrow, false),
+            AccountMeta::new_readonly(self.token_program, false),
+            AccountMeta::new(self.receiver_ata, false),
+        ]
+    }
+}
+
+/// Accounts of [`crate::EscrowInstruction::RefundExpired`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RefundExpiredAccounts {
+    /// Sender of the escrow, receiving the rent of the escrow PDA.
+    pub sender: Pubkey,
+    /// Token account of the sender, receiving the refunded tokens.
+    pub sender_ata: Pubkey,
+    /// Receiver of the escrow.
+    pub receiver: Pubkey,
+    /// Escrow PDA.
+    pub escrow: Pubkey,
+    /// Token account of the escrow, holding the escrowed tokens.
+    pub escrow_ata: Pubkey,
+    /// Token program of the mint.
+    pub token_program: Pubkey,
+}
+
+impl RefundExpiredAccounts {
+    /// Returns the metas of the accounts, followed by the token program.
+    /// None of them signs, the fee payer can be anyone.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.sender, false),
+            AccountMeta::new(self.sender_ata, false),
+            AccountMeta::new_readonly(self.receiver, false),
+            AccountMeta::new(self.escrow, false),
+            AccountMeta::new(self.escrow_ata, false),
+            AccountMeta::new_readonly(self.token_program, false),
+        ]
+    }
+}
+
+/// Accounts of an escrow of [`crate::EscrowInstruction::ExchangeBatch`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchEscrowAccounts {
+    /// Sender of the escrow.
+    pub sender: Pubkey,
+    /// Escrow PDA.
+    pub escrow: Pubkey,
+    /// Token account of the escrow, holding the escrowed tokens.
+    pub escrow_ata: Pubkey,
+}
+
+/// Accounts of [`crate::EscrowInstruction::ExchangeBatch`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExchangeBatchAccounts {
+    /// Receiver of the escrowed tokens of every escrow.
+    pub receiver: Pubkey,
+    /// Token account of the receiver, receiving the escrowed tokens.
+    pub receiver_ata: Pubkey,
+    /// Token program of the mint.
+    pub token_program: Pubkey,
+    /// Escrows to release, in order.
+    pub escrows: Vec<BatchEscrowAccounts>,
+}
+
+impl ExchangeBatchAccounts {
+    /// Returns the accounts of a batch of `exchanges`, e.g. returned by
+    /// [`AccountsBuilder::exchange`].
+    ///
+    /// Panics unless there is at least one exchange and all of them have the
+    /// same receiver, token account of the receiver and token program.
+    pub fn new(exchanges: &[ExchangeAccounts]) -> Self {
+        let first = exchanges.first().expect("a batch needs an exchange");
+        assert!(
+            exchanges.iter().all(|exchange| exchange.receiver == first.receiver
+                && exchange.receiver_ata == first.receiver_ata
+                && exchange.token_program == first.token_program),
+            "the exchanges of a batch have to share the receiver, its token account and the token program"
+        );
+        Self {
+            receiver: first.receiver,
+            receiver_ata: first.receiver_ata,
+            token_program: first.token_program,
+            escrows: exchanges
+                .iter()
+                .map(|exchange| BatchEscrowAccounts {
+                    sender: exchange.sender,
+                    escrow: exchange.escrow,
+                    escrow_ata: exchange.escrow_ata,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the metas of the receiver, its token account and the token
+    /// program, followed by the ones of each escrow.
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let mut metas = vec![
+            AccountMeta::new_readonly(self.receiver, true),
+            AccountMeta::new(self.receiver_ata, false),
+            AccountMeta::new_readonly(self.token_program, false),
+        ];
+        for escrow in &self.escrows {
+            metas.extend([
+                AccountMeta::new_readonly(escrow.sender, false),
+                AccountMeta::new(escrow.escrow, false),
+                AccountMeta::new(escrow.escrow_ata, false),
+            ]);
+        }
+        metas
+    }
+}
+
+/// Builds the accounts of the instructions of an escrow of `mint` tokens
+/// between a sender and a receiver.
+///
+/// The escrow is always the PDA of the sender and the receiver. The token
+/// accounts are the associated token accounts of their owners, unless set.
+#[derive(Clone, Copy, Debug)]
+pub struct AccountsBuilder {
+    sender: Pubkey,
+    receiver: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+    sender_ata: Option<Pubkey>,
+    receiver_ata: Option<Pubkey>,
+    escrow_ata: Option<Pubkey>,
+}
+
+impl AccountsBuilder {
+    /// Creates a builder of the accounts of the escrow of `mint` tokens from
+    /// `sender` to `receiver`.
+    pub fn new(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Self {
+        Self {
+            sender: *sender,
+            receiver: *receiver,
+            mint: *mint,
+            token_program: TOKEN_PROGRAM_ID,
+            sender_ata: None,
+            receiver_ata: None,
+            escrow_ata: None,
+        }
+    }
+
+    /// Sets the token program of the mint, e.g. to
+    /// [`crate::TOKEN_2022_PROGRAM_ID`].
+    pub fn token_program(mut self, token_program: &Pubkey) -> Self {
+        self.token_program = *token_program;
+        self
+    }
+
+    /// Sets the token account of the sender.
+    pub fn sender_ata(mut self, sender_ata: &Pubkey) -> Self {
+        self.sender_ata = Some(*sender_ata);
+        self
+    }
+
+    /// Sets the token account of the receiver.
+    pub fn receiver_ata(mut self, receiver_ata: &Pubkey) -> Self {
+        self.receiver_ata = Some(*receiver_ata);
+        self
+    }
+
+    /// Sets the token account of the escrow.
+    pub fn escrow_ata(mut self, escrow_ata: &Pubkey) -> Self {
+        self.escrow_ata = Some(*escrow_ata);
+        self
+    }
+
+    /// Returns the accounts of an initialization of the escrow.
+    pub fn initialize(&self) -> InitializeAccounts {
+        let (escrow, escrow_ata) = self.resolve_escrow();
+        InitializeAccounts {
+            sender: self.sender,
+            sender_ata: self.resolve_sender_ata(),
+            receiver: self.receiver,
+            escrow,
+            escrow_ata,
+            token_program: self.token_program,
+        }
+    }
+
+    /// Returns the accounts of an initialization of the escrow checking the
+    /// decimals of the amount.
+    pub fn initialize_checked(&self) -> InitializeCheckedAccounts {
+        let accounts = self.initialize();
+        InitializeCheckedAccounts {
+            sender: accounts.sender,
+            sender_ata: accounts.sender_ata,
+            receiver: accounts.receiver,
+            escrow: accounts.escrow,
+            escrow_ata: accounts.escrow_ata,
+            mint: self.mint,
+            token_program: accounts.token_program,
+        }
+    }
+
+    /// Returns the accounts of an exchange of the escrow.
+    pub fn exchange(&self) -> ExchangeAccounts {
+        let (escrow, escrow_ata) = self.resolve_escrow();
+        ExchangeAccounts {
+            sender: self.sender,
+            receiver: self.receiver,
+            receiver_ata: self.resolve_receiver_ata(),
+            escrow,
+            escrow_ata,
+            token_program: self.token_program,
+        }
+    }
+
+    /// Returns the accounts of a cancellation of the escrow.
+    pub fn cancel(&self) -> CancelAccounts {
+        let (escrow, escrow_ata) = self.resolve_escrow();
+        CancelAccounts {
+            sender: self.sender,
+            sender_ata: self.resolve_sender_ata(),
+            receiver: self.receiver,
+            escrow,
+            escrow_ata,
+            token_program: self.token_program,
+            receiver_ata: self.resolve_receiver_ata(),
+        }
+    }
+
+    /// Returns the accounts of an initialization of the escrow as a
+    /// delegated escrow, whose tokens stay in the token account of the
+    /// sender.
+    pub fn initialize_delegated(&self) -> InitializeDelegatedAccounts {
+        let (escrow, _) = find_escrow_address(&self.sender, &self.receiver);
+        InitializeDelegatedAccounts {
+            sender: self.sender,
+            sender_ata: self.resolve_sender_ata(),
+            receiver: self.receiver,
+            escrow,
+            token_program: self.token_program,
+        }
+    }
+
+    /// Returns the accounts of an exchange of the delegated escrow, which
+    /// releases the tokens out of the token account of the sender.
+    pub fn exchange_delegated(&self) -> ExchangeAccounts {
+        ExchangeAccounts {
+            escrow_ata: self.resolve_sender_ata(),
+            ..self.exchange()
+        }
+    }
+
+    /// Returns the accounts of a cancellation of the delegated escrow.
+    pub fn cancel_delegated(&self) -> CancelDelegatedAccounts {
+        let (escrow, _) = find_escrow_address(&self.sender, &self.receiver);
+        CancelDelegatedAccounts {
+            sender: self.sender,
+            sender_ata: self.resolve_sender_ata(),
+            receiver: self.receiver,
+            escrow,
+            token_program: self.token_program,
+            receiver_ata: self.resolve_receiver_ata(),
+        }
+    }
+
+    /// Returns the accounts of an acceptance of the escrow.
+    pub fn accept(&self) -> AcceptAccounts {
+        let (escrow, _) = find_escrow_address(&self.sender, &self.receiver);
+        AcceptAccounts {
+            sender: self.sender,
+            receiver: self.receiver,
+            escrow,
+        }
+    }
+
+    /// Returns the accounts of a refund of the escrow after it expired.
+    pub fn refund_expired(&self) -> RefundExpiredAccounts {
+        let (escrow, escrow_ata) = self.resolve_escrow();
+        RefundExpiredAccounts {
+            sender: self.sender,
+            sender_ata: self.resolve_sender_ata(),
+            receiver: self.receiver,
+            escrow,
+            escrow_ata,
+            token_program: self.token_program,
+        }
+    }
+
+    /// Returns the escrow PDA and its token account.
+    fn resolve_escrow(&self) -> (Pubkey, Pubkey) {
+        let (escrow, _) = find_escrow_address(&self.sender, &self.receiver);
+        let escrow_ata = self
+            .escrow_ata
+            .unwrap_or_else(|| self.find_associated_token_address(&escrow));
+        (escrow, escrow_ata)
+    }
+
+    /// Returns the token account of the sender.
+    fn resolve_sender_ata(&self) -> Pubkey {
+        self.sender_ata
+            .unwrap_or_else(|| self.find_associated_token_address(&self.sender))
+    }
+
+    /// Returns the token account of the receiver.
+    fn resolve_receiver_ata(&self) -> Pubkey {
+        self.receiver_ata
+            .unwrap_or_else(|| self.find_associated_token_address(&self.receiver))
+    }
+
+    /// Returns the associated token account of `owner` for the mint.
+    fn find_associated_token_address(&self, owner: &Pubkey) -> Pubkey {
+        find_associated_token_address_with_program(owner, &self.mint, &self.token_program)
+    }
+}
@@ -0,0 +1,693 @@
+//! Client of the escrow program.
+//!
+//! Builds its instructions from the typed [`accounts`] of each of them and
+//! decodes its accounts off-chain. [`TransactionBuilder`] wraps them into v0
+//! transactions, e.g. with a priority fee. The events logged by the program
+//! are re-exported to decode them from transaction logs. With the `rpc`
+//! feature, [`rpc`] lists its accounts with `getProgramAccounts`. With the
+//! `wasm` feature, [`wasm`] exports it to JavaScript. The client builds for
+//! `wasm32-unknown-unknown` without the `rpc` feature.
+//!
+//! Amounts are in the smallest unit of the mint. [`initialize_ui_amount`]
+//! takes one as shown to users instead, e.g. `1.5`, converted with the
+//! decimals of the mint, which the program checks against the mint.
+
+use escrow::{
+    metadata::{METADATA_PROGRAM_ID, METADATA_SEED},
+    FinalizeInstructionData, FinalizeInstructionDataV2, InitializeCheckedInstructionData,
+    InitializeInstructionData, InitializeInstructionDataV2, InitializeInstructionDataV3,
+    InitializeInstructionDataV4, InitializeNftInstructionData,
+};
+use pinocchio::program_error::ProgramError;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+pub mod accounts;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub mod wasm;
+
+pub use accounts::{
+    AcceptAccounts, AccountsBuilder, BatchEscrowAccounts, CancelAccounts, CancelDelegatedAccounts,
+    ExchangeAccounts, ExchangeBatchAccounts, InitializeAccounts, InitializeCheckedAccounts,
+    InitializeDelegatedAccounts, RefundExpiredAccounts,
+};
+pub use client_utils::{
+    ui_amount::{amount_to_ui_amount, ui_amount_to_amount, UiAmountError},
+    TransactionBuilder,
+};
+pub use common::version::Version;
+pub use escrow::{
+    Escrow, EscrowAccepted, EscrowCancelled, EscrowExchanged, EscrowInitialized, EscrowInstruction,
+    ESCROW_SEED, EXCHANGE_COMPUTE_UNITS, MAX_BATCH_ESCROWS, MAX_PENALTY_BPS,
+};
+
+/// Address of the escrow program.
+pub const ID: Pubkey = Pubkey::new_from_array(escrow::ID);
+/// Address of the SPL Token program, the default token program of the
+/// escrowed tokens.
+pub const TOKEN_PROGRAM_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+/// Address of the Token-2022 program, which the escrow supports as well.
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = Pubkey::new_from_array(escrow::token::TOKEN_2022_ID);
+/// Address of the Metaplex Token Metadata program, owning the metadata
+/// accounts of NFTs.
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey = Pubkey::new_from_array(METADATA_PROGRAM_ID);
+
+/// Length of a mint without extensions.
+const MINT_LEN: usize = 82;
+/// Offset of the decimals in a mint.
+const MINT_DECIMALS_OFFSET: usize = 44;
+/// Offset of the initialization flag in a mint.
+const MINT_IS_INITIALIZED_OFFSET: usize = 45;
+
+/// Returns the escrow PDA between `sender` and `receiver` and its bump.
+pub fn find_escrow_address(sender: &Pubkey, receiver: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ESCROW_SEED.as_bytes(), sender.as_ref(), receiver.as_ref()],
+        &ID,
+    )
+}
+
+/// Returns the metadata account of `mint`, e.g. an NFT.
+pub fn find_metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            METADATA_SEED.as_bytes(),
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Arguments of an escrow instruction, which [`InstructionArgs::encode`]
+/// encodes into its instruction data and [`InstructionArgs::decode`]
+/// decodes from it.
+///
+/// The data is the discriminator of the instruction and its [`Version`],
+/// followed by its fields, little-endian and without padding, so its format
+/// doesn't depend on the layout of any Rust struct.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstructionArgs {
+    /// Deposits `amount` tokens into the escrow, whose PDA has `bump`.
+    Initialize { amount: u64, bump: u8 },
+    /// Releases the escrowed tokens of the escrow, whose PDA has `bump`.
+    Exchange { bump: u8 },
+    /// Refunds the escrowed tokens of the escrow, whose PDA has `bump`.
+    Cancel { bump: u8 },
+    /// Version 2 of [`EscrowInstruction::Exchange`], which fails unless
+    /// `amount` tokens are escrowed.
+    ExchangeV2 { bump: u8, amount: u64 },
+    /// Version 2 of [`EscrowInstruction::Cancel`], which fails unless
+    /// `amount` tokens are escrowed.
+    CancelV2 { bump: u8, amount: u64 },
+    /// Version 2 of [`EscrowInstruction::Initialize`], with a cancellation
+    /// penalty of `penalty_bps` basis points.
+    InitializeV2 {
+        amount: u64,
+        bump: u8,
+        penalty_bps: u16,
+    },
+    /// Accepts the escrow, whose PDA has `bump`, as its receiver.
+    Accept { bump: u8 },
+    /// Releases the escrowed tokens of several escrows, whose PDAs have
+    /// `bumps`, to their receiver.
+    ExchangeBatch { bumps: Vec<u8> },
+    /// Delegates `amount` tokens to the escrow, whose PDA has `bump`.
+    InitializeDelegated { amount: u64, bump: u8 },
+    /// Version 2 of [`EscrowInstruction::InitializeDelegated`], with a
+    /// cancellation penalty of `penalty_bps` basis points.
+    InitializeDelegatedV2 {
+        amount: u64,
+        bump: u8,
+        penalty_bps: u16,
+    },
+    /// Cancels the delegated escrow, whose PDA has `bump`.
+    CancelDelegated { bump: u8 },
+    /// Version 2 of [`EscrowInstruction::CancelDelegated`], which fails
+    /// unless `amount` tokens are escrowed.
+    CancelDelegatedV2 { bump: u8, amount: u64 },
+    /// Version 3 of [`EscrowInstruction::Initialize`], whose cancellation
+    /// burns the escrowed tokens instead of refunding them if
+    /// `burn_on_cancel`.
+    InitializeV3 {
+        amount: u64,
+        bump: u8,
+        penalty_bps: u16,
+        burn_on_cancel: bool,
+    },
+    /// Deposits `amount` tokens of a mint with `decimals` into the escrow,
+    /// like [`InstructionArgs::InitializeV3`], failing unless the mint has
+    /// `decimals`.
+    InitializeChecked {
+        amount: u64,
+        decimals: u8,
+        bump: u8,
+        penalty_bps: u16,
+        burn_on_cancel: bool,
+    },
+    /// Deposits the NFT of a mint into the escrow, whose PDA has `bump`,
+    /// failing unless the mint has no decimals and a supply of 1.
+    InitializeNft { bump: u8 },
+    /// Version 4 of [`EscrowInstruction::Initialize`], after whose
+    /// `expiry_slot` anyone can refund the escrowed tokens, unless it's 0.
+    InitializeV4 {
+        amount: u64,
+        bump: u8,
+        penalty_bps: u16,
+        burn_on_cancel: bool,
+        expiry_slot: u64,
+    },
+    /// Refunds the escrowed tokens of the expired escrow, whose PDA has
+    /// `bump`.
+    RefundExpired { bump: u8 },
+}
+
+impl InstructionArgs {
+    /// Encodes the instruction data.
+    pub fn encode(&self) -> Vec<u8> {
+        let (instruction, version, fields) = match *self {
+            Self::Initialize { amount, bump } => (
+                EscrowInstruction::Initialize,
+                Version::V1,
+                InitializeInstructionData::new(amount, bump)
+                    .to_bytes()
+                    .to_vec(),
+            ),
+            Self::Exchange { bump } => (
+                EscrowInstruction::Exchange,
+                Version::V1,
+                FinalizeInstructionData::new(bump).to_bytes().to_vec(),
+            ),
+            Self::Cancel { bump } => (
+                EscrowInstruction::Cancel,
+                Version::V1,
+                FinalizeInstructionData::new(bump).to_bytes().to_vec(),
+            ),
+            Self::ExchangeV2 { bump, amount } => (
+                EscrowInstruction::Exchange,
+                Version::V2,
+                FinalizeInstructionDataV2::new(bump, amount)
+                    .to_bytes()
+                    .to_vec(),
+            ),
+            Self::CancelV2 { bump, amount } => (
+                EscrowInstruction::Cancel,
+                Version::V2,
+                FinalizeInstructionDataV2::new(bump, amount)
+                    .to_bytes()
+                    .to_vec(),
+            ),
+            Self::InitializeV2 {
+                amount,
+                bump,
+                penalty_bps,
+            } => (
+                EscrowInstruction::Initialize,
+                Version::V2,
+                InitializeInstructionDataV2::new(amount, bump, penalty_bps)
+                    .to_bytes()
+                    .to_vec(),
+            ),
+            Self::Accept { bump } => (
+                EscrowInstruction::Accept,
+                Version::V1,
+                FinalizeInstructionData::new(bump).to_bytes().to_vec(),
+            ),
+            Self::ExchangeBatch { ref bumps } => {
+                (EscrowInstruction::ExchangeBatch, Version::V1, bumps.clone())
+            }
+            Self::InitializeDelegated { amount, bump } => (
+                EscrowInstruction::InitializeDelegated,
+                Version::V1,
+                InitializeInstructionData::new(amount, bump)
+                    .to_bytes()
+                    .to_vec(),
+            ),
+            Self::InitializeDelegatedV2 {
+                amount,
+                bump,
+                penalty_bps,
+            } => (
+                EscrowInstruction::InitializeDelegated,
+                Version::V2,
+                InitializeInstructionDataV2::new(amount, bump, penalty_bps)
+                    .to_bytes()
+                    .to_vec(),
+            ),
+            Self::CancelDelegated { bump } => (
+                EscrowInstruction::CancelDelegated,
+                Version::V1,
+                FinalizeInstructionData::new(bump).to_bytes().to_vec(),
+            ),
+            Self::CancelDelegatedV2 { bump, amount } => (
+                EscrowInstruction::CancelDelegated,
+                Version::V2,
+                FinalizeInstructionDataV2::new(bump, amount)
+                    .to_bytes()
+                    .to_vec(),
+            ),
+            Self::InitializeV3 {
+                amount,
+                bump,
+                penalty_bps,
+                burn_on_cancel,
+            } => (
+                EscrowInstruction::Initialize,
+                Version::V3,
+                InitializeInstructionDataV3::new(amount, bump, penalty_bps, burn_on_cancel as u8)
+                    .to_bytes()
+                    .to_vec(),
+            ),
+            Self::InitializeChecked {
+                amount,
+                decimals,
+                bump,
+                penalty_bps,
+                burn_on_cancel,
+            } => (
+                EscrowInstruction::InitializeChecked,
+                Version::V1,
+                InitializeCheckedInstructionData::new(
+                    amount,
+                    decimals,
+                    bump,
+                    penalty_bps,
+                    burn_on_cancel as u8,
+                )
+                .to_bytes()
+                .to_vec(),
+            ),
+            Self::InitializeNft { bump } => (
+                EscrowInstruction::InitializeNft,
+                Version::V1,
+                InitializeNftInstructionData::new(bump).to_bytes().to_vec(),
+            ),
+            Self::InitializeV4 {
+                amount,
+                bump,
+                penalty_bps,
+                burn_on_cancel,
+                expiry_slot,
+            } => (
+                EscrowInstruction::Initialize,
+                Version::V4,
+                InitializeInstructionDataV4::new(
+                    amount,
+                    bump,
+                    penalty_bps,
+                    burn_on_cancel as u8,
+                    expiry_slot,
+                )
+                .to_bytes()
+                .to_vec(),
+            ),
+            Self::RefundExpired { bump } => (
+                EscrowInstruction::RefundExpired,
+                Version::V1,
+                FinalizeInstructionData::new(bump).to_bytes().to_vec(),
+            ),
+        };
+        [instruction.discriminator(version).as_slice(), &fields].concat()
+    }
+
+    /// Decodes instruction data.
+    ///
+    /// Fails with [`ProgramError::InvalidInstructionData`] for an unknown
+    /// discriminator or version, or data of the wrong length, like the
+    /// program.
+    pub fn decode(data: &[u8]) -> Result<Self, ProgramError> {
+        let (instruction, version, fields) = EscrowInstruction::split(data)?;
+        Ok(match (instruction, version) {
+            (EscrowInstruction::Initialize, Version::V1) => {
+                let fields = InitializeInstructionData::from_bytes(fields)?;
+                Self::Initialize {
+                    amount: fields.amount,
+                    bump: fields.bump,
+                }
+            }
+            (EscrowInstruction::Initialize, Version::V2) => {
+                let fields = InitializeInstructionDataV2::from_bytes(fields)?;
+                Self::InitializeV2 {
+                    amount: fields.amount,
+                    bump: fields.bump,
+                    penalty_bps: fields.penalty_bps,
+                }
+            }
+            (EscrowInstruction::Exchange, Version::V1) => Self::Exchange {
+                bump: FinalizeInstructionData::from_bytes(fields)?.bump,
+            },
+            (EscrowInstruction::Cancel, Version::V1) => Self::Cancel {
+                bump: FinalizeInstructionData::from_bytes(fields)?.bump,
+            },
+            (EscrowInstruction::Exchange, Version::V2) => {
+                let fields = FinalizeInstructionDataV2::from_bytes(fields)?;
+                Self::ExchangeV2 {
+                    bump: fields.bump,
+                    amount: fields.amount,
+                }
+            }
+            (EscrowInstruction::Cancel, Version::V2) => {
+                let fields = FinalizeInstructionDataV2::from_bytes(fields)?;
+                Self::CancelV2 {
+                    bump: fields.bump,
+                    amount: fields.amount,
+                }
+            }
+            (EscrowInstruction::Accept, Version::V1) => Self::Accept {
+                bump: FinalizeInstructionData::from_bytes(fields)?.bump,
+            },
+            (EscrowInstruction::Accept, _) => return Err(ProgramError::InvalidInstructionData),
+            (EscrowInstruction::ExchangeBatch, Version::V1) => {
+                if fields.is_empty() || fields.len() > MAX_BATCH_ESCROWS {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                Self::ExchangeBatch {
+                    bumps: fields.to_vec(),
+                }
+            }
+            (EscrowInstruction::ExchangeBatch, _) => {
+                return Err(ProgramError::InvalidInstructionData)
+            }
+            (EscrowInstruction::InitializeDelegated, Version::V1) => {
+                let fields = InitializeInstructionData::from_bytes(fields)?;
+                Self::InitializeDelegated {
+                    amount: fields.amount,
+                    bump: fields.bump,
+                }
+            }
+            (EscrowInstruction::InitializeDelegated, Version::V2) => {
+                let fields = InitializeInstructionDataV2::from_bytes(fields)?;
+                Self::InitializeDelegatedV2 {
+                    amount: fields.amount,
+                    bump: fields.bump,
+                    penalty_bps: fields.penalty_bps,
+                }
+            }
+            (EscrowInstruction::CancelDelegated, Version::V1) => Self::CancelDelegated {
+                bump: FinalizeInstructionData::from_bytes(fields)?.bump,
+            },
+            (EscrowInstruction::CancelDelegated, Version::V2) => {
+                let fields = FinalizeInstructionDataV2::from_bytes(fields)?;
+                Self::CancelDelegatedV2 {
+                    bump: fields.bump,
+                    amount: fields.amount,
+                }
+            }
+            (EscrowInstruction::Initialize, Version::V3) => {
+                let fields = InitializeInstructionDataV3::from_bytes(fields)?;
+                Self::InitializeV3 {
+                    amount: fields.amount,
+                    bump: fields.bump,
+                    penalty_bps: fields.penalty_bps,
+                    burn_on_cancel: flag(fields.burn_on_cancel)?,
+                }
+            }
+            (EscrowInstruction::InitializeChecked, Version::V1) => {
+                let fields = InitializeCheckedInstructionData::from_bytes(fields)?;
+                Self::InitializeChecked {
+                    amount: fields.amount,
+                    decimals: fields.decimals,
+                    bump: fields.bump,
+                    penalty_bps: fields.penalty_bps,
+                    burn_on_cancel: flag(fields.burn_on_cancel)?,
+                }
+            }
+            (EscrowInstruction::InitializeNft, Version::V1) => Self::InitializeNft {
+                bump: InitializeNftInstructionData::from_bytes(fields)?.bump,
+            },
+            (EscrowInstruction::Initialize, Version::V4) => {
+                let fields = InitializeInstructionDataV4::from_bytes(fields)?;
+                Self::InitializeV4 {
+                    amount: fields.amount,
+                    bump: fields.bump,
+                    penalty_bps: fields.penalty_bps,
+                    burn_on_cancel: flag(fields.burn_on_cancel)?,
+                    expiry_slot: fields.expiry_slot,
+                }
+            }
+            (EscrowInstruction::RefundExpired, Version::V1) => Self::RefundExpired {
+                bump: FinalizeInstructionData::from_bytes(fields)?.bump,
+            },
+            _ => return Err(ProgramError::InvalidInstructionData),
+        })
+    }
+}
+
+/// Decodes a flag of instruction data, which is either 0 or 1.
+fn flag(value: u8) -> Result<bool, ProgramError> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+/// Builds an instruction creating the escrow of `accounts` and depositing
+/// `amount` tokens into it.
+pub fn initialize(accounts: &InitializeAccounts, amount: u64) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::Initialize { amount, bump }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction creating the escrow of `accounts` and depositing
+/// `amount` tokens into it, of which `penalty_bps` basis points are paid to
+/// the receiver if the sender cancels after the receiver accepted.
+pub fn initialize_with_penalty(
+    accounts: &InitializeAccounts,
+    amount: u64,
+    penalty_bps: u16,
+) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::InitializeV2 {
+        amount,
+        bump,
+        penalty_bps,
+    }
+    .encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction like [`initialize_with_penalty`], whose
+/// cancellation burns the escrowed tokens, minus the penalty, instead of
+/// refunding them. Cancel it with [`cancel_burning`].
+pub fn initialize_burning_on_cancel(
+    accounts: &InitializeAccounts,
+    amount: u64,
+    penalty_bps: u16,
+) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::InitializeV3 {
+        amount,
+        bump,
+        penalty_bps,
+        burn_on_cancel: true,
+    }
+    .encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction like [`initialize_with_penalty`], after whose
+/// `expiry_slot` anyone can refund the escrowed tokens to the sender with
+/// [`refund_expired`], in case the receiver never exchanges them.
+pub fn initialize_with_expiry(
+    accounts: &InitializeAccounts,
+    amount: u64,
+    penalty_bps: u16,
+    expiry_slot: u64,
+) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::InitializeV4 {
+        amount,
+        bump,
+        penalty_bps,
+        burn_on_cancel: false,
+        expiry_slot,
+    }
+    .encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction creating the escrow of `accounts` and depositing
+/// `amount` tokens of a mint with `decimals` into it, which fails unless the
+/// mint has `decimals`.
+pub fn initialize_checked(
+    accounts: &InitializeCheckedAccounts,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::InitializeChecked {
+        amount,
+        decimals,
+        bump,
+        penalty_bps: 0,
+        burn_on_cancel: false,
+    }
+    .encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction like [`initialize_checked`] depositing
+/// `ui_amount` tokens as shown to users, e.g. `1.5`, of a mint with
+/// `decimals`, e.g. read with [`try_mint_decimals_from_account_data`].
+pub fn initialize_ui_amount(
+    accounts: &InitializeCheckedAccounts,
+    ui_amount: &str,
+    decimals: u8,
+) -> Result<Instruction, UiAmountError> {
+    let amount = ui_amount_to_amount(ui_amount, decimals)?;
+    Ok(initialize_checked(accounts, amount, decimals))
+}
+
+/// Builds an instruction creating the escrow of `accounts` and depositing
+/// the NFT of their mint into it, which fails unless the mint has no
+/// decimals and a supply of 1.
+pub fn initialize_nft(accounts: &InitializeCheckedAccounts) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::InitializeNft { bump }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction accepting the escrow as its receiver.
+pub fn accept(accounts: &AcceptAccounts) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::Accept { bump }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction releasing the escrowed tokens to the receiver.
+pub fn exchange(accounts: &ExchangeAccounts) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::Exchange { bump }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction refunding the escrowed tokens to the sender, minus
+/// the penalty paid to the receiver if it accepted the escrow.
+pub fn cancel(accounts: &CancelAccounts) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::Cancel { bump }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction cancelling an escrow created by
+/// [`initialize_burning_on_cancel`], burning the escrowed tokens of `mint`
+/// after paying the penalty to the receiver if it accepted the escrow.
+pub fn cancel_burning(accounts: &CancelAccounts, mint: &Pubkey) -> Instruction {
+    let mut instruction = cancel(accounts);
+    instruction.accounts.push(AccountMeta::new(*mint, false));
+    instruction
+}
+
+/// Builds an instruction releasing the escrowed tokens of `mint`, e.g. an
+/// NFT, to the receiver, which fails unless the metadata account of `mint`
+/// exists.
+pub fn exchange_verifying_metadata(accounts: &ExchangeAccounts, mint: &Pubkey) -> Instruction {
+    let mut instruction = exchange(accounts);
+    instruction.accounts.push(AccountMeta::new_readonly(
+        find_metadata_address(mint),
+        false,
+    ));
+    instruction
+}
+
+/// Builds an instruction releasing the escrowed tokens to the receiver,
+/// which fails unless `amount` tokens are escrowed.
+pub fn exchange_expecting(accounts: &ExchangeAccounts, amount: u64) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::ExchangeV2 { bump, amount }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction refunding the escrowed tokens to the sender, which
+/// fails unless `amount` tokens are escrowed.
+pub fn cancel_expecting(accounts: &CancelAccounts, amount: u64) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::CancelV2 { bump, amount }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction refunding all the escrowed tokens to the sender
+/// once the escrow expired, which anyone can send.
+pub fn refund_expired(accounts: &RefundExpiredAccounts) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::RefundExpired { bump }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction releasing the escrowed tokens of the escrows of
+/// `accounts` to their receiver, as many as the compute units of the
+/// transaction allow. Its return data tells how many, see
+/// [`exchanged_escrows`].
+pub fn exchange_batch(accounts: &ExchangeBatchAccounts) -> Instruction {
+    let bumps = accounts
+        .escrows
+        .iter()
+        .map(|escrow| find_escrow_address(&escrow.sender, &accounts.receiver).1)
+        .collect();
+    let data = InstructionArgs::ExchangeBatch { bumps }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction creating the delegated escrow of `accounts`, which
+/// only approves it as the delegate of `amount` tokens of the sender. They
+/// stay in the token account of the sender until the exchange, whose
+/// accounts come from [`AccountsBuilder::exchange_delegated`].
+pub fn initialize_delegated(accounts: &InitializeDelegatedAccounts, amount: u64) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::InitializeDelegated { amount, bump }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction like [`initialize_delegated`], of which
+/// `penalty_bps` basis points are paid to the receiver if the sender
+/// cancels after the receiver accepted.
+pub fn initialize_delegated_with_penalty(
+    accounts: &InitializeDelegatedAccounts,
+    amount: u64,
+    penalty_bps: u16,
+) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::InitializeDelegatedV2 {
+        amount,
+        bump,
+        penalty_bps,
+    }
+    .encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Builds an instruction cancelling the delegated escrow of `accounts`,
+/// revoking its delegation after paying the penalty to the receiver if it
+/// accepted the escrow.
+pub fn cancel_delegated(accounts: &CancelDelegatedAccounts) -> Instruction {
+    let (_, bump) = find_escrow_address(&accounts.sender, &accounts.receiver);
+    let data = InstructionArgs::CancelDelegated { bump }.encode();
+    Instruction::new_with_bytes(ID, &data, accounts.to_account_metas())
+}
+
+/// Returns how many escrows an [`EscrowInstruction::ExchangeBatch`]
+/// released, the first ones of the batch, from its return data.
+pub fn exchanged_escrows(return_data: &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(return_data.try_into().ok()?))
+}
+
+/// Decodes the decimals of a mint of either token program from the data of
+/// its account.
+pub fn try_mint_decimals_from_account_data(data: &[u8]) -> Result<u8, ProgramError> {
+    // The base mint is followed by the extensions of Token-2022 mints.
+    match data.get(..MINT_LEN) {
+        Some(mint) if mint[MINT_IS_INITIALIZED_OFFSET] == 1 => Ok(mint[MINT_DECIMALS_OFFSET]),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Decodes an escrow from the data of its account, which may have the
+/// legacy layout of escrows created before the expiry slot was added.
+pub fn try_from_account_data(data: &[u8]) -> Result<Escrow, ProgramError> {
+    Escrow::read_versioned(data)
+}
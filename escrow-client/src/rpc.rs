@@ -0,0 +1,65 @@
+//! Listing of escrows with `getProgramAccounts`.
+
+use core::mem::offset_of;
+
+use common::AccountData;
+use solana_account::Account;
+use solana_pubkey::Pubkey;
+use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+
+use crate::{try_from_account_data, Escrow};
+
+/// Offset of [`Escrow::sender`] in the data of escrows.
+pub const SENDER_OFFSET: usize = Escrow::PREFIX.len() + offset_of!(Escrow, sender);
+/// Offset of [`Escrow::receiver`] in the data of escrows.
+pub const RECEIVER_OFFSET: usize = Escrow::PREFIX.len() + offset_of!(Escrow, receiver);
+
+/// Returns the filters matching the escrows of `sender`.
+pub fn escrows_by_sender(sender: &Pubkey) -> Vec<RpcFilterType> {
+    escrows_by(SENDER_OFFSET, sender)
+}
+
+/// Returns the filters matching the escrows for `receiver`.
+pub fn escrows_by_receiver(receiver: &Pubkey) -> Vec<RpcFilterType> {
+    escrows_by(RECEIVER_OFFSET, receiver)
+}
+
+/// Returns the filters matching all escrows.
+///
+/// The data size isn't filtered on, as escrows created before the expiry
+/// slot was added are [`Escrow::LEGACY_LEN`] bytes long. The program owns no
+/// other accounts, and [`decode_escrows`] skips any other data anyway.
+pub fn escrows() -> Vec<RpcFilterType> {
+    let mut filters = vec![];
+    if !Escrow::PREFIX.is_empty() {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            0,
+            Escrow::PREFIX,
+        )));
+    }
+    filters
+}
+
+/// Returns the filters matching escrows holding `address` at `offset`.
+fn escrows_by(offset: usize, address: &Pubkey) -> Vec<RpcFilterType> {
+    let mut filters = escrows();
+    filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+        offset,
+        address.as_ref(),
+    )));
+    filters
+}
+
+/// Decodes the accounts returned by `getProgramAccounts`, skipping the ones
+/// which aren't escrows.
+pub fn decode_escrows(
+    accounts: impl IntoIterator<Item = (Pubkey, Account)>,
+) -> Vec<(Pubkey, Escrow)> {
+    accounts
+        .into_iter()
+        .filter_map(|(address, account)| {
+            let escrow = try_from_account_data(&account.data).ok()?;
+            Some((address, escrow))
+        })
+        .collect()
+}
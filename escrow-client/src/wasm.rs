@@ -0,0 +1,235 @@
+//! Bindings for browser dApps.
+//!
+//! Build them with `wasm-pack build --target web -- --features wasm`. On
+//! `wasm32`, [`Pubkey`] and [`Instruction`] are exported to JavaScript by
+//! their own crates, so the instructions can be added to transactions built
+//! with them.
+
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+use wasm_bindgen::prelude::*;
+
+use crate::{find_escrow_address, try_from_account_data, AccountsBuilder};
+
+/// Returns the escrow PDA between `sender` and `receiver`.
+#[wasm_bindgen(js_name = escrowAddress)]
+pub fn escrow_address(sender: &Pubkey, receiver: &Pubkey) -> Pubkey {
+    find_escrow_address(sender, receiver).0
+}
+
+/// Builds an instruction creating the escrow of `mint` tokens between
+/// `sender` and `receiver`, and depositing `amount` tokens from the
+/// associated token account of `sender` into the one of the escrow.
+#[wasm_bindgen]
+pub fn initialize(amount: u64, sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::initialize(
+        &AccountsBuilder::new(sender, receiver, mint).initialize(),
+        amount,
+    )
+}
+
+/// Builds an instruction like [`initialize`], of which `penalty_bps` basis
+/// points are paid to `receiver` if `sender` cancels after it accepted.
+#[wasm_bindgen(js_name = initializeWithPenalty)]
+pub fn initialize_with_penalty(
+    amount: u64,
+    penalty_bps: u16,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    crate::initialize_with_penalty(
+        &AccountsBuilder::new(sender, receiver, mint).initialize(),
+        amount,
+        penalty_bps,
+    )
+}
+
+/// Builds an instruction like [`initialize`] depositing `ui_amount` tokens
+/// as shown to users, e.g. `1.5`, which fails unless `mint` has
+/// `decimals`.
+#[wasm_bindgen(js_name = initializeUiAmount)]
+pub fn initialize_ui_amount(
+    ui_amount: &str,
+    decimals: u8,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Result<Instruction, JsError> {
+    Ok(crate::initialize_ui_amount(
+        &AccountsBuilder::new(sender, receiver, mint).initialize_checked(),
+        ui_amount,
+        decimals,
+    )?)
+}
+
+/// Builds an instruction creating the escrow of the NFT of `mint` between
+/// `sender` and `receiver`, and depositing it from the associated token
+/// account of `sender` into the one of the escrow.
+#[wasm_bindgen(js_name = initializeNft)]
+pub fn initialize_nft(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::initialize_nft(&AccountsBuilder::new(sender, receiver, mint).initialize_checked())
+}
+
+/// Builds an instruction accepting the escrow of `mint` tokens as
+/// `receiver`.
+#[wasm_bindgen]
+pub fn accept(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::accept(&AccountsBuilder::new(sender, receiver, mint).accept())
+}
+
+/// Builds an instruction releasing the escrowed `mint` tokens to the
+/// associated token account of `receiver`.
+#[wasm_bindgen]
+pub fn exchange(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::exchange(&AccountsBuilder::new(sender, receiver, mint).exchange())
+}
+
+/// Builds an instruction like [`exchange`], which fails unless the metadata
+/// account of `mint`, e.g. an NFT, exists.
+#[wasm_bindgen(js_name = exchangeVerifyingMetadata)]
+pub fn exchange_verifying_metadata(
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    crate::exchange_verifying_metadata(
+        &AccountsBuilder::new(sender, receiver, mint).exchange(),
+        mint,
+    )
+}
+
+/// Builds an instruction refunding the escrowed `mint` tokens to the
+/// associated token account of `sender`, minus the penalty paid to the one
+/// of `receiver` if it accepted the escrow.
+#[wasm_bindgen]
+pub fn cancel(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::cancel(&AccountsBuilder::new(sender, receiver, mint).cancel())
+}
+
+/// Builds an instruction releasing the escrowed `mint` tokens to the
+/// associated token account of `receiver`, unless another `amount` than
+/// expected is escrowed.
+#[wasm_bindgen(js_name = exchangeExpecting)]
+pub fn exchange_expecting(
+    amount: u64,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    crate::exchange_expecting(
+        &AccountsBuilder::new(sender, receiver, mint).exchange(),
+        amount,
+    )
+}
+
+/// Builds an instruction refunding the escrowed `mint` tokens to the
+/// associated token account of `sender`, unless another `amount` than
+/// expected is escrowed.
+#[wasm_bindgen(js_name = cancelExpecting)]
+pub fn cancel_expecting(
+    amount: u64,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    crate::cancel_expecting(
+        &AccountsBuilder::new(sender, receiver, mint).cancel(),
+        amount,
+    )
+}
+
+/// Builds an instruction like [`initialize_with_penalty`], whose
+/// cancellation burns the escrowed tokens, minus the penalty, instead of
+/// refunding them.
+#[wasm_bindgen(js_name = initializeBurningOnCancel)]
+pub fn initialize_burning_on_cancel(
+    amount: u64,
+    penalty_bps: u16,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    crate::initialize_burning_on_cancel(
+        &AccountsBuilder::new(sender, receiver, mint).initialize(),
+        amount,
+        penalty_bps,
+    )
+}
+
+/// Builds an instruction cancelling an escrow created by
+/// [`initialize_burning_on_cancel`], burning the escrowed `mint` tokens
+/// after paying the penalty to the associated token account of `receiver`
+/// if it accepted the escrow.
+#[wasm_bindgen(js_name = cancelBurning)]
+pub fn cancel_burning(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::cancel_burning(&AccountsBuilder::new(sender, receiver, mint).cancel(), mint)
+}
+
+/// Builds an instruction creating the delegated escrow of `mint` tokens
+/// between `sender` and `receiver`, which keeps `amount` tokens in the
+/// associated token account of `sender` until the exchange.
+#[wasm_bindgen(js_name = initializeDelegated)]
+pub fn initialize_delegated(
+    amount: u64,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    crate::initialize_delegated(
+        &AccountsBuilder::new(sender, receiver, mint).initialize_delegated(),
+        amount,
+    )
+}
+
+/// Builds an instruction releasing the `mint` tokens of the delegated
+/// escrow from the associated token account of `sender` to the one of
+/// `receiver`.
+#[wasm_bindgen(js_name = exchangeDelegated)]
+pub fn exchange_delegated(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::exchange(&AccountsBuilder::new(sender, receiver, mint).exchange_delegated())
+}
+
+/// Builds an instruction cancelling the delegated escrow of `mint` tokens,
+/// paying the penalty to the associated token account of `receiver` if it
+/// accepted the escrow.
+#[wasm_bindgen(js_name = cancelDelegated)]
+pub fn cancel_delegated(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::cancel_delegated(&AccountsBuilder::new(sender, receiver, mint).cancel_delegated())
+}
+
+/// Builds an instruction like [`initialize_with_penalty`], after whose
+/// `expiry_slot` anyone can refund the escrowed tokens to `sender`.
+#[wasm_bindgen(js_name = initializeWithExpiry)]
+pub fn initialize_with_expiry(
+    amount: u64,
+    penalty_bps: u16,
+    expiry_slot: u64,
+    sender: &Pubkey,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    crate::initialize_with_expiry(
+        &AccountsBuilder::new(sender, receiver, mint).initialize(),
+        amount,
+        penalty_bps,
+        expiry_slot,
+    )
+}
+
+/// Builds an instruction refunding all the escrowed `mint` tokens of the
+/// expired escrow to the associated token account of `sender`, which anyone
+/// can send.
+#[wasm_bindgen(js_name = refundExpired)]
+pub fn refund_expired(sender: &Pubkey, receiver: &Pubkey, mint: &Pubkey) -> Instruction {
+    crate::refund_expired(&AccountsBuilder::new(sender, receiver, mint).refund_expired())
+}
+
+/// Decodes an escrow from the data of its account into an object with the
+/// fields of [`crate::Escrow`], the addresses being base58-encoded.
+#[wasm_bindgen(js_name = decodeEscrow)]
+pub fn decode_escrow(data: &[u8]) -> Result<JsValue, JsError> {
+    let escrow =
+        try_from_account_data(data).map_err(|e| JsError::new(&format!("invalid escrow: {e:?}")))?;
+    Ok(serde_wasm_bindgen::to_value(&escrow)?)
+}
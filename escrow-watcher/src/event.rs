@@ -0,0 +1,196 @@
+//! Events of the escrows, derived from account updates and transaction logs.
+//!
+//! The program subscription reports the state of escrows, which tells when
+//! one is opened or accepted. Exchanges and cancellations only move the
+//! escrowed tokens and leave the state of the escrow as it was, so those are
+//! reported from the events the program logs instead.
+
+use std::{collections::HashMap, fmt};
+
+use escrow_client::{
+    find_escrow_address, try_from_account_data, Escrow, EscrowCancelled, EscrowExchanged, ID,
+};
+use events::decode::decode_events;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+
+/// Change of an escrow.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EscrowEvent {
+    /// An escrow holding `amount` tokens of the sender was opened.
+    Opened {
+        #[serde(with = "common::base58")]
+        escrow: [u8; 32],
+        #[serde(with = "common::base58")]
+        sender: [u8; 32],
+        #[serde(with = "common::base58")]
+        receiver: [u8; 32],
+        amount: u64,
+        penalty_bps: u16,
+    },
+    /// The receiver accepted the escrow.
+    Accepted {
+        #[serde(with = "common::base58")]
+        escrow: [u8; 32],
+        #[serde(with = "common::base58")]
+        sender: [u8; 32],
+        #[serde(with = "common::base58")]
+        receiver: [u8; 32],
+    },
+    /// The escrowed tokens were released to the receiver.
+    Exchanged {
+        #[serde(with = "common::base58")]
+        escrow: [u8; 32],
+        #[serde(with = "common::base58")]
+        sender: [u8; 32],
+        #[serde(with = "common::base58")]
+        receiver: [u8; 32],
+        amount: u64,
+        signature: String,
+    },
+    /// The escrowed tokens were refunded to the sender, minus the `penalty`
+    /// paid to the receiver.
+    Cancelled {
+        #[serde(with = "common::base58")]
+        escrow: [u8; 32],
+        #[serde(with = "common::base58")]
+        sender: [u8; 32],
+        #[serde(with = "common::base58")]
+        receiver: [u8; 32],
+        amount: u64,
+        penalty: u64,
+        signature: String,
+    },
+}
+
+impl fmt::Display for EscrowEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let address = |pubkey: &[u8; 32]| Pubkey::new_from_array(*pubkey);
+        match self {
+            Self::Opened {
+                escrow,
+                sender,
+                receiver,
+                amount,
+                penalty_bps,
+            } => write!(
+                f,
+                "opened {}: {amount} tokens from {} for {}, penalty of {penalty_bps} bps",
+                address(escrow),
+                address(sender),
+                address(receiver),
+            ),
+            Self::Accepted {
+                escrow, receiver, ..
+            } => write!(f, "accepted {} by {}", address(escrow), address(receiver)),
+            Self::Exchanged {
+                escrow,
+                receiver,
+                amount,
+                signature,
+                ..
+            } => write!(
+                f,
+                "exchanged {}: {amount} tokens to {} in {signature}",
+                address(escrow),
+                address(receiver),
+            ),
+            Self::Cancelled {
+                escrow,
+                sender,
+                amount,
+                penalty,
+                signature,
+                ..
+            } => write!(
+                f,
+                "cancelled {}: {} tokens refunded to {}, penalty of {penalty} in {signature}",
+                address(escrow),
+                amount - penalty,
+                address(sender),
+            ),
+        }
+    }
+}
+
+/// Escrows seen by the watcher, to tell new escrows and changes apart from
+/// updates which don't change the state, like lamport transfers.
+pub struct Tracker {
+    escrows: HashMap<Pubkey, Escrow>,
+}
+
+impl Tracker {
+    /// Starts tracking the escrows listed before subscribing, without
+    /// reporting them.
+    pub fn new(escrows: impl IntoIterator<Item = (Pubkey, Escrow)>) -> Self {
+        Self {
+            escrows: escrows.into_iter().collect(),
+        }
+    }
+
+    /// Returns the event of the update of the account at `address` to
+    /// `data`, if it changed the escrow.
+    pub fn account_updated(&mut self, address: Pubkey, data: &[u8]) -> Option<EscrowEvent> {
+        let Ok(escrow) = try_from_account_data(data) else {
+            // Not an escrow anymore, stop tracking it.
+            self.escrows.remove(&address);
+            return None;
+        };
+        let previous = self.escrows.insert(address, escrow);
+
+        match previous {
+            None => Some(EscrowEvent::Opened {
+                escrow: address.to_bytes(),
+                sender: escrow.sender,
+                receiver: escrow.receiver,
+                amount: escrow.amount,
+                penalty_bps: escrow.penalty_bps,
+            }),
+            Some(previous) if previous.accepted == 0 && escrow.accepted != 0 => {
+                Some(EscrowEvent::Accepted {
+                    escrow: address.to_bytes(),
+                    sender: escrow.sender,
+                    receiver: escrow.receiver,
+                })
+            }
+            Some(_) => None,
+        }
+    }
+
+    /// Returns the events of the escrows exchanged or cancelled by the
+    /// transaction with `signature` and `logs`.
+    pub fn transaction_logged(&self, signature: &str, logs: &[String]) -> Vec<EscrowEvent> {
+        let program_id = ID.to_bytes();
+        let exchanged = decode_events::<EscrowExchanged>(&program_id, logs)
+            .into_iter()
+            .map(|event| EscrowEvent::Exchanged {
+                escrow: escrow_address(&event.sender, &event.receiver),
+                sender: event.sender,
+                receiver: event.receiver,
+                amount: event.amount,
+                signature: signature.to_owned(),
+            });
+        let cancelled = decode_events::<EscrowCancelled>(&program_id, logs)
+            .into_iter()
+            .map(|event| EscrowEvent::Cancelled {
+                escrow: escrow_address(&event.sender, &event.receiver),
+                sender: event.sender,
+                receiver: event.receiver,
+                amount: event.amount,
+                penalty: event.penalty,
+                signature: signature.to_owned(),
+            });
+
+        exchanged.chain(cancelled).collect()
+    }
+}
+
+/// Returns the address of the escrow PDA between `sender` and `receiver`.
+fn escrow_address(sender: &[u8; 32], receiver: &[u8; 32]) -> [u8; 32] {
+    let (escrow, _) = find_escrow_address(
+        &Pubkey::new_from_array(*sender),
+        &Pubkey::new_from_array(*receiver),
+    );
+    escrow.to_bytes()
+}
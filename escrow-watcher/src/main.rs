@@ -0,0 +1,196 @@
+//! Watches the escrows of the escrow program deployed on a cluster.
+//!
+//! Lists the open escrows, then subscribes over WebSocket to the updates of
+//! the accounts of the program and to the logs of its transactions, and
+//! prints an event for every escrow opened, accepted, exchanged or
+//! cancelled, either as text or as JSON lines.
+
+mod event;
+
+use std::{
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use escrow_client::{
+    rpc::{decode_escrows, escrows},
+    ID,
+};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    pubsub_client::PubsubClient,
+    rpc_client::RpcClient,
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig,
+        RpcTransactionLogsFilter,
+    },
+};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+use crate::event::{EscrowEvent, Tracker};
+
+#[derive(Parser)]
+#[command(about = "Prints the events of the escrows of the escrow program")]
+struct Cli {
+    /// URL of the cluster, or one of the `localnet` and `devnet` monikers.
+    #[arg(short, long, default_value = "localnet")]
+    url: String,
+    /// WebSocket URL of the cluster. Defaults to the one of the monikers,
+    /// or to the cluster URL with a WebSocket scheme.
+    #[arg(long)]
+    ws_url: Option<String>,
+    /// Prints the events as JSON lines.
+    #[arg(long)]
+    json: bool,
+}
+
+/// Update received from one of the subscriptions.
+enum Update {
+    Account {
+        address: Pubkey,
+        data: Vec<u8>,
+    },
+    Transaction {
+        signature: String,
+        logs: Vec<String>,
+    },
+}
+
+/// Resolves cluster monikers to their RPC URLs.
+fn cluster_url(url: &str) -> &str {
+    match url {
+        "localnet" => "http://127.0.0.1:8899",
+        "devnet" => "https://api.devnet.solana.com",
+        url => url,
+    }
+}
+
+/// Resolves cluster monikers to their WebSocket URLs. Other clusters are
+/// expected to serve WebSocket on the same host and port.
+fn cluster_ws_url(url: &str) -> String {
+    match url {
+        "localnet" => "ws://127.0.0.1:8900".to_owned(),
+        "devnet" => "wss://api.devnet.solana.com".to_owned(),
+        url => url.replacen("http", "ws", 1),
+    }
+}
+
+/// Forwards the updates of the accounts of the program.
+fn subscribe_accounts(ws_url: &str, updates: Sender<Update>) -> Result<()> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(escrows()),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (subscription, receiver) = PubsubClient::program_subscribe(ws_url, &ID, Some(config))?;
+
+    thread::spawn(move || {
+        // Unsubscribes when dropped.
+        let _subscription = subscription;
+        for response in receiver {
+            let keyed = response.value;
+            let (Ok(address), Some(account)) =
+                (keyed.pubkey.parse(), keyed.account.decode::<Account>())
+            else {
+                continue;
+            };
+            let update = Update::Account {
+                address,
+                data: account.data,
+            };
+            if updates.send(update).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Forwards the logs of the successful transactions of the program.
+fn subscribe_logs(ws_url: &str, updates: Sender<Update>) -> Result<()> {
+    let (subscription, receiver) = PubsubClient::logs_subscribe(
+        ws_url,
+        RpcTransactionLogsFilter::Mentions(vec![ID.to_string()]),
+        RpcTransactionLogsConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+        },
+    )?;
+
+    thread::spawn(move || {
+        let _subscription = subscription;
+        for response in receiver {
+            let logs = response.value;
+            if logs.err.is_some() {
+                continue;
+            }
+            let update = Update::Transaction {
+                signature: logs.signature,
+                logs: logs.logs,
+            };
+            if updates.send(update).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+fn print(event: &EscrowEvent, json: bool) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(event)?);
+    } else {
+        println!("{event}");
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let ws_url = cli.ws_url.unwrap_or_else(|| cluster_ws_url(&cli.url));
+    let client = RpcClient::new_with_commitment(
+        cluster_url(&cli.url).to_owned(),
+        CommitmentConfig::confirmed(),
+    );
+
+    // Subscribe first, so no update is missed between the listing and the
+    // subscriptions. Updates of listed escrows which don't change them are
+    // skipped by the tracker.
+    let (updates, receiver) = mpsc::channel();
+    subscribe_accounts(&ws_url, updates.clone())?;
+    subscribe_logs(&ws_url, updates)?;
+
+    let config = RpcProgramAccountsConfig {
+        filters: Some(escrows()),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let accounts = client.get_program_accounts_with_config(&ID, config)?;
+    let mut tracker = Tracker::new(decode_escrows(accounts));
+    eprintln!("Watching the escrows of {ID} on {ws_url}");
+
+    for update in receiver {
+        let events = match update {
+            Update::Account { address, data } => tracker
+                .account_updated(address, &data)
+                .into_iter()
+                .collect(),
+            Update::Transaction { signature, logs } => {
+                tracker.transaction_logged(&signature, &logs)
+            }
+        };
+        for event in &events {
+            print(event, cli.json)?;
+        }
+    }
+    Err(anyhow!("the subscriptions were closed"))
+}
@@ -0,0 +1,187 @@
+//! Accounts of the escrow instructions.
+//!
+//! `#[derive(Accounts)]` destructures the accounts in the order of the
+//! fields and checks their attributes. The instructions check the rest: the
+//! owners of the token accounts, which depend on the token program, and that
+//! no account is passed in two roles.
+//!
+//! The escrow PDA signs the transfers out of it with its seeds, so it's
+//! never a signer of the instructions themselves.
+
+use common::checks::require_not_signer;
+use macros::Accounts;
+use pinocchio::{account_info::AccountInfo, pubkey::Pubkey};
+
+use crate::{token::require_token_program, ESCROW_SEED};
+
+/// Accounts of [`crate::EscrowInstruction::Initialize`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct InitializeAccounts<'a> {
+    /// Sender and payer, authorizing the deposit.
+    #[signer]
+    pub sender: &'a AccountInfo,
+    pub sender_ata: &'a AccountInfo,
+    pub receiver: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    pub escrow: &'a AccountInfo,
+    pub escrow_ata: &'a AccountInfo,
+    #[program = pinocchio_system::ID]
+    pub system_program: &'a AccountInfo,
+    #[check = require_token_program]
+    pub token_program: &'a AccountInfo,
+}
+
+/// Accounts of [`crate::EscrowInstruction::Exchange`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct ExchangeAccounts<'a> {
+    pub sender: &'a AccountInfo,
+    /// Receiver, accepting the exchange.
+    #[signer]
+    pub receiver: &'a AccountInfo,
+    pub receiver_ata: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    #[check = require_not_signer]
+    pub escrow: &'a AccountInfo,
+    pub escrow_ata: &'a AccountInfo,
+    #[check = require_token_program]
+    pub token_program: &'a AccountInfo,
+}
+
+/// Accounts of [`crate::EscrowInstruction::Cancel`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CancelAccounts<'a> {
+    /// Sender, cancelling the escrow.
+    #[signer]
+    pub sender: &'a AccountInfo,
+    pub sender_ata: &'a AccountInfo,
+    pub receiver: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    #[check = require_not_signer]
+    pub escrow: &'a AccountInfo,
+    pub escrow_ata: &'a AccountInfo,
+    #[check = require_token_program]
+    pub token_program: &'a AccountInfo,
+    /// Token account of the receiver, only used to pay the penalty.
+    pub receiver_ata: &'a AccountInfo,
+}
+
+/// Accounts of [`crate::EscrowInstruction::Accept`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct AcceptAccounts<'a> {
+    pub sender: &'a AccountInfo,
+    /// Receiver, accepting the escrow.
+    #[signer]
+    pub receiver: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    pub escrow: &'a AccountInfo,
+}
+
+/// Accounts of [`crate::EscrowInstruction::InitializeDelegated`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct InitializeDelegatedAccounts<'a> {
+    /// Sender and payer, authorizing the delegation.
+    #[signer]
+    pub sender: &'a AccountInfo,
+    pub sender_ata: &'a AccountInfo,
+    pub receiver: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    pub escrow: &'a AccountInfo,
+    #[program = pinocchio_system::ID]
+    pub system_program: &'a AccountInfo,
+    #[check = require_token_program]
+    pub token_program: &'a AccountInfo,
+}
+
+/// Accounts of [`crate::EscrowInstruction::CancelDelegated`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CancelDelegatedAccounts<'a> {
+    /// Sender, cancelling the escrow and revoking the delegation.
+    #[signer]
+    pub sender: &'a AccountInfo,
+    pub sender_ata: &'a AccountInfo,
+    pub receiver: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    #[check = require_not_signer]
+    pub escrow: &'a AccountInfo,
+    #[check = require_token_program]
+    pub token_program: &'a AccountInfo,
+    /// Token account of the receiver, only used to pay the penalty.
+    pub receiver_ata: &'a AccountInfo,
+}
+
+/// Accounts of [`crate::EscrowInstruction::InitializeChecked`] and
+/// [`crate::EscrowInstruction::InitializeNft`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct InitializeCheckedAccounts<'a> {
+    /// Sender and payer, authorizing the deposit.
+    #[signer]
+    pub sender: &'a AccountInfo,
+    pub sender_ata: &'a AccountInfo,
+    pub receiver: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    pub escrow: &'a AccountInfo,
+    pub escrow_ata: &'a AccountInfo,
+    /// Mint of the escrowed tokens, checked by the token program. The
+    /// escrow checks the mint of an NFT itself.
+    pub mint: &'a AccountInfo,
+    #[program = pinocchio_system::ID]
+    pub system_program: &'a AccountInfo,
+    #[check = require_token_program]
+    pub token_program: &'a AccountInfo,
+}
+
+/// Accounts of [`crate::EscrowInstruction::RefundExpired`]. Anyone can
+/// refund an expired escrow, so none of them signs. The escrow PDA is closed
+/// to the sender.
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct RefundExpiredAccounts<'a> {
+    pub sender: &'a AccountInfo,
+    pub sender_ata: &'a AccountInfo,
+    pub receiver: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    #[check = require_not_signer]
+    pub escrow: &'a AccountInfo,
+    pub escrow_ata: &'a AccountInfo,
+    #[check = require_token_program]
+    pub token_program: &'a AccountInfo,
+}
+
+/// Accounts of [`crate::EscrowInstruction::ExchangeBatch`] shared by the
+/// escrows, followed by [`BatchEscrowAccounts`] of each escrow.
+#[derive(Accounts)]
+pub struct ExchangeBatchAccounts<'a> {
+    /// Receiver of the escrows, accepting the exchanges.
+    #[signer]
+    pub receiver: &'a AccountInfo,
+    pub receiver_ata: &'a AccountInfo,
+    #[check = require_token_program]
+    pub token_program: &'a AccountInfo,
+}
+
+/// Accounts of an escrow of [`crate::EscrowInstruction::ExchangeBatch`].
+#[derive(Accounts)]
+#[instruction(bump: u8, receiver: &Pubkey)]
+pub struct BatchEscrowAccounts<'a> {
+    pub sender: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver of the batch.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver], bump = bump)]
+    #[check = require_not_signer]
+    pub escrow: &'a AccountInfo,
+    pub escrow_ata: &'a AccountInfo,
+}
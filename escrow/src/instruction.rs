@@ -0,0 +1,340 @@
+//! Instructions of the escrow program.
+
+use macros::{InstructionData, InstructionDiscriminator};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use shank::{ShankInstruction, ShankType};
+
+/// Escrow program instruction discriminators, the family of each
+/// instruction followed by the version of its data.
+#[derive(InstructionDiscriminator, ShankInstruction)]
+#[repr(u8)]
+#[versioned]
+pub enum EscrowInstruction {
+    /// Creates the escrow and deposits tokens into it. Instruction data:
+    /// [`InitializeInstructionData`] in version 1, without a cancellation
+    /// penalty, [`InitializeInstructionDataV2`] in version 2,
+    /// [`InitializeInstructionDataV3`] in version 3, which may burn the
+    /// tokens on cancellation, and [`InitializeInstructionDataV4`] in
+    /// version 4, which may set an expiry slot.
+    #[account(0, writable, signer, name = "sender", desc = "Sender and payer")]
+    #[account(1, writable, name = "sender_ata", desc = "Sender's token account")]
+    #[account(2, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, writable, name = "escrow_ata", desc = "Escrow's token account")]
+    #[account(5, name = "system_program", desc = "System program")]
+    #[account(6, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    Initialize,
+    /// Releases the escrowed tokens to the receiver, out of the token
+    /// account of the sender for a delegated escrow, checking the metadata
+    /// account of their mint if it's passed. Instruction data:
+    /// [`FinalizeInstructionData`] in version 1 and
+    /// [`FinalizeInstructionDataV2`] in version 2.
+    #[account(0, name = "sender", desc = "Sender of the escrow")]
+    #[account(1, signer, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(2, writable, name = "receiver_ata", desc = "Receiver's token account")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(
+        4,
+        writable,
+        name = "escrow_ata",
+        desc = "Escrow's token account, or the sender's one of a delegated escrow"
+    )]
+    #[account(5, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    #[account(
+        6,
+        optional,
+        name = "metadata",
+        desc = "Metadata account of the mint, only passed to check that it exists"
+    )]
+    Exchange,
+    /// Refunds the escrowed tokens to the sender, minus the penalty paid to
+    /// the receiver if it accepted the escrow, or burns them if the escrow
+    /// burns on cancellation. Instruction data:
+    /// [`FinalizeInstructionData`] in version 1 and
+    /// [`FinalizeInstructionDataV2`] in version 2.
+    #[account(0, signer, name = "sender", desc = "Sender of the escrow")]
+    #[account(1, writable, name = "sender_ata", desc = "Sender's token account")]
+    #[account(2, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, writable, name = "escrow_ata", desc = "Escrow's token account")]
+    #[account(5, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    #[account(
+        6,
+        writable,
+        name = "receiver_ata",
+        desc = "Receiver's token account, paid the penalty"
+    )]
+    #[account(
+        7,
+        optional,
+        writable,
+        name = "mint",
+        desc = "Mint of the escrowed tokens, only used to burn them"
+    )]
+    Cancel,
+    /// Signals that the receiver accepted the escrow, from when a
+    /// cancellation pays it the penalty. Instruction data:
+    /// [`FinalizeInstructionData`], only in version 1.
+    #[account(0, name = "sender", desc = "Sender of the escrow")]
+    #[account(1, signer, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(2, writable, name = "escrow", desc = "Escrow PDA")]
+    Accept,
+    /// Releases the escrowed tokens of several escrows of the same mint to
+    /// their receiver, as many as the remaining compute units allow, and
+    /// returns how many were released. The accounts are followed by the
+    /// sender, the escrow PDA and the escrow's token account of each escrow,
+    /// and the instruction data is the bump of each escrow PDA, only in
+    /// version 1.
+    #[account(0, signer, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(1, writable, name = "receiver_ata", desc = "Receiver's token account")]
+    #[account(2, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    ExchangeBatch,
+    /// Creates a delegated escrow, whose tokens stay in the token account of
+    /// the sender until the exchange, only approving the escrow PDA as the
+    /// delegate of the amount. Instruction data: versions 1 and 2 of the
+    /// ones of [`EscrowInstruction::Initialize`], as the tokens of the
+    /// sender can't be burnt on cancellation.
+    #[account(0, writable, signer, name = "sender", desc = "Sender and payer")]
+    #[account(
+        1,
+        writable,
+        name = "sender_ata",
+        desc = "Sender's token account, delegating the escrowed tokens"
+    )]
+    #[account(2, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, name = "system_program", desc = "System program")]
+    #[account(5, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    InitializeDelegated,
+    /// Cancels a delegated escrow, paying the penalty to the receiver out of
+    /// the token account of the sender if it accepted the escrow, and
+    /// revoking the delegation. Instruction data: the ones of
+    /// [`EscrowInstruction::Cancel`].
+    #[account(0, signer, name = "sender", desc = "Sender of the escrow")]
+    #[account(
+        1,
+        writable,
+        name = "sender_ata",
+        desc = "Sender's token account, delegating the escrowed tokens"
+    )]
+    #[account(2, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(3, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    #[account(
+        5,
+        writable,
+        name = "receiver_ata",
+        desc = "Receiver's token account, paid the penalty"
+    )]
+    CancelDelegated,
+    /// Creates the escrow and deposits tokens into it like
+    /// [`EscrowInstruction::Initialize`], with a `TransferChecked` failing
+    /// unless the decimals of the amount are the ones of the mint.
+    /// Instruction data: [`InitializeCheckedInstructionData`], only in
+    /// version 1.
+    #[account(0, writable, signer, name = "sender", desc = "Sender and payer")]
+    #[account(1, writable, name = "sender_ata", desc = "Sender's token account")]
+    #[account(2, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, writable, name = "escrow_ata", desc = "Escrow's token account")]
+    #[account(5, name = "mint", desc = "Mint of the escrowed tokens")]
+    #[account(6, name = "system_program", desc = "System program")]
+    #[account(7, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    InitializeChecked,
+    /// Creates the escrow of an NFT and deposits it, failing unless the mint
+    /// has no decimals and a supply of 1. Instruction data:
+    /// [`InitializeNftInstructionData`], only in version 1.
+    #[account(0, writable, signer, name = "sender", desc = "Sender and payer")]
+    #[account(1, writable, name = "sender_ata", desc = "Sender's token account")]
+    #[account(2, name = "receiver", desc = "Receiver of the escrowed NFT")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, writable, name = "escrow_ata", desc = "Escrow's token account")]
+    #[account(5, name = "mint", desc = "Mint of the NFT")]
+    #[account(6, name = "system_program", desc = "System program")]
+    #[account(7, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    InitializeNft,
+    /// Refunds all the escrowed tokens to the sender once the slot is past
+    /// the expiry slot of the escrow, without a penalty nor a burn. Anyone
+    /// can invoke it, so the tokens don't stay locked if the receiver never
+    /// shows up. Closes the escrow PDA, refunding its rent to the sender.
+    /// Instruction data: [`FinalizeInstructionData`], only in version 1.
+    #[account(0, writable, name = "sender", desc = "Sender of the escrow")]
+    #[account(1, writable, name = "sender_ata", desc = "Sender's token account")]
+    #[account(2, name = "receiver", desc = "Receiver of the escrowed tokens")]
+    #[account(3, writable, name = "escrow", desc = "Escrow PDA")]
+    #[account(4, writable, name = "escrow_ata", desc = "Escrow's token account")]
+    #[account(5, name = "token_program", desc = "SPL Token or Token-2022 program")]
+    RefundExpired,
+}
+
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct InitializeInstructionData {
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(amount: u64, bump: u8) -> Self {
+        Self { amount, bump }
+    }
+}
+
+/// Instruction data of version 2 of [`EscrowInstruction::Initialize`],
+/// which sets a cancellation penalty.
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct InitializeInstructionDataV2 {
+    pub amount: u64,
+    pub bump: u8,
+    /// Share of the amount paid to the receiver if the sender cancels after
+    /// the receiver accepted, in basis points.
+    pub penalty_bps: u16,
+}
+
+impl InitializeInstructionDataV2 {
+    pub fn new(amount: u64, bump: u8, penalty_bps: u16) -> Self {
+        Self {
+            amount,
+            bump,
+            penalty_bps,
+        }
+    }
+}
+
+/// Instruction data of version 3 of [`EscrowInstruction::Initialize`],
+/// which may burn the escrowed tokens on cancellation instead of refunding
+/// them.
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct InitializeInstructionDataV3 {
+    pub amount: u64,
+    pub bump: u8,
+    /// Share of the amount paid to the receiver if the sender cancels after
+    /// the receiver accepted, in basis points.
+    pub penalty_bps: u16,
+    /// Whether a cancellation burns the escrowed tokens, minus the penalty,
+    /// 1 if it does and 0 if not.
+    pub burn_on_cancel: u8,
+}
+
+impl InitializeInstructionDataV3 {
+    pub fn new(amount: u64, bump: u8, penalty_bps: u16, burn_on_cancel: u8) -> Self {
+        Self {
+            amount,
+            bump,
+            penalty_bps,
+            burn_on_cancel,
+        }
+    }
+}
+
+/// Instruction data of version 4 of [`EscrowInstruction::Initialize`],
+/// which may set a slot after which anyone can refund the escrowed tokens.
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct InitializeInstructionDataV4 {
+    pub amount: u64,
+    pub bump: u8,
+    /// Share of the amount paid to the receiver if the sender cancels after
+    /// the receiver accepted, in basis points.
+    pub penalty_bps: u16,
+    /// Whether a cancellation burns the escrowed tokens, minus the penalty,
+    /// 1 if it does and 0 if not.
+    pub burn_on_cancel: u8,
+    /// Slot after which anyone can refund the escrowed tokens to the sender,
+    /// or 0 if the escrow never expires.
+    pub expiry_slot: u64,
+}
+
+impl InitializeInstructionDataV4 {
+    pub fn new(
+        amount: u64,
+        bump: u8,
+        penalty_bps: u16,
+        burn_on_cancel: u8,
+        expiry_slot: u64,
+    ) -> Self {
+        Self {
+            amount,
+            bump,
+            penalty_bps,
+            burn_on_cancel,
+            expiry_slot,
+        }
+    }
+}
+
+/// Instruction data of [`EscrowInstruction::InitializeChecked`], the one of
+/// version 3 of [`EscrowInstruction::Initialize`] with the decimals of the
+/// amount.
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct InitializeCheckedInstructionData {
+    pub amount: u64,
+    /// Decimals of the mint the amount is expressed in.
+    pub decimals: u8,
+    pub bump: u8,
+    /// Share of the amount paid to the receiver if the sender cancels after
+    /// the receiver accepted, in basis points.
+    pub penalty_bps: u16,
+    /// Whether a cancellation burns the escrowed tokens, minus the penalty,
+    /// 1 if it does and 0 if not.
+    pub burn_on_cancel: u8,
+}
+
+impl InitializeCheckedInstructionData {
+    pub fn new(amount: u64, decimals: u8, bump: u8, penalty_bps: u16, burn_on_cancel: u8) -> Self {
+        Self {
+            amount,
+            decimals,
+            bump,
+            penalty_bps,
+            burn_on_cancel,
+        }
+    }
+}
+
+/// Instruction data of [`EscrowInstruction::InitializeNft`], which always
+/// escrows the single token of the mint, without a cancellation penalty.
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct InitializeNftInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeNftInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FinalizeInstructionData {
+    pub bump: u8,
+}
+
+impl FinalizeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+/// Instruction data of version 2 of [`EscrowInstruction::Exchange`] and
+/// [`EscrowInstruction::Cancel`], which fail unless the escrow holds
+/// `amount`.
+#[derive(Clone, Copy, InstructionData, ShankType)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct FinalizeInstructionDataV2 {
+    pub bump: u8,
+    /// Amount the signer expects to be escrowed.
+    pub amount: u64,
+}
+
+impl FinalizeInstructionDataV2 {
+    pub fn new(bump: u8, amount: u64) -> Self {
+        Self { bump, amount }
+    }
+}
@@ -1,280 +1,1197 @@
 #![no_std]
 
-use core::mem;
+use core::{mem::MaybeUninit, slice};
 
+use common::{
+    checks::{require_distinct, require_program_id},
+    close::close_account,
+    create::create_account,
+    info, keys_eq,
+    version::Version,
+    AccountData,
+};
+use errors::{Namespace, SharedError};
+use events::emit;
+use macros::Event;
 use pinocchio::{
     account_info::AccountInfo,
-    no_allocator, nostd_panic_handler, program_entrypoint,
+    cpi::set_return_data,
+    entrypoint::{InstructionContext, MaybeAccount},
+    instruction::{Seed, Signer},
     program_error::ProgramError,
-    pubkey::{create_program_address, Pubkey},
-    sysvars::{rent::Rent, Sysvar},
+    pubkey::Pubkey,
+    sysvars::{clock::Clock, Sysvar},
     ProgramResult,
 };
-use pinocchio_log::log;
-use pinocchio_system::instructions::CreateAccount;
-use pinocchio_token::{instructions::Transfer, state::TokenAccount};
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+use pinocchio::{lazy_program_entrypoint, no_allocator, nostd_panic_handler};
+
+pub mod accounts;
+pub mod instruction;
+pub mod metadata;
+pub mod state;
+pub mod token;
+
+pub use accounts::{
+    AcceptAccounts, BatchEscrowAccounts, CancelAccounts, CancelDelegatedAccounts, ExchangeAccounts,
+    ExchangeBatchAccounts, InitializeAccounts, InitializeCheckedAccounts,
+    InitializeDelegatedAccounts, RefundExpiredAccounts,
+};
+pub use instruction::{
+    EscrowInstruction, FinalizeInstructionData, FinalizeInstructionDataV2,
+    InitializeCheckedInstructionData, InitializeInstructionData, InitializeInstructionDataV2,
+    InitializeInstructionDataV3, InitializeInstructionDataV4, InitializeNftInstructionData,
+};
+pub use state::{Escrow, ESCROW_SEED, MAX_PENALTY_BPS};
+
+use metadata::require_metadata;
+use token::{
+    mint_supply_and_decimals, token_account_amount, token_account_delegate, token_account_mint,
+    token_account_owner, Approve, Burn, Revoke, Transfer, TransferChecked,
+};
 
-program_entrypoint!(process_instruction);
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+lazy_program_entrypoint!(process_instruction);
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
 no_allocator!();
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
 nostd_panic_handler!();
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+common::build_metadata!();
 
 pinocchio_pubkey::declare_id!("AMeUviQdjAPsvfWwRfboCLrN7t2fjSxqs4eMZguezpQr");
 
-pub const ESCROW_SEED: &'static str = "escrow";
+/// Errors returned by the escrow program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum EscrowError {
+    /// The token account holding the escrowed tokens holds less than the
+    /// escrowed amount.
+    InsufficientEscrowBalance,
+    /// The escrow holds another amount than the signer expects.
+    AmountMismatch,
+    /// The cancellation penalty is above 100%.
+    InvalidPenalty,
+    /// The instruction is for delegated escrows and the escrow isn't one, or
+    /// the other way around.
+    DelegationMismatch,
+    /// The token account of a delegated escrow doesn't delegate the escrowed
+    /// amount to the escrow anymore.
+    MissingDelegation,
+    /// The token account of the sender already has a delegate, which a
+    /// delegated escrow would replace.
+    AlreadyDelegated,
+    /// The mint of an NFT escrow has decimals or a supply other than 1.
+    NotAnNft,
+    /// The escrow never expires or the slot isn't past its expiry slot yet.
+    NotExpired,
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(e: EscrowError) -> Self {
+        Namespace::Escrow.error(e as u32)
+    }
+}
+
+/// Emitted when tokens are deposited into a new escrow.
+#[derive(Clone, Copy, Event)]
+pub struct EscrowInitialized {
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    pub amount: u64,
+}
 
-#[derive(Clone)]
-#[repr(C)]
-pub struct Escrow {
+/// Emitted when the escrowed tokens are released to the receiver.
+#[derive(Clone, Copy, Event)]
+pub struct EscrowExchanged {
     pub sender: Pubkey,
     pub receiver: Pubkey,
     pub amount: u64,
 }
 
-impl Escrow {
-    pub const LEN: usize = mem::size_of::<Self>();
+/// Emitted when the escrowed tokens are refunded to the sender, or burnt,
+/// minus the penalty paid to the receiver. Refunds of expired escrows have
+/// no penalty.
+#[derive(Clone, Copy, Event)]
+pub struct EscrowCancelled {
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    pub amount: u64,
+    pub penalty: u64,
 }
 
-#[repr(u8)]
-pub enum EscrowInstruction {
-    Initialize,
-    Exchange,
-    Cancel,
+/// Emitted when the receiver accepts the escrow.
+#[derive(Clone, Copy, Event)]
+pub struct EscrowAccepted {
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
 }
 
-impl TryFrom<&u8> for EscrowInstruction {
-    type Error = ProgramError;
+/// Most escrows released by an [`EscrowInstruction::ExchangeBatch`].
+pub const MAX_BATCH_ESCROWS: usize = 8;
+
+/// Compute units an [`EscrowInstruction::ExchangeBatch`] keeps for each
+/// exchange, the budget of an [`EscrowInstruction::Exchange`]. The batch
+/// stops before an exchange once fewer are left, so it never runs out
+/// halfway through one.
+pub const EXCHANGE_COMPUTE_UNITS: u64 = 12_000;
+
+/// Most accounts taken by an instruction, the ones of
+/// [`EscrowInstruction::ExchangeBatch`] with [`MAX_BATCH_ESCROWS`] escrows.
+const MAX_ACCOUNTS: usize = 3 + 3 * MAX_BATCH_ESCROWS;
 
-    fn try_from(value: &u8) -> Result<Self, Self::Error> {
-        match *value {
-            0 => Ok(Self::Initialize),
-            1 => Ok(Self::Exchange),
-            2 => Ok(Self::Cancel),
-            _ => Err(ProgramError::InvalidInstructionData),
+/// Entrypoint of the program.
+pub fn process_instruction(mut context: InstructionContext) -> ProgramResult {
+    // The instruction data follows the accounts, so all of them are walked
+    // before the instruction is known, but only the ones an instruction can
+    // use are kept. No account is passed in two roles, so duplicates are
+    // rejected right away.
+    const UNINIT: MaybeUninit<AccountInfo> = MaybeUninit::uninit();
+    let mut accounts = [UNINIT; MAX_ACCOUNTS];
+    let mut len = 0;
+    while context.remaining() > 0 {
+        match context.next_account()? {
+            MaybeAccount::Account(account) if len < MAX_ACCOUNTS => {
+                accounts[len].write(account);
+                len += 1;
+            }
+            MaybeAccount::Account(_) => {}
+            MaybeAccount::Duplicated(_) => return Err(SharedError::DuplicateAccount.into()),
+        }
+    }
+    // SAFETY: The first `len` accounts were written above.
+    let accounts = unsafe { slice::from_raw_parts(accounts.as_ptr().cast::<AccountInfo>(), len) };
+
+    // Check that the program runs under its declared address, which its
+    // PDAs are derived from.
+    require_program_id(context.program_id()?, &ID)?;
+
+    let (instruction, version, instruction_data) =
+        EscrowInstruction::split(context.instruction_data()?)?;
+
+    match (instruction, version) {
+        (EscrowInstruction::Initialize, version) => {
+            process_initialize(accounts, version, instruction_data)
+        }
+        (EscrowInstruction::Exchange, version) => {
+            process_exchange(accounts, version, instruction_data)
+        }
+        (EscrowInstruction::Cancel, version) => process_cancel(accounts, version, instruction_data),
+        (EscrowInstruction::Accept, Version::V1) => process_accept(accounts, instruction_data),
+        (EscrowInstruction::Accept, _) => Err(ProgramError::InvalidInstructionData),
+        (EscrowInstruction::ExchangeBatch, Version::V1) => {
+            process_exchange_batch(accounts, instruction_data)
         }
+        (EscrowInstruction::ExchangeBatch, _) => Err(ProgramError::InvalidInstructionData),
+        (EscrowInstruction::InitializeDelegated, version) => {
+            process_initialize_delegated(accounts, version, instruction_data)
+        }
+        (EscrowInstruction::CancelDelegated, version) => {
+            process_cancel_delegated(accounts, version, instruction_data)
+        }
+        (EscrowInstruction::InitializeChecked, Version::V1) => {
+            process_initialize_checked(accounts, instruction_data)
+        }
+        (EscrowInstruction::InitializeChecked, _) => Err(ProgramError::InvalidInstructionData),
+        (EscrowInstruction::InitializeNft, Version::V1) => {
+            process_initialize_nft(accounts, instruction_data)
+        }
+        (EscrowInstruction::InitializeNft, _) => Err(ProgramError::InvalidInstructionData),
+        (EscrowInstruction::RefundExpired, Version::V1) => {
+            process_refund_expired(accounts, instruction_data)
+        }
+        (EscrowInstruction::RefundExpired, _) => Err(ProgramError::InvalidInstructionData),
     }
 }
 
-#[repr(C)]
-pub struct InitializeInstructionData {
-    pub amount: u64,
-    pub bump: u8,
-    pub _padding: [u8; 7],
+/// Returns the compute units left to the instruction.
+fn remaining_compute_units() -> u64 {
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_remaining_compute_units()
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    u64::MAX
 }
 
-impl InitializeInstructionData {
-    pub fn new(amount: u64, bump: u8) -> Self {
-        Self {
-            amount,
-            bump,
-            _padding: [0; 7],
-        }
+/// Deserializes a flag of instruction data, which is either 0 or 1.
+fn flag(value: u8) -> Result<bool, ProgramError> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(ProgramError::InvalidInstructionData),
     }
 }
 
-#[repr(C)]
-pub struct FinalizeInstructionData {
-    pub bump: u8,
+/// Terms of a new escrow, set by the instruction data of its
+/// initialization.
+struct Terms {
+    amount: u64,
+    penalty_bps: u16,
+    burn_on_cancel: bool,
+    expiry_slot: u64,
+}
+
+/// Deserializes the instruction data of `version` of the initialization,
+/// returning the bump of the escrow PDA and the terms of the escrow. It has
+/// no cancellation penalty in version 1, only burns the tokens on
+/// cancellation from version 3 and only expires from version 4.
+fn initialize_instruction_data(
+    version: Version,
+    instruction_data: &[u8],
+) -> Result<(u8, Terms), ProgramError> {
+    match version {
+        Version::V1 => {
+            let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+            Ok((
+                instruction_data.bump,
+                Terms {
+                    amount: instruction_data.amount,
+                    penalty_bps: 0,
+                    burn_on_cancel: false,
+                    expiry_slot: 0,
+                },
+            ))
+        }
+        Version::V2 => {
+            let instruction_data = InitializeInstructionDataV2::from_bytes(instruction_data)?;
+            Ok((
+                instruction_data.bump,
+                Terms {
+                    amount: instruction_data.amount,
+                    penalty_bps: instruction_data.penalty_bps,
+                    burn_on_cancel: false,
+                    expiry_slot: 0,
+                },
+            ))
+        }
+        Version::V3 => {
+            let instruction_data = InitializeInstructionDataV3::from_bytes(instruction_data)?;
+            Ok((
+                instruction_data.bump,
+                Terms {
+                    amount: instruction_data.amount,
+                    penalty_bps: instruction_data.penalty_bps,
+                    burn_on_cancel: flag(instruction_data.burn_on_cancel)?,
+                    expiry_slot: 0,
+                },
+            ))
+        }
+        Version::V4 => {
+            let instruction_data = InitializeInstructionDataV4::from_bytes(instruction_data)?;
+            Ok((
+                instruction_data.bump,
+                Terms {
+                    amount: instruction_data.amount,
+                    penalty_bps: instruction_data.penalty_bps,
+                    burn_on_cancel: flag(instruction_data.burn_on_cancel)?,
+                    expiry_slot: instruction_data.expiry_slot,
+                },
+            ))
+        }
+    }
 }
 
-impl FinalizeInstructionData {
-    pub fn new(bump: u8) -> Self {
-        Self { bump }
+/// Deserializes the instruction data of `version` of the exchange and the
+/// cancellation, returning the bump of the escrow PDA and, from version 2,
+/// the amount the signer expects to be escrowed.
+fn finalize_instruction_data(
+    version: Version,
+    instruction_data: &[u8],
+) -> Result<(u8, Option<u64>), ProgramError> {
+    match version {
+        Version::V1 => Ok((
+            FinalizeInstructionData::from_bytes(instruction_data)?.bump,
+            None,
+        )),
+        Version::V2 => {
+            let instruction_data = FinalizeInstructionDataV2::from_bytes(instruction_data)?;
+            Ok((instruction_data.bump, Some(instruction_data.amount)))
+        }
+        Version::V3 | Version::V4 => Err(ProgramError::InvalidInstructionData),
     }
 }
 
-pub fn process_instruction(
-    _program_id: &Pubkey,
+pub fn process_initialize(
     accounts: &[AccountInfo],
+    version: Version,
     instruction_data: &[u8],
 ) -> ProgramResult {
-    let (instruction, instruction_data) = instruction_data
-        .split_first()
-        .ok_or(ProgramError::InvalidInstructionData)?;
-    let instruction = EscrowInstruction::try_from(instruction)?;
+    // Deserialize instruction data.
+    let (bump, terms) = initialize_instruction_data(version, instruction_data)?;
+
+    // Retrieve and validate the accounts, checking that `sender` authorized
+    // the deposit, the seeds of `escrow` and the programs.
+    let accounts = InitializeAccounts::from_accounts(accounts, bump)?;
+
+    initialize(&accounts, bump, &terms, None, false)
+}
+
+pub fn process_initialize_checked(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Deserialize instruction data.
+    let instruction_data = InitializeCheckedInstructionData::from_bytes(instruction_data)?;
+    let burn_on_cancel = flag(instruction_data.burn_on_cancel)?;
 
-    match instruction {
-        EscrowInstruction::Initialize => process_initialize(accounts, instruction_data),
-        EscrowInstruction::Exchange => process_exchange(accounts, instruction_data),
-        EscrowInstruction::Cancel => process_cancel(accounts, instruction_data),
+    // Retrieve and validate the accounts, checking that `sender` authorized
+    // the deposit, the seeds of `escrow` and the programs.
+    let InitializeCheckedAccounts {
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        escrow_ata,
+        mint,
+        system_program,
+        token_program,
+    } = InitializeCheckedAccounts::from_accounts(accounts, instruction_data.bump)?;
+
+    initialize(
+        &InitializeAccounts {
+            sender,
+            sender_ata,
+            receiver,
+            escrow,
+            escrow_ata,
+            system_program,
+            token_program,
+        },
+        instruction_data.bump,
+        &Terms {
+            amount: instruction_data.amount,
+            penalty_bps: instruction_data.penalty_bps,
+            burn_on_cancel,
+            expiry_slot: 0,
+        },
+        Some((mint, instruction_data.decimals)),
+        false,
+    )
+}
+
+pub fn process_initialize_nft(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Deserialize instruction data.
+    let instruction_data = InitializeNftInstructionData::from_bytes(instruction_data)?;
+
+    // Retrieve and validate the accounts, checking that `sender` authorized
+    // the deposit, the seeds of `escrow` and the programs.
+    let InitializeCheckedAccounts {
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        escrow_ata,
+        mint,
+        system_program,
+        token_program,
+    } = InitializeCheckedAccounts::from_accounts(accounts, instruction_data.bump)?;
+
+    // Check that `mint` is the mint of an NFT, a single token without
+    // decimals, which is all the escrow holds.
+    if mint_supply_and_decimals(mint, token_program)? != (1, 0) {
+        return Err(EscrowError::NotAnNft.into());
     }
+
+    initialize(
+        &InitializeAccounts {
+            sender,
+            sender_ata,
+            receiver,
+            escrow,
+            escrow_ata,
+            system_program,
+            token_program,
+        },
+        instruction_data.bump,
+        &Terms {
+            amount: 1,
+            penalty_bps: 0,
+            burn_on_cancel: false,
+            expiry_slot: 0,
+        },
+        Some((mint, 0)),
+        true,
+    )
 }
 
-pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-    // Retrieve and validate the accounts.
-    let [sender, sender_ata, receiver, escrow, escrow_ata, _system_program, _token_program] =
-        accounts
-    else {
-        return Err(ProgramError::NotEnoughAccountKeys);
-    };
+/// Creates the escrow of `accounts` with `terms` and deposits the escrowed
+/// tokens into it. With `mint` and its `decimals`, the deposit is a
+/// `TransferChecked`, which fails unless they're the ones of the escrowed
+/// tokens. `nft` marks the escrow of an NFT, whose mint the caller checked.
+fn initialize(
+    accounts: &InitializeAccounts,
+    bump: u8,
+    terms: &Terms,
+    mint: Option<(&AccountInfo, u8)>,
+    nft: bool,
+) -> ProgramResult {
+    let &Terms {
+        amount,
+        penalty_bps,
+        burn_on_cancel,
+        expiry_slot,
+    } = terms;
+    if penalty_bps > MAX_PENALTY_BPS {
+        return Err(EscrowError::InvalidPenalty.into());
+    }
+
+    let &InitializeAccounts {
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        escrow_ata,
+        token_program,
+        ..
+    } = accounts;
+    // Check that no account is passed in two roles, e.g. `sender_ata` as
+    // `escrow_ata`.
+    require_distinct(&[sender, sender_ata, receiver, escrow, escrow_ata])?;
 
     // Check that `sender_ata` is owned by `sender`.
-    if TokenAccount::from_account_info(sender_ata)?.owner() != sender.key() {
+    if !keys_eq(
+        &token_account_owner(sender_ata, token_program)?,
+        sender.key(),
+    ) {
         return Err(ProgramError::IllegalOwner);
     }
     // Check that `escrow_ata` is owned by `escrow`.
-    if TokenAccount::from_account_info(escrow_ata)?.owner() != escrow.key() {
+    if !keys_eq(
+        &token_account_owner(escrow_ata, token_program)?,
+        escrow.key(),
+    ) {
         return Err(ProgramError::IllegalOwner);
     }
 
-    // Deserialize instruction data.
-    let instruction_data: &InitializeInstructionData =
-        unsafe { &*instruction_data.as_ptr().cast() };
-
-    // Check the seeds of `escrow`.
-    let escrow_pda = create_program_address(
-        &[
-            ESCROW_SEED.as_bytes(),
-            sender.key(),
-            receiver.key(),
-            &[instruction_data.bump],
-        ],
+    // Create the escrow PDA, signing for it with its seeds, unless the
+    // address is already in use.
+    let bump = [bump];
+    let escrow_seeds = [
+        Seed::from(ESCROW_SEED.as_bytes()),
+        Seed::from(sender.key()),
+        Seed::from(receiver.key()),
+        Seed::from(&bump),
+    ];
+    create_account(
+        sender,
+        escrow,
+        Escrow::LEN,
         &ID,
+        &[Signer::from(&escrow_seeds)],
     )?;
-    if escrow.key() != &escrow_pda {
-        return Err(ProgramError::InvalidSeeds);
-    }
 
-    // Create the escrow PDA.
-    CreateAccount {
-        from: &sender,
-        to: &escrow,
-        lamports: Rent::get()?.minimum_balance(Escrow::LEN),
-        space: Escrow::LEN as u64,
-        owner: &ID,
-    }
-    .invoke()?;
-
-    // Deserialize the escrow PDA.
-    let mut data = escrow.try_borrow_mut_data()?;
-    let data: &mut Escrow = unsafe { &mut *data.as_mut_ptr().cast() };
+    // Deserialize the new escrow PDA, writing its prefix.
+    let mut data = Escrow::init(escrow)?;
 
     // Initialize the escrow.
-    data.sender = *sender.key();
-    data.receiver = *receiver.key();
+    *data = Escrow {
+        burn_on_cancel: burn_on_cancel as u8,
+        nft: nft as u8,
+        expiry_slot,
+        ..Escrow::new(*sender.key(), *receiver.key(), amount, penalty_bps)
+    };
 
     // Transfer token from sender to escrow.
-    Transfer {
-        from: &sender_ata,
-        to: &escrow_ata,
-        authority: &sender,
-        amount: instruction_data.amount,
+    match mint {
+        Some((mint, decimals)) => TransferChecked {
+            from: &sender_ata,
+            mint,
+            to: &escrow_ata,
+            authority: &sender,
+            token_program,
+            amount,
+            decimals,
+        }
+        .invoke()?,
+        None => Transfer {
+            from: &sender_ata,
+            to: &escrow_ata,
+            authority: &sender,
+            token_program,
+            amount,
+        }
+        .invoke()?,
     }
-    .invoke()?;
 
-    log!("Initialized escrow");
+    info!("Initialized escrow");
+    emit!(EscrowInitialized {
+        sender: data.sender,
+        receiver: data.receiver,
+        amount: data.amount,
+    });
 
     Ok(())
 }
 
-pub fn process_exchange(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-    // Retrieve and validate the accounts.
-    let [sender, receiver, receiver_ata, escrow, escrow_ata, _system_program, _token_program] =
-        accounts
-    else {
-        return Err(ProgramError::NotEnoughAccountKeys);
-    };
+pub fn process_initialize_delegated(
+    accounts: &[AccountInfo],
+    version: Version,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Deserialize instruction data, the same as the one of the
+    // initialization. Tokens of the sender can't be burnt on cancellation,
+    // nor refunded on expiry as they never leave the sender, so it has no
+    // version 3 nor 4.
+    if matches!(version, Version::V3 | Version::V4) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (
+        bump,
+        Terms {
+            amount,
+            penalty_bps,
+            ..
+        },
+    ) = initialize_instruction_data(version, instruction_data)?;
+    if penalty_bps > MAX_PENALTY_BPS {
+        return Err(EscrowError::InvalidPenalty.into());
+    }
 
-    // Check that `receiver_ata` is owned by `receiver`.
-    if TokenAccount::from_account_info(receiver_ata)?.owner() != receiver.key() {
+    // Retrieve and validate the accounts, checking that `sender` authorized
+    // the delegation, the seeds of `escrow` and the programs.
+    let InitializeDelegatedAccounts {
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        token_program,
+        ..
+    } = InitializeDelegatedAccounts::from_accounts(accounts, bump)?;
+    // Check that no account is passed in two roles, e.g. `sender` as
+    // `receiver`.
+    require_distinct(&[sender, sender_ata, receiver, escrow])?;
+
+    // Check that `sender_ata` is owned by `sender` and holds the escrowed
+    // tokens, which only leave it at the exchange.
+    if !keys_eq(
+        &token_account_owner(sender_ata, token_program)?,
+        sender.key(),
+    ) {
         return Err(ProgramError::IllegalOwner);
     }
-    // Check that `escrow_ata` is owned by `escrow`.
-    if TokenAccount::from_account_info(escrow_ata)?.owner() != escrow.key() {
-        return Err(ProgramError::IllegalOwner);
+    if token_account_amount(sender_ata)? < amount {
+        return Err(EscrowError::InsufficientEscrowBalance.into());
+    }
+    // A token account has a single delegate, so approving the escrow would
+    // silently revoke the delegation of another escrow or program.
+    if token_account_delegate(sender_ata)?.is_some() {
+        return Err(EscrowError::AlreadyDelegated.into());
     }
 
-    // Deserialize instruction data.
-    let instruction_data: &FinalizeInstructionData = unsafe { &*instruction_data.as_ptr().cast() };
-
-    // Check the seeds of `escrow`.
-    let escrow_pda = create_program_address(
-        &[
-            ESCROW_SEED.as_bytes(),
-            sender.key(),
-            receiver.key(),
-            &[instruction_data.bump],
-        ],
+    // Create the escrow PDA, signing for it with its seeds, unless the
+    // address is already in use.
+    let bump = [bump];
+    let escrow_seeds = [
+        Seed::from(ESCROW_SEED.as_bytes()),
+        Seed::from(sender.key()),
+        Seed::from(receiver.key()),
+        Seed::from(&bump),
+    ];
+    create_account(
+        sender,
+        escrow,
+        Escrow::LEN,
         &ID,
+        &[Signer::from(&escrow_seeds)],
     )?;
-    if escrow.key() != &escrow_pda {
-        return Err(ProgramError::InvalidSeeds);
+
+    // Deserialize the new escrow PDA, writing its prefix, and initialize the
+    // escrow. The borrow ends before the escrow is passed to the token
+    // program.
+    *Escrow::init(escrow)? = Escrow {
+        delegated: 1,
+        ..Escrow::new(*sender.key(), *receiver.key(), amount, penalty_bps)
+    };
+
+    // Approve the escrow as the delegate of the escrowed tokens, which the
+    // sender keeps until the exchange.
+    Approve {
+        source: &sender_ata,
+        delegate: &escrow,
+        owner: &sender,
+        token_program,
+        amount,
+    }
+    .invoke()?;
+
+    info!("Initialized delegated escrow");
+    emit!(EscrowInitialized {
+        sender: *sender.key(),
+        receiver: *receiver.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+pub fn process_exchange(
+    accounts: &[AccountInfo],
+    version: Version,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Deserialize instruction data.
+    let (bump, expected_amount) = finalize_instruction_data(version, instruction_data)?;
+
+    // An optional seventh account is the metadata account of the mint of
+    // the escrowed tokens, checked if it's passed.
+    let (accounts, metadata) = match accounts {
+        [accounts @ .., metadata] if accounts.len() == 6 => (accounts, Some(metadata)),
+        accounts => (accounts, None),
+    };
+
+    // Retrieve and validate the accounts, checking that `receiver` accepted
+    // the exchange, the seeds of `escrow` and the token program.
+    let accounts = ExchangeAccounts::from_accounts(accounts, bump)?;
+
+    release(&accounts, bump, expected_amount, metadata)
+}
+
+/// Releases the escrowed tokens of the escrow of `accounts` to the
+/// receiver, signing for the escrow PDA with its `bump`, unless the escrow
+/// holds another amount than `expected_amount` or `metadata` isn't the
+/// metadata account of their mint.
+fn release(
+    accounts: &ExchangeAccounts,
+    bump: u8,
+    expected_amount: Option<u64>,
+    metadata: Option<&AccountInfo>,
+) -> ProgramResult {
+    let &ExchangeAccounts {
+        sender,
+        receiver,
+        receiver_ata,
+        escrow,
+        escrow_ata,
+        token_program,
+    } = accounts;
+    // Check that no account is passed in two roles, e.g. `escrow_ata` as
+    // `receiver_ata`.
+    require_distinct(&[sender, receiver, receiver_ata, escrow, escrow_ata])?;
+
+    // Check that `receiver_ata` is owned by `receiver`.
+    if !keys_eq(
+        &token_account_owner(receiver_ata, token_program)?,
+        receiver.key(),
+    ) {
+        return Err(ProgramError::IllegalOwner);
     }
+    let escrow_ata_owner = token_account_owner(escrow_ata, token_program)?;
 
-    // Deserialize the escrow PDA.
-    let data = escrow.try_borrow_data()?;
-    let data: &Escrow = unsafe { &*data.as_ptr().cast() };
+    // Deserialize the escrow PDA, checking that it's owned by the program.
+    // Escrows created before the expiry slot was added are accepted too.
+    #[cfg(feature = "checked-borrows")]
+    let data = Escrow::load_versioned(escrow)?;
+    // SAFETY: Nothing borrows the escrow PDA mutably during the exchange.
+    // It's passed read-only to the token program, which doesn't own it.
+    #[cfg(not(feature = "checked-borrows"))]
+    let data = unsafe { Escrow::load_versioned_unchecked(escrow)? };
 
     // Check that `receiver` is the same as in the escrow account.
-    if &data.receiver != receiver.key() {
+    if !keys_eq(&data.receiver, receiver.key()) {
         return Err(ProgramError::IllegalOwner);
     }
+    if data.delegated == 0 {
+        // Check that `escrow_ata` is owned by `escrow`.
+        if !keys_eq(&escrow_ata_owner, escrow.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+    } else {
+        // The tokens of a delegated escrow are still in `escrow_ata`, a
+        // token account of the sender, which has to delegate them to
+        // `escrow`. The sender may have revoked or replaced the delegation
+        // since.
+        if !keys_eq(&escrow_ata_owner, &data.sender) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        match token_account_delegate(escrow_ata)? {
+            Some((delegate, amount))
+                if keys_eq(&delegate, escrow.key()) && amount >= data.amount => {}
+            _ => return Err(EscrowError::MissingDelegation.into()),
+        }
+    }
+    // Check that the escrow holds the amount `receiver` expects, which may
+    // have changed if the sender cancelled and re-created it since.
+    if expected_amount.is_some_and(|amount| amount != data.amount) {
+        return Err(EscrowError::AmountMismatch.into());
+    }
+    // Check that `escrow_ata` still holds the escrowed tokens. It may hold
+    // more, as anyone can send tokens to it, but only the escrowed amount is
+    // transferred out, so such dust can't block the exchange.
+    if token_account_amount(escrow_ata)? < data.amount {
+        return Err(EscrowError::InsufficientEscrowBalance.into());
+    }
+    // Check that the mint of the escrowed tokens has the metadata account
+    // `receiver` expects, e.g. the one of an NFT shown by a marketplace.
+    if let Some(metadata) = metadata {
+        require_metadata(metadata, &token_account_mint(escrow_ata)?)?;
+    }
 
-    // Transfer tokens from escrow to recipient.
+    // Transfer tokens from escrow to recipient, signing for `escrow` with
+    // its seeds, as the owner or the delegate of `escrow_ata`.
+    let bump = [bump];
+    let escrow_seeds = [
+        Seed::from(ESCROW_SEED.as_bytes()),
+        Seed::from(sender.key()),
+        Seed::from(receiver.key()),
+        Seed::from(&bump),
+    ];
     Transfer {
         from: &escrow_ata,
         to: &receiver_ata,
         authority: &escrow,
+        token_program,
         amount: data.amount,
     }
-    .invoke()?;
+    .invoke_signed(&[Signer::from(&escrow_seeds)])?;
 
-    log!("Exchanged {} tokens", data.amount);
+    info!("Exchanged {} tokens", data.amount);
+    emit!(EscrowExchanged {
+        sender: data.sender,
+        receiver: data.receiver,
+        amount: data.amount,
+    });
 
     Ok(())
 }
 
-pub fn process_cancel(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-    // Retrieve and validate the accounts.
-    let [sender, sender_ata, receiver, escrow, escrow_ata, _system_program, _token_program] =
-        accounts
-    else {
+pub fn process_exchange_batch(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Deserialize instruction data, the bump of each escrow PDA.
+    let bumps = instruction_data;
+    if bumps.is_empty() || bumps.len() > MAX_BATCH_ESCROWS {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Retrieve and validate the accounts shared by the escrows, checking
+    // that `receiver` accepted the exchanges and the token program.
+    if accounts.len() != 3 + 3 * bumps.len() {
         return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let (accounts, escrows) = accounts.split_at(3);
+    let ExchangeBatchAccounts {
+        receiver,
+        receiver_ata,
+        token_program,
+    } = ExchangeBatchAccounts::from_accounts(accounts)?;
+
+    let mut exchanged = 0u64;
+    for (accounts, &bump) in escrows.chunks_exact(3).zip(bumps) {
+        // Stop before an exchange which may not fit into the remaining
+        // compute units, leaving the rest of the escrows to another batch.
+        if remaining_compute_units() < EXCHANGE_COMPUTE_UNITS {
+            break;
+        }
+
+        // Retrieve and validate the accounts of the escrow, checking its
+        // seeds. Accounts of different escrows are distinct, as the
+        // entrypoint rejects duplicates.
+        let BatchEscrowAccounts {
+            sender,
+            escrow,
+            escrow_ata,
+        } = BatchEscrowAccounts::from_accounts(accounts, bump, receiver.key())?;
+
+        release(
+            &ExchangeAccounts {
+                sender,
+                receiver,
+                receiver_ata,
+                escrow,
+                escrow_ata,
+                token_program,
+            },
+            bump,
+            None,
+            None,
+        )?;
+        exchanged += 1;
+    }
+
+    // Tell the caller which escrows are left, the ones after the exchanged
+    // ones.
+    set_return_data(&exchanged.to_le_bytes());
+
+    info!("Exchanged {} of {} escrows", exchanged, bumps.len());
+
+    Ok(())
+}
+
+pub fn process_cancel(
+    accounts: &[AccountInfo],
+    version: Version,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Deserialize instruction data.
+    let (bump, expected_amount) = finalize_instruction_data(version, instruction_data)?;
+
+    // An optional eighth account is the mint of the escrowed tokens, which
+    // only escrows burning on cancellation need.
+    let (accounts, mint) = match accounts {
+        [accounts @ .., mint] if accounts.len() == 7 => (accounts, Some(mint)),
+        accounts => (accounts, None),
     };
 
+    // Retrieve and validate the accounts, checking that `sender` cancelled
+    // the escrow, the seeds of `escrow` and the token program.
+    let CancelAccounts {
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        escrow_ata,
+        token_program,
+        receiver_ata,
+    } = CancelAccounts::from_accounts(accounts, bump)?;
+    // Check that no account is passed in two roles, e.g. `escrow_ata` as
+    // `sender_ata`.
+    require_distinct(&[
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        escrow_ata,
+        receiver_ata,
+    ])?;
+
     // Check that `sender_ata` is owned by `sender`.
-    if TokenAccount::from_account_info(sender_ata)?.owner() != sender.key() {
+    if !keys_eq(
+        &token_account_owner(sender_ata, token_program)?,
+        sender.key(),
+    ) {
         return Err(ProgramError::IllegalOwner);
     }
     // Check that `escrow_ata` is owned by `escrow`.
-    if TokenAccount::from_account_info(escrow_ata)?.owner() != escrow.key() {
+    if !keys_eq(
+        &token_account_owner(escrow_ata, token_program)?,
+        escrow.key(),
+    ) {
         return Err(ProgramError::IllegalOwner);
     }
 
-    // Deserialize instruction data.
-    let instruction_data: &FinalizeInstructionData = unsafe { &*instruction_data.as_ptr().cast() };
+    // Deserialize the escrow PDA, checking that it's owned by the program.
+    // Escrows created before the expiry slot was added are accepted too.
+    #[cfg(feature = "checked-borrows")]
+    let data = Escrow::load_versioned(escrow)?;
+    // SAFETY: Nothing borrows the escrow PDA mutably during the cancellation.
+    // It's passed read-only to the token program, which doesn't own it.
+    #[cfg(not(feature = "checked-borrows"))]
+    let data = unsafe { Escrow::load_versioned_unchecked(escrow)? };
+
+    // Check that escrow was initailized by `sender`.
+    if !keys_eq(&data.sender, sender.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    // Delegated escrows hold no tokens, see `process_cancel_delegated`.
+    if data.delegated != 0 {
+        return Err(EscrowError::DelegationMismatch.into());
+    }
+    // Check that the escrow holds the amount `sender` expects to be
+    // refunded.
+    if expected_amount.is_some_and(|amount| amount != data.amount) {
+        return Err(EscrowError::AmountMismatch.into());
+    }
+    // Check that `escrow_ata` still holds the escrowed tokens. Any tokens
+    // on top of them stay in it.
+    if token_account_amount(escrow_ata)? < data.amount {
+        return Err(EscrowError::InsufficientEscrowBalance.into());
+    }
 
-    // Check the seeds of `escrow`.
-    let escrow_pda = create_program_address(
-        &[
-            ESCROW_SEED.as_bytes(),
-            sender.key(),
+    // The escrow PDA signs the transfers and the burn with its seeds.
+    let bump = [bump];
+    let escrow_seeds = [
+        Seed::from(ESCROW_SEED.as_bytes()),
+        Seed::from(sender.key()),
+        Seed::from(receiver.key()),
+        Seed::from(&bump),
+    ];
+
+    // Pay the penalty to the receiver, if it accepted the escrow.
+    let penalty = data.penalty();
+    if penalty > 0 {
+        // Check that `receiver_ata` is owned by `receiver`.
+        if !keys_eq(
+            &token_account_owner(receiver_ata, token_program)?,
             receiver.key(),
-            &[instruction_data.bump],
-        ],
-        &ID,
-    )?;
-    if escrow.key() != &escrow_pda {
-        return Err(ProgramError::InvalidSeeds);
+        ) {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        Transfer {
+            from: &escrow_ata,
+            to: &receiver_ata,
+            authority: &escrow,
+            token_program,
+            amount: penalty,
+        }
+        .invoke_signed(&[Signer::from(&escrow_seeds)])?;
     }
 
-    let data = escrow.try_borrow_data()?;
-    let data: &Escrow = unsafe { &*data.as_ptr().cast() };
+    let rest = data.amount - penalty;
+    if data.burn_on_cancel == 0 {
+        // Transfer the rest of the tokens from escrow to sender.
+        Transfer {
+            from: &escrow_ata,
+            to: &sender_ata,
+            authority: &escrow,
+            token_program,
+            amount: rest,
+        }
+        .invoke_signed(&[Signer::from(&escrow_seeds)])?;
 
-    // Check that escrow was initailized by `sender`.
-    if &data.sender != sender.key() {
+        info!(
+            "Cancelled escrow, refunded {} tokens with a penalty of {}",
+            rest, penalty
+        );
+    } else {
+        // Burn the rest of the tokens. The token program checks that `mint`
+        // is the mint of `escrow_ata`.
+        let mint = mint.ok_or(ProgramError::NotEnoughAccountKeys)?;
+        Burn {
+            account: &escrow_ata,
+            mint,
+            authority: &escrow,
+            token_program,
+            amount: rest,
+        }
+        .invoke_signed(&[Signer::from(&escrow_seeds)])?;
+
+        info!(
+            "Cancelled escrow, burnt {} tokens with a penalty of {}",
+            rest, penalty
+        );
+    }
+    emit!(EscrowCancelled {
+        sender: data.sender,
+        receiver: data.receiver,
+        amount: data.amount,
+        penalty,
+    });
+
+    Ok(())
+}
+
+pub fn process_cancel_delegated(
+    accounts: &[AccountInfo],
+    version: Version,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Deserialize instruction data, the same as the one of the cancellation.
+    let (bump, expected_amount) = finalize_instruction_data(version, instruction_data)?;
+
+    // Retrieve and validate the accounts, checking that `sender` cancelled
+    // the escrow, the seeds of `escrow` and the token program.
+    let CancelDelegatedAccounts {
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        token_program,
+        receiver_ata,
+    } = CancelDelegatedAccounts::from_accounts(accounts, bump)?;
+    // Check that no account is passed in two roles, e.g. `receiver_ata` as
+    // `sender_ata`.
+    require_distinct(&[sender, sender_ata, receiver, escrow, receiver_ata])?;
+
+    // Check that `sender_ata` is owned by `sender`.
+    if !keys_eq(
+        &token_account_owner(sender_ata, token_program)?,
+        sender.key(),
+    ) {
         return Err(ProgramError::IllegalOwner);
     }
 
-    // Transfer tokens from escrow to sender.
+    // Deserialize the escrow PDA, checking that it's owned by the program.
+    // Escrows created before the expiry slot was added are accepted too.
+    #[cfg(feature = "checked-borrows")]
+    let data = Escrow::load_versioned(escrow)?;
+    // SAFETY: Nothing borrows the escrow PDA mutably during the
+    // cancellation. The token program doesn't get it.
+    #[cfg(not(feature = "checked-borrows"))]
+    let data = unsafe { Escrow::load_versioned_unchecked(escrow)? };
+
+    // Check that escrow was initialized by `sender` and is delegated.
+    if !keys_eq(&data.sender, sender.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if data.delegated == 0 {
+        return Err(EscrowError::DelegationMismatch.into());
+    }
+    // Check that the escrow holds the amount `sender` expects to be
+    // released from the delegation.
+    if expected_amount.is_some_and(|amount| amount != data.amount) {
+        return Err(EscrowError::AmountMismatch.into());
+    }
+
+    // Pay the penalty to the receiver, if it accepted the escrow. The sender
+    // signs the transfer as the owner of `sender_ata`, so revoking the
+    // delegation beforehand doesn't get around the penalty.
+    let penalty = data.penalty();
+    if penalty > 0 {
+        // Check that `receiver_ata` is owned by `receiver`.
+        if !keys_eq(
+            &token_account_owner(receiver_ata, token_program)?,
+            receiver.key(),
+        ) {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        Transfer {
+            from: &sender_ata,
+            to: &receiver_ata,
+            authority: &sender,
+            token_program,
+            amount: penalty,
+        }
+        .invoke()?;
+    }
+
+    // Revoke the delegation, unless the sender already revoked it or
+    // delegated the tokens to someone else since, whose delegation stays.
+    if matches!(
+        token_account_delegate(sender_ata)?,
+        Some((delegate, _)) if keys_eq(&delegate, escrow.key())
+    ) {
+        Revoke {
+            source: &sender_ata,
+            owner: &sender,
+            token_program,
+        }
+        .invoke()?;
+    }
+
+    info!("Cancelled delegated escrow with a penalty of {}", penalty);
+    emit!(EscrowCancelled {
+        sender: data.sender,
+        receiver: data.receiver,
+        amount: data.amount,
+        penalty,
+    });
+
+    Ok(())
+}
+
+pub fn process_accept(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Deserialize instruction data.
+    let instruction_data = FinalizeInstructionData::from_bytes(instruction_data)?;
+
+    // Retrieve and validate the accounts, checking that `receiver` accepted
+    // the escrow and the seeds of `escrow`.
+    let AcceptAccounts {
+        sender,
+        receiver,
+        escrow,
+    } = AcceptAccounts::from_accounts(accounts, instruction_data.bump)?;
+    require_distinct(&[sender, receiver, escrow])?;
+
+    // Deserialize the escrow PDA, checking that it's owned by the program.
+    // Escrows created before the expiry slot was added are accepted too.
+    let data = Escrow::load_versioned(escrow)?;
+
+    // Check that `receiver` is the same as in the escrow account.
+    if !keys_eq(&data.receiver, receiver.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    // Accepting again changes nothing. Only the flag is written, as the
+    // legacy layout can't be borrowed as the current one.
+    escrow.try_borrow_mut_data()?[Escrow::ACCEPTED_OFFSET] = 1;
+
+    info!("Accepted escrow");
+    emit!(EscrowAccepted {
+        sender: data.sender,
+        receiver: data.receiver,
+    });
+
+    Ok(())
+}
+
+pub fn process_refund_expired(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Deserialize instruction data.
+    let instruction_data = FinalizeInstructionData::from_bytes(instruction_data)?;
+
+    // Retrieve and validate the accounts, checking the seeds of `escrow` and
+    // the token program. Nobody has to sign.
+    let RefundExpiredAccounts {
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        escrow_ata,
+        token_program,
+    } = RefundExpiredAccounts::from_accounts(accounts, instruction_data.bump)?;
+    // Check that no account is passed in two roles, e.g. `escrow_ata` as
+    // `sender_ata`.
+    require_distinct(&[sender, sender_ata, receiver, escrow, escrow_ata])?;
+
+    // Check that `sender_ata` is owned by `sender`, as whoever refunds the
+    // escrow picks it.
+    if !keys_eq(
+        &token_account_owner(sender_ata, token_program)?,
+        sender.key(),
+    ) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    // Check that `escrow_ata` is owned by `escrow`.
+    if !keys_eq(
+        &token_account_owner(escrow_ata, token_program)?,
+        escrow.key(),
+    ) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    // Deserialize the escrow PDA, checking that it's owned by the program.
+    // Escrows created before the expiry slot was added are accepted too.
+    #[cfg(feature = "checked-borrows")]
+    let data = Escrow::load_versioned(escrow)?;
+    // SAFETY: Nothing borrows the escrow PDA mutably while it's read.
+    #[cfg(not(feature = "checked-borrows"))]
+    let data = unsafe { Escrow::load_versioned_unchecked(escrow)? };
+
+    // Check that escrow was initialized by `sender`.
+    if !keys_eq(&data.sender, sender.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    // Delegated escrows hold no tokens and never expire.
+    if data.delegated != 0 {
+        return Err(EscrowError::DelegationMismatch.into());
+    }
+    // Check that the escrow expired.
+    if !data.is_expired(Clock::get()?.slot) {
+        return Err(EscrowError::NotExpired.into());
+    }
+    // Check that `escrow_ata` still holds the escrowed tokens. Any tokens
+    // on top of them stay in it, so dust can't lock an expired escrow.
+    if token_account_amount(escrow_ata)? < data.amount {
+        return Err(EscrowError::InsufficientEscrowBalance.into());
+    }
+
+    // Transfer all the escrowed tokens from escrow to sender, signing for
+    // `escrow` with its seeds.
+    let bump = [instruction_data.bump];
+    let escrow_seeds = [
+        Seed::from(ESCROW_SEED.as_bytes()),
+        Seed::from(sender.key()),
+        Seed::from(receiver.key()),
+        Seed::from(&bump),
+    ];
     Transfer {
         from: &escrow_ata,
         to: &sender_ata,
         authority: &escrow,
+        token_program,
         amount: data.amount,
     }
-    .invoke()?;
+    .invoke_signed(&[Signer::from(&escrow_seeds)])?;
 
-    log!("Cancelled escrow, refunded {} tokens", data.amount);
+    info!("Refunded {} tokens of expired escrow", data.amount);
+    emit!(EscrowCancelled {
+        sender: data.sender,
+        receiver: data.receiver,
+        amount: data.amount,
+        penalty: 0,
+    });
 
-    Ok(())
+    // Close the escrow PDA, refunding its rent to `sender`.
+    close_account(escrow, sender)
 }
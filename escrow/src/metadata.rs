@@ -0,0 +1,46 @@
+//! Metadata accounts of NFTs.
+//!
+//! NFTs usually come with a metadata account of the Metaplex Token Metadata
+//! program, its PDA derived from the mint, holding their name, symbol and
+//! URI. Wallets and marketplaces show an NFT by its metadata, so an
+//! exchange can check that the escrowed NFT has one, which a token only
+//! minted to look like an NFT may not.
+
+use common::find_and_check_pda;
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+
+/// Address of the Metaplex Token Metadata program.
+pub const METADATA_PROGRAM_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+pub const METADATA_SEED: &'static str = "metadata";
+
+/// `Key::MetadataV1`, the first byte of metadata accounts.
+const KEY_METADATA_V1: u8 = 4;
+
+/// Checks that `metadata` is the metadata account of `mint`.
+///
+/// The bump of the PDA isn't known, so it's searched for, which costs
+/// more compute units than the other checks of an exchange.
+///
+/// Fails with [`ProgramError::IllegalOwner`] if `metadata` isn't owned by
+/// the Token Metadata program, with [`ProgramError::InvalidAccountData`] if
+/// it isn't a metadata account and with [`ProgramError::InvalidSeeds`] if
+/// it isn't the one of `mint`.
+pub fn require_metadata(metadata: &AccountInfo, mint: &Pubkey) -> ProgramResult {
+    if !metadata.is_owned_by(&METADATA_PROGRAM_ID) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if metadata.try_borrow_data()?.first() != Some(&KEY_METADATA_V1) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    find_and_check_pda(
+        &[METADATA_SEED.as_bytes(), &METADATA_PROGRAM_ID, mint],
+        metadata.key(),
+        &METADATA_PROGRAM_ID,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! State of the escrow accounts.
+
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "bytemuck")]
+use bytemuck::{Pod, Zeroable};
+use common::{assert_layout, AccountData};
+#[cfg(feature = "anchor")]
+use macros::anchor_discriminator;
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use shank::ShankAccount;
+
+use crate::ID;
+
+pub const ESCROW_SEED: &'static str = "escrow";
+
+/// Basis points denominator of the cancellation penalty.
+pub const MAX_PENALTY_BPS: u16 = 10_000;
+
+#[derive(Clone, Copy, ShankAccount)]
+#[cfg_attr(feature = "bytemuck", derive(Pod, Zeroable))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[repr(C)]
+pub struct Escrow {
+    #[cfg_attr(feature = "serde", serde(with = "common::base58"))]
+    pub sender: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "common::base58"))]
+    pub receiver: Pubkey,
+    pub amount: u64,
+    /// Share of the amount paid to the receiver if the sender cancels after
+    /// the receiver accepted, in basis points.
+    pub penalty_bps: u16,
+    /// Whether the receiver accepted the escrow, 1 if it did and 0 if not.
+    pub accepted: u8,
+    /// Whether the escrowed tokens stay in the token account of the sender,
+    /// which delegates them to the escrow, 1 if they do and 0 if they're
+    /// deposited into the token account of the escrow.
+    pub delegated: u8,
+    /// Whether a cancellation burns the escrowed tokens instead of refunding
+    /// them, 1 if it does and 0 if not.
+    pub burn_on_cancel: u8,
+    /// Whether the escrowed token is an NFT, the only token of a mint
+    /// without decimals, 1 if it is and 0 if not.
+    pub nft: u8,
+    pub _padding: [u8; 2],
+    /// Slot after which anyone can refund the escrowed tokens to the sender,
+    /// or 0 if the escrow never expires.
+    pub expiry_slot: u64,
+}
+
+impl Escrow {
+    /// Length of the data of escrows created before [`Escrow::expiry_slot`]
+    /// was added. Their layout is the current one without it.
+    pub const LEGACY_LEN: usize = Self::LEN - size_of::<u64>();
+    /// Offset of [`Escrow::accepted`] in the data of escrows, the same in
+    /// both layouts.
+    pub const ACCEPTED_OFFSET: usize = Self::PREFIX.len() + offset_of!(Escrow, accepted);
+
+    /// Creates an escrow of `amount` tokens deposited into its token
+    /// account, not accepted yet and never expiring.
+    pub fn new(sender: Pubkey, receiver: Pubkey, amount: u64, penalty_bps: u16) -> Self {
+        Self {
+            sender,
+            receiver,
+            amount,
+            penalty_bps,
+            accepted: 0,
+            delegated: 0,
+            burn_on_cancel: 0,
+            nft: 0,
+            _padding: [0; 2],
+            expiry_slot: 0,
+        }
+    }
+
+    /// Returns whether the escrow expired at `slot`, which it only does
+    /// once `slot` is past its expiry slot.
+    pub fn is_expired(&self, slot: u64) -> bool {
+        self.expiry_slot != 0 && slot > self.expiry_slot
+    }
+
+    /// Returns the part of the amount paid to the receiver if the sender
+    /// cancels now, rounded down. It's zero until the receiver accepted.
+    pub fn penalty(&self) -> u64 {
+        if self.accepted == 0 {
+            return 0;
+        }
+        (self.amount as u128 * self.penalty_bps as u128 / MAX_PENALTY_BPS as u128) as u64
+    }
+
+    /// Copies the state out of `data`, which may have the legacy layout of
+    /// [`Escrow::LEGACY_LEN`] bytes. Such an escrow never expires.
+    pub fn read_versioned(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEGACY_LEN {
+            return Self::read(data);
+        }
+        let mut padded = [0; Escrow::LEN];
+        padded[..Self::LEGACY_LEN].copy_from_slice(data);
+        Self::read(&padded)
+    }
+
+    /// Copies the state out of `account`, checking that it's owned by the
+    /// program. Unlike [`AccountData::load`], it accepts the legacy layout.
+    pub fn load_versioned(account: &AccountInfo) -> Result<Self, ProgramError> {
+        if !account.is_owned_by(&Self::OWNER) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        Self::read_versioned(&account.try_borrow_data()?)
+    }
+
+    /// Copies the state out of `account` without tracking the borrow, like
+    /// [`AccountData::load_unchecked`].
+    ///
+    /// # Safety
+    ///
+    /// The data of `account` must not be borrowed mutably during the call.
+    pub unsafe fn load_versioned_unchecked(account: &AccountInfo) -> Result<Self, ProgramError> {
+        if !account.is_owned_by(&Self::OWNER) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        Self::read_versioned(account.borrow_data_unchecked())
+    }
+}
+
+unsafe impl AccountData for Escrow {
+    const OWNER: Pubkey = ID;
+    #[cfg(feature = "anchor")]
+    const PREFIX: &'static [u8] = &anchor_discriminator!("account:Escrow");
+}
+
+assert_layout!(Escrow, size = 88, align = 8, {
+    sender: 0,
+    receiver: 32,
+    amount: 64,
+    penalty_bps: 72,
+    accepted: 74,
+    delegated: 75,
+    burn_on_cancel: 76,
+    nft: 77,
+    expiry_slot: 80,
+});
@@ -0,0 +1,479 @@
+//! Token accounts and transfers of either token program.
+//!
+//! The escrow holds tokens of the SPL Token or the Token-2022 program, the
+//! one passed to its instructions. `pinocchio-token` only supports the
+//! original token program, so the transfers, delegations and burns are
+//! built by hand, the same way `pinocchio-token` does it, and invoke the passed
+//! program.
+//!
+//! The token accounts are read and passed to the token program without
+//! pinocchio's borrow tracking, unless the `checked-borrows` feature is
+//! enabled. The escrow never borrows their data mutably and only the token
+//! program writes them, during the transfer, when the escrow holds no
+//! borrow of them anymore.
+
+use common::keys_eq;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::{invoke_signed, invoke_signed_unchecked},
+    instruction::{Account, AccountMeta, Instruction, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    ProgramResult,
+};
+use pinocchio_token::state::TokenAccount;
+
+/// Token-2022 program ID.
+pub const TOKEN_2022_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Offset of the mint in a token account.
+const MINT_OFFSET: usize = 0;
+
+/// Offset of the owner in a token account.
+const OWNER_OFFSET: usize = 32;
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+
+/// Offset of the delegate in a token account, a `COption<Pubkey>` whose
+/// 4-byte tag is zero if there's none.
+const DELEGATE_OFFSET: usize = 72;
+
+/// Offset of the amount the delegate may transfer in a token account.
+const DELEGATED_AMOUNT_OFFSET: usize = 121;
+
+/// `AccountType::Account` of Token-2022, following the base account when
+/// it has extensions.
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+/// `AccountType::Mint` of Token-2022, following the padding of a mint with
+/// extensions to the length of a token account.
+const ACCOUNT_TYPE_MINT: u8 = 1;
+
+/// Length of a mint without extensions.
+const MINT_LEN: usize = 82;
+
+/// Offset of the supply in a mint.
+const SUPPLY_OFFSET: usize = 36;
+
+/// Offset of the decimals in a mint.
+const DECIMALS_OFFSET: usize = 44;
+
+/// Offset of the flag telling whether a mint is initialized.
+const IS_INITIALIZED_OFFSET: usize = 45;
+
+/// Checks that `account` is the SPL Token or the Token-2022 program.
+///
+/// Fails with [`ProgramError::IncorrectProgramId`].
+pub fn require_token_program(account: &AccountInfo) -> ProgramResult {
+    if !keys_eq(account.key(), &pinocchio_token::ID) && !keys_eq(account.key(), &TOKEN_2022_ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Returns the owner of `account`, a token account of `token_program`.
+///
+/// Fails with [`ProgramError::InvalidAccountData`] if `account` isn't a
+/// token account of `token_program`.
+pub fn token_account_owner(
+    account: &AccountInfo,
+    token_program: &AccountInfo,
+) -> Result<Pubkey, ProgramError> {
+    if !account.is_owned_by(token_program.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    read_data(account, |data| {
+        // Token-2022 accounts with extensions are longer, which mints with
+        // extensions are too, so their account type tells them apart.
+        if data.len() != TokenAccount::LEN
+            && data.get(TokenAccount::LEN) != Some(&ACCOUNT_TYPE_ACCOUNT)
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut owner = Pubkey::default();
+        owner.copy_from_slice(&data[OWNER_OFFSET..OWNER_OFFSET + 32]);
+        Ok(owner)
+    })
+}
+
+/// Returns the mint of `account`, a token account already checked with
+/// [`token_account_owner`].
+pub fn token_account_mint(account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    read_data(account, |data| {
+        let mint = data
+            .get(MINT_OFFSET..MINT_OFFSET + 32)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Ok(mint.try_into().unwrap())
+    })
+}
+
+/// Returns the supply and the decimals of `mint`, an initialized mint of
+/// `token_program`.
+///
+/// Fails with [`ProgramError::InvalidAccountData`] if `mint` isn't a mint
+/// of `token_program` and with [`ProgramError::UninitializedAccount`] if
+/// it isn't initialized.
+pub fn mint_supply_and_decimals(
+    mint: &AccountInfo,
+    token_program: &AccountInfo,
+) -> Result<(u64, u8), ProgramError> {
+    if !mint.is_owned_by(token_program.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    read_data(mint, |data| {
+        // Token-2022 mints with extensions are padded to the length of a
+        // token account, followed by their account type.
+        if data.len() != MINT_LEN && data.get(TokenAccount::LEN) != Some(&ACCOUNT_TYPE_MINT) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if data[IS_INITIALIZED_OFFSET] != 1 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let supply = &data[SUPPLY_OFFSET..SUPPLY_OFFSET + 8];
+        Ok((
+            u64::from_le_bytes(supply.try_into().unwrap()),
+            data[DECIMALS_OFFSET],
+        ))
+    })
+}
+
+/// Returns the amount of tokens held by `account`, a token account already
+/// checked with [`token_account_owner`].
+pub fn token_account_amount(account: &AccountInfo) -> Result<u64, ProgramError> {
+    read_data(account, |data| {
+        let amount = data
+            .get(AMOUNT_OFFSET..AMOUNT_OFFSET + 8)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Ok(u64::from_le_bytes(amount.try_into().unwrap()))
+    })
+}
+
+/// Returns the delegate of `account`, a token account already checked with
+/// [`token_account_owner`], and the amount it may still transfer, or `None`
+/// if it has no delegate.
+pub fn token_account_delegate(
+    account: &AccountInfo,
+) -> Result<Option<(Pubkey, u64)>, ProgramError> {
+    read_data(account, |data| {
+        let delegate = data
+            .get(DELEGATE_OFFSET..DELEGATE_OFFSET + 36)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        if delegate[..4] == [0; 4] {
+            return Ok(None);
+        }
+        let amount = data
+            .get(DELEGATED_AMOUNT_OFFSET..DELEGATED_AMOUNT_OFFSET + 8)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Ok(Some((
+            delegate[4..].try_into().unwrap(),
+            u64::from_le_bytes(amount.try_into().unwrap()),
+        )))
+    })
+}
+
+/// Reads a value out of the data of `account`, a token account or a mint.
+///
+/// `read` can't return a reference into the data, so the data is only
+/// borrowed during the call.
+#[inline(always)]
+fn read_data<T>(
+    account: &AccountInfo,
+    read: impl FnOnce(&[u8]) -> Result<T, ProgramError>,
+) -> Result<T, ProgramError> {
+    if cfg!(feature = "checked-borrows") {
+        read(&account.try_borrow_data()?)
+    } else {
+        // SAFETY: The escrow never borrows the data of token accounts and
+        // mints mutably and the borrow ends with `read`, before any CPI could
+        // write it.
+        read(unsafe { account.borrow_data_unchecked() })
+    }
+}
+
+/// Transfer tokens from one token account to another.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Source token account
+///   1. `[WRITE]` Destination token account
+///   2. `[SIGNER]` Owner of the source token account
+pub struct Transfer<'a> {
+    /// Source token account.
+    pub from: &'a AccountInfo,
+
+    /// Destination token account.
+    pub to: &'a AccountInfo,
+
+    /// Owner of the source token account.
+    pub authority: &'a AccountInfo,
+
+    /// Token program owning the token accounts.
+    pub token_program: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+}
+
+impl Transfer<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.from.key()),
+            AccountMeta::writable(self.to.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: instruction discriminator
+        // -  [1..9 ]: amount
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 3;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        invoke_token_program(
+            self.token_program,
+            &account_metas,
+            &[self.from, self.to, self.authority],
+            &instruction_data,
+            signers,
+        )
+    }
+}
+
+/// Transfer tokens from one token account to another, checking the mint and
+/// its decimals.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Source token account
+///   1. `[]` Mint of the tokens
+///   2. `[WRITE]` Destination token account
+///   3. `[SIGNER]` Owner of the source token account
+pub struct TransferChecked<'a> {
+    /// Source token account.
+    pub from: &'a AccountInfo,
+
+    /// Mint of the tokens.
+    pub mint: &'a AccountInfo,
+
+    /// Destination token account.
+    pub to: &'a AccountInfo,
+
+    /// Owner of the source token account.
+    pub authority: &'a AccountInfo,
+
+    /// Token program owning the token accounts and the mint.
+    pub token_program: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+
+    /// Decimals of the mint.
+    pub decimals: u8,
+}
+
+impl TransferChecked<'_> {
+    pub fn invoke(&self) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 4] = [
+            AccountMeta::writable(self.from.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::writable(self.to.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: instruction discriminator
+        // -  [1..9 ]: amount
+        // -  [9    ]: decimals
+        let mut instruction_data = [0; 10];
+        instruction_data[0] = 12;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+        instruction_data[9] = self.decimals;
+
+        invoke_token_program(
+            self.token_program,
+            &account_metas,
+            &[self.from, self.mint, self.to, self.authority],
+            &instruction_data,
+            &[],
+        )
+    }
+}
+
+/// Approve a delegate to transfer tokens of a token account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Source token account
+///   1. `[]` Delegate
+///   2. `[SIGNER]` Owner of the source token account
+pub struct Approve<'a> {
+    /// Source token account.
+    pub source: &'a AccountInfo,
+
+    /// Delegate, allowed to transfer `amount` tokens of the source.
+    pub delegate: &'a AccountInfo,
+
+    /// Owner of the source token account.
+    pub owner: &'a AccountInfo,
+
+    /// Token program owning the token account.
+    pub token_program: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+}
+
+impl Approve<'_> {
+    pub fn invoke(&self) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.source.key()),
+            AccountMeta::readonly(self.delegate.key()),
+            AccountMeta::readonly_signer(self.owner.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: instruction discriminator
+        // -  [1..9 ]: amount
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 4;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        invoke_token_program(
+            self.token_program,
+            &account_metas,
+            &[self.source, self.delegate, self.owner],
+            &instruction_data,
+            &[],
+        )
+    }
+}
+
+/// Revoke the delegate of a token account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Source token account
+///   1. `[SIGNER]` Owner of the source token account
+pub struct Revoke<'a> {
+    /// Source token account.
+    pub source: &'a AccountInfo,
+
+    /// Owner of the source token account.
+    pub owner: &'a AccountInfo,
+
+    /// Token program owning the token account.
+    pub token_program: &'a AccountInfo,
+}
+
+impl Revoke<'_> {
+    pub fn invoke(&self) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 2] = [
+            AccountMeta::writable(self.source.key()),
+            AccountMeta::readonly_signer(self.owner.key()),
+        ];
+
+        // instruction data
+        // -  [0]: instruction discriminator
+        let instruction_data = [5];
+
+        invoke_token_program(
+            self.token_program,
+            &account_metas,
+            &[self.source, self.owner],
+            &instruction_data,
+            &[],
+        )
+    }
+}
+
+/// Burn tokens of a token account, reducing the supply of their mint.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Token account to burn from
+///   1. `[WRITE]` Mint of the tokens
+///   2. `[SIGNER]` Owner of the token account
+pub struct Burn<'a> {
+    /// Token account to burn from.
+    pub account: &'a AccountInfo,
+
+    /// Mint of the tokens.
+    pub mint: &'a AccountInfo,
+
+    /// Owner of the token account.
+    pub authority: &'a AccountInfo,
+
+    /// Token program owning the token account and the mint.
+    pub token_program: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+}
+
+impl Burn<'_> {
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::writable(self.mint.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: instruction discriminator
+        // -  [1..9 ]: amount
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 8;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        invoke_token_program(
+            self.token_program,
+            &account_metas,
+            &[self.account, self.mint, self.authority],
+            &instruction_data,
+            signers,
+        )
+    }
+}
+
+/// Invokes an instruction of `token_program`, which is checked to be a
+/// token program first, so the signatures are never passed to another
+/// program.
+fn invoke_token_program<const N: usize>(
+    token_program: &AccountInfo,
+    account_metas: &[AccountMeta; N],
+    accounts: &[&AccountInfo; N],
+    instruction_data: &[u8],
+    signers: &[Signer],
+) -> ProgramResult {
+    require_token_program(token_program)?;
+
+    let instruction = Instruction {
+        program_id: token_program.key(),
+        accounts: account_metas,
+        data: instruction_data,
+    };
+
+    if cfg!(feature = "checked-borrows") {
+        return invoke_signed(&instruction, accounts, signers);
+    }
+
+    // SAFETY: The escrow holds no borrow of the token accounts and the
+    // mints, which the token program writes. The other accounts are passed read-only and the
+    // token program doesn't own them, so it can't write them either, even
+    // while the escrow still reads their state.
+    unsafe {
+        invoke_signed_unchecked(&instruction, &accounts.map(Account::from), signers);
+    }
+    Ok(())
+}
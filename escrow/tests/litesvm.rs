@@ -0,0 +1,215 @@
+//! The scenarios of `tests.rs` run through LiteSVM, cross-validating the
+//! program against a second SVM test runtime.
+
+use errors::{Namespace, SharedError};
+use escrow::{EscrowCancelled, EscrowExchanged, EscrowInitialized};
+use escrow_client::{find_escrow_address, try_from_account_data, AccountsBuilder, ID};
+use events::decode::decode_events;
+use litesvm::LiteSVM;
+use solana_instruction::{error::InstructionError, AccountMeta, Instruction};
+use solana_keypair::Keypair;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+use test_utils::{token_account, token_amount};
+
+/// Sends `instruction` in a transaction paid and signed by `payer`, returning
+/// its logs.
+///
+/// `payer` is the only signer of every escrow instruction. The escrow PDA
+/// signs its transfers through the program, so LiteSVM verifies all the
+/// signatures of the transaction.
+fn send(svm: &mut LiteSVM, payer: &Keypair, instruction: Instruction) -> Vec<String> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer],
+        svm.latest_blockhash(),
+    );
+    svm.send_transaction(transaction).unwrap().logs
+}
+
+/// Accounts of an escrow of 100 tokens between a sender and a receiver.
+struct Setup {
+    svm: LiteSVM,
+    mint: Pubkey,
+    sender: Keypair,
+    sender_ata: Pubkey,
+    receiver: Keypair,
+    receiver_ata: Pubkey,
+    escrow: Pubkey,
+    escrow_ata: Pubkey,
+    /// Logs of the initialization.
+    logs: Vec<String>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        Self::with_svm(LiteSVM::new())
+    }
+
+    /// Sets up the escrow in `svm`, which comes with the token program.
+    fn with_svm(mut svm: LiteSVM) -> Self {
+        svm.add_program_from_file(ID, "target/deploy/escrow.so")
+            .unwrap();
+
+        let mint = Pubkey::new_unique();
+        let sender = Keypair::new();
+        svm.airdrop(&sender.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        let receiver = Keypair::new();
+        svm.airdrop(&receiver.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        let (escrow, _) = find_escrow_address(&sender.pubkey(), &receiver.pubkey());
+
+        let sender_ata = Pubkey::new_unique();
+        svm.set_account(
+            sender_ata,
+            token_account(&mint, &sender.pubkey(), 1_000_000),
+        )
+        .unwrap();
+        let receiver_ata = Pubkey::new_unique();
+        svm.set_account(receiver_ata, token_account(&mint, &receiver.pubkey(), 0))
+            .unwrap();
+        let escrow_ata = Pubkey::new_unique();
+        svm.set_account(escrow_ata, token_account(&mint, &escrow, 0))
+            .unwrap();
+
+        let mut setup = Self {
+            svm,
+            mint,
+            sender,
+            sender_ata,
+            receiver,
+            receiver_ata,
+            escrow,
+            escrow_ata,
+            logs: Vec::new(),
+        };
+        let instruction = escrow_client::initialize(&setup.escrow_accounts().initialize(), 100);
+        setup.logs = send(&mut setup.svm, &setup.sender, instruction);
+        setup
+    }
+
+    fn escrow_accounts(&self) -> AccountsBuilder {
+        AccountsBuilder::new(&self.sender.pubkey(), &self.receiver.pubkey(), &self.mint)
+            .sender_ata(&self.sender_ata)
+            .receiver_ata(&self.receiver_ata)
+            .escrow_ata(&self.escrow_ata)
+    }
+
+    fn token_amount(&self, ata: &Pubkey) -> u64 {
+        token_amount(&self.svm.get_account(ata).unwrap())
+    }
+}
+
+#[test]
+fn test_escrow_initialize_success() {
+    let setup = Setup::new();
+
+    let escrow_account = setup.svm.get_account(&setup.escrow).unwrap();
+    let state = try_from_account_data(&escrow_account.data).unwrap();
+    assert_eq!(state.sender, setup.sender.pubkey().to_bytes());
+    assert_eq!(state.receiver, setup.receiver.pubkey().to_bytes());
+    assert_eq!(state.amount, 100);
+
+    assert_eq!(setup.token_amount(&setup.sender_ata), 999_900);
+    assert_eq!(setup.token_amount(&setup.escrow_ata), 100);
+}
+
+#[test]
+fn test_escrow_exchange_success() {
+    let mut setup = Setup::new();
+
+    let instruction = escrow_client::exchange(&setup.escrow_accounts().exchange());
+    send(&mut setup.svm, &setup.receiver, instruction);
+
+    assert_eq!(setup.token_amount(&setup.receiver_ata), 100);
+    assert_eq!(setup.token_amount(&setup.escrow_ata), 0);
+}
+
+#[test]
+fn test_escrow_cancel_success() {
+    let mut setup = Setup::new();
+
+    let instruction = escrow_client::cancel(&setup.escrow_accounts().cancel());
+    send(&mut setup.svm, &setup.sender, instruction);
+
+    assert_eq!(setup.token_amount(&setup.sender_ata), 1_000_000);
+    assert_eq!(setup.token_amount(&setup.escrow_ata), 0);
+}
+
+#[test]
+fn test_escrow_events() {
+    let mut setup = Setup::new();
+    let sender = setup.sender.pubkey().to_bytes();
+    let receiver = setup.receiver.pubkey().to_bytes();
+
+    let events = decode_events::<EscrowInitialized>(&escrow::ID, &setup.logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].sender, sender);
+    assert_eq!(events[0].receiver, receiver);
+    assert_eq!(events[0].amount, 100);
+
+    let instruction = escrow_client::exchange(&setup.escrow_accounts().exchange());
+    let logs = send(&mut setup.svm, &setup.receiver, instruction);
+    let events = decode_events::<EscrowExchanged>(&escrow::ID, &logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].sender, sender);
+    assert_eq!(events[0].receiver, receiver);
+    assert_eq!(events[0].amount, 100);
+    assert!(decode_events::<EscrowCancelled>(&escrow::ID, &logs).is_empty());
+}
+
+#[test]
+fn test_escrow_cancel_event() {
+    let mut setup = Setup::new();
+
+    let instruction = escrow_client::cancel(&setup.escrow_accounts().cancel());
+    let logs = send(&mut setup.svm, &setup.sender, instruction);
+    let events = decode_events::<EscrowCancelled>(&escrow::ID, &logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].sender, setup.sender.pubkey().to_bytes());
+    assert_eq!(events[0].amount, 100);
+    assert_eq!(events[0].penalty, 0);
+}
+
+/// The client never marks the escrow PDA as a signer. Nobody holds its key,
+/// so a transaction marking it as one can't be fully signed. With the
+/// signature verification off, only the signature of the PDA is missing and
+/// the program itself rejects the flag.
+#[test]
+fn test_escrow_pda_is_not_a_signer() {
+    let mut setup = Setup::with_svm(LiteSVM::new().with_sigverify(false));
+    let accounts = setup.escrow_accounts();
+    for instruction in [
+        escrow_client::exchange(&accounts.exchange()),
+        escrow_client::cancel(&accounts.cancel()),
+    ] {
+        assert!(instruction
+            .accounts
+            .iter()
+            .all(|meta| meta.pubkey != setup.escrow || !meta.is_signer));
+    }
+
+    let mut instruction = escrow_client::exchange(&accounts.exchange());
+    for meta in &mut instruction.accounts {
+        if meta.pubkey == setup.escrow {
+            *meta = AccountMeta::new(setup.escrow, true);
+        }
+    }
+    // The receiver, paying the fees, is the only other signer.
+    let mut transaction =
+        Transaction::new_with_payer(&[instruction], Some(&setup.receiver.pubkey()));
+    transaction.partial_sign(&[&setup.receiver], setup.svm.latest_blockhash());
+    assert_eq!(transaction.message.header.num_required_signatures, 2);
+    let err = setup.svm.send_transaction(transaction).unwrap_err().err;
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(Namespace::Shared.code(SharedError::UnexpectedSigner as u32)),
+        )
+    );
+    assert_eq!(setup.token_amount(&setup.escrow_ata), 100);
+}
@@ -0,0 +1,672 @@
+//! Attacks on the escrow program, each rejected with its own error.
+
+use common::AccountData;
+use errors::{Namespace, SharedError};
+use escrow::EscrowError;
+use escrow_client::{
+    find_escrow_address, AccountsBuilder, CancelAccounts, Escrow, EscrowInstruction,
+    ExchangeAccounts, InitializeAccounts, Version, MAX_PENALTY_BPS, TOKEN_2022_PROGRAM_ID,
+    TOKEN_PROGRAM_ID,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::Instruction;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::{
+    delegated_token_account, funded_account, keyed_token_program_with_id, programs::mollusk,
+    state_account, token_account_with_program, token_amount, token_program_tests,
+};
+
+/// The custom error of the system program for payers without enough
+/// lamports.
+const RESULT_WITH_NEGATIVE_LAMPORTS: u32 = 1;
+
+/// The error of an account passed in more than one role.
+fn duplicate_account() -> ProgramError {
+    ProgramError::Custom(Namespace::Shared.code(SharedError::DuplicateAccount as u32))
+}
+
+/// Accounts of an escrow between a sender and a receiver.
+struct Setup {
+    mollusk: Mollusk,
+    token_program: Pubkey,
+    mint: Pubkey,
+    sender: Pubkey,
+    sender_ata: Pubkey,
+    receiver: Pubkey,
+    receiver_ata: Pubkey,
+    escrow: Pubkey,
+    escrow_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    /// Sets up the accounts of tokens of `token_program` before the escrow is
+    /// initialized.
+    fn new(token_program: &Pubkey) -> Self {
+        let mollusk = mollusk();
+
+        let (system_program, system_account) = keyed_account_for_system_program();
+        let (token_program, token_program_account) = keyed_token_program_with_id(token_program);
+
+        let mint = Pubkey::new_unique();
+        let sender = Pubkey::new_unique();
+        let sender_ata = Pubkey::new_unique();
+        let receiver = Pubkey::new_unique();
+        let receiver_ata = Pubkey::new_unique();
+        let (escrow, _) = find_escrow_address(&sender, &receiver);
+        let escrow_ata = Pubkey::new_unique();
+
+        let accounts = vec![
+            (sender, funded_account(LAMPORTS_PER_SOL)),
+            (
+                sender_ata,
+                token_account_with_program(&token_program, &mint, &sender, 1_000),
+            ),
+            (receiver, funded_account(LAMPORTS_PER_SOL)),
+            (
+                receiver_ata,
+                token_account_with_program(&token_program, &mint, &receiver, 0),
+            ),
+            (escrow, Account::new(0, 0, &system_program)),
+            (
+                escrow_ata,
+                token_account_with_program(&token_program, &mint, &escrow, 0),
+            ),
+            (system_program, system_account),
+            (token_program, token_program_account),
+        ];
+        Self {
+            mollusk,
+            token_program,
+            mint,
+            sender,
+            sender_ata,
+            receiver,
+            receiver_ata,
+            escrow,
+            escrow_ata,
+            accounts,
+        }
+    }
+
+    /// Sets up the accounts of an escrow holding 100 tokens of
+    /// `token_program`.
+    fn initialized(token_program: &Pubkey) -> Self {
+        let setup = Self::new(token_program);
+        let escrow_account = state_account(&Escrow::new(
+            setup.sender.to_bytes(),
+            setup.receiver.to_bytes(),
+            100,
+            0,
+        ));
+        let escrow_ata_account = setup.token_account(&setup.escrow, 100);
+        let (escrow, escrow_ata) = (setup.escrow, setup.escrow_ata);
+        setup
+            .with(escrow, escrow_account)
+            .with(escrow_ata, escrow_ata_account)
+    }
+
+    /// Sets up the accounts of an escrow holding 100 tokens of
+    /// `token_program` with a penalty of 10%, which the receiver accepted.
+    fn accepted(token_program: &Pubkey) -> Self {
+        let setup = Self::initialized(token_program);
+        let mut escrow = Escrow::new(
+            setup.sender.to_bytes(),
+            setup.receiver.to_bytes(),
+            100,
+            1_000,
+        );
+        escrow.accepted = 1;
+        let escrow_address = setup.escrow;
+        setup.with(escrow_address, state_account(&escrow))
+    }
+
+    /// Sets up the accounts of a delegated escrow of 100 tokens of
+    /// `token_program`, which the token account of the sender delegates to
+    /// it.
+    fn delegated(token_program: &Pubkey) -> Self {
+        let setup = Self::new(token_program);
+        let escrow_account = state_account(&Escrow {
+            delegated: 1,
+            ..Escrow::new(setup.sender.to_bytes(), setup.receiver.to_bytes(), 100, 0)
+        });
+        let sender_ata_account = delegated_token_account(
+            &setup.token_program,
+            &setup.mint,
+            &setup.sender,
+            1_000,
+            &setup.escrow,
+            100,
+        );
+        let (escrow, sender_ata) = (setup.escrow, setup.sender_ata);
+        setup
+            .with(escrow, escrow_account)
+            .with(sender_ata, sender_ata_account)
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Returns a token account of `owner` holding `amount` tokens of the
+    /// mint.
+    fn token_account(&self, owner: &Pubkey, amount: u64) -> Account {
+        token_account_with_program(&self.token_program, &self.mint, owner, amount)
+    }
+
+    fn escrow_accounts(&self) -> AccountsBuilder {
+        AccountsBuilder::new(&self.sender, &self.receiver, &self.mint)
+            .token_program(&self.token_program)
+            .sender_ata(&self.sender_ata)
+            .receiver_ata(&self.receiver_ata)
+            .escrow_ata(&self.escrow_ata)
+    }
+
+    fn initialize(&self) -> Instruction {
+        escrow_client::initialize(&self.escrow_accounts().initialize(), 100)
+    }
+
+    fn exchange(&self) -> Instruction {
+        escrow_client::exchange(&self.escrow_accounts().exchange())
+    }
+
+    fn cancel(&self) -> Instruction {
+        escrow_client::cancel(&self.escrow_accounts().cancel())
+    }
+
+    fn accept(&self) -> Instruction {
+        escrow_client::accept(&self.escrow_accounts().accept())
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+fn test_missing_signer(token_program: &Pubkey) {
+    let setup = Setup::new(token_program);
+    let mut instruction = setup.initialize();
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+
+    let setup = Setup::initialized(token_program);
+    // Only the receiver can accept the exchange.
+    let mut instruction = setup.exchange();
+    instruction.accounts[1].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+    // Only the sender can cancel the escrow.
+    let mut instruction = setup.cancel();
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+    // Only the receiver can accept the escrow, so the sender can't make
+    // itself owe the penalty to a receiver who never accepted.
+    let mut instruction = setup.accept();
+    instruction.accounts[1].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The escrow PDA marked as a signer, as clients did when the program
+/// relied on the flag instead of signing with its seeds. Mollusk doesn't
+/// verify signatures, so the program has to reject it itself.
+fn test_escrow_marked_as_signer(token_program: &Pubkey) {
+    let setup = Setup::initialized(token_program);
+    let err = ProgramError::Custom(Namespace::Shared.code(SharedError::UnexpectedSigner as u32));
+    for mut instruction in [setup.exchange(), setup.cancel()] {
+        instruction.accounts[3].is_signer = true;
+        setup.expect_err(&instruction, err.clone());
+    }
+}
+
+/// An attacker passes an escrow which isn't the PDA of the sender and the
+/// receiver, together with its token account.
+fn test_wrong_pda(token_program: &Pubkey) {
+    let setup = Setup::initialized(token_program);
+    let fake_escrow = Pubkey::new_unique();
+    let fake_escrow_ata = Pubkey::new_unique();
+    let mut instruction = setup.cancel();
+    instruction.accounts[3].pubkey = fake_escrow;
+    instruction.accounts[4].pubkey = fake_escrow_ata;
+    let fake_escrow_ata_account = setup.token_account(&fake_escrow, 100);
+    let setup = setup
+        .with(fake_escrow, Account::default())
+        .with(fake_escrow_ata, fake_escrow_ata_account);
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+fn test_substituted_token_program(token_program: &Pubkey) {
+    let fake_program = Pubkey::new_unique();
+
+    let setup = Setup::new(token_program).with(fake_program, Account::default());
+    let mut instruction = setup.initialize();
+    instruction.accounts[6].pubkey = fake_program;
+    setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+
+    let setup = Setup::initialized(token_program).with(fake_program, Account::default());
+    for mut instruction in [setup.exchange(), setup.cancel()] {
+        instruction.accounts[5].pubkey = fake_program;
+        setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+    }
+}
+
+/// A deployed program instead of the token program, which would be invoked
+/// with the signature of the sender or the escrow over the tokens.
+fn test_substituted_deployed_program(token_program: &Pubkey) {
+    let program = counter_client::ID;
+    let program_account = create_program_account_loader_v3(&program);
+
+    let setup = Setup::new(token_program).with(program, program_account.clone());
+    let mut instruction = setup.initialize();
+    instruction.accounts[6].pubkey = program;
+    setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+
+    let setup = Setup::initialized(token_program).with(program, program_account);
+    for mut instruction in [setup.exchange(), setup.cancel()] {
+        instruction.accounts[5].pubkey = program;
+        setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+    }
+}
+
+/// A copy of the program deployed at another address, whose escrows would
+/// never match the PDAs derived by the clients.
+fn test_redeployed_program(token_program: &Pubkey) {
+    let redeployed = Pubkey::new_unique();
+    let mut setup = Setup::initialized(token_program);
+    setup
+        .mollusk
+        .add_program(&redeployed, "target/deploy/escrow", &LOADER_V3);
+    for mut instruction in [setup.initialize(), setup.exchange(), setup.cancel()] {
+        instruction.program_id = redeployed;
+        setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+    }
+}
+
+/// A bogus account and a deployed program instead of the system program,
+/// which the initialization would invoke to create the escrow.
+fn test_substituted_system_program(token_program: &Pubkey) {
+    let fake_program = Pubkey::new_unique();
+    let program = counter_client::ID;
+
+    let setup = Setup::new(token_program)
+        .with(fake_program, Account::default())
+        .with(program, create_program_account_loader_v3(&program));
+    for substitute in [fake_program, program] {
+        let mut instruction = setup.initialize();
+        instruction.accounts[5].pubkey = substitute;
+        setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+    }
+}
+
+/// Token accounts of an attacker instead of the escrow and the receiver.
+fn test_attacker_owned_atas(token_program: &Pubkey) {
+    let attacker = Pubkey::new_unique();
+
+    let setup = Setup::new(token_program);
+    let attacker_ata = setup.token_account(&attacker, 0);
+    let escrow_ata = setup.escrow_ata;
+    let setup = setup.with(escrow_ata, attacker_ata.clone());
+    setup.expect_err(&setup.initialize(), ProgramError::IllegalOwner);
+
+    let setup = Setup::initialized(token_program);
+    let receiver_ata = setup.receiver_ata;
+    let setup = setup.with(receiver_ata, attacker_ata.clone());
+    setup.expect_err(&setup.exchange(), ProgramError::IllegalOwner);
+
+    // The sender can't pay the penalty to itself.
+    let setup = Setup::accepted(token_program);
+    let receiver_ata = setup.receiver_ata;
+    let setup = setup.with(receiver_ata, attacker_ata);
+    setup.expect_err(&setup.cancel(), ProgramError::IllegalOwner);
+}
+
+/// A penalty above 100% would make the cancellation pay out more than the
+/// escrowed amount.
+fn test_invalid_penalty(token_program: &Pubkey) {
+    let setup = Setup::new(token_program);
+    let instruction = escrow_client::initialize_with_penalty(
+        &setup.escrow_accounts().initialize(),
+        100,
+        MAX_PENALTY_BPS + 1,
+    );
+    setup.expect_err(
+        &instruction,
+        ProgramError::Custom(Namespace::Escrow.code(EscrowError::InvalidPenalty as u32)),
+    );
+}
+
+/// The token account of the sender is passed as the one of the escrow, so
+/// the deposit would transfer the tokens to the sender itself while the
+/// escrow records them.
+fn test_duplicated_accounts(token_program: &Pubkey) {
+    let setup = Setup::new(token_program);
+    let accounts = InitializeAccounts {
+        escrow_ata: setup.sender_ata,
+        ..setup.escrow_accounts().initialize()
+    };
+    let instruction = escrow_client::initialize(&accounts, 100);
+    setup.expect_err(&instruction, duplicate_account());
+}
+
+/// The token account of the escrow is passed as the one receiving the
+/// escrowed tokens, so the exchange or the cancellation would leave them in
+/// the escrow while the receiver or the sender is considered paid.
+fn test_aliased_escrow_ata(token_program: &Pubkey) {
+    let setup = Setup::initialized(token_program);
+    let exchange = ExchangeAccounts {
+        receiver_ata: setup.escrow_ata,
+        ..setup.escrow_accounts().exchange()
+    };
+    setup.expect_err(&escrow_client::exchange(&exchange), duplicate_account());
+    let cancel = CancelAccounts {
+        sender_ata: setup.escrow_ata,
+        ..setup.escrow_accounts().cancel()
+    };
+    setup.expect_err(&escrow_client::cancel(&cancel), duplicate_account());
+}
+
+/// The sender is passed as the receiver of its own escrow.
+fn test_sender_as_receiver(token_program: &Pubkey) {
+    let setup = Setup::new(token_program);
+    let accounts = AccountsBuilder::new(&setup.sender, &setup.sender, &setup.mint)
+        .token_program(&setup.token_program)
+        .sender_ata(&setup.sender_ata)
+        .initialize();
+    let (escrow, _) = find_escrow_address(&setup.sender, &setup.sender);
+    let escrow_ata_account = setup.token_account(&escrow, 0);
+    let setup = setup
+        .with(escrow, Account::default())
+        .with(accounts.escrow_ata, escrow_ata_account);
+    setup.expect_err(
+        &escrow_client::initialize(&accounts, 100),
+        duplicate_account(),
+    );
+}
+
+fn test_truncated_instruction_data(token_program: &Pubkey) {
+    let setup = Setup::new(token_program);
+    let mut instruction = setup.initialize();
+    // Without the bump and without any data.
+    for len in [
+        instruction.data.len() - 1,
+        EscrowInstruction::DISCRIMINATOR_LEN,
+        0,
+    ] {
+        instruction.data.truncate(len);
+        setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+    }
+
+    let setup = Setup::initialized(token_program);
+    let mut instruction = setup.exchange();
+    instruction
+        .data
+        .truncate(EscrowInstruction::DISCRIMINATOR_LEN);
+    setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+}
+
+/// A sender who can't pay for the rent of the escrow, which is never
+/// created underfunded.
+fn test_underfunded_sender(token_program: &Pubkey) {
+    let setup = Setup::new(token_program);
+    let sender = setup.sender;
+    let setup = setup.with(sender, funded_account(1));
+    setup.expect_err(
+        &setup.initialize(),
+        ProgramError::Custom(RESULT_WITH_NEGATIVE_LAMPORTS),
+    );
+}
+
+/// An attacker sends lamports to the escrow PDA before it's created, which
+/// would make `CreateAccount` fail. The escrow is created anyway.
+fn test_prefunded_escrow(token_program: &Pubkey) {
+    let setup = Setup::new(token_program);
+    let escrow = setup.escrow;
+    let setup = setup.with(escrow, funded_account(1_000));
+    let rent = setup.mollusk.sysvars.rent.minimum_balance(Escrow::LEN);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.initialize(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&escrow)
+                .owner(&escrow_client::ID)
+                .lamports(rent)
+                .space(Escrow::LEN)
+                .build(),
+        ],
+    );
+}
+
+/// The token account of the escrow holds less than the escrowed amount,
+/// e.g. after its tokens were moved by a delegate, so neither the exchange
+/// nor the cancellation can pay it out.
+fn test_underfunded_escrow_ata(token_program: &Pubkey) {
+    let setup = Setup::initialized(token_program);
+    let escrow_ata_account = setup.token_account(&setup.escrow, 50);
+    let escrow_ata = setup.escrow_ata;
+    let setup = setup.with(escrow_ata, escrow_ata_account);
+    let err =
+        ProgramError::Custom(Namespace::Escrow.code(EscrowError::InsufficientEscrowBalance as u32));
+    setup.expect_err(&setup.exchange(), err.clone());
+    setup.expect_err(&setup.cancel(), err);
+}
+
+/// An attacker sends a single token to the token account of the escrow.
+/// The exchange, the cancellation and the refund of an expired escrow
+/// still pay out the escrowed amount, leaving the dust behind.
+fn test_dusted_escrow_ata(token_program: &Pubkey) {
+    let setup = Setup::initialized(token_program);
+    let escrow_ata_account = setup.token_account(&setup.escrow, 101);
+    let escrow_ata = setup.escrow_ata;
+    let setup = setup.with(escrow_ata, escrow_ata_account);
+    for (instruction, ata, balance) in [
+        (setup.exchange(), setup.receiver_ata, 100),
+        (setup.cancel(), setup.sender_ata, 1_100),
+    ] {
+        let res = setup.mollusk.process_and_validate_instruction(
+            &instruction,
+            &setup.accounts,
+            &[Check::success()],
+        );
+        assert_eq!(token_amount(res.get_account(&ata).unwrap()), balance);
+        assert_eq!(token_amount(res.get_account(&escrow_ata).unwrap()), 1);
+    }
+
+    let escrow_account = state_account(&Escrow {
+        expiry_slot: 1,
+        ..Escrow::new(setup.sender.to_bytes(), setup.receiver.to_bytes(), 100, 0)
+    });
+    let escrow = setup.escrow;
+    let mut setup = setup.with(escrow, escrow_account);
+    setup.mollusk.sysvars.clock.slot = 2;
+    let refund = escrow_client::refund_expired(&setup.escrow_accounts().refund_expired());
+    let res = setup.mollusk.process_and_validate_instruction(
+        &refund,
+        &setup.accounts,
+        &[Check::success()],
+    );
+    assert_eq!(
+        token_amount(res.get_account(&setup.sender_ata).unwrap()),
+        1_100
+    );
+    assert_eq!(token_amount(res.get_account(&escrow_ata).unwrap()), 1);
+}
+
+/// The sender cancelled and re-created the escrow with fewer tokens after
+/// the receiver signed version 2 of the exchange, which expects the amount
+/// it saw.
+fn test_amount_mismatch(token_program: &Pubkey) {
+    let setup = Setup::initialized(token_program);
+    let err = ProgramError::Custom(Namespace::Escrow.code(EscrowError::AmountMismatch as u32));
+    setup.expect_err(
+        &escrow_client::exchange_expecting(&setup.escrow_accounts().exchange(), 1_000),
+        err.clone(),
+    );
+    setup.expect_err(
+        &escrow_client::cancel_expecting(&setup.escrow_accounts().cancel(), 99),
+        err,
+    );
+}
+
+/// Versions an instruction doesn't have, and the data of one version sent
+/// as another, are rejected.
+fn test_unknown_versions(token_program: &Pubkey) {
+    // The version follows the family of the instruction.
+    let version = EscrowInstruction::FAMILY_LEN;
+
+    let setup = Setup::new(token_program);
+    let mut instruction = setup.initialize();
+    instruction.data[version] = Version::V2 as u8;
+    setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+
+    let setup = Setup::initialized(token_program);
+    let mut instruction = setup.accept();
+    instruction.data[version] = Version::V2 as u8;
+    setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+
+    for mut instruction in [setup.exchange(), setup.cancel()] {
+        instruction.data[version] = Version::V2 as u8;
+        setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+        instruction.data[version] = 3;
+        setup.expect_err(&instruction, ProgramError::InvalidInstructionData);
+    }
+}
+
+/// Instructions for delegated escrows on an escrow holding its tokens, and
+/// the other way around, which would move tokens out of the wrong account.
+fn test_delegation_mismatch(token_program: &Pubkey) {
+    let err = ProgramError::Custom(Namespace::Escrow.code(EscrowError::DelegationMismatch as u32));
+
+    let setup = Setup::initialized(token_program);
+    setup.expect_err(
+        &escrow_client::cancel_delegated(&setup.escrow_accounts().cancel_delegated()),
+        err.clone(),
+    );
+
+    let setup = Setup::delegated(token_program);
+    setup.expect_err(&setup.cancel(), err);
+}
+
+/// The sender revoked the delegation of a delegated escrow, or replaced it
+/// with a delegation to someone else, before the exchange.
+fn test_missing_delegation(token_program: &Pubkey) {
+    let err = ProgramError::Custom(Namespace::Escrow.code(EscrowError::MissingDelegation as u32));
+
+    let setup = Setup::delegated(token_program);
+    for sender_ata_account in [
+        setup.token_account(&setup.sender, 1_000),
+        delegated_token_account(
+            &setup.token_program,
+            &setup.mint,
+            &setup.sender,
+            1_000,
+            &Pubkey::new_unique(),
+            100,
+        ),
+        delegated_token_account(
+            &setup.token_program,
+            &setup.mint,
+            &setup.sender,
+            1_000,
+            &setup.escrow,
+            99,
+        ),
+    ] {
+        let sender_ata = setup.sender_ata;
+        let setup = Setup::delegated(token_program).with(sender_ata, sender_ata_account);
+        setup.expect_err(
+            &escrow_client::exchange(&setup.escrow_accounts().exchange_delegated()),
+            err.clone(),
+        );
+    }
+}
+
+/// A delegated escrow would replace the delegation of the token account of
+/// the sender to another escrow or program.
+fn test_already_delegated(token_program: &Pubkey) {
+    let setup = Setup::new(token_program);
+    let sender_ata_account = delegated_token_account(
+        &setup.token_program,
+        &setup.mint,
+        &setup.sender,
+        1_000,
+        &Pubkey::new_unique(),
+        100,
+    );
+    let sender_ata = setup.sender_ata;
+    let setup = setup.with(sender_ata, sender_ata_account);
+    setup.expect_err(
+        &escrow_client::initialize_delegated(&setup.escrow_accounts().initialize_delegated(), 100),
+        ProgramError::Custom(Namespace::Escrow.code(EscrowError::AlreadyDelegated as u32)),
+    );
+}
+
+fn test_reinitialization(token_program: &Pubkey) {
+    let setup = Setup::initialized(token_program);
+    setup.expect_err(&setup.initialize(), ProgramError::AccountAlreadyInitialized);
+}
+
+/// Token accounts of the other token program than the one passed to the
+/// instructions.
+fn test_mixed_token_programs(token_program: &Pubkey) {
+    let other_program = if *token_program == TOKEN_PROGRAM_ID {
+        TOKEN_2022_PROGRAM_ID
+    } else {
+        TOKEN_PROGRAM_ID
+    };
+
+    let setup = Setup::new(token_program);
+    let escrow_ata_account =
+        token_account_with_program(&other_program, &setup.mint, &setup.escrow, 0);
+    let escrow_ata = setup.escrow_ata;
+    let setup = setup.with(escrow_ata, escrow_ata_account);
+    setup.expect_err(&setup.initialize(), ProgramError::InvalidAccountData);
+
+    let setup = Setup::initialized(token_program);
+    let receiver_ata_account =
+        token_account_with_program(&other_program, &setup.mint, &setup.receiver, 0);
+    let receiver_ata = setup.receiver_ata;
+    let setup = setup.with(receiver_ata, receiver_ata_account);
+    setup.expect_err(&setup.exchange(), ProgramError::InvalidAccountData);
+}
+
+token_program_tests!(
+    test_missing_signer,
+    test_escrow_marked_as_signer,
+    test_wrong_pda,
+    test_substituted_token_program,
+    test_substituted_deployed_program,
+    test_substituted_system_program,
+    test_redeployed_program,
+    test_attacker_owned_atas,
+    test_invalid_penalty,
+    test_duplicated_accounts,
+    test_aliased_escrow_ata,
+    test_sender_as_receiver,
+    test_truncated_instruction_data,
+    test_reinitialization,
+    test_delegation_mismatch,
+    test_missing_delegation,
+    test_already_delegated,
+    test_underfunded_escrow_ata,
+    test_dusted_escrow_ata,
+    test_amount_mismatch,
+    test_unknown_versions,
+    test_prefunded_escrow,
+    test_underfunded_sender,
+    test_mixed_token_programs,
+);
@@ -0,0 +1,108 @@
+//! Hundreds of escrow lifecycles, checking that the token balances don't
+//! drift from a model of them and that the compute units stay within the
+//! budgets. Run with `cargo test --features stress --test stress`.
+
+use escrow_client::{find_escrow_address, AccountsBuilder};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check};
+use solana_account::Account;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+use test_utils::{
+    assertions::assert_token_balance,
+    budgets,
+    fixtures::{address, alice, mint_a},
+    funded_account, keyed_token_program,
+    programs::mollusk,
+    token_account,
+};
+
+/// Number of escrows created by the sender.
+const LIFECYCLES: u64 = 300;
+/// Tokens held by the sender before the first escrow.
+const SUPPLY: u64 = 1_000_000;
+
+#[test]
+fn test_escrow_stress() {
+    let mollusk = mollusk();
+
+    let (system_program, system_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = keyed_token_program();
+
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let sender_ata = address("alice_ata");
+    let mut sender_accounts = vec![
+        (sender, funded_account(LAMPORTS_PER_SOL)),
+        (sender_ata, token_account(&mint, &sender, SUPPLY)),
+    ];
+
+    let checks = [Check::success()];
+    let mut balance = SUPPLY;
+    let mut compute_units = 0;
+    for i in 0..LIFECYCLES {
+        // Every escrow has its own receiver, as escrows aren't closed.
+        let receiver = Pubkey::new_unique();
+        let receiver_ata = Pubkey::new_unique();
+        let (escrow, _) = find_escrow_address(&sender, &receiver);
+        let escrow_ata = Pubkey::new_unique();
+        let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+            .sender_ata(&sender_ata)
+            .receiver_ata(&receiver_ata)
+            .escrow_ata(&escrow_ata);
+
+        let amount = i + 1;
+        let initialize = escrow_client::initialize(&escrow_accounts.initialize(), amount);
+        // Every other escrow is cancelled instead of exchanged.
+        let exchanged = i % 2 == 0;
+        let finalize = if exchanged {
+            escrow_client::exchange(&escrow_accounts.exchange())
+        } else {
+            escrow_client::cancel(&escrow_accounts.cancel())
+        };
+
+        let mut accounts = sender_accounts.clone();
+        accounts.extend([
+            (receiver, funded_account(LAMPORTS_PER_SOL)),
+            (receiver_ata, token_account(&mint, &receiver, 0)),
+            (escrow, Account::new(0, 0, &system_program)),
+            (escrow_ata, token_account(&mint, &escrow, 0)),
+            (system_program, system_account.clone()),
+            (token_program, token_program_account.clone()),
+        ]);
+        let res = mollusk.process_and_validate_instruction_chain(
+            &[(&initialize, &checks), (&finalize, &checks)],
+            &accounts,
+        );
+
+        if exchanged {
+            balance -= amount;
+            assert_token_balance(&res, &receiver_ata, amount);
+        } else {
+            assert_token_balance(&res, &receiver_ata, 0);
+        }
+        assert_token_balance(&res, &sender_ata, balance);
+        assert_token_balance(&res, &escrow_ata, 0);
+
+        let budget = budgets::escrow::INITIALIZE
+            + if exchanged {
+                budgets::escrow::EXCHANGE
+            } else {
+                budgets::escrow::CANCEL
+            };
+        assert!(
+            res.compute_units_consumed <= budget,
+            "lifecycle {i} consumed {} compute units, over the budget of {budget}",
+            res.compute_units_consumed
+        );
+        compute_units += res.compute_units_consumed;
+
+        sender_accounts = res
+            .resulting_accounts
+            .into_iter()
+            .filter(|(key, _)| *key == sender || *key == sender_ata)
+            .collect();
+    }
+
+    println!("{LIFECYCLES} lifecycles consumed {compute_units} compute units");
+}
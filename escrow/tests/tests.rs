@@ -1,232 +1,71 @@
-use std::mem;
+use std::{fs, path::Path};
 
-use escrow::{
-    Escrow, EscrowInstruction, FinalizeInstructionData, InitializeInstructionData, ESCROW_SEED,
+use common::AccountData;
+use counter_client::Counter;
+use escrow::EscrowError;
+use escrow_client::{
+    accounts::{find_associated_token_address, find_associated_token_address_with_program},
+    find_escrow_address, find_metadata_address, AccountsBuilder, Escrow, EscrowInstruction,
+    ExchangeBatchAccounts, InstructionArgs, UiAmountError, Version, EXCHANGE_COMPUTE_UNITS, ID,
+    TOKEN_2022_PROGRAM_ID, TOKEN_METADATA_PROGRAM_ID, TOKEN_PROGRAM_ID,
 };
 use mollusk_svm::{
-    program::{
-        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
-    },
-    result::{Check, ProgramResult},
+    program::keyed_account_for_system_program,
+    result::{Check, InstructionResult, ProgramResult},
     Mollusk,
 };
-use solana_account::{Account, WritableAccount};
-use solana_instruction::{AccountMeta, Instruction};
+use proptest::prelude::*;
+use shank_idl::{extract_idl, ParseIdlOpts};
+use solana_account::Account;
+use solana_instruction::AccountMeta;
 use solana_native_token::LAMPORTS_PER_SOL;
-use solana_program_pack::Pack;
+use solana_program_error::ProgramError;
 use solana_pubkey::Pubkey;
-use spl_token::{
-    solana_program::program_option::COption,
-    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+use solana_signer::Signer;
+use test_utils::{
+    assertions::{account_state, assert_token_balance},
+    bench::Bencher,
+    budgets::{self, assert_within_budget},
+    fixtures::{address, alice, bob, mint_a, replay_fixtures},
+    funded_account, keyed_token_program, keyed_token_program_with_id,
+    logs::{assert_logs_snapshot, LogRecorder},
+    mint_account, mint_supply,
+    programs::mollusk,
+    scenario::Scenario,
+    state_account, token_account, token_account_with_program, token_amount, token_delegate,
+    token_program_tests,
 };
 
-const ID: Pubkey = Pubkey::new_from_array(escrow::ID);
-const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
-
-fn instruction_initialize(
-    amount: u64,
-    sender: &Pubkey,
-    sender_ata: &Pubkey,
-    receiver: &Pubkey,
-    escrow: &Pubkey,
-    escrow_ata: &Pubkey,
-    bump: u8,
-    system_program: &Pubkey,
-    token_program: &Pubkey,
-) -> Instruction {
-    // Create instruction data.
-    let data = InitializeInstructionData::new(amount, bump);
-    // Serialize instruction data to bytes.
-    let data = unsafe {
-        &*(&data as *const InitializeInstructionData
-            as *const [u8; size_of::<InitializeInstructionData>()])
-    };
-
-    // Construct the full instruction data, consisting of:
-    // * discriminator
-    // * serialized data
-    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
-        mem::size_of::<EscrowInstruction>() + mem::size_of::<InitializeInstructionData>(),
-    );
-    data_with_discriminator.push(EscrowInstruction::Initialize as u8);
-    data_with_discriminator.extend_from_slice(data);
-
-    let ix_accounts = vec![
-        AccountMeta::new(*sender, true),
-        AccountMeta::new(*sender_ata, false),
-        AccountMeta::new(*receiver, false),
-        AccountMeta::new(*escrow, true),
-        AccountMeta::new(*escrow_ata, false),
-        AccountMeta::new_readonly(*system_program, false),
-        AccountMeta::new_readonly(*token_program, false),
-    ];
-    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
-}
-
-fn instruction_exchange(
-    sender: &Pubkey,
-    receiver: &Pubkey,
-    receiver_ata: &Pubkey,
-    escrow: &Pubkey,
-    escrow_ata: &Pubkey,
-    bump: u8,
-    system_program: &Pubkey,
-    token_program: &Pubkey,
-) -> Instruction {
-    // Create instruction data.
-    let data = FinalizeInstructionData::new(bump);
-    // Serialize instruction data to bytes.
-    let data = unsafe {
-        &*(&data as *const FinalizeInstructionData
-            as *const [u8; size_of::<FinalizeInstructionData>()])
-    };
-
-    // Construct the full instruction data, consisting of:
-    // * discriminator
-    // * serialized data
-    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
-        mem::size_of::<EscrowInstruction>() + mem::size_of::<FinalizeInstructionData>(),
-    );
-    data_with_discriminator.push(EscrowInstruction::Exchange as u8);
-    data_with_discriminator.extend_from_slice(data);
-
-    let ix_accounts = vec![
-        AccountMeta::new(*sender, false),
-        AccountMeta::new(*receiver, true),
-        AccountMeta::new(*receiver_ata, false),
-        AccountMeta::new(*escrow, true),
-        AccountMeta::new(*escrow_ata, false),
-        AccountMeta::new_readonly(*system_program, false),
-        AccountMeta::new_readonly(*token_program, false),
-    ];
-    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
-}
+fn test_escrow_initialize_success(token_program: &Pubkey) {
+    let mut mollusk = mollusk();
+    let logs = LogRecorder::new(&mut mollusk);
 
-fn instruction_cancel(
-    sender: &Pubkey,
-    sender_ata: &Pubkey,
-    receiver: &Pubkey,
-    escrow: &Pubkey,
-    escrow_ata: &Pubkey,
-    bump: u8,
-    system_program: &Pubkey,
-    token_program: &Pubkey,
-) -> Instruction {
-    // Create instruction data.
-    let data = FinalizeInstructionData::new(bump);
-    // Serialize instruction data to bytes.
-    let data = unsafe {
-        &*(&data as *const FinalizeInstructionData
-            as *const [u8; size_of::<FinalizeInstructionData>()])
-    };
+    let (system_program, system_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = keyed_token_program_with_id(token_program);
 
-    // Construct the full instruction data, consisting of:
-    // * discriminator
-    // * serialized data
-    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
-        mem::size_of::<EscrowInstruction>() + mem::size_of::<FinalizeInstructionData>(),
-    );
-    data_with_discriminator.push(EscrowInstruction::Cancel as u8);
-    data_with_discriminator.extend_from_slice(data);
-
-    let ix_accounts = vec![
-        AccountMeta::new(*sender, true),
-        AccountMeta::new(*sender_ata, false),
-        AccountMeta::new(*receiver, false),
-        AccountMeta::new(*escrow, true),
-        AccountMeta::new(*escrow_ata, false),
-        AccountMeta::new_readonly(*system_program, false),
-        AccountMeta::new_readonly(*token_program, false),
-    ];
-    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
-}
+    let mint = mint_a();
 
-#[test]
-fn test_escrow_initialize_success() {
-    let mut mollusk = Mollusk::new(&ID, "target/deploy/escrow");
-    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    let sender = alice().pubkey();
+    let sender_account = funded_account(LAMPORTS_PER_SOL);
 
-    let (system_program, system_account) = keyed_account_for_system_program();
-    let (token_program, token_account) = (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID));
-
-    // Initialize mint.
-    let mint = Pubkey::new_unique();
-    let mut mint_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
-        Mint::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        Mint {
-            mint_authority: COption::None,
-            supply: 1_000_000,
-            decimals: 6,
-            is_initialized: true,
-            freeze_authority: COption::None,
-        },
-        mint_account.data_as_mut_slice(),
-    )
-    .unwrap();
-
-    let sender = Pubkey::new_unique();
-    let sender_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &system_program);
-
-    let sender_ata = Pubkey::new_unique();
-    let mut sender_ata_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
-        TokenAccount::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        TokenAccount {
-            mint,
-            owner: sender,
-            amount: 1_000_000,
-            delegate: COption::None,
-            state: TokenAccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        sender_ata_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    let sender_ata = address("alice_ata");
+    let sender_ata_account = token_account_with_program(&token_program, &mint, &sender, 1_000_000);
 
-    let receiver = Pubkey::new_unique();
-    let receiver_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &system_program);
+    let receiver = bob().pubkey();
+    let receiver_account = funded_account(LAMPORTS_PER_SOL);
 
-    let (escrow, bump) = Pubkey::find_program_address(
-        &[
-            ESCROW_SEED.as_bytes(),
-            sender.as_array(),
-            receiver.as_array(),
-        ],
-        &ID,
-    );
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
     // We don't specify the space for the escrow PDA yet - we are letting the
     // `create` instruction do that.
     let escrow_account = Account::new(0, 0, &system_program);
 
-    let escrow_ata = Pubkey::new_unique();
-    let mut escrow_ata_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
-        TokenAccount::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        TokenAccount {
-            mint,
-            owner: escrow,
-            amount: 0,
-            delegate: COption::None,
-            state: TokenAccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        escrow_ata_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    let escrow_ata = address("escrow_ata");
+    let escrow_ata_account = token_account_with_program(&token_program, &mint, &escrow, 0);
+
+    let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+        .token_program(&token_program)
+        .sender_ata(&sender_ata)
+        .escrow_ata(&escrow_ata);
 
     let tx_accounts = &[
         (sender, sender_account),
@@ -235,126 +74,63 @@ fn test_escrow_initialize_success() {
         (escrow, escrow_account),
         (escrow_ata, escrow_ata_account),
         (system_program, system_account),
-        (token_program, token_account),
+        (token_program, token_program_account),
     ];
     let res = mollusk.process_and_validate_instruction_chain(
         &[(
-            &instruction_initialize(
-                100,
-                &sender,
-                &sender_ata,
-                &receiver,
-                &escrow,
-                &escrow_ata,
-                bump,
-                &system_program,
-                &token_program,
-            ),
+            &escrow_client::initialize(&escrow_accounts.initialize(), 100),
             &[Check::success()],
         )],
         tx_accounts,
     );
     assert!(matches!(res.program_result, ProgramResult::Success));
+    assert_within_budget(&res, budgets::escrow::INITIALIZE);
+    assert_logs_snapshot(
+        "snapshots",
+        &snapshot_name("initialize", &token_program),
+        &logs.take(),
+        &[],
+    );
+
+    let state: Escrow = account_state(&res, &escrow);
+    assert_eq!(state.sender, sender.to_bytes());
+    assert_eq!(state.receiver, receiver.to_bytes());
+    assert_eq!(state.amount, 100);
+    assert_token_balance(&res, &sender_ata, 999_900);
+    assert_token_balance(&res, &escrow_ata, 100);
 }
 
-#[test]
-fn test_escrow_exchange_success() {
-    let mut mollusk = Mollusk::new(&ID, "target/deploy/escrow");
-    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+fn test_escrow_exchange_success(token_program: &Pubkey) {
+    let mut mollusk = mollusk();
+    let logs = LogRecorder::new(&mut mollusk);
 
     let (system_program, system_account) = keyed_account_for_system_program();
-    let (token_program, token_account) = (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID));
-
-    // Initialize mint.
-    let mint = Pubkey::new_unique();
-    let mut mint_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
-        Mint::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        Mint {
-            mint_authority: COption::None,
-            supply: 1_000_000,
-            decimals: 6,
-            is_initialized: true,
-            freeze_authority: COption::None,
-        },
-        mint_account.data_as_mut_slice(),
-    )
-    .unwrap();
-
-    let sender = Pubkey::new_unique();
-    let sender_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &system_program);
-
-    let receiver = Pubkey::new_unique();
-    let receiver_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &system_program);
-
-    let receiver_ata = Pubkey::new_unique();
-    let mut receiver_ata_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
-        TokenAccount::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        TokenAccount {
-            mint,
-            owner: receiver,
-            amount: 0,
-            delegate: COption::None,
-            state: TokenAccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        receiver_ata_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    let (token_program, token_program_account) = keyed_token_program_with_id(token_program);
 
-    let (escrow, bump) = Pubkey::find_program_address(
-        &[
-            ESCROW_SEED.as_bytes(),
-            sender.as_array(),
-            receiver.as_array(),
-        ],
-        &ID,
-    );
+    let mint = mint_a();
+
+    let sender = alice().pubkey();
+    let sender_account = funded_account(LAMPORTS_PER_SOL);
+
+    let receiver = bob().pubkey();
+    let receiver_account = funded_account(LAMPORTS_PER_SOL);
+
+    let receiver_ata = address("bob_ata");
+    let receiver_ata_account = token_account_with_program(&token_program, &mint, &receiver, 0);
+
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
     // We don't specify the space for the escrow PDA yet - we are letting the
     // `create` instruction do that.
-    let mut escrow_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(Escrow::LEN),
-        Escrow::LEN,
-        &system_program,
-    );
-    let escrow_data = Escrow {
-        sender: sender.to_bytes(),
-        receiver: receiver.to_bytes(),
-        amount: 100,
-    };
-    let escrow_data =
-        unsafe { &*(&escrow_data as *const Escrow as *const [u8; size_of::<Escrow>()]) };
-    escrow_account.data.copy_from_slice(escrow_data);
-
-    let escrow_ata = Pubkey::new_unique();
-    let mut escrow_ata_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
-        TokenAccount::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        TokenAccount {
-            mint,
-            owner: escrow,
-            amount: 100,
-            delegate: COption::None,
-            state: TokenAccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        escrow_ata_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    let escrow_account =
+        state_account(&Escrow::new(sender.to_bytes(), receiver.to_bytes(), 100, 0));
+
+    let escrow_ata = address("escrow_ata");
+    let escrow_ata_account = token_account_with_program(&token_program, &mint, &escrow, 100);
+
+    let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+        .token_program(&token_program)
+        .receiver_ata(&receiver_ata)
+        .escrow_ata(&escrow_ata);
 
     let tx_accounts = &[
         (sender, sender_account),
@@ -363,148 +139,1275 @@ fn test_escrow_exchange_success() {
         (escrow, escrow_account),
         (escrow_ata, escrow_ata_account),
         (system_program, system_account),
-        (token_program, token_account),
+        (token_program, token_program_account),
     ];
     let res = mollusk.process_and_validate_instruction_chain(
         &[(
-            &instruction_exchange(
-                &sender,
-                &receiver,
-                &receiver_ata,
-                &escrow,
-                &escrow_ata,
-                bump,
-                &system_program,
-                &token_program,
-            ),
+            &escrow_client::exchange(&escrow_accounts.exchange()),
             &[Check::success()],
         )],
         tx_accounts,
     );
     assert!(matches!(res.program_result, ProgramResult::Success));
+    assert_within_budget(&res, budgets::escrow::EXCHANGE);
+    assert_logs_snapshot(
+        "snapshots",
+        &snapshot_name("exchange", &token_program),
+        &logs.take(),
+        &[],
+    );
+    assert_token_balance(&res, &receiver_ata, 100);
+    assert_token_balance(&res, &escrow_ata, 0);
 }
 
-#[test]
-fn test_escrow_cancel_success() {
-    let mut mollusk = Mollusk::new(&ID, "target/deploy/escrow");
-    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+fn test_escrow_cancel_success(token_program: &Pubkey) {
+    let mut mollusk = mollusk();
+    let logs = LogRecorder::new(&mut mollusk);
 
     let (system_program, system_account) = keyed_account_for_system_program();
-    let (token_program, token_account) = (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID));
-
-    // Initialize mint.
-    let mint = Pubkey::new_unique();
-    let mut mint_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
-        Mint::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        Mint {
-            mint_authority: COption::None,
-            supply: 1_000_000,
-            decimals: 6,
-            is_initialized: true,
-            freeze_authority: COption::None,
-        },
-        mint_account.data_as_mut_slice(),
-    )
-    .unwrap();
-
-    let sender = Pubkey::new_unique();
-    let sender_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &system_program);
-
-    let sender_ata = Pubkey::new_unique();
-    let mut sender_ata_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
-        TokenAccount::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        TokenAccount {
-            mint,
-            owner: sender,
-            amount: 1_000_000,
-            delegate: COption::None,
-            state: TokenAccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        sender_ata_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    let (token_program, token_program_account) = keyed_token_program_with_id(token_program);
 
-    let receiver = Pubkey::new_unique();
-    let receiver_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &system_program);
+    let mint = mint_a();
 
-    let (escrow, bump) = Pubkey::find_program_address(
-        &[
-            ESCROW_SEED.as_bytes(),
-            sender.as_array(),
-            receiver.as_array(),
-        ],
-        &ID,
-    );
-    let mut escrow_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(Escrow::LEN),
-        Escrow::LEN,
-        &system_program,
-    );
-    let escrow_data = Escrow {
-        sender: sender.to_bytes(),
-        receiver: receiver.to_bytes(),
-        amount: 100,
-    };
-    let escrow_data =
-        unsafe { &*(&escrow_data as *const Escrow as *const [u8; size_of::<Escrow>()]) };
-    escrow_account.data.copy_from_slice(escrow_data);
-
-    let escrow_ata = Pubkey::new_unique();
-    let mut escrow_ata_account = Account::new(
-        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
-        TokenAccount::LEN,
-        &token_program,
-    );
-    Pack::pack(
-        TokenAccount {
-            mint,
-            owner: escrow,
-            amount: 100,
-            delegate: COption::None,
-            state: TokenAccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        escrow_ata_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    let sender = alice().pubkey();
+    let sender_account = funded_account(LAMPORTS_PER_SOL);
+
+    let sender_ata = address("alice_ata");
+    let sender_ata_account = token_account_with_program(&token_program, &mint, &sender, 1_000_000);
+
+    let receiver = bob().pubkey();
+    let receiver_account = funded_account(LAMPORTS_PER_SOL);
+
+    let receiver_ata = address("bob_ata");
+    let receiver_ata_account = token_account_with_program(&token_program, &mint, &receiver, 0);
+
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_account =
+        state_account(&Escrow::new(sender.to_bytes(), receiver.to_bytes(), 100, 0));
+
+    let escrow_ata = address("escrow_ata");
+    let escrow_ata_account = token_account_with_program(&token_program, &mint, &escrow, 100);
+
+    let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+        .token_program(&token_program)
+        .sender_ata(&sender_ata)
+        .receiver_ata(&receiver_ata)
+        .escrow_ata(&escrow_ata);
 
     let tx_accounts = &[
         (sender, sender_account),
         (sender_ata, sender_ata_account),
         (receiver, receiver_account),
+        (receiver_ata, receiver_ata_account),
         (escrow, escrow_account),
         (escrow_ata, escrow_ata_account),
         (system_program, system_account),
-        (token_program, token_account),
+        (token_program, token_program_account),
     ];
     let res = mollusk.process_and_validate_instruction_chain(
         &[(
-            &instruction_cancel(
-                &sender,
-                &sender_ata,
-                &receiver,
-                &escrow,
-                &escrow_ata,
-                bump,
-                &system_program,
-                &token_program,
-            ),
+            &escrow_client::cancel(&escrow_accounts.cancel()),
             &[Check::success()],
         )],
         tx_accounts,
     );
     assert!(matches!(res.program_result, ProgramResult::Success));
+    assert_within_budget(&res, budgets::escrow::CANCEL);
+    assert_logs_snapshot(
+        "snapshots",
+        &snapshot_name("cancel", &token_program),
+        &logs.take(),
+        &[],
+    );
+    assert_token_balance(&res, &sender_ata, 1_000_100);
+    assert_token_balance(&res, &receiver_ata, 0);
+    assert_token_balance(&res, &escrow_ata, 0);
+}
+
+/// Escrows with a cancellation penalty, cancelled before and after the
+/// receiver accepted them. Only the cancellation after the acceptance pays
+/// the penalty, rounded down.
+fn test_escrow_cancel_with_penalty(token_program: &Pubkey) {
+    let mollusk = mollusk();
+
+    let (system_program, system_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = keyed_token_program_with_id(token_program);
+
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let sender_ata = address("alice_ata");
+    let receiver = bob().pubkey();
+    let receiver_ata = address("bob_ata");
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_ata = address("escrow_ata");
+    let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+        .token_program(&token_program)
+        .sender_ata(&sender_ata)
+        .receiver_ata(&receiver_ata)
+        .escrow_ata(&escrow_ata);
+
+    let tx_accounts = [
+        (sender, funded_account(LAMPORTS_PER_SOL)),
+        (
+            sender_ata,
+            token_account_with_program(&token_program, &mint, &sender, 1_000),
+        ),
+        (receiver, funded_account(LAMPORTS_PER_SOL)),
+        (
+            receiver_ata,
+            token_account_with_program(&token_program, &mint, &receiver, 0),
+        ),
+        (escrow, Account::new(0, 0, &system_program)),
+        (
+            escrow_ata,
+            token_account_with_program(&token_program, &mint, &escrow, 0),
+        ),
+        (system_program, system_account),
+        (token_program, token_program_account),
+    ];
+    // 2.5% of 199 tokens is 4.975 tokens.
+    let initialize =
+        escrow_client::initialize_with_penalty(&escrow_accounts.initialize(), 199, 250);
+    let accept = escrow_client::accept(&escrow_accounts.accept());
+    let cancel = escrow_client::cancel(&escrow_accounts.cancel());
+
+    // Cancelling before the acceptance refunds everything.
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&initialize, &[Check::success()]),
+            (&cancel, &[Check::success()]),
+        ],
+        &tx_accounts,
+    );
+    assert_token_balance(&res, &sender_ata, 1_000);
+    assert_token_balance(&res, &receiver_ata, 0);
+    assert_token_balance(&res, &escrow_ata, 0);
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&initialize, &[Check::success()]),
+            (&accept, &[Check::success()]),
+        ],
+        &tx_accounts,
+    );
+    let state: Escrow = account_state(&res, &escrow);
+    assert_eq!(state.penalty_bps, 250);
+    assert_eq!(state.accepted, 1);
+    assert_eq!(state.penalty(), 4);
+
+    // Cancelling after it pays the penalty.
+    let res = mollusk.process_and_validate_instruction(
+        &cancel,
+        &res.resulting_accounts,
+        &[Check::success()],
+    );
+    assert_token_balance(&res, &sender_ata, 996);
+    assert_token_balance(&res, &receiver_ata, 4);
+    assert_token_balance(&res, &escrow_ata, 0);
+}
+
+/// Escrows without a penalty, initialized with version 1, refund
+/// everything even after the receiver accepted them.
+fn test_escrow_cancel_without_penalty(token_program: &Pubkey) {
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    // The token accounts are the associated ones, like in the scenario.
+    let escrow_accounts =
+        AccountsBuilder::new(&sender, &receiver, &mint).token_program(token_program);
+
+    Scenario::new()
+        .token_program(token_program)
+        .with_mint(&mint, 6, 1_000)
+        .with_funded(&sender, LAMPORTS_PER_SOL)
+        .with_funded(&receiver, LAMPORTS_PER_SOL)
+        .with_empty(&escrow)
+        .with_ata(&sender, &mint, 1_000)
+        .with_ata(&receiver, &mint, 0)
+        .with_ata(&escrow, &mint, 0)
+        .then(escrow_client::initialize(
+            &escrow_accounts.initialize(),
+            100,
+        ))
+        .then(escrow_client::accept(&escrow_accounts.accept()))
+        .then(escrow_client::cancel(&escrow_accounts.cancel()))
+        .expect_balance(&sender, &mint, 1_000)
+        .expect_balance(&receiver, &mint, 0)
+        .expect_balance(&escrow, &mint, 0)
+        .run();
+}
+
+/// Cancelling an escrow burning on cancellation pays the penalty to the
+/// receiver and burns the rest, reducing the supply of the mint.
+fn test_escrow_cancel_burning(token_program: &Pubkey) {
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_accounts =
+        AccountsBuilder::new(&sender, &receiver, &mint).token_program(token_program);
+    let cancel = escrow_accounts.cancel();
+
+    let res = Scenario::new()
+        .token_program(token_program)
+        .with_mint(&mint, 6, 1_000)
+        .with_funded(&sender, LAMPORTS_PER_SOL)
+        .with_funded(&receiver, LAMPORTS_PER_SOL)
+        .with_empty(&escrow)
+        .with_ata(&sender, &mint, 1_000)
+        .with_ata(&receiver, &mint, 0)
+        .with_ata(&escrow, &mint, 0)
+        .then(escrow_client::initialize_burning_on_cancel(
+            &escrow_accounts.initialize(),
+            100,
+            1_000,
+        ))
+        .then(escrow_client::accept(&escrow_accounts.accept()))
+        .run();
+    let state: Escrow = account_state(&res, &escrow);
+    assert_eq!((state.amount, state.burn_on_cancel), (100, 1));
+
+    // The burn needs the mint.
+    let mollusk = mollusk();
+    mollusk.process_and_validate_instruction(
+        &escrow_client::cancel(&cancel),
+        &res.resulting_accounts,
+        &[Check::err(ProgramError::NotEnoughAccountKeys)],
+    );
+
+    let res = mollusk.process_and_validate_instruction(
+        &escrow_client::cancel_burning(&cancel, &mint),
+        &res.resulting_accounts,
+        &[Check::success()],
+    );
+    assert_token_balance(&res, &cancel.sender_ata, 900);
+    assert_token_balance(&res, &cancel.receiver_ata, 10);
+    assert_token_balance(&res, &cancel.escrow_ata, 0);
+    assert_eq!(mint_supply(res.get_account(&mint).unwrap()), 910);
+}
+
+/// Amounts shown to users are converted with the decimals of the mint,
+/// which the deposit checks, so a client mistaking them fails instead of
+/// escrowing a thousand times more or less.
+fn test_escrow_initialize_checked(token_program: &Pubkey) {
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_accounts =
+        AccountsBuilder::new(&sender, &receiver, &mint).token_program(token_program);
+    let scenario = || {
+        Scenario::new()
+            .token_program(token_program)
+            .with_mint(&mint, 6, 10_000_000)
+            .with_funded(&sender, LAMPORTS_PER_SOL)
+            .with_funded(&receiver, LAMPORTS_PER_SOL)
+            .with_empty(&escrow)
+            .with_ata(&sender, &mint, 10_000_000)
+            .with_ata(&escrow, &mint, 0)
+    };
+
+    let mint_account = mint_account(6, 10_000_000);
+    let decimals = escrow_client::try_mint_decimals_from_account_data(&mint_account.data).unwrap();
+    let res = scenario()
+        .then(
+            escrow_client::initialize_ui_amount(
+                &escrow_accounts.initialize_checked(),
+                "1.5",
+                decimals,
+            )
+            .unwrap(),
+        )
+        .expect_balance(&sender, &mint, 8_500_000)
+        .expect_balance(&escrow, &mint, 1_500_000)
+        .run();
+    let state: Escrow = account_state(&res, &escrow);
+    assert_eq!(state.amount, 1_500_000);
+
+    // `MintDecimalsMismatch` of the token program.
+    scenario()
+        .then_check(
+            escrow_client::initialize_ui_amount(&escrow_accounts.initialize_checked(), "1.5", 9)
+                .unwrap(),
+            vec![Check::err(ProgramError::Custom(18))],
+        )
+        .run();
+
+    assert_eq!(
+        escrow_client::initialize_ui_amount(&escrow_accounts.initialize_checked(), "1.5000001", 6),
+        Err(UiAmountError::TooPrecise)
+    );
+}
+
+/// A delegated escrow leaves the tokens in the token account of the sender,
+/// only delegated to the escrow, until the exchange moves them to the
+/// receiver.
+/// Returns a metadata account, of which only the key byte matters to the
+/// escrow.
+fn metadata_account() -> Account {
+    Account {
+        lamports: LAMPORTS_PER_SOL,
+        data: vec![4; 679],
+        owner: TOKEN_METADATA_PROGRAM_ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// An NFT escrow holds the single token of a mint without decimals, and the
+/// exchange can check that the NFT has its metadata account.
+fn test_escrow_nft(token_program: &Pubkey) {
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_accounts =
+        AccountsBuilder::new(&sender, &receiver, &mint).token_program(token_program);
+    let scenario = |decimals, supply| {
+        Scenario::new()
+            .token_program(token_program)
+            .with_mint(&mint, decimals, supply)
+            .with_funded(&sender, LAMPORTS_PER_SOL)
+            .with_funded(&receiver, LAMPORTS_PER_SOL)
+            .with_empty(&escrow)
+            .with_ata(&sender, &mint, 1)
+            .with_ata(&receiver, &mint, 0)
+            .with_ata(&escrow, &mint, 0)
+            .with_account(&find_metadata_address(&mint), metadata_account())
+    };
+
+    let res = scenario(0, 1)
+        .then(escrow_client::initialize_nft(
+            &escrow_accounts.initialize_checked(),
+        ))
+        .expect_balance(&sender, &mint, 0)
+        .expect_balance(&escrow, &mint, 1)
+        .run();
+    let state: Escrow = account_state(&res, &escrow);
+    assert_eq!((state.amount, state.nft), (1, 1));
+
+    scenario(0, 1)
+        .then(escrow_client::initialize_nft(
+            &escrow_accounts.initialize_checked(),
+        ))
+        .then(escrow_client::exchange_verifying_metadata(
+            &escrow_accounts.exchange(),
+            &mint,
+        ))
+        .expect_balance(&escrow, &mint, 0)
+        .expect_balance(&receiver, &mint, 1)
+        .run();
+
+    // Mints with decimals or more than one token aren't NFTs.
+    for (decimals, supply) in [(6, 1), (0, 2)] {
+        scenario(decimals, supply)
+            .then_check(
+                escrow_client::initialize_nft(&escrow_accounts.initialize_checked()),
+                vec![Check::err(EscrowError::NotAnNft.into())],
+            )
+            .run();
+    }
+
+    // The metadata account has to be the one of the mint and owned by the
+    // Token Metadata program.
+    let other_metadata = find_metadata_address(&address("other_mint"));
+    let mut exchange = escrow_client::exchange(&escrow_accounts.exchange());
+    exchange
+        .accounts
+        .push(AccountMeta::new_readonly(other_metadata, false));
+    scenario(0, 1)
+        .with_account(&other_metadata, metadata_account())
+        .then(escrow_client::initialize_nft(
+            &escrow_accounts.initialize_checked(),
+        ))
+        .then_check(exchange, vec![Check::err(ProgramError::InvalidSeeds)])
+        .run();
+    scenario(0, 1)
+        .with_account(
+            &find_metadata_address(&mint),
+            funded_account(LAMPORTS_PER_SOL),
+        )
+        .then(escrow_client::initialize_nft(
+            &escrow_accounts.initialize_checked(),
+        ))
+        .then_check(
+            escrow_client::exchange_verifying_metadata(&escrow_accounts.exchange(), &mint),
+            vec![Check::err(ProgramError::IllegalOwner)],
+        )
+        .run();
+}
+
+fn test_escrow_delegated_exchange(token_program: &Pubkey) {
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_accounts =
+        AccountsBuilder::new(&sender, &receiver, &mint).token_program(token_program);
+    let sender_ata = escrow_accounts.initialize_delegated().sender_ata;
+
+    let res = Scenario::new()
+        .token_program(token_program)
+        .with_mint(&mint, 6, 1_000)
+        .with_funded(&sender, LAMPORTS_PER_SOL)
+        .with_funded(&receiver, LAMPORTS_PER_SOL)
+        .with_empty(&escrow)
+        .with_ata(&sender, &mint, 1_000)
+        .with_ata(&receiver, &mint, 0)
+        .then(escrow_client::initialize_delegated(
+            &escrow_accounts.initialize_delegated(),
+            100,
+        ))
+        .expect_balance(&sender, &mint, 1_000)
+        .run();
+    let state: Escrow = account_state(&res, &escrow);
+    assert_eq!((state.amount, state.delegated), (100, 1));
+    assert_eq!(
+        token_delegate(res.get_account(&sender_ata).unwrap()),
+        Some((escrow, 100))
+    );
+
+    let res = mollusk().process_and_validate_instruction(
+        &escrow_client::exchange(&escrow_accounts.exchange_delegated()),
+        &res.resulting_accounts,
+        &[Check::success()],
+    );
+    assert_token_balance(&res, &sender_ata, 900);
+    assert_token_balance(
+        &res,
+        &escrow_accounts.exchange_delegated().receiver_ata,
+        100,
+    );
+    // The exchange used up the delegation.
+    assert_eq!(token_delegate(res.get_account(&sender_ata).unwrap()), None);
+}
+
+/// Cancelling a delegated escrow pays the penalty out of the token account
+/// of the sender and revokes the delegation, so the receiver can't exchange
+/// it anymore.
+fn test_escrow_delegated_cancel(token_program: &Pubkey) {
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_accounts =
+        AccountsBuilder::new(&sender, &receiver, &mint).token_program(token_program);
+    let sender_ata = escrow_accounts.cancel_delegated().sender_ata;
+
+    let res = Scenario::new()
+        .token_program(token_program)
+        .with_mint(&mint, 6, 1_000)
+        .with_funded(&sender, LAMPORTS_PER_SOL)
+        .with_funded(&receiver, LAMPORTS_PER_SOL)
+        .with_empty(&escrow)
+        .with_ata(&sender, &mint, 1_000)
+        .with_ata(&receiver, &mint, 0)
+        .then(escrow_client::initialize_delegated_with_penalty(
+            &escrow_accounts.initialize_delegated(),
+            100,
+            1_000,
+        ))
+        .then(escrow_client::accept(&escrow_accounts.accept()))
+        .then(escrow_client::cancel_delegated(
+            &escrow_accounts.cancel_delegated(),
+        ))
+        .then_check(
+            escrow_client::exchange(&escrow_accounts.exchange_delegated()),
+            vec![Check::err(EscrowError::MissingDelegation.into())],
+        )
+        .expect_balance(&sender, &mint, 990)
+        .expect_balance(&receiver, &mint, 10)
+        .run();
+    assert_eq!(token_delegate(res.get_account(&sender_ata).unwrap()), None);
+}
+
+/// An exchange followed by an increment of the counter of the receiver,
+/// which records its completed trades, with both programs in one chain.
+fn test_escrow_exchange_with_counter(token_program: &Pubkey) {
+    let mollusk = mollusk();
+
+    let (system_program, system_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = keyed_token_program_with_id(token_program);
+
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let sender_ata = address("alice_ata");
+    let receiver = bob().pubkey();
+    let receiver_ata = address("bob_ata");
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_ata = address("escrow_ata");
+    let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+        .token_program(&token_program)
+        .sender_ata(&sender_ata)
+        .receiver_ata(&receiver_ata)
+        .escrow_ata(&escrow_ata);
+    let (counter, _) = counter_client::find_counter_address(&receiver);
+
+    let tx_accounts = &[
+        (sender, funded_account(LAMPORTS_PER_SOL)),
+        (
+            sender_ata,
+            token_account_with_program(&token_program, &mint, &sender, 1_000_000),
+        ),
+        (receiver, funded_account(LAMPORTS_PER_SOL)),
+        (
+            receiver_ata,
+            token_account_with_program(&token_program, &mint, &receiver, 0),
+        ),
+        (escrow, Account::new(0, 0, &system_program)),
+        (
+            escrow_ata,
+            token_account_with_program(&token_program, &mint, &escrow, 0),
+        ),
+        (counter, Account::new(0, 0, &system_program)),
+        (system_program, system_account),
+        (token_program, token_program_account),
+    ];
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &escrow_client::initialize(&escrow_accounts.initialize(), 100),
+                &[Check::success()],
+            ),
+            (&counter_client::create(&receiver), &[Check::success()]),
+            (
+                &escrow_client::exchange(&escrow_accounts.exchange()),
+                &[Check::success()],
+            ),
+            (&counter_client::increment(&receiver), &[Check::success()]),
+        ],
+        tx_accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+
+    assert_token_balance(&res, &receiver_ata, 100);
+    assert_token_balance(&res, &escrow_ata, 0);
+    let trades: Counter = account_state(&res, &counter);
+    assert_eq!(trades.owner, receiver.to_bytes());
+    assert_eq!(trades.count, 1);
+}
+
+/// A transaction initializing the escrow with version 1 and exchanging it
+/// with version 2, e.g. from a client which only moved the exchange to it.
+fn test_escrow_mixed_versions(token_program: &Pubkey) {
+    let mollusk = mollusk();
+
+    let (system_program, system_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = keyed_token_program_with_id(token_program);
+
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let sender_ata = address("alice_ata");
+    let receiver = bob().pubkey();
+    let receiver_ata = address("bob_ata");
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_ata = address("escrow_ata");
+
+    let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+        .token_program(&token_program)
+        .sender_ata(&sender_ata)
+        .receiver_ata(&receiver_ata)
+        .escrow_ata(&escrow_ata);
+
+    let initialize = escrow_client::initialize(&escrow_accounts.initialize(), 100);
+    let exchange = escrow_client::exchange_expecting(&escrow_accounts.exchange(), 100);
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&initialize, &[Check::success()]),
+            (&exchange, &[Check::success()]),
+        ],
+        &[
+            (sender, funded_account(LAMPORTS_PER_SOL)),
+            (
+                sender_ata,
+                token_account_with_program(&token_program, &mint, &sender, 1_000),
+            ),
+            (receiver, funded_account(LAMPORTS_PER_SOL)),
+            (
+                receiver_ata,
+                token_account_with_program(&token_program, &mint, &receiver, 0),
+            ),
+            (escrow, Account::new(0, 0, &system_program)),
+            (
+                escrow_ata,
+                token_account_with_program(&token_program, &mint, &escrow, 0),
+            ),
+            (system_program, system_account),
+            (token_program, token_program_account),
+        ],
+    );
+    assert_token_balance(&res, &sender_ata, 900);
+    assert_token_balance(&res, &receiver_ata, 100);
+    assert_token_balance(&res, &escrow_ata, 0);
+}
+
+/// Returns a scenario with an escrow of `100 * (i + 1)` tokens from the
+/// `i`-th of `senders` to Bob, and the accounts of a batch releasing them in
+/// order.
+fn batch_scenario<'a>(
+    mollusk: Mollusk,
+    token_program: &Pubkey,
+    senders: &[Pubkey],
+) -> (Scenario<'a>, ExchangeBatchAccounts) {
+    let mint = mint_a();
+    let receiver = bob().pubkey();
+
+    let mut scenario = Scenario::with_mollusk(mollusk)
+        .token_program(token_program)
+        .with_mint(&mint, 6, 1_000_000)
+        .with_funded(&receiver, LAMPORTS_PER_SOL)
+        .with_ata(&receiver, &mint, 0);
+    let mut exchanges = Vec::new();
+    for (i, sender) in senders.iter().enumerate() {
+        let amount = 100 * (i as u64 + 1);
+        let (escrow, _) = find_escrow_address(sender, &receiver);
+        let escrow_account = state_account(&Escrow::new(
+            sender.to_bytes(),
+            receiver.to_bytes(),
+            amount,
+            0,
+        ));
+        scenario = scenario
+            .with_funded(sender, LAMPORTS_PER_SOL)
+            .with_account(&escrow, escrow_account)
+            .with_ata(&escrow, &mint, amount);
+        exchanges.push(
+            AccountsBuilder::new(sender, &receiver, &mint)
+                .token_program(token_program)
+                .exchange(),
+        );
+    }
+    (scenario, ExchangeBatchAccounts::new(&exchanges))
+}
+
+/// A batch releases all escrows to the receiver and returns how many it
+/// released.
+fn test_escrow_exchange_batch_success(token_program: &Pubkey) {
+    let mint = mint_a();
+    let receiver = bob().pubkey();
+    let senders = [alice().pubkey(), address("carol"), address("dave")];
+    let exchanged = 3u64.to_le_bytes();
+
+    let (scenario, batch) = batch_scenario(mollusk(), token_program, &senders);
+    let res = scenario
+        .then_check(
+            escrow_client::exchange_batch(&batch),
+            vec![Check::success(), Check::return_data(&exchanged)],
+        )
+        .expect_balance(&receiver, &mint, 600)
+        .expect_balance(&batch.escrows[0].escrow, &mint, 0)
+        .expect_balance(&batch.escrows[1].escrow, &mint, 0)
+        .expect_balance(&batch.escrows[2].escrow, &mint, 0)
+        .run();
+    assert_within_budget(&res, 3 * budgets::escrow::EXCHANGE);
+}
+
+/// Anyone can refund an escrow to the sender once the slot is past its
+/// expiry slot, but not before, nor an escrow which never expires. The
+/// refund closes the escrow PDA, refunding its rent to the sender.
+fn test_escrow_refund_expired(token_program: &Pubkey) {
+    const EXPIRY_SLOT: u64 = 1_000;
+
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_accounts =
+        AccountsBuilder::new(&sender, &receiver, &mint).token_program(token_program);
+    let refund = escrow_accounts.refund_expired();
+    let instruction = escrow_client::refund_expired(&refund);
+    assert!(instruction.accounts.iter().all(|meta| !meta.is_signer));
+    let scenario = || {
+        Scenario::new()
+            .token_program(token_program)
+            .with_mint(&mint, 6, 1_000)
+            .with_funded(&sender, LAMPORTS_PER_SOL)
+            .with_funded(&receiver, LAMPORTS_PER_SOL)
+            .with_empty(&escrow)
+            .with_ata(&sender, &mint, 1_000)
+            .with_ata(&escrow, &mint, 0)
+    };
+
+    // Escrows initialized with the earlier versions never expire.
+    let res = scenario()
+        .then(escrow_client::initialize(
+            &escrow_accounts.initialize(),
+            100,
+        ))
+        .run();
+    let mut mollusk = mollusk();
+    mollusk.sysvars.clock.slot = u64::MAX;
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &res.resulting_accounts,
+        &[Check::err(EscrowError::NotExpired.into())],
+    );
+
+    let res = scenario()
+        .then(escrow_client::initialize_with_expiry(
+            &escrow_accounts.initialize(),
+            100,
+            0,
+            EXPIRY_SLOT,
+        ))
+        .expect_balance(&sender, &mint, 900)
+        .run();
+    let state: Escrow = account_state(&res, &escrow);
+    assert_eq!(state.expiry_slot, EXPIRY_SLOT);
+
+    // The escrow only expires after its expiry slot.
+    mollusk.sysvars.clock.slot = EXPIRY_SLOT;
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &res.resulting_accounts,
+        &[Check::err(EscrowError::NotExpired.into())],
+    );
+
+    let rent = res.get_account(&escrow).unwrap().lamports;
+    let sender_lamports = res.get_account(&sender).unwrap().lamports;
+    mollusk.sysvars.clock.slot = EXPIRY_SLOT + 1;
+    let res = mollusk.process_and_validate_instruction(
+        &instruction,
+        &res.resulting_accounts,
+        &[
+            Check::success(),
+            Check::account(&escrow).closed().build(),
+            Check::account(&sender)
+                .lamports(sender_lamports + rent)
+                .build(),
+        ],
+    );
+    assert_token_balance(&res, &refund.sender_ata, 1_000);
+    assert_token_balance(&res, &refund.escrow_ata, 0);
+
+    // The tokens are only refunded once, as the escrow PDA is closed.
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &res.resulting_accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+/// Escrows created before the expiry slot was added keep their shorter
+/// layout. They can still be accepted and exchanged, but never expire.
+fn test_escrow_legacy_layout(token_program: &Pubkey) {
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_accounts =
+        AccountsBuilder::new(&sender, &receiver, &mint).token_program(token_program);
+    let res = Scenario::new()
+        .token_program(token_program)
+        .with_mint(&mint, 6, 1_000)
+        .with_funded(&sender, LAMPORTS_PER_SOL)
+        .with_funded(&receiver, LAMPORTS_PER_SOL)
+        .with_empty(&escrow)
+        .with_ata(&sender, &mint, 1_000)
+        .with_ata(&receiver, &mint, 0)
+        .with_ata(&escrow, &mint, 0)
+        .then(escrow_client::initialize(
+            &escrow_accounts.initialize(),
+            100,
+        ))
+        .run();
+
+    let mut accounts = res.resulting_accounts;
+    let (_, escrow_account) = accounts
+        .iter_mut()
+        .find(|(address, _)| *address == escrow)
+        .unwrap();
+    escrow_account.data.truncate(Escrow::LEGACY_LEN);
+    let state = Escrow::read_versioned(&escrow_account.data).unwrap();
+    assert_eq!(state.amount, 100);
+    assert_eq!(state.expiry_slot, 0);
+
+    let mut mollusk = mollusk();
+    mollusk.sysvars.clock.slot = u64::MAX;
+    mollusk.process_and_validate_instruction(
+        &escrow_client::refund_expired(&escrow_accounts.refund_expired()),
+        &accounts,
+        &[Check::err(EscrowError::NotExpired.into())],
+    );
+
+    let exchange = escrow_accounts.exchange();
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &escrow_client::accept(&escrow_accounts.accept()),
+                &[Check::success()],
+            ),
+            (
+                &escrow_client::exchange_expecting(&exchange, 100),
+                &[Check::success()],
+            ),
+        ],
+        &accounts,
+    );
+    let escrow_account = res.get_account(&escrow).unwrap();
+    assert_eq!(escrow_account.data.len(), Escrow::LEGACY_LEN);
+    assert_eq!(escrow_account.data[Escrow::ACCEPTED_OFFSET], 1);
+    assert_token_balance(&res, &exchange.receiver_ata, 100);
+    assert_token_balance(&res, &exchange.escrow_ata, 0);
+}
+
+token_program_tests!(
+    test_escrow_initialize_success,
+    test_escrow_exchange_success,
+    test_escrow_cancel_success,
+    test_escrow_cancel_with_penalty,
+    test_escrow_cancel_without_penalty,
+    test_escrow_exchange_with_counter,
+    test_escrow_mixed_versions,
+    test_escrow_exchange_batch_success,
+    test_escrow_delegated_exchange,
+    test_escrow_delegated_cancel,
+    test_escrow_cancel_burning,
+    test_escrow_initialize_checked,
+    test_escrow_nft,
+    test_escrow_refund_expired,
+    test_escrow_legacy_layout,
+);
+
+/// A batch stops before an exchange which may not fit into the remaining
+/// compute units, releasing only the escrows before it, and succeeds even
+/// if none fits.
+#[test]
+fn test_escrow_exchange_batch_compute_limit() {
+    let mint = mint_a();
+    let receiver = bob().pubkey();
+    let senders = [alice().pubkey(), address("carol"), address("dave")];
+
+    for (compute_unit_limit, exchanged) in [
+        (EXCHANGE_COMPUTE_UNITS + 2_000, 1),
+        (EXCHANGE_COMPUTE_UNITS / 2, 0),
+    ] {
+        let mut mollusk = mollusk();
+        mollusk.compute_budget.compute_unit_limit = compute_unit_limit;
+        let return_data = u64::to_le_bytes(exchanged);
+
+        let (scenario, batch) = batch_scenario(mollusk, &TOKEN_PROGRAM_ID, &senders);
+        scenario
+            .then_check(
+                escrow_client::exchange_batch(&batch),
+                vec![Check::success(), Check::return_data(&return_data)],
+            )
+            .expect_balance(&receiver, &mint, 100 * exchanged)
+            .expect_balance(&batch.escrows[1].escrow, &mint, 200)
+            .expect_balance(&batch.escrows[2].escrow, &mint, 300)
+            .run();
+    }
+}
+
+/// Returns the name of the snapshot of the logs of `instruction`, whose
+/// CPIs log the token program they invoke.
+fn snapshot_name(instruction: &str, token_program: &Pubkey) -> String {
+    if *token_program == TOKEN_2022_PROGRAM_ID {
+        format!("{instruction}_token_2022")
+    } else {
+        instruction.to_owned()
+    }
+}
+
+/// The state is stored as its fields in order, little-endian and without
+/// padding.
+#[test]
+fn test_escrow_layout() {
+    let mut escrow = Escrow::new([1; 32], [2; 32], 0x0102030405060708, 0x0a0b);
+    escrow.accepted = 1;
+    escrow.expiry_slot = 0x1112131415161718;
+    let data = escrow.as_bytes();
+
+    let mut expected = Vec::new();
+    expected.extend_from_slice(&escrow.sender);
+    expected.extend_from_slice(&escrow.receiver);
+    expected.extend_from_slice(&escrow.amount.to_le_bytes());
+    expected.extend_from_slice(&escrow.penalty_bps.to_le_bytes());
+    expected.push(1);
+    expected.extend_from_slice(&[0; 5]);
+    expected.extend_from_slice(&escrow.expiry_slot.to_le_bytes());
+    assert_eq!(data, expected);
+
+    let state = Escrow::read(&state_account(&escrow).data).unwrap();
+    assert_eq!(state.sender, escrow.sender);
+    assert_eq!(state.receiver, escrow.receiver);
+    assert_eq!(state.amount, escrow.amount);
+    assert_eq!(state.penalty_bps, escrow.penalty_bps);
+    assert_eq!(state.accepted, escrow.accepted);
+    assert_eq!(state.expiry_slot, escrow.expiry_slot);
+}
+
+/// Instruction data is the discriminator and the version followed by the
+/// fields in order, little-endian and without padding, and decodes back to
+/// the same arguments.
+#[test]
+fn test_instruction_data_round_trip() {
+    let args = InstructionArgs::Initialize {
+        amount: 0x0102030405060708,
+        bump: 254,
+    };
+    let data = args.encode();
+    assert_eq!(
+        data,
+        [
+            EscrowInstruction::Initialize
+                .discriminator(Version::V1)
+                .as_slice(),
+            &[8, 7, 6, 5, 4, 3, 2, 1, 254]
+        ]
+        .concat()
+    );
+    assert_eq!(InstructionArgs::decode(&data), Ok(args));
+
+    let args = InstructionArgs::ExchangeV2 {
+        bump: 254,
+        amount: 0x0102030405060708,
+    };
+    let data = args.encode();
+    assert_eq!(
+        data,
+        [
+            EscrowInstruction::Exchange
+                .discriminator(Version::V2)
+                .as_slice(),
+            &[254, 8, 7, 6, 5, 4, 3, 2, 1]
+        ]
+        .concat()
+    );
+    assert_eq!(InstructionArgs::decode(&data), Ok(args));
+
+    let args = InstructionArgs::InitializeV2 {
+        amount: 0x0102030405060708,
+        bump: 254,
+        penalty_bps: 0x0a0b,
+    };
+    let data = args.encode();
+    assert_eq!(
+        data,
+        [
+            EscrowInstruction::Initialize
+                .discriminator(Version::V2)
+                .as_slice(),
+            &[8, 7, 6, 5, 4, 3, 2, 1, 254, 0x0b, 0x0a]
+        ]
+        .concat()
+    );
+    assert_eq!(InstructionArgs::decode(&data), Ok(args));
+
+    for args in [
+        InstructionArgs::Exchange { bump: 253 },
+        InstructionArgs::Cancel { bump: 252 },
+        InstructionArgs::CancelV2 {
+            bump: 251,
+            amount: 42,
+        },
+        InstructionArgs::Accept { bump: 250 },
+        InstructionArgs::ExchangeBatch {
+            bumps: vec![249, 248],
+        },
+        InstructionArgs::InitializeDelegated {
+            amount: 42,
+            bump: 247,
+        },
+        InstructionArgs::InitializeDelegatedV2 {
+            amount: 42,
+            bump: 246,
+            penalty_bps: 500,
+        },
+        InstructionArgs::CancelDelegated { bump: 245 },
+        InstructionArgs::CancelDelegatedV2 {
+            bump: 244,
+            amount: 42,
+        },
+        InstructionArgs::InitializeV3 {
+            amount: 42,
+            bump: 243,
+            penalty_bps: 500,
+            burn_on_cancel: true,
+        },
+        InstructionArgs::InitializeChecked {
+            amount: 42,
+            decimals: 6,
+            bump: 242,
+            penalty_bps: 500,
+            burn_on_cancel: false,
+        },
+        InstructionArgs::InitializeNft { bump: 241 },
+        InstructionArgs::InitializeV4 {
+            amount: 42,
+            bump: 240,
+            penalty_bps: 500,
+            burn_on_cancel: false,
+            expiry_slot: 1_000,
+        },
+        InstructionArgs::RefundExpired { bump: 239 },
+    ] {
+        assert_eq!(InstructionArgs::decode(&args.encode()), Ok(args));
+    }
+
+    // The builders encode the same data.
+    let builder = AccountsBuilder::new(&alice().pubkey(), &bob().pubkey(), &mint_a());
+    let (_, bump) = find_escrow_address(&alice().pubkey(), &bob().pubkey());
+    let instruction = escrow_client::initialize(&builder.initialize(), 42);
+    assert_eq!(
+        InstructionArgs::decode(&instruction.data),
+        Ok(InstructionArgs::Initialize { amount: 42, bump })
+    );
+    let instruction = escrow_client::cancel_expecting(&builder.cancel(), 42);
+    assert_eq!(
+        InstructionArgs::decode(&instruction.data),
+        Ok(InstructionArgs::CancelV2 { bump, amount: 42 })
+    );
+
+    // Trailing or missing bytes, and unknown instructions and versions, are
+    // rejected, like by the program.
+    assert!(InstructionArgs::decode(&[1, 1, bump, 0]).is_err());
+    assert!(InstructionArgs::decode(&[1, 2, bump]).is_err());
+    assert!(InstructionArgs::decode(&[0, 1, bump]).is_err());
+    assert!(InstructionArgs::decode(&[0, 2, bump]).is_err());
+    assert!(InstructionArgs::decode(&[1, 3, bump]).is_err());
+    assert!(InstructionArgs::decode(&[3, 2, bump]).is_err());
+    assert!(InstructionArgs::decode(&[4, 1]).is_err());
+    assert!(InstructionArgs::decode(&[4, 2, bump]).is_err());
+    assert!(InstructionArgs::decode(&[5, 1, bump]).is_err());
+    assert!(InstructionArgs::decode(&[7, 1, bump]).is_err());
+    assert!(InstructionArgs::decode(&[8, 2, bump]).is_err());
+    assert!(InstructionArgs::decode(&[9, 2, bump]).is_err());
+    assert!(InstructionArgs::decode(&[1, 4, bump]).is_err());
+    assert!(InstructionArgs::decode(&[5, 3, 42, 0, 0, 0, 0, 0, 0, 0, bump, 0, 0, 0]).is_err());
+    assert!(InstructionArgs::decode(&[0, 3, 42, 0, 0, 0, 0, 0, 0, 0, bump, 0, 0, 2]).is_err());
+    assert!(InstructionArgs::decode(&[1]).is_err());
+}
+
+/// The builder derives the escrow PDA and the associated token accounts of
+/// the mint under its token program, unless the token accounts are set, and
+/// the metas follow the order of the accounts of the program.
+#[test]
+fn test_accounts_builder() {
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let receiver = bob().pubkey();
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let builder = AccountsBuilder::new(&sender, &receiver, &mint);
+
+    let accounts = builder.initialize();
+    assert_eq!(accounts.escrow, escrow);
+    assert_eq!(
+        accounts.sender_ata,
+        find_associated_token_address(&sender, &mint)
+    );
+    assert_eq!(
+        accounts.escrow_ata,
+        find_associated_token_address(&escrow, &mint)
+    );
+    assert_eq!(
+        builder.exchange().receiver_ata,
+        find_associated_token_address(&receiver, &mint)
+    );
+
+    let accounts = builder.token_program(&TOKEN_2022_PROGRAM_ID).exchange();
+    assert_eq!(accounts.token_program, TOKEN_2022_PROGRAM_ID);
+    assert_eq!(
+        accounts.receiver_ata,
+        find_associated_token_address_with_program(&receiver, &mint, &TOKEN_2022_PROGRAM_ID)
+    );
+
+    let escrow_ata = address("escrow_ata");
+    let accounts = builder.escrow_ata(&escrow_ata).cancel();
+    assert_eq!(accounts.escrow_ata, escrow_ata);
+    assert_eq!(
+        accounts.receiver_ata,
+        find_associated_token_address(&receiver, &mint)
+    );
+
+    let addresses: Vec<_> = accounts
+        .to_account_metas()
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(
+        addresses,
+        [
+            sender,
+            accounts.sender_ata,
+            receiver,
+            escrow,
+            escrow_ata,
+            TOKEN_PROGRAM_ID,
+            accounts.receiver_ata,
+        ]
+    );
+}
+
+/// Token balance of the sender and the amount it escrows.
+fn balance_and_amount() -> impl Strategy<Value = (u64, u64)> {
+    (0..1_000_000_000u64).prop_flat_map(|balance| (Just(balance), 0..=balance))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    /// Tokens are conserved across the whole lifecycle of an escrow and end
+    /// up with the receiver after an exchange, or back with the sender after
+    /// a cancellation, with either token program.
+    #[test]
+    fn test_escrow_lifecycle(
+        (balance, amount) in balance_and_amount(),
+        exchange in any::<bool>(),
+    ) {
+        let mollusk = mollusk();
+
+        for token_program in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+            let (system_program, system_account) = keyed_account_for_system_program();
+            let (token_program, token_program_account) =
+                keyed_token_program_with_id(&token_program);
+
+            let mint = Pubkey::new_unique();
+            let sender = Pubkey::new_unique();
+            let sender_ata = Pubkey::new_unique();
+            let receiver = Pubkey::new_unique();
+            let receiver_ata = Pubkey::new_unique();
+            let (escrow, _) = find_escrow_address(&sender, &receiver);
+            let escrow_ata = Pubkey::new_unique();
+            let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+                .token_program(&token_program)
+                .sender_ata(&sender_ata)
+                .receiver_ata(&receiver_ata)
+                .escrow_ata(&escrow_ata);
+
+            let tx_accounts = vec![
+                (sender, funded_account(LAMPORTS_PER_SOL)),
+                (sender_ata, token_account_with_program(&token_program, &mint, &sender, balance)),
+                (receiver, funded_account(LAMPORTS_PER_SOL)),
+                (receiver_ata, token_account_with_program(&token_program, &mint, &receiver, 0)),
+                (escrow, Account::new(0, 0, &system_program)),
+                (escrow_ata, token_account_with_program(&token_program, &mint, &escrow, 0)),
+                (system_program, system_account),
+                (token_program, token_program_account),
+            ];
+            let amounts = |res: &InstructionResult| {
+                [sender_ata, receiver_ata, escrow_ata]
+                    .map(|ata| token_amount(res.get_account(&ata).unwrap()))
+            };
+
+            let res = mollusk.process_and_validate_instruction(
+                &escrow_client::initialize(&escrow_accounts.initialize(), amount),
+                &tx_accounts,
+                &[Check::success()],
+            );
+            prop_assert_eq!(amounts(&res), [balance - amount, 0, amount]);
+
+            let instruction = if exchange {
+                escrow_client::exchange(&escrow_accounts.exchange())
+            } else {
+                escrow_client::cancel(&escrow_accounts.cancel())
+            };
+            let res = mollusk.process_and_validate_instruction(
+                &instruction,
+                &res.resulting_accounts,
+                &[Check::success()],
+            );
+            let expected = if exchange {
+                [balance - amount, amount, 0]
+            } else {
+                [balance, 0, 0]
+            };
+            prop_assert_eq!(amounts(&res), expected);
+        }
+    }
+}
+
+/// Replays the fixtures dumped into `fixtures/` by the other tests.
+#[test]
+fn test_replay_fixtures() {
+    let mollusk = mollusk();
+    assert!(replay_fixtures(&mollusk, "fixtures") > 0);
+}
+
+/// Measures the compute units of every instruction into `benches/`.
+///
+/// With the `checked-borrows` feature, the program built with it is
+/// measured into `benches/checked-borrows/` instead, next to the program
+/// using unchecked borrows:
+///
+/// ```sh
+/// cargo build-sbf --features checked-borrows
+/// cargo test --features checked-borrows test_compute_units
+/// cargo build-sbf
+/// cargo test test_compute_units
+/// ```
+#[test]
+fn test_compute_units() {
+    let mollusk = mollusk();
+    let out_dir = if cfg!(feature = "checked-borrows") {
+        "benches/checked-borrows"
+    } else {
+        "benches"
+    };
+    let mut bencher = Bencher::new(&mollusk, out_dir);
+
+    let (system_program, system_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = keyed_token_program();
+
+    let mint = mint_a();
+    let sender = alice().pubkey();
+    let sender_ata = address("alice_ata");
+    let receiver = bob().pubkey();
+    let receiver_ata = address("bob_ata");
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_ata = address("escrow_ata");
+    let escrow_accounts = AccountsBuilder::new(&sender, &receiver, &mint)
+        .sender_ata(&sender_ata)
+        .receiver_ata(&receiver_ata)
+        .escrow_ata(&escrow_ata);
+
+    let tx_accounts = [
+        (sender, funded_account(LAMPORTS_PER_SOL)),
+        (sender_ata, token_account(&mint, &sender, 1_000_000)),
+        (receiver, funded_account(LAMPORTS_PER_SOL)),
+        (receiver_ata, token_account(&mint, &receiver, 0)),
+        (escrow, Account::new(0, 0, &system_program)),
+        (escrow_ata, token_account(&mint, &escrow, 0)),
+        (system_program, system_account),
+        (token_program, token_program_account),
+    ];
+    let res = bencher.bench(
+        "initialize",
+        &escrow_client::initialize(&escrow_accounts.initialize(), 100),
+        &tx_accounts,
+    );
+    // Exchange and cancel are alternatives, so both start from the
+    // initialized escrow.
+    bencher.bench(
+        "exchange",
+        &escrow_client::exchange(&escrow_accounts.exchange()),
+        &res.resulting_accounts,
+    );
+    bencher.bench(
+        "cancel",
+        &escrow_client::cancel(&escrow_accounts.cancel()),
+        &res.resulting_accounts,
+    );
+    bencher.execute();
+}
+
+/// Extracts the IDL of the program and emits it into `idl/escrow.json`.
+#[test]
+fn test_idl() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let idl = extract_idl(
+        manifest_dir.join("src/lib.rs").to_str().unwrap(),
+        ParseIdlOpts {
+            program_address_override: Some(ID.to_string()),
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .expect("the program should have an IDL");
+    assert_eq!(idl.instructions.len(), 10);
+
+    let idl_dir = manifest_dir.join("idl");
+    fs::create_dir_all(&idl_dir).unwrap();
+    fs::write(idl_dir.join("escrow.json"), idl.try_into_json().unwrap()).unwrap();
 }
@@ -0,0 +1,295 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    log::sol_log_data,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("CU6j8fjEZL5DiHCqbuu9xqBLZZ6BaQrSxjXXzQj4cGch");
+
+/// Maximum number of events stored in the queue.
+pub const QUEUE_CAPACITY: usize = 256;
+
+/// Errors returned by the event queue program.
+#[repr(u32)]
+pub enum EventQueueError {
+    /// The queue account is already initialized.
+    AlreadyInitialized,
+    /// There is no free slot in the queue.
+    QueueFull,
+}
+
+impl From<EventQueueError> for ProgramError {
+    fn from(e: EventQueueError) -> Self {
+        Namespace::EventQueue.error(e as u32)
+    }
+}
+
+/// A single, fixed-size event record.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Event {
+    pub producer: Pubkey,
+    /// Sequence number of the event, unique per queue.
+    pub seq_num: u64,
+    /// Slot in which the event was pushed.
+    pub slot: u64,
+    pub payload: [u8; 16],
+}
+
+assert_layout!(Event, size = 64, align = 8, { producer: 0, seq_num: 32, slot: 40, payload: 48 });
+
+impl Event {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain representation of an event queue.
+///
+/// The queue is a ring buffer: events are pushed at `(head + count) %
+/// QUEUE_CAPACITY` and consumed from `head`. Nothing is ever moved around,
+/// only the indices change.
+#[repr(C)]
+pub struct EventQueue {
+    /// The only account allowed to consume events.
+    pub consumer: Pubkey,
+    pub is_initialized: u8,
+    pub _padding: [u8; 7],
+    /// Position of the oldest event.
+    pub head: u64,
+    /// Number of events in the queue.
+    pub count: u64,
+    /// Sequence number of the next event.
+    pub seq_num: u64,
+    pub events: [Event; QUEUE_CAPACITY],
+}
+
+assert_layout!(EventQueue, size = 16448, align = 8, {
+    consumer: 0,
+    is_initialized: 32,
+    _padding: 33,
+    head: 40,
+    count: 48,
+    seq_num: 56,
+    events: 64,
+});
+
+impl EventQueue {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Event queue program instruction discriminators.
+#[repr(u8)]
+pub enum EventQueueInstruction {
+    /// Initializes a pre-allocated queue account.
+    Initialize,
+    /// Pushes an event to the queue.
+    Push,
+    /// Consumes events from the queue.
+    Consume,
+}
+
+impl TryFrom<&u8> for EventQueueInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Push),
+            2 => Ok(Self::Consume),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct PushInstructionData {
+    pub payload: [u8; 16],
+}
+
+impl PushInstructionData {
+    pub fn new(payload: [u8; 16]) -> Self {
+        Self { payload }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ConsumeInstructionData {
+    /// Maximum number of events to consume.
+    pub limit: u64,
+}
+
+impl ConsumeInstructionData {
+    pub fn new(limit: u64) -> Self {
+        Self { limit }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = EventQueueInstruction::try_from(instruction)?;
+
+    match instruction {
+        EventQueueInstruction::Initialize => process_initialize(accounts),
+        EventQueueInstruction::Push => process_push(accounts, instruction_data),
+        EventQueueInstruction::Consume => process_consume(accounts, instruction_data),
+    }
+}
+
+/// Initializes a queue account.
+///
+/// The account has to be created beforehand with [`EventQueue::LEN`] bytes of
+/// space and this program as the owner, since accounts bigger than 10 KiB
+/// can't be created through CPI.
+pub fn process_initialize(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [consumer, queue] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(consumer)?;
+
+    // Check if the queue account is owned by the program.
+    require_owned_by(queue, &ID)?;
+
+    // Deserialize the queue account.
+    let mut data = queue.try_borrow_mut_data()?;
+    if data.len() != EventQueue::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let queue: &mut EventQueue = unsafe { &mut *data.as_mut_ptr().cast() };
+    if queue.is_initialized != 0 {
+        return Err(EventQueueError::AlreadyInitialized.into());
+    }
+
+    // The rest of the account is already zeroed by the system program.
+    queue.consumer = *consumer.key();
+    queue.is_initialized = 1;
+
+    info!("Initialized queue with capacity {}", QUEUE_CAPACITY);
+
+    Ok(())
+}
+
+/// Pushes an event to the queue. Anyone can be a producer.
+pub fn process_push(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [producer, queue] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(producer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = PushInstructionData::from_bytes(instruction_data)?;
+
+    // Check if the queue account is owned by the program.
+    require_owned_by(queue, &ID)?;
+
+    // Deserialize the queue account.
+    let mut data = queue.try_borrow_mut_data()?;
+    if data.len() != EventQueue::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let queue: &mut EventQueue = unsafe { &mut *data.as_mut_ptr().cast() };
+    if queue.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if queue.count as usize == QUEUE_CAPACITY {
+        return Err(EventQueueError::QueueFull.into());
+    }
+
+    // Write the event directly into its slot.
+    let index = (queue.head + queue.count) as usize % QUEUE_CAPACITY;
+    let event = &mut queue.events[index];
+    event.producer = *producer.key();
+    event.seq_num = queue.seq_num;
+    event.slot = Clock::get()?.slot;
+    event.payload = instruction_data.payload;
+
+    queue.count += 1;
+    queue.seq_num += 1;
+
+    info!("Pushed event {}", event.seq_num);
+
+    Ok(())
+}
+
+/// Consumes up to `limit` of the oldest events, emitting each of them with
+/// `sol_log_data` and returning the number of consumed events as return
+/// data.
+pub fn process_consume(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [consumer, queue] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(consumer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = ConsumeInstructionData::from_bytes(instruction_data)?;
+
+    // Check if the queue account is owned by the program.
+    require_owned_by(queue, &ID)?;
+
+    // Deserialize the queue account.
+    let mut data = queue.try_borrow_mut_data()?;
+    if data.len() != EventQueue::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let queue: &mut EventQueue = unsafe { &mut *data.as_mut_ptr().cast() };
+    if queue.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    // Check if the queue belongs to the `consumer`.
+    if !keys_eq(&queue.consumer, consumer.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let consumed = queue.count.min(instruction_data.limit);
+    for _ in 0..consumed {
+        let event = &mut queue.events[queue.head as usize];
+        let bytes: &[u8; Event::LEN] = unsafe { &*(event as *const Event).cast() };
+        sol_log_data(&[bytes]);
+
+        // Clear the slot, so stale events are never mistaken for new ones.
+        *event = unsafe { mem::zeroed() };
+        queue.head = (queue.head + 1) % QUEUE_CAPACITY as u64;
+    }
+    queue.count -= consumed;
+
+    set_return_data(&consumed.to_le_bytes());
+
+    info!("Consumed {} events", consumed);
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+//! Attacks on the event queue program, each rejected with its own error.
+
+use std::mem;
+
+use event_queue::{ConsumeInstructionData, EventQueue, EventQueueInstruction, PushInstructionData};
+use mollusk_svm::{result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(event_queue::ID);
+
+/// Offset of the count of events in the queue account.
+const COUNT_OFFSET: usize = 48;
+/// Offset of the `events` ring buffer in the queue account.
+const EVENTS_OFFSET: usize = 64;
+
+/// Returns an initialized queue of `consumer` holding one event of
+/// `producer`.
+fn queue_account(mollusk: &Mollusk, consumer: &Pubkey, producer: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(EventQueue::LEN),
+        EventQueue::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(consumer.as_ref());
+    account.data[32] = 1;
+    account.data[COUNT_OFFSET..COUNT_OFFSET + 8].copy_from_slice(&1u64.to_le_bytes());
+    account.data[EVENTS_OFFSET..EVENTS_OFFSET + 32].copy_from_slice(producer.as_ref());
+    account
+}
+
+/// Accounts of a queue with an event of a producer, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    consumer: Pubkey,
+    producer: Pubkey,
+    queue: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/event_queue");
+
+        let consumer = Pubkey::new_unique();
+        let producer = Pubkey::new_unique();
+        let queue = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (consumer, Account::default()),
+            (producer, Account::default()),
+            (queue, queue_account(&mollusk, &consumer, &producer)),
+            (attacker, Account::default()),
+        ];
+
+        Self {
+            mollusk,
+            consumer,
+            producer,
+            queue,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Consumes the events of `queue`, signed by `consumer`.
+    fn instruction_consume(&self, consumer: &Pubkey, queue: &Pubkey) -> Instruction {
+        let mut data = Vec::with_capacity(
+            mem::size_of::<EventQueueInstruction>() + ConsumeInstructionData::LEN,
+        );
+        data.push(EventQueueInstruction::Consume as u8);
+        data.extend_from_slice(&ConsumeInstructionData::new(u64::MAX).to_bytes());
+
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*consumer, true),
+            AccountMeta::new(*queue, false),
+        ];
+        Instruction::new_with_bytes(ID, &data, ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_consumer_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_consume(&setup.consumer, &setup.queue);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker pushes an event in the name of the producer, without its
+/// signature.
+#[test]
+fn test_push_as_other_producer() {
+    let setup = Setup::new();
+    let mut data =
+        Vec::with_capacity(mem::size_of::<EventQueueInstruction>() + PushInstructionData::LEN);
+    data.push(EventQueueInstruction::Push as u8);
+    data.extend_from_slice(&PushInstructionData::new([42; 16]).to_bytes());
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.producer, false),
+        AccountMeta::new(setup.queue, false),
+    ];
+    let instruction = Instruction::new_with_bytes(ID, &data, ix_accounts);
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// A queue of the attacker with a made up event of the producer, owned by
+/// another program, whose events would be logged by the program for
+/// indexers.
+#[test]
+fn test_consume_spoofed_queue() {
+    let setup = Setup::new();
+    let mut queue_account = queue_account(&setup.mollusk, &setup.attacker, &setup.producer);
+    queue_account.owner = Pubkey::new_unique();
+    let queue = Pubkey::new_unique();
+    let setup = setup.with(queue, queue_account);
+    setup.expect_err(
+        &setup.instruction_consume(&setup.attacker, &queue),
+        ProgramError::IllegalOwner,
+    );
+}
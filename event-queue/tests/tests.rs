@@ -0,0 +1,312 @@
+use std::mem;
+
+use errors::Namespace;
+use event_queue::{
+    ConsumeInstructionData, Event, EventQueue, EventQueueError, EventQueueInstruction,
+    PushInstructionData, QUEUE_CAPACITY,
+};
+use mollusk_svm::{
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(event_queue::ID);
+
+/// Offsets of the header fields in the queue account.
+const HEAD_OFFSET: usize = 40;
+const COUNT_OFFSET: usize = 48;
+const SEQ_NUM_OFFSET: usize = 56;
+/// Offset of the `events` ring buffer in the queue account.
+const EVENTS_OFFSET: usize = 64;
+
+fn instruction_initialize(consumer: &Pubkey, queue: &Pubkey) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*consumer, true),
+        AccountMeta::new(*queue, false),
+    ];
+    Instruction::new_with_bytes(ID, &[EventQueueInstruction::Initialize as u8], ix_accounts)
+}
+
+fn instruction_push(payload: [u8; 16], producer: &Pubkey, queue: &Pubkey) -> Instruction {
+    // Create instruction data.
+    let data = PushInstructionData::new(payload);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<EventQueueInstruction>() + PushInstructionData::LEN);
+    data_with_discriminator.push(EventQueueInstruction::Push as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*producer, true),
+        AccountMeta::new(*queue, false),
+    ];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+fn instruction_consume(limit: u64, consumer: &Pubkey, queue: &Pubkey) -> Instruction {
+    // Create instruction data.
+    let data = ConsumeInstructionData::new(limit);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<EventQueueInstruction>() + ConsumeInstructionData::LEN);
+    data_with_discriminator.push(EventQueueInstruction::Consume as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*consumer, true),
+        AccountMeta::new(*queue, false),
+    ];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+/// Creates an empty queue account, like a client would with a top-level
+/// `CreateAccount` instruction.
+fn queue_account(mollusk: &Mollusk) -> Account {
+    Account::new(
+        mollusk.sysvars.rent.minimum_balance(EventQueue::LEN),
+        EventQueue::LEN,
+        &ID,
+    )
+}
+
+/// Creates an initialized queue account with the given header values.
+fn queue_account_with_header(
+    mollusk: &Mollusk,
+    consumer: &Pubkey,
+    head: u64,
+    count: u64,
+    seq_num: u64,
+) -> Account {
+    let mut account = queue_account(mollusk);
+    account.data[..32].copy_from_slice(consumer.as_ref());
+    account.data[32] = 1;
+    account.data[HEAD_OFFSET..COUNT_OFFSET].copy_from_slice(&head.to_le_bytes());
+    account.data[COUNT_OFFSET..SEQ_NUM_OFFSET].copy_from_slice(&count.to_le_bytes());
+    account.data[SEQ_NUM_OFFSET..EVENTS_OFFSET].copy_from_slice(&seq_num.to_le_bytes());
+    account
+}
+
+fn event_offset(index: usize) -> usize {
+    EVENTS_OFFSET + index * Event::LEN
+}
+
+#[test]
+fn test_event_queue_initialize() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/event_queue");
+
+    let consumer = Pubkey::new_unique();
+    let queue = Pubkey::new_unique();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize(&consumer, &queue),
+                &[
+                    Check::success(),
+                    Check::account(&queue)
+                        .data_slice(0, consumer.as_ref())
+                        .data_slice(32, &[1])
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_initialize(&consumer, &queue),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::EventQueue.code(EventQueueError::AlreadyInitialized as u32),
+                ))],
+            ),
+        ],
+        &[
+            (consumer, Account::default()),
+            (queue, queue_account(&mollusk)),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_event_queue_push_and_consume() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/event_queue");
+    mollusk.warp_to_slot(42);
+
+    let consumer = Pubkey::new_unique();
+    let producer_a = Pubkey::new_unique();
+    let producer_b = Pubkey::new_unique();
+    let queue = Pubkey::new_unique();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize(&consumer, &queue),
+                &[Check::success()],
+            ),
+            (
+                &instruction_push([1; 16], &producer_a, &queue),
+                &[Check::success()],
+            ),
+            (
+                &instruction_push([2; 16], &producer_b, &queue),
+                &[Check::success()],
+            ),
+            (
+                &instruction_push([3; 16], &producer_a, &queue),
+                &[
+                    Check::success(),
+                    Check::account(&queue)
+                        .data_slice(HEAD_OFFSET, &0u64.to_le_bytes())
+                        .data_slice(COUNT_OFFSET, &3u64.to_le_bytes())
+                        .data_slice(SEQ_NUM_OFFSET, &3u64.to_le_bytes())
+                        .data_slice(event_offset(1), producer_b.as_ref())
+                        .data_slice(event_offset(1) + 32, &1u64.to_le_bytes())
+                        .data_slice(event_offset(1) + 40, &42u64.to_le_bytes())
+                        .data_slice(event_offset(1) + 48, &[2; 16])
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_consume(2, &consumer, &queue),
+                &[
+                    Check::success(),
+                    Check::return_data(&2u64.to_le_bytes()),
+                    Check::account(&queue)
+                        .data_slice(HEAD_OFFSET, &2u64.to_le_bytes())
+                        .data_slice(COUNT_OFFSET, &1u64.to_le_bytes())
+                        // Consumed slots are cleared.
+                        .data_slice(event_offset(0), &[0; Event::LEN])
+                        .data_slice(event_offset(1), &[0; Event::LEN])
+                        .data_slice(event_offset(2), producer_a.as_ref())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_consume(10, &consumer, &queue),
+                &[
+                    Check::success(),
+                    Check::return_data(&1u64.to_le_bytes()),
+                    Check::account(&queue)
+                        .data_slice(HEAD_OFFSET, &3u64.to_le_bytes())
+                        .data_slice(COUNT_OFFSET, &0u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &[
+            (consumer, Account::default()),
+            (producer_a, Account::default()),
+            (producer_b, Account::default()),
+            (queue, queue_account(&mollusk)),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_event_queue_push_full() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/event_queue");
+
+    let consumer = Pubkey::new_unique();
+    let producer = Pubkey::new_unique();
+    let queue = Pubkey::new_unique();
+
+    mollusk.process_and_validate_instruction(
+        &instruction_push([1; 16], &producer, &queue),
+        &[
+            (producer, Account::default()),
+            (
+                queue,
+                queue_account_with_header(
+                    &mollusk,
+                    &consumer,
+                    0,
+                    QUEUE_CAPACITY as u64,
+                    QUEUE_CAPACITY as u64,
+                ),
+            ),
+        ],
+        &[Check::err(ProgramError::Custom(
+            Namespace::EventQueue.code(EventQueueError::QueueFull as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_event_queue_push_wrap_around() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/event_queue");
+
+    let consumer = Pubkey::new_unique();
+    let producer = Pubkey::new_unique();
+    let queue = Pubkey::new_unique();
+
+    // With the head at the last slot and one event in the queue, the next
+    // event is written to the first slot.
+    let head = QUEUE_CAPACITY as u64 - 1;
+    mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_push([7; 16], &producer, &queue),
+                &[
+                    Check::success(),
+                    Check::account(&queue)
+                        .data_slice(COUNT_OFFSET, &2u64.to_le_bytes())
+                        .data_slice(event_offset(0), producer.as_ref())
+                        .data_slice(event_offset(0) + 32, &100u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_consume(2, &consumer, &queue),
+                &[
+                    Check::success(),
+                    Check::return_data(&2u64.to_le_bytes()),
+                    Check::account(&queue)
+                        .data_slice(HEAD_OFFSET, &1u64.to_le_bytes())
+                        .data_slice(COUNT_OFFSET, &0u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &[
+            (consumer, Account::default()),
+            (producer, Account::default()),
+            (
+                queue,
+                queue_account_with_header(&mollusk, &consumer, head, 1, 100),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn test_event_queue_consume_wrong_consumer() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/event_queue");
+
+    let consumer = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let queue = Pubkey::new_unique();
+
+    mollusk.process_and_validate_instruction(
+        &instruction_consume(1, &attacker, &queue),
+        &[
+            (attacker, Account::default()),
+            (
+                queue,
+                queue_account_with_header(&mollusk, &consumer, 0, 1, 1),
+            ),
+        ],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
@@ -0,0 +1,72 @@
+//! Decoding of events from the logs of transactions.
+
+extern crate alloc;
+
+use alloc::{string::String, vec::Vec};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pinocchio::pubkey::Pubkey;
+
+use crate::Event;
+
+/// Prefix of the lines logged by `sol_log_data`.
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// Returns the events of type `E` emitted by `program_id`, in their order,
+/// from `logs`, the log messages of a transaction.
+///
+/// Events are attributed to the program running when they were logged, so
+/// events of the same type logged by programs invoking `program_id`, or
+/// invoked by it, are skipped.
+pub fn decode_events<E: Event>(program_id: &Pubkey, logs: &[impl AsRef<str>]) -> Vec<E> {
+    program_data(program_id, logs)
+        .into_iter()
+        .filter_map(decode_event)
+        .collect()
+}
+
+/// Returns the fields of the `Program data` lines logged by `program_id`,
+/// in their order, from `logs`.
+///
+/// Decoding each of them with [`decode_event`] for every event type of the
+/// program keeps the order of events of different types.
+pub fn program_data<'a>(program_id: &Pubkey, logs: &'a [impl AsRef<str>]) -> Vec<&'a str> {
+    let program_id = bs58::encode(program_id).into_string();
+    // Programs currently running, the innermost one last.
+    let mut programs: Vec<String> = Vec::new();
+    let mut data = Vec::new();
+
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(fields) = log.strip_prefix(PROGRAM_DATA) {
+            if programs.last() == Some(&program_id) {
+                data.push(fields);
+            }
+        } else if let Some(log) = log.strip_prefix("Program ") {
+            let mut words = log.split(' ');
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => programs.push(program.into()),
+                (Some(_), Some("success" | "failed:")) => {
+                    programs.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    data
+}
+
+/// Decodes an event of type `E` from the base64-encoded fields of a
+/// `Program data` line, if it holds one.
+pub fn decode_event<E: Event>(data: &str) -> Option<E> {
+    let mut fields = data.split(' ').map(|field| STANDARD.decode(field).ok());
+    let (Some(Some(discriminator)), Some(Some(data)), None) =
+        (fields.next(), fields.next(), fields.next())
+    else {
+        return None;
+    };
+    if discriminator != E::DISCRIMINATOR || data.len() != E::LEN {
+        return None;
+    }
+    Some(E::decode(&data))
+}
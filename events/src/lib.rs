@@ -0,0 +1,50 @@
+//! Events emitted by the example programs.
+//!
+//! Programs log events with `sol_log_data` as two fields: the 8-byte
+//! discriminator of the event and its [`Encoding`]. The discriminator is
+//! Anchor's one, so Anchor clients decode the events as well. Events are
+//! structs deriving `macros::Event` and logged with [`emit!`].
+//!
+//! Off-chain, the logs show them as `Program data: <field> <field>` lines
+//! with base64-encoded fields, which [`decode`] parses back into events
+//! with the `decode` feature.
+
+#![no_std]
+
+use common::encoding::Encoding;
+use pinocchio::log::sol_log_data;
+
+#[cfg(feature = "decode")]
+pub mod decode;
+
+/// Maximum length of the encoding of an event.
+pub const MAX_EVENT_LEN: usize = 256;
+
+/// Event logged by a program.
+pub trait Event: Encoding {
+    /// Anchor's discriminator of the event, the first 8 bytes of the
+    /// SHA-256 hash of `"event:<name>"`.
+    const DISCRIMINATOR: [u8; 8];
+
+    /// Logs the event.
+    ///
+    /// Panics if the encoding is longer than [`MAX_EVENT_LEN`].
+    fn emit(&self) {
+        let mut data = [0; MAX_EVENT_LEN];
+        let data = &mut data[..Self::LEN];
+        self.encode(data);
+        sol_log_data(&[&Self::DISCRIMINATOR, data]);
+    }
+}
+
+/// Logs an event.
+///
+/// ```ignore
+/// emit!(CounterIncremented { owner, count });
+/// ```
+#[macro_export]
+macro_rules! emit {
+    ($event:expr) => {
+        $crate::Event::emit(&$event)
+    };
+}
@@ -0,0 +1,151 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use events::{
+    decode::{decode_event, decode_events, program_data},
+    Event,
+};
+use macros::Event;
+use pinocchio::pubkey::Pubkey;
+
+#[derive(Clone, Copy, Debug, Event, PartialEq)]
+struct Transferred {
+    from: Pubkey,
+    amount: u64,
+}
+
+#[derive(Clone, Copy, Debug, Event, PartialEq)]
+struct Burned {
+    amount: u64,
+}
+
+const PROGRAM: Pubkey = [1; 32];
+const OTHER_PROGRAM: Pubkey = [2; 32];
+
+/// Returns the line logged by `sol_log_data` for `event`.
+fn log<E: Event>(event: &E) -> String {
+    let mut data = vec![0; E::LEN];
+    event.encode(&mut data);
+    format!(
+        "Program data: {} {}",
+        STANDARD.encode(E::DISCRIMINATOR),
+        STANDARD.encode(data)
+    )
+}
+
+fn invoke(program: &Pubkey, depth: usize) -> String {
+    format!(
+        "Program {} invoke [{depth}]",
+        bs58::encode(program).into_string()
+    )
+}
+
+fn success(program: &Pubkey) -> String {
+    format!("Program {} success", bs58::encode(program).into_string())
+}
+
+#[test]
+fn test_discriminator() {
+    // `sha256("event:Transferred")[..8]`, as computed by Anchor.
+    assert_eq!(
+        Transferred::DISCRIMINATOR,
+        [21, 132, 239, 64, 146, 239, 166, 68]
+    );
+    assert_ne!(Transferred::DISCRIMINATOR, Burned::DISCRIMINATOR);
+}
+
+#[test]
+fn test_round_trip() {
+    let transferred = Transferred {
+        from: [3; 32],
+        amount: 42,
+    };
+    let burned = Burned { amount: 7 };
+    let logs = [
+        invoke(&PROGRAM, 1),
+        "Program log: Transferring".into(),
+        log(&transferred),
+        log(&burned),
+        log(&Transferred {
+            from: [4; 32],
+            amount: u64::MAX,
+        }),
+        success(&PROGRAM),
+    ];
+
+    assert_eq!(
+        decode_events::<Transferred>(&PROGRAM, &logs),
+        [
+            transferred,
+            Transferred {
+                from: [4; 32],
+                amount: u64::MAX,
+            },
+        ]
+    );
+    assert_eq!(decode_events::<Burned>(&PROGRAM, &logs), [burned]);
+    assert!(decode_events::<Burned>(&OTHER_PROGRAM, &logs).is_empty());
+}
+
+#[test]
+fn test_skips_other_programs() {
+    let outer = Burned { amount: 1 };
+    let inner = Burned { amount: 2 };
+    let logs = [
+        invoke(&PROGRAM, 1),
+        invoke(&OTHER_PROGRAM, 2),
+        log(&inner),
+        format!(
+            "Program {} consumed 100 of 200000 compute units",
+            bs58::encode(OTHER_PROGRAM).into_string()
+        ),
+        success(&OTHER_PROGRAM),
+        log(&outer),
+        success(&PROGRAM),
+    ];
+
+    assert_eq!(decode_events::<Burned>(&PROGRAM, &logs), [outer]);
+    assert_eq!(decode_events::<Burned>(&OTHER_PROGRAM, &logs), [inner]);
+}
+
+#[test]
+fn test_skips_invalid_data() {
+    let logs = [
+        invoke(&PROGRAM, 1),
+        "Program data: not-base64 AAAA".into(),
+        format!("Program data: {}", STANDARD.encode(Burned::DISCRIMINATOR)),
+        format!(
+            "Program data: {} {}",
+            STANDARD.encode(Burned::DISCRIMINATOR),
+            STANDARD.encode([0; 4])
+        ),
+        success(&PROGRAM),
+    ];
+
+    assert!(decode_events::<Burned>(&PROGRAM, &logs).is_empty());
+}
+
+#[test]
+fn test_program_data_order() {
+    let first = Burned { amount: 1 };
+    let second = Transferred {
+        from: [3; 32],
+        amount: 2,
+    };
+    let third = Burned { amount: 3 };
+    let logs = [
+        invoke(&PROGRAM, 1),
+        log(&first),
+        invoke(&OTHER_PROGRAM, 2),
+        log(&Burned { amount: 4 }),
+        success(&OTHER_PROGRAM),
+        log(&second),
+        log(&third),
+        success(&PROGRAM),
+    ];
+
+    let data = program_data(&PROGRAM, &logs);
+    assert_eq!(data.len(), 3);
+    assert_eq!(decode_event::<Burned>(data[0]), Some(first));
+    assert_eq!(decode_event::<Burned>(data[1]), None);
+    assert_eq!(decode_event::<Transferred>(data[1]), Some(second));
+    assert_eq!(decode_event::<Burned>(data[2]), Some(third));
+}
@@ -0,0 +1,81 @@
+//! Commands of the counter program.
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use counter_client::{
+    find_counter_address,
+    rpc::{counters_by_owner, decode_counters},
+    try_from_account_data, ID,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+
+use crate::Context;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Creates the counter of the keypair.
+    Create,
+    /// Increments the counter of the keypair.
+    Increment,
+    /// Decrements the counter of the keypair.
+    Decrement,
+    /// Deletes the counter of the keypair.
+    Delete,
+    /// Shows a counter.
+    Show {
+        /// Owner of the counter. Defaults to the keypair.
+        #[arg(long)]
+        owner: Option<Pubkey>,
+        /// Prints the state as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Lists the counters of an owner.
+    List {
+        /// Owner of the counters. Defaults to the keypair.
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+}
+
+pub fn run(context: &Context, command: Command) -> Result<()> {
+    let owner = context.payer.pubkey();
+    let instruction = match command {
+        Command::Create => counter_client::create(&owner),
+        Command::Increment => counter_client::increment(&owner),
+        Command::Decrement => counter_client::decrement(&owner),
+        Command::Delete => counter_client::delete(&owner),
+        Command::Show { owner: other, json } => {
+            return show(context, &other.unwrap_or(owner), json)
+        }
+        Command::List { owner: other } => return list(context, &other.unwrap_or(owner)),
+    };
+    let signature = context.send(&[instruction])?;
+    println!("Signature: {signature}");
+    Ok(())
+}
+
+fn show(context: &Context, owner: &Pubkey, json: bool) -> Result<()> {
+    let (counter, _) = find_counter_address(owner);
+    let account = context.client.get_account(&counter)?;
+    let state = try_from_account_data(&account.data)
+        .map_err(|e| anyhow!("{counter} is not a counter: {e:?}"))?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&state)?);
+        return Ok(());
+    }
+
+    println!("Counter: {counter}");
+    println!("Owner: {}", Pubkey::new_from_array(state.owner));
+    println!("Count: {}", state.count);
+    Ok(())
+}
+
+fn list(context: &Context, owner: &Pubkey) -> Result<()> {
+    let accounts = context.program_accounts(&ID, counters_by_owner(owner))?;
+    for (counter, state) in decode_counters(accounts) {
+        println!("{counter}: {}", state.count);
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Inspection of any account of the example programs.
+//!
+//! The program owning the account tells how to decode its data, so the
+//! address is all it takes.
+
+use anyhow::{anyhow, Result};
+use program_config::{Config, MAX_FLAGS};
+use solana_sdk::{account::Account, native_token::lamports_to_sol, pubkey::Pubkey};
+
+use crate::Context;
+
+/// Fetches the account at `address` and prints it, with its state decoded
+/// when it's owned by one of the example programs.
+pub fn run(context: &Context, address: &Pubkey) -> Result<()> {
+    let account = context.client.get_account(address)?;
+    let rent_exempt_minimum = context
+        .client
+        .get_minimum_balance_for_rent_exemption(account.data.len())?;
+
+    println!("Address: {address}");
+    println!("Owner: {}", account.owner);
+    println!(
+        "Lamports: {} ({} SOL)",
+        account.lamports,
+        lamports_to_sol(account.lamports)
+    );
+    println!("Data: {} bytes", account.data.len());
+    println!(
+        "Rent: {} lamports for exemption, {}",
+        rent_exempt_minimum,
+        if account.lamports >= rent_exempt_minimum {
+            "exempt"
+        } else {
+            "not exempt"
+        }
+    );
+    println!();
+
+    decode(address, &account)
+}
+
+/// Prints the state of the account decoded by the program owning it.
+fn decode(address: &Pubkey, account: &Account) -> Result<()> {
+    if account.owner == counter_client::ID {
+        let state = counter_client::try_from_account_data(&account.data)
+            .map_err(|e| anyhow!("{address} is not a counter: {e:?}"))?;
+        println!("Counter");
+        println!("  Owner: {}", Pubkey::new_from_array(state.owner));
+        println!("  Count: {}", state.count);
+    } else if account.owner == escrow_client::ID {
+        let state = escrow_client::try_from_account_data(&account.data)
+            .map_err(|e| anyhow!("{address} is not an escrow: {e:?}"))?;
+        println!("Escrow");
+        println!("  Sender: {}", Pubkey::new_from_array(state.sender));
+        println!("  Receiver: {}", Pubkey::new_from_array(state.receiver));
+        println!("  Amount: {}", state.amount);
+        println!("  Penalty: {} bps", state.penalty_bps);
+        println!("  Accepted: {}", state.accepted != 0);
+    } else if account.owner == Pubkey::new_from_array(program_config::ID) {
+        let config = Config::from_bytes(&account.data)
+            .map_err(|e| anyhow!("{address} is not a program config: {e:?}"))?;
+        let pending_authority = if config.pending_authority == [0; 32] {
+            "none".to_owned()
+        } else {
+            Pubkey::new_from_array(config.pending_authority).to_string()
+        };
+        let flags: Vec<_> = (0..MAX_FLAGS)
+            .filter(|flag| config.is_enabled(*flag))
+            .map(|flag| flag.to_string())
+            .collect();
+        println!("Program config");
+        println!("  Authority: {}", Pubkey::new_from_array(config.authority));
+        println!("  Pending authority: {pending_authority}");
+        println!("  Enabled flags: {}", flags.join(", "));
+    } else {
+        println!("Not owned by an example program, the data isn't decoded.");
+    }
+    Ok(())
+}
@@ -0,0 +1,180 @@
+//! Commands of the escrow program.
+//!
+//! Token accounts are the associated token accounts of their owners, which
+//! get created when missing.
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use escrow_client::{
+    find_escrow_address,
+    rpc::{decode_escrows, escrows_by_sender},
+    try_from_account_data, AccountsBuilder, ID, TOKEN_PROGRAM_ID,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use spl_associated_token_account_client::instruction::create_associated_token_account_idempotent;
+
+use crate::Context;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Deposits tokens of the keypair into a new escrow for `receiver`.
+    Initialize {
+        /// Mint of the tokens.
+        #[arg(long)]
+        mint: Pubkey,
+        /// Receiver of the tokens.
+        #[arg(long)]
+        receiver: Pubkey,
+        /// Amount of the tokens, in base units.
+        #[arg(long)]
+        amount: u64,
+        /// Share of the amount paid to the receiver on a cancellation after
+        /// it accepted, in basis points.
+        #[arg(long)]
+        penalty_bps: Option<u16>,
+    },
+    /// Accepts the escrow of `sender` for the keypair.
+    Accept {
+        /// Mint of the tokens.
+        #[arg(long)]
+        mint: Pubkey,
+        /// Sender of the tokens.
+        #[arg(long)]
+        sender: Pubkey,
+    },
+    /// Releases the tokens escrowed by `sender` to the keypair.
+    Exchange {
+        /// Mint of the tokens.
+        #[arg(long)]
+        mint: Pubkey,
+        /// Sender of the tokens.
+        #[arg(long)]
+        sender: Pubkey,
+    },
+    /// Refunds the tokens escrowed by the keypair for `receiver`.
+    Cancel {
+        /// Mint of the tokens.
+        #[arg(long)]
+        mint: Pubkey,
+        /// Receiver of the tokens.
+        #[arg(long)]
+        receiver: Pubkey,
+    },
+    /// Shows an escrow.
+    Show {
+        /// Sender of the tokens.
+        #[arg(long)]
+        sender: Pubkey,
+        /// Receiver of the tokens.
+        #[arg(long)]
+        receiver: Pubkey,
+        /// Prints the state as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Lists the escrows of a sender.
+    List {
+        /// Sender of the tokens. Defaults to the keypair.
+        #[arg(long)]
+        sender: Option<Pubkey>,
+    },
+}
+
+pub fn run(context: &Context, command: Command) -> Result<()> {
+    let payer = context.payer.pubkey();
+    let instructions = match command {
+        Command::Initialize {
+            mint,
+            receiver,
+            amount,
+            penalty_bps,
+        } => {
+            let accounts = AccountsBuilder::new(&payer, &receiver, &mint).initialize();
+            let initialize = match penalty_bps {
+                Some(penalty_bps) => {
+                    escrow_client::initialize_with_penalty(&accounts, amount, penalty_bps)
+                }
+                None => escrow_client::initialize(&accounts, amount),
+            };
+            vec![
+                create_associated_token_account_idempotent(
+                    &payer,
+                    &accounts.escrow,
+                    &mint,
+                    &TOKEN_PROGRAM_ID,
+                ),
+                initialize,
+            ]
+        }
+        Command::Accept { mint, sender } => {
+            vec![escrow_client::accept(
+                &AccountsBuilder::new(&sender, &payer, &mint).accept(),
+            )]
+        }
+        Command::Exchange { mint, sender } => {
+            let accounts = AccountsBuilder::new(&sender, &payer, &mint).exchange();
+            vec![
+                create_associated_token_account_idempotent(
+                    &payer,
+                    &payer,
+                    &mint,
+                    &TOKEN_PROGRAM_ID,
+                ),
+                escrow_client::exchange(&accounts),
+            ]
+        }
+        Command::Cancel { mint, receiver } => {
+            let accounts = AccountsBuilder::new(&payer, &receiver, &mint).cancel();
+            vec![
+                create_associated_token_account_idempotent(
+                    &payer,
+                    &payer,
+                    &mint,
+                    &TOKEN_PROGRAM_ID,
+                ),
+                escrow_client::cancel(&accounts),
+            ]
+        }
+        Command::Show {
+            sender,
+            receiver,
+            json,
+        } => return show(context, &sender, &receiver, json),
+        Command::List { sender } => return list(context, &sender.unwrap_or(payer)),
+    };
+    let signature = context.send(&instructions)?;
+    println!("Signature: {signature}");
+    Ok(())
+}
+
+fn show(context: &Context, sender: &Pubkey, receiver: &Pubkey, json: bool) -> Result<()> {
+    let (escrow, _) = find_escrow_address(sender, receiver);
+    let account = context.client.get_account(&escrow)?;
+    let state = try_from_account_data(&account.data)
+        .map_err(|e| anyhow!("{escrow} is not an escrow: {e:?}"))?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&state)?);
+        return Ok(());
+    }
+
+    println!("Escrow: {escrow}");
+    println!("Sender: {}", Pubkey::new_from_array(state.sender));
+    println!("Receiver: {}", Pubkey::new_from_array(state.receiver));
+    println!("Amount: {}", state.amount);
+    println!("Penalty: {} bps", state.penalty_bps);
+    println!("Accepted: {}", state.accepted != 0);
+    Ok(())
+}
+
+fn list(context: &Context, sender: &Pubkey) -> Result<()> {
+    let accounts = context.program_accounts(&ID, escrows_by_sender(sender))?;
+    for (escrow, state) in decode_escrows(accounts) {
+        println!(
+            "{escrow}: {} tokens for {}",
+            state.amount,
+            Pubkey::new_from_array(state.receiver)
+        );
+    }
+    Ok(())
+}
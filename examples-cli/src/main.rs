@@ -0,0 +1,150 @@
+//! Command-line interface for the example programs deployed on a cluster.
+
+mod counter;
+mod decode;
+mod escrow;
+
+use std::{env, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use client_utils::TransactionBuilder;
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::RpcFilterType,
+};
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+};
+
+#[derive(Parser)]
+#[command(about = "Interacts with the example programs")]
+struct Cli {
+    /// URL of the cluster, or one of the `localnet` and `devnet` monikers.
+    #[arg(short, long, global = true, default_value = "localnet")]
+    url: String,
+    /// Keypair paying for and signing the transactions. Defaults to the
+    /// keypair of the Solana CLI.
+    #[arg(short, long, global = true)]
+    keypair: Option<PathBuf>,
+    /// Priority fee of the transactions, in micro-lamports per compute
+    /// unit.
+    #[arg(long, global = true)]
+    priority_fee: Option<u64>,
+    /// Maximum of compute units consumed by the transactions.
+    #[arg(long, global = true)]
+    compute_unit_limit: Option<u32>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Counter program.
+    #[command(subcommand)]
+    Counter(counter::Command),
+    /// Escrow program.
+    #[command(subcommand)]
+    Escrow(escrow::Command),
+    /// Fetches any account and decodes it with the example program owning
+    /// it.
+    Decode {
+        /// Address of the account.
+        address: Pubkey,
+    },
+}
+
+/// Cluster connection, the keypair sending the transactions and their
+/// compute budget.
+pub struct Context {
+    pub client: RpcClient,
+    pub payer: Keypair,
+    pub priority_fee: Option<u64>,
+    pub compute_unit_limit: Option<u32>,
+}
+
+impl Context {
+    /// Sends `instructions` in one v0 transaction signed by the payer.
+    pub fn send(&self, instructions: &[Instruction]) -> Result<Signature> {
+        let mut builder =
+            TransactionBuilder::new(&self.payer.pubkey()).instructions(instructions.to_vec());
+        if let Some(priority_fee) = self.priority_fee {
+            builder = builder.priority_fee(priority_fee);
+        }
+        if let Some(compute_unit_limit) = self.compute_unit_limit {
+            builder = builder.compute_unit_limit(compute_unit_limit);
+        }
+        let transaction =
+            builder.transaction(self.client.get_latest_blockhash()?, &[&self.payer])?;
+        let signature = self.client.send_and_confirm_transaction(&transaction)?;
+        Ok(signature)
+    }
+
+    /// Fetches the accounts of `program` matching `filters`.
+    pub fn program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let accounts = self
+            .client
+            .get_program_accounts_with_config(program, config)?;
+        Ok(accounts)
+    }
+}
+
+/// Resolves cluster monikers to their RPC URLs.
+fn cluster_url(url: &str) -> &str {
+    match url {
+        "localnet" => "http://127.0.0.1:8899",
+        "devnet" => "https://api.devnet.solana.com",
+        url => url,
+    }
+}
+
+/// Path of the keypair used by the Solana CLI.
+fn default_keypair_path() -> Result<PathBuf> {
+    let home = env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
+    Ok(PathBuf::from(home).join(".config/solana/id.json"))
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let keypair_path = match cli.keypair {
+        Some(keypair_path) => keypair_path,
+        None => default_keypair_path()?,
+    };
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|e| anyhow!("failed to read {}: {e}", keypair_path.display()))?;
+    let client = RpcClient::new_with_commitment(
+        cluster_url(&cli.url).to_owned(),
+        CommitmentConfig::confirmed(),
+    );
+    let context = Context {
+        client,
+        payer,
+        priority_fee: cli.priority_fee,
+        compute_unit_limit: cli.compute_unit_limit,
+    };
+
+    match cli.command {
+        Command::Counter(command) => counter::run(&context, command),
+        Command::Escrow(command) => escrow::run(&context, command),
+        Command::Decode { address } => decode::run(&context, &address),
+    }
+}
@@ -0,0 +1,295 @@
+#![no_std]
+
+use common::{assert_layout, debug, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::{Namespace, SharedError};
+use fixed_point::Q64x64;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("HBBMxp12bBxcrxK4mRWFgX7pbQ4YfXubZaJGbEpCh8LP");
+
+pub const RATE_SEED: &str = "rate";
+
+/// Errors returned by the fixed-point demo program.
+#[repr(u32)]
+pub enum FixedPointDemoError {
+    /// The rate is zero or its denominator is zero.
+    InvalidRate,
+}
+
+impl From<FixedPointDemoError> for ProgramError {
+    fn from(e: FixedPointDemoError) -> Self {
+        Namespace::FixedPointDemo.error(e as u32)
+    }
+}
+
+/// On-chain representation of an exchange rate between two units, e.g.
+/// the price of a token in lamports.
+#[repr(C)]
+pub struct Rate {
+    pub authority: Pubkey,
+    /// Q64.64 rate, stored as little-endian bytes.
+    pub rate: [u8; 16],
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Rate, size = 56, align = 1, { authority: 0, rate: 32, bump: 48 });
+
+impl Rate {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    pub fn rate(&self) -> Q64x64 {
+        Q64x64::from_le_bytes(self.rate)
+    }
+
+    pub fn set_rate(&mut self, rate: Q64x64) {
+        self.rate = rate.to_le_bytes();
+    }
+}
+
+/// Fixed-point demo program instruction discriminators.
+#[repr(u8)]
+pub enum FixedPointDemoInstruction {
+    /// Creates a rate.
+    Initialize,
+    /// Changes a rate.
+    SetRate,
+    /// Converts an amount with a rate, returning `amount * rate`.
+    Convert,
+    /// Converts an amount back with a rate, returning `amount / rate`.
+    ConvertBack,
+}
+
+impl TryFrom<&u8> for FixedPointDemoInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::SetRate),
+            2 => Ok(Self::Convert),
+            3 => Ok(Self::ConvertBack),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Rate given as a ratio of integers, which is converted to Q64.64
+/// on-chain.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct SetRateInstructionData {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl SetRateInstructionData {
+    pub fn new(numerator: u64, denominator: u64) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    fn rate(&self) -> Result<Q64x64, ProgramError> {
+        match Q64x64::from_ratio(self.numerator, self.denominator) {
+            Some(rate) if rate != Q64x64::ZERO => Ok(rate),
+            _ => Err(FixedPointDemoError::InvalidRate.into()),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct InitializeInstructionData {
+    pub rate: SetRateInstructionData,
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(rate: SetRateInstructionData, bump: u8) -> Self {
+        Self { rate, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ConvertInstructionData {
+    pub amount: u64,
+}
+
+impl ConvertInstructionData {
+    pub fn new(amount: u64) -> Self {
+        Self { amount }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = FixedPointDemoInstruction::try_from(instruction)?;
+
+    match instruction {
+        FixedPointDemoInstruction::Initialize => process_initialize(accounts, instruction_data),
+        FixedPointDemoInstruction::SetRate => process_set_rate(accounts, instruction_data),
+        FixedPointDemoInstruction::Convert => {
+            process_convert(accounts, instruction_data, Q64x64::checked_mul_int)
+        }
+        FixedPointDemoInstruction::ConvertBack => {
+            process_convert(accounts, instruction_data, Q64x64::checked_div_int)
+        }
+    }
+}
+
+/// Creates a rate of `authority`.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, rate, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    let value = instruction_data.rate.rate()?;
+
+    // Check the seeds of `rate`.
+    let rate_pda = create_program_address(
+        &[
+            RATE_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(rate.key(), &rate_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the rate PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(RATE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: rate,
+        lamports: Rent::get()?.minimum_balance(Rate::LEN),
+        space: Rate::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the rate PDA.
+    let mut data = rate.try_borrow_mut_data()?;
+    let data: &mut Rate = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the rate.
+    data.authority = *authority.key();
+    data.set_rate(value);
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Initialized rate {}/{}",
+        instruction_data.rate.numerator, instruction_data.rate.denominator
+    );
+
+    Ok(())
+}
+
+/// Changes the rate.
+pub fn process_set_rate(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, rate] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+    // Check if the rate PDA is owned by the program.
+    require_owned_by(rate, &ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = SetRateInstructionData::from_bytes(instruction_data)?;
+
+    let value = instruction_data.rate()?;
+
+    // Deserialize the rate PDA.
+    let mut data = rate.try_borrow_mut_data()?;
+    let data: &mut Rate = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    if !keys_eq(&data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    data.set_rate(value);
+
+    info!(
+        "Set rate to {}/{}",
+        instruction_data.numerator, instruction_data.denominator
+    );
+
+    Ok(())
+}
+
+/// Converts an amount with the rate using `convert`, setting the result as
+/// return data. The result is rounded down.
+pub fn process_convert(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+    convert: fn(Q64x64, u64) -> Option<u64>,
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [rate] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Check if the rate PDA is owned by the program.
+    require_owned_by(rate, &ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = ConvertInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the rate PDA.
+    let data = rate.try_borrow_data()?;
+    let data: &Rate = unsafe { &*data.as_ptr().cast() };
+
+    let result = convert(data.rate(), instruction_data.amount).ok_or(SharedError::Overflow)?;
+
+    set_return_data(&result.to_le_bytes());
+
+    debug!("Converted {} to {}", instruction_data.amount, result);
+
+    Ok(())
+}
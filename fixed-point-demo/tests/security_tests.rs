@@ -0,0 +1,168 @@
+//! Attacks on the fixed-point demo program, each rejected with its own
+//! error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use fixed_point::Q64x64;
+use fixed_point_demo::{
+    ConvertInstructionData, FixedPointDemoInstruction, InitializeInstructionData, Rate,
+    SetRateInstructionData, RATE_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(fixed_point_demo::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: FixedPointDemoInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<FixedPointDemoInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the rate PDA of `authority` and its bump.
+fn rate_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RATE_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Returns a rate of `authority`.
+fn rate_account(mollusk: &Mollusk, authority: &Pubkey, rate: Q64x64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Rate::LEN),
+        Rate::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[32..48].copy_from_slice(&rate.to_le_bytes());
+    account
+}
+
+/// Accounts of an authority with a rate, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    rate: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/fixed_point_demo");
+
+        let authority = Pubkey::new_unique();
+        let (rate, _) = rate_pda(&authority);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                rate,
+                rate_account(&mollusk, &authority, Q64x64::from_ratio(3, 2).unwrap()),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            rate,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.authority, false),
+        AccountMeta::new(setup.rate, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            FixedPointDemoInstruction::SetRate,
+            &SetRateInstructionData::new(2, 1),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the rate of the authority before the authority
+/// does, with their own bump and a rate of their choice.
+#[test]
+fn test_initialize_rate_of_other_authority() {
+    let setup = Setup::new();
+    let rate = setup.rate;
+    let setup = setup.with(rate, Account::default());
+    let (_, bump) = rate_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(rate, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            FixedPointDemoInstruction::Initialize,
+            &InitializeInstructionData::new(SetRateInstructionData::new(1_000, 1), bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// A rate of the attacker owned by another program, used by a program
+/// relying on the conversion.
+#[test]
+fn test_convert_with_spoofed_rate() {
+    let setup = Setup::new();
+    let mut rate_account = rate_account(
+        &setup.mollusk,
+        &setup.attacker,
+        Q64x64::from_ratio(1_000, 1).unwrap(),
+    );
+    rate_account.owner = Pubkey::new_unique();
+    let rate = Pubkey::new_unique();
+    let setup = setup.with(rate, rate_account);
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            FixedPointDemoInstruction::Convert,
+            &ConvertInstructionData::new(LAMPORTS_PER_SOL),
+        ),
+        vec![AccountMeta::new_readonly(rate, false)],
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
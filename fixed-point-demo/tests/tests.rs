@@ -0,0 +1,223 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::{Namespace, SharedError};
+use fixed_point::Q64x64;
+use fixed_point_demo::{
+    ConvertInstructionData, FixedPointDemoError, FixedPointDemoInstruction,
+    InitializeInstructionData, Rate, SetRateInstructionData, RATE_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(fixed_point_demo::ID);
+
+/// Offset of the rate field.
+const RATE_OFFSET: usize = 32;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: FixedPointDemoInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<FixedPointDemoInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn instruction_initialize(
+    authority: &Pubkey,
+    rate: &Pubkey,
+    numerator: u64,
+    denominator: u64,
+    bump: u8,
+) -> Instruction {
+    let data =
+        InitializeInstructionData::new(SetRateInstructionData::new(numerator, denominator), bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*rate, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(FixedPointDemoInstruction::Initialize, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_set_rate(
+    authority: &Pubkey,
+    rate: &Pubkey,
+    numerator: u64,
+    denominator: u64,
+) -> Instruction {
+    let data = SetRateInstructionData::new(numerator, denominator);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*rate, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(FixedPointDemoInstruction::SetRate, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_convert(
+    discriminator: FixedPointDemoInstruction,
+    rate: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let data = ConvertInstructionData::new(amount);
+    let ix_accounts = vec![AccountMeta::new_readonly(*rate, false)];
+    Instruction::new_with_bytes(ID, &instruction_data(discriminator, &data), ix_accounts)
+}
+
+/// Creates an initialized rate account.
+fn rate_account(mollusk: &Mollusk, authority: &Pubkey, rate: Q64x64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Rate::LEN),
+        Rate::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[RATE_OFFSET..RATE_OFFSET + 16].copy_from_slice(&rate.to_le_bytes());
+    account
+}
+
+#[test]
+fn test_fixed_point_demo_initialize_and_set_rate() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/fixed_point_demo");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let authority = Pubkey::new_unique();
+    let (rate, rate_bump) =
+        Pubkey::find_program_address(&[RATE_SEED.as_bytes(), authority.as_ref()], &ID);
+
+    mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize(&authority, &rate, 3, 2, rate_bump),
+                &[
+                    Check::success(),
+                    Check::account(&rate)
+                        .owner(&ID)
+                        .space(Rate::LEN)
+                        .data_slice(0, authority.as_ref())
+                        .data_slice(
+                            RATE_OFFSET,
+                            &Q64x64::from_ratio(3, 2).unwrap().to_le_bytes(),
+                        )
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_set_rate(&authority, &rate, 1, 3),
+                &[
+                    Check::success(),
+                    Check::account(&rate)
+                        .data_slice(
+                            RATE_OFFSET,
+                            &Q64x64::from_ratio(1, 3).unwrap().to_le_bytes(),
+                        )
+                        .build(),
+                ],
+            ),
+        ],
+        &[
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (rate, Account::default()),
+            (system_program, system_account),
+        ],
+    );
+}
+
+#[test]
+fn test_fixed_point_demo_invalid_rate() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/fixed_point_demo");
+
+    let authority = Pubkey::new_unique();
+    let rate = Pubkey::new_unique();
+
+    // A zero denominator and a rate rounded down to zero are both rejected.
+    for (numerator, denominator) in [(1, 0), (0, 1)] {
+        mollusk.process_and_validate_instruction(
+            &instruction_set_rate(&authority, &rate, numerator, denominator),
+            &[
+                (authority, Account::default()),
+                (rate, rate_account(&mollusk, &authority, Q64x64::ONE)),
+            ],
+            &[Check::err(ProgramError::Custom(
+                Namespace::FixedPointDemo.code(FixedPointDemoError::InvalidRate as u32),
+            ))],
+        );
+    }
+}
+
+#[test]
+fn test_fixed_point_demo_set_rate_wrong_authority() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/fixed_point_demo");
+
+    let authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let rate = Pubkey::new_unique();
+
+    mollusk.process_and_validate_instruction(
+        &instruction_set_rate(&attacker, &rate, 1000, 1),
+        &[
+            (attacker, Account::default()),
+            (rate, rate_account(&mollusk, &authority, Q64x64::ONE)),
+        ],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+#[test]
+fn test_fixed_point_demo_convert() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/fixed_point_demo");
+
+    let authority = Pubkey::new_unique();
+    let rate = Pubkey::new_unique();
+    // 1 token = 1.5 lamports.
+    let rate_account = rate_account(&mollusk, &authority, Q64x64::from_ratio(3, 2).unwrap());
+
+    mollusk.process_and_validate_instruction(
+        &instruction_convert(FixedPointDemoInstruction::Convert, &rate, 1_001),
+        &[(rate, rate_account.clone())],
+        &[
+            Check::success(),
+            // 1501.5, rounded down.
+            Check::return_data(&1_501u64.to_le_bytes()),
+        ],
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction_convert(FixedPointDemoInstruction::ConvertBack, &rate, 1_501),
+        &[(rate, rate_account.clone())],
+        &[
+            Check::success(),
+            // 1000.67, rounded down.
+            Check::return_data(&1_000u64.to_le_bytes()),
+        ],
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction_convert(FixedPointDemoInstruction::Convert, &rate, u64::MAX),
+        &[(rate, rate_account)],
+        &[Check::err(ProgramError::Custom(
+            Namespace::Shared.code(SharedError::Overflow as u32),
+        ))],
+    );
+}
@@ -0,0 +1,229 @@
+//! Unsigned Q64.64 fixed-point numbers for on-chain math.
+//!
+//! [`Q64x64`] stores a number as a 128-bit integer scaled by `2^64`, so it
+//! holds 64 integer bits and 64 fractional bits. All arithmetic is checked
+//! and rounds towards zero. Multiplication and division use a 256-bit
+//! intermediate, so they are exact up to the final rounding.
+//!
+//! `u128` is aligned differently on the host and in SBF, so numbers stored
+//! in accounts or instruction data should go through
+//! [`Q64x64::to_le_bytes`] and [`Q64x64::from_le_bytes`].
+
+#![no_std]
+
+use core::fmt;
+
+/// Number of fractional bits.
+pub const FRAC_BITS: u32 = 64;
+
+/// Unsigned Q64.64 fixed-point number.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Q64x64(u128);
+
+impl Q64x64 {
+    pub const ZERO: Self = Self(0);
+    pub const ONE: Self = Self(1 << FRAC_BITS);
+    pub const MAX: Self = Self(u128::MAX);
+    /// Smallest positive number, `2^-64`.
+    pub const EPSILON: Self = Self(1);
+
+    /// Creates a number from its raw representation, scaled by `2^64`.
+    pub const fn from_bits(bits: u128) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw representation, scaled by `2^64`.
+    pub const fn to_bits(self) -> u128 {
+        self.0
+    }
+
+    /// Creates a number from an integer. Every `u64` is representable.
+    pub const fn from_int(value: u64) -> Self {
+        Self((value as u128) << FRAC_BITS)
+    }
+
+    /// Creates the number `numerator / denominator`, rounded towards zero.
+    ///
+    /// Returns `None` if the denominator is zero.
+    pub const fn from_ratio(numerator: u64, denominator: u64) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        Some(Self(
+            ((numerator as u128) << FRAC_BITS) / denominator as u128,
+        ))
+    }
+
+    pub const fn from_le_bytes(bytes: [u8; 16]) -> Self {
+        Self(u128::from_le_bytes(bytes))
+    }
+
+    pub const fn to_le_bytes(self) -> [u8; 16] {
+        self.0.to_le_bytes()
+    }
+
+    /// Returns the integer part.
+    pub const fn floor(self) -> u64 {
+        (self.0 >> FRAC_BITS) as u64
+    }
+
+    /// Returns the smallest integer greater than or equal to the number.
+    ///
+    /// Returns `None` if it doesn't fit into `u64`.
+    pub const fn ceil(self) -> Option<u64> {
+        let int = self.floor();
+        if self.frac().0 == 0 {
+            Some(int)
+        } else {
+            int.checked_add(1)
+        }
+    }
+
+    /// Returns the fractional part.
+    pub const fn frac(self) -> Self {
+        Self(self.0 & (u64::MAX as u128))
+    }
+
+    pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_add(rhs.0) {
+            Some(bits) => Some(Self(bits)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.0.checked_sub(rhs.0) {
+            Some(bits) => Some(Self(bits)),
+            None => None,
+        }
+    }
+
+    pub const fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    /// Multiplies two numbers, rounding towards zero.
+    pub const fn checked_mul(self, rhs: Self) -> Option<Self> {
+        // The product is scaled by 2^128, shift it back by 64 bits.
+        let (high, low) = widening_mul(self.0, rhs.0);
+        if high >> FRAC_BITS != 0 {
+            return None;
+        }
+        Some(Self((high << FRAC_BITS) | (low >> FRAC_BITS)))
+    }
+
+    /// Divides two numbers, rounding towards zero.
+    pub const fn checked_div(self, rhs: Self) -> Option<Self> {
+        // Scale the dividend by another 2^64, so the quotient stays scaled.
+        let high = self.0 >> (128 - FRAC_BITS);
+        let low = self.0 << FRAC_BITS;
+        match div_wide(high, low, rhs.0) {
+            Some(quotient) => Some(Self(quotient)),
+            None => None,
+        }
+    }
+
+    /// Multiplies an integer amount by the number, rounding towards zero.
+    ///
+    /// Returns `None` if the result doesn't fit into `u64`.
+    pub const fn checked_mul_int(self, amount: u64) -> Option<u64> {
+        // The product of a 64-bit and a 128-bit integer fits into 192 bits,
+        // so shifting it back by 64 bits fits into 128 bits.
+        let (high, low) = widening_mul(self.0, amount as u128);
+        let result = (high << FRAC_BITS) | (low >> FRAC_BITS);
+        if result > u64::MAX as u128 {
+            return None;
+        }
+        Some(result as u64)
+    }
+
+    /// Divides an integer amount by the number, rounding towards zero.
+    ///
+    /// Returns `None` if the number is zero or the result doesn't fit into
+    /// `u64`. The integer part of a Q64.64 number always fits into `u64`, so
+    /// the overflow check of [`Self::checked_div`] covers the latter.
+    pub const fn checked_div_int(self, amount: u64) -> Option<u64> {
+        match Self::from_int(amount).checked_div(self) {
+            Some(quotient) => Some(quotient.floor()),
+            None => None,
+        }
+    }
+}
+
+impl fmt::Debug for Q64x64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Formats the number in decimal with up to 20 fractional digits, which is
+/// enough to distinguish every multiple of `2^-64`.
+impl fmt::Display for Q64x64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.floor())?;
+
+        let mut frac = self.frac().0;
+        if frac == 0 {
+            return Ok(());
+        }
+        f.write_str(".")?;
+        let mut digits = 0;
+        while frac != 0 && digits < 20 {
+            // `frac` is below 2^64, so multiplying by 10 can't overflow.
+            frac *= 10;
+            write!(f, "{}", frac >> FRAC_BITS)?;
+            frac &= u64::MAX as u128;
+            digits += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Multiplies two 128-bit integers into a 256-bit product, returned as
+/// `(high, low)` halves.
+const fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+    let lo_lo = a_lo * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_lo = a_hi * b_lo;
+    let hi_hi = a_hi * b_hi;
+
+    let mid = (lo_lo >> 64) + (lo_hi & MASK) + (hi_lo & MASK);
+
+    let low = (lo_lo & MASK) | (mid << 64);
+    let high = hi_hi + (lo_hi >> 64) + (hi_lo >> 64) + (mid >> 64);
+    (high, low)
+}
+
+/// Divides the 256-bit integer `(high, low)` by `divisor`, rounding towards
+/// zero.
+///
+/// Returns `None` if the divisor is zero or the quotient doesn't fit into
+/// 128 bits.
+const fn div_wide(high: u128, low: u128, divisor: u128) -> Option<u128> {
+    if divisor == 0 || high >= divisor {
+        return None;
+    }
+    if high == 0 {
+        return Some(low / divisor);
+    }
+
+    let mut quotient = 0;
+    let mut remainder = high;
+    let mut i = 128;
+    while i > 0 {
+        i -= 1;
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
@@ -0,0 +1,231 @@
+use fixed_point::{Q64x64, FRAC_BITS};
+use num_bigint::BigUint;
+use proptest::prelude::*;
+
+/// `2^64`, the scale of the raw representation.
+const SCALE: u128 = 1 << FRAC_BITS;
+
+fn big(value: u128) -> BigUint {
+    BigUint::from(value)
+}
+
+/// Converts a reference result back to raw bits, if it fits.
+fn bits(value: BigUint) -> Option<u128> {
+    u128::try_from(value).ok()
+}
+
+#[test]
+fn test_fixed_point_constants() {
+    assert_eq!(Q64x64::ZERO.to_bits(), 0);
+    assert_eq!(Q64x64::ONE.to_bits(), SCALE);
+    assert_eq!(Q64x64::from_int(1), Q64x64::ONE);
+    assert_eq!(Q64x64::MAX.floor(), u64::MAX);
+    assert_eq!(Q64x64::EPSILON.floor(), 0);
+    assert_eq!(Q64x64::from_int(u64::MAX).floor(), u64::MAX);
+}
+
+#[test]
+fn test_fixed_point_from_ratio() {
+    let half = Q64x64::from_ratio(1, 2).unwrap();
+    assert_eq!(half.to_bits(), SCALE / 2);
+    assert_eq!(Q64x64::from_ratio(6, 3), Some(Q64x64::from_int(2)));
+    // 1/3 isn't representable, it's rounded down.
+    let third = Q64x64::from_ratio(1, 3).unwrap();
+    assert_eq!(third.to_bits(), SCALE / 3);
+    assert_eq!(
+        Q64x64::from_ratio(u64::MAX, 1),
+        Some(Q64x64::from_int(u64::MAX))
+    );
+    assert_eq!(Q64x64::from_ratio(1, 0), None);
+}
+
+#[test]
+fn test_fixed_point_floor_ceil_frac() {
+    let x = Q64x64::from_ratio(7, 2).unwrap();
+    assert_eq!(x.floor(), 3);
+    assert_eq!(x.ceil(), Some(4));
+    assert_eq!(x.frac(), Q64x64::from_ratio(1, 2).unwrap());
+
+    let three = Q64x64::from_int(3);
+    assert_eq!(three.ceil(), Some(3));
+    assert_eq!(three.frac(), Q64x64::ZERO);
+
+    assert_eq!(Q64x64::MAX.ceil(), None);
+    assert_eq!(Q64x64::EPSILON.ceil(), Some(1));
+}
+
+#[test]
+fn test_fixed_point_add_sub() {
+    let a = Q64x64::from_ratio(3, 2).unwrap();
+    let b = Q64x64::from_ratio(1, 4).unwrap();
+    assert_eq!(a.checked_add(b), Q64x64::from_ratio(7, 4));
+    assert_eq!(a.checked_sub(b), Q64x64::from_ratio(5, 4));
+    assert_eq!(b.checked_sub(a), None);
+    assert_eq!(b.saturating_sub(a), Q64x64::ZERO);
+    assert_eq!(Q64x64::MAX.checked_add(Q64x64::EPSILON), None);
+}
+
+#[test]
+fn test_fixed_point_mul() {
+    let a = Q64x64::from_ratio(3, 2).unwrap();
+    let b = Q64x64::from_int(4);
+    assert_eq!(a.checked_mul(b), Some(Q64x64::from_int(6)));
+    assert_eq!(a.checked_mul(Q64x64::ONE), Some(a));
+    assert_eq!(a.checked_mul(Q64x64::ZERO), Some(Q64x64::ZERO));
+    // 2^-64 * 2^-64 rounds down to zero.
+    assert_eq!(
+        Q64x64::EPSILON.checked_mul(Q64x64::EPSILON),
+        Some(Q64x64::ZERO)
+    );
+    // 2^32 * 2^32 = 2^64 doesn't fit.
+    let big = Q64x64::from_int(1 << 32);
+    assert_eq!(big.checked_mul(big), None);
+    assert_eq!(
+        big.checked_mul(Q64x64::from_int((1 << 32) - 1)),
+        Some(Q64x64::from_int(u64::MAX - (1 << 32) + 1))
+    );
+}
+
+#[test]
+fn test_fixed_point_div() {
+    let a = Q64x64::from_int(3);
+    let b = Q64x64::from_int(4);
+    assert_eq!(a.checked_div(b), Q64x64::from_ratio(3, 4));
+    assert_eq!(a.checked_div(Q64x64::ONE), Some(a));
+    assert_eq!(a.checked_div(Q64x64::ZERO), None);
+    // Dividing by a number below one grows the result, until it overflows.
+    assert_eq!(
+        Q64x64::from_int(u64::MAX).checked_div(Q64x64::from_ratio(1, 2).unwrap()),
+        None
+    );
+    assert_eq!(
+        Q64x64::ONE.checked_div(Q64x64::EPSILON),
+        None,
+        "2^64 doesn't fit"
+    );
+    assert_eq!(
+        Q64x64::from_ratio(1, 2)
+            .unwrap()
+            .checked_div(Q64x64::EPSILON),
+        Some(Q64x64::from_int(1 << 63))
+    );
+}
+
+#[test]
+fn test_fixed_point_int_ops() {
+    // A 2.5% fee.
+    let fee = Q64x64::from_ratio(25, 1000).unwrap();
+    assert_eq!(fee.checked_mul_int(1_000_000), Some(24_999));
+    assert_eq!(Q64x64::ONE.checked_mul_int(u64::MAX), Some(u64::MAX));
+    assert_eq!(Q64x64::from_int(2).checked_mul_int(u64::MAX), None);
+
+    let price = Q64x64::from_ratio(3, 2).unwrap();
+    assert_eq!(price.checked_div_int(300), Some(200));
+    assert_eq!(Q64x64::ZERO.checked_div_int(1), None);
+    assert_eq!(
+        Q64x64::from_ratio(1, 2).unwrap().checked_div_int(u64::MAX),
+        None
+    );
+}
+
+#[test]
+fn test_fixed_point_bytes() {
+    let x = Q64x64::from_ratio(22, 7).unwrap();
+    assert_eq!(Q64x64::from_le_bytes(x.to_le_bytes()), x);
+    assert_eq!(Q64x64::ONE.to_le_bytes()[8], 1);
+}
+
+#[test]
+fn test_fixed_point_display() {
+    assert_eq!(Q64x64::from_int(42).to_string(), "42");
+    assert_eq!(Q64x64::from_ratio(5, 2).unwrap().to_string(), "2.5");
+    assert_eq!(Q64x64::from_ratio(1, 8).unwrap().to_string(), "0.125");
+    assert_eq!(Q64x64::EPSILON.to_string(), "0.00000000000000000005");
+    assert_eq!(format!("{:?}", Q64x64::ONE), "1");
+}
+
+/// Checks every ratio of small integers against the exact result.
+#[test]
+fn test_fixed_point_exhaustive_small() {
+    for n in 0..64u64 {
+        for d in 1..64u64 {
+            let x = Q64x64::from_ratio(n, d).unwrap();
+            assert_eq!(x.to_bits(), ((n as u128) << 64) / d as u128);
+            assert_eq!(x.floor(), n / d);
+            assert_eq!(x.ceil(), Some(n.div_ceil(d)));
+            let inexact = !((n as u128) << 64).is_multiple_of(d as u128);
+            assert_eq!(x.checked_mul_int(d), Some(n - inexact as u64));
+
+            let y = Q64x64::from_int(d);
+            if n > 0 {
+                assert_eq!(y.checked_mul(x).map(|p| p.ceil()), Some(Some(n)));
+            }
+            assert_eq!(Q64x64::from_int(n).checked_div(y), Q64x64::from_ratio(n, d));
+        }
+    }
+}
+
+fn q64x64() -> impl Strategy<Value = Q64x64> {
+    any::<u128>().prop_map(Q64x64::from_bits)
+}
+
+proptest! {
+    #[test]
+    fn test_fixed_point_add_sub_matches_reference(a in q64x64(), b in q64x64()) {
+        prop_assert_eq!(
+            a.checked_add(b).map(Q64x64::to_bits),
+            bits(big(a.to_bits()) + big(b.to_bits()))
+        );
+        if a >= b {
+            prop_assert_eq!(a.checked_sub(b).map(Q64x64::to_bits), Some(a.to_bits() - b.to_bits()));
+        } else {
+            prop_assert_eq!(a.checked_sub(b), None);
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_mul_matches_reference(a in q64x64(), b in q64x64()) {
+        let expected = (big(a.to_bits()) * big(b.to_bits())) >> FRAC_BITS;
+        prop_assert_eq!(a.checked_mul(b).map(Q64x64::to_bits), bits(expected));
+    }
+
+    #[test]
+    fn test_fixed_point_div_matches_reference(a in q64x64(), b in q64x64()) {
+        if b == Q64x64::ZERO {
+            prop_assert_eq!(a.checked_div(b), None);
+        } else {
+            let expected = (big(a.to_bits()) << FRAC_BITS) / big(b.to_bits());
+            prop_assert_eq!(a.checked_div(b).map(Q64x64::to_bits), bits(expected));
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_int_ops_match_reference(x in q64x64(), amount in any::<u64>()) {
+        let expected = (big(x.to_bits()) * BigUint::from(amount)) >> FRAC_BITS;
+        prop_assert_eq!(x.checked_mul_int(amount), u64::try_from(expected).ok());
+
+        if x == Q64x64::ZERO {
+            prop_assert_eq!(x.checked_div_int(amount), None);
+        } else {
+            let expected = (BigUint::from(amount) << FRAC_BITS) / big(x.to_bits());
+            prop_assert_eq!(x.checked_div_int(amount), u64::try_from(expected).ok());
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_mul_div_roundtrip(a in q64x64(), b in 1..=u64::MAX) {
+        // Multiplying by an integer is exact, so dividing it back restores
+        // the number.
+        let b = Q64x64::from_int(b);
+        if let Some(product) = a.checked_mul(b) {
+            prop_assert_eq!(product.checked_div(b), Some(a));
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_ordering(a in q64x64(), b in q64x64()) {
+        prop_assert_eq!(a.cmp(&b), a.to_bits().cmp(&b.to_bits()));
+        prop_assert!(a.floor() as u128 <= a.to_bits() >> FRAC_BITS);
+        prop_assert!(a.frac() < Q64x64::ONE);
+    }
+}
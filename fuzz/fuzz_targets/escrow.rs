@@ -0,0 +1,6 @@
+#![no_main]
+
+use examples_fuzz::escrow::{run, Input};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: Input| run(input));
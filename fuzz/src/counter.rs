@@ -0,0 +1,52 @@
+//! Arbitrary instruction data sent to the counter, with or without an
+//! existing counter.
+
+use arbitrary::Arbitrary;
+use counter_client::{Counter, CounterAccounts, ID};
+use mollusk_svm::{program::keyed_account_for_system_program, Mollusk};
+use solana_account::Account;
+use solana_instruction::Instruction;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_pubkey::Pubkey;
+use test_utils::{funded_account, state_account};
+
+use crate::{assert_no_crash, program_path};
+
+thread_local! {
+    static MOLLUSK: Mollusk = Mollusk::new(&ID, program_path!("counter/target/deploy/counter"));
+}
+
+/// Input of the target, decoded from the bytes of the fuzzer.
+#[derive(Arbitrary, Debug)]
+pub struct Input {
+    /// Whether the counter already exists, holding this count.
+    count: Option<u64>,
+    data: Vec<u8>,
+}
+
+/// Runs the input.
+pub fn run(input: Input) {
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_from_array([1; 32]);
+    let accounts = CounterAccounts::new(&owner);
+    let counter = accounts.counter;
+    let counter_account = match input.count {
+        Some(count) => state_account(&Counter {
+            owner: owner.to_bytes(),
+            count,
+        }),
+        None => Account::new(0, 0, &system_program),
+    };
+
+    // Take the accounts of a valid instruction, so the program gets past
+    // them to the instruction data.
+    let instruction = Instruction::new_with_bytes(ID, &input.data, accounts.to_account_metas());
+    let tx_accounts = [
+        (owner, funded_account(LAMPORTS_PER_SOL)),
+        (counter, counter_account),
+        (system_program, system_account),
+    ];
+    let res = MOLLUSK.with(|mollusk| mollusk.process_instruction(&instruction, &tx_accounts));
+    assert_no_crash(&res);
+}
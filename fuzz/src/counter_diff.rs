@@ -0,0 +1,178 @@
+//! Instructions sent to both the counter and `counter-reference`, whose
+//! results and resulting accounts have to be the same.
+
+use arbitrary::Arbitrary;
+use counter_client::{Counter, ID};
+use mollusk_svm::{
+    program::{keyed_account_for_system_program, loader_keys::LOADER_V3},
+    result::{InstructionResult, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_pubkey::Pubkey;
+use test_utils::{funded_account, state_account};
+
+use crate::{assert_no_crash, program_path};
+
+thread_local! {
+    static MOLLUSK: Mollusk = {
+        let mut mollusk = Mollusk::new(&ID, program_path!("counter/target/deploy/counter"));
+        mollusk.add_program(
+            &counter_reference::ID,
+            program_path!("counter-reference/target/deploy/counter_reference"),
+            &LOADER_V3,
+        );
+        mollusk
+    };
+}
+
+/// An instruction sent to both programs.
+#[derive(Arbitrary, Debug)]
+pub struct Step {
+    /// Discriminator of the instruction, also one without an instruction.
+    discriminator: u8,
+    /// Version of the instruction data, also one no instruction has.
+    version: u8,
+    /// Bump of the counter PDA, or the bump of the counter of each program.
+    bump: Option<u8>,
+    /// Amount following the bump, as in version 2 of the instructions
+    /// which have one.
+    amount: Option<u64>,
+}
+
+/// Input of the target, decoded from the bytes of the fuzzer.
+#[derive(Arbitrary, Debug)]
+pub struct Input {
+    /// Whether the counter already exists, holding this count.
+    count: Option<u64>,
+    steps: Vec<Step>,
+}
+
+/// Accounts of the counter of the owner in one of the programs.
+///
+/// Both programs take the same instruction data and store the counter with
+/// the same layout, only under their own address, so the same steps have to
+/// end with the same lamports and data.
+struct Program {
+    id: Pubkey,
+    counter: Pubkey,
+    bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Program {
+    fn new(id: Pubkey, owner: Pubkey, (counter, bump): (Pubkey, u8), count: Option<u64>) -> Self {
+        let (system_program, system_account) = keyed_account_for_system_program();
+        let counter_account = match count {
+            Some(count) => {
+                let mut account = state_account(&Counter {
+                    owner: owner.to_bytes(),
+                    count,
+                });
+                account.owner = id;
+                account
+            }
+            None => Account::new(0, 0, &system_program),
+        };
+        Self {
+            id,
+            counter,
+            bump,
+            accounts: vec![
+                (owner, funded_account(LAMPORTS_PER_SOL)),
+                (counter, counter_account),
+                (system_program, system_account),
+            ],
+        }
+    }
+
+    /// Runs `step` with `bump`, keeping the resulting accounts if it
+    /// succeeded.
+    fn process(
+        &mut self,
+        mollusk: &Mollusk,
+        owner: Pubkey,
+        bump: u8,
+        step: &Step,
+    ) -> InstructionResult {
+        let mut data = vec![step.discriminator, step.version, bump];
+        if let Some(amount) = step.amount {
+            data.extend_from_slice(&amount.to_le_bytes());
+        }
+        let instruction = Instruction::new_with_bytes(
+            self.id,
+            &data,
+            vec![
+                AccountMeta::new(owner, true),
+                AccountMeta::new(self.counter, false),
+                AccountMeta::new_readonly(self.accounts[2].0, false),
+            ],
+        );
+        let res = mollusk.process_instruction(&instruction, &self.accounts);
+        assert_no_crash(&res);
+        if matches!(res.program_result, ProgramResult::Success) {
+            self.accounts = res.resulting_accounts.clone();
+        }
+        res
+    }
+
+    /// Returns the lamports of the owner and the lamports, data and whether
+    /// the program owns the counter. A closed counter has no data.
+    fn state(&self) -> (u64, u64, Vec<u8>, bool) {
+        let owner = &self.accounts[0].1;
+        let counter = &self.accounts[1].1;
+        let data = if counter.lamports == 0 {
+            Vec::new()
+        } else {
+            counter.data.clone()
+        };
+        (
+            owner.lamports,
+            counter.lamports,
+            data,
+            counter.owner == self.id,
+        )
+    }
+}
+
+/// Runs the input.
+pub fn run(input: Input) {
+    let owner = Pubkey::new_from_array([1; 32]);
+    let mut pinocchio = Program::new(
+        ID,
+        owner,
+        counter_client::find_counter_address(&owner),
+        input.count,
+    );
+    let mut reference = Program::new(
+        counter_reference::ID,
+        owner,
+        counter_reference::find_counter_address(&owner),
+        input.count,
+    );
+
+    MOLLUSK.with(|mollusk| {
+        for step in &input.steps {
+            // The PDAs of the programs have their own bumps, so a bump is
+            // only sent to both if it's wrong for both.
+            let (pinocchio_bump, reference_bump) = match step.bump {
+                Some(bump) if bump != pinocchio.bump && bump != reference.bump => (bump, bump),
+                _ => (pinocchio.bump, reference.bump),
+            };
+            let pinocchio_res = pinocchio.process(mollusk, owner, pinocchio_bump, step);
+            let reference_res = reference.process(mollusk, owner, reference_bump, step);
+
+            assert_eq!(
+                pinocchio_res.program_result, reference_res.program_result,
+                "the programs returned different results for {step:?}"
+            );
+            assert_eq!(
+                pinocchio.state(),
+                reference.state(),
+                "the programs ended in different states after {step:?}"
+            );
+        }
+    });
+}
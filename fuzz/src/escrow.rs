@@ -0,0 +1,78 @@
+//! Arbitrary instruction data sent to the escrow, with or without an
+//! existing escrow.
+
+use arbitrary::Arbitrary;
+use escrow_client::{find_escrow_address, CancelAccounts, Escrow, ID, TOKEN_PROGRAM_ID};
+use mollusk_svm::{program::keyed_account_for_system_program, Mollusk};
+use solana_account::Account;
+use solana_instruction::Instruction;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_pubkey::Pubkey;
+use test_utils::{
+    funded_account, keyed_token_program, programs::mollusk, state_account, token_account,
+};
+
+use crate::assert_no_crash;
+
+thread_local! {
+    // The token programs are loaded from wherever `cargo xtask fetch` put
+    // them, like in the tests of the escrow.
+    static MOLLUSK: Mollusk = mollusk();
+}
+
+/// Input of the target, decoded from the bytes of the fuzzer.
+#[derive(Arbitrary, Debug)]
+pub struct Input {
+    /// Whether the escrow already exists, holding this amount.
+    amount: Option<u64>,
+    data: Vec<u8>,
+}
+
+/// Runs the input.
+pub fn run(input: Input) {
+    let (system_program, system_account) = keyed_account_for_system_program();
+    let (token_program, token_program_account) = keyed_token_program();
+
+    let mint = Pubkey::new_from_array([1; 32]);
+    let sender = Pubkey::new_from_array([2; 32]);
+    let sender_ata = Pubkey::new_from_array([3; 32]);
+    let receiver = Pubkey::new_from_array([4; 32]);
+    let receiver_ata = Pubkey::new_from_array([6; 32]);
+    let (escrow, _) = find_escrow_address(&sender, &receiver);
+    let escrow_ata = Pubkey::new_from_array([5; 32]);
+
+    let escrow_account = match input.amount {
+        Some(amount) => state_account(&Escrow::new(
+            sender.to_bytes(),
+            receiver.to_bytes(),
+            amount,
+            0,
+        )),
+        None => Account::new(0, 0, &system_program),
+    };
+
+    // Take the accounts of a valid cancellation, so the program gets past
+    // them to the instruction data.
+    let accounts = CancelAccounts {
+        sender,
+        sender_ata,
+        receiver,
+        escrow,
+        escrow_ata,
+        token_program: TOKEN_PROGRAM_ID,
+        receiver_ata,
+    };
+    let instruction = Instruction::new_with_bytes(ID, &input.data, accounts.to_account_metas());
+    let tx_accounts = [
+        (sender, funded_account(LAMPORTS_PER_SOL)),
+        (sender_ata, token_account(&mint, &sender, 1_000_000)),
+        (receiver, funded_account(LAMPORTS_PER_SOL)),
+        (escrow, escrow_account),
+        (receiver_ata, token_account(&mint, &receiver, 0)),
+        (escrow_ata, token_account(&mint, &escrow, 1_000_000)),
+        (system_program, system_account),
+        (token_program, token_program_account),
+    ];
+    let res = MOLLUSK.with(|mollusk| mollusk.process_instruction(&instruction, &tx_accounts));
+    assert_no_crash(&res);
+}
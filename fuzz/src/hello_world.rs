@@ -0,0 +1,20 @@
+//! Arbitrary instruction data sent to hello-world.
+
+use mollusk_svm::Mollusk;
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+
+use crate::{assert_no_crash, program_path};
+
+const ID: Pubkey = Pubkey::new_from_array(hello_world::ID);
+
+thread_local! {
+    static MOLLUSK: Mollusk = Mollusk::new(&ID, program_path!("hello-world/target/deploy/hello_world"));
+}
+
+/// Runs the instruction with `data`.
+pub fn run(data: &[u8]) {
+    let instruction = Instruction::new_with_bytes(ID, data, Vec::new());
+    let res = MOLLUSK.with(|mollusk| mollusk.process_instruction(&instruction, &[]));
+    assert_no_crash(&res);
+}
@@ -0,0 +1,63 @@
+//! Fuzz targets feeding arbitrary instruction data to the example programs.
+//!
+//! The programs run in Mollusk, so they have to be built first with
+//! `cargo build-sbf` in their crates. Then run a target with e.g.
+//! `cargo fuzz run counter` in this directory.
+//!
+//! `counter_diff` runs the same instructions through the counter and
+//! `counter-reference`, failing as soon as their results or the resulting
+//! accounts differ.
+//!
+//! Each target is a module with the `run` function of its fuzz target, so
+//! the inputs committed under `corpus/<target>/` are replayed by `cargo test`
+//! in this directory too, without cargo-fuzz. The corpus keeps the crashes
+//! found so far and the edge cases of each program. After a fuzzing run,
+//! minimize it with `cargo fuzz cmin <target>` and only commit the inputs
+//! worth keeping, e.g. a crash minimized with `cargo fuzz tmin`.
+
+use std::{fs, path::PathBuf};
+
+use mollusk_svm::result::{InstructionResult, ProgramResult};
+
+pub mod counter;
+pub mod counter_diff;
+pub mod escrow;
+pub mod hello_world;
+
+/// Path of a program relative to the root of the repository.
+#[macro_export]
+macro_rules! program_path {
+    ($path:literal) => {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../", $path)
+    };
+}
+
+/// Checks that the program either succeeded or returned an error.
+///
+/// Panics, memory access violations and running out of compute units
+/// don't map to a program error and abort the runtime instead.
+pub fn assert_no_crash(res: &InstructionResult) {
+    if let ProgramResult::UnknownError(error) = &res.program_result {
+        panic!("the program crashed: {error:?}");
+    }
+}
+
+/// Returns the paths and contents of the inputs in the corpus of `target`,
+/// sorted by path.
+pub fn corpus(target: &str) -> Vec<(PathBuf, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("corpus")
+        .join(target);
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("failed to read {}: {e}", dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let data = fs::read(&path).unwrap();
+            (path, data)
+        })
+        .collect()
+}
@@ -0,0 +1,43 @@
+use arbitrary::{Arbitrary, Unstructured};
+use examples_fuzz::{corpus, counter, counter_diff, escrow, hello_world};
+
+/// Runs every input in the corpus of `target` through `run`, decoding it
+/// like `fuzz_target!` does. Inputs which don't decode are skipped by the
+/// fuzzer too.
+fn replay<T: for<'a> Arbitrary<'a>>(target: &str, run: fn(T)) {
+    let inputs = corpus(target);
+    assert!(!inputs.is_empty(), "the corpus of {target} is empty");
+
+    for (path, data) in inputs {
+        eprintln!("replaying {}", path.display());
+        if let Ok(input) = T::arbitrary_take_rest(Unstructured::new(&data)) {
+            run(input);
+        }
+    }
+}
+
+#[test]
+fn test_hello_world_corpus() {
+    let inputs = corpus("hello_world");
+    assert!(!inputs.is_empty(), "the corpus of hello_world is empty");
+
+    for (path, data) in inputs {
+        eprintln!("replaying {}", path.display());
+        hello_world::run(&data);
+    }
+}
+
+#[test]
+fn test_counter_corpus() {
+    replay("counter", counter::run);
+}
+
+#[test]
+fn test_counter_diff_corpus() {
+    replay("counter_diff", counter_diff::run);
+}
+
+#[test]
+fn test_escrow_corpus() {
+    replay("escrow", escrow::run);
+}
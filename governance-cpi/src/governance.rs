@@ -0,0 +1,117 @@
+//! Hand-written encoding of the SPL Governance instructions used by the
+//! program.
+//!
+//! SPL Governance takes borsh-serialized instruction data, starting with the
+//! index of the variant of its `GovernanceInstruction` enum. Pulling in the
+//! `spl-governance` crate to serialize it would drag `std` and half of the
+//! Solana SDK into the program, so the few instructions needed are encoded by
+//! hand into buffers on the stack.
+
+use pinocchio::pubkey::Pubkey;
+
+/// ID of the SPL Governance program deployed on mainnet.
+pub const GOVERNANCE_PROGRAM_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw");
+
+/// Variant indices of `GovernanceInstruction`.
+pub const CREATE_PROPOSAL: u8 = 6;
+pub const SIGN_OFF_PROPOSAL: u8 = 12;
+pub const CAST_VOTE: u8 = 13;
+
+/// Maximum length of a proposal name accepted by the program.
+pub const MAX_PROPOSAL_NAME_LEN: usize = 32;
+
+/// Label of the only option of the created proposals.
+pub const PROPOSAL_OPTION: &str = "Approve";
+
+/// Maximum length of the `CreateProposal` instruction data.
+pub const CREATE_PROPOSAL_MAX_LEN: usize = 1 // instruction
+    + 4 + MAX_PROPOSAL_NAME_LEN // name
+    + 4 // empty description link
+    + 1 // vote type
+    + 4 + 4 + PROPOSAL_OPTION.len() // options
+    + 1 // use deny option
+    + 32; // proposal seed
+
+/// Borsh serializer writing into a fixed buffer, as there is no allocator.
+///
+/// Writing past the end of the buffer panics, so buffers have to be sized
+/// for the largest possible input.
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write_bytes(&[value]);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    /// Writes a `u32`, which is also how borsh encodes lengths.
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Writes a `String`, given as bytes.
+    pub fn write_string(&mut self, value: &[u8]) {
+        self.write_u32(value.len() as u32);
+        self.write_bytes(value);
+    }
+
+    /// Returns the written bytes.
+    pub fn finish(self) -> &'a [u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// Encodes `CreateProposal` for a single choice proposal with one
+/// [`PROPOSAL_OPTION`], which can also be denied.
+///
+/// `name` must not be longer than [`MAX_PROPOSAL_NAME_LEN`].
+pub fn create_proposal<'a>(
+    buf: &'a mut [u8; CREATE_PROPOSAL_MAX_LEN],
+    name: &[u8],
+    proposal_seed: &Pubkey,
+) -> &'a [u8] {
+    let mut writer = Writer::new(buf);
+    writer.write_u8(CREATE_PROPOSAL);
+    writer.write_string(name);
+    // Description link.
+    writer.write_string(&[]);
+    // `VoteType::SingleChoice`.
+    writer.write_u8(0);
+    // Options.
+    writer.write_u32(1);
+    writer.write_string(PROPOSAL_OPTION.as_bytes());
+    // Use deny option.
+    writer.write_bool(true);
+    writer.write_bytes(proposal_seed);
+    writer.finish()
+}
+
+/// `SignOffProposal` has no arguments.
+pub const SIGN_OFF_PROPOSAL_DATA: [u8; 1] = [SIGN_OFF_PROPOSAL];
+
+/// `CastVote` with `Vote::Approve`, giving the full weight to the only option
+/// of the proposal. After the instruction index, it consists of:
+///
+/// * `Vote::Approve` variant index
+/// * vector length of 1
+/// * `VoteChoice` with rank 0 and 100% weight
+pub const APPROVE_VOTE_DATA: [u8; 8] = [CAST_VOTE, 0, 1, 0, 0, 0, 0, 100];
+
+/// `CastVote` with `Vote::Deny`.
+pub const DENY_VOTE_DATA: [u8; 2] = [CAST_VOTE, 1];
@@ -0,0 +1,361 @@
+//! Governance delegate, creating proposals and voting in SPL Governance
+//! through CPIs.
+//!
+//! Token owners set the delegate PDA of this program as the governance
+//! delegate of their token owner record. The program can then act on their
+//! behalf, signing for the delegate.
+
+#![no_std]
+
+use common::{checks::require_signer, info, keys_eq};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+use governance::{
+    APPROVE_VOTE_DATA, CREATE_PROPOSAL_MAX_LEN, DENY_VOTE_DATA, GOVERNANCE_PROGRAM_ID,
+    MAX_PROPOSAL_NAME_LEN, SIGN_OFF_PROPOSAL_DATA,
+};
+
+pub mod governance;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("A4CgwvjsgGHQHfoDBdJGj8VUg5QGnHwvxikDq1h4XMhp");
+
+pub const DELEGATE_SEED: &str = "delegate";
+
+/// Errors returned by the governance CPI program.
+#[repr(u32)]
+pub enum GovernanceCpiError {
+    /// The proposal name is empty or longer than [`MAX_PROPOSAL_NAME_LEN`].
+    InvalidProposalName,
+}
+
+impl From<GovernanceCpiError> for ProgramError {
+    fn from(e: GovernanceCpiError) -> Self {
+        Namespace::GovernanceCpi.error(e as u32)
+    }
+}
+
+/// Governance CPI program instruction discriminators.
+#[repr(u8)]
+pub enum GovernanceCpiInstruction {
+    /// Creates a proposal and signs it off, opening it for voting.
+    Propose,
+    /// Casts a vote on a proposal.
+    Vote,
+}
+
+impl TryFrom<&u8> for GovernanceCpiInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Propose),
+            1 => Ok(Self::Vote),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[repr(u8)]
+pub enum Vote {
+    Approve,
+    Deny,
+}
+
+impl TryFrom<&u8> for Vote {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Approve),
+            1 => Ok(Self::Deny),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ProposeInstructionData {
+    /// Seed of the proposal PDA, which makes its address unique.
+    pub proposal_seed: Pubkey,
+    /// Name of the proposal, padded with zeros.
+    pub name: [u8; MAX_PROPOSAL_NAME_LEN],
+    pub name_len: u8,
+    /// Bump of the delegate PDA.
+    pub bump: u8,
+}
+
+impl ProposeInstructionData {
+    pub fn new(proposal_seed: Pubkey, name: &[u8], bump: u8) -> Self {
+        let mut padded_name = [0; MAX_PROPOSAL_NAME_LEN];
+        padded_name[..name.len()].copy_from_slice(name);
+        Self {
+            proposal_seed,
+            name: padded_name,
+            name_len: name.len() as u8,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct VoteInstructionData {
+    /// [`Vote`] to cast.
+    pub vote: u8,
+    /// Bump of the delegate PDA.
+    pub bump: u8,
+}
+
+impl VoteInstructionData {
+    pub fn new(vote: Vote, bump: u8) -> Self {
+        Self {
+            vote: vote as u8,
+            bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = GovernanceCpiInstruction::try_from(instruction)?;
+
+    match instruction {
+        GovernanceCpiInstruction::Propose => process_propose(accounts, instruction_data),
+        GovernanceCpiInstruction::Vote => process_vote(accounts, instruction_data),
+    }
+}
+
+/// Checks that `delegate` is the delegate PDA of `authority` and that
+/// `governance_program` is SPL Governance.
+fn check_delegate(
+    authority: &AccountInfo,
+    delegate: &AccountInfo,
+    governance_program: &AccountInfo,
+    bump: u8,
+) -> ProgramResult {
+    require_signer(authority)?;
+
+    let delegate_pda =
+        create_program_address(&[DELEGATE_SEED.as_bytes(), authority.key(), &[bump]], &ID)?;
+    if !keys_eq(delegate.key(), &delegate_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    if !keys_eq(governance_program.key(), &GOVERNANCE_PROGRAM_ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    Ok(())
+}
+
+/// Creates a proposal on behalf of `authority` and signs it off.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Authority, paying for the proposal
+///   1. `[]` Delegate PDA of the authority
+///   2. `[]` Realm
+///   3. `[WRITE]` Proposal
+///   4. `[WRITE]` Governance
+///   5. `[WRITE]` Token owner record of the authority
+///   6. `[]` Governing token mint
+///   7. `[]` Realm config
+///   8. `[WRITE]` Proposal deposit
+///   9. `[]` System program
+///   10. `[]` SPL Governance program
+pub fn process_propose(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, delegate, realm, proposal, governance, token_owner_record, governing_token_mint, realm_config, proposal_deposit, system_program, governance_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = ProposeInstructionData::from_bytes(instruction_data)?;
+
+    let name_len = instruction_data.name_len as usize;
+    if name_len == 0 || name_len > MAX_PROPOSAL_NAME_LEN {
+        return Err(GovernanceCpiError::InvalidProposalName.into());
+    }
+    let name = &instruction_data.name[..name_len];
+
+    check_delegate(
+        authority,
+        delegate,
+        governance_program,
+        instruction_data.bump,
+    )?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(DELEGATE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+
+    // Create the proposal.
+    let mut data = [0; CREATE_PROPOSAL_MAX_LEN];
+    invoke_signed(
+        &Instruction {
+            program_id: &GOVERNANCE_PROGRAM_ID,
+            accounts: &[
+                AccountMeta::readonly(realm.key()),
+                AccountMeta::writable(proposal.key()),
+                AccountMeta::writable(governance.key()),
+                AccountMeta::writable(token_owner_record.key()),
+                AccountMeta::readonly(governing_token_mint.key()),
+                AccountMeta::readonly_signer(delegate.key()),
+                AccountMeta::writable_signer(authority.key()),
+                AccountMeta::readonly(system_program.key()),
+                AccountMeta::readonly(realm_config.key()),
+                AccountMeta::writable(proposal_deposit.key()),
+            ],
+            data: governance::create_proposal(&mut data, name, &instruction_data.proposal_seed),
+        },
+        &[
+            realm,
+            proposal,
+            governance,
+            token_owner_record,
+            governing_token_mint,
+            delegate,
+            authority,
+            system_program,
+            realm_config,
+            proposal_deposit,
+        ],
+        &[Signer::from(&seeds)],
+    )?;
+
+    // Sign it off. Without any signatories, the proposal owner can do it.
+    invoke_signed(
+        &Instruction {
+            program_id: &GOVERNANCE_PROGRAM_ID,
+            accounts: &[
+                AccountMeta::readonly(realm.key()),
+                AccountMeta::readonly(governance.key()),
+                AccountMeta::writable(proposal.key()),
+                AccountMeta::readonly_signer(delegate.key()),
+                AccountMeta::readonly(token_owner_record.key()),
+            ],
+            data: &SIGN_OFF_PROPOSAL_DATA,
+        },
+        &[realm, governance, proposal, delegate, token_owner_record],
+        &[Signer::from(&seeds)],
+    )?;
+
+    info!("Created a proposal");
+
+    Ok(())
+}
+
+/// Votes on a proposal on behalf of `authority`.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Authority, paying for the vote record
+///   1. `[]` Delegate PDA of the authority
+///   2. `[]` Realm
+///   3. `[WRITE]` Governance
+///   4. `[WRITE]` Proposal
+///   5. `[WRITE]` Token owner record of the proposal owner
+///   6. `[WRITE]` Token owner record of the authority
+///   7. `[WRITE]` Vote record
+///   8. `[]` Governing token mint
+///   9. `[]` Realm config
+///   10. `[]` System program
+///   11. `[]` SPL Governance program
+pub fn process_vote(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, delegate, realm, governance, proposal, proposal_owner_record, token_owner_record, vote_record, governing_token_mint, realm_config, system_program, governance_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = VoteInstructionData::from_bytes(instruction_data)?;
+    let vote = Vote::try_from(&instruction_data.vote)?;
+
+    check_delegate(
+        authority,
+        delegate,
+        governance_program,
+        instruction_data.bump,
+    )?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(DELEGATE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+
+    let data: &[u8] = match vote {
+        Vote::Approve => &APPROVE_VOTE_DATA,
+        Vote::Deny => &DENY_VOTE_DATA,
+    };
+    invoke_signed(
+        &Instruction {
+            program_id: &GOVERNANCE_PROGRAM_ID,
+            accounts: &[
+                AccountMeta::readonly(realm.key()),
+                AccountMeta::writable(governance.key()),
+                AccountMeta::writable(proposal.key()),
+                AccountMeta::writable(proposal_owner_record.key()),
+                AccountMeta::writable(token_owner_record.key()),
+                AccountMeta::readonly_signer(delegate.key()),
+                AccountMeta::writable(vote_record.key()),
+                AccountMeta::readonly(governing_token_mint.key()),
+                AccountMeta::writable_signer(authority.key()),
+                AccountMeta::readonly(system_program.key()),
+                AccountMeta::readonly(realm_config.key()),
+            ],
+            data,
+        },
+        &[
+            realm,
+            governance,
+            proposal,
+            proposal_owner_record,
+            token_owner_record,
+            delegate,
+            vote_record,
+            governing_token_mint,
+            authority,
+            system_program,
+            realm_config,
+        ],
+        &[Signer::from(&seeds)],
+    )?;
+
+    info!("Voted on a proposal");
+
+    Ok(())
+}
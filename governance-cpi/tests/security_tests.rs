@@ -0,0 +1,196 @@
+//! Attacks on the governance delegate program, each rejected with its own
+//! error before any CPI, so SPL Governance doesn't have to be loaded.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use governance_cpi::{
+    governance::GOVERNANCE_PROGRAM_ID, GovernanceCpiInstruction, ProposeInstructionData, Vote,
+    VoteInstructionData, DELEGATE_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(governance_cpi::ID);
+const GOVERNANCE_ID: Pubkey = Pubkey::new_from_array(GOVERNANCE_PROGRAM_ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: GovernanceCpiInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<GovernanceCpiInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the delegate PDA of `authority` and its bump.
+fn delegate_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[DELEGATE_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Accounts of a token owner who set their delegate PDA as the governance
+/// delegate of their token owner record, and of an attacker. The accounts
+/// of SPL Governance are never read by the program itself.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    attacker: Pubkey,
+    /// Realm, governance, proposal, token owner record, vote record,
+    /// governing token mint, realm config and proposal deposit.
+    governance_accounts: [Pubkey; 8],
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/governance_cpi");
+
+        let authority = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let governance_accounts = [(); 8].map(|_| Pubkey::new_unique());
+
+        let mut accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (delegate_pda(&authority).0, Account::default()),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (delegate_pda(&attacker).0, Account::default()),
+            keyed_account_for_system_program(),
+            (GOVERNANCE_ID, Account::default()),
+        ];
+        accounts.extend(
+            governance_accounts
+                .iter()
+                .map(|address| (*address, Account::default())),
+        );
+
+        Self {
+            mollusk,
+            authority,
+            attacker,
+            governance_accounts,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Creates a proposal as `delegate`, signed by the attacker.
+    fn attacker_propose(&self, delegate: &Pubkey) -> Instruction {
+        let [realm, governance, proposal, token_owner_record, _, mint, realm_config, proposal_deposit] =
+            self.governance_accounts;
+        let (_, bump) = delegate_pda(&self.attacker);
+        let data = ProposeInstructionData::new(Pubkey::new_unique().to_bytes(), b"Drain", bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new_readonly(*delegate, false),
+            AccountMeta::new_readonly(realm, false),
+            AccountMeta::new(proposal, false),
+            AccountMeta::new(governance, false),
+            AccountMeta::new(token_owner_record, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(realm_config, false),
+            AccountMeta::new(proposal_deposit, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(GOVERNANCE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(GovernanceCpiInstruction::Propose, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Votes as the delegate PDA of `authority`, through
+    /// `governance_program`.
+    fn instruction_vote(&self, authority: &Pubkey, governance_program: &Pubkey) -> Instruction {
+        let [realm, governance, proposal, token_owner_record, vote_record, mint, realm_config, _] =
+            self.governance_accounts;
+        let (delegate, bump) = delegate_pda(authority);
+        let data = VoteInstructionData::new(Vote::Approve, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(delegate, false),
+            AccountMeta::new_readonly(realm, false),
+            AccountMeta::new(governance, false),
+            AccountMeta::new(proposal, false),
+            AccountMeta::new(token_owner_record, false),
+            AccountMeta::new(token_owner_record, false),
+            AccountMeta::new(vote_record, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(realm_config, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(*governance_program, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(GovernanceCpiInstruction::Vote, &data),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_vote(&setup.authority, &GOVERNANCE_ID);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates a proposal with the voting power of the token
+/// owner, as the delegate PDA the owner set.
+#[test]
+fn test_propose_as_delegate_of_other_authority() {
+    let setup = Setup::new();
+    let (delegate, _) = delegate_pda(&setup.authority);
+    setup.expect_err(
+        &setup.attacker_propose(&delegate),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker votes with the voting power of the token owner, as the
+/// delegate PDA the owner set.
+#[test]
+fn test_vote_as_delegate_of_other_authority() {
+    let setup = Setup::new();
+    let (delegate, _) = delegate_pda(&setup.authority);
+    let mut instruction = setup.instruction_vote(&setup.attacker, &GOVERNANCE_ID);
+    instruction.accounts[1].pubkey = delegate;
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// A program posing as SPL Governance, which would be invoked with the
+/// signature of the delegate PDA and could use it in SPL Governance.
+#[test]
+fn test_fake_governance_program() {
+    let setup = Setup::new();
+    let governance_program = Pubkey::new_unique();
+    let setup = setup.with(governance_program, Account::default());
+    setup.expect_err(
+        &setup.instruction_vote(&setup.attacker, &governance_program),
+        ProgramError::IncorrectProgramId,
+    );
+}
@@ -0,0 +1,514 @@
+//! The integration tests need the SPL Governance and SPL Token programs,
+//! which are not built from this repository. Dump them from mainnet before
+//! running them:
+//!
+//! ```sh
+//! cargo xtask fetch governance-cpi
+//! ```
+
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use governance_cpi::{
+    governance::{
+        self, APPROVE_VOTE_DATA, CREATE_PROPOSAL_MAX_LEN, DENY_VOTE_DATA, GOVERNANCE_PROGRAM_ID,
+        SIGN_OFF_PROPOSAL_DATA,
+    },
+    GovernanceCpiError, GovernanceCpiInstruction, ProposeInstructionData, Vote,
+    VoteInstructionData, DELEGATE_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(governance_cpi::ID);
+const GOVERNANCE_ID: Pubkey = Pubkey::new_from_array(GOVERNANCE_PROGRAM_ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(spl_token::ID.to_bytes());
+
+const REALM_NAME: &str = "pinocchio";
+const PROPOSAL_NAME: &[u8] = b"Fund the examples";
+const AMOUNT: u64 = 1_000_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: GovernanceCpiInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<GovernanceCpiInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+#[test]
+fn test_governance_cpi_create_proposal_data() {
+    let proposal_seed = [7; 32];
+    let mut buf = [0; CREATE_PROPOSAL_MAX_LEN];
+    let data = governance::create_proposal(&mut buf, b"Test", &proposal_seed);
+
+    let mut expected = vec![6];
+    expected.extend_from_slice(&[4, 0, 0, 0]);
+    expected.extend_from_slice(b"Test");
+    expected.extend_from_slice(&[0, 0, 0, 0]);
+    expected.push(0);
+    expected.extend_from_slice(&[1, 0, 0, 0, 7, 0, 0, 0]);
+    expected.extend_from_slice(b"Approve");
+    expected.push(1);
+    expected.extend_from_slice(&proposal_seed);
+    assert_eq!(data, expected);
+
+    // The longest name fills the whole buffer.
+    let name = [b'a'; governance::MAX_PROPOSAL_NAME_LEN];
+    let data = governance::create_proposal(&mut buf, &name, &proposal_seed);
+    assert_eq!(data.len(), CREATE_PROPOSAL_MAX_LEN);
+}
+
+#[test]
+fn test_governance_cpi_vote_data() {
+    assert_eq!(SIGN_OFF_PROPOSAL_DATA, [12]);
+    assert_eq!(APPROVE_VOTE_DATA, [13, 0, 1, 0, 0, 0, 0, 100]);
+    assert_eq!(DENY_VOTE_DATA, [13, 1]);
+}
+
+/// Borsh-serialized data of the SPL Governance instructions used to set up a
+/// realm.
+mod spl_governance {
+    pub fn create_realm(name: &str) -> Vec<u8> {
+        let mut data = vec![0];
+        data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        data.extend_from_slice(name.as_bytes());
+        // Use council mint.
+        data.push(0);
+        // Min community weight to create governance.
+        data.extend_from_slice(&1u64.to_le_bytes());
+        // `MintMaxVoterWeightSource::SupplyFraction` of 100%.
+        data.push(0);
+        data.extend_from_slice(&10_000_000_000u64.to_le_bytes());
+        // Community and council token configs, without addins, liquid.
+        data.extend_from_slice(&[0, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0]);
+        data
+    }
+
+    pub fn deposit_governing_tokens(amount: u64) -> Vec<u8> {
+        let mut data = vec![1];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data
+    }
+
+    pub fn set_governance_delegate(delegate: &[u8; 32]) -> Vec<u8> {
+        let mut data = vec![3, 1];
+        data.extend_from_slice(delegate);
+        data
+    }
+
+    pub fn create_governance() -> Vec<u8> {
+        let mut data = vec![4];
+        // Community vote threshold: 60% of yes votes.
+        data.extend_from_slice(&[0, 60]);
+        // Min community weight to create proposal.
+        data.extend_from_slice(&1u64.to_le_bytes());
+        // Min transaction hold up time.
+        data.extend_from_slice(&0u32.to_le_bytes());
+        // Voting base time.
+        data.extend_from_slice(&3_600u32.to_le_bytes());
+        // Community vote tipping: strict.
+        data.push(0);
+        // Council vote threshold: 60% of yes votes.
+        data.extend_from_slice(&[0, 60]);
+        // Council veto vote threshold: disabled.
+        data.push(2);
+        // Min council weight to create proposal.
+        data.extend_from_slice(&1u64.to_le_bytes());
+        // Council vote tipping: strict.
+        data.push(0);
+        // Community veto vote threshold: disabled.
+        data.push(2);
+        // Voting cool off time.
+        data.extend_from_slice(&0u32.to_le_bytes());
+        // Deposit exempt proposal count.
+        data.push(10);
+        data
+    }
+}
+
+fn mint_account(mollusk: &Mollusk, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn governance_address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &GOVERNANCE_ID).0
+}
+
+/// A realm with a single member, who delegated their voting power to the
+/// delegate PDA.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    delegate: Pubkey,
+    bump: u8,
+    mint: Pubkey,
+    source: Pubkey,
+    realm: Pubkey,
+    holding: Pubkey,
+    realm_config: Pubkey,
+    token_owner_record: Pubkey,
+    governance_seed: Pubkey,
+    governance: Pubkey,
+    proposal_seed: Pubkey,
+    proposal: Pubkey,
+    proposal_deposit: Pubkey,
+    vote_record: Pubkey,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/governance_cpi");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+        mollusk.add_program(&GOVERNANCE_ID, "third-party/spl_governance", &LOADER_V3);
+
+        let authority = Pubkey::new_unique();
+        let (delegate, bump) =
+            Pubkey::find_program_address(&[DELEGATE_SEED.as_bytes(), authority.as_ref()], &ID);
+        let mint = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+
+        let realm = governance_address(&[b"governance", REALM_NAME.as_bytes()]);
+        let holding = governance_address(&[b"governance", realm.as_ref(), mint.as_ref()]);
+        let realm_config = governance_address(&[b"realm-config", realm.as_ref()]);
+        let token_owner_record = governance_address(&[
+            b"governance",
+            realm.as_ref(),
+            mint.as_ref(),
+            authority.as_ref(),
+        ]);
+        let governance_seed = Pubkey::new_unique();
+        let governance = governance_address(&[
+            b"account-governance",
+            realm.as_ref(),
+            governance_seed.as_ref(),
+        ]);
+        let proposal_seed = Pubkey::new_unique();
+        let proposal = governance_address(&[
+            b"governance",
+            governance.as_ref(),
+            mint.as_ref(),
+            proposal_seed.as_ref(),
+        ]);
+        let proposal_deposit =
+            governance_address(&[b"proposal-deposit", proposal.as_ref(), authority.as_ref()]);
+        let vote_record = governance_address(&[
+            b"governance",
+            proposal.as_ref(),
+            token_owner_record.as_ref(),
+        ]);
+
+        Self {
+            mollusk,
+            authority,
+            delegate,
+            bump,
+            mint,
+            source,
+            realm,
+            holding,
+            realm_config,
+            token_owner_record,
+            governance_seed,
+            governance,
+            proposal_seed,
+            proposal,
+            proposal_deposit,
+            vote_record,
+        }
+    }
+
+    fn accounts(&self) -> Vec<(Pubkey, Account)> {
+        vec![
+            (
+                self.authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (self.delegate, Account::default()),
+            (self.mint, mint_account(&self.mollusk, AMOUNT)),
+            (
+                self.source,
+                token_account(&self.mollusk, &self.mint, &self.authority, AMOUNT),
+            ),
+            (self.realm, Account::default()),
+            (self.holding, Account::default()),
+            (self.realm_config, Account::default()),
+            (self.token_owner_record, Account::default()),
+            (self.governance_seed, Account::default()),
+            (self.governance, Account::default()),
+            (self.proposal_seed, Account::default()),
+            (self.proposal, Account::default()),
+            (self.proposal_deposit, Account::default()),
+            (self.vote_record, Account::default()),
+            keyed_account_for_system_program(),
+            self.mollusk.sysvars.keyed_account_for_rent_sysvar(),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+            (
+                GOVERNANCE_ID,
+                create_program_account_loader_v3(&GOVERNANCE_ID),
+            ),
+        ]
+    }
+
+    /// SPL Governance instructions creating the realm, depositing the tokens
+    /// of the authority, delegating them and creating the governance.
+    fn instructions_setup(&self) -> Vec<Instruction> {
+        let system_program = keyed_account_for_system_program().0;
+        let rent_sysvar = self.mollusk.sysvars.keyed_account_for_rent_sysvar().0;
+        vec![
+            Instruction::new_with_bytes(
+                GOVERNANCE_ID,
+                &spl_governance::create_realm(REALM_NAME),
+                vec![
+                    AccountMeta::new(self.realm, false),
+                    AccountMeta::new_readonly(self.authority, false),
+                    AccountMeta::new_readonly(self.mint, false),
+                    AccountMeta::new(self.holding, false),
+                    AccountMeta::new(self.authority, true),
+                    AccountMeta::new_readonly(system_program, false),
+                    AccountMeta::new_readonly(TOKEN_ID, false),
+                    AccountMeta::new_readonly(rent_sysvar, false),
+                    AccountMeta::new(self.realm_config, false),
+                ],
+            ),
+            Instruction::new_with_bytes(
+                GOVERNANCE_ID,
+                &spl_governance::deposit_governing_tokens(AMOUNT),
+                vec![
+                    AccountMeta::new_readonly(self.realm, false),
+                    AccountMeta::new(self.holding, false),
+                    AccountMeta::new(self.source, false),
+                    AccountMeta::new_readonly(self.authority, true),
+                    AccountMeta::new_readonly(self.authority, true),
+                    AccountMeta::new(self.token_owner_record, false),
+                    AccountMeta::new(self.authority, true),
+                    AccountMeta::new_readonly(system_program, false),
+                    AccountMeta::new_readonly(TOKEN_ID, false),
+                    AccountMeta::new_readonly(self.realm_config, false),
+                ],
+            ),
+            Instruction::new_with_bytes(
+                GOVERNANCE_ID,
+                &spl_governance::set_governance_delegate(&self.delegate.to_bytes()),
+                vec![
+                    AccountMeta::new_readonly(self.authority, true),
+                    AccountMeta::new(self.token_owner_record, false),
+                ],
+            ),
+            Instruction::new_with_bytes(
+                GOVERNANCE_ID,
+                &spl_governance::create_governance(),
+                vec![
+                    AccountMeta::new_readonly(self.realm, false),
+                    AccountMeta::new(self.governance, false),
+                    AccountMeta::new_readonly(self.governance_seed, false),
+                    AccountMeta::new_readonly(self.token_owner_record, false),
+                    AccountMeta::new(self.authority, true),
+                    AccountMeta::new_readonly(system_program, false),
+                    AccountMeta::new_readonly(self.authority, true),
+                    AccountMeta::new_readonly(self.realm_config, false),
+                ],
+            ),
+        ]
+    }
+
+    fn instruction_propose(
+        &self,
+        name: &[u8],
+        bump: u8,
+        governance_program: &Pubkey,
+    ) -> Instruction {
+        let data = ProposeInstructionData::new(self.proposal_seed.to_bytes(), name, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new_readonly(self.delegate, false),
+            AccountMeta::new_readonly(self.realm, false),
+            AccountMeta::new(self.proposal, false),
+            AccountMeta::new(self.governance, false),
+            AccountMeta::new(self.token_owner_record, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(self.realm_config, false),
+            AccountMeta::new(self.proposal_deposit, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(*governance_program, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(GovernanceCpiInstruction::Propose, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_vote(&self, vote: Vote, bump: u8) -> Instruction {
+        let data = VoteInstructionData::new(vote, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new_readonly(self.delegate, false),
+            AccountMeta::new_readonly(self.realm, false),
+            AccountMeta::new(self.governance, false),
+            AccountMeta::new(self.proposal, false),
+            AccountMeta::new(self.token_owner_record, false),
+            AccountMeta::new(self.token_owner_record, false),
+            AccountMeta::new(self.vote_record, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(self.realm_config, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(GOVERNANCE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(GovernanceCpiInstruction::Vote, &data),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_governance_cpi_propose_and_vote() {
+    let setup = Setup::new();
+
+    let instructions_setup = setup.instructions_setup();
+    let propose = setup.instruction_propose(PROPOSAL_NAME, setup.bump, &GOVERNANCE_ID);
+    let vote = setup.instruction_vote(Vote::Approve, setup.bump);
+
+    let success = [Check::success()];
+    let mut chain: Vec<(&Instruction, &[Check])> = instructions_setup
+        .iter()
+        .map(|instruction| (instruction, &success[..]))
+        .collect();
+    let propose_checks = [
+        Check::success(),
+        Check::account(&setup.proposal)
+            .owner(&GOVERNANCE_ID)
+            .build(),
+    ];
+    let vote_checks = [
+        Check::success(),
+        Check::account(&setup.vote_record)
+            .owner(&GOVERNANCE_ID)
+            .build(),
+    ];
+    chain.push((&propose, &propose_checks));
+    chain.push((&vote, &vote_checks));
+
+    setup
+        .mollusk
+        .process_and_validate_instruction_chain(&chain, &setup.accounts());
+}
+
+#[test]
+fn test_governance_cpi_invalid() {
+    let setup = Setup::new();
+    let accounts = setup.accounts();
+
+    // The name has to fit in the proposal.
+    for name in [&b""[..], &[b'a'; 33]] {
+        let mut instruction = setup.instruction_propose(PROPOSAL_NAME, setup.bump, &GOVERNANCE_ID);
+        // `ProposeInstructionData::new` would panic on the too long name.
+        instruction.data[1 + 32 + 32] = name.len() as u8;
+        setup.mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[Check::err(ProgramError::Custom(
+                Namespace::GovernanceCpi.code(GovernanceCpiError::InvalidProposalName as u32),
+            ))],
+        );
+    }
+
+    // Only the real SPL Governance program can be called.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_propose(PROPOSAL_NAME, setup.bump, &TOKEN_ID),
+        &accounts,
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+
+    // The delegate has to be the PDA of the authority.
+    let wrong_bump = (0..setup.bump)
+        .rev()
+        .find(|bump| {
+            Pubkey::create_program_address(
+                &[DELEGATE_SEED.as_bytes(), setup.authority.as_ref(), &[*bump]],
+                &ID,
+            )
+            .is_ok()
+        })
+        .unwrap();
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_vote(Vote::Deny, wrong_bump),
+        &accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+
+    // Invalid vote.
+    let mut instruction = setup.instruction_vote(Vote::Deny, setup.bump);
+    instruction.data[1] = 2;
+    setup.mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::InvalidInstructionData)],
+    );
+}
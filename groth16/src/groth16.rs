@@ -0,0 +1,159 @@
+//! Groth16 verifier on top of the alt_bn128 syscalls.
+//!
+//! Points are encoded as specified in EIP-197, which the syscalls expect:
+//! a G1 point is `be(x) || be(y)`, a G2 point is
+//! `be(x1) || be(x0) || be(y1) || be(y0)`. The point at infinity is all
+//! zeros.
+
+use macros::InstructionData;
+use pinocchio::program_error::ProgramError;
+
+use crate::{verifying_key, Groth16Error};
+
+/// Size of a field element.
+pub const FIELD_SIZE: usize = 32;
+pub const G1_POINT_SIZE: usize = 2 * FIELD_SIZE;
+pub const G2_POINT_SIZE: usize = 4 * FIELD_SIZE;
+
+/// Size of a pair of points taken by the pairing syscall.
+pub const PAIRING_ELEMENT_SIZE: usize = G1_POINT_SIZE + G2_POINT_SIZE;
+
+/// Operations of the `sol_alt_bn128_group_op` syscall.
+const ALT_BN128_ADD: u64 = 0;
+const ALT_BN128_MUL: u64 = 2;
+const ALT_BN128_PAIRING: u64 = 3;
+
+/// Modulus of the base field, big-endian.
+pub const BASE_FIELD_MODULUS: [u8; FIELD_SIZE] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
+/// Modulus of the scalar field, big-endian. Public inputs have to be
+/// smaller.
+pub const SCALAR_FIELD_MODULUS: [u8; FIELD_SIZE] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+/// A Groth16 proof.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct Proof {
+    pub a: [u8; G1_POINT_SIZE],
+    pub b: [u8; G2_POINT_SIZE],
+    pub c: [u8; G1_POINT_SIZE],
+}
+
+/// Calls the `sol_alt_bn128_group_op` syscall. The syscall fails for points
+/// which are not on the curve.
+fn alt_bn128_group_op(op: u64, input: &[u8], result: &mut [u8]) -> Result<(), ProgramError> {
+    #[cfg(target_os = "solana")]
+    {
+        let ret = unsafe {
+            pinocchio::syscalls::sol_alt_bn128_group_op(
+                op,
+                input.as_ptr(),
+                input.len() as u64,
+                result.as_mut_ptr(),
+            )
+        };
+        if ret != 0 {
+            return Err(Groth16Error::InvalidProof.into());
+        }
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((op, input, result));
+
+    Ok(())
+}
+
+/// Returns `-point`. For a point `(x, y)`, that's `(x, q - y)`.
+pub fn negate_g1(point: &[u8; G1_POINT_SIZE]) -> Result<[u8; G1_POINT_SIZE], ProgramError> {
+    let (x, y) = point.split_at(FIELD_SIZE);
+    // Big-endian numbers compare the same way as byte arrays.
+    if y >= BASE_FIELD_MODULUS.as_slice() {
+        return Err(Groth16Error::InvalidProof.into());
+    }
+
+    let mut negated = [0; G1_POINT_SIZE];
+    negated[..FIELD_SIZE].copy_from_slice(x);
+    // The point at infinity is its own negation.
+    if point.iter().all(|b| *b == 0) {
+        return Ok(negated);
+    }
+    let mut borrow = 0;
+    for i in (0..FIELD_SIZE).rev() {
+        let diff = BASE_FIELD_MODULUS[i] as i16 - y[i] as i16 - borrow;
+        negated[FIELD_SIZE + i] = diff.rem_euclid(256) as u8;
+        borrow = (diff < 0) as i16;
+    }
+    Ok(negated)
+}
+
+/// Computes `IC[0] + public_inputs[0] * IC[1] + ...`, the linear
+/// combination of the public inputs with the verifying key.
+pub fn prepare_inputs(
+    public_inputs: &[[u8; FIELD_SIZE]; verifying_key::NR_PUBLIC_INPUTS],
+) -> Result<[u8; G1_POINT_SIZE], ProgramError> {
+    let mut prepared = verifying_key::IC[0];
+    for (input, ic) in public_inputs.iter().zip(&verifying_key::IC[1..]) {
+        // The multiplication doesn't reduce the scalar, so `x` and `x + r`
+        // would both be accepted for the same proof.
+        if input >= &SCALAR_FIELD_MODULUS {
+            return Err(Groth16Error::PublicInputOutOfRange.into());
+        }
+
+        let mut mul_input = [0; G1_POINT_SIZE + FIELD_SIZE];
+        mul_input[..G1_POINT_SIZE].copy_from_slice(ic);
+        mul_input[G1_POINT_SIZE..].copy_from_slice(input);
+        let mut product = [0; G1_POINT_SIZE];
+        alt_bn128_group_op(ALT_BN128_MUL, &mul_input, &mut product)?;
+
+        let mut add_input = [0; 2 * G1_POINT_SIZE];
+        add_input[..G1_POINT_SIZE].copy_from_slice(&prepared);
+        add_input[G1_POINT_SIZE..].copy_from_slice(&product);
+        alt_bn128_group_op(ALT_BN128_ADD, &add_input, &mut prepared)?;
+    }
+    Ok(prepared)
+}
+
+/// Builds the input of the pairing check
+/// `e(-A, B) * e(alpha, beta) * e(prepared_inputs, gamma) * e(C, delta) == 1`.
+pub fn pairing_input(
+    proof: &Proof,
+    prepared_inputs: &[u8; G1_POINT_SIZE],
+) -> Result<[u8; 4 * PAIRING_ELEMENT_SIZE], ProgramError> {
+    let negated_a = negate_g1(&proof.a)?;
+    let pairs: [(&[u8; G1_POINT_SIZE], &[u8; G2_POINT_SIZE]); 4] = [
+        (&negated_a, &proof.b),
+        (&verifying_key::ALPHA_G1, &verifying_key::BETA_G2),
+        (prepared_inputs, &verifying_key::GAMMA_G2),
+        (&proof.c, &verifying_key::DELTA_G2),
+    ];
+
+    let mut input = [0; 4 * PAIRING_ELEMENT_SIZE];
+    for (element, (g1, g2)) in input.chunks_exact_mut(PAIRING_ELEMENT_SIZE).zip(pairs) {
+        element[..G1_POINT_SIZE].copy_from_slice(g1);
+        element[G1_POINT_SIZE..].copy_from_slice(g2);
+    }
+    Ok(input)
+}
+
+/// Verifies `proof` for `public_inputs` against the hard-coded verifying key.
+pub fn verify(
+    proof: &Proof,
+    public_inputs: &[[u8; FIELD_SIZE]; verifying_key::NR_PUBLIC_INPUTS],
+) -> Result<(), ProgramError> {
+    let prepared_inputs = prepare_inputs(public_inputs)?;
+    let input = pairing_input(proof, &prepared_inputs)?;
+
+    // The result is 1 as a big-endian number if the check passed.
+    let mut result = [0; FIELD_SIZE];
+    alt_bn128_group_op(ALT_BN128_PAIRING, &input, &mut result)?;
+    if result[FIELD_SIZE - 1] != 1 || result[..FIELD_SIZE - 1].iter().any(|b| *b != 0) {
+        return Err(Groth16Error::InvalidProof.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,190 @@
+//! Groth16 proof verification with the alt_bn128 syscalls.
+//!
+//! The program records that a user proved a statement by creating a
+//! verification PDA for them. Only a valid proof against the hard-coded
+//! verifying key lets the user create it.
+//!
+//! The proof has two public inputs: the key of the user, so a proof seen in
+//! a transaction can't be replayed by somebody else, and a value attested by
+//! the proof, which gets stored in the verification account.
+
+#![no_std]
+
+use core::mem;
+
+use common::{assert_layout, checks::require_signer, info, keys_eq};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::groth16::{Proof, FIELD_SIZE};
+
+pub mod groth16;
+pub mod verifying_key;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("HjnZayQFhmWbtfMcKFeQ4BFKrCsxvSG4trdKTU2rQFb7");
+
+pub const VERIFICATION_SEED: &str = "verification";
+
+/// Errors returned by the Groth16 program.
+#[repr(u32)]
+pub enum Groth16Error {
+    /// The proof doesn't verify, or its points are not on the curve.
+    InvalidProof,
+    /// A public input is not smaller than the modulus of the scalar field.
+    PublicInputOutOfRange,
+}
+
+impl From<Groth16Error> for ProgramError {
+    fn from(e: Groth16Error) -> Self {
+        Namespace::Groth16.error(e as u32)
+    }
+}
+
+/// On-chain record of a verified proof.
+#[repr(C)]
+pub struct Verification {
+    pub owner: Pubkey,
+    /// Value attested by the proof.
+    pub value: [u8; FIELD_SIZE],
+}
+
+assert_layout!(Verification, size = 64, align = 1, { owner: 0, value: 32 });
+
+impl Verification {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Returns the public input representing `key`. The first byte is cleared,
+/// so the input is always smaller than the modulus of the scalar field.
+pub fn key_to_public_input(key: &Pubkey) -> [u8; FIELD_SIZE] {
+    let mut input = *key;
+    input[0] = 0;
+    input
+}
+
+/// Groth16 program instruction discriminators.
+#[repr(u8)]
+pub enum Groth16Instruction {
+    /// Verifies a proof and records it.
+    Verify,
+}
+
+impl TryFrom<&u8> for Groth16Instruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Verify),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct VerifyInstructionData {
+    pub proof: Proof,
+    /// Value attested by the proof, big-endian.
+    pub value: [u8; FIELD_SIZE],
+    pub bump: u8,
+}
+
+impl VerifyInstructionData {
+    pub fn new(proof: Proof, value: [u8; FIELD_SIZE], bump: u8) -> Self {
+        Self { proof, value, bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = Groth16Instruction::try_from(instruction)?;
+
+    match instruction {
+        Groth16Instruction::Verify => process_verify(accounts, instruction_data),
+    }
+}
+
+/// Verifies the proof of `owner` and creates their verification PDA.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner
+///   1. `[WRITE]` Verification PDA of the owner
+///   2. `[]` System program
+pub fn process_verify(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, verification, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    let instruction_data = VerifyInstructionData::from_bytes(instruction_data)?;
+
+    let verification_pda = create_program_address(
+        &[
+            VERIFICATION_SEED.as_bytes(),
+            owner.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(verification.key(), &verification_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    groth16::verify(
+        &instruction_data.proof,
+        &[key_to_public_input(owner.key()), instruction_data.value],
+    )?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(VERIFICATION_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: owner,
+        to: verification,
+        lamports: Rent::get()?.minimum_balance(Verification::LEN),
+        space: Verification::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut data = verification.try_borrow_mut_data()?;
+    let verification: &mut Verification = unsafe { &mut *data.as_mut_ptr().cast() };
+    verification.owner = *owner.key();
+    verification.value = instruction_data.value;
+
+    info!("Verified the proof");
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Verifying key of the proofs accepted by the program.
+//!
+//! Generated off-chain with the toy trusted setup from the tests. A real
+//! program embeds the verifying key of its circuit instead.
+
+use crate::groth16::{G1_POINT_SIZE, G2_POINT_SIZE};
+
+/// Number of public inputs of the circuit.
+pub const NR_PUBLIC_INPUTS: usize = 2;
+
+/// `alpha` in G1.
+pub const ALPHA_G1: [u8; G1_POINT_SIZE] = [
+    0x04, 0xf3, 0xe4, 0x1f, 0x07, 0x1c, 0x2c, 0xda, 0x06, 0xb7, 0x97, 0xb5, 0xc5, 0xa2, 0x75, 0x19,
+    0x04, 0x1d, 0x6b, 0xb5, 0x28, 0xaf, 0x3e, 0xe4, 0x7b, 0x99, 0x50, 0x9a, 0x1d, 0xb7, 0x46, 0xef,
+    0x09, 0xd0, 0x66, 0x3c, 0xa4, 0xf2, 0xed, 0xaa, 0x99, 0x0c, 0xfd, 0xc0, 0x6c, 0x09, 0xf3, 0x97,
+    0xf2, 0xed, 0x9d, 0x08, 0x93, 0x71, 0x69, 0x06, 0xe3, 0x77, 0x4a, 0x9c, 0xe5, 0x90, 0xcc, 0xf5,
+];
+
+/// `beta` in G2.
+pub const BETA_G2: [u8; G2_POINT_SIZE] = [
+    0x1f, 0x23, 0x84, 0x61, 0xe9, 0xfb, 0x5b, 0x8f, 0x1d, 0xef, 0xd1, 0x92, 0x8b, 0x60, 0xec, 0x00,
+    0x69, 0xff, 0x59, 0xbb, 0x4e, 0xf2, 0xca, 0xf2, 0x91, 0xb2, 0xca, 0x45, 0x8c, 0xf6, 0x77, 0xae,
+    0x24, 0x36, 0xfa, 0x52, 0x3a, 0x20, 0x33, 0xf1, 0xfe, 0xf5, 0xee, 0x06, 0x01, 0x2b, 0x72, 0x8f,
+    0x76, 0x70, 0xbc, 0xe7, 0x6b, 0x52, 0x98, 0x63, 0xd9, 0x6a, 0x45, 0x46, 0x97, 0x26, 0xf9, 0x71,
+    0x20, 0x12, 0x75, 0xe7, 0xde, 0xe2, 0x91, 0x89, 0xb4, 0x52, 0xdf, 0x18, 0xb2, 0xb1, 0x63, 0x0e,
+    0xed, 0xf0, 0x9b, 0x1f, 0x54, 0xfb, 0xad, 0x81, 0x6f, 0x40, 0xda, 0x2e, 0x0e, 0x6b, 0x8e, 0x6a,
+    0x1d, 0xf7, 0x40, 0xb6, 0x29, 0x01, 0xdb, 0x90, 0x42, 0xf9, 0x74, 0x8c, 0x11, 0xe9, 0xe7, 0x86,
+    0x26, 0x94, 0x69, 0xda, 0x69, 0xf4, 0xcf, 0x30, 0x33, 0x6c, 0x0d, 0x4f, 0xbb, 0x98, 0x24, 0x01,
+];
+
+/// `gamma` in G2.
+pub const GAMMA_G2: [u8; G2_POINT_SIZE] = [
+    0x18, 0xac, 0x3d, 0xa7, 0x1d, 0x38, 0x4c, 0x4b, 0x76, 0x1e, 0x88, 0x96, 0x43, 0x8f, 0xa0, 0xf4,
+    0x04, 0xd2, 0xd9, 0x7a, 0x14, 0x2e, 0x46, 0x6c, 0xbc, 0x0f, 0x68, 0x48, 0xf1, 0x2a, 0x2d, 0x6f,
+    0x08, 0x93, 0x2a, 0x1c, 0x5b, 0x36, 0x22, 0x7b, 0xba, 0xf5, 0x86, 0xa4, 0x5d, 0x77, 0xda, 0xd9,
+    0x10, 0xc7, 0xb2, 0x15, 0x67, 0x27, 0x0d, 0x78, 0x18, 0x17, 0x60, 0xd9, 0xc7, 0x9e, 0xd5, 0x2f,
+    0x25, 0xdf, 0x37, 0xac, 0x1a, 0x8e, 0xae, 0x1b, 0xa6, 0x10, 0x41, 0x52, 0x82, 0x4e, 0x17, 0x80,
+    0xd2, 0x35, 0xad, 0xf4, 0x0b, 0x64, 0x93, 0x3b, 0x01, 0x89, 0x1e, 0xef, 0x15, 0xcc, 0x94, 0x52,
+    0x2f, 0x8b, 0xd4, 0x19, 0xa0, 0x2a, 0xae, 0x74, 0xbc, 0xb3, 0xd7, 0x7f, 0xc3, 0x2d, 0xbc, 0xcc,
+    0x08, 0x90, 0x8c, 0x86, 0x9f, 0x5b, 0x0a, 0x50, 0x3e, 0x6e, 0xe8, 0x38, 0x13, 0x05, 0x67, 0xea,
+];
+
+/// `delta` in G2.
+pub const DELTA_G2: [u8; G2_POINT_SIZE] = [
+    0x1f, 0xd2, 0x95, 0x61, 0x0d, 0x19, 0x8e, 0xad, 0xaf, 0x57, 0xcb, 0x7d, 0x7f, 0xe6, 0x17, 0x3c,
+    0x39, 0x53, 0xd7, 0x97, 0x86, 0x2f, 0x06, 0x97, 0x01, 0xfb, 0x5a, 0x5c, 0x1e, 0x47, 0x15, 0x2b,
+    0x21, 0x87, 0x9f, 0x0e, 0xf0, 0x3b, 0x31, 0xc5, 0x26, 0xe9, 0x77, 0xff, 0xfe, 0x38, 0x54, 0x82,
+    0x24, 0xfa, 0xc0, 0x6b, 0xc2, 0xc6, 0x1f, 0xdd, 0x0a, 0x28, 0x09, 0x17, 0xca, 0x95, 0x19, 0x49,
+    0x2e, 0xf6, 0x3b, 0x6b, 0x96, 0x1e, 0xe6, 0x45, 0x60, 0x70, 0x1c, 0x87, 0xbc, 0x6c, 0x8b, 0x42,
+    0xa5, 0x1a, 0xc3, 0xf6, 0x0d, 0x1e, 0x7f, 0x2b, 0xb6, 0x02, 0xcd, 0xa7, 0xe8, 0x07, 0x0c, 0x8a,
+    0x0a, 0x71, 0x74, 0x2f, 0xa9, 0xbd, 0x02, 0x20, 0x2e, 0xbb, 0xe4, 0x8d, 0x1c, 0xd2, 0x52, 0xe8,
+    0x0e, 0x9f, 0x17, 0x73, 0xb6, 0x30, 0x6d, 0x02, 0x09, 0xee, 0xaa, 0xe3, 0xc3, 0xfb, 0x6a, 0x8b,
+];
+
+/// Points multiplied by the public inputs, the first one by 1.
+pub const IC: [[u8; G1_POINT_SIZE]; NR_PUBLIC_INPUTS + 1] = [
+    [
+        0x27, 0xc0, 0xf3, 0x8b, 0x05, 0x63, 0x6c, 0x84, 0x20, 0x76, 0xe5, 0xa6, 0xf1, 0x5a, 0x4e,
+        0x53, 0x12, 0x55, 0xbd, 0x4c, 0xd0, 0xe3, 0x5f, 0x2d, 0x62, 0xe4, 0x8f, 0x70, 0x7b, 0x26,
+        0x6f, 0xf8, 0x2a, 0xfc, 0x99, 0xa5, 0x4c, 0x78, 0xa4, 0x75, 0xe1, 0x65, 0x5e, 0x03, 0x3d,
+        0x8c, 0x87, 0x40, 0xf0, 0x28, 0x7c, 0x4a, 0x14, 0x2c, 0x6e, 0xec, 0x0a, 0x76, 0x39, 0xed,
+        0x3d, 0x31, 0x07, 0x38,
+    ],
+    [
+        0x09, 0xf2, 0x16, 0x2e, 0xf8, 0xe4, 0x87, 0xe9, 0xc8, 0x98, 0x47, 0xac, 0x89, 0x45, 0xfe,
+        0xdb, 0x5a, 0x37, 0xbb, 0x59, 0x37, 0x6f, 0xb1, 0x18, 0x0d, 0x71, 0x74, 0x03, 0x5c, 0x95,
+        0x8b, 0xbd, 0x06, 0x06, 0x5c, 0x60, 0x30, 0xfc, 0x18, 0xb1, 0x13, 0x43, 0xe2, 0xe8, 0xd8,
+        0x32, 0x1f, 0x9e, 0xdc, 0xcc, 0x06, 0x84, 0x99, 0xd3, 0x30, 0x7b, 0x91, 0x21, 0x04, 0x78,
+        0x3a, 0x65, 0x4f, 0xf4,
+    ],
+    [
+        0x24, 0x69, 0x2a, 0x09, 0xf7, 0xe8, 0x94, 0xe9, 0xef, 0x71, 0x9a, 0xf5, 0x42, 0x91, 0x98,
+        0x44, 0xac, 0x5f, 0x43, 0x46, 0x87, 0x4e, 0x10, 0x31, 0xc1, 0x56, 0x4b, 0x9f, 0x07, 0xdf,
+        0xa9, 0xa4, 0x2c, 0x8e, 0x39, 0x73, 0x4f, 0x92, 0xfd, 0xde, 0x88, 0x4e, 0xa2, 0xb4, 0xef,
+        0x03, 0x5d, 0x99, 0x0f, 0xac, 0x9d, 0xab, 0x1d, 0x69, 0x2a, 0x44, 0xc5, 0x26, 0x9c, 0x3d,
+        0x22, 0xdc, 0xcc, 0x7d,
+    ],
+];
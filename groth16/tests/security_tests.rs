@@ -0,0 +1,120 @@
+//! Attacks on the Groth16 program, each rejected with its own error before
+//! the proof is verified, so the proofs are left empty.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use groth16::{
+    groth16::{Proof, G1_POINT_SIZE, G2_POINT_SIZE},
+    Groth16Instruction, VerifyInstructionData, VERIFICATION_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(groth16::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: Groth16Instruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<Groth16Instruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the verification PDA of `owner` and its bump.
+fn verification_pda(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VERIFICATION_SEED.as_bytes(), owner.as_ref()], &ID)
+}
+
+/// Accounts of an owner without a verification yet, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    verification: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/groth16");
+
+        let owner = Pubkey::new_unique();
+        let (verification, _) = verification_pda(&owner);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (verification, Account::default()),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            verification,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Verifies an empty proof of `owner` into the verification of the
+    /// owner, with `bump`.
+    fn instruction_verify(&self, owner: &Pubkey, bump: u8) -> Instruction {
+        let proof = Proof {
+            a: [0; G1_POINT_SIZE],
+            b: [0; G2_POINT_SIZE],
+            c: [0; G1_POINT_SIZE],
+        };
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(self.verification, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                Groth16Instruction::Verify,
+                &VerifyInstructionData::new(proof, [0; 32], bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let (_, bump) = verification_pda(&setup.owner);
+    let mut instruction = setup.instruction_verify(&setup.owner, bump);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker records a verification of the owner, with a value proven
+/// for themselves, before the owner does.
+#[test]
+fn test_verify_into_verification_of_other_owner() {
+    let setup = Setup::new();
+    let (_, bump) = verification_pda(&setup.attacker);
+    setup.expect_err(
+        &setup.instruction_verify(&setup.attacker, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
@@ -0,0 +1,360 @@
+//! The verifying key and the proof fixtures come from a toy trusted setup
+//! with a known trapdoor, implemented in the `setup` module. Knowing the
+//! trapdoor, [`setup::prove`] computes a valid proof for any public inputs,
+//! the same way as the simulator in the zero-knowledge proof of Groth16.
+//! That's enough to exercise the verifier, but a real verifying key has to
+//! come from a setup nobody knows the trapdoor of, for a real circuit, e.g.
+//! with `snarkjs` or `ark-groth16`.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use groth16::{
+    groth16::{negate_g1, pairing_input, prepare_inputs, Proof, SCALAR_FIELD_MODULUS},
+    key_to_public_input, verifying_key, Groth16Error, Groth16Instruction, Verification,
+    VerifyInstructionData, VERIFICATION_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_bn254::prelude::{alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(groth16::ID);
+
+/// `Groth16Error::InvalidProof`.
+const INVALID_PROOF: u32 = Namespace::Groth16.code(0);
+
+/// Owner the proof fixture was generated for.
+const OWNER: Pubkey = Pubkey::new_from_array([7; 32]);
+
+/// Value attested by the proof fixture.
+const VALUE: [u8; 32] = {
+    let mut value = [0; 32];
+    value[31] = 42;
+    value
+};
+
+/// Proof fixture for [`OWNER`] and [`VALUE`].
+const PROOF_A: [u8; 64] = [
+    0x09, 0x3d, 0xf7, 0x34, 0x64, 0x36, 0x56, 0x14, 0x83, 0xce, 0x8e, 0x59, 0xc0, 0x72, 0xd6, 0xdf,
+    0xd2, 0x32, 0x92, 0x3c, 0x27, 0x58, 0x2f, 0x93, 0x95, 0xe4, 0x36, 0xd7, 0x1c, 0xf1, 0xb1, 0x37,
+    0x00, 0x26, 0x77, 0x23, 0x74, 0x5f, 0x41, 0xb3, 0x95, 0x75, 0x3a, 0x4a, 0x6b, 0xe0, 0x61, 0x1b,
+    0x68, 0x4c, 0x6c, 0xe6, 0xf0, 0x21, 0x08, 0xa6, 0x6d, 0x48, 0xf9, 0x0d, 0x6d, 0xbe, 0x00, 0x26,
+];
+const PROOF_B: [u8; 128] = [
+    0x10, 0x7b, 0xf8, 0xd0, 0x08, 0xb1, 0x00, 0x23, 0x05, 0x40, 0x04, 0x98, 0x91, 0xf1, 0xa3, 0xba,
+    0xf9, 0x47, 0x6f, 0x48, 0x77, 0x33, 0xa9, 0x5d, 0x9e, 0xa7, 0xae, 0x53, 0x1b, 0xa5, 0xe1, 0x52,
+    0x2a, 0x94, 0x12, 0x17, 0x12, 0xb7, 0x11, 0xc0, 0x99, 0xb5, 0x37, 0x36, 0xf0, 0x8f, 0x24, 0x71,
+    0x79, 0x8a, 0xf0, 0xfa, 0xe8, 0xa5, 0xcc, 0x51, 0xc9, 0xf8, 0x95, 0xa9, 0xc3, 0x55, 0xc1, 0xdf,
+    0x15, 0x19, 0x89, 0x70, 0x9b, 0x9c, 0xaf, 0x93, 0x2f, 0xc6, 0x71, 0x1d, 0x00, 0x15, 0xf5, 0x1a,
+    0x25, 0x0e, 0xd4, 0x01, 0x21, 0xc5, 0xdc, 0xb1, 0x9d, 0x39, 0x39, 0xc6, 0x49, 0xee, 0x13, 0x2d,
+    0x1b, 0xa6, 0x16, 0xfa, 0x20, 0x59, 0x76, 0xc8, 0x75, 0x1a, 0xe2, 0x0f, 0xca, 0xa6, 0x24, 0x98,
+    0x74, 0x07, 0xff, 0xdc, 0x65, 0x22, 0x97, 0xc6, 0xce, 0x16, 0x73, 0x79, 0x80, 0xe1, 0xb8, 0x72,
+];
+const PROOF_C: [u8; 64] = [
+    0x20, 0x83, 0x9b, 0x4d, 0xfa, 0xe3, 0xda, 0x54, 0xba, 0x7f, 0x94, 0xa6, 0x5d, 0xfd, 0x7d, 0xf7,
+    0x85, 0xa4, 0x74, 0x91, 0x2f, 0x35, 0xa8, 0xa2, 0xfb, 0x0b, 0x90, 0x76, 0x36, 0x97, 0x14, 0xef,
+    0x14, 0x7c, 0x9d, 0xba, 0xc3, 0x41, 0x0c, 0x3c, 0x71, 0x0a, 0x7b, 0xf2, 0x6d, 0x2f, 0x03, 0x63,
+    0xeb, 0xa0, 0x03, 0x6d, 0xb3, 0xdd, 0x5a, 0x26, 0x28, 0xd8, 0xbb, 0x78, 0xe3, 0xc0, 0x16, 0xfc,
+];
+
+fn proof_fixture() -> Proof {
+    Proof {
+        a: PROOF_A,
+        b: PROOF_B,
+        c: PROOF_C,
+    }
+}
+
+/// Toy trusted setup and prover.
+mod setup {
+    use ark_bn254::{Fr, G1Affine, G2Affine};
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ark_ff::{BigInteger, Field, PrimeField};
+
+    /// Trapdoor of the setup.
+    const ALPHA: u64 = 0x1d7a_5b3c_9e21_04f8;
+    const BETA: u64 = 0x6c0f_e2a9_3b57_d411;
+    const GAMMA: u64 = 0x2b94_7e1d_c038_5a6f;
+    const DELTA: u64 = 0x7f31_09c6_a4e8_2db5;
+    /// Evaluations of the public input polynomials, divided by gamma.
+    const IC: [u64; 3] = [
+        0x45e8_b217_6d9c_0f3a,
+        0x13c6_f05a_8e72_b4d9,
+        0x5a2d_7c91_e04b_36f8,
+    ];
+
+    /// Encodes a G1 point as specified in EIP-197.
+    pub fn g1(point: G1Affine) -> [u8; 64] {
+        let mut bytes = [0; 64];
+        if point.is_zero() {
+            return bytes;
+        }
+        bytes[..32].copy_from_slice(&point.x.into_bigint().to_bytes_be());
+        bytes[32..].copy_from_slice(&point.y.into_bigint().to_bytes_be());
+        bytes
+    }
+
+    /// Encodes a G2 point as specified in EIP-197.
+    pub fn g2(point: G2Affine) -> [u8; 128] {
+        let mut bytes = [0; 128];
+        bytes[..32].copy_from_slice(&point.x.c1.into_bigint().to_bytes_be());
+        bytes[32..64].copy_from_slice(&point.x.c0.into_bigint().to_bytes_be());
+        bytes[64..96].copy_from_slice(&point.y.c1.into_bigint().to_bytes_be());
+        bytes[96..].copy_from_slice(&point.y.c0.into_bigint().to_bytes_be());
+        bytes
+    }
+
+    pub struct VerifyingKey {
+        pub alpha_g1: [u8; 64],
+        pub beta_g2: [u8; 128],
+        pub gamma_g2: [u8; 128],
+        pub delta_g2: [u8; 128],
+        pub ic: Vec<[u8; 64]>,
+    }
+
+    pub fn verifying_key() -> VerifyingKey {
+        let g1_generator = G1Affine::generator();
+        let g2_generator = G2Affine::generator();
+        VerifyingKey {
+            alpha_g1: g1((g1_generator * Fr::from(ALPHA)).into_affine()),
+            beta_g2: g2((g2_generator * Fr::from(BETA)).into_affine()),
+            gamma_g2: g2((g2_generator * Fr::from(GAMMA)).into_affine()),
+            delta_g2: g2((g2_generator * Fr::from(DELTA)).into_affine()),
+            ic: IC
+                .iter()
+                .map(|ic| g1((g1_generator * Fr::from(*ic)).into_affine()))
+                .collect(),
+        }
+    }
+
+    /// Computes a proof for `public_inputs`. `a` and `b` take the place of
+    /// the randomness of the prover.
+    pub fn prove(public_inputs: &[[u8; 32]], a: u64, b: u64) -> ([u8; 64], [u8; 128], [u8; 64]) {
+        let (a, b) = (Fr::from(a), Fr::from(b));
+        let prepared_inputs = public_inputs
+            .iter()
+            .zip(&IC[1..])
+            .fold(Fr::from(IC[0]), |acc, (input, ic)| {
+                acc + Fr::from_be_bytes_mod_order(input) * Fr::from(*ic)
+            });
+        // a * b = alpha * beta + prepared_inputs * gamma + c * delta
+        let c = (a * b - Fr::from(ALPHA) * Fr::from(BETA) - prepared_inputs * Fr::from(GAMMA))
+            * Fr::from(DELTA).inverse().unwrap();
+
+        (
+            g1((G1Affine::generator() * a).into_affine()),
+            g2((G2Affine::generator() * b).into_affine()),
+            g1((G1Affine::generator() * c).into_affine()),
+        )
+    }
+}
+
+/// Builds the proof fixture, with the randomness it was generated with.
+fn prove_fixture() -> Proof {
+    let (a, b, c) = setup::prove(
+        &[key_to_public_input(&OWNER.to_bytes()), VALUE],
+        0x3e5c_81a7,
+        0x9b24_d6f0,
+    );
+    Proof { a, b, c }
+}
+
+/// Verifies `proof` on the host. The program does the same with the
+/// syscalls, which are not available outside of the SVM.
+fn verify_on_host(proof: &Proof, public_inputs: &[[u8; 32]; 2]) -> bool {
+    let mut prepared_inputs = verifying_key::IC[0];
+    for (input, ic) in public_inputs.iter().zip(&verifying_key::IC[1..]) {
+        let product = alt_bn128_multiplication(&[ic.as_slice(), input].concat()).unwrap();
+        let sum = alt_bn128_addition(&[prepared_inputs.as_slice(), &product].concat()).unwrap();
+        prepared_inputs.copy_from_slice(&sum);
+    }
+
+    let input = pairing_input(proof, &prepared_inputs).unwrap();
+    let result = alt_bn128_pairing(&input).unwrap();
+    result[31] == 1
+}
+
+#[test]
+fn test_verifying_key_fixture() {
+    let vk = setup::verifying_key();
+    assert_eq!(vk.alpha_g1, verifying_key::ALPHA_G1);
+    assert_eq!(vk.beta_g2, verifying_key::BETA_G2);
+    assert_eq!(vk.gamma_g2, verifying_key::GAMMA_G2);
+    assert_eq!(vk.delta_g2, verifying_key::DELTA_G2);
+    assert_eq!(vk.ic, verifying_key::IC);
+}
+
+#[test]
+fn test_proof_fixture() {
+    let proof = prove_fixture();
+    assert_eq!(proof.a, PROOF_A);
+    assert_eq!(proof.b, PROOF_B);
+    assert_eq!(proof.c, PROOF_C);
+
+    let public_inputs = [key_to_public_input(&OWNER.to_bytes()), VALUE];
+    assert!(verify_on_host(&proof_fixture(), &public_inputs));
+
+    // The proof doesn't hold for other public inputs.
+    let mut other_value = VALUE;
+    other_value[31] += 1;
+    assert!(!verify_on_host(
+        &proof_fixture(),
+        &[public_inputs[0], other_value]
+    ));
+    let other_owner = key_to_public_input(&Pubkey::new_unique().to_bytes());
+    assert!(!verify_on_host(&proof_fixture(), &[other_owner, VALUE]));
+
+    // Nor for another proof.
+    let mut proof = proof_fixture();
+    proof.c = PROOF_A;
+    assert!(!verify_on_host(&proof, &public_inputs));
+}
+
+#[test]
+fn test_negate_g1() {
+    let negated = negate_g1(&PROOF_A).unwrap();
+    assert_eq!(negated[..32], PROOF_A[..32]);
+    let sum = alt_bn128_addition(&[PROOF_A, negated].concat()).unwrap();
+    assert_eq!(sum, [0; 64]);
+
+    assert_eq!(negate_g1(&[0; 64]).unwrap(), [0; 64]);
+
+    // `y` has to be a canonical field element.
+    let mut point = PROOF_A;
+    point[32..].fill(0xff);
+    assert_eq!(negate_g1(&point), Err(Groth16Error::InvalidProof.into()));
+}
+
+#[test]
+fn test_public_input_out_of_range() {
+    // `VALUE + r` would have the same effect as `VALUE` in the
+    // multiplication, so it's rejected before.
+    let mut value = SCALAR_FIELD_MODULUS;
+    value[31] += VALUE[31];
+    assert_eq!(
+        prepare_inputs(&[key_to_public_input(&OWNER.to_bytes()), value]),
+        Err(Groth16Error::PublicInputOutOfRange.into())
+    );
+}
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: Groth16Instruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<Groth16Instruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn instruction_verify(
+    owner: &Pubkey,
+    verification: &Pubkey,
+    data: &VerifyInstructionData,
+) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*verification, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(Groth16Instruction::Verify, data),
+        ix_accounts,
+    )
+}
+
+fn accounts(owner: &Pubkey, verification: &Pubkey) -> Vec<(Pubkey, Account)> {
+    vec![
+        (
+            *owner,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        ),
+        (*verification, Account::default()),
+        keyed_account_for_system_program(),
+    ]
+}
+
+#[test]
+fn test_groth16_verify() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/groth16");
+
+    let (verification, bump) =
+        Pubkey::find_program_address(&[VERIFICATION_SEED.as_bytes(), OWNER.as_ref()], &ID);
+
+    mollusk.process_and_validate_instruction(
+        &instruction_verify(
+            &OWNER,
+            &verification,
+            &VerifyInstructionData::new(proof_fixture(), VALUE, bump),
+        ),
+        &accounts(&OWNER, &verification),
+        &[
+            Check::success(),
+            Check::account(&verification)
+                .owner(&ID)
+                .space(Verification::LEN)
+                .data_slice(0, OWNER.as_ref())
+                .data_slice(32, &VALUE)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_groth16_verify_invalid() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/groth16");
+
+    // The proof is bound to its owner, nobody else can submit it.
+    let attacker = Pubkey::new_unique();
+    let (verification, bump) =
+        Pubkey::find_program_address(&[VERIFICATION_SEED.as_bytes(), attacker.as_ref()], &ID);
+    mollusk.process_and_validate_instruction(
+        &instruction_verify(
+            &attacker,
+            &verification,
+            &VerifyInstructionData::new(proof_fixture(), VALUE, bump),
+        ),
+        &accounts(&attacker, &verification),
+        &[Check::err(ProgramError::Custom(INVALID_PROOF))],
+    );
+
+    // The value is bound to the proof as well.
+    let (verification, bump) =
+        Pubkey::find_program_address(&[VERIFICATION_SEED.as_bytes(), OWNER.as_ref()], &ID);
+    let mut other_value = VALUE;
+    other_value[31] += 1;
+    mollusk.process_and_validate_instruction(
+        &instruction_verify(
+            &OWNER,
+            &verification,
+            &VerifyInstructionData::new(proof_fixture(), other_value, bump),
+        ),
+        &accounts(&OWNER, &verification),
+        &[Check::err(ProgramError::Custom(INVALID_PROOF))],
+    );
+
+    // Points which are not on the curve are rejected by the syscalls.
+    let mut proof = proof_fixture();
+    proof.a[63] ^= 1;
+    mollusk.process_and_validate_instruction(
+        &instruction_verify(
+            &OWNER,
+            &verification,
+            &VerifyInstructionData::new(proof, VALUE, bump),
+        ),
+        &accounts(&OWNER, &verification),
+        &[Check::err(ProgramError::Custom(INVALID_PROOF))],
+    );
+}
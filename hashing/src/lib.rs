@@ -0,0 +1,153 @@
+#![no_std]
+
+use common::info;
+use pinocchio::{
+    account_info::AccountInfo, cpi::set_return_data, log::sol_log_data,
+    program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("7WQYvBq2ZQYUjUNuaeT29J6SaLhpmYj7HAVbp7JF8jbB");
+
+/// Length of the digests of all supported hash functions.
+pub const HASH_LEN: usize = 32;
+
+/// Hashing program instruction discriminators.
+///
+/// Every instruction hashes the rest of the instruction data, logs the
+/// digest with `sol_log_data` and sets it as return data.
+#[repr(u8)]
+pub enum HashingInstruction {
+    /// Hashes with SHA-256.
+    Sha256,
+    /// Hashes with Keccak-256, as used by Ethereum. Note that it differs
+    /// from the standardized SHA3-256 in padding.
+    Keccak256,
+    /// Hashes with BLAKE3.
+    Blake3,
+    /// Hashes with all of the above, returning the digests concatenated in
+    /// the same order.
+    All,
+}
+
+impl TryFrom<&u8> for HashingInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Sha256),
+            1 => Ok(Self::Keccak256),
+            2 => Ok(Self::Blake3),
+            3 => Ok(Self::All),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = HashingInstruction::try_from(instruction)?;
+
+    let vals = [instruction_data];
+    match instruction {
+        HashingInstruction::Sha256 => emit(&sha256v(&vals)),
+        HashingInstruction::Keccak256 => emit(&keccak256v(&vals)),
+        HashingInstruction::Blake3 => emit(&blake3v(&vals)),
+        HashingInstruction::All => {
+            let mut digests = [0; 3 * HASH_LEN];
+            digests[..HASH_LEN].copy_from_slice(&sha256v(&vals));
+            digests[HASH_LEN..2 * HASH_LEN].copy_from_slice(&keccak256v(&vals));
+            digests[2 * HASH_LEN..].copy_from_slice(&blake3v(&vals));
+            emit(&digests)
+        }
+    }
+
+    info!("Hashed {} bytes", instruction_data.len());
+
+    Ok(())
+}
+
+/// Logs the digests and sets them as return data.
+fn emit(digests: &[u8]) {
+    sol_log_data(&[digests]);
+    set_return_data(digests);
+}
+
+/// Computes the SHA-256 hash of the concatenation of `vals` using the
+/// `sol_sha256` syscall.
+pub fn sha256v(vals: &[&[u8]]) -> [u8; HASH_LEN] {
+    let mut hash = [0; HASH_LEN];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        );
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((vals, &mut hash));
+
+    hash
+}
+
+/// Computes the Keccak-256 hash of the concatenation of `vals` using the
+/// `sol_keccak256` syscall.
+pub fn keccak256v(vals: &[&[u8]]) -> [u8; HASH_LEN] {
+    let mut hash = [0; HASH_LEN];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_keccak256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        );
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((vals, &mut hash));
+
+    hash
+}
+
+/// Computes the BLAKE3 hash of the concatenation of `vals` using the
+/// `sol_blake3` syscall.
+///
+/// The syscall is behind a feature gate which isn't active on all clusters.
+/// Where it's inactive, programs using it fail to load.
+pub fn blake3v(vals: &[&[u8]]) -> [u8; HASH_LEN] {
+    let mut hash = [0; HASH_LEN];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_blake3(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        );
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((vals, &mut hash));
+
+    hash
+}
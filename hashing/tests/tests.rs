@@ -0,0 +1,95 @@
+use hashing::HashingInstruction;
+use mollusk_svm::{result::Check, Mollusk};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+use solana_instruction::Instruction;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(hashing::ID);
+
+/// Inputs covering the empty input, a single block and multiple blocks of
+/// every hash function, including more than one 1 KiB BLAKE3 chunk.
+const INPUTS: &[&[u8]] = &[b"", b"pinocchio", &[0xab; 200], &[0x01; 1100]];
+
+fn instruction_hash(instruction: HashingInstruction, input: &[u8]) -> Instruction {
+    let mut data = vec![instruction as u8];
+    data.extend_from_slice(input);
+    Instruction::new_with_bytes(ID, &data, vec![])
+}
+
+fn sha256(input: &[u8]) -> [u8; 32] {
+    Sha256::digest(input).into()
+}
+
+fn keccak256(input: &[u8]) -> [u8; 32] {
+    Keccak256::digest(input).into()
+}
+
+fn blake3(input: &[u8]) -> [u8; 32] {
+    blake3::hash(input).into()
+}
+
+#[test]
+fn test_hashing_sha256() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/hashing");
+
+    for input in INPUTS {
+        mollusk.process_and_validate_instruction(
+            &instruction_hash(HashingInstruction::Sha256, input),
+            &[],
+            &[Check::success(), Check::return_data(&sha256(input))],
+        );
+    }
+}
+
+#[test]
+fn test_hashing_keccak256() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/hashing");
+
+    for input in INPUTS {
+        mollusk.process_and_validate_instruction(
+            &instruction_hash(HashingInstruction::Keccak256, input),
+            &[],
+            &[Check::success(), Check::return_data(&keccak256(input))],
+        );
+    }
+}
+
+#[test]
+fn test_hashing_blake3() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/hashing");
+
+    for input in INPUTS {
+        mollusk.process_and_validate_instruction(
+            &instruction_hash(HashingInstruction::Blake3, input),
+            &[],
+            &[Check::success(), Check::return_data(&blake3(input))],
+        );
+    }
+}
+
+#[test]
+fn test_hashing_all() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/hashing");
+
+    for input in INPUTS {
+        let expected = [sha256(input), keccak256(input), blake3(input)].concat();
+        mollusk.process_and_validate_instruction(
+            &instruction_hash(HashingInstruction::All, input),
+            &[],
+            &[Check::success(), Check::return_data(&expected)],
+        );
+    }
+}
+
+#[test]
+fn test_hashing_invalid_instruction() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/hashing");
+
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(ID, &[4], vec![]),
+        &[],
+        &[Check::err(ProgramError::InvalidInstructionData)],
+    );
+}
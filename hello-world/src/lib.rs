@@ -1,18 +1,40 @@
 #![no_std]
 
-use pinocchio::{
-    entrypoint::InstructionContext, lazy_program_entrypoint, no_allocator, nostd_panic_handler,
-    ProgramResult,
-};
+use common::checks::require_program_id;
+use pinocchio::{entrypoint::InstructionContext, ProgramResult};
+#[cfg(target_os = "solana")]
+use pinocchio::{lazy_program_entrypoint, no_allocator, nostd_panic_handler};
 use pinocchio_log::log;
+use shank::ShankInstruction;
 
+#[cfg(target_os = "solana")]
 lazy_program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
 no_allocator!();
+#[cfg(target_os = "solana")]
 nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
 
 pinocchio_pubkey::declare_id!("CYfPbdyLefX3mmAQJfiarrUWjERYLS7iTTqeGTgoxWr2");
 
-pub fn process_instruction(_context: InstructionContext) -> ProgramResult {
+/// Hello world program instructions. The program ignores instruction data,
+/// so this only describes its interface.
+#[derive(ShankInstruction)]
+#[repr(u8)]
+pub enum HelloWorldInstruction {
+    /// Logs "Hello, world!".
+    Hello,
+}
+
+pub fn process_instruction(mut context: InstructionContext) -> ProgramResult {
+    // Skip the accounts, which the program ignores, to get to its address.
+    while context.remaining() > 0 {
+        context.next_account()?;
+    }
+    // Check that the program runs under its declared address.
+    require_program_id(context.program_id()?, &ID)?;
+
     log!("Hello, world!");
     Ok(())
 }
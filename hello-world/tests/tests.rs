@@ -1,9 +1,14 @@
+use std::{fs, path::Path};
+
 use mollusk_svm::{
     result::{Check, ProgramResult},
     Mollusk,
 };
+use shank_idl::{extract_idl, ParseIdlOpts};
 use solana_instruction::Instruction;
+use solana_program_error::ProgramError;
 use solana_pubkey::Pubkey;
+use test_utils::bench::Bencher;
 
 const ID: Pubkey = Pubkey::new_from_array(hello_world::ID);
 
@@ -21,3 +26,54 @@ fn test_hello_world() {
     );
     assert!(matches!(res.program_result, ProgramResult::Success));
 }
+
+/// A copy of the program deployed at another address refuses to run.
+#[test]
+fn test_redeployed_program() {
+    let redeployed = Pubkey::new_unique();
+    let mollusk = Mollusk::new(&redeployed, "target/deploy/hello_world");
+
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(redeployed, &[], Vec::new()),
+        &[],
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+}
+
+/// Measures the compute units of the instruction into `benches/`.
+#[test]
+fn test_compute_units() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/hello_world");
+    let mut bencher = Bencher::new(&mollusk, "benches");
+
+    bencher.bench(
+        "hello",
+        &Instruction::new_with_bytes(ID, &[], Vec::new()),
+        &[],
+    );
+    bencher.execute();
+}
+
+/// Extracts the IDL of the program and emits it into `idl/hello_world.json`.
+#[test]
+fn test_idl() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let idl = extract_idl(
+        manifest_dir.join("src/lib.rs").to_str().unwrap(),
+        ParseIdlOpts {
+            program_address_override: Some(ID.to_string()),
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .expect("the program should have an IDL");
+    assert_eq!(idl.instructions.len(), 1);
+
+    let idl_dir = manifest_dir.join("idl");
+    fs::create_dir_all(&idl_dir).unwrap();
+    fs::write(
+        idl_dir.join("hello_world.json"),
+        idl.try_into_json().unwrap(),
+    )
+    .unwrap();
+}
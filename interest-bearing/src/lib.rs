@@ -0,0 +1,349 @@
+//! Interest-bearing tokens with the token-2022 program.
+//!
+//! The program creates a mint with the interest-bearing extension, whose
+//! rate is managed by a PDA, and converts raw amounts to UI amounts
+//! on-chain.
+//!
+//! The interest is purely cosmetic. Balances never change, the token
+//! program only scales the raw amount by the interest accrued since the
+//! mint was initialized when converting it to a UI amount. Programs which
+//! need the accrued value have to ask the token program for it, like
+//! `AmountToUiAmount` does here.
+
+#![no_std]
+
+use core::str;
+
+use common::{checks::require_signer, debug, info, keys_eq};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::{get_return_data, set_return_data},
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::token_2022::{
+    AmountToUiAmount, InitializeInterestBearingMint, InitializeMint2, MintTo, UpdateRate,
+    MINT_WITH_INTEREST_BEARING_CONFIG_LEN,
+};
+
+pub mod token_2022;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("5YtyLehNvLFEHcZPokT9FoA3QB8UYdYSYVxQC8uDbN3Q");
+
+pub const RATE_AUTHORITY_SEED: &str = "rate_authority";
+
+/// Interest-bearing program instruction discriminators.
+#[repr(u8)]
+pub enum InterestBearingInstruction {
+    /// Creates an interest-bearing mint.
+    InitializeMint,
+    /// Mints tokens to an account.
+    MintTo,
+    /// Updates the interest rate.
+    UpdateRate,
+    /// Converts a raw amount to a UI amount, including the accrued interest,
+    /// and sets it as return data.
+    AmountToUiAmount,
+}
+
+impl TryFrom<&u8> for InterestBearingInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeMint),
+            1 => Ok(Self::MintTo),
+            2 => Ok(Self::UpdateRate),
+            3 => Ok(Self::AmountToUiAmount),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeMintInstructionData {
+    /// Interest rate in basis points.
+    pub rate: i16,
+    pub decimals: u8,
+    pub bump: u8,
+}
+
+impl InitializeMintInstructionData {
+    pub fn new(rate: i16, decimals: u8, bump: u8) -> Self {
+        Self {
+            rate,
+            decimals,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct MintToInstructionData {
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl MintToInstructionData {
+    pub fn new(amount: u64, bump: u8) -> Self {
+        Self { amount, bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct UpdateRateInstructionData {
+    /// Interest rate in basis points.
+    pub rate: i16,
+    pub bump: u8,
+}
+
+impl UpdateRateInstructionData {
+    pub fn new(rate: i16, bump: u8) -> Self {
+        Self { rate, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AmountToUiAmountInstructionData {
+    pub amount: u64,
+}
+
+impl AmountToUiAmountInstructionData {
+    pub fn new(amount: u64) -> Self {
+        Self { amount }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = InterestBearingInstruction::try_from(instruction)?;
+
+    match instruction {
+        InterestBearingInstruction::InitializeMint => {
+            process_initialize_mint(accounts, instruction_data)
+        }
+        InterestBearingInstruction::MintTo => process_mint_to(accounts, instruction_data),
+        InterestBearingInstruction::UpdateRate => process_update_rate(accounts, instruction_data),
+        InterestBearingInstruction::AmountToUiAmount => {
+            process_amount_to_ui_amount(accounts, instruction_data)
+        }
+    }
+}
+
+/// Creates an interest-bearing mint. The rate authority PDA of `admin` is
+/// both the rate authority and the mint authority.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[WRITE, SIGNER]` Mint
+///   2. `[]` Rate authority PDA
+///   3. `[]` System program
+///   4. `[]` Token-2022 program
+pub fn process_initialize_mint(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, mint, rate_authority, _system_program, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeMintInstructionData::from_bytes(instruction_data)?;
+
+    validate_rate_authority(admin, mint, rate_authority, instruction_data.bump)?;
+
+    CreateAccount {
+        from: admin,
+        to: mint,
+        lamports: Rent::get()?.minimum_balance(MINT_WITH_INTEREST_BEARING_CONFIG_LEN),
+        space: MINT_WITH_INTEREST_BEARING_CONFIG_LEN as u64,
+        owner: &token_2022::ID,
+    }
+    .invoke()?;
+
+    // Extensions have to be initialized before the mint itself.
+    InitializeInterestBearingMint {
+        mint,
+        rate_authority: Some(rate_authority.key()),
+        rate: instruction_data.rate,
+    }
+    .invoke()?;
+
+    InitializeMint2 {
+        mint,
+        decimals: instruction_data.decimals,
+        mint_authority: rate_authority.key(),
+        freeze_authority: None,
+    }
+    .invoke()?;
+
+    info!("Initialized an interest-bearing mint");
+
+    Ok(())
+}
+
+/// Mints `amount` tokens to `token_account`.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Admin
+///   1. `[WRITE]` Mint
+///   2. `[WRITE]` Token account
+///   3. `[]` Rate authority PDA
+///   4. `[]` Token-2022 program
+pub fn process_mint_to(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, mint, token_account, rate_authority, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = MintToInstructionData::from_bytes(instruction_data)?;
+
+    validate_rate_authority(admin, mint, rate_authority, instruction_data.bump)?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(RATE_AUTHORITY_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(admin.key()),
+        Seed::from(&bump),
+    ];
+    MintTo {
+        mint,
+        account: token_account,
+        mint_authority: rate_authority,
+        amount: instruction_data.amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Minted {} tokens", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Updates the interest rate of `mint`. The interest accrued so far is
+/// kept, the new rate only applies from now on.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Admin
+///   1. `[WRITE]` Mint
+///   2. `[]` Rate authority PDA
+///   3. `[]` Token-2022 program
+pub fn process_update_rate(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, mint, rate_authority, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = UpdateRateInstructionData::from_bytes(instruction_data)?;
+
+    validate_rate_authority(admin, mint, rate_authority, instruction_data.bump)?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(RATE_AUTHORITY_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(admin.key()),
+        Seed::from(&bump),
+    ];
+    UpdateRate {
+        mint,
+        rate_authority,
+        rate: instruction_data.rate,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Updated the rate to {} bps", instruction_data.rate);
+
+    Ok(())
+}
+
+/// Converts `amount` to a UI amount of `mint` with the token program, logs
+/// it and sets it as return data.
+///
+/// ### Accounts:
+///   0. `[]` Mint
+///   1. `[]` Token-2022 program
+pub fn process_amount_to_ui_amount(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [mint, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = AmountToUiAmountInstructionData::from_bytes(instruction_data)?;
+
+    AmountToUiAmount {
+        mint,
+        amount: instruction_data.amount,
+    }
+    .invoke()?;
+
+    let return_data = get_return_data().ok_or(ProgramError::InvalidAccountData)?;
+    if return_data.program_id() != &token_2022::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let ui_amount =
+        str::from_utf8(return_data.as_slice()).map_err(|_| ProgramError::InvalidAccountData)?;
+
+    debug!("{} is {}", instruction_data.amount, ui_amount);
+    set_return_data(ui_amount.as_bytes());
+
+    Ok(())
+}
+
+/// Checks that `rate_authority` is the rate authority PDA of `admin` for
+/// `mint`.
+fn validate_rate_authority(
+    admin: &AccountInfo,
+    mint: &AccountInfo,
+    rate_authority: &AccountInfo,
+    bump: u8,
+) -> ProgramResult {
+    let rate_authority_pda = create_program_address(
+        &[
+            RATE_AUTHORITY_SEED.as_bytes(),
+            mint.key(),
+            admin.key(),
+            &[bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(rate_authority.key(), &rate_authority_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
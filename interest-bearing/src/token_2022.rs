@@ -0,0 +1,284 @@
+//! Instructions and account layout of the token-2022 program.
+//!
+//! `pinocchio-token` only supports the original token program, so the CPIs
+//! are built by hand, the same way `pinocchio-token` does it.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+/// Token-2022 program ID.
+pub const ID: Pubkey = pinocchio_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Size of a token account without extensions. Mints with extensions are
+/// padded to the same size, so the account type byte and the extensions
+/// start at the same offset in both.
+pub const ACCOUNT_LEN: usize = 165;
+
+/// Offset of the account type byte, followed by the extensions. Every
+/// extension is encoded as its type (`u16`), length (`u16`) and value.
+pub const ACCOUNT_TYPE_OFFSET: usize = ACCOUNT_LEN;
+pub const EXTENSIONS_OFFSET: usize = ACCOUNT_TYPE_OFFSET + 1;
+pub const EXTENSION_HEADER_LEN: usize = 4;
+
+/// `ExtensionType::InterestBearingConfig`.
+pub const INTEREST_BEARING_CONFIG: u16 = 10;
+
+/// Size of the `InterestBearingConfig` extension.
+pub const INTEREST_BEARING_CONFIG_LEN: usize = 52;
+
+/// Size of a mint with the `InterestBearingConfig` extension.
+pub const MINT_WITH_INTEREST_BEARING_CONFIG_LEN: usize =
+    EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN + INTEREST_BEARING_CONFIG_LEN;
+
+/// `TokenInstruction::InterestBearingMintExtension`.
+const INTEREST_BEARING_MINT_EXTENSION: u8 = 33;
+
+/// Initialize the `InterestBearingConfig` extension of a mint. Has to
+/// precede `InitializeMint2`.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializeInterestBearingMint<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Authority allowed to update the rate.
+    pub rate_authority: Option<&'a Pubkey>,
+
+    /// Interest rate in basis points, compounded continuously.
+    pub rate: i16,
+}
+
+impl InitializeInterestBearingMint<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0    ]: extension discriminator
+        // -  [1    ]: instruction discriminator
+        // -  [2..34 ]: rate authority, zeros if none
+        // -  [34..36]: rate
+        let mut instruction_data = [0; 36];
+        instruction_data[0] = INTEREST_BEARING_MINT_EXTENSION;
+        if let Some(rate_authority) = self.rate_authority {
+            instruction_data[2..34].copy_from_slice(rate_authority);
+        }
+        instruction_data[34..36].copy_from_slice(&self.rate.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Update the interest rate of a mint.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Mint
+///   1. `[SIGNER]` Rate authority
+pub struct UpdateRate<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Rate authority.
+    pub rate_authority: &'a AccountInfo,
+
+    /// New interest rate in basis points.
+    pub rate: i16,
+}
+
+impl UpdateRate<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 2] = [
+            AccountMeta::writable(self.mint.key()),
+            AccountMeta::readonly_signer(self.rate_authority.key()),
+        ];
+
+        // instruction data
+        // -  [0   ]: extension discriminator
+        // -  [1   ]: instruction discriminator
+        // -  [2..4]: rate
+        let mut instruction_data = [0; 4];
+        instruction_data[0] = INTEREST_BEARING_MINT_EXTENSION;
+        instruction_data[1] = 1;
+        instruction_data[2..4].copy_from_slice(&self.rate.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint, self.rate_authority], signers)
+    }
+}
+
+/// Initialize a mint, without requiring the rent sysvar.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializeMint2<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Decimals.
+    pub decimals: u8,
+
+    /// Mint authority.
+    pub mint_authority: &'a Pubkey,
+
+    /// Freeze authority.
+    pub freeze_authority: Option<&'a Pubkey>,
+}
+
+impl InitializeMint2<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1   ]: decimals
+        // -  [2..34]: mint authority
+        // -  [34  ]: freeze authority presence flag
+        // -  [35..67]: freeze authority
+        let mut instruction_data = [0; 67];
+        instruction_data[0] = 20;
+        instruction_data[1] = self.decimals;
+        instruction_data[2..34].copy_from_slice(self.mint_authority);
+        if let Some(freeze_authority) = self.freeze_authority {
+            instruction_data[34] = 1;
+            instruction_data[35..67].copy_from_slice(freeze_authority);
+        }
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Mint new tokens to an account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Mint
+///   1. `[WRITE]` Token account to mint to
+///   2. `[SIGNER]` Mint authority
+pub struct MintTo<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Mint authority.
+    pub mint_authority: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+}
+
+impl MintTo<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.mint.key()),
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly_signer(self.mint_authority.key()),
+        ];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1..9]: amount
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 7;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.mint, self.account, self.mint_authority],
+            signers,
+        )
+    }
+}
+
+/// Convert a raw amount to its UI representation, including the accrued
+/// interest. The token program sets the UI amount as return data, as an
+/// UTF-8 string.
+///
+/// ### Accounts:
+///   0. `[]` Mint
+pub struct AmountToUiAmount<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Raw amount.
+    pub amount: u64,
+}
+
+impl AmountToUiAmount<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::readonly(self.mint.key())];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1..9]: amount
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 23;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
@@ -0,0 +1,285 @@
+//! Attacks on the interest-bearing program, each rejected with its own
+//! error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use interest_bearing::{
+    token_2022::{
+        ACCOUNT_LEN, ACCOUNT_TYPE_OFFSET, EXTENSIONS_OFFSET, EXTENSION_HEADER_LEN,
+        INTEREST_BEARING_CONFIG, INTEREST_BEARING_CONFIG_LEN,
+        MINT_WITH_INTEREST_BEARING_CONFIG_LEN,
+    },
+    AmountToUiAmountInstructionData, InterestBearingInstruction, MintToInstructionData,
+    UpdateRateInstructionData, RATE_AUTHORITY_SEED,
+};
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(interest_bearing::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(interest_bearing::token_2022::ID);
+
+/// `TokenError::OwnerMismatch`.
+const OWNER_MISMATCH: u32 = 4;
+
+const DECIMALS: u8 = 6;
+/// 5% a year.
+const RATE: i16 = 500;
+const NOW: i64 = 1_000_000;
+
+/// Offset of the value of the first extension.
+const EXTENSION_OFFSET: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN;
+/// Offsets of the `InterestBearingConfig` fields.
+const INITIALIZATION_TIMESTAMP_OFFSET: usize = EXTENSION_OFFSET + 32;
+const PRE_UPDATE_AVERAGE_RATE_OFFSET: usize = EXTENSION_OFFSET + 40;
+const LAST_UPDATE_TIMESTAMP_OFFSET: usize = EXTENSION_OFFSET + 42;
+const CURRENT_RATE_OFFSET: usize = EXTENSION_OFFSET + 50;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: InterestBearingInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator =
+        vec![0; mem::size_of::<InterestBearingInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the rate authority PDA of `admin` for `mint` and its bump.
+fn rate_authority_pda(mint: &Pubkey, admin: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            RATE_AUTHORITY_SEED.as_bytes(),
+            mint.as_ref(),
+            admin.as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// Returns an interest-bearing mint initialized at [`NOW`] with `rate`.
+fn mint_account(mollusk: &Mollusk, rate_authority: &Pubkey, rate: i16) -> Account {
+    let mut data = vec![0; MINT_WITH_INTEREST_BEARING_CONFIG_LEN];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(rate_authority.as_ref());
+    data[44] = DECIMALS;
+    data[45] = 1;
+    data[ACCOUNT_TYPE_OFFSET] = 1;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&INTEREST_BEARING_CONFIG.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..EXTENSION_OFFSET]
+        .copy_from_slice(&(INTEREST_BEARING_CONFIG_LEN as u16).to_le_bytes());
+    data[EXTENSION_OFFSET..EXTENSION_OFFSET + 32].copy_from_slice(rate_authority.as_ref());
+    data[INITIALIZATION_TIMESTAMP_OFFSET..INITIALIZATION_TIMESTAMP_OFFSET + 8]
+        .copy_from_slice(&NOW.to_le_bytes());
+    data[PRE_UPDATE_AVERAGE_RATE_OFFSET..PRE_UPDATE_AVERAGE_RATE_OFFSET + 2]
+        .copy_from_slice(&rate.to_le_bytes());
+    data[LAST_UPDATE_TIMESTAMP_OFFSET..LAST_UPDATE_TIMESTAMP_OFFSET + 8]
+        .copy_from_slice(&NOW.to_le_bytes());
+    data[CURRENT_RATE_OFFSET..CURRENT_RATE_OFFSET + 2].copy_from_slice(&rate.to_le_bytes());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Accounts of an interest-bearing mint of an admin, and of an attacker
+/// with a token account of the mint and their own rate authority PDA for
+/// it.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    mint: Pubkey,
+    rate_authority: Pubkey,
+    rate_authority_bump: u8,
+    attacker: Pubkey,
+    attacker_token_account: Pubkey,
+    attacker_rate_authority: Pubkey,
+    attacker_rate_authority_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/interest_bearing");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let admin = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (rate_authority, rate_authority_bump) = rate_authority_pda(&mint, &admin);
+        let attacker = Pubkey::new_unique();
+        let attacker_token_account = Pubkey::new_unique();
+        let (attacker_rate_authority, attacker_rate_authority_bump) =
+            rate_authority_pda(&mint, &attacker);
+
+        let mut token_account = Account::new(
+            mollusk.sysvars.rent.minimum_balance(ACCOUNT_LEN),
+            ACCOUNT_LEN,
+            &TOKEN_ID,
+        );
+        token_account.data[0..32].copy_from_slice(mint.as_ref());
+        token_account.data[32..64].copy_from_slice(attacker.as_ref());
+        // Initialized.
+        token_account.data[108] = 1;
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &rate_authority, RATE)),
+            (rate_authority, Account::default()),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (attacker_token_account, token_account),
+            (attacker_rate_authority, Account::default()),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            mint,
+            rate_authority,
+            rate_authority_bump,
+            attacker,
+            attacker_token_account,
+            attacker_rate_authority,
+            attacker_rate_authority_bump,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Mints tokens to the attacker through the rate authority PDA of the
+    /// attacker, signed by the attacker.
+    fn attacker_mint_to(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.attacker, true),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.attacker_token_account, false),
+            AccountMeta::new_readonly(self.attacker_rate_authority, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                InterestBearingInstruction::MintTo,
+                &MintToInstructionData::new(1_000, self.attacker_rate_authority_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Sets the rate to the maximum through the rate authority PDA of
+    /// `admin`, signed by `admin`.
+    fn instruction_update_rate(
+        &self,
+        admin: &Pubkey,
+        rate_authority: &Pubkey,
+        bump: u8,
+    ) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(*rate_authority, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                InterestBearingInstruction::UpdateRate,
+                &UpdateRateInstructionData::new(i16::MAX, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_admin_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_update_rate(
+        &setup.admin,
+        &setup.rate_authority,
+        setup.rate_authority_bump,
+    );
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker updates the rate with their own rate authority PDA for the
+/// mint, which the program signs for, but which is not the rate authority
+/// of the mint.
+#[test]
+fn test_update_rate_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_update_rate(
+            &setup.attacker,
+            &setup.attacker_rate_authority,
+            setup.attacker_rate_authority_bump,
+        ),
+        ProgramError::Custom(OWNER_MISMATCH),
+    );
+}
+
+/// The attacker mints to themselves with their own rate authority PDA for
+/// the mint.
+#[test]
+fn test_mint_to_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_mint_to(),
+        ProgramError::Custom(OWNER_MISMATCH),
+    );
+}
+
+/// A mint with the layout of the token program and a made up rate, but
+/// owned by another program, whose UI amounts the program would report.
+#[test]
+fn test_spoofed_mint() {
+    let setup = Setup::new();
+    let mint = Pubkey::new_unique();
+    let mut mint_account = mint_account(&setup.mollusk, &setup.rate_authority, i16::MAX);
+    mint_account.owner = Pubkey::new_unique();
+    let setup = setup.with(mint, mint_account);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(mint, false),
+        AccountMeta::new_readonly(TOKEN_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            InterestBearingInstruction::AmountToUiAmount,
+            &AmountToUiAmountInstructionData::new(1_000),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+}
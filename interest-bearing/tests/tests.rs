@@ -0,0 +1,342 @@
+//! The token-2022 program is loaded from `third-party/spl_token_2022.so`,
+//! which can be dumped from mainnet with:
+//!
+//! ```sh
+//! solana program dump -um TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb \
+//!     third-party/spl_token_2022.so
+//! ```
+
+use std::mem;
+
+use common::encoding::Encoding;
+use interest_bearing::{
+    token_2022::{
+        ACCOUNT_LEN, ACCOUNT_TYPE_OFFSET, EXTENSIONS_OFFSET, EXTENSION_HEADER_LEN,
+        INTEREST_BEARING_CONFIG, INTEREST_BEARING_CONFIG_LEN,
+        MINT_WITH_INTEREST_BEARING_CONFIG_LEN,
+    },
+    AmountToUiAmountInstructionData, InitializeMintInstructionData, InterestBearingInstruction,
+    MintToInstructionData, UpdateRateInstructionData, RATE_AUTHORITY_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(interest_bearing::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(interest_bearing::token_2022::ID);
+
+const DECIMALS: u8 = 6;
+/// 5% a year.
+const RATE: i16 = 500;
+const NOW: i64 = 1_000_000;
+/// Length of a year, as used by the token program.
+const SECONDS_PER_YEAR: i64 = 31_556_736;
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offset of the value of the first extension.
+const EXTENSION_OFFSET: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN;
+/// Offsets of the `InterestBearingConfig` fields.
+const INITIALIZATION_TIMESTAMP_OFFSET: usize = EXTENSION_OFFSET + 32;
+const PRE_UPDATE_AVERAGE_RATE_OFFSET: usize = EXTENSION_OFFSET + 40;
+const LAST_UPDATE_TIMESTAMP_OFFSET: usize = EXTENSION_OFFSET + 42;
+const CURRENT_RATE_OFFSET: usize = EXTENSION_OFFSET + 50;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: InterestBearingInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<InterestBearingInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/interest_bearing");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+    mollusk.sysvars.clock.unix_timestamp = NOW;
+    mollusk
+}
+
+/// Returns an interest-bearing mint initialized at [`NOW`] with [`RATE`],
+/// as created by `InitializeMint`.
+fn mint_account(mollusk: &Mollusk, rate_authority: &Pubkey) -> Account {
+    let mut data = vec![0; MINT_WITH_INTEREST_BEARING_CONFIG_LEN];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(rate_authority.as_ref());
+    data[44] = DECIMALS;
+    data[45] = 1;
+    data[ACCOUNT_TYPE_OFFSET] = 1;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&INTEREST_BEARING_CONFIG.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..EXTENSION_OFFSET]
+        .copy_from_slice(&(INTEREST_BEARING_CONFIG_LEN as u16).to_le_bytes());
+    data[EXTENSION_OFFSET..EXTENSION_OFFSET + 32].copy_from_slice(rate_authority.as_ref());
+    data[INITIALIZATION_TIMESTAMP_OFFSET..INITIALIZATION_TIMESTAMP_OFFSET + 8]
+        .copy_from_slice(&NOW.to_le_bytes());
+    data[PRE_UPDATE_AVERAGE_RATE_OFFSET..PRE_UPDATE_AVERAGE_RATE_OFFSET + 2]
+        .copy_from_slice(&RATE.to_le_bytes());
+    data[LAST_UPDATE_TIMESTAMP_OFFSET..LAST_UPDATE_TIMESTAMP_OFFSET + 8]
+        .copy_from_slice(&NOW.to_le_bytes());
+    data[CURRENT_RATE_OFFSET..CURRENT_RATE_OFFSET + 2].copy_from_slice(&RATE.to_le_bytes());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns a token account without extensions.
+fn empty_token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let mut data = vec![0; ACCOUNT_LEN];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    // Initialized.
+    data[108] = 1;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(ACCOUNT_LEN),
+        ACCOUNT_LEN,
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// An admin with an interest-bearing mint and a token account.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    mint: Pubkey,
+    rate_authority: Pubkey,
+    rate_authority_bump: u8,
+    token_account: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let admin = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (rate_authority, rate_authority_bump) = Pubkey::find_program_address(
+            &[
+                RATE_AUTHORITY_SEED.as_bytes(),
+                mint.as_ref(),
+                admin.as_ref(),
+            ],
+            &ID,
+        );
+        let token_account = Pubkey::new_unique();
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &rate_authority)),
+            (rate_authority, Account::default()),
+            (
+                token_account,
+                empty_token_account(&mollusk, &mint, &Pubkey::new_unique()),
+            ),
+            keyed_account_for_system_program(),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            mint,
+            rate_authority,
+            rate_authority_bump,
+            token_account,
+            accounts,
+        }
+    }
+
+    fn instruction_initialize_mint(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.mint, true),
+            AccountMeta::new_readonly(self.rate_authority, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                InterestBearingInstruction::InitializeMint,
+                &InitializeMintInstructionData::new(RATE, DECIMALS, self.rate_authority_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_mint_to(&self, amount: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.token_account, false),
+            AccountMeta::new_readonly(self.rate_authority, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                InterestBearingInstruction::MintTo,
+                &MintToInstructionData::new(amount, self.rate_authority_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_update_rate(&self, rate: i16) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(self.rate_authority, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                InterestBearingInstruction::UpdateRate,
+                &UpdateRateInstructionData::new(rate, self.rate_authority_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_amount_to_ui_amount(&self, amount: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                InterestBearingInstruction::AmountToUiAmount,
+                &AmountToUiAmountInstructionData::new(amount),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Converts `amount` to a UI amount with the program.
+    fn ui_amount(&self, amount: u64) -> f64 {
+        let res = self.mollusk.process_and_validate_instruction(
+            &self.instruction_amount_to_ui_amount(amount),
+            &self.accounts,
+            &[Check::success()],
+        );
+        String::from_utf8(res.return_data).unwrap().parse().unwrap()
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(MINT_WITH_INTEREST_BEARING_CONFIG_LEN, 222);
+    assert_eq!(InitializeMintInstructionData::LEN, 4);
+    assert_eq!(UpdateRateInstructionData::LEN, 4);
+}
+
+#[test]
+fn test_initialize_mint() {
+    let mut setup = Setup::new();
+    // The mint is created by the program.
+    setup.accounts[1].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_mint(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.mint)
+                .owner(&TOKEN_ID)
+                .space(MINT_WITH_INTEREST_BEARING_CONFIG_LEN)
+                .data_slice(4, setup.rate_authority.as_ref())
+                .data_slice(44, &[DECIMALS, 1])
+                .data_slice(ACCOUNT_TYPE_OFFSET, &[1])
+                .data_slice(EXTENSIONS_OFFSET, &INTEREST_BEARING_CONFIG.to_le_bytes())
+                .data_slice(EXTENSION_OFFSET, setup.rate_authority.as_ref())
+                .data_slice(INITIALIZATION_TIMESTAMP_OFFSET, &NOW.to_le_bytes())
+                .data_slice(CURRENT_RATE_OFFSET, &RATE.to_le_bytes())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_mint_and_update_rate() {
+    let mut setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_mint_to(1_000_000),
+                &[
+                    Check::success(),
+                    Check::account(&setup.token_account)
+                        .data_slice(AMOUNT_OFFSET, &1_000_000u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_update_rate(-RATE),
+                &[
+                    Check::success(),
+                    Check::account(&setup.mint)
+                        .data_slice(CURRENT_RATE_OFFSET, &(-RATE).to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+
+    // Nobody but the admin can update the rate.
+    let mut ix = setup.instruction_update_rate(RATE);
+    ix.accounts[0].pubkey = Pubkey::new_unique();
+    setup.accounts[0].0 = ix.accounts[0].pubkey;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
+
+#[test]
+fn test_amount_to_ui_amount() {
+    let mut setup = Setup::new();
+
+    // No interest accrued yet.
+    assert_eq!(setup.ui_amount(1_000_000), 1.0);
+
+    // The interest compounds continuously.
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + SECONDS_PER_YEAR;
+    let expected = (RATE as f64 / 10_000.0).exp();
+    assert!((setup.ui_amount(1_000_000) - expected).abs() < 1e-6);
+
+    let expected = 2.0 * (2.0 * RATE as f64 / 10_000.0).exp();
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + 2 * SECONDS_PER_YEAR;
+    assert!((setup.ui_amount(2_000_000) - expected).abs() < 1e-6);
+}
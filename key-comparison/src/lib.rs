@@ -0,0 +1,99 @@
+//! Program measuring the compute units of comparing keys.
+//!
+//! Programs compare keys all the time, e.g. a passed account with the PDA
+//! it should be or the owner stored in the state. `==` on the 32-byte
+//! arrays calls the `sol_memcmp_` syscall, while [`common::keys_eq`]
+//! compares them as four `u64` words. Both instructions do the same
+//! comparisons, one way each:
+//!
+//! * [`KeyComparisonInstruction::SliceEq`] compares with `==`.
+//! * [`KeyComparisonInstruction::KeysEq`] compares with
+//!   [`common::keys_eq`].
+//!
+//! They compare the key of the first account with the keys of all the
+//! accounts, [`CompareInstructionData::rounds`] times, and set the number
+//! of equal keys as return data. The difference of their compute units,
+//! divided by the number of comparisons, is the cost saved by every
+//! comparison.
+
+#![no_std]
+
+use core::hint::black_box;
+
+use common::keys_eq;
+use macros::{InstructionData, InstructionDiscriminator};
+use pinocchio::{
+    account_info::AccountInfo, cpi::set_return_data, program_error::ProgramError, pubkey::Pubkey,
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("6WzWHAsWp1g3QGSMZYYqpbG2qcNmnDGfVUsiCXJgXBVm");
+
+/// Key comparison program instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum KeyComparisonInstruction {
+    /// Compares the keys with `==`.
+    SliceEq,
+    /// Compares the keys with [`common::keys_eq`].
+    KeysEq,
+}
+
+/// Instruction data of both instructions.
+#[derive(Clone, Copy, InstructionData)]
+pub struct CompareInstructionData {
+    /// Number of times all the keys are compared.
+    pub rounds: u8,
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = KeyComparisonInstruction::split(instruction_data)?;
+    let rounds = CompareInstructionData::from_bytes(instruction_data)?.rounds;
+
+    let equal = match instruction {
+        KeyComparisonInstruction::SliceEq => count_equal(accounts, rounds, |a, b| a == b)?,
+        KeyComparisonInstruction::KeysEq => count_equal(accounts, rounds, keys_eq)?,
+    };
+    set_return_data(&equal.to_le_bytes());
+    Ok(())
+}
+
+/// Compares the key of the first account with the keys of all `accounts`
+/// with `eq`, `rounds` times, and returns the number of equal keys.
+#[inline(always)]
+fn count_equal(
+    accounts: &[AccountInfo],
+    rounds: u8,
+    eq: impl Fn(&Pubkey, &Pubkey) -> bool,
+) -> Result<u64, ProgramError> {
+    let [first, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let mut equal = 0;
+    for _ in 0..rounds {
+        for account in accounts {
+            // Keep the compiler from comparing the keys once for all
+            // rounds.
+            if eq(black_box(first.key()), black_box(account.key())) {
+                equal += 1;
+            }
+        }
+    }
+    Ok(equal)
+}
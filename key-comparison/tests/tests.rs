@@ -0,0 +1,75 @@
+use key_comparison::{CompareInstructionData, KeyComparisonInstruction};
+use mollusk_svm::{result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(key_comparison::ID);
+
+/// Number of accounts whose keys are compared.
+const ACCOUNTS: usize = 8;
+
+fn instruction(
+    instruction: KeyComparisonInstruction,
+    rounds: u8,
+    accounts: &[(Pubkey, Account)],
+) -> Instruction {
+    let mut data = instruction.discriminator().to_vec();
+    data.extend_from_slice(&CompareInstructionData { rounds }.to_bytes());
+    let metas = accounts
+        .iter()
+        .map(|(key, _)| AccountMeta::new_readonly(*key, false))
+        .collect();
+    Instruction::new_with_bytes(ID, &data, metas)
+}
+
+/// Returns the accounts to compare: the first one, one whose key only
+/// differs from it in the last byte and unrelated ones.
+fn accounts() -> Vec<(Pubkey, Account)> {
+    let first = Pubkey::new_unique();
+    let mut last_byte = first.to_bytes();
+    last_byte[31] ^= 1;
+
+    let mut keys = vec![first, Pubkey::new_from_array(last_byte)];
+    keys.resize_with(ACCOUNTS, Pubkey::new_unique);
+    keys.into_iter()
+        .map(|key| (key, Account::default()))
+        .collect()
+}
+
+#[test]
+fn test_compare() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/key_comparison");
+    let accounts = accounts();
+
+    for kind in [
+        KeyComparisonInstruction::SliceEq,
+        KeyComparisonInstruction::KeysEq,
+    ] {
+        // Only the first account equals itself, in every round.
+        mollusk.process_and_validate_instruction(
+            &instruction(kind, 3, &accounts),
+            &accounts,
+            &[Check::success(), Check::return_data(&3u64.to_le_bytes())],
+        );
+    }
+}
+
+/// Checks that comparing the words costs fewer compute units than `==`.
+#[test]
+fn test_compute_units() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/key_comparison");
+    let accounts = accounts();
+    let rounds = 16;
+
+    let slice_eq = mollusk.process_instruction(
+        &instruction(KeyComparisonInstruction::SliceEq, rounds, &accounts),
+        &accounts,
+    );
+    let keys_eq = mollusk.process_instruction(
+        &instruction(KeyComparisonInstruction::KeysEq, rounds, &accounts),
+        &accounts,
+    );
+    // Comparing the words saves the syscall of every comparison.
+    assert!(keys_eq.compute_units_consumed < slice_eq.compute_units_consumed);
+}
@@ -0,0 +1,127 @@
+//! Counter module, a counter per owner.
+
+use common::{assert_layout, info, keys_eq, AccountData};
+use errors::SharedError;
+use macros::{Accounts, InstructionData, InstructionDiscriminator};
+use pinocchio::{
+    account_info::AccountInfo, instruction::Seed, program_error::ProgramError, pubkey::Pubkey,
+    ProgramResult,
+};
+
+use crate::{
+    state::{create_state, AccountKind},
+    ID,
+};
+
+pub const COUNTER_SEED: &'static str = "counter";
+
+/// Counter module instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum CounterInstruction {
+    /// Creates the counter of the owner. Accounts: [`CreateAccounts`],
+    /// instruction data: [`CreateInstructionData`].
+    Create,
+    /// Increments a counter by one. Accounts: [`IncrementAccounts`], no
+    /// instruction data.
+    Increment,
+}
+
+/// Instruction data of [`CounterInstruction::Create`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct CreateInstructionData {
+    pub bump: u8,
+}
+
+/// Accounts of [`CounterInstruction::Create`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateAccounts<'a> {
+    /// Owner and payer.
+    #[signer]
+    pub owner: &'a AccountInfo,
+    /// Counter PDA of the owner.
+    #[pda(seeds = [COUNTER_SEED.as_bytes(), owner.key()], bump = bump)]
+    pub counter: &'a AccountInfo,
+    #[program = pinocchio_system::ID]
+    pub system_program: &'a AccountInfo,
+}
+
+/// Accounts of [`CounterInstruction::Increment`].
+#[derive(Accounts)]
+pub struct IncrementAccounts<'a> {
+    /// Owner of the counter.
+    #[signer]
+    pub owner: &'a AccountInfo,
+    /// Counter of the owner. Its seeds aren't checked, the stored owner is.
+    #[owner = ID]
+    pub counter: &'a AccountInfo,
+}
+
+/// On-chain representation of a counter.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Counter {
+    /// [`AccountKind::Counter`].
+    pub discriminator: u8,
+    pub _padding: [u8; 7],
+    pub owner: Pubkey,
+    pub count: u64,
+}
+
+unsafe impl AccountData for Counter {
+    const OWNER: Pubkey = ID;
+    const DISCRIMINATOR: Option<u8> = Some(AccountKind::Counter as u8);
+}
+
+assert_layout!(Counter, size = 48, align = 8, {
+    discriminator: 0,
+    _padding: 1,
+    owner: 8,
+    count: 40,
+});
+
+/// Processes an instruction of the module.
+pub fn process(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let (instruction, instruction_data) = CounterInstruction::split(instruction_data)?;
+    match instruction {
+        CounterInstruction::Create => {
+            let bump = CreateInstructionData::from_bytes(instruction_data)?.bump;
+            process_create(CreateAccounts::from_accounts(accounts, bump)?, bump)
+        }
+        CounterInstruction::Increment => {
+            if !instruction_data.is_empty() {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            process_increment(IncrementAccounts::from_accounts(accounts)?)
+        }
+    }
+}
+
+/// Creates the counter of the owner, starting at zero.
+pub fn process_create(accounts: CreateAccounts, bump: u8) -> ProgramResult {
+    let bump = [bump];
+    let seeds = [
+        Seed::from(COUNTER_SEED.as_bytes()),
+        Seed::from(accounts.owner.key()),
+        Seed::from(&bump),
+    ];
+    let mut counter = create_state::<Counter>(accounts.owner, accounts.counter, &seeds)?;
+    counter.owner = *accounts.owner.key();
+    counter.count = 0;
+
+    info!("Created the counter account");
+    Ok(())
+}
+
+/// Increments the counter of the owner by one.
+pub fn process_increment(accounts: IncrementAccounts) -> ProgramResult {
+    let mut counter = Counter::load_mut(accounts.counter)?;
+    if !keys_eq(&counter.owner, accounts.owner.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    counter.count = counter.count.checked_add(1).ok_or(SharedError::Overflow)?;
+
+    info!("Incremented the counter to {}", counter.count);
+    Ok(())
+}
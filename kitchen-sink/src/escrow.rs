@@ -0,0 +1,205 @@
+//! Escrow module, lamports deposited by a sender for a receiver.
+//!
+//! Unlike the `escrow` program, which escrows tokens, the deposit is held
+//! by the escrow PDA itself, on top of its rent.
+
+use common::{assert_layout, close::close_account, info, keys_eq, AccountData};
+use errors::SharedError;
+use macros::{Accounts, InstructionData, InstructionDiscriminator};
+use pinocchio::{
+    account_info::AccountInfo, instruction::Seed, program_error::ProgramError, pubkey::Pubkey,
+    ProgramResult,
+};
+use pinocchio_system::instructions::Transfer;
+
+use crate::{
+    state::{create_state, AccountKind},
+    ID,
+};
+
+pub const ESCROW_SEED: &'static str = "escrow";
+
+/// Escrow module instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum EscrowInstruction {
+    /// Deposits lamports for the receiver. Accounts: [`InitializeAccounts`],
+    /// instruction data: [`InitializeInstructionData`].
+    Initialize,
+    /// Moves the deposit to the receiver and the rent back to the sender.
+    /// Accounts: [`ExchangeAccounts`], no instruction data.
+    Exchange,
+    /// Moves the deposit and the rent back to the sender. Accounts:
+    /// [`CancelAccounts`], no instruction data.
+    Cancel,
+}
+
+/// Instruction data of [`EscrowInstruction::Initialize`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct InitializeInstructionData {
+    /// Deposited lamports.
+    pub amount: u64,
+    pub bump: u8,
+}
+
+/// Accounts of [`EscrowInstruction::Initialize`].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct InitializeAccounts<'a> {
+    /// Sender and payer of the deposit and the rent.
+    #[signer]
+    pub sender: &'a AccountInfo,
+    pub receiver: &'a AccountInfo,
+    /// Escrow PDA of the sender and the receiver.
+    #[pda(seeds = [ESCROW_SEED.as_bytes(), sender.key(), receiver.key()], bump = bump)]
+    pub escrow: &'a AccountInfo,
+    #[program = pinocchio_system::ID]
+    pub system_program: &'a AccountInfo,
+}
+
+/// Accounts of [`EscrowInstruction::Exchange`].
+#[derive(Accounts)]
+pub struct ExchangeAccounts<'a> {
+    /// Sender, receiving the rent.
+    pub sender: &'a AccountInfo,
+    /// Receiver, accepting the deposit.
+    #[signer]
+    pub receiver: &'a AccountInfo,
+    /// Escrow of the sender and the receiver. Its seeds aren't checked, the
+    /// stored sender and receiver are.
+    #[owner = ID]
+    pub escrow: &'a AccountInfo,
+}
+
+/// Accounts of [`EscrowInstruction::Cancel`].
+#[derive(Accounts)]
+pub struct CancelAccounts<'a> {
+    /// Sender, cancelling the escrow.
+    #[signer]
+    pub sender: &'a AccountInfo,
+    /// Escrow of the sender.
+    #[owner = ID]
+    pub escrow: &'a AccountInfo,
+}
+
+/// On-chain representation of an escrow.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Escrow {
+    /// [`AccountKind::Escrow`].
+    pub discriminator: u8,
+    pub _padding: [u8; 7],
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    /// Deposited lamports, on top of the rent.
+    pub amount: u64,
+}
+
+unsafe impl AccountData for Escrow {
+    const OWNER: Pubkey = ID;
+    const DISCRIMINATOR: Option<u8> = Some(AccountKind::Escrow as u8);
+}
+
+assert_layout!(Escrow, size = 80, align = 8, {
+    discriminator: 0,
+    _padding: 1,
+    sender: 8,
+    receiver: 40,
+    amount: 72,
+});
+
+/// Processes an instruction of the module.
+pub fn process(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let (instruction, instruction_data) = EscrowInstruction::split(instruction_data)?;
+    if !matches!(instruction, EscrowInstruction::Initialize) && !instruction_data.is_empty() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    match instruction {
+        EscrowInstruction::Initialize => {
+            let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+            process_initialize(
+                InitializeAccounts::from_accounts(accounts, instruction_data.bump)?,
+                instruction_data.amount,
+                instruction_data.bump,
+            )
+        }
+        EscrowInstruction::Exchange => process_exchange(ExchangeAccounts::from_accounts(accounts)?),
+        EscrowInstruction::Cancel => process_cancel(CancelAccounts::from_accounts(accounts)?),
+    }
+}
+
+/// Creates the escrow of the sender and the receiver and deposits `amount`
+/// lamports into it.
+pub fn process_initialize(accounts: InitializeAccounts, amount: u64, bump: u8) -> ProgramResult {
+    if amount == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+
+    let bump = [bump];
+    let seeds = [
+        Seed::from(ESCROW_SEED.as_bytes()),
+        Seed::from(accounts.sender.key()),
+        Seed::from(accounts.receiver.key()),
+        Seed::from(&bump),
+    ];
+    {
+        let mut escrow = create_state::<Escrow>(accounts.sender, accounts.escrow, &seeds)?;
+        escrow.sender = *accounts.sender.key();
+        escrow.receiver = *accounts.receiver.key();
+        escrow.amount = amount;
+    }
+
+    Transfer {
+        from: accounts.sender,
+        to: accounts.escrow,
+        lamports: amount,
+    }
+    .invoke()?;
+
+    info!("Deposited {} lamports", amount);
+    Ok(())
+}
+
+/// Moves the deposit to the receiver and closes the escrow to the sender.
+pub fn process_exchange(accounts: ExchangeAccounts) -> ProgramResult {
+    let amount = {
+        let escrow = Escrow::load(accounts.escrow)?;
+        if !keys_eq(&escrow.sender, accounts.sender.key())
+            || !keys_eq(&escrow.receiver, accounts.receiver.key())
+        {
+            return Err(ProgramError::IllegalOwner);
+        }
+        escrow.amount
+    };
+
+    // The program owns the escrow, so it moves the deposit without a CPI.
+    {
+        let mut escrow_lamports = accounts.escrow.try_borrow_mut_lamports()?;
+        *escrow_lamports = escrow_lamports
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        let mut receiver_lamports = accounts.receiver.try_borrow_mut_lamports()?;
+        *receiver_lamports = receiver_lamports
+            .checked_add(amount)
+            .ok_or(SharedError::Overflow)?;
+    }
+    close_account(accounts.escrow, accounts.sender)?;
+
+    info!("Released {} lamports", amount);
+    Ok(())
+}
+
+/// Closes the escrow, moving the deposit and the rent back to the sender.
+pub fn process_cancel(accounts: CancelAccounts) -> ProgramResult {
+    if !keys_eq(
+        &Escrow::load(accounts.escrow)?.sender,
+        accounts.sender.key(),
+    ) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    close_account(accounts.escrow, accounts.sender)?;
+
+    info!("Cancelled the escrow");
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! Hello world module.
+
+use macros::InstructionDiscriminator;
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+use pinocchio_log::log;
+
+/// Hello module instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum HelloInstruction {
+    /// Logs "Hello, world!". Takes no accounts and no instruction data.
+    Hello,
+}
+
+/// Processes an instruction of the module.
+pub fn process(_accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    let (instruction, instruction_data) = HelloInstruction::split(instruction_data)?;
+    if !instruction_data.is_empty() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    match instruction {
+        HelloInstruction::Hello => log!("Hello, world!"),
+    }
+    Ok(())
+}
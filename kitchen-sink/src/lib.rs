@@ -0,0 +1,71 @@
+//! Program hosting several feature sets, organized in modules.
+//!
+//! The other examples are one program per feature, mostly in a single
+//! file. Larger programs grow out of that, so this one shows how to lay
+//! them out, with the features of the hello world, the counter and a
+//! lamport escrow:
+//!
+//! * Each feature is a module with its own instructions, accounts, state
+//!   and `process` function: [`hello`], [`counter`] and [`escrow`].
+//! * The first byte of the instruction data, a [`Module`], picks the
+//!   module, which splits its own instruction discriminator off the rest,
+//!   so every module numbers its instructions from zero.
+//! * [`state`] holds what the modules share, the kinds of accounts telling
+//!   their state apart and the creation of state accounts.
+//!
+//! Adding a feature means adding a module and a [`Module`] variant, without
+//! touching the instructions of the others.
+
+#![no_std]
+
+use common::checks::require_program_id;
+use macros::InstructionDiscriminator;
+use pinocchio::{account_info::AccountInfo, pubkey::Pubkey, ProgramResult};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+pub mod counter;
+pub mod escrow;
+pub mod hello;
+pub mod state;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("6iW6M26S1ZA9gypZPqQU2bzHY6bRLsMk2FQXR6NxfUvD");
+
+/// Modules of the program, the first byte of the instruction data. The
+/// instruction discriminator of the module follows.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum Module {
+    /// Instructions of [`hello`].
+    Hello,
+    /// Instructions of [`counter`].
+    Counter,
+    /// Instructions of [`escrow`].
+    Escrow,
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Check that the program runs under its declared address, which the
+    // PDAs of all modules are derived from.
+    require_program_id(program_id, &ID)?;
+
+    let (module, instruction_data) = Module::split(instruction_data)?;
+    match module {
+        Module::Hello => hello::process(accounts, instruction_data),
+        Module::Counter => counter::process(accounts, instruction_data),
+        Module::Escrow => escrow::process(accounts, instruction_data),
+    }
+}
@@ -0,0 +1,36 @@
+//! State shared by the modules.
+//!
+//! All modules store their state in accounts of the same program, so a
+//! counter could be passed where an escrow is expected. Every state starts
+//! with its [`AccountKind`] as the [`AccountData::DISCRIMINATOR`], which
+//! [`AccountData::load`] checks.
+
+use common::{create::create_account, AccountData};
+use pinocchio::{
+    account_info::{AccountInfo, RefMut},
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+};
+
+use crate::ID;
+
+/// Kinds of accounts of the program, the first byte of their data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AccountKind {
+    /// A [`crate::counter::Counter`].
+    Counter = 1,
+    /// A [`crate::escrow::Escrow`].
+    Escrow = 2,
+}
+
+/// Creates `account`, the PDA of `seeds`, paid by `payer`, and borrows its
+/// state `T` mutably, with its discriminator written.
+pub fn create_state<'a, T: AccountData>(
+    payer: &AccountInfo,
+    account: &'a AccountInfo,
+    seeds: &[Seed],
+) -> Result<RefMut<'a, T>, ProgramError> {
+    create_account(payer, account, T::LEN, &ID, &[Signer::from(seeds)])?;
+    T::init(account)
+}
@@ -0,0 +1,241 @@
+//! Attacks on the kitchen sink program, each rejected with its own error.
+
+use common::AccountData;
+use kitchen_sink::{
+    escrow::{Escrow, EscrowInstruction, InitializeInstructionData, ESCROW_SEED},
+    hello::HelloInstruction,
+    state::AccountKind,
+    Module,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(kitchen_sink::ID);
+
+const LAMPORTS: u64 = 1_000_000_000;
+
+const AMOUNT: u64 = 100_000;
+
+/// Returns an instruction of `module`, its instruction discriminator being
+/// the first byte of `data`.
+fn instruction(module: Module, data: &[u8], accounts: Vec<AccountMeta>) -> Instruction {
+    let mut instruction_data = module.discriminator().to_vec();
+    instruction_data.extend_from_slice(data);
+    Instruction::new_with_bytes(ID, &instruction_data, accounts)
+}
+
+/// Returns the escrow PDA of `sender` and `receiver` and its bump.
+fn escrow_pda(sender: &Pubkey, receiver: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ESCROW_SEED.as_bytes(), sender.as_ref(), receiver.as_ref()],
+        &ID,
+    )
+}
+
+/// Returns an escrow of `sender` for `receiver`, holding `amount` lamports
+/// on top of its rent.
+fn escrow_account(mollusk: &Mollusk, sender: &Pubkey, receiver: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Escrow::LEN) + amount,
+        Escrow::LEN,
+        &ID,
+    );
+    account.data[0] = AccountKind::Escrow as u8;
+    account.data[8..40].copy_from_slice(sender.as_ref());
+    account.data[40..72].copy_from_slice(receiver.as_ref());
+    account.data[72..80].copy_from_slice(&amount.to_le_bytes());
+    account
+}
+
+/// Accounts of an escrow of a sender for a receiver, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    sender: Pubkey,
+    receiver: Pubkey,
+    escrow: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/kitchen_sink");
+
+        let sender = Pubkey::new_unique();
+        let receiver = Pubkey::new_unique();
+        let (escrow, _) = escrow_pda(&sender, &receiver);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (sender, Account::new(LAMPORTS, 0, &Pubkey::default())),
+            (receiver, Account::new(LAMPORTS, 0, &Pubkey::default())),
+            (escrow, escrow_account(&mollusk, &sender, &receiver, AMOUNT)),
+            (attacker, Account::new(LAMPORTS, 0, &Pubkey::default())),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            sender,
+            receiver,
+            escrow,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Creates `escrow` for the receiver, signed by the attacker.
+    fn attacker_initialize(&self, escrow: &Pubkey, system_program: &Pubkey) -> Instruction {
+        let (_, bump) = escrow_pda(&self.attacker, &self.receiver);
+        let mut data = EscrowInstruction::Initialize.discriminator().to_vec();
+        data.extend_from_slice(
+            &InitializeInstructionData {
+                amount: AMOUNT,
+                bump,
+            }
+            .to_bytes(),
+        );
+        instruction(
+            Module::Escrow,
+            &data,
+            vec![
+                AccountMeta::new(self.attacker, true),
+                AccountMeta::new_readonly(self.receiver, false),
+                AccountMeta::new(*escrow, false),
+                AccountMeta::new_readonly(*system_program, false),
+            ],
+        )
+    }
+
+    /// Accepts `escrow`, signed by `receiver`, with the rent going to
+    /// `sender`.
+    fn instruction_exchange(
+        &self,
+        sender: &Pubkey,
+        receiver: &Pubkey,
+        escrow: &Pubkey,
+    ) -> Instruction {
+        instruction(
+            Module::Escrow,
+            EscrowInstruction::Exchange.discriminator(),
+            vec![
+                AccountMeta::new(*sender, false),
+                AccountMeta::new(*receiver, true),
+                AccountMeta::new(*escrow, false),
+            ],
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_receiver_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_exchange(&setup.sender, &setup.receiver, &setup.escrow);
+    instruction.accounts[1].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker cancels the escrow of the sender, closing it with the
+/// deposit to themselves as the sender.
+#[test]
+fn test_cancel_escrow_of_other_sender() {
+    let setup = Setup::new();
+    let cancel = instruction(
+        Module::Escrow,
+        EscrowInstruction::Cancel.discriminator(),
+        vec![
+            AccountMeta::new(setup.attacker, true),
+            AccountMeta::new(setup.escrow, false),
+        ],
+    );
+    setup.expect_err(&cancel, ProgramError::IllegalOwner);
+}
+
+/// The receiver accepts the deposit and takes the rent of the escrow too,
+/// passing another account of theirs as the sender.
+#[test]
+fn test_exchange_rent_to_other_sender() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_exchange(&setup.attacker, &setup.receiver, &setup.escrow),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker creates an escrow at the address of the escrow of the
+/// sender for the receiver, before the sender does.
+#[test]
+fn test_initialize_escrow_of_other_sender() {
+    let setup = Setup::new();
+    let escrow = setup.escrow;
+    let setup = setup.with(escrow, Account::default());
+    setup.expect_err(
+        &setup.attacker_initialize(&escrow, &keyed_account_for_system_program().0),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// A program posing as the system program, which would be invoked with the
+/// signature of the escrow PDA.
+#[test]
+fn test_fake_system_program() {
+    let setup = Setup::new();
+    let (escrow, _) = escrow_pda(&setup.attacker, &setup.receiver);
+    let system_program = Pubkey::new_unique();
+    let setup = setup
+        .with(escrow, Account::default())
+        .with(system_program, Account::default());
+    setup.expect_err(
+        &setup.attacker_initialize(&escrow, &system_program),
+        ProgramError::IncorrectProgramId,
+    );
+}
+
+/// An escrow of the attacker for themselves with a made up deposit, owned
+/// by another program.
+#[test]
+fn test_exchange_spoofed_escrow() {
+    let setup = Setup::new();
+    let mut escrow_account =
+        escrow_account(&setup.mollusk, &setup.attacker, &setup.attacker, LAMPORTS);
+    escrow_account.owner = Pubkey::new_unique();
+    let escrow = Pubkey::new_unique();
+    let setup = setup.with(escrow, escrow_account);
+    setup.expect_err(
+        &setup.instruction_exchange(&setup.attacker, &setup.attacker, &escrow),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A copy of the program deployed at another address, whose PDAs wouldn't
+/// be the ones of the modules, refuses to run.
+#[test]
+fn test_redeployed_program() {
+    let redeployed = Pubkey::new_unique();
+    let mollusk = Mollusk::new(&redeployed, "target/deploy/kitchen_sink");
+    let mut data = Module::Hello.discriminator().to_vec();
+    data.extend_from_slice(HelloInstruction::Hello.discriminator());
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(redeployed, &data, Vec::new()),
+        &[],
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+}
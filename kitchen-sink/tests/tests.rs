@@ -0,0 +1,257 @@
+use errors::{Namespace, SharedError};
+use kitchen_sink::{
+    counter::{Counter, CounterInstruction, CreateInstructionData, COUNTER_SEED},
+    escrow::{Escrow, EscrowInstruction, InitializeInstructionData, ESCROW_SEED},
+    hello::HelloInstruction,
+    Module,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::{
+    assertions::{account_state, assert_closed},
+    scenario::Scenario,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(kitchen_sink::ID);
+
+const LAMPORTS: u64 = 1_000_000_000;
+
+fn mollusk() -> Mollusk {
+    Mollusk::new(&ID, "target/deploy/kitchen_sink")
+}
+
+/// Returns an instruction of `module`, its instruction discriminator being
+/// the first byte of `data`.
+fn instruction(module: Module, data: &[u8], accounts: Vec<AccountMeta>) -> Instruction {
+    let mut instruction_data = module.discriminator().to_vec();
+    instruction_data.extend_from_slice(data);
+    Instruction::new_with_bytes(ID, &instruction_data, accounts)
+}
+
+fn create_counter(owner: &Pubkey) -> (Pubkey, Instruction) {
+    let (counter, bump) =
+        Pubkey::find_program_address(&[COUNTER_SEED.as_bytes(), owner.as_ref()], &ID);
+    let mut data = CounterInstruction::Create.discriminator().to_vec();
+    data.extend_from_slice(&CreateInstructionData { bump }.to_bytes());
+    let instruction = instruction(
+        Module::Counter,
+        &data,
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(counter, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ],
+    );
+    (counter, instruction)
+}
+
+fn increment(owner: &Pubkey, counter: &Pubkey) -> Instruction {
+    instruction(
+        Module::Counter,
+        CounterInstruction::Increment.discriminator(),
+        vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*counter, false),
+        ],
+    )
+}
+
+fn initialize_escrow(sender: &Pubkey, receiver: &Pubkey, amount: u64) -> (Pubkey, Instruction) {
+    let (escrow, bump) = Pubkey::find_program_address(
+        &[ESCROW_SEED.as_bytes(), sender.as_ref(), receiver.as_ref()],
+        &ID,
+    );
+    let mut data = EscrowInstruction::Initialize.discriminator().to_vec();
+    data.extend_from_slice(&InitializeInstructionData { amount, bump }.to_bytes());
+    let instruction = instruction(
+        Module::Escrow,
+        &data,
+        vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new_readonly(*receiver, false),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ],
+    );
+    (escrow, instruction)
+}
+
+fn exchange(sender: &Pubkey, receiver: &Pubkey, escrow: &Pubkey) -> Instruction {
+    instruction(
+        Module::Escrow,
+        EscrowInstruction::Exchange.discriminator(),
+        vec![
+            AccountMeta::new(*sender, false),
+            AccountMeta::new(*receiver, true),
+            AccountMeta::new(*escrow, false),
+        ],
+    )
+}
+
+fn cancel(sender: &Pubkey, escrow: &Pubkey) -> Instruction {
+    instruction(
+        Module::Escrow,
+        EscrowInstruction::Cancel.discriminator(),
+        vec![
+            AccountMeta::new(*sender, true),
+            AccountMeta::new(*escrow, false),
+        ],
+    )
+}
+
+#[test]
+fn test_hello() {
+    mollusk().process_and_validate_instruction(
+        &instruction(
+            Module::Hello,
+            HelloInstruction::Hello.discriminator(),
+            vec![],
+        ),
+        &[],
+        &[Check::success()],
+    );
+}
+
+#[test]
+fn test_counter() {
+    let owner = Pubkey::new_unique();
+    let (counter, create) = create_counter(&owner);
+
+    let res = Scenario::with_mollusk(mollusk())
+        .with_funded(&owner, LAMPORTS)
+        .with_empty(&counter)
+        .then(create)
+        .then(increment(&owner, &counter))
+        .then(increment(&owner, &counter))
+        .run();
+    let state: Counter = account_state(&res, &counter);
+    assert_eq!(state.owner, owner.to_bytes());
+    assert_eq!(state.count, 2);
+
+    // Only the owner increments the counter.
+    let other = Pubkey::new_unique();
+    let (counter, create) = create_counter(&owner);
+    Scenario::with_mollusk(mollusk())
+        .with_funded(&owner, LAMPORTS)
+        .with_funded(&other, LAMPORTS)
+        .with_empty(&counter)
+        .then(create)
+        .then_check(
+            increment(&other, &counter),
+            vec![Check::err(ProgramError::IllegalOwner)],
+        )
+        .run();
+}
+
+#[test]
+fn test_escrow() {
+    let sender = Pubkey::new_unique();
+    let receiver = Pubkey::new_unique();
+    let amount = 100_000;
+    let (escrow, initialize) = initialize_escrow(&sender, &receiver, amount);
+
+    let res = Scenario::with_mollusk(mollusk())
+        .with_funded(&sender, LAMPORTS)
+        .with_funded(&receiver, LAMPORTS)
+        .with_empty(&escrow)
+        .then(initialize.clone())
+        .run();
+    let state: Escrow = account_state(&res, &escrow);
+    assert_eq!(state.sender, sender.to_bytes());
+    assert_eq!(state.receiver, receiver.to_bytes());
+    assert_eq!(state.amount, amount);
+
+    // The receiver gets the deposit, the sender gets back the rent.
+    let res = Scenario::with_mollusk(mollusk())
+        .with_funded(&sender, LAMPORTS)
+        .with_funded(&receiver, LAMPORTS)
+        .with_empty(&escrow)
+        .then(initialize.clone())
+        .then(exchange(&sender, &receiver, &escrow))
+        .run();
+    assert_closed(&res, &escrow);
+    assert_eq!(
+        res.get_account(&sender).unwrap().lamports,
+        LAMPORTS - amount
+    );
+    assert_eq!(
+        res.get_account(&receiver).unwrap().lamports,
+        LAMPORTS + amount
+    );
+
+    // The sender gets back the deposit and the rent.
+    let res = Scenario::with_mollusk(mollusk())
+        .with_funded(&sender, LAMPORTS)
+        .with_funded(&receiver, LAMPORTS)
+        .with_empty(&escrow)
+        .then(initialize.clone())
+        .then(cancel(&sender, &escrow))
+        .run();
+    assert_closed(&res, &escrow);
+    assert_eq!(res.get_account(&sender).unwrap().lamports, LAMPORTS);
+
+    // Only the receiver of the escrow accepts it.
+    let other = Pubkey::new_unique();
+    Scenario::with_mollusk(mollusk())
+        .with_funded(&sender, LAMPORTS)
+        .with_funded(&receiver, LAMPORTS)
+        .with_funded(&other, LAMPORTS)
+        .with_empty(&escrow)
+        .then(initialize)
+        .then_check(
+            exchange(&sender, &other, &escrow),
+            vec![Check::err(ProgramError::IllegalOwner)],
+        )
+        .run();
+
+    // Nothing to deposit.
+    let (escrow, initialize) = initialize_escrow(&sender, &receiver, 0);
+    Scenario::with_mollusk(mollusk())
+        .with_funded(&sender, LAMPORTS)
+        .with_funded(&receiver, LAMPORTS)
+        .with_empty(&escrow)
+        .then_check(
+            initialize,
+            vec![Check::err(ProgramError::Custom(
+                Namespace::Shared.code(SharedError::InvalidAmount as u32),
+            ))],
+        )
+        .run();
+}
+
+/// The state of every module starts with its kind, so the accounts of one
+/// module can't be passed to another.
+#[test]
+fn test_account_kinds() {
+    let owner = Pubkey::new_unique();
+    let (counter, create) = create_counter(&owner);
+
+    Scenario::with_mollusk(mollusk())
+        .with_funded(&owner, LAMPORTS)
+        .with_empty(&counter)
+        .then(create)
+        .then_check(
+            cancel(&owner, &counter),
+            vec![Check::err(ProgramError::InvalidAccountData)],
+        )
+        .run();
+}
+
+#[test]
+fn test_invalid_instruction() {
+    let mollusk = mollusk();
+    for data in [
+        &[][..],
+        &[3],
+        &[Module::Hello as u8, 1],
+        &[Module::Counter as u8, 2],
+    ] {
+        mollusk.process_and_validate_instruction(
+            &Instruction::new_with_bytes(ID, data, vec![]),
+            &[],
+            &[Check::err(ProgramError::InvalidInstructionData)],
+        );
+    }
+}
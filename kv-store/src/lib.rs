@@ -0,0 +1,339 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_rent_exempt, require_signer};
+use common::close::close_account;
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::{set_return_data, MAX_RETURN_DATA},
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::{CreateAccount, Transfer as SystemTransfer};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("DGHDQV5W1KA7jExxjAVS7RHBo8AHUfJAY1cYcVhgjggH");
+
+pub const ENTRY_SEED: &str = "entry";
+
+/// Maximum length of a key.
+pub const MAX_KEY_LEN: usize = u8::MAX as usize;
+/// Maximum length of a value. Values are limited to the size of return data,
+/// so `Get` can always return them whole.
+pub const MAX_VALUE_LEN: usize = MAX_RETURN_DATA;
+
+/// Errors returned by the key-value store program.
+#[repr(u32)]
+pub enum KvError {
+    /// The key is empty or longer than [`MAX_KEY_LEN`].
+    InvalidKey,
+    /// The value is longer than [`MAX_VALUE_LEN`].
+    ValueTooLarge,
+    /// The entry doesn't belong to the key.
+    KeyMismatch,
+}
+
+impl From<KvError> for ProgramError {
+    fn from(e: KvError) -> Self {
+        Namespace::KvStore.error(e as u32)
+    }
+}
+
+/// Header of an entry, followed by the value.
+///
+/// Entries are PDAs derived from the owner and the SHA-256 hash of the key,
+/// so keys of any length up to [`MAX_KEY_LEN`] fit into a seed. The size of
+/// the account follows the size of the value.
+#[repr(C)]
+pub struct Entry {
+    pub owner: Pubkey,
+    /// SHA-256 hash of the key.
+    pub key_hash: [u8; 32],
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Entry, size = 72, align = 1, { owner: 0, key_hash: 32, bump: 64 });
+
+impl Entry {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Key-value store program instruction discriminators.
+#[repr(u8)]
+pub enum KvInstruction {
+    /// Creates or updates an entry.
+    Put,
+    /// Returns the value of an entry.
+    Get,
+    /// Closes an entry.
+    Delete,
+}
+
+impl TryFrom<&u8> for KvInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Put),
+            1 => Ok(Self::Get),
+            2 => Ok(Self::Delete),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Fixed part of the put instruction data. It's followed by `key_len` bytes
+/// of the key and the value, which takes the rest of the instruction data.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct PutInstructionData {
+    pub bump: u8,
+    pub key_len: u8,
+}
+
+impl PutInstructionData {
+    pub fn new(bump: u8, key_len: u8) -> Self {
+        Self { bump, key_len }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = KvInstruction::try_from(instruction)?;
+
+    match instruction {
+        KvInstruction::Put => process_put(accounts, instruction_data),
+        KvInstruction::Get => process_get(accounts, instruction_data),
+        KvInstruction::Delete => process_delete(accounts),
+    }
+}
+
+/// Computes the SHA-256 hash of the concatenation of `vals` using the
+/// `sol_sha256` syscall.
+pub fn hashv(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash = [0; 32];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        );
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((vals, &mut hash));
+
+    hash
+}
+
+/// Checks the length of `key` and returns its hash.
+fn hash_key(key: &[u8]) -> Result<[u8; 32], ProgramError> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Err(KvError::InvalidKey.into());
+    }
+    Ok(hashv(&[key]))
+}
+
+/// Sets the value of the key passed in the instruction data.
+///
+/// Creates the entry if it doesn't exist yet. Otherwise resizes it to the
+/// new value, topping up the rent from `owner` when growing and refunding the
+/// excess to `owner` when shrinking.
+pub fn process_put(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, entry, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    if instruction_data.len() < PutInstructionData::LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (header, rest) = instruction_data.split_at(PutInstructionData::LEN);
+    let header = PutInstructionData::from_bytes(header)?;
+    if rest.len() < header.key_len as usize {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (key, value) = rest.split_at(header.key_len as usize);
+
+    let key_hash = hash_key(key)?;
+    if value.len() > MAX_VALUE_LEN {
+        return Err(KvError::ValueTooLarge.into());
+    }
+
+    let space = Entry::LEN + value.len();
+    let rent = Rent::get()?;
+    let min_balance = rent.minimum_balance(space);
+
+    if entry.is_owned_by(&ID) {
+        check_entry(owner, entry, &key_hash)?;
+
+        // Top up the rent before growing, so the entry stays rent-exempt.
+        let lamports = entry.lamports();
+        if min_balance > lamports {
+            SystemTransfer {
+                from: owner,
+                to: entry,
+                lamports: min_balance - lamports,
+            }
+            .invoke()?;
+        }
+
+        entry.realloc(space, false)?;
+
+        // The entry is owned by the program, so the excess rent can be moved
+        // directly.
+        if lamports > min_balance {
+            *entry.try_borrow_mut_lamports()? -= lamports - min_balance;
+            *owner.try_borrow_mut_lamports()? += lamports - min_balance;
+        }
+    } else {
+        // Check the seeds of `entry`.
+        let entry_pda = create_program_address(
+            &[
+                ENTRY_SEED.as_bytes(),
+                owner.key(),
+                &key_hash,
+                &[header.bump],
+            ],
+            &ID,
+        )?;
+        if !keys_eq(entry.key(), &entry_pda) {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        // Create the entry PDA.
+        let bump = [header.bump];
+        let seeds = [
+            Seed::from(ENTRY_SEED.as_bytes()),
+            Seed::from(owner.key()),
+            Seed::from(&key_hash),
+            Seed::from(&bump),
+        ];
+        CreateAccount {
+            from: owner,
+            to: entry,
+            lamports: min_balance,
+            space: space as u64,
+            owner: &ID,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        // Initialize the header.
+        let mut data = entry.try_borrow_mut_data()?;
+        let data: &mut Entry = unsafe { &mut *data.as_mut_ptr().cast() };
+        data.owner = *owner.key();
+        data.key_hash = key_hash;
+        data.bump = header.bump;
+    }
+
+    // Check that the created or resized entry is rent-exempt before
+    // writing into it.
+    require_rent_exempt(entry, &rent)?;
+
+    // Write the value.
+    let mut data = entry.try_borrow_mut_data()?;
+    data[Entry::LEN..].copy_from_slice(value);
+
+    info!("Put value of {} bytes", value.len());
+
+    Ok(())
+}
+
+/// Checks that `entry` is an entry of `owner` with the given key hash.
+fn check_entry(owner: &AccountInfo, entry: &AccountInfo, key_hash: &[u8; 32]) -> ProgramResult {
+    if entry.data_len() < Entry::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let data = entry.try_borrow_data()?;
+    let data: &Entry = unsafe { &*data.as_ptr().cast() };
+
+    if !keys_eq(&data.owner, owner.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if &data.key_hash != key_hash {
+        return Err(KvError::KeyMismatch.into());
+    }
+    Ok(())
+}
+
+/// Sets the value of the key passed in the instruction data as return data.
+///
+/// Doesn't require a signature, the entries are public.
+pub fn process_get(accounts: &[AccountInfo], key: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, entry] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Check if the entry PDA is owned by the program.
+    require_owned_by(entry, &ID)?;
+
+    let key_hash = hash_key(key)?;
+    check_entry(owner, entry, &key_hash)?;
+
+    let data = entry.try_borrow_data()?;
+    set_return_data(&data[Entry::LEN..]);
+
+    Ok(())
+}
+
+/// Closes the entry, returning the rent to `owner`.
+pub fn process_delete(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, entry] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+    // Check if the entry PDA is owned by the program.
+    require_owned_by(entry, &ID)?;
+    if entry.data_len() < Entry::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    {
+        let data = entry.try_borrow_data()?;
+        let data: &Entry = unsafe { &*data.as_ptr().cast() };
+
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+    }
+
+    // Close the entry.
+    close_account(entry, owner)?;
+
+    info!("Deleted entry");
+
+    Ok(())
+}
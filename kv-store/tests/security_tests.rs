@@ -0,0 +1,193 @@
+//! Attacks on the key-value store program, each rejected with its own
+//! error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use kv_store::{Entry, KvError, KvInstruction, PutInstructionData, ENTRY_SEED};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use sha2::{Digest, Sha256};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(kv_store::ID);
+
+const KEY: &[u8] = b"config/theme";
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: KvInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<KvInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn key_hash(key: &[u8]) -> [u8; 32] {
+    Sha256::digest(key).into()
+}
+
+/// Returns the entry PDA of `key` of `owner` and its bump.
+fn entry_pda(owner: &Pubkey, key: &[u8]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ENTRY_SEED.as_bytes(), owner.as_ref(), &key_hash(key)],
+        &ID,
+    )
+}
+
+/// Returns an entry of `key` of `owner` holding `value`.
+fn entry_account(mollusk: &Mollusk, owner: &Pubkey, key: &[u8], value: &[u8]) -> Account {
+    let space = Entry::LEN + value.len();
+    let mut account = Account::new(mollusk.sysvars.rent.minimum_balance(space), space, &ID);
+    account.data[..32].copy_from_slice(owner.as_ref());
+    account.data[32..64].copy_from_slice(&key_hash(key));
+    account.data[Entry::LEN..].copy_from_slice(value);
+    account
+}
+
+/// Accounts of an owner and of an attacker, each with an entry of [`KEY`].
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    entry: Pubkey,
+    attacker: Pubkey,
+    attacker_entry: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/kv_store");
+
+        let owner = Pubkey::new_unique();
+        let (entry, _) = entry_pda(&owner, KEY);
+        let attacker = Pubkey::new_unique();
+        let (attacker_entry, _) = entry_pda(&attacker, KEY);
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (entry, entry_account(&mollusk, &owner, KEY, b"dark")),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_entry,
+                entry_account(&mollusk, &attacker, KEY, b"light"),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            entry,
+            attacker,
+            attacker_entry,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Sets `value` of `key` in `entry`, signed by `owner`.
+    fn instruction_put(
+        &self,
+        owner: &Pubkey,
+        entry: &Pubkey,
+        key: &[u8],
+        value: &[u8],
+        bump: u8,
+    ) -> Instruction {
+        let mut data = instruction_data(
+            KvInstruction::Put,
+            &PutInstructionData::new(bump, key.len() as u8),
+        );
+        data.extend_from_slice(key);
+        data.extend_from_slice(value);
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*entry, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(ID, &data, ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let (_, bump) = entry_pda(&setup.owner, KEY);
+    let mut instruction = setup.instruction_put(&setup.owner, &setup.entry, KEY, b"light", bump);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the entry of the owner before the owner does, with
+/// a value of their choice.
+#[test]
+fn test_put_entry_of_other_owner() {
+    let setup = Setup::new();
+    let entry = setup.entry;
+    let setup = setup.with(entry, Account::default());
+    let (_, bump) = entry_pda(&setup.attacker, KEY);
+    setup.expect_err(
+        &setup.instruction_put(&setup.attacker, &entry, KEY, b"hacked", bump),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker writes the value of another key into their entry of
+/// [`KEY`], which `Get` would then return for [`KEY`].
+#[test]
+fn test_put_under_other_key() {
+    let setup = Setup::new();
+    let (_, bump) = entry_pda(&setup.attacker, b"other");
+    setup.expect_err(
+        &setup.instruction_put(
+            &setup.attacker,
+            &setup.attacker_entry,
+            b"other",
+            b"hacked",
+            bump,
+        ),
+        ProgramError::Custom(Namespace::KvStore.code(KvError::KeyMismatch as u32)),
+    );
+}
+
+/// An entry of the owner with a made up value, owned by another program,
+/// returned to programs reading the entries of the owner.
+#[test]
+fn test_get_spoofed_entry() {
+    let setup = Setup::new();
+    let mut entry_account = entry_account(&setup.mollusk, &setup.owner, KEY, b"hacked");
+    entry_account.owner = Pubkey::new_unique();
+    let entry = Pubkey::new_unique();
+    let setup = setup.with(entry, entry_account);
+    let mut data = vec![KvInstruction::Get as u8];
+    data.extend_from_slice(KEY);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.owner, false),
+        AccountMeta::new_readonly(entry, false),
+    ];
+    setup.expect_err(
+        &Instruction::new_with_bytes(ID, &data, ix_accounts),
+        ProgramError::IllegalOwner,
+    );
+}
@@ -0,0 +1,325 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use kv_store::{Entry, KvError, KvInstruction, PutInstructionData, ENTRY_SEED};
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use sha2::{Digest, Sha256};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(kv_store::ID);
+
+const KEY: &[u8] = b"config/theme";
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: KvInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<KvInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn key_hash(key: &[u8]) -> [u8; 32] {
+    Sha256::digest(key).into()
+}
+
+fn find_entry(owner: &Pubkey, key: &[u8]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ENTRY_SEED.as_bytes(), owner.as_ref(), &key_hash(key)],
+        &ID,
+    )
+}
+
+fn instruction_put(
+    owner: &Pubkey,
+    entry: &Pubkey,
+    key: &[u8],
+    value: &[u8],
+    bump: u8,
+) -> Instruction {
+    let data = PutInstructionData::new(bump, key.len() as u8);
+    let mut data = instruction_data(KvInstruction::Put, &data);
+    data.extend_from_slice(key);
+    data.extend_from_slice(value);
+    let ix_accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*entry, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(ID, &data, ix_accounts)
+}
+
+fn instruction_get(owner: &Pubkey, entry: &Pubkey, key: &[u8]) -> Instruction {
+    let mut data = vec![KvInstruction::Get as u8];
+    data.extend_from_slice(key);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*owner, false),
+        AccountMeta::new_readonly(*entry, false),
+    ];
+    Instruction::new_with_bytes(ID, &data, ix_accounts)
+}
+
+fn instruction_delete(owner: &Pubkey, entry: &Pubkey) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*entry, false),
+    ];
+    Instruction::new_with_bytes(ID, &[KvInstruction::Delete as u8], ix_accounts)
+}
+
+/// Creates an entry account of `owner` holding `value`.
+fn entry_account(mollusk: &Mollusk, owner: &Pubkey, key: &[u8], value: &[u8]) -> Account {
+    let space = Entry::LEN + value.len();
+    let mut account = Account::new(mollusk.sysvars.rent.minimum_balance(space), space, &ID);
+    account.data[..32].copy_from_slice(owner.as_ref());
+    account.data[32..64].copy_from_slice(&key_hash(key));
+    account.data[Entry::LEN..].copy_from_slice(value);
+    account
+}
+
+#[test]
+fn test_kv_store_put_and_get() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/kv_store");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_unique();
+    let (entry, bump) = find_entry(&owner, KEY);
+    let value = b"dark";
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_put(&owner, &entry, KEY, value, bump),
+                &[
+                    Check::success(),
+                    Check::account(&entry)
+                        .owner(&ID)
+                        .space(Entry::LEN + value.len())
+                        .lamports(
+                            mollusk
+                                .sysvars
+                                .rent
+                                .minimum_balance(Entry::LEN + value.len()),
+                        )
+                        .data_slice(0, owner.as_ref())
+                        .data_slice(32, &key_hash(KEY))
+                        .data_slice(Entry::LEN, value)
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_get(&owner, &entry, KEY),
+                &[Check::success(), Check::return_data(value)],
+            ),
+        ],
+        &[
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (entry, Account::default()),
+            (system_program, system_account),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_kv_store_put_resize() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/kv_store");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_unique();
+    let (entry, bump) = find_entry(&owner, KEY);
+    let long_value = [7u8; 512];
+    let short_value = b"light";
+
+    let rent_short = mollusk
+        .sysvars
+        .rent
+        .minimum_balance(Entry::LEN + short_value.len());
+    let rent_long = mollusk
+        .sysvars
+        .rent
+        .minimum_balance(Entry::LEN + long_value.len());
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            // Growing tops up the rent from the owner.
+            (
+                &instruction_put(&owner, &entry, KEY, &long_value, bump),
+                &[
+                    Check::success(),
+                    Check::account(&owner)
+                        .lamports(LAMPORTS_PER_SOL - (rent_long - rent_short))
+                        .build(),
+                    Check::account(&entry)
+                        .space(Entry::LEN + long_value.len())
+                        .lamports(rent_long)
+                        .data_slice(Entry::LEN, &long_value)
+                        .build(),
+                ],
+            ),
+            // Shrinking refunds the excess rent.
+            (
+                &instruction_put(&owner, &entry, KEY, short_value, bump),
+                &[
+                    Check::success(),
+                    Check::account(&owner).lamports(LAMPORTS_PER_SOL).build(),
+                    Check::account(&entry)
+                        .space(Entry::LEN + short_value.len())
+                        .lamports(rent_short)
+                        .data_slice(Entry::LEN, short_value)
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_get(&owner, &entry, KEY),
+                &[Check::success(), Check::return_data(short_value)],
+            ),
+        ],
+        &[
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (entry, entry_account(&mollusk, &owner, KEY, b"night")),
+            (system_program, system_account),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+/// The custom error of the system program for payers without enough
+/// lamports.
+const RESULT_WITH_NEGATIVE_LAMPORTS: u32 = 1;
+
+#[test]
+fn test_kv_store_put_underfunded_owner() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/kv_store");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_unique();
+    let (entry, bump) = find_entry(&owner, KEY);
+    let long_value = [7u8; 512];
+
+    // The owner can't pay for the rent of a new entry, nor top up the rent
+    // of a growing one, so neither is left underfunded.
+    for entry_account in [
+        Account::default(),
+        entry_account(&mollusk, &owner, KEY, b"night"),
+    ] {
+        mollusk.process_and_validate_instruction(
+            &instruction_put(&owner, &entry, KEY, &long_value, bump),
+            &[
+                (owner, Account::new(1, 0, &Pubkey::default())),
+                (entry, entry_account),
+                (system_program, system_account.clone()),
+            ],
+            &[Check::err(ProgramError::Custom(
+                RESULT_WITH_NEGATIVE_LAMPORTS,
+            ))],
+        );
+    }
+}
+
+#[test]
+fn test_kv_store_invalid_put() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/kv_store");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_unique();
+    let (entry, bump) = find_entry(&owner, KEY);
+
+    let accounts = [
+        (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+        (entry, Account::default()),
+        (system_program, system_account),
+    ];
+    for (instruction, err) in [
+        (
+            instruction_put(&owner, &entry, b"", b"dark", bump),
+            KvError::InvalidKey,
+        ),
+        (
+            instruction_put(&owner, &entry, KEY, &[0; 1025], bump),
+            KvError::ValueTooLarge,
+        ),
+    ] {
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[Check::err(ProgramError::Custom(
+                Namespace::KvStore.code(err as u32),
+            ))],
+        );
+    }
+}
+
+#[test]
+fn test_kv_store_wrong_entry() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/kv_store");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_unique();
+    let (entry, bump) = find_entry(&owner, KEY);
+    let attacker = Pubkey::new_unique();
+
+    let accounts = [
+        (owner, Account::default()),
+        (
+            attacker,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        ),
+        (entry, entry_account(&mollusk, &owner, KEY, b"dark")),
+        (system_program, system_account),
+    ];
+    for (instruction, err) in [
+        // The entry belongs to a different key.
+        (
+            instruction_get(&owner, &entry, b"other"),
+            ProgramError::Custom(Namespace::KvStore.code(KvError::KeyMismatch as u32)),
+        ),
+        // Only the owner can overwrite and delete the entry.
+        (
+            instruction_put(&attacker, &entry, KEY, b"hacked", bump),
+            ProgramError::IllegalOwner,
+        ),
+        (
+            instruction_delete(&attacker, &entry),
+            ProgramError::IllegalOwner,
+        ),
+    ] {
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::err(err)]);
+    }
+}
+
+#[test]
+fn test_kv_store_delete() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/kv_store");
+
+    let owner = Pubkey::new_unique();
+    let (entry, _) = find_entry(&owner, KEY);
+    let entry_account = entry_account(&mollusk, &owner, KEY, b"dark");
+    let rent = entry_account.lamports;
+
+    mollusk.process_and_validate_instruction(
+        &instruction_delete(&owner, &entry),
+        &[(owner, Account::default()), (entry, entry_account)],
+        &[
+            Check::success(),
+            Check::account(&owner).lamports(rent).build(),
+            Check::account(&entry).closed().build(),
+        ],
+    );
+}
@@ -0,0 +1,789 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use common::close::close_account;
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::{CreateAccount, Transfer as SystemTransfer};
+use pinocchio_token::{instructions::Transfer, state::TokenAccount};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("7iP56TbWGee7kNx5WgkDmdrz8rmZPkzzKJfj3Pc1SWrh");
+
+pub const SALE_SEED: &str = "sale";
+pub const ALLOWLIST_SEED: &str = "allowlist";
+pub const PURCHASE_SEED: &str = "purchase";
+
+/// Errors returned by the launchpad program.
+#[repr(u32)]
+pub enum LaunchpadError {
+    /// The sale parameters are inconsistent.
+    InvalidConfig,
+    /// The vault doesn't hold enough tokens for the hard cap.
+    InsufficientSaleTokens,
+    /// The sale hasn't started yet.
+    NotStarted,
+    /// The sale has ended.
+    Ended,
+    /// The sale hasn't ended yet.
+    NotEnded,
+    /// The buyer isn't on the allowlist.
+    NotAllowlisted,
+    /// The purchase exceeds the per-wallet cap.
+    WalletCapExceeded,
+    /// The purchase exceeds the tokens left for sale.
+    HardCapExceeded,
+    /// The sale didn't reach the soft cap, buyers get refunds.
+    SoftCapNotReached,
+    /// The sale reached the soft cap, buyers get tokens.
+    SoftCapReached,
+    /// The proceeds were already withdrawn.
+    AlreadyWithdrawn,
+}
+
+impl From<LaunchpadError> for ProgramError {
+    fn from(e: LaunchpadError) -> Self {
+        Namespace::Launchpad.error(e as u32)
+    }
+}
+
+/// On-chain representation of a fixed-price token sale.
+///
+/// The tokens for sale are held by a token account owned by the sale PDA,
+/// the lamports paid by buyers by the sale PDA itself. Buyers get their
+/// tokens only after the end of the sale, and only if it reached the soft
+/// cap. Otherwise they get their lamports back.
+#[repr(C)]
+pub struct Sale {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    /// Price of one token (in base units), in lamports.
+    pub price: u64,
+    /// Lamports to raise for the sale to succeed.
+    pub soft_cap: u64,
+    /// Tokens for sale.
+    pub hard_cap: u64,
+    /// Tokens a single wallet can buy.
+    pub wallet_cap: u64,
+    /// Unix timestamp of the start of the sale.
+    pub start: i64,
+    /// Unix timestamp of the end of the sale.
+    pub end: i64,
+    /// Tokens sold.
+    pub sold: u64,
+    /// Lamports raised.
+    pub raised: u64,
+    /// Tokens claimed by buyers.
+    pub claimed: u64,
+    /// Whether only allowlisted wallets can buy.
+    pub allowlist: u8,
+    /// Whether the authority withdrew the proceeds.
+    pub withdrawn: u8,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+}
+
+assert_layout!(Sale, size = 176, align = 8, {
+    authority: 0,
+    mint: 32,
+    vault: 64,
+    price: 96,
+    soft_cap: 104,
+    hard_cap: 112,
+    wallet_cap: 120,
+    start: 128,
+    end: 136,
+    sold: 144,
+    raised: 152,
+    claimed: 160,
+    allowlist: 168,
+    withdrawn: 169,
+    bump: 170,
+});
+
+impl Sale {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    pub fn succeeded(&self) -> bool {
+        self.raised >= self.soft_cap
+    }
+}
+
+/// On-chain representation of an allowlisted wallet.
+#[repr(C)]
+pub struct AllowlistEntry {
+    pub sale: Pubkey,
+    pub wallet: Pubkey,
+    pub bump: u8,
+}
+
+assert_layout!(AllowlistEntry, size = 65, align = 1, { sale: 0, wallet: 32, bump: 64 });
+
+impl AllowlistEntry {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain representation of the purchases of a buyer.
+#[repr(C)]
+pub struct Purchase {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    /// Tokens bought.
+    pub amount: u64,
+    /// Lamports paid.
+    pub paid: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Purchase, size = 88, align = 8, {
+    sale: 0,
+    buyer: 32,
+    amount: 64,
+    paid: 72,
+    bump: 80,
+});
+
+impl Purchase {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Launchpad program instruction discriminators.
+#[repr(u8)]
+pub enum LaunchpadInstruction {
+    /// Creates a sale.
+    Initialize,
+    /// Adds a wallet to the allowlist of a sale.
+    Allow,
+    /// Buys tokens.
+    Buy,
+    /// Claims the bought tokens of a successful sale.
+    Claim,
+    /// Refunds the lamports paid in a failed sale.
+    Refund,
+    /// Withdraws the proceeds and the unsold tokens.
+    Withdraw,
+}
+
+impl TryFrom<&u8> for LaunchpadInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Allow),
+            2 => Ok(Self::Buy),
+            3 => Ok(Self::Claim),
+            4 => Ok(Self::Refund),
+            5 => Ok(Self::Withdraw),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Parameters of a sale.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct SaleConfig {
+    pub price: u64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub wallet_cap: u64,
+    pub start: i64,
+    pub end: i64,
+}
+
+#[derive(InstructionData)]
+pub struct InitializeInstructionData {
+    pub config: SaleConfig,
+    pub allowlist: u8,
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(config: SaleConfig, allowlist: bool, bump: u8) -> Self {
+        Self {
+            config,
+            allowlist: allowlist as u8,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AllowInstructionData {
+    pub bump: u8,
+}
+
+impl AllowInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct BuyInstructionData {
+    pub amount: u64,
+    /// Bump of the purchase PDA, used on the first purchase.
+    pub bump: u8,
+}
+
+impl BuyInstructionData {
+    pub fn new(amount: u64, bump: u8) -> Self {
+        Self { amount, bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = LaunchpadInstruction::try_from(instruction)?;
+
+    match instruction {
+        LaunchpadInstruction::Initialize => process_initialize(accounts, instruction_data),
+        LaunchpadInstruction::Allow => process_allow(accounts, instruction_data),
+        LaunchpadInstruction::Buy => process_buy(accounts, instruction_data),
+        LaunchpadInstruction::Claim => process_claim(accounts),
+        LaunchpadInstruction::Refund => process_refund(accounts),
+        LaunchpadInstruction::Withdraw => process_withdraw(accounts),
+    }
+}
+
+/// Checks that `sale` is a sale of the program. The program owns purchases
+/// and allowlist entries too, which are told apart by their length.
+fn check_sale(sale: &AccountInfo) -> ProgramResult {
+    require_owned_by(sale, &ID)?;
+    if sale.data_len() != Sale::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Checks that the sale has ended and returns whether it succeeded.
+fn check_ended(sale: &AccountInfo) -> Result<bool, ProgramError> {
+    check_sale(sale)?;
+
+    let data = sale.try_borrow_data()?;
+    let data: &Sale = unsafe { &*data.as_ptr().cast() };
+
+    if Clock::get()?.unix_timestamp < data.end {
+        return Err(LaunchpadError::NotEnded.into());
+    }
+    Ok(data.succeeded())
+}
+
+/// Checks that `purchase` belongs to `sale` and `buyer`, and returns the
+/// bought amount and the paid lamports.
+fn purchase_amounts(
+    sale: &AccountInfo,
+    buyer: &AccountInfo,
+    purchase: &AccountInfo,
+) -> Result<(u64, u64), ProgramError> {
+    require_signer(buyer)?;
+    require_owned_by(purchase, &ID)?;
+    // An allowlist entry of the buyer has the same keys at the same offsets.
+    if purchase.data_len() != Purchase::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let data = purchase.try_borrow_data()?;
+    let data: &Purchase = unsafe { &*data.as_ptr().cast() };
+
+    if !keys_eq(&data.sale, sale.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !keys_eq(&data.buyer, buyer.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok((data.amount, data.paid))
+}
+
+/// Closes `purchase`, returning the rent to `buyer`.
+fn close_purchase(buyer: &AccountInfo, purchase: &AccountInfo) -> ProgramResult {
+    close_account(purchase, buyer)
+}
+
+/// Creates a sale of the tokens held by `vault`, which has to be owned by the
+/// sale PDA and hold at least the hard cap.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, sale, vault, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+    let config = &instruction_data.config;
+
+    if config.price == 0
+        || config.wallet_cap == 0
+        || config.start >= config.end
+        || config.hard_cap.checked_mul(config.price).is_none()
+        || config.soft_cap > config.hard_cap * config.price
+    {
+        return Err(LaunchpadError::InvalidConfig.into());
+    }
+
+    // Check that `vault` is owned by `sale` and holds the tokens for sale.
+    let mint = {
+        let vault = TokenAccount::from_account_info(vault)?;
+        if !keys_eq(vault.owner(), sale.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if vault.amount() < config.hard_cap {
+            return Err(LaunchpadError::InsufficientSaleTokens.into());
+        }
+        *vault.mint()
+    };
+
+    // Check the seeds of `sale`.
+    let sale_pda = create_program_address(
+        &[
+            SALE_SEED.as_bytes(),
+            authority.key(),
+            &mint,
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(sale.key(), &sale_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the sale PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(SALE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&mint),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: sale,
+        lamports: Rent::get()?.minimum_balance(Sale::LEN),
+        space: Sale::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the sale PDA.
+    let mut data = sale.try_borrow_mut_data()?;
+    let data: &mut Sale = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the sale.
+    data.authority = *authority.key();
+    data.mint = mint;
+    data.vault = *vault.key();
+    data.price = config.price;
+    data.soft_cap = config.soft_cap;
+    data.hard_cap = config.hard_cap;
+    data.wallet_cap = config.wallet_cap;
+    data.start = config.start;
+    data.end = config.end;
+    data.allowlist = instruction_data.allowlist;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Initialized sale of {} tokens at {} lamports",
+        config.hard_cap, config.price
+    );
+
+    Ok(())
+}
+
+/// Adds `wallet` to the allowlist of `sale`.
+pub fn process_allow(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, sale, wallet, entry, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Check that `sale` is a sale PDA of the program.
+    check_sale(sale)?;
+    {
+        let data = sale.try_borrow_data()?;
+        let data: &Sale = unsafe { &*data.as_ptr().cast() };
+
+        // Only the authority can manage the allowlist.
+        if !keys_eq(&data.authority, authority.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = AllowInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `entry`.
+    let entry_pda = create_program_address(
+        &[
+            ALLOWLIST_SEED.as_bytes(),
+            sale.key(),
+            wallet.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(entry.key(), &entry_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the allowlist entry PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(ALLOWLIST_SEED.as_bytes()),
+        Seed::from(sale.key()),
+        Seed::from(wallet.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: entry,
+        lamports: Rent::get()?.minimum_balance(AllowlistEntry::LEN),
+        space: AllowlistEntry::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the allowlist entry PDA.
+    let mut data = entry.try_borrow_mut_data()?;
+    let data: &mut AllowlistEntry = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the allowlist entry.
+    data.sale = *sale.key();
+    data.wallet = *wallet.key();
+    data.bump = instruction_data.bump;
+
+    info!("Added wallet to the allowlist");
+
+    Ok(())
+}
+
+/// Buys `amount` tokens, paying the lamports to the sale PDA. The tokens can
+/// be claimed after the end of the sale.
+///
+/// Sales with an allowlist need the allowlist entry of the buyer as an
+/// additional account.
+pub fn process_buy(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [buyer, sale, purchase, _system_program, allowlist_accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(buyer)?;
+
+    // Check that `sale` is a sale PDA of the program.
+    check_sale(sale)?;
+
+    // Deserialize instruction data.
+    let instruction_data = BuyInstructionData::from_bytes(instruction_data)?;
+
+    // Check the time window, the allowlist and the hard cap.
+    let cost = {
+        let data = sale.try_borrow_data()?;
+        let data: &Sale = unsafe { &*data.as_ptr().cast() };
+
+        let now = Clock::get()?.unix_timestamp;
+        if now < data.start {
+            return Err(LaunchpadError::NotStarted.into());
+        }
+        if now >= data.end {
+            return Err(LaunchpadError::Ended.into());
+        }
+
+        if data.allowlist != 0 {
+            let [entry, ..] = allowlist_accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            check_allowlisted(sale, buyer, entry)?;
+        }
+
+        if instruction_data.amount > data.hard_cap - data.sold {
+            return Err(LaunchpadError::HardCapExceeded.into());
+        }
+        instruction_data.amount * data.price
+    };
+
+    // Create the purchase PDA on the first purchase.
+    if !purchase.is_owned_by(&ID) {
+        create_purchase(buyer, sale, purchase, instruction_data.bump)?;
+    }
+
+    SystemTransfer {
+        from: buyer,
+        to: sale,
+        lamports: cost,
+    }
+    .invoke()?;
+
+    // Deserialize the purchase PDA.
+    if purchase.data_len() != Purchase::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut purchase_data = purchase.try_borrow_mut_data()?;
+    let purchase_data: &mut Purchase = unsafe { &mut *purchase_data.as_mut_ptr().cast() };
+
+    if !keys_eq(&purchase_data.sale, sale.key()) || !keys_eq(&purchase_data.buyer, buyer.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Deserialize the sale PDA.
+    let mut data = sale.try_borrow_mut_data()?;
+    let data: &mut Sale = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // The cap applies to all purchases of the buyer together.
+    if instruction_data.amount > data.wallet_cap - purchase_data.amount {
+        return Err(LaunchpadError::WalletCapExceeded.into());
+    }
+
+    purchase_data.amount += instruction_data.amount;
+    purchase_data.paid += cost;
+    data.sold += instruction_data.amount;
+    data.raised += cost;
+
+    info!(
+        "Bought {} tokens for {} lamports",
+        instruction_data.amount, cost
+    );
+
+    Ok(())
+}
+
+/// Checks that `entry` is the allowlist entry of `buyer` in `sale`.
+fn check_allowlisted(
+    sale: &AccountInfo,
+    buyer: &AccountInfo,
+    entry: &AccountInfo,
+) -> ProgramResult {
+    if !entry.is_owned_by(&ID) || entry.data_len() != AllowlistEntry::LEN {
+        return Err(LaunchpadError::NotAllowlisted.into());
+    }
+
+    let data = entry.try_borrow_data()?;
+    let data: &AllowlistEntry = unsafe { &*data.as_ptr().cast() };
+
+    if !keys_eq(&data.sale, sale.key()) || !keys_eq(&data.wallet, buyer.key()) {
+        return Err(LaunchpadError::NotAllowlisted.into());
+    }
+    Ok(())
+}
+
+/// Creates the purchase PDA of `buyer`.
+fn create_purchase(
+    buyer: &AccountInfo,
+    sale: &AccountInfo,
+    purchase: &AccountInfo,
+    bump: u8,
+) -> ProgramResult {
+    // Check the seeds of `purchase`.
+    let purchase_pda = create_program_address(
+        &[PURCHASE_SEED.as_bytes(), sale.key(), buyer.key(), &[bump]],
+        &ID,
+    )?;
+    if !keys_eq(purchase.key(), &purchase_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the purchase PDA.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(PURCHASE_SEED.as_bytes()),
+        Seed::from(sale.key()),
+        Seed::from(buyer.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: buyer,
+        to: purchase,
+        lamports: Rent::get()?.minimum_balance(Purchase::LEN),
+        space: Purchase::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the purchase PDA.
+    let mut data = purchase.try_borrow_mut_data()?;
+    let data: &mut Purchase = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the purchase.
+    data.sale = *sale.key();
+    data.buyer = *buyer.key();
+    data.bump = bump[0];
+
+    Ok(())
+}
+
+/// Transfers the bought tokens to `buyer_ata` after a successful sale and
+/// closes the purchase PDA.
+pub fn process_claim(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [buyer, buyer_ata, sale, purchase, vault, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !check_ended(sale)? {
+        return Err(LaunchpadError::SoftCapNotReached.into());
+    }
+    let (amount, _) = purchase_amounts(sale, buyer, purchase)?;
+
+    let (authority, mint, bump) = {
+        let mut data = sale.try_borrow_mut_data()?;
+        let data: &mut Sale = unsafe { &mut *data.as_mut_ptr().cast() };
+
+        if !keys_eq(&data.vault, vault.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        data.claimed += amount;
+
+        (data.authority, data.mint, data.bump)
+    };
+
+    // Transfer the tokens from the vault to the buyer.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(SALE_SEED.as_bytes()),
+        Seed::from(&authority),
+        Seed::from(&mint),
+        Seed::from(&bump),
+    ];
+    Transfer {
+        from: vault,
+        to: buyer_ata,
+        authority: sale,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    close_purchase(buyer, purchase)?;
+
+    info!("Claimed {} tokens", amount);
+
+    Ok(())
+}
+
+/// Refunds the lamports paid by `buyer` after a failed sale and closes the
+/// purchase PDA.
+pub fn process_refund(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [buyer, sale, purchase] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if check_ended(sale)? {
+        return Err(LaunchpadError::SoftCapReached.into());
+    }
+    let (_, paid) = purchase_amounts(sale, buyer, purchase)?;
+
+    // The sale PDA is owned by the program, so the refund can be paid
+    // without a CPI.
+    *sale.try_borrow_mut_lamports()? -= paid;
+    *buyer.try_borrow_mut_lamports()? += paid;
+
+    close_purchase(buyer, purchase)?;
+
+    info!("Refunded {} lamports", paid);
+
+    Ok(())
+}
+
+/// Withdraws the tokens which buyers can't claim to `authority_ata` and,
+/// after a successful sale, the raised lamports to `authority`.
+pub fn process_withdraw(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, authority_ata, sale, vault, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    let succeeded = check_ended(sale)?;
+
+    let (raised, mint, bump) = {
+        let mut data = sale.try_borrow_mut_data()?;
+        let data: &mut Sale = unsafe { &mut *data.as_mut_ptr().cast() };
+
+        if !keys_eq(&data.authority, authority.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if !keys_eq(&data.vault, vault.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if data.withdrawn != 0 {
+            return Err(LaunchpadError::AlreadyWithdrawn.into());
+        }
+        data.withdrawn = 1;
+
+        // After a failed sale, the raised lamports stay for refunds.
+        let raised = if succeeded { data.raised } else { 0 };
+        (raised, data.mint, data.bump)
+    };
+
+    // Keep the tokens which buyers still have to claim.
+    let unsold = {
+        let data = sale.try_borrow_data()?;
+        let data: &Sale = unsafe { &*data.as_ptr().cast() };
+
+        let owed = if succeeded {
+            data.sold - data.claimed
+        } else {
+            0
+        };
+        TokenAccount::from_account_info(vault)?.amount() - owed
+    };
+
+    let bump = [bump];
+    let seeds = [
+        Seed::from(SALE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&mint),
+        Seed::from(&bump),
+    ];
+    if unsold > 0 {
+        Transfer {
+            from: vault,
+            to: authority_ata,
+            authority: sale,
+            amount: unsold,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+    }
+
+    *sale.try_borrow_mut_lamports()? -= raised;
+    *authority.try_borrow_mut_lamports()? += raised;
+
+    info!("Withdrew {} lamports and {} tokens", raised, unsold);
+
+    Ok(())
+}
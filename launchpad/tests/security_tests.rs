@@ -0,0 +1,461 @@
+//! Attacks on the launchpad program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use launchpad::{
+    AllowInstructionData, AllowlistEntry, BuyInstructionData, LaunchpadError, LaunchpadInstruction,
+    Purchase, Sale, ALLOWLIST_SEED, PURCHASE_SEED, SALE_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(launchpad::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+const PRICE: u64 = 1_000;
+const SOFT_CAP: u64 = 200 * PRICE;
+const HARD_CAP: u64 = 1_000;
+const WALLET_CAP: u64 = 300;
+const START: i64 = 1_000_000;
+const END: i64 = START + 3_600;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: LaunchpadInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<LaunchpadInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns an account of the program holding `data`.
+fn program_account(mollusk: &Mollusk, data: Vec<u8>) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns the purchase PDA of `buyer` in `sale` and its bump.
+fn purchase_pda(sale: &Pubkey, buyer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[PURCHASE_SEED.as_bytes(), sale.as_ref(), buyer.as_ref()],
+        &ID,
+    )
+}
+
+/// Returns the purchase PDA of `buyer` in `sale` and its account, for
+/// [`WALLET_CAP`] tokens.
+fn purchase_account(mollusk: &Mollusk, sale: &Pubkey, buyer: &Pubkey) -> (Pubkey, Account) {
+    let (purchase, bump) = purchase_pda(sale, buyer);
+    let mut data = vec![0; Purchase::LEN];
+    data[0..32].copy_from_slice(sale.as_ref());
+    data[32..64].copy_from_slice(buyer.as_ref());
+    data[64..72].copy_from_slice(&WALLET_CAP.to_le_bytes());
+    data[72..80].copy_from_slice(&(WALLET_CAP * PRICE).to_le_bytes());
+    data[80] = bump;
+    (purchase, program_account(mollusk, data))
+}
+
+/// Accounts of a successful sale with an allowlist, in which the buyer
+/// bought [`WALLET_CAP`] tokens, and of an allowlisted attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    authority_ata: Pubkey,
+    mint: Pubkey,
+    sale: Pubkey,
+    vault: Pubkey,
+    buyer: Pubkey,
+    buyer_ata: Pubkey,
+    purchase: Pubkey,
+    attacker: Pubkey,
+    attacker_ata: Pubkey,
+    attacker_entry: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/launchpad");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+        mollusk.sysvars.clock.unix_timestamp = START;
+
+        let authority = Pubkey::new_unique();
+        let authority_ata = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (sale, sale_bump) = Pubkey::find_program_address(
+            &[SALE_SEED.as_bytes(), authority.as_ref(), mint.as_ref()],
+            &ID,
+        );
+        let vault = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let buyer_ata = Pubkey::new_unique();
+        let (purchase, purchase_account) = purchase_account(&mollusk, &sale, &buyer);
+        let attacker = Pubkey::new_unique();
+        let attacker_ata = Pubkey::new_unique();
+        let (attacker_entry, attacker_entry_bump) = Pubkey::find_program_address(
+            &[ALLOWLIST_SEED.as_bytes(), sale.as_ref(), attacker.as_ref()],
+            &ID,
+        );
+
+        let mut sale_data = vec![0; Sale::LEN];
+        sale_data[0..32].copy_from_slice(authority.as_ref());
+        sale_data[32..64].copy_from_slice(mint.as_ref());
+        sale_data[64..96].copy_from_slice(vault.as_ref());
+        sale_data[96..104].copy_from_slice(&PRICE.to_le_bytes());
+        sale_data[104..112].copy_from_slice(&SOFT_CAP.to_le_bytes());
+        sale_data[112..120].copy_from_slice(&HARD_CAP.to_le_bytes());
+        sale_data[120..128].copy_from_slice(&WALLET_CAP.to_le_bytes());
+        sale_data[128..136].copy_from_slice(&START.to_le_bytes());
+        sale_data[136..144].copy_from_slice(&END.to_le_bytes());
+        sale_data[144..152].copy_from_slice(&WALLET_CAP.to_le_bytes());
+        sale_data[152..160].copy_from_slice(&(WALLET_CAP * PRICE).to_le_bytes());
+        sale_data[168] = 1;
+        sale_data[170] = sale_bump;
+        let mut sale_account = program_account(&mollusk, sale_data);
+        sale_account.lamports += WALLET_CAP * PRICE;
+
+        let mut entry_data = vec![0; AllowlistEntry::LEN];
+        entry_data[0..32].copy_from_slice(sale.as_ref());
+        entry_data[32..64].copy_from_slice(attacker.as_ref());
+        entry_data[64] = attacker_entry_bump;
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (authority_ata, token_account(&mollusk, &mint, &authority, 0)),
+            (sale, sale_account),
+            (vault, token_account(&mollusk, &mint, &sale, HARD_CAP)),
+            (buyer, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (buyer_ata, token_account(&mollusk, &mint, &buyer, 0)),
+            (purchase, purchase_account),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (attacker_ata, token_account(&mollusk, &mint, &attacker, 0)),
+            (attacker_entry, program_account(&mollusk, entry_data)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            authority_ata,
+            mint,
+            sale,
+            vault,
+            buyer,
+            buyer_ata,
+            purchase,
+            attacker,
+            attacker_ata,
+            attacker_entry,
+            accounts,
+        }
+    }
+
+    /// Moves the clock to the end of the sale.
+    fn ended(mut self) -> Self {
+        self.mollusk.sysvars.clock.unix_timestamp = END;
+        self
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    fn instruction_allow(&self, authority: &Pubkey, wallet: &Pubkey) -> Instruction {
+        let (entry, bump) = Pubkey::find_program_address(
+            &[
+                ALLOWLIST_SEED.as_bytes(),
+                self.sale.as_ref(),
+                wallet.as_ref(),
+            ],
+            &ID,
+        );
+        let data = AllowInstructionData::new(bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(self.sale, false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new(entry, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LaunchpadInstruction::Allow, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_buy(
+        &self,
+        buyer: &Pubkey,
+        purchase: &Pubkey,
+        bump: u8,
+        entry: &Pubkey,
+    ) -> Instruction {
+        let data = BuyInstructionData::new(100, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(self.sale, false),
+            AccountMeta::new(*purchase, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(*entry, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LaunchpadInstruction::Buy, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_claim(&self, buyer: &Pubkey, buyer_ata: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*buyer_ata, false),
+            AccountMeta::new(self.sale, false),
+            AccountMeta::new(self.purchase, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[LaunchpadInstruction::Claim as u8], ix_accounts)
+    }
+
+    fn instruction_refund(&self, sale: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.buyer, true),
+            AccountMeta::new(*sale, false),
+            AccountMeta::new(self.purchase, false),
+        ];
+        Instruction::new_with_bytes(ID, &[LaunchpadInstruction::Refund as u8], ix_accounts)
+    }
+
+    fn instruction_withdraw(&self, authority: &Pubkey, authority_ata: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*authority_ata, false),
+            AccountMeta::new(self.sale, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[LaunchpadInstruction::Withdraw as u8], ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+fn launchpad_error(err: LaunchpadError) -> ProgramError {
+    ProgramError::Custom(Namespace::Launchpad.code(err as u32))
+}
+
+#[test]
+fn test_missing_buyer_signature() {
+    let setup = Setup::new();
+    let (purchase, bump) = purchase_pda(&setup.sale, &setup.attacker);
+    let mut instruction =
+        setup.instruction_buy(&setup.attacker, &purchase, bump, &setup.attacker_entry);
+    instruction.accounts[0].is_signer = false;
+    let setup = setup.with(purchase, Account::default());
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker adds themselves to the allowlist.
+#[test]
+fn test_allow_by_attacker() {
+    let setup = Setup::new();
+    let wallet = Pubkey::new_unique();
+    let instruction = setup.instruction_allow(&setup.attacker, &wallet);
+    let entry = instruction.accounts[3].pubkey;
+    let setup = setup
+        .with(wallet, Account::default())
+        .with(entry, Account::default());
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// The attacker withdraws the proceeds of the sale.
+#[test]
+fn test_withdraw_by_attacker() {
+    let setup = Setup::new().ended();
+    setup.expect_err(
+        &setup.instruction_withdraw(&setup.attacker, &setup.attacker_ata),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker claims the tokens bought by the buyer.
+#[test]
+fn test_claim_purchase_of_other_buyer() {
+    let setup = Setup::new().ended();
+    setup.expect_err(
+        &setup.instruction_claim(&setup.attacker, &setup.attacker_ata),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A purchase of another sale of the same authority and mint would be paid
+/// out from the vault of this one.
+#[test]
+fn test_purchase_of_other_sale() {
+    let setup = Setup::new().ended();
+    let (_, other_purchase) = purchase_account(&setup.mollusk, &Pubkey::new_unique(), &setup.buyer);
+    let purchase = setup.purchase;
+    let setup = setup.with(purchase, other_purchase);
+    setup.expect_err(
+        &setup.instruction_claim(&setup.buyer, &setup.buyer_ata),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The buyer passes their purchase as the sale, which would be read past
+/// the end of its data.
+#[test]
+fn test_purchase_as_sale() {
+    let setup = Setup::new().ended();
+    setup.expect_err(
+        &setup.instruction_refund(&setup.purchase),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A sale with the layout of the program, but owned by another one, e.g.
+/// with a lower price.
+#[test]
+fn test_spoofed_sale() {
+    let setup = Setup::new();
+    let sale = setup.sale;
+    let mut sale_account = setup.account(&sale);
+    sale_account.owner = Pubkey::new_unique();
+    let (purchase, bump) = purchase_pda(&sale, &setup.attacker);
+    let instruction =
+        setup.instruction_buy(&setup.attacker, &purchase, bump, &setup.attacker_entry);
+    let setup = setup
+        .with(sale, sale_account)
+        .with(purchase, Account::default());
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// A claim or a withdrawal from a token account of the sale PDA other than
+/// its vault, e.g. the vault of another sale of the same authority.
+#[test]
+fn test_wrong_vault() {
+    let mut setup = Setup::new().ended();
+    let other_vault = Pubkey::new_unique();
+    let other_vault_account = token_account(&setup.mollusk, &setup.mint, &setup.sale, HARD_CAP);
+    setup.vault = other_vault;
+    let setup = setup.with(other_vault, other_vault_account);
+    setup.expect_err(
+        &setup.instruction_claim(&setup.buyer, &setup.buyer_ata),
+        ProgramError::InvalidAccountData,
+    );
+    setup.expect_err(
+        &setup.instruction_withdraw(&setup.authority, &setup.authority_ata),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A first purchase at an address other than the PDA of the buyer, which
+/// would let them exceed the wallet cap with several purchases.
+#[test]
+fn test_wrong_purchase_seeds() {
+    let setup = Setup::new();
+    let purchase = Pubkey::new_unique();
+    let (_, bump) = purchase_pda(&setup.sale, &setup.attacker);
+    let instruction =
+        setup.instruction_buy(&setup.attacker, &purchase, bump, &setup.attacker_entry);
+    let setup = setup.with(purchase, Account::default());
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// An allowlist entry with the layout of the program, but owned by another
+/// one.
+#[test]
+fn test_spoofed_allowlist_entry() {
+    let setup = Setup::new();
+    let wallet = Pubkey::new_unique();
+    let (purchase, bump) = purchase_pda(&setup.sale, &wallet);
+    let fake_entry = Pubkey::new_unique();
+    let mut entry_data = vec![0; AllowlistEntry::LEN];
+    entry_data[0..32].copy_from_slice(setup.sale.as_ref());
+    entry_data[32..64].copy_from_slice(wallet.as_ref());
+    let mut entry_account = program_account(&setup.mollusk, entry_data);
+    entry_account.owner = Pubkey::new_unique();
+    let instruction = setup.instruction_buy(&wallet, &purchase, bump, &fake_entry);
+    let setup = setup
+        .with(
+            wallet,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        )
+        .with(purchase, Account::default())
+        .with(fake_entry, entry_account);
+    setup.expect_err(
+        &instruction,
+        launchpad_error(LaunchpadError::NotAllowlisted),
+    );
+}
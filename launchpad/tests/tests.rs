@@ -0,0 +1,659 @@
+use std::{cell::RefCell, mem};
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use launchpad::{
+    AllowInstructionData, AllowlistEntry, BuyInstructionData, InitializeInstructionData,
+    LaunchpadError, LaunchpadInstruction, Purchase, Sale, SaleConfig, ALLOWLIST_SEED,
+    PURCHASE_SEED, SALE_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(launchpad::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offsets of the sale account fields.
+const SOLD_OFFSET: usize = 144;
+const RAISED_OFFSET: usize = 152;
+const WITHDRAWN_OFFSET: usize = 169;
+
+const PRICE: u64 = 1_000;
+const SOFT_CAP: u64 = 200 * PRICE;
+const HARD_CAP: u64 = 1_000;
+const WALLET_CAP: u64 = 300;
+const START: i64 = 1_000_000;
+const END: i64 = START + 3_600;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: LaunchpadInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<LaunchpadInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/launchpad");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk.sysvars.clock.unix_timestamp = START;
+    mollusk
+}
+
+fn config() -> SaleConfig {
+    SaleConfig {
+        price: PRICE,
+        soft_cap: SOFT_CAP,
+        hard_cap: HARD_CAP,
+        wallet_cap: WALLET_CAP,
+        start: START,
+        end: END,
+    }
+}
+
+fn mint_account(mollusk: &Mollusk) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply: HARD_CAP,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn sale_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    vault: &Pubkey,
+    allowlist: bool,
+    bump: u8,
+) -> Account {
+    let mut data = vec![0; Sale::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64..96].copy_from_slice(vault.as_ref());
+    data[96..104].copy_from_slice(&PRICE.to_le_bytes());
+    data[104..112].copy_from_slice(&SOFT_CAP.to_le_bytes());
+    data[112..120].copy_from_slice(&HARD_CAP.to_le_bytes());
+    data[120..128].copy_from_slice(&WALLET_CAP.to_le_bytes());
+    data[128..136].copy_from_slice(&START.to_le_bytes());
+    data[136..144].copy_from_slice(&END.to_le_bytes());
+    data[168] = allowlist as u8;
+    data[170] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Sale::LEN),
+        Sale::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// A buyer with an empty token account.
+struct Buyer {
+    key: Pubkey,
+    ata: Pubkey,
+    purchase: Pubkey,
+    bump: u8,
+}
+
+impl Buyer {
+    fn new(sale: &Pubkey) -> Self {
+        let key = Pubkey::new_unique();
+        let (purchase, bump) = Pubkey::find_program_address(
+            &[PURCHASE_SEED.as_bytes(), sale.as_ref(), key.as_ref()],
+            &ID,
+        );
+        Self {
+            key,
+            ata: Pubkey::new_unique(),
+            purchase,
+            bump,
+        }
+    }
+}
+
+/// An open sale holding the tokens for sale and a buyer.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    authority_ata: Pubkey,
+    sale: Pubkey,
+    vault: Pubkey,
+    buyer: Buyer,
+    accounts: RefCell<Vec<(Pubkey, Account)>>,
+}
+
+impl Setup {
+    fn new(allowlist: bool) -> Self {
+        let mollusk = mollusk();
+
+        let authority = Pubkey::new_unique();
+        let authority_ata = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (sale, sale_bump) = Pubkey::find_program_address(
+            &[SALE_SEED.as_bytes(), authority.as_ref(), mint.as_ref()],
+            &ID,
+        );
+        let vault = Pubkey::new_unique();
+        let buyer = Buyer::new(&sale);
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (authority_ata, token_account(&mollusk, &mint, &authority, 0)),
+            (mint, mint_account(&mollusk)),
+            (
+                sale,
+                sale_account(&mollusk, &authority, &mint, &vault, allowlist, sale_bump),
+            ),
+            (vault, token_account(&mollusk, &mint, &sale, HARD_CAP)),
+            (
+                buyer.key,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (buyer.ata, token_account(&mollusk, &mint, &buyer.key, 0)),
+            (buyer.purchase, Account::default()),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            authority_ata,
+            sale,
+            vault,
+            buyer,
+            accounts: RefCell::new(accounts),
+        }
+    }
+
+    /// Processes `instruction` and keeps the resulting accounts.
+    fn process(&self, instruction: &Instruction, checks: &[Check]) {
+        let res = self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts.borrow(),
+            checks,
+        );
+        if matches!(res.program_result, ProgramResult::Success) {
+            *self.accounts.borrow_mut() = res.resulting_accounts;
+        }
+    }
+
+    fn instruction_allow(&self, wallet: &Pubkey) -> (Pubkey, Instruction) {
+        let (entry, bump) = Pubkey::find_program_address(
+            &[
+                ALLOWLIST_SEED.as_bytes(),
+                self.sale.as_ref(),
+                wallet.as_ref(),
+            ],
+            &ID,
+        );
+        let data = AllowInstructionData::new(bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new_readonly(self.sale, false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new(entry, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        let instruction = Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LaunchpadInstruction::Allow, &data),
+            ix_accounts,
+        );
+        (entry, instruction)
+    }
+
+    fn instruction_buy(&self, amount: u64, allowlist_entry: Option<&Pubkey>) -> Instruction {
+        let data = BuyInstructionData::new(amount, self.buyer.bump);
+        let mut ix_accounts = vec![
+            AccountMeta::new(self.buyer.key, true),
+            AccountMeta::new(self.sale, false),
+            AccountMeta::new(self.buyer.purchase, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        ix_accounts.extend(
+            allowlist_entry
+                .iter()
+                .map(|entry| AccountMeta::new_readonly(**entry, false)),
+        );
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LaunchpadInstruction::Buy, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_claim(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.buyer.key, true),
+            AccountMeta::new(self.buyer.ata, false),
+            AccountMeta::new(self.sale, false),
+            AccountMeta::new(self.buyer.purchase, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[LaunchpadInstruction::Claim as u8], ix_accounts)
+    }
+
+    fn instruction_refund(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.buyer.key, true),
+            AccountMeta::new(self.sale, false),
+            AccountMeta::new(self.buyer.purchase, false),
+        ];
+        Instruction::new_with_bytes(ID, &[LaunchpadInstruction::Refund as u8], ix_accounts)
+    }
+
+    fn instruction_withdraw(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new(self.authority_ata, false),
+            AccountMeta::new(self.sale, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[LaunchpadInstruction::Withdraw as u8], ix_accounts)
+    }
+}
+
+#[test]
+fn test_launchpad_initialize() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let (sale, bump) = Pubkey::find_program_address(
+        &[SALE_SEED.as_bytes(), authority.as_ref(), mint.as_ref()],
+        &ID,
+    );
+    let vault = Pubkey::new_unique();
+
+    let instruction = |config| {
+        let data = InitializeInstructionData::new(config, true, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(sale, false),
+            AccountMeta::new_readonly(vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LaunchpadInstruction::Initialize, &data),
+            ix_accounts,
+        )
+    };
+
+    let accounts = [
+        (
+            authority,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        ),
+        (sale, Account::default()),
+        (vault, token_account(&mollusk, &mint, &sale, HARD_CAP)),
+        keyed_account_for_system_program(),
+    ];
+    for (sale_config, err) in [
+        (
+            SaleConfig {
+                end: START,
+                ..config()
+            },
+            LaunchpadError::InvalidConfig,
+        ),
+        (
+            SaleConfig {
+                hard_cap: HARD_CAP + 1,
+                ..config()
+            },
+            LaunchpadError::InsufficientSaleTokens,
+        ),
+    ] {
+        mollusk.process_and_validate_instruction(
+            &instruction(sale_config),
+            &accounts,
+            &[Check::err(ProgramError::Custom(
+                Namespace::Launchpad.code(err as u32),
+            ))],
+        );
+    }
+    let res = mollusk.process_and_validate_instruction(
+        &instruction(config()),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&sale)
+                .owner(&ID)
+                .data(&sale_account(&mollusk, &authority, &mint, &vault, true, bump).data)
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_launchpad_buy_time_window() {
+    let mut setup = Setup::new(false);
+
+    setup.mollusk.sysvars.clock.unix_timestamp = START - 1;
+    setup.process(
+        &setup.instruction_buy(100, None),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::NotStarted as u32),
+        ))],
+    );
+
+    setup.mollusk.sysvars.clock.unix_timestamp = END;
+    setup.process(
+        &setup.instruction_buy(100, None),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::Ended as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_launchpad_buy_caps() {
+    let setup = Setup::new(false);
+
+    setup.process(
+        &setup.instruction_buy(200, None),
+        &[
+            Check::success(),
+            Check::account(&setup.buyer.purchase)
+                .owner(&ID)
+                .space(Purchase::LEN)
+                .data_slice(0, setup.sale.as_ref())
+                .data_slice(32, setup.buyer.key.as_ref())
+                .data_slice(64, &200u64.to_le_bytes())
+                .data_slice(72, &(200 * PRICE).to_le_bytes())
+                .build(),
+        ],
+    );
+    // The wallet cap counts all purchases.
+    setup.process(
+        &setup.instruction_buy(WALLET_CAP - 199, None),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::WalletCapExceeded as u32),
+        ))],
+    );
+    setup.process(
+        &setup.instruction_buy(WALLET_CAP - 200, None),
+        &[
+            Check::success(),
+            Check::account(&setup.buyer.purchase)
+                .data_slice(64, &WALLET_CAP.to_le_bytes())
+                .build(),
+            Check::account(&setup.sale)
+                .data_slice(SOLD_OFFSET, &WALLET_CAP.to_le_bytes())
+                .data_slice(RAISED_OFFSET, &(WALLET_CAP * PRICE).to_le_bytes())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_launchpad_buy_hard_cap() {
+    let setup = Setup::new(false);
+
+    // Almost everything is sold already.
+    let mut accounts = setup.accounts.borrow_mut();
+    let sale = accounts
+        .iter_mut()
+        .find(|(key, _)| key == &setup.sale)
+        .unwrap();
+    sale.1.data[SOLD_OFFSET..SOLD_OFFSET + 8].copy_from_slice(&(HARD_CAP - 100).to_le_bytes());
+    drop(accounts);
+
+    setup.process(
+        &setup.instruction_buy(101, None),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::HardCapExceeded as u32),
+        ))],
+    );
+    setup.process(&setup.instruction_buy(100, None), &[Check::success()]);
+}
+
+#[test]
+fn test_launchpad_allowlist() {
+    let setup = Setup::new(true);
+
+    let other = Pubkey::new_unique();
+    let (other_entry, allow_other) = setup.instruction_allow(&other);
+    let (entry, allow) = setup.instruction_allow(&setup.buyer.key);
+    setup.accounts.borrow_mut().extend([
+        (other, Account::default()),
+        (other_entry, Account::default()),
+        (entry, Account::default()),
+    ]);
+
+    setup.process(
+        &setup.instruction_buy(100, None),
+        &[Check::err(ProgramError::NotEnoughAccountKeys)],
+    );
+
+    // The entry of another wallet doesn't let the buyer in.
+    setup.process(&allow_other, &[Check::success()]);
+    setup.process(
+        &setup.instruction_buy(100, Some(&other_entry)),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::NotAllowlisted as u32),
+        ))],
+    );
+
+    setup.process(
+        &allow,
+        &[
+            Check::success(),
+            Check::account(&entry)
+                .owner(&ID)
+                .space(AllowlistEntry::LEN)
+                .data_slice(0, setup.sale.as_ref())
+                .data_slice(32, setup.buyer.key.as_ref())
+                .build(),
+        ],
+    );
+    setup.process(
+        &setup.instruction_buy(100, Some(&entry)),
+        &[Check::success()],
+    );
+}
+
+/// The allowlist entry of the buyer has the sale and the buyer at the same
+/// offsets as a purchase, but must not be claimed as one.
+#[test]
+fn test_launchpad_claim_with_allowlist_entry() {
+    let mut setup = Setup::new(true);
+
+    let (entry, allow) = setup.instruction_allow(&setup.buyer.key);
+    setup
+        .accounts
+        .borrow_mut()
+        .push((entry, Account::default()));
+    setup.process(&allow, &[Check::success()]);
+    setup.process(
+        &setup.instruction_buy(WALLET_CAP, Some(&entry)),
+        &[Check::success()],
+    );
+
+    setup.mollusk.sysvars.clock.unix_timestamp = END;
+    let mut claim = setup.instruction_claim();
+    claim.accounts[3].pubkey = entry;
+    setup.process(&claim, &[Check::err(ProgramError::InvalidAccountData)]);
+}
+
+#[test]
+fn test_launchpad_successful_sale() {
+    let mut setup = Setup::new(false);
+    let sale_rent = setup.mollusk.sysvars.rent.minimum_balance(Sale::LEN);
+    let raised = WALLET_CAP * PRICE;
+
+    setup.process(
+        &setup.instruction_buy(WALLET_CAP, None),
+        &[Check::success()],
+    );
+
+    setup.process(
+        &setup.instruction_claim(),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::NotEnded as u32),
+        ))],
+    );
+
+    setup.mollusk.sysvars.clock.unix_timestamp = END;
+    setup.process(
+        &setup.instruction_refund(),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::SoftCapReached as u32),
+        ))],
+    );
+    // The authority can withdraw before the buyer claims, the bought tokens
+    // stay in the vault.
+    setup.process(
+        &setup.instruction_withdraw(),
+        &[
+            Check::success(),
+            Check::account(&setup.authority)
+                .lamports(LAMPORTS_PER_SOL + raised)
+                .build(),
+            Check::account(&setup.authority_ata)
+                .data_slice(AMOUNT_OFFSET, &(HARD_CAP - WALLET_CAP).to_le_bytes())
+                .build(),
+            Check::account(&setup.sale)
+                .lamports(sale_rent)
+                .data_slice(WITHDRAWN_OFFSET, &[1])
+                .build(),
+        ],
+    );
+    setup.process(
+        &setup.instruction_withdraw(),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::AlreadyWithdrawn as u32),
+        ))],
+    );
+    setup.process(
+        &setup.instruction_claim(),
+        &[
+            Check::success(),
+            Check::account(&setup.buyer.ata)
+                .data_slice(AMOUNT_OFFSET, &WALLET_CAP.to_le_bytes())
+                .build(),
+            Check::account(&setup.vault)
+                .data_slice(AMOUNT_OFFSET, &0u64.to_le_bytes())
+                .build(),
+            Check::account(&setup.buyer.purchase).closed().build(),
+        ],
+    );
+}
+
+#[test]
+fn test_launchpad_failed_sale() {
+    let mut setup = Setup::new(false);
+    let sale_rent = setup.mollusk.sysvars.rent.minimum_balance(Sale::LEN);
+    let paid = 100 * PRICE;
+
+    setup.process(&setup.instruction_buy(100, None), &[Check::success()]);
+
+    setup.mollusk.sysvars.clock.unix_timestamp = END;
+    setup.process(
+        &setup.instruction_claim(),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Launchpad.code(LaunchpadError::SoftCapNotReached as u32),
+        ))],
+    );
+    // All the tokens go back to the authority, the lamports stay for
+    // refunds.
+    setup.process(
+        &setup.instruction_withdraw(),
+        &[
+            Check::success(),
+            Check::account(&setup.authority)
+                .lamports(LAMPORTS_PER_SOL)
+                .build(),
+            Check::account(&setup.authority_ata)
+                .data_slice(AMOUNT_OFFSET, &HARD_CAP.to_le_bytes())
+                .build(),
+            Check::account(&setup.sale)
+                .lamports(sale_rent + paid)
+                .build(),
+        ],
+    );
+    setup.process(
+        &setup.instruction_refund(),
+        &[
+            Check::success(),
+            // The buyer gets back the payment and the rent of the purchase
+            // PDA.
+            Check::account(&setup.buyer.key)
+                .lamports(LAMPORTS_PER_SOL)
+                .build(),
+            Check::account(&setup.sale).lamports(sale_rent).build(),
+            Check::account(&setup.buyer.purchase).closed().build(),
+        ],
+    );
+}
@@ -0,0 +1,242 @@
+//! Leaderboard of the counters of the `counter` example.
+//!
+//! The leaderboard PDA keeps the [`LEADERBOARD_LEN`] counters with the
+//! highest counts in a fixed-size array, sorted by count. Anyone can submit
+//! a counter with [`LeaderboardInstruction::SubmitScore`]. Its count is read
+//! from the counter account, so the submitter can't make it up, and the
+//! counter is inserted at its rank, evicting the last entry of a full
+//! leaderboard. A counter already on the leaderboard moves to the rank of
+//! its current count instead, also down after it was decremented.
+//!
+//! Like `counter-reader`, the program never calls the counter program. It
+//! reads counter accounts with the layout of the `counter` crate, after
+//! [`AccountData::load`] checked that the counter program owns them.
+//!
+//! The entries are sorted in place, in the account data, without copying
+//! the leaderboard to the stack. Inserting an entry shifts the ones below it
+//! down by one, removing an entry shifts them up.
+
+#![no_std]
+
+use common::{
+    assert_layout, check_pda,
+    checks::{require_program, require_signer},
+    create::create_account,
+    info, AccountData,
+};
+use counter::Counter;
+use errors::Namespace;
+use macros::{InstructionData, InstructionDiscriminator};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("7cVkBhZzX2Ykv3QXqBzRrWVqDzUcgk4g3HFmtV4o9LbA");
+
+pub const LEADERBOARD_SEED: &str = "leaderboard";
+
+/// Number of counters on the leaderboard.
+pub const LEADERBOARD_LEN: usize = 10;
+
+/// Errors returned by the leaderboard program.
+#[derive(Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum LeaderboardError {
+    /// The leaderboard is full and the count doesn't beat its last entry.
+    ScoreTooLow,
+}
+
+impl From<LeaderboardError> for ProgramError {
+    fn from(e: LeaderboardError) -> Self {
+        Namespace::Leaderboard.error(e as u32)
+    }
+}
+
+/// Counter on the leaderboard, with the count it had when it was last
+/// submitted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Entry {
+    pub counter: Pubkey,
+    pub count: u64,
+}
+
+assert_layout!(Entry, size = 40, align = 8, { counter: 0, count: 32 });
+
+/// On-chain representation of the leaderboard.
+#[repr(C)]
+pub struct Leaderboard {
+    /// Number of entries in use, at the start of `entries`.
+    pub len: u64,
+    /// Entries sorted by descending count. Counters with the same count are
+    /// in the order they were submitted with it.
+    pub entries: [Entry; LEADERBOARD_LEN],
+}
+
+unsafe impl AccountData for Leaderboard {
+    const OWNER: Pubkey = ID;
+}
+
+assert_layout!(Leaderboard, size = 408, align = 8, { len: 0, entries: 8 });
+
+impl Leaderboard {
+    /// Returns the entries in use.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries[..(self.len as usize).min(LEADERBOARD_LEN)]
+    }
+
+    /// Records `count` for `counter`, keeping the entries sorted, and
+    /// returns the rank of `counter`, starting at 0.
+    ///
+    /// Fails with [`LeaderboardError::ScoreTooLow`] if `counter` isn't on
+    /// the leaderboard, which is full, and `count` doesn't beat its last
+    /// entry.
+    pub fn submit(&mut self, counter: &Pubkey, count: u64) -> Result<usize, LeaderboardError> {
+        let mut len = self.entries().len();
+
+        // Take a counter already on the leaderboard out first, so it moves
+        // to the rank of its current count, whether it's higher or lower.
+        if let Some(i) = self.entries[..len]
+            .iter()
+            .position(|entry| &entry.counter == counter)
+        {
+            self.entries.copy_within(i + 1..len, i);
+            len -= 1;
+        }
+
+        // Rank after the entries with the same or a higher count.
+        let rank = self.entries[..len].partition_point(|entry| entry.count >= count);
+        if rank == LEADERBOARD_LEN {
+            return Err(LeaderboardError::ScoreTooLow);
+        }
+
+        // Shift the lower entries down. The last one falls off a full
+        // leaderboard.
+        let end = len.min(LEADERBOARD_LEN - 1);
+        self.entries.copy_within(rank..end, rank + 1);
+        self.entries[rank] = Entry {
+            counter: *counter,
+            count,
+        };
+        self.len = end as u64 + 1;
+
+        Ok(rank)
+    }
+}
+
+/// Leaderboard program instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum LeaderboardInstruction {
+    /// Creates the leaderboard.
+    Create,
+    /// Submits the count of a counter.
+    SubmitScore,
+}
+
+/// Instruction data of [`LeaderboardInstruction::Create`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct CreateInstructionData {
+    pub bump: u8,
+}
+
+impl CreateInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = LeaderboardInstruction::split(instruction_data)?;
+
+    match instruction {
+        LeaderboardInstruction::Create => process_create(accounts, instruction_data),
+        LeaderboardInstruction::SubmitScore => process_submit_score(accounts),
+    }
+}
+
+/// Creates the empty leaderboard.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Payer
+///   1. `[WRITE]` Leaderboard PDA
+///   2. `[]` System program
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [payer, leaderboard, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(payer)?;
+    require_program(system_program, &pinocchio_system::ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CreateInstructionData::from_bytes(instruction_data)?;
+
+    check_pda(
+        &[LEADERBOARD_SEED.as_bytes()],
+        instruction_data.bump,
+        leaderboard.key(),
+        &ID,
+    )?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [Seed::from(LEADERBOARD_SEED.as_bytes()), Seed::from(&bump)];
+    create_account(
+        payer,
+        leaderboard,
+        Leaderboard::LEN,
+        &ID,
+        &[Signer::from(&seeds)],
+    )?;
+
+    // The data of the new account is zeroed, so the leaderboard is empty.
+    Leaderboard::init(leaderboard)?;
+
+    info!("Created the leaderboard");
+
+    Ok(())
+}
+
+/// Submits the current count of `counter` to the leaderboard.
+///
+/// Anyone can submit any counter, the count comes from the counter account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Leaderboard PDA
+///   1. `[]` Counter
+pub fn process_submit_score(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [leaderboard, counter] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Fails for accounts not owned by the counter program, which could have
+    // any data.
+    let count = Counter::load(counter)?.count;
+
+    let rank = Leaderboard::load_mut(leaderboard)?.submit(counter.key(), count)?;
+
+    info!("Submitted a count of {} at rank {}", count, rank);
+
+    Ok(())
+}
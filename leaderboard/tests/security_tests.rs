@@ -0,0 +1,139 @@
+//! Attacks on the leaderboard program, each rejected with its own error.
+
+use common::AccountData;
+use leaderboard::{CreateInstructionData, Leaderboard, LeaderboardInstruction, LEADERBOARD_SEED};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(leaderboard::ID);
+
+/// Returns a leaderboard holding one counter with a count of 42.
+fn leaderboard_account(mollusk: &Mollusk) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Leaderboard::LEN),
+        Leaderboard::LEN,
+        &ID,
+    );
+    account.data[0..8].copy_from_slice(&1u64.to_le_bytes());
+    account.data[8..40].copy_from_slice(Pubkey::new_unique().as_ref());
+    account.data[40..48].copy_from_slice(&42u64.to_le_bytes());
+    account
+}
+
+/// Accounts of the leaderboard with an entry, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    leaderboard: Pubkey,
+    bump: u8,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/leaderboard");
+
+        let (leaderboard, bump) = Pubkey::find_program_address(&[LEADERBOARD_SEED.as_bytes()], &ID);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (leaderboard, leaderboard_account(&mollusk)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            leaderboard,
+            bump,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Creates `leaderboard`, paid by the attacker.
+    fn attacker_create(&self, leaderboard: &Pubkey, system_program: &Pubkey) -> Instruction {
+        let mut data = LeaderboardInstruction::Create.discriminator().to_vec();
+        data.extend_from_slice(&CreateInstructionData::new(self.bump).to_bytes());
+        Instruction::new_with_bytes(
+            ID,
+            &data,
+            vec![
+                AccountMeta::new(self.attacker, true),
+                AccountMeta::new(*leaderboard, false),
+                AccountMeta::new_readonly(*system_program, false),
+            ],
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_payer_signature() {
+    let setup = Setup::new();
+    let mut instruction =
+        setup.attacker_create(&setup.leaderboard, &keyed_account_for_system_program().0);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the leaderboard again, which would wipe its
+/// entries.
+#[test]
+fn test_reset_leaderboard() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_create(&setup.leaderboard, &keyed_account_for_system_program().0),
+        ProgramError::AccountAlreadyInitialized,
+    );
+}
+
+/// The attacker creates a second leaderboard at another address, owned by
+/// the program like the real one.
+#[test]
+fn test_create_other_leaderboard() {
+    let setup = Setup::new();
+    let leaderboard = Pubkey::new_unique();
+    let setup = setup.with(leaderboard, Account::default());
+    setup.expect_err(
+        &setup.attacker_create(&leaderboard, &keyed_account_for_system_program().0),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// A program posing as the system program, which would be invoked with the
+/// signature of the leaderboard PDA.
+#[test]
+fn test_fake_system_program() {
+    let setup = Setup::new();
+    let leaderboard = setup.leaderboard;
+    let system_program = Pubkey::new_unique();
+    let setup = setup
+        .with(leaderboard, Account::default())
+        .with(system_program, Account::default());
+    setup.expect_err(
+        &setup.attacker_create(&leaderboard, &system_program),
+        ProgramError::IncorrectProgramId,
+    );
+}
@@ -0,0 +1,334 @@
+use common::AccountData;
+use counter::Counter;
+use errors::Namespace;
+use leaderboard::{
+    CreateInstructionData, Entry, Leaderboard, LeaderboardError, LeaderboardInstruction,
+    LEADERBOARD_LEN, LEADERBOARD_SEED,
+};
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(leaderboard::ID);
+const COUNTER_ID: Pubkey = Pubkey::new_from_array(counter::ID);
+
+/// `LeaderboardError::ScoreTooLow`.
+const SCORE_TOO_LOW: u32 = Namespace::Leaderboard.code(0);
+
+/// Creates an account with the layout of a counter, owned by `program_id`.
+fn counter_account(mollusk: &Mollusk, program_id: &Pubkey, count: u64) -> Account {
+    let mut data = vec![0; Counter::LEN];
+    data[0..32].copy_from_slice(Pubkey::new_unique().as_ref());
+    data[32..40].copy_from_slice(&count.to_le_bytes());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Counter::LEN),
+        Counter::LEN,
+        program_id,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns the data of a leaderboard holding `entries`.
+fn leaderboard_data(entries: &[(Pubkey, u64)]) -> Vec<u8> {
+    let mut data = vec![0; Leaderboard::LEN];
+    data[0..8].copy_from_slice(&(entries.len() as u64).to_le_bytes());
+    for (i, (counter, count)) in entries.iter().enumerate() {
+        let offset = 8 + i * 40;
+        data[offset..offset + 32].copy_from_slice(counter.as_ref());
+        data[offset + 32..offset + 40].copy_from_slice(&count.to_le_bytes());
+    }
+    data
+}
+
+fn empty_leaderboard() -> Leaderboard {
+    Leaderboard {
+        len: 0,
+        entries: [Entry {
+            counter: [0; 32],
+            count: 0,
+        }; LEADERBOARD_LEN],
+    }
+}
+
+fn counts(leaderboard: &Leaderboard) -> Vec<u64> {
+    leaderboard
+        .entries()
+        .iter()
+        .map(|entry| entry.count)
+        .collect()
+}
+
+/// The leaderboard PDA and counters of the counter program.
+struct Setup {
+    mollusk: Mollusk,
+    payer: Pubkey,
+    leaderboard: Pubkey,
+    leaderboard_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/leaderboard");
+
+        let payer = Pubkey::new_unique();
+        let (leaderboard, leaderboard_bump) =
+            Pubkey::find_program_address(&[LEADERBOARD_SEED.as_bytes()], &ID);
+
+        let system_program = keyed_account_for_system_program();
+        let mut leaderboard_account = Account::new(
+            mollusk.sysvars.rent.minimum_balance(Leaderboard::LEN),
+            Leaderboard::LEN,
+            &ID,
+        );
+        leaderboard_account.data = leaderboard_data(&[]);
+        let accounts = vec![
+            (payer, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (leaderboard, leaderboard_account),
+            system_program,
+        ];
+
+        Self {
+            mollusk,
+            payer,
+            leaderboard,
+            leaderboard_bump,
+            accounts,
+        }
+    }
+
+    /// Adds a counter of the counter program holding `count`.
+    fn add_counter(&mut self, count: u64) -> Pubkey {
+        let counter = Pubkey::new_unique();
+        let account = counter_account(&self.mollusk, &COUNTER_ID, count);
+        self.accounts.push((counter, account));
+        counter
+    }
+
+    /// Sets the count of `counter`.
+    fn set_count(&mut self, counter: &Pubkey, count: u64) {
+        let (_, account) = self
+            .accounts
+            .iter_mut()
+            .find(|(key, _)| key == counter)
+            .unwrap();
+        account.data[32..40].copy_from_slice(&count.to_le_bytes());
+    }
+
+    fn instruction_create(&self) -> Instruction {
+        let mut data = LeaderboardInstruction::Create.discriminator().to_vec();
+        data.extend_from_slice(&CreateInstructionData::new(self.leaderboard_bump).to_bytes());
+        Instruction::new_with_bytes(
+            ID,
+            &data,
+            vec![
+                AccountMeta::new(self.payer, true),
+                AccountMeta::new(self.leaderboard, false),
+                AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            ],
+        )
+    }
+
+    fn instruction_submit_score(&self, counter: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            ID,
+            &LeaderboardInstruction::SubmitScore.discriminator(),
+            vec![
+                AccountMeta::new(self.leaderboard, false),
+                AccountMeta::new_readonly(*counter, false),
+            ],
+        )
+    }
+
+    /// Submits `counter`, keeping the resulting accounts if it succeeded.
+    fn submit(&mut self, counter: &Pubkey, checks: &[Check]) {
+        let res = self.mollusk.process_and_validate_instruction(
+            &self.instruction_submit_score(counter),
+            &self.accounts,
+            checks,
+        );
+        if matches!(res.program_result, ProgramResult::Success) {
+            self.accounts = res.resulting_accounts;
+        }
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(Leaderboard::LEN, 8 + LEADERBOARD_LEN * 40);
+}
+
+#[test]
+fn test_submit_sorted() {
+    let mut leaderboard = empty_leaderboard();
+    let counters: Vec<_> = (0..LEADERBOARD_LEN as u8 + 2).map(|i| [i; 32]).collect();
+
+    assert_eq!(leaderboard.submit(&counters[0], 5), Ok(0));
+    assert_eq!(leaderboard.submit(&counters[1], 7), Ok(0));
+    assert_eq!(leaderboard.submit(&counters[2], 3), Ok(2));
+    // Ties keep the counter submitted first ahead.
+    assert_eq!(leaderboard.submit(&counters[3], 5), Ok(2));
+    assert_eq!(counts(&leaderboard), [7, 5, 5, 3]);
+    assert_eq!(leaderboard.entries()[1].counter, counters[0]);
+    assert_eq!(leaderboard.entries()[2].counter, counters[3]);
+
+    for (i, counter) in counters[4..LEADERBOARD_LEN].iter().enumerate() {
+        leaderboard.submit(counter, 10 + i as u64).unwrap();
+    }
+    assert_eq!(counts(&leaderboard), [15, 14, 13, 12, 11, 10, 7, 5, 5, 3]);
+
+    // A full leaderboard evicts its last entry.
+    assert_eq!(leaderboard.submit(&counters[10], 6), Ok(7));
+    assert_eq!(counts(&leaderboard), [15, 14, 13, 12, 11, 10, 7, 6, 5, 5]);
+    assert!(!leaderboard
+        .entries()
+        .iter()
+        .any(|e| e.counter == counters[2]));
+
+    // Matching the last entry isn't enough.
+    assert_eq!(
+        leaderboard.submit(&counters[11], 5),
+        Err(LeaderboardError::ScoreTooLow)
+    );
+    assert_eq!(counts(&leaderboard), [15, 14, 13, 12, 11, 10, 7, 6, 5, 5]);
+}
+
+#[test]
+fn test_submit_existing() {
+    let mut leaderboard = empty_leaderboard();
+    let counters: Vec<_> = (0..LEADERBOARD_LEN as u8).map(|i| [i; 32]).collect();
+    for (i, counter) in counters.iter().enumerate() {
+        leaderboard.submit(counter, i as u64).unwrap();
+    }
+    assert_eq!(counts(&leaderboard), [9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+
+    // A counter already on the leaderboard is never duplicated, moves up
+    // after increments and down after decrements.
+    assert_eq!(leaderboard.submit(&counters[2], 20), Ok(0));
+    assert_eq!(leaderboard.submit(&counters[9], 0), Ok(9));
+    assert_eq!(leaderboard.submit(&counters[0], 0), Ok(9));
+    assert_eq!(leaderboard.len, LEADERBOARD_LEN as u64);
+    assert_eq!(counts(&leaderboard), [20, 8, 7, 6, 5, 4, 3, 1, 0, 0]);
+    assert_eq!(leaderboard.entries()[8].counter, counters[9]);
+    assert_eq!(leaderboard.entries()[9].counter, counters[0]);
+}
+
+#[test]
+fn test_create() {
+    let mut setup = Setup::new();
+    // The leaderboard is created by the program.
+    setup.accounts[1].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_create(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.leaderboard)
+                .owner(&ID)
+                .data(&leaderboard_data(&[]))
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_create_invalid_bump() {
+    let mut setup = Setup::new();
+    setup.accounts[1].1 = Account::default();
+    setup.leaderboard_bump = setup.leaderboard_bump.wrapping_sub(1);
+
+    let res = setup
+        .mollusk
+        .process_instruction(&setup.instruction_create(), &setup.accounts);
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_submit_score() {
+    let mut setup = Setup::new();
+    let low = setup.add_counter(3);
+    let high = setup.add_counter(8);
+
+    setup.submit(&low, &[Check::success()]);
+    setup.submit(
+        &high,
+        &[
+            Check::success(),
+            Check::account(&setup.leaderboard)
+                .data(&leaderboard_data(&[(high, 8), (low, 3)]))
+                .build(),
+        ],
+    );
+
+    // The counter moves to the rank of its current count.
+    setup.set_count(&low, 12);
+    setup.submit(
+        &low,
+        &[
+            Check::success(),
+            Check::account(&setup.leaderboard)
+                .data(&leaderboard_data(&[(low, 12), (high, 8)]))
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_submit_score_full() {
+    let mut setup = Setup::new();
+    let mut expected = Vec::new();
+    for count in (1..=LEADERBOARD_LEN as u64).rev() {
+        let counter = setup.add_counter(count * 10);
+        setup.submit(&counter, &[Check::success()]);
+        expected.push((counter, count * 10));
+    }
+
+    // Not beating the last entry fails.
+    let low = setup.add_counter(10);
+    setup.submit(&low, &[Check::err(ProgramError::Custom(SCORE_TOO_LOW))]);
+
+    // Beating it evicts the last entry.
+    let high = setup.add_counter(55);
+    expected.insert(5, (high, 55));
+    expected.pop();
+    setup.submit(
+        &high,
+        &[
+            Check::success(),
+            Check::account(&setup.leaderboard)
+                .data(&leaderboard_data(&expected))
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_submit_fake_counter() {
+    let mut setup = Setup::new();
+    // Same layout, but created by another program, which could write any
+    // count into it.
+    let fake = Pubkey::new_unique();
+    let account = counter_account(&setup.mollusk, &Pubkey::new_unique(), u64::MAX);
+    setup.accounts.push((fake, account));
+
+    setup.submit(&fake, &[Check::err(ProgramError::IllegalOwner)]);
+}
+
+#[test]
+fn test_submit_fake_leaderboard() {
+    let mut setup = Setup::new();
+    let counter = setup.add_counter(1);
+    setup.accounts[1].1.owner = Pubkey::new_unique();
+
+    setup.submit(&counter, &[Check::err(ProgramError::IllegalOwner)]);
+}
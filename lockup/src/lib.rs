@@ -0,0 +1,456 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use common::close::close_account;
+use errors::{Namespace, SharedError};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+use pinocchio_token::{
+    instructions::{CloseAccount, Transfer},
+    state::TokenAccount,
+};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("Qypo3K7NWhQor4nn4ZJLrvSddcfGZbprG5YadmSPtiZ");
+
+pub const LOCKER_SEED: &str = "locker";
+pub const LOCK_SEED: &str = "lock";
+
+/// Penalties are expressed in basis points of the locked amount.
+pub const MAX_PENALTY_BPS: u16 = 10_000;
+
+/// Errors returned by the lockup program.
+#[repr(u32)]
+pub enum LockupError {
+    /// The penalty exceeds [`MAX_PENALTY_BPS`].
+    InvalidPenalty,
+    /// The unlock time is not in the future.
+    InvalidUnlockTime,
+    /// The penalty vault doesn't match the locker.
+    PenaltyVaultMismatch,
+}
+
+impl From<LockupError> for ProgramError {
+    fn from(e: LockupError) -> Self {
+        Namespace::Lockup.error(e as u32)
+    }
+}
+
+/// On-chain representation of a locker.
+#[repr(C)]
+pub struct Locker {
+    pub authority: Pubkey,
+    /// Mint of the tokens which can be locked.
+    pub mint: Pubkey,
+    /// Token account receiving the penalties.
+    pub penalty_vault: Pubkey,
+    /// Share of the locked amount forfeited on early withdrawal.
+    pub penalty_bps: u16,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+}
+
+assert_layout!(Locker, size = 104, align = 2, {
+    authority: 0,
+    mint: 32,
+    penalty_vault: 64,
+    penalty_bps: 96,
+    bump: 98,
+});
+
+impl Locker {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Returns the penalty for withdrawing `amount` before the unlock time.
+    pub fn penalty(&self, amount: u64) -> u64 {
+        (amount as u128 * self.penalty_bps as u128 / MAX_PENALTY_BPS as u128) as u64
+    }
+}
+
+/// On-chain representation of a lock.
+///
+/// The locked tokens are held by a token account owned by the lock PDA.
+#[repr(C)]
+pub struct Lock {
+    pub locker: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// Unix timestamp after which the tokens can be withdrawn without
+    /// penalty.
+    pub unlock_at: i64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Lock, size = 88, align = 8, {
+    locker: 0,
+    owner: 32,
+    amount: 64,
+    unlock_at: 72,
+    bump: 80,
+});
+
+impl Lock {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Lockup program instruction discriminators.
+#[repr(u8)]
+pub enum LockupInstruction {
+    /// Creates a locker.
+    InitializeLocker,
+    /// Locks tokens until the given time.
+    Lock,
+    /// Withdraws locked tokens, paying the penalty if they are still locked.
+    Withdraw,
+}
+
+impl TryFrom<&u8> for LockupInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeLocker),
+            1 => Ok(Self::Lock),
+            2 => Ok(Self::Withdraw),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeLockerInstructionData {
+    pub penalty_bps: u16,
+    pub bump: u8,
+}
+
+impl InitializeLockerInstructionData {
+    pub fn new(penalty_bps: u16, bump: u8) -> Self {
+        Self { penalty_bps, bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct LockInstructionData {
+    pub amount: u64,
+    pub unlock_at: i64,
+    pub bump: u8,
+}
+
+impl LockInstructionData {
+    pub fn new(amount: u64, unlock_at: i64, bump: u8) -> Self {
+        Self {
+            amount,
+            unlock_at,
+            bump,
+        }
+    }
+}
+
+/// Checks that `locker` is a locker of the program. The program owns locks
+/// too, which are told apart by their length.
+fn check_locker(locker: &AccountInfo) -> ProgramResult {
+    require_owned_by(locker, &ID)?;
+    if locker.data_len() != Locker::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = LockupInstruction::try_from(instruction)?;
+
+    match instruction {
+        LockupInstruction::InitializeLocker => {
+            process_initialize_locker(accounts, instruction_data)
+        }
+        LockupInstruction::Lock => process_lock(accounts, instruction_data),
+        LockupInstruction::Withdraw => process_withdraw(accounts),
+    }
+}
+
+/// Creates a locker for the tokens of the mint of `penalty_vault`. Early
+/// withdrawals forfeit `penalty_bps` of the locked amount to `penalty_vault`.
+pub fn process_initialize_locker(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, locker, penalty_vault, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    let mint = *TokenAccount::from_account_info(penalty_vault)?.mint();
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeLockerInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.penalty_bps > MAX_PENALTY_BPS {
+        return Err(LockupError::InvalidPenalty.into());
+    }
+
+    // Check the seeds of `locker`.
+    let locker_pda = create_program_address(
+        &[
+            LOCKER_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(locker.key(), &locker_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the locker PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(LOCKER_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: locker,
+        lamports: Rent::get()?.minimum_balance(Locker::LEN),
+        space: Locker::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the locker PDA.
+    let mut data = locker.try_borrow_mut_data()?;
+    let data: &mut Locker = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the locker.
+    data.authority = *authority.key();
+    data.mint = mint;
+    data.penalty_vault = *penalty_vault.key();
+    data.penalty_bps = instruction_data.penalty_bps;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Initialized locker with {} bps penalty",
+        instruction_data.penalty_bps
+    );
+
+    Ok(())
+}
+
+/// Locks `amount` tokens from `owner_ata` until `unlock_at`. The tokens are
+/// moved to `lock_vault`, owned by the lock PDA.
+pub fn process_lock(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, owner_ata, locker, lock, lock_vault, _system_program, _token_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Check that `locker` is a locker PDA of the program.
+    check_locker(locker)?;
+    let mint = {
+        let data = locker.try_borrow_data()?;
+        let data: &Locker = unsafe { &*data.as_ptr().cast() };
+        data.mint
+    };
+
+    // Check that `lock_vault` is owned by `lock` and holds the tokens of the
+    // locker. The token program checks `owner_ata`.
+    {
+        let lock_vault = TokenAccount::from_account_info(lock_vault)?;
+        if !keys_eq(lock_vault.owner(), lock.key()) || !keys_eq(lock_vault.mint(), &mint) {
+            return Err(ProgramError::IllegalOwner);
+        }
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = LockInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.amount == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+    if instruction_data.unlock_at <= Clock::get()?.unix_timestamp {
+        return Err(LockupError::InvalidUnlockTime.into());
+    }
+
+    // Check the seeds of `lock`.
+    let lock_pda = create_program_address(
+        &[
+            LOCK_SEED.as_bytes(),
+            locker.key(),
+            owner.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(lock.key(), &lock_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the lock PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(LOCK_SEED.as_bytes()),
+        Seed::from(locker.key()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: owner,
+        to: lock,
+        lamports: Rent::get()?.minimum_balance(Lock::LEN),
+        space: Lock::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the lock PDA.
+    let mut data = lock.try_borrow_mut_data()?;
+    let data: &mut Lock = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the lock.
+    data.locker = *locker.key();
+    data.owner = *owner.key();
+    data.amount = instruction_data.amount;
+    data.unlock_at = instruction_data.unlock_at;
+    data.bump = instruction_data.bump;
+
+    // Transfer the tokens from owner to lock.
+    Transfer {
+        from: owner_ata,
+        to: lock_vault,
+        authority: owner,
+        amount: instruction_data.amount,
+    }
+    .invoke()?;
+
+    info!(
+        "Locked {} tokens until {}",
+        instruction_data.amount, instruction_data.unlock_at
+    );
+
+    Ok(())
+}
+
+/// Withdraws the locked tokens to `owner_ata` and closes the lock.
+///
+/// Before the unlock time, the penalty of the locker is transferred to the
+/// penalty vault and only the rest goes to the owner.
+pub fn process_withdraw(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, owner_ata, locker, lock, lock_vault, penalty_vault, _token_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Check that `locker` and `lock` are a locker and a lock PDA of the
+    // program.
+    check_locker(locker)?;
+    require_owned_by(lock, &ID)?;
+    if lock.data_len() != Lock::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Deserialize the lock PDA.
+    let (amount, unlock_at, bump) = {
+        let data = lock.try_borrow_data()?;
+        let data: &Lock = unsafe { &*data.as_ptr().cast() };
+
+        // Check that the lock belongs to `locker` and `owner`.
+        if !keys_eq(&data.locker, locker.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        (data.amount, data.unlock_at, data.bump)
+    };
+
+    // Compute the penalty.
+    let penalty = if Clock::get()?.unix_timestamp < unlock_at {
+        let data = locker.try_borrow_data()?;
+        let data: &Locker = unsafe { &*data.as_ptr().cast() };
+
+        if !keys_eq(&data.penalty_vault, penalty_vault.key()) {
+            return Err(LockupError::PenaltyVaultMismatch.into());
+        }
+        data.penalty(amount)
+    } else {
+        0
+    };
+
+    // Transfer the tokens from lock to owner and penalty vault.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(LOCK_SEED.as_bytes()),
+        Seed::from(locker.key()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    Transfer {
+        from: lock_vault,
+        to: owner_ata,
+        authority: lock,
+        amount: amount - penalty,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+    if penalty > 0 {
+        Transfer {
+            from: lock_vault,
+            to: penalty_vault,
+            authority: lock,
+            amount: penalty,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+    }
+
+    // Close the (now empty) lock vault and the lock PDA, returning the rent
+    // to the owner.
+    CloseAccount {
+        account: lock_vault,
+        destination: owner,
+        authority: lock,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    close_account(lock, owner)?;
+
+    info!("Withdrew {} tokens, penalty {}", amount - penalty, penalty);
+
+    Ok(())
+}
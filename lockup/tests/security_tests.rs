@@ -0,0 +1,430 @@
+//! Attacks on the lockup program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use lockup::{
+    InitializeLockerInstructionData, Lock, LockInstructionData, Locker, LockupInstruction,
+    LOCKER_SEED, LOCK_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(lockup::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+const PENALTY_BPS: u16 = 2_000;
+const AMOUNT: u64 = 1_000_000;
+const NOW: i64 = 1_000_000;
+const UNLOCK_AT: i64 = NOW + 86_400;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: LockupInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<LockupInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn mint_account(mollusk: &Mollusk) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply: 2 * AMOUNT,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns an account of the program holding `data`.
+fn program_account(mollusk: &Mollusk, data: Vec<u8>) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns the locker PDA of `authority` and its account.
+fn locker_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    penalty_vault: &Pubkey,
+    penalty_bps: u16,
+) -> (Pubkey, Account) {
+    let (locker, bump) =
+        Pubkey::find_program_address(&[LOCKER_SEED.as_bytes(), authority.as_ref()], &ID);
+    let mut data = vec![0; Locker::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64..96].copy_from_slice(penalty_vault.as_ref());
+    data[96..98].copy_from_slice(&penalty_bps.to_le_bytes());
+    data[98] = bump;
+    (locker, program_account(mollusk, data))
+}
+
+/// Returns the lock PDA of `owner` in `locker` and its bump.
+fn lock_pda(locker: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[LOCK_SEED.as_bytes(), locker.as_ref(), owner.as_ref()],
+        &ID,
+    )
+}
+
+/// Accounts of a locker in which the owner locked [`AMOUNT`] tokens until
+/// [`UNLOCK_AT`], and of an attacker holding tokens of the same mint.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    mint: Pubkey,
+    locker: Pubkey,
+    penalty_vault: Pubkey,
+    owner: Pubkey,
+    owner_ata: Pubkey,
+    lock: Pubkey,
+    lock_vault: Pubkey,
+    attacker: Pubkey,
+    attacker_ata: Pubkey,
+    attacker_lock: Pubkey,
+    attacker_lock_bump: u8,
+    attacker_lock_vault: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/lockup");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let penalty_vault = Pubkey::new_unique();
+        let (locker, locker_account) =
+            locker_account(&mollusk, &authority, &mint, &penalty_vault, PENALTY_BPS);
+
+        let owner = Pubkey::new_unique();
+        let owner_ata = Pubkey::new_unique();
+        let (lock, lock_bump) = lock_pda(&locker, &owner);
+        let lock_vault = Pubkey::new_unique();
+        let mut lock_data = vec![0; Lock::LEN];
+        lock_data[0..32].copy_from_slice(locker.as_ref());
+        lock_data[32..64].copy_from_slice(owner.as_ref());
+        lock_data[64..72].copy_from_slice(&AMOUNT.to_le_bytes());
+        lock_data[72..80].copy_from_slice(&UNLOCK_AT.to_le_bytes());
+        lock_data[80] = lock_bump;
+
+        let attacker = Pubkey::new_unique();
+        let attacker_ata = Pubkey::new_unique();
+        let (attacker_lock, attacker_lock_bump) = lock_pda(&locker, &attacker);
+        let attacker_lock_vault = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (mint, mint_account(&mollusk)),
+            (locker, locker_account),
+            (penalty_vault, token_account(&mollusk, &mint, &authority, 0)),
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (owner_ata, token_account(&mollusk, &mint, &owner, 0)),
+            (lock, program_account(&mollusk, lock_data)),
+            (lock_vault, token_account(&mollusk, &mint, &lock, AMOUNT)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (
+                attacker_ata,
+                token_account(&mollusk, &mint, &attacker, AMOUNT),
+            ),
+            (attacker_lock, Account::default()),
+            (
+                attacker_lock_vault,
+                token_account(&mollusk, &mint, &attacker_lock, 0),
+            ),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            mint,
+            locker,
+            penalty_vault,
+            owner,
+            owner_ata,
+            lock,
+            lock_vault,
+            attacker,
+            attacker_ata,
+            attacker_lock,
+            attacker_lock_bump,
+            attacker_lock_vault,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    fn instruction_initialize_locker(&self, locker: &Pubkey, bump: u8) -> Instruction {
+        let data = InitializeLockerInstructionData::new(PENALTY_BPS, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new(*locker, false),
+            AccountMeta::new_readonly(self.penalty_vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LockupInstruction::InitializeLocker, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Returns an instruction locking the tokens of the attacker.
+    fn instruction_lock(&self, locker: &Pubkey, lock: &Pubkey, lock_vault: &Pubkey) -> Instruction {
+        let data = LockInstructionData::new(AMOUNT, UNLOCK_AT, self.attacker_lock_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new(self.attacker_ata, false),
+            AccountMeta::new_readonly(*locker, false),
+            AccountMeta::new(*lock, false),
+            AccountMeta::new(*lock_vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LockupInstruction::Lock, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Returns an instruction withdrawing the tokens of the owner.
+    fn instruction_withdraw(&self, owner: &Pubkey, locker: &Pubkey, lock: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(self.owner_ata, false),
+            AccountMeta::new_readonly(*locker, false),
+            AccountMeta::new(*lock, false),
+            AccountMeta::new(self.lock_vault, false),
+            AccountMeta::new(self.penalty_vault, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[LockupInstruction::Withdraw as u8], ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_signature_on_withdraw() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_withdraw(&setup.owner, &setup.locker, &setup.lock);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+#[test]
+fn test_missing_signature_on_lock() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_lock(
+        &setup.locker,
+        &setup.attacker_lock,
+        &setup.attacker_lock_vault,
+    );
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker withdraws the tokens locked by the owner.
+#[test]
+fn test_withdraw_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_withdraw(&setup.attacker, &setup.locker, &setup.lock),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The owner withdraws early through a locker of their own without a
+/// penalty, avoiding the penalty of the locker they locked in.
+#[test]
+fn test_withdraw_through_other_locker() {
+    let setup = Setup::new();
+    let (other_locker, other_locker_account) = locker_account(
+        &setup.mollusk,
+        &setup.owner,
+        &setup.mint,
+        &setup.owner_ata,
+        0,
+    );
+    let instruction = setup.instruction_withdraw(&setup.owner, &other_locker, &setup.lock);
+    let setup = setup.with(other_locker, other_locker_account);
+    setup.expect_err(&instruction, ProgramError::InvalidAccountData);
+}
+
+/// A locker with the layout of the program, but owned by another one, e.g.
+/// with a penalty vault of the attacker.
+#[test]
+fn test_spoofed_locker() {
+    let setup = Setup::new();
+    let locker = setup.locker;
+    let mut locker_account = setup.account(&locker);
+    locker_account.owner = Pubkey::new_unique();
+    let instruction = setup.instruction_withdraw(&setup.owner, &locker, &setup.lock);
+    let setup = setup.with(locker, locker_account);
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// A lock with the layout of the program, but owned by another one, e.g.
+/// with an unlock time in the past.
+#[test]
+fn test_spoofed_lock() {
+    let setup = Setup::new();
+    let lock = setup.lock;
+    let mut lock_account = setup.account(&lock);
+    lock_account.owner = Pubkey::new_unique();
+    let instruction = setup.instruction_withdraw(&setup.owner, &setup.locker, &lock);
+    let setup = setup.with(lock, lock_account);
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// The locker passed as the lock, which would be read as a lock of its
+/// authority.
+#[test]
+fn test_locker_as_lock() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_withdraw(&setup.authority, &setup.locker, &setup.locker),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// Tokens locked in a vault which the lock PDA doesn't own, from which the
+/// attacker could take them out before the unlock time.
+#[test]
+fn test_lock_vault_of_other_owner() {
+    let setup = Setup::new();
+    let vault = Pubkey::new_unique();
+    let vault_account = token_account(&setup.mollusk, &setup.mint, &setup.attacker, 0);
+    let instruction = setup.instruction_lock(&setup.locker, &setup.attacker_lock, &vault);
+    let setup = setup.with(vault, vault_account);
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// Tokens of another mint locked in the locker.
+#[test]
+fn test_lock_vault_of_other_mint() {
+    let setup = Setup::new();
+    let vault = Pubkey::new_unique();
+    let vault_account = token_account(
+        &setup.mollusk,
+        &Pubkey::new_unique(),
+        &setup.attacker_lock,
+        0,
+    );
+    let instruction = setup.instruction_lock(&setup.locker, &setup.attacker_lock, &vault);
+    let setup = setup.with(vault, vault_account);
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// A lock at an address other than the PDA of the attacker.
+#[test]
+fn test_wrong_lock_seeds() {
+    let setup = Setup::new();
+    let lock = Pubkey::new_unique();
+    let vault = Pubkey::new_unique();
+    let vault_account = token_account(&setup.mollusk, &setup.mint, &lock, 0);
+    let instruction = setup.instruction_lock(&setup.locker, &lock, &vault);
+    let setup = setup
+        .with(lock, Account::default())
+        .with(vault, vault_account);
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker creates a locker at the PDA of another authority.
+#[test]
+fn test_wrong_locker_seeds() {
+    let setup = Setup::new();
+    let (locker, bump) =
+        Pubkey::find_program_address(&[LOCKER_SEED.as_bytes(), setup.owner.as_ref()], &ID);
+    let instruction = setup.instruction_initialize_locker(&locker, bump);
+    let setup = setup.with(locker, Account::default());
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
@@ -0,0 +1,417 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::{Namespace, SharedError};
+use lockup::{
+    InitializeLockerInstructionData, Lock, LockInstructionData, Locker, LockupError,
+    LockupInstruction, LOCKER_SEED, LOCK_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(lockup::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+
+const PENALTY_BPS: u16 = 2_000;
+const AMOUNT: u64 = 1_000_000;
+const NOW: i64 = 1_000_000;
+const UNLOCK_AT: i64 = NOW + 86_400;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: LockupInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<LockupInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/lockup");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk.sysvars.clock.unix_timestamp = NOW;
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn locker_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    penalty_vault: &Pubkey,
+    bump: u8,
+) -> Account {
+    let mut data = vec![0; Locker::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64..96].copy_from_slice(penalty_vault.as_ref());
+    data[96..98].copy_from_slice(&PENALTY_BPS.to_le_bytes());
+    data[98] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Locker::LEN),
+        Locker::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Accounts shared by the lock tests: a locker and an owner holding tokens.
+struct Setup {
+    mollusk: Mollusk,
+    locker: Pubkey,
+    penalty_vault: Pubkey,
+    owner: Pubkey,
+    owner_ata: Pubkey,
+    lock: Pubkey,
+    lock_bump: u8,
+    lock_vault: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let authority = Pubkey::new_unique();
+        let (locker, locker_bump) =
+            Pubkey::find_program_address(&[LOCKER_SEED.as_bytes(), authority.as_ref()], &ID);
+        let mint = Pubkey::new_unique();
+        let penalty_vault = Pubkey::new_unique();
+
+        let owner = Pubkey::new_unique();
+        let owner_ata = Pubkey::new_unique();
+        let (lock, lock_bump) = Pubkey::find_program_address(
+            &[LOCK_SEED.as_bytes(), locker.as_ref(), owner.as_ref()],
+            &ID,
+        );
+        let lock_vault = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                locker,
+                locker_account(&mollusk, &authority, &mint, &penalty_vault, locker_bump),
+            ),
+            (mint, mint_account(&mollusk, AMOUNT)),
+            (penalty_vault, token_account(&mollusk, &mint, &authority, 0)),
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (owner_ata, token_account(&mollusk, &mint, &owner, AMOUNT)),
+            (lock, Account::default()),
+            (lock_vault, token_account(&mollusk, &mint, &lock, 0)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            locker,
+            penalty_vault,
+            owner,
+            owner_ata,
+            lock,
+            lock_bump,
+            lock_vault,
+            accounts,
+        }
+    }
+
+    fn instruction_lock(&self, amount: u64, unlock_at: i64) -> Instruction {
+        let data = LockInstructionData::new(amount, unlock_at, self.lock_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.owner_ata, false),
+            AccountMeta::new_readonly(self.locker, false),
+            AccountMeta::new(self.lock, false),
+            AccountMeta::new(self.lock_vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LockupInstruction::Lock, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_withdraw(&self, owner: &Pubkey, penalty_vault: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(self.owner_ata, false),
+            AccountMeta::new_readonly(self.locker, false),
+            AccountMeta::new(self.lock, false),
+            AccountMeta::new(self.lock_vault, false),
+            AccountMeta::new(*penalty_vault, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[LockupInstruction::Withdraw as u8], ix_accounts)
+    }
+
+    /// Locks all tokens of the owner until [`UNLOCK_AT`] and keeps the
+    /// resulting accounts.
+    fn lock(&mut self) {
+        let res = self.mollusk.process_and_validate_instruction(
+            &self.instruction_lock(AMOUNT, UNLOCK_AT),
+            &self.accounts,
+            &[
+                Check::success(),
+                Check::account(&self.lock)
+                    .owner(&ID)
+                    .space(Lock::LEN)
+                    .data_slice(0, self.locker.as_ref())
+                    .data_slice(32, self.owner.as_ref())
+                    .data_slice(64, &AMOUNT.to_le_bytes())
+                    .data_slice(72, &UNLOCK_AT.to_le_bytes())
+                    .build(),
+                Check::account(&self.lock_vault)
+                    .data_slice(AMOUNT_OFFSET, &AMOUNT.to_le_bytes())
+                    .build(),
+            ],
+        );
+        self.accounts = res.resulting_accounts;
+    }
+}
+
+#[test]
+fn test_lockup_initialize_locker() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let (locker, bump) =
+        Pubkey::find_program_address(&[LOCKER_SEED.as_bytes(), authority.as_ref()], &ID);
+    let mint = Pubkey::new_unique();
+    let penalty_vault = Pubkey::new_unique();
+
+    let instruction = |penalty_bps| {
+        let data = InitializeLockerInstructionData::new(penalty_bps, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(locker, false),
+            AccountMeta::new_readonly(penalty_vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LockupInstruction::InitializeLocker, &data),
+            ix_accounts,
+        )
+    };
+
+    let accounts = [
+        (
+            authority,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        ),
+        (locker, Account::default()),
+        (penalty_vault, token_account(&mollusk, &mint, &authority, 0)),
+        keyed_account_for_system_program(),
+    ];
+    mollusk.process_and_validate_instruction(
+        &instruction(10_001),
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Lockup.code(LockupError::InvalidPenalty as u32),
+        ))],
+    );
+    let res = mollusk.process_and_validate_instruction(
+        &instruction(PENALTY_BPS),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&locker)
+                .owner(&ID)
+                .data(&locker_account(&mollusk, &authority, &mint, &penalty_vault, bump).data)
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_lockup_lock_invalid() {
+    let setup = Setup::new();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_lock(0, UNLOCK_AT),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Shared.code(SharedError::InvalidAmount as u32),
+        ))],
+    );
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_lock(AMOUNT, NOW),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Lockup.code(LockupError::InvalidUnlockTime as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_lockup_lock_in_lock() {
+    let mut setup = Setup::new();
+    setup.lock();
+
+    // A lock has no penalty vault and bps, they would be read past its data.
+    let mut instruction = setup.instruction_lock(AMOUNT, UNLOCK_AT);
+    instruction.accounts[2].pubkey = setup.lock;
+    setup.mollusk.process_and_validate_instruction(
+        &instruction,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+#[test]
+fn test_lockup_withdraw_after_unlock() {
+    let mut setup = Setup::new();
+    setup.lock();
+
+    setup.mollusk.sysvars.clock.unix_timestamp = UNLOCK_AT;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_withdraw(&setup.owner, &setup.penalty_vault),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.owner_ata)
+                .data_slice(AMOUNT_OFFSET, &AMOUNT.to_le_bytes())
+                .build(),
+            Check::account(&setup.penalty_vault)
+                .data_slice(AMOUNT_OFFSET, &0u64.to_le_bytes())
+                .build(),
+            Check::account(&setup.lock).closed().build(),
+            Check::account(&setup.lock_vault).closed().build(),
+        ],
+    );
+}
+
+#[test]
+fn test_lockup_withdraw_early() {
+    let mut setup = Setup::new();
+    setup.lock();
+
+    let penalty = AMOUNT * PENALTY_BPS as u64 / 10_000;
+
+    setup.mollusk.sysvars.clock.unix_timestamp = UNLOCK_AT - 1;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_withdraw(&setup.owner, &setup.penalty_vault),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.owner_ata)
+                .data_slice(AMOUNT_OFFSET, &(AMOUNT - penalty).to_le_bytes())
+                .build(),
+            Check::account(&setup.penalty_vault)
+                .data_slice(AMOUNT_OFFSET, &penalty.to_le_bytes())
+                .build(),
+            Check::account(&setup.lock).closed().build(),
+            Check::account(&setup.lock_vault).closed().build(),
+        ],
+    );
+}
+
+#[test]
+fn test_lockup_withdraw_wrong_penalty_vault() {
+    let mut setup = Setup::new();
+    setup.lock();
+
+    // The penalty must not be redirected to someone else's account.
+    let mint = setup.accounts[1].0;
+    let attacker_vault = Pubkey::new_unique();
+    let attacker_vault_account = token_account(&setup.mollusk, &mint, &Pubkey::new_unique(), 0);
+    setup
+        .accounts
+        .push((attacker_vault, attacker_vault_account));
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_withdraw(&setup.owner, &attacker_vault),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Lockup.code(LockupError::PenaltyVaultMismatch as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_lockup_withdraw_wrong_owner() {
+    let mut setup = Setup::new();
+    setup.lock();
+
+    let attacker = Pubkey::new_unique();
+    setup.accounts.push((attacker, Account::default()));
+
+    setup.mollusk.sysvars.clock.unix_timestamp = UNLOCK_AT;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_withdraw(&attacker, &setup.penalty_vault),
+        &setup.accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
@@ -0,0 +1,390 @@
+//! Address lookup tables managed by a program.
+//!
+//! Every owner gets an authority PDA, which controls their lookup tables.
+//! The program creates, extends, deactivates and closes the tables through
+//! CPIs into the address lookup table program, signing for the authority.
+
+#![no_std]
+
+use common::{checks::require_signer, info, keys_eq};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+use lookup_table::{
+    extend_lookup_table_len, ADDRESS_LOOKUP_TABLE_PROGRAM_ID, CLOSE_LOOKUP_TABLE_DATA,
+    DEACTIVATE_LOOKUP_TABLE_DATA,
+};
+
+pub mod lookup_table;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("HaA4YYARzEbaB7DYs83fTKnJtuiixcsiMhC1eYtGKvCm");
+
+pub const AUTHORITY_SEED: &str = "authority";
+
+/// Maximum number of addresses added in one instruction. More wouldn't fit
+/// in a transaction anyway.
+pub const MAX_NEW_ADDRESSES: usize = 20;
+
+/// Errors returned by the lookup tables program.
+#[repr(u32)]
+pub enum LookupTablesError {
+    /// The number of new addresses is zero or more than
+    /// [`MAX_NEW_ADDRESSES`].
+    InvalidAddressCount,
+}
+
+impl From<LookupTablesError> for ProgramError {
+    fn from(e: LookupTablesError) -> Self {
+        Namespace::LookupTables.error(e as u32)
+    }
+}
+
+/// Lookup tables program instruction discriminators.
+#[repr(u8)]
+pub enum LookupTablesInstruction {
+    /// Creates a lookup table.
+    Create,
+    /// Adds addresses to a lookup table.
+    Extend,
+    /// Deactivates a lookup table, so it can be closed once it cools down.
+    Deactivate,
+    /// Closes a deactivated lookup table.
+    Close,
+}
+
+impl TryFrom<&u8> for LookupTablesInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Create),
+            1 => Ok(Self::Extend),
+            2 => Ok(Self::Deactivate),
+            3 => Ok(Self::Close),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct CreateInstructionData {
+    /// Recent slot, from which the table address is derived.
+    pub recent_slot: u64,
+    /// Bump of the authority PDA.
+    pub authority_bump: u8,
+    /// Bump of the table address.
+    pub table_bump: u8,
+}
+
+impl CreateInstructionData {
+    pub fn new(recent_slot: u64, authority_bump: u8, table_bump: u8) -> Self {
+        Self {
+            recent_slot,
+            authority_bump,
+            table_bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ExtendInstructionData {
+    /// New addresses, only the first `len` are used.
+    pub addresses: [Pubkey; MAX_NEW_ADDRESSES],
+    pub len: u8,
+    /// Bump of the authority PDA.
+    pub authority_bump: u8,
+}
+
+impl ExtendInstructionData {
+    pub fn new(addresses: &[Pubkey], authority_bump: u8) -> Self {
+        let mut padded_addresses = [[0; 32]; MAX_NEW_ADDRESSES];
+        padded_addresses[..addresses.len()].copy_from_slice(addresses);
+        Self {
+            addresses: padded_addresses,
+            len: addresses.len() as u8,
+            authority_bump,
+        }
+    }
+}
+
+/// Instruction data of `Deactivate` and `Close`.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AuthorityInstructionData {
+    /// Bump of the authority PDA.
+    pub authority_bump: u8,
+}
+
+impl AuthorityInstructionData {
+    pub fn new(authority_bump: u8) -> Self {
+        Self { authority_bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = LookupTablesInstruction::try_from(instruction)?;
+
+    match instruction {
+        LookupTablesInstruction::Create => process_create(accounts, instruction_data),
+        LookupTablesInstruction::Extend => process_extend(accounts, instruction_data),
+        LookupTablesInstruction::Deactivate => process_deactivate(accounts, instruction_data),
+        LookupTablesInstruction::Close => process_close(accounts, instruction_data),
+    }
+}
+
+/// Checks that `authority` is the authority PDA of `owner` and that
+/// `lookup_table_program` is the address lookup table program.
+fn check_authority(
+    owner: &AccountInfo,
+    authority: &AccountInfo,
+    lookup_table_program: &AccountInfo,
+    bump: u8,
+) -> ProgramResult {
+    require_signer(owner)?;
+
+    let authority_pda =
+        create_program_address(&[AUTHORITY_SEED.as_bytes(), owner.key(), &[bump]], &ID)?;
+    if !keys_eq(authority.key(), &authority_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    if !keys_eq(lookup_table_program.key(), &ADDRESS_LOOKUP_TABLE_PROGRAM_ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    Ok(())
+}
+
+/// Creates a lookup table controlled by the authority PDA of `owner`.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner, paying for the table
+///   1. `[]` Authority PDA of the owner
+///   2. `[WRITE]` Lookup table
+///   3. `[]` System program
+///   4. `[]` Address lookup table program
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, authority, table, system_program, lookup_table_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = CreateInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(
+        owner,
+        authority,
+        lookup_table_program,
+        instruction_data.authority_bump,
+    )?;
+
+    // The lookup table program checks the table address itself.
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    invoke_signed(
+        &Instruction {
+            program_id: &ADDRESS_LOOKUP_TABLE_PROGRAM_ID,
+            accounts: &[
+                AccountMeta::writable(table.key()),
+                AccountMeta::readonly_signer(authority.key()),
+                AccountMeta::writable_signer(owner.key()),
+                AccountMeta::readonly(system_program.key()),
+            ],
+            data: &lookup_table::create_lookup_table(
+                instruction_data.recent_slot,
+                instruction_data.table_bump,
+            ),
+        },
+        &[table, authority, owner, system_program],
+        &[Signer::from(&seeds)],
+    )?;
+
+    info!("Created a lookup table");
+
+    Ok(())
+}
+
+/// Adds addresses to a lookup table of `owner`.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner, paying for the reallocation
+///   1. `[]` Authority PDA of the owner
+///   2. `[WRITE]` Lookup table
+///   3. `[]` System program
+///   4. `[]` Address lookup table program
+pub fn process_extend(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, authority, table, system_program, lookup_table_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = ExtendInstructionData::from_bytes(instruction_data)?;
+
+    let len = instruction_data.len as usize;
+    if len == 0 || len > MAX_NEW_ADDRESSES {
+        return Err(LookupTablesError::InvalidAddressCount.into());
+    }
+    let new_addresses = &instruction_data.addresses[..len];
+
+    check_authority(
+        owner,
+        authority,
+        lookup_table_program,
+        instruction_data.authority_bump,
+    )?;
+
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    let mut data = [0; extend_lookup_table_len(MAX_NEW_ADDRESSES)];
+    invoke_signed(
+        &Instruction {
+            program_id: &ADDRESS_LOOKUP_TABLE_PROGRAM_ID,
+            accounts: &[
+                AccountMeta::writable(table.key()),
+                AccountMeta::readonly_signer(authority.key()),
+                AccountMeta::writable_signer(owner.key()),
+                AccountMeta::readonly(system_program.key()),
+            ],
+            data: lookup_table::extend_lookup_table(&mut data, new_addresses),
+        },
+        &[table, authority, owner, system_program],
+        &[Signer::from(&seeds)],
+    )?;
+
+    info!("Added {} addresses to a lookup table", len);
+
+    Ok(())
+}
+
+/// Deactivates a lookup table of `owner`.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Owner
+///   1. `[]` Authority PDA of the owner
+///   2. `[WRITE]` Lookup table
+///   3. `[]` Address lookup table program
+pub fn process_deactivate(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, authority, table, lookup_table_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = AuthorityInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(
+        owner,
+        authority,
+        lookup_table_program,
+        instruction_data.authority_bump,
+    )?;
+
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    invoke_signed(
+        &Instruction {
+            program_id: &ADDRESS_LOOKUP_TABLE_PROGRAM_ID,
+            accounts: &[
+                AccountMeta::writable(table.key()),
+                AccountMeta::readonly_signer(authority.key()),
+            ],
+            data: &DEACTIVATE_LOOKUP_TABLE_DATA,
+        },
+        &[table, authority],
+        &[Signer::from(&seeds)],
+    )?;
+
+    info!("Deactivated a lookup table");
+
+    Ok(())
+}
+
+/// Closes a deactivated lookup table of `owner`, returning its lamports to
+/// the owner.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Owner
+///   1. `[]` Authority PDA of the owner
+///   2. `[WRITE]` Lookup table
+///   3. `[]` Address lookup table program
+pub fn process_close(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, authority, table, lookup_table_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = AuthorityInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(
+        owner,
+        authority,
+        lookup_table_program,
+        instruction_data.authority_bump,
+    )?;
+
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    invoke_signed(
+        &Instruction {
+            program_id: &ADDRESS_LOOKUP_TABLE_PROGRAM_ID,
+            accounts: &[
+                AccountMeta::writable(table.key()),
+                AccountMeta::readonly_signer(authority.key()),
+                AccountMeta::writable(owner.key()),
+            ],
+            data: &CLOSE_LOOKUP_TABLE_DATA,
+        },
+        &[table, authority, owner],
+        &[Signer::from(&seeds)],
+    )?;
+
+    info!("Closed a lookup table");
+
+    Ok(())
+}
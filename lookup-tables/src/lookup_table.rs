@@ -0,0 +1,61 @@
+//! Layout and instructions of the address lookup table program.
+//!
+//! The program takes bincode-serialized instruction data: a `u32` variant
+//! index, followed by the fields of the variant. Vectors are prefixed with a
+//! `u64` length.
+
+use pinocchio::pubkey::Pubkey;
+
+/// ID of the address lookup table program.
+pub const ADDRESS_LOOKUP_TABLE_PROGRAM_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("AddressLookupTab1e1111111111111111111111111");
+
+/// Variant indices of `ProgramInstruction`.
+pub const CREATE_LOOKUP_TABLE: u32 = 0;
+pub const EXTEND_LOOKUP_TABLE: u32 = 2;
+pub const DEACTIVATE_LOOKUP_TABLE: u32 = 3;
+pub const CLOSE_LOOKUP_TABLE: u32 = 4;
+
+/// Size of the metadata at the start of a lookup table account. The stored
+/// addresses follow right after it.
+pub const LOOKUP_TABLE_META_SIZE: usize = 56;
+
+/// Maximum number of addresses a lookup table can store.
+pub const LOOKUP_TABLE_MAX_ADDRESSES: usize = 256;
+
+/// Length of the `CreateLookupTable` instruction data.
+pub const CREATE_LOOKUP_TABLE_LEN: usize = 4 + 8 + 1;
+
+/// Encodes `CreateLookupTable`. The table address is derived by the lookup
+/// table program from the authority and `recent_slot`, with `bump_seed`.
+pub fn create_lookup_table(recent_slot: u64, bump_seed: u8) -> [u8; CREATE_LOOKUP_TABLE_LEN] {
+    let mut data = [0; CREATE_LOOKUP_TABLE_LEN];
+    data[..4].copy_from_slice(&CREATE_LOOKUP_TABLE.to_le_bytes());
+    data[4..12].copy_from_slice(&recent_slot.to_le_bytes());
+    data[12] = bump_seed;
+    data
+}
+
+/// Returns the length of the `ExtendLookupTable` instruction data with `len`
+/// new addresses.
+pub const fn extend_lookup_table_len(len: usize) -> usize {
+    4 + 8 + len * 32
+}
+
+/// Encodes `ExtendLookupTable` into `buf`, which has to be at least
+/// [`extend_lookup_table_len`] long.
+pub fn extend_lookup_table<'a>(buf: &'a mut [u8], new_addresses: &[Pubkey]) -> &'a [u8] {
+    let len = extend_lookup_table_len(new_addresses.len());
+    buf[..4].copy_from_slice(&EXTEND_LOOKUP_TABLE.to_le_bytes());
+    buf[4..12].copy_from_slice(&(new_addresses.len() as u64).to_le_bytes());
+    for (chunk, address) in buf[12..len].chunks_exact_mut(32).zip(new_addresses) {
+        chunk.copy_from_slice(address);
+    }
+    &buf[..len]
+}
+
+/// `DeactivateLookupTable` has no arguments.
+pub const DEACTIVATE_LOOKUP_TABLE_DATA: [u8; 4] = DEACTIVATE_LOOKUP_TABLE.to_le_bytes();
+
+/// `CloseLookupTable` has no arguments.
+pub const CLOSE_LOOKUP_TABLE_DATA: [u8; 4] = CLOSE_LOOKUP_TABLE.to_le_bytes();
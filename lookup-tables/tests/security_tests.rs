@@ -0,0 +1,147 @@
+//! Attacks on the lookup tables program, each rejected with its own error
+//! before any CPI, so the address lookup table program doesn't have to be
+//! loaded.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use lookup_tables::{
+    lookup_table::ADDRESS_LOOKUP_TABLE_PROGRAM_ID, AuthorityInstructionData, ExtendInstructionData,
+    LookupTablesInstruction, AUTHORITY_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(lookup_tables::ID);
+const LOOKUP_TABLE_ID: Pubkey = Pubkey::new_from_array(ADDRESS_LOOKUP_TABLE_PROGRAM_ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: LookupTablesInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<LookupTablesInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the authority PDA of `owner` and its bump.
+fn authority_pda(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[AUTHORITY_SEED.as_bytes(), owner.as_ref()], &ID)
+}
+
+/// Accounts of an owner with a lookup table controlled by their authority
+/// PDA, and of an attacker. The table is never read by the program itself.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    authority: Pubkey,
+    table: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/lookup_tables");
+
+        let owner = Pubkey::new_unique();
+        let (authority, _) = authority_pda(&owner);
+        let table = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (authority, Account::default()),
+            (table, Account::default()),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+            (LOOKUP_TABLE_ID, Account::default()),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            authority,
+            table,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Closes the table with `authority` to `owner`, who signs.
+    fn instruction_close(&self, owner: &Pubkey, authority: &Pubkey, bump: u8) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(*authority, false),
+            AccountMeta::new(self.table, false),
+            AccountMeta::new_readonly(LOOKUP_TABLE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                LookupTablesInstruction::Close,
+                &AuthorityInstructionData::new(bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let (_, bump) = authority_pda(&setup.owner);
+    let mut instruction = setup.instruction_close(&setup.owner, &setup.authority, bump);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker adds addresses of their choice to the table of the owner,
+/// as the authority PDA of the owner.
+#[test]
+fn test_extend_table_of_other_owner() {
+    let setup = Setup::new();
+    let (_, bump) = authority_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new_readonly(setup.authority, false),
+        AccountMeta::new(setup.table, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        AccountMeta::new_readonly(LOOKUP_TABLE_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            LookupTablesInstruction::Extend,
+            &ExtendInstructionData::new(&[Pubkey::new_unique().to_bytes()], bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker closes the table of the owner as the authority PDA of the
+/// owner, taking its lamports.
+#[test]
+fn test_close_table_of_other_owner() {
+    let setup = Setup::new();
+    let (_, bump) = authority_pda(&setup.attacker);
+    setup.expect_err(
+        &setup.instruction_close(&setup.attacker, &setup.authority, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
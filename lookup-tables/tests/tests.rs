@@ -0,0 +1,370 @@
+//! The integration tests need the address lookup table program, which is not
+//! a builtin of Mollusk. Dump it from mainnet before running them:
+//!
+//! ```sh
+//! cargo xtask fetch lookup-tables
+//! ```
+
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use lookup_tables::{
+    lookup_table::{
+        self, ADDRESS_LOOKUP_TABLE_PROGRAM_ID, CLOSE_LOOKUP_TABLE_DATA,
+        DEACTIVATE_LOOKUP_TABLE_DATA, LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE,
+    },
+    AuthorityInstructionData, CreateInstructionData, ExtendInstructionData, LookupTablesError,
+    LookupTablesInstruction, AUTHORITY_SEED, MAX_NEW_ADDRESSES,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_message::{v0, AddressLookupTableAccount};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(lookup_tables::ID);
+const LOOKUP_TABLE_ID: Pubkey = Pubkey::new_from_array(ADDRESS_LOOKUP_TABLE_PROGRAM_ID);
+
+/// Offsets of the lookup table metadata fields.
+const DEACTIVATION_SLOT_OFFSET: usize = 4;
+const AUTHORITY_OFFSET: usize = 22;
+
+const SLOT: u64 = 100;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: LookupTablesInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<LookupTablesInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+#[test]
+fn test_lookup_tables_instruction_data() {
+    let mut expected = vec![0, 0, 0, 0];
+    expected.extend_from_slice(&42u64.to_le_bytes());
+    expected.push(254);
+    assert_eq!(lookup_table::create_lookup_table(42, 254), &expected[..]);
+
+    let addresses = [[1; 32], [2; 32]];
+    let mut buf = [0; lookup_table::extend_lookup_table_len(MAX_NEW_ADDRESSES)];
+    let mut expected = vec![2, 0, 0, 0];
+    expected.extend_from_slice(&2u64.to_le_bytes());
+    expected.extend_from_slice(&[1; 32]);
+    expected.extend_from_slice(&[2; 32]);
+    assert_eq!(
+        lookup_table::extend_lookup_table(&mut buf, &addresses),
+        &expected[..]
+    );
+
+    assert_eq!(DEACTIVATE_LOOKUP_TABLE_DATA, [3, 0, 0, 0]);
+    assert_eq!(CLOSE_LOOKUP_TABLE_DATA, [4, 0, 0, 0]);
+}
+
+/// Returns the addresses stored in a lookup table account. That's all a
+/// client needs to compile versioned transactions using the table.
+fn lookup_table_addresses(data: &[u8]) -> Vec<Pubkey> {
+    data[LOOKUP_TABLE_META_SIZE..]
+        .chunks_exact(32)
+        .map(|address| Pubkey::try_from(address).unwrap())
+        .collect()
+}
+
+/// Returns the data of an active lookup table storing `addresses`.
+fn lookup_table_data(authority: &Pubkey, addresses: &[Pubkey]) -> Vec<u8> {
+    let mut data = vec![0; LOOKUP_TABLE_META_SIZE];
+    // `ProgramState::LookupTable`.
+    data[0] = 1;
+    data[DEACTIVATION_SLOT_OFFSET..DEACTIVATION_SLOT_OFFSET + 8]
+        .copy_from_slice(&u64::MAX.to_le_bytes());
+    data[AUTHORITY_OFFSET - 1] = 1;
+    data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + 32].copy_from_slice(authority.as_ref());
+    for address in addresses {
+        data.extend_from_slice(address.as_ref());
+    }
+    data
+}
+
+#[test]
+fn test_lookup_tables_versioned_message() {
+    let payer = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let table = Pubkey::new_unique();
+    let addresses: Vec<Pubkey> = (0..LOOKUP_TABLE_MAX_ADDRESSES)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+
+    // Load the table, as a client would after fetching the account.
+    let data = lookup_table_data(&Pubkey::new_unique(), &addresses);
+    let table_account = AddressLookupTableAccount {
+        key: table,
+        addresses: lookup_table_addresses(&data),
+    };
+    assert_eq!(table_account.addresses, addresses);
+
+    // An instruction touching way more accounts than a legacy transaction
+    // could fit.
+    let mut accounts = vec![AccountMeta::new(payer, true)];
+    accounts.extend(
+        addresses[..64]
+            .iter()
+            .map(|address| AccountMeta::new(*address, false)),
+    );
+    accounts.extend(
+        addresses[64..128]
+            .iter()
+            .map(|address| AccountMeta::new_readonly(*address, false)),
+    );
+    let instruction = Instruction::new_with_bytes(program, &[], accounts);
+
+    let message =
+        v0::Message::try_compile(&payer, &[instruction], &[table_account], Hash::default())
+            .unwrap();
+
+    // Signers and invoked programs can't be loaded from a table, everything
+    // else is referenced by its index in the table.
+    assert_eq!(message.account_keys, vec![payer, program]);
+    assert_eq!(message.address_table_lookups.len(), 1);
+    let lookup = &message.address_table_lookups[0];
+    assert_eq!(lookup.account_key, table);
+    assert_eq!(lookup.writable_indexes, (0..64).collect::<Vec<u8>>());
+    assert_eq!(lookup.readonly_indexes, (64..128).collect::<Vec<u8>>());
+
+    // Account indices of the compiled instruction refer to the static keys
+    // first, then to the writable and readonly table addresses.
+    let compiled = &message.instructions[0];
+    assert_eq!(compiled.program_id_index, 1);
+    assert_eq!(
+        compiled.accounts,
+        [0].into_iter().chain(2..130).collect::<Vec<u8>>()
+    );
+}
+
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    authority: Pubkey,
+    authority_bump: u8,
+    table: Pubkey,
+    table_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/lookup_tables");
+        mollusk.add_program(
+            &LOOKUP_TABLE_ID,
+            "third-party/address_lookup_table",
+            &LOADER_V3,
+        );
+        mollusk.warp_to_slot(SLOT);
+
+        let owner = Pubkey::new_unique();
+        let (authority, authority_bump) =
+            Pubkey::find_program_address(&[AUTHORITY_SEED.as_bytes(), owner.as_ref()], &ID);
+        let (table, table_bump) = Pubkey::find_program_address(
+            &[authority.as_ref(), &(SLOT - 1).to_le_bytes()],
+            &LOOKUP_TABLE_ID,
+        );
+        let accounts = vec![
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (authority, Account::default()),
+            (table, Account::default()),
+            keyed_account_for_system_program(),
+            (
+                LOOKUP_TABLE_ID,
+                create_program_account_loader_v3(&LOOKUP_TABLE_ID),
+            ),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            authority,
+            authority_bump,
+            table,
+            table_bump,
+            accounts,
+        }
+    }
+
+    fn instruction_create(&self, authority_bump: u8) -> Instruction {
+        let data = CreateInstructionData::new(SLOT - 1, authority_bump, self.table_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new(self.table, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(LOOKUP_TABLE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LookupTablesInstruction::Create, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_extend(&self, addresses: &[Pubkey]) -> Instruction {
+        let addresses: Vec<[u8; 32]> = addresses.iter().map(|a| a.to_bytes()).collect();
+        let data = ExtendInstructionData::new(&addresses, self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new(self.table, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(LOOKUP_TABLE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(LookupTablesInstruction::Extend, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_authority(&self, discriminator: LookupTablesInstruction) -> Instruction {
+        let data = AuthorityInstructionData::new(self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new(self.table, false),
+            AccountMeta::new_readonly(LOOKUP_TABLE_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &instruction_data(discriminator, &data), ix_accounts)
+    }
+
+    /// Runs the instruction, checks the result and returns the resulting
+    /// accounts.
+    fn process(&self, instruction: &Instruction, checks: &[Check]) -> Vec<(Pubkey, Account)> {
+        self.mollusk
+            .process_and_validate_instruction(instruction, &self.accounts, checks)
+            .resulting_accounts
+    }
+}
+
+#[test]
+fn test_lookup_tables_lifecycle() {
+    let mut setup = Setup::new();
+    let addresses = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+
+    setup.accounts = setup.process(
+        &setup.instruction_create(setup.authority_bump),
+        &[
+            Check::success(),
+            Check::account(&setup.table)
+                .owner(&LOOKUP_TABLE_ID)
+                .data(&lookup_table_data(&setup.authority, &[]))
+                .build(),
+        ],
+    );
+
+    // Addresses can't be used in the slot in which they were added, so the
+    // extension is checked only through the stored addresses.
+    setup.accounts = setup.process(
+        &setup.instruction_extend(&addresses),
+        &[
+            Check::success(),
+            Check::account(&setup.table)
+                .data_slice(
+                    LOOKUP_TABLE_META_SIZE,
+                    &lookup_table_data(&setup.authority, &addresses)[LOOKUP_TABLE_META_SIZE..],
+                )
+                .build(),
+        ],
+    );
+    let table = &setup
+        .accounts
+        .iter()
+        .find(|(key, _)| key == &setup.table)
+        .unwrap()
+        .1;
+    assert_eq!(lookup_table_addresses(&table.data), addresses);
+
+    setup.mollusk.warp_to_slot(SLOT + 1);
+    setup.accounts = setup.process(
+        &setup.instruction_authority(LookupTablesInstruction::Deactivate),
+        &[
+            Check::success(),
+            Check::account(&setup.table)
+                .data_slice(DEACTIVATION_SLOT_OFFSET, &(SLOT + 1).to_le_bytes())
+                .build(),
+        ],
+    );
+
+    // Once the deactivation slot is gone from `SlotHashes`, the table can be
+    // closed.
+    setup.mollusk.warp_to_slot(SLOT + 1_000);
+    let lamports = setup.accounts[0].1.lamports + setup.accounts[2].1.lamports;
+    setup.process(
+        &setup.instruction_authority(LookupTablesInstruction::Close),
+        &[
+            Check::success(),
+            Check::account(&setup.owner).lamports(lamports).build(),
+            Check::account(&setup.table).closed().build(),
+        ],
+    );
+}
+
+#[test]
+fn test_lookup_tables_invalid() {
+    let setup = Setup::new();
+
+    // The authority has to be the PDA of the owner.
+    let wrong_bump = (0..setup.authority_bump)
+        .rev()
+        .find(|bump| {
+            Pubkey::create_program_address(
+                &[AUTHORITY_SEED.as_bytes(), setup.owner.as_ref(), &[*bump]],
+                &ID,
+            )
+            .is_ok()
+        })
+        .unwrap();
+    setup.process(
+        &setup.instruction_create(wrong_bump),
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+
+    // Only the lookup table program can be called.
+    let mut instruction = setup.instruction_authority(LookupTablesInstruction::Deactivate);
+    instruction.accounts[3].pubkey = keyed_account_for_system_program().0;
+    setup.process(
+        &instruction,
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+
+    // Too many addresses can't be even built with
+    // `ExtendInstructionData::new`, so fake the length.
+    let mut too_many = setup.instruction_extend(&[Pubkey::new_unique(); MAX_NEW_ADDRESSES]);
+    too_many.data[1 + 32 * MAX_NEW_ADDRESSES] += 1;
+    for instruction in [setup.instruction_extend(&[]), too_many] {
+        setup.process(
+            &instruction,
+            &[Check::err(ProgramError::Custom(
+                Namespace::LookupTables.code(LookupTablesError::InvalidAddressCount as u32),
+            ))],
+        );
+    }
+}
@@ -0,0 +1,618 @@
+//! Derive macros for the example programs.
+//!
+//! [`InstructionDiscriminator`] replaces the hand-written conversions from
+//! the first byte of instruction data to an instruction enum, and
+//! [`InstructionData`] the casts of the rest of it to structs. [`Event`]
+//! derives the events logged by the programs. [`Accounts`] replaces the
+//! destructuring and validation of the accounts of instructions.
+//!
+//! With the `anchor` feature, instructions are identified by Anchor's
+//! 8-byte discriminators instead, so Anchor clients and explorers can decode
+//! them. [`anchor_discriminator!`] computes such discriminators for
+//! accounts.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use sha2::{Digest, Sha256};
+use syn::{
+    parse::ParseStream, parse_macro_input, Data, DeriveInput, Error, Expr, Fields, Ident, LitStr,
+    Meta, Token, Type,
+};
+
+/// Returns Anchor's discriminator of `preimage`, the first 8 bytes of its
+/// SHA-256 hash.
+fn discriminator(preimage: &str) -> [u8; 8] {
+    Sha256::digest(preimage.as_bytes())[..8].try_into().unwrap()
+}
+
+/// Converts the name of a variant to the snake case name of an Anchor
+/// instruction.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// Expands to Anchor's 8-byte discriminator of a preimage, e.g.
+/// `"account:Counter"` for the `Counter` account.
+///
+/// ```ignore
+/// const PREFIX: &'static [u8] = &anchor_discriminator!("account:Counter");
+/// ```
+#[proc_macro]
+pub fn anchor_discriminator(input: TokenStream) -> TokenStream {
+    let preimage = parse_macro_input!(input as LitStr);
+    let bytes = discriminator(&preimage.value());
+    quote! { [#(#bytes),*] }.into()
+}
+
+/// Derives parsing of instruction discriminators for a `#[repr(u8)]` enum
+/// with unit variants.
+///
+/// Generates:
+///
+/// * `TryFrom<&u8>`, which maps a discriminant to its variant.
+/// * `DISCRIMINATOR_LEN` and `discriminator(&self) -> &'static [u8]`, the
+///   bytes identifying the instruction in instruction data.
+/// * `split(&[u8]) -> Result<(Self, &[u8]), ProgramError>`, which splits
+///   instruction data into the instruction and the rest of the data.
+///
+/// Both fail with `ProgramError::InvalidInstructionData` for unknown
+/// discriminators and `split` also for too short data.
+///
+/// The discriminator is the discriminant of the variant, unless the `anchor`
+/// feature is enabled. Then it's Anchor's discriminator of the snake case
+/// name of the variant, e.g. the hash of `"global:create"` for `Create`.
+///
+/// With `#[versioned]`, that discriminator is the family of the instruction
+/// and is followed by a `common::version::Version` byte, so the layout of
+/// the rest of the data can change between versions. It's then exposed as
+/// `FAMILY_LEN`, `family(&self)` and `split_family(&[u8])`, while
+/// `DISCRIMINATOR_LEN` counts the version too, `discriminator(&self,
+/// version)` returns both and `split` returns the version as well:
+///
+/// ```ignore
+/// #[derive(InstructionDiscriminator)]
+/// #[repr(u8)]
+/// #[versioned]
+/// pub enum CounterInstruction {
+///     Create,
+///     Increment,
+/// }
+///
+/// let (instruction, version, instruction_data) = CounterInstruction::split(data)?;
+/// ```
+///
+/// ```ignore
+/// #[derive(InstructionDiscriminator)]
+/// #[repr(u8)]
+/// pub enum CounterInstruction {
+///     Create,
+///     Increment,
+/// }
+///
+/// let (instruction, instruction_data) = CounterInstruction::split(data)?;
+/// ```
+#[proc_macro_derive(InstructionDiscriminator, attributes(versioned))]
+pub fn derive_instruction_discriminator(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    instruction_discriminator(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn instruction_discriminator(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input,
+            "InstructionDiscriminator can only be derived for enums",
+        ));
+    };
+    let is_repr_u8 = input.attrs.iter().any(|attr| {
+        attr.path().is_ident("repr")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|repr| repr == "u8")
+    });
+    if !is_repr_u8 {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "InstructionDiscriminator requires #[repr(u8)]",
+        ));
+    }
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| match variant.fields {
+            Fields::Unit => Ok(&variant.ident),
+            _ => Err(Error::new_spanned(
+                variant,
+                "InstructionDiscriminator requires unit variants",
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let versioned = input
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("versioned"));
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // With `#[versioned]`, the discriminator identifies the family of the
+    // instruction and the version follows it.
+    let (len, discriminator_fn, split) = if versioned {
+        (
+            format_ident!("FAMILY_LEN"),
+            format_ident!("family"),
+            format_ident!("split_family"),
+        )
+    } else {
+        (
+            format_ident!("DISCRIMINATOR_LEN"),
+            format_ident!("discriminator"),
+            format_ident!("split"),
+        )
+    };
+
+    let discriminators = if cfg!(feature = "anchor") {
+        let discriminators = variants
+            .iter()
+            .map(|variant| {
+                let bytes = discriminator(&format!("global:{}", snake_case(&variant.to_string())));
+                quote! { [#(#bytes),*] }
+            })
+            .collect::<Vec<_>>();
+        quote! {
+            /// Length of the discriminator.
+            pub const #len: usize = 8;
+
+            /// Returns the discriminator of the instruction.
+            pub fn #discriminator_fn(&self) -> &'static [u8] {
+                match self {
+                    #(Self::#variants => &#discriminators,)*
+                }
+            }
+
+            /// Splits instruction data into the instruction and the rest of
+            /// the data.
+            pub fn #split(
+                data: &[u8],
+            ) -> ::core::result::Result<(Self, &[u8]), ::pinocchio::program_error::ProgramError>
+            {
+                if data.len() < Self::#len {
+                    return Err(::pinocchio::program_error::ProgramError::InvalidInstructionData);
+                }
+                let (discriminator, data) = data.split_at(Self::#len);
+                let instruction = match discriminator {
+                    #(#discriminators => Self::#variants,)*
+                    _ => {
+                        return Err(
+                            ::pinocchio::program_error::ProgramError::InvalidInstructionData,
+                        )
+                    }
+                };
+                Ok((instruction, data))
+            }
+        }
+    } else {
+        quote! {
+            /// Length of the discriminator.
+            pub const #len: usize = 1;
+
+            /// Returns the discriminator of the instruction.
+            pub fn #discriminator_fn(&self) -> &'static [u8] {
+                match self {
+                    #(Self::#variants => {
+                        const DISCRIMINATOR: [u8; 1] = [#name::#variants as u8];
+                        &DISCRIMINATOR
+                    })*
+                }
+            }
+
+            /// Splits instruction data into the instruction and the rest of
+            /// the data.
+            pub fn #split(
+                data: &[u8],
+            ) -> ::core::result::Result<(Self, &[u8]), ::pinocchio::program_error::ProgramError>
+            {
+                let (discriminator, data) = data
+                    .split_first()
+                    .ok_or(::pinocchio::program_error::ProgramError::InvalidInstructionData)?;
+                Ok((Self::try_from(discriminator)?, data))
+            }
+        }
+    };
+
+    let versions = if versioned {
+        quote! {
+            /// Length of the discriminator, the family and the version.
+            pub const DISCRIMINATOR_LEN: usize = Self::FAMILY_LEN + 1;
+
+            /// Returns the discriminator of `version` of the instruction.
+            pub fn discriminator(
+                &self,
+                version: ::common::version::Version,
+            ) -> [u8; Self::DISCRIMINATOR_LEN] {
+                let mut discriminator = [0; Self::DISCRIMINATOR_LEN];
+                discriminator[..Self::FAMILY_LEN].copy_from_slice(self.family());
+                discriminator[Self::FAMILY_LEN] = version as u8;
+                discriminator
+            }
+
+            /// Splits instruction data into the instruction, its version and
+            /// the rest of the data.
+            pub fn split(
+                data: &[u8],
+            ) -> ::core::result::Result<
+                (Self, ::common::version::Version, &[u8]),
+                ::pinocchio::program_error::ProgramError,
+            > {
+                let (instruction, data) = Self::split_family(data)?;
+                let (version, data) = ::common::version::Version::split(data)?;
+                Ok((instruction, version, data))
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::TryFrom<&u8> for #name #ty_generics #where_clause {
+            type Error = ::pinocchio::program_error::ProgramError;
+
+            fn try_from(value: &u8) -> ::core::result::Result<Self, Self::Error> {
+                match *value {
+                    #(v if v == Self::#variants as u8 => Ok(Self::#variants),)*
+                    _ => Err(::pinocchio::program_error::ProgramError::InvalidInstructionData),
+                }
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #discriminators
+            #versions
+        }
+    })
+}
+
+/// Derives the encoding of a struct as instruction data.
+///
+/// The fields are encoded one after another, without padding, with
+/// `common::encoding::Encoding`, which the struct implements as well, so it
+/// can be nested in other instruction data. Generates:
+///
+/// * `LEN`, the length of the encoding.
+/// * `from_bytes(&[u8]) -> Result<Self, ProgramError>`, which fails with
+///   `ProgramError::InvalidInstructionData` if the data isn't exactly `LEN`
+///   bytes long.
+/// * `to_bytes(&self) -> [u8; LEN]`.
+///
+/// ```ignore
+/// #[derive(InstructionData)]
+/// pub struct TransferInstructionData {
+///     pub amount: u64,
+///     pub bump: u8,
+/// }
+///
+/// let instruction_data = TransferInstructionData::from_bytes(instruction_data)?;
+/// ```
+#[proc_macro_derive(InstructionData)]
+pub fn derive_instruction_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    instruction_data(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn instruction_data(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let encoding = encoding(&input, "InstructionData")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        #encoding
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Length of the encoded instruction data.
+            pub const LEN: usize = <Self as ::common::encoding::Encoding>::LEN;
+
+            /// Decodes instruction data, checking its length.
+            pub fn from_bytes(
+                data: &[u8],
+            ) -> ::core::result::Result<Self, ::pinocchio::program_error::ProgramError> {
+                if data.len() != Self::LEN {
+                    return Err(::pinocchio::program_error::ProgramError::InvalidInstructionData);
+                }
+                Ok(<Self as ::common::encoding::Encoding>::decode(data))
+            }
+
+            /// Encodes instruction data.
+            pub fn to_bytes(&self) -> [u8; Self::LEN] {
+                let mut data = [0; Self::LEN];
+                ::common::encoding::Encoding::encode(self, &mut data);
+                data
+            }
+        }
+    })
+}
+
+/// Derives an event emitted by a program with `sol_log_data`.
+///
+/// The fields are encoded like with [`InstructionData`], implementing
+/// `common::encoding::Encoding`, and the event implements `events::Event`
+/// with Anchor's discriminator of its name, e.g. the hash of
+/// `"event:CounterIncremented"` for `CounterIncremented`.
+///
+/// ```ignore
+/// #[derive(Event)]
+/// pub struct CounterIncremented {
+///     pub owner: Pubkey,
+///     pub count: u64,
+/// }
+///
+/// emit!(CounterIncremented { owner, count });
+/// ```
+#[proc_macro_derive(Event)]
+pub fn derive_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    event(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn event(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let encoding = encoding(&input, "Event")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let bytes = discriminator(&format!("event:{name}"));
+
+    Ok(quote! {
+        #encoding
+
+        impl #impl_generics ::events::Event for #name #ty_generics #where_clause {
+            const DISCRIMINATOR: [u8; 8] = [#(#bytes),*];
+        }
+    })
+}
+
+/// Implements `common::encoding::Encoding` for a struct with named fields,
+/// encoding them one after another.
+fn encoding(input: &DeriveInput, derive: &str) -> Result<proc_macro2::TokenStream, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            format!("{derive} can only be derived for structs"),
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(
+            input,
+            format!("{derive} requires named fields"),
+        ));
+    };
+    let names = fields
+        .named
+        .iter()
+        .map(|field| &field.ident)
+        .collect::<Vec<_>>();
+    let lens = fields
+        .named
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+            quote! { <#ty as ::common::encoding::Encoding>::LEN }
+        })
+        .collect::<Vec<_>>();
+    // Offset of each field, the sum of the lengths of the previous ones.
+    let starts = (0..lens.len())
+        .map(|i| {
+            let previous = &lens[..i];
+            quote! { 0 #(+ #previous)* }
+        })
+        .collect::<Vec<_>>();
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::common::encoding::Encoding for #name #ty_generics #where_clause {
+            const LEN: usize = 0 #(+ #lens)*;
+
+            #[inline(always)]
+            fn decode(data: &[u8]) -> Self {
+                Self {
+                    #(
+                        #names: ::common::encoding::Encoding::decode(
+                            &data[#starts..#starts + #lens],
+                        ),
+                    )*
+                }
+            }
+
+            #[inline(always)]
+            fn encode(&self, data: &mut [u8]) {
+                #(
+                    ::common::encoding::Encoding::encode(
+                        &self.#names,
+                        &mut data[#starts..#starts + #lens],
+                    );
+                )*
+            }
+        }
+    })
+}
+
+/// Derives the destructuring and validation of the accounts of an
+/// instruction into a struct with a `&'a AccountInfo` field per account, in
+/// the order of the accounts.
+///
+/// Generates `from_accounts(&'a [AccountInfo]) -> Result<Self, ProgramError>`,
+/// which fails with `ProgramError::NotEnoughAccountKeys` unless exactly one
+/// account is passed per field, and then checks the fields in order, each
+/// with the checks of its attributes in order:
+///
+/// * `#[signer]` with `common::checks::require_signer`.
+/// * `#[writable]` with `common::checks::require_writable`.
+/// * `#[owner = ID]` with `common::checks::require_owned_by`.
+/// * `#[program = ID]` with `common::checks::require_program`.
+/// * `#[pda(seeds = [...], bump = ...)]` with `common::check_pda`, for the
+///   PDA of `crate::ID` unless set with `program_id = ...`. The seeds can
+///   refer to the other accounts by the names of their fields.
+/// * `#[check = f]` with any `f(&AccountInfo) -> ProgramResult`.
+///
+/// Values the checks need besides the accounts, e.g. a bump from the
+/// instruction data, are declared with `#[instruction(...)]` and become
+/// arguments of `from_accounts`.
+///
+/// ```ignore
+/// #[derive(Accounts)]
+/// #[instruction(bump: u8)]
+/// pub struct IncrementAccounts<'a> {
+///     #[signer]
+///     pub owner: &'a AccountInfo,
+///     #[pda(seeds = [COUNTER_SEED.as_bytes(), owner.key()], bump = bump)]
+///     #[owner = ID]
+///     pub counter: &'a AccountInfo,
+/// }
+///
+/// let accounts = IncrementAccounts::from_accounts(accounts, instruction_data.bump)?;
+/// ```
+#[proc_macro_derive(
+    Accounts,
+    attributes(instruction, signer, writable, owner, program, pda, check)
+)]
+pub fn derive_accounts(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    accounts(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn accounts(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input,
+            "Accounts can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(&input, "Accounts requires named fields"));
+    };
+    let Some(lifetime) = input.generics.lifetimes().next() else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "Accounts requires the lifetime of the accounts, e.g. `'a`",
+        ));
+    };
+    let lifetime = &lifetime.lifetime;
+
+    let mut args = Vec::new();
+    for attr in &input.attrs {
+        if attr.path().is_ident("instruction") {
+            args.extend(attr.parse_args_with(|input: ParseStream| {
+                input.parse_terminated(
+                    |input| {
+                        let name = input.parse::<Ident>()?;
+                        input.parse::<Token![:]>()?;
+                        let ty = input.parse::<Type>()?;
+                        Ok(quote! { #name: #ty })
+                    },
+                    Token![,],
+                )
+            })?);
+        }
+    }
+
+    let names = fields
+        .named
+        .iter()
+        .map(|field| &field.ident)
+        .collect::<Vec<_>>();
+    let mut checks = Vec::new();
+    for field in &fields.named {
+        let name = &field.ident;
+        for attr in &field.attrs {
+            let path = attr.path();
+            let check = if path.is_ident("signer") {
+                attr.meta.require_path_only()?;
+                quote! { ::common::checks::require_signer(#name)?; }
+            } else if path.is_ident("writable") {
+                attr.meta.require_path_only()?;
+                quote! { ::common::checks::require_writable(#name)?; }
+            } else if path.is_ident("owner") {
+                let owner = name_value(&attr.meta)?;
+                quote! { ::common::checks::require_owned_by(#name, &(#owner))?; }
+            } else if path.is_ident("program") {
+                let program_id = name_value(&attr.meta)?;
+                quote! { ::common::checks::require_program(#name, &(#program_id))?; }
+            } else if path.is_ident("check") {
+                let check = name_value(&attr.meta)?;
+                quote! { (#check)(#name)?; }
+            } else if path.is_ident("pda") {
+                pda(attr, name)?
+            } else {
+                continue;
+            };
+            checks.push(check);
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Destructures and validates the accounts of the instruction.
+            #[inline(always)]
+            pub fn from_accounts(
+                accounts: &#lifetime [::pinocchio::account_info::AccountInfo],
+                #(#args,)*
+            ) -> ::core::result::Result<Self, ::pinocchio::program_error::ProgramError> {
+                let [#(#names),*] = accounts else {
+                    return Err(::pinocchio::program_error::ProgramError::NotEnoughAccountKeys);
+                };
+                #(#checks)*
+                Ok(Self { #(#names),* })
+            }
+        }
+    })
+}
+
+/// Returns the value of an attribute like `#[owner = ID]`.
+fn name_value(meta: &Meta) -> Result<&Expr, Error> {
+    Ok(&meta.require_name_value()?.value)
+}
+
+/// Returns the check of a `#[pda(seeds = [...], bump = ...)]` attribute of
+/// the account `name`.
+fn pda(attr: &syn::Attribute, name: &Option<Ident>) -> Result<proc_macro2::TokenStream, Error> {
+    let mut seeds = None;
+    let mut bump = None;
+    let mut program_id = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("seeds") {
+            seeds = Some(meta.value()?.parse::<Expr>()?);
+        } else if meta.path.is_ident("bump") {
+            bump = Some(meta.value()?.parse::<Expr>()?);
+        } else if meta.path.is_ident("program_id") {
+            program_id = Some(meta.value()?.parse::<Expr>()?);
+        } else {
+            return Err(meta.error("expected `seeds`, `bump` or `program_id`"));
+        }
+        Ok(())
+    })?;
+    let seeds = seeds.ok_or_else(|| Error::new_spanned(attr, "pda requires `seeds`"))?;
+    let bump = bump.ok_or_else(|| Error::new_spanned(attr, "pda requires `bump`"))?;
+    let program_id = program_id.unwrap_or_else(|| syn::parse_quote! { crate::ID });
+
+    Ok(quote! {
+        ::common::check_pda(&#seeds, #bump, #name.key(), &(#program_id))?;
+    })
+}
@@ -0,0 +1,218 @@
+use common::version::Version;
+use macros::{anchor_discriminator, Accounts, InstructionData, InstructionDiscriminator};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+#[derive(Debug, PartialEq, InstructionDiscriminator)]
+#[repr(u8)]
+enum Instruction {
+    Create,
+    Increment,
+    Decrement,
+}
+
+#[derive(Debug, PartialEq, InstructionDiscriminator)]
+#[repr(u8)]
+enum SparseInstruction {
+    First = 3,
+    Second,
+    Last = 255,
+}
+
+#[derive(Debug, PartialEq, InstructionDiscriminator)]
+#[repr(u8)]
+#[versioned]
+enum VersionedInstruction {
+    Create,
+    Increment,
+}
+
+#[test]
+fn test_round_trip() {
+    for discriminator in 0..=u8::MAX {
+        match Instruction::try_from(&discriminator) {
+            Ok(instruction) => assert_eq!(instruction as u8, discriminator),
+            Err(e) => {
+                assert!(discriminator > Instruction::Decrement as u8);
+                assert_eq!(e, ProgramError::InvalidInstructionData);
+            }
+        }
+    }
+    for instruction in [
+        Instruction::Create,
+        Instruction::Increment,
+        Instruction::Decrement,
+    ] {
+        let discriminator = instruction as u8;
+        assert_eq!(
+            Instruction::try_from(&discriminator).map(|i| i as u8),
+            Ok(discriminator)
+        );
+    }
+}
+
+#[test]
+fn test_round_trip_explicit_discriminants() {
+    let mut parsed = Vec::new();
+    for discriminator in 0..=u8::MAX {
+        if let Ok(instruction) = SparseInstruction::try_from(&discriminator) {
+            assert_eq!(instruction as u8, discriminator);
+            parsed.push(discriminator);
+        }
+    }
+    assert_eq!(parsed, [3, 4, 255]);
+}
+
+#[cfg(not(feature = "anchor"))]
+#[test]
+fn test_split() {
+    assert_eq!(Instruction::DISCRIMINATOR_LEN, 1);
+    assert_eq!(Instruction::Decrement.discriminator(), [2]);
+    assert_eq!(
+        Instruction::split(&[1, 2, 3]),
+        Ok((Instruction::Increment, [2, 3].as_slice()))
+    );
+    assert_eq!(
+        Instruction::split(&[2]),
+        Ok((Instruction::Decrement, [].as_slice()))
+    );
+    assert_eq!(
+        Instruction::split(&[3, 0]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        Instruction::split(&[]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[cfg(not(feature = "anchor"))]
+#[test]
+fn test_split_versioned() {
+    assert_eq!(VersionedInstruction::DISCRIMINATOR_LEN, 2);
+    assert_eq!(
+        VersionedInstruction::Increment.discriminator(Version::V2),
+        [1, 2]
+    );
+    assert_eq!(
+        VersionedInstruction::split(&[1, 2, 3]),
+        Ok((VersionedInstruction::Increment, Version::V2, [3].as_slice()))
+    );
+    assert_eq!(
+        VersionedInstruction::split(&[0, 1]),
+        Ok((VersionedInstruction::Create, Version::V1, [].as_slice()))
+    );
+    // Unknown families and versions, and a missing version.
+    for data in [&[2, 1][..], &[0, 0], &[0, 3], &[0]] {
+        assert_eq!(
+            VersionedInstruction::split(data),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
+
+#[cfg(feature = "anchor")]
+#[test]
+fn test_split_anchor() {
+    let mut data = Instruction::Increment.discriminator().to_vec();
+    data.push(2);
+    assert_eq!(
+        Instruction::split(&data),
+        Ok((Instruction::Increment, [2].as_slice()))
+    );
+    assert_eq!(
+        Instruction::split(&data[..7]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        Instruction::split(&[0; 8]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_anchor_discriminator() {
+    // The discriminator of Anchor's `initialize` instructions.
+    assert_eq!(
+        anchor_discriminator!("global:initialize"),
+        [175, 175, 109, 31, 13, 152, 155, 237]
+    );
+}
+
+#[derive(Debug, PartialEq, InstructionData)]
+struct Inner {
+    a: u16,
+    b: [u8; 3],
+}
+
+#[derive(Debug, PartialEq, InstructionData)]
+struct Outer {
+    amount: u64,
+    delta: i32,
+    bump: u8,
+    inner: Inner,
+    keys: [[u8; 2]; 2],
+}
+
+fn outer() -> Outer {
+    Outer {
+        amount: 0x0102_0304_0506_0708,
+        delta: -2,
+        bump: 9,
+        inner: Inner {
+            a: 0x0a0b,
+            b: [1, 2, 3],
+        },
+        keys: [[4, 5], [6, 7]],
+    }
+}
+
+#[test]
+fn test_instruction_data_layout() {
+    assert_eq!(Inner::LEN, 5);
+    assert_eq!(Outer::LEN, 22);
+    assert_eq!(
+        outer().to_bytes(),
+        [8, 7, 6, 5, 4, 3, 2, 1, 0xfe, 0xff, 0xff, 0xff, 9, 0x0b, 0x0a, 1, 2, 3, 4, 5, 6, 7]
+    );
+}
+
+#[test]
+fn test_instruction_data_round_trip() {
+    let data = outer().to_bytes();
+    assert_eq!(Outer::from_bytes(&data), Ok(outer()));
+}
+
+#[test]
+fn test_instruction_data_invalid_len() {
+    let data = outer().to_bytes();
+    for invalid in [&data[..0], &data[..Outer::LEN - 1], &[0; Outer::LEN + 1]] {
+        assert_eq!(
+            Outer::from_bytes(invalid),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+}
+
+/// Program of the PDAs checked by `Accounts`.
+const ID: Pubkey = [7; 32];
+
+// The fields are only destructured, without a runtime to pass accounts.
+#[allow(dead_code)]
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+struct IncrementAccounts<'a> {
+    #[signer]
+    owner: &'a AccountInfo,
+    #[writable]
+    #[pda(seeds = [b"counter".as_slice(), owner.key()], bump = bump)]
+    #[owner = ID]
+    counter: &'a AccountInfo,
+}
+
+#[test]
+fn test_accounts_not_enough_keys() {
+    assert_eq!(
+        IncrementAccounts::from_accounts(&[], 255).err(),
+        Some(ProgramError::NotEnoughAccountKeys)
+    );
+}
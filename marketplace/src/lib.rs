@@ -0,0 +1,572 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use common::close::close_account;
+use errors::{Namespace, SharedError};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::{CreateAccount, Transfer as SystemTransfer};
+use pinocchio_token::{
+    instructions::{CloseAccount, Transfer},
+    state::TokenAccount,
+};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("BFKMAr76CF9Lry5ceF9tfs81bVbtp9tMiDymZJYrDr7X");
+
+pub const MARKET_SEED: &str = "market";
+pub const LISTING_SEED: &str = "listing";
+
+/// Fees are expressed in basis points of the price.
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+/// Errors returned by the marketplace program.
+#[repr(u32)]
+pub enum MarketplaceError {
+    /// The fee exceeds [`MAX_FEE_BPS`].
+    InvalidFee,
+    /// A payment token account has a wrong owner or mint.
+    PaymentAccountMismatch,
+}
+
+impl From<MarketplaceError> for ProgramError {
+    fn from(e: MarketplaceError) -> Self {
+        Namespace::Marketplace.error(e as u32)
+    }
+}
+
+/// On-chain representation of a marketplace.
+#[repr(C)]
+pub struct Market {
+    pub authority: Pubkey,
+    /// The account receiving the fees.
+    pub treasury: Pubkey,
+    pub fee_bps: u16,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+}
+
+assert_layout!(Market, size = 72, align = 2, { authority: 0, treasury: 32, fee_bps: 64, bump: 66 });
+
+impl Market {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Returns the fee for a purchase at the given price.
+    pub fn fee(&self, price: u64) -> u64 {
+        (price as u128 * self.fee_bps as u128 / MAX_FEE_BPS as u128) as u64
+    }
+}
+
+/// On-chain representation of a listing.
+///
+/// The listed tokens are held by a token account owned by the listing PDA.
+#[repr(C)]
+pub struct Listing {
+    pub market: Pubkey,
+    pub seller: Pubkey,
+    /// Mint of the listed asset.
+    pub mint: Pubkey,
+    /// Mint of the token the seller wants to be paid in. All zeroes mean
+    /// lamports.
+    pub payment_mint: Pubkey,
+    pub price: u64,
+    pub amount: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Listing, size = 152, align = 8, {
+    market: 0,
+    seller: 32,
+    mint: 64,
+    payment_mint: 96,
+    price: 128,
+    amount: 136,
+    bump: 144,
+});
+
+impl Listing {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Marketplace program instruction discriminators.
+#[repr(u8)]
+pub enum MarketplaceInstruction {
+    /// Creates a marketplace.
+    InitializeMarket,
+    /// Lists tokens for sale.
+    List,
+    /// Buys listed tokens.
+    Purchase,
+    /// Cancels a listing.
+    Delist,
+}
+
+impl TryFrom<&u8> for MarketplaceInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeMarket),
+            1 => Ok(Self::List),
+            2 => Ok(Self::Purchase),
+            3 => Ok(Self::Delist),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeMarketInstructionData {
+    pub fee_bps: u16,
+    pub bump: u8,
+}
+
+impl InitializeMarketInstructionData {
+    pub fn new(fee_bps: u16, bump: u8) -> Self {
+        Self { fee_bps, bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct ListInstructionData {
+    pub payment_mint: Pubkey,
+    pub price: u64,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl ListInstructionData {
+    pub fn new(payment_mint: Pubkey, price: u64, amount: u64, bump: u8) -> Self {
+        Self {
+            payment_mint,
+            price,
+            amount,
+            bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = MarketplaceInstruction::try_from(instruction)?;
+
+    match instruction {
+        MarketplaceInstruction::InitializeMarket => {
+            process_initialize_market(accounts, instruction_data)
+        }
+        MarketplaceInstruction::List => process_list(accounts, instruction_data),
+        MarketplaceInstruction::Purchase => process_purchase(accounts),
+        MarketplaceInstruction::Delist => process_delist(accounts),
+    }
+}
+
+/// Creates a marketplace charging `fee_bps` on every purchase.
+pub fn process_initialize_market(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, market, treasury, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeMarketInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.fee_bps > MAX_FEE_BPS {
+        return Err(MarketplaceError::InvalidFee.into());
+    }
+
+    // Check the seeds of `market`.
+    let market_pda = create_program_address(
+        &[
+            MARKET_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(market.key(), &market_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the market PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(MARKET_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: market,
+        lamports: Rent::get()?.minimum_balance(Market::LEN),
+        space: Market::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the market PDA.
+    let mut data = market.try_borrow_mut_data()?;
+    let data: &mut Market = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the market.
+    data.authority = *authority.key();
+    data.treasury = *treasury.key();
+    data.fee_bps = instruction_data.fee_bps;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Initialized market with {} bps fee",
+        instruction_data.fee_bps
+    );
+
+    Ok(())
+}
+
+/// Lists `amount` tokens from `seller_ata` for `price`. The tokens are moved
+/// to `listing_ata`, owned by the listing PDA, until they are sold or
+/// delisted.
+pub fn process_list(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [seller, seller_ata, market, listing, listing_ata, _system_program, _token_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(seller)?;
+
+    // Check that `market` is a market PDA of the program.
+    check_market(market)?;
+
+    // Check that `seller_ata` is owned by `seller`.
+    let mint = {
+        let seller_ata = TokenAccount::from_account_info(seller_ata)?;
+        if !keys_eq(seller_ata.owner(), seller.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        *seller_ata.mint()
+    };
+    // Check that `listing_ata` is owned by `listing`.
+    {
+        let listing_ata = TokenAccount::from_account_info(listing_ata)?;
+        if !keys_eq(listing_ata.owner(), listing.key()) || !keys_eq(listing_ata.mint(), &mint) {
+            return Err(ProgramError::IllegalOwner);
+        }
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = ListInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `listing`.
+    let listing_pda = create_program_address(
+        &[
+            LISTING_SEED.as_bytes(),
+            market.key(),
+            seller.key(),
+            &mint,
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(listing.key(), &listing_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the listing PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(LISTING_SEED.as_bytes()),
+        Seed::from(market.key()),
+        Seed::from(seller.key()),
+        Seed::from(&mint),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: seller,
+        to: listing,
+        lamports: Rent::get()?.minimum_balance(Listing::LEN),
+        space: Listing::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the listing PDA.
+    let mut data = listing.try_borrow_mut_data()?;
+    let data: &mut Listing = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the listing.
+    data.market = *market.key();
+    data.seller = *seller.key();
+    data.mint = mint;
+    data.payment_mint = instruction_data.payment_mint;
+    data.price = instruction_data.price;
+    data.amount = instruction_data.amount;
+    data.bump = instruction_data.bump;
+
+    // Transfer the listed tokens from seller to listing.
+    Transfer {
+        from: seller_ata,
+        to: listing_ata,
+        authority: seller,
+        amount: instruction_data.amount,
+    }
+    .invoke()?;
+
+    info!(
+        "Listed {} tokens for {}",
+        instruction_data.amount, instruction_data.price
+    );
+
+    Ok(())
+}
+
+/// Buys the tokens of a listing.
+///
+/// Listings priced in lamports are paid with system transfers. Listings
+/// priced in tokens need three more accounts: the token accounts of the
+/// buyer, the seller and the treasury, in that order.
+pub fn process_purchase(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [buyer, buyer_ata, seller, market, treasury, listing, listing_ata, _system_program, _token_program, payment_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(buyer)?;
+
+    // Check that `market` is a market PDA and that the listing PDA is owned
+    // by the program.
+    check_market(market)?;
+    require_owned_by(listing, &ID)?;
+
+    // Deserialize the market PDA.
+    let market_data = market.try_borrow_data()?;
+    let market_data: &Market = unsafe { &*market_data.as_ptr().cast() };
+
+    // Deserialize the listing PDA.
+    let (price, amount, payment_mint, mint, bump) = {
+        let data = listing.try_borrow_data()?;
+        let data: &Listing = unsafe { &*data.as_ptr().cast() };
+
+        // Check that the listing belongs to `market` and `seller`.
+        if !keys_eq(&data.market, market.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !keys_eq(&data.seller, seller.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        (
+            data.price,
+            data.amount,
+            data.payment_mint,
+            data.mint,
+            data.bump,
+        )
+    };
+
+    // Pay the seller and the treasury.
+    let fee = market_data.fee(price);
+    let proceeds = price.checked_sub(fee).ok_or(SharedError::Overflow)?;
+    if payment_mint == Pubkey::default() {
+        // Check that `treasury` is the same as in the market account.
+        if !keys_eq(&market_data.treasury, treasury.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        SystemTransfer {
+            from: buyer,
+            to: seller,
+            lamports: proceeds,
+        }
+        .invoke()?;
+        if fee > 0 {
+            SystemTransfer {
+                from: buyer,
+                to: treasury,
+                lamports: fee,
+            }
+            .invoke()?;
+        }
+    } else {
+        let [buyer_payment_ata, seller_payment_ata, treasury_payment_ata, ..] = payment_accounts
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        // Check that the payment token accounts belong to `seller` and the
+        // treasury. The token program checks `buyer_payment_ata`.
+        check_payment_account(seller_payment_ata, seller.key(), &payment_mint)?;
+        check_payment_account(treasury_payment_ata, &market_data.treasury, &payment_mint)?;
+
+        Transfer {
+            from: buyer_payment_ata,
+            to: seller_payment_ata,
+            authority: buyer,
+            amount: proceeds,
+        }
+        .invoke()?;
+        if fee > 0 {
+            Transfer {
+                from: buyer_payment_ata,
+                to: treasury_payment_ata,
+                authority: buyer,
+                amount: fee,
+            }
+            .invoke()?;
+        }
+    }
+
+    // Transfer the listed tokens from listing to buyer.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(LISTING_SEED.as_bytes()),
+        Seed::from(market.key()),
+        Seed::from(seller.key()),
+        Seed::from(&mint),
+        Seed::from(&bump),
+    ];
+    Transfer {
+        from: listing_ata,
+        to: buyer_ata,
+        authority: listing,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    close_listing(seller, listing, listing_ata, &seeds)?;
+
+    info!("Purchased {} tokens for {}, fee {}", amount, price, fee);
+
+    Ok(())
+}
+
+/// Cancels a listing, returning the tokens to the seller.
+pub fn process_delist(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [seller, seller_ata, listing, listing_ata, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(seller)?;
+
+    // Check if the listing PDA is owned by the program.
+    require_owned_by(listing, &ID)?;
+
+    // Deserialize the listing PDA.
+    let (market, amount, mint, bump) = {
+        let data = listing.try_borrow_data()?;
+        let data: &Listing = unsafe { &*data.as_ptr().cast() };
+
+        // Check that the listing was created by `seller`.
+        if !keys_eq(&data.seller, seller.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        (data.market, data.amount, data.mint, data.bump)
+    };
+
+    // Transfer the listed tokens from listing back to seller.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(LISTING_SEED.as_bytes()),
+        Seed::from(&market),
+        Seed::from(seller.key()),
+        Seed::from(&mint),
+        Seed::from(&bump),
+    ];
+    Transfer {
+        from: listing_ata,
+        to: seller_ata,
+        authority: listing,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    close_listing(seller, listing, listing_ata, &seeds)?;
+
+    info!("Delisted {} tokens", amount);
+
+    Ok(())
+}
+
+/// Checks that `market` is a market PDA of the program before its data is
+/// read. The program owns listings too, so the ownership alone would let a
+/// listing pose as a market, with its seller read as the treasury.
+fn check_market(market: &AccountInfo) -> ProgramResult {
+    require_owned_by(market, &ID)?;
+    if market.data_len() != Market::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Check the seeds of `market`.
+    let data = market.try_borrow_data()?;
+    let data: &Market = unsafe { &*data.as_ptr().cast() };
+    let market_pda = create_program_address(
+        &[MARKET_SEED.as_bytes(), &data.authority, &[data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(market.key(), &market_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Checks that a payment token account is owned by `owner` and holds tokens
+/// of `mint`.
+fn check_payment_account(account: &AccountInfo, owner: &Pubkey, mint: &Pubkey) -> ProgramResult {
+    let account = TokenAccount::from_account_info(account)?;
+    if !keys_eq(account.owner(), owner) || !keys_eq(account.mint(), mint) {
+        return Err(MarketplaceError::PaymentAccountMismatch.into());
+    }
+    Ok(())
+}
+
+/// Closes the (already emptied) listing token account and the listing PDA,
+/// returning the rent to the seller.
+fn close_listing(
+    seller: &AccountInfo,
+    listing: &AccountInfo,
+    listing_ata: &AccountInfo,
+    seeds: &[Seed],
+) -> ProgramResult {
+    CloseAccount {
+        account: listing_ata,
+        destination: seller,
+        authority: listing,
+    }
+    .invoke_signed(&[Signer::from(seeds)])?;
+
+    close_account(listing, seller)
+}
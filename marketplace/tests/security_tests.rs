@@ -0,0 +1,405 @@
+//! Attacks on the marketplace program, each rejected with its own error.
+
+use errors::Namespace;
+use marketplace::{
+    ListInstructionData, Listing, Market, MarketplaceError, MarketplaceInstruction, LISTING_SEED,
+    MARKET_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(marketplace::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+const FEE_BPS: u16 = 250;
+const PRICE: u64 = LAMPORTS_PER_SOL / 2;
+
+fn mint_account(mollusk: &Mollusk) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply: 1_000_000,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns the market PDA of `authority` and its account.
+fn market(mollusk: &Mollusk, authority: &Pubkey, treasury: &Pubkey) -> (Pubkey, Account) {
+    let (market, bump) =
+        Pubkey::find_program_address(&[MARKET_SEED.as_bytes(), authority.as_ref()], &ID);
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Market::LEN),
+        Market::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(authority.as_ref());
+    account.data[32..64].copy_from_slice(treasury.as_ref());
+    account.data[64..66].copy_from_slice(&FEE_BPS.to_le_bytes());
+    account.data[66] = bump;
+    (market, account)
+}
+
+/// Accounts of a market and a seller holding one token, before it's
+/// listed.
+struct Setup {
+    mollusk: Mollusk,
+    market: Pubkey,
+    treasury: Pubkey,
+    mint: Pubkey,
+    seller: Pubkey,
+    seller_ata: Pubkey,
+    buyer: Pubkey,
+    buyer_ata: Pubkey,
+    listing: Pubkey,
+    listing_bump: u8,
+    listing_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/marketplace");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+
+        let treasury = Pubkey::new_unique();
+        let (market, market_account) = market(&mollusk, &Pubkey::new_unique(), &treasury);
+
+        let mint = Pubkey::new_unique();
+        let seller = Pubkey::new_unique();
+        let seller_ata = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let buyer_ata = Pubkey::new_unique();
+        let (listing, listing_bump) = Pubkey::find_program_address(
+            &[
+                LISTING_SEED.as_bytes(),
+                market.as_ref(),
+                seller.as_ref(),
+                mint.as_ref(),
+            ],
+            &ID,
+        );
+        let listing_ata = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (market, market_account),
+            (treasury, Account::new(0, 0, &system_program.0)),
+            (mint, mint_account(&mollusk)),
+            (seller, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (seller_ata, token_account(&mollusk, &mint, &seller, 1)),
+            (buyer, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (buyer_ata, token_account(&mollusk, &mint, &buyer, 0)),
+            (listing, Account::default()),
+            (listing_ata, token_account(&mollusk, &mint, &listing, 0)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            market,
+            treasury,
+            mint,
+            seller,
+            seller_ata,
+            buyer,
+            buyer_ata,
+            listing,
+            listing_bump,
+            listing_ata,
+            accounts,
+        }
+    }
+
+    /// Lists the token of the seller for [`PRICE`] in `payment_mint`.
+    fn listed(self, payment_mint: &Pubkey) -> Self {
+        let mut listing_account = Account::new(
+            self.mollusk.sysvars.rent.minimum_balance(Listing::LEN),
+            Listing::LEN,
+            &ID,
+        );
+        let data = &mut listing_account.data;
+        data[0..32].copy_from_slice(self.market.as_ref());
+        data[32..64].copy_from_slice(self.seller.as_ref());
+        data[64..96].copy_from_slice(self.mint.as_ref());
+        data[96..128].copy_from_slice(payment_mint.as_ref());
+        data[128..136].copy_from_slice(&PRICE.to_le_bytes());
+        data[136..144].copy_from_slice(&1u64.to_le_bytes());
+        data[144] = self.listing_bump;
+
+        let seller_ata = token_account(&self.mollusk, &self.mint, &self.seller, 0);
+        let listing_ata = token_account(&self.mollusk, &self.mint, &self.listing, 1);
+        let (seller_ata_key, listing, listing_ata_key) =
+            (self.seller_ata, self.listing, self.listing_ata);
+        self.with(seller_ata_key, seller_ata)
+            .with(listing, listing_account)
+            .with(listing_ata_key, listing_ata)
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn instruction_list(&self) -> Instruction {
+        let data = ListInstructionData::new([0; 32], PRICE, 1, self.listing_bump);
+        let mut data_with_discriminator = vec![MarketplaceInstruction::List as u8];
+        data_with_discriminator.extend_from_slice(&data.to_bytes());
+
+        let ix_accounts = vec![
+            AccountMeta::new(self.seller, true),
+            AccountMeta::new(self.seller_ata, false),
+            AccountMeta::new_readonly(self.market, false),
+            AccountMeta::new(self.listing, false),
+            AccountMeta::new(self.listing_ata, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+    }
+
+    /// Listings priced in tokens need the payment token accounts of the
+    /// buyer, the seller and the treasury appended.
+    fn instruction_purchase(&self, payment_atas: &[Pubkey]) -> Instruction {
+        let mut ix_accounts = vec![
+            AccountMeta::new(self.buyer, true),
+            AccountMeta::new(self.buyer_ata, false),
+            AccountMeta::new(self.seller, false),
+            AccountMeta::new_readonly(self.market, false),
+            AccountMeta::new(self.treasury, false),
+            AccountMeta::new(self.listing, false),
+            AccountMeta::new(self.listing_ata, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        ix_accounts.extend(payment_atas.iter().map(|ata| AccountMeta::new(*ata, false)));
+        Instruction::new_with_bytes(ID, &[MarketplaceInstruction::Purchase as u8], ix_accounts)
+    }
+
+    fn instruction_delist(&self, seller: &Pubkey, seller_ata: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(*seller, true),
+            AccountMeta::new(*seller_ata, false),
+            AccountMeta::new(self.listing, false),
+            AccountMeta::new(self.listing_ata, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[MarketplaceInstruction::Delist as u8], ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_seller_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_list();
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+#[test]
+fn test_missing_buyer_signature() {
+    let setup = Setup::new().listed(&Pubkey::default());
+    let mut instruction = setup.instruction_purchase(&[]);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// A seller lists the tokens of someone else's token account.
+#[test]
+fn test_foreign_seller_ata() {
+    let setup = Setup::new();
+    let (seller_ata, mint) = (setup.seller_ata, setup.mint);
+    let victim_ata = token_account(&setup.mollusk, &mint, &Pubkey::new_unique(), 1);
+    let setup = setup.with(seller_ata, victim_ata);
+    setup.expect_err(&setup.instruction_list(), ProgramError::IllegalOwner);
+}
+
+/// The listed tokens are sent to a token account the listing doesn't own,
+/// from which the seller could take them back after the sale.
+#[test]
+fn test_listing_ata_of_other_owner() {
+    let setup = Setup::new();
+    let (listing_ata, mint, seller) = (setup.listing_ata, setup.mint, setup.seller);
+    let seller_owned = token_account(&setup.mollusk, &mint, &seller, 0);
+    let setup = setup.with(listing_ata, seller_owned);
+    setup.expect_err(&setup.instruction_list(), ProgramError::IllegalOwner);
+}
+
+/// A listing at an address other than its PDA, which purchases would
+/// never find.
+#[test]
+fn test_wrong_listing_seeds() {
+    let mut setup = Setup::new();
+    let listing = Pubkey::new_unique();
+    let listing_ata = token_account(&setup.mollusk, &setup.mint, &listing, 0);
+    setup.listing = listing;
+    let listing_ata_key = setup.listing_ata;
+    let setup = setup
+        .with(listing, Account::default())
+        .with(listing_ata_key, listing_ata);
+    setup.expect_err(&setup.instruction_list(), ProgramError::InvalidSeeds);
+}
+
+/// An attacker delists the tokens of the seller to their own token
+/// account.
+#[test]
+fn test_delist_by_attacker() {
+    let setup = Setup::new().listed(&Pubkey::default());
+    let attacker = Pubkey::new_unique();
+    let attacker_ata = Pubkey::new_unique();
+    let attacker_ata_account = token_account(&setup.mollusk, &setup.mint, &attacker, 0);
+    let setup = setup
+        .with(
+            attacker,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        )
+        .with(attacker_ata, attacker_ata_account);
+    setup.expect_err(
+        &setup.instruction_delist(&attacker, &attacker_ata),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The listing PDA holds a listing, but owned by another program.
+#[test]
+fn test_spoofed_listing() {
+    let setup = Setup::new().listed(&Pubkey::default());
+    let listing = setup.listing;
+    let mut listing_account = setup
+        .accounts
+        .iter()
+        .find(|(key, _)| *key == listing)
+        .unwrap()
+        .1
+        .clone();
+    listing_account.owner = Pubkey::new_unique();
+    let setup = setup.with(listing, listing_account);
+    setup.expect_err(&setup.instruction_purchase(&[]), ProgramError::IllegalOwner);
+}
+
+/// A listing bought through another market, which would send the fee to
+/// the treasury of that market.
+#[test]
+fn test_listing_of_other_market() {
+    let mut setup = Setup::new().listed(&Pubkey::default());
+    let (other_market, other_market_account) =
+        market(&setup.mollusk, &Pubkey::new_unique(), &setup.treasury);
+    setup.market = other_market;
+    let setup = setup.with(other_market, other_market_account);
+    setup.expect_err(
+        &setup.instruction_purchase(&[]),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The fee of a purchase in lamports is sent to an attacker instead of the
+/// treasury of the market.
+#[test]
+fn test_wrong_treasury() {
+    let mut setup = Setup::new().listed(&Pubkey::default());
+    let attacker = Pubkey::new_unique();
+    setup.treasury = attacker;
+    let setup = setup.with(attacker, Account::default());
+    setup.expect_err(
+        &setup.instruction_purchase(&[]),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The fee of a purchase in tokens is sent to a token account of an
+/// attacker instead of the treasury.
+#[test]
+fn test_wrong_treasury_payment_account() {
+    let payment_mint = Pubkey::new_unique();
+    let setup = Setup::new().listed(&payment_mint);
+
+    let buyer_payment_ata = Pubkey::new_unique();
+    let seller_payment_ata = Pubkey::new_unique();
+    let attacker_payment_ata = Pubkey::new_unique();
+    let accounts = [
+        (payment_mint, mint_account(&setup.mollusk)),
+        (
+            buyer_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &setup.buyer, PRICE),
+        ),
+        (
+            seller_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &setup.seller, 0),
+        ),
+        (
+            attacker_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &Pubkey::new_unique(), 0),
+        ),
+    ];
+    let setup = accounts
+        .into_iter()
+        .fold(setup, |setup, (address, account)| {
+            setup.with(address, account)
+        });
+    setup.expect_err(
+        &setup.instruction_purchase(&[buyer_payment_ata, seller_payment_ata, attacker_payment_ata]),
+        ProgramError::Custom(
+            Namespace::Marketplace.code(MarketplaceError::PaymentAccountMismatch as u32),
+        ),
+    );
+}
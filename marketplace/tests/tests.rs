@@ -0,0 +1,578 @@
+use std::mem;
+
+use errors::Namespace;
+use marketplace::{
+    InitializeMarketInstructionData, ListInstructionData, Listing, Market, MarketplaceError,
+    MarketplaceInstruction, LISTING_SEED, MARKET_SEED,
+};
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(marketplace::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+const FEE_BPS: u16 = 250;
+
+fn instruction_initialize_market(
+    fee_bps: u16,
+    authority: &Pubkey,
+    market: &Pubkey,
+    treasury: &Pubkey,
+    bump: u8,
+) -> Instruction {
+    // Create instruction data.
+    let data = InitializeMarketInstructionData::new(fee_bps, bump);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
+        mem::size_of::<MarketplaceInstruction>() + InitializeMarketInstructionData::LEN,
+    );
+    data_with_discriminator.push(MarketplaceInstruction::InitializeMarket as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*market, false),
+        AccountMeta::new_readonly(*treasury, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+fn instruction_delist(
+    seller: &Pubkey,
+    seller_ata: &Pubkey,
+    listing: &Pubkey,
+    listing_ata: &Pubkey,
+) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new(*seller, true),
+        AccountMeta::new(*seller_ata, false),
+        AccountMeta::new(*listing, false),
+        AccountMeta::new(*listing_ata, false),
+        AccountMeta::new_readonly(TOKEN_ID, false),
+    ];
+    Instruction::new_with_bytes(ID, &[MarketplaceInstruction::Delist as u8], ix_accounts)
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/marketplace");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, supply: u64, decimals: u8) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn market_account(mollusk: &Mollusk, authority: &Pubkey, treasury: &Pubkey, bump: u8) -> Account {
+    let mut data = vec![0; Market::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(treasury.as_ref());
+    data[64..66].copy_from_slice(&FEE_BPS.to_le_bytes());
+    data[66] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Market::LEN),
+        Market::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Accounts shared by the listing tests: a market, a seller holding an NFT
+/// and a buyer.
+struct Setup {
+    mollusk: Mollusk,
+    market: Pubkey,
+    treasury: Pubkey,
+    mint: Pubkey,
+    seller: Pubkey,
+    seller_ata: Pubkey,
+    buyer: Pubkey,
+    buyer_ata: Pubkey,
+    listing: Pubkey,
+    listing_bump: u8,
+    listing_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let authority = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let (market, market_bump) =
+            Pubkey::find_program_address(&[MARKET_SEED.as_bytes(), authority.as_ref()], &ID);
+
+        let mint = Pubkey::new_unique();
+        let seller = Pubkey::new_unique();
+        let seller_ata = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let buyer_ata = Pubkey::new_unique();
+        let (listing, listing_bump) = Pubkey::find_program_address(
+            &[
+                LISTING_SEED.as_bytes(),
+                market.as_ref(),
+                seller.as_ref(),
+                mint.as_ref(),
+            ],
+            &ID,
+        );
+        let listing_ata = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                market,
+                market_account(&mollusk, &authority, &treasury, market_bump),
+            ),
+            (treasury, Account::new(0, 0, &system_program.0)),
+            (mint, mint_account(&mollusk, 1, 0)),
+            (seller, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (seller_ata, token_account(&mollusk, &mint, &seller, 1)),
+            (buyer, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (buyer_ata, token_account(&mollusk, &mint, &buyer, 0)),
+            (listing, Account::default()),
+            (listing_ata, token_account(&mollusk, &mint, &listing, 0)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            market,
+            treasury,
+            mint,
+            seller,
+            seller_ata,
+            buyer,
+            buyer_ata,
+            listing,
+            listing_bump,
+            listing_ata,
+            accounts,
+        }
+    }
+
+    /// Moves the listing to `market`, re-deriving the listing PDA and giving
+    /// it a new token account.
+    fn set_market(&mut self, market: Pubkey, account: Account) {
+        let (listing, listing_bump) = Pubkey::find_program_address(
+            &[
+                LISTING_SEED.as_bytes(),
+                market.as_ref(),
+                self.seller.as_ref(),
+                self.mint.as_ref(),
+            ],
+            &ID,
+        );
+        let listing_ata = Pubkey::new_unique();
+        self.accounts.extend([
+            (market, account),
+            (listing, Account::default()),
+            (
+                listing_ata,
+                token_account(&self.mollusk, &self.mint, &listing, 0),
+            ),
+        ]);
+        self.market = market;
+        self.listing = listing;
+        self.listing_bump = listing_bump;
+        self.listing_ata = listing_ata;
+    }
+
+    fn instruction_list(&self, payment_mint: &Pubkey, price: u64) -> Instruction {
+        // Create instruction data.
+        let data = ListInstructionData::new(payment_mint.to_bytes(), price, 1, self.listing_bump);
+        // Serialize instruction data to bytes.
+        let data = data.to_bytes();
+
+        // Construct the full instruction data, consisting of:
+        // * discriminator
+        // * serialized data
+        let mut data_with_discriminator: Vec<u8> =
+            Vec::with_capacity(mem::size_of::<MarketplaceInstruction>() + ListInstructionData::LEN);
+        data_with_discriminator.push(MarketplaceInstruction::List as u8);
+        data_with_discriminator.extend_from_slice(&data);
+
+        let ix_accounts = vec![
+            AccountMeta::new(self.seller, true),
+            AccountMeta::new(self.seller_ata, false),
+            AccountMeta::new_readonly(self.market, false),
+            AccountMeta::new(self.listing, false),
+            AccountMeta::new(self.listing_ata, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+    }
+
+    /// Listings priced in tokens need the payment token accounts of the
+    /// buyer, the seller and the treasury appended.
+    fn instruction_purchase(&self, seller: &Pubkey, payment_atas: &[Pubkey]) -> Instruction {
+        let mut ix_accounts = vec![
+            AccountMeta::new(self.buyer, true),
+            AccountMeta::new(self.buyer_ata, false),
+            AccountMeta::new(*seller, false),
+            AccountMeta::new_readonly(self.market, false),
+            AccountMeta::new(self.treasury, false),
+            AccountMeta::new(self.listing, false),
+            AccountMeta::new(self.listing_ata, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        ix_accounts.extend(payment_atas.iter().map(|ata| AccountMeta::new(*ata, false)));
+        Instruction::new_with_bytes(ID, &[MarketplaceInstruction::Purchase as u8], ix_accounts)
+    }
+}
+
+#[test]
+fn test_marketplace_initialize_market() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    let (market, bump) =
+        Pubkey::find_program_address(&[MARKET_SEED.as_bytes(), authority.as_ref()], &ID);
+
+    let accounts = [
+        (
+            authority,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        ),
+        (market, Account::default()),
+        (treasury, Account::default()),
+        keyed_account_for_system_program(),
+    ];
+    mollusk.process_and_validate_instruction(
+        &instruction_initialize_market(10_001, &authority, &market, &treasury, bump),
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Marketplace.code(MarketplaceError::InvalidFee as u32),
+        ))],
+    );
+    let res = mollusk.process_and_validate_instruction(
+        &instruction_initialize_market(FEE_BPS, &authority, &market, &treasury, bump),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&market)
+                .owner(&ID)
+                .data(&market_account(&mollusk, &authority, &treasury, bump).data)
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_marketplace_purchase_with_lamports() {
+    let setup = Setup::new();
+    let rent = &setup.mollusk.sysvars.rent;
+
+    let price = LAMPORTS_PER_SOL / 2;
+    let fee = price * FEE_BPS as u64 / 10_000;
+    let listing_ata_rent = rent.minimum_balance(TokenAccount::LEN);
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_list(&Pubkey::default(), price),
+                &[
+                    Check::success(),
+                    Check::account(&setup.listing)
+                        .owner(&ID)
+                        .space(Listing::LEN)
+                        .data_slice(32, setup.seller.as_ref())
+                        .data_slice(64, setup.mint.as_ref())
+                        .build(),
+                    Check::account(&setup.listing_ata)
+                        .data_slice(64, &1u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_purchase(&setup.seller, &[]),
+                &[
+                    Check::success(),
+                    Check::account(&setup.buyer_ata)
+                        .data_slice(64, &1u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.buyer)
+                        .lamports(LAMPORTS_PER_SOL - price)
+                        .build(),
+                    // The seller gets the proceeds and the rent of both
+                    // listing accounts.
+                    Check::account(&setup.seller)
+                        .lamports(LAMPORTS_PER_SOL + price - fee + listing_ata_rent)
+                        .build(),
+                    Check::account(&setup.treasury).lamports(fee).build(),
+                    Check::account(&setup.listing).closed().build(),
+                    Check::account(&setup.listing_ata).closed().build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_marketplace_purchase_with_tokens() {
+    let mut setup = Setup::new();
+
+    let payment_mint = Pubkey::new_unique();
+    let buyer_payment_ata = Pubkey::new_unique();
+    let seller_payment_ata = Pubkey::new_unique();
+    let treasury_payment_ata = Pubkey::new_unique();
+    let accounts = [
+        (payment_mint, mint_account(&setup.mollusk, 1_000_000, 6)),
+        (
+            buyer_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &setup.buyer, 1_000_000),
+        ),
+        (
+            seller_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &setup.seller, 0),
+        ),
+        (
+            treasury_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &setup.treasury, 0),
+        ),
+    ];
+    setup.accounts.extend(accounts);
+
+    let price = 400_000;
+    let fee = price * FEE_BPS as u64 / 10_000;
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_list(&payment_mint, price),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_purchase(
+                    &setup.seller,
+                    &[buyer_payment_ata, seller_payment_ata, treasury_payment_ata],
+                ),
+                &[
+                    Check::success(),
+                    Check::account(&setup.buyer_ata)
+                        .data_slice(64, &1u64.to_le_bytes())
+                        .build(),
+                    Check::account(&buyer_payment_ata)
+                        .data_slice(64, &(1_000_000 - price).to_le_bytes())
+                        .build(),
+                    Check::account(&seller_payment_ata)
+                        .data_slice(64, &(price - fee).to_le_bytes())
+                        .build(),
+                    Check::account(&treasury_payment_ata)
+                        .data_slice(64, &fee.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.listing).closed().build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_marketplace_purchase_wrong_payment_account() {
+    let mut setup = Setup::new();
+
+    let payment_mint = Pubkey::new_unique();
+    let buyer_payment_ata = Pubkey::new_unique();
+    let attacker_payment_ata = Pubkey::new_unique();
+    let treasury_payment_ata = Pubkey::new_unique();
+    let accounts = [
+        (payment_mint, mint_account(&setup.mollusk, 1_000_000, 6)),
+        (
+            buyer_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &setup.buyer, 1_000_000),
+        ),
+        // The proceeds must not be redirected to someone else's account.
+        (
+            attacker_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &Pubkey::new_unique(), 0),
+        ),
+        (
+            treasury_payment_ata,
+            token_account(&setup.mollusk, &payment_mint, &setup.treasury, 0),
+        ),
+    ];
+    setup.accounts.extend(accounts);
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_list(&payment_mint, 400_000),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_purchase(
+                    &setup.seller,
+                    &[
+                        buyer_payment_ata,
+                        attacker_payment_ata,
+                        treasury_payment_ata,
+                    ],
+                ),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Marketplace.code(MarketplaceError::PaymentAccountMismatch as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_marketplace_purchase_wrong_seller() {
+    let mut setup = Setup::new();
+
+    let attacker = Pubkey::new_unique();
+    setup.accounts.push((attacker, Account::default()));
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_list(&Pubkey::default(), LAMPORTS_PER_SOL / 2),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_purchase(&attacker, &[]),
+                &[Check::err(ProgramError::IllegalOwner)],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_marketplace_list_in_fake_market() {
+    // A listing is owned by the program as well, but must not pass for a
+    // market. Its seller would be read as the treasury.
+    let mut setup = Setup::new();
+    let mut listing_data = vec![0; Listing::LEN];
+    listing_data[0..32].copy_from_slice(setup.market.as_ref());
+    listing_data[32..64].copy_from_slice(setup.seller.as_ref());
+    listing_data[64..96].copy_from_slice(setup.mint.as_ref());
+    let mut listing_account = Account::new(
+        setup.mollusk.sysvars.rent.minimum_balance(Listing::LEN),
+        Listing::LEN,
+        &ID,
+    );
+    listing_account.data = listing_data;
+    setup.set_market(Pubkey::new_unique(), listing_account);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_list(&Pubkey::default(), LAMPORTS_PER_SOL),
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+
+    // Neither must a copy of the market at an address other than its PDA.
+    let mut setup = Setup::new();
+    let market_account = setup.accounts[0].1.clone();
+    setup.set_market(Pubkey::new_unique(), market_account);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_list(&Pubkey::default(), LAMPORTS_PER_SOL),
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
+
+#[test]
+fn test_marketplace_delist() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_list(&Pubkey::default(), LAMPORTS_PER_SOL / 2),
+                &[Check::success()],
+            ),
+            (
+                &instruction_delist(
+                    &setup.seller,
+                    &setup.seller_ata,
+                    &setup.listing,
+                    &setup.listing_ata,
+                ),
+                &[
+                    Check::success(),
+                    Check::account(&setup.seller_ata)
+                        .data_slice(64, &1u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.listing).closed().build(),
+                    Check::account(&setup.listing_ata).closed().build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
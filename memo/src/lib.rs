@@ -0,0 +1,65 @@
+//! Reimplementation of the [SPL Memo] program (v3).
+//!
+//! The whole instruction data is the memo, which has to be valid UTF-8.
+//! Every account passed to the instruction has to be a signer, which lets
+//! transactions attach a memo signed by a set of keys.
+//!
+//! The memo is logged the same way as the original, except that it isn't
+//! escaped, as that needs an allocator.
+//!
+//! [SPL Memo]: https://github.com/solana-program/memo
+
+#![no_std]
+
+use core::str;
+
+use pinocchio::{
+    account_info::AccountInfo,
+    log::sol_log,
+    program_error::ProgramError,
+    pubkey::{self, Pubkey},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_log::log;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+
+pinocchio_pubkey::declare_id!("3oCGKbdi9bFUsAGYxsXYudEEkJTkpg5W43HAwZxQ6FHE");
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Log all signers before failing on a missing signature, like the
+    // original does.
+    let mut missing_required_signature = false;
+    for account in accounts {
+        if account.is_signer() {
+            log!("Signed by");
+            pubkey::log(account.key());
+        } else {
+            missing_required_signature = true;
+        }
+    }
+    if missing_required_signature {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let memo = str::from_utf8(instruction_data).map_err(|err| {
+        log!("Invalid UTF-8, from byte {}", err.valid_up_to());
+        ProgramError::InvalidInstructionData
+    })?;
+
+    log!("Memo (len {}):", memo.len());
+    sol_log(memo);
+
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use mollusk_svm::{
+    program::loader_keys::LOADER_V2,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(memo::ID);
+const SPL_MEMO_ID: Pubkey = Pubkey::from_str_const("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+fn instruction_memo(program_id: &Pubkey, memo: &[u8], signers: &[(Pubkey, bool)]) -> Instruction {
+    let ix_accounts = signers
+        .iter()
+        .map(|(signer, is_signer)| AccountMeta::new_readonly(*signer, *is_signer))
+        .collect();
+    Instruction::new_with_bytes(*program_id, memo, ix_accounts)
+}
+
+fn accounts(signers: &[(Pubkey, bool)]) -> Vec<(Pubkey, Account)> {
+    signers
+        .iter()
+        .map(|(signer, _)| (*signer, Account::default()))
+        .collect()
+}
+
+struct Case {
+    memo: &'static [u8],
+    /// Accounts and whether they sign.
+    signers: Vec<(Pubkey, bool)>,
+    expected: ProgramResult,
+}
+
+/// Memos and signers exercising every branch of the program.
+fn cases() -> Vec<Case> {
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    vec![
+        Case {
+            memo: b"",
+            signers: vec![],
+            expected: ProgramResult::Success,
+        },
+        Case {
+            memo: b"hello",
+            signers: vec![(alice, true)],
+            expected: ProgramResult::Success,
+        },
+        Case {
+            memo: "zażółć gęślą jaźń 🦀".as_bytes(),
+            signers: vec![(alice, true), (bob, true)],
+            expected: ProgramResult::Success,
+        },
+        Case {
+            // Truncated 4-byte sequence.
+            memo: &[b'o', b'k', 0xf0, 0x9f],
+            signers: vec![(alice, true)],
+            expected: ProgramResult::Failure(ProgramError::InvalidInstructionData),
+        },
+        Case {
+            memo: b"hello",
+            signers: vec![(alice, true), (bob, false)],
+            expected: ProgramResult::Failure(ProgramError::MissingRequiredSignature),
+        },
+        // Signers are checked before the memo.
+        Case {
+            memo: &[0xff],
+            signers: vec![(bob, false)],
+            expected: ProgramResult::Failure(ProgramError::MissingRequiredSignature),
+        },
+    ]
+}
+
+#[test]
+fn test_memo() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/memo");
+
+    for Case {
+        memo,
+        signers,
+        expected,
+    } in cases()
+    {
+        let check = match expected {
+            ProgramResult::Success => Check::success(),
+            ProgramResult::Failure(err) => Check::err(err),
+            ProgramResult::UnknownError(_) => unreachable!(),
+        };
+        mollusk.process_and_validate_instruction(
+            &instruction_memo(&ID, memo, &signers),
+            &accounts(&signers),
+            &[check],
+        );
+    }
+}
+
+/// Runs the same instructions through the clone and the original program.
+///
+/// The original isn't built from source, dump it from mainnet with:
+///
+/// ```sh
+/// solana program dump -u m MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr third-party/spl_memo.so
+/// ```
+#[test]
+fn test_memo_matches_spl_memo() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/memo");
+    let mut spl_mollusk = Mollusk::default();
+    spl_mollusk.add_program(&SPL_MEMO_ID, "third-party/spl_memo", &LOADER_V2);
+
+    for Case {
+        memo,
+        signers,
+        expected,
+    } in cases()
+    {
+        let accounts = accounts(&signers);
+        let res = mollusk.process_instruction(&instruction_memo(&ID, memo, &signers), &accounts);
+        let spl_res = spl_mollusk
+            .process_instruction(&instruction_memo(&SPL_MEMO_ID, memo, &signers), &accounts);
+
+        assert_eq!(spl_res.program_result, expected, "memo: {memo:?}");
+        assert_eq!(res.program_result, spl_res.program_result, "memo: {memo:?}");
+        assert_eq!(res.resulting_accounts, spl_res.resulting_accounts);
+    }
+}
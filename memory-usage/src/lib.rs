@@ -0,0 +1,166 @@
+//! Program measuring how much of its stack and heap it uses.
+//!
+//! Programs run with a stack of 4 KiB frames, one per function call, up to
+//! a call depth of 64, and with a 32 KiB heap. Pinocchio programs usually
+//! opt out of the heap with `no_allocator!`, keeping all their data on the
+//! stack or in the accounts. This one installs a bump allocator instead, to
+//! show what both limits mean in practice:
+//!
+//! * [`MemoryInstruction::Stack`] recurses into frames holding a 1 KiB
+//!   buffer each and logs `Stack used: <bytes> bytes`, the distance between
+//!   the outermost and the deepest frame. Recursing deeper than the call
+//!   depth aborts the program.
+//! * [`MemoryInstruction::Heap`] allocates a buffer and logs `Heap used:
+//!   <bytes> bytes`, the high-water mark of the allocator. Allocating more
+//!   than the heap makes the allocator fail, which panics.
+//!
+//! A single frame can't exceed 4 KiB either. `cargo build-sbf` reports the
+//! functions whose locals don't fit, which overwrite other frames at
+//! runtime, so large buffers belong in accounts or on the heap.
+
+#![no_std]
+
+extern crate alloc;
+
+use core::hint::black_box;
+#[cfg(target_os = "solana")]
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    mem::size_of,
+    ptr::null_mut,
+};
+
+use macros::{InstructionData, InstructionDiscriminator};
+use pinocchio::{account_info::AccountInfo, pubkey::Pubkey, ProgramResult};
+#[cfg(target_os = "solana")]
+use pinocchio::{nostd_panic_handler, program_entrypoint};
+use pinocchio_log::log;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("tkRoeZpUpsucM7ZU2j83YByq4CXpdkH9dHHBtsMzL7w");
+
+/// Start of the heap in the address space of programs.
+#[cfg(target_os = "solana")]
+const HEAP_START: usize = 0x300000000;
+/// Length of the heap, unless the transaction requests a larger one.
+pub const HEAP_LEN: usize = 32 * 1024;
+/// Length of the buffer held by every frame of
+/// [`MemoryInstruction::Stack`].
+pub const FRAME_BUFFER_LEN: usize = 1024;
+
+/// Memory usage program instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum MemoryInstruction {
+    /// Recurses [`StackInstructionData::depth`] times.
+    Stack,
+    /// Allocates [`HeapInstructionData::len`] bytes.
+    Heap,
+}
+
+/// Instruction data of [`MemoryInstruction::Stack`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct StackInstructionData {
+    pub depth: u8,
+}
+
+/// Instruction data of [`MemoryInstruction::Heap`].
+#[derive(Clone, Copy, InstructionData)]
+pub struct HeapInstructionData {
+    pub len: u32,
+}
+
+/// Bump allocator which never frees memory.
+///
+/// Programs can't have mutable statics, so the number of used bytes is
+/// stored at the start of the heap, which is zeroed at the start of the
+/// program.
+#[cfg(target_os = "solana")]
+struct BumpAllocator;
+
+#[cfg(target_os = "solana")]
+unsafe impl GlobalAlloc for BumpAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let used = HEAP_START as *mut usize;
+        let start = (HEAP_START + size_of::<usize>() + *used).next_multiple_of(layout.align());
+        let end = start.saturating_add(layout.size());
+        if end > HEAP_START + HEAP_LEN {
+            return null_mut();
+        }
+        *used = end - HEAP_START - size_of::<usize>();
+        start as *mut u8
+    }
+
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
+}
+
+#[cfg(target_os = "solana")]
+#[global_allocator]
+static ALLOCATOR: BumpAllocator = BumpAllocator;
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = MemoryInstruction::split(instruction_data)?;
+
+    match instruction {
+        MemoryInstruction::Stack => {
+            let instruction_data = StackInstructionData::from_bytes(instruction_data)?;
+            process_stack(instruction_data.depth)
+        }
+        MemoryInstruction::Heap => {
+            let instruction_data = HeapInstructionData::from_bytes(instruction_data)?;
+            process_heap(instruction_data.len)
+        }
+    }
+}
+
+/// Logs the distance between the outermost frame and the one `depth` calls
+/// deeper.
+pub fn process_stack(depth: u8) -> ProgramResult {
+    let outermost = stack_address();
+    let deepest = recurse(depth);
+    log!("Stack used: {} bytes", outermost.abs_diff(deepest));
+    Ok(())
+}
+
+/// Logs the high-water mark of the heap after allocating `len` bytes.
+pub fn process_heap(len: u32) -> ProgramResult {
+    let buffer = alloc::vec![1u8; len as usize];
+    black_box(&buffer);
+    // The allocator only counts the used bytes on-chain.
+    #[cfg(target_os = "solana")]
+    let used = unsafe { *(HEAP_START as *const usize) };
+    #[cfg(not(target_os = "solana"))]
+    let used = buffer.len();
+    log!("Heap used: {} bytes", used);
+    Ok(())
+}
+
+/// Returns the address of a local of the current frame.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    black_box(&marker) as *const u8 as usize
+}
+
+/// Recurses `depth` times, holding a buffer in every frame, and returns the
+/// address of the deepest frame.
+#[inline(never)]
+fn recurse(depth: u8) -> usize {
+    let buffer = black_box([depth; FRAME_BUFFER_LEN]);
+    let deepest = match depth {
+        0 => stack_address(),
+        _ => recurse(depth - 1),
+    };
+    black_box(&buffer);
+    deepest
+}
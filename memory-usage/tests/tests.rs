@@ -0,0 +1,85 @@
+use memory_usage::{
+    HeapInstructionData, MemoryInstruction, StackInstructionData, FRAME_BUFFER_LEN, HEAP_LEN,
+};
+use mollusk_svm::{
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+use test_utils::logs::LogRecorder;
+
+const ID: Pubkey = Pubkey::new_from_array(memory_usage::ID);
+
+fn instruction_stack(depth: u8) -> Instruction {
+    let mut data = MemoryInstruction::Stack.discriminator().to_vec();
+    data.extend_from_slice(&StackInstructionData { depth }.to_bytes());
+    Instruction::new_with_bytes(ID, &data, vec![])
+}
+
+fn instruction_heap(len: u32) -> Instruction {
+    let mut data = MemoryInstruction::Heap.discriminator().to_vec();
+    data.extend_from_slice(&HeapInstructionData { len }.to_bytes());
+    Instruction::new_with_bytes(ID, &data, vec![])
+}
+
+/// Returns the number of bytes in the `<prefix> <bytes> bytes` log.
+fn logged_bytes(logs: &[String], prefix: &str) -> usize {
+    let prefix = format!("Program log: {prefix} ");
+    logs.iter()
+        .find_map(|log| log.strip_prefix(&prefix)?.strip_suffix(" bytes"))
+        .unwrap_or_else(|| panic!("no {prefix:?} log in {logs:?}"))
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn test_stack_usage() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/memory_usage");
+    let logs = LogRecorder::new(&mut mollusk);
+
+    let mut previous = 0;
+    for depth in [1, 8, 32] {
+        mollusk.process_and_validate_instruction(
+            &instruction_stack(depth),
+            &[],
+            &[Check::success()],
+        );
+        let used = logged_bytes(&logs.take(), "Stack used:");
+        // Every frame holds at least its buffer.
+        assert!(used >= depth as usize * FRAME_BUFFER_LEN, "{used} bytes");
+        assert!(used > previous);
+        previous = used;
+    }
+}
+
+#[test]
+fn test_stack_call_depth_exceeded() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/memory_usage");
+
+    // The call depth is limited to 64 frames.
+    let res = mollusk.process_instruction(&instruction_stack(64), &[]);
+    assert!(!matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_heap_usage() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/memory_usage");
+    let logs = LogRecorder::new(&mut mollusk);
+
+    for len in [1, 1024, 16 * 1024] {
+        mollusk.process_and_validate_instruction(&instruction_heap(len), &[], &[Check::success()]);
+        let used = logged_bytes(&logs.take(), "Heap used:");
+        assert!(used >= len as usize, "{used} bytes");
+        assert!(used <= HEAP_LEN);
+    }
+}
+
+#[test]
+fn test_heap_exhausted() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/memory_usage");
+
+    // The allocation fails, which panics.
+    let res = mollusk.process_instruction(&instruction_heap(HEAP_LEN as u32), &[]);
+    assert!(!matches!(res.program_result, ProgramResult::Success));
+}
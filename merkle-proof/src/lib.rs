@@ -0,0 +1,368 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("C3UVf2jjS7vyWzyMbJsR6ZMrvioJpZiEuh1JCZC8FkZS");
+
+pub const TREE_SEED: &str = "tree";
+pub const RECEIPT_SEED: &str = "receipt";
+
+/// Errors returned by the Merkle proof program.
+#[repr(u32)]
+pub enum MerkleProofError {
+    /// The proof doesn't prove the inclusion of the leaf.
+    InvalidProof,
+    /// The leaf was already verified.
+    AlreadyVerified,
+}
+
+impl From<MerkleProofError> for ProgramError {
+    fn from(e: MerkleProofError) -> Self {
+        Namespace::MerkleProof.error(e as u32)
+    }
+}
+
+/// On-chain representation of a Merkle tree. Only the root is stored.
+#[repr(C)]
+pub struct Tree {
+    pub authority: Pubkey,
+    pub root: [u8; 32],
+    /// Number of leaves verified against the tree.
+    pub verified: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Tree, size = 80, align = 8, { authority: 0, root: 32, verified: 64, bump: 72 });
+
+impl Tree {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain record of a verified leaf.
+#[repr(C)]
+pub struct Receipt {
+    pub tree: Pubkey,
+    /// Leaf data.
+    pub leaf: [u8; 32],
+    /// Root which the leaf was verified against.
+    pub root: [u8; 32],
+    /// Slot of the verification.
+    pub slot: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Receipt, size = 112, align = 8, {
+    tree: 0,
+    leaf: 32,
+    root: 64,
+    slot: 96,
+    bump: 104,
+});
+
+impl Receipt {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Merkle proof program instruction discriminators.
+#[repr(u8)]
+pub enum MerkleProofInstruction {
+    /// Creates a tree with the given root.
+    Initialize,
+    /// Replaces the root of a tree.
+    SetRoot,
+    /// Verifies an inclusion proof and records the leaf.
+    Verify,
+}
+
+impl TryFrom<&u8> for MerkleProofInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::SetRoot),
+            2 => Ok(Self::Verify),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub root: [u8; 32],
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(root: [u8; 32], bump: u8) -> Self {
+        Self { root, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct SetRootInstructionData {
+    pub root: [u8; 32],
+}
+
+impl SetRootInstructionData {
+    pub fn new(root: [u8; 32]) -> Self {
+        Self { root }
+    }
+}
+
+/// Fixed part of the verify instruction data. It's followed by the proof,
+/// up to [`merkle::MAX_DEPTH`] 32-byte nodes ordered from the leaf to the
+/// root.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct VerifyInstructionData {
+    /// Leaf data, e.g. a hash of an off-chain record. The program hashes it
+    /// with [`merkle::hash_leaf`], so inner nodes can't be passed as leaves.
+    pub leaf: [u8; 32],
+    /// Bump of the receipt PDA.
+    pub bump: u8,
+}
+
+impl VerifyInstructionData {
+    pub fn new(leaf: [u8; 32], bump: u8) -> Self {
+        Self { leaf, bump }
+    }
+}
+
+/// Checks that `tree` is a tree of the program. The program owns receipts
+/// too, which are told apart by their length.
+fn check_tree(tree: &AccountInfo) -> ProgramResult {
+    require_owned_by(tree, &ID)?;
+    if tree.data_len() != Tree::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = MerkleProofInstruction::try_from(instruction)?;
+
+    match instruction {
+        MerkleProofInstruction::Initialize => process_initialize(accounts, instruction_data),
+        MerkleProofInstruction::SetRoot => process_set_root(accounts, instruction_data),
+        MerkleProofInstruction::Verify => process_verify(accounts, instruction_data),
+    }
+}
+
+/// Creates a tree of `authority`.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, tree, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `tree`.
+    let tree_pda = create_program_address(
+        &[
+            TREE_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(tree.key(), &tree_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the tree PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(TREE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: tree,
+        lamports: Rent::get()?.minimum_balance(Tree::LEN),
+        space: Tree::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the tree PDA.
+    let mut data = tree.try_borrow_mut_data()?;
+    let data: &mut Tree = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the tree.
+    data.authority = *authority.key();
+    data.root = instruction_data.root;
+    data.bump = instruction_data.bump;
+
+    info!("Initialized tree");
+
+    Ok(())
+}
+
+/// Replaces the root of the tree.
+///
+/// Receipts of leaves verified against the old root stay valid and are not
+/// verified again.
+pub fn process_set_root(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, tree] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+    // Check that `tree` is a tree PDA of the program.
+    check_tree(tree)?;
+
+    // Deserialize instruction data.
+    let instruction_data = SetRootInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the tree PDA.
+    let mut data = tree.try_borrow_mut_data()?;
+    let data: &mut Tree = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    if !keys_eq(&data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    data.root = instruction_data.root;
+
+    info!("Set new root");
+
+    Ok(())
+}
+
+/// Verifies the inclusion of a leaf in the tree and records it in a receipt
+/// PDA paid by `payer`.
+///
+/// Anyone can verify a leaf, but every leaf only once.
+pub fn process_verify(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [payer, tree, receipt, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(payer)?;
+    // Check that `tree` is a tree PDA of the program.
+    check_tree(tree)?;
+    if receipt.is_owned_by(&ID) {
+        return Err(MerkleProofError::AlreadyVerified.into());
+    }
+
+    // Deserialize instruction data.
+    if instruction_data.len() < VerifyInstructionData::LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (instruction_data, proof) = instruction_data.split_at(VerifyInstructionData::LEN);
+    let instruction_data = VerifyInstructionData::from_bytes(instruction_data)?;
+    if proof.len() % 32 != 0 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    // SAFETY: `[u8; 32]` has the same alignment as `u8` and the length was
+    // checked above.
+    let proof: &[[u8; 32]] =
+        unsafe { core::slice::from_raw_parts(proof.as_ptr().cast(), proof.len() / 32) };
+
+    // Verify the proof.
+    let root = {
+        let data = tree.try_borrow_data()?;
+        let data: &Tree = unsafe { &*data.as_ptr().cast() };
+
+        let leaf = merkle::hash_leaf(&instruction_data.leaf);
+        if !merkle::verify(&leaf, proof, &data.root) {
+            return Err(MerkleProofError::InvalidProof.into());
+        }
+        data.root
+    };
+
+    // Check the seeds of `receipt`.
+    let receipt_pda = create_program_address(
+        &[
+            RECEIPT_SEED.as_bytes(),
+            tree.key(),
+            &instruction_data.leaf,
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(receipt.key(), &receipt_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the receipt PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(RECEIPT_SEED.as_bytes()),
+        Seed::from(tree.key()),
+        Seed::from(&instruction_data.leaf),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: payer,
+        to: receipt,
+        lamports: Rent::get()?.minimum_balance(Receipt::LEN),
+        space: Receipt::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the receipt PDA.
+    let mut data = receipt.try_borrow_mut_data()?;
+    let data: &mut Receipt = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the receipt.
+    data.tree = *tree.key();
+    data.leaf = instruction_data.leaf;
+    data.root = root;
+    data.slot = Clock::get()?.slot;
+    data.bump = instruction_data.bump;
+
+    // Count the verified leaf.
+    let mut tree_data = tree.try_borrow_mut_data()?;
+    let tree_data: &mut Tree = unsafe { &mut *tree_data.as_mut_ptr().cast() };
+    tree_data.verified += 1;
+
+    info!("Verified leaf with proof of depth {}", proof.len());
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+//! Attacks on the Merkle proof program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use merkle_proof::{
+    InitializeInstructionData, MerkleProofInstruction, SetRootInstructionData, Tree,
+    VerifyInstructionData, RECEIPT_SEED, TREE_SEED,
+};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(merkle_proof::ID);
+
+/// The only leaf of the tree, which is its own root.
+const LEAF: [u8; 32] = [1; 32];
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: MerkleProofInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<MerkleProofInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the tree PDA of `authority` and its bump.
+fn tree_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TREE_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Returns the receipt PDA of `leaf` in `tree` and its bump.
+fn receipt_pda(tree: &Pubkey, leaf: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECEIPT_SEED.as_bytes(), tree.as_ref(), leaf], &ID)
+}
+
+/// Returns a tree of `authority` holding only [`LEAF`].
+fn tree_account(mollusk: &Mollusk, authority: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Tree::LEN),
+        Tree::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[32..64].copy_from_slice(&merkle::hash_leaf(&LEAF));
+    account
+}
+
+/// Accounts of an authority with a tree, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    tree: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/merkle_proof");
+
+        let authority = Pubkey::new_unique();
+        let (tree, _) = tree_pda(&authority);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (tree, tree_account(&mollusk, &authority)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            tree,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Replaces the root of `tree`, signed by `authority`.
+    fn instruction_set_root(&self, authority: &Pubkey, tree: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*tree, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                MerkleProofInstruction::SetRoot,
+                &SetRootInstructionData::new([7; 32]),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Verifies [`LEAF`] in `tree` into `receipt`, paid by the attacker.
+    fn attacker_verify(&self, tree: &Pubkey, receipt: &Pubkey, bump: u8) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new(*tree, false),
+            AccountMeta::new(*receipt, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                MerkleProofInstruction::Verify,
+                &VerifyInstructionData::new(LEAF, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_set_root(&setup.authority, &setup.tree);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates a tree at the address of the tree of the authority,
+/// with a root of their choice, before the authority does.
+#[test]
+fn test_initialize_tree_of_other_authority() {
+    let setup = Setup::new();
+    let tree = setup.tree;
+    let setup = setup.with(tree, Account::default());
+    let (_, bump) = tree_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(tree, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            MerkleProofInstruction::Initialize,
+            &InitializeInstructionData::new([7; 32], bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// A tree of the attacker owned by another program, which the program
+/// would write a root into.
+#[test]
+fn test_set_root_of_spoofed_tree() {
+    let setup = Setup::new();
+    let mut tree_account = tree_account(&setup.mollusk, &setup.attacker);
+    tree_account.owner = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+    let setup = setup.with(tree, tree_account);
+    setup.expect_err(
+        &setup.instruction_set_root(&setup.attacker, &tree),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A tree of the attacker with a root of their choice, owned by another
+/// program, whose receipts would be created by the program.
+#[test]
+fn test_verify_in_spoofed_tree() {
+    let setup = Setup::new();
+    let mut tree_account = tree_account(&setup.mollusk, &setup.attacker);
+    tree_account.owner = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+    let (receipt, bump) = receipt_pda(&tree, &LEAF);
+    let setup = setup
+        .with(tree, tree_account)
+        .with(receipt, Account::default());
+    setup.expect_err(
+        &setup.attacker_verify(&tree, &receipt, bump),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker records a valid leaf of the tree in a receipt at an address
+/// of their choice, so the leaf could be verified again into its real
+/// receipt.
+#[test]
+fn test_verify_into_other_receipt() {
+    let setup = Setup::new();
+    let receipt = Pubkey::new_unique();
+    let setup = setup.with(receipt, Account::default());
+    let (_, bump) = receipt_pda(&setup.tree, &LEAF);
+    setup.expect_err(
+        &setup.attacker_verify(&setup.tree, &receipt, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
@@ -0,0 +1,379 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use merkle_proof::{
+    InitializeInstructionData, MerkleProofError, MerkleProofInstruction, Receipt,
+    SetRootInstructionData, Tree, VerifyInstructionData, RECEIPT_SEED, TREE_SEED,
+};
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use proptest::prelude::*;
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(merkle_proof::ID);
+
+/// Offset of the verified leaves counter of a tree.
+const VERIFIED_OFFSET: usize = 64;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: MerkleProofInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<MerkleProofInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+/// Builds all levels of a tree, from the hashed leaves up to the root. The
+/// last node of a level with an odd number of nodes is promoted to the next
+/// level as is.
+fn build_tree(leaves: &[[u8; 32]]) -> Vec<Vec<[u8; 32]>> {
+    let mut levels = vec![leaves
+        .iter()
+        .map(|leaf| merkle::hash_leaf(leaf))
+        .collect::<Vec<_>>()];
+    while levels.last().unwrap().len() > 1 {
+        let level = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => merkle::hash_pair(a, b),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(level);
+    }
+    levels
+}
+
+fn root(levels: &[Vec<[u8; 32]>]) -> [u8; 32] {
+    levels.last().unwrap()[0]
+}
+
+/// Returns the proof of the leaf at `index`.
+fn proof(levels: &[Vec<[u8; 32]>], mut index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    for level in &levels[..levels.len() - 1] {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        index /= 2;
+    }
+    proof
+}
+
+fn instruction_initialize(
+    authority: &Pubkey,
+    tree: &Pubkey,
+    root: [u8; 32],
+    bump: u8,
+) -> Instruction {
+    let data = InitializeInstructionData::new(root, bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*tree, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(MerkleProofInstruction::Initialize, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_set_root(authority: &Pubkey, tree: &Pubkey, root: [u8; 32]) -> Instruction {
+    let data = SetRootInstructionData::new(root);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*tree, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(MerkleProofInstruction::SetRoot, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_verify(
+    payer: &Pubkey,
+    tree: &Pubkey,
+    receipt: &Pubkey,
+    leaf: [u8; 32],
+    proof: &[[u8; 32]],
+    bump: u8,
+) -> Instruction {
+    let data = VerifyInstructionData::new(leaf, bump);
+    let mut data = instruction_data(MerkleProofInstruction::Verify, &data);
+    data.extend(proof.iter().flatten());
+    let ix_accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*tree, false),
+        AccountMeta::new(*receipt, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(ID, &data, ix_accounts)
+}
+
+/// Creates an initialized tree account with the given root.
+fn tree_account(mollusk: &Mollusk, authority: &Pubkey, root: &[u8; 32]) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Tree::LEN),
+        Tree::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[32..64].copy_from_slice(root);
+    account
+}
+
+fn leaves(n: u8) -> Vec<[u8; 32]> {
+    (0..n).map(|i| [i; 32]).collect()
+}
+
+#[test]
+fn test_merkle_proof_initialize_and_verify() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/merkle_proof");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let leaves = leaves(5);
+    let levels = build_tree(&leaves);
+
+    let authority = Pubkey::new_unique();
+    let (tree, tree_bump) =
+        Pubkey::find_program_address(&[TREE_SEED.as_bytes(), authority.as_ref()], &ID);
+    let payer = Pubkey::new_unique();
+    let (receipt, receipt_bump) =
+        Pubkey::find_program_address(&[RECEIPT_SEED.as_bytes(), tree.as_ref(), &leaves[4]], &ID);
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize(&authority, &tree, root(&levels), tree_bump),
+                &[
+                    Check::success(),
+                    Check::account(&tree)
+                        .owner(&ID)
+                        .space(Tree::LEN)
+                        .data_slice(0, authority.as_ref())
+                        .data_slice(32, &root(&levels))
+                        .build(),
+                ],
+            ),
+            // The last leaf was promoted, so its proof is shorter.
+            (
+                &instruction_verify(
+                    &payer,
+                    &tree,
+                    &receipt,
+                    leaves[4],
+                    &proof(&levels, 4),
+                    receipt_bump,
+                ),
+                &[
+                    Check::success(),
+                    Check::account(&receipt)
+                        .owner(&ID)
+                        .space(Receipt::LEN)
+                        .data_slice(0, tree.as_ref())
+                        .data_slice(32, &leaves[4])
+                        .data_slice(64, &root(&levels))
+                        .build(),
+                    Check::account(&tree)
+                        .data_slice(VERIFIED_OFFSET, &1u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_verify(
+                    &payer,
+                    &tree,
+                    &receipt,
+                    leaves[4],
+                    &proof(&levels, 4),
+                    receipt_bump,
+                ),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::MerkleProof.code(MerkleProofError::AlreadyVerified as u32),
+                ))],
+            ),
+        ],
+        &[
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (tree, Account::default()),
+            (payer, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (receipt, Account::default()),
+            (system_program, system_account),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_merkle_proof_invalid_proof() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/merkle_proof");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let leaves = leaves(8);
+    let levels = build_tree(&leaves);
+
+    let authority = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let receipt = Pubkey::new_unique();
+
+    // An inner node isn't accepted as a leaf, even with a valid proof of it.
+    let inner_node = levels[1][0];
+    let inner_proof = proof(&levels[1..], 0);
+
+    let accounts = [
+        (payer, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+        (tree, tree_account(&mollusk, &authority, &root(&levels))),
+        (receipt, Account::default()),
+        (system_program, system_account),
+    ];
+    for (leaf, leaf_proof) in [(leaves[0], proof(&levels, 1)), (inner_node, inner_proof)] {
+        mollusk.process_and_validate_instruction(
+            &instruction_verify(&payer, &tree, &receipt, leaf, &leaf_proof, 255),
+            &accounts,
+            &[Check::err(ProgramError::Custom(
+                Namespace::MerkleProof.code(MerkleProofError::InvalidProof as u32),
+            ))],
+        );
+    }
+}
+
+/// A receipt is owned by the program like a tree, but isn't accepted as one,
+/// even with a valid proof against its leaf as the root.
+#[test]
+fn test_merkle_proof_receipt_as_tree() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/merkle_proof");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let leaves = leaves(2);
+    let levels = build_tree(&leaves);
+
+    let payer = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+    let receipt = Pubkey::new_unique();
+
+    let mut tree_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Receipt::LEN),
+        Receipt::LEN,
+        &ID,
+    );
+    tree_account.data[32..64].copy_from_slice(&root(&levels));
+
+    mollusk.process_and_validate_instruction(
+        &instruction_verify(&payer, &tree, &receipt, leaves[0], &proof(&levels, 0), 255),
+        &[
+            (payer, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (tree, tree_account),
+            (receipt, Account::default()),
+            (system_program, system_account),
+        ],
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+#[test]
+fn test_merkle_proof_set_root() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/merkle_proof");
+
+    let authority = Pubkey::new_unique();
+    let tree = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let new_root = [7; 32];
+
+    let accounts = [
+        (authority, Account::default()),
+        (attacker, Account::default()),
+        (tree, tree_account(&mollusk, &authority, &[0; 32])),
+    ];
+    mollusk.process_and_validate_instruction(
+        &instruction_set_root(&attacker, &tree, new_root),
+        &accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction_set_root(&authority, &tree, new_root),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&tree).data_slice(32, &new_root).build(),
+        ],
+    );
+}
+
+proptest! {
+    #[test]
+    fn test_merkle_proof_verify_all_leaves(
+        leaves in prop::collection::vec(any::<[u8; 32]>(), 1..64),
+    ) {
+        let levels = build_tree(&leaves);
+        let root = root(&levels);
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = proof(&levels, i);
+            prop_assert!(merkle::verify(&merkle::hash_leaf(leaf), &proof, &root));
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof_reject_other_leaf(
+        leaves in prop::collection::vec(any::<[u8; 32]>(), 1..64),
+        other in any::<[u8; 32]>(),
+        index in any::<prop::sample::Index>(),
+    ) {
+        prop_assume!(!leaves.contains(&other));
+
+        let levels = build_tree(&leaves);
+        let proof = proof(&levels, index.index(leaves.len()));
+        prop_assert!(!merkle::verify(&merkle::hash_leaf(&other), &proof, &root(&levels)));
+    }
+
+    #[test]
+    fn test_merkle_proof_reject_tampered_proof(
+        leaves in prop::collection::vec(any::<[u8; 32]>(), 2..64),
+        index in any::<prop::sample::Index>(),
+        node in any::<prop::sample::Index>(),
+        bit in 0..256usize,
+    ) {
+        let levels = build_tree(&leaves);
+        let index = index.index(leaves.len());
+        let mut proof = proof(&levels, index);
+
+        let node = node.index(proof.len());
+        proof[node][bit / 8] ^= 1 << (bit % 8);
+        prop_assert!(!merkle::verify(&merkle::hash_leaf(&leaves[index]), &proof, &root(&levels)));
+    }
+
+    #[test]
+    fn test_merkle_proof_reject_too_deep(
+        leaf in any::<[u8; 32]>(),
+        proof in prop::collection::vec(any::<[u8; 32]>(), merkle::MAX_DEPTH + 1..merkle::MAX_DEPTH + 4),
+    ) {
+        let leaf = merkle::hash_leaf(&leaf);
+        let root = merkle::compute_root(&leaf, &proof);
+        prop_assert!(!merkle::verify(&leaf, &proof, &root));
+    }
+}
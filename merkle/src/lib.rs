@@ -0,0 +1,74 @@
+//! Merkle proof verification shared by the example programs.
+//!
+//! Trees are built from SHA-256 hashes with domain separation between leaves
+//! and inner nodes, so an inner node can't be passed off as a leaf. The
+//! children of inner nodes are sorted before hashing, which means proofs
+//! don't need to encode the position of the leaf.
+//!
+//! On-chain, hashing uses the `sol_sha256` syscall. Off-chain, it uses the
+//! `sha2` crate, so clients and tests compute the same hashes as programs.
+
+#![no_std]
+
+/// Maximum supported depth of a proof.
+pub const MAX_DEPTH: usize = 32;
+
+/// Prefix of hashed leaves.
+pub const LEAF_PREFIX: &[u8] = &[0];
+/// Prefix of hashed inner nodes.
+pub const NODE_PREFIX: &[u8] = &[1];
+
+/// Computes the SHA-256 hash of the concatenation of `vals`.
+#[cfg(target_os = "solana")]
+pub fn hashv(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash = [0; 32];
+    unsafe {
+        pinocchio::syscalls::sol_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        );
+    }
+    hash
+}
+
+/// Computes the SHA-256 hash of the concatenation of `vals`.
+#[cfg(not(target_os = "solana"))]
+pub fn hashv(vals: &[&[u8]]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for val in vals {
+        hasher.update(val);
+    }
+    hasher.finalize().into()
+}
+
+/// Hashes leaf data into a leaf of a tree.
+pub fn hash_leaf(data: &[u8]) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, data])
+}
+
+/// Hashes two children into their parent node.
+pub fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    if a <= b {
+        hashv(&[NODE_PREFIX, a, b])
+    } else {
+        hashv(&[NODE_PREFIX, b, a])
+    }
+}
+
+/// Computes the root of the tree containing `leaf`, given its proof.
+pub fn compute_root(leaf: &[u8; 32], proof: &[[u8; 32]]) -> [u8; 32] {
+    proof
+        .iter()
+        .fold(*leaf, |node, sibling| hash_pair(&node, sibling))
+}
+
+/// Returns whether `proof` proves the inclusion of `leaf` in the tree with
+/// the given `root`.
+///
+/// Proofs deeper than [`MAX_DEPTH`] are rejected.
+pub fn verify(leaf: &[u8; 32], proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    proof.len() <= MAX_DEPTH && &compute_root(leaf, proof) == root
+}
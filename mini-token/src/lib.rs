@@ -0,0 +1,401 @@
+//! Subset of the SPL Token program, written with zero-copy state.
+//!
+//! Implements `InitializeMint`, `InitializeAccount`, `Transfer` and `Burn`
+//! with the same instruction encoding, account layouts and error codes as
+//! SPL Token, so `spl_token::instruction` builders and `spl_token::state`
+//! work with it. Multisig authorities and wrapped SOL aren't supported.
+
+#![no_std]
+
+use common::{checks::require_signer, info, keys_eq};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, sysvars::rent::Rent,
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+use crate::state::{AccountState, Mint, TokenAccount};
+
+pub mod state;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("4zUpfSTWzwj2K9RaKR9drVvBhYX4jbEvoDotasZXa39b");
+
+/// Mint of wrapped SOL.
+pub const NATIVE_MINT: Pubkey =
+    pinocchio_pubkey::pubkey!("So11111111111111111111111111111111111111112");
+
+/// Errors returned by the token program. The codes match `TokenError` of
+/// SPL Token.
+#[repr(u32)]
+pub enum TokenError {
+    /// Lamport balance below rent-exempt threshold.
+    NotRentExempt = 0,
+    /// Insufficient funds for the operation requested.
+    InsufficientFunds = 1,
+    /// Invalid mint.
+    InvalidMint = 2,
+    /// Account not associated with this mint.
+    MintMismatch = 3,
+    /// Owner does not match.
+    OwnerMismatch = 4,
+    /// The account cannot be initialized because it is already being used.
+    AlreadyInUse = 6,
+    /// Instruction does not support native tokens.
+    NativeNotSupported = 10,
+    /// Invalid instruction.
+    InvalidInstruction = 12,
+    /// Operation overflowed.
+    Overflow = 14,
+    /// Account is frozen.
+    AccountFrozen = 17,
+}
+
+impl From<TokenError> for ProgramError {
+    fn from(e: TokenError) -> Self {
+        Namespace::Token.error(e as u32)
+    }
+}
+
+/// Token program instruction discriminators, the same as in SPL Token.
+#[repr(u8)]
+pub enum TokenInstruction {
+    /// Initializes a new mint.
+    ///
+    /// ### Accounts:
+    ///   0. `[WRITE]` Mint
+    ///   1. `[]` Rent sysvar
+    InitializeMint = 0,
+    /// Initializes a new token account.
+    ///
+    /// ### Accounts:
+    ///   0. `[WRITE]` Token account
+    ///   1. `[]` Mint
+    ///   2. `[]` Owner
+    ///   3. `[]` Rent sysvar
+    InitializeAccount = 1,
+    /// Transfers tokens between accounts of the same mint.
+    ///
+    /// ### Accounts:
+    ///   0. `[WRITE]` Source token account
+    ///   1. `[WRITE]` Destination token account
+    ///   2. `[SIGNER]` Owner or delegate of the source
+    Transfer = 3,
+    /// Burns tokens, reducing the supply of the mint.
+    ///
+    /// ### Accounts:
+    ///   0. `[WRITE]` Token account
+    ///   1. `[WRITE]` Mint
+    ///   2. `[SIGNER]` Owner or delegate of the token account
+    Burn = 8,
+}
+
+impl TryFrom<&u8> for TokenInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeMint),
+            1 => Ok(Self::InitializeAccount),
+            3 => Ok(Self::Transfer),
+            8 => Ok(Self::Burn),
+            _ => Err(TokenError::InvalidInstruction.into()),
+        }
+    }
+}
+
+/// Fixed part of the `InitializeMint` instruction data. It's followed by
+/// the optional freeze authority, encoded as a 1-byte tag and the key.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeMintInstructionData {
+    pub decimals: u8,
+    pub mint_authority: Pubkey,
+}
+
+/// Instruction data of `Transfer` and `Burn`.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AmountInstructionData {
+    pub amount: [u8; 8],
+}
+
+impl AmountInstructionData {
+    pub fn new(amount: u64) -> Self {
+        Self {
+            amount: amount.to_le_bytes(),
+        }
+    }
+
+    fn parse(instruction_data: &[u8]) -> Result<u64, ProgramError> {
+        // Trailing bytes are ignored, like in SPL Token.
+        if instruction_data.len() < Self::LEN {
+            return Err(TokenError::InvalidInstruction.into());
+        }
+        let instruction_data = Self::from_bytes(&instruction_data[..Self::LEN])?;
+        Ok(u64::from_le_bytes(instruction_data.amount))
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(TokenError::InvalidInstruction)?;
+    let instruction = TokenInstruction::try_from(instruction)?;
+
+    match instruction {
+        TokenInstruction::InitializeMint => process_initialize_mint(accounts, instruction_data),
+        TokenInstruction::InitializeAccount => process_initialize_account(accounts),
+        TokenInstruction::Transfer => process_transfer(accounts, instruction_data),
+        TokenInstruction::Burn => process_burn(accounts, instruction_data),
+    }
+}
+
+/// Initializes a mint, which has to be allocated and assigned to the
+/// program beforehand.
+pub fn process_initialize_mint(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    info!("Instruction: InitializeMint");
+
+    // Retrieve and validate the accounts.
+    let [mint, rent_sysvar, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !mint.is_owned_by(&ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Deserialize instruction data.
+    if instruction_data.len() < InitializeMintInstructionData::LEN {
+        return Err(TokenError::InvalidInstruction.into());
+    }
+    let (header, freeze_authority) = instruction_data.split_at(InitializeMintInstructionData::LEN);
+    let header = InitializeMintInstructionData::from_bytes(header)?;
+    let freeze_authority: Option<&Pubkey> = match freeze_authority.split_first() {
+        Some((0, _)) => None,
+        Some((1, key)) if key.len() >= 32 => Some(unsafe { &*key.as_ptr().cast() }),
+        _ => return Err(TokenError::InvalidInstruction.into()),
+    };
+
+    let rent = Rent::from_account_info(rent_sysvar)?;
+
+    // Deserialize the mint.
+    let mut data = mint.try_borrow_mut_data()?;
+    let data = Mint::load_mut_uninitialized(&mut data)?;
+
+    if data.is_initialized() {
+        return Err(TokenError::AlreadyInUse.into());
+    }
+    if !rent.is_exempt(mint.lamports(), Mint::LEN) {
+        return Err(TokenError::NotRentExempt.into());
+    }
+
+    // Initialize the mint.
+    data.mint_authority.set(Some(&header.mint_authority));
+    data.decimals = header.decimals;
+    data.set_initialized();
+    data.freeze_authority.set(freeze_authority);
+
+    Ok(())
+}
+
+/// Initializes a token account, which has to be allocated and assigned to
+/// the program beforehand.
+pub fn process_initialize_account(accounts: &[AccountInfo]) -> ProgramResult {
+    info!("Instruction: InitializeAccount");
+
+    // Retrieve and validate the accounts.
+    let [account, mint, owner, rent_sysvar, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !account.is_owned_by(&ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let rent = Rent::from_account_info(rent_sysvar)?;
+
+    // Deserialize the token account.
+    let mut data = account.try_borrow_mut_data()?;
+    let data = TokenAccount::load_mut_uninitialized(&mut data)?;
+
+    if data.state() != AccountState::Uninitialized {
+        return Err(TokenError::AlreadyInUse.into());
+    }
+    if !rent.is_exempt(account.lamports(), TokenAccount::LEN) {
+        return Err(TokenError::NotRentExempt.into());
+    }
+
+    // Check the mint.
+    if keys_eq(mint.key(), &NATIVE_MINT) {
+        return Err(TokenError::NativeNotSupported.into());
+    }
+    if !mint.is_owned_by(&ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Mint::load(&mint.try_borrow_data()?).map_err(|_| TokenError::InvalidMint)?;
+
+    // Initialize the token account. The remaining fields are already zeroed.
+    data.mint = *mint.key();
+    data.owner = *owner.key();
+    data.set_state(AccountState::Initialized);
+
+    Ok(())
+}
+
+/// Transfers tokens from the source to the destination account.
+pub fn process_transfer(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    info!("Instruction: Transfer");
+
+    // Retrieve and validate the accounts.
+    let [source, destination, authority, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !source.is_owned_by(&ID) || !destination.is_owned_by(&ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Deserialize instruction data.
+    let amount = AmountInstructionData::parse(instruction_data)?;
+
+    // Deserialize the token accounts. Transferring to the same account is
+    // allowed, but the account can be borrowed only once.
+    let self_transfer = keys_eq(source.key(), destination.key());
+    let mut source_data = source.try_borrow_mut_data()?;
+    let source_data = TokenAccount::load_mut(&mut source_data)?;
+    let mut destination_data = if self_transfer {
+        None
+    } else {
+        Some(destination.try_borrow_mut_data()?)
+    };
+    let destination_data = destination_data
+        .as_deref_mut()
+        .map(TokenAccount::load_mut)
+        .transpose()?;
+
+    if source_data.is_frozen() || destination_data.as_ref().is_some_and(|d| d.is_frozen()) {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    if source_data.amount() < amount {
+        return Err(TokenError::InsufficientFunds.into());
+    }
+    if let Some(destination_data) = &destination_data {
+        if source_data.mint != destination_data.mint {
+            return Err(TokenError::MintMismatch.into());
+        }
+    }
+
+    // Self-transfers only check the authority, without moving any tokens.
+    authorize(source_data, authority, amount, !self_transfer)?;
+    let Some(destination_data) = destination_data else {
+        return Ok(());
+    };
+
+    let destination_amount = destination_data
+        .amount()
+        .checked_add(amount)
+        .ok_or(TokenError::Overflow)?;
+    source_data.set_amount(source_data.amount() - amount);
+    destination_data.set_amount(destination_amount);
+
+    Ok(())
+}
+
+/// Burns tokens from a token account.
+pub fn process_burn(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    info!("Instruction: Burn");
+
+    // Retrieve and validate the accounts.
+    let [account, mint, authority, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !account.is_owned_by(&ID) || !mint.is_owned_by(&ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Deserialize instruction data.
+    let amount = AmountInstructionData::parse(instruction_data)?;
+
+    // Deserialize the token account and the mint.
+    let mut account_data = account.try_borrow_mut_data()?;
+    let account_data = TokenAccount::load_mut(&mut account_data)?;
+    let mut mint_data = mint.try_borrow_mut_data()?;
+    let mint_data = Mint::load_mut(&mut mint_data)?;
+
+    if account_data.is_frozen() {
+        return Err(TokenError::AccountFrozen.into());
+    }
+    if account_data.is_native.get().is_some() {
+        return Err(TokenError::NativeNotSupported.into());
+    }
+    if account_data.amount() < amount {
+        return Err(TokenError::InsufficientFunds.into());
+    }
+    if !keys_eq(mint.key(), &account_data.mint) {
+        return Err(TokenError::MintMismatch.into());
+    }
+
+    authorize(account_data, authority, amount, true)?;
+
+    let supply = mint_data
+        .supply()
+        .checked_sub(amount)
+        .ok_or(TokenError::Overflow)?;
+    account_data.set_amount(account_data.amount() - amount);
+    mint_data.set_supply(supply);
+
+    Ok(())
+}
+
+/// Checks that `authority` can spend `amount` tokens of `account`, either
+/// as its owner or as its delegate. If `spend` is set, the delegated
+/// amount is reduced, and the delegate is removed once it's used up.
+fn authorize(
+    account: &mut TokenAccount,
+    authority: &AccountInfo,
+    amount: u64,
+    spend: bool,
+) -> ProgramResult {
+    match account.delegate.get() {
+        Some(delegate) if delegate == authority.key() => {
+            validate_owner(delegate, authority)?;
+            let delegated_amount = account
+                .delegated_amount()
+                .checked_sub(amount)
+                .ok_or(TokenError::InsufficientFunds)?;
+            if spend {
+                account.set_delegated_amount(delegated_amount);
+                if delegated_amount == 0 {
+                    account.delegate.set(None);
+                }
+            }
+            Ok(())
+        }
+        _ => validate_owner(&account.owner, authority),
+    }
+}
+
+fn validate_owner(expected_owner: &Pubkey, authority: &AccountInfo) -> ProgramResult {
+    if expected_owner != authority.key() {
+        return Err(TokenError::OwnerMismatch.into());
+    }
+    require_signer(authority)?;
+    Ok(())
+}
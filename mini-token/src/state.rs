@@ -0,0 +1,228 @@
+//! Zero-copy state of mints and token accounts.
+//!
+//! The layouts are the same as in SPL Token, so wallets, explorers and
+//! `spl_token::state` can read the accounts. Every field is a byte array,
+//! which gives the structs an alignment of 1, so they can be cast from any
+//! account data without copying.
+
+use common::assert_layout;
+use core::mem;
+
+use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+
+/// Optional pubkey, laid out like `COption<Pubkey>`: a 4-byte little-endian
+/// tag followed by the key, which is zeroed when the tag is 0.
+#[repr(C)]
+pub struct OptionalPubkey {
+    tag: [u8; 4],
+    value: Pubkey,
+}
+
+assert_layout!(OptionalPubkey, size = 36, align = 1, { tag: 0, value: 4 });
+
+impl OptionalPubkey {
+    pub fn get(&self) -> Option<&Pubkey> {
+        if u32::from_le_bytes(self.tag) == 1 {
+            Some(&self.value)
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, value: Option<&Pubkey>) {
+        match value {
+            Some(value) => {
+                self.tag = 1u32.to_le_bytes();
+                self.value = *value;
+            }
+            None => {
+                self.tag = [0; 4];
+                self.value = [0; 32];
+            }
+        }
+    }
+}
+
+/// Optional amount, laid out like `COption<u64>`.
+#[repr(C)]
+pub struct OptionalAmount {
+    tag: [u8; 4],
+    value: [u8; 8],
+}
+
+assert_layout!(OptionalAmount, size = 12, align = 1, { tag: 0, value: 4 });
+
+impl OptionalAmount {
+    pub fn get(&self) -> Option<u64> {
+        if u32::from_le_bytes(self.tag) == 1 {
+            Some(u64::from_le_bytes(self.value))
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tag = [0; 4];
+        self.value = [0; 8];
+    }
+}
+
+#[repr(C)]
+pub struct Mint {
+    /// Authority allowed to mint new tokens. Minting is fixed when unset.
+    pub mint_authority: OptionalPubkey,
+    supply: [u8; 8],
+    /// Number of base 10 digits to the right of the decimal place.
+    pub decimals: u8,
+    is_initialized: u8,
+    /// Authority allowed to freeze token accounts.
+    pub freeze_authority: OptionalPubkey,
+}
+
+assert_layout!(Mint, size = 82, align = 1, {
+    mint_authority: 0,
+    supply: 36,
+    decimals: 44,
+    is_initialized: 45,
+    freeze_authority: 46,
+});
+
+impl Mint {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Casts the account data of an initialized mint.
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mint: &Self = unsafe { &*data.as_ptr().cast() };
+        if !mint.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        Ok(mint)
+    }
+
+    /// Casts the account data of an initialized mint.
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let mint = Self::load_mut_uninitialized(data)?;
+        if !mint.is_initialized() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        Ok(mint)
+    }
+
+    /// Casts the account data of a mint, which might not be initialized yet.
+    pub fn load_mut_uninitialized(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(unsafe { &mut *data.as_mut_ptr().cast() })
+    }
+
+    pub fn supply(&self) -> u64 {
+        u64::from_le_bytes(self.supply)
+    }
+
+    pub fn set_supply(&mut self, supply: u64) {
+        self.supply = supply.to_le_bytes();
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.is_initialized == 1
+    }
+
+    pub fn set_initialized(&mut self) {
+        self.is_initialized = 1;
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountState {
+    Uninitialized,
+    Initialized,
+    /// Frozen by the freeze authority of the mint. Frozen accounts can't
+    /// send or receive tokens.
+    Frozen,
+}
+
+#[repr(C)]
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    amount: [u8; 8],
+    /// Authority allowed to spend up to `delegated_amount` tokens.
+    pub delegate: OptionalPubkey,
+    state: u8,
+    /// Rent-exempt reserve of wrapped SOL accounts. Wrapped SOL isn't
+    /// supported by this program.
+    pub is_native: OptionalAmount,
+    delegated_amount: [u8; 8],
+    pub close_authority: OptionalPubkey,
+}
+
+assert_layout!(TokenAccount, size = 165, align = 1, {
+    mint: 0,
+    owner: 32,
+    amount: 64,
+    delegate: 72,
+    state: 108,
+    is_native: 109,
+    delegated_amount: 121,
+    close_authority: 129,
+});
+
+impl TokenAccount {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Casts the account data of an initialized token account.
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let account = Self::load_mut_uninitialized(data)?;
+        if account.state() == AccountState::Uninitialized {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        Ok(account)
+    }
+
+    /// Casts the account data of a token account, which might not be
+    /// initialized yet.
+    pub fn load_mut_uninitialized(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let account: &mut Self = unsafe { &mut *data.as_mut_ptr().cast() };
+        if account.state > AccountState::Frozen as u8 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(account)
+    }
+
+    pub fn amount(&self) -> u64 {
+        u64::from_le_bytes(self.amount)
+    }
+
+    pub fn set_amount(&mut self, amount: u64) {
+        self.amount = amount.to_le_bytes();
+    }
+
+    pub fn delegated_amount(&self) -> u64 {
+        u64::from_le_bytes(self.delegated_amount)
+    }
+
+    pub fn set_delegated_amount(&mut self, delegated_amount: u64) {
+        self.delegated_amount = delegated_amount.to_le_bytes();
+    }
+
+    pub fn state(&self) -> AccountState {
+        // Validated when loading the account.
+        unsafe { mem::transmute::<u8, AccountState>(self.state) }
+    }
+
+    pub fn set_state(&mut self, state: AccountState) {
+        self.state = state as u8;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.state() == AccountState::Frozen
+    }
+}
@@ -0,0 +1,283 @@
+//! Attacks on the mini token program, each rejected with its own error.
+
+use mini_token::TokenError;
+use mollusk_svm::{result::Check, Mollusk};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::Instruction;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(mini_token::ID);
+
+const AMOUNT: u64 = 100;
+
+/// Points an SPL Token instruction to the clone. The builders accept only
+/// the SPL Token ID, but the encoding is the same.
+fn with_program_id(instruction: Instruction) -> Instruction {
+    Instruction {
+        program_id: ID,
+        ..instruction
+    }
+}
+
+fn mint_account(mollusk: &Mollusk, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns a token account of `owner`, which allows `delegate` to spend
+/// all of its tokens if set.
+fn token_account(
+    mollusk: &Mollusk,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    delegate: Option<&Pubkey>,
+    amount: u64,
+) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: delegate.map_or(COption::None, |delegate| COption::Some(*delegate)),
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: if delegate.is_some() { amount } else { 0 },
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Accounts of a mint with [`AMOUNT`] tokens, all held by Alice, and of an
+/// attacker with an empty token account, which they delegated to
+/// themselves.
+struct Setup {
+    mollusk: Mollusk,
+    mint: Pubkey,
+    alice: Pubkey,
+    alice_account: Pubkey,
+    attacker: Pubkey,
+    attacker_account: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/mini_token");
+
+        let mint = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let alice_account = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let attacker_account = Pubkey::new_unique();
+
+        let accounts = vec![
+            (mint, mint_account(&mollusk, AMOUNT)),
+            (alice, Account::default()),
+            (
+                alice_account,
+                token_account(&mollusk, &mint, &alice, None, AMOUNT),
+            ),
+            (attacker, Account::default()),
+            (
+                attacker_account,
+                token_account(&mollusk, &mint, &attacker, Some(&attacker), 0),
+            ),
+            mollusk.sysvars.keyed_account_for_rent_sysvar(),
+        ];
+
+        Self {
+            mollusk,
+            mint,
+            alice,
+            alice_account,
+            attacker,
+            attacker_account,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Transfers all of the tokens from `source` to the attacker, signed by
+    /// the attacker.
+    fn attacker_transfer(&self, source: &Pubkey) -> Instruction {
+        with_program_id(
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                source,
+                &self.attacker_account,
+                &self.attacker,
+                &[],
+                AMOUNT,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn instruction_burn(&self, account: &Pubkey, mint: &Pubkey) -> Instruction {
+        with_program_id(
+            spl_token::instruction::burn(&spl_token::ID, account, mint, &self.alice, &[], AMOUNT)
+                .unwrap(),
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+/// The attacker spends the tokens of Alice as a delegate, which they are
+/// only of their own account.
+#[test]
+fn test_delegate_of_other_account() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_transfer(&setup.alice_account),
+        ProgramError::Custom(TokenError::OwnerMismatch as u32),
+    );
+}
+
+/// A token account with the layout of the program and a balance, but owned
+/// by another program, e.g. SPL Token.
+#[test]
+fn test_spoofed_source() {
+    let setup = Setup::new();
+    let source = Pubkey::new_unique();
+    let mut source_account =
+        token_account(&setup.mollusk, &setup.mint, &setup.attacker, None, AMOUNT);
+    source_account.owner = spl_token::ID;
+    let setup = setup.with(source, source_account);
+    setup.expect_err(
+        &setup.attacker_transfer(&source),
+        ProgramError::IncorrectProgramId,
+    );
+}
+
+/// The mint is passed as the source, which is shorter than a token account,
+/// so its owner and balance would be read past the end of the data.
+#[test]
+fn test_mint_as_token_account() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_transfer(&setup.mint),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A token account is passed as the mint of a burn, so the supply of the
+/// real mint is left as it was.
+#[test]
+fn test_token_account_as_mint() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_burn(&setup.alice_account, &setup.attacker_account),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A mint with the layout of the program, but owned by another program, so
+/// the supply of the real mint is left as it was.
+#[test]
+fn test_spoofed_mint() {
+    let setup = Setup::new();
+    let other_mint = Pubkey::new_unique();
+    let mut other_mint_account = mint_account(&setup.mollusk, AMOUNT);
+    other_mint_account.owner = spl_token::ID;
+    let setup = setup.with(other_mint, other_mint_account);
+    setup.expect_err(
+        &setup.instruction_burn(&setup.alice_account, &other_mint),
+        ProgramError::IncorrectProgramId,
+    );
+}
+
+/// The token account of Alice is initialized again as a mint, to overwrite
+/// her owner and balance.
+#[test]
+fn test_reinitialize_token_account_as_mint() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &with_program_id(
+            spl_token::instruction::initialize_mint(
+                &spl_token::ID,
+                &setup.alice_account,
+                &setup.attacker,
+                None,
+                6,
+            )
+            .unwrap(),
+        ),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A token account is initialized with a mint of another program, whose
+/// mint authority could print tokens out of thin air.
+#[test]
+fn test_initialize_account_with_spoofed_mint() {
+    let setup = Setup::new();
+    let (mint, account) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut mint_account = mint_account(&setup.mollusk, 0);
+    mint_account.owner = spl_token::ID;
+    let uninitialized_account = Account::new(
+        setup
+            .mollusk
+            .sysvars
+            .rent
+            .minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &ID,
+    );
+    let setup = setup
+        .with(mint, mint_account)
+        .with(account, uninitialized_account);
+    setup.expect_err(
+        &with_program_id(
+            spl_token::instruction::initialize_account(
+                &spl_token::ID,
+                &account,
+                &mint,
+                &setup.attacker,
+            )
+            .unwrap(),
+        ),
+        ProgramError::IncorrectProgramId,
+    );
+}
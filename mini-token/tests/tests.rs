@@ -0,0 +1,628 @@
+use mini_token::TokenError;
+use mollusk_svm::{
+    program::loader_keys::LOADER_V3,
+    result::{Check, InstructionResult, ProgramResult},
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::Instruction;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(mini_token::ID);
+const TOKEN_ID: Pubkey = spl_token::ID;
+
+/// Loads both the clone and SPL Token, so every instruction can run against
+/// both of them.
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/mini_token");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk
+}
+
+/// Points an SPL Token instruction to `program_id`. The builders accept only
+/// the SPL Token ID, but the encoding is the same for the clone.
+fn with_program_id(program_id: &Pubkey, instruction: Instruction) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        ..instruction
+    }
+}
+
+fn instruction_initialize_mint(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    mint_authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+    decimals: u8,
+) -> Instruction {
+    let instruction = spl_token::instruction::initialize_mint(
+        &TOKEN_ID,
+        mint,
+        mint_authority,
+        freeze_authority,
+        decimals,
+    )
+    .unwrap();
+    with_program_id(program_id, instruction)
+}
+
+fn instruction_initialize_account(
+    program_id: &Pubkey,
+    account: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Instruction {
+    let instruction =
+        spl_token::instruction::initialize_account(&TOKEN_ID, account, mint, owner).unwrap();
+    with_program_id(program_id, instruction)
+}
+
+fn instruction_transfer(
+    program_id: &Pubkey,
+    source: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let instruction =
+        spl_token::instruction::transfer(&TOKEN_ID, source, destination, authority, &[], amount)
+            .unwrap();
+    with_program_id(program_id, instruction)
+}
+
+fn instruction_burn(
+    program_id: &Pubkey,
+    account: &Pubkey,
+    mint: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let instruction =
+        spl_token::instruction::burn(&TOKEN_ID, account, mint, authority, &[], amount).unwrap();
+    with_program_id(program_id, instruction)
+}
+
+/// Creates an allocated, but uninitialized account of `program_id`.
+fn uninitialized_account(mollusk: &Mollusk, program_id: &Pubkey, space: usize) -> Account {
+    Account::new(
+        mollusk.sysvars.rent.minimum_balance(space),
+        space,
+        program_id,
+    )
+}
+
+fn mint_account(mollusk: &Mollusk, program_id: &Pubkey, supply: u64) -> Account {
+    let mut account = uninitialized_account(mollusk, program_id, Mint::LEN);
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account_with(mollusk: &Mollusk, program_id: &Pubkey, state: TokenAccount) -> Account {
+    let mut account = uninitialized_account(mollusk, program_id, TokenAccount::LEN);
+    Pack::pack(state, account.data_as_mut_slice()).unwrap();
+    account
+}
+
+fn token_account(
+    mollusk: &Mollusk,
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Account {
+    token_account_with(
+        mollusk,
+        program_id,
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+    )
+}
+
+/// Runs an instruction against the clone and SPL Token, with the accounts
+/// owned by the respective program, and checks that both produce the same
+/// result and the same account data. Returns the result of the clone.
+fn assert_same_as_spl_token(
+    mollusk: &Mollusk,
+    instruction: impl Fn(&Pubkey) -> Instruction,
+    accounts: impl Fn(&Pubkey) -> Vec<(Pubkey, Account)>,
+) -> InstructionResult {
+    let res = mollusk.process_instruction(&instruction(&ID), &accounts(&ID));
+    let spl_res = mollusk.process_instruction(&instruction(&TOKEN_ID), &accounts(&TOKEN_ID));
+
+    assert_eq!(res.program_result, spl_res.program_result);
+    for ((key, account), (spl_key, spl_account)) in res
+        .resulting_accounts
+        .iter()
+        .zip(spl_res.resulting_accounts.iter())
+    {
+        assert_eq!(key, spl_key);
+        assert_eq!(account.lamports, spl_account.lamports, "account {key}");
+        assert_eq!(account.data, spl_account.data, "account {key}");
+    }
+    res
+}
+
+#[test]
+fn test_mini_token_initialize() {
+    let mollusk = mollusk();
+    let rent_sysvar = mollusk.sysvars.keyed_account_for_rent_sysvar();
+
+    let mint = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let freeze_authority = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+
+    for freeze_authority in [None, Some(&freeze_authority)] {
+        let res = assert_same_as_spl_token(
+            &mollusk,
+            |program_id| {
+                instruction_initialize_mint(program_id, &mint, &mint_authority, freeze_authority, 9)
+            },
+            |program_id| {
+                vec![
+                    (mint, uninitialized_account(&mollusk, program_id, Mint::LEN)),
+                    rent_sysvar.clone(),
+                ]
+            },
+        );
+        assert!(matches!(res.program_result, ProgramResult::Success));
+
+        let state = Mint::unpack(&res.get_account(&mint).unwrap().data).unwrap();
+        assert_eq!(state.mint_authority, COption::Some(mint_authority));
+        assert_eq!(state.freeze_authority, freeze_authority.copied().into());
+        assert_eq!(state.decimals, 9);
+    }
+
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_initialize_account(program_id, &account, &mint, &owner),
+        |program_id| {
+            vec![
+                (
+                    account,
+                    uninitialized_account(&mollusk, program_id, TokenAccount::LEN),
+                ),
+                (mint, mint_account(&mollusk, program_id, 0)),
+                (owner, Account::default()),
+                rent_sysvar.clone(),
+            ]
+        },
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+
+    let state = TokenAccount::unpack(&res.get_account(&account).unwrap().data).unwrap();
+    assert_eq!(state.mint, mint);
+    assert_eq!(state.owner, owner);
+    assert_eq!(state.amount, 0);
+    assert_eq!(state.state, TokenAccountState::Initialized);
+}
+
+#[test]
+fn test_mini_token_initialize_errors() {
+    let mollusk = mollusk();
+    let rent_sysvar = mollusk.sysvars.keyed_account_for_rent_sysvar();
+
+    let mint = Pubkey::new_unique();
+    let mint_authority = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+
+    // Initialized mint.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_initialize_mint(program_id, &mint, &mint_authority, None, 9),
+        |program_id| {
+            vec![
+                (mint, mint_account(&mollusk, program_id, 0)),
+                rent_sysvar.clone(),
+            ]
+        },
+    );
+    assert_eq!(
+        res.program_result,
+        ProgramResult::Failure(ProgramError::Custom(TokenError::AlreadyInUse as u32))
+    );
+
+    // Mint below the rent-exempt minimum.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_initialize_mint(program_id, &mint, &mint_authority, None, 9),
+        |program_id| {
+            let mut account = uninitialized_account(&mollusk, program_id, Mint::LEN);
+            account.lamports -= 1;
+            vec![(mint, account), rent_sysvar.clone()]
+        },
+    );
+    assert_eq!(
+        res.program_result,
+        ProgramResult::Failure(ProgramError::Custom(TokenError::NotRentExempt as u32))
+    );
+
+    // Mint of a wrong size.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_initialize_mint(program_id, &mint, &mint_authority, None, 9),
+        |program_id| {
+            vec![
+                (
+                    mint,
+                    uninitialized_account(&mollusk, program_id, Mint::LEN + 1),
+                ),
+                rent_sysvar.clone(),
+            ]
+        },
+    );
+    assert_eq!(
+        res.program_result,
+        ProgramResult::Failure(ProgramError::InvalidAccountData)
+    );
+
+    // Token account of an uninitialized mint.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_initialize_account(program_id, &account, &mint, &owner),
+        |program_id| {
+            vec![
+                (
+                    account,
+                    uninitialized_account(&mollusk, program_id, TokenAccount::LEN),
+                ),
+                (mint, uninitialized_account(&mollusk, program_id, Mint::LEN)),
+                (owner, Account::default()),
+                rent_sysvar.clone(),
+            ]
+        },
+    );
+    assert_eq!(
+        res.program_result,
+        ProgramResult::Failure(ProgramError::Custom(TokenError::InvalidMint as u32))
+    );
+
+    // Initialized token account.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_initialize_account(program_id, &account, &mint, &owner),
+        |program_id| {
+            vec![
+                (
+                    account,
+                    token_account(&mollusk, program_id, &mint, &owner, 0),
+                ),
+                (mint, mint_account(&mollusk, program_id, 0)),
+                (owner, Account::default()),
+                rent_sysvar.clone(),
+            ]
+        },
+    );
+    assert_eq!(
+        res.program_result,
+        ProgramResult::Failure(ProgramError::Custom(TokenError::AlreadyInUse as u32))
+    );
+}
+
+#[test]
+fn test_mini_token_transfer_and_burn() {
+    let mollusk = mollusk();
+
+    let mint = Pubkey::new_unique();
+    let alice = Pubkey::new_unique();
+    let alice_account = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let bob_account = Pubkey::new_unique();
+
+    let accounts = |program_id: &Pubkey| {
+        vec![
+            (
+                alice_account,
+                token_account(&mollusk, program_id, &mint, &alice, 600),
+            ),
+            (
+                bob_account,
+                token_account(&mollusk, program_id, &mint, &bob, 400),
+            ),
+            (mint, mint_account(&mollusk, program_id, 1_000)),
+            (alice, Account::default()),
+            (bob, Account::default()),
+        ]
+    };
+
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_transfer(program_id, &alice_account, &bob_account, &alice, 100),
+        accounts,
+    );
+    let alice_state = TokenAccount::unpack(&res.get_account(&alice_account).unwrap().data).unwrap();
+    let bob_state = TokenAccount::unpack(&res.get_account(&bob_account).unwrap().data).unwrap();
+    assert_eq!(alice_state.amount, 500);
+    assert_eq!(bob_state.amount, 500);
+
+    // Transferring to self succeeds without changes.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_transfer(program_id, &alice_account, &alice_account, &alice, 100),
+        accounts,
+    );
+    let alice_state = TokenAccount::unpack(&res.get_account(&alice_account).unwrap().data).unwrap();
+    assert_eq!(alice_state.amount, 600);
+
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_burn(program_id, &bob_account, &mint, &bob, 150),
+        accounts,
+    );
+    let bob_state = TokenAccount::unpack(&res.get_account(&bob_account).unwrap().data).unwrap();
+    let mint_state = Mint::unpack(&res.get_account(&mint).unwrap().data).unwrap();
+    assert_eq!(bob_state.amount, 250);
+    assert_eq!(mint_state.supply, 850);
+}
+
+#[test]
+fn test_mini_token_delegate() {
+    let mollusk = mollusk();
+
+    let mint = Pubkey::new_unique();
+    let alice = Pubkey::new_unique();
+    let alice_account = Pubkey::new_unique();
+    let bob_account = Pubkey::new_unique();
+    let delegate = Pubkey::new_unique();
+
+    let accounts = |program_id: &Pubkey| {
+        vec![
+            (
+                alice_account,
+                token_account_with(
+                    &mollusk,
+                    program_id,
+                    TokenAccount {
+                        mint,
+                        owner: alice,
+                        amount: 600,
+                        delegate: COption::Some(delegate),
+                        state: TokenAccountState::Initialized,
+                        is_native: COption::None,
+                        delegated_amount: 100,
+                        close_authority: COption::None,
+                    },
+                ),
+            ),
+            (
+                bob_account,
+                token_account(&mollusk, program_id, &mint, &Pubkey::new_unique(), 0),
+            ),
+            (mint, mint_account(&mollusk, program_id, 600)),
+            (delegate, Account::default()),
+        ]
+    };
+
+    // Spending a part of the allowance keeps the delegate.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_transfer(program_id, &alice_account, &bob_account, &delegate, 60),
+        accounts,
+    );
+    let state = TokenAccount::unpack(&res.get_account(&alice_account).unwrap().data).unwrap();
+    assert_eq!(state.delegate, COption::Some(delegate));
+    assert_eq!(state.delegated_amount, 40);
+
+    // Spending all of it removes the delegate.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_burn(program_id, &alice_account, &mint, &delegate, 100),
+        accounts,
+    );
+    let state = TokenAccount::unpack(&res.get_account(&alice_account).unwrap().data).unwrap();
+    assert_eq!(state.delegate, COption::None);
+    assert_eq!(state.delegated_amount, 0);
+    assert_eq!(state.amount, 500);
+
+    // The allowance is exceeded.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| instruction_transfer(program_id, &alice_account, &bob_account, &delegate, 101),
+        accounts,
+    );
+    assert_eq!(
+        res.program_result,
+        ProgramResult::Failure(ProgramError::Custom(TokenError::InsufficientFunds as u32))
+    );
+}
+
+#[test]
+fn test_mini_token_transfer_errors() {
+    let mollusk = mollusk();
+
+    let mint = Pubkey::new_unique();
+    let other_mint = Pubkey::new_unique();
+    let alice = Pubkey::new_unique();
+    let alice_account = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let bob_account = Pubkey::new_unique();
+    let carol_account = Pubkey::new_unique();
+    let frozen_account = Pubkey::new_unique();
+
+    let accounts = |program_id: &Pubkey| {
+        vec![
+            (
+                alice_account,
+                token_account(&mollusk, program_id, &mint, &alice, 100),
+            ),
+            (
+                bob_account,
+                token_account(&mollusk, program_id, &mint, &bob, 0),
+            ),
+            (
+                carol_account,
+                token_account(&mollusk, program_id, &other_mint, &bob, 0),
+            ),
+            (
+                frozen_account,
+                token_account_with(
+                    &mollusk,
+                    program_id,
+                    TokenAccount {
+                        mint,
+                        owner: bob,
+                        amount: 0,
+                        delegate: COption::None,
+                        state: TokenAccountState::Frozen,
+                        is_native: COption::None,
+                        delegated_amount: 0,
+                        close_authority: COption::None,
+                    },
+                ),
+            ),
+            (mint, mint_account(&mollusk, program_id, 100)),
+            (other_mint, mint_account(&mollusk, program_id, 0)),
+            (alice, Account::default()),
+            (bob, Account::default()),
+        ]
+    };
+
+    let cases = [
+        (
+            instruction_transfer as fn(&Pubkey, &Pubkey, &Pubkey, &Pubkey, u64) -> Instruction,
+            (alice_account, bob_account, alice, 101),
+            ProgramError::Custom(TokenError::InsufficientFunds as u32),
+        ),
+        (
+            instruction_transfer,
+            (alice_account, bob_account, bob, 1),
+            ProgramError::Custom(TokenError::OwnerMismatch as u32),
+        ),
+        (
+            instruction_transfer,
+            (alice_account, carol_account, alice, 1),
+            ProgramError::Custom(TokenError::MintMismatch as u32),
+        ),
+        (
+            instruction_transfer,
+            (alice_account, frozen_account, alice, 1),
+            ProgramError::Custom(TokenError::AccountFrozen as u32),
+        ),
+        (
+            instruction_burn,
+            (alice_account, mint, alice, 101),
+            ProgramError::Custom(TokenError::InsufficientFunds as u32),
+        ),
+        (
+            instruction_burn,
+            (alice_account, other_mint, alice, 1),
+            ProgramError::Custom(TokenError::MintMismatch as u32),
+        ),
+        (
+            instruction_burn,
+            (frozen_account, mint, bob, 0),
+            ProgramError::Custom(TokenError::AccountFrozen as u32),
+        ),
+    ];
+    for (instruction, (a, b, authority, amount), expected) in cases {
+        let res = assert_same_as_spl_token(
+            &mollusk,
+            |program_id| instruction(program_id, &a, &b, &authority, amount),
+            accounts,
+        );
+        assert_eq!(res.program_result, ProgramResult::Failure(expected));
+    }
+
+    // The authority doesn't sign.
+    let res = assert_same_as_spl_token(
+        &mollusk,
+        |program_id| {
+            let mut instruction =
+                instruction_transfer(program_id, &alice_account, &bob_account, &alice, 1);
+            instruction.accounts[2].is_signer = false;
+            instruction
+        },
+        accounts,
+    );
+    assert_eq!(
+        res.program_result,
+        ProgramResult::Failure(ProgramError::MissingRequiredSignature)
+    );
+}
+
+/// Prints the compute units consumed by the clone and SPL Token. Run with
+/// `--nocapture` to see the results.
+#[test]
+fn bench_mini_token_compute_units() {
+    let mollusk = mollusk();
+    let rent_sysvar = mollusk.sysvars.keyed_account_for_rent_sysvar();
+
+    let mint = Pubkey::new_unique();
+    let alice = Pubkey::new_unique();
+    let alice_account = Pubkey::new_unique();
+    let bob_account = Pubkey::new_unique();
+
+    let instruction = |name: &str, program_id: &Pubkey| match name {
+        "initialize_mint" => instruction_initialize_mint(program_id, &mint, &alice, None, 6),
+        "initialize_account" => {
+            instruction_initialize_account(program_id, &bob_account, &mint, &alice)
+        }
+        "transfer" => instruction_transfer(program_id, &alice_account, &bob_account, &alice, 1),
+        "burn" => instruction_burn(program_id, &alice_account, &mint, &alice, 1),
+        _ => unreachable!(),
+    };
+
+    println!(
+        "| {:<20} | {:>10} | {:>10} |",
+        "case", "mini-token", "spl-token"
+    );
+    for name in ["initialize_mint", "initialize_account", "transfer", "burn"] {
+        let compute_units = [ID, TOKEN_ID].map(|program_id| {
+            let mint_account = if name == "initialize_mint" {
+                uninitialized_account(&mollusk, &program_id, Mint::LEN)
+            } else {
+                mint_account(&mollusk, &program_id, 100)
+            };
+            let bob_state = if name == "initialize_account" {
+                uninitialized_account(&mollusk, &program_id, TokenAccount::LEN)
+            } else {
+                token_account(&mollusk, &program_id, &mint, &alice, 0)
+            };
+            let res = mollusk.process_and_validate_instruction(
+                &instruction(name, &program_id),
+                &[
+                    (
+                        alice_account,
+                        token_account(&mollusk, &program_id, &mint, &alice, 100),
+                    ),
+                    (bob_account, bob_state),
+                    (mint, mint_account),
+                    (alice, Account::default()),
+                    rent_sysvar.clone(),
+                ],
+                &[Check::success()],
+            );
+            res.compute_units_consumed
+        });
+        println!(
+            "| {:<20} | {:>10} | {:>10} |",
+            name, compute_units[0], compute_units[1]
+        );
+    }
+}
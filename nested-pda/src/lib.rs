@@ -0,0 +1,367 @@
+//! PDAs derived from other PDAs.
+//!
+//! An admin has a treasury PDA, which hands out allowances to members. The
+//! allowance PDA of a member is derived from the key of the treasury, so
+//! every treasury has its own set of allowances, and the program can find
+//! the treasury of an allowance without storing it anywhere:
+//!
+//! ```text
+//! treasury  = ["treasury", admin]
+//! allowance = ["allowance", treasury, member]
+//! ```
+//!
+//! Both PDAs are plain system accounts holding lamports, so moving lamports
+//! out of them requires their signature. The program signs for the
+//! treasury when it pays for or funds an allowance, and for an allowance
+//! when its member spends from it. Creating an allowance needs both
+//! signatures in a single CPI.
+
+#![no_std]
+
+use common::{checks::require_signer, info, keys_eq};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::{CreateAccount, Transfer};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("Gk2idR1ekNYKNuEFEwfJAEzjr978Dt9ZjEmbsqRXLG3G");
+
+pub const TREASURY_SEED: &str = "treasury";
+pub const ALLOWANCE_SEED: &str = "allowance";
+
+/// Nested PDA program instruction discriminators.
+#[repr(u8)]
+pub enum NestedPdaInstruction {
+    /// Creates the treasury of an admin.
+    CreateTreasury,
+    /// Creates an allowance of a member, paid by the treasury.
+    CreateAllowance,
+    /// Moves lamports from the treasury to an allowance.
+    Fund,
+    /// Moves lamports from an allowance to a recipient.
+    Spend,
+}
+
+impl TryFrom<&u8> for NestedPdaInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::CreateTreasury),
+            1 => Ok(Self::CreateAllowance),
+            2 => Ok(Self::Fund),
+            3 => Ok(Self::Spend),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct CreateTreasuryInstructionData {
+    pub treasury_bump: u8,
+}
+
+impl CreateTreasuryInstructionData {
+    pub fn new(treasury_bump: u8) -> Self {
+        Self { treasury_bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct CreateAllowanceInstructionData {
+    pub treasury_bump: u8,
+    pub allowance_bump: u8,
+}
+
+impl CreateAllowanceInstructionData {
+    pub fn new(treasury_bump: u8, allowance_bump: u8) -> Self {
+        Self {
+            treasury_bump,
+            allowance_bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct FundInstructionData {
+    pub lamports: u64,
+    pub treasury_bump: u8,
+    pub allowance_bump: u8,
+}
+
+impl FundInstructionData {
+    pub fn new(lamports: u64, treasury_bump: u8, allowance_bump: u8) -> Self {
+        Self {
+            lamports,
+            treasury_bump,
+            allowance_bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct SpendInstructionData {
+    pub lamports: u64,
+    pub allowance_bump: u8,
+}
+
+impl SpendInstructionData {
+    pub fn new(lamports: u64, allowance_bump: u8) -> Self {
+        Self {
+            lamports,
+            allowance_bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = NestedPdaInstruction::try_from(instruction)?;
+
+    match instruction {
+        NestedPdaInstruction::CreateTreasury => process_create_treasury(accounts, instruction_data),
+        NestedPdaInstruction::CreateAllowance => {
+            process_create_allowance(accounts, instruction_data)
+        }
+        NestedPdaInstruction::Fund => process_fund(accounts, instruction_data),
+        NestedPdaInstruction::Spend => process_spend(accounts, instruction_data),
+    }
+}
+
+/// Creates the treasury of `admin` as an empty system account. Anyone can
+/// send lamports to it afterwards.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[WRITE]` Treasury PDA
+///   2. `[]` System program
+pub fn process_create_treasury(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, treasury, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CreateTreasuryInstructionData::from_bytes(instruction_data)?;
+
+    validate_treasury(admin, treasury, instruction_data.treasury_bump)?;
+
+    let treasury_bump = [instruction_data.treasury_bump];
+    let treasury_seeds = [
+        Seed::from(TREASURY_SEED.as_bytes()),
+        Seed::from(admin.key()),
+        Seed::from(&treasury_bump),
+    ];
+    CreateAccount {
+        from: admin,
+        to: treasury,
+        lamports: Rent::get()?.minimum_balance(0),
+        space: 0,
+        owner: &pinocchio_system::ID,
+    }
+    .invoke_signed(&[Signer::from(&treasury_seeds)])?;
+
+    info!("Created the treasury");
+
+    Ok(())
+}
+
+/// Creates the allowance of `member` in `treasury`, which pays for it.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Admin
+///   1. `[WRITE]` Treasury PDA
+///   2. `[]` Member
+///   3. `[WRITE]` Allowance PDA
+///   4. `[]` System program
+pub fn process_create_allowance(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, treasury, member, allowance, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CreateAllowanceInstructionData::from_bytes(instruction_data)?;
+
+    validate_treasury(admin, treasury, instruction_data.treasury_bump)?;
+    validate_allowance(treasury, member, allowance, instruction_data.allowance_bump)?;
+
+    // The treasury signs as the payer, the allowance as the new account.
+    let treasury_bump = [instruction_data.treasury_bump];
+    let treasury_seeds = [
+        Seed::from(TREASURY_SEED.as_bytes()),
+        Seed::from(admin.key()),
+        Seed::from(&treasury_bump),
+    ];
+    let allowance_bump = [instruction_data.allowance_bump];
+    let allowance_seeds = [
+        Seed::from(ALLOWANCE_SEED.as_bytes()),
+        Seed::from(treasury.key()),
+        Seed::from(member.key()),
+        Seed::from(&allowance_bump),
+    ];
+    CreateAccount {
+        from: treasury,
+        to: allowance,
+        lamports: Rent::get()?.minimum_balance(0),
+        space: 0,
+        owner: &pinocchio_system::ID,
+    }
+    .invoke_signed(&[
+        Signer::from(&treasury_seeds),
+        Signer::from(&allowance_seeds),
+    ])?;
+
+    info!("Created the allowance");
+
+    Ok(())
+}
+
+/// Moves `lamports` from `treasury` to the allowance of `member`.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Admin
+///   1. `[WRITE]` Treasury PDA
+///   2. `[]` Member
+///   3. `[WRITE]` Allowance PDA
+///   4. `[]` System program
+pub fn process_fund(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, treasury, member, allowance, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = FundInstructionData::from_bytes(instruction_data)?;
+
+    validate_treasury(admin, treasury, instruction_data.treasury_bump)?;
+    validate_allowance(treasury, member, allowance, instruction_data.allowance_bump)?;
+
+    let treasury_bump = [instruction_data.treasury_bump];
+    let treasury_seeds = [
+        Seed::from(TREASURY_SEED.as_bytes()),
+        Seed::from(admin.key()),
+        Seed::from(&treasury_bump),
+    ];
+    Transfer {
+        from: treasury,
+        to: allowance,
+        lamports: instruction_data.lamports,
+    }
+    .invoke_signed(&[Signer::from(&treasury_seeds)])?;
+
+    info!(
+        "Funded the allowance with {} lamports",
+        instruction_data.lamports
+    );
+
+    Ok(())
+}
+
+/// Moves `lamports` from the allowance of `member` to `recipient`.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Member
+///   1. `[]` Treasury PDA
+///   2. `[WRITE]` Allowance PDA
+///   3. `[WRITE]` Recipient
+///   4. `[]` System program
+pub fn process_spend(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [member, treasury, allowance, recipient, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(member)?;
+
+    // Deserialize instruction data.
+    let instruction_data = SpendInstructionData::from_bytes(instruction_data)?;
+
+    // The allowance seeds tie it to the treasury, no need to check the
+    // treasury itself.
+    validate_allowance(treasury, member, allowance, instruction_data.allowance_bump)?;
+
+    let allowance_bump = [instruction_data.allowance_bump];
+    let allowance_seeds = [
+        Seed::from(ALLOWANCE_SEED.as_bytes()),
+        Seed::from(treasury.key()),
+        Seed::from(member.key()),
+        Seed::from(&allowance_bump),
+    ];
+    Transfer {
+        from: allowance,
+        to: recipient,
+        lamports: instruction_data.lamports,
+    }
+    .invoke_signed(&[Signer::from(&allowance_seeds)])?;
+
+    info!("Spent {} lamports", instruction_data.lamports);
+
+    Ok(())
+}
+
+/// Checks that `treasury` is the treasury PDA of `admin`.
+fn validate_treasury(admin: &AccountInfo, treasury: &AccountInfo, bump: u8) -> ProgramResult {
+    let treasury_pda =
+        create_program_address(&[TREASURY_SEED.as_bytes(), admin.key(), &[bump]], &ID)?;
+    if !keys_eq(treasury.key(), &treasury_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Checks that `allowance` is the allowance PDA of `member` in `treasury`.
+fn validate_allowance(
+    treasury: &AccountInfo,
+    member: &AccountInfo,
+    allowance: &AccountInfo,
+    bump: u8,
+) -> ProgramResult {
+    let allowance_pda = create_program_address(
+        &[
+            ALLOWANCE_SEED.as_bytes(),
+            treasury.key(),
+            member.key(),
+            &[bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(allowance.key(), &allowance_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
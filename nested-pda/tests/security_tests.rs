@@ -0,0 +1,182 @@
+//! Attacks on the nested PDA program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use nested_pda::{
+    CreateAllowanceInstructionData, CreateTreasuryInstructionData, NestedPdaInstruction,
+    SpendInstructionData, ALLOWANCE_SEED, TREASURY_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(nested_pda::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: NestedPdaInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<NestedPdaInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the treasury PDA of `admin` and its bump.
+fn treasury_pda(admin: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TREASURY_SEED.as_bytes(), admin.as_ref()], &ID)
+}
+
+/// Returns the allowance PDA of `member` in `treasury` and its bump.
+fn allowance_pda(treasury: &Pubkey, member: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            ALLOWANCE_SEED.as_bytes(),
+            treasury.as_ref(),
+            member.as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// Accounts of an admin with a funded treasury, of a member with a funded
+/// allowance in it, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    treasury: Pubkey,
+    member: Pubkey,
+    allowance: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/nested_pda");
+
+        let admin = Pubkey::new_unique();
+        let (treasury, _) = treasury_pda(&admin);
+        let member = Pubkey::new_unique();
+        let (allowance, _) = allowance_pda(&treasury, &member);
+        let attacker = Pubkey::new_unique();
+        let rent = mollusk.sysvars.rent.minimum_balance(0);
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (
+                treasury,
+                Account::new(rent + 10 * LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                member,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                allowance,
+                Account::new(rent + LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            treasury,
+            member,
+            allowance,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_member_signature() {
+    let setup = Setup::new();
+    let (_, bump) = allowance_pda(&setup.treasury, &setup.member);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.member, false),
+        AccountMeta::new_readonly(setup.treasury, false),
+        AccountMeta::new(setup.allowance, false),
+        AccountMeta::new(setup.attacker, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            NestedPdaInstruction::Spend,
+            &SpendInstructionData::new(LAMPORTS_PER_SOL, bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the treasury of the admin before the admin does.
+#[test]
+fn test_create_treasury_of_other_admin() {
+    let setup = Setup::new();
+    let treasury = setup.treasury;
+    let setup = setup.with(treasury, Account::default());
+    let (_, bump) = treasury_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(treasury, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            NestedPdaInstruction::CreateTreasury,
+            &CreateTreasuryInstructionData::new(bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker creates an allowance of their own in the treasury of the
+/// admin, paid by the treasury, to be funded from it later.
+#[test]
+fn test_create_allowance_in_other_treasury() {
+    let setup = Setup::new();
+    let (allowance, allowance_bump) = allowance_pda(&setup.treasury, &setup.attacker);
+    let setup = setup.with(allowance, Account::default());
+    let (_, treasury_bump) = treasury_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.attacker, true),
+        AccountMeta::new(setup.treasury, false),
+        AccountMeta::new_readonly(setup.attacker, false),
+        AccountMeta::new(allowance, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            NestedPdaInstruction::CreateAllowance,
+            &CreateAllowanceInstructionData::new(treasury_bump, allowance_bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
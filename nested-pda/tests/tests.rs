@@ -0,0 +1,280 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use nested_pda::{
+    CreateAllowanceInstructionData, CreateTreasuryInstructionData, FundInstructionData,
+    NestedPdaInstruction, SpendInstructionData, ALLOWANCE_SEED, TREASURY_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(nested_pda::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: NestedPdaInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<NestedPdaInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+/// An admin with a treasury and a member with an allowance.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    treasury: Pubkey,
+    treasury_bump: u8,
+    member: Pubkey,
+    allowance: Pubkey,
+    allowance_bump: u8,
+    recipient: Pubkey,
+    /// Rent-exempt minimum of the PDAs.
+    rent: u64,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/nested_pda");
+
+        let admin = Pubkey::new_unique();
+        let (treasury, treasury_bump) =
+            Pubkey::find_program_address(&[TREASURY_SEED.as_bytes(), admin.as_ref()], &ID);
+        let member = Pubkey::new_unique();
+        // The allowance is derived from the treasury PDA.
+        let (allowance, allowance_bump) = Pubkey::find_program_address(
+            &[
+                ALLOWANCE_SEED.as_bytes(),
+                treasury.as_ref(),
+                member.as_ref(),
+            ],
+            &ID,
+        );
+        let recipient = Pubkey::new_unique();
+        let rent = mollusk.sysvars.rent.minimum_balance(0);
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (
+                treasury,
+                Account::new(rent + 10 * LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (member, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (
+                allowance,
+                Account::new(rent + LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (recipient, Account::new(0, 0, &system_program.0)),
+            system_program,
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            treasury,
+            treasury_bump,
+            member,
+            allowance,
+            allowance_bump,
+            recipient,
+            rent,
+            accounts,
+        }
+    }
+
+    fn instruction_create_treasury(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.treasury, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                NestedPdaInstruction::CreateTreasury,
+                &CreateTreasuryInstructionData::new(self.treasury_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_create_allowance(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new(self.treasury, false),
+            AccountMeta::new_readonly(self.member, false),
+            AccountMeta::new(self.allowance, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                NestedPdaInstruction::CreateAllowance,
+                &CreateAllowanceInstructionData::new(self.treasury_bump, self.allowance_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_fund(&self, lamports: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new(self.treasury, false),
+            AccountMeta::new_readonly(self.member, false),
+            AccountMeta::new(self.allowance, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                NestedPdaInstruction::Fund,
+                &FundInstructionData::new(lamports, self.treasury_bump, self.allowance_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_spend(&self, lamports: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.member, true),
+            AccountMeta::new_readonly(self.treasury, false),
+            AccountMeta::new(self.allowance, false),
+            AccountMeta::new(self.recipient, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                NestedPdaInstruction::Spend,
+                &SpendInstructionData::new(lamports, self.allowance_bump),
+            ),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_create() {
+    let mut setup = Setup::new();
+    // Both PDAs are created by the program.
+    setup.accounts[1].1 = Account::default();
+    setup.accounts[3].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_create_treasury(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.treasury)
+                .lamports(setup.rent)
+                .space(0)
+                .build(),
+            Check::account(&setup.admin)
+                .lamports(LAMPORTS_PER_SOL - setup.rent)
+                .build(),
+        ],
+    );
+
+    // The treasury pays for the allowance.
+    setup.accounts[1].1.lamports = setup.rent + LAMPORTS_PER_SOL;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_create_allowance(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.allowance)
+                .lamports(setup.rent)
+                .space(0)
+                .build(),
+            Check::account(&setup.treasury)
+                .lamports(LAMPORTS_PER_SOL)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_fund_and_spend() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_fund(2 * LAMPORTS_PER_SOL),
+                &[
+                    Check::success(),
+                    Check::account(&setup.treasury)
+                        .lamports(setup.rent + 8 * LAMPORTS_PER_SOL)
+                        .build(),
+                    Check::account(&setup.allowance)
+                        .lamports(setup.rent + 3 * LAMPORTS_PER_SOL)
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_spend(LAMPORTS_PER_SOL),
+                &[
+                    Check::success(),
+                    Check::account(&setup.allowance)
+                        .lamports(setup.rent + 2 * LAMPORTS_PER_SOL)
+                        .build(),
+                    Check::account(&setup.recipient)
+                        .lamports(LAMPORTS_PER_SOL)
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_invalid_signers() {
+    let setup = Setup::new();
+    let other = setup.recipient;
+
+    // Only the admin can move lamports out of the treasury.
+    let mut ix = setup.instruction_fund(LAMPORTS_PER_SOL);
+    ix.accounts[0].pubkey = other;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+
+    // Only the member can spend from their allowance.
+    let mut ix = setup.instruction_spend(LAMPORTS_PER_SOL);
+    ix.accounts[0].pubkey = other;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+
+    // An allowance can't be spent through another treasury.
+    let mut ix = setup.instruction_spend(LAMPORTS_PER_SOL);
+    ix.accounts[1].pubkey = setup.admin;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
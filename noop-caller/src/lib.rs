@@ -0,0 +1,90 @@
+//! Example of emitting events through the noop program.
+
+#![no_std]
+
+use common::{checks::require_signer, info, keys_eq};
+use errors::Namespace;
+use pinocchio::{
+    account_info::AccountInfo, cpi::invoke, instruction::Instruction, program_error::ProgramError,
+    pubkey::Pubkey, ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("9TShnBpR3a3d7WHJ3uzZBtBivU1a8yv99NPvFVn5jQGW");
+
+/// ID of the program from the `noop` example.
+pub const NOOP_PROGRAM_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("4o3T9ZpyejfhzDBNFBLD4TrqH97u2mod5rdi7p8yavRr");
+
+/// Maximum length of a message.
+pub const MAX_MESSAGE_LEN: usize = 256;
+
+/// Errors returned by the noop caller program.
+#[repr(u32)]
+pub enum NoopCallerError {
+    /// The message is longer than [`MAX_MESSAGE_LEN`].
+    MessageTooLong,
+}
+
+impl From<NoopCallerError> for ProgramError {
+    fn from(e: NoopCallerError) -> Self {
+        Namespace::NoopCaller.error(e as u32)
+    }
+}
+
+/// Emits the message from the instruction data as an event, consisting of:
+///
+/// * sender
+/// * message
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Sender
+///   1. `[]` Noop program
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [sender, noop_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(sender)?;
+    if !keys_eq(noop_program.key(), &NOOP_PROGRAM_ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if instruction_data.len() > MAX_MESSAGE_LEN {
+        return Err(NoopCallerError::MessageTooLong.into());
+    }
+
+    // Build the event on the stack, there is no allocator.
+    let mut event = [0; 32 + MAX_MESSAGE_LEN];
+    let len = 32 + instruction_data.len();
+    event[..32].copy_from_slice(sender.key());
+    event[32..len].copy_from_slice(instruction_data);
+
+    invoke::<0>(
+        &Instruction {
+            program_id: noop_program.key(),
+            accounts: &[],
+            data: &event[..len],
+        },
+        &[],
+    )?;
+
+    info!("Emitted a message of {} bytes", instruction_data.len());
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use errors::Namespace;
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use noop_caller::{NoopCallerError, MAX_MESSAGE_LEN};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_log_collector::LogCollector;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(noop_caller::ID);
+const NOOP_ID: Pubkey = Pubkey::new_from_array(noop_caller::NOOP_PROGRAM_ID);
+
+fn instruction_emit(sender: &Pubkey, noop_program: &Pubkey, message: &[u8]) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*sender, true),
+        AccountMeta::new_readonly(*noop_program, false),
+    ];
+    Instruction::new_with_bytes(ID, message, ix_accounts)
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/noop_caller");
+    // Built with `cargo build-sbf` in the `noop` directory.
+    mollusk.add_program(&NOOP_ID, "../noop/target/deploy/noop", &LOADER_V3);
+    mollusk
+}
+
+#[test]
+fn test_noop_caller_emit() {
+    let mut mollusk = mollusk();
+    let logger = LogCollector::new_ref();
+    mollusk.logger = Some(logger.clone());
+
+    let sender = Pubkey::new_unique();
+    let message = b"gm";
+
+    mollusk.process_and_validate_instruction(
+        &instruction_emit(&sender, &NOOP_ID, message),
+        &[
+            (sender, Account::default()),
+            (NOOP_ID, create_program_account_loader_v3(&NOOP_ID)),
+        ],
+        &[Check::success()],
+    );
+
+    // The noop program logged the event.
+    let mut event = sender.to_bytes().to_vec();
+    event.extend_from_slice(message);
+    let expected = format!("Program data: {}", STANDARD.encode(&event));
+    assert!(logger
+        .borrow()
+        .get_recorded_content()
+        .iter()
+        .any(|log| log == &expected));
+}
+
+#[test]
+fn test_noop_caller_errors() {
+    let mollusk = mollusk();
+
+    let sender = Pubkey::new_unique();
+    let fake_noop = Pubkey::new_unique();
+
+    mollusk.process_and_validate_instruction(
+        &instruction_emit(&sender, &NOOP_ID, &[0; MAX_MESSAGE_LEN + 1]),
+        &[
+            (sender, Account::default()),
+            (NOOP_ID, create_program_account_loader_v3(&NOOP_ID)),
+        ],
+        &[Check::err(ProgramError::Custom(
+            Namespace::NoopCaller.code(NoopCallerError::MessageTooLong as u32),
+        ))],
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction_emit(&sender, &fake_noop, b"gm"),
+        &[
+            (sender, Account::default()),
+            (fake_noop, Account::default()),
+        ],
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+}
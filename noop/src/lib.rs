@@ -0,0 +1,31 @@
+//! Program which does nothing but log its instruction data.
+//!
+//! Programs emit events by invoking it, like they would with SPL Noop. The
+//! event ends up in the inner instructions of the transaction, which, unlike
+//! logs, are never truncated, so indexers can rely on them. The data is also
+//! logged with `sol_log_data`, which is handy when only logs are available,
+//! e.g. in tests.
+
+#![no_std]
+
+use pinocchio::{account_info::AccountInfo, log::sol_log_data, pubkey::Pubkey, ProgramResult};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+
+pinocchio_pubkey::declare_id!("4o3T9ZpyejfhzDBNFBLD4TrqH97u2mod5rdi7p8yavRr");
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    sol_log_data(&[instruction_data]);
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use mollusk_svm::{result::Check, Mollusk};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_log_collector::LogCollector;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(noop::ID);
+
+#[test]
+fn test_noop() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/noop");
+    let logger = LogCollector::new_ref();
+    mollusk.logger = Some(logger.clone());
+
+    let event = b"any bytes \x00\xff";
+    // Accounts are ignored.
+    let account = Pubkey::new_unique();
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(ID, event, vec![AccountMeta::new_readonly(account, false)]),
+        &[(account, Default::default())],
+        &[Check::success()],
+    );
+
+    let expected = format!("Program data: {}", STANDARD.encode(event));
+    assert!(logger
+        .borrow()
+        .get_recorded_content()
+        .iter()
+        .any(|log| log == &expected));
+}
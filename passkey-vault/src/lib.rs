@@ -0,0 +1,353 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{instructions::Instructions, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("54Qr2xvGm9RBoLpMv1nfA42bnf7wvYT9A5ad3WknvR7K");
+
+/// ID of the native secp256r1 signature verification precompile.
+pub const SECP256R1_PROGRAM_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("Secp256r1SigVerify1111111111111111111111111");
+
+pub const VAULT_SEED: &str = "vault";
+
+/// Length of a SEC1-compressed P-256 public key.
+pub const COMPRESSED_PUBKEY_LEN: usize = 33;
+/// Length of a P-256 signature (`r || s`).
+pub const SIGNATURE_LEN: usize = 64;
+/// Offset of the first `Secp256r1SignatureOffsets` in the precompile
+/// instruction data (after `num_signatures` and one padding byte).
+pub const SIGNATURE_OFFSETS_START: usize = 2;
+/// Size of a single serialized `Secp256r1SignatureOffsets`.
+pub const SIGNATURE_OFFSETS_LEN: usize = 14;
+/// Length of the message which has to be signed by the passkey to authorize
+/// a withdrawal.
+pub const WITHDRAW_MESSAGE_LEN: usize = 80;
+
+/// Errors returned by the passkey vault program.
+#[repr(u32)]
+pub enum PasskeyVaultError {
+    /// The instruction preceding `Withdraw` is not a secp256r1 verification.
+    MissingSignatureVerification,
+    /// The precompile instruction does not verify exactly one signature
+    /// stored in its own instruction data.
+    InvalidSignatureVerification,
+    /// The signature was produced by a different passkey.
+    PasskeyMismatch,
+    /// The signed message does not authorize this withdrawal.
+    MessageMismatch,
+}
+
+impl From<PasskeyVaultError> for ProgramError {
+    fn from(e: PasskeyVaultError) -> Self {
+        Namespace::PasskeyVault.error(e as u32)
+    }
+}
+
+/// On-chain representation of a passkey-controlled vault.
+#[repr(C)]
+pub struct Vault {
+    /// SEC1-compressed P-256 public key of the passkey.
+    pub pubkey: [u8; COMPRESSED_PUBKEY_LEN],
+    pub bump: u8,
+    pub _padding: [u8; 6],
+    /// Incremented on every withdrawal, included in the signed message to
+    /// prevent replays.
+    pub nonce: u64,
+}
+
+assert_layout!(Vault, size = 48, align = 8, { pubkey: 0, bump: 33, _padding: 34, nonce: 40 });
+
+impl Vault {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Passkey vault program instruction discriminators.
+#[repr(u8)]
+pub enum PasskeyVaultInstruction {
+    /// Creates a vault controlled by the given passkey and deposits lamports
+    /// into it.
+    Create,
+    /// Withdraws lamports from the vault, authorized by a passkey signature
+    /// verified by the secp256r1 precompile.
+    Withdraw,
+}
+
+impl TryFrom<&u8> for PasskeyVaultInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Create),
+            1 => Ok(Self::Withdraw),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct CreateInstructionData {
+    pub deposit: u64,
+    pub pubkey: [u8; COMPRESSED_PUBKEY_LEN],
+    pub bump: u8,
+}
+
+impl CreateInstructionData {
+    pub fn new(deposit: u64, pubkey: [u8; COMPRESSED_PUBKEY_LEN], bump: u8) -> Self {
+        Self {
+            deposit,
+            pubkey,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct WithdrawInstructionData {
+    pub amount: u64,
+}
+
+impl WithdrawInstructionData {
+    pub fn new(amount: u64) -> Self {
+        Self { amount }
+    }
+}
+
+/// Builds the message which the passkey has to sign to authorize
+/// a withdrawal:
+///
+/// * vault address (32 bytes)
+/// * destination address (32 bytes)
+/// * amount (u64, little-endian)
+/// * vault nonce (u64, little-endian)
+///
+/// Real WebAuthn authenticators sign `authenticatorData || sha256(clientDataJSON)`,
+/// where this message would be embedded as the challenge. The verification
+/// flow on-chain stays the same, only the message parsing differs.
+pub fn withdraw_message(
+    vault: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+    nonce: u64,
+) -> [u8; WITHDRAW_MESSAGE_LEN] {
+    let mut message = [0; WITHDRAW_MESSAGE_LEN];
+    message[0..32].copy_from_slice(vault);
+    message[32..64].copy_from_slice(destination);
+    message[64..72].copy_from_slice(&amount.to_le_bytes());
+    message[72..80].copy_from_slice(&nonce.to_le_bytes());
+    message
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = PasskeyVaultInstruction::try_from(instruction)?;
+
+    match instruction {
+        PasskeyVaultInstruction::Create => process_create(accounts, instruction_data),
+        PasskeyVaultInstruction::Withdraw => process_withdraw(accounts, instruction_data),
+    }
+}
+
+/// Creates a vault controlled by the given passkey.
+pub fn process_create(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [payer, vault, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(payer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CreateInstructionData::from_bytes(instruction_data)?;
+
+    // The vault address is derived from the passkey. A compressed key doesn't
+    // fit into a single seed (max 32 bytes), so the parity byte and the
+    // x-coordinate are passed as separate seeds.
+    let vault_pda = create_program_address(
+        &[
+            VAULT_SEED.as_bytes(),
+            &instruction_data.pubkey[..1],
+            &instruction_data.pubkey[1..],
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(vault.key(), &vault_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the vault PDA, funding it with the rent-exempt minimum plus the
+    // initial deposit.
+    let lamports = Rent::get()?
+        .minimum_balance(Vault::LEN)
+        .checked_add(instruction_data.deposit)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    CreateAccount {
+        from: payer,
+        to: vault,
+        lamports,
+        space: Vault::LEN as u64,
+        owner: &ID,
+    }
+    .invoke()?;
+
+    // Deserialize the vault PDA.
+    let mut data = vault.try_borrow_mut_data()?;
+    let data: &mut Vault = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the vault.
+    data.pubkey = instruction_data.pubkey;
+    data.bump = instruction_data.bump;
+    data.nonce = 0;
+
+    info!("Created vault with {} lamports", instruction_data.deposit);
+
+    Ok(())
+}
+
+/// Withdraws lamports from the vault.
+///
+/// The instruction directly preceding this one in the transaction has to be
+/// a secp256r1 precompile instruction verifying a signature of the vault's
+/// passkey over [`withdraw_message`]. The runtime rejects the whole
+/// transaction if the signature is invalid, so the program only needs to
+/// check *what* was verified, not the signature itself.
+pub fn process_withdraw(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [vault, destination, instructions_sysvar] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Check if the vault PDA is owned by the program.
+    require_owned_by(vault, &ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = WithdrawInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the vault PDA.
+    let mut data = vault.try_borrow_mut_data()?;
+    if data.len() != Vault::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data: &mut Vault = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Load the previous instruction. `Instructions::try_from` checks that the
+    // passed account is the instructions sysvar.
+    let instructions = Instructions::try_from(instructions_sysvar)?;
+    let verify_instruction = instructions
+        .get_instruction_relative(-1)
+        .map_err(|_| PasskeyVaultError::MissingSignatureVerification)?;
+    if verify_instruction.get_program_id() != &SECP256R1_PROGRAM_ID {
+        return Err(PasskeyVaultError::MissingSignatureVerification.into());
+    }
+
+    // Check which key and message were verified.
+    let (pubkey, message) = parse_secp256r1_instruction(verify_instruction.get_instruction_data())?;
+    if pubkey != data.pubkey {
+        return Err(PasskeyVaultError::PasskeyMismatch.into());
+    }
+    let expected_message = withdraw_message(
+        vault.key(),
+        destination.key(),
+        instruction_data.amount,
+        data.nonce,
+    );
+    if message != expected_message {
+        return Err(PasskeyVaultError::MessageMismatch.into());
+    }
+
+    // Bump the nonce, so the same signature can't be replayed.
+    data.nonce = data
+        .nonce
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // Move the lamports, keeping the vault rent-exempt.
+    let min_balance = Rent::get()?.minimum_balance(Vault::LEN);
+    let mut vault_lamports = vault.try_borrow_mut_lamports()?;
+    let available = vault_lamports.saturating_sub(min_balance);
+    if instruction_data.amount > available {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    let mut destination_lamports = destination.try_borrow_mut_lamports()?;
+    *vault_lamports -= instruction_data.amount;
+    *destination_lamports = destination_lamports
+        .checked_add(instruction_data.amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    info!("Withdrew {} lamports from vault", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Parses the data of a secp256r1 precompile instruction, returning the
+/// public key and the message of its only signature.
+///
+/// Only signatures whose key, signature and message are all embedded in the
+/// precompile instruction itself (instruction index `u16::MAX`) are accepted.
+/// Otherwise an attacker could point the offsets at data of a different
+/// instruction than the one we inspect.
+fn parse_secp256r1_instruction(data: &[u8]) -> Result<(&[u8], &[u8]), ProgramError> {
+    // Exactly one signature.
+    if data.first() != Some(&1) {
+        return Err(PasskeyVaultError::InvalidSignatureVerification.into());
+    }
+    let offsets = data
+        .get(SIGNATURE_OFFSETS_START..SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN)
+        .ok_or(PasskeyVaultError::InvalidSignatureVerification)?;
+    let read_u16 = |i: usize| u16::from_le_bytes([offsets[i], offsets[i + 1]]);
+
+    // Layout of `Secp256r1SignatureOffsets`.
+    let signature_instruction_index = read_u16(2);
+    let public_key_offset = read_u16(4) as usize;
+    let public_key_instruction_index = read_u16(6);
+    let message_data_offset = read_u16(8) as usize;
+    let message_data_size = read_u16(10) as usize;
+    let message_instruction_index = read_u16(12);
+
+    if signature_instruction_index != u16::MAX
+        || public_key_instruction_index != u16::MAX
+        || message_instruction_index != u16::MAX
+    {
+        return Err(PasskeyVaultError::InvalidSignatureVerification.into());
+    }
+
+    let pubkey = data
+        .get(public_key_offset..public_key_offset + COMPRESSED_PUBKEY_LEN)
+        .ok_or(PasskeyVaultError::InvalidSignatureVerification)?;
+    let message = data
+        .get(message_data_offset..message_data_offset + message_data_size)
+        .ok_or(PasskeyVaultError::InvalidSignatureVerification)?;
+
+    Ok((pubkey, message))
+}
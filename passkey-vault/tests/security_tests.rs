@@ -0,0 +1,298 @@
+//! Attacks on the passkey vault program, each rejected with its own error.
+//!
+//! The runtime verifies the secp256r1 precompile instructions before the
+//! transaction runs, so the withdrawals are processed on their own, with the
+//! verification only in the instructions sysvar.
+
+use std::mem;
+
+use errors::Namespace;
+use mollusk_svm::{result::Check, Mollusk};
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use passkey_vault::{
+    withdraw_message, PasskeyVaultError, PasskeyVaultInstruction, Vault, WithdrawInstructionData,
+    COMPRESSED_PUBKEY_LEN, VAULT_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, BorrowedAccountMeta, BorrowedInstruction, Instruction};
+use solana_instructions_sysvar::{construct_instructions_data, store_current_index_checked};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(passkey_vault::ID);
+const SECP256R1_ID: Pubkey = Pubkey::new_from_array(passkey_vault::SECP256R1_PROGRAM_ID);
+const INSTRUCTIONS_ID: Pubkey = solana_sdk_ids::sysvar::instructions::ID;
+
+const AMOUNT: u64 = LAMPORTS_PER_SOL;
+
+/// Offset of the nonce in a vault.
+const NONCE_OFFSET: usize = 40;
+
+fn instruction_withdraw(vault: &Pubkey, destination: &Pubkey) -> Instruction {
+    let data = WithdrawInstructionData::new(AMOUNT);
+    let mut data_with_discriminator = Vec::with_capacity(
+        mem::size_of::<PasskeyVaultInstruction>() + WithdrawInstructionData::LEN,
+    );
+    data_with_discriminator.push(PasskeyVaultInstruction::Withdraw as u8);
+    data_with_discriminator.extend_from_slice(&data.to_bytes());
+
+    let ix_accounts = vec![
+        AccountMeta::new(*vault, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(INSTRUCTIONS_ID, false),
+    ];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+/// Creates a secp256r1 precompile instruction verifying a signature of
+/// `signing_key` over `message`, with all the data embedded in the
+/// instruction itself.
+fn instruction_secp256r1(signing_key: &SigningKey, message: &[u8]) -> Instruction {
+    const DATA_START: u16 = 16;
+
+    let pubkey = signing_key.verifying_key().to_encoded_point(true);
+    let signature: Signature = signing_key.sign(message);
+    let signature = signature.normalize_s().unwrap_or(signature);
+
+    let public_key_offset = DATA_START;
+    let signature_offset = public_key_offset + COMPRESSED_PUBKEY_LEN as u16;
+    let message_data_offset = signature_offset + 64;
+
+    let mut data = vec![1, 0];
+    for value in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_data_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_bytes());
+    data.extend_from_slice(&signature.to_bytes());
+    data.extend_from_slice(message);
+
+    Instruction::new_with_bytes(SECP256R1_ID, &data, vec![])
+}
+
+/// Returns the data of the instructions sysvar for a transaction consisting
+/// of `instructions`, currently executing the last one.
+fn instructions_sysvar_data(instructions: &[&Instruction]) -> Vec<u8> {
+    let borrowed = instructions
+        .iter()
+        .map(|ix| BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: &ix.data,
+        })
+        .collect::<Vec<_>>();
+    let mut data = construct_instructions_data(&borrowed);
+    store_current_index_checked(&mut data, instructions.len() as u16 - 1).unwrap();
+    data
+}
+
+/// Accounts of a vault of a passkey holding 10 SOL, of which the passkey
+/// authorizes withdrawals to the destination.
+struct Setup {
+    mollusk: Mollusk,
+    signing_key: SigningKey,
+    vault: Pubkey,
+    destination: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/passkey_vault");
+
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let pubkey = signing_key.verifying_key().to_encoded_point(true);
+        let (vault, bump) = Pubkey::find_program_address(
+            &[
+                VAULT_SEED.as_bytes(),
+                &pubkey.as_bytes()[..1],
+                &pubkey.as_bytes()[1..],
+            ],
+            &ID,
+        );
+        let mut vault_account = Account::new(
+            mollusk.sysvars.rent.minimum_balance(Vault::LEN) + 10 * LAMPORTS_PER_SOL,
+            Vault::LEN,
+            &ID,
+        );
+        vault_account.data[..COMPRESSED_PUBKEY_LEN].copy_from_slice(pubkey.as_bytes());
+        vault_account.data[COMPRESSED_PUBKEY_LEN] = bump;
+
+        let destination = Pubkey::new_unique();
+        let accounts = vec![
+            (vault, vault_account),
+            (destination, Account::new(0, 0, &Pubkey::default())),
+        ];
+
+        Self {
+            mollusk,
+            signing_key,
+            vault,
+            destination,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    /// Returns the verification of a signature of the passkey authorizing
+    /// the withdrawal of [`AMOUNT`] to the destination at `nonce`.
+    fn instruction_verify(&self, nonce: u64) -> Instruction {
+        let message = withdraw_message(
+            self.vault.as_array(),
+            self.destination.as_array(),
+            AMOUNT,
+            nonce,
+        );
+        instruction_secp256r1(&self.signing_key, &message)
+    }
+
+    /// Processes `withdraw`, as the last instruction of a transaction
+    /// consisting of `instructions` and `withdraw`.
+    fn expect_err(&self, instructions: &[&Instruction], withdraw: &Instruction, err: ProgramError) {
+        let mut transaction = instructions.to_vec();
+        transaction.push(withdraw);
+        let mut sysvar_account = Account::new(1, 0, &solana_sdk_ids::sysvar::ID);
+        sysvar_account.data = instructions_sysvar_data(&transaction);
+
+        let mut accounts = self.accounts.clone();
+        accounts.push((withdraw.accounts[2].pubkey, sysvar_account));
+        self.mollusk
+            .process_and_validate_instruction(withdraw, &accounts, &[Check::err(err)]);
+    }
+}
+
+fn passkey_vault_error(err: PasskeyVaultError) -> ProgramError {
+    ProgramError::Custom(Namespace::PasskeyVault.code(err as u32))
+}
+
+/// A signature which authorized an earlier withdrawal is replayed.
+#[test]
+fn test_replayed_signature() {
+    let setup = Setup::new();
+    let vault = setup.vault;
+    let mut vault_account = setup.account(&vault);
+    vault_account.data[NONCE_OFFSET..].copy_from_slice(&1u64.to_le_bytes());
+    let setup = setup.with(vault, vault_account);
+    setup.expect_err(
+        &[&setup.instruction_verify(0)],
+        &instruction_withdraw(&setup.vault, &setup.destination),
+        passkey_vault_error(PasskeyVaultError::MessageMismatch),
+    );
+}
+
+/// A signature authorizing a withdrawal to the destination is used for
+/// a withdrawal to the attacker.
+#[test]
+fn test_redirected_withdrawal() {
+    let setup = Setup::new();
+    let attacker = Pubkey::new_unique();
+    let setup = setup.with(attacker, Account::new(0, 0, &Pubkey::default()));
+    setup.expect_err(
+        &[&setup.instruction_verify(0)],
+        &instruction_withdraw(&setup.vault, &attacker),
+        passkey_vault_error(PasskeyVaultError::MessageMismatch),
+    );
+}
+
+/// A vault with the layout of the program, but owned by another one.
+#[test]
+fn test_spoofed_vault() {
+    let setup = Setup::new();
+    let vault = setup.vault;
+    let mut vault_account = setup.account(&vault);
+    vault_account.owner = Pubkey::new_unique();
+    let setup = setup.with(vault, vault_account);
+    setup.expect_err(
+        &[&setup.instruction_verify(0)],
+        &instruction_withdraw(&setup.vault, &setup.destination),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// An account with the layout of the instructions sysvar, listing
+/// a verification which never ran.
+#[test]
+fn test_fake_instructions_sysvar() {
+    let setup = Setup::new();
+    let mut withdraw = instruction_withdraw(&setup.vault, &setup.destination);
+    withdraw.accounts[2].pubkey = Pubkey::new_unique();
+    setup.expect_err(
+        &[&setup.instruction_verify(0)],
+        &withdraw,
+        ProgramError::UnsupportedSysvar,
+    );
+}
+
+/// The verification is followed by another instruction, which could be
+/// verified instead of the withdrawal.
+#[test]
+fn test_verification_not_preceding() {
+    let setup = Setup::new();
+    let other = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+    setup.expect_err(
+        &[&setup.instruction_verify(0), &other],
+        &instruction_withdraw(&setup.vault, &setup.destination),
+        passkey_vault_error(PasskeyVaultError::MissingSignatureVerification),
+    );
+}
+
+/// The verification verifies the key and message of another instruction,
+/// while its own data holds the key of the vault and a message of the
+/// attacker.
+#[test]
+fn test_verification_of_other_instruction() {
+    let setup = Setup::new();
+    let mut verify = setup.instruction_verify(0);
+    // Public key instruction index of the first signature.
+    verify.data[8..10].copy_from_slice(&0u16.to_le_bytes());
+    setup.expect_err(
+        &[&verify],
+        &instruction_withdraw(&setup.vault, &setup.destination),
+        passkey_vault_error(PasskeyVaultError::InvalidSignatureVerification),
+    );
+}
+
+/// The verification verifies several signatures, of which only the first
+/// would be checked.
+#[test]
+fn test_several_signatures() {
+    let setup = Setup::new();
+    let mut verify = setup.instruction_verify(0);
+    verify.data[0] = 2;
+    setup.expect_err(
+        &[&verify],
+        &instruction_withdraw(&setup.vault, &setup.destination),
+        passkey_vault_error(PasskeyVaultError::InvalidSignatureVerification),
+    );
+}
@@ -0,0 +1,355 @@
+use std::mem;
+
+use errors::Namespace;
+use mollusk_svm::{
+    program::{keyed_account_for_system_program, loader_keys::NATIVE_LOADER},
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use passkey_vault::{
+    withdraw_message, CreateInstructionData, PasskeyVaultError, PasskeyVaultInstruction, Vault,
+    WithdrawInstructionData, COMPRESSED_PUBKEY_LEN, VAULT_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, BorrowedAccountMeta, BorrowedInstruction, Instruction};
+use solana_instructions_sysvar::{construct_instructions_data, store_current_index_checked};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(passkey_vault::ID);
+const SECP256R1_ID: Pubkey = Pubkey::new_from_array(passkey_vault::SECP256R1_PROGRAM_ID);
+
+/// Generates a deterministic passkey and returns it together with its
+/// compressed public key.
+fn passkey(seed: u8) -> (SigningKey, [u8; COMPRESSED_PUBKEY_LEN]) {
+    let signing_key = SigningKey::from_slice(&[seed; 32]).unwrap();
+    let pubkey = signing_key.verifying_key().to_encoded_point(true);
+    (signing_key, pubkey.as_bytes().try_into().unwrap())
+}
+
+fn vault_address(pubkey: &[u8; COMPRESSED_PUBKEY_LEN]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_SEED.as_bytes(), &pubkey[..1], &pubkey[1..]], &ID)
+}
+
+fn instruction_create(
+    deposit: u64,
+    pubkey: [u8; COMPRESSED_PUBKEY_LEN],
+    bump: u8,
+    payer: &Pubkey,
+    vault: &Pubkey,
+    system_program: &Pubkey,
+) -> Instruction {
+    // Create instruction data.
+    let data = CreateInstructionData::new(deposit, pubkey, bump);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<PasskeyVaultInstruction>() + CreateInstructionData::LEN);
+    data_with_discriminator.push(PasskeyVaultInstruction::Create as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*vault, false),
+        AccountMeta::new_readonly(*system_program, false),
+    ];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+fn instruction_withdraw(amount: u64, vault: &Pubkey, destination: &Pubkey) -> Instruction {
+    // Create instruction data.
+    let data = WithdrawInstructionData::new(amount);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
+        mem::size_of::<PasskeyVaultInstruction>() + WithdrawInstructionData::LEN,
+    );
+    data_with_discriminator.push(PasskeyVaultInstruction::Withdraw as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![
+        AccountMeta::new(*vault, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(solana_sdk_ids::sysvar::instructions::ID, false),
+    ];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+/// Creates a secp256r1 precompile instruction verifying a signature of
+/// `signing_key` over `message`, with all the data embedded in the
+/// instruction itself.
+fn instruction_secp256r1(signing_key: &SigningKey, message: &[u8]) -> Instruction {
+    const DATA_START: u16 = 16;
+
+    let pubkey = signing_key.verifying_key().to_encoded_point(true);
+    let signature: Signature = signing_key.sign(message);
+    // The precompile only accepts low-S signatures.
+    let signature = signature.normalize_s().unwrap_or(signature);
+
+    let public_key_offset = DATA_START;
+    let signature_offset = public_key_offset + COMPRESSED_PUBKEY_LEN as u16;
+    let message_data_offset = signature_offset + 64;
+
+    let mut data = vec![1, 0];
+    for value in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_data_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_bytes());
+    data.extend_from_slice(&signature.to_bytes());
+    data.extend_from_slice(message);
+
+    Instruction::new_with_bytes(SECP256R1_ID, &data, vec![])
+}
+
+/// Builds the instructions sysvar account for a transaction consisting of
+/// `instructions`, currently executing the one at `current_index`.
+fn instructions_sysvar(instructions: &[&Instruction], current_index: u16) -> (Pubkey, Account) {
+    let borrowed = instructions
+        .iter()
+        .map(|ix| BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: &ix.data,
+        })
+        .collect::<Vec<_>>();
+    let mut data = construct_instructions_data(&borrowed);
+    store_current_index_checked(&mut data, current_index).unwrap();
+
+    let mut account = Account::new(1, data.len(), &solana_sdk_ids::sysvar::ID);
+    account.data = data;
+    (solana_sdk_ids::sysvar::instructions::ID, account)
+}
+
+fn precompile_account() -> Account {
+    let mut account = Account::new(1, 0, &NATIVE_LOADER);
+    account.executable = true;
+    account
+}
+
+fn vault_account(mollusk: &Mollusk, pubkey: [u8; COMPRESSED_PUBKEY_LEN], bump: u8) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Vault::LEN) + 10 * LAMPORTS_PER_SOL,
+        Vault::LEN,
+        &ID,
+    );
+    let vault_data = Vault {
+        pubkey,
+        bump,
+        _padding: [0; 6],
+        nonce: 0,
+    };
+    let vault_data = unsafe { &*(&vault_data as *const Vault as *const [u8; size_of::<Vault>()]) };
+    account.data.copy_from_slice(vault_data);
+    account
+}
+
+#[test]
+fn test_passkey_vault_create_success() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/passkey_vault");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let payer = Pubkey::new_unique();
+    let payer_account = Account::new(42 * LAMPORTS_PER_SOL, 0, &system_program);
+
+    let (_, pubkey) = passkey(1);
+    let (vault, bump) = vault_address(&pubkey);
+    // We don't specify the space for the vault PDA yet - we are letting the
+    // `create` instruction do that.
+    let vault_account = Account::new(0, 0, &system_program);
+
+    let res = mollusk.process_and_validate_instruction(
+        &instruction_create(
+            LAMPORTS_PER_SOL,
+            pubkey,
+            bump,
+            &payer,
+            &vault,
+            &system_program,
+        ),
+        &[
+            (payer, payer_account),
+            (vault, vault_account),
+            (system_program, system_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&vault)
+                .lamports(mollusk.sysvars.rent.minimum_balance(Vault::LEN) + LAMPORTS_PER_SOL)
+                .space(Vault::LEN)
+                .owner(&ID)
+                .data_slice(0, &pubkey)
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_passkey_vault_withdraw_success() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/passkey_vault");
+
+    let (signing_key, pubkey) = passkey(1);
+    let (vault, bump) = vault_address(&pubkey);
+    let vault_account = vault_account(&mollusk, pubkey, bump);
+    let vault_lamports = vault_account.lamports;
+
+    let destination = Pubkey::new_unique();
+    let destination_account = Account::new(0, 0, &Pubkey::default());
+
+    let amount = 2 * LAMPORTS_PER_SOL;
+    let message = withdraw_message(vault.as_array(), destination.as_array(), amount, 0);
+    let verify_ix = instruction_secp256r1(&signing_key, &message);
+    let withdraw_ix = instruction_withdraw(amount, &vault, &destination);
+
+    let tx_accounts = &[
+        (vault, vault_account),
+        (destination, destination_account),
+        instructions_sysvar(&[&verify_ix, &withdraw_ix], 1),
+        (SECP256R1_ID, precompile_account()),
+    ];
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (&verify_ix, &[Check::success()]),
+            (
+                &withdraw_ix,
+                &[
+                    Check::success(),
+                    Check::account(&vault)
+                        .lamports(vault_lamports - amount)
+                        // The nonce got bumped.
+                        .data_slice(40, &1u64.to_le_bytes())
+                        .build(),
+                    Check::account(&destination).lamports(amount).build(),
+                ],
+            ),
+        ],
+        tx_accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_passkey_vault_withdraw_wrong_passkey() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/passkey_vault");
+
+    let (_, pubkey) = passkey(1);
+    let (attacker_key, _) = passkey(2);
+    let (vault, bump) = vault_address(&pubkey);
+
+    let destination = Pubkey::new_unique();
+
+    // The signature is valid, but made by a key not controlling the vault.
+    let amount = LAMPORTS_PER_SOL;
+    let message = withdraw_message(vault.as_array(), destination.as_array(), amount, 0);
+    let verify_ix = instruction_secp256r1(&attacker_key, &message);
+    let withdraw_ix = instruction_withdraw(amount, &vault, &destination);
+
+    let tx_accounts = &[
+        (vault, vault_account(&mollusk, pubkey, bump)),
+        (destination, Account::new(0, 0, &Pubkey::default())),
+        instructions_sysvar(&[&verify_ix, &withdraw_ix], 1),
+        (SECP256R1_ID, precompile_account()),
+    ];
+    mollusk.process_and_validate_instruction_chain(
+        &[
+            (&verify_ix, &[Check::success()]),
+            (
+                &withdraw_ix,
+                &[Check::err(ProgramError::Custom(
+                    Namespace::PasskeyVault.code(PasskeyVaultError::PasskeyMismatch as u32),
+                ))],
+            ),
+        ],
+        tx_accounts,
+    );
+}
+
+#[test]
+fn test_passkey_vault_withdraw_message_mismatch() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/passkey_vault");
+
+    let (signing_key, pubkey) = passkey(1);
+    let (vault, bump) = vault_address(&pubkey);
+
+    let destination = Pubkey::new_unique();
+
+    // The passkey authorized 1 SOL, but the instruction asks for 5 SOL.
+    let message = withdraw_message(
+        vault.as_array(),
+        destination.as_array(),
+        LAMPORTS_PER_SOL,
+        0,
+    );
+    let verify_ix = instruction_secp256r1(&signing_key, &message);
+    let withdraw_ix = instruction_withdraw(5 * LAMPORTS_PER_SOL, &vault, &destination);
+
+    let tx_accounts = &[
+        (vault, vault_account(&mollusk, pubkey, bump)),
+        (destination, Account::new(0, 0, &Pubkey::default())),
+        instructions_sysvar(&[&verify_ix, &withdraw_ix], 1),
+        (SECP256R1_ID, precompile_account()),
+    ];
+    mollusk.process_and_validate_instruction_chain(
+        &[
+            (&verify_ix, &[Check::success()]),
+            (
+                &withdraw_ix,
+                &[Check::err(ProgramError::Custom(
+                    Namespace::PasskeyVault.code(PasskeyVaultError::MessageMismatch as u32),
+                ))],
+            ),
+        ],
+        tx_accounts,
+    );
+}
+
+#[test]
+fn test_passkey_vault_withdraw_without_verification() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/passkey_vault");
+
+    let (_, pubkey) = passkey(1);
+    let (vault, bump) = vault_address(&pubkey);
+
+    let destination = Pubkey::new_unique();
+
+    // No precompile instruction precedes the withdrawal.
+    let withdraw_ix = instruction_withdraw(LAMPORTS_PER_SOL, &vault, &destination);
+
+    mollusk.process_and_validate_instruction(
+        &withdraw_ix,
+        &[
+            (vault, vault_account(&mollusk, pubkey, bump)),
+            (destination, Account::new(0, 0, &Pubkey::default())),
+            instructions_sysvar(&[&withdraw_ix], 0),
+        ],
+        &[Check::err(ProgramError::Custom(
+            Namespace::PasskeyVault.code(PasskeyVaultError::MissingSignatureVerification as u32),
+        ))],
+    );
+}
@@ -0,0 +1,487 @@
+//! Clawbacks with the permanent delegate extension of token-2022.
+//!
+//! A permanent delegate can transfer or burn tokens from any account of
+//! its mint, without the consent of the owner. That's what regulated tokens
+//! need to enforce sanctions or court orders, but a plain keypair with that
+//! power is a liability for every holder.
+//!
+//! Here the permanent delegate is the config PDA of the program, so the
+//! power can only be used through the program, under its rules:
+//!
+//! * The admin has to flag a holder first, which creates their compliance
+//!   PDA and is visible on-chain.
+//! * Tokens can only be clawed back after [`CLAWBACK_DELAY`] since the
+//!   flag, which gives the holder time to appeal. Unflagging them cancels
+//!   the clawback.
+//! * Clawed back tokens can only go to token accounts of the admin.
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use common::close::close_account;
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+use crate::token_2022::{
+    InitializeMint2, InitializePermanentDelegate, TransferChecked, ACCOUNT_LEN,
+    ACCOUNT_MINT_OFFSET, ACCOUNT_OWNER_OFFSET, MINT_DECIMALS_OFFSET,
+    MINT_WITH_PERMANENT_DELEGATE_LEN,
+};
+
+pub mod token_2022;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("F5JHScknGsr5Mqh2zeqyycpU9K3ZCXAgTeJHVWiMinBA");
+
+pub const CONFIG_SEED: &str = "config";
+pub const COMPLIANCE_SEED: &str = "compliance";
+
+/// Time between flagging a holder and clawing back their tokens.
+pub const CLAWBACK_DELAY: i64 = 7 * 24 * 60 * 60;
+
+/// Errors returned by the permanent delegate program.
+#[repr(u32)]
+pub enum PermanentDelegateError {
+    /// The holder of the source account is not flagged.
+    NotFlagged,
+    /// The clawback delay since flagging the holder didn't pass yet.
+    ClawbackTooEarly,
+    /// The destination account doesn't belong to the admin.
+    InvalidDestination,
+}
+
+impl From<PermanentDelegateError> for ProgramError {
+    fn from(e: PermanentDelegateError) -> Self {
+        Namespace::PermanentDelegate.error(e as u32)
+    }
+}
+
+/// On-chain configuration of a mint. The config PDA is the permanent
+/// delegate of the mint.
+#[repr(C)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+assert_layout!(Config, size = 65, align = 1, { admin: 0, mint: 32, bump: 64 });
+
+impl Config {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain record of a flagged holder.
+#[repr(C)]
+pub struct Compliance {
+    pub mint: Pubkey,
+    pub holder: Pubkey,
+    /// Unix timestamp of the flag.
+    pub flagged_at: i64,
+}
+
+assert_layout!(Compliance, size = 72, align = 8, { mint: 0, holder: 32, flagged_at: 64 });
+
+impl Compliance {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Permanent delegate program instruction discriminators.
+#[repr(u8)]
+pub enum PermanentDelegateInstruction {
+    /// Creates a mint with the config PDA as its permanent delegate.
+    InitializeMint,
+    /// Flags a holder.
+    Flag,
+    /// Removes the flag of a holder.
+    Unflag,
+    /// Claws back tokens of a flagged holder.
+    Clawback,
+}
+
+impl TryFrom<&u8> for PermanentDelegateInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeMint),
+            1 => Ok(Self::Flag),
+            2 => Ok(Self::Unflag),
+            3 => Ok(Self::Clawback),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeMintInstructionData {
+    pub decimals: u8,
+    pub bump: u8,
+}
+
+impl InitializeMintInstructionData {
+    pub fn new(decimals: u8, bump: u8) -> Self {
+        Self { decimals, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct FlagInstructionData {
+    pub bump: u8,
+}
+
+impl FlagInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ClawbackInstructionData {
+    pub amount: u64,
+}
+
+impl ClawbackInstructionData {
+    pub fn new(amount: u64) -> Self {
+        Self { amount }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = PermanentDelegateInstruction::try_from(instruction)?;
+
+    match instruction {
+        PermanentDelegateInstruction::InitializeMint => {
+            process_initialize_mint(accounts, instruction_data)
+        }
+        PermanentDelegateInstruction::Flag => process_flag(accounts, instruction_data),
+        PermanentDelegateInstruction::Unflag => process_unflag(accounts),
+        PermanentDelegateInstruction::Clawback => process_clawback(accounts, instruction_data),
+    }
+}
+
+/// Creates a mint with the permanent delegate extension and its config
+/// PDA, which is the permanent delegate. `admin` is the mint authority.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[WRITE, SIGNER]` Mint
+///   2. `[WRITE]` Config PDA of the mint
+///   3. `[]` System program
+///   4. `[]` Token-2022 program
+pub fn process_initialize_mint(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, mint, config, _system_program, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeMintInstructionData::from_bytes(instruction_data)?;
+
+    let config_pda = create_program_address(
+        &[CONFIG_SEED.as_bytes(), mint.key(), &[instruction_data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(config.key(), &config_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(CONFIG_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: admin,
+        to: config,
+        lamports: Rent::get()?.minimum_balance(Config::LEN),
+        space: Config::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    {
+        let mut data = config.try_borrow_mut_data()?;
+        let config: &mut Config = unsafe { &mut *data.as_mut_ptr().cast() };
+        config.admin = *admin.key();
+        config.mint = *mint.key();
+        config.bump = instruction_data.bump;
+    }
+
+    CreateAccount {
+        from: admin,
+        to: mint,
+        lamports: Rent::get()?.minimum_balance(MINT_WITH_PERMANENT_DELEGATE_LEN),
+        space: MINT_WITH_PERMANENT_DELEGATE_LEN as u64,
+        owner: &token_2022::ID,
+    }
+    .invoke()?;
+
+    // Extensions have to be initialized before the mint itself.
+    InitializePermanentDelegate {
+        mint,
+        delegate: config.key(),
+    }
+    .invoke()?;
+
+    InitializeMint2 {
+        mint,
+        decimals: instruction_data.decimals,
+        mint_authority: admin.key(),
+        freeze_authority: None,
+    }
+    .invoke()?;
+
+    info!("Initialized a mint with a permanent delegate");
+
+    Ok(())
+}
+
+/// Flags `holder` by creating their compliance PDA.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[]` Config PDA
+///   2. `[]` Holder
+///   3. `[WRITE]` Compliance PDA of the holder
+///   4. `[]` System program
+pub fn process_flag(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, config, holder, compliance, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = FlagInstructionData::from_bytes(instruction_data)?;
+
+    let data = config.try_borrow_data()?;
+    let config = validate_config(admin, config, &data)?;
+
+    let compliance_pda = create_program_address(
+        &[
+            COMPLIANCE_SEED.as_bytes(),
+            &config.mint,
+            holder.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(compliance.key(), &compliance_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(COMPLIANCE_SEED.as_bytes()),
+        Seed::from(&config.mint),
+        Seed::from(holder.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: admin,
+        to: compliance,
+        lamports: Rent::get()?.minimum_balance(Compliance::LEN),
+        space: Compliance::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut data = compliance.try_borrow_mut_data()?;
+    let compliance: &mut Compliance = unsafe { &mut *data.as_mut_ptr().cast() };
+    compliance.mint = config.mint;
+    compliance.holder = *holder.key();
+    compliance.flagged_at = Clock::get()?.unix_timestamp;
+
+    info!("Flagged the holder");
+
+    Ok(())
+}
+
+/// Removes the flag of a holder by closing their compliance PDA.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Admin
+///   1. `[]` Config PDA
+///   2. `[WRITE]` Compliance PDA of the holder
+pub fn process_unflag(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, config, compliance] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    {
+        let data = config.try_borrow_data()?;
+        let config = validate_config(admin, config, &data)?;
+
+        if !compliance.is_owned_by(&ID) {
+            return Err(PermanentDelegateError::NotFlagged.into());
+        }
+        let data = compliance.try_borrow_data()?;
+        if data.len() != Compliance::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let compliance: &Compliance = unsafe { &*data.as_ptr().cast() };
+        if compliance.mint != config.mint {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    close_account(compliance, admin)?;
+
+    info!("Unflagged the holder");
+
+    Ok(())
+}
+
+/// Claws back `amount` tokens from `source`, whose owner has to be flagged
+/// for at least [`CLAWBACK_DELAY`], to a token account of the admin.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Admin
+///   1. `[]` Config PDA
+///   2. `[]` Mint
+///   3. `[WRITE]` Source token account
+///   4. `[]` Compliance PDA of the owner of the source token account
+///   5. `[WRITE]` Destination token account, owned by the admin
+///   6. `[]` Token-2022 program
+pub fn process_clawback(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, config, mint, source, compliance, destination, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = ClawbackInstructionData::from_bytes(instruction_data)?;
+
+    // The config is the signer of the transfer, so it can't stay borrowed.
+    let (config_admin, config_bump) = {
+        let data = config.try_borrow_data()?;
+        let config = validate_config(admin, config, &data)?;
+        if !keys_eq(&config.mint, mint.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        (config.admin, config.bump)
+    };
+
+    let holder = token_account_owner(source, mint)?;
+
+    if !compliance.is_owned_by(&ID) {
+        return Err(PermanentDelegateError::NotFlagged.into());
+    }
+    {
+        let data = compliance.try_borrow_data()?;
+        if data.len() != Compliance::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let compliance: &Compliance = unsafe { &*data.as_ptr().cast() };
+        if !keys_eq(&compliance.mint, mint.key()) || compliance.holder != holder {
+            return Err(PermanentDelegateError::NotFlagged.into());
+        }
+        if Clock::get()?.unix_timestamp < compliance.flagged_at + CLAWBACK_DELAY {
+            return Err(PermanentDelegateError::ClawbackTooEarly.into());
+        }
+    }
+
+    if token_account_owner(destination, mint)? != config_admin {
+        return Err(PermanentDelegateError::InvalidDestination.into());
+    }
+
+    require_owned_by(mint, &token_2022::ID)?;
+    let decimals = *mint
+        .try_borrow_data()?
+        .get(MINT_DECIMALS_OFFSET)
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    let bump = [config_bump];
+    let seeds = [
+        Seed::from(CONFIG_SEED.as_bytes()),
+        Seed::from(mint.key()),
+        Seed::from(&bump),
+    ];
+    TransferChecked {
+        from: source,
+        mint,
+        to: destination,
+        authority: config,
+        amount: instruction_data.amount,
+        decimals,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Clawed back {} tokens", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Checks that `config` is a config PDA administered by `admin`, who has
+/// to sign. Returns the deserialized config.
+fn validate_config<'a>(
+    admin: &AccountInfo,
+    config: &AccountInfo,
+    data: &'a [u8],
+) -> Result<&'a Config, ProgramError> {
+    require_signer(admin)?;
+    require_owned_by(config, &ID)?;
+    if data.len() != Config::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let config: &Config = unsafe { &*data.as_ptr().cast() };
+    if !keys_eq(&config.admin, admin.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(config)
+}
+
+/// Returns the owner of `token_account`, which has to be a token-2022
+/// account of `mint`.
+fn token_account_owner(
+    token_account: &AccountInfo,
+    mint: &AccountInfo,
+) -> Result<Pubkey, ProgramError> {
+    require_owned_by(token_account, &token_2022::ID)?;
+    let data = token_account.try_borrow_data()?;
+    if data.len() < ACCOUNT_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if &data[ACCOUNT_MINT_OFFSET..ACCOUNT_MINT_OFFSET + 32] != mint.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut owner = [0; 32];
+    owner.copy_from_slice(&data[ACCOUNT_OWNER_OFFSET..ACCOUNT_OWNER_OFFSET + 32]);
+    Ok(owner)
+}
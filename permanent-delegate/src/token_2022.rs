@@ -0,0 +1,201 @@
+//! Instructions and account layout of the token-2022 program.
+//!
+//! `pinocchio-token` only supports the original token program, so the CPIs
+//! are built by hand, the same way `pinocchio-token` does it.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+/// Token-2022 program ID.
+pub const ID: Pubkey = pinocchio_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Size of a token account without extensions. Mints with extensions are
+/// padded to the same size, so the account type byte and the extensions
+/// start at the same offset in both.
+pub const ACCOUNT_LEN: usize = 165;
+
+/// Offset of the decimals in a mint.
+pub const MINT_DECIMALS_OFFSET: usize = 44;
+/// Offsets of the mint and the owner in a token account.
+pub const ACCOUNT_MINT_OFFSET: usize = 0;
+pub const ACCOUNT_OWNER_OFFSET: usize = 32;
+
+/// Offset of the account type byte, followed by the extensions. Every
+/// extension is encoded as its type (`u16`), length (`u16`) and value.
+pub const ACCOUNT_TYPE_OFFSET: usize = ACCOUNT_LEN;
+pub const EXTENSIONS_OFFSET: usize = ACCOUNT_TYPE_OFFSET + 1;
+pub const EXTENSION_HEADER_LEN: usize = 4;
+
+/// `ExtensionType::PermanentDelegate`.
+pub const PERMANENT_DELEGATE: u16 = 12;
+
+/// Size of the `PermanentDelegate` extension.
+pub const PERMANENT_DELEGATE_LEN: usize = 32;
+
+/// Size of a mint with the `PermanentDelegate` extension.
+pub const MINT_WITH_PERMANENT_DELEGATE_LEN: usize =
+    EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN + PERMANENT_DELEGATE_LEN;
+
+/// Initialize the `PermanentDelegate` extension of a mint. Has to precede
+/// `InitializeMint2`.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializePermanentDelegate<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Delegate allowed to transfer and burn tokens from any account of
+    /// the mint.
+    pub delegate: &'a Pubkey,
+}
+
+impl InitializePermanentDelegate<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0    ]: instruction discriminator
+        // -  [1..33]: delegate
+        let mut instruction_data = [0; 33];
+        instruction_data[0] = 35;
+        instruction_data[1..33].copy_from_slice(self.delegate);
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Initialize a mint, without requiring the rent sysvar.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized mint
+pub struct InitializeMint2<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Decimals.
+    pub decimals: u8,
+
+    /// Mint authority.
+    pub mint_authority: &'a Pubkey,
+
+    /// Freeze authority.
+    pub freeze_authority: Option<&'a Pubkey>,
+}
+
+impl InitializeMint2<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 1] = [AccountMeta::writable(self.mint.key())];
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1   ]: decimals
+        // -  [2..34]: mint authority
+        // -  [34  ]: freeze authority presence flag
+        // -  [35..67]: freeze authority
+        let mut instruction_data = [0; 67];
+        instruction_data[0] = 20;
+        instruction_data[1] = self.decimals;
+        instruction_data[2..34].copy_from_slice(self.mint_authority);
+        if let Some(freeze_authority) = self.freeze_authority {
+            instruction_data[34] = 1;
+            instruction_data[35..67].copy_from_slice(freeze_authority);
+        }
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.mint], signers)
+    }
+}
+
+/// Transfer tokens, checking the mint and its decimals.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Source token account
+///   1. `[]` Mint
+///   2. `[WRITE]` Destination token account
+///   3. `[SIGNER]` Owner or delegate of the source token account
+pub struct TransferChecked<'a> {
+    /// Source token account.
+    pub from: &'a AccountInfo,
+
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Destination token account.
+    pub to: &'a AccountInfo,
+
+    /// Owner or delegate of the source token account.
+    pub authority: &'a AccountInfo,
+
+    /// Amount.
+    pub amount: u64,
+
+    /// Decimals of the mint.
+    pub decimals: u8,
+}
+
+impl TransferChecked<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 4] = [
+            AccountMeta::writable(self.from.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::writable(self.to.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        // instruction data
+        // -  [0    ]: instruction discriminator
+        // -  [1..9 ]: amount
+        // -  [9    ]: decimals
+        let mut instruction_data = [0; 10];
+        instruction_data[0] = 12;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+        instruction_data[9] = self.decimals;
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.from, self.mint, self.to, self.authority],
+            signers,
+        )
+    }
+}
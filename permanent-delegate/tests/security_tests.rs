@@ -0,0 +1,351 @@
+//! Attacks on the permanent delegate program, each rejected with its own
+//! error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::{create_program_account_loader_v3, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use permanent_delegate::{
+    token_2022::{
+        ACCOUNT_LEN, ACCOUNT_TYPE_OFFSET, EXTENSIONS_OFFSET, EXTENSION_HEADER_LEN,
+        MINT_WITH_PERMANENT_DELEGATE_LEN, PERMANENT_DELEGATE, PERMANENT_DELEGATE_LEN,
+    },
+    ClawbackInstructionData, Compliance, Config, PermanentDelegateInstruction, CLAWBACK_DELAY,
+    COMPLIANCE_SEED, CONFIG_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(permanent_delegate::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(permanent_delegate::token_2022::ID);
+
+/// `PermanentDelegateError::NotFlagged`.
+const NOT_FLAGGED: u32 = Namespace::PermanentDelegate.code(0);
+
+const DECIMALS: u8 = 6;
+const NOW: i64 = 1_000_000_000;
+
+/// Offset of the value of the first extension.
+const EXTENSION_OFFSET: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: PermanentDelegateInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator =
+        vec![0; mem::size_of::<PermanentDelegateInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns a mint of `admin` with `delegate` as its permanent delegate.
+fn mint_account(mollusk: &Mollusk, admin: &Pubkey, delegate: &Pubkey) -> Account {
+    let mut data = vec![0; MINT_WITH_PERMANENT_DELEGATE_LEN];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(admin.as_ref());
+    data[36..44].copy_from_slice(&1_000u64.to_le_bytes());
+    data[44] = DECIMALS;
+    data[45] = 1;
+    data[ACCOUNT_TYPE_OFFSET] = 1;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&PERMANENT_DELEGATE.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..EXTENSION_OFFSET]
+        .copy_from_slice(&(PERMANENT_DELEGATE_LEN as u16).to_le_bytes());
+    data[EXTENSION_OFFSET..EXTENSION_OFFSET + 32].copy_from_slice(delegate.as_ref());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns a token account without extensions.
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; ACCOUNT_LEN];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&amount.to_le_bytes());
+    // Initialized.
+    data[108] = 1;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(ACCOUNT_LEN),
+        ACCOUNT_LEN,
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns the config PDA of `mint` and its account, administered by
+/// `admin`.
+fn config_account(mollusk: &Mollusk, admin: &Pubkey, mint: &Pubkey) -> (Pubkey, Account) {
+    let (config, bump) =
+        Pubkey::find_program_address(&[CONFIG_SEED.as_bytes(), mint.as_ref()], &ID);
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(admin.as_ref());
+    account.data[32..64].copy_from_slice(mint.as_ref());
+    account.data[64] = bump;
+    (config, account)
+}
+
+/// Returns the compliance PDA of `holder` in `mint` and its account,
+/// flagged long enough ago to claw back.
+fn compliance_account(mollusk: &Mollusk, mint: &Pubkey, holder: &Pubkey) -> (Pubkey, Account) {
+    let (compliance, _) = Pubkey::find_program_address(
+        &[COMPLIANCE_SEED.as_bytes(), mint.as_ref(), holder.as_ref()],
+        &ID,
+    );
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Compliance::LEN),
+        Compliance::LEN,
+        &ID,
+    );
+    account.data[0..32].copy_from_slice(mint.as_ref());
+    account.data[32..64].copy_from_slice(holder.as_ref());
+    account.data[64..72].copy_from_slice(&(NOW - CLAWBACK_DELAY).to_le_bytes());
+    (compliance, account)
+}
+
+/// Accounts of a mint of an admin, with a flagged holder whose tokens can
+/// be clawed back, and of an attacker administering a mint of their own,
+/// with a token account of the first mint.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    mint: Pubkey,
+    config: Pubkey,
+    holder: Pubkey,
+    source: Pubkey,
+    compliance: Pubkey,
+    destination: Pubkey,
+    attacker: Pubkey,
+    attacker_mint: Pubkey,
+    attacker_config: Pubkey,
+    attacker_destination: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/permanent_delegate");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let admin = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (config, config_account) = config_account(&mollusk, &admin, &mint);
+        let holder = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let (compliance, compliance_account) = compliance_account(&mollusk, &mint, &holder);
+        let destination = Pubkey::new_unique();
+
+        let attacker = Pubkey::new_unique();
+        let attacker_mint = Pubkey::new_unique();
+        let (attacker_config, attacker_config_account) =
+            config_account(&mollusk, &attacker, &attacker_mint);
+        let attacker_destination = Pubkey::new_unique();
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &admin, &config)),
+            (config, config_account),
+            (holder, Account::default()),
+            (source, token_account(&mollusk, &mint, &holder, 1_000)),
+            (compliance, compliance_account),
+            (destination, token_account(&mollusk, &mint, &admin, 0)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_mint,
+                mint_account(&mollusk, &attacker, &attacker_config),
+            ),
+            (attacker_config, attacker_config_account),
+            (
+                attacker_destination,
+                token_account(&mollusk, &mint, &attacker, 0),
+            ),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            mint,
+            config,
+            holder,
+            source,
+            compliance,
+            destination,
+            attacker,
+            attacker_mint,
+            attacker_config,
+            attacker_destination,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    /// Claws back the tokens of the holder, signed by the admin.
+    fn instruction_clawback(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new(self.source, false),
+            AccountMeta::new_readonly(self.compliance, false),
+            AccountMeta::new(self.destination, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                PermanentDelegateInstruction::Clawback,
+                &ClawbackInstructionData::new(1_000),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Claws back the tokens of the holder to the attacker, signed by the
+    /// attacker with `config`.
+    fn attacker_clawback(&self, config: &Pubkey) -> Instruction {
+        let mut instruction = self.instruction_clawback();
+        instruction.accounts[0].pubkey = self.attacker;
+        instruction.accounts[1].pubkey = *config;
+        instruction.accounts[5].pubkey = self.attacker_destination;
+        instruction
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_admin_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_clawback();
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker claws back the tokens of the holder with the config of
+/// their own mint, which they administer.
+#[test]
+fn test_config_of_other_mint() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_clawback(&setup.attacker_config),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A config with the layout of the program, administered by the attacker,
+/// but owned by another program.
+#[test]
+fn test_spoofed_config() {
+    let setup = Setup::new();
+    let (_, mut config_account) = config_account(&setup.mollusk, &setup.attacker, &setup.mint);
+    config_account.owner = Pubkey::new_unique();
+    let config = Pubkey::new_unique();
+    let setup = setup.with(config, config_account);
+    setup.expect_err(
+        &setup.attacker_clawback(&config),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A compliance record flagged long ago, but owned by another program, to
+/// skip the clawback delay of a holder flagged just now.
+#[test]
+fn test_spoofed_compliance() {
+    let setup = Setup::new();
+    let compliance = setup.compliance;
+    let mut compliance_account = setup.account(&compliance);
+    compliance_account.owner = Pubkey::new_unique();
+    let setup = setup.with(compliance, compliance_account);
+    setup.expect_err(
+        &setup.instruction_clawback(),
+        ProgramError::Custom(NOT_FLAGGED),
+    );
+}
+
+/// A flag of the holder in another mint, which doesn't count in this one.
+#[test]
+fn test_compliance_of_other_mint() {
+    let setup = Setup::new();
+    let (compliance, compliance_account) =
+        compliance_account(&setup.mollusk, &setup.attacker_mint, &setup.holder);
+    let setup = setup.with(compliance, compliance_account);
+    let mut instruction = setup.instruction_clawback();
+    instruction.accounts[4].pubkey = compliance;
+    setup.expect_err(&instruction, ProgramError::Custom(NOT_FLAGGED));
+}
+
+/// Tokens of a holder of another mint, which the config is not the
+/// delegate of.
+#[test]
+fn test_source_of_other_mint() {
+    let setup = Setup::new();
+    let source = setup.source;
+    let source_account = token_account(&setup.mollusk, &setup.attacker_mint, &setup.holder, 1_000);
+    let setup = setup.with(source, source_account);
+    setup.expect_err(
+        &setup.instruction_clawback(),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker unflags the holder with the config of their own mint, to
+/// collect the rent of the compliance PDA.
+#[test]
+fn test_unflag_by_attacker() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new_readonly(setup.attacker_config, false),
+        AccountMeta::new(setup.compliance, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &[PermanentDelegateInstruction::Unflag as u8],
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidAccountData);
+}
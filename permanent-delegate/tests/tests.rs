@@ -0,0 +1,447 @@
+//! The token-2022 program is loaded from `third-party/spl_token_2022.so`,
+//! which can be dumped from mainnet with:
+//!
+//! ```sh
+//! solana program dump -um TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb \
+//!     third-party/spl_token_2022.so
+//! ```
+
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use permanent_delegate::{
+    token_2022::{
+        ACCOUNT_LEN, ACCOUNT_TYPE_OFFSET, EXTENSIONS_OFFSET, EXTENSION_HEADER_LEN,
+        MINT_WITH_PERMANENT_DELEGATE_LEN, PERMANENT_DELEGATE, PERMANENT_DELEGATE_LEN,
+    },
+    ClawbackInstructionData, Compliance, Config, FlagInstructionData,
+    InitializeMintInstructionData, PermanentDelegateInstruction, CLAWBACK_DELAY, COMPLIANCE_SEED,
+    CONFIG_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(permanent_delegate::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(permanent_delegate::token_2022::ID);
+
+/// `PermanentDelegateError::NotFlagged`.
+const NOT_FLAGGED: u32 = Namespace::PermanentDelegate.code(0);
+/// `PermanentDelegateError::ClawbackTooEarly`.
+const CLAWBACK_TOO_EARLY: u32 = Namespace::PermanentDelegate.code(1);
+/// `PermanentDelegateError::InvalidDestination`.
+const INVALID_DESTINATION: u32 = Namespace::PermanentDelegate.code(2);
+
+const DECIMALS: u8 = 6;
+const NOW: i64 = 1_000_000_000;
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offset of the value of the first extension.
+const EXTENSION_OFFSET: usize = EXTENSIONS_OFFSET + EXTENSION_HEADER_LEN;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: PermanentDelegateInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<PermanentDelegateInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/permanent_delegate");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token_2022", &LOADER_V3);
+    mollusk.sysvars.clock.unix_timestamp = NOW;
+    mollusk
+}
+
+/// Returns a mint with `delegate` as its permanent delegate, as created by
+/// `InitializeMint`.
+fn mint_account(mollusk: &Mollusk, admin: &Pubkey, delegate: &Pubkey, supply: u64) -> Account {
+    let mut data = vec![0; MINT_WITH_PERMANENT_DELEGATE_LEN];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(admin.as_ref());
+    data[36..44].copy_from_slice(&supply.to_le_bytes());
+    data[44] = DECIMALS;
+    data[45] = 1;
+    data[ACCOUNT_TYPE_OFFSET] = 1;
+    data[EXTENSIONS_OFFSET..EXTENSIONS_OFFSET + 2]
+        .copy_from_slice(&PERMANENT_DELEGATE.to_le_bytes());
+    data[EXTENSIONS_OFFSET + 2..EXTENSION_OFFSET]
+        .copy_from_slice(&(PERMANENT_DELEGATE_LEN as u16).to_le_bytes());
+    data[EXTENSION_OFFSET..EXTENSION_OFFSET + 32].copy_from_slice(delegate.as_ref());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns a token account without extensions.
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; ACCOUNT_LEN];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[AMOUNT_OFFSET..AMOUNT_OFFSET + 8].copy_from_slice(&amount.to_le_bytes());
+    // Initialized.
+    data[108] = 1;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(ACCOUNT_LEN),
+        ACCOUNT_LEN,
+        &TOKEN_ID,
+    );
+    account.data = data;
+    account
+}
+
+fn config_account(mollusk: &Mollusk, admin: &Pubkey, mint: &Pubkey, bump: u8) -> Account {
+    let mut data = vec![0; Config::LEN];
+    data[0..32].copy_from_slice(admin.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+fn compliance_account(
+    mollusk: &Mollusk,
+    mint: &Pubkey,
+    holder: &Pubkey,
+    flagged_at: i64,
+) -> Account {
+    let mut data = vec![0; Compliance::LEN];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(holder.as_ref());
+    data[64..72].copy_from_slice(&flagged_at.to_le_bytes());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Compliance::LEN),
+        Compliance::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// A mint administered by the program, a holder with tokens and a token
+/// account of the admin.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    mint: Pubkey,
+    config: Pubkey,
+    config_bump: u8,
+    holder: Pubkey,
+    source: Pubkey,
+    compliance: Pubkey,
+    compliance_bump: u8,
+    destination: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let admin = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (config, config_bump) =
+            Pubkey::find_program_address(&[CONFIG_SEED.as_bytes(), mint.as_ref()], &ID);
+        let holder = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let (compliance, compliance_bump) = Pubkey::find_program_address(
+            &[COMPLIANCE_SEED.as_bytes(), mint.as_ref(), holder.as_ref()],
+            &ID,
+        );
+        let destination = Pubkey::new_unique();
+
+        let accounts = vec![
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &admin, &config, 1_000)),
+            (config, config_account(&mollusk, &admin, &mint, config_bump)),
+            (holder, Account::default()),
+            (source, token_account(&mollusk, &mint, &holder, 1_000)),
+            (compliance, Account::default()),
+            (destination, token_account(&mollusk, &mint, &admin, 0)),
+            keyed_account_for_system_program(),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            mint,
+            config,
+            config_bump,
+            holder,
+            source,
+            compliance,
+            compliance_bump,
+            destination,
+            accounts,
+        }
+    }
+
+    /// Sets the compliance PDA of the holder, flagged at `flagged_at`.
+    fn flagged_at(&mut self, flagged_at: i64) {
+        self.accounts[5].1 =
+            compliance_account(&self.mollusk, &self.mint, &self.holder, flagged_at);
+    }
+
+    fn instruction_initialize_mint(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.mint, true),
+            AccountMeta::new(self.config, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                PermanentDelegateInstruction::InitializeMint,
+                &InitializeMintInstructionData::new(DECIMALS, self.config_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_flag(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new_readonly(self.holder, false),
+            AccountMeta::new(self.compliance, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                PermanentDelegateInstruction::Flag,
+                &FlagInstructionData::new(self.compliance_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_unflag(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new(self.compliance, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &[PermanentDelegateInstruction::Unflag as u8],
+            ix_accounts,
+        )
+    }
+
+    fn instruction_clawback(&self, amount: u64) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new_readonly(self.config, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new(self.source, false),
+            AccountMeta::new_readonly(self.compliance, false),
+            AccountMeta::new(self.destination, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                PermanentDelegateInstruction::Clawback,
+                &ClawbackInstructionData::new(amount),
+            ),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(MINT_WITH_PERMANENT_DELEGATE_LEN, 202);
+    assert_eq!(Config::LEN, 65);
+    assert_eq!(Compliance::LEN, 72);
+}
+
+#[test]
+fn test_initialize_mint() {
+    let mut setup = Setup::new();
+    // The mint and the config are created by the program.
+    setup.accounts[1].1 = Account::default();
+    setup.accounts[2].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_mint(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.mint)
+                .owner(&TOKEN_ID)
+                .space(MINT_WITH_PERMANENT_DELEGATE_LEN)
+                .data_slice(4, setup.admin.as_ref())
+                .data_slice(44, &[DECIMALS, 1])
+                .data_slice(ACCOUNT_TYPE_OFFSET, &[1])
+                .data_slice(EXTENSIONS_OFFSET, &PERMANENT_DELEGATE.to_le_bytes())
+                .data_slice(EXTENSION_OFFSET, setup.config.as_ref())
+                .build(),
+            Check::account(&setup.config)
+                .owner(&ID)
+                .data(
+                    &[
+                        setup.admin.as_ref(),
+                        setup.mint.as_ref(),
+                        &[setup.config_bump],
+                    ]
+                    .concat(),
+                )
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_flag_and_unflag() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_flag(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.compliance)
+                        .owner(&ID)
+                        .data_slice(0, setup.mint.as_ref())
+                        .data_slice(32, setup.holder.as_ref())
+                        .data_slice(64, &NOW.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            // The holder has time to appeal before their tokens can be
+            // clawed back.
+            (
+                &setup.instruction_clawback(1_000),
+                &[Check::err(ProgramError::Custom(CLAWBACK_TOO_EARLY))],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+
+    let mut setup = Setup::new();
+    setup.flagged_at(NOW);
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_unflag(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.compliance).closed().build(),
+                    Check::account(&setup.admin)
+                        .lamports(LAMPORTS_PER_SOL)
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_clawback(1_000),
+                &[Check::err(ProgramError::Custom(NOT_FLAGGED))],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_clawback() {
+    let mut setup = Setup::new();
+    setup.flagged_at(NOW - CLAWBACK_DELAY);
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_clawback(400),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.source)
+                .data_slice(AMOUNT_OFFSET, &600u64.to_le_bytes())
+                .build(),
+            Check::account(&setup.destination)
+                .data_slice(AMOUNT_OFFSET, &400u64.to_le_bytes())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_clawback_invalid() {
+    let mut setup = Setup::new();
+    setup.flagged_at(NOW - CLAWBACK_DELAY);
+
+    // Clawed back tokens can only go to the admin.
+    let mut accounts = setup.accounts.clone();
+    accounts[6].1 = token_account(&setup.mollusk, &setup.mint, &Pubkey::new_unique(), 0);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_clawback(400),
+        &accounts,
+        &[Check::err(ProgramError::Custom(INVALID_DESTINATION))],
+    );
+
+    // The flag of one holder doesn't allow clawing back from another.
+    let mut accounts = setup.accounts.clone();
+    accounts[4].1 = token_account(&setup.mollusk, &setup.mint, &Pubkey::new_unique(), 1_000);
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_clawback(400),
+        &accounts,
+        &[Check::err(ProgramError::Custom(NOT_FLAGGED))],
+    );
+
+    // The config is shorter than a compliance PDA, which would be read past
+    // its end.
+    let mut ix = setup.instruction_clawback(400);
+    ix.accounts[4].pubkey = setup.config;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+
+    // Only the admin can claw back.
+    let mut ix = setup.instruction_clawback(400);
+    ix.accounts[0].pubkey = setup.holder;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
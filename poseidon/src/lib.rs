@@ -0,0 +1,157 @@
+//! Poseidon hashing with the `sol_poseidon` syscall.
+//!
+//! Poseidon is a hash function designed for zero-knowledge circuits, where
+//! it needs far fewer constraints than SHA-256 or Keccak. Hashing the same
+//! values on-chain and in a circuit is how ZK and state compression programs
+//! tie the two together. The syscall uses the BN254 parameters from circom,
+//! with the x^5 S-box.
+
+#![no_std]
+
+use common::info;
+use errors::Namespace;
+use pinocchio::{
+    account_info::AccountInfo, cpi::set_return_data, program_error::ProgramError, pubkey::Pubkey,
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("HeY7zyeHCom6S3f1CSBmBKdyhCxM13fKD75UAn4KSUWQ");
+
+/// Length of a hash, and of every input.
+pub const HASH_LEN: usize = 32;
+
+/// Maximum number of inputs the syscall hashes at once.
+pub const MAX_INPUTS: usize = 12;
+
+/// `Parameters::Bn254X5` of the syscall.
+const BN254_X5: u64 = 0;
+
+/// `Endianness::BigEndian` of the syscall.
+const BIG_ENDIAN: u64 = 0;
+
+/// Errors returned by the Poseidon program.
+#[repr(u32)]
+pub enum PoseidonError {
+    /// The hash of the inputs differs from the expected one.
+    HashMismatch,
+    /// An input is not smaller than the modulus of the BN254 scalar field.
+    InputOutOfRange,
+}
+
+impl From<PoseidonError> for ProgramError {
+    fn from(e: PoseidonError) -> Self {
+        Namespace::Poseidon.error(e as u32)
+    }
+}
+
+/// Poseidon program instruction discriminators.
+///
+/// The inputs are big-endian field elements of [`HASH_LEN`] bytes each,
+/// between 1 and [`MAX_INPUTS`] of them.
+#[repr(u8)]
+pub enum PoseidonInstruction {
+    /// Hashes the inputs in the rest of the instruction data and sets the
+    /// hash as return data.
+    Hash,
+    /// Checks that the inputs hash to the expected hash, which precedes them
+    /// in the instruction data.
+    Verify,
+}
+
+impl TryFrom<&u8> for PoseidonInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Hash),
+            1 => Ok(Self::Verify),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = PoseidonInstruction::try_from(instruction)?;
+
+    match instruction {
+        PoseidonInstruction::Hash => {
+            let hash = poseidon(instruction_data)?;
+            set_return_data(&hash);
+            info!("Hashed {} inputs", instruction_data.len() / HASH_LEN);
+        }
+        PoseidonInstruction::Verify => {
+            if instruction_data.len() < HASH_LEN {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let (expected, inputs) = instruction_data.split_at(HASH_LEN);
+            if poseidon(inputs)? != expected {
+                return Err(PoseidonError::HashMismatch.into());
+            }
+            info!("Verified the hash of {} inputs", inputs.len() / HASH_LEN);
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits `data` into inputs of [`HASH_LEN`] bytes. Returns the inputs and
+/// their count.
+pub fn split_inputs(data: &[u8]) -> Result<([&[u8]; MAX_INPUTS], usize), ProgramError> {
+    let len = data.len() / HASH_LEN;
+    if data.len() != len * HASH_LEN || len == 0 || len > MAX_INPUTS {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut inputs: [&[u8]; MAX_INPUTS] = [&[]; MAX_INPUTS];
+    for (input, chunk) in inputs.iter_mut().zip(data.chunks_exact(HASH_LEN)) {
+        *input = chunk;
+    }
+    Ok((inputs, len))
+}
+
+/// Computes the Poseidon hash of the inputs in `data` using the
+/// `sol_poseidon` syscall.
+pub fn poseidon(data: &[u8]) -> Result<[u8; HASH_LEN], ProgramError> {
+    let (inputs, len) = split_inputs(data)?;
+    let vals = &inputs[..len];
+    let mut hash = [0; HASH_LEN];
+
+    #[cfg(target_os = "solana")]
+    {
+        let ret = unsafe {
+            pinocchio::syscalls::sol_poseidon(
+                BN254_X5,
+                BIG_ENDIAN,
+                vals as *const _ as *const u8,
+                vals.len() as u64,
+                hash.as_mut_ptr(),
+            )
+        };
+        if ret != 0 {
+            return Err(PoseidonError::InputOutOfRange.into());
+        }
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((BN254_X5, BIG_ENDIAN, vals, &mut hash));
+
+    Ok(hash)
+}
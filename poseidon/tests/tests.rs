@@ -0,0 +1,156 @@
+use ark_bn254::Fr;
+use errors::Namespace;
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
+use mollusk_svm::{result::Check, Mollusk};
+use poseidon::{split_inputs, PoseidonInstruction, HASH_LEN, MAX_INPUTS};
+use solana_instruction::Instruction;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(poseidon::ID);
+
+/// `PoseidonError::HashMismatch`.
+const HASH_MISMATCH: u32 = Namespace::Poseidon.code(0);
+/// `PoseidonError::InputOutOfRange`.
+const INPUT_OUT_OF_RANGE: u32 = Namespace::Poseidon.code(1);
+
+/// Modulus of the BN254 scalar field, big-endian.
+const MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+/// Returns `value` as a big-endian field element.
+fn field(value: u64) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    bytes[24..].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+/// Hashes `inputs` with the host-side implementation.
+fn poseidon(inputs: &[[u8; 32]]) -> [u8; 32] {
+    let inputs: Vec<&[u8]> = inputs.iter().map(|input| input.as_slice()).collect();
+    Poseidon::<Fr>::new_circom(inputs.len())
+        .unwrap()
+        .hash_bytes_be(&inputs)
+        .unwrap()
+}
+
+/// Sets of inputs covering a single input, the maximum number of inputs and
+/// the largest field element.
+fn inputs() -> Vec<Vec<[u8; 32]>> {
+    let mut largest = MODULUS;
+    largest[31] -= 1;
+    vec![
+        vec![field(1)],
+        vec![field(1), field(2)],
+        (1..=MAX_INPUTS as u64).map(field).collect(),
+        vec![largest, [0; 32]],
+    ]
+}
+
+fn instruction_hash(inputs: &[[u8; 32]]) -> Instruction {
+    let mut data = vec![PoseidonInstruction::Hash as u8];
+    data.extend(inputs.concat());
+    Instruction::new_with_bytes(ID, &data, vec![])
+}
+
+fn instruction_verify(expected: &[u8; 32], inputs: &[[u8; 32]]) -> Instruction {
+    let mut data = vec![PoseidonInstruction::Verify as u8];
+    data.extend_from_slice(expected);
+    data.extend(inputs.concat());
+    Instruction::new_with_bytes(ID, &data, vec![])
+}
+
+#[test]
+fn test_host_poseidon() {
+    // Known hash from circomlib.
+    assert_eq!(
+        poseidon(&[field(1), field(2)]),
+        [
+            0x11, 0x5c, 0xc0, 0xf5, 0xe7, 0xd6, 0x90, 0x41, 0x3d, 0xf6, 0x4c, 0x6b, 0x96, 0x62,
+            0xe9, 0xcf, 0x2a, 0x36, 0x17, 0xf2, 0x74, 0x32, 0x45, 0x51, 0x9e, 0x19, 0x60, 0x7a,
+            0x44, 0x17, 0x18, 0x9a,
+        ]
+    );
+}
+
+#[test]
+fn test_split_inputs() {
+    for inputs in inputs() {
+        let data = inputs.concat();
+        let (split, len) = split_inputs(&data).unwrap();
+        assert_eq!(len, inputs.len());
+        for (split, input) in split.iter().zip(&inputs) {
+            assert_eq!(*split, input.as_slice());
+        }
+    }
+
+    // Inputs have to be complete, and there has to be at least one and at
+    // most `MAX_INPUTS` of them.
+    for len in [0, HASH_LEN - 1, HASH_LEN + 1, (MAX_INPUTS + 1) * HASH_LEN] {
+        assert_eq!(
+            split_inputs(&vec![0; len]).err(),
+            Some(pinocchio::program_error::ProgramError::InvalidInstructionData)
+        );
+    }
+}
+
+#[test]
+fn test_poseidon_hash() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/poseidon");
+
+    for inputs in inputs() {
+        mollusk.process_and_validate_instruction(
+            &instruction_hash(&inputs),
+            &[],
+            &[Check::success(), Check::return_data(&poseidon(&inputs))],
+        );
+    }
+}
+
+#[test]
+fn test_poseidon_verify() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/poseidon");
+
+    for inputs in inputs() {
+        let expected = poseidon(&inputs);
+        mollusk.process_and_validate_instruction(
+            &instruction_verify(&expected, &inputs),
+            &[],
+            &[Check::success()],
+        );
+
+        // The hash depends on the order of the inputs.
+        let mut reversed = inputs.clone();
+        reversed.reverse();
+        if reversed != inputs {
+            mollusk.process_and_validate_instruction(
+                &instruction_verify(&expected, &reversed),
+                &[],
+                &[Check::err(ProgramError::Custom(HASH_MISMATCH))],
+            );
+        }
+    }
+}
+
+#[test]
+fn test_poseidon_invalid() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/poseidon");
+
+    // Inputs have to be smaller than the modulus, otherwise `x` and `x + p`
+    // would have the same hash.
+    mollusk.process_and_validate_instruction(
+        &instruction_hash(&[MODULUS]),
+        &[],
+        &[Check::err(ProgramError::Custom(INPUT_OUT_OF_RANGE))],
+    );
+
+    for inputs in [vec![], vec![field(1); MAX_INPUTS + 1]] {
+        mollusk.process_and_validate_instruction(
+            &instruction_hash(&inputs),
+            &[],
+            &[Check::err(ProgramError::InvalidInstructionData)],
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Global config of a program, with feature flags and two-step authority
+//! rotation.
+//!
+//! Other programs can read the config by depending on this crate with the
+//! `no-entrypoint` feature and calling [`Config::load`], clients by calling
+//! [`Config::from_bytes`] on the fetched data.
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::{AccountInfo, Ref, RefMut},
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+program_entrypoint!(process_instruction);
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+no_allocator!();
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+nostd_panic_handler!();
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("HxyX3BnFveiTTnkVk7PsXCWU3iAd1D2RnHiZ77u4jjyt");
+
+/// Upgradeable BPF loader program ID.
+pub const LOADER_V3_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111");
+
+pub const CONFIG_SEED: &str = "config";
+
+/// Number of available feature flags.
+pub const MAX_FLAGS: u8 = 64;
+
+/// Errors returned by the program config program.
+#[repr(u32)]
+pub enum ProgramConfigError {
+    /// The flag index is not below [`MAX_FLAGS`].
+    InvalidFlag,
+    /// There is no pending authority to accept.
+    NoPendingAuthority,
+}
+
+impl From<ProgramConfigError> for ProgramError {
+    fn from(e: ProgramConfigError) -> Self {
+        Namespace::ProgramConfig.error(e as u32)
+    }
+}
+
+/// On-chain representation of the config, a singleton PDA.
+#[repr(C)]
+pub struct Config {
+    pub authority: Pubkey,
+    /// Authority proposed by the current one, which still has to accept the
+    /// role. All zeroes if there is none.
+    pub pending_authority: Pubkey,
+    /// Bitset of feature flags.
+    flags: [u8; 8],
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Config, size = 80, align = 1, {
+    authority: 0,
+    pending_authority: 32,
+    flags: 64,
+    bump: 72,
+});
+
+impl Config {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Borrows the config from its account.
+    ///
+    /// The program creates no other accounts than the config PDA, so any
+    /// account owned by the program with the right size is the config.
+    pub fn load(account: &AccountInfo) -> Result<Ref<'_, Self>, ProgramError> {
+        require_owned_by(account, &ID)?;
+        let data = account.try_borrow_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Ref::map(data, |data| unsafe { &*data.as_ptr().cast() }))
+    }
+
+    /// Casts the data of the config account, e.g. fetched by a client.
+    pub fn from_bytes(data: &[u8]) -> Result<&Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        // All fields are byte arrays, so the config can be cast from any
+        // address.
+        Ok(unsafe { &*data.as_ptr().cast() })
+    }
+
+    /// Borrows the config mutably from its account.
+    fn load_mut(account: &AccountInfo) -> Result<RefMut<'_, Self>, ProgramError> {
+        require_owned_by(account, &ID)?;
+        let data = account.try_borrow_mut_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(RefMut::map(data, |data| unsafe {
+            &mut *data.as_mut_ptr().cast()
+        }))
+    }
+
+    pub fn flags(&self) -> u64 {
+        u64::from_le_bytes(self.flags)
+    }
+
+    /// Returns whether the flag with the given index is set. Unknown flags
+    /// are never set.
+    pub fn is_enabled(&self, flag: u8) -> bool {
+        flag < MAX_FLAGS && self.flags() & (1 << flag) != 0
+    }
+
+    fn set_flag(&mut self, flag: u8, enabled: bool) {
+        let flags = if enabled {
+            self.flags() | (1 << flag)
+        } else {
+            self.flags() & !(1 << flag)
+        };
+        self.flags = flags.to_le_bytes();
+    }
+}
+
+/// Program config program instruction discriminators.
+#[repr(u8)]
+pub enum ProgramConfigInstruction {
+    /// Creates the config. Only the upgrade authority of the program can do
+    /// it, otherwise anyone could front-run the deployer.
+    Initialize,
+    /// Sets or clears a feature flag.
+    SetFlag,
+    /// Proposes a new authority, or cancels the proposal with an all-zero
+    /// key.
+    TransferAuthority,
+    /// Accepts the authority role by the proposed authority.
+    AcceptAuthority,
+}
+
+impl TryFrom<&u8> for ProgramConfigInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::SetFlag),
+            2 => Ok(Self::TransferAuthority),
+            3 => Ok(Self::AcceptAuthority),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct SetFlagInstructionData {
+    pub flag: u8,
+    pub enabled: u8,
+}
+
+impl SetFlagInstructionData {
+    pub fn new(flag: u8, enabled: bool) -> Self {
+        Self {
+            flag,
+            enabled: enabled as u8,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct TransferAuthorityInstructionData {
+    pub new_authority: Pubkey,
+}
+
+impl TransferAuthorityInstructionData {
+    pub fn new(new_authority: Pubkey) -> Self {
+        Self { new_authority }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = ProgramConfigInstruction::try_from(instruction)?;
+
+    match instruction {
+        ProgramConfigInstruction::Initialize => process_initialize(accounts, instruction_data),
+        ProgramConfigInstruction::SetFlag => process_set_flag(accounts, instruction_data),
+        ProgramConfigInstruction::TransferAuthority => {
+            process_transfer_authority(accounts, instruction_data)
+        }
+        ProgramConfigInstruction::AcceptAuthority => process_accept_authority(accounts),
+    }
+}
+
+/// Returns the upgrade authority of `program`, read from its ProgramData
+/// account.
+fn upgrade_authority(
+    program: &AccountInfo,
+    programdata: &AccountInfo,
+) -> Result<Option<Pubkey>, ProgramError> {
+    if !program.is_owned_by(&LOADER_V3_ID) || !programdata.is_owned_by(&LOADER_V3_ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // The program account data is a bincode-serialized
+    // `UpgradeableLoaderState::Program`:
+    // -  [0..4 ]: enum discriminator, `2` for `Program`
+    // -  [4..36]: ProgramData address
+    let data = program.try_borrow_data()?;
+    if data.len() < 36 || data[..4] != 2u32.to_le_bytes() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !keys_eq(&data[4..36], programdata.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // `UpgradeableLoaderState::ProgramData`:
+    // -  [0..4  ]: enum discriminator, `3` for `ProgramData`
+    // -  [4..12 ]: slot of the last deployment
+    // -  [12    ]: `Option` tag of the upgrade authority
+    // -  [13..45]: upgrade authority
+    let data = programdata.try_borrow_data()?;
+    if data.len() < 45 || data[..4] != 3u32.to_le_bytes() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if data[12] == 0 {
+        return Ok(None);
+    }
+    let mut authority = [0; 32];
+    authority.copy_from_slice(&data[13..45]);
+    Ok(Some(authority))
+}
+
+/// Creates the config, with `authority` as its authority and no flags set.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, config, program, programdata, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+    if !keys_eq(program.key(), &ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if upgrade_authority(program, programdata)?.as_ref() != Some(authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `config`.
+    let config_pda =
+        create_program_address(&[CONFIG_SEED.as_bytes(), &[instruction_data.bump]], &ID)?;
+    if !keys_eq(config.key(), &config_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the config PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [Seed::from(CONFIG_SEED.as_bytes()), Seed::from(&bump)];
+    CreateAccount {
+        from: authority,
+        to: config,
+        lamports: Rent::get()?.minimum_balance(Config::LEN),
+        space: Config::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the config PDA.
+    let mut data = config.try_borrow_mut_data()?;
+    let data: &mut Config = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the config.
+    data.authority = *authority.key();
+    data.bump = instruction_data.bump;
+
+    info!("Initialized config");
+
+    Ok(())
+}
+
+/// Sets or clears a feature flag.
+pub fn process_set_flag(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = SetFlagInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.flag >= MAX_FLAGS {
+        return Err(ProgramConfigError::InvalidFlag.into());
+    }
+    let enabled = match instruction_data.enabled {
+        0 => false,
+        1 => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    // Deserialize the config.
+    let mut data = Config::load_mut(config)?;
+
+    if !keys_eq(&data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    data.set_flag(instruction_data.flag, enabled);
+
+    info!("Set flag {} to {}", instruction_data.flag, enabled as u8);
+
+    Ok(())
+}
+
+/// Proposes a new authority. The current authority stays in charge until
+/// the new one accepts, so a typo in the key can't lock the config.
+pub fn process_transfer_authority(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = TransferAuthorityInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the config.
+    let mut data = Config::load_mut(config)?;
+
+    if !keys_eq(&data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    data.pending_authority = instruction_data.new_authority;
+
+    if instruction_data.new_authority == Pubkey::default() {
+        info!("Cancelled authority transfer");
+    } else {
+        info!("Proposed new authority");
+    }
+
+    Ok(())
+}
+
+/// Makes the pending authority the authority of the config.
+pub fn process_accept_authority(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [pending_authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(pending_authority)?;
+
+    // Deserialize the config.
+    let mut data = Config::load_mut(config)?;
+
+    if data.pending_authority == Pubkey::default() {
+        return Err(ProgramConfigError::NoPendingAuthority.into());
+    }
+    if !keys_eq(&data.pending_authority, pending_authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    data.authority = data.pending_authority;
+    data.pending_authority = Pubkey::default();
+
+    info!("Accepted authority");
+
+    Ok(())
+}
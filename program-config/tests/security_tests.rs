@@ -0,0 +1,181 @@
+//! Attacks on the program config program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{keyed_account_for_system_program, loader_keys::LOADER_V3},
+    result::Check,
+    Mollusk,
+};
+use program_config::{Config, InitializeInstructionData, ProgramConfigInstruction, CONFIG_SEED};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(program_config::ID);
+
+/// Offset of the pending authority in the config.
+const PENDING_AUTHORITY_OFFSET: usize = 32;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: ProgramConfigInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<ProgramConfigInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns an account of the upgradeable loader with the given state.
+fn loader_account(mollusk: &Mollusk, state: &UpgradeableLoaderState) -> Account {
+    let data = bincode::serialize(state).unwrap();
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &LOADER_V3,
+    );
+    account.data = data;
+    account.executable = matches!(state, UpgradeableLoaderState::Program { .. });
+    account
+}
+
+/// Returns a config of `authority`, which proposed `pending_authority`.
+fn config_account(mollusk: &Mollusk, authority: &Pubkey, pending_authority: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[PENDING_AUTHORITY_OFFSET..PENDING_AUTHORITY_OFFSET + 32]
+        .copy_from_slice(pending_authority.as_ref());
+    account
+}
+
+/// Accounts of a config whose authority proposed a new one, and of an
+/// attacker with a program of their own, which they can upgrade.
+struct Setup {
+    mollusk: Mollusk,
+    config: Pubkey,
+    pending_authority: Pubkey,
+    attacker: Pubkey,
+    attacker_program: Pubkey,
+    attacker_programdata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/program_config");
+
+        let authority = Pubkey::new_unique();
+        let pending_authority = Pubkey::new_unique();
+        let (config, _) = Pubkey::find_program_address(&[CONFIG_SEED.as_bytes()], &ID);
+        let attacker = Pubkey::new_unique();
+        let attacker_program = Pubkey::new_unique();
+        let (attacker_programdata, _) =
+            Pubkey::find_program_address(&[attacker_program.as_ref()], &LOADER_V3);
+
+        let accounts = vec![
+            (authority, Account::default()),
+            (pending_authority, Account::default()),
+            (
+                config,
+                config_account(&mollusk, &authority, &pending_authority),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_program,
+                loader_account(
+                    &mollusk,
+                    &UpgradeableLoaderState::Program {
+                        programdata_address: attacker_programdata,
+                    },
+                ),
+            ),
+            (
+                attacker_programdata,
+                loader_account(
+                    &mollusk,
+                    &UpgradeableLoaderState::ProgramData {
+                        slot: 0,
+                        upgrade_authority_address: Some(attacker),
+                    },
+                ),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            config,
+            pending_authority,
+            attacker,
+            attacker_program,
+            attacker_programdata,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_pending_authority_signature() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.pending_authority, false),
+        AccountMeta::new(setup.config, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &[ProgramConfigInstruction::AcceptAuthority as u8],
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the config before the deployer does, proving the
+/// upgrade authority over their own program instead of the config program.
+#[test]
+fn test_initialize_with_other_program() {
+    let setup = Setup::new();
+    let config = setup.config;
+    let setup = setup.with(config, Account::default());
+    let (_, bump) = Pubkey::find_program_address(&[CONFIG_SEED.as_bytes()], &ID);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(config, false),
+        AccountMeta::new_readonly(setup.attacker_program, false),
+        AccountMeta::new_readonly(setup.attacker_programdata, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            ProgramConfigInstruction::Initialize,
+            &InitializeInstructionData::new(bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::IncorrectProgramId);
+}
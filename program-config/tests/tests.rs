@@ -0,0 +1,506 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::{keyed_account_for_system_program, loader_keys::LOADER_V3},
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use program_config::{
+    Config, InitializeInstructionData, ProgramConfigError, ProgramConfigInstruction,
+    SetFlagInstructionData, TransferAuthorityInstructionData, CONFIG_SEED, MAX_FLAGS,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(program_config::ID);
+
+/// Offsets of the config fields.
+const PENDING_AUTHORITY_OFFSET: usize = 32;
+const FLAGS_OFFSET: usize = 64;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: ProgramConfigInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<ProgramConfigInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED.as_bytes()], &ID)
+}
+
+fn programdata_address(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program.as_ref()], &LOADER_V3).0
+}
+
+fn instruction_initialize(
+    authority: &Pubkey,
+    config: &Pubkey,
+    programdata: &Pubkey,
+    bump: u8,
+) -> Instruction {
+    let data = InitializeInstructionData::new(bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*config, false),
+        AccountMeta::new_readonly(ID, false),
+        AccountMeta::new_readonly(*programdata, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(ProgramConfigInstruction::Initialize, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_set_flag(
+    authority: &Pubkey,
+    config: &Pubkey,
+    flag: u8,
+    enabled: bool,
+) -> Instruction {
+    let data = SetFlagInstructionData::new(flag, enabled);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*config, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(ProgramConfigInstruction::SetFlag, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_transfer_authority(
+    authority: &Pubkey,
+    config: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    let data = TransferAuthorityInstructionData::new(new_authority.to_bytes());
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*config, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(ProgramConfigInstruction::TransferAuthority, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_accept_authority(pending_authority: &Pubkey, config: &Pubkey) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*pending_authority, true),
+        AccountMeta::new(*config, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &[ProgramConfigInstruction::AcceptAuthority as u8],
+        ix_accounts,
+    )
+}
+
+/// Creates an account of the upgradeable loader with the given state.
+fn loader_account(mollusk: &Mollusk, state: &UpgradeableLoaderState) -> Account {
+    let data = bincode::serialize(state).unwrap();
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &LOADER_V3,
+    );
+    account.data = data;
+    account.executable = matches!(state, UpgradeableLoaderState::Program { .. });
+    account
+}
+
+/// Creates the program account and the ProgramData account of the config
+/// program, with the given upgrade authority.
+fn program_accounts(
+    mollusk: &Mollusk,
+    upgrade_authority: Option<Pubkey>,
+) -> [(Pubkey, Account); 2] {
+    let programdata = programdata_address(&ID);
+    [
+        (
+            ID,
+            loader_account(
+                mollusk,
+                &UpgradeableLoaderState::Program {
+                    programdata_address: programdata,
+                },
+            ),
+        ),
+        (
+            programdata,
+            loader_account(
+                mollusk,
+                &UpgradeableLoaderState::ProgramData {
+                    slot: 0,
+                    upgrade_authority_address: upgrade_authority,
+                },
+            ),
+        ),
+    ]
+}
+
+/// Creates an initialized config account.
+fn config_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    pending_authority: Option<&Pubkey>,
+    flags: u64,
+) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    if let Some(pending_authority) = pending_authority {
+        account.data[PENDING_AUTHORITY_OFFSET..PENDING_AUTHORITY_OFFSET + 32]
+            .copy_from_slice(pending_authority.as_ref());
+    }
+    account.data[FLAGS_OFFSET..FLAGS_OFFSET + 8].copy_from_slice(&flags.to_le_bytes());
+    account
+}
+
+#[test]
+fn test_program_config_lifecycle() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/program_config");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let authority = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+    let (config, config_bump) = config_address();
+    let [program_account, programdata_account] = program_accounts(&mollusk, Some(authority));
+
+    mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize(&authority, &config, &programdata_account.0, config_bump),
+                &[
+                    Check::success(),
+                    Check::account(&config)
+                        .owner(&ID)
+                        .space(Config::LEN)
+                        .data_slice(0, authority.as_ref())
+                        .data_slice(PENDING_AUTHORITY_OFFSET, &[0; 32])
+                        .data_slice(FLAGS_OFFSET, &0u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_set_flag(&authority, &config, 0, true),
+                &[Check::success()],
+            ),
+            (
+                &instruction_set_flag(&authority, &config, MAX_FLAGS - 1, true),
+                &[
+                    Check::success(),
+                    Check::account(&config)
+                        .data_slice(FLAGS_OFFSET, &(1u64 | 1 << 63).to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_set_flag(&authority, &config, 0, false),
+                &[
+                    Check::success(),
+                    Check::account(&config)
+                        .data_slice(FLAGS_OFFSET, &(1u64 << 63).to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_transfer_authority(&authority, &config, &new_authority),
+                &[
+                    Check::success(),
+                    // The old authority stays until the new one accepts.
+                    Check::account(&config)
+                        .data_slice(0, authority.as_ref())
+                        .data_slice(PENDING_AUTHORITY_OFFSET, new_authority.as_ref())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_accept_authority(&new_authority, &config),
+                &[
+                    Check::success(),
+                    Check::account(&config)
+                        .data_slice(0, new_authority.as_ref())
+                        .data_slice(PENDING_AUTHORITY_OFFSET, &[0; 32])
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_set_flag(&new_authority, &config, 1, true),
+                &[
+                    Check::success(),
+                    Check::account(&config)
+                        .data_slice(FLAGS_OFFSET, &(1u64 << 63 | 1 << 1).to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &[
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (config, Account::default()),
+            program_account,
+            programdata_account,
+            (system_program, system_account),
+            (new_authority, Account::default()),
+        ],
+    );
+}
+
+#[test]
+fn test_program_config_initialize_not_upgrade_authority() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/program_config");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let upgrade_authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let (config, config_bump) = config_address();
+
+    // Someone else than the upgrade authority, or anyone if the program is
+    // immutable, can't front-run the initialization.
+    for upgrade_authority in [Some(upgrade_authority), None] {
+        let [program_account, programdata_account] = program_accounts(&mollusk, upgrade_authority);
+        mollusk.process_and_validate_instruction(
+            &instruction_initialize(&attacker, &config, &programdata_account.0, config_bump),
+            &[
+                (
+                    attacker,
+                    Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+                ),
+                (config, Account::default()),
+                program_account,
+                programdata_account,
+                (system_program, system_account.clone()),
+            ],
+            &[Check::err(ProgramError::IllegalOwner)],
+        );
+    }
+}
+
+#[test]
+fn test_program_config_initialize_fake_programdata() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/program_config");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let attacker = Pubkey::new_unique();
+    let (config, config_bump) = config_address();
+    let [program_account, (programdata, _)] =
+        program_accounts(&mollusk, Some(Pubkey::new_unique()));
+
+    // ProgramData of a program the attacker controls.
+    let fake_programdata = Pubkey::new_unique();
+    let fake_programdata_account = loader_account(
+        &mollusk,
+        &UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(attacker),
+        },
+    );
+    // Account with the right layout, but not owned by the loader.
+    let mut forged_programdata_account = fake_programdata_account.clone();
+    forged_programdata_account.owner = attacker;
+
+    let cases = [
+        (
+            (fake_programdata, fake_programdata_account),
+            ProgramError::InvalidAccountData,
+        ),
+        (
+            (programdata, forged_programdata_account),
+            ProgramError::IncorrectProgramId,
+        ),
+    ];
+    for (programdata_account, expected) in cases {
+        mollusk.process_and_validate_instruction(
+            &instruction_initialize(&attacker, &config, &programdata_account.0, config_bump),
+            &[
+                (
+                    attacker,
+                    Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+                ),
+                (config, Account::default()),
+                program_account.clone(),
+                programdata_account,
+                (system_program, system_account.clone()),
+            ],
+            &[Check::err(expected)],
+        );
+    }
+}
+
+#[test]
+fn test_program_config_wrong_authority() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/program_config");
+
+    let authority = Pubkey::new_unique();
+    let pending_authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let (config, _) = config_address();
+    let config_account = config_account(&mollusk, &authority, Some(&pending_authority), 0);
+
+    // Neither an attacker, nor the pending authority before accepting, can
+    // change the config.
+    for signer in [attacker, pending_authority] {
+        for instruction in [
+            instruction_set_flag(&signer, &config, 0, true),
+            instruction_transfer_authority(&signer, &config, &signer),
+        ] {
+            mollusk.process_and_validate_instruction(
+                &instruction,
+                &[
+                    (signer, Account::default()),
+                    (config, config_account.clone()),
+                ],
+                &[Check::err(ProgramError::IllegalOwner)],
+            );
+        }
+    }
+
+    // Only the pending authority can accept.
+    mollusk.process_and_validate_instruction(
+        &instruction_accept_authority(&attacker, &config),
+        &[
+            (attacker, Account::default()),
+            (config, config_account.clone()),
+        ],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+
+    // The authority has to sign.
+    let mut instruction = instruction_set_flag(&authority, &config, 0, true);
+    instruction.accounts[0].is_signer = false;
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[(authority, Account::default()), (config, config_account)],
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+#[test]
+fn test_program_config_fake_config() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/program_config");
+
+    let attacker = Pubkey::new_unique();
+    let (config, _) = config_address();
+
+    // A config with the attacker as authority, but not owned by the program.
+    let mut fake_config_account = config_account(&mollusk, &attacker, None, 0);
+    fake_config_account.owner = attacker;
+
+    mollusk.process_and_validate_instruction(
+        &instruction_set_flag(&attacker, &config, 0, true),
+        &[
+            (attacker, Account::default()),
+            (config, fake_config_account),
+        ],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+#[test]
+fn test_program_config_cancel_transfer() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/program_config");
+
+    let authority = Pubkey::new_unique();
+    let pending_authority = Pubkey::new_unique();
+    let (config, _) = config_address();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_transfer_authority(&authority, &config, &Pubkey::default()),
+                &[
+                    Check::success(),
+                    Check::account(&config)
+                        .data_slice(PENDING_AUTHORITY_OFFSET, &[0; 32])
+                        .build(),
+                ],
+            ),
+            // The cancelled proposal can't be accepted anymore.
+            (
+                &instruction_accept_authority(&pending_authority, &config),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::ProgramConfig.code(ProgramConfigError::NoPendingAuthority as u32),
+                ))],
+            ),
+        ],
+        &[
+            (authority, Account::default()),
+            (
+                config,
+                config_account(&mollusk, &authority, Some(&pending_authority), 0),
+            ),
+            (pending_authority, Account::default()),
+        ],
+    );
+    assert!(matches!(
+        res.program_result,
+        ProgramResult::Failure(ProgramError::Custom(_))
+    ));
+}
+
+#[test]
+fn test_program_config_invalid_flag() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/program_config");
+
+    let authority = Pubkey::new_unique();
+    let (config, _) = config_address();
+
+    mollusk.process_and_validate_instruction(
+        &instruction_set_flag(&authority, &config, MAX_FLAGS, true),
+        &[
+            (authority, Account::default()),
+            (config, config_account(&mollusk, &authority, None, 0)),
+        ],
+        &[Check::err(ProgramError::Custom(
+            Namespace::ProgramConfig.code(ProgramConfigError::InvalidFlag as u32),
+        ))],
+    );
+}
+
+/// Clients decode the fields of the config from its data.
+#[test]
+fn test_program_config_from_bytes() {
+    let authority = Pubkey::new_unique();
+    let pending_authority = Pubkey::new_unique();
+    let account = config_account(
+        &Mollusk::default(),
+        &authority,
+        Some(&pending_authority),
+        1 << 3,
+    );
+
+    let config = Config::from_bytes(&account.data).unwrap();
+    assert_eq!(config.authority, authority.to_bytes());
+    assert_eq!(config.pending_authority, pending_authority.to_bytes());
+    assert_eq!(config.flags(), 1 << 3);
+    assert!(config.is_enabled(3));
+    assert!(!config.is_enabled(2));
+
+    assert!(Config::from_bytes(&account.data[1..]).is_err());
+}
@@ -0,0 +1,66 @@
+//! Client of the {{project-name}} program.
+//!
+//! Builds its instructions and decodes its accounts off-chain.
+
+use common::AccountData;
+use pinocchio::program_error::ProgramError;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::system_program;
+
+pub use {{crate_name}}::{
+    InitializeInstructionData, State, UpdateInstructionData, {{crate_name | pascal_case}}Instruction,
+    STATE_SEED,
+};
+
+/// Address of the {{project-name}} program.
+pub const ID: Pubkey = Pubkey::new_from_array({{crate_name}}::ID);
+
+/// Returns the state PDA of `authority` and its bump.
+pub fn find_state_address(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STATE_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Builds an instruction creating the state of `authority`.
+pub fn initialize(authority: &Pubkey) -> Instruction {
+    let (state, bump) = find_state_address(authority);
+
+    let mut data = {{crate_name | pascal_case}}Instruction::Initialize
+        .discriminator()
+        .to_vec();
+    data.extend_from_slice(&InitializeInstructionData::new(bump).to_bytes());
+
+    Instruction::new_with_bytes(
+        ID,
+        &data,
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(state, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+/// Builds an instruction setting the value of the state of `authority`.
+pub fn update(authority: &Pubkey, value: u64) -> Instruction {
+    let (state, _) = find_state_address(authority);
+
+    let mut data = {{crate_name | pascal_case}}Instruction::Update
+        .discriminator()
+        .to_vec();
+    data.extend_from_slice(&UpdateInstructionData::new(value).to_bytes());
+
+    Instruction::new_with_bytes(
+        ID,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state, false),
+        ],
+    )
+}
+
+/// Decodes the data of a state account.
+pub fn try_from_account_data(data: &[u8]) -> Result<State, ProgramError> {
+    State::read(data)
+}
@@ -0,0 +1,46 @@
+//! Instructions of the {{project-name}} program.
+
+use macros::{InstructionData, InstructionDiscriminator};
+
+/// {{project-name}} program instruction discriminators.
+#[derive(InstructionDiscriminator)]
+#[repr(u8)]
+pub enum {{crate_name | pascal_case}}Instruction {
+    /// Creates the state of the authority. Instruction data:
+    /// [`InitializeInstructionData`].
+    ///
+    /// ### Accounts:
+    ///   0. `[WRITE, SIGNER]` Authority and payer
+    ///   1. `[WRITE]` State PDA
+    ///   2. `[]` System program
+    Initialize,
+    /// Sets the value of the state. Instruction data:
+    /// [`UpdateInstructionData`].
+    ///
+    /// ### Accounts:
+    ///   0. `[SIGNER]` Authority
+    ///   1. `[WRITE]` State PDA
+    Update,
+}
+
+#[derive(Clone, Copy, InstructionData)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(Clone, Copy, InstructionData)]
+pub struct UpdateInstructionData {
+    pub value: u64,
+}
+
+impl UpdateInstructionData {
+    pub fn new(value: u64) -> Self {
+        Self { value }
+    }
+}
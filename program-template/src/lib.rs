@@ -0,0 +1,140 @@
+//! {{project-name}} program.
+//!
+//! Keeps a value in a PDA of its authority, which only the authority can
+//! update. Replace it with what the example demonstrates.
+
+#![no_std]
+
+use common::{
+    check_pda,
+    checks::{require_program, require_program_id, require_signer},
+    create::create_account,
+    info, keys_eq, AccountData,
+};
+use errors::SharedError;
+use pinocchio::{
+    account_info::AccountInfo,
+    entrypoint::{InstructionContext, MaybeAccount},
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    ProgramResult,
+};
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+use pinocchio::{lazy_program_entrypoint, no_allocator, nostd_panic_handler};
+
+pub mod instruction;
+pub mod state;
+
+pub use instruction::{
+    InitializeInstructionData, UpdateInstructionData, {{crate_name | pascal_case}}Instruction,
+};
+pub use state::{State, STATE_SEED};
+
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+lazy_program_entrypoint!(process_instruction);
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+no_allocator!();
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+nostd_panic_handler!();
+#[cfg(all(target_os = "solana", not(feature = "no-entrypoint")))]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("{{program_id}}");
+
+/// Entrypoint of the program.
+pub fn process_instruction(mut context: InstructionContext) -> ProgramResult {
+    // The first account is the authority of the state.
+    let MaybeAccount::Account(authority) = context.next_account()? else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    require_signer(&authority)?;
+
+    // The second account is the state PDA, which can't be the authority.
+    let MaybeAccount::Account(state) = context.next_account()? else {
+        return Err(SharedError::DuplicateAccount.into());
+    };
+
+    // The third account is the system program, which only the
+    // initialization takes.
+    let system_program = if context.remaining() > 0 {
+        let MaybeAccount::Account(system_program) = context.next_account()? else {
+            return Err(ProgramError::IncorrectProgramId);
+        };
+        require_program(&system_program, &pinocchio_system::ID)?;
+        Some(system_program)
+    } else {
+        None
+    };
+
+    // Check that the program runs under its declared address, which its
+    // PDAs are derived from.
+    require_program_id(context.program_id()?, &ID)?;
+
+    // Deserialize instruction and instruction data.
+    let (instruction, instruction_data) =
+        {{crate_name | pascal_case}}Instruction::split(context.instruction_data()?)?;
+
+    match instruction {
+        {{crate_name | pascal_case}}Instruction::Initialize => {
+            if system_program.is_none() {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            }
+            let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+            process_initialize(&authority, &state, instruction_data.bump)
+        }
+        {{crate_name | pascal_case}}Instruction::Update => {
+            let instruction_data = UpdateInstructionData::from_bytes(instruction_data)?;
+            process_update(&authority, &state, instruction_data.value)
+        }
+    }
+}
+
+/// Creates the state of `authority`.
+pub fn process_initialize(authority: &AccountInfo, state: &AccountInfo, bump: u8) -> ProgramResult {
+    check_pda(
+        &[STATE_SEED.as_bytes(), authority.key()],
+        bump,
+        state.key(),
+        &ID,
+    )?;
+
+    // Create the PDA, signing for it with its seeds, unless the address is
+    // already in use.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(STATE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    create_account(
+        authority,
+        state,
+        State::LEN,
+        &ID,
+        &[Signer::from(&seeds)],
+    )?;
+
+    // Deserialize the new state PDA, writing its prefix.
+    let mut data = State::init(state)?;
+    data.authority = *authority.key();
+
+    info!("Created the state");
+
+    Ok(())
+}
+
+/// Sets the value of the state of `authority`.
+pub fn process_update(authority: &AccountInfo, state: &AccountInfo, value: u64) -> ProgramResult {
+    // Deserialize the state PDA, checking that it's owned by the program.
+    // The program only creates states at the PDA of their authority, so
+    // checking the stored authority is enough.
+    let mut data = State::load_mut(state)?;
+    if !keys_eq(&data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    data.value = value;
+
+    info!("Set the value to {}", value);
+
+    Ok(())
+}
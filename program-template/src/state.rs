@@ -0,0 +1,25 @@
+//! State of the {{project-name}} accounts.
+
+#[cfg(feature = "bytemuck")]
+use bytemuck::{Pod, Zeroable};
+use common::{assert_layout, AccountData};
+use pinocchio::pubkey::Pubkey;
+
+use crate::ID;
+
+pub const STATE_SEED: &str = "state";
+
+/// On-chain representation of the state of an authority.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "bytemuck", derive(Pod, Zeroable))]
+#[repr(C)]
+pub struct State {
+    pub authority: Pubkey,
+    pub value: u64,
+}
+
+unsafe impl AccountData for State {
+    const OWNER: Pubkey = ID;
+}
+
+assert_layout!(State, size = 40, align = 8, { authority: 0, value: 32 });
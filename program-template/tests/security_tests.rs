@@ -0,0 +1,144 @@
+//! Attacks on the {{project-name}} program, each rejected with its own
+//! error. Add the attacks on the accounts of the example next to them.
+
+use errors::{Namespace, SharedError};
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_instruction::Instruction;
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::{funded_account, state_account};
+
+use {{crate_name}}_client::{find_state_address, State, ID};
+
+fn mollusk() -> Mollusk {
+    Mollusk::new(&ID, "target/deploy/{{crate_name}}")
+}
+
+/// Returns the state of `authority`, with a value of zero.
+fn authority_state(authority: &Pubkey) -> State {
+    State {
+        authority: authority.to_bytes(),
+        value: 0,
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let authority = Pubkey::new_unique();
+    let (state, _) = find_state_address(&authority);
+
+    let mut instruction = {{crate_name}}_client::update(&authority, 42);
+    instruction.accounts[0].is_signer = false;
+
+    mollusk().process_and_validate_instruction(
+        &instruction,
+        &[
+            (authority, funded_account(LAMPORTS_PER_SOL)),
+            (state, state_account(&authority_state(&authority))),
+        ],
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+}
+
+/// The attacker creates the state of the authority before the authority
+/// does, with their own bump.
+#[test]
+fn test_initialize_state_of_other_authority() {
+    let authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let (state, _) = find_state_address(&authority);
+
+    let mut instruction = {{crate_name}}_client::initialize(&attacker);
+    instruction.accounts[1].pubkey = state;
+
+    mollusk().process_and_validate_instruction(
+        &instruction,
+        &[
+            (attacker, funded_account(LAMPORTS_PER_SOL)),
+            (state, funded_account(0)),
+            keyed_account_for_system_program(),
+        ],
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
+
+/// A program posing as the system program, which would be invoked with the
+/// signature of the state PDA.
+#[test]
+fn test_fake_system_program() {
+    let authority = Pubkey::new_unique();
+    let (state, _) = find_state_address(&authority);
+    let system_program = Pubkey::new_unique();
+
+    let mut instruction = {{crate_name}}_client::initialize(&authority);
+    instruction.accounts[2].pubkey = system_program;
+
+    mollusk().process_and_validate_instruction(
+        &instruction,
+        &[
+            (authority, funded_account(LAMPORTS_PER_SOL)),
+            (state, funded_account(0)),
+            (system_program, funded_account(0)),
+        ],
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+}
+
+/// The authority passed as its own state, so the program would write the
+/// state into the account paying for it.
+#[test]
+fn test_authority_as_state() {
+    let authority = Pubkey::new_unique();
+
+    let mut instruction = {{crate_name}}_client::update(&authority, 42);
+    instruction.accounts[1].pubkey = authority;
+
+    mollusk().process_and_validate_instruction(
+        &instruction,
+        &[(authority, funded_account(LAMPORTS_PER_SOL))],
+        &[Check::err(ProgramError::Custom(
+            Namespace::Shared.code(SharedError::DuplicateAccount as u32),
+        ))],
+    );
+}
+
+/// A state of the attacker with the layout of the program, owned by
+/// another program.
+#[test]
+fn test_update_spoofed_state() {
+    let attacker = Pubkey::new_unique();
+    let (state, _) = find_state_address(&attacker);
+    let mut state_account = state_account(&authority_state(&attacker));
+    state_account.owner = Pubkey::new_unique();
+
+    mollusk().process_and_validate_instruction(
+        &{{crate_name}}_client::update(&attacker, 42),
+        &[
+            (attacker, funded_account(LAMPORTS_PER_SOL)),
+            (state, state_account),
+        ],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+/// A copy of the program deployed at another address, whose PDAs wouldn't
+/// be the ones of the program, refuses to run.
+#[test]
+fn test_redeployed_program() {
+    let redeployed = Pubkey::new_unique();
+    let mollusk = Mollusk::new(&redeployed, "target/deploy/{{crate_name}}");
+
+    let authority = Pubkey::new_unique();
+    let (state, _) = find_state_address(&authority);
+    let update = {{crate_name}}_client::update(&authority, 42);
+
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(redeployed, &update.data, update.accounts),
+        &[
+            (authority, funded_account(LAMPORTS_PER_SOL)),
+            (state, state_account(&authority_state(&authority))),
+        ],
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+}
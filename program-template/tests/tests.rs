@@ -0,0 +1,83 @@
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use test_utils::{assertions::account_state, funded_account, state_account};
+
+use {{crate_name}}_client::{find_state_address, State, ID};
+
+fn mollusk() -> Mollusk {
+    Mollusk::new(&ID, "target/deploy/{{crate_name}}")
+}
+
+#[test]
+fn test_initialize() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let (state, _) = find_state_address(&authority);
+
+    let res = mollusk.process_and_validate_instruction(
+        &{{crate_name}}_client::initialize(&authority),
+        &[
+            (authority, funded_account(LAMPORTS_PER_SOL)),
+            (state, funded_account(0)),
+            keyed_account_for_system_program(),
+        ],
+        &[Check::success()],
+    );
+
+    let data: State = account_state(&res, &state);
+    assert_eq!(data.authority, authority.to_bytes());
+    assert_eq!(data.value, 0);
+}
+
+#[test]
+fn test_update() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let (state, _) = find_state_address(&authority);
+    let state_account = state_account(&State {
+        authority: authority.to_bytes(),
+        value: 0,
+    });
+
+    let res = mollusk.process_and_validate_instruction(
+        &{{crate_name}}_client::update(&authority, 42),
+        &[
+            (authority, funded_account(LAMPORTS_PER_SOL)),
+            (state, state_account),
+        ],
+        &[Check::success()],
+    );
+
+    let data: State = account_state(&res, &state);
+    assert_eq!(data.value, 42);
+}
+
+#[test]
+fn test_update_wrong_authority() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let (state, _) = find_state_address(&authority);
+    let state_account = state_account(&State {
+        authority: authority.to_bytes(),
+        value: 0,
+    });
+
+    // The attacker signs, passing the state of another authority.
+    let mut instruction = {{crate_name}}_client::update(&attacker, 42);
+    instruction.accounts[1].pubkey = state;
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[
+            (attacker, funded_account(LAMPORTS_PER_SOL)),
+            (state, state_account),
+        ],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
@@ -0,0 +1,289 @@
+//! Freshness checks with the SlotHashes sysvar.
+//!
+//! Callers prove that they signed an action recently by including a slot
+//! and its hash, which they can only know once the slot was produced. The
+//! program checks the pair against the SlotHashes sysvar before executing
+//! the action:
+//!
+//! * The slot has to be at most [`MAX_SLOT_AGE`] slots old, so signed
+//!   actions expire, similarly to transactions referencing a blockhash.
+//! * The hash has to match the one in the sysvar, so actions can't be
+//!   prepared for future slots.
+//! * The slot has to be newer than the one of the previous action of the
+//!   caller, so every proof can be used only once.
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::{mem, slice};
+
+use common::checks::{require_key_eq, require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("EbmeYQ8kZdXVAzyTykKMerVQHFsvGv2LqN2W8i1YK1sB");
+
+pub const RECORD_SEED: &str = "record";
+
+/// SlotHashes sysvar ID.
+pub const SLOT_HASHES_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("SysvarS1otHashes111111111111111111111111111");
+
+/// Maximum age of the slot of a proof, in slots.
+pub const MAX_SLOT_AGE: u64 = 150;
+
+/// Errors returned by the proof of recency program.
+#[repr(u32)]
+pub enum ProofOfRecencyError {
+    /// The slot is older than `MAX_SLOT_AGE`.
+    StaleSlot,
+    /// The slot has no entry in the SlotHashes sysvar, either because it
+    /// was skipped or because it wasn't produced yet.
+    UnknownSlot,
+    /// The hash doesn't match the one of the slot.
+    HashMismatch,
+    /// The slot isn't newer than the one of the previous action.
+    Replayed,
+}
+
+impl From<ProofOfRecencyError> for ProgramError {
+    fn from(e: ProofOfRecencyError) -> Self {
+        Namespace::ProofOfRecency.error(e as u32)
+    }
+}
+
+/// Entry of the SlotHashes sysvar.
+#[repr(C)]
+pub struct SlotHash {
+    pub slot: u64,
+    pub hash: [u8; 32],
+}
+
+assert_layout!(SlotHash, size = 40, align = 8, { slot: 0, hash: 8 });
+
+/// On-chain record of the actions of a user.
+#[repr(C)]
+pub struct Record {
+    pub user: Pubkey,
+    /// Slot of the proof of the last action.
+    pub last_slot: u64,
+    /// Number of executed actions.
+    pub actions: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Record, size = 56, align = 8, { user: 0, last_slot: 32, actions: 40, bump: 48 });
+
+impl Record {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Proof of recency program instruction discriminators.
+#[repr(u8)]
+pub enum ProofOfRecencyInstruction {
+    /// Creates a record account.
+    Initialize,
+    /// Executes an action with a proof of recency.
+    Act,
+}
+
+impl TryFrom<&u8> for ProofOfRecencyInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Act),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ActInstructionData {
+    /// Recent slot.
+    pub slot: u64,
+    /// Hash of `slot`.
+    pub hash: [u8; 32],
+}
+
+impl ActInstructionData {
+    pub fn new(slot: u64, hash: [u8; 32]) -> Self {
+        Self { slot, hash }
+    }
+}
+
+/// Returns the hash of `slot` from the data of the SlotHashes sysvar.
+fn slot_hash(slot_hashes: &[u8], slot: u64) -> Result<Option<[u8; 32]>, ProgramError> {
+    let len = u64::from_le_bytes(
+        slot_hashes
+            .get(..8)
+            .ok_or(ProgramError::InvalidAccountData)?
+            .try_into()
+            .unwrap(),
+    ) as usize;
+    let entries = slot_hashes
+        .get(8..8 + len * mem::size_of::<SlotHash>())
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let entries: &[SlotHash] = unsafe { slice::from_raw_parts(entries.as_ptr().cast(), len) };
+
+    // Entries are sorted from the newest.
+    Ok(entries
+        .binary_search_by(|entry| slot.cmp(&entry.slot))
+        .ok()
+        .map(|i| entries[i].hash))
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = ProofOfRecencyInstruction::try_from(instruction)?;
+
+    match instruction {
+        ProofOfRecencyInstruction::Initialize => process_initialize(accounts, instruction_data),
+        ProofOfRecencyInstruction::Act => process_act(accounts, instruction_data),
+    }
+}
+
+/// Creates a record account of `user`.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` User
+///   1. `[WRITE]` Record PDA
+///   2. `[]` System program
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, record, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(user)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    let record_pda = create_program_address(
+        &[RECORD_SEED.as_bytes(), user.key(), &[instruction_data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(record.key(), &record_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(RECORD_SEED.as_bytes()),
+        Seed::from(user.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: user,
+        to: record,
+        lamports: Rent::get()?.minimum_balance(Record::LEN),
+        space: Record::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let mut data = record.try_borrow_mut_data()?;
+    let record: &mut Record = unsafe { &mut *data.as_mut_ptr().cast() };
+    record.user = *user.key();
+    record.bump = instruction_data.bump;
+
+    info!("Created the record account");
+
+    Ok(())
+}
+
+/// Executes an action of `user`, after checking that `slot` and `hash`
+/// prove that it was signed recently and wasn't executed before.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` User
+///   1. `[WRITE]` Record PDA
+///   2. `[]` SlotHashes sysvar
+pub fn process_act(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, record, slot_hashes] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(user)?;
+    require_owned_by(record, &ID)?;
+    // Check that the hashes come from the actual sysvar and not from an
+    // account with the same layout.
+    require_key_eq(slot_hashes, &SLOT_HASHES_ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = ActInstructionData::from_bytes(instruction_data)?;
+
+    let mut data = record.try_borrow_mut_data()?;
+    if data.len() != Record::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let record: &mut Record = unsafe { &mut *data.as_mut_ptr().cast() };
+    if !keys_eq(&record.user, user.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    if Clock::get()?.slot.saturating_sub(instruction_data.slot) > MAX_SLOT_AGE {
+        return Err(ProofOfRecencyError::StaleSlot.into());
+    }
+    let hash = slot_hash(&slot_hashes.try_borrow_data()?, instruction_data.slot)?
+        .ok_or(ProofOfRecencyError::UnknownSlot)?;
+    if hash != instruction_data.hash {
+        return Err(ProofOfRecencyError::HashMismatch.into());
+    }
+    if instruction_data.slot <= record.last_slot {
+        return Err(ProofOfRecencyError::Replayed.into());
+    }
+
+    record.last_slot = instruction_data.slot;
+    record.actions += 1;
+
+    info!(
+        "Executed action {} with a proof from slot {}",
+        record.actions, instruction_data.slot
+    );
+
+    Ok(())
+}
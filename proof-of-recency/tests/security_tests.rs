@@ -0,0 +1,171 @@
+//! Attacks on the proof of recency program, each rejected with its own
+//! error before the proof is checked, so the SlotHashes sysvar stays empty.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use proof_of_recency::{
+    ActInstructionData, InitializeInstructionData, ProofOfRecencyInstruction, Record, RECORD_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(proof_of_recency::ID);
+const SLOT_HASHES_ID: Pubkey = Pubkey::new_from_array(proof_of_recency::SLOT_HASHES_ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: ProofOfRecencyInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<ProofOfRecencyInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the record PDA of `user` and its bump.
+fn record_pda(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECORD_SEED.as_bytes(), user.as_ref()], &ID)
+}
+
+/// Returns a record of `user` without any actions.
+fn record_account(mollusk: &Mollusk, user: &Pubkey, bump: u8) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Record::LEN),
+        Record::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(user.as_ref());
+    account.data[48] = bump;
+    account
+}
+
+/// Accounts of a user with a record, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    user: Pubkey,
+    record: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/proof_of_recency");
+
+        let user = Pubkey::new_unique();
+        let (record, record_bump) = record_pda(&user);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (user, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (record, record_account(&mollusk, &user, record_bump)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (SLOT_HASHES_ID, Account::default()),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            user,
+            record,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Executes an action of `user`, recorded in `record`.
+    fn instruction_act(&self, user: &Pubkey, record: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*user, true),
+            AccountMeta::new(*record, false),
+            AccountMeta::new_readonly(SLOT_HASHES_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                ProofOfRecencyInstruction::Act,
+                &ActInstructionData::new(0, [0; 32]),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_user_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_act(&setup.user, &setup.record);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the record of the user before the user does, with
+/// their own bump.
+#[test]
+fn test_initialize_record_of_other_user() {
+    let setup = Setup::new();
+    let record = setup.record;
+    let setup = setup.with(record, Account::default());
+    let (_, bump) = record_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(record, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            ProofOfRecencyInstruction::Initialize,
+            &InitializeInstructionData::new(bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker acts with the record of the user, so the proofs of the user
+/// would be replayed against a newer slot.
+#[test]
+fn test_act_with_record_of_other_user() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_act(&setup.attacker, &setup.record),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A record of the attacker owned by another program, whose last slot they
+/// can reset to replay their proofs.
+#[test]
+fn test_act_with_spoofed_record() {
+    let setup = Setup::new();
+    let (record, bump) = record_pda(&setup.attacker);
+    let mut record_account = record_account(&setup.mollusk, &setup.attacker, bump);
+    record_account.owner = Pubkey::new_unique();
+    let setup = setup.with(record, record_account);
+    setup.expect_err(
+        &setup.instruction_act(&setup.attacker, &record),
+        ProgramError::IllegalOwner,
+    );
+}
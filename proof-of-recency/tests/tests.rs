@@ -0,0 +1,271 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use proof_of_recency::{
+    ActInstructionData, InitializeInstructionData, ProofOfRecencyInstruction, Record, MAX_SLOT_AGE,
+    RECORD_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(proof_of_recency::ID);
+const SLOT_HASHES_ID: Pubkey = Pubkey::new_from_array(proof_of_recency::SLOT_HASHES_ID);
+
+/// `ProofOfRecencyError::StaleSlot`.
+const STALE_SLOT: u32 = Namespace::ProofOfRecency.code(0);
+/// `ProofOfRecencyError::UnknownSlot`.
+const UNKNOWN_SLOT: u32 = Namespace::ProofOfRecency.code(1);
+/// `ProofOfRecencyError::HashMismatch`.
+const HASH_MISMATCH: u32 = Namespace::ProofOfRecency.code(2);
+/// `ProofOfRecencyError::Replayed`.
+const REPLAYED: u32 = Namespace::ProofOfRecency.code(3);
+
+const SLOT: u64 = 1_000;
+/// Slot without a block.
+const SKIPPED_SLOT: u64 = 995;
+
+/// Offsets of the fields of a record.
+const LAST_SLOT_OFFSET: usize = 32;
+const ACTIONS_OFFSET: usize = 40;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: ProofOfRecencyInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<ProofOfRecencyInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/proof_of_recency");
+    mollusk.sysvars.clock.slot = SLOT;
+    mollusk
+}
+
+/// Returns a made up hash of `slot`.
+fn hash(slot: u64) -> [u8; 32] {
+    let mut hash = [0xab; 32];
+    hash[..8].copy_from_slice(&slot.to_le_bytes());
+    hash
+}
+
+/// Creates a SlotHashes sysvar account with the hashes of the slots before
+/// [`SLOT`], sorted from the newest, except [`SKIPPED_SLOT`]. Older slots
+/// are evicted.
+fn slot_hashes_account() -> Account {
+    let slots: Vec<u64> = (SLOT - 200..SLOT)
+        .rev()
+        .filter(|slot| *slot != SKIPPED_SLOT)
+        .collect();
+    let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+    for slot in slots {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(&hash(slot));
+    }
+    Account {
+        lamports: LAMPORTS_PER_SOL,
+        data,
+        ..Account::default()
+    }
+}
+
+fn record_account(mollusk: &Mollusk, user: &Pubkey, last_slot: u64, bump: u8) -> Account {
+    let mut data = vec![0; Record::LEN];
+    data[0..32].copy_from_slice(user.as_ref());
+    data[LAST_SLOT_OFFSET..ACTIONS_OFFSET].copy_from_slice(&last_slot.to_le_bytes());
+    data[48] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Record::LEN),
+        Record::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// A user with a record and the SlotHashes sysvar.
+struct Setup {
+    mollusk: Mollusk,
+    user: Pubkey,
+    record: Pubkey,
+    record_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let user = Pubkey::new_unique();
+        let (record, record_bump) =
+            Pubkey::find_program_address(&[RECORD_SEED.as_bytes(), user.as_ref()], &ID);
+
+        let accounts = vec![
+            (user, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (record, record_account(&mollusk, &user, 0, record_bump)),
+            (SLOT_HASHES_ID, slot_hashes_account()),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            user,
+            record,
+            record_bump,
+            accounts,
+        }
+    }
+
+    fn instruction_initialize(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.user, true),
+            AccountMeta::new(self.record, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                ProofOfRecencyInstruction::Initialize,
+                &InitializeInstructionData::new(self.record_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_act(&self, slot: u64, hash: [u8; 32]) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.user, true),
+            AccountMeta::new(self.record, false),
+            AccountMeta::new_readonly(SLOT_HASHES_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                ProofOfRecencyInstruction::Act,
+                &ActInstructionData::new(slot, hash),
+            ),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(Record::LEN, 56);
+    assert_eq!(ActInstructionData::LEN, 40);
+}
+
+#[test]
+fn test_initialize() {
+    let mut setup = Setup::new();
+    // The record is created by the program.
+    setup.accounts[1].1 = Account::default();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.record)
+                .owner(&ID)
+                .data(&record_account(&setup.mollusk, &setup.user, 0, setup.record_bump).data)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_act() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_act(SLOT - 10, hash(SLOT - 10)),
+                &[
+                    Check::success(),
+                    Check::account(&setup.record)
+                        .data_slice(LAST_SLOT_OFFSET, &(SLOT - 10).to_le_bytes())
+                        .data_slice(ACTIONS_OFFSET, &1u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_act(SLOT - 1, hash(SLOT - 1)),
+                &[
+                    Check::success(),
+                    Check::account(&setup.record)
+                        .data_slice(LAST_SLOT_OFFSET, &(SLOT - 1).to_le_bytes())
+                        .data_slice(ACTIONS_OFFSET, &2u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+
+    // Every proof can be used once, and only proofs newer than the last one
+    // are accepted.
+    for slot in [SLOT - 1, SLOT - 10] {
+        setup.mollusk.process_and_validate_instruction(
+            &setup.instruction_act(slot, hash(slot)),
+            &res.resulting_accounts,
+            &[Check::err(ProgramError::Custom(REPLAYED))],
+        );
+    }
+}
+
+#[test]
+fn test_act_invalid() {
+    let setup = Setup::new();
+
+    for (slot, hash, err) in [
+        // Still in the sysvar, but too old.
+        (
+            SLOT - MAX_SLOT_AGE - 1,
+            hash(SLOT - MAX_SLOT_AGE - 1),
+            STALE_SLOT,
+        ),
+        (SKIPPED_SLOT, hash(SKIPPED_SLOT), UNKNOWN_SLOT),
+        // Proofs can't be made for future slots.
+        (SLOT + 1, hash(SLOT + 1), UNKNOWN_SLOT),
+        (SLOT - 10, hash(SLOT - 11), HASH_MISMATCH),
+    ] {
+        setup.mollusk.process_and_validate_instruction(
+            &setup.instruction_act(slot, hash),
+            &setup.accounts,
+            &[Check::err(ProgramError::Custom(err))],
+        );
+    }
+
+    // An account with the layout of SlotHashes, but controlled by the user.
+    let fake_slot_hashes = Pubkey::new_unique();
+    let mut ix = setup.instruction_act(SLOT - 10, hash(SLOT - 10));
+    ix.accounts[2].pubkey = fake_slot_hashes;
+    let mut accounts = setup.accounts.clone();
+    accounts[2].0 = fake_slot_hashes;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &accounts,
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
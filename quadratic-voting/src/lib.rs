@@ -0,0 +1,815 @@
+//! Quadratic voting.
+//!
+//! Voters deposit tokens into a realm and receive one credit per token.
+//! Committing credits to a proposal gives a vote weight equal to the integer
+//! square root of the committed credits, so `n` votes cost `n^2` credits.
+//! Once voting ends, the committed credits are refunded and can be withdrawn
+//! as tokens.
+
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use common::close::close_account;
+use errors::{Namespace, SharedError};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+use pinocchio_token::{instructions::Transfer, state::TokenAccount};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("9jDQuVHDWbYskgX1UueXwLijVadppx6XBJXoizB5t7Td");
+
+pub const REALM_SEED: &str = "realm";
+pub const VOTER_SEED: &str = "voter";
+pub const PROPOSAL_SEED: &str = "proposal";
+pub const BALLOT_SEED: &str = "ballot";
+
+/// Errors returned by the quadratic voting program.
+#[repr(u32)]
+pub enum QuadraticVotingError {
+    /// The voter doesn't have enough uncommitted credits.
+    InsufficientCredits,
+    /// The vault doesn't match the realm.
+    VaultMismatch,
+    /// The end of the voting period is not in the future.
+    InvalidEndTime,
+    /// The voting period of the proposal is over.
+    VotingClosed,
+    /// The voting period of the proposal is not over yet.
+    VotingOpen,
+    /// The ballot was cast for the other side.
+    SideMismatch,
+}
+
+impl From<QuadraticVotingError> for ProgramError {
+    fn from(e: QuadraticVotingError) -> Self {
+        Namespace::QuadraticVoting.error(e as u32)
+    }
+}
+
+/// Returns the floor of the square root of `value`.
+pub fn isqrt(value: u64) -> u64 {
+    if value < 2 {
+        return value;
+    }
+
+    // Newton's method, starting from a power of two above the root, so the
+    // iterations decrease monotonically towards the floor of the root.
+    let bits = 64 - value.leading_zeros();
+    let mut root = 1u64 << bits.div_ceil(2);
+    loop {
+        let next = (root + value / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+/// On-chain representation of a realm.
+///
+/// The deposited tokens are held by a token account owned by the realm PDA.
+#[repr(C)]
+pub struct Realm {
+    pub authority: Pubkey,
+    /// Mint of the tokens which can be deposited.
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Realm, size = 104, align = 1, { authority: 0, mint: 32, vault: 64, bump: 96 });
+
+impl Realm {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain representation of a voter in a realm.
+#[repr(C)]
+pub struct Voter {
+    pub realm: Pubkey,
+    pub owner: Pubkey,
+    /// Credits which are not committed to any ballot.
+    pub credits: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Voter, size = 80, align = 8, { realm: 0, owner: 32, credits: 64, bump: 72 });
+
+impl Voter {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain representation of a proposal.
+#[repr(C)]
+pub struct Proposal {
+    pub realm: Pubkey,
+    pub id: u64,
+    /// Unix timestamp at which the voting ends.
+    pub ends_at: i64,
+    pub yes_weight: u64,
+    pub no_weight: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Proposal, size = 72, align = 8, {
+    realm: 0,
+    id: 32,
+    ends_at: 40,
+    yes_weight: 48,
+    no_weight: 56,
+    bump: 64,
+});
+
+impl Proposal {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Side of a ballot.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Yes,
+    No,
+}
+
+impl TryFrom<u8> for Side {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Yes),
+            1 => Ok(Self::No),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// On-chain representation of the credits committed by a voter to a
+/// proposal.
+#[repr(C)]
+pub struct Ballot {
+    pub proposal: Pubkey,
+    pub owner: Pubkey,
+    pub credits: u64,
+    /// Weight added to the tally of the proposal, `isqrt(credits)`.
+    pub weight: u64,
+    pub side: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+assert_layout!(Ballot, size = 88, align = 8, {
+    proposal: 0,
+    owner: 32,
+    credits: 64,
+    weight: 72,
+    side: 80,
+    bump: 81,
+});
+
+impl Ballot {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Quadratic voting program instruction discriminators.
+#[repr(u8)]
+pub enum QuadraticVotingInstruction {
+    /// Creates a realm.
+    CreateRealm,
+    /// Deposits tokens in exchange for credits.
+    Deposit,
+    /// Withdraws tokens in exchange for uncommitted credits.
+    Withdraw,
+    /// Creates a proposal.
+    CreateProposal,
+    /// Commits credits to a proposal.
+    Vote,
+    /// Refunds the credits committed to a proposal after the voting ends.
+    Refund,
+}
+
+impl TryFrom<&u8> for QuadraticVotingInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::CreateRealm),
+            1 => Ok(Self::Deposit),
+            2 => Ok(Self::Withdraw),
+            3 => Ok(Self::CreateProposal),
+            4 => Ok(Self::Vote),
+            5 => Ok(Self::Refund),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct CreateRealmInstructionData {
+    pub bump: u8,
+}
+
+impl CreateRealmInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct DepositInstructionData {
+    pub amount: u64,
+    /// Bump of the voter PDA, used when it doesn't exist yet.
+    pub bump: u8,
+}
+
+impl DepositInstructionData {
+    pub fn new(amount: u64, bump: u8) -> Self {
+        Self { amount, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct WithdrawInstructionData {
+    pub amount: u64,
+}
+
+impl WithdrawInstructionData {
+    pub fn new(amount: u64) -> Self {
+        Self { amount }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct CreateProposalInstructionData {
+    pub id: u64,
+    pub ends_at: i64,
+    pub bump: u8,
+}
+
+impl CreateProposalInstructionData {
+    pub fn new(id: u64, ends_at: i64, bump: u8) -> Self {
+        Self { id, ends_at, bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct VoteInstructionData {
+    pub credits: u64,
+    pub side: u8,
+    /// Bump of the ballot PDA, used when it doesn't exist yet.
+    pub bump: u8,
+}
+
+impl VoteInstructionData {
+    pub fn new(credits: u64, side: Side, bump: u8) -> Self {
+        Self {
+            credits,
+            side: side as u8,
+            bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = QuadraticVotingInstruction::try_from(instruction)?;
+
+    match instruction {
+        QuadraticVotingInstruction::CreateRealm => process_create_realm(accounts, instruction_data),
+        QuadraticVotingInstruction::Deposit => process_deposit(accounts, instruction_data),
+        QuadraticVotingInstruction::Withdraw => process_withdraw(accounts, instruction_data),
+        QuadraticVotingInstruction::CreateProposal => {
+            process_create_proposal(accounts, instruction_data)
+        }
+        QuadraticVotingInstruction::Vote => process_vote(accounts, instruction_data),
+        QuadraticVotingInstruction::Refund => process_refund(accounts),
+    }
+}
+
+/// Creates a realm for the tokens of the mint of `vault`, which has to be
+/// owned by the realm PDA.
+pub fn process_create_realm(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, realm, vault, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    let mint = {
+        let vault = TokenAccount::from_account_info(vault)?;
+        if !keys_eq(vault.owner(), realm.key()) {
+            return Err(QuadraticVotingError::VaultMismatch.into());
+        }
+        *vault.mint()
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = CreateRealmInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `realm`.
+    let realm_pda = create_program_address(
+        &[
+            REALM_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(realm.key(), &realm_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the realm PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(REALM_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: realm,
+        lamports: Rent::get()?.minimum_balance(Realm::LEN),
+        space: Realm::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the realm PDA.
+    let mut data = realm.try_borrow_mut_data()?;
+    let data: &mut Realm = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the realm.
+    data.authority = *authority.key();
+    data.mint = mint;
+    data.vault = *vault.key();
+    data.bump = instruction_data.bump;
+
+    info!("Created realm");
+
+    Ok(())
+}
+
+/// Checks that `account` is owned by the program and has the given length.
+/// The program owns realms, voters, proposals and ballots, which are told
+/// apart by their length.
+fn check_state(account: &AccountInfo, len: usize) -> ProgramResult {
+    require_owned_by(account, &ID)?;
+    if account.data_len() != len {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Returns the vault of `realm`, checking that the realm is a realm of the
+/// program.
+fn realm_vault(realm: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    check_state(realm, Realm::LEN)?;
+    let data = realm.try_borrow_data()?;
+    let data: &Realm = unsafe { &*data.as_ptr().cast() };
+    Ok(data.vault)
+}
+
+/// Deposits `amount` tokens from `owner_ata` into the vault of the realm and
+/// credits the voter with the same amount of credits. Creates the voter PDA
+/// on the first deposit.
+pub fn process_deposit(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, owner_ata, realm, voter, vault, _system_program, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    if !keys_eq(vault.key(), &realm_vault(realm)?) {
+        return Err(QuadraticVotingError::VaultMismatch.into());
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = DepositInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.amount == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+
+    if !voter.is_owned_by(&ID) {
+        // Check the seeds of `voter`.
+        let voter_pda = create_program_address(
+            &[
+                VOTER_SEED.as_bytes(),
+                realm.key(),
+                owner.key(),
+                &[instruction_data.bump],
+            ],
+            &ID,
+        )?;
+        if !keys_eq(voter.key(), &voter_pda) {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        // Create the voter PDA.
+        let bump = [instruction_data.bump];
+        let seeds = [
+            Seed::from(VOTER_SEED.as_bytes()),
+            Seed::from(realm.key()),
+            Seed::from(owner.key()),
+            Seed::from(&bump),
+        ];
+        CreateAccount {
+            from: owner,
+            to: voter,
+            lamports: Rent::get()?.minimum_balance(Voter::LEN),
+            space: Voter::LEN as u64,
+            owner: &ID,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        let mut data = voter.try_borrow_mut_data()?;
+        let data: &mut Voter = unsafe { &mut *data.as_mut_ptr().cast() };
+        data.realm = *realm.key();
+        data.owner = *owner.key();
+        data.bump = instruction_data.bump;
+    }
+
+    // Credit the voter.
+    check_state(voter, Voter::LEN)?;
+    {
+        let mut data = voter.try_borrow_mut_data()?;
+        let data: &mut Voter = unsafe { &mut *data.as_mut_ptr().cast() };
+
+        if !keys_eq(&data.realm, realm.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        data.credits = data
+            .credits
+            .checked_add(instruction_data.amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+    }
+
+    // Transfer the tokens from owner to the vault.
+    Transfer {
+        from: owner_ata,
+        to: vault,
+        authority: owner,
+        amount: instruction_data.amount,
+    }
+    .invoke()?;
+
+    info!("Deposited {} tokens", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Withdraws `amount` tokens from the vault of the realm to `owner_ata`,
+/// spending the same amount of uncommitted credits.
+pub fn process_withdraw(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, owner_ata, realm, voter, vault, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    if !keys_eq(vault.key(), &realm_vault(realm)?) {
+        return Err(QuadraticVotingError::VaultMismatch.into());
+    }
+    check_state(voter, Voter::LEN)?;
+
+    // Deserialize instruction data.
+    let instruction_data = WithdrawInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.amount == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+
+    // Spend the credits.
+    {
+        let mut data = voter.try_borrow_mut_data()?;
+        let data: &mut Voter = unsafe { &mut *data.as_mut_ptr().cast() };
+
+        if !keys_eq(&data.realm, realm.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        data.credits = data
+            .credits
+            .checked_sub(instruction_data.amount)
+            .ok_or(QuadraticVotingError::InsufficientCredits)?;
+    }
+
+    // Transfer the tokens from the vault to owner.
+    let (authority, bump) = {
+        let data = realm.try_borrow_data()?;
+        let data: &Realm = unsafe { &*data.as_ptr().cast() };
+        (data.authority, data.bump)
+    };
+    let bump = [bump];
+    let seeds = [
+        Seed::from(REALM_SEED.as_bytes()),
+        Seed::from(&authority),
+        Seed::from(&bump),
+    ];
+    Transfer {
+        from: vault,
+        to: owner_ata,
+        authority: realm,
+        amount: instruction_data.amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Withdrew {} tokens", instruction_data.amount);
+
+    Ok(())
+}
+
+/// Creates a proposal in the realm. Only the realm authority can create
+/// proposals.
+pub fn process_create_proposal(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, realm, proposal, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Check that `realm` is a realm PDA of the program.
+    check_state(realm, Realm::LEN)?;
+    {
+        let data = realm.try_borrow_data()?;
+        let data: &Realm = unsafe { &*data.as_ptr().cast() };
+        if !keys_eq(&data.authority, authority.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = CreateProposalInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.ends_at <= Clock::get()?.unix_timestamp {
+        return Err(QuadraticVotingError::InvalidEndTime.into());
+    }
+
+    // Check the seeds of `proposal`.
+    let id = instruction_data.id.to_le_bytes();
+    let proposal_pda = create_program_address(
+        &[
+            PROPOSAL_SEED.as_bytes(),
+            realm.key(),
+            &id,
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(proposal.key(), &proposal_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the proposal PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(PROPOSAL_SEED.as_bytes()),
+        Seed::from(realm.key()),
+        Seed::from(&id),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: proposal,
+        lamports: Rent::get()?.minimum_balance(Proposal::LEN),
+        space: Proposal::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the proposal PDA.
+    let mut data = proposal.try_borrow_mut_data()?;
+    let data: &mut Proposal = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the proposal.
+    data.realm = *realm.key();
+    data.id = instruction_data.id;
+    data.ends_at = instruction_data.ends_at;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Created proposal {} ending at {}",
+        instruction_data.id, instruction_data.ends_at
+    );
+
+    Ok(())
+}
+
+/// Commits `credits` of the voter to one side of the proposal. Creates the
+/// ballot on the first vote. Further votes add credits to the same side and
+/// the tally is updated by the change of the square root of the total.
+pub fn process_vote(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, voter, proposal, ballot, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Check that `voter` and `proposal` are voter and proposal PDAs of the
+    // program.
+    check_state(voter, Voter::LEN)?;
+    check_state(proposal, Proposal::LEN)?;
+
+    // Deserialize instruction data.
+    let instruction_data = VoteInstructionData::from_bytes(instruction_data)?;
+
+    let side = Side::try_from(instruction_data.side)?;
+    if instruction_data.credits == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+
+    // Check that the voting is open and the voter belongs to the realm of
+    // the proposal.
+    let mut proposal_data = proposal.try_borrow_mut_data()?;
+    let proposal_data: &mut Proposal = unsafe { &mut *proposal_data.as_mut_ptr().cast() };
+    if Clock::get()?.unix_timestamp >= proposal_data.ends_at {
+        return Err(QuadraticVotingError::VotingClosed.into());
+    }
+
+    let mut voter_data = voter.try_borrow_mut_data()?;
+    let voter_data: &mut Voter = unsafe { &mut *voter_data.as_mut_ptr().cast() };
+    if voter_data.realm != proposal_data.realm {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !keys_eq(&voter_data.owner, owner.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    if !ballot.is_owned_by(&ID) {
+        // Check the seeds of `ballot`.
+        let ballot_pda = create_program_address(
+            &[
+                BALLOT_SEED.as_bytes(),
+                proposal.key(),
+                owner.key(),
+                &[instruction_data.bump],
+            ],
+            &ID,
+        )?;
+        if !keys_eq(ballot.key(), &ballot_pda) {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        // Create the ballot PDA.
+        let bump = [instruction_data.bump];
+        let seeds = [
+            Seed::from(BALLOT_SEED.as_bytes()),
+            Seed::from(proposal.key()),
+            Seed::from(owner.key()),
+            Seed::from(&bump),
+        ];
+        CreateAccount {
+            from: owner,
+            to: ballot,
+            lamports: Rent::get()?.minimum_balance(Ballot::LEN),
+            space: Ballot::LEN as u64,
+            owner: &ID,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        let mut data = ballot.try_borrow_mut_data()?;
+        let data: &mut Ballot = unsafe { &mut *data.as_mut_ptr().cast() };
+        data.proposal = *proposal.key();
+        data.owner = *owner.key();
+        data.side = side as u8;
+        data.bump = instruction_data.bump;
+    }
+
+    check_state(ballot, Ballot::LEN)?;
+    let mut ballot_data = ballot.try_borrow_mut_data()?;
+    let ballot_data: &mut Ballot = unsafe { &mut *ballot_data.as_mut_ptr().cast() };
+    if !keys_eq(&ballot_data.proposal, proposal.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !keys_eq(&ballot_data.owner, owner.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if ballot_data.side != side as u8 {
+        return Err(QuadraticVotingError::SideMismatch.into());
+    }
+
+    // Move the credits from the voter to the ballot.
+    voter_data.credits = voter_data
+        .credits
+        .checked_sub(instruction_data.credits)
+        .ok_or(QuadraticVotingError::InsufficientCredits)?;
+    // Can't overflow, the sum of both is bounded by the deposited supply.
+    ballot_data.credits += instruction_data.credits;
+
+    // Replace the previous weight of the ballot in the tally.
+    let weight = isqrt(ballot_data.credits);
+    let tally = match side {
+        Side::Yes => &mut proposal_data.yes_weight,
+        Side::No => &mut proposal_data.no_weight,
+    };
+    *tally = *tally - ballot_data.weight + weight;
+    ballot_data.weight = weight;
+
+    info!(
+        "Committed {} credits, ballot weight {}",
+        instruction_data.credits, weight
+    );
+
+    Ok(())
+}
+
+/// Refunds the credits committed to the proposal to the voter and closes the
+/// ballot, once the voting is over. The tally of the proposal is kept.
+pub fn process_refund(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, voter, proposal, ballot] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Check that the accounts are voter, proposal and ballot PDAs of the
+    // program.
+    check_state(voter, Voter::LEN)?;
+    check_state(proposal, Proposal::LEN)?;
+    check_state(ballot, Ballot::LEN)?;
+
+    let realm = {
+        let data = proposal.try_borrow_data()?;
+        let data: &Proposal = unsafe { &*data.as_ptr().cast() };
+        if Clock::get()?.unix_timestamp < data.ends_at {
+            return Err(QuadraticVotingError::VotingOpen.into());
+        }
+        data.realm
+    };
+
+    let credits = {
+        let data = ballot.try_borrow_data()?;
+        let data: &Ballot = unsafe { &*data.as_ptr().cast() };
+        if !keys_eq(&data.proposal, proposal.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        data.credits
+    };
+
+    // Return the credits to the voter.
+    {
+        let mut data = voter.try_borrow_mut_data()?;
+        let data: &mut Voter = unsafe { &mut *data.as_mut_ptr().cast() };
+        if data.realm != realm {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        data.credits += credits;
+    }
+
+    // Close the ballot, returning the rent to the owner.
+    close_account(ballot, owner)?;
+
+    info!("Refunded {} credits", credits);
+
+    Ok(())
+}
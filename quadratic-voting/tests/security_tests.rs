@@ -0,0 +1,500 @@
+//! Attacks on the quadratic voting program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use quadratic_voting::{
+    isqrt, Ballot, DepositInstructionData, Proposal, QuadraticVotingInstruction, Realm, Side,
+    VoteInstructionData, Voter, WithdrawInstructionData, BALLOT_SEED, PROPOSAL_SEED, REALM_SEED,
+    VOTER_SEED,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(quadratic_voting::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+const AMOUNT: u64 = 100;
+/// Credits committed by the owner to the proposal.
+const COMMITTED: u64 = 16;
+const NOW: i64 = 1_000_000;
+const ENDS_AT: i64 = NOW + 86_400;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: QuadraticVotingInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator =
+        vec![0; mem::size_of::<QuadraticVotingInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns an account of the program holding `data`.
+fn program_account(mollusk: &Mollusk, data: Vec<u8>) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns the realm PDA of `authority` and its account.
+fn realm_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    vault: &Pubkey,
+) -> (Pubkey, Account) {
+    let (realm, bump) =
+        Pubkey::find_program_address(&[REALM_SEED.as_bytes(), authority.as_ref()], &ID);
+    let mut data = vec![0; Realm::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64..96].copy_from_slice(vault.as_ref());
+    data[96] = bump;
+    (realm, program_account(mollusk, data))
+}
+
+/// Returns the voter PDA of `owner` in `realm` and its account.
+fn voter_account(
+    mollusk: &Mollusk,
+    realm: &Pubkey,
+    owner: &Pubkey,
+    credits: u64,
+) -> (Pubkey, Account) {
+    let (voter, bump) = Pubkey::find_program_address(
+        &[VOTER_SEED.as_bytes(), realm.as_ref(), owner.as_ref()],
+        &ID,
+    );
+    let mut data = vec![0; Voter::LEN];
+    data[0..32].copy_from_slice(realm.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[64..72].copy_from_slice(&credits.to_le_bytes());
+    data[72] = bump;
+    (voter, program_account(mollusk, data))
+}
+
+/// Returns the proposal PDA with `id` in `realm` and its account.
+fn proposal_account(
+    mollusk: &Mollusk,
+    realm: &Pubkey,
+    id: u64,
+    ends_at: i64,
+    yes_weight: u64,
+) -> (Pubkey, Account) {
+    let (proposal, bump) = Pubkey::find_program_address(
+        &[PROPOSAL_SEED.as_bytes(), realm.as_ref(), &id.to_le_bytes()],
+        &ID,
+    );
+    let mut data = vec![0; Proposal::LEN];
+    data[0..32].copy_from_slice(realm.as_ref());
+    data[32..40].copy_from_slice(&id.to_le_bytes());
+    data[40..48].copy_from_slice(&ends_at.to_le_bytes());
+    data[48..56].copy_from_slice(&yes_weight.to_le_bytes());
+    data[64] = bump;
+    (proposal, program_account(mollusk, data))
+}
+
+/// Returns the ballot PDA of `owner` for `proposal` and its bump.
+fn ballot_pda(proposal: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[BALLOT_SEED.as_bytes(), proposal.as_ref(), owner.as_ref()],
+        &ID,
+    )
+}
+
+/// Accounts of a realm with an open proposal, to which the owner committed
+/// [`COMMITTED`] of their [`AMOUNT`] credits, and of an attacker holding
+/// [`AMOUNT`] credits both in the realm and in a realm of their own.
+struct Setup {
+    mollusk: Mollusk,
+    realm: Pubkey,
+    vault: Pubkey,
+    proposal: Pubkey,
+    owner: Pubkey,
+    owner_ata: Pubkey,
+    voter: Pubkey,
+    ballot: Pubkey,
+    attacker: Pubkey,
+    attacker_ata: Pubkey,
+    attacker_voter: Pubkey,
+    other_voter: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/quadratic_voting");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+        let (realm, realm_account) = realm_account(&mollusk, &authority, &mint, &vault);
+        let (proposal, proposal_account) =
+            proposal_account(&mollusk, &realm, 1, ENDS_AT, isqrt(COMMITTED));
+
+        let owner = Pubkey::new_unique();
+        let owner_ata = Pubkey::new_unique();
+        let (voter, voter_account) = voter_account(&mollusk, &realm, &owner, AMOUNT - COMMITTED);
+        let (ballot, ballot_bump) = ballot_pda(&proposal, &owner);
+        let mut ballot_data = vec![0; Ballot::LEN];
+        ballot_data[0..32].copy_from_slice(proposal.as_ref());
+        ballot_data[32..64].copy_from_slice(owner.as_ref());
+        ballot_data[64..72].copy_from_slice(&COMMITTED.to_le_bytes());
+        ballot_data[72..80].copy_from_slice(&isqrt(COMMITTED).to_le_bytes());
+        ballot_data[80] = Side::Yes as u8;
+        ballot_data[81] = ballot_bump;
+
+        let attacker = Pubkey::new_unique();
+        let attacker_ata = Pubkey::new_unique();
+        let (attacker_voter, attacker_voter_account) =
+            voter_account(&mollusk, &realm, &attacker, AMOUNT);
+        let other_mint = Pubkey::new_unique();
+        let other_vault = Pubkey::new_unique();
+        let (other_realm, other_realm_account) =
+            realm_account(&mollusk, &attacker, &other_mint, &other_vault);
+        let (other_voter, other_voter_account) =
+            voter_account(&mollusk, &other_realm, &attacker, AMOUNT);
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (realm, realm_account),
+            (vault, token_account(&mollusk, &mint, &realm, 2 * AMOUNT)),
+            (proposal, proposal_account),
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (owner_ata, token_account(&mollusk, &mint, &owner, 0)),
+            (voter, voter_account),
+            (ballot, program_account(&mollusk, ballot_data)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (attacker_ata, token_account(&mollusk, &mint, &attacker, 0)),
+            (attacker_voter, attacker_voter_account),
+            (other_realm, other_realm_account),
+            (
+                other_vault,
+                token_account(&mollusk, &other_mint, &other_realm, AMOUNT),
+            ),
+            (other_voter, other_voter_account),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            realm,
+            vault,
+            proposal,
+            owner,
+            owner_ata,
+            voter,
+            ballot,
+            attacker,
+            attacker_ata,
+            attacker_voter,
+            other_voter,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    fn instruction_deposit(
+        &self,
+        owner: &Pubkey,
+        owner_ata: &Pubkey,
+        realm: &Pubkey,
+        voter: &Pubkey,
+        bump: u8,
+    ) -> Instruction {
+        let data = DepositInstructionData::new(1, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*owner_ata, false),
+            AccountMeta::new_readonly(*realm, false),
+            AccountMeta::new(*voter, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(QuadraticVotingInstruction::Deposit, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_withdraw(
+        &self,
+        owner: &Pubkey,
+        owner_ata: &Pubkey,
+        realm: &Pubkey,
+        voter: &Pubkey,
+    ) -> Instruction {
+        let data = WithdrawInstructionData::new(AMOUNT - COMMITTED);
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*owner_ata, false),
+            AccountMeta::new_readonly(*realm, false),
+            AccountMeta::new(*voter, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(QuadraticVotingInstruction::Withdraw, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Votes with the credits of the attacker in `voter`, creating the
+    /// ballot at `ballot`.
+    fn instruction_vote(&self, voter: &Pubkey, ballot: &Pubkey, bump: u8) -> Instruction {
+        let data = VoteInstructionData::new(AMOUNT, Side::No, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.attacker, true),
+            AccountMeta::new(*voter, false),
+            AccountMeta::new(self.proposal, false),
+            AccountMeta::new(*ballot, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(QuadraticVotingInstruction::Vote, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_refund(&self, proposal: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.voter, false),
+            AccountMeta::new_readonly(*proposal, false),
+            AccountMeta::new(self.ballot, false),
+        ];
+        Instruction::new_with_bytes(ID, &[QuadraticVotingInstruction::Refund as u8], ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let mut instruction =
+        setup.instruction_withdraw(&setup.owner, &setup.owner_ata, &setup.realm, &setup.voter);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker withdraws the uncommitted credits of the owner.
+#[test]
+fn test_withdraw_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_withdraw(
+            &setup.attacker,
+            &setup.attacker_ata,
+            &setup.realm,
+            &setup.voter,
+        ),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker withdraws from the vault of the realm with credits of their
+/// own realm.
+#[test]
+fn test_withdraw_with_voter_of_other_realm() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_withdraw(
+            &setup.attacker,
+            &setup.attacker_ata,
+            &setup.realm,
+            &setup.other_voter,
+        ),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A realm with the layout of the program, but owned by another one.
+#[test]
+fn test_spoofed_realm() {
+    let setup = Setup::new();
+    let realm = setup.realm;
+    let mut realm_account = setup.account(&realm);
+    realm_account.owner = Pubkey::new_unique();
+    let setup = setup.with(realm, realm_account);
+    setup.expect_err(
+        &setup.instruction_withdraw(
+            &setup.attacker,
+            &setup.attacker_ata,
+            &setup.realm,
+            &setup.attacker_voter,
+        ),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A voter with the layout of the program, but owned by another one, e.g.
+/// with more credits.
+#[test]
+fn test_spoofed_voter() {
+    let setup = Setup::new();
+    let attacker_voter = setup.attacker_voter;
+    let mut voter_account = setup.account(&attacker_voter);
+    voter_account.owner = Pubkey::new_unique();
+    let setup = setup.with(attacker_voter, voter_account);
+    setup.expect_err(
+        &setup.instruction_withdraw(
+            &setup.attacker,
+            &setup.attacker_ata,
+            &setup.realm,
+            &setup.attacker_voter,
+        ),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker passes the proposal as the realm, whose vault would be read
+/// past the end of its data.
+#[test]
+fn test_proposal_as_realm() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_deposit(
+            &setup.attacker,
+            &setup.attacker_ata,
+            &setup.proposal,
+            &setup.attacker_voter,
+            0,
+        ),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker votes on the proposal with credits of their own realm.
+#[test]
+fn test_vote_with_voter_of_other_realm() {
+    let setup = Setup::new();
+    let (ballot, bump) = ballot_pda(&setup.proposal, &setup.attacker);
+    let setup = setup.with(ballot, Account::default());
+    setup.expect_err(
+        &setup.instruction_vote(&setup.other_voter, &ballot, bump),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The owner refunds their ballot before the proposal ends, through another
+/// proposal which already ended.
+#[test]
+fn test_refund_through_other_proposal() {
+    let setup = Setup::new();
+    let (other_proposal, other_proposal_account) =
+        proposal_account(&setup.mollusk, &setup.realm, 2, NOW, 0);
+    let setup = setup.with(other_proposal, other_proposal_account);
+    setup.expect_err(
+        &setup.instruction_refund(&other_proposal),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker creates the voter of another wallet, which couldn't deposit
+/// into it.
+#[test]
+fn test_wrong_voter_seeds() {
+    let setup = Setup::new();
+    let wallet = Pubkey::new_unique();
+    let (voter, bump) = Pubkey::find_program_address(
+        &[VOTER_SEED.as_bytes(), setup.realm.as_ref(), wallet.as_ref()],
+        &ID,
+    );
+    let setup = setup.with(voter, Account::default());
+    setup.expect_err(
+        &setup.instruction_deposit(
+            &setup.attacker,
+            &setup.attacker_ata,
+            &setup.realm,
+            &voter,
+            bump,
+        ),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker creates the ballot of another wallet on the side it opposes,
+/// so that it can't vote for the other one.
+#[test]
+fn test_wrong_ballot_seeds() {
+    let setup = Setup::new();
+    let (ballot, bump) = ballot_pda(&setup.proposal, &Pubkey::new_unique());
+    let setup = setup.with(ballot, Account::default());
+    setup.expect_err(
+        &setup.instruction_vote(&setup.attacker_voter, &ballot, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
@@ -0,0 +1,668 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use proptest::prelude::*;
+use quadratic_voting::{
+    isqrt, Ballot, CreateProposalInstructionData, CreateRealmInstructionData,
+    DepositInstructionData, Proposal, QuadraticVotingError, QuadraticVotingInstruction, Realm,
+    Side, VoteInstructionData, Voter, WithdrawInstructionData, BALLOT_SEED, PROPOSAL_SEED,
+    REALM_SEED, VOTER_SEED,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(quadratic_voting::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offset of the credits in voters and ballots.
+const CREDITS_OFFSET: usize = 64;
+/// Offsets of the proposal fields.
+const YES_WEIGHT_OFFSET: usize = 48;
+const NO_WEIGHT_OFFSET: usize = 56;
+/// Offset of the weight in a ballot.
+const WEIGHT_OFFSET: usize = 72;
+
+const AMOUNT: u64 = 100;
+const PROPOSAL_ID: u64 = 1;
+const NOW: i64 = 1_000_000;
+const ENDS_AT: i64 = NOW + 86_400;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: QuadraticVotingInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<QuadraticVotingInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/quadratic_voting");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk.sysvars.clock.unix_timestamp = NOW;
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn realm_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    vault: &Pubkey,
+    bump: u8,
+) -> Account {
+    let mut data = vec![0; Realm::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(mint.as_ref());
+    data[64..96].copy_from_slice(vault.as_ref());
+    data[96] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Realm::LEN),
+        Realm::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+fn proposal_account(mollusk: &Mollusk, realm: &Pubkey, bump: u8) -> Account {
+    let mut data = vec![0; Proposal::LEN];
+    data[0..32].copy_from_slice(realm.as_ref());
+    data[32..40].copy_from_slice(&PROPOSAL_ID.to_le_bytes());
+    data[40..48].copy_from_slice(&ENDS_AT.to_le_bytes());
+    data[64] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Proposal::LEN),
+        Proposal::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Accounts shared by the voting tests: a realm with a proposal and a voter
+/// holding tokens.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    realm: Pubkey,
+    vault: Pubkey,
+    proposal: Pubkey,
+    proposal_bump: u8,
+    owner: Pubkey,
+    owner_ata: Pubkey,
+    voter: Pubkey,
+    voter_bump: u8,
+    ballot: Pubkey,
+    ballot_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let authority = Pubkey::new_unique();
+        let (realm, realm_bump) =
+            Pubkey::find_program_address(&[REALM_SEED.as_bytes(), authority.as_ref()], &ID);
+        let mint = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+        let (proposal, proposal_bump) = Pubkey::find_program_address(
+            &[
+                PROPOSAL_SEED.as_bytes(),
+                realm.as_ref(),
+                &PROPOSAL_ID.to_le_bytes(),
+            ],
+            &ID,
+        );
+
+        let owner = Pubkey::new_unique();
+        let owner_ata = Pubkey::new_unique();
+        let (voter, voter_bump) = Pubkey::find_program_address(
+            &[VOTER_SEED.as_bytes(), realm.as_ref(), owner.as_ref()],
+            &ID,
+        );
+        let (ballot, ballot_bump) = Pubkey::find_program_address(
+            &[BALLOT_SEED.as_bytes(), proposal.as_ref(), owner.as_ref()],
+            &ID,
+        );
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                realm,
+                realm_account(&mollusk, &authority, &mint, &vault, realm_bump),
+            ),
+            (mint, mint_account(&mollusk, AMOUNT)),
+            (vault, token_account(&mollusk, &mint, &realm, 0)),
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (proposal, Account::default()),
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (owner_ata, token_account(&mollusk, &mint, &owner, AMOUNT)),
+            (voter, Account::default()),
+            (ballot, Account::default()),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            realm,
+            vault,
+            proposal,
+            proposal_bump,
+            owner,
+            owner_ata,
+            voter,
+            voter_bump,
+            ballot,
+            ballot_bump,
+            accounts,
+        }
+    }
+
+    fn instruction_deposit(&self, amount: u64) -> Instruction {
+        let data = DepositInstructionData::new(amount, self.voter_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.owner_ata, false),
+            AccountMeta::new_readonly(self.realm, false),
+            AccountMeta::new(self.voter, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(QuadraticVotingInstruction::Deposit, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_withdraw(&self, amount: u64, vault: &Pubkey) -> Instruction {
+        let data = WithdrawInstructionData::new(amount);
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.owner_ata, false),
+            AccountMeta::new_readonly(self.realm, false),
+            AccountMeta::new(self.voter, false),
+            AccountMeta::new(*vault, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(QuadraticVotingInstruction::Withdraw, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_create_proposal(&self, authority: &Pubkey, ends_at: i64) -> Instruction {
+        let data = CreateProposalInstructionData::new(PROPOSAL_ID, ends_at, self.proposal_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(self.realm, false),
+            AccountMeta::new(self.proposal, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(QuadraticVotingInstruction::CreateProposal, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_vote(&self, owner: &Pubkey, credits: u64, side: Side) -> Instruction {
+        let data = VoteInstructionData::new(credits, side, self.ballot_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(self.voter, false),
+            AccountMeta::new(self.proposal, false),
+            AccountMeta::new(self.ballot, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(QuadraticVotingInstruction::Vote, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_refund(&self, owner: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(self.voter, false),
+            AccountMeta::new_readonly(self.proposal, false),
+            AccountMeta::new(self.ballot, false),
+        ];
+        Instruction::new_with_bytes(ID, &[QuadraticVotingInstruction::Refund as u8], ix_accounts)
+    }
+
+    /// Runs the instruction, checks the result and returns the resulting
+    /// accounts.
+    fn process(&self, instruction: &Instruction, checks: &[Check]) -> Vec<(Pubkey, Account)> {
+        self.mollusk
+            .process_and_validate_instruction(instruction, &self.accounts, checks)
+            .resulting_accounts
+    }
+
+    /// Creates the proposal and deposits all tokens of the owner.
+    fn deposit(&mut self) {
+        self.accounts = self.process(
+            &self.instruction_create_proposal(&self.authority, ENDS_AT),
+            &[
+                Check::success(),
+                Check::account(&self.proposal)
+                    .owner(&ID)
+                    .data(&proposal_account(&self.mollusk, &self.realm, self.proposal_bump).data)
+                    .build(),
+            ],
+        );
+        self.accounts = self.process(
+            &self.instruction_deposit(AMOUNT),
+            &[
+                Check::success(),
+                Check::account(&self.voter)
+                    .owner(&ID)
+                    .space(Voter::LEN)
+                    .data_slice(0, self.realm.as_ref())
+                    .data_slice(32, self.owner.as_ref())
+                    .data_slice(CREDITS_OFFSET, &AMOUNT.to_le_bytes())
+                    .build(),
+                Check::account(&self.vault)
+                    .data_slice(AMOUNT_OFFSET, &AMOUNT.to_le_bytes())
+                    .build(),
+            ],
+        );
+    }
+}
+
+#[test]
+fn test_quadratic_voting_isqrt() {
+    for (value, root) in [
+        (0, 0),
+        (1, 1),
+        (2, 1),
+        (3, 1),
+        (4, 2),
+        (15, 3),
+        (16, 4),
+        (99, 9),
+        (100, 10),
+        (u32::MAX as u64, 65_535),
+        (u64::MAX, u32::MAX as u64),
+    ] {
+        assert_eq!(isqrt(value), root, "isqrt({value})");
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_quadratic_voting_isqrt_is_floor(value in any::<u64>()) {
+        let root = isqrt(value) as u128;
+        prop_assert!(root * root <= value as u128);
+        prop_assert!((root + 1) * (root + 1) > value as u128);
+    }
+}
+
+#[test]
+fn test_quadratic_voting_create_realm() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let (realm, bump) =
+        Pubkey::find_program_address(&[REALM_SEED.as_bytes(), authority.as_ref()], &ID);
+    let mint = Pubkey::new_unique();
+    let vault = Pubkey::new_unique();
+    let foreign_vault = Pubkey::new_unique();
+
+    let instruction = |vault| {
+        let data = CreateRealmInstructionData::new(bump);
+        let ix_accounts = vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(realm, false),
+            AccountMeta::new_readonly(vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(QuadraticVotingInstruction::CreateRealm, &data),
+            ix_accounts,
+        )
+    };
+
+    let accounts = [
+        (
+            authority,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        ),
+        (realm, Account::default()),
+        (vault, token_account(&mollusk, &mint, &realm, 0)),
+        (foreign_vault, token_account(&mollusk, &mint, &authority, 0)),
+        keyed_account_for_system_program(),
+    ];
+    // The vault has to be owned by the realm.
+    mollusk.process_and_validate_instruction(
+        &instruction(foreign_vault),
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::QuadraticVoting.code(QuadraticVotingError::VaultMismatch as u32),
+        ))],
+    );
+    let res = mollusk.process_and_validate_instruction(
+        &instruction(vault),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&realm)
+                .owner(&ID)
+                .data(&realm_account(&mollusk, &authority, &mint, &vault, bump).data)
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_quadratic_voting_create_proposal_invalid() {
+    let setup = Setup::new();
+
+    setup.process(
+        &setup.instruction_create_proposal(&setup.authority, NOW),
+        &[Check::err(ProgramError::Custom(
+            Namespace::QuadraticVoting.code(QuadraticVotingError::InvalidEndTime as u32),
+        ))],
+    );
+    // Only the realm authority can create proposals.
+    setup.process(
+        &setup.instruction_create_proposal(&setup.owner, ENDS_AT),
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+#[test]
+fn test_quadratic_voting_vote_and_refund() {
+    let mut setup = Setup::new();
+    setup.deposit();
+
+    // 16 credits buy 4 votes.
+    setup.accounts = setup.process(
+        &setup.instruction_vote(&setup.owner, 16, Side::Yes),
+        &[
+            Check::success(),
+            Check::account(&setup.ballot)
+                .owner(&ID)
+                .space(Ballot::LEN)
+                .data_slice(0, setup.proposal.as_ref())
+                .data_slice(32, setup.owner.as_ref())
+                .data_slice(CREDITS_OFFSET, &16u64.to_le_bytes())
+                .data_slice(WEIGHT_OFFSET, &4u64.to_le_bytes())
+                .build(),
+            Check::account(&setup.proposal)
+                .data_slice(YES_WEIGHT_OFFSET, &4u64.to_le_bytes())
+                .build(),
+            Check::account(&setup.voter)
+                .data_slice(CREDITS_OFFSET, &(AMOUNT - 16).to_le_bytes())
+                .build(),
+        ],
+    );
+
+    // The fifth vote costs another 9 credits, not 1.
+    setup.accounts = setup.process(
+        &setup.instruction_vote(&setup.owner, 9, Side::Yes),
+        &[
+            Check::success(),
+            Check::account(&setup.ballot)
+                .data_slice(CREDITS_OFFSET, &25u64.to_le_bytes())
+                .data_slice(WEIGHT_OFFSET, &5u64.to_le_bytes())
+                .build(),
+            Check::account(&setup.proposal)
+                .data_slice(YES_WEIGHT_OFFSET, &5u64.to_le_bytes())
+                .data_slice(NO_WEIGHT_OFFSET, &0u64.to_le_bytes())
+                .build(),
+            Check::account(&setup.voter)
+                .data_slice(CREDITS_OFFSET, &(AMOUNT - 25).to_le_bytes())
+                .build(),
+        ],
+    );
+
+    // Committed credits can't be withdrawn or used for the other side.
+    let insufficient_credits = || {
+        [Check::err(ProgramError::Custom(
+            Namespace::QuadraticVoting.code(QuadraticVotingError::InsufficientCredits as u32),
+        ))]
+    };
+    setup.process(
+        &setup.instruction_withdraw(AMOUNT, &setup.vault),
+        &insufficient_credits(),
+    );
+    setup.process(
+        &setup.instruction_vote(&setup.owner, 1, Side::No),
+        &[Check::err(ProgramError::Custom(
+            Namespace::QuadraticVoting.code(QuadraticVotingError::SideMismatch as u32),
+        ))],
+    );
+    setup.process(
+        &setup.instruction_vote(&setup.owner, AMOUNT, Side::Yes),
+        &insufficient_credits(),
+    );
+    setup.process(
+        &setup.instruction_refund(&setup.owner),
+        &[Check::err(ProgramError::Custom(
+            Namespace::QuadraticVoting.code(QuadraticVotingError::VotingOpen as u32),
+        ))],
+    );
+
+    // Once the voting ends, the credits are refunded and can be withdrawn,
+    // while the tally stays.
+    setup.mollusk.sysvars.clock.unix_timestamp = ENDS_AT;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_vote(&setup.owner, 1, Side::Yes),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::QuadraticVoting.code(QuadraticVotingError::VotingClosed as u32),
+        ))],
+    );
+    setup.accounts = setup.process(
+        &setup.instruction_refund(&setup.owner),
+        &[
+            Check::success(),
+            Check::account(&setup.ballot).closed().build(),
+            Check::account(&setup.voter)
+                .data_slice(CREDITS_OFFSET, &AMOUNT.to_le_bytes())
+                .build(),
+            Check::account(&setup.proposal)
+                .data_slice(YES_WEIGHT_OFFSET, &5u64.to_le_bytes())
+                .build(),
+        ],
+    );
+    setup.accounts = setup.process(
+        &setup.instruction_withdraw(AMOUNT, &setup.vault),
+        &[
+            Check::success(),
+            Check::account(&setup.owner_ata)
+                .data_slice(AMOUNT_OFFSET, &AMOUNT.to_le_bytes())
+                .build(),
+            Check::account(&setup.voter)
+                .data_slice(CREDITS_OFFSET, &0u64.to_le_bytes())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_quadratic_voting_wrong_owner() {
+    let mut setup = Setup::new();
+    setup.deposit();
+    setup.accounts = setup.process(
+        &setup.instruction_vote(&setup.owner, 16, Side::No),
+        &[
+            Check::success(),
+            Check::account(&setup.proposal)
+                .data_slice(NO_WEIGHT_OFFSET, &4u64.to_le_bytes())
+                .build(),
+        ],
+    );
+
+    let attacker = Pubkey::new_unique();
+    setup.accounts.push((
+        attacker,
+        Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+    ));
+
+    // Nobody else can spend the credits of the voter or claim its refund.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_vote(&attacker, 1, Side::No),
+        &setup.accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+    setup.mollusk.sysvars.clock.unix_timestamp = ENDS_AT;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_refund(&attacker),
+        &setup.accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+#[test]
+fn test_quadratic_voting_withdraw_wrong_vault() {
+    let mut setup = Setup::new();
+    setup.deposit();
+
+    // A token account owned by the realm PDA, but not its vault.
+    let mint = setup.accounts[1].0;
+    let other_vault = Pubkey::new_unique();
+    let other_vault_account = token_account(&setup.mollusk, &mint, &setup.realm, AMOUNT);
+    setup.accounts.push((other_vault, other_vault_account));
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_withdraw(AMOUNT, &other_vault),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::QuadraticVoting.code(QuadraticVotingError::VaultMismatch as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_quadratic_voting_voter_as_proposal() {
+    let mut setup = Setup::new();
+    setup.deposit();
+
+    // A voter of another owner, whose key read as the end of a proposal is
+    // still in the future.
+    let victim = Pubkey::new_from_array([1; 32]);
+    let victim_voter = Pubkey::new_unique();
+    let mut data = vec![0; Voter::LEN];
+    data[0..32].copy_from_slice(setup.realm.as_ref());
+    data[32..64].copy_from_slice(victim.as_ref());
+    data[CREDITS_OFFSET..CREDITS_OFFSET + 8].copy_from_slice(&AMOUNT.to_le_bytes());
+    let mut victim_voter_account = Account::new(
+        setup.mollusk.sysvars.rent.minimum_balance(Voter::LEN),
+        Voter::LEN,
+        &ID,
+    );
+    victim_voter_account.data = data;
+    let (ballot, ballot_bump) = Pubkey::find_program_address(
+        &[
+            BALLOT_SEED.as_bytes(),
+            victim_voter.as_ref(),
+            setup.owner.as_ref(),
+        ],
+        &ID,
+    );
+    setup.accounts.push((victim_voter, victim_voter_account));
+    setup.accounts.push((ballot, Account::default()));
+
+    // Voting on it would add the weight of the ballot to the key of the
+    // victim, locking their credits.
+    let data = VoteInstructionData::new(16, Side::Yes, ballot_bump);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.owner, true),
+        AccountMeta::new(setup.voter, false),
+        AccountMeta::new(victim_voter, false),
+        AccountMeta::new(ballot, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(QuadraticVotingInstruction::Vote, &data),
+        ix_accounts,
+    );
+    setup.process(
+        &instruction,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
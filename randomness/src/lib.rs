@@ -0,0 +1,544 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_key_eq, require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("HcyAwe9QHa8GqCzKHm2aRz6WSXAJCvFtJKJGmFKSmLJX");
+
+pub const RECORD_SEED: &str = "record";
+
+/// SlotHashes sysvar ID.
+pub const SLOT_HASHES_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("SysvarS1otHashes111111111111111111111111111");
+
+/// RecentBlockhashes sysvar ID.
+pub const RECENT_BLOCKHASHES_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("SysvarRecentB1ockHashes11111111111111111111");
+
+/// Size of a SlotHashes entry: slot and hash.
+const SLOT_HASH_ENTRY_LEN: usize = 40;
+/// Maximum number of SlotHashes entries.
+const SLOT_HASHES_MAX_ENTRIES: u64 = 512;
+
+/// Errors returned by the randomness program.
+#[repr(u32)]
+pub enum RandomnessError {
+    /// The instruction was invoked through CPI.
+    CpiNotAllowed,
+    /// The user already drew in the current slot.
+    AlreadyDrawnThisSlot,
+    /// The user has a commitment that wasn't revealed yet.
+    PendingCommitment,
+    /// The user has no commitment to reveal.
+    NoCommitment,
+    /// The secret doesn't match the commitment.
+    InvalidSecret,
+    /// The hash of the commitment slot isn't known yet.
+    TooEarly,
+    /// The hash of the commitment slot isn't in the SlotHashes sysvar
+    /// anymore.
+    CommitmentExpired,
+}
+
+impl From<RandomnessError> for ProgramError {
+    fn from(e: RandomnessError) -> Self {
+        Namespace::Randomness.error(e as u32)
+    }
+}
+
+/// Sources of randomness.
+///
+/// None of them is truly random. Each trusts someone not to manipulate the
+/// outcome:
+///
+/// * `SlotHashes` and `RecentBlockhashes` - the leader producing the slot can
+///   grind the hash by reordering or dropping transactions. Everyone else can
+///   read the value before submitting a transaction in the same slot, so the
+///   outcome is only unpredictable to the user if they can't choose when
+///   their transaction lands.
+/// * `CommitReveal` - the user commits to a secret before the slot hash it's
+///   mixed with exists. The leader of the commitment slot can still grind
+///   the hash, but only while colluding with the user. The user can withhold
+///   an unfavourable reveal, so applications have to penalize commitments
+///   which are never revealed.
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum RandomnessSource {
+    /// Most recent entry of the SlotHashes sysvar.
+    SlotHashes,
+    /// Most recent entry of the deprecated RecentBlockhashes sysvar.
+    RecentBlockhashes,
+    /// User secret XOR the hash of the slot in which it was committed.
+    CommitReveal,
+}
+
+impl TryFrom<&u8> for RandomnessSource {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::SlotHashes),
+            1 => Ok(Self::RecentBlockhashes),
+            2 => Ok(Self::CommitReveal),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// On-chain record of the randomness drawn by a user.
+#[repr(C)]
+pub struct Record {
+    pub user: Pubkey,
+    /// SHA-256 hash of the secret, all zeros if there is no pending
+    /// commitment.
+    pub commitment: [u8; 32],
+    /// Slot in which the commitment was made.
+    pub commit_slot: u64,
+    /// Last drawn value.
+    pub result: [u8; 32],
+    /// Slot in which the last value was drawn.
+    pub result_slot: u64,
+    /// `RandomnessSource` of the last drawn value.
+    pub source: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+assert_layout!(Record, size = 120, align = 8, {
+    user: 0,
+    commitment: 32,
+    commit_slot: 64,
+    result: 72,
+    result_slot: 104,
+    source: 112,
+    bump: 113,
+});
+
+impl Record {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Stores a drawn value.
+    fn record(&mut self, source: RandomnessSource, result: [u8; 32], slot: u64) {
+        self.result = result;
+        self.result_slot = slot;
+        self.source = source as u8;
+
+        set_return_data(&result);
+    }
+}
+
+/// Randomness program instruction discriminators.
+#[repr(u8)]
+pub enum RandomnessInstruction {
+    /// Creates a record account.
+    Initialize,
+    /// Draws a value from a sysvar source.
+    Draw,
+    /// Commits to a secret.
+    Commit,
+    /// Reveals the secret and draws a value from it.
+    Reveal,
+}
+
+impl TryFrom<&u8> for RandomnessInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Draw),
+            2 => Ok(Self::Commit),
+            3 => Ok(Self::Reveal),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct DrawInstructionData {
+    /// `RandomnessSource`, either `SlotHashes` or `RecentBlockhashes`.
+    pub source: u8,
+}
+
+impl DrawInstructionData {
+    pub fn new(source: RandomnessSource) -> Self {
+        Self {
+            source: source as u8,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct CommitInstructionData {
+    /// SHA-256 hash of the secret.
+    pub commitment: [u8; 32],
+}
+
+impl CommitInstructionData {
+    pub fn new(commitment: [u8; 32]) -> Self {
+        Self { commitment }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct RevealInstructionData {
+    pub secret: [u8; 32],
+}
+
+impl RevealInstructionData {
+    pub fn new(secret: [u8; 32]) -> Self {
+        Self { secret }
+    }
+}
+
+/// Computes the SHA-256 hash of the concatenation of `vals` using the
+/// `sol_sha256` syscall.
+pub fn hashv(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash = [0; 32];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        );
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    core::hint::black_box((vals, &mut hash));
+
+    hash
+}
+
+/// Returns the height of the invocation stack, 1 for top-level instructions.
+fn stack_height() -> u64 {
+    #[cfg(target_os = "solana")]
+    unsafe {
+        pinocchio::syscalls::sol_get_stack_height()
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    1
+}
+
+/// Returns the most recent slot hash from the SlotHashes sysvar.
+fn slot_hashes_entropy(slot_hashes: &AccountInfo) -> Result<[u8; 32], ProgramError> {
+    let data = slot_hashes.try_borrow_data()?;
+    // Entries are sorted from the newest. Skip the length and the slot.
+    data.get(16..48)
+        .ok_or(ProgramError::InvalidAccountData)?
+        .try_into()
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Returns the most recent blockhash from the RecentBlockhashes sysvar.
+fn recent_blockhashes_entropy(recent_blockhashes: &AccountInfo) -> Result<[u8; 32], ProgramError> {
+    let data = recent_blockhashes.try_borrow_data()?;
+    // Entries are sorted from the newest. Skip the length.
+    data.get(8..40)
+        .ok_or(ProgramError::InvalidAccountData)?
+        .try_into()
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Returns the hash of the oldest slot not older than `commit_slot`.
+///
+/// Slots can be skipped, so the commitment slot itself might have no hash.
+fn commit_slot_hash(slot_hashes: &AccountInfo, commit_slot: u64) -> Result<[u8; 32], ProgramError> {
+    let data = slot_hashes.try_borrow_data()?;
+    let len = u64::from_le_bytes(
+        data.get(..8)
+            .ok_or(ProgramError::InvalidAccountData)?
+            .try_into()
+            .unwrap(),
+    ) as usize;
+    let entries = data
+        .get(8..8 + len * SLOT_HASH_ENTRY_LEN)
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    // Entries are sorted from the newest.
+    let mut found = None;
+    let mut reached_older = false;
+    for entry in entries.chunks_exact(SLOT_HASH_ENTRY_LEN) {
+        let slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        if slot < commit_slot {
+            reached_older = true;
+            break;
+        }
+        found = Some((slot, &entry[8..]));
+    }
+
+    match found {
+        // No hash was produced since the commitment.
+        None => Err(RandomnessError::TooEarly.into()),
+        // All entries are newer than the commitment, so the hash of the
+        // commitment slot might have been evicted. The entry found instead
+        // could have been known when committing.
+        Some((slot, _)) if slot != commit_slot && !reached_older => {
+            Err(RandomnessError::CommitmentExpired.into())
+        }
+        Some((_, hash)) => Ok(hash.try_into().unwrap()),
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = RandomnessInstruction::try_from(instruction)?;
+
+    match instruction {
+        RandomnessInstruction::Initialize => process_initialize(accounts, instruction_data),
+        RandomnessInstruction::Draw => process_draw(accounts, instruction_data),
+        RandomnessInstruction::Commit => process_commit(accounts, instruction_data),
+        RandomnessInstruction::Reveal => process_reveal(accounts, instruction_data),
+    }
+}
+
+/// Checks that `user` signed and owns `record`.
+fn check_user(user: &AccountInfo, record: &AccountInfo) -> ProgramResult {
+    require_signer(user)?;
+    // Check if the record PDA is owned by the program.
+    require_owned_by(record, &ID)?;
+
+    let data = record.try_borrow_data()?;
+    let data: &Record = unsafe { &*data.as_ptr().cast() };
+    if !keys_eq(&data.user, user.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    Ok(())
+}
+
+/// Creates a record account of `user`.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, record, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(user)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `record`.
+    let record_pda = create_program_address(
+        &[RECORD_SEED.as_bytes(), user.key(), &[instruction_data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(record.key(), &record_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the record PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(RECORD_SEED.as_bytes()),
+        Seed::from(user.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: user,
+        to: record,
+        lamports: Rent::get()?.minimum_balance(Record::LEN),
+        space: Record::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the record PDA.
+    let mut data = record.try_borrow_mut_data()?;
+    let data: &mut Record = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the record.
+    data.user = *user.key();
+    data.bump = instruction_data.bump;
+
+    info!("Created the record account");
+
+    Ok(())
+}
+
+/// Draws a value from a sysvar and records it.
+///
+/// The value is the hash of the sysvar entropy, the user and the previous
+/// value, so users drawing in the same slot get different values.
+pub fn process_draw(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, record, sysvar] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_user(user, record)?;
+
+    // Deserialize instruction data.
+    let instruction_data = DrawInstructionData::from_bytes(instruction_data)?;
+    let source = RandomnessSource::try_from(&instruction_data.source)?;
+
+    // A calling program could inspect the outcome and revert the whole
+    // transaction if it doesn't like it. Only accept top-level instructions.
+    if stack_height() != 1 {
+        return Err(RandomnessError::CpiNotAllowed.into());
+    }
+
+    // Check that the entropy comes from the actual sysvar and not from an
+    // account crafted by the caller.
+    let entropy = match source {
+        RandomnessSource::SlotHashes => {
+            require_key_eq(sysvar, &SLOT_HASHES_ID)?;
+            slot_hashes_entropy(sysvar)?
+        }
+        RandomnessSource::RecentBlockhashes => {
+            require_key_eq(sysvar, &RECENT_BLOCKHASHES_ID)?;
+            recent_blockhashes_entropy(sysvar)?
+        }
+        // Needs a commitment first.
+        RandomnessSource::CommitReveal => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    // Deserialize the record PDA.
+    let mut data = record.try_borrow_mut_data()?;
+    let data: &mut Record = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // The entropy doesn't change within a slot, so another draw would be
+    // derived only from values which are already known.
+    let slot = Clock::get()?.slot;
+    if slot <= data.result_slot {
+        return Err(RandomnessError::AlreadyDrawnThisSlot.into());
+    }
+
+    let result = hashv(&[&entropy, user.key(), &data.result]);
+    data.record(source, result, slot);
+
+    info!("Drew a value in slot {}", slot);
+
+    Ok(())
+}
+
+/// Commits to the SHA-256 hash of a secret.
+pub fn process_commit(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, record] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_user(user, record)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CommitInstructionData::from_bytes(instruction_data)?;
+
+    // All zeros means no commitment.
+    if instruction_data.commitment == [0; 32] {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Deserialize the record PDA.
+    let mut data = record.try_borrow_mut_data()?;
+    let data: &mut Record = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Replacing a commitment would let the user pick a new secret after
+    // seeing the outcome of the old one. Only expired commitments, which
+    // can't be revealed anymore, can be replaced.
+    let slot = Clock::get()?.slot;
+    if data.commitment != [0; 32] && slot.saturating_sub(data.commit_slot) < SLOT_HASHES_MAX_ENTRIES
+    {
+        return Err(RandomnessError::PendingCommitment.into());
+    }
+
+    data.commitment = instruction_data.commitment;
+    data.commit_slot = slot;
+
+    info!("Committed in slot {}", slot);
+
+    Ok(())
+}
+
+/// Reveals the secret and records it XOR the hash of the commitment slot.
+pub fn process_reveal(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, record, slot_hashes] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_user(user, record)?;
+
+    // Check that the slot hashes come from the actual sysvar and not from an
+    // account crafted by the caller.
+    require_key_eq(slot_hashes, &SLOT_HASHES_ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = RevealInstructionData::from_bytes(instruction_data)?;
+
+    // Deserialize the record PDA.
+    let mut data = record.try_borrow_mut_data()?;
+    let data: &mut Record = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    if data.commitment == [0; 32] {
+        return Err(RandomnessError::NoCommitment.into());
+    }
+    if hashv(&[&instruction_data.secret]) != data.commitment {
+        return Err(RandomnessError::InvalidSecret.into());
+    }
+
+    // The hash didn't exist when the user committed, so the user couldn't
+    // pick a secret matching it.
+    let hash = commit_slot_hash(slot_hashes, data.commit_slot)?;
+    let mut result = instruction_data.secret;
+    for (r, h) in result.iter_mut().zip(hash) {
+        *r ^= h;
+    }
+
+    data.commitment = [0; 32];
+    let slot = Clock::get()?.slot;
+    data.record(RandomnessSource::CommitReveal, result, slot);
+
+    info!("Revealed a value committed in slot {}", data.commit_slot);
+
+    Ok(())
+}
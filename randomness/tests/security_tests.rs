@@ -0,0 +1,202 @@
+//! Attacks on the randomness program, each rejected with its own error
+//! before any entropy is read, so the sysvars stay empty.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use randomness::{
+    CommitInstructionData, DrawInstructionData, InitializeInstructionData, RandomnessInstruction,
+    RandomnessSource, Record, RevealInstructionData, RECORD_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(randomness::ID);
+const SLOT_HASHES_ID: Pubkey = Pubkey::new_from_array(randomness::SLOT_HASHES_ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: RandomnessInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<RandomnessInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the record PDA of `user` and its bump.
+fn record_pda(user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECORD_SEED.as_bytes(), user.as_ref()], &ID)
+}
+
+/// Returns a record of `user` with a pending commitment.
+fn record_account(mollusk: &Mollusk, user: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Record::LEN),
+        Record::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(user.as_ref());
+    account.data[32..64].copy_from_slice(&[1; 32]);
+    account
+}
+
+/// Accounts of a user with a record, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    user: Pubkey,
+    record: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/randomness");
+
+        let user = Pubkey::new_unique();
+        let (record, _) = record_pda(&user);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (user, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (record, record_account(&mollusk, &user)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (SLOT_HASHES_ID, Account::default()),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            user,
+            record,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Commits to a secret of `user` in `record`.
+    fn instruction_commit(&self, user: &Pubkey, record: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*user, true),
+            AccountMeta::new(*record, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                RandomnessInstruction::Commit,
+                &CommitInstructionData::new([7; 32]),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_user_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_commit(&setup.user, &setup.record);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the record of the user before the user does, with
+/// their own bump.
+#[test]
+fn test_initialize_record_of_other_user() {
+    let setup = Setup::new();
+    let record = setup.record;
+    let setup = setup.with(record, Account::default());
+    let (_, bump) = record_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(record, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            RandomnessInstruction::Initialize,
+            &InitializeInstructionData::new(bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker replaces the pending commitment of the user with one of
+/// their own.
+#[test]
+fn test_commit_in_record_of_other_user() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_commit(&setup.attacker, &setup.record),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker reveals the commitment of the user, whose secret they
+/// learned, at a time of their choice.
+#[test]
+fn test_reveal_in_record_of_other_user() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.attacker, true),
+        AccountMeta::new(setup.record, false),
+        AccountMeta::new_readonly(SLOT_HASHES_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            RandomnessInstruction::Reveal,
+            &RevealInstructionData::new([7; 32]),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// A record of the attacker owned by another program, in which they could
+/// reset the slot of their last draw to draw again in the same slot.
+#[test]
+fn test_draw_with_spoofed_record() {
+    let setup = Setup::new();
+    let mut record_account = record_account(&setup.mollusk, &setup.attacker);
+    record_account.owner = Pubkey::new_unique();
+    let record = Pubkey::new_unique();
+    let setup = setup.with(record, record_account);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.attacker, true),
+        AccountMeta::new(record, false),
+        AccountMeta::new_readonly(SLOT_HASHES_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            RandomnessInstruction::Draw,
+            &DrawInstructionData::new(RandomnessSource::SlotHashes),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
@@ -0,0 +1,434 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use randomness::{
+    CommitInstructionData, DrawInstructionData, InitializeInstructionData, RandomnessError,
+    RandomnessInstruction, RandomnessSource, Record, RevealInstructionData, RECORD_SEED,
+};
+use sha2::{Digest, Sha256};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(randomness::ID);
+const SLOT_HASHES_ID: Pubkey = Pubkey::new_from_array(randomness::SLOT_HASHES_ID);
+const RECENT_BLOCKHASHES_ID: Pubkey = Pubkey::new_from_array(randomness::RECENT_BLOCKHASHES_ID);
+
+/// Offsets of the record account fields.
+const COMMITMENT_OFFSET: usize = 32;
+const COMMIT_SLOT_OFFSET: usize = 64;
+const RESULT_OFFSET: usize = 72;
+const RESULT_SLOT_OFFSET: usize = 104;
+const SOURCE_OFFSET: usize = 112;
+
+const SECRET: [u8; 32] = [7; 32];
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: RandomnessInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<RandomnessInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn instruction_initialize(user: &Pubkey, record: &Pubkey, bump: u8) -> Instruction {
+    let data = InitializeInstructionData::new(bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*user, true),
+        AccountMeta::new(*record, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(RandomnessInstruction::Initialize, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_draw(
+    source: RandomnessSource,
+    user: &Pubkey,
+    record: &Pubkey,
+    sysvar: &Pubkey,
+) -> Instruction {
+    let data = DrawInstructionData::new(source);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*user, true),
+        AccountMeta::new(*record, false),
+        AccountMeta::new_readonly(*sysvar, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(RandomnessInstruction::Draw, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_commit(commitment: [u8; 32], user: &Pubkey, record: &Pubkey) -> Instruction {
+    let data = CommitInstructionData::new(commitment);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*user, true),
+        AccountMeta::new(*record, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(RandomnessInstruction::Commit, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_reveal(secret: [u8; 32], user: &Pubkey, record: &Pubkey) -> Instruction {
+    let data = RevealInstructionData::new(secret);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*user, true),
+        AccountMeta::new(*record, false),
+        AccountMeta::new_readonly(SLOT_HASHES_ID, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(RandomnessInstruction::Reveal, &data),
+        ix_accounts,
+    )
+}
+
+/// Creates an initialized record account with the given commitment.
+fn record_account(
+    mollusk: &Mollusk,
+    user: &Pubkey,
+    commitment: [u8; 32],
+    commit_slot: u64,
+) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Record::LEN),
+        Record::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(user.as_ref());
+    account.data[COMMITMENT_OFFSET..COMMIT_SLOT_OFFSET].copy_from_slice(&commitment);
+    account.data[COMMIT_SLOT_OFFSET..RESULT_OFFSET].copy_from_slice(&commit_slot.to_le_bytes());
+    account
+}
+
+/// Creates a SlotHashes sysvar account with the given entries, sorted from
+/// the newest.
+fn slot_hashes_account(entries: &[(u64, [u8; 32])]) -> Account {
+    let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+    for (slot, hash) in entries {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(hash);
+    }
+    Account {
+        lamports: LAMPORTS_PER_SOL,
+        data,
+        ..Account::default()
+    }
+}
+
+/// Creates a RecentBlockhashes sysvar account with a single blockhash.
+fn recent_blockhashes_account(blockhash: [u8; 32]) -> Account {
+    let mut data = 1u64.to_le_bytes().to_vec();
+    data.extend_from_slice(&blockhash);
+    data.extend_from_slice(&5000u64.to_le_bytes());
+    Account {
+        lamports: LAMPORTS_PER_SOL,
+        data,
+        ..Account::default()
+    }
+}
+
+fn xor(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let mut result = a;
+    for (r, b) in result.iter_mut().zip(b) {
+        *r ^= b;
+    }
+    result
+}
+
+#[test]
+fn test_randomness_initialize() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/randomness");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let user = Pubkey::new_unique();
+    let (record, bump) =
+        Pubkey::find_program_address(&[RECORD_SEED.as_bytes(), user.as_ref()], &ID);
+
+    mollusk.process_and_validate_instruction(
+        &instruction_initialize(&user, &record, bump),
+        &[
+            (user, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (record, Account::default()),
+            (system_program, system_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&record)
+                .owner(&ID)
+                .space(Record::LEN)
+                .data_slice(0, user.as_ref())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_randomness_draw_slot_hashes() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/randomness");
+    mollusk.sysvars.clock.slot = 10;
+
+    let user = Pubkey::new_unique();
+    let record = Pubkey::new_unique();
+    let slot_hash = [1; 32];
+
+    let expected: [u8; 32] = Sha256::new()
+        .chain_update(slot_hash)
+        .chain_update(user)
+        .chain_update([0; 32])
+        .finalize()
+        .into();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_draw(
+                    RandomnessSource::SlotHashes,
+                    &user,
+                    &record,
+                    &SLOT_HASHES_ID,
+                ),
+                &[
+                    Check::success(),
+                    Check::return_data(&expected),
+                    Check::account(&record)
+                        .data_slice(RESULT_OFFSET, &expected)
+                        .data_slice(RESULT_SLOT_OFFSET, &10u64.to_le_bytes())
+                        .data_slice(SOURCE_OFFSET, &[RandomnessSource::SlotHashes as u8])
+                        .build(),
+                ],
+            ),
+            // The entropy is the same for the whole slot.
+            (
+                &instruction_draw(
+                    RandomnessSource::SlotHashes,
+                    &user,
+                    &record,
+                    &SLOT_HASHES_ID,
+                ),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Randomness.code(RandomnessError::AlreadyDrawnThisSlot as u32),
+                ))],
+            ),
+        ],
+        &[
+            (user, Account::default()),
+            (record, record_account(&mollusk, &user, [0; 32], 0)),
+            (SLOT_HASHES_ID, slot_hashes_account(&[(9, slot_hash)])),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_randomness_draw_recent_blockhashes() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/randomness");
+    mollusk.sysvars.clock.slot = 10;
+
+    let user = Pubkey::new_unique();
+    let record = Pubkey::new_unique();
+    let blockhash = [2; 32];
+
+    let expected: [u8; 32] = Sha256::new()
+        .chain_update(blockhash)
+        .chain_update(user)
+        .chain_update([0; 32])
+        .finalize()
+        .into();
+
+    mollusk.process_and_validate_instruction(
+        &instruction_draw(
+            RandomnessSource::RecentBlockhashes,
+            &user,
+            &record,
+            &RECENT_BLOCKHASHES_ID,
+        ),
+        &[
+            (user, Account::default()),
+            (record, record_account(&mollusk, &user, [0; 32], 0)),
+            (RECENT_BLOCKHASHES_ID, recent_blockhashes_account(blockhash)),
+        ],
+        &[
+            Check::success(),
+            Check::return_data(&expected),
+            Check::account(&record)
+                .data_slice(RESULT_OFFSET, &expected)
+                .data_slice(SOURCE_OFFSET, &[RandomnessSource::RecentBlockhashes as u8])
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_randomness_draw_fake_sysvar() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/randomness");
+    mollusk.sysvars.clock.slot = 10;
+
+    let user = Pubkey::new_unique();
+    let record = Pubkey::new_unique();
+    // An account with the layout of SlotHashes, but controlled by the user.
+    let fake_slot_hashes = Pubkey::new_unique();
+
+    mollusk.process_and_validate_instruction(
+        &instruction_draw(
+            RandomnessSource::SlotHashes,
+            &user,
+            &record,
+            &fake_slot_hashes,
+        ),
+        &[
+            (user, Account::default()),
+            (record, record_account(&mollusk, &user, [0; 32], 0)),
+            (fake_slot_hashes, slot_hashes_account(&[(9, [1; 32])])),
+        ],
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_randomness_commit() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/randomness");
+    mollusk.sysvars.clock.slot = 100;
+
+    let user = Pubkey::new_unique();
+    let record = Pubkey::new_unique();
+    let commitment: [u8; 32] = Sha256::digest(SECRET).into();
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_commit(commitment, &user, &record),
+                &[
+                    Check::success(),
+                    Check::account(&record)
+                        .data_slice(COMMITMENT_OFFSET, &commitment)
+                        .data_slice(COMMIT_SLOT_OFFSET, &100u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            // The user can't change their mind before revealing.
+            (
+                &instruction_commit([3; 32], &user, &record),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Randomness.code(RandomnessError::PendingCommitment as u32),
+                ))],
+            ),
+        ],
+        &[
+            (user, Account::default()),
+            (record, record_account(&mollusk, &user, [0; 32], 0)),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_randomness_reveal() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/randomness");
+    mollusk.sysvars.clock.slot = 103;
+
+    let user = Pubkey::new_unique();
+    let record = Pubkey::new_unique();
+    let commitment: [u8; 32] = Sha256::digest(SECRET).into();
+
+    // Slot 100, in which the user committed, was skipped. The next one is
+    // used instead.
+    let expected = xor(SECRET, [101; 32]);
+
+    mollusk.process_and_validate_instruction(
+        &instruction_reveal(SECRET, &user, &record),
+        &[
+            (user, Account::default()),
+            (record, record_account(&mollusk, &user, commitment, 100)),
+            (
+                SLOT_HASHES_ID,
+                slot_hashes_account(&[(102, [102; 32]), (101, [101; 32]), (99, [99; 32])]),
+            ),
+        ],
+        &[
+            Check::success(),
+            Check::return_data(&expected),
+            Check::account(&record)
+                .data_slice(COMMITMENT_OFFSET, &[0; 32])
+                .data_slice(RESULT_OFFSET, &expected)
+                .data_slice(RESULT_SLOT_OFFSET, &103u64.to_le_bytes())
+                .data_slice(SOURCE_OFFSET, &[RandomnessSource::CommitReveal as u8])
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_randomness_reveal_errors() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/randomness");
+    mollusk.sysvars.clock.slot = 1000;
+
+    let user = Pubkey::new_unique();
+    let record = Pubkey::new_unique();
+    let commitment: [u8; 32] = Sha256::digest(SECRET).into();
+
+    let cases = [
+        // Wrong secret.
+        (
+            100,
+            [8; 32],
+            &[(101, [101; 32]), (99, [99; 32])],
+            RandomnessError::InvalidSecret,
+        ),
+        // No hash produced since the commitment.
+        (
+            100,
+            SECRET,
+            &[(99, [99; 32]), (98, [98; 32])],
+            RandomnessError::TooEarly,
+        ),
+        // The hash of the commitment slot was evicted.
+        (
+            100,
+            SECRET,
+            &[(700, [200; 32]), (600, [100; 32])],
+            RandomnessError::CommitmentExpired,
+        ),
+    ];
+
+    for (commit_slot, secret, slot_hashes, err) in cases {
+        mollusk.process_and_validate_instruction(
+            &instruction_reveal(secret, &user, &record),
+            &[
+                (user, Account::default()),
+                (
+                    record,
+                    record_account(&mollusk, &user, commitment, commit_slot),
+                ),
+                (SLOT_HASHES_ID, slot_hashes_account(slot_hashes)),
+            ],
+            &[Check::err(ProgramError::Custom(
+                Namespace::Randomness.code(err as u32),
+            ))],
+        );
+    }
+}
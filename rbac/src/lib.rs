@@ -0,0 +1,528 @@
+#![no_std]
+
+use common::{assert_layout, debug, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use common::close::close_account;
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("7rCNWzZtQS5Pgf7Ti5wnEb8dGdBoc6H6ZEFVWBS6h7rQ");
+
+pub const ORGANIZATION_SEED: &str = "organization";
+pub const MEMBERSHIP_SEED: &str = "membership";
+
+/// Errors returned by the RBAC program.
+#[repr(u32)]
+pub enum RbacError {
+    /// The role doesn't exist.
+    InvalidRole,
+    /// The member's role doesn't allow the action.
+    InsufficientRole,
+    /// The role of the creator of the organization can't be changed.
+    CreatorRoleImmutable,
+}
+
+impl From<RbacError> for ProgramError {
+    fn from(e: RbacError) -> Self {
+        Namespace::Rbac.error(e as u32)
+    }
+}
+
+/// Roles of organization members.
+///
+/// Roles are ordered, each role is allowed to do everything that the roles
+/// below it can.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Can read the organization value.
+    Viewer = 1,
+    /// Can increment the organization value.
+    Operator,
+    /// Can set the organization value and manage roles.
+    Admin,
+}
+
+impl TryFrom<&u8> for Role {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            1 => Ok(Self::Viewer),
+            2 => Ok(Self::Operator),
+            3 => Ok(Self::Admin),
+            _ => Err(RbacError::InvalidRole.into()),
+        }
+    }
+}
+
+/// On-chain representation of an organization.
+#[repr(C)]
+pub struct Organization {
+    pub creator: Pubkey,
+    /// Value guarded by roles.
+    pub value: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Organization, size = 48, align = 8, { creator: 0, value: 32, bump: 40 });
+
+impl Organization {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// On-chain representation of a role of a member in an organization.
+#[repr(C)]
+pub struct Membership {
+    pub organization: Pubkey,
+    pub member: Pubkey,
+    pub role: u8,
+    pub bump: u8,
+}
+
+assert_layout!(Membership, size = 66, align = 1, {
+    organization: 0,
+    member: 32,
+    role: 64,
+    bump: 65,
+});
+
+impl Membership {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Checks that `member` signed and holds at least the `required` role in
+/// `organization`, according to `membership`.
+///
+/// Other programs can call this function to guard their instructions with
+/// roles managed by this program.
+pub fn check_role(
+    member: &AccountInfo,
+    organization: &AccountInfo,
+    membership: &AccountInfo,
+    required: Role,
+) -> Result<Role, ProgramError> {
+    require_signer(member)?;
+    // Only this program can write memberships. An account with the same
+    // layout owned by anyone else could say anything.
+    require_owned_by(organization, &ID)?;
+    require_owned_by(membership, &ID)?;
+    // Both accounts are owned by this program, tell them apart by size.
+    if organization.data_len() != Organization::LEN || membership.data_len() != Membership::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let data = membership.try_borrow_data()?;
+    let data: &Membership = unsafe { &*data.as_ptr().cast() };
+
+    // A membership in another organization, or of another member, doesn't
+    // grant anything here.
+    if !keys_eq(&data.organization, organization.key()) || !keys_eq(&data.member, member.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let role = Role::try_from(&data.role)?;
+    if role < required {
+        return Err(RbacError::InsufficientRole.into());
+    }
+
+    Ok(role)
+}
+
+/// RBAC program instruction discriminators.
+#[repr(u8)]
+pub enum RbacInstruction {
+    /// Creates an organization and makes the creator its admin.
+    Initialize,
+    /// Grants a role to a member.
+    GrantRole,
+    /// Revokes the role of a member.
+    RevokeRole,
+    /// Performs an action requiring a role.
+    GuardedAction,
+}
+
+impl TryFrom<&u8> for RbacInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::GrantRole),
+            2 => Ok(Self::RevokeRole),
+            3 => Ok(Self::GuardedAction),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// Actions of `GuardedAction`.
+#[repr(u8)]
+pub enum Action {
+    /// Returns the value. Requires `Viewer`.
+    Read,
+    /// Increments the value. Requires `Operator`.
+    Increment,
+    /// Sets the value. Requires `Admin`.
+    Set,
+}
+
+impl TryFrom<&u8> for Action {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Read),
+            1 => Ok(Self::Increment),
+            2 => Ok(Self::Set),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+impl Action {
+    /// Returns the minimum role allowed to perform the action.
+    pub fn required_role(&self) -> Role {
+        match self {
+            Self::Read => Role::Viewer,
+            Self::Increment => Role::Operator,
+            Self::Set => Role::Admin,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub organization_bump: u8,
+    pub membership_bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(organization_bump: u8, membership_bump: u8) -> Self {
+        Self {
+            organization_bump,
+            membership_bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct GrantRoleInstructionData {
+    pub role: u8,
+    /// Bump of the membership PDA of the member.
+    pub bump: u8,
+}
+
+impl GrantRoleInstructionData {
+    pub fn new(role: Role, bump: u8) -> Self {
+        Self {
+            role: role as u8,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct GuardedActionInstructionData {
+    pub value: u64,
+    pub action: u8,
+}
+
+impl GuardedActionInstructionData {
+    pub fn new(action: Action, value: u64) -> Self {
+        Self {
+            value,
+            action: action as u8,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = RbacInstruction::try_from(instruction)?;
+
+    match instruction {
+        RbacInstruction::Initialize => process_initialize(accounts, instruction_data),
+        RbacInstruction::GrantRole => process_grant_role(accounts, instruction_data),
+        RbacInstruction::RevokeRole => process_revoke_role(accounts),
+        RbacInstruction::GuardedAction => process_guarded_action(accounts, instruction_data),
+    }
+}
+
+/// Creates a membership PDA of `member` in `organization` with `role`.
+fn create_membership(
+    payer: &AccountInfo,
+    organization: &AccountInfo,
+    member: &Pubkey,
+    membership: &AccountInfo,
+    role: Role,
+    bump: u8,
+) -> ProgramResult {
+    // Check the seeds of `membership`.
+    let membership_pda = create_program_address(
+        &[
+            MEMBERSHIP_SEED.as_bytes(),
+            organization.key(),
+            member,
+            &[bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(membership.key(), &membership_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the membership PDA.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(MEMBERSHIP_SEED.as_bytes()),
+        Seed::from(organization.key()),
+        Seed::from(member),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: payer,
+        to: membership,
+        lamports: Rent::get()?.minimum_balance(Membership::LEN),
+        space: Membership::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the membership PDA.
+    let mut data = membership.try_borrow_mut_data()?;
+    let data: &mut Membership = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the membership.
+    data.organization = *organization.key();
+    data.member = *member;
+    data.role = role as u8;
+    data.bump = bump[0];
+
+    Ok(())
+}
+
+/// Creates an organization of `creator` and makes `creator` its admin.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [creator, organization, membership, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(creator)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `organization`.
+    let organization_pda = create_program_address(
+        &[
+            ORGANIZATION_SEED.as_bytes(),
+            creator.key(),
+            &[instruction_data.organization_bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(organization.key(), &organization_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the organization PDA.
+    let bump = [instruction_data.organization_bump];
+    let seeds = [
+        Seed::from(ORGANIZATION_SEED.as_bytes()),
+        Seed::from(creator.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: creator,
+        to: organization,
+        lamports: Rent::get()?.minimum_balance(Organization::LEN),
+        space: Organization::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    {
+        // Deserialize the organization PDA.
+        let mut data = organization.try_borrow_mut_data()?;
+        let data: &mut Organization = unsafe { &mut *data.as_mut_ptr().cast() };
+
+        // Initialize the organization.
+        data.creator = *creator.key();
+        data.bump = instruction_data.organization_bump;
+    }
+
+    create_membership(
+        creator,
+        organization,
+        creator.key(),
+        membership,
+        Role::Admin,
+        instruction_data.membership_bump,
+    )?;
+
+    info!("Created the organization");
+
+    Ok(())
+}
+
+/// Grants `role` to `member`. Only admins can grant roles.
+///
+/// Creates the membership PDA of `member` if it doesn't exist, otherwise
+/// changes the role.
+pub fn process_grant_role(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, organization, admin_membership, member, membership, _system_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_role(admin, organization, admin_membership, Role::Admin)?;
+
+    // Deserialize instruction data.
+    let instruction_data = GrantRoleInstructionData::from_bytes(instruction_data)?;
+    let role = Role::try_from(&instruction_data.role)?;
+
+    check_not_creator(organization, member.key())?;
+
+    if membership.is_owned_by(&ID) {
+        if membership.data_len() != Membership::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Deserialize the membership PDA.
+        let mut data = membership.try_borrow_mut_data()?;
+        let data: &mut Membership = unsafe { &mut *data.as_mut_ptr().cast() };
+
+        if !keys_eq(&data.organization, organization.key()) || !keys_eq(&data.member, member.key())
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        data.role = role as u8;
+    } else {
+        create_membership(
+            admin,
+            organization,
+            member.key(),
+            membership,
+            role,
+            instruction_data.bump,
+        )?;
+    }
+
+    info!("Granted role {} to member", role as u8);
+
+    Ok(())
+}
+
+/// Revokes the role of a member by closing their membership PDA. Only admins
+/// can revoke roles.
+pub fn process_revoke_role(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, organization, admin_membership, membership] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_role(admin, organization, admin_membership, Role::Admin)?;
+
+    // Check if the membership PDA is owned by the program.
+    require_owned_by(membership, &ID)?;
+    if membership.data_len() != Membership::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    {
+        // Deserialize the membership PDA.
+        let data = membership.try_borrow_data()?;
+        let data: &Membership = unsafe { &*data.as_ptr().cast() };
+
+        if !keys_eq(&data.organization, organization.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        check_not_creator(organization, &data.member)?;
+    }
+
+    // Close the membership account by moving its lamports to the admin.
+    close_account(membership, admin)?;
+
+    info!("Revoked role");
+
+    Ok(())
+}
+
+/// Performs `action` on the organization value if the member's role allows
+/// it.
+pub fn process_guarded_action(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [member, organization, membership] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = GuardedActionInstructionData::from_bytes(instruction_data)?;
+    let action = Action::try_from(&instruction_data.action)?;
+
+    check_role(member, organization, membership, action.required_role())?;
+
+    // Deserialize the organization PDA.
+    let mut data = organization.try_borrow_mut_data()?;
+    let data: &mut Organization = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    match action {
+        Action::Read => set_return_data(&data.value.to_le_bytes()),
+        Action::Increment => data.value = data.value.saturating_add(1),
+        Action::Set => data.value = instruction_data.value,
+    }
+
+    debug!("Value is {}", data.value);
+
+    Ok(())
+}
+
+/// Checks that `member` isn't the creator of `organization`, whose admin
+/// role can't be changed. Otherwise admins could lock the organization out
+/// of its last admin.
+fn check_not_creator(organization: &AccountInfo, member: &Pubkey) -> ProgramResult {
+    let data = organization.try_borrow_data()?;
+    let data: &Organization = unsafe { &*data.as_ptr().cast() };
+
+    if &data.creator == member {
+        return Err(RbacError::CreatorRoleImmutable.into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,279 @@
+//! Attacks on the RBAC program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use rbac::{
+    Action, GrantRoleInstructionData, GuardedActionInstructionData, InitializeInstructionData,
+    Membership, Organization, RbacInstruction, Role, MEMBERSHIP_SEED, ORGANIZATION_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(rbac::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: RbacInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<RbacInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the organization PDA of `creator` and its bump.
+fn organization_pda(creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ORGANIZATION_SEED.as_bytes(), creator.as_ref()], &ID)
+}
+
+/// Returns the membership PDA of `member` in `organization` and its bump.
+fn membership_pda(organization: &Pubkey, member: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            MEMBERSHIP_SEED.as_bytes(),
+            organization.as_ref(),
+            member.as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// Returns an organization of `creator`.
+fn organization_account(mollusk: &Mollusk, creator: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Organization::LEN),
+        Organization::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(creator.as_ref());
+    account
+}
+
+/// Returns a membership of `member` in `organization` with `role`.
+fn membership_account(
+    mollusk: &Mollusk,
+    organization: &Pubkey,
+    member: &Pubkey,
+    role: Role,
+) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Membership::LEN),
+        Membership::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(organization.as_ref());
+    account.data[32..64].copy_from_slice(member.as_ref());
+    account.data[64] = role as u8;
+    account
+}
+
+/// Accounts of an organization with its creator and a viewer, and of an
+/// attacker, who is the admin of an organization of their own.
+struct Setup {
+    mollusk: Mollusk,
+    creator: Pubkey,
+    organization: Pubkey,
+    creator_membership: Pubkey,
+    member: Pubkey,
+    membership: Pubkey,
+    attacker: Pubkey,
+    attacker_organization: Pubkey,
+    attacker_membership: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/rbac");
+
+        let creator = Pubkey::new_unique();
+        let (organization, _) = organization_pda(&creator);
+        let (creator_membership, _) = membership_pda(&organization, &creator);
+        let member = Pubkey::new_unique();
+        let (membership, _) = membership_pda(&organization, &member);
+        let attacker = Pubkey::new_unique();
+        let (attacker_organization, _) = organization_pda(&attacker);
+        let (attacker_membership, _) = membership_pda(&attacker_organization, &attacker);
+
+        let accounts = vec![
+            (
+                creator,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (organization, organization_account(&mollusk, &creator)),
+            (
+                creator_membership,
+                membership_account(&mollusk, &organization, &creator, Role::Admin),
+            ),
+            (member, Account::default()),
+            (
+                membership,
+                membership_account(&mollusk, &organization, &member, Role::Viewer),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_organization,
+                organization_account(&mollusk, &attacker),
+            ),
+            (
+                attacker_membership,
+                membership_account(&mollusk, &attacker_organization, &attacker, Role::Admin),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            creator,
+            organization,
+            creator_membership,
+            member,
+            membership,
+            attacker,
+            attacker_organization,
+            attacker_membership,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Grants the admin role to the member of the organization in
+    /// `membership`, as `admin` of `organization`.
+    fn instruction_grant_role(
+        &self,
+        admin: &Pubkey,
+        organization: &Pubkey,
+        admin_membership: &Pubkey,
+    ) -> Instruction {
+        let (_, bump) = membership_pda(organization, &self.member);
+        let ix_accounts = vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(*organization, false),
+            AccountMeta::new_readonly(*admin_membership, false),
+            AccountMeta::new_readonly(self.member, false),
+            AccountMeta::new(self.membership, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                RbacInstruction::GrantRole,
+                &GrantRoleInstructionData::new(Role::Admin, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_admin_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_grant_role(
+        &setup.creator,
+        &setup.organization,
+        &setup.creator_membership,
+    );
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the organization of the creator before the creator
+/// does, becoming its admin.
+#[test]
+fn test_initialize_organization_of_other_creator() {
+    let setup = Setup::new();
+    let organization = setup.organization;
+    let creator_membership = setup.creator_membership;
+    let setup = setup
+        .with(organization, Account::default())
+        .with(creator_membership, Account::default());
+    let (_, organization_bump) = organization_pda(&setup.attacker);
+    let (_, membership_bump) = membership_pda(&organization, &setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(organization, false),
+        AccountMeta::new(creator_membership, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            RbacInstruction::Initialize,
+            &InitializeInstructionData::new(organization_bump, membership_bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker, as the admin of their own organization, makes the member
+/// of the organization an admin of it by rewriting the membership.
+#[test]
+fn test_grant_role_in_other_organization() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_grant_role(
+            &setup.attacker,
+            &setup.attacker_organization,
+            &setup.attacker_membership,
+        ),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The attacker, as the admin of their own organization, revokes the role
+/// of the member of the organization and takes the rent of the membership.
+#[test]
+fn test_revoke_role_in_other_organization() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new_readonly(setup.attacker_organization, false),
+        AccountMeta::new_readonly(setup.attacker_membership, false),
+        AccountMeta::new(setup.membership, false),
+    ];
+    let instruction =
+        Instruction::new_with_bytes(ID, &[RbacInstruction::RevokeRole as u8], ix_accounts);
+    setup.expect_err(&instruction, ProgramError::InvalidAccountData);
+}
+
+/// The organization of the attacker passed as their membership in the
+/// organization, which has the same owner.
+#[test]
+fn test_organization_as_membership() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.attacker, true),
+        AccountMeta::new(setup.organization, false),
+        AccountMeta::new_readonly(setup.attacker_organization, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            RbacInstruction::GuardedAction,
+            &GuardedActionInstructionData::new(Action::Set, 42),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidAccountData);
+}
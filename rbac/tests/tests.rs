@@ -0,0 +1,456 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use rbac::{
+    Action, GrantRoleInstructionData, GuardedActionInstructionData, InitializeInstructionData,
+    Membership, Organization, RbacError, RbacInstruction, Role, MEMBERSHIP_SEED, ORGANIZATION_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(rbac::ID);
+
+/// Offset of the role in the membership account.
+const ROLE_OFFSET: usize = 64;
+/// Offset of the value in the organization account.
+const VALUE_OFFSET: usize = 32;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: RbacInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<RbacInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn membership_address(organization: &Pubkey, member: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            MEMBERSHIP_SEED.as_bytes(),
+            organization.as_ref(),
+            member.as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// An organization with its creator and a member without a role.
+struct Setup {
+    mollusk: Mollusk,
+    creator: Pubkey,
+    organization: Pubkey,
+    creator_membership: Pubkey,
+    member: Pubkey,
+    membership: Pubkey,
+    membership_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/rbac");
+
+        let creator = Pubkey::new_unique();
+        let organization = Pubkey::new_unique();
+        let (creator_membership, _) = membership_address(&organization, &creator);
+        let member = Pubkey::new_unique();
+        let (membership, membership_bump) = membership_address(&organization, &member);
+
+        let accounts = vec![
+            (
+                creator,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (organization, organization_account(&mollusk, &creator, 0)),
+            (
+                creator_membership,
+                membership_account(&mollusk, &organization, &creator, Role::Admin),
+            ),
+            (member, Account::default()),
+            (membership, Account::default()),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            creator,
+            organization,
+            creator_membership,
+            member,
+            membership,
+            membership_bump,
+            accounts,
+        }
+    }
+
+    /// Adds a membership of `member` with `role` to the accounts.
+    fn with_role(mut self, role: Role) -> Self {
+        let account = membership_account(&self.mollusk, &self.organization, &self.member, role);
+        self.accounts
+            .iter_mut()
+            .find(|(key, _)| key == &self.membership)
+            .unwrap()
+            .1 = account;
+        self
+    }
+
+    fn instruction_grant_role(
+        &self,
+        admin: &Pubkey,
+        admin_membership: &Pubkey,
+        member: &Pubkey,
+        membership: &Pubkey,
+        role: Role,
+    ) -> Instruction {
+        let data = GrantRoleInstructionData::new(role, self.membership_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(self.organization, false),
+            AccountMeta::new_readonly(*admin_membership, false),
+            AccountMeta::new_readonly(*member, false),
+            AccountMeta::new(*membership, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(RbacInstruction::GrantRole, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_revoke_role(&self, membership: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.creator, true),
+            AccountMeta::new_readonly(self.organization, false),
+            AccountMeta::new_readonly(self.creator_membership, false),
+            AccountMeta::new(*membership, false),
+        ];
+        Instruction::new_with_bytes(ID, &[RbacInstruction::RevokeRole as u8], ix_accounts)
+    }
+
+    fn instruction_guarded_action(&self, action: Action, value: u64) -> Instruction {
+        let data = GuardedActionInstructionData::new(action, value);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.member, true),
+            AccountMeta::new(self.organization, false),
+            AccountMeta::new_readonly(self.membership, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(RbacInstruction::GuardedAction, &data),
+            ix_accounts,
+        )
+    }
+}
+
+/// Creates an initialized organization account.
+fn organization_account(mollusk: &Mollusk, creator: &Pubkey, value: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Organization::LEN),
+        Organization::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(creator.as_ref());
+    account.data[VALUE_OFFSET..VALUE_OFFSET + 8].copy_from_slice(&value.to_le_bytes());
+    account
+}
+
+/// Creates an initialized membership account.
+fn membership_account(
+    mollusk: &Mollusk,
+    organization: &Pubkey,
+    member: &Pubkey,
+    role: Role,
+) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Membership::LEN),
+        Membership::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(organization.as_ref());
+    account.data[32..64].copy_from_slice(member.as_ref());
+    account.data[ROLE_OFFSET] = role as u8;
+    account
+}
+
+#[test]
+fn test_rbac_initialize() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/rbac");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let creator = Pubkey::new_unique();
+    let (organization, organization_bump) =
+        Pubkey::find_program_address(&[ORGANIZATION_SEED.as_bytes(), creator.as_ref()], &ID);
+    let (membership, membership_bump) = membership_address(&organization, &creator);
+
+    let data = InitializeInstructionData::new(organization_bump, membership_bump);
+    let ix_accounts = vec![
+        AccountMeta::new(creator, true),
+        AccountMeta::new(organization, false),
+        AccountMeta::new(membership, false),
+        AccountMeta::new_readonly(system_program, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(RbacInstruction::Initialize, &data),
+        ix_accounts,
+    );
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[
+            (
+                creator,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (organization, Account::default()),
+            (membership, Account::default()),
+            (system_program, system_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&organization)
+                .owner(&ID)
+                .space(Organization::LEN)
+                .data_slice(0, creator.as_ref())
+                .build(),
+            Check::account(&membership)
+                .owner(&ID)
+                .space(Membership::LEN)
+                .data_slice(0, organization.as_ref())
+                .data_slice(32, creator.as_ref())
+                .data_slice(ROLE_OFFSET, &[Role::Admin as u8])
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_rbac_grant_and_act() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_grant_role(
+                    &setup.creator,
+                    &setup.creator_membership,
+                    &setup.member,
+                    &setup.membership,
+                    Role::Operator,
+                ),
+                &[
+                    Check::success(),
+                    Check::account(&setup.membership)
+                        .owner(&ID)
+                        .data_slice(32, setup.member.as_ref())
+                        .data_slice(ROLE_OFFSET, &[Role::Operator as u8])
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_guarded_action(Action::Increment, 0),
+                &[
+                    Check::success(),
+                    Check::account(&setup.organization)
+                        .data_slice(VALUE_OFFSET, &1u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_guarded_action(Action::Read, 0),
+                &[Check::success(), Check::return_data(&1u64.to_le_bytes())],
+            ),
+            // Setting the value is reserved for admins.
+            (
+                &setup.instruction_guarded_action(Action::Set, 42),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Rbac.code(RbacError::InsufficientRole as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_rbac_change_role() {
+    let setup = Setup::new().with_role(Role::Viewer);
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_guarded_action(Action::Increment, 0),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Rbac.code(RbacError::InsufficientRole as u32),
+        ))],
+    );
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_grant_role(
+                    &setup.creator,
+                    &setup.creator_membership,
+                    &setup.member,
+                    &setup.membership,
+                    Role::Admin,
+                ),
+                &[
+                    Check::success(),
+                    Check::account(&setup.membership)
+                        .data_slice(ROLE_OFFSET, &[Role::Admin as u8])
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_guarded_action(Action::Set, 42),
+                &[
+                    Check::success(),
+                    Check::account(&setup.organization)
+                        .data_slice(VALUE_OFFSET, &42u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_rbac_operator_cannot_escalate() {
+    let setup = Setup::new().with_role(Role::Operator);
+
+    // An operator granting themselves admin.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_grant_role(
+            &setup.member,
+            &setup.membership,
+            &setup.member,
+            &setup.membership,
+            Role::Admin,
+        ),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Rbac.code(RbacError::InsufficientRole as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_rbac_foreign_membership() {
+    let mut setup = Setup::new();
+
+    // The attacker is an admin of their own organization.
+    let attacker = Pubkey::new_unique();
+    let other_organization = Pubkey::new_unique();
+    let (attacker_membership, _) = membership_address(&other_organization, &attacker);
+    let attacker_membership_account =
+        membership_account(&setup.mollusk, &other_organization, &attacker, Role::Admin);
+    setup.accounts.push((
+        attacker,
+        Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+    ));
+    setup
+        .accounts
+        .push((attacker_membership, attacker_membership_account.clone()));
+
+    // A membership account with the admin role, but not written by the
+    // program.
+    let fake_membership = Pubkey::new_unique();
+    let mut fake_membership_account = attacker_membership_account;
+    fake_membership_account.data[..32].copy_from_slice(setup.organization.as_ref());
+    fake_membership_account.owner = Pubkey::new_unique();
+    setup
+        .accounts
+        .push((fake_membership, fake_membership_account));
+
+    for (membership, err) in [
+        // Admin role in another organization.
+        (attacker_membership, ProgramError::InvalidAccountData),
+        // Forged admin role.
+        (fake_membership, ProgramError::IllegalOwner),
+        // Someone else's admin role.
+        (setup.creator_membership, ProgramError::InvalidAccountData),
+    ] {
+        setup.mollusk.process_and_validate_instruction(
+            &setup.instruction_grant_role(
+                &attacker,
+                &membership,
+                &setup.member,
+                &setup.membership,
+                Role::Admin,
+            ),
+            &setup.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_rbac_revoke_role() {
+    let setup = Setup::new().with_role(Role::Operator);
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_revoke_role(&setup.membership),
+                &[
+                    Check::success(),
+                    Check::account(&setup.membership).closed().build(),
+                ],
+            ),
+            (
+                &setup.instruction_guarded_action(Action::Read, 0),
+                &[Check::err(ProgramError::IllegalOwner)],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_rbac_creator_role_immutable() {
+    let setup = Setup::new();
+
+    let creator_role_immutable = || {
+        [Check::err(ProgramError::Custom(
+            Namespace::Rbac.code(RbacError::CreatorRoleImmutable as u32),
+        ))]
+    };
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_revoke_role(&setup.creator_membership),
+        &setup.accounts,
+        &creator_role_immutable(),
+    );
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_grant_role(
+            &setup.creator,
+            &setup.creator_membership,
+            &setup.creator,
+            &setup.creator_membership,
+            Role::Viewer,
+        ),
+        &setup.accounts,
+        &creator_role_immutable(),
+    );
+}
@@ -0,0 +1,590 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::{Namespace, SharedError};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey, MAX_SEED_LEN},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::{CreateAccount, Transfer as SystemTransfer};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("BrWxpVJ4VWQX6mSSUcbEAmLcebvng1DFHqQxcLxzTbzf");
+
+pub const MERCHANT_SEED: &str = "merchant";
+pub const CODE_SEED: &str = "code";
+pub const REFEREE_SEED: &str = "referee";
+
+/// Maximum length of a referral code. The code is used as a PDA seed as is,
+/// so it can't be longer than a single seed.
+pub const MAX_CODE_LEN: usize = MAX_SEED_LEN;
+
+/// Basis points denominator of the reward share.
+pub const MAX_REWARD_BPS: u16 = 10_000;
+
+/// Errors returned by the referral program.
+#[repr(u32)]
+pub enum ReferralError {
+    /// The reward share is above 100%.
+    InvalidRewardBps,
+    /// The code is empty, too long or contains characters other than ASCII
+    /// letters, digits, `-` and `_`.
+    InvalidCode,
+    /// The buyer tried to use their own code.
+    SelfReferral,
+    /// The buyer was already referred by a different code.
+    ReferrerMismatch,
+    /// There are no accrued rewards to claim.
+    NothingToClaim,
+}
+
+impl From<ReferralError> for ProgramError {
+    fn from(e: ReferralError) -> Self {
+        Namespace::Referral.error(e as u32)
+    }
+}
+
+/// On-chain representation of a merchant accepting referred purchases.
+#[repr(C)]
+pub struct Merchant {
+    /// Receiver of the purchase payments.
+    pub authority: Pubkey,
+    /// Share of every referred purchase paid to the referrer, in basis
+    /// points.
+    pub reward_bps: u16,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+}
+
+assert_layout!(Merchant, size = 40, align = 2, { authority: 0, reward_bps: 32, bump: 34 });
+
+impl Merchant {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Returns the reward of a purchase of `amount` lamports.
+    pub fn reward(&self, amount: u64) -> u64 {
+        (amount as u128 * self.reward_bps as u128 / MAX_REWARD_BPS as u128) as u64
+    }
+}
+
+/// On-chain representation of a referral code.
+///
+/// The rewards are held by the code PDA itself until the owner claims them.
+#[repr(C)]
+pub struct ReferralCode {
+    pub owner: Pubkey,
+    /// Number of buyers referred by the code.
+    pub referees: u64,
+    /// Number of purchases made with the code.
+    pub purchases: u64,
+    /// Rewards accrued since the last claim, in lamports.
+    pub accrued: u64,
+    /// Rewards earned over the lifetime of the code, in lamports.
+    pub earned: u64,
+    /// The code, padded with zeros.
+    pub code: [u8; MAX_CODE_LEN],
+    pub code_len: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+assert_layout!(ReferralCode, size = 104, align = 8, {
+    owner: 0,
+    referees: 32,
+    purchases: 40,
+    accrued: 48,
+    earned: 56,
+    code: 64,
+    code_len: 96,
+    bump: 97,
+});
+
+impl ReferralCode {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Returns the code as bytes.
+    pub fn code(&self) -> &[u8] {
+        &self.code[..self.code_len as usize]
+    }
+}
+
+/// On-chain representation of a buyer of a merchant.
+///
+/// Created on the first purchase, it binds the buyer to the code they used,
+/// so all their later purchases reward the same referrer.
+#[repr(C)]
+pub struct Referee {
+    pub merchant: Pubkey,
+    pub buyer: Pubkey,
+    /// The referral code PDA which referred the buyer.
+    pub code: Pubkey,
+    pub purchases: u64,
+    /// Total amount spent by the buyer, in lamports.
+    pub spent: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Referee, size = 120, align = 8, {
+    merchant: 0,
+    buyer: 32,
+    code: 64,
+    purchases: 96,
+    spent: 104,
+    bump: 112,
+});
+
+impl Referee {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Referral program instruction discriminators.
+#[repr(u8)]
+pub enum ReferralInstruction {
+    /// Creates a merchant.
+    InitializeMerchant,
+    /// Registers a referral code.
+    RegisterCode,
+    /// Pays a merchant, rewarding the referrer.
+    Purchase,
+    /// Withdraws the accrued rewards of a code.
+    Claim,
+}
+
+impl TryFrom<&u8> for ReferralInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeMerchant),
+            1 => Ok(Self::RegisterCode),
+            2 => Ok(Self::Purchase),
+            3 => Ok(Self::Claim),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct InitializeMerchantInstructionData {
+    pub reward_bps: u16,
+    pub bump: u8,
+}
+
+impl InitializeMerchantInstructionData {
+    pub fn new(reward_bps: u16, bump: u8) -> Self {
+        Self { reward_bps, bump }
+    }
+}
+
+/// Fixed part of the register code instruction data. It's followed by the
+/// code itself, which has a variable length.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct RegisterCodeInstructionData {
+    pub bump: u8,
+}
+
+impl RegisterCodeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct PurchaseInstructionData {
+    /// Amount to pay, in lamports.
+    pub amount: u64,
+    /// Bump of the referee PDA.
+    pub bump: u8,
+}
+
+impl PurchaseInstructionData {
+    pub fn new(amount: u64, bump: u8) -> Self {
+        Self { amount, bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = ReferralInstruction::try_from(instruction)?;
+
+    match instruction {
+        ReferralInstruction::InitializeMerchant => {
+            process_initialize_merchant(accounts, instruction_data)
+        }
+        ReferralInstruction::RegisterCode => process_register_code(accounts, instruction_data),
+        ReferralInstruction::Purchase => process_purchase(accounts, instruction_data),
+        ReferralInstruction::Claim => process_claim(accounts),
+    }
+}
+
+/// Creates a merchant of `authority`.
+pub fn process_initialize_merchant(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, merchant, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeMerchantInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.reward_bps > MAX_REWARD_BPS {
+        return Err(ReferralError::InvalidRewardBps.into());
+    }
+
+    // Check the seeds of `merchant`.
+    let merchant_pda = create_program_address(
+        &[
+            MERCHANT_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(merchant.key(), &merchant_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the merchant PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(MERCHANT_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: merchant,
+        lamports: Rent::get()?.minimum_balance(Merchant::LEN),
+        space: Merchant::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the merchant PDA.
+    let mut data = merchant.try_borrow_mut_data()?;
+    let data: &mut Merchant = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the merchant.
+    data.authority = *authority.key();
+    data.reward_bps = instruction_data.reward_bps;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Initialized merchant with reward of {} bps",
+        instruction_data.reward_bps
+    );
+
+    Ok(())
+}
+
+/// Checks that `code` is a non-empty string of ASCII letters, digits, `-`
+/// and `_`, fitting into a single seed.
+///
+/// Codes are case-sensitive, `ABC` and `abc` are different codes.
+pub fn validate_code(code: &[u8]) -> ProgramResult {
+    if code.is_empty()
+        || code.len() > MAX_CODE_LEN
+        || !code
+            .iter()
+            .all(|c| c.is_ascii_alphanumeric() || *c == b'-' || *c == b'_')
+    {
+        return Err(ReferralError::InvalidCode.into());
+    }
+    Ok(())
+}
+
+/// Registers the code passed in the instruction data, owned by `owner`.
+///
+/// The code PDA is derived from the code string, so every code can be
+/// registered only once.
+pub fn process_register_code(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, code, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+
+    // Deserialize instruction data.
+    if instruction_data.len() < RegisterCodeInstructionData::LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (instruction_data, code_bytes) =
+        instruction_data.split_at(RegisterCodeInstructionData::LEN);
+    let instruction_data = RegisterCodeInstructionData::from_bytes(instruction_data)?;
+
+    validate_code(code_bytes)?;
+
+    // Check the seeds of `code`.
+    let code_pda = create_program_address(
+        &[CODE_SEED.as_bytes(), code_bytes, &[instruction_data.bump]],
+        &ID,
+    )?;
+    if !keys_eq(code.key(), &code_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the code PDA. Creating fails if the code is already taken.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(CODE_SEED.as_bytes()),
+        Seed::from(code_bytes),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: owner,
+        to: code,
+        lamports: Rent::get()?.minimum_balance(ReferralCode::LEN),
+        space: ReferralCode::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the code PDA.
+    let mut data = code.try_borrow_mut_data()?;
+    let data: &mut ReferralCode = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the code.
+    data.owner = *owner.key();
+    data.code[..code_bytes.len()].copy_from_slice(code_bytes);
+    data.code_len = code_bytes.len() as u8;
+    data.bump = instruction_data.bump;
+
+    info!("Registered referral code of length {}", code_bytes.len());
+
+    Ok(())
+}
+
+/// Pays `amount` lamports from `buyer` to the merchant, sending the reward
+/// share to `code`.
+///
+/// The first purchase of the buyer creates their referee PDA, which records
+/// the code. Later purchases have to use the same code.
+pub fn process_purchase(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [buyer, merchant, authority, referee, code, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(buyer)?;
+    // Check if the PDAs are owned by the program. The program owns
+    // merchants, codes and referees, which are told apart by their length.
+    require_owned_by(merchant, &ID)?;
+    if merchant.data_len() != Merchant::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    require_owned_by(code, &ID)?;
+    if code.data_len() != ReferralCode::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = PurchaseInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.amount == 0 {
+        return Err(SharedError::InvalidAmount.into());
+    }
+
+    // Check the payment receiver and compute the reward.
+    let reward = {
+        let data = merchant.try_borrow_data()?;
+        let data: &Merchant = unsafe { &*data.as_ptr().cast() };
+
+        if !keys_eq(&data.authority, authority.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        data.reward(instruction_data.amount)
+    };
+
+    // Referring yourself would be a discount on your own purchases.
+    {
+        let data = code.try_borrow_data()?;
+        let data: &ReferralCode = unsafe { &*data.as_ptr().cast() };
+
+        if keys_eq(&data.owner, buyer.key()) {
+            return Err(ReferralError::SelfReferral.into());
+        }
+    }
+
+    // Create the referee PDA on the first purchase, binding the buyer to the
+    // code.
+    let new_referee = !referee.is_owned_by(&ID);
+    if new_referee {
+        create_referee(buyer, merchant, referee, code, instruction_data.bump)?;
+    }
+
+    SystemTransfer {
+        from: buyer,
+        to: authority,
+        lamports: instruction_data.amount - reward,
+    }
+    .invoke()?;
+    if reward > 0 {
+        SystemTransfer {
+            from: buyer,
+            to: code,
+            lamports: reward,
+        }
+        .invoke()?;
+    }
+
+    // Deserialize the referee PDA.
+    if referee.data_len() != Referee::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut referee_data = referee.try_borrow_mut_data()?;
+    let referee_data: &mut Referee = unsafe { &mut *referee_data.as_mut_ptr().cast() };
+
+    if !keys_eq(&referee_data.merchant, merchant.key())
+        || !keys_eq(&referee_data.buyer, buyer.key())
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !keys_eq(&referee_data.code, code.key()) {
+        return Err(ReferralError::ReferrerMismatch.into());
+    }
+
+    referee_data.purchases += 1;
+    referee_data.spent += instruction_data.amount;
+
+    // Deserialize the code PDA.
+    let mut data = code.try_borrow_mut_data()?;
+    let data: &mut ReferralCode = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    if new_referee {
+        data.referees += 1;
+    }
+    data.purchases += 1;
+    data.accrued += reward;
+    data.earned += reward;
+
+    info!(
+        "Purchased for {} lamports, referrer reward {}",
+        instruction_data.amount, reward
+    );
+
+    Ok(())
+}
+
+/// Creates the referee PDA of `buyer`, referred by `code`.
+fn create_referee(
+    buyer: &AccountInfo,
+    merchant: &AccountInfo,
+    referee: &AccountInfo,
+    code: &AccountInfo,
+    bump: u8,
+) -> ProgramResult {
+    // Check the seeds of `referee`.
+    let referee_pda = create_program_address(
+        &[
+            REFEREE_SEED.as_bytes(),
+            merchant.key(),
+            buyer.key(),
+            &[bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(referee.key(), &referee_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the referee PDA.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(REFEREE_SEED.as_bytes()),
+        Seed::from(merchant.key()),
+        Seed::from(buyer.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: buyer,
+        to: referee,
+        lamports: Rent::get()?.minimum_balance(Referee::LEN),
+        space: Referee::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the referee PDA.
+    let mut data = referee.try_borrow_mut_data()?;
+    let data: &mut Referee = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the referee.
+    data.merchant = *merchant.key();
+    data.buyer = *buyer.key();
+    data.code = *code.key();
+    data.bump = bump[0];
+
+    Ok(())
+}
+
+/// Transfers the accrued rewards of `code` to its owner.
+pub fn process_claim(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, code] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(owner)?;
+    // Check if the code PDA is owned by the program.
+    require_owned_by(code, &ID)?;
+    if code.data_len() != ReferralCode::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let accrued = {
+        let mut data = code.try_borrow_mut_data()?;
+        let data: &mut ReferralCode = unsafe { &mut *data.as_mut_ptr().cast() };
+
+        if !keys_eq(&data.owner, owner.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if data.accrued == 0 {
+            return Err(ReferralError::NothingToClaim.into());
+        }
+
+        let accrued = data.accrued;
+        data.accrued = 0;
+        accrued
+    };
+
+    // The code PDA is owned by the program, so the lamports can be moved
+    // directly. The rent-exempt reserve stays in the PDA.
+    *code.try_borrow_mut_lamports()? -= accrued;
+    *owner.try_borrow_mut_lamports()? += accrued;
+
+    info!("Claimed {} lamports of rewards", accrued);
+
+    Ok(())
+}
@@ -0,0 +1,370 @@
+//! Attacks on the referral program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use referral::{
+    InitializeMerchantInstructionData, Merchant, PurchaseInstructionData, Referee, ReferralCode,
+    ReferralInstruction, RegisterCodeInstructionData, CODE_SEED, MERCHANT_SEED, REFEREE_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(referral::ID);
+
+/// Reward share of 5%.
+const REWARD_BPS: u16 = 500;
+const CODE: &[u8] = b"alice-2024";
+const ACCRUED: u64 = LAMPORTS_PER_SOL / 10;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: ReferralInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<ReferralInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns an account of the program holding `data`.
+fn program_account(mollusk: &Mollusk, data: Vec<u8>) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns the merchant PDA of `authority` and its account.
+fn merchant_account(mollusk: &Mollusk, authority: &Pubkey) -> (Pubkey, Account) {
+    let (merchant, bump) =
+        Pubkey::find_program_address(&[MERCHANT_SEED.as_bytes(), authority.as_ref()], &ID);
+    let mut data = vec![0; Merchant::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..34].copy_from_slice(&REWARD_BPS.to_le_bytes());
+    data[34] = bump;
+    (merchant, program_account(mollusk, data))
+}
+
+/// Returns the referee PDA of `buyer` at `merchant` and its bump.
+fn referee_pda(merchant: &Pubkey, buyer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[REFEREE_SEED.as_bytes(), merchant.as_ref(), buyer.as_ref()],
+        &ID,
+    )
+}
+
+/// Returns the referee PDA of `buyer` at `merchant`, referred by `code`, and
+/// its account.
+fn referee_account(
+    mollusk: &Mollusk,
+    merchant: &Pubkey,
+    buyer: &Pubkey,
+    code: &Pubkey,
+) -> (Pubkey, Account) {
+    let (referee, bump) = referee_pda(merchant, buyer);
+    let mut data = vec![0; Referee::LEN];
+    data[0..32].copy_from_slice(merchant.as_ref());
+    data[32..64].copy_from_slice(buyer.as_ref());
+    data[64..96].copy_from_slice(code.as_ref());
+    data[112] = bump;
+    (referee, program_account(mollusk, data))
+}
+
+fn instruction_initialize_merchant(authority: &Pubkey, merchant: &Pubkey, bump: u8) -> Instruction {
+    let data = InitializeMerchantInstructionData::new(REWARD_BPS, bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*merchant, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(ReferralInstruction::InitializeMerchant, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_register_code(owner: &Pubkey, code: &Pubkey, bump: u8) -> Instruction {
+    let data = RegisterCodeInstructionData::new(bump);
+    let mut data = instruction_data(ReferralInstruction::RegisterCode, &data);
+    data.extend_from_slice(b"mallory");
+    let ix_accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*code, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(ID, &data, ix_accounts)
+}
+
+fn instruction_claim(owner: &Pubkey, code: &Pubkey) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*code, false),
+    ];
+    Instruction::new_with_bytes(ID, &[ReferralInstruction::Claim as u8], ix_accounts)
+}
+
+/// Accounts of a merchant, of a referral code of the referrer holding
+/// [`ACCRUED`] lamports of rewards, of a buyer who was referred by it and of
+/// an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    merchant: Pubkey,
+    code: Pubkey,
+    buyer: Pubkey,
+    referee: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/referral");
+
+        let authority = Pubkey::new_unique();
+        let (merchant, merchant_account) = merchant_account(&mollusk, &authority);
+
+        let referrer = Pubkey::new_unique();
+        let (code, code_bump) = Pubkey::find_program_address(&[CODE_SEED.as_bytes(), CODE], &ID);
+        let mut code_data = vec![0; ReferralCode::LEN];
+        code_data[0..32].copy_from_slice(referrer.as_ref());
+        code_data[32..40].copy_from_slice(&1u64.to_le_bytes());
+        code_data[40..48].copy_from_slice(&1u64.to_le_bytes());
+        code_data[48..56].copy_from_slice(&ACCRUED.to_le_bytes());
+        code_data[56..64].copy_from_slice(&ACCRUED.to_le_bytes());
+        code_data[64..64 + CODE.len()].copy_from_slice(CODE);
+        code_data[96] = CODE.len() as u8;
+        code_data[97] = code_bump;
+        let mut code_account = program_account(&mollusk, code_data);
+        code_account.lamports += ACCRUED;
+
+        let buyer = Pubkey::new_unique();
+        let (referee, referee_account) = referee_account(&mollusk, &merchant, &buyer, &code);
+        let attacker = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (authority, Account::default()),
+            (merchant, merchant_account),
+            (code, code_account),
+            (
+                buyer,
+                Account::new(10 * LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (referee, referee_account),
+            (
+                attacker,
+                Account::new(10 * LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            system_program,
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            merchant,
+            code,
+            buyer,
+            referee,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    /// Pays [`LAMPORTS_PER_SOL`] from `buyer` to the merchant, rewarding the
+    /// code.
+    fn instruction_purchase(&self, buyer: &Pubkey, referee: &Pubkey, bump: u8) -> Instruction {
+        let data = PurchaseInstructionData::new(LAMPORTS_PER_SOL, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new_readonly(self.merchant, false),
+            AccountMeta::new(self.authority, false),
+            AccountMeta::new(*referee, false),
+            AccountMeta::new(self.code, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(ReferralInstruction::Purchase, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Returns a purchase of the attacker, who wasn't referred yet.
+    fn attacker_purchase(&self) -> (Pubkey, Instruction) {
+        let (referee, bump) = referee_pda(&self.merchant, &self.attacker);
+        let instruction = self.instruction_purchase(&self.attacker, &referee, bump);
+        (referee, instruction)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_buyer_signature() {
+    let setup = Setup::new();
+    let (referee, mut instruction) = setup.attacker_purchase();
+    instruction.accounts[0].is_signer = false;
+    let setup = setup.with(referee, Account::default());
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker claims the rewards of a code with the layout of the program,
+/// but owned by another one.
+#[test]
+fn test_spoofed_code() {
+    let setup = Setup::new();
+    let code = setup.code;
+    let mut code_account = setup.account(&code);
+    code_account.data[0..32].copy_from_slice(setup.attacker.as_ref());
+    code_account.owner = Pubkey::new_unique();
+    let setup = setup.with(code, code_account);
+    setup.expect_err(
+        &instruction_claim(&setup.attacker, &setup.code),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A merchant with the layout of the program, but owned by another one,
+/// which pays the whole purchase to the referrer.
+#[test]
+fn test_spoofed_merchant() {
+    let setup = Setup::new();
+    let merchant = setup.merchant;
+    let mut merchant_account = setup.account(&merchant);
+    merchant_account.data[32..34].copy_from_slice(&10_000u16.to_le_bytes());
+    merchant_account.owner = Pubkey::new_unique();
+    let (referee, instruction) = setup.attacker_purchase();
+    let setup = setup
+        .with(merchant, merchant_account)
+        .with(referee, Account::default());
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// The attacker pays another wallet of theirs instead of the merchant
+/// authority, getting the purchase recorded while keeping the payment.
+#[test]
+fn test_purchase_paying_attacker() {
+    let setup = Setup::new();
+    let wallet = Pubkey::new_unique();
+    let (referee, mut instruction) = setup.attacker_purchase();
+    instruction.accounts[2].pubkey = wallet;
+    let setup = setup
+        .with(wallet, Account::default())
+        .with(referee, Account::default());
+    setup.expect_err(&instruction, ProgramError::InvalidAccountData);
+}
+
+/// The attacker makes a purchase recorded in the referee of the buyer.
+#[test]
+fn test_referee_of_other_buyer() {
+    let setup = Setup::new();
+    let (_, bump) = referee_pda(&setup.merchant, &setup.attacker);
+    setup.expect_err(
+        &setup.instruction_purchase(&setup.attacker, &setup.referee, bump),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The buyer makes a purchase with their referee of another merchant,
+/// escaping the code they were referred by.
+#[test]
+fn test_referee_of_other_merchant() {
+    let setup = Setup::new();
+    let other_authority = Pubkey::new_unique();
+    let (other_merchant, other_merchant_account) =
+        merchant_account(&setup.mollusk, &other_authority);
+    let other_code = Pubkey::new_unique();
+    let (other_referee, other_referee_account) =
+        referee_account(&setup.mollusk, &other_merchant, &setup.buyer, &other_code);
+    let (_, bump) = referee_pda(&setup.merchant, &setup.buyer);
+    let setup = setup
+        .with(other_merchant, other_merchant_account)
+        .with(other_referee, other_referee_account);
+    setup.expect_err(
+        &setup.instruction_purchase(&setup.buyer, &other_referee, bump),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// The buyer passes their referee as the code, so their purchases are
+/// rewarded to a PDA only they are bound to.
+#[test]
+fn test_referee_as_code() {
+    let setup = Setup::new();
+    let (_, bump) = referee_pda(&setup.merchant, &setup.buyer);
+    let mut instruction = setup.instruction_purchase(&setup.buyer, &setup.referee, bump);
+    instruction.accounts[4].pubkey = setup.referee;
+    setup.expect_err(&instruction, ProgramError::InvalidAccountData);
+}
+
+/// The attacker creates the referee of another buyer, binding them to a code
+/// of the attacker's choice.
+#[test]
+fn test_wrong_referee_seeds() {
+    let setup = Setup::new();
+    let (referee, bump) = referee_pda(&setup.merchant, &Pubkey::new_unique());
+    let setup = setup.with(referee, Account::default());
+    setup.expect_err(
+        &setup.instruction_purchase(&setup.attacker, &referee, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker registers their code at the address of another one, which
+/// isn't taken yet.
+#[test]
+fn test_wrong_code_seeds() {
+    let setup = Setup::new();
+    let (code, bump) = Pubkey::find_program_address(&[CODE_SEED.as_bytes(), b"bob"], &ID);
+    let setup = setup.with(code, Account::default());
+    setup.expect_err(
+        &instruction_register_code(&setup.attacker, &code, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker creates the merchant of another authority, with a reward
+/// share of their choice.
+#[test]
+fn test_wrong_merchant_seeds() {
+    let setup = Setup::new();
+    let (merchant, bump) = Pubkey::find_program_address(
+        &[MERCHANT_SEED.as_bytes(), Pubkey::new_unique().as_ref()],
+        &ID,
+    );
+    let setup = setup.with(merchant, Account::default());
+    setup.expect_err(
+        &instruction_initialize_merchant(&setup.attacker, &merchant, bump),
+        ProgramError::InvalidSeeds,
+    );
+}
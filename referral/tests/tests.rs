@@ -0,0 +1,510 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use referral::{
+    InitializeMerchantInstructionData, Merchant, PurchaseInstructionData, Referee, ReferralCode,
+    ReferralError, ReferralInstruction, RegisterCodeInstructionData, CODE_SEED, MERCHANT_SEED,
+    REFEREE_SEED,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+
+const ID: Pubkey = Pubkey::new_from_array(referral::ID);
+
+/// Offsets of the referral code fields.
+const REFEREES_OFFSET: usize = 32;
+const PURCHASES_OFFSET: usize = 40;
+const ACCRUED_OFFSET: usize = 48;
+const EARNED_OFFSET: usize = 56;
+const CODE_OFFSET: usize = 64;
+const CODE_LEN_OFFSET: usize = 96;
+
+/// Offsets of the referee fields.
+const REFEREE_CODE_OFFSET: usize = 64;
+const REFEREE_PURCHASES_OFFSET: usize = 96;
+const REFEREE_SPENT_OFFSET: usize = 104;
+
+/// Reward share of 5%.
+const REWARD_BPS: u16 = 500;
+const CODE: &[u8] = b"alice-2024";
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: ReferralInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<ReferralInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn instruction_initialize_merchant(
+    authority: &Pubkey,
+    merchant: &Pubkey,
+    reward_bps: u16,
+    bump: u8,
+) -> Instruction {
+    let data = InitializeMerchantInstructionData::new(reward_bps, bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*merchant, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(ReferralInstruction::InitializeMerchant, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_register_code(
+    owner: &Pubkey,
+    code: &Pubkey,
+    code_str: &[u8],
+    bump: u8,
+) -> Instruction {
+    let data = RegisterCodeInstructionData::new(bump);
+    let mut data = instruction_data(ReferralInstruction::RegisterCode, &data);
+    data.extend_from_slice(code_str);
+    let ix_accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*code, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(ID, &data, ix_accounts)
+}
+
+fn instruction_purchase(
+    amount: u64,
+    buyer: &Pubkey,
+    merchant: &Pubkey,
+    authority: &Pubkey,
+    referee: &Pubkey,
+    code: &Pubkey,
+    bump: u8,
+) -> Instruction {
+    let data = PurchaseInstructionData::new(amount, bump);
+    let ix_accounts = vec![
+        AccountMeta::new(*buyer, true),
+        AccountMeta::new_readonly(*merchant, false),
+        AccountMeta::new(*authority, false),
+        AccountMeta::new(*referee, false),
+        AccountMeta::new(*code, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(ReferralInstruction::Purchase, &data),
+        ix_accounts,
+    )
+}
+
+fn instruction_claim(owner: &Pubkey, code: &Pubkey) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*code, false),
+    ];
+    Instruction::new_with_bytes(ID, &[ReferralInstruction::Claim as u8], ix_accounts)
+}
+
+/// Creates an initialized merchant account.
+fn merchant_account(mollusk: &Mollusk, authority: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Merchant::LEN),
+        Merchant::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[32..34].copy_from_slice(&REWARD_BPS.to_le_bytes());
+    account
+}
+
+/// Creates an initialized referral code account holding `accrued` lamports
+/// of rewards.
+fn code_account(mollusk: &Mollusk, owner: &Pubkey, code: &[u8], accrued: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(ReferralCode::LEN) + accrued,
+        ReferralCode::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(owner.as_ref());
+    account.data[ACCRUED_OFFSET..ACCRUED_OFFSET + 8].copy_from_slice(&accrued.to_le_bytes());
+    account.data[EARNED_OFFSET..EARNED_OFFSET + 8].copy_from_slice(&accrued.to_le_bytes());
+    account.data[CODE_OFFSET..CODE_OFFSET + code.len()].copy_from_slice(code);
+    account.data[CODE_LEN_OFFSET] = code.len() as u8;
+    account
+}
+
+/// Creates an initialized referee account referred by `code`.
+fn referee_account(mollusk: &Mollusk, merchant: &Pubkey, buyer: &Pubkey, code: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Referee::LEN),
+        Referee::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(merchant.as_ref());
+    account.data[32..64].copy_from_slice(buyer.as_ref());
+    account.data[REFEREE_CODE_OFFSET..REFEREE_CODE_OFFSET + 32].copy_from_slice(code.as_ref());
+    account
+}
+
+#[test]
+fn test_referral_register_and_purchase() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/referral");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let authority = Pubkey::new_unique();
+    let (merchant, merchant_bump) =
+        Pubkey::find_program_address(&[MERCHANT_SEED.as_bytes(), authority.as_ref()], &ID);
+    let owner = Pubkey::new_unique();
+    let (code, code_bump) = Pubkey::find_program_address(&[CODE_SEED.as_bytes(), CODE], &ID);
+    let buyer = Pubkey::new_unique();
+    let (referee, referee_bump) = Pubkey::find_program_address(
+        &[REFEREE_SEED.as_bytes(), merchant.as_ref(), buyer.as_ref()],
+        &ID,
+    );
+
+    let amount = LAMPORTS_PER_SOL;
+    let reward = amount / 20;
+    let code_rent = mollusk.sysvars.rent.minimum_balance(ReferralCode::LEN);
+
+    let res = mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_initialize_merchant(&authority, &merchant, REWARD_BPS, merchant_bump),
+                &[
+                    Check::success(),
+                    Check::account(&merchant)
+                        .owner(&ID)
+                        .space(Merchant::LEN)
+                        .data_slice(0, authority.as_ref())
+                        .data_slice(32, &REWARD_BPS.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_register_code(&owner, &code, CODE, code_bump),
+                &[
+                    Check::success(),
+                    Check::account(&code)
+                        .owner(&ID)
+                        .space(ReferralCode::LEN)
+                        .data_slice(0, owner.as_ref())
+                        .data_slice(CODE_OFFSET, CODE)
+                        .data_slice(CODE_LEN_OFFSET, &[CODE.len() as u8])
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_purchase(
+                    amount,
+                    &buyer,
+                    &merchant,
+                    &authority,
+                    &referee,
+                    &code,
+                    referee_bump,
+                ),
+                &[
+                    Check::success(),
+                    Check::account(&referee)
+                        .owner(&ID)
+                        .space(Referee::LEN)
+                        .data_slice(0, merchant.as_ref())
+                        .data_slice(32, buyer.as_ref())
+                        .data_slice(REFEREE_CODE_OFFSET, code.as_ref())
+                        .data_slice(REFEREE_SPENT_OFFSET, &amount.to_le_bytes())
+                        .build(),
+                    Check::account(&code)
+                        .lamports(code_rent + reward)
+                        .data_slice(REFEREES_OFFSET, &1u64.to_le_bytes())
+                        .data_slice(ACCRUED_OFFSET, &reward.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            // The second purchase rewards the same code, but doesn't count as
+            // a new referee.
+            (
+                &instruction_purchase(
+                    amount,
+                    &buyer,
+                    &merchant,
+                    &authority,
+                    &referee,
+                    &code,
+                    referee_bump,
+                ),
+                &[
+                    Check::success(),
+                    Check::account(&referee)
+                        .data_slice(REFEREE_PURCHASES_OFFSET, &2u64.to_le_bytes())
+                        .data_slice(REFEREE_SPENT_OFFSET, &(2 * amount).to_le_bytes())
+                        .build(),
+                    Check::account(&code)
+                        .lamports(code_rent + 2 * reward)
+                        .data_slice(REFEREES_OFFSET, &1u64.to_le_bytes())
+                        .data_slice(PURCHASES_OFFSET, &2u64.to_le_bytes())
+                        .data_slice(ACCRUED_OFFSET, &(2 * reward).to_le_bytes())
+                        .data_slice(EARNED_OFFSET, &(2 * reward).to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &[
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (merchant, Account::default()),
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (code, Account::default()),
+            (
+                buyer,
+                Account::new(10 * LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (referee, Account::default()),
+            (system_program, system_account),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+
+    // The merchant got the rest of the payments.
+    let authority_account = res.get_account(&authority).unwrap();
+    let merchant_rent = mollusk.sysvars.rent.minimum_balance(Merchant::LEN);
+    assert_eq!(
+        authority_account.lamports,
+        LAMPORTS_PER_SOL - merchant_rent + 2 * (amount - reward)
+    );
+}
+
+#[test]
+fn test_referral_invalid_code() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/referral");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_unique();
+    let code = Pubkey::new_unique();
+
+    for code_str in [&b""[..], &[b'a'; 33], b"with space", "zażółć".as_bytes()] {
+        mollusk.process_and_validate_instruction(
+            &instruction_register_code(&owner, &code, code_str, 255),
+            &[
+                (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+                (code, Account::default()),
+                (system_program, system_account.clone()),
+            ],
+            &[Check::err(ProgramError::Custom(
+                Namespace::Referral.code(ReferralError::InvalidCode as u32),
+            ))],
+        );
+    }
+}
+
+#[test]
+fn test_referral_code_taken() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/referral");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let owner = Pubkey::new_unique();
+    let (code, code_bump) = Pubkey::find_program_address(&[CODE_SEED.as_bytes(), CODE], &ID);
+    let squatter = Pubkey::new_unique();
+
+    // The code PDA already exists, so it can't be created again.
+    let res = mollusk.process_instruction(
+        &instruction_register_code(&squatter, &code, CODE, code_bump),
+        &[
+            (
+                squatter,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (code, code_account(&mollusk, &owner, CODE, 0)),
+            (system_program, system_account),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Failure(_)));
+}
+
+#[test]
+fn test_referral_self_referral() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/referral");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let authority = Pubkey::new_unique();
+    let merchant = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let code = Pubkey::new_unique();
+    let referee = Pubkey::new_unique();
+
+    mollusk.process_and_validate_instruction(
+        &instruction_purchase(
+            LAMPORTS_PER_SOL,
+            &owner,
+            &merchant,
+            &authority,
+            &referee,
+            &code,
+            255,
+        ),
+        &[
+            (owner, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (merchant, merchant_account(&mollusk, &authority)),
+            (authority, Account::default()),
+            (referee, Account::default()),
+            (code, code_account(&mollusk, &owner, CODE, 0)),
+            (system_program, system_account),
+        ],
+        &[Check::err(ProgramError::Custom(
+            Namespace::Referral.code(ReferralError::SelfReferral as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_referral_referrer_mismatch() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/referral");
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let authority = Pubkey::new_unique();
+    let merchant = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let code = Pubkey::new_unique();
+    let other_code = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let referee = Pubkey::new_unique();
+
+    // The buyer was referred by `code`, so `other_code` can't claim them.
+    mollusk.process_and_validate_instruction(
+        &instruction_purchase(
+            LAMPORTS_PER_SOL,
+            &buyer,
+            &merchant,
+            &authority,
+            &referee,
+            &other_code,
+            255,
+        ),
+        &[
+            (buyer, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (merchant, merchant_account(&mollusk, &authority)),
+            (authority, Account::default()),
+            (referee, referee_account(&mollusk, &merchant, &buyer, &code)),
+            (other_code, code_account(&mollusk, &owner, b"bob", 0)),
+            (system_program, system_account),
+        ],
+        &[Check::err(ProgramError::Custom(
+            Namespace::Referral.code(ReferralError::ReferrerMismatch as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_referral_claim() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/referral");
+
+    let owner = Pubkey::new_unique();
+    let code = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+
+    let accrued = LAMPORTS_PER_SOL / 10;
+    let code_rent = mollusk.sysvars.rent.minimum_balance(ReferralCode::LEN);
+
+    let accounts = [
+        (owner, Account::default()),
+        (attacker, Account::default()),
+        (code, code_account(&mollusk, &owner, CODE, accrued)),
+    ];
+    // Only the owner can claim the rewards.
+    mollusk.process_and_validate_instruction(
+        &instruction_claim(&attacker, &code),
+        &accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+    mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &instruction_claim(&owner, &code),
+                &[
+                    Check::success(),
+                    Check::account(&owner).lamports(accrued).build(),
+                    Check::account(&code)
+                        .lamports(code_rent)
+                        .data_slice(ACCRUED_OFFSET, &0u64.to_le_bytes())
+                        .data_slice(EARNED_OFFSET, &accrued.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &instruction_claim(&owner, &code),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Referral.code(ReferralError::NothingToClaim as u32),
+                ))],
+            ),
+        ],
+        &accounts,
+    );
+}
+
+#[test]
+fn test_referral_type_confusion() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/referral");
+
+    let authority = Pubkey::new_unique();
+    let merchant = Pubkey::new_unique();
+    let referrer = Pubkey::new_unique();
+    let code = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let (referee, referee_bump) = Pubkey::find_program_address(
+        &[REFEREE_SEED.as_bytes(), code.as_ref(), buyer.as_ref()],
+        &ID,
+    );
+
+    let accounts = [
+        (authority, Account::default()),
+        (merchant, merchant_account(&mollusk, &authority)),
+        (referrer, Account::default()),
+        (code, code_account(&mollusk, &referrer, CODE, 0)),
+        (
+            buyer,
+            Account::new(2 * LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        ),
+        (referee, Account::default()),
+        keyed_account_for_system_program(),
+    ];
+    // A merchant has its authority at the offset of the owner of a code, but
+    // the accrued rewards would be read past the end of its data.
+    mollusk.process_and_validate_instruction(
+        &instruction_claim(&authority, &merchant),
+        &accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+    // A code has its owner at the offset of the authority of a merchant, so
+    // it could pose as a merchant which pays no rewards.
+    mollusk.process_and_validate_instruction(
+        &instruction_purchase(
+            LAMPORTS_PER_SOL,
+            &buyer,
+            &code,
+            &referrer,
+            &referee,
+            &code,
+            referee_bump,
+        ),
+        &accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
@@ -0,0 +1,433 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+use pinocchio_token::{instructions::Transfer, state::TokenAccount};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("BnrAcMzKxvyoNJUWjbcKk1yQYd4t4jPivEMqVdKdmYVT");
+
+pub const DISTRIBUTION_SEED: &str = "distribution";
+pub const STAKEHOLDER_SEED: &str = "stakeholder";
+
+/// Scale of the fixed-point revenue per weight unit.
+pub const PRECISION: u128 = 1_000_000_000_000;
+
+/// Errors returned by the royalties program.
+#[repr(u32)]
+pub enum RoyaltiesError {
+    /// The weight of a stakeholder is zero.
+    InvalidWeight,
+    /// The stakeholder has nothing to claim.
+    NothingToClaim,
+}
+
+impl From<RoyaltiesError> for ProgramError {
+    fn from(e: RoyaltiesError) -> Self {
+        Namespace::Royalties.error(e as u32)
+    }
+}
+
+/// On-chain representation of a revenue distribution.
+///
+/// Revenue is any token transferred to the vault, by anyone. Instead of
+/// paying every stakeholder when revenue arrives, the distribution keeps the
+/// cumulative revenue per weight unit, and every stakeholder keeps the value
+/// it had at their last claim. The difference, multiplied by the weight of
+/// the stakeholder, is what they can claim.
+///
+/// The u128 fields are stored as bytes, since the alignment of u128 differs
+/// between targets.
+#[repr(C)]
+pub struct Distribution {
+    pub authority: Pubkey,
+    /// Token account holding the revenue, owned by the distribution PDA.
+    pub vault: Pubkey,
+    pub total_weight: u64,
+    /// Vault balance already accounted in `revenue_per_weight`, minus the
+    /// claimed amounts.
+    pub accounted: u64,
+    /// Cumulative revenue per weight unit, scaled by [`PRECISION`].
+    pub revenue_per_weight: [u8; 16],
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Distribution, size = 104, align = 8, {
+    authority: 0,
+    vault: 32,
+    total_weight: 64,
+    accounted: 72,
+    revenue_per_weight: 80,
+    bump: 96,
+});
+
+impl Distribution {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    pub fn revenue_per_weight(&self) -> u128 {
+        u128::from_le_bytes(self.revenue_per_weight)
+    }
+
+    /// Accounts the revenue which arrived in the vault since the last sync.
+    ///
+    /// Revenue arriving while there are no stakeholders stays unaccounted
+    /// and goes to the first ones.
+    pub fn sync(&mut self, vault_balance: u64) {
+        if self.total_weight == 0 {
+            return;
+        }
+        let revenue = vault_balance - self.accounted;
+        let revenue_per_weight =
+            self.revenue_per_weight() + revenue as u128 * PRECISION / self.total_weight as u128;
+        self.revenue_per_weight = revenue_per_weight.to_le_bytes();
+        self.accounted = vault_balance;
+    }
+}
+
+/// On-chain representation of a stakeholder.
+#[repr(C)]
+pub struct Stakeholder {
+    pub distribution: Pubkey,
+    pub holder: Pubkey,
+    pub weight: u64,
+    /// Revenue per weight unit of the distribution at the last claim.
+    pub checkpoint: [u8; 16],
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Stakeholder, size = 96, align = 8, {
+    distribution: 0,
+    holder: 32,
+    weight: 64,
+    checkpoint: 72,
+    bump: 88,
+});
+
+impl Stakeholder {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    pub fn checkpoint(&self) -> u128 {
+        u128::from_le_bytes(self.checkpoint)
+    }
+
+    /// Returns the amount the stakeholder can claim and moves the checkpoint
+    /// to the current revenue per weight unit.
+    ///
+    /// Rounding down leaves dust in the vault, never more than one token per
+    /// claim.
+    pub fn settle(&mut self, revenue_per_weight: u128) -> u64 {
+        let owed = (revenue_per_weight - self.checkpoint()) * self.weight as u128 / PRECISION;
+        self.checkpoint = revenue_per_weight.to_le_bytes();
+        owed as u64
+    }
+}
+
+/// Royalties program instruction discriminators.
+#[repr(u8)]
+pub enum RoyaltiesInstruction {
+    /// Creates a distribution.
+    Initialize,
+    /// Adds a stakeholder to a distribution.
+    AddStakeholder,
+    /// Claims the share of a stakeholder.
+    Claim,
+}
+
+impl TryFrom<&u8> for RoyaltiesInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::AddStakeholder),
+            2 => Ok(Self::Claim),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct AddStakeholderInstructionData {
+    pub weight: u64,
+    pub bump: u8,
+}
+
+impl AddStakeholderInstructionData {
+    pub fn new(weight: u64, bump: u8) -> Self {
+        Self { weight, bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = RoyaltiesInstruction::try_from(instruction)?;
+
+    match instruction {
+        RoyaltiesInstruction::Initialize => process_initialize(accounts, instruction_data),
+        RoyaltiesInstruction::AddStakeholder => process_add_stakeholder(accounts, instruction_data),
+        RoyaltiesInstruction::Claim => process_claim(accounts),
+    }
+}
+
+/// Returns the balance of `vault`, checking that it's the vault of
+/// `distribution`.
+fn vault_balance(distribution: &Distribution, vault: &AccountInfo) -> Result<u64, ProgramError> {
+    if !keys_eq(&distribution.vault, vault.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(TokenAccount::from_account_info(vault)?.amount())
+}
+
+/// Creates a distribution of the revenue accumulating in `vault`.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, distribution, vault, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Check that `vault` is owned by `distribution`.
+    let vault_balance = {
+        let vault = TokenAccount::from_account_info(vault)?;
+        if !keys_eq(vault.owner(), distribution.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        vault.amount()
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `distribution`.
+    let distribution_pda = create_program_address(
+        &[
+            DISTRIBUTION_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(distribution.key(), &distribution_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the distribution PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(DISTRIBUTION_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: distribution,
+        lamports: Rent::get()?.minimum_balance(Distribution::LEN),
+        space: Distribution::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the distribution PDA.
+    let mut data = distribution.try_borrow_mut_data()?;
+    let data: &mut Distribution = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the distribution.
+    data.authority = *authority.key();
+    data.vault = *vault.key();
+    data.bump = instruction_data.bump;
+
+    info!("Initialized distribution, vault holds {}", vault_balance);
+
+    Ok(())
+}
+
+/// Adds `holder` as a stakeholder with the given weight.
+///
+/// The revenue received so far is accounted first, so the new stakeholder
+/// only gets a share of future revenue.
+pub fn process_add_stakeholder(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, distribution, vault, holder, stakeholder, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Check if the distribution PDA is owned by the program.
+    require_owned_by(distribution, &ID)?;
+
+    // Deserialize instruction data.
+    let instruction_data = AddStakeholderInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.weight == 0 {
+        return Err(RoyaltiesError::InvalidWeight.into());
+    }
+
+    // Check the seeds of `stakeholder`.
+    let stakeholder_pda = create_program_address(
+        &[
+            STAKEHOLDER_SEED.as_bytes(),
+            distribution.key(),
+            holder.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(stakeholder.key(), &stakeholder_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the stakeholder PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(STAKEHOLDER_SEED.as_bytes()),
+        Seed::from(distribution.key()),
+        Seed::from(holder.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: stakeholder,
+        lamports: Rent::get()?.minimum_balance(Stakeholder::LEN),
+        space: Stakeholder::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the distribution PDA.
+    let mut distribution_data = distribution.try_borrow_mut_data()?;
+    let distribution_data: &mut Distribution =
+        unsafe { &mut *distribution_data.as_mut_ptr().cast() };
+
+    // Only the authority can add stakeholders.
+    if !keys_eq(&distribution_data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    distribution_data.sync(vault_balance(distribution_data, vault)?);
+    distribution_data.total_weight += instruction_data.weight;
+
+    // Deserialize the stakeholder PDA.
+    let mut data = stakeholder.try_borrow_mut_data()?;
+    let data: &mut Stakeholder = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the stakeholder.
+    data.distribution = *distribution.key();
+    data.holder = *holder.key();
+    data.weight = instruction_data.weight;
+    data.checkpoint = distribution_data.revenue_per_weight;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Added stakeholder with weight {} of {}",
+        instruction_data.weight, distribution_data.total_weight
+    );
+
+    Ok(())
+}
+
+/// Transfers the share of the revenue received since the last claim of
+/// `holder` to `holder_ata`.
+pub fn process_claim(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [holder, holder_ata, distribution, vault, stakeholder, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(holder)?;
+
+    // Check if the distribution and stakeholder PDAs are owned by the
+    // program.
+    require_owned_by(distribution, &ID)?;
+    require_owned_by(stakeholder, &ID)?;
+
+    // Account the new revenue and settle the share of the stakeholder.
+    let (amount, authority, bump) = {
+        let mut distribution_data = distribution.try_borrow_mut_data()?;
+        let distribution_data: &mut Distribution =
+            unsafe { &mut *distribution_data.as_mut_ptr().cast() };
+
+        let mut data = stakeholder.try_borrow_mut_data()?;
+        let data: &mut Stakeholder = unsafe { &mut *data.as_mut_ptr().cast() };
+
+        // Check that the stakeholder belongs to `distribution` and `holder`.
+        if !keys_eq(&data.distribution, distribution.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !keys_eq(&data.holder, holder.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+
+        distribution_data.sync(vault_balance(distribution_data, vault)?);
+        let amount = data.settle(distribution_data.revenue_per_weight());
+        if amount == 0 {
+            return Err(RoyaltiesError::NothingToClaim.into());
+        }
+        distribution_data.accounted -= amount;
+
+        (amount, distribution_data.authority, distribution_data.bump)
+    };
+
+    // Transfer the share from the vault to the holder.
+    let bump = [bump];
+    let seeds = [
+        Seed::from(DISTRIBUTION_SEED.as_bytes()),
+        Seed::from(&authority),
+        Seed::from(&bump),
+    ];
+    Transfer {
+        from: vault,
+        to: holder_ata,
+        authority: distribution,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Claimed {} tokens", amount);
+
+    Ok(())
+}
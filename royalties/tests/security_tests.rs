@@ -0,0 +1,426 @@
+//! Attacks on the royalties program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use royalties::{
+    AddStakeholderInstructionData, Distribution, InitializeInstructionData, RoyaltiesInstruction,
+    Stakeholder, DISTRIBUTION_SEED, STAKEHOLDER_SEED,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(royalties::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+const REVENUE: u64 = 1_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: RoyaltiesInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<RoyaltiesInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns an account of the program holding `data`.
+fn program_account(mollusk: &Mollusk, data: Vec<u8>) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Returns the distribution PDA of `authority` and its bump.
+fn distribution_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[DISTRIBUTION_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Returns the distribution PDA of `authority` and its account, with
+/// a total weight of `total_weight` and nothing accounted yet.
+fn distribution_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    vault: &Pubkey,
+    total_weight: u64,
+) -> (Pubkey, Account) {
+    let (distribution, bump) = distribution_pda(authority);
+    let mut data = vec![0; Distribution::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(vault.as_ref());
+    data[64..72].copy_from_slice(&total_weight.to_le_bytes());
+    data[96] = bump;
+    (distribution, program_account(mollusk, data))
+}
+
+/// Returns the stakeholder PDA of `holder` in `distribution` and its bump.
+fn stakeholder_pda(distribution: &Pubkey, holder: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            STAKEHOLDER_SEED.as_bytes(),
+            distribution.as_ref(),
+            holder.as_ref(),
+        ],
+        &ID,
+    )
+}
+
+/// Returns the stakeholder PDA of `holder` in `distribution` and its account,
+/// which didn't claim anything yet.
+fn stakeholder_account(
+    mollusk: &Mollusk,
+    distribution: &Pubkey,
+    holder: &Pubkey,
+    weight: u64,
+) -> (Pubkey, Account) {
+    let (stakeholder, bump) = stakeholder_pda(distribution, holder);
+    let mut data = vec![0; Stakeholder::LEN];
+    data[0..32].copy_from_slice(distribution.as_ref());
+    data[32..64].copy_from_slice(holder.as_ref());
+    data[64..72].copy_from_slice(&weight.to_le_bytes());
+    data[88] = bump;
+    (stakeholder, program_account(mollusk, data))
+}
+
+/// Accounts of a distribution with [`REVENUE`] tokens in the vault and
+/// a single stakeholder, and of an attacker with a distribution of their own,
+/// in which they hold all the weight.
+struct Setup {
+    mollusk: Mollusk,
+    mint: Pubkey,
+    distribution: Pubkey,
+    vault: Pubkey,
+    holder: Pubkey,
+    holder_ata: Pubkey,
+    stakeholder: Pubkey,
+    attacker: Pubkey,
+    attacker_ata: Pubkey,
+    attacker_stakeholder: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/royalties");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+
+        let authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+        let (distribution, distribution_account) =
+            distribution_account(&mollusk, &authority, &vault, 1);
+        let holder = Pubkey::new_unique();
+        let holder_ata = Pubkey::new_unique();
+        let (stakeholder, stakeholder_account) =
+            stakeholder_account(&mollusk, &distribution, &holder, 1);
+
+        let attacker = Pubkey::new_unique();
+        let attacker_ata = Pubkey::new_unique();
+        let attacker_vault = Pubkey::new_unique();
+        let (attacker_distribution, attacker_distribution_account) =
+            distribution_account(&mollusk, &attacker, &attacker_vault, 1);
+        let (attacker_stakeholder, attacker_stakeholder_account) =
+            stakeholder_account(&mollusk, &attacker_distribution, &attacker, 1);
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (distribution, distribution_account),
+            (
+                vault,
+                token_account(&mollusk, &mint, &distribution, REVENUE),
+            ),
+            (holder, Account::default()),
+            (holder_ata, token_account(&mollusk, &mint, &holder, 0)),
+            (stakeholder, stakeholder_account),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (attacker_ata, token_account(&mollusk, &mint, &attacker, 0)),
+            (attacker_distribution, attacker_distribution_account),
+            (
+                attacker_vault,
+                token_account(&mollusk, &mint, &attacker_distribution, 0),
+            ),
+            (attacker_stakeholder, attacker_stakeholder_account),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            mint,
+            distribution,
+            vault,
+            holder,
+            holder_ata,
+            stakeholder,
+            attacker,
+            attacker_ata,
+            attacker_stakeholder,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    fn instruction_initialize(
+        &self,
+        authority: &Pubkey,
+        distribution: &Pubkey,
+        bump: u8,
+        vault: &Pubkey,
+    ) -> Instruction {
+        let data = InitializeInstructionData::new(bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*distribution, false),
+            AccountMeta::new_readonly(*vault, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(RoyaltiesInstruction::Initialize, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Adds `holder` to the distribution, signed by `authority`.
+    fn instruction_add_stakeholder(&self, authority: &Pubkey, holder: &Pubkey) -> Instruction {
+        let (stakeholder, bump) = stakeholder_pda(&self.distribution, holder);
+        let data = AddStakeholderInstructionData::new(1_000, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(self.distribution, false),
+            AccountMeta::new_readonly(self.vault, false),
+            AccountMeta::new_readonly(*holder, false),
+            AccountMeta::new(stakeholder, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(RoyaltiesInstruction::AddStakeholder, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_claim(
+        &self,
+        holder: &Pubkey,
+        holder_ata: &Pubkey,
+        vault: &Pubkey,
+        stakeholder: &Pubkey,
+    ) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*holder, true),
+            AccountMeta::new(*holder_ata, false),
+            AccountMeta::new(self.distribution, false),
+            AccountMeta::new(*vault, false),
+            AccountMeta::new(*stakeholder, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[RoyaltiesInstruction::Claim as u8], ix_accounts)
+    }
+
+    /// Returns a claim of the revenue by the attacker, with their stakeholder
+    /// in `stakeholder`.
+    fn attacker_claim(&self, stakeholder: &Pubkey) -> Instruction {
+        self.instruction_claim(&self.attacker, &self.attacker_ata, &self.vault, stakeholder)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_holder_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_claim(
+        &setup.holder,
+        &setup.holder_ata,
+        &setup.vault,
+        &setup.stakeholder,
+    );
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker adds themselves as a stakeholder of the distribution.
+#[test]
+fn test_add_stakeholder_by_attacker() {
+    let setup = Setup::new();
+    let (stakeholder, _) = stakeholder_pda(&setup.distribution, &setup.attacker);
+    let setup = setup.with(stakeholder, Account::default());
+    setup.expect_err(
+        &setup.instruction_add_stakeholder(&setup.attacker, &setup.attacker),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker claims the revenue with their stakeholder of their own
+/// distribution.
+#[test]
+fn test_stakeholder_of_other_distribution() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.attacker_claim(&setup.attacker_stakeholder),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A stakeholder with the layout of the program, but owned by another one.
+#[test]
+fn test_spoofed_stakeholder() {
+    let setup = Setup::new();
+    let (stakeholder, mut stakeholder_account) =
+        stakeholder_account(&setup.mollusk, &setup.distribution, &setup.attacker, 1_000);
+    stakeholder_account.owner = Pubkey::new_unique();
+    let setup = setup.with(stakeholder, stakeholder_account);
+    setup.expect_err(
+        &setup.attacker_claim(&stakeholder),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// A distribution with the layout of the program, but owned by another one,
+/// e.g. with a lower total weight.
+#[test]
+fn test_spoofed_distribution() {
+    let setup = Setup::new();
+    let distribution = setup.distribution;
+    let mut distribution_account = setup.account(&distribution);
+    distribution_account.owner = Pubkey::new_unique();
+    let setup = setup.with(distribution, distribution_account);
+    setup.expect_err(
+        &setup.instruction_claim(
+            &setup.holder,
+            &setup.holder_ata,
+            &setup.vault,
+            &setup.stakeholder,
+        ),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// Another token account of the distribution, whose balance would be
+/// accounted as revenue.
+#[test]
+fn test_wrong_vault() {
+    let setup = Setup::new();
+    let other_vault = Pubkey::new_unique();
+    let other_vault_account = token_account(
+        &setup.mollusk,
+        &setup.mint,
+        &setup.distribution,
+        10 * REVENUE,
+    );
+    let setup = setup.with(other_vault, other_vault_account);
+    setup.expect_err(
+        &setup.instruction_claim(
+            &setup.holder,
+            &setup.holder_ata,
+            &other_vault,
+            &setup.stakeholder,
+        ),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A distribution is created with a vault of the attacker, which it couldn't
+/// pay out from.
+#[test]
+fn test_vault_of_other_owner() {
+    let setup = Setup::new();
+    let authority = Pubkey::new_unique();
+    let (distribution, bump) = distribution_pda(&authority);
+    let setup = setup
+        .with(
+            authority,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        )
+        .with(distribution, Account::default());
+    setup.expect_err(
+        &setup.instruction_initialize(&authority, &distribution, bump, &setup.attacker_ata),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker creates the distribution of another authority.
+#[test]
+fn test_wrong_distribution_seeds() {
+    let setup = Setup::new();
+    let (distribution, bump) = distribution_pda(&Pubkey::new_unique());
+    let vault = Pubkey::new_unique();
+    let vault_account = token_account(&setup.mollusk, &setup.mint, &distribution, 0);
+    let setup = setup
+        .with(distribution, Account::default())
+        .with(vault, vault_account);
+    setup.expect_err(
+        &setup.instruction_initialize(&setup.attacker, &distribution, bump, &vault),
+        ProgramError::InvalidSeeds,
+    );
+}
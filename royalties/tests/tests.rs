@@ -0,0 +1,500 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use royalties::{
+    AddStakeholderInstructionData, Distribution, InitializeInstructionData, RoyaltiesError,
+    RoyaltiesInstruction, Stakeholder, DISTRIBUTION_SEED, PRECISION, STAKEHOLDER_SEED,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(royalties::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+/// Offsets of the distribution account fields.
+const TOTAL_WEIGHT_OFFSET: usize = 64;
+const ACCOUNTED_OFFSET: usize = 72;
+const REVENUE_PER_WEIGHT_OFFSET: usize = 80;
+/// Offset of the checkpoint in a stakeholder account.
+const CHECKPOINT_OFFSET: usize = 72;
+
+const REVENUE: u64 = 1_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: RoyaltiesInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<RoyaltiesInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/royalties");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn distribution_account(
+    mollusk: &Mollusk,
+    authority: &Pubkey,
+    vault: &Pubkey,
+    total_weight: u64,
+    bump: u8,
+) -> Account {
+    let mut data = vec![0; Distribution::LEN];
+    data[0..32].copy_from_slice(authority.as_ref());
+    data[32..64].copy_from_slice(vault.as_ref());
+    data[TOTAL_WEIGHT_OFFSET..TOTAL_WEIGHT_OFFSET + 8].copy_from_slice(&total_weight.to_le_bytes());
+    data[96] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Distribution::LEN),
+        Distribution::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+fn stakeholder_account(
+    mollusk: &Mollusk,
+    distribution: &Pubkey,
+    holder: &Pubkey,
+    weight: u64,
+) -> Account {
+    let mut data = vec![0; Stakeholder::LEN];
+    data[0..32].copy_from_slice(distribution.as_ref());
+    data[32..64].copy_from_slice(holder.as_ref());
+    data[64..72].copy_from_slice(&weight.to_le_bytes());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Stakeholder::LEN),
+        Stakeholder::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// A holder of a share of a distribution.
+struct Holder {
+    key: Pubkey,
+    ata: Pubkey,
+    stakeholder: Pubkey,
+}
+
+/// A distribution with two stakeholders, weighted 1 and 3, and some revenue
+/// in the vault.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    mint: Pubkey,
+    distribution: Pubkey,
+    vault: Pubkey,
+    holders: [Holder; 2],
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let authority = Pubkey::new_unique();
+        let (distribution, distribution_bump) =
+            Pubkey::find_program_address(&[DISTRIBUTION_SEED.as_bytes(), authority.as_ref()], &ID);
+        let mint = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let mut accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (
+                distribution,
+                distribution_account(&mollusk, &authority, &vault, 4, distribution_bump),
+            ),
+            (mint, mint_account(&mollusk, REVENUE)),
+            (
+                vault,
+                token_account(&mollusk, &mint, &distribution, REVENUE),
+            ),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        let holders = [1, 3].map(|weight| {
+            let holder = Holder::new(&distribution);
+            accounts.extend([
+                (holder.key, Account::default()),
+                (holder.ata, token_account(&mollusk, &mint, &holder.key, 0)),
+                (
+                    holder.stakeholder,
+                    stakeholder_account(&mollusk, &distribution, &holder.key, weight),
+                ),
+            ]);
+            holder
+        });
+
+        Self {
+            mollusk,
+            authority,
+            mint,
+            distribution,
+            vault,
+            holders,
+            accounts,
+        }
+    }
+
+    fn instruction_add_stakeholder(&self, holder: &Holder, weight: u64) -> Instruction {
+        let (_, bump) = Pubkey::find_program_address(
+            &[
+                STAKEHOLDER_SEED.as_bytes(),
+                self.distribution.as_ref(),
+                holder.key.as_ref(),
+            ],
+            &ID,
+        );
+        let data = AddStakeholderInstructionData::new(weight, bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new(self.distribution, false),
+            AccountMeta::new_readonly(self.vault, false),
+            AccountMeta::new_readonly(holder.key, false),
+            AccountMeta::new(holder.stakeholder, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(RoyaltiesInstruction::AddStakeholder, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_claim(
+        &self,
+        holder: &Pubkey,
+        stakeholder: &Pubkey,
+        ata: &Pubkey,
+    ) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*holder, true),
+            AccountMeta::new(*ata, false),
+            AccountMeta::new(self.distribution, false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new(*stakeholder, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[RoyaltiesInstruction::Claim as u8], ix_accounts)
+    }
+
+    fn instruction_claim_holder(&self, holder: &Holder) -> Instruction {
+        self.instruction_claim(&holder.key, &holder.stakeholder, &holder.ata)
+    }
+}
+
+impl Holder {
+    fn new(distribution: &Pubkey) -> Self {
+        let key = Pubkey::new_unique();
+        let (stakeholder, _) = Pubkey::find_program_address(
+            &[
+                STAKEHOLDER_SEED.as_bytes(),
+                distribution.as_ref(),
+                key.as_ref(),
+            ],
+            &ID,
+        );
+        Self {
+            key,
+            ata: Pubkey::new_unique(),
+            stakeholder,
+        }
+    }
+}
+
+#[test]
+fn test_royalties_initialize() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let (distribution, bump) =
+        Pubkey::find_program_address(&[DISTRIBUTION_SEED.as_bytes(), authority.as_ref()], &ID);
+    let mint = Pubkey::new_unique();
+    let vault = Pubkey::new_unique();
+
+    let data = InitializeInstructionData::new(bump);
+    let ix_accounts = vec![
+        AccountMeta::new(authority, true),
+        AccountMeta::new(distribution, false),
+        AccountMeta::new_readonly(vault, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(RoyaltiesInstruction::Initialize, &data),
+        ix_accounts,
+    );
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (distribution, Account::default()),
+            (vault, token_account(&mollusk, &mint, &distribution, 0)),
+            keyed_account_for_system_program(),
+        ],
+        &[
+            Check::success(),
+            Check::account(&distribution)
+                .owner(&ID)
+                .data(&distribution_account(&mollusk, &authority, &vault, 0, bump).data)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_royalties_claim() {
+    let setup = Setup::new();
+    let [a, b] = &setup.holders;
+
+    let revenue_per_weight = REVENUE as u128 * PRECISION / 4;
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_claim_holder(a),
+                &[
+                    Check::success(),
+                    Check::account(&a.ata)
+                        .data_slice(AMOUNT_OFFSET, &(REVENUE / 4).to_le_bytes())
+                        .build(),
+                    Check::account(&a.stakeholder)
+                        .data_slice(CHECKPOINT_OFFSET, &revenue_per_weight.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.distribution)
+                        .data_slice(ACCOUNTED_OFFSET, &(REVENUE * 3 / 4).to_le_bytes())
+                        .data_slice(REVENUE_PER_WEIGHT_OFFSET, &revenue_per_weight.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_claim_holder(b),
+                &[
+                    Check::success(),
+                    Check::account(&b.ata)
+                        .data_slice(AMOUNT_OFFSET, &(REVENUE * 3 / 4).to_le_bytes())
+                        .build(),
+                    Check::account(&setup.vault)
+                        .data_slice(AMOUNT_OFFSET, &0u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_royalties_claim_twice() {
+    let setup = Setup::new();
+    let [a, _] = &setup.holders;
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (&setup.instruction_claim_holder(a), &[Check::success()]),
+            (
+                &setup.instruction_claim_holder(a),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Royalties.code(RoyaltiesError::NothingToClaim as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_royalties_claim_new_revenue() {
+    let mut setup = Setup::new();
+
+    // Both holders claim the initial revenue.
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_claim_holder(&setup.holders[0]),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_claim_holder(&setup.holders[1]),
+                &[Check::success()],
+            ),
+        ],
+        &setup.accounts,
+    );
+    setup.accounts = res.resulting_accounts;
+
+    // More revenue arrives.
+    let vault = setup
+        .accounts
+        .iter_mut()
+        .find(|(key, _)| key == &setup.vault)
+        .unwrap();
+    vault.1.data[AMOUNT_OFFSET..AMOUNT_OFFSET + 8].copy_from_slice(&400u64.to_le_bytes());
+
+    let [a, _] = &setup.holders;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_claim_holder(a),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&a.ata)
+                .data_slice(AMOUNT_OFFSET, &(REVENUE / 4 + 100).to_le_bytes())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_royalties_add_stakeholder() {
+    let mut setup = Setup::new();
+
+    let c = Holder::new(&setup.distribution);
+    let accounts = [
+        (c.key, Account::default()),
+        (c.ata, token_account(&setup.mollusk, &setup.mint, &c.key, 0)),
+        (c.stakeholder, Account::default()),
+    ];
+    setup.accounts.extend(accounts);
+
+    let revenue_per_weight = REVENUE as u128 * PRECISION / 4;
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_add_stakeholder(&c, 0),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Royalties.code(RoyaltiesError::InvalidWeight as u32),
+        ))],
+    );
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_add_stakeholder(&c, 4),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&c.stakeholder)
+                .owner(&ID)
+                .space(Stakeholder::LEN)
+                .data_slice(64, &4u64.to_le_bytes())
+                .data_slice(CHECKPOINT_OFFSET, &revenue_per_weight.to_le_bytes())
+                .build(),
+            Check::account(&setup.distribution)
+                .data_slice(TOTAL_WEIGHT_OFFSET, &8u64.to_le_bytes())
+                .data_slice(ACCOUNTED_OFFSET, &REVENUE.to_le_bytes())
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+
+    // The revenue received before the stakeholder was added isn't theirs.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_claim_holder(&c),
+        &res.resulting_accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::Royalties.code(RoyaltiesError::NothingToClaim as u32),
+        ))],
+    );
+}
+
+#[test]
+fn test_royalties_claim_wrong_holder() {
+    let mut setup = Setup::new();
+
+    let attacker = Pubkey::new_unique();
+    let attacker_ata = Pubkey::new_unique();
+    let attacker_ata_account = token_account(&setup.mollusk, &setup.mint, &attacker, 0);
+    setup.accounts.extend([
+        (attacker, Account::default()),
+        (attacker_ata, attacker_ata_account),
+    ]);
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_claim(&attacker, &setup.holders[1].stakeholder, &attacker_ata),
+        &setup.accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
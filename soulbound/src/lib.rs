@@ -0,0 +1,362 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+use pinocchio_token::{
+    instructions::{Approve, Burn, FreezeAccount, InitializeMint2, MintTo, ThawAccount},
+    state::{Mint, TokenAccount},
+};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("pBnf2b5iJkNNovQJR6EuoR925aUfnU8DbwsRFeNYnjm");
+
+pub const ISSUER_SEED: &str = "issuer";
+pub const MINT_SEED: &str = "mint";
+
+/// Errors returned by the soulbound program.
+#[repr(u32)]
+pub enum SoulboundError {
+    /// The token account already holds a token.
+    AlreadyIssued,
+}
+
+impl From<SoulboundError> for ProgramError {
+    fn from(e: SoulboundError) -> Self {
+        Namespace::Soulbound.error(e as u32)
+    }
+}
+
+/// On-chain representation of an issuer.
+///
+/// The issuer PDA is both the mint authority and the freeze authority of
+/// its mint.
+#[repr(C)]
+pub struct Issuer {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    /// Number of tokens currently issued.
+    pub issued: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Issuer, size = 80, align = 8, { authority: 0, mint: 32, issued: 64, bump: 72 });
+
+impl Issuer {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Soulbound program instruction discriminators.
+#[repr(u8)]
+pub enum SoulboundInstruction {
+    /// Creates an issuer and its mint.
+    Initialize,
+    /// Mints a token to a recipient and freezes it.
+    Issue,
+    /// Burns an issued token.
+    Revoke,
+}
+
+impl TryFrom<&u8> for SoulboundInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Issue),
+            2 => Ok(Self::Revoke),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub issuer_bump: u8,
+    pub mint_bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(issuer_bump: u8, mint_bump: u8) -> Self {
+        Self {
+            issuer_bump,
+            mint_bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = SoulboundInstruction::try_from(instruction)?;
+
+    match instruction {
+        SoulboundInstruction::Initialize => process_initialize(accounts, instruction_data),
+        SoulboundInstruction::Issue => process_issue(accounts),
+        SoulboundInstruction::Revoke => process_revoke(accounts),
+    }
+}
+
+/// Checks that `authority` signed and is the authority of `issuer`, and that
+/// `mint` is the mint of `issuer`.
+fn check_issuer(
+    authority: &AccountInfo,
+    issuer: &AccountInfo,
+    mint: &AccountInfo,
+) -> ProgramResult {
+    require_signer(authority)?;
+    // Check if the issuer PDA is owned by the program.
+    require_owned_by(issuer, &ID)?;
+
+    let data = issuer.try_borrow_data()?;
+    let data: &Issuer = unsafe { &*data.as_ptr().cast() };
+
+    if !keys_eq(&data.authority, authority.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if !keys_eq(&data.mint, mint.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
+
+/// Returns the bump of `issuer`.
+///
+/// The data borrow is released before returning, so the issuer PDA can be
+/// passed to CPIs.
+fn issuer_bump(issuer: &AccountInfo) -> Result<u8, ProgramError> {
+    let data = issuer.try_borrow_data()?;
+    let data: &Issuer = unsafe { &*data.as_ptr().cast() };
+    Ok(data.bump)
+}
+
+/// Creates an issuer of `authority` and a mint with no decimals, controlled
+/// by the issuer PDA.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, issuer, mint, _system_program, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `issuer` and `mint`.
+    let issuer_pda = create_program_address(
+        &[
+            ISSUER_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.issuer_bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(issuer.key(), &issuer_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let mint_pda = create_program_address(
+        &[
+            MINT_SEED.as_bytes(),
+            issuer.key(),
+            &[instruction_data.mint_bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(mint.key(), &mint_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the issuer PDA.
+    let bump = [instruction_data.issuer_bump];
+    let seeds = [
+        Seed::from(ISSUER_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: issuer,
+        lamports: Rent::get()?.minimum_balance(Issuer::LEN),
+        space: Issuer::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Create the mint PDA, owned by the token program.
+    let bump = [instruction_data.mint_bump];
+    let seeds = [
+        Seed::from(MINT_SEED.as_bytes()),
+        Seed::from(issuer.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: mint,
+        lamports: Rent::get()?.minimum_balance(Mint::LEN),
+        space: Mint::LEN as u64,
+        owner: &pinocchio_token::ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+    InitializeMint2 {
+        mint,
+        decimals: 0,
+        mint_authority: issuer.key(),
+        freeze_authority: Some(issuer.key()),
+    }
+    .invoke()?;
+
+    // Deserialize the issuer PDA.
+    let mut data = issuer.try_borrow_mut_data()?;
+    let data: &mut Issuer = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the issuer.
+    data.authority = *authority.key();
+    data.mint = *mint.key();
+    data.bump = instruction_data.issuer_bump;
+
+    info!("Created the issuer");
+
+    Ok(())
+}
+
+/// Mints a token to the token account of `recipient` and freezes it, so it
+/// can't be transferred.
+///
+/// The recipient has to sign as well, approving the issuer PDA as the
+/// delegate of the token. Otherwise the issuer couldn't burn the token on
+/// revocation. A frozen account can't revoke the delegate.
+pub fn process_issue(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, recipient, issuer, mint, recipient_token_account, _token_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_issuer(authority, issuer, mint)?;
+
+    require_signer(recipient)?;
+
+    {
+        let token_account = TokenAccount::from_account_info(recipient_token_account)?;
+        if !keys_eq(token_account.mint(), mint.key())
+            || !keys_eq(token_account.owner(), recipient.key())
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if token_account.amount() != 0 || token_account.is_frozen() {
+            return Err(SoulboundError::AlreadyIssued.into());
+        }
+    }
+
+    Approve {
+        source: recipient_token_account,
+        delegate: issuer,
+        authority: recipient,
+        amount: 1,
+    }
+    .invoke()?;
+
+    let bump = [issuer_bump(issuer)?];
+    let seeds = [
+        Seed::from(ISSUER_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    MintTo {
+        mint,
+        account: recipient_token_account,
+        mint_authority: issuer,
+        amount: 1,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+    FreezeAccount {
+        account: recipient_token_account,
+        mint,
+        freeze_authority: issuer,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the issuer PDA.
+    let mut data = issuer.try_borrow_mut_data()?;
+    let data: &mut Issuer = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    data.issued += 1;
+
+    info!("Issued token, {} issued in total", data.issued);
+
+    Ok(())
+}
+
+/// Thaws the token account and burns its token, using the issuer PDA as the
+/// delegate.
+pub fn process_revoke(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, issuer, mint, token_account, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    check_issuer(authority, issuer, mint)?;
+
+    // The token program checks that the account belongs to `mint` and that
+    // the issuer PDA is its delegate.
+    let bump = [issuer_bump(issuer)?];
+    let seeds = [
+        Seed::from(ISSUER_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    ThawAccount {
+        account: token_account,
+        mint,
+        freeze_authority: issuer,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+    Burn {
+        account: token_account,
+        mint,
+        authority: issuer,
+        amount: 1,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the issuer PDA.
+    let mut data = issuer.try_borrow_mut_data()?;
+    let data: &mut Issuer = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    data.issued -= 1;
+
+    info!("Revoked token, {} issued in total", data.issued);
+
+    Ok(())
+}
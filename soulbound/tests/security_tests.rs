@@ -0,0 +1,228 @@
+//! Attacks on the soulbound program, each rejected with its own error
+//! before any CPI, so the token program doesn't have to be loaded.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use soulbound::{InitializeInstructionData, Issuer, SoulboundInstruction, ISSUER_SEED, MINT_SEED};
+
+const ID: Pubkey = Pubkey::new_from_array(soulbound::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: SoulboundInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<SoulboundInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the issuer PDA of `authority` and its bump.
+fn issuer_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ISSUER_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Returns the mint PDA of `issuer` and its bump.
+fn mint_pda(issuer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_SEED.as_bytes(), issuer.as_ref()], &ID)
+}
+
+/// Returns an issuer of `authority` with `mint`, which issued one token.
+fn issuer_account(mollusk: &Mollusk, authority: &Pubkey, mint: &Pubkey, bump: u8) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Issuer::LEN),
+        Issuer::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[32..64].copy_from_slice(mint.as_ref());
+    account.data[64..72].copy_from_slice(&1u64.to_le_bytes());
+    account.data[72] = bump;
+    account
+}
+
+/// Accounts of an issuer with a token issued to a recipient, and of an
+/// attacker with an issuer of their own. The mint and the token account are
+/// never read by the program itself.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    issuer: Pubkey,
+    mint: Pubkey,
+    recipient: Pubkey,
+    token_account: Pubkey,
+    attacker: Pubkey,
+    attacker_issuer: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/soulbound");
+
+        let authority = Pubkey::new_unique();
+        let (issuer, issuer_bump) = issuer_pda(&authority);
+        let (mint, _) = mint_pda(&issuer);
+        let recipient = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let (attacker_issuer, attacker_issuer_bump) = issuer_pda(&attacker);
+        let (attacker_mint, _) = mint_pda(&attacker_issuer);
+
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                issuer,
+                issuer_account(&mollusk, &authority, &mint, issuer_bump),
+            ),
+            (mint, Account::default()),
+            (recipient, Account::default()),
+            (token_account, Account::default()),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (
+                attacker_issuer,
+                issuer_account(&mollusk, &attacker, &attacker_mint, attacker_issuer_bump),
+            ),
+            keyed_account_for_system_program(),
+            (TOKEN_ID, Account::default()),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            issuer,
+            mint,
+            recipient,
+            token_account,
+            attacker,
+            attacker_issuer,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Issues a token of the mint to the recipient, as `authority` of
+    /// `issuer`.
+    fn instruction_issue(&self, authority: &Pubkey, issuer: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(self.recipient, true),
+            AccountMeta::new(*issuer, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.token_account, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[SoulboundInstruction::Issue as u8], ix_accounts)
+    }
+
+    /// Revokes the token of the recipient, as `authority` of `issuer`.
+    fn instruction_revoke(&self, authority: &Pubkey, issuer: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*issuer, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.token_account, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[SoulboundInstruction::Revoke as u8], ix_accounts)
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_revoke(&setup.authority, &setup.issuer);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the issuer of the authority before the authority
+/// does, with their own bump.
+#[test]
+fn test_initialize_issuer_of_other_authority() {
+    let setup = Setup::new();
+    let issuer = setup.issuer;
+    let mint = setup.mint;
+    let setup = setup.with(issuer, Account::default());
+    let (_, issuer_bump) = issuer_pda(&setup.attacker);
+    let (_, mint_bump) = mint_pda(&issuer);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(issuer, false),
+        AccountMeta::new(mint, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        AccountMeta::new_readonly(TOKEN_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            SoulboundInstruction::Initialize,
+            &InitializeInstructionData::new(issuer_bump, mint_bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker revokes the token issued to the recipient by the issuer.
+#[test]
+fn test_revoke_as_other_authority() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_revoke(&setup.attacker, &setup.issuer),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker issues a token of the mint of the issuer with their own
+/// issuer, which would count it as issued by them.
+#[test]
+fn test_issue_with_mint_of_other_issuer() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_issue(&setup.attacker, &setup.attacker_issuer),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// An issuer of the attacker with the mint of the issuer, owned by another
+/// program.
+#[test]
+fn test_revoke_with_spoofed_issuer() {
+    let setup = Setup::new();
+    let (_, bump) = issuer_pda(&setup.attacker);
+    let mut issuer_account = issuer_account(&setup.mollusk, &setup.attacker, &setup.mint, bump);
+    issuer_account.owner = Pubkey::new_unique();
+    let issuer = Pubkey::new_unique();
+    let setup = setup.with(issuer, issuer_account);
+    setup.expect_err(
+        &setup.instruction_revoke(&setup.attacker, &issuer),
+        ProgramError::IllegalOwner,
+    );
+}
@@ -0,0 +1,359 @@
+use std::mem;
+
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use soulbound::{
+    InitializeInstructionData, Issuer, SoulboundError, SoulboundInstruction, ISSUER_SEED, MINT_SEED,
+};
+use spl_token::{
+    error::TokenError,
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+const ID: Pubkey = Pubkey::new_from_array(soulbound::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Offsets of the token account fields.
+const AMOUNT_OFFSET: usize = 64;
+const DELEGATE_OFFSET: usize = 76;
+const STATE_OFFSET: usize = 108;
+/// Offset of the issued counter in the issuer account.
+const ISSUED_OFFSET: usize = 64;
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/soulbound");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, authority: &Pubkey, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::Some(*authority),
+            supply,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::Some(*authority),
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount: 0,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn issuer_account(mollusk: &Mollusk, authority: &Pubkey, mint: &Pubkey, bump: u8) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Issuer::LEN),
+        Issuer::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[32..64].copy_from_slice(mint.as_ref());
+    account.data[72] = bump;
+    account
+}
+
+/// An issuer with its mint and a recipient with an empty token account.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    issuer: Pubkey,
+    mint: Pubkey,
+    recipient: Pubkey,
+    recipient_ata: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let authority = Pubkey::new_unique();
+        let (issuer, issuer_bump) =
+            Pubkey::find_program_address(&[ISSUER_SEED.as_bytes(), authority.as_ref()], &ID);
+        let (mint, _) = Pubkey::find_program_address(&[MINT_SEED.as_bytes(), issuer.as_ref()], &ID);
+        let recipient = Pubkey::new_unique();
+        let recipient_ata = Pubkey::new_unique();
+
+        let accounts = vec![
+            (authority, Account::default()),
+            (
+                issuer,
+                issuer_account(&mollusk, &authority, &mint, issuer_bump),
+            ),
+            (mint, mint_account(&mollusk, &issuer, 0)),
+            (recipient, Account::default()),
+            (recipient_ata, token_account(&mollusk, &mint, &recipient)),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            issuer,
+            mint,
+            recipient,
+            recipient_ata,
+            accounts,
+        }
+    }
+
+    fn instruction_issue(&self, authority: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(self.recipient, true),
+            AccountMeta::new(self.issuer, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.recipient_ata, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[SoulboundInstruction::Issue as u8], ix_accounts)
+    }
+
+    fn instruction_revoke(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.authority, true),
+            AccountMeta::new(self.issuer, false),
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.recipient_ata, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &[SoulboundInstruction::Revoke as u8], ix_accounts)
+    }
+}
+
+#[test]
+fn test_soulbound_initialize() {
+    let mollusk = mollusk();
+    let (system_program, system_account) = keyed_account_for_system_program();
+
+    let authority = Pubkey::new_unique();
+    let (issuer, issuer_bump) =
+        Pubkey::find_program_address(&[ISSUER_SEED.as_bytes(), authority.as_ref()], &ID);
+    let (mint, mint_bump) =
+        Pubkey::find_program_address(&[MINT_SEED.as_bytes(), issuer.as_ref()], &ID);
+
+    // Create instruction data.
+    let data = InitializeInstructionData::new(issuer_bump, mint_bump);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<SoulboundInstruction>() + InitializeInstructionData::LEN);
+    data_with_discriminator.push(SoulboundInstruction::Initialize as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![
+        AccountMeta::new(authority, true),
+        AccountMeta::new(issuer, false),
+        AccountMeta::new(mint, false),
+        AccountMeta::new_readonly(system_program, false),
+        AccountMeta::new_readonly(TOKEN_ID, false),
+    ];
+    let instruction = Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts);
+
+    // The mint and freeze authorities of the mint are the issuer PDA.
+    let mut expected_mint = vec![0; Mint::LEN];
+    Pack::pack(
+        Mint {
+            mint_authority: COption::Some(issuer),
+            supply: 0,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::Some(issuer),
+        },
+        &mut expected_mint,
+    )
+    .unwrap();
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &[
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program),
+            ),
+            (issuer, Account::default()),
+            (mint, Account::default()),
+            (system_program, system_account),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ],
+        &[
+            Check::success(),
+            Check::account(&issuer)
+                .owner(&ID)
+                .space(Issuer::LEN)
+                .data_slice(0, authority.as_ref())
+                .data_slice(32, mint.as_ref())
+                .build(),
+            Check::account(&mint)
+                .owner(&TOKEN_ID)
+                .data(&expected_mint)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_soulbound_issue() {
+    let mut setup = Setup::new();
+
+    let other = Pubkey::new_unique();
+    let other_ata = Pubkey::new_unique();
+    let other_ata_account = token_account(&setup.mollusk, &setup.mint, &other);
+    setup.accounts.push((other_ata, other_ata_account));
+
+    // The recipient trying to move the token away.
+    let transfer = spl_token::instruction::transfer(
+        &TOKEN_ID,
+        &setup.recipient_ata,
+        &other_ata,
+        &setup.recipient,
+        &[],
+        1,
+    )
+    .unwrap();
+    // ...or to revoke the issuer's delegation.
+    let revoke =
+        spl_token::instruction::revoke(&TOKEN_ID, &setup.recipient_ata, &setup.recipient, &[])
+            .unwrap();
+
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_issue(&setup.authority),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.recipient_ata)
+                .data_slice(AMOUNT_OFFSET, &1u64.to_le_bytes())
+                .data_slice(DELEGATE_OFFSET, setup.issuer.as_ref())
+                .data_slice(STATE_OFFSET, &[TokenAccountState::Frozen as u8])
+                .build(),
+            Check::account(&setup.issuer)
+                .data_slice(ISSUED_OFFSET, &1u64.to_le_bytes())
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+    for instruction in [transfer, revoke] {
+        setup.mollusk.process_and_validate_instruction(
+            &instruction,
+            &res.resulting_accounts,
+            &[Check::err(ProgramError::Custom(
+                TokenError::AccountFrozen as u32,
+            ))],
+        );
+    }
+}
+
+#[test]
+fn test_soulbound_issue_twice() {
+    let setup = Setup::new();
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_issue(&setup.authority),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_issue(&setup.authority),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::Soulbound.code(SoulboundError::AlreadyIssued as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_soulbound_issue_wrong_authority() {
+    let mut setup = Setup::new();
+
+    let attacker = Pubkey::new_unique();
+    setup.accounts.push((attacker, Account::default()));
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_issue(&attacker),
+        &setup.accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+#[test]
+fn test_soulbound_revoke() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_issue(&setup.authority),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_revoke(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.recipient_ata)
+                        .data_slice(AMOUNT_OFFSET, &0u64.to_le_bytes())
+                        .data_slice(STATE_OFFSET, &[TokenAccountState::Initialized as u8])
+                        .build(),
+                    Check::account(&setup.issuer)
+                        .data_slice(ISSUED_OFFSET, &0u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            // The account can receive a new token.
+            (
+                &setup.instruction_issue(&setup.authority),
+                &[Check::success()],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
@@ -0,0 +1,449 @@
+#![no_std]
+
+use common::{checks::require_signer, info, keys_eq};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+pub mod stake;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("223zvSFCdaLktZQc1RPmbSHZ6mH4snYZZEQKz1kL9B6M");
+
+pub const STAKE_SEED: &str = "stake";
+pub const AUTHORITY_SEED: &str = "authority";
+pub const SPLIT_SEED: &str = "split";
+
+/// Stake manager program instruction discriminators.
+#[repr(u8)]
+pub enum StakeManagerInstruction {
+    /// Creates and initializes a stake account.
+    CreateStake,
+    /// Delegates the stake account to a vote account.
+    Delegate,
+    /// Deactivates the stake account.
+    Deactivate,
+    /// Withdraws lamports from the stake account.
+    Withdraw,
+    /// Splits lamports of a stake account into a new stake account PDA.
+    Split,
+    /// Merges a stake account into another one.
+    Merge,
+}
+
+impl TryFrom<&u8> for StakeManagerInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::CreateStake),
+            1 => Ok(Self::Delegate),
+            2 => Ok(Self::Deactivate),
+            3 => Ok(Self::Withdraw),
+            4 => Ok(Self::Split),
+            5 => Ok(Self::Merge),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct CreateStakeInstructionData {
+    /// Lamports to stake, on top of the rent-exempt minimum.
+    pub lamports: u64,
+    pub stake_bump: u8,
+    pub authority_bump: u8,
+}
+
+impl CreateStakeInstructionData {
+    pub fn new(lamports: u64, stake_bump: u8, authority_bump: u8) -> Self {
+        Self {
+            lamports,
+            stake_bump,
+            authority_bump,
+        }
+    }
+}
+
+/// Instruction data of `Delegate`, `Deactivate` and `Merge`.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AuthorityInstructionData {
+    pub authority_bump: u8,
+}
+
+impl AuthorityInstructionData {
+    pub fn new(authority_bump: u8) -> Self {
+        Self { authority_bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct WithdrawInstructionData {
+    pub lamports: u64,
+    pub authority_bump: u8,
+}
+
+impl WithdrawInstructionData {
+    pub fn new(lamports: u64, authority_bump: u8) -> Self {
+        Self {
+            lamports,
+            authority_bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct SplitInstructionData {
+    /// Lamports to move to the new stake account.
+    pub lamports: u64,
+    /// Index of the new stake account among the split stake accounts of the
+    /// owner.
+    pub index: u64,
+    pub split_bump: u8,
+    pub authority_bump: u8,
+}
+
+impl SplitInstructionData {
+    pub fn new(lamports: u64, index: u64, split_bump: u8, authority_bump: u8) -> Self {
+        Self {
+            lamports,
+            index,
+            split_bump,
+            authority_bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = StakeManagerInstruction::try_from(instruction)?;
+
+    match instruction {
+        StakeManagerInstruction::CreateStake => process_create_stake(accounts, instruction_data),
+        StakeManagerInstruction::Delegate => process_delegate(accounts, instruction_data),
+        StakeManagerInstruction::Deactivate => process_deactivate(accounts, instruction_data),
+        StakeManagerInstruction::Withdraw => process_withdraw(accounts, instruction_data),
+        StakeManagerInstruction::Split => process_split(accounts, instruction_data),
+        StakeManagerInstruction::Merge => process_merge(accounts, instruction_data),
+    }
+}
+
+/// Checks that `owner` signed and that `authority` is the stake authority
+/// PDA of `owner`.
+///
+/// The program keeps no state: since `owner` is one of the seeds, the PDA can
+/// only ever sign on behalf of the owner who signed the transaction.
+fn check_authority(owner: &AccountInfo, authority: &AccountInfo, bump: u8) -> ProgramResult {
+    require_signer(owner)?;
+
+    // Check the seeds of `authority`.
+    let authority_pda =
+        create_program_address(&[AUTHORITY_SEED.as_bytes(), owner.key(), &[bump]], &ID)?;
+    if !keys_eq(authority.key(), &authority_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    Ok(())
+}
+
+/// Creates a stake account PDA of `owner` and initializes it with the
+/// authority PDA of `owner` as both the staker and the withdrawer.
+pub fn process_create_stake(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, stake_account, authority, rent_sysvar, _system_program, _stake_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = CreateStakeInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(owner, authority, instruction_data.authority_bump)?;
+
+    // Check the seeds of `stake_account`.
+    let stake_pda = create_program_address(
+        &[
+            STAKE_SEED.as_bytes(),
+            owner.key(),
+            &[instruction_data.stake_bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(stake_account.key(), &stake_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the stake account, owned by the stake program.
+    let lamports = Rent::get()?
+        .minimum_balance(stake::STAKE_ACCOUNT_LEN)
+        .checked_add(instruction_data.lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    let bump = [instruction_data.stake_bump];
+    let seeds = [
+        Seed::from(STAKE_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: owner,
+        to: stake_account,
+        lamports,
+        space: stake::STAKE_ACCOUNT_LEN as u64,
+        owner: &stake::ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Initialize the stake account. Only the stake program can write to it
+    // from now on.
+    stake::Initialize {
+        stake: stake_account,
+        rent_sysvar,
+        staker: authority.key(),
+        withdrawer: authority.key(),
+    }
+    .invoke()?;
+
+    info!("Created stake account with {} lamports", lamports);
+
+    Ok(())
+}
+
+/// Delegates the stake account to `vote`.
+pub fn process_delegate(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, stake_account, authority, vote, clock_sysvar, stake_history_sysvar, stake_config, _stake_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = AuthorityInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(owner, authority, instruction_data.authority_bump)?;
+
+    // The stake program checks that `authority` is the staker of
+    // `stake_account`.
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    stake::DelegateStake {
+        stake: stake_account,
+        vote,
+        clock_sysvar,
+        stake_history_sysvar,
+        stake_config,
+        staker: authority,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Delegated stake");
+
+    Ok(())
+}
+
+/// Deactivates the stake account. The stake becomes withdrawable after the
+/// cooldown, at the end of the epoch.
+pub fn process_deactivate(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, stake_account, authority, clock_sysvar, _stake_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = AuthorityInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(owner, authority, instruction_data.authority_bump)?;
+
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    stake::Deactivate {
+        stake: stake_account,
+        clock_sysvar,
+        staker: authority,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Deactivated stake");
+
+    Ok(())
+}
+
+/// Withdraws inactive lamports from the stake account to `recipient`.
+pub fn process_withdraw(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, stake_account, authority, recipient, clock_sysvar, stake_history_sysvar, _stake_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = WithdrawInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(owner, authority, instruction_data.authority_bump)?;
+
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    stake::Withdraw {
+        stake: stake_account,
+        recipient,
+        clock_sysvar,
+        stake_history_sysvar,
+        withdrawer: authority,
+        lamports: instruction_data.lamports,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Withdrew {} lamports", instruction_data.lamports);
+
+    Ok(())
+}
+
+/// Splits `lamports` of the stake account into a new split stake account PDA
+/// of `owner`.
+///
+/// The stake program expects the new account to be allocated with the size
+/// of a stake account and already owned by it. It also has to hold the
+/// rent-exempt reserve on its own, otherwise splitting a delegated stake
+/// fails. Both accounts need to keep at least the minimum delegation if the
+/// stake is delegated.
+pub fn process_split(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, stake_account, split_stake_account, authority, _system_program, _stake_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = SplitInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(owner, authority, instruction_data.authority_bump)?;
+
+    // Check the seeds of `split_stake_account`.
+    let index = instruction_data.index.to_le_bytes();
+    let split_pda = create_program_address(
+        &[
+            SPLIT_SEED.as_bytes(),
+            owner.key(),
+            &index,
+            &[instruction_data.split_bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(split_stake_account.key(), &split_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the new account with only the rent-exempt reserve. The stake
+    // program initializes it with the state of `stake_account`.
+    let bump = [instruction_data.split_bump];
+    let seeds = [
+        Seed::from(SPLIT_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&index),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: owner,
+        to: split_stake_account,
+        lamports: Rent::get()?.minimum_balance(stake::STAKE_ACCOUNT_LEN),
+        space: stake::STAKE_ACCOUNT_LEN as u64,
+        owner: &stake::ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    stake::Split {
+        stake: stake_account,
+        split_stake: split_stake_account,
+        staker: authority,
+        lamports: instruction_data.lamports,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Split {} lamports", instruction_data.lamports);
+
+    Ok(())
+}
+
+/// Merges `source` into `destination`, draining `source`.
+///
+/// The stake program only merges stakes which are both inactive, both
+/// activating in the same epoch, or both fully active, delegated to the same
+/// vote account. Inactive stake can also be merged into an activating one.
+/// Stakes in the middle of warming up or cooling down can't be merged at
+/// all.
+pub fn process_merge(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [owner, destination, source, authority, clock_sysvar, stake_history_sysvar, _stake_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = AuthorityInstructionData::from_bytes(instruction_data)?;
+
+    check_authority(owner, authority, instruction_data.authority_bump)?;
+
+    let bump = [instruction_data.authority_bump];
+    let seeds = [
+        Seed::from(AUTHORITY_SEED.as_bytes()),
+        Seed::from(owner.key()),
+        Seed::from(&bump),
+    ];
+    stake::Merge {
+        destination,
+        source,
+        clock_sysvar,
+        stake_history_sysvar,
+        staker: authority,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Merged stake accounts");
+
+    Ok(())
+}
@@ -0,0 +1,385 @@
+//! Instructions of the native stake program.
+//!
+//! There is no pinocchio crate for the stake program, so the CPIs are built
+//! by hand, the same way `pinocchio-system` and `pinocchio-token` do it.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+/// Stake program ID.
+pub const ID: Pubkey = pinocchio_pubkey::pubkey!("Stake11111111111111111111111111111111111111");
+
+/// Size of a stake account.
+pub const STAKE_ACCOUNT_LEN: usize = 200;
+
+/// Initialize a stake account with no lockup.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized stake account
+///   1. `[]` Rent sysvar
+pub struct Initialize<'a> {
+    /// Stake account.
+    pub stake: &'a AccountInfo,
+
+    /// Rent sysvar.
+    pub rent_sysvar: &'a AccountInfo,
+
+    /// Authority allowed to delegate and deactivate the stake.
+    pub staker: &'a Pubkey,
+
+    /// Authority allowed to withdraw from the stake account.
+    pub withdrawer: &'a Pubkey,
+}
+
+impl Initialize<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 2] = [
+            AccountMeta::writable(self.stake.key()),
+            AccountMeta::readonly(self.rent_sysvar.key()),
+        ];
+
+        // instruction data
+        // -  [0..4  ]: instruction discriminator
+        // -  [4..36 ]: staker
+        // -  [36..68]: withdrawer
+        // -  [68..76]: lockup unix timestamp
+        // -  [76..84]: lockup epoch
+        // -  [84..116]: lockup custodian
+        let mut instruction_data = [0; 116];
+        instruction_data[4..36].copy_from_slice(self.staker);
+        instruction_data[36..68].copy_from_slice(self.withdrawer);
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.stake, self.rent_sysvar], signers)
+    }
+}
+
+/// Delegate a stake account to a vote account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Initialized stake account
+///   1. `[]` Vote account
+///   2. `[]` Clock sysvar
+///   3. `[]` Stake history sysvar
+///   4. `[]` Unused account, formerly the stake config
+///   5. `[SIGNER]` Stake authority
+pub struct DelegateStake<'a> {
+    /// Stake account.
+    pub stake: &'a AccountInfo,
+
+    /// Vote account.
+    pub vote: &'a AccountInfo,
+
+    /// Clock sysvar.
+    pub clock_sysvar: &'a AccountInfo,
+
+    /// Stake history sysvar.
+    pub stake_history_sysvar: &'a AccountInfo,
+
+    /// Stake config account.
+    pub stake_config: &'a AccountInfo,
+
+    /// Stake authority.
+    pub staker: &'a AccountInfo,
+}
+
+impl DelegateStake<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 6] = [
+            AccountMeta::writable(self.stake.key()),
+            AccountMeta::readonly(self.vote.key()),
+            AccountMeta::readonly(self.clock_sysvar.key()),
+            AccountMeta::readonly(self.stake_history_sysvar.key()),
+            AccountMeta::readonly(self.stake_config.key()),
+            AccountMeta::readonly_signer(self.staker.key()),
+        ];
+
+        // instruction data
+        // -  [0..4]: instruction discriminator
+        let instruction_data = 2u32.to_le_bytes();
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.stake,
+                self.vote,
+                self.clock_sysvar,
+                self.stake_history_sysvar,
+                self.stake_config,
+                self.staker,
+            ],
+            signers,
+        )
+    }
+}
+
+/// Withdraw lamports from a stake account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Stake account
+///   1. `[WRITE]` Recipient account
+///   2. `[]` Clock sysvar
+///   3. `[]` Stake history sysvar
+///   4. `[SIGNER]` Withdraw authority
+pub struct Withdraw<'a> {
+    /// Stake account.
+    pub stake: &'a AccountInfo,
+
+    /// Recipient account.
+    pub recipient: &'a AccountInfo,
+
+    /// Clock sysvar.
+    pub clock_sysvar: &'a AccountInfo,
+
+    /// Stake history sysvar.
+    pub stake_history_sysvar: &'a AccountInfo,
+
+    /// Withdraw authority.
+    pub withdrawer: &'a AccountInfo,
+
+    /// Amount of lamports to withdraw.
+    pub lamports: u64,
+}
+
+impl Withdraw<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 5] = [
+            AccountMeta::writable(self.stake.key()),
+            AccountMeta::writable(self.recipient.key()),
+            AccountMeta::readonly(self.clock_sysvar.key()),
+            AccountMeta::readonly(self.stake_history_sysvar.key()),
+            AccountMeta::readonly_signer(self.withdrawer.key()),
+        ];
+
+        // instruction data
+        // -  [0..4 ]: instruction discriminator
+        // -  [4..12]: lamports amount
+        let mut instruction_data = [0; 12];
+        instruction_data[0] = 4;
+        instruction_data[4..12].copy_from_slice(&self.lamports.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.stake,
+                self.recipient,
+                self.clock_sysvar,
+                self.stake_history_sysvar,
+                self.withdrawer,
+            ],
+            signers,
+        )
+    }
+}
+
+/// Deactivate a delegated stake account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Delegated stake account
+///   1. `[]` Clock sysvar
+///   2. `[SIGNER]` Stake authority
+pub struct Deactivate<'a> {
+    /// Stake account.
+    pub stake: &'a AccountInfo,
+
+    /// Clock sysvar.
+    pub clock_sysvar: &'a AccountInfo,
+
+    /// Stake authority.
+    pub staker: &'a AccountInfo,
+}
+
+impl Deactivate<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.stake.key()),
+            AccountMeta::readonly(self.clock_sysvar.key()),
+            AccountMeta::readonly_signer(self.staker.key()),
+        ];
+
+        // instruction data
+        // -  [0..4]: instruction discriminator
+        let instruction_data = 5u32.to_le_bytes();
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.stake, self.clock_sysvar, self.staker],
+            signers,
+        )
+    }
+}
+
+/// Split lamports of a stake account into an uninitialized stake account,
+/// which inherits the authorities, the lockup and the delegation.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Stake account to split
+///   1. `[WRITE]` Uninitialized stake account, with the rent-exempt reserve
+///   2. `[SIGNER]` Stake authority
+pub struct Split<'a> {
+    /// Stake account to split.
+    pub stake: &'a AccountInfo,
+
+    /// Stake account receiving the lamports.
+    pub split_stake: &'a AccountInfo,
+
+    /// Stake authority.
+    pub staker: &'a AccountInfo,
+
+    /// Amount of lamports to split.
+    pub lamports: u64,
+}
+
+impl Split<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.stake.key()),
+            AccountMeta::writable(self.split_stake.key()),
+            AccountMeta::readonly_signer(self.staker.key()),
+        ];
+
+        // instruction data
+        // -  [0..4 ]: instruction discriminator
+        // -  [4..12]: lamports amount
+        let mut instruction_data = [0; 12];
+        instruction_data[0] = 3;
+        instruction_data[4..12].copy_from_slice(&self.lamports.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.stake, self.split_stake, self.staker],
+            signers,
+        )
+    }
+}
+
+/// Merge a source stake account into a destination stake account. Both need
+/// the same authorities and lockup, and compatible delegations. The source
+/// is drained and becomes uninitialized.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Destination stake account
+///   1. `[WRITE]` Source stake account
+///   2. `[]` Clock sysvar
+///   3. `[]` Stake history sysvar
+///   4. `[SIGNER]` Stake authority
+pub struct Merge<'a> {
+    /// Destination stake account.
+    pub destination: &'a AccountInfo,
+
+    /// Source stake account.
+    pub source: &'a AccountInfo,
+
+    /// Clock sysvar.
+    pub clock_sysvar: &'a AccountInfo,
+
+    /// Stake history sysvar.
+    pub stake_history_sysvar: &'a AccountInfo,
+
+    /// Stake authority.
+    pub staker: &'a AccountInfo,
+}
+
+impl Merge<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 5] = [
+            AccountMeta::writable(self.destination.key()),
+            AccountMeta::writable(self.source.key()),
+            AccountMeta::readonly(self.clock_sysvar.key()),
+            AccountMeta::readonly(self.stake_history_sysvar.key()),
+            AccountMeta::readonly_signer(self.staker.key()),
+        ];
+
+        // instruction data
+        // -  [0..4]: instruction discriminator
+        let instruction_data = 7u32.to_le_bytes();
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.destination,
+                self.source,
+                self.clock_sysvar,
+                self.stake_history_sysvar,
+                self.staker,
+            ],
+            signers,
+        )
+    }
+}
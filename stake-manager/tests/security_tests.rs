@@ -0,0 +1,375 @@
+//! Attacks on the stake manager program, each rejected with its own error.
+//!
+//! The program keeps no state of its own, so most of the attacks pass its
+//! checks with the authority PDA of the attacker and are rejected by the
+//! stake program, which only accepts the authority PDA of the owner.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{create_keyed_account_for_builtin_program, keyed_account_for_system_program},
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use solana_vote_interface::state::{VoteInit, VoteState, VoteStateVersions};
+use stake_manager::{
+    AuthorityInstructionData, CreateStakeInstructionData, SplitInstructionData,
+    StakeManagerInstruction, WithdrawInstructionData, AUTHORITY_SEED, SPLIT_SEED, STAKE_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(stake_manager::ID);
+const STAKE_ID: Pubkey = Pubkey::new_from_array(stake_manager::stake::ID);
+
+/// `StakeError::MergeMismatch`.
+const MERGE_MISMATCH: u32 = 6;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: StakeManagerInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<StakeManagerInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn vote_account(mollusk: &Mollusk) -> Account {
+    let vote_state = VoteState::new(
+        &VoteInit {
+            node_pubkey: Pubkey::new_unique(),
+            authorized_voter: Pubkey::new_unique(),
+            authorized_withdrawer: Pubkey::new_unique(),
+            commission: 0,
+        },
+        &mollusk.sysvars.clock,
+    );
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(VoteState::size_of()),
+        VoteState::size_of(),
+        &solana_sdk_ids::vote::ID,
+    );
+    VoteState::serialize(
+        &VoteStateVersions::new_current(vote_state),
+        &mut account.data,
+    )
+    .unwrap();
+    account
+}
+
+/// A wallet with its stake and authority PDAs.
+struct Staker {
+    key: Pubkey,
+    stake: Pubkey,
+    stake_bump: u8,
+    authority: Pubkey,
+    authority_bump: u8,
+}
+
+impl Staker {
+    fn new() -> Self {
+        let key = Pubkey::new_unique();
+        let (stake, stake_bump) =
+            Pubkey::find_program_address(&[STAKE_SEED.as_bytes(), key.as_ref()], &ID);
+        let (authority, authority_bump) =
+            Pubkey::find_program_address(&[AUTHORITY_SEED.as_bytes(), key.as_ref()], &ID);
+        Self {
+            key,
+            stake,
+            stake_bump,
+            authority,
+            authority_bump,
+        }
+    }
+
+    fn split_stake(&self, index: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                SPLIT_SEED.as_bytes(),
+                self.key.as_ref(),
+                &index.to_le_bytes(),
+            ],
+            &ID,
+        )
+    }
+
+    fn instruction_create_stake(&self, lamports: u64) -> Instruction {
+        let data = CreateStakeInstructionData::new(lamports, self.stake_bump, self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.key, true),
+            AccountMeta::new(self.stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::rent::ID, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::CreateStake, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Delegates `stake` with the authority of the staker.
+    fn instruction_delegate(&self, stake: &Pubkey, vote: &Pubkey) -> Instruction {
+        let data = AuthorityInstructionData::new(self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.key, true),
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(*vote, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::stake::config::ID, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Delegate, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Deactivates `stake` with the authority of the staker.
+    fn instruction_deactivate(&self, stake: &Pubkey) -> Instruction {
+        let data = AuthorityInstructionData::new(self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.key, true),
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Deactivate, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Withdraws `lamports` of `stake` to `recipient` with the authority of
+    /// the staker.
+    fn instruction_withdraw(
+        &self,
+        stake: &Pubkey,
+        lamports: u64,
+        recipient: &Pubkey,
+    ) -> Instruction {
+        let data = WithdrawInstructionData::new(lamports, self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.key, true),
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new(*recipient, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Withdraw, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Splits `lamports` of `stake` into the first split stake account of
+    /// the staker, with its authority.
+    fn instruction_split(&self, stake: &Pubkey, lamports: u64) -> Instruction {
+        let (split_stake, split_bump) = self.split_stake(0);
+        let data = SplitInstructionData::new(lamports, 0, split_bump, self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.key, true),
+            AccountMeta::new(*stake, false),
+            AccountMeta::new(split_stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Split, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Merges `source` into `destination` with the authority of the staker.
+    fn instruction_merge(&self, destination: &Pubkey, source: &Pubkey) -> Instruction {
+        let data = AuthorityInstructionData::new(self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.key, true),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Merge, &data),
+            ix_accounts,
+        )
+    }
+}
+
+/// Accounts of an owner and an attacker, both with their own stake and
+/// authority PDAs, of a vote account to delegate to and of a recipient of
+/// withdrawals.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Staker,
+    attacker: Staker,
+    vote: Pubkey,
+    recipient: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/stake_manager");
+
+        let owner = Staker::new();
+        let attacker = Staker::new();
+        let vote = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+
+        let mut accounts = vec![
+            (vote, vote_account(&mollusk)),
+            (recipient, Account::default()),
+            (solana_sdk_ids::stake::config::ID, Account::default()),
+            mollusk.sysvars.keyed_account_for_rent_sysvar(),
+            mollusk.sysvars.keyed_account_for_clock_sysvar(),
+            mollusk.sysvars.keyed_account_for_stake_history_sysvar(),
+            keyed_account_for_system_program(),
+            create_keyed_account_for_builtin_program(&STAKE_ID, "solana_stake_program"),
+        ];
+        for staker in [&owner, &attacker] {
+            accounts.extend([
+                (
+                    staker.key,
+                    Account::new(10 * LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+                ),
+                (staker.stake, Account::default()),
+                (staker.authority, Account::default()),
+                (staker.split_stake(0).0, Account::default()),
+            ]);
+        }
+
+        Self {
+            mollusk,
+            owner,
+            attacker,
+            vote,
+            recipient,
+            accounts,
+        }
+    }
+
+    /// Processes `instructions`, of which all but the last one succeed,
+    /// after the owner staked 4 SOL.
+    fn expect_err(&self, instructions: &[Instruction], err: ProgramError) {
+        let create_stake = self.owner.instruction_create_stake(4 * LAMPORTS_PER_SOL);
+        let success = [Check::success()];
+        let failure = [Check::err(err)];
+
+        let (attack, preceding) = instructions.split_last().unwrap();
+        let mut chain = vec![(&create_stake, &success[..])];
+        chain.extend(preceding.iter().map(|ix| (ix, &success[..])));
+        chain.push((attack, &failure[..]));
+        self.mollusk
+            .process_and_validate_instruction_chain(&chain, &self.accounts);
+    }
+}
+
+#[test]
+fn test_missing_owner_signature() {
+    let setup = Setup::new();
+    let owner = &setup.owner;
+    let mut withdraw = owner.instruction_withdraw(&owner.stake, LAMPORTS_PER_SOL, &setup.recipient);
+    withdraw.accounts[0].is_signer = false;
+    setup.expect_err(&[withdraw], ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker withdraws the stake of the owner, signing with their own
+/// authority PDA.
+#[test]
+fn test_withdraw_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &[setup.attacker.instruction_withdraw(
+            &setup.owner.stake,
+            LAMPORTS_PER_SOL,
+            &setup.recipient,
+        )],
+        ProgramError::MissingRequiredSignature,
+    );
+}
+
+/// The attacker delegates the stake of the owner to a vote account of their
+/// choice.
+#[test]
+fn test_delegate_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &[setup
+            .attacker
+            .instruction_delegate(&setup.owner.stake, &setup.vote)],
+        ProgramError::MissingRequiredSignature,
+    );
+}
+
+/// The attacker deactivates the delegated stake of the owner.
+#[test]
+fn test_deactivate_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &[
+            setup
+                .owner
+                .instruction_delegate(&setup.owner.stake, &setup.vote),
+            setup.attacker.instruction_deactivate(&setup.owner.stake),
+        ],
+        ProgramError::MissingRequiredSignature,
+    );
+}
+
+/// The attacker splits the stake of the owner into their own split stake
+/// account.
+#[test]
+fn test_split_by_attacker() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &[setup
+            .attacker
+            .instruction_split(&setup.owner.stake, LAMPORTS_PER_SOL)],
+        ProgramError::MissingRequiredSignature,
+    );
+}
+
+/// The attacker merges the stake of the owner into their own stake account.
+#[test]
+fn test_merge_into_attacker_stake() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &[
+            setup.attacker.instruction_create_stake(LAMPORTS_PER_SOL),
+            setup
+                .attacker
+                .instruction_merge(&setup.attacker.stake, &setup.owner.stake),
+        ],
+        ProgramError::Custom(MERGE_MISMATCH),
+    );
+}
+
+/// The attacker creates their stake account at the address of the stake PDA
+/// of the owner.
+#[test]
+fn test_wrong_stake_seeds() {
+    let setup = Setup::new();
+    let mut create_stake = setup.attacker.instruction_create_stake(LAMPORTS_PER_SOL);
+    create_stake.accounts[1].pubkey = setup.owner.stake;
+    setup.expect_err(&[create_stake], ProgramError::InvalidSeeds);
+}
@@ -0,0 +1,532 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{create_keyed_account_for_builtin_program, keyed_account_for_system_program},
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use solana_vote_interface::state::{VoteInit, VoteState, VoteStateVersions};
+use stake_manager::{
+    stake::STAKE_ACCOUNT_LEN, AuthorityInstructionData, CreateStakeInstructionData,
+    SplitInstructionData, StakeManagerInstruction, WithdrawInstructionData, AUTHORITY_SEED,
+    SPLIT_SEED, STAKE_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(stake_manager::ID);
+const STAKE_ID: Pubkey = Pubkey::new_from_array(stake_manager::stake::ID);
+
+/// Offsets of the stake account fields.
+const STAKER_OFFSET: usize = 12;
+const WITHDRAWER_OFFSET: usize = 44;
+const VOTER_OFFSET: usize = 124;
+const DEACTIVATION_EPOCH_OFFSET: usize = 172;
+
+/// `StakeError::MergeMismatch`.
+const MERGE_MISMATCH: u32 = 6;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: StakeManagerInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<StakeManagerInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+/// An owner with their stake and authority PDAs, and a vote account to
+/// delegate to.
+struct Setup {
+    mollusk: Mollusk,
+    owner: Pubkey,
+    stake: Pubkey,
+    stake_bump: u8,
+    authority: Pubkey,
+    authority_bump: u8,
+    vote: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/stake_manager");
+
+        let owner = Pubkey::new_unique();
+        let (stake, stake_bump) =
+            Pubkey::find_program_address(&[STAKE_SEED.as_bytes(), owner.as_ref()], &ID);
+        let (authority, authority_bump) =
+            Pubkey::find_program_address(&[AUTHORITY_SEED.as_bytes(), owner.as_ref()], &ID);
+        let vote = Pubkey::new_unique();
+
+        let accounts = vec![
+            (
+                owner,
+                Account::new(10 * LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (stake, Account::default()),
+            (authority, Account::default()),
+            (vote, vote_account(&mollusk)),
+            (solana_sdk_ids::stake::config::ID, Account::default()),
+            mollusk.sysvars.keyed_account_for_rent_sysvar(),
+            mollusk.sysvars.keyed_account_for_clock_sysvar(),
+            mollusk.sysvars.keyed_account_for_stake_history_sysvar(),
+            keyed_account_for_system_program(),
+            create_keyed_account_for_builtin_program(&STAKE_ID, "solana_stake_program"),
+        ];
+
+        Self {
+            mollusk,
+            owner,
+            stake,
+            stake_bump,
+            authority,
+            authority_bump,
+            vote,
+            accounts,
+        }
+    }
+
+    fn instruction_create_stake(&self, lamports: u64) -> Instruction {
+        let data = CreateStakeInstructionData::new(lamports, self.stake_bump, self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::rent::ID, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::CreateStake, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_delegate(&self, owner: &Pubkey) -> Instruction {
+        let data = AuthorityInstructionData::new(self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(self.stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(self.vote, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::stake::config::ID, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Delegate, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_deactivate(&self) -> Instruction {
+        let data = AuthorityInstructionData::new(self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Deactivate, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_withdraw(&self, lamports: u64, recipient: &Pubkey) -> Instruction {
+        let data = WithdrawInstructionData::new(lamports, self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(self.stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new(*recipient, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Withdraw, &data),
+            ix_accounts,
+        )
+    }
+
+    fn split_stake(&self, index: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                SPLIT_SEED.as_bytes(),
+                self.owner.as_ref(),
+                &index.to_le_bytes(),
+            ],
+            &ID,
+        )
+    }
+
+    fn instruction_split(&self, lamports: u64, index: u64) -> Instruction {
+        let (split_stake, split_bump) = self.split_stake(index);
+        let data = SplitInstructionData::new(lamports, index, split_bump, self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.owner, true),
+            AccountMeta::new(self.stake, false),
+            AccountMeta::new(split_stake, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Split, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_merge(&self, destination: &Pubkey, source: &Pubkey) -> Instruction {
+        let data = AuthorityInstructionData::new(self.authority_bump);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.owner, true),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(self.authority, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+            AccountMeta::new_readonly(solana_sdk_ids::sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(STAKE_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(StakeManagerInstruction::Merge, &data),
+            ix_accounts,
+        )
+    }
+}
+
+fn vote_account(mollusk: &Mollusk) -> Account {
+    let vote_state = VoteState::new(
+        &VoteInit {
+            node_pubkey: Pubkey::new_unique(),
+            authorized_voter: Pubkey::new_unique(),
+            authorized_withdrawer: Pubkey::new_unique(),
+            commission: 0,
+        },
+        &mollusk.sysvars.clock,
+    );
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(VoteState::size_of()),
+        VoteState::size_of(),
+        &solana_sdk_ids::vote::ID,
+    );
+    VoteState::serialize(
+        &VoteStateVersions::new_current(vote_state),
+        &mut account.data,
+    )
+    .unwrap();
+    account
+}
+
+#[test]
+fn test_stake_manager_create_stake() {
+    let setup = Setup::new();
+
+    let rent = setup
+        .mollusk
+        .sysvars
+        .rent
+        .minimum_balance(STAKE_ACCOUNT_LEN);
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_create_stake(LAMPORTS_PER_SOL),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.stake)
+                .owner(&STAKE_ID)
+                .space(STAKE_ACCOUNT_LEN)
+                .lamports(rent + LAMPORTS_PER_SOL)
+                // `StakeStateV2::Initialized`
+                .data_slice(0, &1u32.to_le_bytes())
+                .data_slice(STAKER_OFFSET, setup.authority.as_ref())
+                .data_slice(WITHDRAWER_OFFSET, setup.authority.as_ref())
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_stake_manager_delegate_and_deactivate() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_create_stake(LAMPORTS_PER_SOL),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_delegate(&setup.owner),
+                &[
+                    Check::success(),
+                    Check::account(&setup.stake)
+                        // `StakeStateV2::Stake`
+                        .data_slice(0, &2u32.to_le_bytes())
+                        .data_slice(VOTER_OFFSET, setup.vote.as_ref())
+                        .data_slice(DEACTIVATION_EPOCH_OFFSET, &u64::MAX.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_deactivate(),
+                &[
+                    Check::success(),
+                    Check::account(&setup.stake)
+                        .data_slice(
+                            DEACTIVATION_EPOCH_OFFSET,
+                            &setup.mollusk.sysvars.clock.epoch.to_le_bytes(),
+                        )
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_stake_manager_delegate_wrong_owner() {
+    let mut setup = Setup::new();
+
+    // The authority PDA is derived from the owner, so a different signer
+    // can't make it sign.
+    let attacker = Pubkey::new_unique();
+    setup.accounts.push((attacker, Account::default()));
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_create_stake(LAMPORTS_PER_SOL),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_delegate(&attacker),
+                &[Check::err(ProgramError::InvalidSeeds)],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_stake_manager_withdraw_undelegated() {
+    let mut setup = Setup::new();
+
+    let recipient = Pubkey::new_unique();
+    setup.accounts.push((recipient, Account::default()));
+
+    // Stake that was never delegated can be withdrawn right away, including
+    // the rent-exempt reserve.
+    let rent = setup
+        .mollusk
+        .sysvars
+        .rent
+        .minimum_balance(STAKE_ACCOUNT_LEN);
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_create_stake(LAMPORTS_PER_SOL),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_withdraw(rent + LAMPORTS_PER_SOL, &recipient),
+                &[
+                    Check::success(),
+                    Check::account(&recipient)
+                        .lamports(rent + LAMPORTS_PER_SOL)
+                        .build(),
+                    Check::account(&setup.stake).lamports(0).build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_stake_manager_split_and_merge() {
+    let mut setup = Setup::new();
+
+    let (split_stake, _) = setup.split_stake(0);
+    setup.accounts.push((split_stake, Account::default()));
+
+    let rent = setup
+        .mollusk
+        .sysvars
+        .rent
+        .minimum_balance(STAKE_ACCOUNT_LEN);
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_create_stake(3 * LAMPORTS_PER_SOL),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_split(LAMPORTS_PER_SOL, 0),
+                &[
+                    Check::success(),
+                    Check::account(&setup.stake)
+                        .lamports(rent + 2 * LAMPORTS_PER_SOL)
+                        .build(),
+                    // The owner only paid the rent-exempt reserve, the
+                    // authorities come from the split stake account.
+                    Check::account(&split_stake)
+                        .owner(&STAKE_ID)
+                        .space(STAKE_ACCOUNT_LEN)
+                        .lamports(rent + LAMPORTS_PER_SOL)
+                        .data_slice(0, &1u32.to_le_bytes())
+                        .data_slice(STAKER_OFFSET, setup.authority.as_ref())
+                        .data_slice(WITHDRAWER_OFFSET, setup.authority.as_ref())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_merge(&setup.stake, &split_stake),
+                &[
+                    Check::success(),
+                    // The reserve of the split stake account is merged too.
+                    Check::account(&setup.stake)
+                        .lamports(2 * rent + 3 * LAMPORTS_PER_SOL)
+                        .build(),
+                    // `StakeStateV2::Uninitialized`
+                    Check::account(&split_stake)
+                        .lamports(0)
+                        .data_slice(0, &0u32.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_stake_manager_split_delegated() {
+    let mut setup = Setup::new();
+
+    let (split_stake, _) = setup.split_stake(0);
+    setup.accounts.push((split_stake, Account::default()));
+
+    // The split stake account keeps the delegation.
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_create_stake(4 * LAMPORTS_PER_SOL),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_delegate(&setup.owner),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_split(2 * LAMPORTS_PER_SOL, 0),
+                &[
+                    Check::success(),
+                    Check::account(&split_stake)
+                        .data_slice(0, &2u32.to_le_bytes())
+                        .data_slice(VOTER_OFFSET, setup.vote.as_ref())
+                        .data_slice(DEACTIVATION_EPOCH_OFFSET, &u64::MAX.to_le_bytes())
+                        .build(),
+                ],
+            ),
+            // Activating stakes delegated to the same vote account can be
+            // merged back.
+            (
+                &setup.instruction_merge(&setup.stake, &split_stake),
+                &[
+                    Check::success(),
+                    Check::account(&split_stake).lamports(0).build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_stake_manager_merge_mismatch() {
+    let mut setup = Setup::new();
+
+    let (split_stake, _) = setup.split_stake(0);
+    let other_vote = Pubkey::new_unique();
+    setup.accounts.push((split_stake, Account::default()));
+    setup
+        .accounts
+        .push((other_vote, vote_account(&setup.mollusk)));
+
+    // Delegate the split stake account to another vote account.
+    let mut delegate_split = setup.instruction_delegate(&setup.owner);
+    delegate_split.accounts[1] = AccountMeta::new(split_stake, false);
+    delegate_split.accounts[3] = AccountMeta::new_readonly(other_vote, false);
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_create_stake(4 * LAMPORTS_PER_SOL),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_split(2 * LAMPORTS_PER_SOL, 0),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_delegate(&setup.owner),
+                &[Check::success()],
+            ),
+            (&delegate_split, &[Check::success()]),
+            (
+                &setup.instruction_merge(&setup.stake, &split_stake),
+                &[Check::err(ProgramError::Custom(MERGE_MISMATCH))],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_stake_manager_split_invalid_seeds() {
+    let mut setup = Setup::new();
+
+    // The split stake account has to match the index.
+    let (split_stake, _) = setup.split_stake(1);
+    setup.accounts.push((split_stake, Account::default()));
+    let mut split = setup.instruction_split(LAMPORTS_PER_SOL, 0);
+    split.accounts[2] = AccountMeta::new(split_stake, false);
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &setup.instruction_create_stake(3 * LAMPORTS_PER_SOL),
+                &[Check::success()],
+            ),
+            (&split, &[Check::err(ProgramError::InvalidSeeds)]),
+        ],
+        &setup.accounts,
+    );
+}
@@ -0,0 +1,494 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+use pinocchio_token::{instructions::Transfer, state::TokenAccount};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("3LW9DUEJkZ75iShivcM74NnsAWtCbKbVXoqvt7rvGSPn");
+
+pub const DESK_SEED: &str = "desk";
+
+/// Errors returned by the swap desk program.
+#[repr(u32)]
+pub enum SwapDeskError {
+    /// The denominator of the rate is zero.
+    InvalidRate,
+    /// The swap would result in zero tokens.
+    ZeroOutput,
+    /// The B vault doesn't hold enough tokens.
+    InsufficientLiquidity,
+}
+
+impl From<SwapDeskError> for ProgramError {
+    fn from(e: SwapDeskError) -> Self {
+        Namespace::SwapDesk.error(e as u32)
+    }
+}
+
+/// On-chain representation of a swap desk.
+///
+/// The desk sells token B for token A at a fixed rate of `rate_num /
+/// rate_den` B per A. The rate is applied to raw token amounts, so it has to
+/// account for the decimals of both mints.
+#[repr(C)]
+pub struct Desk {
+    pub admin: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    /// Token account of the desk holding token A.
+    pub vault_a: Pubkey,
+    /// Token account of the desk holding token B.
+    pub vault_b: Pubkey,
+    pub rate_num: u64,
+    pub rate_den: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+}
+
+assert_layout!(Desk, size = 184, align = 8, {
+    admin: 0,
+    mint_a: 32,
+    mint_b: 64,
+    vault_a: 96,
+    vault_b: 128,
+    rate_num: 160,
+    rate_den: 168,
+    bump: 176,
+});
+
+impl Desk {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Returns the amount of token B for `amount_in` of token A, rounded
+    /// down.
+    pub fn quote(&self, amount_in: u64) -> Result<u64, ProgramError> {
+        let amount_out = amount_in as u128 * self.rate_num as u128 / self.rate_den as u128;
+        amount_out
+            .try_into()
+            .map_err(|_| ProgramError::ArithmeticOverflow)
+    }
+}
+
+/// Swap desk program instruction discriminators.
+#[repr(u8)]
+pub enum SwapDeskInstruction {
+    /// Creates a desk.
+    Initialize,
+    /// Changes the rate of a desk.
+    SetRate,
+    /// Moves tokens from the admin to the vaults.
+    Fund,
+    /// Moves tokens from the vaults to the admin.
+    Withdraw,
+    /// Swaps token A for token B.
+    Swap,
+}
+
+impl TryFrom<&u8> for SwapDeskInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::SetRate),
+            2 => Ok(Self::Fund),
+            3 => Ok(Self::Withdraw),
+            4 => Ok(Self::Swap),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct InitializeInstructionData {
+    pub rate_num: u64,
+    pub rate_den: u64,
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(rate_num: u64, rate_den: u64, bump: u8) -> Self {
+        Self {
+            rate_num,
+            rate_den,
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct SetRateInstructionData {
+    pub rate_num: u64,
+    pub rate_den: u64,
+}
+
+impl SetRateInstructionData {
+    pub fn new(rate_num: u64, rate_den: u64) -> Self {
+        Self { rate_num, rate_den }
+    }
+}
+
+/// Instruction data of both `Fund` and `Withdraw`.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AmountsInstructionData {
+    pub amount_a: u64,
+    pub amount_b: u64,
+}
+
+impl AmountsInstructionData {
+    pub fn new(amount_a: u64, amount_b: u64) -> Self {
+        Self { amount_a, amount_b }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct SwapInstructionData {
+    /// Amount of token A to sell.
+    pub amount_in: u64,
+}
+
+impl SwapInstructionData {
+    pub fn new(amount_in: u64) -> Self {
+        Self { amount_in }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = SwapDeskInstruction::try_from(instruction)?;
+
+    match instruction {
+        SwapDeskInstruction::Initialize => process_initialize(accounts, instruction_data),
+        SwapDeskInstruction::SetRate => process_set_rate(accounts, instruction_data),
+        SwapDeskInstruction::Fund => process_fund(accounts, instruction_data),
+        SwapDeskInstruction::Withdraw => process_withdraw(accounts, instruction_data),
+        SwapDeskInstruction::Swap => process_swap(accounts, instruction_data),
+    }
+}
+
+/// Creates a desk trading the tokens held by `vault_a` and `vault_b`, which
+/// have to be owned by the desk PDA.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, desk, vault_a, vault_b, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Check that the vaults are owned by `desk`.
+    let mint_a = {
+        let vault_a = TokenAccount::from_account_info(vault_a)?;
+        if !keys_eq(vault_a.owner(), desk.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        *vault_a.mint()
+    };
+    let mint_b = {
+        let vault_b = TokenAccount::from_account_info(vault_b)?;
+        if !keys_eq(vault_b.owner(), desk.key()) {
+            return Err(ProgramError::IllegalOwner);
+        }
+        *vault_b.mint()
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.rate_den == 0 {
+        return Err(SwapDeskError::InvalidRate.into());
+    }
+
+    // Check the seeds of `desk`.
+    let desk_pda = create_program_address(
+        &[
+            DESK_SEED.as_bytes(),
+            admin.key(),
+            &mint_a,
+            &mint_b,
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(desk.key(), &desk_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the desk PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(DESK_SEED.as_bytes()),
+        Seed::from(admin.key()),
+        Seed::from(&mint_a),
+        Seed::from(&mint_b),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: admin,
+        to: desk,
+        lamports: Rent::get()?.minimum_balance(Desk::LEN),
+        space: Desk::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the desk PDA.
+    let mut data = desk.try_borrow_mut_data()?;
+    let data: &mut Desk = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the desk.
+    data.admin = *admin.key();
+    data.mint_a = mint_a;
+    data.mint_b = mint_b;
+    data.vault_a = *vault_a.key();
+    data.vault_b = *vault_b.key();
+    data.rate_num = instruction_data.rate_num;
+    data.rate_den = instruction_data.rate_den;
+    data.bump = instruction_data.bump;
+
+    info!(
+        "Initialized desk with rate {}/{}",
+        instruction_data.rate_num, instruction_data.rate_den
+    );
+
+    Ok(())
+}
+
+/// Changes the rate of the desk.
+pub fn process_set_rate(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, desk] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = SetRateInstructionData::from_bytes(instruction_data)?;
+
+    if instruction_data.rate_den == 0 {
+        return Err(SwapDeskError::InvalidRate.into());
+    }
+
+    check_admin(admin, desk)?;
+
+    // Deserialize the desk PDA.
+    let mut data = desk.try_borrow_mut_data()?;
+    let data: &mut Desk = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    data.rate_num = instruction_data.rate_num;
+    data.rate_den = instruction_data.rate_den;
+
+    info!(
+        "Set rate to {}/{}",
+        instruction_data.rate_num, instruction_data.rate_den
+    );
+
+    Ok(())
+}
+
+/// Moves tokens from the admin's token accounts to the vaults.
+pub fn process_fund(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, admin_ata_a, admin_ata_b, desk, vault_a, vault_b, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = AmountsInstructionData::from_bytes(instruction_data)?;
+
+    check_admin(admin, desk)?;
+    check_vaults(desk, vault_a, vault_b)?;
+
+    // Transfer tokens from admin to the vaults.
+    Transfer {
+        from: admin_ata_a,
+        to: vault_a,
+        authority: admin,
+        amount: instruction_data.amount_a,
+    }
+    .invoke()?;
+    Transfer {
+        from: admin_ata_b,
+        to: vault_b,
+        authority: admin,
+        amount: instruction_data.amount_b,
+    }
+    .invoke()?;
+
+    info!(
+        "Funded desk with {} A and {} B",
+        instruction_data.amount_a, instruction_data.amount_b
+    );
+
+    Ok(())
+}
+
+/// Moves tokens from the vaults to the admin's token accounts.
+pub fn process_withdraw(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, admin_ata_a, admin_ata_b, desk, vault_a, vault_b, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = AmountsInstructionData::from_bytes(instruction_data)?;
+
+    check_admin(admin, desk)?;
+    check_vaults(desk, vault_a, vault_b)?;
+
+    // Transfer tokens from the vaults to admin.
+    let data = desk.try_borrow_data()?;
+    let data: &Desk = unsafe { &*data.as_ptr().cast() };
+    let bump = [data.bump];
+    let seeds = [
+        Seed::from(DESK_SEED.as_bytes()),
+        Seed::from(&data.admin),
+        Seed::from(&data.mint_a),
+        Seed::from(&data.mint_b),
+        Seed::from(&bump),
+    ];
+    Transfer {
+        from: vault_a,
+        to: admin_ata_a,
+        authority: desk,
+        amount: instruction_data.amount_a,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+    Transfer {
+        from: vault_b,
+        to: admin_ata_b,
+        authority: desk,
+        amount: instruction_data.amount_b,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!(
+        "Withdrew {} A and {} B from desk",
+        instruction_data.amount_a, instruction_data.amount_b
+    );
+
+    Ok(())
+}
+
+/// Sells `amount_in` of token A to the desk for token B at the desk's rate.
+pub fn process_swap(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [user, user_ata_a, user_ata_b, desk, vault_a, vault_b, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(user)?;
+
+    // Deserialize instruction data.
+    let instruction_data = SwapInstructionData::from_bytes(instruction_data)?;
+
+    // Check if the desk PDA is owned by the program.
+    require_owned_by(desk, &ID)?;
+    check_vaults(desk, vault_a, vault_b)?;
+
+    // Deserialize the desk PDA.
+    let data = desk.try_borrow_data()?;
+    let data: &Desk = unsafe { &*data.as_ptr().cast() };
+
+    // Compute the amount of token B and check that the desk can pay it.
+    let amount_out = data.quote(instruction_data.amount_in)?;
+    if amount_out == 0 {
+        return Err(SwapDeskError::ZeroOutput.into());
+    }
+    if TokenAccount::from_account_info(vault_b)?.amount() < amount_out {
+        return Err(SwapDeskError::InsufficientLiquidity.into());
+    }
+
+    // Transfer token A from user to the desk.
+    Transfer {
+        from: user_ata_a,
+        to: vault_a,
+        authority: user,
+        amount: instruction_data.amount_in,
+    }
+    .invoke()?;
+
+    // Transfer token B from the desk to user.
+    let bump = [data.bump];
+    let seeds = [
+        Seed::from(DESK_SEED.as_bytes()),
+        Seed::from(&data.admin),
+        Seed::from(&data.mint_a),
+        Seed::from(&data.mint_b),
+        Seed::from(&bump),
+    ];
+    Transfer {
+        from: vault_b,
+        to: user_ata_b,
+        authority: desk,
+        amount: amount_out,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!(
+        "Swapped {} A for {} B",
+        instruction_data.amount_in, amount_out
+    );
+
+    Ok(())
+}
+
+/// Checks that `admin` signed and is the admin of `desk`.
+fn check_admin(admin: &AccountInfo, desk: &AccountInfo) -> ProgramResult {
+    require_signer(admin)?;
+
+    // Check if the desk PDA is owned by the program.
+    require_owned_by(desk, &ID)?;
+
+    // Check that `admin` is the same as in the desk account.
+    let data = desk.try_borrow_data()?;
+    let data: &Desk = unsafe { &*data.as_ptr().cast() };
+    if !keys_eq(&data.admin, admin.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    Ok(())
+}
+
+/// Checks that `vault_a` and `vault_b` are the vaults of `desk`.
+fn check_vaults(desk: &AccountInfo, vault_a: &AccountInfo, vault_b: &AccountInfo) -> ProgramResult {
+    let data = desk.try_borrow_data()?;
+    let data: &Desk = unsafe { &*data.as_ptr().cast() };
+    if !keys_eq(&data.vault_a, vault_a.key()) || !keys_eq(&data.vault_b, vault_b.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(())
+}
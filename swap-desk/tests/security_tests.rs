@@ -0,0 +1,372 @@
+//! Attacks on the swap desk program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+use swap_desk::{
+    AmountsInstructionData, Desk, InitializeInstructionData, SetRateInstructionData, SwapDeskError,
+    SwapDeskInstruction, SwapInstructionData, DESK_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(swap_desk::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: SwapDeskInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<SwapDeskInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn mint_account(mollusk: &Mollusk) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply: 2_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Accounts of a desk selling 3/2 B per A, whose B vault holds 1_000
+/// tokens, and of a user holding token A.
+struct Setup {
+    mollusk: Mollusk,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    admin: Pubkey,
+    user: Pubkey,
+    user_ata_a: Pubkey,
+    user_ata_b: Pubkey,
+    desk: Pubkey,
+    desk_bump: u8,
+    vault_a: Pubkey,
+    vault_b: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/swap_desk");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let user_ata_a = Pubkey::new_unique();
+        let user_ata_b = Pubkey::new_unique();
+        let (desk, desk_bump) = Pubkey::find_program_address(
+            &[
+                DESK_SEED.as_bytes(),
+                admin.as_ref(),
+                mint_a.as_ref(),
+                mint_b.as_ref(),
+            ],
+            &ID,
+        );
+        let vault_a = Pubkey::new_unique();
+        let vault_b = Pubkey::new_unique();
+
+        let mut desk_account = Account::new(
+            mollusk.sysvars.rent.minimum_balance(Desk::LEN),
+            Desk::LEN,
+            &ID,
+        );
+        let data = &mut desk_account.data;
+        data[0..32].copy_from_slice(admin.as_ref());
+        data[32..64].copy_from_slice(mint_a.as_ref());
+        data[64..96].copy_from_slice(mint_b.as_ref());
+        data[96..128].copy_from_slice(vault_a.as_ref());
+        data[128..160].copy_from_slice(vault_b.as_ref());
+        data[160..168].copy_from_slice(&3u64.to_le_bytes());
+        data[168..176].copy_from_slice(&2u64.to_le_bytes());
+        data[176] = desk_bump;
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (mint_a, mint_account(&mollusk)),
+            (mint_b, mint_account(&mollusk)),
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (user, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (user_ata_a, token_account(&mollusk, &mint_a, &user, 1_000)),
+            (user_ata_b, token_account(&mollusk, &mint_b, &user, 0)),
+            (desk, desk_account),
+            (vault_a, token_account(&mollusk, &mint_a, &desk, 0)),
+            (vault_b, token_account(&mollusk, &mint_b, &desk, 1_000)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            mint_a,
+            mint_b,
+            admin,
+            user,
+            user_ata_a,
+            user_ata_b,
+            desk,
+            desk_bump,
+            vault_a,
+            vault_b,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn account(&self, address: &Pubkey) -> Account {
+        self.accounts
+            .iter()
+            .find(|(other, _)| other == address)
+            .unwrap()
+            .1
+            .clone()
+    }
+
+    fn instruction_initialize(&self) -> Instruction {
+        let data = InitializeInstructionData::new(3, 2, self.desk_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.desk, false),
+            AccountMeta::new_readonly(self.vault_a, false),
+            AccountMeta::new_readonly(self.vault_b, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(SwapDeskInstruction::Initialize, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_set_rate(&self, rate_num: u64, rate_den: u64) -> Instruction {
+        let data = SetRateInstructionData::new(rate_num, rate_den);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new(self.desk, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(SwapDeskInstruction::SetRate, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Builds a `Withdraw` of all the tokens of the vaults by `admin` to
+    /// its token accounts.
+    fn instruction_withdraw(
+        &self,
+        admin: &Pubkey,
+        admin_ata_a: &Pubkey,
+        admin_ata_b: &Pubkey,
+    ) -> Instruction {
+        let data = AmountsInstructionData::new(0, 1_000);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*admin_ata_a, false),
+            AccountMeta::new(*admin_ata_b, false),
+            AccountMeta::new_readonly(self.desk, false),
+            AccountMeta::new(self.vault_a, false),
+            AccountMeta::new(self.vault_b, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(SwapDeskInstruction::Withdraw, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_swap(&self, amount_in: u64) -> Instruction {
+        let data = SwapInstructionData::new(amount_in);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.user, true),
+            AccountMeta::new(self.user_ata_a, false),
+            AccountMeta::new(self.user_ata_b, false),
+            AccountMeta::new_readonly(self.desk, false),
+            AccountMeta::new(self.vault_a, false),
+            AccountMeta::new(self.vault_b, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(SwapDeskInstruction::Swap, &data),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+/// A withdrawal without the signature of the admin, here to the token
+/// accounts of the user.
+#[test]
+fn test_missing_admin_signature() {
+    let setup = Setup::new();
+    let mut instruction =
+        setup.instruction_withdraw(&setup.admin, &setup.user_ata_a, &setup.user_ata_b);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+#[test]
+fn test_missing_user_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_swap(100);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// An attacker withdraws the vaults of the desk to their own token
+/// accounts.
+#[test]
+fn test_withdraw_by_attacker() {
+    let setup = Setup::new();
+    let attacker = Pubkey::new_unique();
+    let attacker_ata_a = Pubkey::new_unique();
+    let attacker_ata_b = Pubkey::new_unique();
+    let instruction = setup.instruction_withdraw(&attacker, &attacker_ata_a, &attacker_ata_b);
+    let (mint_a, mint_b) = (setup.mint_a, setup.mint_b);
+    let ata_a = token_account(&setup.mollusk, &mint_a, &attacker, 0);
+    let ata_b = token_account(&setup.mollusk, &mint_b, &attacker, 0);
+    let setup = setup
+        .with(
+            attacker,
+            Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+        )
+        .with(attacker_ata_a, ata_a)
+        .with(attacker_ata_b, ata_b);
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
+
+/// A desk with the layout of the program, but owned by another one, e.g.
+/// with a rate paying out more than the real one.
+#[test]
+fn test_spoofed_desk() {
+    let setup = Setup::new();
+    let desk = setup.desk;
+    let mut desk_account = setup.account(&desk);
+    desk_account.owner = Pubkey::new_unique();
+    let setup = setup.with(desk, desk_account);
+    setup.expect_err(&setup.instruction_swap(100), ProgramError::IllegalOwner);
+}
+
+/// The B tokens of a swap come from a token account other than the vault of
+/// the desk.
+#[test]
+fn test_wrong_vault() {
+    let mut setup = Setup::new();
+    let other_vault = Pubkey::new_unique();
+    let other_vault_account = token_account(&setup.mollusk, &setup.mint_b, &setup.desk, 1_000);
+    setup.vault_b = other_vault;
+    let setup = setup.with(other_vault, other_vault_account);
+    setup.expect_err(
+        &setup.instruction_swap(100),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A rate with a zero denominator, which would make every quote divide by
+/// zero.
+#[test]
+fn test_zero_denominator() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_set_rate(1, 0),
+        ProgramError::Custom(Namespace::SwapDesk.code(SwapDeskError::InvalidRate as u32)),
+    );
+}
+
+/// A desk created with a vault of someone else, who could move the tokens
+/// swapped into it.
+#[test]
+fn test_foreign_vault_on_initialize() {
+    let setup = Setup::new();
+    let (desk, vault_a, mint_a) = (setup.desk, setup.vault_a, setup.mint_a);
+    let foreign_vault = token_account(&setup.mollusk, &mint_a, &Pubkey::new_unique(), 0);
+    let setup = setup
+        .with(desk, Account::default())
+        .with(vault_a, foreign_vault);
+    setup.expect_err(&setup.instruction_initialize(), ProgramError::IllegalOwner);
+}
+
+/// A desk at an address other than its PDA, which it couldn't sign for.
+#[test]
+fn test_wrong_desk_seeds() {
+    let mut setup = Setup::new();
+    let desk = Pubkey::new_unique();
+    let vault_a = token_account(&setup.mollusk, &setup.mint_a, &desk, 0);
+    let vault_b = token_account(&setup.mollusk, &setup.mint_b, &desk, 0);
+    setup.desk = desk;
+    let (vault_a_key, vault_b_key) = (setup.vault_a, setup.vault_b);
+    let setup = setup
+        .with(desk, Account::default())
+        .with(vault_a_key, vault_a)
+        .with(vault_b_key, vault_b);
+    setup.expect_err(&setup.instruction_initialize(), ProgramError::InvalidSeeds);
+}
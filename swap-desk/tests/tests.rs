@@ -0,0 +1,430 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+use swap_desk::{
+    AmountsInstructionData, Desk, InitializeInstructionData, SetRateInstructionData, SwapDeskError,
+    SwapDeskInstruction, SwapInstructionData, DESK_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(swap_desk::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: SwapDeskInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<SwapDeskInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/swap_desk");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// A desk with an admin and a user holding both tokens.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    admin_ata_a: Pubkey,
+    admin_ata_b: Pubkey,
+    user: Pubkey,
+    user_ata_a: Pubkey,
+    user_ata_b: Pubkey,
+    desk: Pubkey,
+    desk_bump: u8,
+    vault_a: Pubkey,
+    vault_b: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let admin_ata_a = Pubkey::new_unique();
+        let admin_ata_b = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let user_ata_a = Pubkey::new_unique();
+        let user_ata_b = Pubkey::new_unique();
+        let (desk, desk_bump) = Pubkey::find_program_address(
+            &[
+                DESK_SEED.as_bytes(),
+                admin.as_ref(),
+                mint_a.as_ref(),
+                mint_b.as_ref(),
+            ],
+            &ID,
+        );
+        let vault_a = Pubkey::new_unique();
+        let vault_b = Pubkey::new_unique();
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (mint_a, mint_account(&mollusk, 2_000_000)),
+            (mint_b, mint_account(&mollusk, 2_000_000)),
+            (admin, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (
+                admin_ata_a,
+                token_account(&mollusk, &mint_a, &admin, 1_000_000),
+            ),
+            (
+                admin_ata_b,
+                token_account(&mollusk, &mint_b, &admin, 1_000_000),
+            ),
+            (user, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (
+                user_ata_a,
+                token_account(&mollusk, &mint_a, &user, 1_000_000),
+            ),
+            (user_ata_b, token_account(&mollusk, &mint_b, &user, 0)),
+            (desk, Account::default()),
+            (vault_a, token_account(&mollusk, &mint_a, &desk, 0)),
+            (vault_b, token_account(&mollusk, &mint_b, &desk, 0)),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            admin_ata_a,
+            admin_ata_b,
+            user,
+            user_ata_a,
+            user_ata_b,
+            desk,
+            desk_bump,
+            vault_a,
+            vault_b,
+            accounts,
+        }
+    }
+
+    fn instruction_initialize(&self, rate_num: u64, rate_den: u64) -> Instruction {
+        let data = InitializeInstructionData::new(rate_num, rate_den, self.desk_bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.admin, true),
+            AccountMeta::new(self.desk, false),
+            AccountMeta::new_readonly(self.vault_a, false),
+            AccountMeta::new_readonly(self.vault_b, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(SwapDeskInstruction::Initialize, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_set_rate(&self, admin: &Pubkey, rate_num: u64, rate_den: u64) -> Instruction {
+        let data = SetRateInstructionData::new(rate_num, rate_den);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(self.desk, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(SwapDeskInstruction::SetRate, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Builds a `Fund` or `Withdraw` instruction.
+    fn instruction_amounts(
+        &self,
+        discriminator: SwapDeskInstruction,
+        amount_a: u64,
+        amount_b: u64,
+    ) -> Instruction {
+        let data = AmountsInstructionData::new(amount_a, amount_b);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.admin, true),
+            AccountMeta::new(self.admin_ata_a, false),
+            AccountMeta::new(self.admin_ata_b, false),
+            AccountMeta::new_readonly(self.desk, false),
+            AccountMeta::new(self.vault_a, false),
+            AccountMeta::new(self.vault_b, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(ID, &instruction_data(discriminator, &data), ix_accounts)
+    }
+
+    fn instruction_swap(&self, amount_in: u64) -> Instruction {
+        let data = SwapInstructionData::new(amount_in);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(self.user, true),
+            AccountMeta::new(self.user_ata_a, false),
+            AccountMeta::new(self.user_ata_b, false),
+            AccountMeta::new_readonly(self.desk, false),
+            AccountMeta::new(self.vault_a, false),
+            AccountMeta::new(self.vault_b, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(SwapDeskInstruction::Swap, &data),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_swap_desk_initialize() {
+    let setup = Setup::new();
+
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize(3, 0),
+        &setup.accounts,
+        &[Check::err(ProgramError::Custom(
+            Namespace::SwapDesk.code(SwapDeskError::InvalidRate as u32),
+        ))],
+    );
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize(3, 2),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.desk)
+                .owner(&ID)
+                .space(Desk::LEN)
+                .data_slice(0, setup.admin.as_ref())
+                .data_slice(96, setup.vault_a.as_ref())
+                .data_slice(128, setup.vault_b.as_ref())
+                .data_slice(160, &3u64.to_le_bytes())
+                .data_slice(168, &2u64.to_le_bytes())
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_swap_desk_swap() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (&setup.instruction_initialize(3, 2), &[Check::success()]),
+            (
+                &setup.instruction_amounts(SwapDeskInstruction::Fund, 0, 1_000_000),
+                &[Check::success()],
+            ),
+            (
+                // 3/2 B per A, rounded down.
+                &setup.instruction_swap(101),
+                &[
+                    Check::success(),
+                    Check::account(&setup.user_ata_a)
+                        .data_slice(64, &(1_000_000u64 - 101).to_le_bytes())
+                        .build(),
+                    Check::account(&setup.user_ata_b)
+                        .data_slice(64, &151u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.vault_a)
+                        .data_slice(64, &101u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.vault_b)
+                        .data_slice(64, &(1_000_000u64 - 151).to_le_bytes())
+                        .build(),
+                ],
+            ),
+            (
+                &setup.instruction_swap(1),
+                &[
+                    Check::success(),
+                    Check::account(&setup.user_ata_b)
+                        .data_slice(64, &152u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_swap_desk_swap_insufficient_liquidity() {
+    let setup = Setup::new();
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (&setup.instruction_initialize(2, 1), &[Check::success()]),
+            (
+                &setup.instruction_amounts(SwapDeskInstruction::Fund, 0, 100),
+                &[Check::success()],
+            ),
+            (&setup.instruction_swap(50), &[Check::success()]),
+            (
+                &setup.instruction_swap(1),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::SwapDesk.code(SwapDeskError::InsufficientLiquidity as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_swap_desk_swap_zero_output() {
+    let setup = Setup::new();
+
+    setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (&setup.instruction_initialize(1, 10), &[Check::success()]),
+            (
+                &setup.instruction_amounts(SwapDeskInstruction::Fund, 0, 100),
+                &[Check::success()],
+            ),
+            (
+                &setup.instruction_swap(9),
+                &[Check::err(ProgramError::Custom(
+                    Namespace::SwapDesk.code(SwapDeskError::ZeroOutput as u32),
+                ))],
+            ),
+        ],
+        &setup.accounts,
+    );
+}
+
+#[test]
+fn test_swap_desk_set_rate() {
+    let setup = Setup::new();
+
+    let accounts = setup
+        .mollusk
+        .process_and_validate_instruction(
+            &setup.instruction_initialize(3, 2),
+            &setup.accounts,
+            &[Check::success()],
+        )
+        .resulting_accounts;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_set_rate(&setup.user, 100, 1),
+        &accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_set_rate(&setup.admin, 1, 4),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.desk)
+                .data_slice(160, &1u64.to_le_bytes())
+                .data_slice(168, &4u64.to_le_bytes())
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_swap_desk_withdraw() {
+    let setup = Setup::new();
+
+    let res = setup.mollusk.process_and_validate_instruction_chain(
+        &[
+            (&setup.instruction_initialize(3, 2), &[Check::success()]),
+            (
+                &setup.instruction_amounts(SwapDeskInstruction::Fund, 0, 1_000),
+                &[Check::success()],
+            ),
+            (&setup.instruction_swap(100), &[Check::success()]),
+            (
+                &setup.instruction_amounts(SwapDeskInstruction::Withdraw, 100, 850),
+                &[
+                    Check::success(),
+                    Check::account(&setup.admin_ata_a)
+                        .data_slice(64, &1_000_100u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.admin_ata_b)
+                        .data_slice(64, &999_850u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.vault_a)
+                        .data_slice(64, &0u64.to_le_bytes())
+                        .build(),
+                    Check::account(&setup.vault_b)
+                        .data_slice(64, &0u64.to_le_bytes())
+                        .build(),
+                ],
+            ),
+        ],
+        &setup.accounts,
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
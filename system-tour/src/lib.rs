@@ -0,0 +1,376 @@
+//! Tour of the less common system program instructions.
+//!
+//! * `Allocate` and `Assign` turn an address which already holds lamports
+//!   into a program account. `CreateAccount` fails for such addresses, which
+//!   anyone can cause by sending lamports to a PDA before it's created.
+//! * `CreateAccountWithSeed` and `TransferWithSeed` work with addresses
+//!   derived from a base address, a seed and an owner. Here the base is a
+//!   PDA, so the program signs for it.
+
+#![no_std]
+
+use core::str;
+
+use common::{checks::require_signer, info, keys_eq};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::{
+    Allocate, Assign, CreateAccountWithSeed, Transfer, TransferWithSeed,
+};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("7HJukbbzda2pK5dXEbvo4iUhrbB59Y5mXhvScB1Y9x8M");
+
+pub const ACCOUNT_SEED: &str = "account";
+pub const BASE_SEED: &str = "base";
+
+/// Maximum length of a seed of an address derived with
+/// `Pubkey::create_with_seed`.
+pub const MAX_SEED_LEN: usize = 32;
+
+/// System tour program instruction discriminators.
+#[repr(u8)]
+pub enum SystemTourInstruction {
+    /// Funds a PDA for rent exemption and allocates its space.
+    Allocate,
+    /// Assigns an allocated PDA to the program.
+    Assign,
+    /// Creates a program account at an address derived from the base PDA.
+    CreateWithSeed,
+    /// Transfers lamports from an address derived from the base PDA.
+    TransferWithSeed,
+}
+
+impl TryFrom<&u8> for SystemTourInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Allocate),
+            1 => Ok(Self::Assign),
+            2 => Ok(Self::CreateWithSeed),
+            3 => Ok(Self::TransferWithSeed),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct AllocateInstructionData {
+    pub space: u64,
+    pub bump: u8,
+}
+
+impl AllocateInstructionData {
+    pub fn new(space: u64, bump: u8) -> Self {
+        Self { space, bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AssignInstructionData {
+    pub bump: u8,
+}
+
+impl AssignInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+/// A seed of up to [`MAX_SEED_LEN`] bytes, padded with zeros.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct AddressSeed {
+    pub seed: [u8; MAX_SEED_LEN],
+    pub len: u8,
+}
+
+impl AddressSeed {
+    pub fn new(seed: &str) -> Self {
+        let mut padded_seed = [0; MAX_SEED_LEN];
+        padded_seed[..seed.len()].copy_from_slice(seed.as_bytes());
+        Self {
+            seed: padded_seed,
+            len: seed.len() as u8,
+        }
+    }
+
+    /// Returns the seed, which has to be valid UTF-8.
+    pub fn as_str(&self) -> Result<&str, ProgramError> {
+        let seed = self
+            .seed
+            .get(..self.len as usize)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        str::from_utf8(seed).map_err(|_| ProgramError::InvalidInstructionData)
+    }
+}
+
+#[derive(InstructionData)]
+pub struct CreateWithSeedInstructionData {
+    pub space: u64,
+    pub seed: AddressSeed,
+    /// Bump of the base PDA.
+    pub bump: u8,
+}
+
+impl CreateWithSeedInstructionData {
+    pub fn new(space: u64, seed: &str, bump: u8) -> Self {
+        Self {
+            space,
+            seed: AddressSeed::new(seed),
+            bump,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct TransferWithSeedInstructionData {
+    pub lamports: u64,
+    pub seed: AddressSeed,
+    /// Bump of the base PDA.
+    pub bump: u8,
+}
+
+impl TransferWithSeedInstructionData {
+    pub fn new(lamports: u64, seed: &str, bump: u8) -> Self {
+        Self {
+            lamports,
+            seed: AddressSeed::new(seed),
+            bump,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = SystemTourInstruction::try_from(instruction)?;
+
+    match instruction {
+        SystemTourInstruction::Allocate => process_allocate(accounts, instruction_data),
+        SystemTourInstruction::Assign => process_assign(accounts, instruction_data),
+        SystemTourInstruction::CreateWithSeed => {
+            process_create_with_seed(accounts, instruction_data)
+        }
+        SystemTourInstruction::TransferWithSeed => {
+            process_transfer_with_seed(accounts, instruction_data)
+        }
+    }
+}
+
+/// Checks that `pda` is the PDA of `authority` with the given prefix.
+fn check_pda(pda: &AccountInfo, prefix: &str, authority: &AccountInfo, bump: u8) -> ProgramResult {
+    let expected = create_program_address(&[prefix.as_bytes(), authority.key(), &[bump]], &ID)?;
+    if !keys_eq(pda.key(), &expected) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Tops the account PDA of `payer` up to the rent-exempt minimum and
+/// allocates `space` bytes for it. The PDA may already hold lamports.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Payer
+///   1. `[WRITE]` Account PDA of the payer
+///   2. `[]` System program
+pub fn process_allocate(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [payer, account, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(payer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = AllocateInstructionData::from_bytes(instruction_data)?;
+
+    check_pda(account, ACCOUNT_SEED, payer, instruction_data.bump)?;
+
+    // Only pay what's missing.
+    let rent = Rent::get()?.minimum_balance(instruction_data.space as usize);
+    let missing = rent.saturating_sub(account.lamports());
+    if missing > 0 {
+        Transfer {
+            from: payer,
+            to: account,
+            lamports: missing,
+        }
+        .invoke()?;
+    }
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(ACCOUNT_SEED.as_bytes()),
+        Seed::from(payer.key()),
+        Seed::from(&bump),
+    ];
+    Allocate {
+        account,
+        space: instruction_data.space,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Allocated {} bytes", instruction_data.space);
+
+    Ok(())
+}
+
+/// Assigns the account PDA of `payer` to the program.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Payer
+///   1. `[WRITE]` Account PDA of the payer
+///   2. `[]` System program
+pub fn process_assign(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [payer, account, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(payer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = AssignInstructionData::from_bytes(instruction_data)?;
+
+    check_pda(account, ACCOUNT_SEED, payer, instruction_data.bump)?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(ACCOUNT_SEED.as_bytes()),
+        Seed::from(payer.key()),
+        Seed::from(&bump),
+    ];
+    Assign {
+        account,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Assigned the account to the program");
+
+    Ok(())
+}
+
+/// Creates a program account at the address derived from the base PDA of
+/// `payer` and the seed.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Payer
+///   1. `[]` Base PDA of the payer
+///   2. `[WRITE]` Account derived from the base PDA, the seed and the program
+///   3. `[]` System program
+pub fn process_create_with_seed(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [payer, base, account, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(payer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = CreateWithSeedInstructionData::from_bytes(instruction_data)?;
+    let seed = instruction_data.seed.as_str()?;
+
+    check_pda(base, BASE_SEED, payer, instruction_data.bump)?;
+
+    // The system program checks the derived address.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(BASE_SEED.as_bytes()),
+        Seed::from(payer.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccountWithSeed {
+        from: payer,
+        to: account,
+        base: Some(base),
+        seed,
+        lamports: Rent::get()?.minimum_balance(instruction_data.space as usize),
+        space: instruction_data.space,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Created an account with seed {}", seed);
+
+    Ok(())
+}
+
+/// Transfers lamports from the system account at the address derived from
+/// the base PDA of `authority` and the seed.
+///
+/// ### Accounts:
+///   0. `[SIGNER]` Authority
+///   1. `[]` Base PDA of the authority
+///   2. `[WRITE]` Account derived from the base PDA, the seed and the system
+///      program
+///   3. `[WRITE]` Recipient
+///   4. `[]` System program
+pub fn process_transfer_with_seed(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, base, from, to, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = TransferWithSeedInstructionData::from_bytes(instruction_data)?;
+    let seed = instruction_data.seed.as_str()?;
+
+    check_pda(base, BASE_SEED, authority, instruction_data.bump)?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(BASE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    TransferWithSeed {
+        from,
+        base,
+        to,
+        lamports: instruction_data.lamports,
+        seed,
+        owner: &pinocchio_system::ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!(
+        "Transferred {} lamports from seed {}",
+        instruction_data.lamports, seed
+    );
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+//! Attacks on the system tour program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use system_tour::{
+    AllocateInstructionData, CreateWithSeedInstructionData, SystemTourInstruction,
+    TransferWithSeedInstructionData, ACCOUNT_SEED, BASE_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(system_tour::ID);
+
+/// Seed of the addresses derived from the base PDA of the authority.
+const SEED: &str = "savings";
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: SystemTourInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<SystemTourInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the PDA of `authority` with `prefix` and its bump.
+fn pda(prefix: &str, authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[prefix.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Accounts of an authority with a funded system account derived from their
+/// base PDA, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    base: Pubkey,
+    savings: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/system_tour");
+
+        let authority = Pubkey::new_unique();
+        let (base, _) = pda(BASE_SEED, &authority);
+        let system_program = keyed_account_for_system_program().0;
+        let savings = Pubkey::create_with_seed(&base, SEED, &system_program).unwrap();
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (base, Account::default()),
+            (
+                savings,
+                Account::new(10 * LAMPORTS_PER_SOL, 0, &system_program),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            base,
+            savings,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let (_, bump) = pda(BASE_SEED, &setup.authority);
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.authority, false),
+        AccountMeta::new_readonly(setup.base, false),
+        AccountMeta::new(setup.savings, false),
+        AccountMeta::new(setup.attacker, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            SystemTourInstruction::TransferWithSeed,
+            &TransferWithSeedInstructionData::new(LAMPORTS_PER_SOL, SEED, bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker allocates the account PDA of the authority before the
+/// authority does, with their own bump.
+#[test]
+fn test_allocate_account_of_other_payer() {
+    let setup = Setup::new();
+    let (account, _) = pda(ACCOUNT_SEED, &setup.authority);
+    let setup = setup.with(account, Account::default());
+    let (_, bump) = pda(ACCOUNT_SEED, &setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(account, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            SystemTourInstruction::Allocate,
+            &AllocateInstructionData::new(64, bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// The attacker creates an account derived from the base PDA of the
+/// authority, with their own bump, so the authority can't create it.
+#[test]
+fn test_create_with_base_of_other_payer() {
+    let setup = Setup::new();
+    let account = Pubkey::create_with_seed(&setup.base, SEED, &ID).unwrap();
+    let setup = setup.with(account, Account::default());
+    let (_, bump) = pda(BASE_SEED, &setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new_readonly(setup.base, false),
+        AccountMeta::new(account, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            SystemTourInstruction::CreateWithSeed,
+            &CreateWithSeedInstructionData::new(64, SEED, bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
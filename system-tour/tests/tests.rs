@@ -0,0 +1,285 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use system_tour::{
+    AllocateInstructionData, AssignInstructionData, CreateWithSeedInstructionData,
+    SystemTourInstruction, TransferWithSeedInstructionData, ACCOUNT_SEED, BASE_SEED, MAX_SEED_LEN,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(system_tour::ID);
+
+/// Offset of the seed length in the `CreateWithSeed` and `TransferWithSeed`
+/// instruction data, including the discriminator.
+const SEED_LEN_OFFSET: usize = 1 + 8 + MAX_SEED_LEN;
+
+const SPACE: u64 = 64;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: SystemTourInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<SystemTourInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    Mollusk::new(&ID, "target/deploy/system_tour")
+}
+
+fn instruction_pda(data: Vec<u8>, payer: &Pubkey, account: &Pubkey) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*account, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(ID, &data, ix_accounts)
+}
+
+#[test]
+fn test_system_tour_allocate_and_assign() {
+    let mollusk = mollusk();
+
+    let payer = Pubkey::new_unique();
+    let (account, bump) =
+        Pubkey::find_program_address(&[ACCOUNT_SEED.as_bytes(), payer.as_ref()], &ID);
+
+    // Someone sent lamports to the PDA before it was created, which would
+    // make `CreateAccount` fail.
+    let prefunded = 1_000_000;
+    let rent = mollusk.sysvars.rent.minimum_balance(SPACE as usize);
+
+    let allocate = instruction_pda(
+        instruction_data(
+            SystemTourInstruction::Allocate,
+            &AllocateInstructionData::new(SPACE, bump),
+        ),
+        &payer,
+        &account,
+    );
+    let assign = instruction_pda(
+        instruction_data(
+            SystemTourInstruction::Assign,
+            &AssignInstructionData::new(bump),
+        ),
+        &payer,
+        &account,
+    );
+
+    mollusk.process_and_validate_instruction_chain(
+        &[
+            (
+                &allocate,
+                &[
+                    Check::success(),
+                    Check::account(&payer)
+                        .lamports(LAMPORTS_PER_SOL - (rent - prefunded))
+                        .build(),
+                    Check::account(&account)
+                        .lamports(rent)
+                        .owner(&Pubkey::default())
+                        .space(SPACE as usize)
+                        .build(),
+                ],
+            ),
+            (
+                &assign,
+                &[
+                    Check::success(),
+                    Check::account(&account)
+                        .owner(&ID)
+                        .space(SPACE as usize)
+                        .build(),
+                ],
+            ),
+            // The space can't change anymore, `SystemError::AccountAlreadyInUse`.
+            (&allocate, &[Check::err(ProgramError::Custom(0))]),
+        ],
+        &[
+            (payer, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (account, Account::new(prefunded, 0, &Pubkey::default())),
+            keyed_account_for_system_program(),
+        ],
+    );
+}
+
+#[test]
+fn test_system_tour_assign_invalid_pda() {
+    let mollusk = mollusk();
+
+    let payer = Pubkey::new_unique();
+    let (account, _) = Pubkey::find_program_address(&[BASE_SEED.as_bytes(), payer.as_ref()], &ID);
+    let (_, bump) = Pubkey::find_program_address(&[ACCOUNT_SEED.as_bytes(), payer.as_ref()], &ID);
+
+    mollusk.process_and_validate_instruction(
+        &instruction_pda(
+            instruction_data(
+                SystemTourInstruction::Assign,
+                &AssignInstructionData::new(bump),
+            ),
+            &payer,
+            &account,
+        ),
+        &[
+            (payer, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (account, Account::default()),
+            keyed_account_for_system_program(),
+        ],
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
+
+fn instruction_create_with_seed(
+    payer: &Pubkey,
+    base: &Pubkey,
+    account: &Pubkey,
+    data: &CreateWithSeedInstructionData,
+) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(*base, false),
+        AccountMeta::new(*account, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(SystemTourInstruction::CreateWithSeed, data),
+        ix_accounts,
+    )
+}
+
+#[test]
+fn test_system_tour_create_with_seed() {
+    let mollusk = mollusk();
+
+    let payer = Pubkey::new_unique();
+    let (base, bump) = Pubkey::find_program_address(&[BASE_SEED.as_bytes(), payer.as_ref()], &ID);
+    let seed = "vault-1";
+    let account = Pubkey::create_with_seed(&base, seed, &ID).unwrap();
+    let rent = mollusk.sysvars.rent.minimum_balance(SPACE as usize);
+
+    let accounts = [
+        (payer, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+        (base, Account::default()),
+        (account, Account::default()),
+        keyed_account_for_system_program(),
+    ];
+
+    let data = CreateWithSeedInstructionData::new(SPACE, seed, bump);
+    mollusk.process_and_validate_instruction(
+        &instruction_create_with_seed(&payer, &base, &account, &data),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&payer)
+                .lamports(LAMPORTS_PER_SOL - rent)
+                .build(),
+            Check::account(&account)
+                .lamports(rent)
+                .owner(&ID)
+                .space(SPACE as usize)
+                .build(),
+        ],
+    );
+
+    // The seed has to be valid UTF-8 and fit in the buffer.
+    let mut invalid_utf8 = instruction_create_with_seed(&payer, &base, &account, &data);
+    invalid_utf8.data[1 + 8] = 0xff;
+    let mut too_long = instruction_create_with_seed(&payer, &base, &account, &data);
+    too_long.data[SEED_LEN_OFFSET] = MAX_SEED_LEN as u8 + 1;
+    for instruction in [invalid_utf8, too_long] {
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[Check::err(ProgramError::InvalidInstructionData)],
+        );
+    }
+
+    // The system program rejects addresses derived from other seeds with
+    // `SystemError::AddressWithSeedMismatch`.
+    let data = CreateWithSeedInstructionData::new(SPACE, "vault-2", bump);
+    mollusk.process_and_validate_instruction(
+        &instruction_create_with_seed(&payer, &base, &account, &data),
+        &accounts,
+        &[Check::err(ProgramError::Custom(5))],
+    );
+}
+
+fn instruction_transfer_with_seed(
+    authority: &Pubkey,
+    base: &Pubkey,
+    from: &Pubkey,
+    to: &Pubkey,
+    data: &TransferWithSeedInstructionData,
+) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new_readonly(*base, false),
+        AccountMeta::new(*from, false),
+        AccountMeta::new(*to, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(SystemTourInstruction::TransferWithSeed, data),
+        ix_accounts,
+    )
+}
+
+#[test]
+fn test_system_tour_transfer_with_seed() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_unique();
+    let (base, bump) =
+        Pubkey::find_program_address(&[BASE_SEED.as_bytes(), authority.as_ref()], &ID);
+    let seed = "savings";
+    let system_program = keyed_account_for_system_program().0;
+    let from = Pubkey::create_with_seed(&base, seed, &system_program).unwrap();
+    let to = Pubkey::new_unique();
+    let amount = LAMPORTS_PER_SOL / 4;
+
+    let accounts = [
+        (authority, Account::default()),
+        (base, Account::default()),
+        (from, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+        (to, Account::default()),
+        keyed_account_for_system_program(),
+    ];
+
+    let data = TransferWithSeedInstructionData::new(amount, seed, bump);
+    mollusk.process_and_validate_instruction(
+        &instruction_transfer_with_seed(&authority, &base, &from, &to, &data),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&from)
+                .lamports(LAMPORTS_PER_SOL - amount)
+                .build(),
+            Check::account(&to).lamports(amount).build(),
+        ],
+    );
+
+    // Only the authority of the base PDA can move the funds.
+    let attacker = Pubkey::new_unique();
+    let mut accounts = accounts.to_vec();
+    accounts.push((attacker, Account::default()));
+    mollusk.process_and_validate_instruction(
+        &instruction_transfer_with_seed(&attacker, &base, &from, &attacker, &data),
+        &accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
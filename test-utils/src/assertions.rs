@@ -0,0 +1,49 @@
+//! Assertions on the accounts resulting from instructions.
+
+use common::AccountData;
+use mollusk_svm::result::InstructionResult;
+use solana_pubkey::Pubkey;
+use solana_sdk_ids::system_program;
+
+use crate::token_amount;
+
+/// Returns the state of the program account at `address`, checking that
+/// it's owned by the program of `T`.
+pub fn account_state<T: AccountData>(res: &InstructionResult, address: &Pubkey) -> T {
+    let account = res
+        .get_account(address)
+        .unwrap_or_else(|| panic!("no account {address}"));
+    assert_eq!(
+        account.owner,
+        Pubkey::new_from_array(T::OWNER),
+        "account {address} is not owned by the program"
+    );
+    T::read(&account.data).unwrap_or_else(|e| panic!("invalid state of {address}: {e:?}"))
+}
+
+/// Checks that the token account at `address` holds `amount` tokens.
+pub fn assert_token_balance(res: &InstructionResult, address: &Pubkey, amount: u64) {
+    let account = res
+        .get_account(address)
+        .unwrap_or_else(|| panic!("no account {address}"));
+    assert_eq!(
+        token_amount(account),
+        amount,
+        "unexpected balance of {address}"
+    );
+}
+
+/// Checks that the account at `address` was closed, which leaves it
+/// without lamports and data, owned by the system program.
+pub fn assert_closed(res: &InstructionResult, address: &Pubkey) {
+    let account = res
+        .get_account(address)
+        .unwrap_or_else(|| panic!("no account {address}"));
+    assert_eq!(account.lamports, 0, "account {address} is not closed");
+    assert!(account.data.is_empty(), "account {address} has data");
+    assert_eq!(
+        account.owner,
+        system_program::ID,
+        "account {address} isn't owned by the system program"
+    );
+}
@@ -0,0 +1,180 @@
+//! Compute-unit benchmarks of program instructions.
+//!
+//! [`Bencher`] runs instructions, records the compute units they consume
+//! and writes them into `compute_units.md` in the benchmark directory of the
+//! program. They're compared with the baseline committed in
+//! `compute_units.json` in the same directory, and the run fails if any
+//! instruction consumes more than the allowed percentage over it or has no
+//! baseline. The baseline is only written by running the benchmarks with
+//! `UPDATE_BENCHES=1`, after which the diff can be reviewed with git.
+//!
+//! Only hello-world, counter, escrow and default-account-state are benched
+//! so far. Other programs opt in with a `test_compute_units` test of their
+//! own.
+//!
+//! Every run also writes a machine-readable report of the program into
+//! `target/bench-report/<program>.json` and `<program>.csv`, with a record
+//! of the compute units of every instruction and the size of the binary of
+//! the program. `cargo xtask bench-report` collects the reports of all
+//! programs and compares them with the baseline in `bench_baseline.json` in
+//! the root of the repository, which `cargo xtask bench-report --update`
+//! writes to be committed.
+
+use std::{collections::BTreeMap, env, fmt::Write, fs, path::PathBuf};
+
+use mollusk_svm::{
+    result::{Check, InstructionResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+
+/// Default percentage by which instructions may exceed their baseline.
+pub const DEFAULT_THRESHOLD: u64 = 5;
+
+/// Directory of the machine-readable reports, relative to the crate of the
+/// program.
+pub const REPORT_DIR: &str = "target/bench-report";
+
+/// Records the compute units consumed by instructions of a program.
+pub struct Bencher<'a> {
+    mollusk: &'a Mollusk,
+    out_dir: PathBuf,
+    threshold: u64,
+    results: Vec<(&'static str, u64)>,
+}
+
+impl<'a> Bencher<'a> {
+    /// Creates a bencher writing its reports into `out_dir`.
+    pub fn new(mollusk: &'a Mollusk, out_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            mollusk,
+            out_dir: out_dir.into(),
+            threshold: DEFAULT_THRESHOLD,
+            results: Vec::new(),
+        }
+    }
+
+    /// Sets the percentage by which instructions may exceed their baseline.
+    pub fn threshold(mut self, threshold: u64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Runs `instruction`, which has to succeed, and records its compute
+    /// units under `name`.
+    ///
+    /// The result is returned, so the resulting accounts can be passed to
+    /// the next benchmarked instruction.
+    pub fn bench(
+        &mut self,
+        name: &'static str,
+        instruction: &Instruction,
+        accounts: &[(Pubkey, Account)],
+    ) -> InstructionResult {
+        let res = self.mollusk.process_and_validate_instruction(
+            instruction,
+            accounts,
+            &[Check::success()],
+        );
+        self.results.push((name, res.compute_units_consumed));
+        res
+    }
+
+    /// Writes the reports and panics if any instruction regressed or has
+    /// no baseline.
+    ///
+    /// With `UPDATE_BENCHES` set, writes the baseline instead.
+    pub fn execute(self) {
+        let json_path = self.out_dir.join("compute_units.json");
+        let update = env::var_os("UPDATE_BENCHES").is_some();
+        let baseline: BTreeMap<String, u64> = match fs::read_to_string(&json_path) {
+            Ok(json) => serde_json::from_str(&json).unwrap(),
+            Err(_) if update => BTreeMap::new(),
+            Err(e) => panic!(
+                "failed to read the baseline {}, run with UPDATE_BENCHES=1 to create it: {e}",
+                json_path.display()
+            ),
+        };
+
+        let mut markdown =
+            String::from("| Instruction | Compute units | Baseline | Delta |\n|---|---|---|---|\n");
+        let mut regressions = Vec::new();
+        for (name, compute_units) in &self.results {
+            match baseline.get(*name) {
+                Some(&previous) => {
+                    let delta = *compute_units as i64 - previous as i64;
+                    writeln!(
+                        markdown,
+                        "| {name} | {compute_units} | {previous} | {delta:+} |"
+                    )
+                    .unwrap();
+                    if compute_units * 100 > previous * (100 + self.threshold) {
+                        regressions.push(format!("{name}: {previous} -> {compute_units}"));
+                    }
+                }
+                None => {
+                    writeln!(markdown, "| {name} | {compute_units} | - | - |").unwrap();
+                    regressions.push(format!("{name}: no baseline"));
+                }
+            }
+        }
+
+        fs::create_dir_all(&self.out_dir).unwrap();
+        fs::write(self.out_dir.join("compute_units.md"), markdown).unwrap();
+        self.write_report();
+        if update {
+            let results: BTreeMap<_, _> = self.results.into_iter().collect();
+            fs::write(json_path, serde_json::to_string_pretty(&results).unwrap()).unwrap();
+            return;
+        }
+        assert!(
+            regressions.is_empty(),
+            "compute units regressed by more than {}% or have no baseline, rerun with \
+             UPDATE_BENCHES=1 if expected:\n{}",
+            self.threshold,
+            regressions.join("\n")
+        );
+    }
+
+    /// Writes the results into the JSON and CSV reports of the program in
+    /// [`REPORT_DIR`].
+    ///
+    /// The tests run in the crate of the program, named after it, whose
+    /// binary is in `target/deploy`. The size is `null` in JSON and empty
+    /// in CSV if the binary isn't there.
+    fn write_report(&self) {
+        let crate_dir = env::current_dir().unwrap();
+        let program = crate_dir
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .replace('-', "_");
+        let binary_size = fs::metadata(crate_dir.join(format!("target/deploy/{program}.so")))
+            .ok()
+            .map(|metadata| metadata.len());
+
+        let mut csv = String::from("program,instruction,compute_units,binary_size\n");
+        let mut records = Vec::new();
+        for (name, compute_units) in &self.results {
+            let size = binary_size.map(|size| size.to_string()).unwrap_or_default();
+            writeln!(csv, "{program},{name},{compute_units},{size}").unwrap();
+            records.push(serde_json::json!({
+                "program": program,
+                "instruction": name,
+                "compute_units": compute_units,
+                "binary_size": binary_size,
+            }));
+        }
+
+        let report_dir = crate_dir.join(REPORT_DIR);
+        fs::create_dir_all(&report_dir).unwrap();
+        fs::write(
+            report_dir.join(format!("{program}.json")),
+            serde_json::to_string_pretty(&records).unwrap(),
+        )
+        .unwrap();
+        fs::write(report_dir.join(format!("{program}.csv")), csv).unwrap();
+    }
+}
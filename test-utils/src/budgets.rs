@@ -0,0 +1,33 @@
+//! Compute-unit budgets of the program instructions.
+//!
+//! The tests fail as soon as an instruction consumes more than its budget,
+//! unlike [`crate::bench`], which only compares with the previous run.
+//! Raise a budget here when an instruction legitimately gets more
+//! expensive.
+
+use mollusk_svm::result::InstructionResult;
+
+pub mod counter {
+    pub const CREATE: u64 = 5_000;
+    pub const INCREMENT: u64 = 1_000;
+    pub const DECREMENT: u64 = 1_000;
+    pub const DELETE: u64 = 1_000;
+}
+
+pub mod escrow {
+    /// Includes the CPIs creating the escrow and transferring the tokens.
+    pub const INITIALIZE: u64 = 15_000;
+    /// Includes the CPI transferring the tokens.
+    pub const EXCHANGE: u64 = 12_000;
+    /// Includes the CPI transferring the tokens.
+    pub const CANCEL: u64 = 12_000;
+}
+
+/// Checks that the instruction stayed within `budget` compute units.
+pub fn assert_within_budget(res: &InstructionResult, budget: u64) {
+    assert!(
+        res.compute_units_consumed <= budget,
+        "consumed {} compute units, over the budget of {budget}",
+        res.compute_units_consumed
+    );
+}
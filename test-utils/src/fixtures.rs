@@ -0,0 +1,88 @@
+//! Replay of Mollusk fixtures as golden files.
+//!
+//! Mollusk dumps a fixture of every instruction it processes into the
+//! directory set in `EJECT_FUZZ_FIXTURES`. A fixture holds the input
+//! accounts and the expected result, so dumping the fixtures of the test
+//! scenarios with
+//!
+//! ```sh
+//! EJECT_FUZZ_FIXTURES=fixtures cargo test
+//! ```
+//!
+//! committing them and replaying them with [`replay_fixtures`] detects any
+//! change of the resulting accounts after a refactor.
+//!
+//! The fixtures only stay the same across runs if the addresses in them do,
+//! which `Pubkey::new_unique` doesn't guarantee, as it depends on the order
+//! the tests run in. Tests use the named keypairs and addresses derived
+//! from fixed seeds, like [`alice`] and [`mint_a`], instead.
+
+use std::{fs, path::Path};
+
+use mollusk_svm::Mollusk;
+use mollusk_svm_fuzz_fixture::Fixture;
+use mollusk_svm_fuzz_fs::FsHandler;
+use solana_keypair::Keypair;
+use solana_pubkey::Pubkey;
+use solana_signer::Signer;
+
+/// Returns the keypair named `name`, whose secret key is the name padded
+/// with zeros.
+///
+/// Panics if the name is longer than 32 bytes.
+pub fn keypair(name: &str) -> Keypair {
+    let mut secret_key = [0; 32];
+    secret_key[..name.len()].copy_from_slice(name.as_bytes());
+    Keypair::new_from_array(secret_key)
+}
+
+/// Returns the address of the keypair named `name`.
+pub fn address(name: &str) -> Pubkey {
+    keypair(name).pubkey()
+}
+
+/// Returns the keypair of Alice, e.g. the sender of an escrow.
+pub fn alice() -> Keypair {
+    keypair("alice")
+}
+
+/// Returns the keypair of Bob, e.g. the receiver of an escrow.
+pub fn bob() -> Keypair {
+    keypair("bob")
+}
+
+/// Returns the address of the first mint.
+pub fn mint_a() -> Pubkey {
+    address("mint_a")
+}
+
+/// Returns the address of the second mint.
+pub fn mint_b() -> Pubkey {
+    address("mint_b")
+}
+
+/// Replays all `.fix` fixtures in `dir`, panicking on the first one whose
+/// result differs. Returns the number of replayed fixtures.
+///
+/// Panics if `dir` can't be read, so that missing fixtures don't go
+/// unnoticed.
+pub fn replay_fixtures(mollusk: &Mollusk, dir: impl AsRef<Path>) -> usize {
+    let dir = dir.as_ref();
+    let entries = fs::read_dir(dir).unwrap_or_else(|e| {
+        panic!(
+            "failed to read the fixtures in {}, dump them with EJECT_FUZZ_FIXTURES: {e}",
+            dir.display()
+        )
+    });
+    let mut paths: Vec<_> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "fix"))
+        .collect();
+    paths.sort();
+
+    for path in &paths {
+        let fixture = Fixture::load_from_blob_file(path.to_str().unwrap());
+        mollusk.process_and_validate_fixture(&fixture);
+    }
+    paths.len()
+}
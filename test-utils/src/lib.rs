@@ -0,0 +1,166 @@
+//! Account fixtures shared by the Mollusk tests of the example programs.
+//!
+//! Accounts are rent-exempt under the default rent, which Mollusk uses
+//! unless a test changes its sysvars.
+//!
+//! [`assertions`] check the accounts resulting from instructions, [`bench`]
+//! measures the compute units consumed by instructions, [`budgets`] bounds
+//! them and [`fixtures`] replays golden files of their results, with
+//! deterministic keypairs keeping them stable. [`logs`] snapshots the logs
+//! of the programs and [`programs`] loads them into Mollusk once for all
+//! tests. [`token_program_tests`] runs scenarios against both token
+//! programs, which [`scenario`] composes declaratively.
+
+use bytemuck::Pod;
+use common::AccountData;
+use mollusk_svm::program::create_program_account_loader_v3;
+use solana_account::Account;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use solana_rent::Rent;
+use solana_sdk_ids::system_program;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint},
+};
+
+pub mod assertions;
+pub mod bench;
+pub mod budgets;
+pub mod fixtures;
+pub mod logs;
+pub mod programs;
+pub mod scenario;
+
+/// Returns a system account holding `lamports`.
+pub fn funded_account(lamports: u64) -> Account {
+    Account::new(lamports, 0, &system_program::ID)
+}
+
+/// Returns an account of the program of `T` holding `state`, preceded by
+/// its prefix.
+pub fn state_account<T: AccountData + Pod>(state: &T) -> Account {
+    let mut account = Account::new(
+        Rent::default().minimum_balance(T::LEN),
+        T::LEN,
+        &Pubkey::new_from_array(T::OWNER),
+    );
+    let (prefix, data) = account.data.split_at_mut(T::PREFIX.len());
+    prefix.copy_from_slice(T::PREFIX);
+    data.copy_from_slice(state.as_bytes());
+    account
+}
+
+/// Returns an initialized mint without authorities.
+pub fn mint_account(decimals: u8, supply: u64) -> Account {
+    packed_account(
+        &spl_token::ID,
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+    )
+}
+
+/// Returns an initialized token account of `owner` holding `amount` tokens
+/// of `mint`.
+pub fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    token_account_with_program(&spl_token::ID, mint, owner, amount)
+}
+
+/// Returns an initialized token account of `token_program`, either the SPL
+/// Token or the Token-2022 program, of `owner` holding `amount` tokens of
+/// `mint`. Token-2022 accounts without extensions have the same layout.
+pub fn token_account_with_program(
+    token_program: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) -> Account {
+    packed_account(
+        token_program,
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+    )
+}
+
+/// Returns a token account of `token_program` like
+/// [`token_account_with_program`], whose `delegate` may transfer
+/// `delegated_amount` of its tokens.
+pub fn delegated_token_account(
+    token_program: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+    delegate: &Pubkey,
+    delegated_amount: u64,
+) -> Account {
+    packed_account(
+        token_program,
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::Some(*delegate),
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount,
+            close_authority: COption::None,
+        },
+    )
+}
+
+/// Returns the amount of tokens held by a token account.
+pub fn token_amount(account: &Account) -> u64 {
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+/// Returns the supply of a mint.
+pub fn mint_supply(account: &Account) -> u64 {
+    Mint::unpack(&account.data).unwrap().supply
+}
+
+/// Returns the delegate of a token account and the amount it may still
+/// transfer, or `None` if it has no delegate.
+pub fn token_delegate(account: &Account) -> Option<(Pubkey, u64)> {
+    let account = TokenAccount::unpack(&account.data).unwrap();
+    match account.delegate {
+        COption::Some(delegate) => Some((delegate, account.delegated_amount)),
+        COption::None => None,
+    }
+}
+
+/// Returns the address and the program account of the token program.
+pub fn keyed_token_program() -> (Pubkey, Account) {
+    keyed_token_program_with_id(&spl_token::ID)
+}
+
+/// Returns the address and the program account of `token_program`.
+pub fn keyed_token_program_with_id(token_program: &Pubkey) -> (Pubkey, Account) {
+    (
+        *token_program,
+        create_program_account_loader_v3(token_program),
+    )
+}
+
+/// Returns an account of `token_program` holding `state`.
+fn packed_account<T: Pack>(token_program: &Pubkey, state: T) -> Account {
+    let mut account = Account::new(
+        Rent::default().minimum_balance(T::LEN),
+        T::LEN,
+        token_program,
+    );
+    T::pack(state, &mut account.data).unwrap();
+    account
+}
@@ -0,0 +1,90 @@
+//! Snapshots of the logs of programs.
+//!
+//! [`LogRecorder`] captures the logs of the instructions processed by
+//! Mollusk and [`assert_logs_snapshot`] compares them with the snapshot
+//! committed in the `snapshots` directory of the program, so that a change
+//! of the messages logged with `pinocchio_log` doesn't go unnoticed.
+//!
+//! A missing or changed snapshot fails the test checking it. Snapshots are
+//! only written by running the tests with `UPDATE_SNAPSHOTS=1`, after which
+//! the diff can be reviewed and committed with git.
+
+use std::{cell::RefCell, env, fs, path::Path, rc::Rc};
+
+use mollusk_svm::Mollusk;
+use solana_log_collector::LogCollector;
+
+/// Placeholder of the redacted compute units.
+const REDACTED_COMPUTE_UNITS: &str = "[compute units]";
+
+/// Records the logs of the instructions processed by a Mollusk instance.
+pub struct LogRecorder(Rc<RefCell<LogCollector>>);
+
+impl LogRecorder {
+    /// Creates a recorder of the logs of `mollusk`.
+    pub fn new(mollusk: &mut Mollusk) -> Self {
+        let collector = LogCollector::new_ref();
+        mollusk.logger = Some(collector.clone());
+        Self(collector)
+    }
+
+    /// Returns the logs recorded since the last call.
+    pub fn take(&self) -> Vec<String> {
+        self.0.replace(LogCollector::default()).into_messages()
+    }
+}
+
+/// Replaces the fields of `log` which change without any change of the
+/// program's messages, i.e. the consumed compute units.
+///
+/// `redactions` are the other variable values with their placeholders, e.g.
+/// addresses generated by the tests.
+pub fn redact(log: &str, redactions: &[(&str, &str)]) -> String {
+    let mut log = match log.split_once(" consumed ") {
+        Some((program, consumed))
+            if log.starts_with("Program ") && consumed.ends_with(" compute units") =>
+        {
+            format!("{program} consumed {REDACTED_COMPUTE_UNITS}")
+        }
+        _ => log.to_owned(),
+    };
+    for (value, placeholder) in redactions {
+        log = log.replace(value, placeholder);
+    }
+    log
+}
+
+/// Checks that `logs`, once redacted, match the snapshot `name` in `dir`.
+///
+/// With `UPDATE_SNAPSHOTS` set, writes the snapshot instead.
+pub fn assert_logs_snapshot(
+    dir: impl AsRef<Path>,
+    name: &str,
+    logs: &[String],
+    redactions: &[(&str, &str)],
+) {
+    let mut snapshot = String::new();
+    for log in logs {
+        snapshot.push_str(&redact(log, redactions));
+        snapshot.push('\n');
+    }
+
+    let path = dir.as_ref().join(format!("{name}.log"));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(dir.as_ref()).unwrap();
+        fs::write(&path, snapshot).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read the snapshot {}, run with UPDATE_SNAPSHOTS=1 to create it: {e}",
+            path.display()
+        )
+    });
+    assert_eq!(
+        snapshot,
+        expected,
+        "logs differ from the snapshot {}, rerun with UPDATE_SNAPSHOTS=1 to update it",
+        path.display()
+    );
+}
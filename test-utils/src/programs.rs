@@ -0,0 +1,124 @@
+//! A Mollusk with the example programs and the third-party programs they
+//! call.
+//!
+//! Creating a Mollusk for every test reads the binaries of its programs
+//! from disk again. [`mollusk`] reads them once per test binary and loads
+//! every new instance from memory. Mollusk keeps its program cache in
+//! `Rc`s, so an instance can't be shared across the threads running the
+//! tests, but creating one from the cached binaries is cheap.
+//!
+//! The example programs are loaded from their `target/deploy` and the
+//! third-party programs from the `third-party` directory of any program
+//! that fetched them, so `cargo xtask build` and `cargo xtask fetch` have
+//! to run first.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use mollusk_svm::{program::loader_keys::LOADER_V3, Mollusk};
+use solana_pubkey::{pubkey, Pubkey};
+
+/// Address of the SPL Token program.
+pub const TOKEN_PROGRAM_ID: Pubkey = spl_token::ID;
+/// Address of the Token-2022 program.
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Generates a test of every scenario, a function taking the address of a
+/// token program, against the SPL Token program in the module `token` and
+/// against the Token-2022 program in the module `token_2022`, so a failure
+/// names the program it happened with.
+#[macro_export]
+macro_rules! token_program_tests {
+    ($($scenario:ident),* $(,)?) => {
+        mod token {
+            $(
+                #[test]
+                fn $scenario() {
+                    super::$scenario(&$crate::programs::TOKEN_PROGRAM_ID);
+                }
+            )*
+        }
+
+        mod token_2022 {
+            $(
+                #[test]
+                fn $scenario() {
+                    super::$scenario(&$crate::programs::TOKEN_2022_PROGRAM_ID);
+                }
+            )*
+        }
+    };
+}
+
+/// Example programs loaded by [`mollusk`], with the paths of their binaries
+/// relative to the root of the repository.
+const PROGRAMS: &[(Pubkey, &str)] = &[
+    (
+        pubkey!("9YxC88EDFbs4a2ypUmKy8HPUFdg1FTnwnZm7358J3w9u"),
+        "counter/target/deploy/counter.so",
+    ),
+    (
+        pubkey!("AMeUviQdjAPsvfWwRfboCLrN7t2fjSxqs4eMZguezpQr"),
+        "escrow/target/deploy/escrow.so",
+    ),
+];
+
+/// Third-party programs loaded by [`mollusk`], with the names of their
+/// binaries.
+const THIRD_PARTY_PROGRAMS: &[(Pubkey, &str)] = &[
+    (TOKEN_PROGRAM_ID, "spl_token.so"),
+    (TOKEN_2022_PROGRAM_ID, "spl_token_2022.so"),
+];
+
+/// Binaries of all programs, read by the first call of [`mollusk`].
+static BINARIES: OnceLock<Vec<(Pubkey, Vec<u8>)>> = OnceLock::new();
+
+/// Returns a Mollusk with the counter, escrow, SPL Token and Token-2022
+/// programs loaded.
+pub fn mollusk() -> Mollusk {
+    let binaries = BINARIES.get_or_init(|| {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let programs = PROGRAMS
+            .iter()
+            .map(|(program_id, path)| (*program_id, root.join(path)));
+        let third_party = THIRD_PARTY_PROGRAMS
+            .iter()
+            .map(|(program_id, name)| (*program_id, third_party_binary(root, name)));
+        programs
+            .chain(third_party)
+            .map(|(program_id, path)| {
+                let elf = fs::read(&path).unwrap_or_else(|_| {
+                    panic!(
+                        "{} is missing, run `cargo xtask build` and `cargo xtask fetch`",
+                        path.display()
+                    )
+                });
+                (program_id, elf)
+            })
+            .collect()
+    });
+
+    let mut mollusk = Mollusk::default();
+    for (program_id, elf) in binaries {
+        mollusk.add_program_with_elf_and_loader(program_id, elf, &LOADER_V3);
+    }
+    mollusk
+}
+
+/// Returns the path of the third-party binary `name` in the first crate
+/// which fetched it, or in the escrow if none did.
+fn third_party_binary(root: &Path, name: &str) -> PathBuf {
+    let mut crates: Vec<_> = fs::read_dir(root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    crates.sort();
+    crates
+        .into_iter()
+        .map(|krate| krate.join("third-party").join(name))
+        .find(|binary| binary.exists())
+        .unwrap_or_else(|| root.join("escrow/third-party").join(name))
+}
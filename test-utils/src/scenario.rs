@@ -0,0 +1,189 @@
+//! Declarative scenarios of instructions of several programs.
+//!
+//! A [`Scenario`] lists the accounts of a test, the chain of instructions
+//! run on them and the checks of the resulting state, and compiles them
+//! down to one Mollusk instruction chain:
+//!
+//! ```ignore
+//! Scenario::new()
+//!     .with_mint(&mint, 6, 1_000)
+//!     .with_funded(&alice, LAMPORTS_PER_SOL)
+//!     .with_ata(&alice, &mint, 1_000)
+//!     .with_ata(&bob, &mint, 0)
+//!     .then(transfer(&alice, &bob, &mint, 100))
+//!     .expect_balance(&alice, &mint, 900)
+//!     .expect_balance(&bob, &mint, 100)
+//!     .run();
+//! ```
+//!
+//! Token accounts are the associated token accounts of their owner, of the
+//! token program of the scenario, the SPL Token program unless
+//! [`Scenario::token_program`] sets another one. The system program and
+//! the token program are always included.
+
+use mollusk_svm::{
+    program::keyed_account_for_system_program,
+    result::{Check, InstructionResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::Instruction;
+use solana_pubkey::{pubkey, Pubkey};
+use solana_sdk_ids::system_program;
+
+use crate::{
+    assertions::assert_token_balance,
+    funded_account, keyed_token_program_with_id, mint_account,
+    programs::{mollusk, TOKEN_PROGRAM_ID},
+    token_account_with_program,
+};
+
+/// Address of the Associated Token Account program.
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWWjeFNtiiZdpPbVm7kJL");
+
+/// Returns the associated token account of `owner` for `mint`, a mint of
+/// `token_program`.
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Check of the state after the instructions.
+enum Expectation {
+    /// The token account holds the amount.
+    Balance(Pubkey, u64),
+}
+
+/// Accounts, instructions and expected results of a test.
+pub struct Scenario<'a> {
+    mollusk: Mollusk,
+    token_program: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+    instructions: Vec<(Instruction, Vec<Check<'a>>)>,
+    expectations: Vec<Expectation>,
+}
+
+impl Default for Scenario<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Scenario<'a> {
+    /// Starts a scenario with the programs loaded by [`mollusk`] and the
+    /// SPL Token program.
+    pub fn new() -> Self {
+        Self::with_mollusk(mollusk())
+    }
+
+    /// Starts a scenario running on `mollusk`, e.g. with other programs
+    /// loaded.
+    pub fn with_mollusk(mollusk: Mollusk) -> Self {
+        Self {
+            mollusk,
+            token_program: TOKEN_PROGRAM_ID,
+            accounts: Vec::new(),
+            instructions: Vec::new(),
+            expectations: Vec::new(),
+        }
+    }
+
+    /// Sets the token program of the mints and token accounts added after,
+    /// either the SPL Token or the Token-2022 program.
+    pub fn token_program(mut self, token_program: &Pubkey) -> Self {
+        self.token_program = *token_program;
+        self
+    }
+
+    /// Adds `account` at `address`, replacing any account added there
+    /// before.
+    pub fn with_account(mut self, address: &Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| other != address);
+        self.accounts.push((*address, account));
+        self
+    }
+
+    /// Adds a system account holding `lamports`, e.g. a signer paying for
+    /// new accounts.
+    pub fn with_funded(self, address: &Pubkey, lamports: u64) -> Self {
+        self.with_account(address, funded_account(lamports))
+    }
+
+    /// Adds an empty system account, e.g. a PDA created by an instruction.
+    pub fn with_empty(self, address: &Pubkey) -> Self {
+        self.with_account(address, Account::new(0, 0, &system_program::ID))
+    }
+
+    /// Adds an initialized mint without authorities.
+    pub fn with_mint(self, mint: &Pubkey, decimals: u8, supply: u64) -> Self {
+        let mut account = mint_account(decimals, supply);
+        // Token-2022 mints without extensions have the same layout.
+        account.owner = self.token_program;
+        self.with_account(mint, account)
+    }
+
+    /// Adds the associated token account of `owner` for `mint`, holding
+    /// `amount` tokens.
+    pub fn with_ata(self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Self {
+        let address = associated_token_address(owner, mint, &self.token_program);
+        let account = token_account_with_program(&self.token_program, mint, owner, amount);
+        self.with_account(&address, account)
+    }
+
+    /// Runs `instruction` after the previous ones, expecting it to succeed.
+    pub fn then(self, instruction: Instruction) -> Self {
+        self.then_check(instruction, vec![Check::success()])
+    }
+
+    /// Runs `instruction` after the previous ones, validating its result
+    /// with `checks`, e.g. an expected error ending the scenario.
+    pub fn then_check(mut self, instruction: Instruction, checks: Vec<Check<'a>>) -> Self {
+        self.instructions.push((instruction, checks));
+        self
+    }
+
+    /// Expects the associated token account of `owner` for `mint` to hold
+    /// `amount` tokens after the instructions.
+    pub fn expect_balance(mut self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Self {
+        let address = associated_token_address(owner, mint, &self.token_program);
+        self.expectations
+            .push(Expectation::Balance(address, amount));
+        self
+    }
+
+    /// Runs the instructions, checks the expectations and returns the
+    /// result for further assertions.
+    pub fn run(self) -> InstructionResult {
+        let mut accounts = self.accounts;
+        for (address, account) in [
+            keyed_account_for_system_program(),
+            keyed_token_program_with_id(&self.token_program),
+        ] {
+            if !accounts.iter().any(|(other, _)| *other == address) {
+                accounts.push((address, account));
+            }
+        }
+
+        let instructions: Vec<_> = self
+            .instructions
+            .iter()
+            .map(|(instruction, checks)| (instruction, checks.as_slice()))
+            .collect();
+        let res = self
+            .mollusk
+            .process_and_validate_instruction_chain(&instructions, &accounts);
+
+        for expectation in &self.expectations {
+            match expectation {
+                Expectation::Balance(address, amount) => {
+                    assert_token_balance(&res, address, *amount)
+                }
+            }
+        }
+        res
+    }
+}
@@ -0,0 +1,271 @@
+//! SPL token multisig authorities co-signed by a program.
+//!
+//! A token multisig is an account of the token program listing up to
+//! [`MAX_SIGNERS`] signers, `m` of which have to sign instructions which
+//! need its authority. Any account can be a signer, including a PDA, so a
+//! program can be one of the signers and co-sign operations alongside
+//! regular keypairs.
+//!
+//! Here the cosigner PDA of the program is always the first signer of the
+//! multisig. The program only co-signs what goes through it, so it's the
+//! place to enforce rules the other signers can't bypass, as long as `m`
+//! is larger than the number of other signers which can collude.
+
+#![no_std]
+
+use core::array;
+
+use common::{checks::require_signer, info, keys_eq};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+use pinocchio_token::instructions::{AuthorityType, SetAuthority};
+
+use crate::multisig::{InitializeMultisig2, MintTo, MAX_SIGNERS, MULTISIG_LEN};
+
+pub mod multisig;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("5BqbNyKGiaJbbgtsm5hbAT53Zawt7aPGyFkPEHPcWC2a");
+
+pub const COSIGNER_SEED: &str = "cosigner";
+
+/// Token multisig program instruction discriminators.
+#[repr(u8)]
+pub enum TokenMultisigInstruction {
+    /// Creates a multisig with the cosigner PDA as its first signer.
+    InitializeMultisig,
+    /// Sets a multisig as the mint authority of a mint.
+    SetMintAuthority,
+    /// Mints tokens with a multisig mint authority, co-signed by the
+    /// cosigner PDA.
+    MintTo,
+}
+
+impl TryFrom<&u8> for TokenMultisigInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::InitializeMultisig),
+            1 => Ok(Self::SetMintAuthority),
+            2 => Ok(Self::MintTo),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeMultisigInstructionData {
+    /// Number of signatures required.
+    pub m: u8,
+    pub bump: u8,
+}
+
+impl InitializeMultisigInstructionData {
+    pub fn new(m: u8, bump: u8) -> Self {
+        Self { m, bump }
+    }
+}
+
+#[derive(InstructionData)]
+pub struct MintToInstructionData {
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl MintToInstructionData {
+    pub fn new(amount: u64, bump: u8) -> Self {
+        Self { amount, bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = TokenMultisigInstruction::try_from(instruction)?;
+
+    match instruction {
+        TokenMultisigInstruction::InitializeMultisig => {
+            process_initialize_multisig(accounts, instruction_data)
+        }
+        TokenMultisigInstruction::SetMintAuthority => process_set_mint_authority(accounts),
+        TokenMultisigInstruction::MintTo => process_mint_to(accounts, instruction_data),
+    }
+}
+
+/// Creates a multisig requiring `m` signatures of the cosigner PDA and the
+/// other signers.
+///
+/// ### Accounts:
+///   0. `[WRITE, SIGNER]` Payer
+///   1. `[WRITE, SIGNER]` Multisig
+///   2. `[]` Cosigner PDA of the multisig
+///   3. `[]` System program
+///   4. `[]` Token program
+///   5. ..`5+N` `[]` Other signers
+pub fn process_initialize_multisig(
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [payer, multisig, cosigner, _system_program, _token_program, others @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(payer)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeMultisigInstructionData::from_bytes(instruction_data)?;
+
+    validate_cosigner(multisig, cosigner, instruction_data.bump)?;
+    let (signers, len) = with_cosigner(cosigner, others)?;
+
+    CreateAccount {
+        from: payer,
+        to: multisig,
+        lamports: Rent::get()?.minimum_balance(MULTISIG_LEN),
+        space: MULTISIG_LEN as u64,
+        owner: &pinocchio_token::ID,
+    }
+    .invoke()?;
+
+    InitializeMultisig2 {
+        multisig,
+        signers: &signers[..len],
+        m: instruction_data.m,
+    }
+    .invoke()?;
+
+    info!(
+        "Initialized a {} of {} multisig",
+        instruction_data.m, len as u8
+    );
+
+    Ok(())
+}
+
+/// Sets `multisig` as the mint authority of `mint`. From then on, minting
+/// requires the signatures of the multisig.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Mint
+///   1. `[SIGNER]` Current mint authority
+///   2. `[]` Multisig
+///   3. `[]` Token program
+pub fn process_set_mint_authority(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [mint, authority, multisig, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !multisig.is_owned_by(&pinocchio_token::ID) || multisig.data_len() != MULTISIG_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    SetAuthority {
+        account: mint,
+        authority,
+        authority_type: AuthorityType::MintTokens,
+        new_authority: Some(multisig.key()),
+    }
+    .invoke()?;
+
+    info!("Set the multisig as the mint authority");
+
+    Ok(())
+}
+
+/// Mints `amount` tokens to `token_account`. The cosigner PDA signs with
+/// the other signers, which have to provide the rest of the signatures.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Mint
+///   1. `[WRITE]` Token account
+///   2. `[]` Multisig mint authority
+///   3. `[]` Cosigner PDA of the multisig
+///   4. `[]` Token program
+///   5. ..`5+M` `[SIGNER]` Other signers
+pub fn process_mint_to(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [mint, token_account, multisig, cosigner, _token_program, others @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = MintToInstructionData::from_bytes(instruction_data)?;
+
+    validate_cosigner(multisig, cosigner, instruction_data.bump)?;
+    let (signers, len) = with_cosigner(cosigner, others)?;
+
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(COSIGNER_SEED.as_bytes()),
+        Seed::from(multisig.key()),
+        Seed::from(&bump),
+    ];
+    MintTo {
+        mint,
+        account: token_account,
+        multisig,
+        signers: &signers[..len],
+        amount: instruction_data.amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!(
+        "Minted {} tokens with {} signatures",
+        instruction_data.amount, len as u8
+    );
+
+    Ok(())
+}
+
+/// Checks that `cosigner` is the cosigner PDA of `multisig`.
+fn validate_cosigner(multisig: &AccountInfo, cosigner: &AccountInfo, bump: u8) -> ProgramResult {
+    let cosigner_pda =
+        create_program_address(&[COSIGNER_SEED.as_bytes(), multisig.key(), &[bump]], &ID)?;
+    if !keys_eq(cosigner.key(), &cosigner_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(())
+}
+
+/// Returns the signers of a multisig, `cosigner` followed by `others`, and
+/// their number. Only the first `len` entries are valid.
+fn with_cosigner<'a>(
+    cosigner: &'a AccountInfo,
+    others: &'a [AccountInfo],
+) -> Result<([&'a AccountInfo; MAX_SIGNERS], usize), ProgramError> {
+    if others.len() >= MAX_SIGNERS {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let signers = array::from_fn(|i| match i {
+        0 => cosigner,
+        i => others.get(i - 1).unwrap_or(cosigner),
+    });
+    Ok((signers, 1 + others.len()))
+}
@@ -0,0 +1,143 @@
+//! Multisig instructions and account layout of the token program.
+//!
+//! `pinocchio-token` only supports single authorities, so the CPIs are
+//! built by hand, the same way `pinocchio-token` does it. The number of
+//! signers varies, so the accounts are passed as slices.
+
+use core::array;
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::slice_invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    program_error::ProgramError,
+    ProgramResult,
+};
+
+/// Maximum number of signers of a multisig.
+pub const MAX_SIGNERS: usize = 11;
+
+/// Size of a multisig: `m`, `n`, the initialized flag and the signers.
+pub const MULTISIG_LEN: usize = 3 + MAX_SIGNERS * 32;
+
+/// Initialize a multisig, without requiring the rent sysvar.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Uninitialized multisig
+///   1. ..`1+N` `[]` Signers
+pub struct InitializeMultisig2<'a> {
+    /// Multisig.
+    pub multisig: &'a AccountInfo,
+
+    /// Signers, up to [`MAX_SIGNERS`].
+    pub signers: &'a [&'a AccountInfo],
+
+    /// Number of signatures required.
+    pub m: u8,
+}
+
+impl InitializeMultisig2<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        if self.signers.len() > MAX_SIGNERS {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let len = 1 + self.signers.len();
+
+        // account metadata
+        let account_metas: [AccountMeta; 1 + MAX_SIGNERS] = array::from_fn(|i| match i {
+            0 => AccountMeta::writable(self.multisig.key()),
+            i => AccountMeta::readonly(self.signers.get(i - 1).unwrap_or(&self.multisig).key()),
+        });
+        let account_infos: [&AccountInfo; 1 + MAX_SIGNERS] = array::from_fn(|i| match i {
+            0 => self.multisig,
+            i => self.signers.get(i - 1).unwrap_or(&self.multisig),
+        });
+
+        // instruction data
+        // -  [0]: instruction discriminator
+        // -  [1]: m
+        let instruction_data = [19, self.m];
+
+        let instruction = Instruction {
+            program_id: &pinocchio_token::ID,
+            accounts: &account_metas[..len],
+            data: &instruction_data,
+        };
+
+        slice_invoke_signed(&instruction, &account_infos[..len], signers)
+    }
+}
+
+/// Mint new tokens to an account, with a multisig as the mint authority.
+///
+/// ### Accounts:
+///   0. `[WRITE]` Mint
+///   1. `[WRITE]` Token account to mint to
+///   2. `[]` Multisig mint authority
+///   3. ..`3+M` `[SIGNER]` Signers of the multisig
+pub struct MintTo<'a> {
+    /// Mint.
+    pub mint: &'a AccountInfo,
+
+    /// Token account.
+    pub account: &'a AccountInfo,
+
+    /// Multisig mint authority.
+    pub multisig: &'a AccountInfo,
+
+    /// Signers of the multisig, at least `m` of them.
+    pub signers: &'a [&'a AccountInfo],
+
+    /// Amount.
+    pub amount: u64,
+}
+
+impl MintTo<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        if self.signers.len() > MAX_SIGNERS {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let len = 3 + self.signers.len();
+
+        // account metadata
+        let account_metas: [AccountMeta; 3 + MAX_SIGNERS] = array::from_fn(|i| match i {
+            0 => AccountMeta::writable(self.mint.key()),
+            1 => AccountMeta::writable(self.account.key()),
+            2 => AccountMeta::readonly(self.multisig.key()),
+            i => AccountMeta::readonly_signer(
+                self.signers.get(i - 3).unwrap_or(&self.multisig).key(),
+            ),
+        });
+        let account_infos: [&AccountInfo; 3 + MAX_SIGNERS] = array::from_fn(|i| match i {
+            0 => self.mint,
+            1 => self.account,
+            2 => self.multisig,
+            i => self.signers.get(i - 3).unwrap_or(&self.multisig),
+        });
+
+        // instruction data
+        // -  [0   ]: instruction discriminator
+        // -  [1..9]: amount
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 7;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: &pinocchio_token::ID,
+            accounts: &account_metas[..len],
+            data: &instruction_data,
+        };
+
+        slice_invoke_signed(&instruction, &account_infos[..len], signers)
+    }
+}
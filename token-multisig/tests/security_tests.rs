@@ -0,0 +1,409 @@
+//! Attacks on the token multisig program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    error::TokenError,
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint, Multisig},
+};
+use token_multisig::{
+    multisig::MAX_SIGNERS, InitializeMultisigInstructionData, MintToInstructionData,
+    TokenMultisigInstruction, COSIGNER_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(token_multisig::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Signatures required by the multisigs.
+const M: u8 = 2;
+
+const AMOUNT: u64 = 1_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: TokenMultisigInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<TokenMultisigInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+fn mint_account(mollusk: &Mollusk, mint_authority: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::Some(*mint_authority),
+            supply: 0,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount: 0,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Returns the cosigner PDA of `multisig` and its bump.
+fn cosigner_pda(multisig: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[COSIGNER_SEED.as_bytes(), multisig.as_ref()], &ID)
+}
+
+/// Returns a multisig of the token program requiring [`M`] of `signers`.
+fn multisig_account(mollusk: &Mollusk, signers: &[Pubkey]) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Multisig::LEN),
+        Multisig::LEN,
+        &TOKEN_ID,
+    );
+    let mut multisig = Multisig {
+        m: M,
+        n: signers.len() as u8,
+        is_initialized: true,
+        signers: [Pubkey::default(); MAX_SIGNERS],
+    };
+    multisig.signers[..signers.len()].copy_from_slice(signers);
+    Pack::pack(multisig, account.data_as_mut_slice()).unwrap();
+    account
+}
+
+/// Accounts of a mint whose authority is a 2 of 3 multisig of the cosigner
+/// PDA, Alice and Bob, and of an attacker with a 2 of 2 multisig of their
+/// own, with its cosigner PDA.
+struct Setup {
+    mollusk: Mollusk,
+    payer: Pubkey,
+    mint: Pubkey,
+    multisig: Pubkey,
+    cosigner: Pubkey,
+    cosigner_bump: u8,
+    alice: Pubkey,
+    bob: Pubkey,
+    destination: Pubkey,
+    attacker: Pubkey,
+    attacker_multisig: Pubkey,
+    attacker_cosigner: Pubkey,
+    attacker_cosigner_bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/token_multisig");
+        mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let multisig = Pubkey::new_unique();
+        let (cosigner, cosigner_bump) = cosigner_pda(&multisig);
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+
+        let attacker = Pubkey::new_unique();
+        let attacker_multisig = Pubkey::new_unique();
+        let (attacker_cosigner, attacker_cosigner_bump) = cosigner_pda(&attacker_multisig);
+
+        let system_program = keyed_account_for_system_program();
+        let accounts = vec![
+            (payer, Account::new(LAMPORTS_PER_SOL, 0, &system_program.0)),
+            (mint, mint_account(&mollusk, &multisig)),
+            (
+                multisig,
+                multisig_account(&mollusk, &[cosigner, alice, bob]),
+            ),
+            (cosigner, Account::default()),
+            (alice, Account::default()),
+            (bob, Account::default()),
+            (
+                destination,
+                token_account(&mollusk, &mint, &Pubkey::new_unique()),
+            ),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &system_program.0),
+            ),
+            (
+                attacker_multisig,
+                multisig_account(&mollusk, &[attacker_cosigner, attacker]),
+            ),
+            (attacker_cosigner, Account::default()),
+            system_program,
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            payer,
+            mint,
+            multisig,
+            cosigner,
+            cosigner_bump,
+            alice,
+            bob,
+            destination,
+            attacker,
+            attacker_multisig,
+            attacker_cosigner,
+            attacker_cosigner_bump,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Creates `multisig` with `cosigner`, Alice and Bob as its signers.
+    fn instruction_initialize_multisig(
+        &self,
+        multisig: &Pubkey,
+        cosigner: &Pubkey,
+        bump: u8,
+    ) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new(*multisig, true),
+            AccountMeta::new_readonly(*cosigner, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+            AccountMeta::new_readonly(self.alice, false),
+            AccountMeta::new_readonly(self.bob, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                TokenMultisigInstruction::InitializeMultisig,
+                &InitializeMultisigInstructionData::new(M, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    /// Hands the mint authority over to `multisig`, signed by the payer.
+    fn instruction_set_mint_authority(&self, multisig: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(self.payer, true),
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &[TokenMultisigInstruction::SetMintAuthority as u8],
+            ix_accounts,
+        )
+    }
+
+    /// Mints [`AMOUNT`] tokens through `multisig` and `cosigner`, with
+    /// `others` as the rest of the signers.
+    fn instruction_mint_to(
+        &self,
+        multisig: &Pubkey,
+        cosigner: &Pubkey,
+        bump: u8,
+        others: &[AccountMeta],
+    ) -> Instruction {
+        let mut ix_accounts = vec![
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.destination, false),
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new_readonly(*cosigner, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        ix_accounts.extend_from_slice(others);
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                TokenMultisigInstruction::MintTo,
+                &MintToInstructionData::new(AMOUNT, bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_payer_signature() {
+    let setup = Setup::new();
+    let multisig = setup.multisig;
+    let setup = setup.with(multisig, Account::default());
+    let mut instruction = setup.instruction_initialize_multisig(
+        &setup.multisig,
+        &setup.cosigner,
+        setup.cosigner_bump,
+    );
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The cosigner PDA is passed again as one of the other signers, to count
+/// the signature of the program twice.
+#[test]
+fn test_cosigner_signing_twice() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_mint_to(
+            &setup.multisig,
+            &setup.cosigner,
+            setup.cosigner_bump,
+            &[AccountMeta::new_readonly(setup.cosigner, false)],
+        ),
+        ProgramError::MissingRequiredSignature,
+    );
+}
+
+/// The attacker signs with the cosigner PDA of their own multisig, which is
+/// not a signer of the multisig of the mint.
+#[test]
+fn test_cosigner_of_other_multisig() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_mint_to(
+            &setup.multisig,
+            &setup.attacker_cosigner,
+            setup.cosigner_bump,
+            &[AccountMeta::new_readonly(setup.attacker, true)],
+        ),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// The attacker mints through their own multisig, of which they hold enough
+/// signatures, but which is not the mint authority.
+#[test]
+fn test_multisig_of_other_mint() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_mint_to(
+            &setup.attacker_multisig,
+            &setup.attacker_cosigner,
+            setup.attacker_cosigner_bump,
+            &[AccountMeta::new_readonly(setup.attacker, true)],
+        ),
+        ProgramError::Custom(TokenError::OwnerMismatch as u32),
+    );
+}
+
+/// More other signers than fit in a multisig next to the cosigner PDA.
+#[test]
+fn test_too_many_signers() {
+    let mut setup = Setup::new();
+    let others: Vec<_> = (0..MAX_SIGNERS).map(|_| Pubkey::new_unique()).collect();
+    for other in &others {
+        setup = setup.with(*other, Account::default());
+    }
+    let others: Vec<_> = others
+        .iter()
+        .map(|other| AccountMeta::new_readonly(*other, true))
+        .collect();
+    setup.expect_err(
+        &setup.instruction_mint_to(
+            &setup.multisig,
+            &setup.cosigner,
+            setup.cosigner_bump,
+            &others,
+        ),
+        ProgramError::InvalidArgument,
+    );
+}
+
+/// A multisig is created with the cosigner PDA of another multisig, which
+/// the program would never sign for.
+#[test]
+fn test_initialize_with_cosigner_of_other_multisig() {
+    let setup = Setup::new();
+    let multisig = setup.multisig;
+    let setup = setup.with(multisig, Account::default());
+    setup.expect_err(
+        &setup.instruction_initialize_multisig(
+            &setup.multisig,
+            &setup.attacker_cosigner,
+            setup.cosigner_bump,
+        ),
+        ProgramError::InvalidSeeds,
+    );
+}
+
+/// A token account of the token program is handed the mint authority as if
+/// it was a multisig.
+#[test]
+fn test_token_account_as_multisig() {
+    let setup = Setup::new();
+    let (mint, mint_account) = (setup.mint, mint_account(&setup.mollusk, &setup.payer));
+    let setup = setup.with(mint, mint_account);
+    setup.expect_err(
+        &setup.instruction_set_mint_authority(&setup.destination),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A multisig with the layout of the token program, but owned by another
+/// program, which could rewrite its signers.
+#[test]
+fn test_spoofed_multisig() {
+    let setup = Setup::new();
+    let mut multisig_account = multisig_account(&setup.mollusk, &[setup.cosigner, setup.attacker]);
+    multisig_account.owner = Pubkey::new_unique();
+    let (mint, mint_account) = (setup.mint, mint_account(&setup.mollusk, &setup.payer));
+    let multisig = setup.multisig;
+    let setup = setup
+        .with(mint, mint_account)
+        .with(multisig, multisig_account);
+    setup.expect_err(
+        &setup.instruction_set_mint_authority(&setup.multisig),
+        ProgramError::InvalidAccountData,
+    );
+}
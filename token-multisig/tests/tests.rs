@@ -0,0 +1,316 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::{Account, WritableAccount};
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_program_pack::Pack;
+use solana_pubkey::Pubkey;
+use spl_token::{
+    solana_program::program_option::COption,
+    state::{Account as TokenAccount, AccountState as TokenAccountState, Mint, Multisig},
+};
+use token_multisig::{
+    multisig::{MAX_SIGNERS, MULTISIG_LEN},
+    InitializeMultisigInstructionData, MintToInstructionData, TokenMultisigInstruction,
+    COSIGNER_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(token_multisig::ID);
+const TOKEN_ID: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+/// Offset of the amount in a token account.
+const AMOUNT_OFFSET: usize = 64;
+
+/// Signatures required by the multisig, out of the cosigner PDA and the
+/// two other signers.
+const M: u8 = 2;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: TokenMultisigInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<TokenMultisigInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/token_multisig");
+    mollusk.add_program(&TOKEN_ID, "third-party/spl_token", &LOADER_V3);
+    mollusk
+}
+
+fn mint_account(mollusk: &Mollusk, mint_authority: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        Mint {
+            mint_authority: COption::Some(*mint_authority),
+            supply: 0,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn token_account(mollusk: &Mollusk, mint: &Pubkey, owner: &Pubkey) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &TOKEN_ID,
+    );
+    Pack::pack(
+        TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount: 0,
+            delegate: COption::None,
+            state: TokenAccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+fn multisig_account(mollusk: &Mollusk, signers: &[Pubkey]) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Multisig::LEN),
+        Multisig::LEN,
+        &TOKEN_ID,
+    );
+    let mut multisig = Multisig {
+        m: M,
+        n: signers.len() as u8,
+        is_initialized: true,
+        signers: [Pubkey::default(); MAX_SIGNERS],
+    };
+    multisig.signers[..signers.len()].copy_from_slice(signers);
+    Pack::pack(multisig, account.data_as_mut_slice()).unwrap();
+    account
+}
+
+/// A mint whose authority is a 2 of 3 multisig of the cosigner PDA, Alice
+/// and Bob.
+struct Setup {
+    mollusk: Mollusk,
+    payer: Pubkey,
+    mint: Pubkey,
+    multisig: Pubkey,
+    cosigner: Pubkey,
+    cosigner_bump: u8,
+    alice: Pubkey,
+    bob: Pubkey,
+    destination: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = mollusk();
+
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let multisig = Pubkey::new_unique();
+        let (cosigner, cosigner_bump) =
+            Pubkey::find_program_address(&[COSIGNER_SEED.as_bytes(), multisig.as_ref()], &ID);
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+
+        let accounts = vec![
+            (payer, Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default())),
+            (mint, mint_account(&mollusk, &multisig)),
+            (
+                multisig,
+                multisig_account(&mollusk, &[cosigner, alice, bob]),
+            ),
+            (cosigner, Account::default()),
+            (alice, Account::default()),
+            (bob, Account::default()),
+            (
+                destination,
+                token_account(&mollusk, &mint, &Pubkey::new_unique()),
+            ),
+            keyed_account_for_system_program(),
+            (TOKEN_ID, create_program_account_loader_v3(&TOKEN_ID)),
+        ];
+
+        Self {
+            mollusk,
+            payer,
+            mint,
+            multisig,
+            cosigner,
+            cosigner_bump,
+            alice,
+            bob,
+            destination,
+            accounts,
+        }
+    }
+
+    fn instruction_initialize_multisig(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.payer, true),
+            AccountMeta::new(self.multisig, true),
+            AccountMeta::new_readonly(self.cosigner, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+            AccountMeta::new_readonly(self.alice, false),
+            AccountMeta::new_readonly(self.bob, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                TokenMultisigInstruction::InitializeMultisig,
+                &InitializeMultisigInstructionData::new(M, self.cosigner_bump),
+            ),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_set_mint_authority(&self) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new_readonly(self.payer, true),
+            AccountMeta::new_readonly(self.multisig, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &[TokenMultisigInstruction::SetMintAuthority as u8],
+            ix_accounts,
+        )
+    }
+
+    fn instruction_mint_to(&self, amount: u64, signers: &[Pubkey]) -> Instruction {
+        let mut ix_accounts = vec![
+            AccountMeta::new(self.mint, false),
+            AccountMeta::new(self.destination, false),
+            AccountMeta::new_readonly(self.multisig, false),
+            AccountMeta::new_readonly(self.cosigner, false),
+            AccountMeta::new_readonly(TOKEN_ID, false),
+        ];
+        ix_accounts.extend(
+            signers
+                .iter()
+                .map(|signer| AccountMeta::new_readonly(*signer, true)),
+        );
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(
+                TokenMultisigInstruction::MintTo,
+                &MintToInstructionData::new(amount, self.cosigner_bump),
+            ),
+            ix_accounts,
+        )
+    }
+}
+
+#[test]
+fn test_layout() {
+    assert_eq!(MULTISIG_LEN, Multisig::LEN);
+}
+
+#[test]
+fn test_initialize_multisig() {
+    let mut setup = Setup::new();
+    // The multisig is created by the program.
+    setup.accounts[2].1 = Account::default();
+
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_initialize_multisig(),
+        &setup.accounts,
+        &[
+            Check::success(),
+            Check::account(&setup.multisig)
+                .owner(&TOKEN_ID)
+                .space(Multisig::LEN)
+                .build(),
+        ],
+    );
+
+    let multisig = Multisig::unpack(&res.get_account(&setup.multisig).unwrap().data).unwrap();
+    assert_eq!(multisig.m, M);
+    assert_eq!(multisig.n, 3);
+    assert_eq!(
+        multisig.signers[..3],
+        [setup.cosigner, setup.alice, setup.bob]
+    );
+}
+
+#[test]
+fn test_set_mint_authority() {
+    let mut setup = Setup::new();
+    setup.accounts[1].1 = mint_account(&setup.mollusk, &setup.payer);
+
+    let res = setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_set_mint_authority(),
+        &setup.accounts,
+        &[Check::success()],
+    );
+
+    let mint = Mint::unpack(&res.get_account(&setup.mint).unwrap().data).unwrap();
+    assert_eq!(mint.mint_authority, COption::Some(setup.multisig));
+}
+
+#[test]
+fn test_mint_to() {
+    let setup = Setup::new();
+
+    // The cosigner PDA and either of the other signers reach the threshold.
+    for signer in [setup.alice, setup.bob] {
+        setup.mollusk.process_and_validate_instruction(
+            &setup.instruction_mint_to(1_000, &[signer]),
+            &setup.accounts,
+            &[
+                Check::success(),
+                Check::account(&setup.destination)
+                    .data_slice(AMOUNT_OFFSET, &1_000u64.to_le_bytes())
+                    .build(),
+            ],
+        );
+    }
+
+    // The signature of the program alone is not enough.
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_mint_to(1_000, &[]),
+        &setup.accounts,
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+
+    let mut ix = setup.instruction_mint_to(1_000, &[setup.alice]);
+    ix.accounts[3].pubkey = setup.bob;
+    setup.mollusk.process_and_validate_instruction(
+        &ix,
+        &setup.accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
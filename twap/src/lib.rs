@@ -0,0 +1,391 @@
+//! Time-weighted average price (TWAP) accumulator.
+//!
+//! The authority of an oracle records price observations, which are stored in
+//! a ring buffer together with the cumulative price, i.e. the integral of the
+//! price over time. The average price over any window covered by the buffer
+//! is then the difference of two cumulative values divided by the window
+//! length, no matter how many observations fall in between.
+
+#![no_std]
+
+use common::{assert_layout, debug, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::{AccountInfo, Ref, RefMut},
+    cpi::set_return_data,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("3KBQfummQdnwDtHP6gDDcdEoHdfcvipjfjpJpAp2XhwZ");
+
+pub const ORACLE_SEED: &str = "oracle";
+
+/// Number of observations kept by an oracle.
+pub const OBSERVATIONS_CAPACITY: usize = 64;
+
+/// Errors returned by the TWAP program.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TwapError {
+    /// An observation was already recorded at the current timestamp.
+    ObservationTooEarly,
+    /// The window is not positive.
+    InvalidWindow,
+    /// The oldest stored observation is more recent than the start of the
+    /// window.
+    InsufficientHistory,
+}
+
+impl From<TwapError> for ProgramError {
+    fn from(e: TwapError) -> Self {
+        Namespace::Twap.error(e as u32)
+    }
+}
+
+/// A single price observation.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Observation {
+    /// Unix timestamp of the observation.
+    pub timestamp: i64,
+    /// Price in effect from `timestamp` until the next observation.
+    pub price: u64,
+    /// Sum of `price * seconds` from the first observation up to
+    /// `timestamp`, wrapping on overflow.
+    pub cumulative: [u8; 16],
+}
+
+assert_layout!(Observation, size = 32, align = 8, { timestamp: 0, price: 8, cumulative: 16 });
+
+impl Observation {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    pub fn cumulative(&self) -> u128 {
+        u128::from_le_bytes(self.cumulative)
+    }
+
+    /// Returns the cumulative price at `timestamp`, which has to be at or
+    /// after this observation and before the next one.
+    pub fn cumulative_at(&self, timestamp: i64) -> u128 {
+        let elapsed = (timestamp - self.timestamp) as u128;
+        self.cumulative().wrapping_add(self.price as u128 * elapsed)
+    }
+}
+
+/// On-chain representation of an oracle.
+///
+/// Observation `n` is stored at index `n % OBSERVATIONS_CAPACITY`, so once
+/// the buffer is full every new observation overwrites the oldest one.
+#[repr(C)]
+pub struct Oracle {
+    /// The only account allowed to record observations.
+    pub authority: Pubkey,
+    /// Number of observations recorded so far, including overwritten ones.
+    pub count: u64,
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub observations: [Observation; OBSERVATIONS_CAPACITY],
+}
+
+assert_layout!(Oracle, size = 2096, align = 8, {
+    authority: 0,
+    count: 32,
+    bump: 40,
+    _padding: 41,
+    observations: 48,
+});
+
+impl Oracle {
+    pub const LEN: usize = mem::size_of::<Self>();
+
+    /// Loads an oracle owned by the program.
+    fn load(account: &AccountInfo) -> Result<Ref<'_, Self>, ProgramError> {
+        require_owned_by(account, &ID)?;
+        if account.data_len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Ref::map(account.try_borrow_data()?, |data| unsafe {
+            &*data.as_ptr().cast()
+        }))
+    }
+
+    /// Loads an oracle owned by the program for modification.
+    fn load_mut(account: &AccountInfo) -> Result<RefMut<'_, Self>, ProgramError> {
+        require_owned_by(account, &ID)?;
+        if account.data_len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(RefMut::map(account.try_borrow_mut_data()?, |data| unsafe {
+            &mut *data.as_mut_ptr().cast()
+        }))
+    }
+
+    /// Returns the number of stored observations.
+    pub fn len(&self) -> usize {
+        (self.count as usize).min(OBSERVATIONS_CAPACITY)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the `i`-th most recent observation, `0` being the latest.
+    fn nth_latest(&self, i: usize) -> &Observation {
+        let index = (self.count as usize - 1 - i) % OBSERVATIONS_CAPACITY;
+        &self.observations[index]
+    }
+
+    /// Returns the latest observation, if any.
+    pub fn latest(&self) -> Option<&Observation> {
+        (!self.is_empty()).then(|| self.nth_latest(0))
+    }
+
+    /// Records `price` at `timestamp`, accumulating the previous price over
+    /// the time elapsed since the previous observation.
+    pub fn record(&mut self, timestamp: i64, price: u64) -> Result<(), TwapError> {
+        let cumulative = match self.latest() {
+            Some(latest) if timestamp <= latest.timestamp => {
+                return Err(TwapError::ObservationTooEarly)
+            }
+            Some(latest) => latest.cumulative_at(timestamp),
+            None => 0,
+        };
+
+        self.observations[self.count as usize % OBSERVATIONS_CAPACITY] = Observation {
+            timestamp,
+            price,
+            cumulative: cumulative.to_le_bytes(),
+        };
+        self.count += 1;
+
+        Ok(())
+    }
+
+    /// Returns the cumulative price at `timestamp`, which has to be at or
+    /// after the oldest stored observation.
+    ///
+    /// Prices are constant between observations, so the value is exact.
+    pub fn cumulative_at(&self, timestamp: i64) -> Result<u128, TwapError> {
+        (0..self.len())
+            .map(|i| self.nth_latest(i))
+            .find(|observation| observation.timestamp <= timestamp)
+            .map(|observation| observation.cumulative_at(timestamp))
+            .ok_or(TwapError::InsufficientHistory)
+    }
+
+    /// Returns the time-weighted average price over the `window` seconds
+    /// preceding `now`, rounded down.
+    pub fn twap(&self, now: i64, window: i64) -> Result<u64, TwapError> {
+        if window <= 0 {
+            return Err(TwapError::InvalidWindow);
+        }
+        let start = now
+            .checked_sub(window)
+            .ok_or(TwapError::InsufficientHistory)?;
+
+        // The cumulative values wrap on overflow, but their difference is
+        // correct as long as it fits in 128 bits, which it always does.
+        let sum = self
+            .cumulative_at(now)?
+            .wrapping_sub(self.cumulative_at(start)?);
+
+        // An average of `u64` prices always fits in `u64`.
+        Ok((sum / window as u128) as u64)
+    }
+}
+
+/// TWAP program instruction discriminators.
+#[repr(u8)]
+pub enum TwapInstruction {
+    /// Creates an oracle.
+    Initialize,
+    /// Records a price observation.
+    Record,
+    /// Returns the time-weighted average price over a window.
+    Consult,
+}
+
+impl TryFrom<&u8> for TwapInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Record),
+            2 => Ok(Self::Consult),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct RecordInstructionData {
+    pub price: u64,
+}
+
+impl RecordInstructionData {
+    pub fn new(price: u64) -> Self {
+        Self { price }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct ConsultInstructionData {
+    /// Length of the window, in seconds.
+    pub window: i64,
+}
+
+impl ConsultInstructionData {
+    pub fn new(window: i64) -> Self {
+        Self { window }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = TwapInstruction::try_from(instruction)?;
+
+    match instruction {
+        TwapInstruction::Initialize => process_initialize(accounts, instruction_data),
+        TwapInstruction::Record => process_record(accounts, instruction_data),
+        TwapInstruction::Consult => process_consult(accounts, instruction_data),
+    }
+}
+
+/// Creates an oracle PDA, with `authority` as the only publisher.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, oracle, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `oracle`.
+    let oracle_pda = create_program_address(
+        &[
+            ORACLE_SEED.as_bytes(),
+            authority.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(oracle.key(), &oracle_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the oracle PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(ORACLE_SEED.as_bytes()),
+        Seed::from(authority.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: authority,
+        to: oracle,
+        lamports: Rent::get()?.minimum_balance(Oracle::LEN),
+        space: Oracle::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Initialize the oracle. The observations are already zeroed.
+    let mut data = Oracle::load_mut(oracle)?;
+    data.authority = *authority.key();
+    data.bump = instruction_data.bump;
+
+    Ok(())
+}
+
+/// Records a price observation at the current timestamp.
+pub fn process_record(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [authority, oracle] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(authority)?;
+
+    // Deserialize instruction data.
+    let instruction_data = RecordInstructionData::from_bytes(instruction_data)?;
+
+    let mut data = Oracle::load_mut(oracle)?;
+    if !keys_eq(authority.key(), &data.authority) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    data.record(Clock::get()?.unix_timestamp, instruction_data.price)?;
+
+    info!(
+        "Recorded observation {}: price {}",
+        data.count - 1,
+        instruction_data.price
+    );
+
+    Ok(())
+}
+
+/// Returns the time-weighted average price over the last `window` seconds
+/// as return data. Anyone can consult an oracle.
+pub fn process_consult(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [oracle] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Deserialize instruction data.
+    let instruction_data = ConsultInstructionData::from_bytes(instruction_data)?;
+
+    let data = Oracle::load(oracle)?;
+    let twap = data.twap(Clock::get()?.unix_timestamp, instruction_data.window)?;
+
+    set_return_data(&twap.to_le_bytes());
+
+    debug!("TWAP over {} seconds: {}", instruction_data.window, twap);
+
+    Ok(())
+}
@@ -0,0 +1,161 @@
+//! Attacks on the TWAP program, each rejected with its own error.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use twap::{
+    ConsultInstructionData, InitializeInstructionData, Oracle, RecordInstructionData,
+    TwapInstruction, ORACLE_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(twap::ID);
+
+/// Offsets of the fields of an oracle.
+const COUNT_OFFSET: usize = 32;
+const OBSERVATIONS_OFFSET: usize = 48;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: TwapInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<TwapInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the oracle PDA of `authority` and its bump.
+fn oracle_pda(authority: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ORACLE_SEED.as_bytes(), authority.as_ref()], &ID)
+}
+
+/// Returns an oracle of `authority` with a single observation of `price`
+/// at the start of time.
+fn oracle_account(mollusk: &Mollusk, authority: &Pubkey, price: u64) -> Account {
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Oracle::LEN),
+        Oracle::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(authority.as_ref());
+    account.data[COUNT_OFFSET..COUNT_OFFSET + 8].copy_from_slice(&1u64.to_le_bytes());
+    account.data[OBSERVATIONS_OFFSET + 8..OBSERVATIONS_OFFSET + 16]
+        .copy_from_slice(&price.to_le_bytes());
+    account
+}
+
+/// Accounts of an authority with an oracle, and of an attacker.
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    oracle: Pubkey,
+    attacker: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/twap");
+        mollusk.sysvars.clock.unix_timestamp = 1_000;
+
+        let authority = Pubkey::new_unique();
+        let (oracle, _) = oracle_pda(&authority);
+        let attacker = Pubkey::new_unique();
+
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (oracle, oracle_account(&mollusk, &authority, 100)),
+            (
+                attacker,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            oracle,
+            attacker,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_authority_signature() {
+    let setup = Setup::new();
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(setup.authority, false),
+        AccountMeta::new(setup.oracle, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(TwapInstruction::Record, &RecordInstructionData::new(200)),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the oracle of the authority before the authority
+/// does, with their own bump, to publish prices under its address.
+#[test]
+fn test_initialize_oracle_of_other_authority() {
+    let setup = Setup::new();
+    let oracle = setup.oracle;
+    let setup = setup.with(oracle, Account::default());
+    let (_, bump) = oracle_pda(&setup.attacker);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(oracle, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            TwapInstruction::Initialize,
+            &InitializeInstructionData::new(bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidSeeds);
+}
+
+/// An oracle of the attacker with a price of their choice, owned by another
+/// program, consulted by a program relying on the TWAP.
+#[test]
+fn test_consult_spoofed_oracle() {
+    let setup = Setup::new();
+    let mut oracle_account = oracle_account(&setup.mollusk, &setup.attacker, u64::MAX);
+    oracle_account.owner = Pubkey::new_unique();
+    let oracle = Pubkey::new_unique();
+    let setup = setup.with(oracle, oracle_account);
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(TwapInstruction::Consult, &ConsultInstructionData::new(10)),
+        vec![AccountMeta::new_readonly(oracle, false)],
+    );
+    setup.expect_err(&instruction, ProgramError::IllegalOwner);
+}
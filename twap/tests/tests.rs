@@ -0,0 +1,360 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{program::keyed_account_for_system_program, result::Check, Mollusk};
+use proptest::prelude::*;
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_native_token::LAMPORTS_PER_SOL;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use twap::{
+    ConsultInstructionData, InitializeInstructionData, Observation, Oracle, RecordInstructionData,
+    TwapError, TwapInstruction, OBSERVATIONS_CAPACITY, ORACLE_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(twap::ID);
+
+/// Offset of the observation count in an oracle.
+const COUNT_OFFSET: usize = 32;
+/// Offset of the first observation in an oracle.
+const OBSERVATIONS_OFFSET: usize = 48;
+
+const NOW: i64 = 1_000_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: TwapInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<TwapInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn empty_oracle() -> Oracle {
+    Oracle {
+        authority: [0; 32],
+        count: 0,
+        bump: 0,
+        _padding: [0; 7],
+        observations: [Observation::default(); OBSERVATIONS_CAPACITY],
+    }
+}
+
+/// Serializes an observation the way it's stored in an oracle.
+fn observation(timestamp: i64, price: u64, cumulative: u128) -> Vec<u8> {
+    let mut data = Vec::with_capacity(Observation::LEN);
+    data.extend_from_slice(&timestamp.to_le_bytes());
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&cumulative.to_le_bytes());
+    data
+}
+
+#[test]
+fn test_twap_oracle_math() {
+    let mut oracle = empty_oracle();
+    assert_eq!(oracle.twap(NOW, 1), Err(TwapError::InsufficientHistory));
+
+    // 100 for 10 seconds, then 200 for 30 seconds, then 400.
+    oracle.record(NOW, 100).unwrap();
+    oracle.record(NOW + 10, 200).unwrap();
+    assert_eq!(
+        oracle.record(NOW + 10, 300),
+        Err(TwapError::ObservationTooEarly)
+    );
+    oracle.record(NOW + 40, 400).unwrap();
+
+    assert_eq!(oracle.latest().unwrap().cumulative(), 100 * 10 + 200 * 30);
+    assert_eq!(oracle.twap(NOW + 40, 40), Ok(175));
+    assert_eq!(oracle.twap(NOW + 40, 30), Ok(200));
+    // Windows don't have to start at an observation.
+    assert_eq!(
+        oracle.twap(NOW + 45, 40),
+        Ok((100 * 5 + 200 * 30 + 400 * 5) / 40)
+    );
+    // The result is rounded down.
+    assert_eq!(oracle.twap(NOW + 41, 3), Ok((200 * 2 + 400) / 3));
+
+    assert_eq!(
+        oracle.twap(NOW + 40, 41),
+        Err(TwapError::InsufficientHistory)
+    );
+    assert_eq!(oracle.twap(NOW + 40, 0), Err(TwapError::InvalidWindow));
+    assert_eq!(oracle.twap(NOW + 40, -1), Err(TwapError::InvalidWindow));
+}
+
+#[test]
+fn test_twap_oracle_ring_buffer() {
+    let mut oracle = empty_oracle();
+    let observations = OBSERVATIONS_CAPACITY as i64 + 10;
+    for i in 0..observations {
+        oracle.record(NOW + i, i as u64).unwrap();
+    }
+
+    assert_eq!(oracle.count, observations as u64);
+    assert_eq!(oracle.len(), OBSERVATIONS_CAPACITY);
+    assert_eq!(oracle.latest().unwrap().timestamp, NOW + observations - 1);
+
+    // Only the last `OBSERVATIONS_CAPACITY` observations are available.
+    let oldest = NOW + observations - OBSERVATIONS_CAPACITY as i64;
+    let now = NOW + observations;
+    let window = now - oldest;
+    let expected = (oldest - NOW..observations).sum::<i64>() / window;
+    assert_eq!(oracle.twap(now, window), Ok(expected as u64));
+    assert_eq!(
+        oracle.twap(now, window + 1),
+        Err(TwapError::InsufficientHistory)
+    );
+}
+
+#[test]
+fn test_twap_oracle_cumulative_wraps() {
+    let mut oracle = empty_oracle();
+    oracle.observations[0] = Observation {
+        timestamp: NOW,
+        price: u64::MAX,
+        cumulative: (u128::MAX - 5).to_le_bytes(),
+    };
+    oracle.count = 1;
+    oracle.record(NOW + 2, u64::MAX).unwrap();
+
+    assert!(oracle.latest().unwrap().cumulative() < u128::MAX - 5);
+    assert_eq!(oracle.twap(NOW + 3, 3), Ok(u64::MAX));
+}
+
+proptest! {
+    #[test]
+    fn test_twap_oracle_matches_naive_average(
+        steps in prop::collection::vec((1i64..1_000, any::<u64>()), 1..100),
+        now_offset in 0i64..1_000,
+        window_fraction in 1u32..=100,
+    ) {
+        let mut oracle = empty_oracle();
+        let mut timestamp = NOW;
+        let mut history = Vec::new();
+        for (elapsed, price) in steps {
+            oracle.record(timestamp, price).unwrap();
+            history.push((timestamp, price));
+            timestamp += elapsed;
+        }
+
+        let now = timestamp + now_offset;
+        let oldest = history[history.len().saturating_sub(OBSERVATIONS_CAPACITY)].0;
+        let window = ((now - oldest) * window_fraction as i64 / 100).max(1);
+        let start = now - window;
+
+        // Add up the overlap of every price interval with the window.
+        let sum = history
+            .iter()
+            .enumerate()
+            .map(|(i, (from, price))| {
+                let to = history.get(i + 1).map_or(now, |(to, _)| *to);
+                let overlap = to.min(now) - (*from).max(start);
+                overlap.max(0) as u128 * *price as u128
+            })
+            .sum::<u128>();
+
+        prop_assert_eq!(oracle.twap(now, window), Ok((sum / window as u128) as u64));
+    }
+}
+
+struct Setup {
+    mollusk: Mollusk,
+    authority: Pubkey,
+    oracle: Pubkey,
+    bump: u8,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mut mollusk = Mollusk::new(&ID, "target/deploy/twap");
+        mollusk.sysvars.clock.unix_timestamp = NOW;
+
+        let authority = Pubkey::new_unique();
+        let (oracle, bump) =
+            Pubkey::find_program_address(&[ORACLE_SEED.as_bytes(), authority.as_ref()], &ID);
+        let accounts = vec![
+            (
+                authority,
+                Account::new(LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (oracle, Account::default()),
+            keyed_account_for_system_program(),
+        ];
+
+        Self {
+            mollusk,
+            authority,
+            oracle,
+            bump,
+            accounts,
+        }
+    }
+
+    fn instruction_initialize(&self) -> Instruction {
+        let data = InitializeInstructionData::new(self.bump);
+        let ix_accounts = vec![
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new(self.oracle, false),
+            AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(TwapInstruction::Initialize, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_record(&self, authority: &Pubkey, price: u64) -> Instruction {
+        let data = RecordInstructionData::new(price);
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(self.oracle, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(TwapInstruction::Record, &data),
+            ix_accounts,
+        )
+    }
+
+    fn instruction_consult(&self, window: i64) -> Instruction {
+        let data = ConsultInstructionData::new(window);
+        let ix_accounts = vec![AccountMeta::new_readonly(self.oracle, false)];
+        Instruction::new_with_bytes(
+            ID,
+            &instruction_data(TwapInstruction::Consult, &data),
+            ix_accounts,
+        )
+    }
+
+    /// Runs the instruction, checks the result and returns the resulting
+    /// accounts.
+    fn process(&self, instruction: &Instruction, checks: &[Check]) -> Vec<(Pubkey, Account)> {
+        self.mollusk
+            .process_and_validate_instruction(instruction, &self.accounts, checks)
+            .resulting_accounts
+    }
+
+    /// Records `price` at `timestamp`.
+    fn record(&mut self, timestamp: i64, price: u64) {
+        self.mollusk.sysvars.clock.unix_timestamp = timestamp;
+        self.accounts = self.process(
+            &self.instruction_record(&self.authority, price),
+            &[Check::success()],
+        );
+    }
+}
+
+#[test]
+fn test_twap_initialize() {
+    let setup = Setup::new();
+
+    let mut expected = vec![0; Oracle::LEN];
+    expected[..32].copy_from_slice(setup.authority.as_ref());
+    expected[40] = setup.bump;
+
+    setup.process(
+        &setup.instruction_initialize(),
+        &[
+            Check::success(),
+            Check::account(&setup.oracle)
+                .owner(&ID)
+                .data(&expected)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_twap_record_and_consult() {
+    let mut setup = Setup::new();
+    setup.accounts = setup.process(&setup.instruction_initialize(), &[Check::success()]);
+
+    // No price yet.
+    setup.process(
+        &setup.instruction_consult(1),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Twap.code(TwapError::InsufficientHistory as u32),
+        ))],
+    );
+
+    setup.record(NOW, 100);
+    setup.record(NOW + 10, 200);
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + 40;
+    setup.accounts = setup.process(
+        &setup.instruction_record(&setup.authority, 400),
+        &[
+            Check::success(),
+            Check::account(&setup.oracle)
+                .data_slice(COUNT_OFFSET, &3u64.to_le_bytes())
+                .data_slice(
+                    OBSERVATIONS_OFFSET + 2 * Observation::LEN,
+                    &observation(NOW + 40, 400, 100 * 10 + 200 * 30),
+                )
+                .build(),
+        ],
+    );
+
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + 45;
+    for (window, twap) in [
+        (5, 400),
+        (35, (200 * 30 + 400 * 5) / 35),
+        (45, (100 * 10 + 200 * 30 + 400 * 5) / 45),
+    ] {
+        setup.process(
+            &setup.instruction_consult(window),
+            &[
+                Check::success(),
+                Check::return_data(&(twap as u64).to_le_bytes()),
+            ],
+        );
+    }
+
+    for (window, error) in [
+        (46, TwapError::InsufficientHistory),
+        (0, TwapError::InvalidWindow),
+    ] {
+        setup.process(
+            &setup.instruction_consult(window),
+            &[Check::err(ProgramError::Custom(
+                Namespace::Twap.code(error as u32),
+            ))],
+        );
+    }
+}
+
+#[test]
+fn test_twap_record_invalid() {
+    let mut setup = Setup::new();
+    setup.accounts = setup.process(&setup.instruction_initialize(), &[Check::success()]);
+    setup.record(NOW, 100);
+
+    // Only one observation per second.
+    setup.process(
+        &setup.instruction_record(&setup.authority, 200),
+        &[Check::err(ProgramError::Custom(
+            Namespace::Twap.code(TwapError::ObservationTooEarly as u32),
+        ))],
+    );
+
+    // Only the authority can record.
+    let attacker = Pubkey::new_unique();
+    let mut accounts = setup.accounts.clone();
+    accounts.push((attacker, Account::default()));
+    setup.mollusk.sysvars.clock.unix_timestamp = NOW + 1;
+    setup.mollusk.process_and_validate_instruction(
+        &setup.instruction_record(&attacker, 200),
+        &accounts,
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
@@ -0,0 +1,280 @@
+#![no_std]
+
+use common::{assert_layout, info, keys_eq};
+use core::mem;
+
+use common::checks::{require_owned_by, require_signer};
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+use pinocchio_system::instructions::CreateAccount;
+
+pub mod loader;
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("3J1DXBBTVwdNXC5ivNrmHkatkgCLsU5YQnAmgHQ11a8x");
+
+pub const MANAGER_SEED: &str = "manager";
+
+/// On-chain representation of an upgrade manager.
+///
+/// The manager PDA is meant to be the upgrade authority of `program`. Only
+/// `admin` can make the manager sign loader instructions on its behalf.
+#[repr(C)]
+pub struct Manager {
+    pub admin: Pubkey,
+    /// The managed program.
+    pub program: Pubkey,
+    pub bump: u8,
+}
+
+assert_layout!(Manager, size = 65, align = 1, { admin: 0, program: 32, bump: 64 });
+
+impl Manager {
+    pub const LEN: usize = mem::size_of::<Self>();
+}
+
+/// Upgrade manager program instruction discriminators.
+#[repr(u8)]
+pub enum UpgradeManagerInstruction {
+    /// Creates a manager PDA for a program.
+    Initialize,
+    /// Upgrades the managed program from a buffer.
+    Upgrade,
+    /// Hands over the upgrade authority of the managed program.
+    SetAuthority,
+}
+
+impl TryFrom<&u8> for UpgradeManagerInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::Initialize),
+            1 => Ok(Self::Upgrade),
+            2 => Ok(Self::SetAuthority),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct InitializeInstructionData {
+    pub bump: u8,
+}
+
+impl InitializeInstructionData {
+    pub fn new(bump: u8) -> Self {
+        Self { bump }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = UpgradeManagerInstruction::try_from(instruction)?;
+
+    match instruction {
+        UpgradeManagerInstruction::Initialize => process_initialize(accounts, instruction_data),
+        UpgradeManagerInstruction::Upgrade => process_upgrade(accounts),
+        UpgradeManagerInstruction::SetAuthority => process_set_authority(accounts),
+    }
+}
+
+/// Checks that `admin` signed and is the admin of `manager`, which has to be
+/// the manager of `program`. Returns the bump of the manager PDA.
+fn check_manager(
+    admin: &AccountInfo,
+    manager: &AccountInfo,
+    program: &AccountInfo,
+) -> Result<u8, ProgramError> {
+    require_signer(admin)?;
+
+    // Check if the manager PDA is owned by the program.
+    require_owned_by(manager, &ID)?;
+
+    // Deserialize the manager PDA.
+    let data = manager.try_borrow_data()?;
+    if data.len() != Manager::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data: &Manager = unsafe { &*data.as_ptr().cast() };
+
+    // Check that `admin` is the same as in the manager account.
+    if !keys_eq(&data.admin, admin.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    // Check that the manager belongs to `program`.
+    if !keys_eq(&data.program, program.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(data.bump)
+}
+
+/// Creates a manager PDA for `program`, administered by the current upgrade
+/// authority of `program`.
+///
+/// The upgrade authority of `program` has to be transferred to the manager
+/// PDA separately, with a `SetAuthority` instruction signed by the current
+/// authority.
+pub fn process_initialize(accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, manager, program, programdata, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    require_signer(admin)?;
+
+    // Check that `program` is an upgradeable program.
+    if !program.is_owned_by(&loader::ID) || !program.executable() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    // Check that `admin` can upgrade `program`. There is only one manager
+    // PDA per program, so otherwise anyone could take it before the upgrade
+    // authority does.
+    if &loader::programdata_address(program)? != programdata.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if loader::upgrade_authority(programdata)?.as_ref() != Some(admin.key()) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    // Deserialize instruction data.
+    let instruction_data = InitializeInstructionData::from_bytes(instruction_data)?;
+
+    // Check the seeds of `manager`.
+    let manager_pda = create_program_address(
+        &[
+            MANAGER_SEED.as_bytes(),
+            program.key(),
+            &[instruction_data.bump],
+        ],
+        &ID,
+    )?;
+    if !keys_eq(manager.key(), &manager_pda) {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // Create the manager PDA.
+    let bump = [instruction_data.bump];
+    let seeds = [
+        Seed::from(MANAGER_SEED.as_bytes()),
+        Seed::from(program.key()),
+        Seed::from(&bump),
+    ];
+    CreateAccount {
+        from: admin,
+        to: manager,
+        lamports: Rent::get()?.minimum_balance(Manager::LEN),
+        space: Manager::LEN as u64,
+        owner: &ID,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    // Deserialize the manager PDA.
+    let mut data = manager.try_borrow_mut_data()?;
+    let data: &mut Manager = unsafe { &mut *data.as_mut_ptr().cast() };
+
+    // Initialize the manager.
+    data.admin = *admin.key();
+    data.program = *program.key();
+    data.bump = instruction_data.bump;
+
+    info!("Initialized upgrade manager");
+
+    Ok(())
+}
+
+/// Upgrades the managed program with the contents of `buffer`.
+///
+/// The authority of `buffer` has to be the manager PDA as well, the loader
+/// refuses to upgrade from a buffer with a different authority.
+pub fn process_upgrade(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, manager, program, programdata, buffer, spill, rent_sysvar, clock_sysvar, _loader] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let bump = [check_manager(admin, manager, program)?];
+    let seeds = [
+        Seed::from(MANAGER_SEED.as_bytes()),
+        Seed::from(program.key()),
+        Seed::from(&bump),
+    ];
+
+    // Upgrade the program, with the manager PDA signing as the upgrade
+    // authority. The loader checks that `programdata` belongs to `program`.
+    loader::Upgrade {
+        programdata,
+        program,
+        buffer,
+        spill,
+        rent_sysvar,
+        clock_sysvar,
+        authority: manager,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Upgraded program");
+
+    Ok(())
+}
+
+/// Transfers the upgrade authority of the managed program to
+/// `new_authority`. The manager PDA is useless afterwards.
+pub fn process_set_authority(accounts: &[AccountInfo]) -> ProgramResult {
+    // Retrieve and validate the accounts.
+    let [admin, manager, program, programdata, new_authority, _loader] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let bump = [check_manager(admin, manager, program)?];
+    let seeds = [
+        Seed::from(MANAGER_SEED.as_bytes()),
+        Seed::from(program.key()),
+        Seed::from(&bump),
+    ];
+
+    // Check that `programdata` belongs to `program`. Unlike `Upgrade`,
+    // `SetAuthority` doesn't take the program account, so the loader can't
+    // check it.
+    if &loader::programdata_address(program)? != programdata.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    loader::SetAuthority {
+        account: programdata,
+        authority: manager,
+        new_authority,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])?;
+
+    info!("Transferred upgrade authority");
+
+    Ok(())
+}
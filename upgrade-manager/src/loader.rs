@@ -0,0 +1,185 @@
+//! Instructions of the upgradeable BPF loader.
+//!
+//! There is no pinocchio crate for the loader, so the CPIs are built by hand,
+//! the same way `pinocchio-system` and `pinocchio-token` do it.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+/// Upgradeable BPF loader program ID.
+pub const ID: Pubkey = pinocchio_pubkey::pubkey!("BPFLoaderUpgradeab1e11111111111111111111111");
+
+/// Returns the address of the ProgramData account of an upgradeable
+/// program.
+pub fn programdata_address(program: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    if !program.is_owned_by(&ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // The account data is a bincode-serialized `UpgradeableLoaderState`:
+    // -  [0..4 ]: enum discriminator, `2` for `Program`
+    // -  [4..36]: ProgramData address
+    let data = program.try_borrow_data()?;
+    if data.len() < 36 || data[..4] != 2u32.to_le_bytes() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mut address = [0; 32];
+    address.copy_from_slice(&data[4..36]);
+    Ok(address)
+}
+
+/// Returns the upgrade authority of an upgradeable program, read from its
+/// ProgramData account.
+pub fn upgrade_authority(programdata: &AccountInfo) -> Result<Option<Pubkey>, ProgramError> {
+    if !programdata.is_owned_by(&ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // The account data is a bincode-serialized `UpgradeableLoaderState`:
+    // -  [0..4  ]: enum discriminator, `3` for `ProgramData`
+    // -  [4..12 ]: slot of the last deployment
+    // -  [12    ]: `Option` tag of the upgrade authority
+    // -  [13..45]: upgrade authority
+    let data = programdata.try_borrow_data()?;
+    if data.len() < 45 || data[..4] != 3u32.to_le_bytes() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if data[12] == 0 {
+        return Ok(None);
+    }
+    let mut authority = [0; 32];
+    authority.copy_from_slice(&data[13..45]);
+    Ok(Some(authority))
+}
+
+/// Upgrade a program.
+///
+/// ### Accounts:
+///   0. `[WRITE]` The ProgramData account
+///   1. `[WRITE]` The Program account
+///   2. `[WRITE]` The Buffer account with the new program data
+///   3. `[WRITE]` The spill account
+///   4. `[]` Rent sysvar
+///   5. `[]` Clock sysvar
+///   6. `[SIGNER]` The program's authority
+pub struct Upgrade<'a> {
+    /// ProgramData account.
+    pub programdata: &'a AccountInfo,
+
+    /// Program account.
+    pub program: &'a AccountInfo,
+
+    /// Buffer account.
+    pub buffer: &'a AccountInfo,
+
+    /// Spill account, receiving the lamports left over in the buffer.
+    pub spill: &'a AccountInfo,
+
+    /// Rent sysvar.
+    pub rent_sysvar: &'a AccountInfo,
+
+    /// Clock sysvar.
+    pub clock_sysvar: &'a AccountInfo,
+
+    /// Upgrade authority.
+    pub authority: &'a AccountInfo,
+}
+
+impl Upgrade<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 7] = [
+            AccountMeta::writable(self.programdata.key()),
+            AccountMeta::writable(self.program.key()),
+            AccountMeta::writable(self.buffer.key()),
+            AccountMeta::writable(self.spill.key()),
+            AccountMeta::readonly(self.rent_sysvar.key()),
+            AccountMeta::readonly(self.clock_sysvar.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        // instruction data
+        // -  [0..4]: instruction discriminator
+        let instruction_data = 3u32.to_le_bytes();
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.programdata,
+                self.program,
+                self.buffer,
+                self.spill,
+                self.rent_sysvar,
+                self.clock_sysvar,
+                self.authority,
+            ],
+            signers,
+        )
+    }
+}
+
+/// Set a new authority of a Buffer or ProgramData account.
+///
+/// ### Accounts:
+///   0. `[WRITE]` The Buffer or ProgramData account
+///   1. `[SIGNER]` The current authority
+///   2. `[]` The new authority
+pub struct SetAuthority<'a> {
+    /// Buffer or ProgramData account.
+    pub account: &'a AccountInfo,
+
+    /// Current authority.
+    pub authority: &'a AccountInfo,
+
+    /// New authority.
+    pub new_authority: &'a AccountInfo,
+}
+
+impl SetAuthority<'_> {
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        // account metadata
+        let account_metas: [AccountMeta; 3] = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+            AccountMeta::readonly(self.new_authority.key()),
+        ];
+
+        // instruction data
+        // -  [0..4]: instruction discriminator
+        let instruction_data = 4u32.to_le_bytes();
+
+        let instruction = Instruction {
+            program_id: &ID,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(
+            &instruction,
+            &[self.account, self.authority, self.new_authority],
+            signers,
+        )
+    }
+}
@@ -0,0 +1,244 @@
+//! Attacks on the upgrade manager program, each rejected with its own error
+//! before any CPI, so the upgradeable loader doesn't have to be loaded.
+
+use std::mem;
+
+use common::encoding::Encoding;
+use mollusk_svm::{
+    program::{
+        create_program_account_loader_v3, keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::Check,
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use upgrade_manager::{
+    InitializeInstructionData, Manager, UpgradeManagerInstruction, MANAGER_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(upgrade_manager::ID);
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: UpgradeManagerInstruction, data: &T) -> Vec<u8> {
+    let mut data_with_discriminator = vec![0; mem::size_of::<UpgradeManagerInstruction>() + T::LEN];
+    data_with_discriminator[0] = discriminator as u8;
+    data.encode(&mut data_with_discriminator[1..]);
+    data_with_discriminator
+}
+
+/// Returns the manager PDA of `program` and its bump.
+fn manager_pda(program: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MANAGER_SEED.as_bytes(), program.as_ref()], &ID)
+}
+
+/// Returns the ProgramData address of `program`.
+fn programdata_address(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program.as_ref()], &LOADER_V3).0
+}
+
+/// Returns a manager of `program`, administered by `admin`.
+fn manager_account(mollusk: &Mollusk, admin: &Pubkey, program: &Pubkey) -> Account {
+    let (_, bump) = manager_pda(program);
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Manager::LEN),
+        Manager::LEN,
+        &ID,
+    );
+    account.data[..32].copy_from_slice(admin.as_ref());
+    account.data[32..64].copy_from_slice(program.as_ref());
+    account.data[64] = bump;
+    account
+}
+
+/// Returns a ProgramData account with `authority` as the upgrade authority.
+fn programdata_account(mollusk: &Mollusk, authority: &Pubkey) -> Account {
+    let data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(*authority),
+    })
+    .unwrap();
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &LOADER_V3,
+    );
+    account.data = data;
+    account
+}
+
+/// Accounts of a program whose upgrade authority is its manager PDA,
+/// administered by an admin, and of an attacker whose own program is
+/// managed the same way.
+struct Setup {
+    mollusk: Mollusk,
+    admin: Pubkey,
+    manager: Pubkey,
+    program: Pubkey,
+    attacker: Pubkey,
+    attacker_manager: Pubkey,
+    attacker_program: Pubkey,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let mollusk = Mollusk::new(&ID, "target/deploy/upgrade_manager");
+
+        let admin = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let (manager, _) = manager_pda(&program);
+        let attacker = Pubkey::new_unique();
+        let attacker_program = Pubkey::new_unique();
+        let (attacker_manager, _) = manager_pda(&attacker_program);
+
+        let accounts = vec![
+            (admin, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (manager, manager_account(&mollusk, &admin, &program)),
+            (program, create_program_account_loader_v3(&program)),
+            (
+                programdata_address(&program),
+                programdata_account(&mollusk, &manager),
+            ),
+            (attacker, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (
+                attacker_manager,
+                manager_account(&mollusk, &attacker, &attacker_program),
+            ),
+            (
+                attacker_program,
+                create_program_account_loader_v3(&attacker_program),
+            ),
+            (
+                programdata_address(&attacker_program),
+                programdata_account(&mollusk, &attacker_manager),
+            ),
+            keyed_account_for_system_program(),
+            (LOADER_V3, Account::default()),
+        ];
+
+        Self {
+            mollusk,
+            admin,
+            manager,
+            program,
+            attacker,
+            attacker_manager,
+            attacker_program,
+            accounts,
+        }
+    }
+
+    /// Adds an account, or replaces the one at its address.
+    fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.retain(|(other, _)| *other != address);
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Hands the upgrade authority of the program over to the attacker, as
+    /// `admin` of `manager`.
+    fn instruction_set_authority(&self, admin: &Pubkey, manager: &Pubkey) -> Instruction {
+        let ix_accounts = vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new_readonly(*manager, false),
+            AccountMeta::new_readonly(self.program, false),
+            AccountMeta::new(programdata_address(&self.program), false),
+            AccountMeta::new_readonly(self.attacker, false),
+            AccountMeta::new_readonly(LOADER_V3, false),
+        ];
+        Instruction::new_with_bytes(
+            ID,
+            &[UpgradeManagerInstruction::SetAuthority as u8],
+            ix_accounts,
+        )
+    }
+
+    fn expect_err(&self, instruction: &Instruction, err: ProgramError) {
+        self.mollusk.process_and_validate_instruction(
+            instruction,
+            &self.accounts,
+            &[Check::err(err)],
+        );
+    }
+}
+
+#[test]
+fn test_missing_admin_signature() {
+    let setup = Setup::new();
+    let mut instruction = setup.instruction_set_authority(&setup.admin, &setup.manager);
+    instruction.accounts[0].is_signer = false;
+    setup.expect_err(&instruction, ProgramError::MissingRequiredSignature);
+}
+
+/// The attacker creates the manager of a program before its upgrade
+/// authority does, proving the upgrade authority over their own program
+/// instead.
+#[test]
+fn test_initialize_with_programdata_of_other_program() {
+    let setup = Setup::new();
+    let program = Pubkey::new_unique();
+    let (manager, bump) = manager_pda(&program);
+    let attacker_programdata = programdata_address(&setup.attacker_program);
+    let attacker_programdata_account = programdata_account(&setup.mollusk, &setup.attacker);
+    let program_account = create_program_account_loader_v3(&program);
+    let setup = setup
+        .with(program, program_account)
+        .with(manager, Account::default())
+        .with(attacker_programdata, attacker_programdata_account);
+    let ix_accounts = vec![
+        AccountMeta::new(setup.attacker, true),
+        AccountMeta::new(manager, false),
+        AccountMeta::new_readonly(program, false),
+        AccountMeta::new_readonly(attacker_programdata, false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    let instruction = Instruction::new_with_bytes(
+        ID,
+        &instruction_data(
+            UpgradeManagerInstruction::Initialize,
+            &InitializeInstructionData::new(bump),
+        ),
+        ix_accounts,
+    );
+    setup.expect_err(&instruction, ProgramError::InvalidAccountData);
+}
+
+/// The attacker takes over the upgrade authority of the program.
+#[test]
+fn test_set_authority_as_other_admin() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_set_authority(&setup.attacker, &setup.manager),
+        ProgramError::IllegalOwner,
+    );
+}
+
+/// The attacker, as the admin of the manager of their own program, takes
+/// over the upgrade authority of the program.
+#[test]
+fn test_set_authority_with_manager_of_other_program() {
+    let setup = Setup::new();
+    setup.expect_err(
+        &setup.instruction_set_authority(&setup.attacker, &setup.attacker_manager),
+        ProgramError::InvalidAccountData,
+    );
+}
+
+/// A manager of the program administered by the attacker, owned by another
+/// program.
+#[test]
+fn test_set_authority_with_spoofed_manager() {
+    let setup = Setup::new();
+    let mut manager_account = manager_account(&setup.mollusk, &setup.attacker, &setup.program);
+    manager_account.owner = Pubkey::new_unique();
+    let manager = Pubkey::new_unique();
+    let setup = setup.with(manager, manager_account);
+    setup.expect_err(
+        &setup.instruction_set_authority(&setup.attacker, &manager),
+        ProgramError::IllegalOwner,
+    );
+}
@@ -0,0 +1,392 @@
+use std::mem;
+
+use mollusk_svm::{
+    file::load_program_elf,
+    program::{
+        create_keyed_account_for_builtin_program, create_program_account_loader_v3,
+        keyed_account_for_system_program, loader_keys::LOADER_V3,
+    },
+    result::{Check, ProgramResult},
+    Mollusk,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_loader_v3_interface::state::UpgradeableLoaderState;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use upgrade_manager::{
+    InitializeInstructionData, Manager, UpgradeManagerInstruction, MANAGER_SEED,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(upgrade_manager::ID);
+
+fn manager_address(program: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MANAGER_SEED.as_bytes(), program.as_ref()], &ID)
+}
+
+fn programdata_address(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program.as_ref()], &LOADER_V3).0
+}
+
+fn instruction_initialize(
+    admin: &Pubkey,
+    manager: &Pubkey,
+    program: &Pubkey,
+    bump: u8,
+) -> Instruction {
+    // Create instruction data.
+    let data = InitializeInstructionData::new(bump);
+    // Serialize instruction data to bytes.
+    let data = data.to_bytes();
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> = Vec::with_capacity(
+        mem::size_of::<UpgradeManagerInstruction>() + InitializeInstructionData::LEN,
+    );
+    data_with_discriminator.push(UpgradeManagerInstruction::Initialize as u8);
+    data_with_discriminator.extend_from_slice(&data);
+
+    let ix_accounts = vec![
+        AccountMeta::new(*admin, true),
+        AccountMeta::new(*manager, false),
+        AccountMeta::new_readonly(*program, false),
+        AccountMeta::new_readonly(programdata_address(program), false),
+        AccountMeta::new_readonly(keyed_account_for_system_program().0, false),
+    ];
+    Instruction::new_with_bytes(ID, &data_with_discriminator, ix_accounts)
+}
+
+fn instruction_upgrade(
+    admin: &Pubkey,
+    manager: &Pubkey,
+    program: &Pubkey,
+    buffer: &Pubkey,
+    spill: &Pubkey,
+) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new_readonly(*manager, false),
+        AccountMeta::new(*program, false),
+        AccountMeta::new(programdata_address(program), false),
+        AccountMeta::new(*buffer, false),
+        AccountMeta::new(*spill, false),
+        AccountMeta::new_readonly(solana_sdk_ids::sysvar::rent::ID, false),
+        AccountMeta::new_readonly(solana_sdk_ids::sysvar::clock::ID, false),
+        AccountMeta::new_readonly(LOADER_V3, false),
+    ];
+    Instruction::new_with_bytes(ID, &[UpgradeManagerInstruction::Upgrade as u8], ix_accounts)
+}
+
+fn instruction_set_authority(
+    admin: &Pubkey,
+    manager: &Pubkey,
+    program: &Pubkey,
+    programdata: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    let ix_accounts = vec![
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new_readonly(*manager, false),
+        AccountMeta::new_readonly(*program, false),
+        AccountMeta::new(*programdata, false),
+        AccountMeta::new_readonly(*new_authority, false),
+        AccountMeta::new_readonly(LOADER_V3, false),
+    ];
+    Instruction::new_with_bytes(
+        ID,
+        &[UpgradeManagerInstruction::SetAuthority as u8],
+        ix_accounts,
+    )
+}
+
+fn keyed_account_for_loader_v3_program() -> (Pubkey, Account) {
+    create_keyed_account_for_builtin_program(&LOADER_V3, "solana_bpf_loader_upgradeable_program")
+}
+
+fn manager_account(mollusk: &Mollusk, admin: &Pubkey, program: &Pubkey, bump: u8) -> Account {
+    let mut data = vec![0; Manager::LEN];
+    data[0..32].copy_from_slice(admin.as_ref());
+    data[32..64].copy_from_slice(program.as_ref());
+    data[64] = bump;
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Manager::LEN),
+        Manager::LEN,
+        &ID,
+    );
+    account.data = data;
+    account
+}
+
+/// Creates an account of the upgradeable loader with the given metadata,
+/// followed by `elf`.
+fn loader_account(mollusk: &Mollusk, state: &UpgradeableLoaderState, elf: &[u8]) -> Account {
+    let mut data = bincode::serialize(state).unwrap();
+    data.extend_from_slice(elf);
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &LOADER_V3,
+    );
+    account.data = data;
+    account
+}
+
+fn programdata_account(mollusk: &Mollusk, authority: &Pubkey, elf: &[u8]) -> Account {
+    loader_account(
+        mollusk,
+        &UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(*authority),
+        },
+        elf,
+    )
+}
+
+#[test]
+fn test_upgrade_manager_initialize() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/upgrade_manager");
+
+    let admin = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let (manager, bump) = manager_address(&program);
+
+    let mut expected = admin.to_bytes().to_vec();
+    expected.extend_from_slice(program.as_ref());
+    expected.push(bump);
+
+    let res = mollusk.process_and_validate_instruction(
+        &instruction_initialize(&admin, &manager, &program, bump),
+        &[
+            (admin, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (manager, Account::default()),
+            (program, create_program_account_loader_v3(&program)),
+            (
+                programdata_address(&program),
+                programdata_account(&mollusk, &admin, &[0; 64]),
+            ),
+            keyed_account_for_system_program(),
+        ],
+        &[
+            Check::success(),
+            Check::account(&manager)
+                .owner(&ID)
+                .space(Manager::LEN)
+                .data(&expected)
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_upgrade_manager_initialize_not_a_program() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/upgrade_manager");
+
+    let admin = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let (manager, bump) = manager_address(&program);
+
+    mollusk.process_and_validate_instruction(
+        &instruction_initialize(&admin, &manager, &program, bump),
+        &[
+            (admin, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (manager, Account::default()),
+            (program, Account::default()),
+            (programdata_address(&program), Account::default()),
+            keyed_account_for_system_program(),
+        ],
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+}
+
+#[test]
+fn test_upgrade_manager_initialize_not_upgrade_authority() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/upgrade_manager");
+
+    let admin = Pubkey::new_unique();
+    let upgrade_authority = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let (manager, bump) = manager_address(&program);
+
+    // Only the upgrade authority of the program can create its manager.
+    mollusk.process_and_validate_instruction(
+        &instruction_initialize(&admin, &manager, &program, bump),
+        &[
+            (admin, Account::new(1_000_000_000, 0, &Pubkey::default())),
+            (manager, Account::default()),
+            (program, create_program_account_loader_v3(&program)),
+            (
+                programdata_address(&program),
+                programdata_account(&mollusk, &upgrade_authority, &[0; 64]),
+            ),
+            keyed_account_for_system_program(),
+        ],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+#[test]
+fn test_upgrade_manager_upgrade() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/upgrade_manager");
+    // The loader refuses to upgrade a program in the slot it was deployed in.
+    mollusk.warp_to_slot(10);
+
+    let admin = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let programdata = programdata_address(&program);
+    let buffer = Pubkey::new_unique();
+    let spill = Pubkey::new_unique();
+    let (manager, bump) = manager_address(&program);
+
+    // Any valid program works as the new version, so just use this one.
+    let elf = load_program_elf("upgrade_manager");
+    let buffer_account = loader_account(
+        &mollusk,
+        &UpgradeableLoaderState::Buffer {
+            authority_address: Some(manager),
+        },
+        &elf,
+    );
+    let buffer_lamports = buffer_account.lamports;
+
+    let res = mollusk.process_and_validate_instruction(
+        &instruction_upgrade(&admin, &manager, &program, &buffer, &spill),
+        &[
+            (admin, Account::default()),
+            (manager, manager_account(&mollusk, &admin, &program, bump)),
+            (program, create_program_account_loader_v3(&program)),
+            (programdata, programdata_account(&mollusk, &manager, &elf)),
+            (buffer, buffer_account),
+            (spill, Account::default()),
+            mollusk.sysvars.keyed_account_for_rent_sysvar(),
+            mollusk.sysvars.keyed_account_for_clock_sysvar(),
+            keyed_account_for_loader_v3_program(),
+        ],
+        &[
+            Check::success(),
+            // The deployment slot is updated and the authority is kept.
+            Check::account(&programdata)
+                .data_slice(4, &10u64.to_le_bytes())
+                .data_slice(12, &[1])
+                .data_slice(13, manager.as_ref())
+                .build(),
+            // Lamports of the buffer go to the spill account.
+            Check::account(&buffer).lamports(0).build(),
+            Check::account(&spill).lamports(buffer_lamports).build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_upgrade_manager_upgrade_wrong_admin() {
+    let mut mollusk = Mollusk::new(&ID, "target/deploy/upgrade_manager");
+    mollusk.warp_to_slot(10);
+
+    let admin = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let programdata = programdata_address(&program);
+    let buffer = Pubkey::new_unique();
+    let (manager, bump) = manager_address(&program);
+
+    let elf = load_program_elf("upgrade_manager");
+    mollusk.process_and_validate_instruction(
+        &instruction_upgrade(&attacker, &manager, &program, &buffer, &attacker),
+        &[
+            (attacker, Account::default()),
+            (manager, manager_account(&mollusk, &admin, &program, bump)),
+            (program, create_program_account_loader_v3(&program)),
+            (programdata, programdata_account(&mollusk, &manager, &elf)),
+            (
+                buffer,
+                loader_account(
+                    &mollusk,
+                    &UpgradeableLoaderState::Buffer {
+                        authority_address: Some(manager),
+                    },
+                    &elf,
+                ),
+            ),
+            mollusk.sysvars.keyed_account_for_rent_sysvar(),
+            mollusk.sysvars.keyed_account_for_clock_sysvar(),
+            keyed_account_for_loader_v3_program(),
+        ],
+        &[Check::err(ProgramError::IllegalOwner)],
+    );
+}
+
+#[test]
+fn test_upgrade_manager_set_authority() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/upgrade_manager");
+
+    let admin = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let programdata = programdata_address(&program);
+    let (manager, bump) = manager_address(&program);
+
+    let res = mollusk.process_and_validate_instruction(
+        &instruction_set_authority(&admin, &manager, &program, &programdata, &new_authority),
+        &[
+            (admin, Account::default()),
+            (manager, manager_account(&mollusk, &admin, &program, bump)),
+            (program, create_program_account_loader_v3(&program)),
+            (
+                programdata,
+                programdata_account(&mollusk, &manager, &[0; 64]),
+            ),
+            (new_authority, Account::default()),
+            keyed_account_for_loader_v3_program(),
+        ],
+        &[
+            Check::success(),
+            Check::account(&programdata)
+                .data_slice(12, &[1])
+                .data_slice(13, new_authority.as_ref())
+                .build(),
+        ],
+    );
+    assert!(matches!(res.program_result, ProgramResult::Success));
+}
+
+#[test]
+fn test_upgrade_manager_set_authority_wrong_programdata() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/upgrade_manager");
+
+    let admin = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+    let program = Pubkey::new_unique();
+    let other_program = Pubkey::new_unique();
+    let (manager, bump) = manager_address(&program);
+    let (other_manager, _) = manager_address(&other_program);
+
+    // The programdata of a program managed by a different manager must not
+    // be accepted in place of the managed program's one.
+    let other_programdata = programdata_address(&other_program);
+    mollusk.process_and_validate_instruction(
+        &instruction_set_authority(
+            &admin,
+            &manager,
+            &program,
+            &other_programdata,
+            &new_authority,
+        ),
+        &[
+            (admin, Account::default()),
+            (manager, manager_account(&mollusk, &admin, &program, bump)),
+            (program, create_program_account_loader_v3(&program)),
+            (
+                other_programdata,
+                programdata_account(&mollusk, &other_manager, &[0; 64]),
+            ),
+            (new_authority, Account::default()),
+            keyed_account_for_loader_v3_program(),
+        ],
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
@@ -0,0 +1,302 @@
+#![no_std]
+
+use common::info;
+use errors::Namespace;
+use macros::InstructionData;
+use pinocchio::{
+    account_info::AccountInfo, cpi::set_return_data, program_error::ProgramError, pubkey::Pubkey,
+    ProgramResult,
+};
+#[cfg(target_os = "solana")]
+use pinocchio::{no_allocator, nostd_panic_handler, program_entrypoint};
+
+#[cfg(target_os = "solana")]
+program_entrypoint!(process_instruction);
+#[cfg(target_os = "solana")]
+no_allocator!();
+#[cfg(target_os = "solana")]
+nostd_panic_handler!();
+#[cfg(target_os = "solana")]
+common::build_metadata!();
+
+pinocchio_pubkey::declare_id!("3x7tghWvMKwyzeV9b7ViApiJQMHTyeijVNQjsM5aNcWQ");
+
+/// Errors returned by the wide math program.
+#[repr(u32)]
+pub enum WideMathError {
+    /// The result doesn't fit into 128 bits.
+    Overflow,
+    /// The denominator is zero.
+    DivisionByZero,
+    /// The rounding mode is unknown.
+    InvalidRounding,
+}
+
+impl From<WideMathError> for ProgramError {
+    fn from(e: WideMathError) -> Self {
+        Namespace::WideMath.error(e as u32)
+    }
+}
+
+/// Rounding mode of a division.
+///
+/// Protocols should round in their own favor: down when paying out, up when
+/// charging.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// Towards zero.
+    Down,
+    /// Away from zero.
+    Up,
+    /// To the nearest integer, halves away from zero.
+    Nearest,
+}
+
+impl TryFrom<u8> for Rounding {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Down),
+            1 => Ok(Self::Up),
+            2 => Ok(Self::Nearest),
+            _ => Err(WideMathError::InvalidRounding.into()),
+        }
+    }
+}
+
+/// Multiplies two 128-bit integers into a 256-bit product, returned as
+/// `(high, low)` halves.
+///
+/// Every 64-bit partial product fits into 128 bits, so the product is
+/// assembled from four of them like in schoolbook multiplication.
+pub fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+    let lo_lo = a_lo * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_lo = a_hi * b_lo;
+    let hi_hi = a_hi * b_hi;
+
+    // Sum of the middle 64-bit column, at most 3 * (2^64 - 1).
+    let mid = (lo_lo >> 64) + (lo_hi & MASK) + (hi_lo & MASK);
+
+    let low = (lo_lo & MASK) | (mid << 64);
+    let high = hi_hi + (lo_hi >> 64) + (hi_lo >> 64) + (mid >> 64);
+    (high, low)
+}
+
+/// Divides the 256-bit integer `(high, low)` by `divisor`, returning the
+/// quotient and the remainder.
+///
+/// Returns `None` if the divisor is zero or the quotient doesn't fit into
+/// 128 bits, which is the case when `high >= divisor`.
+pub fn div_wide(high: u128, low: u128, divisor: u128) -> Option<(u128, u128)> {
+    if divisor == 0 || high >= divisor {
+        return None;
+    }
+    // Avoid the slow path when the dividend fits into 128 bits.
+    if high == 0 {
+        return Some((low / divisor, low % divisor));
+    }
+
+    // Binary long division. The remainder is always below the divisor, but
+    // shifting it can overflow 128 bits, so the shifted out bit is kept.
+    let mut quotient = 0;
+    let mut remainder = high;
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    Some((quotient, remainder))
+}
+
+/// Computes `a * b / denominator` with the given rounding, without
+/// overflowing in the intermediate product.
+///
+/// This is the basic operation of fixed-point math, e.g. converting an
+/// amount with a price scaled by `10^18`:
+/// `mul_div(amount, price, 10^18, Rounding::Down)`.
+pub fn mul_div(
+    a: u128,
+    b: u128,
+    denominator: u128,
+    rounding: Rounding,
+) -> Result<u128, WideMathError> {
+    if denominator == 0 {
+        return Err(WideMathError::DivisionByZero);
+    }
+
+    let (high, low) = widening_mul(a, b);
+    let (quotient, remainder) = div_wide(high, low, denominator).ok_or(WideMathError::Overflow)?;
+
+    let round_up = match rounding {
+        Rounding::Down => false,
+        Rounding::Up => remainder != 0,
+        // `remainder * 2 >= denominator` without overflowing.
+        Rounding::Nearest => remainder >= denominator - remainder,
+    };
+    if round_up {
+        quotient.checked_add(1).ok_or(WideMathError::Overflow)
+    } else {
+        Ok(quotient)
+    }
+}
+
+/// Computes the square root of `value` with the given rounding.
+///
+/// Used e.g. for the initial liquidity of constant product pools,
+/// `sqrt(x * y)`. The result always fits, as the square root of a 128-bit
+/// integer has at most 64 bits.
+pub fn sqrt(value: u128, rounding: Rounding) -> u128 {
+    if value < 2 {
+        return value;
+    }
+
+    // Newton's method, starting from a power of two above the root, so the
+    // iterations decrease monotonically towards the floor of the root.
+    let bits = 128 - value.leading_zeros();
+    let mut root = 1u128 << bits.div_ceil(2);
+    loop {
+        let next = (root + value / root) / 2;
+        if next >= root {
+            break;
+        }
+        root = next;
+    }
+
+    let round_up = match rounding {
+        Rounding::Down => false,
+        Rounding::Up => root * root < value,
+        // The root is nearer to `root + 1` if `value >= (root + 0.5)^2`,
+        // which is `value > root^2 + root` in integers.
+        Rounding::Nearest => value - root * root > root,
+    };
+    if round_up {
+        root + 1
+    } else {
+        root
+    }
+}
+
+/// Wide math program instruction discriminators.
+///
+/// Every instruction sets its result as a 16-byte little-endian return data.
+#[repr(u8)]
+pub enum WideMathInstruction {
+    /// Computes `a * b / denominator`.
+    MulDiv,
+    /// Computes the square root of a value.
+    Sqrt,
+}
+
+impl TryFrom<&u8> for WideMathInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(Self::MulDiv),
+            1 => Ok(Self::Sqrt),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+/// 128-bit integers are passed as little-endian bytes, as the alignment of
+/// `u128` differs between the host and SBF.
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct MulDivInstructionData {
+    pub a: [u8; 16],
+    pub b: [u8; 16],
+    pub denominator: [u8; 16],
+    pub rounding: u8,
+}
+
+impl MulDivInstructionData {
+    pub fn new(a: u128, b: u128, denominator: u128, rounding: Rounding) -> Self {
+        Self {
+            a: a.to_le_bytes(),
+            b: b.to_le_bytes(),
+            denominator: denominator.to_le_bytes(),
+            rounding: rounding as u8,
+        }
+    }
+}
+
+#[derive(InstructionData)]
+#[repr(C)]
+pub struct SqrtInstructionData {
+    pub value: [u8; 16],
+    pub rounding: u8,
+}
+
+impl SqrtInstructionData {
+    pub fn new(value: u128, rounding: Rounding) -> Self {
+        Self {
+            value: value.to_le_bytes(),
+            rounding: rounding as u8,
+        }
+    }
+}
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (instruction, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    let instruction = WideMathInstruction::try_from(instruction)?;
+
+    match instruction {
+        WideMathInstruction::MulDiv => process_mul_div(instruction_data),
+        WideMathInstruction::Sqrt => process_sqrt(instruction_data),
+    }
+}
+
+/// Computes `a * b / denominator` and sets the result as return data.
+pub fn process_mul_div(instruction_data: &[u8]) -> ProgramResult {
+    // Deserialize instruction data.
+    let instruction_data = MulDivInstructionData::from_bytes(instruction_data)?;
+
+    let result = mul_div(
+        u128::from_le_bytes(instruction_data.a),
+        u128::from_le_bytes(instruction_data.b),
+        u128::from_le_bytes(instruction_data.denominator),
+        Rounding::try_from(instruction_data.rounding)?,
+    )?;
+
+    set_return_data(&result.to_le_bytes());
+
+    info!("mul_div succeeded");
+
+    Ok(())
+}
+
+/// Computes the square root of a value and sets the result as return data.
+pub fn process_sqrt(instruction_data: &[u8]) -> ProgramResult {
+    // Deserialize instruction data.
+    let instruction_data = SqrtInstructionData::from_bytes(instruction_data)?;
+
+    let result = sqrt(
+        u128::from_le_bytes(instruction_data.value),
+        Rounding::try_from(instruction_data.rounding)?,
+    );
+
+    set_return_data(&result.to_le_bytes());
+
+    info!("sqrt succeeded");
+
+    Ok(())
+}
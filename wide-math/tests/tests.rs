@@ -0,0 +1,248 @@
+use std::mem;
+
+use common::encoding::Encoding;
+use errors::Namespace;
+use mollusk_svm::{result::Check, Mollusk};
+use proptest::prelude::*;
+use solana_instruction::Instruction;
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use wide_math::{
+    mul_div, sqrt, widening_mul, MulDivInstructionData, Rounding, SqrtInstructionData,
+    WideMathError, WideMathInstruction,
+};
+
+const ID: Pubkey = Pubkey::new_from_array(wide_math::ID);
+
+/// Scale of 18-decimal fixed-point numbers.
+const WAD: u128 = 1_000_000_000_000_000_000;
+
+/// Serializes instruction data and prepends the discriminator.
+fn instruction_data<T: Encoding>(discriminator: WideMathInstruction, data: &T) -> Vec<u8> {
+    // Serialize instruction data to bytes.
+    let mut serialized = vec![0; T::LEN];
+    data.encode(&mut serialized);
+
+    // Construct the full instruction data, consisting of:
+    // * discriminator
+    // * serialized data
+    let mut data_with_discriminator: Vec<u8> =
+        Vec::with_capacity(mem::size_of::<WideMathInstruction>() + T::LEN);
+    data_with_discriminator.push(discriminator as u8);
+    data_with_discriminator.extend_from_slice(&serialized);
+    data_with_discriminator
+}
+
+fn instruction_mul_div(a: u128, b: u128, denominator: u128, rounding: Rounding) -> Instruction {
+    let data = MulDivInstructionData::new(a, b, denominator, rounding);
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(WideMathInstruction::MulDiv, &data),
+        vec![],
+    )
+}
+
+fn instruction_sqrt(value: u128, rounding: Rounding) -> Instruction {
+    let data = SqrtInstructionData::new(value, rounding);
+    Instruction::new_with_bytes(
+        ID,
+        &instruction_data(WideMathInstruction::Sqrt, &data),
+        vec![],
+    )
+}
+
+#[test]
+fn test_wide_math_widening_mul() {
+    assert_eq!(widening_mul(0, u128::MAX), (0, 0));
+    assert_eq!(widening_mul(u128::MAX, 1), (0, u128::MAX));
+    assert_eq!(widening_mul(1 << 64, 1 << 64), (1, 0));
+    // (2^128 - 1)^2 = 2^256 - 2^129 + 1
+    assert_eq!(widening_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+}
+
+#[test]
+fn test_wide_math_mul_div() {
+    // Intermediate product above 2^128.
+    assert_eq!(
+        mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down).ok(),
+        Some(u128::MAX)
+    );
+    assert_eq!(
+        mul_div(3 * WAD, 2 * WAD, WAD, Rounding::Down).ok(),
+        Some(6 * WAD)
+    );
+
+    // 10 / 4 = 2.5
+    assert_eq!(mul_div(10, 1, 4, Rounding::Down).ok(), Some(2));
+    assert_eq!(mul_div(10, 1, 4, Rounding::Up).ok(), Some(3));
+    assert_eq!(mul_div(10, 1, 4, Rounding::Nearest).ok(), Some(3));
+    // 10 / 3 = 3.33
+    assert_eq!(mul_div(10, 1, 3, Rounding::Nearest).ok(), Some(3));
+    assert_eq!(mul_div(10, 1, 3, Rounding::Up).ok(), Some(4));
+    // Exact results aren't rounded.
+    assert_eq!(mul_div(12, 1, 3, Rounding::Up).ok(), Some(4));
+
+    assert!(matches!(
+        mul_div(u128::MAX, 2, 1, Rounding::Down),
+        Err(WideMathError::Overflow)
+    ));
+    assert!(matches!(
+        mul_div(1, 1, 0, Rounding::Down),
+        Err(WideMathError::DivisionByZero)
+    ));
+}
+
+#[test]
+fn test_wide_math_sqrt() {
+    assert_eq!(sqrt(0, Rounding::Up), 0);
+    assert_eq!(sqrt(1, Rounding::Up), 1);
+    assert_eq!(sqrt(15, Rounding::Down), 3);
+    assert_eq!(sqrt(15, Rounding::Up), 4);
+    assert_eq!(sqrt(16, Rounding::Up), 4);
+    // sqrt(12) = 3.46, sqrt(13) = 3.61
+    assert_eq!(sqrt(12, Rounding::Nearest), 3);
+    assert_eq!(sqrt(13, Rounding::Nearest), 4);
+    assert_eq!(sqrt(u128::MAX, Rounding::Down), u64::MAX as u128);
+    assert_eq!(sqrt(u128::MAX, Rounding::Up), 1 << 64);
+}
+
+proptest! {
+    #[test]
+    fn test_wide_math_mul_div_matches_u128(
+        a in any::<u64>(),
+        b in any::<u64>(),
+        denominator in 1..=u64::MAX,
+    ) {
+        // The product of two `u64`s fits into `u128`, so plain `u128` math
+        // is the reference.
+        let (a, b, denominator) = (a as u128, b as u128, denominator as u128);
+        let product = a * b;
+
+        let down = product / denominator;
+        let up = product.div_ceil(denominator);
+        let nearest = (product + denominator / 2) / denominator;
+        prop_assert_eq!(mul_div(a, b, denominator, Rounding::Down).ok(), Some(down));
+        prop_assert_eq!(mul_div(a, b, denominator, Rounding::Up).ok(), Some(up));
+        prop_assert_eq!(mul_div(a, b, denominator, Rounding::Nearest).ok(), Some(nearest));
+    }
+
+    #[test]
+    fn test_wide_math_mul_div_bounds(
+        a in any::<u128>(),
+        b in any::<u128>(),
+        denominator in 1..=u128::MAX,
+    ) {
+        // q = floor(a * b / d) satisfies q * d <= a * b < (q + 1) * d.
+        if let Ok(quotient) = mul_div(a, b, denominator, Rounding::Down) {
+            let product = widening_mul(a, b);
+            prop_assert!(widening_mul(quotient, denominator) <= product);
+            if let Some(next) = quotient.checked_add(1) {
+                prop_assert!(widening_mul(next, denominator) > product);
+            }
+        } else {
+            // Only overflows if the quotient would exceed 128 bits.
+            prop_assert!(widening_mul(u128::MAX, denominator) < widening_mul(a, b));
+        }
+    }
+
+    #[test]
+    fn test_wide_math_sqrt_bounds(value in any::<u128>()) {
+        let root = sqrt(value, Rounding::Down);
+        prop_assert!(root * root <= value);
+        prop_assert!(widening_mul(root + 1, root + 1) > (0, value));
+
+        let up = sqrt(value, Rounding::Up);
+        prop_assert_eq!(up, if root * root == value { root } else { root + 1 });
+    }
+}
+
+#[test]
+fn test_wide_math_instructions() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/wide_math");
+
+    mollusk.process_and_validate_instruction(
+        &instruction_mul_div(u128::MAX, 3 * WAD, 4 * WAD, Rounding::Up),
+        &[],
+        &[
+            Check::success(),
+            Check::return_data(&(u128::MAX / 4 * 3 + 3).to_le_bytes()),
+        ],
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction_sqrt(u128::MAX, Rounding::Down),
+        &[],
+        &[
+            Check::success(),
+            Check::return_data(&(u64::MAX as u128).to_le_bytes()),
+        ],
+    );
+}
+
+#[test]
+fn test_wide_math_errors() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/wide_math");
+
+    mollusk.process_and_validate_instruction(
+        &instruction_mul_div(u128::MAX, 2, 1, Rounding::Down),
+        &[],
+        &[Check::err(ProgramError::Custom(
+            Namespace::WideMath.code(WideMathError::Overflow as u32),
+        ))],
+    );
+    mollusk.process_and_validate_instruction(
+        &instruction_mul_div(1, 1, 0, Rounding::Down),
+        &[],
+        &[Check::err(ProgramError::Custom(
+            Namespace::WideMath.code(WideMathError::DivisionByZero as u32),
+        ))],
+    );
+
+    let mut data = instruction_data(
+        WideMathInstruction::Sqrt,
+        &SqrtInstructionData::new(4, Rounding::Down),
+    );
+    *data.last_mut().unwrap() = 3;
+    mollusk.process_and_validate_instruction(
+        &Instruction::new_with_bytes(ID, &data, vec![]),
+        &[],
+        &[Check::err(ProgramError::Custom(
+            Namespace::WideMath.code(WideMathError::InvalidRounding as u32),
+        ))],
+    );
+}
+
+/// Prints the compute units consumed by the instructions, from the fast
+/// paths to the worst cases. Run with `--nocapture` to see the results.
+#[test]
+fn bench_wide_math_compute_units() {
+    let mollusk = Mollusk::new(&ID, "target/deploy/wide_math");
+
+    let cases = [
+        (
+            "mul_div, product below 2^128",
+            instruction_mul_div(1_000 * WAD, 3 * WAD, WAD, Rounding::Down),
+        ),
+        (
+            "mul_div, product above 2^128",
+            instruction_mul_div(u128::MAX, WAD, 2 * WAD, Rounding::Down),
+        ),
+        (
+            "mul_div, product above 2^128, rounding up",
+            instruction_mul_div(u128::MAX, WAD, 3 * WAD, Rounding::Up),
+        ),
+        (
+            "sqrt, 64-bit value",
+            instruction_sqrt(u64::MAX as u128, Rounding::Down),
+        ),
+        (
+            "sqrt, 128-bit value",
+            instruction_sqrt(u128::MAX, Rounding::Down),
+        ),
+    ];
+
+    println!("| {:<45} | {:>8} |", "case", "CUs");
+    for (name, instruction) in cases {
+        let res = mollusk.process_and_validate_instruction(&instruction, &[], &[Check::success()]);
+        println!("| {:<45} | {:>8} |", name, res.compute_units_consumed);
+    }
+}
@@ -0,0 +1,480 @@
+//! Builds the example programs and runs their tests.
+//!
+//! The tests of a program load its SBF binary from `target/deploy` and the
+//! binaries of third-party programs it calls from `third-party`, so both
+//! have to exist before `cargo test`. Run with `cargo xtask <command>` from
+//! the root of the repository.
+//!
+//! Small binaries are one of the points of the examples, so `sizes` checks
+//! the sizes of the built programs against the baseline committed in
+//! `binary_sizes.json`, only rewritten with `--update`. `bench-report`
+//! collects the compute units of the instructions measured by the tests,
+//! together with the sizes of the binaries, into
+//! `target/bench-report/report.json` and `report.csv`, and compares them
+//! with the baseline committed in `bench_baseline.json`, only rewritten with
+//! `--update` when cutting a release.
+//!
+//! `codama` emits the IDLs of the programs with clients and renders the
+//! clients from them with the script in `codama`, which needs Node.js.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+
+/// Third-party programs loaded by the tests, dumped from mainnet.
+const THIRD_PARTY_PROGRAMS: &[(&str, &str)] = &[
+    (
+        "address_lookup_table",
+        "AddressLookupTab1e1111111111111111111111111",
+    ),
+    (
+        "spl_governance",
+        "GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw",
+    ),
+    ("spl_memo", "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"),
+    ("spl_token", "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+    (
+        "spl_token_2022",
+        "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+    ),
+];
+
+/// Programs whose clients are rendered by Codama.
+const CODAMA_PROGRAMS: &[&str] = &["counter", "escrow"];
+
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Cmd,
+}
+
+#[derive(Subcommand)]
+enum Cmd {
+    /// Builds the SBF binaries of the programs.
+    Build {
+        /// Compiles in the logs of the programs with the `verbose-logs`
+        /// feature.
+        #[arg(long)]
+        verbose_logs: bool,
+        /// Crates to build. Defaults to all programs.
+        crates: Vec<String>,
+    },
+    /// Fetches the third-party binaries missing in the programs.
+    Fetch {
+        /// Crates to fetch for. Defaults to all programs.
+        crates: Vec<String>,
+    },
+    /// Builds all programs, fetches the third-party binaries and runs the
+    /// tests.
+    Test {
+        /// Crates to test. Defaults to all crates.
+        crates: Vec<String>,
+    },
+    /// Checks the sizes of the built programs against their baseline.
+    Sizes {
+        /// Percentage by which a program may grow.
+        #[arg(long, default_value_t = 5)]
+        threshold: u64,
+        /// Writes the sizes into the baseline instead of comparing with it.
+        #[arg(long)]
+        update: bool,
+        /// Programs to check. Defaults to all programs.
+        crates: Vec<String>,
+    },
+    /// Emits the IDLs of the programs and renders their clients with
+    /// Codama.
+    Codama,
+    /// Collects the compute-unit reports written by the tests of the
+    /// programs and compares them with their baseline.
+    BenchReport {
+        /// Percentage by which an instruction may consume more compute
+        /// units.
+        #[arg(long, default_value_t = 5)]
+        threshold: u64,
+        /// Replaces the baseline with the collected report instead of
+        /// comparing with it.
+        #[arg(long)]
+        update: bool,
+    },
+}
+
+fn main() -> Result<()> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+
+    match Cli::parse().command {
+        Cmd::Build {
+            verbose_logs,
+            crates,
+        } => build(&select(root, crates, is_program)?, verbose_logs),
+        Cmd::Fetch { crates } => fetch(&select(root, crates, is_program)?),
+        Cmd::Test { crates } => {
+            // Tests can load programs of other crates, so all of them are
+            // built.
+            let programs = select(root, Vec::new(), is_program)?;
+            build(&programs, false)?;
+            fetch(&programs)?;
+            test(&select(root, crates, |_| true)?)
+        }
+        Cmd::Sizes {
+            threshold,
+            update,
+            crates,
+        } => sizes(root, &select(root, crates, is_program)?, threshold, update),
+        Cmd::Codama => codama(root),
+        Cmd::BenchReport { threshold, update } => bench_report(root, threshold, update),
+    }
+}
+
+/// Returns the crates named in `names`, or all crates matching `filter`.
+fn select(root: &Path, names: Vec<String>, filter: fn(&Path) -> bool) -> Result<Vec<PathBuf>> {
+    if !names.is_empty() {
+        return names
+            .into_iter()
+            .map(|name| {
+                let path = root.join(&name);
+                if !path.join("Cargo.toml").exists() {
+                    bail!("unknown crate {name}");
+                }
+                Ok(path)
+            })
+            .collect();
+    }
+
+    let mut crates = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        // The fuzz targets need a nightly toolchain, the tasks don't test
+        // themselves and the template isn't a crate until it's generated.
+        if path.ends_with("fuzz") || path.ends_with("xtask") || path.ends_with("program-template") {
+            continue;
+        }
+        if path.join("Cargo.toml").exists() && filter(&path) {
+            crates.push(path);
+        }
+    }
+    crates.sort();
+    Ok(crates)
+}
+
+/// Whether the crate is a program, built into a shared object. Clients are
+/// shared objects too, but built into WebAssembly.
+fn is_program(path: &Path) -> bool {
+    fs::read_to_string(path.join("Cargo.toml"))
+        .is_ok_and(|manifest| manifest.contains("cdylib") && !manifest.contains("wasm-bindgen"))
+}
+
+/// Builds the programs into their `target/deploy`, with the `verbose-logs`
+/// feature of the programs which have it if `verbose_logs` is set.
+fn build(programs: &[PathBuf], verbose_logs: bool) -> Result<()> {
+    for program in programs {
+        let mut command = Command::new("cargo");
+        command
+            .arg("build-sbf")
+            .arg("--sbf-out-dir")
+            .arg(program.join("target/deploy"))
+            .current_dir(program);
+        let manifest = fs::read_to_string(program.join("Cargo.toml"))?;
+        if verbose_logs && manifest.contains("verbose-logs") {
+            command.args(["--features", "verbose-logs"]);
+        }
+        run(&mut command)?;
+    }
+    Ok(())
+}
+
+/// Third-party programs loaded by the shared Mollusk of
+/// `test_utils::programs`, which the fuzz targets use too.
+const SHARED_MOLLUSK_PROGRAMS: &[&str] = &["spl_token", "spl_token_2022"];
+
+/// Fetches the third-party binaries used by the tests of `programs` into
+/// their `third-party`, copying them from other programs when already
+/// fetched.
+///
+/// A binary is used if any test file refers to `third-party/<name>`. Tests
+/// using the shared Mollusk need the binaries it loads.
+fn fetch(programs: &[PathBuf]) -> Result<()> {
+    for program in programs {
+        let mut tests = String::new();
+        if let Ok(entries) = fs::read_dir(program.join("tests")) {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_some_and(|extension| extension == "rs") {
+                    tests += &fs::read_to_string(&path)?;
+                }
+            }
+        }
+        let shared_mollusk = tests.contains("programs::mollusk");
+        for (name, address) in THIRD_PARTY_PROGRAMS {
+            let used = tests.contains(&format!("third-party/{name}"))
+                || shared_mollusk && SHARED_MOLLUSK_PROGRAMS.contains(name);
+            if !used {
+                continue;
+            }
+            let binary = program.join("third-party").join(format!("{name}.so"));
+            if binary.exists() {
+                continue;
+            }
+            fs::create_dir_all(binary.parent().unwrap())?;
+
+            let root = program.parent().unwrap();
+            let fetched = select(root, Vec::new(), is_program)?
+                .into_iter()
+                .map(|other| other.join("third-party").join(format!("{name}.so")))
+                .find(|other| other.exists());
+            match fetched {
+                Some(fetched) => {
+                    fs::copy(&fetched, &binary)
+                        .with_context(|| format!("failed to copy {}", fetched.display()))?;
+                }
+                None => run(Command::new("solana")
+                    .args(["program", "dump", "-um", address])
+                    .arg(&binary))?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the tests of `crates`.
+fn test(crates: &[PathBuf]) -> Result<()> {
+    for krate in crates {
+        run(Command::new("cargo").arg("test").current_dir(krate))?;
+    }
+    Ok(())
+}
+
+/// Compares the sizes of the binaries of `programs` with the baseline in
+/// `binary_sizes.json`, printing them as a Markdown table, and fails if any
+/// grew by more than `threshold` percent or has no baseline. With `update`,
+/// the sizes are written into the baseline instead.
+fn sizes(root: &Path, programs: &[PathBuf], threshold: u64, update: bool) -> Result<()> {
+    let baseline_path = root.join("binary_sizes.json");
+    let mut baseline: BTreeMap<String, u64> = match fs::read_to_string(&baseline_path) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(_) if update => BTreeMap::new(),
+        Err(e) => {
+            return Err(e).with_context(|| {
+                format!(
+                    "failed to read {}, create it with `cargo xtask sizes --update`",
+                    baseline_path.display()
+                )
+            })
+        }
+    };
+
+    let mut table = String::from("| Program | Size | Baseline | Delta |\n|---|---|---|---|\n");
+    let mut regressions = Vec::new();
+    let mut results = Vec::new();
+    for program in programs {
+        let name = program
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .replace('-', "_");
+        let binary = program.join("target/deploy").join(format!("{name}.so"));
+        let size = fs::metadata(&binary)
+            .with_context(|| format!("{} is not built", binary.display()))?
+            .len();
+        match baseline.get(&name) {
+            Some(&previous) => {
+                let delta = size as i64 - previous as i64;
+                writeln!(table, "| {name} | {size} | {previous} | {delta:+} |")?;
+                if size * 100 > previous * (100 + threshold) {
+                    regressions.push(format!("{name}: {previous} -> {size}"));
+                }
+            }
+            None => {
+                writeln!(table, "| {name} | {size} | - | - |")?;
+                regressions.push(format!("{name}: no baseline"));
+            }
+        }
+        results.push((name, size));
+    }
+    print!("{table}");
+
+    if update {
+        baseline.extend(results);
+        fs::write(
+            &baseline_path,
+            serde_json::to_string_pretty(&baseline)? + "\n",
+        )?;
+        println!("updated {}", baseline_path.display());
+        return Ok(());
+    }
+    if !regressions.is_empty() {
+        bail!(
+            "programs grew by more than {threshold}% or have no baseline, rerun with \
+             `--update` if expected:\n{}",
+            regressions.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Collects the reports in `target/bench-report` of every program into the
+/// one of the repository, as JSON and CSV, and compares its compute units
+/// and binary sizes with the baseline in `bench_baseline.json`, printing
+/// them as a Markdown table. Fails if any instruction consumes more than
+/// `threshold` percent over the baseline or has no baseline, unless
+/// `update` replaces it.
+fn bench_report(root: &Path, threshold: u64, update: bool) -> Result<()> {
+    let baseline_path = root.join("bench_baseline.json");
+    if !update && !baseline_path.exists() {
+        bail!(
+            "no baseline in {}, create it with `cargo xtask bench-report --update` and commit it",
+            baseline_path.display()
+        );
+    }
+
+    let mut records = Vec::new();
+    for program in select(root, Vec::new(), is_program)? {
+        let Ok(entries) = fs::read_dir(program.join("target/bench-report")) else {
+            continue;
+        };
+        let mut paths: Vec<_> = entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        paths.retain(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        });
+        paths.sort();
+        for path in paths {
+            let report: Vec<serde_json::Value> = serde_json::from_str(&fs::read_to_string(&path)?)
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+            records.extend(report);
+        }
+    }
+    if records.is_empty() {
+        bail!("no reports in target/bench-report, run `cargo xtask test` first");
+    }
+
+    let report_dir = root.join("target/bench-report");
+    fs::create_dir_all(&report_dir)?;
+    let mut csv = String::from("program,instruction,compute_units,binary_size\n");
+    for record in &records {
+        let (key, compute_units, binary_size) = bench_record(record);
+        let size = binary_size.map(|size| size.to_string()).unwrap_or_default();
+        writeln!(csv, "{},{compute_units},{size}", key.replace('/', ","))?;
+    }
+    fs::write(report_dir.join("report.csv"), csv)?;
+    let json = serde_json::to_string_pretty(&records)? + "\n";
+    fs::write(report_dir.join("report.json"), &json)?;
+
+    if update {
+        fs::write(&baseline_path, json)?;
+        println!("updated {}", baseline_path.display());
+        return Ok(());
+    }
+    let baseline = fs::read_to_string(&baseline_path).with_context(|| {
+        format!(
+            "failed to read {}, create it with `cargo xtask bench-report --update`",
+            baseline_path.display()
+        )
+    })?;
+    let baseline: Vec<serde_json::Value> = serde_json::from_str(&baseline)?;
+    let baseline: BTreeMap<_, _> = baseline
+        .iter()
+        .map(|record| {
+            let (key, compute_units, binary_size) = bench_record(record);
+            (key, (compute_units, binary_size))
+        })
+        .collect();
+
+    let mut table = String::from(
+        "| Instruction | Compute units | Baseline | Delta | Size | Baseline | Delta |\n\
+         |---|---|---|---|---|---|---|\n",
+    );
+    let mut regressions = Vec::new();
+    for record in &records {
+        let (key, compute_units, binary_size) = bench_record(record);
+        let size = binary_size.map_or("-".to_owned(), |size| size.to_string());
+        match baseline.get(&key) {
+            Some(&(previous, previous_size)) => {
+                let delta = compute_units as i64 - previous as i64;
+                let (previous_size, size_delta) = match (binary_size, previous_size) {
+                    (Some(size), Some(previous_size)) => (
+                        previous_size.to_string(),
+                        format!("{:+}", size as i64 - previous_size as i64),
+                    ),
+                    _ => ("-".to_owned(), "-".to_owned()),
+                };
+                writeln!(
+                    table,
+                    "| {key} | {compute_units} | {previous} | {delta:+} | {size} | \
+                     {previous_size} | {size_delta} |"
+                )?;
+                if compute_units * 100 > previous * (100 + threshold) {
+                    regressions.push(format!("{key}: {previous} -> {compute_units}"));
+                }
+            }
+            None => {
+                writeln!(
+                    table,
+                    "| {key} | {compute_units} | - | - | {size} | - | - |"
+                )?;
+                regressions.push(format!("{key}: no baseline"));
+            }
+        }
+    }
+    print!("{table}");
+
+    if !regressions.is_empty() {
+        bail!(
+            "compute units regressed by more than {threshold}% or have no baseline, rerun \
+             with `--update` if expected:\n{}",
+            regressions.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Returns the `program/instruction` key, the compute units and the binary
+/// size of a record of a report.
+fn bench_record(record: &serde_json::Value) -> (String, u64, Option<u64>) {
+    let key = format!(
+        "{}/{}",
+        record["program"].as_str().unwrap_or_default(),
+        record["instruction"].as_str().unwrap_or_default()
+    );
+    (
+        key,
+        record["compute_units"].as_u64().unwrap_or_default(),
+        record["binary_size"].as_u64(),
+    )
+}
+
+/// Emits the IDLs of [`CODAMA_PROGRAMS`] with their `test_idl` tests and
+/// renders their clients, installing the dependencies of the script first.
+fn codama(root: &Path) -> Result<()> {
+    for program in CODAMA_PROGRAMS {
+        run(Command::new("cargo")
+            .args(["test", "--test", "tests", "test_idl"])
+            .current_dir(root.join(program)))?;
+    }
+
+    let codama = root.join("codama");
+    if !codama.join("node_modules").exists() {
+        run(Command::new("npm").arg("install").current_dir(&codama))?;
+    }
+    run(Command::new("node")
+        .arg("generate.mjs")
+        .current_dir(&codama))
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("failed to run {command:?}"))?;
+    if !status.success() {
+        bail!("{command:?} failed with {status}");
+    }
+    Ok(())
+}